@@ -0,0 +1,319 @@
+//! Timings of the hot paths, with criterion. Their throughput is in
+//! elements per second, which are pixels or points, so machines can be
+//! compared. Run with `cargo bench`, optionally with a part of a benchmark's
+//! name to only run those, and `-- --save-baseline NAME` and
+//! `-- --baseline NAME` to compare a change against what came before.
+
+use rustlebrot::coloring::{Coloring, Phase, Transfer};
+use rustlebrot::dd::DoubleDouble;
+use rustlebrot::dither::Dither;
+use rustlebrot::complex::Complex;
+use rustlebrot::formula::Formula;
+use rustlebrot::fractal::{EscapeTimeFractal, Fractal, Mandelbrot};
+use rustlebrot::palette::{self, Adjust, Colormap, Cycle, Palette};
+use rustlebrot::render::{
+    self, Alpha, BitDepth, ColorOptions, EscapeBuffer, RenderOptions, Rotation, Scripted,
+    Subdivision, WorkUnits,
+};
+use rustlebrot::script::Script;
+use rustlebrot::trap::Trap;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::hint::black_box;
+
+/// Pixels along each side of the rendered frames.
+const SIZE: u32 = 512;
+
+/// Samples taken of benchmarks of whole frames, which take long enough
+/// that criterion's default 100 would take minutes.
+const FRAME_SAMPLES: usize = 20;
+
+/// The Seahorse valley filament of the golden tests, where most pixels
+/// take long to escape.
+const FILAMENT: ((f64, f64), f64) = ((-0.743643887, 0.131825904), 5e-4);
+
+/// The duotone example of `--color-expr`, which reads the smooth escape
+/// time and the magnification.
+const DUOTONE: &str = include_str!("../examples/duotone.expr");
+
+/// The Mandelbrot iteration as an `EscapeTimeFractal`.
+struct Quadratic;
+
+impl EscapeTimeFractal for Quadratic {
+    fn init(&self, _c: (f64, f64)) -> (f64, f64) {
+        (0.0, 0.0)
+    }
+
+    #[inline]
+    fn step(&self, z: (f64, f64), c: (f64, f64)) -> (f64, f64) {
+        let z = Complex::from(z);
+        (z * z + c.into()).into()
+    }
+}
+
+/// `z^3 + cz + c` as an `EscapeTimeFractal`, the formula
+/// `escape_time_formula_program` runs as a program.
+struct Cubic;
+
+impl EscapeTimeFractal for Cubic {
+    fn init(&self, _c: (f64, f64)) -> (f64, f64) {
+        (0.0, 0.0)
+    }
+
+    #[inline]
+    fn step(&self, z: (f64, f64), c: (f64, f64)) -> (f64, f64) {
+        let (z, c) = (Complex::from(z), Complex::from(c));
+        (z * z * z + c * z + c).into()
+    }
+
+    fn bailout(&self) -> f64 {
+        rustlebrot::formula::FORMULA_BAILOUT
+    }
+}
+
+/// The scalar loops on their own, a point at a time over the default view.
+fn escape_time(c: &mut Criterion) {
+    let points = grid((0.0, 0.0), 4.0, 256);
+    let mut group = c.benchmark_group("escape_time");
+    group.throughput(Throughput::Elements(points.len() as u64));
+    group.bench_function("mandelbrot", |b| {
+        b.iter(|| {
+            for &c in &points {
+                black_box(Mandelbrot.escape_time(black_box(c), 1000, 2.0, Some(1e-6), None));
+            }
+        })
+    });
+    // The same through the formula loop, which doesn't know to skip the
+    // cardioid and bulb.
+    group.bench_function("formula", |b| {
+        b.iter(|| {
+            for &c in &points {
+                black_box(Quadratic.escape_time(black_box(c), 1000, 2.0, Some(1e-6), None));
+            }
+        })
+    });
+    // `--formula`, specialized to the power kernel.
+    let power = Formula::parse("z^2 + c").unwrap();
+    group.bench_function("formula_power", |b| {
+        b.iter(|| {
+            for &c in &points {
+                black_box(power.escape_time(black_box(c), 1000, 2.0, Some(1e-6), None));
+            }
+        })
+    });
+    group.bench_function("cubic", |b| {
+        b.iter(|| {
+            for &c in &points {
+                black_box(Cubic.escape_time(black_box(c), 1000, 2.0, Some(1e-6), None));
+            }
+        })
+    });
+    // The same formula as `cubic`, run as a program.
+    let program = Formula::parse("z*z*z + c*z + c").unwrap();
+    group.bench_function("formula_program", |b| {
+        b.iter(|| {
+            for &c in &points {
+                black_box(program.escape_time(black_box(c), 1000, 2.0, Some(1e-6), None));
+            }
+        })
+    });
+    group.finish();
+}
+
+/// The cost of double-double against f64, on the same points, where the
+/// cardioid check skips none of them.
+fn escape_time_filament(c: &mut Criterion) {
+    let (center, width) = FILAMENT;
+    let points = grid(center, width, 128);
+    let mut group = c.benchmark_group("escape_time_filament");
+    group.throughput(Throughput::Elements(points.len() as u64));
+    group.bench_function("f64", |b| {
+        b.iter(|| {
+            for &c in &points {
+                black_box(Mandelbrot.escape_time(black_box(c), 2000, 2.0, Some(1e-10), None));
+            }
+        })
+    });
+    let points: Vec<_> =
+        points.iter().map(|&(x, y)| (DoubleDouble::from(x), DoubleDouble::from(y))).collect();
+    group.bench_function("dd", |b| {
+        b.iter(|| {
+            for &c in &points {
+                black_box(Mandelbrot.escape_time_dd(black_box(c), 2000, 2.0, Some(1e-10)));
+            }
+        })
+    });
+    group.finish();
+}
+
+/// Whole frames, through `render::compute_escape`.
+fn frames(c: &mut Criterion) {
+    let mut group = c.benchmark_group("frame");
+    group.throughput(Throughput::Elements(pixels() as u64));
+    group.sample_size(FRAME_SAMPLES);
+    group.bench_function("default_view", |b| {
+        b.iter(|| frame((0.0, 0.0), 4.0, 1000, Coloring::Smooth))
+    });
+    // The same frame iterating every point of the cardioid and bulb, as it
+    // did before they were skipped.
+    group.bench_function("default_view_unskipped", |b| {
+        b.iter(|| frame_of(&Quadratic, (0.0, 0.0), 4.0, 1000, Coloring::Smooth, Subdivision::Off))
+    });
+    // Whole escape times, which subdivision fills in the bands of as well as
+    // the interior.
+    group.bench_function("default_view_banded", |b| {
+        b.iter(|| frame((0.0, 0.0), 4.0, 1000, Coloring::EscapeTime))
+    });
+    group.bench_function("default_view_subdivided", |b| {
+        b.iter(|| {
+            frame_of(&Mandelbrot, (0.0, 0.0), 4.0, 1000, Coloring::EscapeTime, Subdivision::On)
+        })
+    });
+    let (center, width) = FILAMENT;
+    group.bench_function("filament", |b| {
+        b.iter(|| frame(center, width, 2000, Coloring::Smooth))
+    });
+    // The loop of its own that stripe averages take, on the filament.
+    group.bench_function("stripes", |b| {
+        b.iter(|| frame(center, width, 2000, Coloring::Stripes(5.0)))
+    });
+    // The scalar loop that keeps the orbits of a script's samples, on the
+    // filament.
+    let needs = Script::compile(DUOTONE).unwrap().needs(Trap::Point(0.0, 0.0));
+    group.bench_function("script", |b| {
+        b.iter(|| frame(center, width, 2000, Coloring::Script(needs)))
+    });
+    group.finish();
+}
+
+/// The colors of the gradient positions of a frame, from the lookup table
+/// the colorize pass reads at the smallest, default and largest
+/// `--palette-resolution`, and straight from the gradient.
+fn colormap(c: &mut Criterion) {
+    let gradient = Palette::Sinebow.gradient();
+    let adjust = Adjust::default();
+    let positions: Vec<f64> = (0..pixels()).map(|i| i as f64 / pixels() as f64).collect();
+    let mut group = c.benchmark_group("colormap");
+    group.throughput(Throughput::Elements(pixels() as u64));
+    let (fewest, most) = palette::TABLE_SIZES;
+    for entries in [fewest, palette::TABLE_SIZE, most] {
+        let colormap = Colormap::with_entries(&gradient, &adjust, entries);
+        group.bench_with_input(BenchmarkId::new("table", entries), &colormap, |b, colormap| {
+            b.iter(|| {
+                for &t in &positions {
+                    black_box(colormap.at(black_box(t)).to_rgba8());
+                }
+            })
+        });
+    }
+    group.bench_function("direct", |b| {
+        b.iter(|| {
+            for &t in &positions {
+                black_box(adjust.apply(gradient.at(black_box(t))).to_rgba8());
+            }
+        })
+    });
+    group.finish();
+}
+
+/// The colorize pass on its own, over a frame of the default view.
+fn colorize(c: &mut Criterion) {
+    let gradient = Palette::Sinebow.gradient();
+    let adjust = Adjust {
+        invert: true,
+        ..Adjust::default()
+    };
+    let colormap = Colormap::new(&gradient, &adjust);
+    let colors = ColorOptions {
+        palette_iter: 1000,
+        histogram_clip: 0.0,
+        transfer: Transfer::Linear,
+        reference: None,
+        colormap: &colormap,
+        cycle: Cycle::new(&gradient, 4.0, 0.0, false),
+        interior: (0, 0, 0),
+        bit_depth: BitDepth::Eight,
+        dither: Dither::None,
+        alpha: Alpha::None,
+        phase: Phase::default(),
+        lighting: None,
+        script: None,
+        interior_coloring: None,
+        contours: None,
+        silhouette: None,
+        line_art: None,
+    };
+    let mut group = c.benchmark_group("colorize");
+    group.throughput(Throughput::Elements(pixels() as u64));
+    let buffer = frame((0.0, 0.0), 4.0, 1000, Coloring::Smooth);
+    group.bench_function("smooth", |b| b.iter(|| render::colorize(black_box(&buffer), &colors)));
+    let script = Script::compile(DUOTONE).unwrap();
+    let coloring = Coloring::Script(script.needs(Trap::Point(0.0, 0.0)));
+    let buffer = frame((0.0, 0.0), 4.0, 1000, coloring);
+    let colors = ColorOptions {
+        script: Some(Scripted {
+            script: &script,
+            frame: 0,
+            magnification: 1.0,
+        }),
+        ..colors
+    };
+    group.bench_function("script", |b| b.iter(|| render::colorize(black_box(&buffer), &colors)));
+    group.finish();
+}
+
+criterion_group!(benches, escape_time, escape_time_filament, frames, colormap, colorize);
+criterion_main!(benches);
+
+fn pixels() -> usize {
+    (SIZE * SIZE) as usize
+}
+
+/// `size` by `size` points evenly over the square `width` across around
+/// `center`.
+fn grid(center: (f64, f64), width: f64, size: u32) -> Vec<(f64, f64)> {
+    let step = width / size as f64;
+    (0..size * size)
+        .map(|i| {
+            let (x, y) = ((i % size) as f64, (i / size) as f64);
+            (center.0 - width / 2.0 + x * step, center.1 + width / 2.0 - y * step)
+        })
+        .collect()
+}
+
+/// Computes a frame of the square `width` across around `center` in
+/// `coloring`, smooth as the command line program does by default, at the
+/// coloring's default bailout.
+fn frame(center: (f64, f64), width: f64, max_iter: u32, coloring: Coloring) -> EscapeBuffer {
+    frame_of(&Mandelbrot, center, width, max_iter, coloring, Subdivision::Off)
+}
+
+/// `frame`, of `fractal` instead of the Mandelbrot set and with
+/// `subdivision`.
+fn frame_of<F: Fractal>(
+    fractal: &F,
+    center: (f64, f64),
+    width: f64,
+    max_iter: u32,
+    coloring: Coloring,
+    subdivision: Subdivision,
+) -> EscapeBuffer {
+    let options = RenderOptions {
+        max_iter,
+        periodicity: true,
+        bailout: coloring.default_bailout(),
+        coloring,
+        single_precision: false,
+        subdivision,
+        work_units: WorkUnits::Auto,
+        reuse: None,
+        refine: None,
+        rotation: Rotation::NONE,
+        window: None,
+        timing: None,
+        attractors: false,
+    };
+    let half = width / 2.0;
+    let x_range = (center.0 - half, center.0 + half);
+    let y_range = (center.1 - half, center.1 + half);
+    render::compute_escape(fractal, SIZE, SIZE, x_range, y_range, &options)
+}
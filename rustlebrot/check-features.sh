@@ -0,0 +1,32 @@
+#!/bin/sh
+# Runs clippy over every target in the feature combinations that matter,
+# so code behind a feature, or behind its absence, doesn't rot unseen.
+set -e
+cd "$(dirname "$0")"
+
+check() {
+    echo "== $*"
+    cargo clippy --all-targets "$@" -- -D warnings
+}
+
+check
+check --no-default-features
+check --no-default-features --features simd
+check --no-default-features --features bigfloat
+check --no-default-features --features cli
+check --no-default-features --features stream
+for feature in bigfloat serve explore window daemon video-internal dashboard; do
+    check --no-default-features --features "simd cli $feature"
+done
+check --all-features
+
+# The library in the browser, the way examples/wasm.html loads it. This needs
+# `rustup target add wasm32-unknown-unknown`; wasm-pack is optional, and
+# --no-opt keeps it from downloading wasm-opt.
+echo "== wasm32-unknown-unknown"
+cargo clippy --lib --target wasm32-unknown-unknown --no-default-features --features wasm \
+    -- -D warnings
+if command -v wasm-pack > /dev/null; then
+    wasm-pack build --no-opt --target web --out-dir target/pkg \
+        -- --no-default-features --features wasm
+fi
@@ -0,0 +1,113 @@
+//! Renders a short zoom into the Celtic Mandelbrot set, a formula of its
+//! own plugged into the renderer through `EscapeTimeFractal`.
+//!
+//! Run it with `cargo run --release --example custom_fractal [DIR]`. The
+//! frames are written as PNGs to `DIR`, `celtic` by default, and can be
+//! put together into a video with
+//! `ffmpeg -framerate 4 -i celtic/celtic_%02d.png celtic.mp4`.
+
+use rustlebrot::coloring::{Coloring, Phase, Transfer};
+use rustlebrot::dither::Dither;
+use rustlebrot::fractal::EscapeTimeFractal;
+use rustlebrot::palette::{Adjust, Colormap, Cycle, Palette};
+use rustlebrot::render::{
+    self, Alpha, BitDepth, ColorOptions, RenderOptions, Rotation, Subdivision, WorkUnits,
+};
+use std::path::PathBuf;
+
+/// The Mandelbrot iteration with the absolute value of the real part of
+/// `z^2` taken, which folds the left half of every bulb over to the right.
+struct Celtic;
+
+impl EscapeTimeFractal for Celtic {
+    fn init(&self, _c: (f64, f64)) -> (f64, f64) {
+        (0.0, 0.0)
+    }
+
+    #[inline]
+    fn step(&self, z: (f64, f64), c: (f64, f64)) -> (f64, f64) {
+        ((z.0 * z.0 - z.1 * z.1).abs() + c.0, 2.0 * z.0 * z.1 + c.1)
+    }
+
+    // Conjugating z keeps the real part of z^2 and negates the imaginary.
+    fn symmetric(&self) -> bool {
+        true
+    }
+}
+
+/// Pixels along each side of every frame.
+const SIZE: u32 = 400;
+
+/// Samples along each side of a pixel.
+const SUPERSAMPLE: u32 = 3;
+
+/// The frames of the zoom, each half as wide as the one before.
+const FRAMES: u32 = 12;
+
+/// The point the zoom closes in on, where the period doublings along the
+/// real axis end, which the Celtic set shares with the Mandelbrot set as
+/// its orbits there stay real.
+const CENTER: (f64, f64) = (-1.401155, 0.0);
+
+fn main() {
+    let dir = PathBuf::from(std::env::args().nth(1).unwrap_or_else(|| "celtic".to_string()));
+    std::fs::create_dir_all(&dir).expect("can't create the output directory");
+
+    let gradient = Palette::Sinebow.gradient();
+    let adjust = Adjust {
+        invert: true,
+        ..Adjust::default()
+    };
+    let colormap = Colormap::new(&gradient, &adjust);
+    for frame in 0..FRAMES {
+        let half = 2.0 / 2f64.powi(frame as i32);
+        let max_iter = 200 + 100 * frame;
+        let options = RenderOptions {
+            max_iter,
+            periodicity: true,
+            bailout: 2.0,
+            coloring: Coloring::Smooth,
+            single_precision: false,
+            subdivision: Subdivision::Off,
+            work_units: WorkUnits::Auto,
+            reuse: None,
+            refine: None,
+            rotation: Rotation::NONE,
+            window: None,
+            timing: None,
+            attractors: false,
+        };
+        let samples = SIZE * SUPERSAMPLE;
+        let mut buffer = render::compute_escape(
+            &Celtic,
+            samples,
+            samples,
+            (CENTER.0 - half, CENTER.0 + half),
+            (CENTER.1 - half, CENTER.1 + half),
+            &options,
+        );
+        buffer.samples = SUPERSAMPLE;
+        let colors = ColorOptions {
+            palette_iter: max_iter,
+            histogram_clip: 0.0,
+            transfer: Transfer::Linear,
+            reference: None,
+            colormap: &colormap,
+            cycle: Cycle::new(&gradient, 4.0, 0.0, false),
+            interior: (0, 0, 0),
+            bit_depth: BitDepth::Eight,
+            dither: Dither::None,
+            alpha: Alpha::None,
+            phase: Phase::default(),
+            lighting: None,
+            script: None,
+            interior_coloring: None,
+            contours: None,
+            silhouette: None,
+            line_art: None,
+        };
+        let path = dir.join(format!("celtic_{:02}.png", frame));
+        render::colorize(&buffer, &colors).save(&path).expect("can't write the frame");
+        println!("{}", path.display());
+    }
+}
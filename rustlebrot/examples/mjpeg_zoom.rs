@@ -0,0 +1,71 @@
+//! Serves a zoom into Seahorse Valley as an MJPEG stream, which browsers
+//! play as it arrives, every client a zoom of its own.
+//!
+//! Run it with `cargo run --release --example mjpeg_zoom [ADDR]` and open
+//! `http://127.0.0.1:8080/` (or `ADDR`) in a browser. A client that reads
+//! slowly pauses its zoom, and one that goes away stops it, so nothing
+//! is rendered that isn't sent. The frames are the items of a stream, which
+//! a web framework can send the same way, like axum with
+//! `Body::from_stream`.
+
+use rustlebrot::stream::{Encoding, ZoomJob, ZoomOptions};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+const BOUNDARY: &str = "rustlebrot-frame";
+
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    let addr = std::env::args().nth(1).unwrap_or_else(|| "127.0.0.1:8080".to_string());
+    let listener = TcpListener::bind(&addr).await?;
+    println!("Serving a zoom at http://{}/", addr);
+    loop {
+        let (socket, peer) = listener.accept().await?;
+        tokio::spawn(async move {
+            match serve(socket).await {
+                Ok(frames) => println!("{}: sent {} frames", peer, frames),
+                Err(e) => println!("{}: {}", peer, e),
+            }
+        });
+    }
+}
+
+/// Sends the frames of a zoom to the client of `socket` as they're
+/// rendered, whatever it asked for, until the zoom ends or the client goes
+/// away. Returns the frames sent.
+async fn serve(mut socket: TcpStream) -> std::io::Result<u32> {
+    // The request itself doesn't matter; every path is the zoom.
+    let mut request = [0; 4096];
+    let _ = socket.read(&mut request).await?;
+    let header = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: multipart/x-mixed-replace; boundary={}\r\n\
+         Cache-Control: no-cache\r\nConnection: close\r\n\r\n",
+        BOUNDARY
+    );
+    socket.write_all(header.as_bytes()).await?;
+    let options = ZoomOptions {
+        width: 480,
+        height: 360,
+        scale: 3.0 / 480.0,
+        zoom_factor: 1.04,
+        frames: 400,
+        encoding: Encoding::Jpeg(80),
+        ..ZoomOptions::default()
+    };
+    let mut job = ZoomJob::stream(options).map_err(std::io::Error::other)?;
+    let mut sent = 0;
+    // A failed write drops the job on the way out, which stops its zoom.
+    while let Some(frame) = job.next().await {
+        let frame = frame.map_err(std::io::Error::other)?;
+        let part = format!(
+            "--{}\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\r\n",
+            BOUNDARY,
+            frame.bytes.len()
+        );
+        socket.write_all(part.as_bytes()).await?;
+        socket.write_all(&frame.bytes).await?;
+        socket.write_all(b"\r\n").await?;
+        sent += 1;
+    }
+    Ok(sent)
+}
@@ -0,0 +1,158 @@
+use crate::cli::{self, StillArgs};
+use crate::error::RustlebrotError;
+use serde::Deserialize;
+use std::fs;
+
+/// Version of the batch file schema. It changes whenever a field is
+/// removed or changes meaning.
+pub const BATCH_VERSION: u32 = 1;
+
+/// A file of stills for `render-batch`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BatchFile {
+    version: u32,
+    stills: Vec<Entry>,
+}
+
+/// A still of a batch. Every field but the output is optional and defaults
+/// as the flag of `still` it stands for does.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Entry {
+    /// What failures of the still are reported by, along with its place
+    /// in the file.
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    fractal: Option<String>,
+    /// The center with every digit, and how far in the view is from that of
+    /// a plain zoom's first frame, whose shorter side spans twice the
+    /// fractal's default half width.
+    #[serde(default)]
+    center: Option<(String, String)>,
+    #[serde(default)]
+    magnification: Option<f64>,
+    /// A location file, such as the bookmarks `explore --window` saves,
+    /// and the name of the location in it, in place of a center.
+    #[serde(default)]
+    location: Option<String>,
+    #[serde(default)]
+    location_name: Option<String>,
+    /// The view itself, in place of a center.
+    #[serde(default)]
+    x_range: Option<(f64, f64)>,
+    #[serde(default)]
+    y_range: Option<(f64, f64)>,
+    #[serde(default)]
+    width: Option<u32>,
+    #[serde(default)]
+    height: Option<u32>,
+    #[serde(default)]
+    max_iter: Option<u32>,
+    /// The name of a palette, or the path of a gradient file.
+    #[serde(default)]
+    palette: Option<String>,
+    #[serde(default)]
+    coloring: Option<String>,
+    /// The PNG the still is saved to.
+    pub output: String,
+}
+
+/// Reads the stills of the batch file at `path`.
+pub fn read(path: &str) -> Result<Vec<Entry>, RustlebrotError> {
+    let json = fs::read_to_string(path).map_err(|e| RustlebrotError::read(path, e))?;
+    let file: BatchFile =
+        serde_json::from_str(&json).map_err(|e| RustlebrotError::format(path, e))?;
+    if file.version > BATCH_VERSION {
+        return Err(RustlebrotError::format(
+            path,
+            format!(
+                "the batch is of version {}, newer than this build reads, {}",
+                file.version, BATCH_VERSION
+            ),
+        ));
+    }
+    if file.stills.is_empty() {
+        return Err(RustlebrotError::format(path, "there are no stills"));
+    }
+    Ok(file.stills)
+}
+
+impl Entry {
+    /// How the still at `index` of the batch is referred to, like
+    /// `still 3 (seahorse)`.
+    pub fn label(&self, index: usize) -> String {
+        match &self.name {
+            Some(name) => format!("still {} ({})", index + 1, name),
+            None => format!("still {}", index + 1),
+        }
+    }
+
+    /// The options of the still, as `still` would take them from the flags
+    /// the fields stand for, with a band of it taking at most `max_memory`.
+    pub fn still_args(&self, max_memory: u64, overwrite: bool) -> Result<StillArgs, String> {
+        let mut args = vec!["--output".to_string(), self.output.clone()];
+        let mut flag = |name: &str, value: String| {
+            args.push(format!("--{}", name));
+            args.push(value);
+        };
+        if let Some(fractal) = &self.fractal {
+            flag("fractal", fractal.clone());
+        }
+        if let Some(location) = &self.location {
+            if self.center.is_some() || self.x_range.is_some() || self.y_range.is_some() {
+                return Err("give either a location, a center or ranges, not more".to_string());
+            }
+            flag("location", location.clone());
+        }
+        if let Some(name) = &self.location_name {
+            flag("location-name", name.clone());
+        }
+        match (&self.center, self.magnification, self.x_range, self.y_range) {
+            (Some(_), _, Some(_), _) | (Some(_), _, _, Some(_)) => {
+                return Err("give either a center or ranges, not both".to_string())
+            }
+            (None, Some(_), _, _) if self.location.is_none() => {
+                return Err("a magnification needs a center to zoom into".to_string())
+            }
+            (Some((x, y)), magnification, None, None) => {
+                flag("center", format!("{},{}", x, y));
+                if let Some(magnification) = magnification {
+                    flag("magnification", format!("{:?}", magnification));
+                }
+            }
+            (None, magnification, x_range, y_range) => {
+                if let Some(magnification) = magnification {
+                    flag("magnification", format!("{:?}", magnification));
+                }
+                if let Some((min, max)) = x_range {
+                    flag("x-range", format!("{:?},{:?}", min, max));
+                }
+                if let Some((min, max)) = y_range {
+                    flag("y-range", format!("{:?},{:?}", min, max));
+                }
+            }
+        }
+        if let Some(width) = self.width {
+            flag("width", width.to_string());
+        }
+        if let Some(height) = self.height {
+            flag("height", height.to_string());
+        }
+        if let Some(max_iter) = self.max_iter {
+            flag("max-iter", max_iter.to_string());
+        }
+        if let Some(palette) = &self.palette {
+            flag("palette", palette.clone());
+        }
+        if let Some(coloring) = &self.coloring {
+            flag("coloring", coloring.clone());
+        }
+        flag("max-memory", max_memory.to_string());
+        if overwrite {
+            args.push("--overwrite".to_string());
+        }
+        cli::parse_still(&args)
+    }
+}
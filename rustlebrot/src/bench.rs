@@ -0,0 +1,270 @@
+use crate::coloring::Coloring;
+use crate::formula::Formula;
+use crate::fractal::{self, Fractal, Mandelbrot};
+use crate::precision::Precision;
+use crate::render::{compute_escape, RenderOptions, Rotation, Sample, Subdivision, WorkUnits};
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+/// Pixels along each side of every scene.
+pub const SIZE: u32 = 512;
+
+/// Times each scene is rendered unless `--repeats` says otherwise, after a
+/// first render to warm up.
+pub const DEFAULT_REPEATS: u32 = 5;
+
+/// A view `bench` renders. These are never to change, so the numbers of
+/// one version compare with those of another; add a scene instead.
+#[derive(Clone, Copy, Debug)]
+pub struct Scene {
+    pub name: &'static str,
+    pub center: (f64, f64),
+    pub half_width: f64,
+    pub max_iter: u32,
+}
+
+/// The scenes of `bench`, in the order they are rendered.
+pub const SCENES: [Scene; 3] = [
+    // The first frame of a zoom with the default center, where most
+    // points escape within a few iterations.
+    Scene {
+        name: "full",
+        center: (-1.7499984109937408, -1.6571246929541869e-15),
+        half_width: 2.0,
+        max_iter: 1000,
+    },
+    // The Seahorse valley filament of the golden tests, where most points
+    // take long to escape.
+    Scene {
+        name: "filament",
+        center: (-0.743643887, 0.131825904),
+        half_width: 5e-4,
+        max_iter: 2000,
+    },
+    // Around the upper period-3 bulb, off the real axis so no rows are
+    // mirrored, where most points never escape. The main cardioid would
+    // be skipped in closed form.
+    Scene {
+        name: "interior",
+        center: (-0.1226, 0.7449),
+        half_width: 0.1,
+        max_iter: 1000,
+    },
+];
+
+/// What the renders of one scene measured.
+#[derive(Clone, Debug, Serialize)]
+pub struct SceneResult {
+    pub name: &'static str,
+    pub width: u32,
+    pub height: u32,
+    pub max_iter: u32,
+    pub precision: &'static str,
+    /// The width and height of the units of work, as `64x64`.
+    pub work_units: String,
+    pub repeats: u32,
+    /// The median time of a render.
+    pub seconds: f64,
+    /// Iterations of a render, see `run`.
+    pub iterations: u64,
+    pub megapixels_per_second: f64,
+    pub iterations_per_second: f64,
+    /// The same scene iterated with the formula of `--formula`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub formula: Option<FormulaResult>,
+}
+
+/// What the renders of a scene with a formula measured, see `run`.
+#[derive(Clone, Debug, Serialize)]
+pub struct FormulaResult {
+    /// The median time of a render.
+    pub seconds: f64,
+    /// Iterations of a render, which aren't those of the built-in kernel
+    /// unless the formula is the Mandelbrot set's.
+    pub iterations: u64,
+    pub iterations_per_second: f64,
+    pub nanoseconds_per_iteration: f64,
+    /// How many times as long an iteration of the formula takes as one of
+    /// the built-in kernel.
+    pub slowdown: f64,
+}
+
+/// The results of a `bench` run, with what they were measured on.
+#[derive(Clone, Debug, Serialize)]
+pub struct Report {
+    pub version: &'static str,
+    pub threads: usize,
+    pub backend: &'static str,
+    /// Whether this is a debug build, whose numbers are no measure of a
+    /// release.
+    pub debug: bool,
+    /// The formula of `--formula`, as given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub formula: Option<String>,
+    /// How the formula is iterated, see `Formula::kernel`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kernel: Option<String>,
+    pub scenes: Vec<SceneResult>,
+}
+
+/// The loop escape times are iterated in: four lanes at a time with the
+/// `simd` feature, one otherwise.
+pub fn backend() -> &'static str {
+    match cfg!(feature = "simd") {
+        true => "simd",
+        false => "scalar",
+    }
+}
+
+/// Renders `scene` once to warm up and then `repeats` times, measuring the
+/// compute pass alone, in the precision an auto render picks for it and in
+/// `work_units`. With
+/// `formula`, the scene is rendered the same way with it too, in f64, to
+/// compare the time an iteration of each takes.
+///
+/// Periodicity checking and subdivision are off, so every sample takes as
+/// many iterations as its escape time and interior ones all `max_iter`,
+/// unless the cardioid or bulb check skips them. Rows mirrored across the
+/// real axis count as iterated, so this is the work of the frame rather
+/// than of the loop.
+pub fn run(
+    scene: &Scene,
+    repeats: u32,
+    formula: Option<&Formula>,
+    work_units: WorkUnits,
+) -> SceneResult {
+    let pixel_size = 2.0 * scene.half_width / SIZE as f64;
+    let precision = Precision::Auto.resolve(scene.center, pixel_size);
+    let single = precision == Precision::F32;
+    let (seconds, iterations) = measure(&Mandelbrot, scene, repeats, single, true, work_units);
+    let iterations_per_second = iterations as f64 / seconds;
+    let formula = formula.map(|formula| {
+        let (seconds, iterations) = measure(formula, scene, repeats, false, false, work_units);
+        FormulaResult {
+            seconds,
+            iterations,
+            iterations_per_second: iterations as f64 / seconds,
+            nanoseconds_per_iteration: seconds * 1e9 / iterations as f64,
+            slowdown: iterations_per_second / (iterations as f64 / seconds),
+        }
+    });
+    SceneResult {
+        name: scene.name,
+        width: SIZE,
+        height: SIZE,
+        max_iter: scene.max_iter,
+        precision: precision.name(),
+        work_units: {
+            let (width, height) = work_units.size(SIZE, scene.max_iter);
+            format!("{}x{}", width, height)
+        },
+        repeats,
+        seconds,
+        iterations,
+        megapixels_per_second: (SIZE * SIZE) as f64 / 1e6 / seconds,
+        iterations_per_second,
+        formula,
+    }
+}
+
+/// The median time of `repeats` renders of `scene` with `fractal`, after
+/// one to warm up, and the iterations of a render, in f32 if `single` and
+/// in `work_units`.
+/// `skips` says whether the kernel skips the cardioid and bulb, whose
+/// samples are then not iterated.
+fn measure<F: Fractal>(
+    fractal: &F,
+    scene: &Scene,
+    repeats: u32,
+    single: bool,
+    skips: bool,
+    work_units: WorkUnits,
+) -> (f64, u64) {
+    let options = RenderOptions {
+        max_iter: scene.max_iter,
+        periodicity: false,
+        subdivision: Subdivision::Off,
+        work_units,
+        reuse: None,
+        refine: None,
+        rotation: Rotation::NONE,
+        window: None,
+        timing: None,
+        attractors: false,
+        bailout: 2.0,
+        coloring: Coloring::EscapeTime,
+        single_precision: single,
+    };
+    let (x, y) = scene.center;
+    let x_range = (x - scene.half_width, x + scene.half_width);
+    let y_range = (y - scene.half_width, y + scene.half_width);
+    let render = || compute_escape(fractal, SIZE, SIZE, x_range, y_range, &options);
+
+    let buffer = render();
+    let scale = 2.0 * scene.half_width / SIZE as f64;
+    let point = |index: usize| {
+        let (x, y) = (index as u32 % SIZE, index as u32 / SIZE);
+        (x_range.0 + x as f64 * scale, y_range.1 - y as f64 * scale)
+    };
+    let iterations = buffer.values.iter().enumerate().map(|(index, sample)| match sample {
+        Sample::Value(value) => *value as u64,
+        _ if skips && fractal::in_cardioid_or_bulb(point(index)) => 0,
+        _ => scene.max_iter as u64,
+    });
+    let iterations = iterations.sum();
+    let mut times: Vec<Duration> = (0..repeats)
+        .map(|_| {
+            let start = Instant::now();
+            std::hint::black_box(render());
+            start.elapsed()
+        })
+        .collect();
+    times.sort();
+    (times[times.len() / 2].as_secs_f64(), iterations)
+}
+
+/// The report as a table, a row per scene.
+pub fn table(report: &Report) -> String {
+    let mut text = format!(
+        "rustlebrot {} bench, {} threads, {} backend\n",
+        report.version, report.threads, report.backend
+    );
+    text.push_str(&format!(
+        "{:<10} {:>9} {:>8} {:>9} {:>9} {:>9} {:>9} {:>10}\n",
+        "scene", "size", "max_iter", "precision", "units", "median", "MP/s", "Giter/s"
+    ));
+    for scene in &report.scenes {
+        text.push_str(&format!(
+            "{:<10} {:>9} {:>8} {:>9} {:>9} {:>8.1}ms {:>9.2} {:>10.3}\n",
+            scene.name,
+            format!("{}x{}", scene.width, scene.height),
+            scene.max_iter,
+            scene.precision,
+            scene.work_units,
+            scene.seconds * 1e3,
+            scene.megapixels_per_second,
+            scene.iterations_per_second / 1e9,
+        ));
+    }
+    if let (Some(formula), Some(kernel)) = (&report.formula, &report.kernel) {
+        text.push_str(&format!("\n{}, as {}\n", formula, kernel));
+        text.push_str(&format!(
+            "{:<10} {:>9} {:>10} {:>9} {:>9}\n",
+            "scene", "median", "Giter/s", "ns/iter", "slowdown"
+        ));
+        for scene in &report.scenes {
+            let Some(result) = &scene.formula else {
+                continue;
+            };
+            text.push_str(&format!(
+                "{:<10} {:>7.1}ms {:>10.3} {:>9.2} {:>8.2}x\n",
+                scene.name,
+                result.seconds * 1e3,
+                result.iterations_per_second / 1e9,
+                result.nanoseconds_per_iteration,
+                result.slowdown,
+            ));
+        }
+    }
+    text
+}
@@ -0,0 +1,230 @@
+#[cfg(feature = "bigfloat")]
+use dashu_float::round::mode::HalfAway;
+#[cfg(feature = "bigfloat")]
+use dashu_float::{DBig, FBig};
+#[cfg(feature = "bigfloat")]
+use std::str::FromStr;
+
+/// Binary arbitrary-precision float used for deep zooms past f64.
+#[cfg(feature = "bigfloat")]
+pub type Big = FBig<HalfAway, 2>;
+
+/// Without the `bigfloat` feature, centers and reference orbits are kept in
+/// f64 by a stand-in with the parts of the interface of `FBig` that the
+/// rest of the crate uses.
+#[cfg(not(feature = "bigfloat"))]
+pub use fallback::Big;
+
+/// Bits kept beyond what is needed to tell neighboring pixels apart.
+const GUARD_BITS: usize = 32;
+
+/// Returns how many mantissa bits are needed to resolve `pixel_size` around
+/// a center of the given magnitude.
+pub fn required_bits(center: (f64, f64), pixel_size: f64) -> usize {
+    let magnitude = center.0.abs().max(center.1.abs()).max(1.0);
+    let bits = (magnitude / pixel_size).log2().ceil().max(53.0) as usize;
+    bits + GUARD_BITS
+}
+
+/// Parses a decimal string such as the stored zoom centers into a `Big`
+/// rounded to `bits` of precision.
+#[cfg(feature = "bigfloat")]
+pub fn parse_decimal(s: &str, bits: usize) -> Result<Big, String> {
+    let decimal = DBig::from_str(s.trim()).map_err(|e| format!("invalid number '{}': {}", s, e))?;
+    Ok(decimal.with_base_and_precision::<2>(bits).value())
+}
+
+/// Formats `value` as a decimal string with `digits` significant digits,
+/// which `parse_decimal` reads back.
+#[cfg(feature = "bigfloat")]
+pub fn to_decimal(value: &Big, digits: usize) -> String {
+    value
+        .clone()
+        .with_base_and_precision::<10>(digits)
+        .value()
+        .to_string()
+}
+
+/// Converts an f64 to a `Big` with the given precision. The conversion is
+/// exact since every f64 is a binary fraction.
+#[cfg(feature = "bigfloat")]
+pub fn from_f64(value: f64, bits: usize) -> Big {
+    Big::try_from(value)
+        .expect("pixel offsets are finite")
+        .with_precision(bits)
+        .value()
+}
+
+#[cfg(not(feature = "bigfloat"))]
+pub fn parse_decimal(s: &str, _bits: usize) -> Result<Big, String> {
+    let value: f64 = s.trim().parse().map_err(|e| format!("invalid number '{}': {}", s, e))?;
+    Big::try_from(value)
+}
+
+#[cfg(not(feature = "bigfloat"))]
+pub fn to_decimal(value: &Big, digits: usize) -> String {
+    // Rounded through scientific notation, but written out in full, which
+    // is how `DBig` writes numbers too.
+    let rounded = format!("{:.*e}", digits.max(1) - 1, value.to_f64().value());
+    rounded.parse::<f64>().expect("formatted as a number").to_string()
+}
+
+#[cfg(not(feature = "bigfloat"))]
+pub fn from_f64(value: f64, _bits: usize) -> Big {
+    Big::try_from(value).expect("pixel offsets are finite")
+}
+
+/// Computes the escape time of `c` with every operation carried out at the
+/// precision of `c`.
+///
+/// This mirrors the f64 kernels in `fractal` exactly, including the escape
+/// test against `bailout` on the freshly computed `z`. With `conjugate` set
+/// the Tricorn iteration `conj(z)^2 + c` is used instead of `z^2 + c`.
+pub fn escape_time(c: &(Big, Big), max_iter: u32, bailout: f64, conjugate: bool) -> f64 {
+    let bailout_sqr = Big::try_from(bailout * bailout).expect("bailout is finite");
+    let mut z: (Big, Big) = (Big::ZERO, Big::ZERO);
+    let mut z_sqr: (Big, Big) = (Big::ZERO, Big::ZERO);
+    for i in 0..max_iter {
+        let cross = (&z.0 * &z.1) << 1;
+        let x = &z_sqr.0 - &z_sqr.1 + &c.0;
+        let y = if conjugate { &c.1 - cross } else { cross + &c.1 };
+        let (x_sqr, y_sqr) = (x.sqr(), y.sqr());
+        if &x_sqr + &y_sqr > bailout_sqr {
+            return i as f64;
+        }
+        z = (x, y);
+        z_sqr = (x_sqr, y_sqr);
+    }
+    max_iter as f64
+}
+
+#[cfg(not(feature = "bigfloat"))]
+mod fallback {
+    use std::cmp::Ordering;
+    use std::ops::{Add, Div, Mul, Neg, Shl, Sub};
+
+    /// An f64 standing in for `FBig`, whatever precision is asked of it.
+    #[derive(Clone, Debug, Default, PartialEq, PartialOrd)]
+    pub struct Big(f64);
+
+    /// The result of a conversion, exact or not, as `dashu` returns them.
+    pub struct Approximation<T>(T);
+
+    impl<T> Approximation<T> {
+        pub fn value(self) -> T {
+            self.0
+        }
+    }
+
+    impl Big {
+        pub const ZERO: Big = Big(0.0);
+        pub const ONE: Big = Big(1.0);
+
+        pub fn to_f64(&self) -> Approximation<f64> {
+            Approximation(self.0)
+        }
+
+        /// The bits of the mantissa, which are those of f64.
+        pub fn precision(&self) -> usize {
+            f64::MANTISSA_DIGITS as usize
+        }
+
+        pub fn with_precision(self, _bits: usize) -> Approximation<Big> {
+            Approximation(self)
+        }
+
+        pub fn sqr(&self) -> Big {
+            Big(self.0 * self.0)
+        }
+
+        /// This times 2^`bits`, as shifting `FBig` left by `bits` is.
+        fn shifted(&self, bits: usize) -> Big {
+            Big(self.0 * 2f64.powi(bits as i32))
+        }
+    }
+
+    impl TryFrom<f64> for Big {
+        type Error = String;
+
+        fn try_from(value: f64) -> Result<Big, String> {
+            match value.is_finite() {
+                true => Ok(Big(value)),
+                false => Err(format!("{} isn't a finite number", value)),
+            }
+        }
+    }
+
+    impl PartialEq<f64> for Big {
+        fn eq(&self, other: &f64) -> bool {
+            self.0 == *other
+        }
+    }
+
+    impl PartialOrd<f64> for Big {
+        fn partial_cmp(&self, other: &f64) -> Option<Ordering> {
+            self.0.partial_cmp(other)
+        }
+    }
+
+    macro_rules! binary {
+        ($trait:ident, $method:ident) => {
+            impl $trait<Big> for Big {
+                type Output = Big;
+                fn $method(self, other: Big) -> Big {
+                    Big(self.0.$method(other.0))
+                }
+            }
+            impl $trait<&Big> for Big {
+                type Output = Big;
+                fn $method(self, other: &Big) -> Big {
+                    Big(self.0.$method(other.0))
+                }
+            }
+            impl $trait<Big> for &Big {
+                type Output = Big;
+                fn $method(self, other: Big) -> Big {
+                    Big(self.0.$method(other.0))
+                }
+            }
+            impl $trait<&Big> for &Big {
+                type Output = Big;
+                fn $method(self, other: &Big) -> Big {
+                    Big(self.0.$method(other.0))
+                }
+            }
+        };
+    }
+
+    binary!(Add, add);
+    binary!(Sub, sub);
+    binary!(Mul, mul);
+    binary!(Div, div);
+
+    impl Neg for Big {
+        type Output = Big;
+        fn neg(self) -> Big {
+            Big(-self.0)
+        }
+    }
+
+    impl Neg for &Big {
+        type Output = Big;
+        fn neg(self) -> Big {
+            Big(-self.0)
+        }
+    }
+
+    impl Shl<usize> for Big {
+        type Output = Big;
+        fn shl(self, bits: usize) -> Big {
+            self.shifted(bits)
+        }
+    }
+
+    impl Shl<usize> for &Big {
+        type Output = Big;
+        fn shl(self, bits: usize) -> Big {
+            self.shifted(bits)
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use crate::decimal::Decimal;
+use crate::error::RustlebrotError;
+use crate::fractal::FractalKind;
+use crate::location::Location;
+use crate::newton::Newton;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Version of the location file schema. It changes whenever a field is
+/// removed or changes meaning.
+pub const LOCATIONS_VERSION: u32 = 1;
+
+/// A file of locations, a list of bookmarks to zoom into with `--location`
+/// and to add to with `--save-location`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct LocationFile {
+    version: u32,
+    locations: Vec<LocationRecord>,
+}
+
+/// A location as it is written in a file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct LocationRecord {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    notes: Option<String>,
+    fractal: String,
+    /// The coefficients of the polynomial of a Newton fractal, highest
+    /// power first.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    poly: Option<Vec<f64>>,
+    /// The center with every digit.
+    center: (String, String),
+    magnification: f64,
+    max_iter: u32,
+    #[serde(default)]
+    rotation: f64,
+}
+
+impl From<&Location> for LocationRecord {
+    fn from(location: &Location) -> Self {
+        LocationRecord {
+            name: location.name.clone(),
+            notes: location.notes.clone(),
+            fractal: location.fractal.name().to_string(),
+            poly: location.newton.as_ref().map(|newton| newton.coefficients().to_vec()),
+            center: location.center.clone(),
+            magnification: location.magnification,
+            max_iter: location.max_iter,
+            rotation: location.rotation,
+        }
+    }
+}
+
+impl LocationRecord {
+    /// The location the record is of, if it is a valid one.
+    fn location(self) -> Result<Location, String> {
+        let fractal = FractalKind::from_name(&self.fractal)
+            .ok_or_else(|| format!("unknown fractal '{}'", self.fractal))?;
+        let newton = match (fractal, &self.poly) {
+            (FractalKind::Newton, poly) => Some(match poly {
+                Some(poly) => Newton::new(poly)?,
+                None => Newton::default(),
+            }),
+            (_, Some(_)) => return Err("only Newton fractals have a poly".to_string()),
+            (_, None) => None,
+        };
+        for coordinate in [&self.center.0, &self.center.1] {
+            Decimal::parse(coordinate)?;
+        }
+        if !(self.magnification > 0.0 && self.magnification.is_finite()) {
+            return Err(format!("magnification should be positive, got {}", self.magnification));
+        }
+        if self.max_iter == 0 {
+            return Err("max_iter should be at least 1".to_string());
+        }
+        if !self.rotation.is_finite() {
+            return Err("rotation should be a finite number of degrees".to_string());
+        }
+        Ok(Location {
+            name: self.name,
+            notes: self.notes,
+            fractal,
+            newton,
+            center: self.center,
+            magnification: self.magnification,
+            max_iter: self.max_iter,
+            rotation: self.rotation,
+        })
+    }
+}
+
+/// Reads the locations of the file at `path`, a location file or a
+/// Kalles Fraktaler `.kfr` file, which is told by whether it is JSON.
+/// Locations of a `.kfr` file come with the keys of it that were ignored.
+pub fn read(path: &str) -> Result<(Vec<Location>, Vec<String>), String> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("can't read location file '{}': {}", path, e))?;
+    if !text.trim_start().starts_with('{') {
+        let (location, ignored) =
+            Location::parse_kfr(&text).map_err(|e| format!("{}: {}", path, e))?;
+        return Ok((vec![location], ignored));
+    }
+    Ok((parse(&text).map_err(|e| format!("{}: {}", path, e))?, Vec::new()))
+}
+
+/// Parses the JSON of a location file.
+fn parse(json: &str) -> Result<Vec<Location>, String> {
+    let file: LocationFile = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    if file.version > LOCATIONS_VERSION {
+        return Err(format!(
+            "locations of version {} are newer than this build reads, {}",
+            file.version, LOCATIONS_VERSION
+        ));
+    }
+    let locations = file.locations.into_iter().enumerate().map(|(index, record)| {
+        record.location().map_err(|e| format!("location {}: {}", index + 1, e))
+    });
+    let locations = locations.collect::<Result<Vec<_>, _>>()?;
+    if locations.is_empty() {
+        return Err("there are no locations".to_string());
+    }
+    Ok(locations)
+}
+
+/// Picks the location of `locations`, from the file at `path`, that is
+/// named `name`, or the only one when no name is given.
+pub fn select(
+    path: &str,
+    locations: Vec<Location>,
+    name: Option<&str>,
+) -> Result<Location, String> {
+    let names = || {
+        let names = locations.iter().map(|location| location.name.as_deref().unwrap_or("?"));
+        names.collect::<Vec<_>>().join(", ")
+    };
+    match name {
+        Some(name) => match locations.iter().position(|l| l.name.as_deref() == Some(name)) {
+            Some(index) => Ok(locations[index].clone()),
+            None => Err(format!("{} has no location named '{}', only {}", path, name, names())),
+        },
+        None if locations.len() == 1 => Ok(locations[0].clone()),
+        None => Err(format!(
+            "{} has {} locations, pick one of {} with --location-name",
+            path,
+            locations.len(),
+            names()
+        )),
+    }
+}
+
+/// The JSON of a location file of `locations`.
+pub fn to_json(locations: &[Location]) -> String {
+    let file = LocationFile {
+        version: LOCATIONS_VERSION,
+        locations: locations.iter().map(LocationRecord::from).collect(),
+    };
+    serde_json::to_string_pretty(&file).expect("locations serialize to JSON") + "\n"
+}
+
+/// Adds `location` to the location file at `path`, in place of the one of
+/// the same name if there is one, creating the file if there is none.
+pub fn save(path: &str, location: &Location) -> Result<(), RustlebrotError> {
+    let mut locations = match Path::new(path).exists() {
+        true => {
+            let json = fs::read_to_string(path).map_err(|e| RustlebrotError::read(path, e))?;
+            parse(&json).map_err(|e| RustlebrotError::format(path, e))?
+        }
+        false => Vec::new(),
+    };
+    let same =
+        locations.iter().position(|saved| location.name.is_some() && saved.name == location.name);
+    match same {
+        Some(index) => locations[index] = location.clone(),
+        None => locations.push(location.clone()),
+    }
+    // Written next to it first, so a failed write leaves the old file.
+    let partial = format!("{}.part", path);
+    fs::write(&partial, to_json(&locations))
+        .and_then(|_| fs::rename(&partial, path))
+        .map_err(|e| RustlebrotError::write(path, e))
+}
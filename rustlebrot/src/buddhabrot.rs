@@ -0,0 +1,501 @@
+use crate::fractal::Fractal;
+use crate::render::{BitDepth, Channel, RenderOptions, Rotation};
+use crate::throttle;
+use image::DynamicImage;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use std::f64::consts::TAU;
+
+/// Samples drawn per batch. Each batch has its own generator, seeded with
+/// the batch index so renders are reproducible regardless of scheduling.
+const BATCH_SIZE: u64 = 1 << 16;
+
+/// Chains of Metropolis sampling the samples are split between. Like
+/// batches, each has its own generator seeded with its index, so renders
+/// don't depend on how many threads run them.
+const CHAINS: u64 = 64;
+
+/// How often a chain jumps to a point anywhere in the sampled square
+/// rather than stepping near where it is, so it can't get stuck on one
+/// patch of orbits.
+const LARGE_MUTATION: f64 = 0.2;
+
+/// How many times longer the longest of the small steps of a chain is than
+/// the shortest.
+const STEP_RANGE: f64 = 1e4;
+
+/// Half the side of the square samples are drawn from. Every point outside
+/// the radius 2 disk escapes immediately, so this covers all orbits that
+/// can contribute.
+const SAMPLE_RADIUS: f64 = 2.0;
+
+/// Cycle threshold for skipping points that don't escape. It is fixed
+/// rather than relative to the pixel size since samples are drawn from the
+/// whole plane, and kept tiny so slow escapers aren't mistaken for cycles.
+const PERIODICITY_EPSILON: f64 = 1e-12;
+
+/// How hit counts are compressed into pixel brightness.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ToneMap {
+    Sqrt,
+    Log,
+}
+
+impl ToneMap {
+    pub fn from_name(name: &str) -> Option<ToneMap> {
+        match name {
+            "sqrt" => Some(ToneMap::Sqrt),
+            "log" => Some(ToneMap::Log),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ToneMap::Sqrt => "sqrt",
+            ToneMap::Log => "log",
+        }
+    }
+
+    /// Maps `count` to a brightness between 0 and 1, relative to the
+    /// largest count in the grid.
+    fn apply(self, count: f64, max: f64) -> f64 {
+        if max <= 0.0 {
+            return 0.0;
+        }
+        match self {
+            ToneMap::Sqrt => (count / max).sqrt(),
+            ToneMap::Log => count.ln_1p() / max.ln_1p(),
+        }
+    }
+}
+
+/// How the points whose orbits are plotted are drawn.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Sampler {
+    /// Uniformly from the square around the set, every orbit counting the
+    /// same.
+    Uniform,
+    /// By Metropolis-Hastings, see `Metropolis`.
+    Metropolis(Metropolis),
+}
+
+impl Sampler {
+    pub fn from_name(name: &str) -> Option<Sampler> {
+        match name {
+            "uniform" => Some(Sampler::Uniform),
+            "metropolis" => Some(Sampler::Metropolis(Metropolis::default())),
+            _ => None,
+        }
+    }
+}
+
+/// Metropolis-Hastings sampling of the points whose orbits are plotted.
+/// Chains of points wander the plane, drawn to the points with the most of
+/// their orbit in view, so a close-up gets most of the samples where
+/// uniform sampling would hardly ever land. Every orbit is counted in
+/// inverse proportion to the hits it has in view, which makes up for that,
+/// so the image comes out as uniform sampling would show it given far more
+/// samples.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Metropolis {
+    /// The longest of the small steps of the chains, as a share of the
+    /// larger side of the view.
+    pub mutation: f64,
+    /// Samples every chain takes before its orbits are counted, so they
+    /// start counting from where they are drawn to rather than from where
+    /// they were started.
+    pub burn_in: u64,
+}
+
+impl Default for Metropolis {
+    fn default() -> Self {
+        Metropolis {
+            mutation: 0.1,
+            burn_in: 1000,
+        }
+    }
+}
+
+/// Settings for `--mode buddhabrot` and `--mode nebulabrot`.
+pub struct BuddhabrotOptions {
+    /// Total number of random points to sample.
+    pub samples: u64,
+    pub sampler: Sampler,
+    /// Mixed into the seed of every generator, for renders that differ
+    /// only in their noise.
+    pub seed: u64,
+    /// Orbits escaping in fewer iterations than this are left out. Together
+    /// with max_iter this picks the band of orbits that get plotted.
+    pub min_iter: u32,
+    pub tone: ToneMap,
+    /// Escape time limits of the red, green and blue Nebulabrot channels.
+    pub bands: [u32; 3],
+    pub bit_depth: BitDepth,
+}
+
+/// Maps orbit points onto the pixels of the rendered region, with row 0 at
+/// `y_range.1` and the grid turned by `rotation` as in
+/// `render::compute_escape`.
+struct Plot {
+    width: u32,
+    height: u32,
+    origin: (f64, f64),
+    scale: (f64, f64),
+    middle: (f64, f64),
+    /// The turn that takes points back onto the axis-aligned grid.
+    unturn: Rotation,
+}
+
+impl Plot {
+    fn new(
+        width: u32,
+        height: u32,
+        x_range: (f64, f64),
+        y_range: (f64, f64),
+        rotation: Rotation,
+    ) -> Self {
+        Plot {
+            width,
+            height,
+            origin: (x_range.0, y_range.1),
+            scale: (
+                (x_range.1 - x_range.0) / width as f64,
+                (y_range.0 - y_range.1) / height as f64,
+            ),
+            middle: ((x_range.0 + x_range.1) / 2.0, (y_range.0 + y_range.1) / 2.0),
+            unturn: rotation.inverse(),
+        }
+    }
+
+    /// The length of the longer side of the view in the plane.
+    fn extent(&self) -> f64 {
+        (self.scale.0 * self.width as f64).abs().max((self.scale.1 * self.height as f64).abs())
+    }
+
+    /// The index of the pixel `z` falls in, if it is in view.
+    #[inline]
+    fn pixel(&self, z: (f64, f64)) -> Option<usize> {
+        let z = match self.unturn.is_none() {
+            true => z,
+            false => {
+                let (dx, dy) = self.unturn.apply((z.0 - self.middle.0, z.1 - self.middle.1));
+                (self.middle.0 + dx, self.middle.1 + dy)
+            }
+        };
+        let px = ((z.0 - self.origin.0) / self.scale.0).floor();
+        let py = ((z.1 - self.origin.1) / self.scale.1).floor();
+        if px >= 0.0 && py >= 0.0 && px < self.width as f64 && py < self.height as f64 {
+            Some(py as usize * self.width as usize + px as usize)
+        } else {
+            None
+        }
+    }
+}
+
+/// Renders the Buddhabrot of `fractal` over the given region.
+///
+/// Points are sampled uniformly from the square around the origin that
+/// contains the whole set, and each one escaping after between
+/// `buddhabrot.min_iter` and `options.max_iter` iterations has every point
+/// of its orbit counted in the pixel it lands on. The counts are then
+/// tone-mapped to grayscale, brightest where orbits pass most often.
+///
+/// Since samples are drawn from the whole plane regardless of the view,
+/// zoomed in views collect proportionally fewer hits and need more samples,
+/// unless they are drawn by `Sampler::Metropolis`.
+pub fn render_buddhabrot<F: Fractal>(
+    fractal: &F,
+    width: u32,
+    height: u32,
+    x_range: (f64, f64),
+    y_range: (f64, f64),
+    options: &RenderOptions,
+    buddhabrot: &BuddhabrotOptions,
+) -> DynamicImage {
+    let plot = Plot::new(width, height, x_range, y_range, options.rotation);
+    let grid = hits(fractal, &plot, options, &[options.max_iter], buddhabrot);
+    let max = grid.iter().copied().fold(0.0, f64::max);
+    let gray: Vec<f64> = grid.iter().flat_map(|&count| [count; 3]).collect();
+    tone_map(width, height, &gray, [max; 3], buddhabrot.bit_depth, buddhabrot.tone)
+}
+
+/// Renders the Nebulabrot of `fractal`, a Buddhabrot per color channel.
+///
+/// Red, green and blue count the orbits escaping before the respective
+/// entry of `bands`, so each channel shows a different depth of orbit. All
+/// three come out of one sampling pass, with every orbit counted in each
+/// channel it qualifies for, and each channel is tone-mapped against its
+/// own maximum.
+pub fn render_nebulabrot<F: Fractal>(
+    fractal: &F,
+    width: u32,
+    height: u32,
+    x_range: (f64, f64),
+    y_range: (f64, f64),
+    options: &RenderOptions,
+    buddhabrot: &BuddhabrotOptions,
+) -> DynamicImage {
+    let plot = Plot::new(width, height, x_range, y_range, options.rotation);
+    let grid = hits(fractal, &plot, options, &buddhabrot.bands, buddhabrot);
+    let mut max = [0.0f64; 3];
+    for counts in grid.chunks(3) {
+        for (max, &count) in max.iter_mut().zip(counts) {
+            *max = max.max(count);
+        }
+    }
+    tone_map(width, height, &grid, max, buddhabrot.bit_depth, buddhabrot.tone)
+}
+
+/// Tone-maps interleaved RGB hit counts against the maximum of each channel
+/// into an image of the given bit depth.
+fn tone_map(
+    width: u32,
+    height: u32,
+    grid: &[f64],
+    max: [f64; 3],
+    bit_depth: BitDepth,
+    tone: ToneMap,
+) -> DynamicImage {
+    fn channels<T: Channel>(grid: &[f64], max: [f64; 3], tone: ToneMap) -> Vec<T> {
+        grid.chunks(3)
+            .flat_map(|counts| [0, 1, 2].map(|k| T::from_unit(tone.apply(counts[k], max[k]))))
+            .collect()
+    }
+    match bit_depth {
+        BitDepth::Eight => u8::image(width, height, channels(grid, max, tone)),
+        BitDepth::Sixteen => u16::image(width, height, channels(grid, max, tone)),
+    }
+}
+
+/// The hits of the orbits `buddhabrot.sampler` draws per pixel, with one
+/// counter per entry of `bands`, interleaved.
+fn hits<F: Fractal>(
+    fractal: &F,
+    plot: &Plot,
+    options: &RenderOptions,
+    bands: &[u32],
+    buddhabrot: &BuddhabrotOptions,
+) -> Vec<f64> {
+    match buddhabrot.sampler {
+        Sampler::Uniform => accumulate(fractal, plot, options, bands, buddhabrot)
+            .into_iter()
+            .map(f64::from)
+            .collect(),
+        Sampler::Metropolis(metropolis) => {
+            explore(fractal, plot, options, bands, buddhabrot, &metropolis)
+        }
+    }
+}
+
+/// The generator of batch or chain `index`, which is seeded with the index
+/// alone for a `seed` of 0.
+fn generator(seed: u64, index: u64) -> SmallRng {
+    SmallRng::seed_from_u64(index ^ seed.wrapping_mul(0x9E37_79B9_7F4A_7C15))
+}
+
+/// Samples orbits uniformly and counts their hits per pixel, with one
+/// counter per entry of `bands`, interleaved. An orbit is counted in every
+/// band whose limit it escapes before.
+///
+/// Batches of samples are spread over threads, and each thread accumulates
+/// into its own grid, so the hit counts need no atomics. The grids are
+/// summed once every batch is done.
+fn accumulate<F: Fractal>(
+    fractal: &F,
+    plot: &Plot,
+    options: &RenderOptions,
+    bands: &[u32],
+    buddhabrot: &BuddhabrotOptions,
+) -> Vec<u32> {
+    let max_iter = bands.iter().copied().max().unwrap_or(0);
+    let bailout = options.bailout;
+    let periodicity = options.periodicity.then_some(PERIODICITY_EPSILON);
+    let channels = bands.len();
+    let len = plot.width as usize * plot.height as usize * channels;
+    let batches = buddhabrot.samples.div_ceil(BATCH_SIZE);
+
+    (0..batches)
+        .into_par_iter()
+        .fold(
+            || vec![0u32; len],
+            |mut grid, batch| {
+                let mut rng = generator(buddhabrot.seed, batch);
+                let samples = BATCH_SIZE.min(buddhabrot.samples - batch * BATCH_SIZE);
+                throttle::paced(|| {
+                    for _ in 0..samples {
+                        let c = (
+                            rng.gen_range(-SAMPLE_RADIUS..SAMPLE_RADIUS),
+                            rng.gen_range(-SAMPLE_RADIUS..SAMPLE_RADIUS),
+                        );
+                        let escape = fractal.escape_time(c, max_iter, bailout, periodicity, None);
+                        let iterations = escape.iterations as u32;
+                        if iterations >= max_iter || iterations < buddhabrot.min_iter {
+                            continue;
+                        }
+                        fractal.orbit(c, iterations, |z| {
+                            if let Some(pixel) = plot.pixel(z) {
+                                for (k, &band) in bands.iter().enumerate() {
+                                    if iterations < band {
+                                        grid[pixel * channels + k] += 1;
+                                    }
+                                }
+                            }
+                        });
+                    }
+                });
+                grid
+            },
+        )
+        .reduce(
+            || vec![0u32; len],
+            |mut total, grid| {
+                for (total, count) in total.iter_mut().zip(grid) {
+                    *total += count;
+                }
+                total
+            },
+        )
+}
+
+/// The orbit of a point of a Metropolis chain, with the pixels it hits.
+struct Orbit {
+    c: (f64, f64),
+    iterations: u32,
+    pixels: Vec<usize>,
+}
+
+/// Samples orbits by the Metropolis chains of `metropolis` and sums their
+/// hits per pixel like `accumulate`, each orbit weighing one over its hits
+/// in view.
+///
+/// Every chain first looks for a point with an orbit in view, drawing
+/// points from the sampled square and the view in turn, which takes some
+/// of its samples. From there each step proposes a point: one anywhere in
+/// the square `LARGE_MUTATION` of the time, or else one a `nudge` from the
+/// current point. Both proposals are as likely
+/// from either end, so the proposal is taken with the chance of its hits
+/// over those of the current point, and the chain counts the orbit of
+/// wherever it is after the step.
+fn explore<F: Fractal>(
+    fractal: &F,
+    plot: &Plot,
+    options: &RenderOptions,
+    bands: &[u32],
+    buddhabrot: &BuddhabrotOptions,
+    metropolis: &Metropolis,
+) -> Vec<f64> {
+    let max_iter = bands.iter().copied().max().unwrap_or(0);
+    let bailout = options.bailout;
+    let periodicity = options.periodicity.then_some(PERIODICITY_EPSILON);
+    let channels = bands.len();
+    let len = plot.width as usize * plot.height as usize * channels;
+    let chains = CHAINS.min(buddhabrot.samples.max(1));
+    let step = metropolis.mutation * plot.extent();
+    // The square around the middle of the view that holds all of it,
+    // however it's turned.
+    let reach = plot.extent() * std::f64::consts::SQRT_2 / 2.0;
+    // Fills `pixels` with the pixels the orbit of `c` hits, and returns its
+    // escape time if it escapes in the band plotted.
+    let trace = |c: (f64, f64), pixels: &mut Vec<usize>| {
+        pixels.clear();
+        let escape = fractal.escape_time(c, max_iter, bailout, periodicity, None);
+        let iterations = escape.iterations as u32;
+        if iterations >= max_iter || iterations < buddhabrot.min_iter {
+            return None;
+        }
+        fractal.orbit(c, iterations, |z| pixels.extend(plot.pixel(z)));
+        Some(iterations)
+    };
+
+    (0..chains)
+        .into_par_iter()
+        .fold(
+            || vec![0.0f64; len],
+            |mut grid, chain| {
+                let mut rng = generator(buddhabrot.seed, chain);
+                let samples = buddhabrot.samples / chains
+                    + (chain < buddhabrot.samples % chains) as u64;
+                let square = |rng: &mut SmallRng| {
+                    (
+                        rng.gen_range(-SAMPLE_RADIUS..SAMPLE_RADIUS),
+                        rng.gen_range(-SAMPLE_RADIUS..SAMPLE_RADIUS),
+                    )
+                };
+                throttle::paced(|| {
+                    let mut taken = 0;
+                    let mut current = None;
+                    while taken < samples && current.is_none() {
+                        let c = match taken % 2 {
+                            0 => square(&mut rng),
+                            _ => (
+                                plot.middle.0 + rng.gen_range(-reach..reach),
+                                plot.middle.1 + rng.gen_range(-reach..reach),
+                            ),
+                        };
+                        taken += 1;
+                        let mut pixels = Vec::new();
+                        current = trace(c, &mut pixels)
+                            .filter(|_| !pixels.is_empty())
+                            .map(|iterations| Orbit {
+                                c,
+                                iterations,
+                                pixels,
+                            });
+                    }
+                    let Some(mut current) = current else {
+                        return;
+                    };
+                    let mut proposed = Vec::new();
+                    for n in 0..samples - taken {
+                        let c = match rng.gen_bool(LARGE_MUTATION) {
+                            true => square(&mut rng),
+                            false => {
+                                let (dx, dy) = nudge(&mut rng);
+                                (current.c.0 + dx * step, current.c.1 + dy * step)
+                            }
+                        };
+                        if let Some(iterations) = trace(c, &mut proposed) {
+                            let chance = proposed.len() as f64 / current.pixels.len() as f64;
+                            if rng.gen::<f64>() < chance {
+                                current.c = c;
+                                current.iterations = iterations;
+                                std::mem::swap(&mut current.pixels, &mut proposed);
+                            }
+                        }
+                        if n < metropolis.burn_in {
+                            continue;
+                        }
+                        let weight = 1.0 / current.pixels.len() as f64;
+                        for &pixel in &current.pixels {
+                            for (k, &band) in bands.iter().enumerate() {
+                                if current.iterations < band {
+                                    grid[pixel * channels + k] += weight;
+                                }
+                            }
+                        }
+                    }
+                });
+                grid
+            },
+        )
+        .reduce(
+            || vec![0.0f64; len],
+            |mut total, grid| {
+                for (total, count) in total.iter_mut().zip(grid) {
+                    *total += count;
+                }
+                total
+            },
+        )
+}
+
+/// A step in a random direction, of a length between `1 / STEP_RANGE`
+/// and 1 spread evenly over its scales, so chains both creep along the
+/// orbits they have found and hop between ones further apart.
+fn nudge(rng: &mut SmallRng) -> (f64, f64) {
+    let length = STEP_RANGE.powf(-rng.gen::<f64>());
+    let angle = TAU * rng.gen::<f64>();
+    (length * angle.cos(), length * angle.sin())
+}
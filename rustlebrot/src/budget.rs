@@ -0,0 +1,263 @@
+/// The share of a time budget spent on rendering. The probes only time
+/// the escape loop, so the rest is left for coloring, saving and encoding
+/// frames.
+pub const MARGIN: f64 = 0.9;
+
+/// How far the iteration limits can be scaled from the ones a run asks
+/// for, either way.
+pub const MAX_SCALE: f64 = 64.0;
+
+/// Re-estimates that move the scale by less than this factor leave it be,
+/// so the limits don't wobble with the timing noise of every frame.
+const ADJUST_FACTOR: f64 = 1.1;
+
+/// Frames written before re-estimating. The first few take longer while
+/// the threads warm up, and say little about the rest.
+const SETTLE_FRAMES: usize = 3;
+
+/// How much every frame moves the rate frames take against the model's
+/// prediction. Frames keep getting deeper, so the recent ones say the most
+/// about the ones to come.
+const RATE_WEIGHT: f64 = 0.25;
+
+/// One calibration render for `--time-budget`: a frame of the run, rendered
+/// small at one iteration limit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Probe {
+    pub frame: u32,
+    pub max_iter: u32,
+    pub samples: u64,
+    pub seconds: f64,
+}
+
+/// What a frame of a run costs to render, as a function of its iteration
+/// limit and how deep it is.
+///
+/// Every probed frame is rendered at two limits. A frame costs some time
+/// per sample whatever the limit, and some per iteration of the samples
+/// that are still going at the limit, which periodicity checking makes
+/// far less than the limit for much of the interior, so the time per
+/// sample runs in a line through the two probes and on past them. Deeper
+/// frames cost more per iteration as they take more precision, which
+/// probes at different depths see. Between probed frames, costs are
+/// interpolated.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CostModel {
+    fits: Vec<Fit>,
+}
+
+/// The cost of one probed frame, as its limits and seconds per sample at
+/// the two probes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Fit {
+    frame: u32,
+    low: (f64, f64),
+    high: (f64, f64),
+}
+
+impl Fit {
+    fn new(low: &Probe, high: &Probe) -> Fit {
+        let point = |probe: &Probe| (probe.max_iter as f64, probe.seconds / probe.samples as f64);
+        Fit {
+            frame: low.frame,
+            low: point(low),
+            high: point(high),
+        }
+    }
+
+    fn seconds_per_sample(&self, max_iter: u32) -> f64 {
+        let ((low_iter, low_time), (high_iter, high_time)) = (self.low, self.high);
+        let max_iter = max_iter as f64;
+        // Timing noise can make the higher limit look cheaper, which no
+        // frame is.
+        let slope = match high_iter > low_iter {
+            true => ((high_time - low_time) / (high_iter - low_iter)).max(0.0),
+            false => 0.0,
+        };
+        let time = low_time + slope * (max_iter - low_iter);
+        // Below the probes, nothing costs less than if it were all
+        // iterations.
+        time.max(low_time * (max_iter / low_iter).min(1.0))
+    }
+}
+
+impl CostModel {
+    /// The model fitted to `probes`, two of every probed frame at different
+    /// limits.
+    pub fn new(probes: &[Probe]) -> CostModel {
+        let mut probes = probes.to_vec();
+        probes.sort_by_key(|probe| (probe.frame, probe.max_iter));
+        let fits = probes
+            .chunk_by(|a, b| a.frame == b.frame)
+            .map(|probes| Fit::new(&probes[0], &probes[probes.len() - 1]))
+            .collect();
+        CostModel { fits }
+    }
+
+    /// The seconds `frame` takes to render with `samples` samples at
+    /// `max_iter`.
+    pub fn seconds(&self, frame: u32, samples: u64, max_iter: u32) -> f64 {
+        let next = self.fits.partition_point(|fit| fit.frame <= frame);
+        let per_sample = match (next.checked_sub(1).map(|at| &self.fits[at]), self.fits.get(next)) {
+            (Some(before), Some(after)) => {
+                let t = (frame - before.frame) as f64 / (after.frame - before.frame) as f64;
+                let (from, to) =
+                    (before.seconds_per_sample(max_iter), after.seconds_per_sample(max_iter));
+                from + t * (to - from)
+            }
+            (Some(fit), None) | (None, Some(fit)) => fit.seconds_per_sample(max_iter),
+            (None, None) => 0.0,
+        };
+        samples as f64 * per_sample
+    }
+
+    /// The seconds `frames`, given with their limits at scale 1, take to
+    /// render with `samples` samples each at `scale`.
+    pub fn total(&self, frames: &[(u32, u32)], samples: u64, scale: f64) -> f64 {
+        frames
+            .iter()
+            .map(|&(frame, max_iter)| self.seconds(frame, samples, scaled(max_iter, scale)))
+            .sum()
+    }
+
+    /// The largest scale of the limits of `frames` that renders them in
+    /// `seconds`, between `1 / MAX_SCALE` and `MAX_SCALE`.
+    pub fn scale(&self, frames: &[(u32, u32)], samples: u64, seconds: f64) -> f64 {
+        let (mut low, mut high) = (-MAX_SCALE.ln(), MAX_SCALE.ln());
+        if self.total(frames, samples, high.exp()) <= seconds {
+            return MAX_SCALE;
+        }
+        for _ in 0..40 {
+            let middle = (low + high) / 2.0;
+            match self.total(frames, samples, middle.exp()) <= seconds {
+                true => low = middle,
+                false => high = middle,
+            }
+        }
+        low.exp()
+    }
+
+    /// The most samples along each side of a pixel, up to `max`, with which
+    /// `frames` of `pixels` pixels still render in `seconds` at no less
+    /// than the limits they ask for, or 1 if none do.
+    pub fn supersample(&self, frames: &[(u32, u32)], pixels: u64, seconds: f64, max: u32) -> u32 {
+        (1..=max)
+            .rev()
+            .find(|&samples| {
+                let samples = pixels * (samples * samples) as u64;
+                self.scale(frames, samples, seconds) >= 1.0
+            })
+            .unwrap_or(1)
+    }
+}
+
+/// `max_iter` scaled by `scale`, and at least 1.
+pub fn scaled(max_iter: u32, scale: f64) -> u32 {
+    (max_iter as f64 * scale).round().clamp(1.0, u32::MAX as f64) as u32
+}
+
+/// The iteration limits of a run fitted into a time budget, kept on track
+/// as frames come in, as with `--time-budget`.
+///
+/// The limits the run asks for, which grow with depth as auto-iter has
+/// them, are all scaled alike, so the deep frames keep the most. After
+/// every frame, the time it took is set against what the model predicted,
+/// and the remaining frames are scaled to fit what is left at the rate of
+/// the recent frames. The time a frame took is the time since the one
+/// before it was written, which is what it adds to the run however many
+/// frames render at once.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimeBudget {
+    model: CostModel,
+    /// The frames still to be written, with their limits at scale 1.
+    remaining: Vec<(u32, u32)>,
+    samples: u64,
+    seconds: f64,
+    scale: f64,
+    written: usize,
+    /// The seconds into the budget the last frame was written at.
+    elapsed: f64,
+    /// The time recent frames took, over what the model predicted.
+    ratio: Option<f64>,
+}
+
+/// A change of the scale of a run's limits partway through it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Adjustment {
+    /// The frame after which the scale changed.
+    pub frame: u32,
+    /// The time recent frames took, over what the model predicted.
+    pub ratio: f64,
+    /// The scale of the limits of the frames from here on.
+    pub scale: f64,
+}
+
+impl TimeBudget {
+    /// Fits `frames`, given with their limits at scale 1 and rendered with
+    /// `samples` samples each, into `seconds`.
+    pub fn new(model: CostModel, frames: Vec<(u32, u32)>, samples: u64, seconds: f64) -> Self {
+        let scale = model.scale(&frames, samples, MARGIN * seconds);
+        TimeBudget {
+            model,
+            remaining: frames,
+            samples,
+            seconds,
+            scale,
+            written: 0,
+            elapsed: 0.0,
+            ratio: None,
+        }
+    }
+
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// The limit of a frame that asks for `max_iter`.
+    pub fn max_iter(&self, max_iter: u32) -> u32 {
+        scaled(max_iter, self.scale)
+    }
+
+    /// The limit of every frame still to be written, as it stands.
+    pub fn schedule(&self) -> Vec<(u32, u32)> {
+        self.remaining.iter().map(|&(frame, max_iter)| (frame, self.max_iter(max_iter))).collect()
+    }
+
+    /// The seconds the frames still to be written are predicted to take.
+    pub fn predicted_seconds(&self) -> f64 {
+        self.model.total(&self.remaining, self.samples, self.scale)
+    }
+
+    /// Takes in that `frame` was written, rendered at `max_iter`, `elapsed`
+    /// seconds into the budget, and rescales the frames still to come if
+    /// they'd miss it by much at the recent rate.
+    pub fn written(&mut self, frame: u32, max_iter: u32, elapsed: f64) -> Option<Adjustment> {
+        self.remaining.retain(|&(remaining, _)| remaining != frame);
+        let predicted = self.model.seconds(frame, self.samples, max_iter);
+        let took = elapsed - std::mem::replace(&mut self.elapsed, elapsed);
+        if predicted > 0.0 {
+            let rate = took / predicted;
+            let ratio = self.ratio.map_or(rate, |ratio| ratio + RATE_WEIGHT * (rate - ratio));
+            self.ratio = Some(ratio);
+        }
+        self.written += 1;
+        let ratio = match self.ratio {
+            Some(ratio) if ratio > 0.0 => ratio,
+            _ => return None,
+        };
+        if self.remaining.is_empty() || self.written < SETTLE_FRAMES {
+            return None;
+        }
+        let left = (self.seconds - elapsed).max(0.0);
+        let scale = self.model.scale(&self.remaining, self.samples, MARGIN * left / ratio);
+        if (scale / self.scale).ln().abs() < ADJUST_FACTOR.ln() {
+            return None;
+        }
+        self.scale = scale;
+        Some(Adjustment {
+            frame,
+            ratio,
+            scale,
+        })
+    }
+}
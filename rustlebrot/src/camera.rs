@@ -0,0 +1,850 @@
+use crate::bigfloat::{self, Big};
+use crate::decimal::Decimal;
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+
+/// A view the camera passes through, as read from `--keyframes`, or every
+/// frame of `--camera-path`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Keyframe {
+    /// The frame the camera is at this view.
+    pub frame: u32,
+    /// The center as decimal strings, with every digit given.
+    pub center: (String, String),
+    /// The scale of the view relative to the first frame of a plain zoom.
+    pub magnification: f64,
+    /// The iteration limit here, in place of the one `max_iter` and
+    /// auto-iter give.
+    pub max_iter: Option<u32>,
+    /// The turn of the view here in degrees, in place of the one
+    /// `--initial-rotation` and `--rotation-per-frame` give. Only rows of
+    /// a camera path set it.
+    pub rotation: Option<f64>,
+}
+
+/// Where the camera is in one frame.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Camera {
+    /// The center as decimal strings, with as many digits as the frame
+    /// needs.
+    pub center: (String, String),
+    pub magnification: f64,
+    pub max_iter: u32,
+}
+
+impl Camera {
+    /// The center, rounded to f64.
+    pub fn approx_center(&self) -> (f64, f64) {
+        approx(&self.center)
+    }
+}
+
+/// `center`, rounded to f64.
+fn approx(center: &(String, String)) -> (f64, f64) {
+    let parse = |digits: &str| digits.parse().expect("centers are validated when read");
+    (parse(&center.0), parse(&center.1))
+}
+
+/// The path the camera takes through the frames of a zoom.
+///
+/// Between two keyframes, the magnification is interpolated in log space,
+/// so the zoom runs at a steady rate, and the center moves so that the next
+/// keyframe's center approaches the middle of the frame steadily on screen.
+/// Before the first keyframe and after the last, the camera stays on the
+/// keyframe's center and zooms by `zoom_factor` every frame, so a path of
+/// one keyframe at frame 0 and magnification 1 is a plain zoom.
+pub struct CameraPath {
+    keyframes: Vec<Keyframe>,
+    zoom_factor: f64,
+    /// The point a plain zoom closes in on, if not its center.
+    toward: Option<(String, String)>,
+    /// The distance between samples at magnification 1, which sets the
+    /// precision interpolated centers are computed in.
+    sample_size: f64,
+}
+
+impl CameraPath {
+    /// The path through `keyframes`, which `read_keyframes` has validated.
+    pub fn new(keyframes: Vec<Keyframe>, zoom_factor: f64, sample_size: f64) -> Self {
+        CameraPath {
+            keyframes,
+            zoom_factor,
+            toward: None,
+            sample_size,
+        }
+    }
+
+    /// The path of a plain zoom into `center`.
+    pub fn fixed(center: (String, String), zoom_factor: f64, sample_size: f64) -> Self {
+        let keyframe = Keyframe {
+            frame: 0,
+            center,
+            magnification: 1.0,
+            max_iter: None,
+            rotation: None,
+        };
+        CameraPath::new(vec![keyframe], zoom_factor, sample_size)
+    }
+
+    /// The path of a plain zoom from a view around `center` that closes in
+    /// on `target`, which stays where it is in the frame.
+    pub fn toward(
+        center: (String, String),
+        target: (String, String),
+        zoom_factor: f64,
+        sample_size: f64,
+    ) -> Self {
+        CameraPath {
+            toward: Some(target),
+            ..CameraPath::fixed(center, zoom_factor, sample_size)
+        }
+    }
+
+    /// The path of `--camera-path`, with a keyframe in every frame of
+    /// `path`, whose views `initial_width` across are at magnification 1.
+    pub fn through(path: &FramePath, initial_width: f64, sample_size: f64) -> Self {
+        let keyframes = path.rows.iter().map(|row| Keyframe {
+            frame: row.frame,
+            center: row.center.clone(),
+            magnification: initial_width / row.width,
+            max_iter: row.max_iter,
+            rotation: row.rotation,
+        });
+        // Every frame is held at its keyframe, so the zoom factor never
+        // comes into it.
+        CameraPath::new(keyframes.collect(), 1.0, sample_size)
+    }
+
+    /// The center the path starts from.
+    pub fn first_center(&self) -> &(String, String) {
+        &self.keyframes[0].center
+    }
+
+    /// The point a plain zoom closes in on, if not its center.
+    pub fn target(&self) -> Option<&(String, String)> {
+        self.toward.as_ref()
+    }
+
+    /// The turn of the view at `frame`, if a keyframe there gives it.
+    pub fn rotation(&self, frame: f64) -> Option<f64> {
+        let keyframe = self.keyframes.iter().find(|keyframe| keyframe.frame as f64 == frame);
+        keyframe.and_then(|keyframe| keyframe.rotation)
+    }
+
+    /// Where the camera is at `frame`, which easing can put between two
+    /// frames. Keyframes without an iteration limit take the one `max_iter`
+    /// gives for their magnification.
+    pub fn at(&self, frame: f64, max_iter: impl Fn(f64) -> u32) -> Camera {
+        let next = self.keyframes.iter().position(|keyframe| keyframe.frame as f64 > frame);
+        let (from, to) = match next {
+            Some(0) => return self.hold(&self.keyframes[0], frame, max_iter),
+            None => return self.hold(self.keyframes.last().unwrap(), frame, max_iter),
+            Some(next) => (&self.keyframes[next - 1], &self.keyframes[next]),
+        };
+        if frame == from.frame as f64 {
+            return self.hold(from, frame, max_iter);
+        }
+        let t = (frame - from.frame as f64) / (to.frame - from.frame) as f64;
+        let ratio = to.magnification / from.magnification;
+        let magnification = from.magnification * ratio.powf(t);
+        let budget = |keyframe: &Keyframe| {
+            keyframe.max_iter.unwrap_or_else(|| max_iter(keyframe.magnification)) as f64
+        };
+        let (start, end) = (budget(from), budget(to));
+        Camera {
+            center: self.between(from, to, t, ratio),
+            magnification,
+            max_iter: (start * (end / start).powf(t)).round() as u32,
+        }
+    }
+
+    /// The camera in `frame`, after or before `keyframe`, zooming by
+    /// `zoom_factor` on its center.
+    ///
+    /// A keyframe's iteration limit grows from there on as `max_iter` does.
+    fn hold(&self, keyframe: &Keyframe, frame: f64, max_iter: impl Fn(f64) -> u32) -> Camera {
+        let steps = frame - keyframe.frame as f64;
+        // On whole frames, exactly as a zoom without easing has it.
+        let magnification = keyframe.magnification
+            * match steps.fract() == 0.0 {
+                true => self.zoom_factor.powi(steps as i32),
+                false => self.zoom_factor.powf(steps),
+            };
+        let growth = max_iter(magnification) as f64 / max_iter(keyframe.magnification) as f64;
+        let center = match &self.toward {
+            Some(target) if steps != 0.0 => {
+                self.closing_in(&keyframe.center, target, keyframe.magnification / magnification)
+            }
+            _ => keyframe.center.clone(),
+        };
+        Camera {
+            center,
+            magnification,
+            max_iter: match keyframe.max_iter {
+                Some(limit) => (limit as f64 * growth).round().max(1.0) as u32,
+                None => max_iter(magnification),
+            },
+        }
+    }
+
+    /// The center of the view around `center` shrunk by `ratio` about
+    /// `target`, which keeps the target where it was in the frame.
+    ///
+    /// The offset from the target is what shrinks, so it's taken in enough
+    /// bits for the pixels of the smaller view, as in `between`.
+    fn closing_in(
+        &self,
+        center: &(String, String),
+        target: &(String, String),
+        ratio: f64,
+    ) -> (String, String) {
+        let bits = bigfloat::required_bits(approx(target), self.sample_size * ratio);
+        let parse = |digits: &str| {
+            bigfloat::parse_decimal(digits, bits).expect("centers are validated when read")
+        };
+        let point = |center: &str, target: &str| -> String {
+            let target = parse(target);
+            let offset = &parse(center) - &target;
+            let point = target + offset * bigfloat::from_f64(ratio, bits);
+            bigfloat::to_decimal(&point, (bits as f64 * 2f64.log10()).ceil() as usize + 1)
+        };
+        (point(&center.0, &target.0), point(&center.1, &target.1))
+    }
+
+    /// The center a fraction `t` of the way from `from` to `to`, whose
+    /// magnifications differ by `ratio`.
+    ///
+    /// Zooming in, the remaining offset to `to` is what has to stay accurate
+    /// at the deep end, so it is computed on its own instead of as one minus
+    /// the part covered, and added to `to` in enough bits for the deeper
+    /// keyframe's pixels. Zooming out, the same holds for the part covered
+    /// and `from`.
+    fn between(&self, from: &Keyframe, to: &Keyframe, t: f64, ratio: f64) -> (String, String) {
+        // The offset to the target shrinks linearly in units of the view
+        // width, which keeps it in sight all the way.
+        let (covered, remaining) = if ratio >= 1.0 {
+            let remaining = (1.0 - t) * ratio.powf(-t);
+            (1.0 - remaining, remaining)
+        } else {
+            let covered = t * ratio.powf(1.0 - t);
+            (covered, 1.0 - covered)
+        };
+        let deepest = from.magnification.max(to.magnification);
+        let bits = bigfloat::required_bits(approx(&from.center), self.sample_size / deepest);
+        let parse = |digits: &str| {
+            bigfloat::parse_decimal(digits, bits).expect("centers are validated when read")
+        };
+        let point = |start: &str, end: &str| -> String {
+            let (start, end) = (parse(start), parse(end));
+            let offset = &end - &start;
+            let point: Big = match covered <= 0.5 {
+                true => start + offset * bigfloat::from_f64(covered, bits),
+                false => end - offset * bigfloat::from_f64(remaining, bits),
+            };
+            // Enough decimal digits to carry every bit.
+            bigfloat::to_decimal(&point, (bits as f64 * 2f64.log10()).ceil() as usize + 1)
+        };
+        (
+            point(&from.center.0, &to.center.0),
+            point(&from.center.1, &to.center.1),
+        )
+    }
+}
+
+/// How the zoom is spread over the frames of a run, as chosen with
+/// `--easing`.
+///
+/// Eased zooms start and end where plain ones do, but ramp the zoom speed
+/// up from a standstill, down to one, or both.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    Smoothstep,
+}
+
+impl Easing {
+    pub fn from_name(name: &str) -> Option<Easing> {
+        match name {
+            "linear" => Some(Easing::Linear),
+            "ease-in" => Some(Easing::EaseIn),
+            "ease-out" => Some(Easing::EaseOut),
+            "ease-in-out" => Some(Easing::EaseInOut),
+            "smoothstep" => Some(Easing::Smoothstep),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Easing::Linear => "linear",
+            Easing::EaseIn => "ease-in",
+            Easing::EaseOut => "ease-out",
+            Easing::EaseInOut => "ease-in-out",
+            Easing::Smoothstep => "smoothstep",
+        }
+    }
+
+    /// Where in the run the camera is once a fraction `u` of its frames
+    /// have passed, as a fraction as well.
+    pub fn apply(self, u: f64) -> f64 {
+        match self {
+            Easing::Linear => u,
+            Easing::EaseIn => u * u,
+            Easing::EaseOut => 1.0 - (1.0 - u) * (1.0 - u),
+            Easing::EaseInOut if u < 0.5 => 4.0 * u * u * u,
+            Easing::EaseInOut => 1.0 - (2.0 - 2.0 * u).powi(3) / 2.0,
+            Easing::Smoothstep => u * u * (3.0 - 2.0 * u),
+        }
+    }
+}
+
+/// `--motion-blur`: every frame is the mean of `sub_frames` views taken
+/// while the shutter is open, which is `shutter_angle` degrees of the 360
+/// a frame lasts, centered on the frame.
+///
+/// The views are spread evenly over that time, each in the middle of its
+/// share of it, so the blur of a frame reaches halfway to the next only
+/// with a shutter open all the time, at 360°.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MotionBlur {
+    pub sub_frames: u32,
+    pub shutter_angle: f64,
+}
+
+impl MotionBlur {
+    /// When the views of `frame` are taken, in frames.
+    pub fn times(self, frame: u32) -> impl Iterator<Item = f64> {
+        let open = self.shutter_angle / 360.0;
+        let share = move |index: u32| (index as f64 + 0.5) / self.sub_frames as f64 - 0.5;
+        (0..self.sub_frames).map(move |index| frame as f64 + open * share(index))
+    }
+}
+
+/// Which way the camera moves over the frames of a run, as chosen with
+/// `--direction`.
+///
+/// A zoom factor below 1 already zooms out from the first view. `Out`
+/// shows the frames of the zoom in backwards instead, pulling back from
+/// the deepest of them to the first view, and `InOut` has the video play
+/// the zoom in and then back out, from the frames rendered once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    In,
+    Out,
+    InOut,
+}
+
+impl Direction {
+    pub fn from_name(name: &str) -> Option<Direction> {
+        match name {
+            "in" => Some(Direction::In),
+            "out" => Some(Direction::Out),
+            "in-out" => Some(Direction::InOut),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Direction::In => "in",
+            Direction::Out => "out",
+            Direction::InOut => "in-out",
+        }
+    }
+}
+
+/// Reads the keyframes of a camera path from the TOML file at `path`.
+///
+/// Only the small part of TOML keyframes need is understood: a
+/// `[[keyframe]]` table per keyframe, each with `frame`, `center` as an
+/// array of two numbers, best quoted to keep every digit, `magnification`
+/// and optionally `max_iter`, one per line, and `#` comments. Keyframes
+/// have to come in frame order, at least a frame apart.
+pub fn read_keyframes(path: &str) -> Result<Vec<Keyframe>, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("can't read keyframes file '{}': {}", path, e))?;
+    let mut tables: Vec<(usize, Table)> = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let number = index + 1;
+        // Digits are quoted, but never contain a '#'.
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line == "[[keyframe]]" {
+            tables.push((number, HashMap::new()));
+            continue;
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("{}:{}: expected [[keyframe]] or key = value", path, number))?;
+        let (_, table) = tables.last_mut().ok_or_else(|| {
+            format!("{}:{}: '{}' comes before any [[keyframe]]", path, number, line)
+        })?;
+        if table.insert(key.trim(), (value.trim(), number)).is_some() {
+            return Err(format!("{}:{}: '{}' is given twice", path, number, key.trim()));
+        }
+    }
+
+    let mut keyframes: Vec<Keyframe> = Vec::new();
+    for (start, table) in &tables {
+        let keyframe = keyframe(table).map_err(|e| format!("{}:{}: {}", path, start, e))?;
+        if let Some(previous) = keyframes.last() {
+            if keyframe.frame <= previous.frame {
+                return Err(format!(
+                    "{}:{}: the keyframe at frame {} comes after one at frame {}; keyframes have \
+                     to be in frame order, at least one frame apart",
+                    path, start, keyframe.frame, previous.frame
+                ));
+            }
+        }
+        keyframes.push(keyframe);
+    }
+    if keyframes.is_empty() {
+        return Err(format!("{} has no [[keyframe]] tables", path));
+    }
+    Ok(keyframes)
+}
+
+/// The keys of a `[[keyframe]]` table, with their values and line numbers.
+type Table<'a> = HashMap<&'a str, (&'a str, usize)>;
+
+/// The keyframe of one `[[keyframe]]` table.
+fn keyframe(table: &Table) -> Result<Keyframe, String> {
+    if let Some((key, (_, number))) = table
+        .iter()
+        .find(|(key, _)| !["frame", "center", "magnification", "max_iter"].contains(key))
+    {
+        return Err(format!("unknown key '{}' on line {}", key, number));
+    }
+    let field = |key: &str| {
+        table.get(key).map(|&(value, _)| value).ok_or_else(|| format!("no '{}' given", key))
+    };
+    let integer = |key: &str, value: &str| {
+        value.parse::<u32>().map_err(|_| format!("'{}' should be an integer", key))
+    };
+    let number = |value: &str| value.trim().trim_matches('"').trim().to_string();
+
+    let center = field("center")?;
+    let (x, y) = center
+        .strip_prefix('[')
+        .and_then(|center| center.strip_suffix(']'))
+        .and_then(|center| center.split_once(','))
+        .map(|(x, y)| (number(x), number(y)))
+        .filter(|(x, y)| Decimal::parse(x).is_ok() && Decimal::parse(y).is_ok())
+        .ok_or_else(|| {
+            format!("'center' should be two numbers like [\"x\", \"y\"], got {}", center)
+        })?;
+    let magnification: f64 = field("magnification")?
+        .parse()
+        .map_err(|_| "'magnification' should be a number".to_string())?;
+    if !(magnification > 0.0 && magnification.is_finite()) {
+        return Err(format!("'magnification' should be above 0, got {}", magnification));
+    }
+    let max_iter = table
+        .get("max_iter")
+        .map(|&(value, _)| integer("max_iter", value))
+        .transpose()?;
+    if max_iter == Some(0) {
+        return Err("'max_iter' should be at least 1".to_string());
+    }
+    Ok(Keyframe {
+        frame: integer("frame", field("frame")?)?,
+        center: (x, y),
+        magnification,
+        max_iter,
+        rotation: None,
+    })
+}
+
+/// The camera in every frame, as read from `--camera-path`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FramePath {
+    /// The file the rows were read from.
+    pub file: String,
+    /// The CRC-32 of the file, which tells a run whether it still holds
+    /// the path an earlier one followed.
+    pub crc32: u32,
+    /// A row for every frame from the first of them, in frame order.
+    pub rows: Vec<PathRow>,
+}
+
+/// Where the camera is in one frame of `--camera-path`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathRow {
+    pub frame: u32,
+    /// The center as decimal strings, with every digit given.
+    pub center: (String, String),
+    /// The width of the view in the complex plane.
+    pub width: f64,
+    /// The turn of the view in degrees, if given.
+    pub rotation: Option<f64>,
+    /// The iteration limit of the frame, if given.
+    pub max_iter: Option<u32>,
+}
+
+impl FramePath {
+    /// The frames the path has rows for.
+    pub fn frames(&self) -> Range<u32> {
+        self.rows[0].frame..self.rows[self.rows.len() - 1].frame + 1
+    }
+}
+
+/// Reads a camera path from the CSV file at `path`.
+///
+/// The header names the columns: `frame,center_x,center_y,width` and then
+/// `rotation`, `max_iter` or both, if the rows give them. Rows leaving
+/// those empty take the rotation and limit the run would give the frame.
+/// There has to be a row for every frame, in frame order, and centers are
+/// best given with every digit the frame needs. Blank lines and `#`
+/// comments are skipped.
+pub fn read_camera_path(path: &str) -> Result<FramePath, String> {
+    let text =
+        fs::read_to_string(path).map_err(|e| format!("can't read camera path '{}': {}", path, e))?;
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.split('#').next().unwrap_or("").trim()))
+        .filter(|(_, line)| !line.is_empty());
+    let Some((number, header)) = lines.next() else {
+        return Err(format!("{} has no header and no rows", path));
+    };
+    let columns: Vec<&str> = header.split(',').map(str::trim).collect();
+    let optional = &columns[4.min(columns.len())..];
+    let repeated = optional.iter().enumerate().any(|(at, name)| optional[..at].contains(name));
+    if columns[..4.min(columns.len())] != ["frame", "center_x", "center_y", "width"]
+        || optional.iter().any(|name| !["rotation", "max_iter"].contains(name))
+        || repeated
+    {
+        return Err(format!(
+            "{}:{}: expected a header of frame,center_x,center_y,width, then rotation, max_iter \
+             or both, got '{}'",
+            path, number, header
+        ));
+    }
+
+    let mut rows: Vec<PathRow> = Vec::new();
+    for (number, line) in lines {
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        if fields.len() != columns.len() {
+            return Err(format!(
+                "{}:{}: expected {} fields like the header, got '{}'",
+                path,
+                number,
+                columns.len(),
+                line
+            ));
+        }
+        let given = |name: &str| {
+            let at = columns.iter().position(|column| *column == name)?;
+            Some(fields[at]).filter(|value| !value.is_empty())
+        };
+        let invalid = |name: &str, expected: &str| {
+            format!("{}:{}: {} should be {}, got '{}'", path, number, name, expected, line)
+        };
+        let frame: u32 = fields[0].parse().map_err(|_| invalid("frame", "an integer"))?;
+        let (x, y) = (fields[1].to_string(), fields[2].to_string());
+        if Decimal::parse(&x).is_err() || Decimal::parse(&y).is_err() {
+            return Err(invalid("the center", "two numbers"));
+        }
+        let width = fields[3].parse().ok().filter(|&width: &f64| width > 0.0 && width.is_finite());
+        let width = width.ok_or_else(|| invalid("width", "a number above 0"))?;
+        let rotation = given("rotation")
+            .map(|value| value.parse().ok().filter(|rotation: &f64| rotation.is_finite()))
+            .map(|rotation| rotation.ok_or_else(|| invalid("rotation", "a number of degrees")))
+            .transpose()?;
+        let max_iter = given("max_iter")
+            .map(|value| value.parse().ok().filter(|&max_iter: &u32| max_iter > 0))
+            .map(|max_iter| max_iter.ok_or_else(|| invalid("max_iter", "an integer above 0")))
+            .transpose()?;
+        if let Some(previous) = rows.last() {
+            if previous.frame.checked_add(1) != Some(frame) {
+                return Err(format!(
+                    "{}:{}: the row for frame {} comes after the one for frame {}; rows have to \
+                     be one for every frame, in frame order",
+                    path, number, frame, previous.frame
+                ));
+            }
+        }
+        rows.push(PathRow {
+            frame,
+            center: (x, y),
+            width,
+            rotation,
+            max_iter,
+        });
+    }
+    if rows.is_empty() {
+        return Err(format!("{} has no rows after its header", path));
+    }
+    Ok(FramePath {
+        file: path.to_string(),
+        crc32: crc32fast::hash(text.as_bytes()),
+        rows,
+    })
+}
+
+/// A glide of the camera along a path at a fixed magnification, as `pan`
+/// and `--pan-from` give it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pan {
+    /// The centers the camera passes through, from the first frame's to
+    /// the last's.
+    pub waypoints: Vec<(String, String)>,
+    /// The width of the view in the plane.
+    pub view_width: f64,
+    /// The samples along the edges the view moves toward that every frame
+    /// taken from the one before computes again, with those newly in view.
+    /// Orbits that nearly escape at the edge of a frame pick up the
+    /// rounding of its center, and those right in from it get another go.
+    pub refresh: u32,
+}
+
+/// Where the camera is in every frame of a pan.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PanPlan {
+    pub first_frame: u32,
+    /// How far every frame is from the first waypoint, in whole pixels
+    /// right and up.
+    pub offsets: Vec<(i64, i64)>,
+    /// A keyframe at every frame, at its offset.
+    pub keyframes: Vec<Keyframe>,
+    pub refresh: u32,
+}
+
+impl PanPlan {
+    /// The pixels the view moves right and up by from frame `from` to
+    /// frame `to`, if both are in the pan.
+    pub fn shift(&self, from: u32, to: u32) -> Option<(i64, i64)> {
+        let offset = |frame: u32| self.offsets.get(frame.checked_sub(self.first_frame)? as usize);
+        let (from, to) = (offset(from)?, offset(to)?);
+        Some((to.0 - from.0, to.1 - from.1))
+    }
+}
+
+impl Pan {
+    /// The plan of the pan over `frames` at `magnification`, with pixels
+    /// `pixel_size` wide and samples `sample_size` apart, moving along the
+    /// path at the pace `easing` sets.
+    ///
+    /// The camera goes the same distance along the path between frames
+    /// unless eased, and every frame is put on a whole pixel from the
+    /// first waypoint, so the samples it shares with the one before are at
+    /// the same points. The last frame can be up to half a pixel from the
+    /// last waypoint.
+    pub fn plan(
+        &self,
+        frames: Range<u32>,
+        easing: Easing,
+        magnification: f64,
+        pixel_size: f64,
+        sample_size: f64,
+    ) -> PanPlan {
+        let bits = bigfloat::required_bits(approx(&self.waypoints[0]), sample_size);
+        let parse = |digits: &str| {
+            bigfloat::parse_decimal(digits, bits).expect("centers are validated when read")
+        };
+        let start = (parse(&self.waypoints[0].0), parse(&self.waypoints[0].1));
+        // Only the differences between waypoints need every bit, and in
+        // pixels they fit in f64.
+        let points: Vec<(f64, f64)> = self
+            .waypoints
+            .iter()
+            .map(|(x, y)| {
+                let pixels = |digits: &str, start: &Big| {
+                    (&parse(digits) - start).to_f64().value() / pixel_size
+                };
+                (pixels(x, &start.0), pixels(y, &start.1))
+            })
+            .collect();
+        let lengths: Vec<f64> = points
+            .windows(2)
+            .map(|pair| (pair[1].0 - pair[0].0).hypot(pair[1].1 - pair[0].1))
+            .collect();
+        let total: f64 = lengths.iter().sum();
+        let count = frames.len();
+        let offsets: Vec<(i64, i64)> = (0..count)
+            .map(|index| {
+                let u = index as f64 / (count.max(2) - 1) as f64;
+                let mut along = easing.apply(u) * total;
+                let mut point = points[0];
+                for (pair, &length) in points.windows(2).zip(&lengths) {
+                    if along <= length {
+                        let t = match length > 0.0 {
+                            true => along / length,
+                            false => 0.0,
+                        };
+                        let (from, to) = (pair[0], pair[1]);
+                        point = (from.0 + t * (to.0 - from.0), from.1 + t * (to.1 - from.1));
+                        break;
+                    }
+                    along -= length;
+                    point = pair[1];
+                }
+                (point.0.round() as i64, point.1.round() as i64)
+            })
+            .collect();
+        // Enough decimal digits to carry every bit.
+        let digits = (bits as f64 * 2f64.log10()).ceil() as usize + 1;
+        let moved = |start: &Big, pixels: i64| {
+            let pixels = bigfloat::from_f64(pixels as f64, bits);
+            let point = start.clone() + bigfloat::from_f64(pixel_size, bits) * pixels;
+            bigfloat::to_decimal(&point, digits)
+        };
+        let keyframes = frames
+            .clone()
+            .zip(&offsets)
+            .map(|(frame, &(x, y))| Keyframe {
+                frame,
+                center: (moved(&start.0, x), moved(&start.1, y)),
+                magnification,
+                max_iter: None,
+                rotation: None,
+            })
+            .collect();
+        PanPlan {
+            first_frame: frames.start,
+            offsets,
+            keyframes,
+            refresh: self.refresh,
+        }
+    }
+}
+
+/// How `animate-iter` runs the iteration limit from its start to its end.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IterCurve {
+    /// By the same number of iterations every frame.
+    Linear,
+    /// By the same factor every frame, so the early frames, where the
+    /// bands of low escape times move the most, aren't over in a few.
+    Log,
+}
+
+impl IterCurve {
+    pub fn from_name(name: &str) -> Option<IterCurve> {
+        match name {
+            "linear" => Some(IterCurve::Linear),
+            "log" => Some(IterCurve::Log),
+            _ => None,
+        }
+    }
+
+    /// The limit of `frame` of `frames`, from `start` at the first to `end`
+    /// at the last.
+    pub fn at(self, start: u32, end: u32, frame: u32, frames: u32) -> u32 {
+        let u = frame as f64 / (frames.max(2) - 1) as f64;
+        let (start, end) = (start as f64, end as f64);
+        let max_iter = match self {
+            IterCurve::Linear => start + u * (end - start),
+            IterCurve::Log => start * (end / start).powf(u),
+        };
+        max_iter.round() as u32
+    }
+}
+
+/// The iteration limit of every frame, as read from `--iter-schedule`, in
+/// place of the one `max_iter` gives.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IterSchedule {
+    /// The frames the limit is given at and the limit there, in frame
+    /// order.
+    points: Vec<(u32, u32)>,
+    /// Whether the limit runs in a straight line from one point to the next,
+    /// rather than holding until the next.
+    interpolate: bool,
+}
+
+impl IterSchedule {
+    /// The frame the schedule starts at, before which it gives no limit.
+    pub fn first_frame(&self) -> u32 {
+        self.points[0].0
+    }
+
+    /// The frames the limit is given at and the limit there.
+    pub fn points(&self) -> &[(u32, u32)] {
+        &self.points
+    }
+
+    pub fn interpolates(&self) -> bool {
+        self.interpolate
+    }
+
+    /// The iteration limit of `frame`. Past the last point, its limit
+    /// holds.
+    pub fn at(&self, frame: u32) -> u32 {
+        let next = self.points.partition_point(|&(start, _)| start <= frame);
+        let (start, max_iter) = self.points[next.max(1) - 1];
+        match self.points.get(next) {
+            Some(&(end, end_iter)) if self.interpolate && frame >= start => {
+                let t = (frame - start) as f64 / (end - start) as f64;
+                (max_iter as f64 + t * (end_iter as f64 - max_iter as f64)).round() as u32
+            }
+            _ => max_iter,
+        }
+    }
+}
+
+/// Reads an iteration schedule from the CSV file at `path`.
+///
+/// The header says what the rows are: under `frame_start,max_iter` each
+/// row's limit holds from its frame until the next row's, and under
+/// `frame,max_iter` the rows are breakpoints the limit runs between in
+/// straight lines. Rows have to come in frame order, at least a frame
+/// apart. Blank lines and `#` comments are skipped.
+pub fn read_iter_schedule(path: &str) -> Result<IterSchedule, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("can't read iteration schedule '{}': {}", path, e))?;
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.split('#').next().unwrap_or("").trim()))
+        .filter(|(_, line)| !line.is_empty());
+    let interpolate = match lines.next().map(|(number, line)| (number, line.replace(' ', ""))) {
+        Some((_, header)) if header == "frame_start,max_iter" => false,
+        Some((_, header)) if header == "frame,max_iter" => true,
+        Some((number, header)) => {
+            return Err(format!(
+                "{}:{}: expected a header of frame_start,max_iter or frame,max_iter, got '{}'",
+                path, number, header
+            ))
+        }
+        None => return Err(format!("{} has no header and no rows", path)),
+    };
+
+    let mut points: Vec<(u32, u32)> = Vec::new();
+    for (number, line) in lines {
+        let (frame, max_iter) = line
+            .split_once(',')
+            .and_then(|(frame, max_iter)| {
+                Some((frame.trim().parse::<u32>().ok()?, max_iter.trim().parse::<u32>().ok()?))
+            })
+            .ok_or_else(|| {
+                format!("{}:{}: expected two integers like 100,5000, got '{}'", path, number, line)
+            })?;
+        if max_iter == 0 {
+            return Err(format!("{}:{}: max_iter should be at least 1", path, number));
+        }
+        if let Some(&(previous, _)) = points.last() {
+            if frame <= previous {
+                return Err(format!(
+                    "{}:{}: the row for frame {} comes after one for frame {}; rows have to be \
+                     in frame order, at least one frame apart",
+                    path, number, frame, previous
+                ));
+            }
+        }
+        points.push((frame, max_iter));
+    }
+    if points.is_empty() {
+        return Err(format!("{} has no rows after its header", path));
+    }
+    Ok(IterSchedule {
+        points,
+        interpolate,
+    })
+}
@@ -0,0 +1,5872 @@
+use crate::bench;
+use colorgrad::Color;
+#[cfg(feature = "daemon")]
+use crate::daemon::{self, Endpoint};
+use crate::precision::Precision;
+use crate::preset::{self, Preset};
+use crate::quality::{self, Quality};
+use crate::camera::{
+    self, Direction, Easing, FramePath, IterCurve, IterSchedule, Keyframe, MotionBlur, Pan,
+};
+use crate::events::{self, Verbosity};
+use crate::formula::Formula;
+use crate::fractal::FractalKind;
+use crate::hud::{Corner, Hud};
+use crate::inset::JuliaInset;
+use crate::julia::CPath;
+use crate::bookmarks;
+use crate::location::Location;
+use crate::lyapunov::{self, Lyapunov};
+use crate::newton::Newton;
+use crate::coloring::{Coloring, Phase, Transfer, MAX_BINARY_SECTORS};
+use crate::contour::{Contours, Line};
+use crate::debug::DebugChannels;
+use crate::decimal::Decimal;
+use crate::difference;
+use crate::dither::Dither;
+use crate::error::RustlebrotError;
+use crate::trap::Trap;
+use crate::lighting::Lighting;
+use crate::lineart::{LineArt, Paper};
+use crate::mesh::{self, Plateau};
+use crate::mode::Mode;
+use crate::buddhabrot::{Sampler, ToneMap};
+use crate::interior::InteriorColoring;
+use crate::isoline::Spacing;
+use crate::ray::ExternalAngle;
+use crate::orbit;
+use crate::outputs::Output;
+use crate::export::{Export, ImageFormat};
+use crate::events::ProgressFormat;
+use crate::manifest::{
+    rgb, AdaptiveRecord, CPathRecord, ContoursRecord, IterScheduleRecord, KeyframeRecord,
+    LightingRecord, LineArtRecord, LineRecord, LyapunovRecord, PaletteRecord, PanRecord,
+    RangesRecord, RoiRecord, Settings, Shard, Shutter, StopRecord, ZoomTarget,
+};
+use crate::render::{Adaptive, Alpha, BitDepth, Incremental, Roi, Subdivision, WorkUnits};
+use crate::script::Script;
+use crate::stats::EarlyStop;
+use crate::template::{self, FilenameTemplate};
+use crate::terminal::Protocol;
+use crate::video::{EncoderKind, GifOptions, Sequence, VideoOptions};
+use crate::palette::{self, Adjust, Blending, Palette, Stop};
+use crate::post::Chain;
+use crate::view::{Corners, Fit};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const USAGE: &str =
+    "Usage: rustlebrot [--quiet | --verbose] [--output-dir PATH] <command> ...\n   or: rustlebrot render (--max-iter N --zoom-start A --zoom-end B --zoom-factor F | <max_iter> <zoom_start> <zoom_end> <zoom_factor>\n       | --max-iter N --target-magnification M --duration D [--fps N])\n       [--fractal mandelbrot|tricorn|newton|julia|lyapunov] [--poly COEFFS]\n       [--c-path circle:center=C,radius=R[,turns=N]|keyframes:C,C,...] [--c-easing linear|ease-in|ease-out|ease-in-out|smoothstep]\n       [--sequence AB...] [--warmup N]\n       [--formula EXPR] [--formula-log-base B] [--precision auto|f32|f64|dd|perturb|big] [--force-precision f32|f64|dd|perturb|big]\n       [--allow-precision-loss] [--series-terms N]\n       [--no-periodicity] [--subdivide] [--show-subdivision] [--work-unit rows|tiles] [--chunk-size N] [--supersample N]\n       [--adaptive] [--adaptive-threshold T]\n       [--incremental] [--incremental-threshold T] [--keyframe-every N] [--coloring escape|smooth|histogram|distance|trap|phase|binary[:K]|stripes]\n       [--histogram-clip P] [--stabilize-colors W] [--transfer linear|sqrt|log|power:G] [--phase-weight W] [--phase-turns N] [--stripe-density S]\n       [--color-expr PATH] [--interior-coloring period|derivative|both]\n       [--lighting angle=A,elevation=E,strength=S[,specular=K][,spin=D]]
+       [--contours every=N[,width=W][,color=COLOR][,background=COLOR]] [--silhouette width=W[,color=COLOR]] [--style palette|lineart] [--line-threshold T] [--line-weight K] [--line-silhouette] [--line-interior] [--line-thin] [--line-paper white|transparent] [--palette NAME|PATH]... [--gradient STOPS] [--gradient-file PATH]\n       [--palette-image PATH] [--palette-map PATH] [--map-interpolate] [--interior-color COLOR]\n       [--palette-resolution N] [--palette-cycles N] [--palette-offset P] [--palette-reverse] [--palette-drift C] [--invert on|off] [--hue-shift DEG]\n       [--saturation S] [--gamma G] [--legacy-gamma] [--trap point[:x,y]|cross[:x,y]|circle[:r]]\n       [--mode escape|buddhabrot|nebulabrot] [--samples N] [--min-iter N] [--tone sqrt|log] [--bands R,G,B]\n       [--sampler uniform|metropolis] [--mutation-scale S] [--burn-in N] [--seed N]\n       [--auto-iter] [--iter-growth K] [--iter-schedule PATH] [--dry-run] [--yes] [--bailout R] [--center x,y]\n       [--preset NAME] [--location PATH] [--location-name NAME]\n       [--quality draft|preview|standard|high|insane]\n       [--save-location PATH] [--keyframes PATH] [--camera-path PATH]\n       [--pan-from x,y [--pan-via x,y]... --pan-to x,y --view-width W [--refresh-band N]] [--easing linear|ease-in|ease-out|ease-in-out|smoothstep]\n       [--initial-rotation DEG] [--rotation-per-frame DEG] [--direction in|out|in-out]\n       [--motion-blur N] [--shutter-angle DEG] [--expmap]\n       [--x-range MIN,MAX --y-range MIN,MAX] [--view x_min,x_max,y_min,y_max [--zoom-to x,y]]\n       [--fit width|height|cover|contain]\n       [--width N] [--height N] [--roi X,Y,W,H [--roi-fill]]\n       [--outputs WxH[@center-crop],...] [--flip-y] [--bit-depth 8|16]\n       [--dither none|ordered|blue-noise] [--export png|exr|png,exr] [--dump-iterations]\n       [--image-format png|jpeg|webp|tiff|bmp] [--jpeg-quality Q] [--webp-lossless]\n       [--alpha none|interior|threshold:V] [--debug-channels iter,time,samples]\n       [--frame-stats] [--measure-dimension] [--no-early-stop] [--early-stop-frames K] [--early-stop-spread S]\n       [--no-video] [--pipe-video] [--preview-every N] [--encoder ffmpeg|internal]\n       [--preview-progressive PATH] [--term-preview] [--term-preview-every N]\n       [--term-protocol kitty|sixel|blocks] [--dashboard ADDR:PORT]\n       [--post gaussian-blur:S,unsharp:A,vignette:V,levels:B-W] [--hud]\n       [--hud-position top-left|top-right|bottom-left|bottom-right] [--hud-size N] [--hud-scale-bar] [--hud-only-video] [--julia-inset size=P%[,corner=CORNER][,iter=N]]\n       [--ray ANGLE]...\n       [--format video|gif|apng] [--gif-colors N] [--gif-delay MS] [--gif-loop N|forever]\n       [--fps N] [--codec x264|x265|vp9|av1|NAME] [--crf N] [--ffmpeg-arg ARG] [--pad-to-even]\n       [--video-sequence normal|boomerang|loop-hold:SECONDS]\n       [--video-out PATH] [--overwrite] [--output-dir PATH] [--run-name NAME] [--resume]\n       [--filename-template TEMPLATE]\n       [--progress-format human|json] [--frame-parallelism N] [--max-memory SIZE]\n       [--threads N] [--background] [--time-budget DURATION]\n       [--shard-index I --shard-count N] [--assemble]\n   or: rustlebrot animate-julia --c-path SPEC --frames N [--c-easing EASING] [--zoom-factor F] [--max-iter N] ... as render\n   or: rustlebrot pan --from x,y [--via x,y]... --to x,y --view-width W --frames N [--easing EASING] [--refresh-band N]\n       [--max-iter N] ... as render\n   or: rustlebrot find-target [--fractal mandelbrot|tricorn] [--center x,y] [--depth D] [--max-iter N] [--seed S]\n       [--contact PATH] [--save-location PATH [--location-name NAME]]\n   or: rustlebrot survey [--fractal mandelbrot|tricorn] [--center x,y] [--radius R] [--grid CxR]\n       [--depth N] [--max-iter N] [--thumbnail N] [--output-dir PATH]\n   or: rustlebrot find-nucleus --near x,y --radius R [--period P]\n       [--save-location PATH [--location-name NAME]]\n   or: rustlebrot orbit --point RE IM [--fractal mandelbrot|tricorn] [--max-iter N] [--bailout R]\n       [--precision f32|f64|dd|perturb|big [--reference x,y]] [--output PATH.csv|PATH.json]\n       [--plot PATH [--width N] [--height N]] [--overwrite]\n   or: rustlebrot explore [--fractal mandelbrot|tricorn] [--bind ADDR] [--port N] [--center x,y]\n       [--x-range MIN,MAX --y-range MIN,MAX] [--max-iter N] [--auto-iter] [--iter-growth K]\n       [--coloring escape|smooth|distance] [--palette NAME] ... [--workers N] [--cache-tiles N]\n       [--cache-dir PATH] [--max-zoom Z]\n       [--window [--width N] [--height N] [--bookmarks PATH]]\n   or: rustlebrot still [--fractal mandelbrot|tricorn] [--precision auto|f32|f64] [--center x,y]\n       [--magnification M] [--preset NAME] [--location PATH [--location-name NAME]]\n       [--x-range MIN,MAX --y-range MIN,MAX] [--view x_min,x_max,y_min,y_max]\n       [--fit width|height|cover|contain] [--width N] [--height N]\n       [--supersample N] [--tile-size N] [--max-iter N] [--coloring escape|smooth|distance] [--palette NAME] ...\n       [--output PATH [--band-height N] [--max-memory SIZE] | --tiles DIR]\n       [--overwrite]\n   or: rustlebrot animate-palette --frames N [--from DUMP] [--center x,y] [--magnification M] ... as still\n       [--output-dir PATH] [--no-video] [--encoder ffmpeg|internal] [--fps N] ... [--overwrite] as render\n   or: rustlebrot animate-iter --frames N --iter-start N --iter-end N [--curve linear|log] [--center x,y]\n       [--magnification M] ... as still [--output-dir PATH] [--no-video] [--encoder ffmpeg|internal] [--fps N] ... [--overwrite] as render\n   or: rustlebrot export-dzi [--out PATH] [--tile-size N] [--overlap N] [--format jpg|png] [--jpeg-quality Q]\n       [--resume] [--center x,y] [--magnification M] [--width N] [--height N] ... [--overwrite] as still\n   or: rustlebrot export-mesh [--out PATH.obj|PATH.stl]... [--z-scale S] [--smooth N] [--plateau top|base]\n       [--solid-base T] [--texture PATH.png] [--center x,y] [--magnification M] [--width N] ... as still\n   or: rustlebrot export-svg [--out PATH.svg] [--levels N [--spacing even|log] | --level V ...] [--tolerance PX]\n       [--stroke-width W|LOW-HIGH] [--stroke-color COLOR] [--set-fill COLOR] [--center x,y] ... as still\n   or: rustlebrot render-batch --input PATH [--max-memory SIZE] [--overwrite]\n   or: rustlebrot recolor [DIR] [--coloring escape|smooth|histogram] [--no-video] [--encoder ffmpeg|internal]\n       [--histogram-clip P] [--transfer linear|sqrt|log|power:G] [--palette NAME] ... [--bit-depth 8|16] [--dither none|ordered|blue-noise] [--fps N] ... [--overwrite] as above\n   or: rustlebrot merge <DIR|manifest.json>... [--output-dir PATH] [--no-video] [--encoder ffmpeg|internal]\n       [--fps N] ... [--overwrite] as above\n   or: rustlebrot bench [--scene full|filament|interior]... [--repeats N] [--threads N] [--work-unit rows|tiles] [--chunk-size N] [--json]\n       [--allow-debug] [--formula EXPR]\n   or: rustlebrot daemon [--socket PATH | --listen ADDR:PORT] [--queue PATH]\n   or: rustlebrot submit <job.json> | --status | --cancel ID [--socket PATH | --connect ADDR:PORT] [--json]\n   or: rustlebrot render-frame --manifest PATH --frame N [--scale K] [--samples N] [--output PATH [--overwrite]]\n   or: rustlebrot validate (--manifest PATH | ... as render) --frame N --backends f64,dd,perturb\n       [--threshold T] [--tolerance F] [--heatmaps DIR] [--overwrite]\n   or: rustlebrot assemble [DIR] [--palette NAME] [--full-decode] [--repair] [--allow-gaps]\n       [--encoder ffmpeg|internal] [--fps N] ... [--overwrite] as above\n   or: rustlebrot verify [DIR] [--palette NAME] [--full-decode] [--repair]\n   or: rustlebrot montage [DIR | --manifest PATH] [--palette NAME] [--every N] [--columns N] [--thumbnail N]\n       [--max-size N] [--output PATH] [--overwrite]\n   or: rustlebrot info <file.png|manifest.json|DIR>\n   or: rustlebrot --list-palettes\n   or: rustlebrot --list-presets\n   or: rustlebrot --help\n   or: rustlebrot <max_iter> <zoom_start> <zoom_end> <zoom_factor> ... as render, deprecated";
+
+/// The flags given before the subcommand, which apply to any of them.
+pub struct Global {
+    pub verbosity: Verbosity,
+    /// The directory the subcommand writes to, or reads the render in for
+    /// `recolor` and `assemble`.
+    pub output_dir: Option<String>,
+}
+
+impl Global {
+    /// `args` of a subcommand with an `--output-dir` of its own, passed
+    /// the global one.
+    pub fn pass_output_dir(&self, command: &str, args: &[String]) -> Result<Vec<String>, String> {
+        let Some(dir) = &self.output_dir else {
+            return Ok(args.to_vec());
+        };
+        if uses_flag(args, &["output-dir"]) {
+            return Err(format!(
+                "--output-dir is given both before and after {}; give it once",
+                command
+            ));
+        }
+        Ok([&["--output-dir".to_string(), dir.clone()], args].concat())
+    }
+
+    /// Fails if an output directory was given to `command`, which doesn't
+    /// write to one.
+    pub fn refuse_output_dir(&self, command: &str) -> Result<(), String> {
+        match self.output_dir {
+            Some(_) => Err(format!("{} doesn't write to an output directory", command)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Parses the global flags at the start of `args`, not including the
+/// program name, and returns them with the arguments after them, starting
+/// with the subcommand.
+pub fn parse_global(args: &[String]) -> Result<(Global, &[String]), String> {
+    let mut global = Global {
+        verbosity: Verbosity::Normal,
+        output_dir: None,
+    };
+    let mut verbosity = None;
+    let mut next = 0;
+    while let Some(arg) = args.get(next) {
+        next += 1;
+        match arg.as_str() {
+            "--quiet" | "-q" | "--verbose" | "-v" => {
+                let asked = match arg.as_str() {
+                    "--quiet" | "-q" => Verbosity::Quiet,
+                    _ => Verbosity::Verbose,
+                };
+                if verbosity.is_some_and(|verbosity| verbosity != asked) {
+                    return Err("--quiet and --verbose can't be used together".to_string());
+                }
+                verbosity = Some(asked);
+            }
+            "--output-dir" => {
+                let dir = args.get(next).filter(|dir| !dir.starts_with("--"));
+                let dir = dir.ok_or_else(|| {
+                    "--output-dir needs the directory to write to, like --output-dir rust_data"
+                        .to_string()
+                })?;
+                global.output_dir = Some(dir.clone());
+                next += 1;
+            }
+            _ => match arg.strip_prefix("--output-dir=") {
+                Some("") => {
+                    return Err("--output-dir needs the directory to write to, like \
+                                --output-dir=rust_data"
+                        .to_string())
+                }
+                Some(dir) => global.output_dir = Some(dir.to_string()),
+                None => {
+                    next -= 1;
+                    break;
+                }
+            },
+        }
+    }
+    global.verbosity = verbosity.unwrap_or(Verbosity::Normal);
+    Ok((global, &args[next..]))
+}
+
+/// Everything the user asked for on the command line.
+pub struct Args {
+    pub max_iter: u32,
+    pub zoom_start: u32,
+    pub zoom_end: u32,
+    pub zoom_factor: f64,
+    /// The length and depth the frames were derived from, as given with
+    /// `--target-magnification` and `--duration`.
+    pub target: Option<ZoomTarget>,
+    pub fractal: FractalKind,
+    /// The polynomial of `--fractal newton`, z³ - 1 unless `--poly` gives
+    /// another.
+    pub newton: Option<Newton>,
+    /// The formula of `--formula`, which the zoom iterates in place of a
+    /// built-in fractal.
+    pub formula: Option<Formula>,
+    /// The path the `c` of a zoom of `--fractal julia` takes over the
+    /// frames, and how it speeds up and slows down along it.
+    pub c_path: Option<CPath>,
+    pub c_easing: Easing,
+    /// The sequence and iterations of `--fractal lyapunov`, which
+    /// `--samples` gives the number of measured ones of.
+    pub lyapunov: Option<Lyapunov>,
+    pub precision: Precision,
+    /// Terms of the series approximation used with perturbation, 0 to
+    /// disable it.
+    pub series_terms: usize,
+    /// Whether to stop iterating orbits once they are found to be periodic.
+    pub periodicity: bool,
+    /// Fill in rectangles with a uniform border instead of iterating them,
+    /// and with `--show-subdivision`, show which.
+    pub subdivision: Subdivision,
+    /// How the frames are cut into units of work for the threads.
+    pub work_units: WorkUnits,
+    /// Take what can be taken of every frame from the one before, with a
+    /// full keyframe every so often.
+    pub incremental: Option<Incremental>,
+    /// Samples along each side of a pixel, whose colors are averaged into
+    /// the pixel's.
+    pub supersample: u32,
+    /// Anti-alias only the pixels that stand out from their neighbors, with
+    /// up to as many samples as `--supersample` gives, in place of it.
+    pub adaptive: Option<Adaptive>,
+    pub coloring: Coloring,
+    /// How the interior is colored by the cycles its orbits settle on, if
+    /// it isn't plain.
+    pub interior_coloring: Option<InteriorColoring>,
+    /// The script frames are colored by in place of a coloring, read from
+    /// the file of `--color-expr`.
+    pub color_expr: Option<ScriptArg>,
+    pub colors: ColorArgs,
+    /// How far each frame moves the distribution histogram coloring spreads
+    /// escape times by, when it is carried from frame to frame.
+    pub stabilize_colors: Option<f64>,
+    pub mode: Mode,
+    /// Points sampled per Buddhabrot frame.
+    pub samples: u64,
+    /// How the points of a Buddhabrot frame are drawn.
+    pub sampler: Sampler,
+    /// Mixed into the seeds of the points drawn for Buddhabrot frames.
+    pub seed: u64,
+    /// Shortest escape time of the orbits plotted in a Buddhabrot.
+    pub min_iter: u32,
+    pub tone: ToneMap,
+    /// Escape time limits of the Nebulabrot channels, by default max_iter
+    /// and a tenth and hundredth of it.
+    pub bands: Option<[u32; 3]>,
+    /// How fast max_iter grows with magnification, or `None` to keep it
+    /// fixed.
+    pub auto_iter: Option<f64>,
+    /// The iteration limit of every frame, in place of `max_iter`.
+    pub iter_schedule: Option<IterSchedule>,
+    /// Print the frame schedule instead of rendering, as JSON events with
+    /// `--progress-format json`.
+    pub dry_run: bool,
+    /// Go ahead without asking once the preflight summary is printed.
+    pub yes: bool,
+    /// The radius past which orbits count as escaped, at least 2.
+    pub bailout: f64,
+    /// The zoom center as decimal strings, in place of the fractal's
+    /// default.
+    pub center: Option<(String, String)>,
+    /// The x and y ranges of the first frame, in place of the center and
+    /// the fractal's default extent.
+    pub ranges: Option<((f64, f64), (f64, f64))>,
+    /// The corners of `--view` the ranges were given as, if they were.
+    pub view: Option<Corners>,
+    /// The point a zoom from the ranges closes in on, which stays where it
+    /// is in the frame, in place of their middle.
+    pub zoom_to: Option<(String, String)>,
+    /// The camera path to fly along, in place of a zoom into one center.
+    pub keyframes: Option<Vec<Keyframe>>,
+    /// The camera in every frame, in place of keyframes or a zoom into one
+    /// center.
+    pub camera_path: Option<FramePath>,
+    /// The path of a pan along the plane at a fixed magnification, in
+    /// place of a zoom.
+    pub pan: Option<Pan>,
+    /// The place of a `--location` file the center, the fractal and the
+    /// rotation, and without the positional arguments the frames and
+    /// max_iter, came from.
+    pub location: Option<LocationArg>,
+    /// The location file the deepest frame is added to after the run.
+    pub save_location: Option<String>,
+    /// The name of the location picked from `--location` and of the one
+    /// saved in `--save-location`.
+    pub location_name: Option<String>,
+    /// The quality of `--quality`, whose defaults the settings the flags
+    /// leave out were taken from.
+    pub quality: Option<&'static Quality>,
+    /// How the zoom speeds up and slows down over the frames.
+    pub easing: Easing,
+    /// Which way the camera goes over the frames, and with the video.
+    pub direction: Direction,
+    /// Average every frame over views taken while the shutter is open,
+    /// instead of showing the one at the frame.
+    pub motion_blur: Option<MotionBlur>,
+    /// Render the exponential map around the center once, in strips, and
+    /// resample every frame from it instead of rendering it.
+    pub expmap: bool,
+    /// The turn of the first frame and how far every frame turns further,
+    /// in degrees counterclockwise about the center.
+    pub initial_rotation: f64,
+    pub rotation_per_frame: f64,
+    /// How the first frame's view is fitted to the frame's shape.
+    pub fit: Fit,
+    /// The frame size in pixels.
+    pub width: u32,
+    pub height: u32,
+    /// The region of every frame computed, in place of all of it.
+    pub roi: Option<Roi>,
+    /// The sizes the colored frames are written at with `--outputs`, the
+    /// first the one they're rendered at, or none to write them as
+    /// rendered.
+    pub outputs: Vec<Output>,
+    /// Draw the imaginary axis pointing down, with the top row at the
+    /// smallest imaginary part, as frames were before it was turned the
+    /// usual way up.
+    pub flip_y: bool,
+    /// The files written for every frame.
+    pub export: Export,
+    /// The format the colored frames are saved in.
+    pub image_format: ImageFormat,
+    /// Which pixels of the frames are transparent.
+    pub alpha: Alpha,
+    /// Write every frame's samples as a `.npy` array with a JSON sidecar.
+    pub dump_iterations: bool,
+    /// Print a line of statistics after every frame, on top of writing
+    /// them to `stats.csv`.
+    pub frame_stats: bool,
+    /// Estimate the box-counting dimension of the boundary in every frame,
+    /// for `stats.csv` and the manifest.
+    pub measure_dimension: bool,
+    /// The diagnostic images written beside every frame.
+    pub debug_channels: DebugChannels,
+    /// End the zoom once this many frames in a row are uniform, unless
+    /// `--no-early-stop` turned it off.
+    pub early_stop: Option<EarlyStop>,
+    /// Stream frames into ffmpeg instead of saving them as PNGs.
+    pub pipe_video: bool,
+    /// With `pipe_video`, still save every Nth frame as a PNG.
+    pub preview_every: Option<u32>,
+    /// Where every frame is previewed as it renders, coarsest first.
+    pub preview_progressive: Option<String>,
+    /// Draw every Nth frame on the terminal once it's saved.
+    pub term_preview: Option<u32>,
+    /// How previews are drawn on the terminal, in place of the best one it
+    /// is found to show.
+    pub term_protocol: Option<Protocol>,
+    /// Where the page showing the run in a browser is served, if anywhere.
+    pub dashboard: Option<SocketAddr>,
+    /// The filters every colored frame goes through before it's saved or
+    /// piped, if any.
+    pub post: Option<Chain>,
+    /// The overlay written on every frame, if any.
+    pub hud: Option<Hud>,
+    /// The Julia set of the center drawn in a corner of every frame, if
+    /// it is.
+    pub julia_inset: Option<JuliaInset>,
+    /// The angles of the external rays drawn on every frame.
+    pub rays: Vec<ExternalAngle>,
+    /// The video encoder asked for, or `None` to use ffmpeg if it's there.
+    /// `--format gif` and `--format apng` ask for the GIF and APNG encoders.
+    pub encoder: Option<EncoderKind>,
+    pub video: VideoOptions,
+    /// Only save the frames, without encoding them into a video.
+    pub no_video: bool,
+    /// The directory everything is written to, with the run name given
+    /// with `--run-name` as a subdirectory.
+    pub output_dir: String,
+    /// Where the files of every frame go in `output_dir`, with the run name
+    /// and timestamp filled in.
+    pub filenames: FilenameTemplate,
+    /// Keep the frames of the run saved already, and only render the rest.
+    pub resume: bool,
+    pub progress_format: ProgressFormat,
+    /// Frames rendered at once, each on a share of the threads.
+    /// `--threads` counts the threads of all of them together, so the two
+    /// don't multiply.
+    pub frame_parallelism: usize,
+    /// Bytes the frames rendered at once may take up between them.
+    pub max_memory: u64,
+    /// Keep rendering frames past the resolution of their precision,
+    /// instead of stopping the zoom there.
+    pub allow_precision_loss: bool,
+    /// Render threads, or `None` for one per CPU, or one fewer with
+    /// `--background`.
+    pub threads: Option<usize>,
+    /// Keep the machine usable: fewer threads at a lower priority, pausing
+    /// between rows.
+    pub background: bool,
+    /// Seconds the whole run is fitted into, by scaling every frame's
+    /// max_iter to what a calibration predicts.
+    pub time_budget: Option<f64>,
+    /// Whether the time budget picks the supersampling too, as it does
+    /// unless `--supersample` or `--adaptive` is given.
+    pub fit_supersample: bool,
+    /// The share of the frames this run renders, when the zoom is split
+    /// between several runs to be put together with `merge`.
+    pub shard: Option<Shard>,
+    /// With `shard`, encode the shard's frames into a video of their own.
+    pub assemble: bool,
+}
+
+impl Args {
+    /// The parameters that change what the frames look like, past the ones
+    /// the manifest has fields of its own for. `merge` checks the shards of
+    /// a zoom agree on them, so each is written out in full.
+    pub fn settings(&self) -> Settings {
+        let colors = &self.colors;
+        let (image_format, jpeg_quality) = match self.image_format {
+            ImageFormat::Jpeg { quality } => ("jpeg", Some(quality)),
+            format => (format.name(), None),
+        };
+        let debug = self.debug_channels;
+        let debug_channels =
+            [("iter", debug.iter), ("time", debug.time), ("samples", debug.samples)];
+        let export = [("png", self.export.png), ("exr", self.export.exr)];
+        let chosen = |names: &[(&str, bool)]| {
+            names.iter().filter(|(_, on)| *on).map(|(name, _)| name.to_string()).collect()
+        };
+        Settings {
+            newton: self.newton.as_ref().map(|newton| newton.coefficients().to_vec()),
+            formula: self.formula.as_ref().map(|formula| formula.source().to_string()),
+            formula_log_base: self.formula.as_ref().map(|formula| formula.log_base),
+            c_path: self.c_path.as_ref().map(CPathRecord::from),
+            c_easing: self.c_easing.name().to_string(),
+            lyapunov: self.lyapunov.as_ref().map(LyapunovRecord::from),
+            precision: self.precision.name().to_string(),
+            allow_precision_loss: self.allow_precision_loss,
+            series_terms: self.series_terms,
+            periodicity: self.periodicity,
+            subdivision: match self.subdivision {
+                Subdivision::Off => "off",
+                Subdivision::On => "on",
+                Subdivision::Show => "show",
+            }
+            .to_string(),
+            supersample: self.supersample,
+            adaptive: self.adaptive.map(AdaptiveRecord::from),
+            coloring: self.coloring.into(),
+            interior_coloring: self.interior_coloring.map(|coloring| coloring.name().to_string()),
+            color_expr: self.color_expr.as_ref().map(|arg| arg.script.source().to_string()),
+            histogram_clip: colors.histogram_clip,
+            transfer: colors.transfer.into(),
+            stabilize_colors: self.stabilize_colors,
+            phase: colors.phase.into(),
+            lighting: colors.lighting.map(LightingRecord::from),
+            contours: colors.contours.map(ContoursRecord::from),
+            silhouette: colors.silhouette.map(LineRecord::from),
+            line_art: colors.line_art().map(LineArtRecord::from),
+            palette_sources: colors.palettes.iter().map(PaletteSource::record).collect(),
+            interior: rgb(colors.interior),
+            palette_cycles: colors.palette_cycles,
+            palette_offset: colors.palette_offset,
+            palette_reverse: colors.palette_reverse,
+            palette_drift: colors.palette_drift,
+            adjust: colors.adjust.into(),
+            bit_depth: match colors.bit_depth {
+                BitDepth::Eight => 8,
+                BitDepth::Sixteen => 16,
+            },
+            dither: colors.dither.name().to_string(),
+            blending: match colors.blending {
+                Blending::Linear => "linear",
+                Blending::Srgb => "srgb",
+            }
+            .to_string(),
+            palette_resolution: colors.palette_resolution,
+            samples: self.samples,
+            sampler: self.sampler.into(),
+            seed: self.seed,
+            min_iter: self.min_iter,
+            tone: self.tone.name().to_string(),
+            bands: self.bands,
+            auto_iter: self.auto_iter,
+            iter_schedule: self.iter_schedule.as_ref().map(IterScheduleRecord::from),
+            bailout: self.bailout,
+            ranges: self.ranges.map(|(x_range, y_range)| RangesRecord { x_range, y_range }),
+            zoom_to: self.zoom_to.clone(),
+            keyframes: self.keyframes.as_ref().map(|keyframes| {
+                keyframes.iter().map(KeyframeRecord::from).collect()
+            }),
+            camera_path: self.camera_path.as_ref().map(|path| format!("{:08x}", path.crc32)),
+            pan: self.pan.as_ref().map(PanRecord::from),
+            easing: self.easing.name().to_string(),
+            motion_blur: self.motion_blur.map(|blur| Shutter {
+                sub_frames: blur.sub_frames,
+                angle: blur.shutter_angle,
+            }),
+            expmap: self.expmap,
+            initial_rotation: self.initial_rotation,
+            rotation_per_frame: self.rotation_per_frame,
+            fit: self.fit.name().to_string(),
+            roi: self.roi.map(RoiRecord::from),
+            outputs: self.outputs.iter().map(Output::name).collect(),
+            flip_y: self.flip_y,
+            export: chosen(&export),
+            image_format: image_format.to_string(),
+            jpeg_quality,
+            alpha: self.alpha.into(),
+            dump_iterations: self.dump_iterations,
+            debug_channels: chosen(&debug_channels),
+            post: self.post.as_ref().map(Chain::to_string),
+            filename_template: self.filenames.to_string(),
+        }
+    }
+}
+
+/// The location a zoom goes to, as given with `--location`.
+pub struct LocationArg {
+    pub path: String,
+    pub location: Location,
+    /// The keys of a Kalles Fraktaler file that were ignored.
+    pub ignored: Vec<String>,
+}
+
+/// The coloring script of a zoom, as given with `--color-expr`.
+pub struct ScriptArg {
+    pub path: String,
+    pub script: Script,
+}
+
+/// The options that only affect how frames are colored, which rendering
+/// and `recolor` share.
+pub struct ColorArgs {
+    /// Percentage of pixels clamped at either end by histogram coloring.
+    pub histogram_clip: f64,
+    /// How escape times are spread over the gradient, linearly unless
+    /// `--transfer` says otherwise.
+    pub transfer: Transfer,
+    /// How phase coloring blends in the angle's color.
+    pub phase: Phase,
+    /// The light slopes are shaded with, if any.
+    pub lighting: Option<Lighting>,
+    /// The contour lines drawn between bands of values, if any.
+    pub contours: Option<Contours>,
+    /// The line drawn around the set, if any.
+    pub silhouette: Option<Line>,
+    /// Whether frames are drawn as line art in place of the palette, as
+    /// `--style lineart` asks.
+    pub lineart: bool,
+    /// How the line art is drawn, as the `--line-*` flags set it.
+    pub lines: LineArt,
+    /// The last of the `--line-*` flags given, which need `--style
+    /// lineart`.
+    line_flag: Option<String>,
+    /// The palettes asked for, in order, or none for the default. With
+    /// several, every frame is colored with each of them.
+    pub palettes: Vec<PaletteSource>,
+    /// The color of points in the set, black by default.
+    pub interior: (u8, u8, u8),
+    /// How many times the gradient repeats over the palette range.
+    pub palette_cycles: f64,
+    /// Shift of the gradient, in cycles.
+    pub palette_offset: f64,
+    pub palette_reverse: bool,
+    /// Cycles the palette phase advances by every frame.
+    pub palette_drift: f64,
+    /// Inversion, hue, saturation and gamma adjustments of the palette.
+    /// Inversion is on by default, which is how frames have always looked.
+    pub adjust: Adjust,
+    /// Bits per channel of the saved frames.
+    pub bit_depth: BitDepth,
+    /// How channels are rounded to `bit_depth`.
+    pub dither: Dither,
+    /// The light gradients and samples are mixed in, linear unless
+    /// `--legacy-gamma` is given.
+    pub blending: Blending,
+    /// Whether the colors of `.map` palettes are blended into each other,
+    /// as `--map-interpolate` asks, instead of stepping from one to the
+    /// next.
+    pub map_interpolate: bool,
+    /// Steps every gradient is sampled into for its lookup table.
+    pub palette_resolution: usize,
+}
+
+impl Default for ColorArgs {
+    fn default() -> Self {
+        ColorArgs {
+            histogram_clip: 0.0,
+            transfer: Transfer::Linear,
+            phase: Phase::default(),
+            lighting: None,
+            contours: None,
+            silhouette: None,
+            lineart: false,
+            lines: LineArt::default(),
+            line_flag: None,
+            palettes: Vec::new(),
+            interior: (0, 0, 0),
+            palette_cycles: 4.0,
+            palette_offset: 0.0,
+            palette_reverse: false,
+            palette_drift: 0.0,
+            adjust: Adjust {
+                invert: true,
+                ..Adjust::default()
+            },
+            bit_depth: BitDepth::Eight,
+            dither: Dither::None,
+            blending: Blending::Linear,
+            map_interpolate: false,
+            palette_resolution: palette::TABLE_SIZE,
+        }
+    }
+}
+
+/// A palette given with `--palette`, `--gradient`, `--gradient-file`,
+/// `--palette-image` or `--palette-map`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PaletteSource {
+    Named(Palette),
+    /// Color stops, named `custom` when given with `--gradient`, or after
+    /// the file they were read from. A palette image is read into a stop
+    /// for every pixel along it.
+    Stops { name: String, stops: Vec<Stop> },
+    /// The entries of a Fractint `.map` file, named after it, which become
+    /// stops once `--map-interpolate` says how.
+    Map { name: String, colors: Vec<Color> },
+}
+
+impl PaletteSource {
+    /// The name the palette goes by in the frame metadata, and with
+    /// several palettes the directory of its frames.
+    pub fn name(&self) -> &str {
+        match self {
+            PaletteSource::Named(palette) => palette.name(),
+            PaletteSource::Stops { name, .. } | PaletteSource::Map { name, .. } => name,
+        }
+    }
+
+    /// The palette as the manifest records it.
+    pub fn record(&self) -> PaletteRecord {
+        let color = |color: &Color| {
+            let [r, g, b, _] = color.to_rgba8();
+            [r, g, b]
+        };
+        let stops = match self {
+            PaletteSource::Stops { stops, .. } => stops
+                .iter()
+                .map(|stop| StopRecord { position: stop.position, color: color(&stop.color) })
+                .collect(),
+            _ => Vec::new(),
+        };
+        let colors = match self {
+            PaletteSource::Map { colors, .. } => colors.iter().map(color).collect(),
+            _ => Vec::new(),
+        };
+        PaletteRecord { name: self.name().to_string(), stops, colors }
+    }
+}
+
+/// The settings of a render with neither flags nor `--quality` for them.
+const BUILT_IN: quality::Settings = quality::Settings {
+    width: Some(1200),
+    height: Some(1200),
+    supersample: None,
+    adaptive: None,
+    jpeg_quality: Some(ImageFormat::JPEG_QUALITY),
+    bit_depth: Some(BitDepth::Eight),
+};
+
+/// The palette of frames no `--palette` is given for.
+const DEFAULT_PALETTES: &[PaletteSource] = &[PaletteSource::Named(Palette::Sinebow)];
+
+impl ColorArgs {
+    /// The palettes frames are colored with, the default one unless any
+    /// were given.
+    pub fn palettes(&self) -> &[PaletteSource] {
+        match self.palettes.is_empty() {
+            true => DEFAULT_PALETTES,
+            false => &self.palettes,
+        }
+    }
+
+    /// Fails unless at most one palette was given, for the subcommands that
+    /// color with one.
+    fn single_palette(&self, command: &str) -> Result<(), String> {
+        match self.palettes.len() {
+            0 | 1 => Ok(()),
+            _ => Err(format!(
+                "{} colors with one palette, so --palette can only be given once",
+                command
+            )),
+        }
+    }
+
+    /// The line art frames are drawn as, with `--style lineart`.
+    pub fn line_art(&self) -> Option<LineArt> {
+        self.lineart.then_some(self.lines)
+    }
+
+    /// Fails if `--lighting`, `--contours`, `--silhouette` or `--style
+    /// lineart` was given, for the subcommands that color in tiles, whose
+    /// samples at the seams don't have the neighbors across them to take
+    /// their slope or lines from.
+    fn whole_frames(&self, command: &str) -> Result<(), String> {
+        let flag = match (self.lighting, self.contours, self.silhouette) {
+            (Some(_), _, _) => "lighting",
+            (_, Some(_), _) => "contours",
+            (_, _, Some(_)) => "silhouette",
+            _ if self.lineart => "style lineart",
+            (None, None, None) => return Ok(()),
+        };
+        Err(format!("{} colors in tiles, which --{} would leave seams between", command, flag))
+    }
+
+    /// Fails if `--map-interpolate` was given without a `.map` palette to
+    /// interpolate.
+    fn check_map_interpolate(&self) -> Result<(), String> {
+        let maps = self.palettes.iter().any(|palette| matches!(palette, PaletteSource::Map { .. }));
+        match self.map_interpolate && !maps {
+            true => Err("--map-interpolate only applies to the palettes of --palette-map".to_string()),
+            false => Ok(()),
+        }
+    }
+
+    /// Fails if a `--line-*` flag was given without `--style lineart`, or
+    /// line art with the shading and lines it would be drawn in place of.
+    fn check_line_art(&self) -> Result<(), String> {
+        if let (false, Some(flag)) = (self.lineart, &self.line_flag) {
+            return Err(format!("--{} only applies to --style lineart", flag));
+        }
+        let flag = match (self.lighting, self.contours, self.silhouette) {
+            _ if !self.lineart => return Ok(()),
+            (Some(_), _, _) => "lighting",
+            (_, Some(_), _) => "contours",
+            (_, _, Some(_)) => "silhouette",
+            (None, None, None) => return Ok(()),
+        };
+        Err(format!(
+            "--style lineart draws lines in place of the colors, so it can't be used with --{}; \
+             --line-silhouette draws the outline of the set as part of it",
+            flag
+        ))
+    }
+
+    /// Fails if `--transfer` was given for a `coloring` whose values aren't
+    /// escape times spread in proportion.
+    pub fn check_transfer(&self, coloring: Coloring) -> Result<(), String> {
+        match (self.transfer, coloring) {
+            (Transfer::Linear, _) => Ok(()),
+            (_, Coloring::Histogram) => Err("--transfer can't be used with --coloring histogram, \
+                                             which spreads escape times by their distribution \
+                                             instead"
+                .to_string()),
+            (_, coloring) if !coloring.takes_transfer() => Err(format!(
+                "--transfer spreads escape times, which --coloring {} doesn't color by",
+                coloring.name()
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Adds `palette`, unless one of the same name was given already, whose
+    /// frames would go to the same place.
+    fn add_palette(&mut self, palette: PaletteSource) -> Result<(), String> {
+        if self.palettes.iter().any(|given| given.name() == palette.name()) {
+            return Err(format!("the palette '{}' is given twice", palette.name()));
+        }
+        self.palettes.push(palette);
+        Ok(())
+    }
+
+    /// Handles `--name` if it is one of the color options, returning
+    /// whether it was.
+    fn flag(
+        &mut self,
+        name: &str,
+        value: &mut dyn FnMut() -> Result<String, String>,
+    ) -> Result<bool, String> {
+        match name {
+            "histogram-clip" => {
+                self.histogram_clip = value()?
+                    .parse()
+                    .map_err(|_| "histogram-clip should be a float".to_string())?;
+                if !(0.0..50.0).contains(&self.histogram_clip) {
+                    return Err(format!(
+                        "histogram-clip should be a percentage from 0 up to 50, got {}",
+                        self.histogram_clip
+                    ));
+                }
+            }
+            "transfer" => self.transfer = Transfer::from_spec(&value()?)?,
+            "phase-weight" => {
+                self.phase.weight = value()?
+                    .parse()
+                    .map_err(|_| "phase-weight should be a float".to_string())?;
+                if !(0.0..=1.0).contains(&self.phase.weight) {
+                    return Err(format!(
+                        "phase-weight should be from 0 to 1, got {}",
+                        self.phase.weight
+                    ));
+                }
+            }
+            "phase-turns" => {
+                self.phase.turns = value()?
+                    .parse()
+                    .map_err(|_| "phase-turns should be a float".to_string())?;
+                if !(self.phase.turns > 0.0 && self.phase.turns.is_finite()) {
+                    return Err(format!(
+                        "phase-turns should be positive, got {}",
+                        self.phase.turns
+                    ));
+                }
+            }
+            "lighting" => self.lighting = Some(Lighting::from_spec(&value()?)?),
+            "contours" => self.contours = Some(Contours::from_spec(&value()?)?),
+            "silhouette" => self.silhouette = Some(Line::silhouette_from_spec(&value()?)?),
+            "style" => {
+                self.lineart = match value()?.as_str() {
+                    "palette" => false,
+                    "lineart" => true,
+                    other => {
+                        return Err(format!("style should be palette or lineart, got '{}'", other))
+                    }
+                };
+            }
+            "line-threshold" => {
+                self.lines.threshold = value()?
+                    .parse()
+                    .ok()
+                    .filter(|threshold: &f64| *threshold > 0.0 && threshold.is_finite())
+                    .ok_or_else(|| "line-threshold should be a positive float".to_string())?;
+            }
+            "line-weight" => {
+                self.lines.weight = value()?
+                    .parse()
+                    .map_err(|_| "line-weight should be a whole number of pixels".to_string())?;
+            }
+            "line-silhouette" => self.lines.silhouette = true,
+            "line-interior" => self.lines.interior = true,
+            "line-thin" => self.lines.thin = true,
+            "line-paper" => {
+                let value = value()?;
+                self.lines.paper = Paper::from_name(&value).ok_or_else(|| {
+                    format!("line-paper should be white or transparent, got '{}'", value)
+                })?;
+            }
+            "palette" => {
+                let value = value()?;
+                // Anything that isn't the name of a palette is taken for a
+                // gradient file, or a palette map by its extension, if there
+                // is one.
+                let is_map = Path::new(&value).extension().is_some_and(|ext| ext == "map");
+                let palette = match Palette::from_name(&value) {
+                    Some(palette) => PaletteSource::Named(palette),
+                    None if is_map && Path::new(&value).is_file() => read_palette_map(&value)?,
+                    None if Path::new(&value).is_file() => read_gradient_file(&value)?,
+                    None => {
+                        return Err(format!(
+                            "unknown palette '{}', and no gradient file of that name; see \
+                             --list-palettes",
+                            value
+                        ))
+                    }
+                };
+                self.add_palette(palette)?;
+            }
+            "gradient" => self.add_palette(PaletteSource::Stops {
+                name: "custom".to_string(),
+                stops: palette::parse_stops(&value()?)?,
+            })?,
+            "gradient-file" => self.add_palette(read_gradient_file(&value()?)?)?,
+            "palette-image" => self.add_palette(read_palette_image(&value()?)?)?,
+            "palette-map" => self.add_palette(read_palette_map(&value()?)?)?,
+            "map-interpolate" => self.map_interpolate = true,
+            "palette-resolution" => {
+                let (fewest, most) = palette::TABLE_SIZES;
+                self.palette_resolution = value()?
+                    .parse()
+                    .ok()
+                    .filter(|entries| (fewest..=most).contains(entries))
+                    .ok_or_else(|| {
+                        format!("palette-resolution should be {} to {} entries", fewest, most)
+                    })?;
+            }
+            "interior-color" => self.interior = palette::parse_color(&value()?)?,
+            "palette-cycles" => {
+                self.palette_cycles = value()?
+                    .parse()
+                    .map_err(|_| "palette-cycles should be a float".to_string())?;
+                if !(self.palette_cycles > 0.0 && self.palette_cycles.is_finite()) {
+                    return Err(format!(
+                        "palette-cycles should be positive, got {}",
+                        self.palette_cycles
+                    ));
+                }
+            }
+            "palette-offset" => {
+                self.palette_offset = value()?
+                    .parse()
+                    .map_err(|_| "palette-offset should be a float".to_string())?;
+            }
+            "palette-reverse" => self.palette_reverse = true,
+            "legacy-gamma" => self.blending = Blending::Srgb,
+            "palette-drift" => {
+                self.palette_drift = value()?
+                    .parse()
+                    .map_err(|_| "palette-drift should be a float".to_string())?;
+            }
+            "invert" => {
+                self.adjust.invert = match value()?.as_str() {
+                    "on" => true,
+                    "off" => false,
+                    other => return Err(format!("invert should be on or off, got '{}'", other)),
+                };
+            }
+            "hue-shift" => {
+                self.adjust.hue_shift = value()?
+                    .parse()
+                    .map_err(|_| "hue-shift should be a float".to_string())?;
+            }
+            "saturation" => {
+                let saturation: f64 = value()?
+                    .parse()
+                    .map_err(|_| "saturation should be a float".to_string())?;
+                if saturation.is_nan() || saturation < 0.0 {
+                    return Err(format!("saturation should not be negative, got {}", saturation));
+                }
+                self.adjust.saturation = saturation;
+            }
+            "gamma" => {
+                self.adjust.gamma = value()?
+                    .parse()
+                    .map_err(|_| "gamma should be a float".to_string())?;
+                if !(self.adjust.gamma > 0.0 && self.adjust.gamma.is_finite()) {
+                    return Err(format!("gamma should be positive, got {}", self.adjust.gamma));
+                }
+            }
+            "bit-depth" => {
+                let value = value()?;
+                self.bit_depth = BitDepth::from_name(&value)
+                    .ok_or_else(|| format!("bit-depth should be 8 or 16, got '{}'", value))?;
+            }
+            "dither" => {
+                let value = value()?;
+                self.dither = Dither::from_name(&value).ok_or_else(|| {
+                    format!("dither should be none, ordered or blue-noise, got '{}'", value)
+                })?;
+            }
+            _ => return Ok(false),
+        }
+        if name.starts_with("line-") {
+            self.line_flag = Some(name.to_string());
+        }
+        Ok(true)
+    }
+}
+
+/// The options of the `recolor` subcommand.
+pub struct RecolorArgs {
+    /// The directory the frames were dumped to with `--dump-iterations`.
+    pub dir: String,
+    /// The coloring to switch to, if the dumped values allow it.
+    pub coloring: Option<Coloring>,
+    pub colors: ColorArgs,
+    pub encoder: Option<EncoderKind>,
+    pub video: VideoOptions,
+    pub no_video: bool,
+}
+
+/// The options of the `assemble` subcommand.
+pub struct AssembleArgs {
+    /// The output directory of the render whose frames are encoded.
+    pub dir: String,
+    /// Which palette's frames to encode, of a render with several.
+    pub palette: Option<String>,
+    pub encoder: Option<EncoderKind>,
+    pub video: VideoOptions,
+    pub check: FrameCheck,
+    /// Whether the frames found missing or corrupt are left out of the
+    /// video, rather than keeping it from being encoded.
+    pub allow_gaps: bool,
+}
+
+/// How `verify` and `assemble` check the frames of a run before they're
+/// relied on.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FrameCheck {
+    /// Whether every frame is decoded in full, rather than checked by its
+    /// header and the checksums of its chunks.
+    pub full_decode: bool,
+    /// Whether the frames found missing or corrupt are rendered again, as
+    /// `render-frame` would.
+    pub repair: bool,
+}
+
+impl FrameCheck {
+    /// Sets the flag `name` of the check, returning whether it is one.
+    fn flag(&mut self, name: &str) -> bool {
+        match name {
+            "full-decode" => self.full_decode = true,
+            "repair" => self.repair = true,
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// The options of the `verify` subcommand.
+pub struct VerifyArgs {
+    /// The output directory of the render whose frames are checked.
+    pub dir: String,
+    /// Which palette's frames to check, of a render with several, or all
+    /// of them.
+    pub palette: Option<String>,
+    pub check: FrameCheck,
+}
+
+/// The options of the `montage` subcommand.
+pub struct MontageArgs {
+    /// The directory of the frames, the output directory of a render.
+    pub dir: String,
+    /// The manifest of the run, which the frames are found by unless the
+    /// directory has none.
+    pub manifest: Option<String>,
+    /// Which palette's frames to show, of a render with several.
+    pub palette: Option<String>,
+    /// Every how many frames one is shown, or as many as keep the montage
+    /// to `montage::TILES` thumbnails.
+    pub every: Option<u32>,
+    /// Thumbnails across a row.
+    pub columns: u32,
+    /// Width of every thumbnail at most, in pixels.
+    pub thumbnail: u32,
+    /// Pixels across and down the montage at most, which the thumbnails
+    /// shrink to keep to.
+    pub max_size: u32,
+    /// Where the montage is saved, `montage.png` in the directory unless
+    /// given.
+    pub output: Option<String>,
+    pub overwrite: bool,
+}
+
+/// The options of the `render-frame` subcommand.
+pub struct RenderFrameArgs {
+    /// The manifest of the run the frame is of.
+    pub manifest: String,
+    pub frame: u32,
+    /// How many times the width and height of the run the frame is
+    /// rendered at.
+    pub scale: u32,
+    /// Samples along each side of a pixel, in place of the run's.
+    pub samples: Option<u32>,
+    /// Where the frame is written, over the run's own file unless given.
+    pub output: Option<String>,
+    pub overwrite: bool,
+}
+
+/// Where `validate` takes the frame it compares from.
+pub enum FrameSource {
+    /// The manifest of a run, which records the arguments it was rendered
+    /// with.
+    Manifest(String),
+    /// The arguments of a render, as `render` takes them.
+    Render(Vec<String>),
+}
+
+/// The options of the `validate` subcommand.
+pub struct ValidateArgs {
+    pub source: FrameSource,
+    pub frame: u32,
+    /// The backends the frame is rendered in, at least two, in the order
+    /// given.
+    pub backends: Vec<Precision>,
+    /// See `difference::DEFAULT_THRESHOLD`.
+    pub threshold: f64,
+    /// See `difference::DEFAULT_TOLERANCE`.
+    pub tolerance: f64,
+    /// The directory the heatmaps of the differences are written to.
+    pub heatmaps: String,
+    pub overwrite: bool,
+}
+
+/// The options of the `merge` subcommand.
+pub struct MergeArgs {
+    /// The output directories of the shards, or their manifests.
+    pub shards: Vec<String>,
+    /// The directory the frames are put together in.
+    pub output_dir: String,
+    pub encoder: Option<EncoderKind>,
+    pub video: VideoOptions,
+    pub no_video: bool,
+}
+
+/// The options of the `find-target` subcommand.
+#[cfg(feature = "bigfloat")]
+pub struct TargetArgs {
+    pub fractal: FractalKind,
+    /// Center of the region to start from, the origin by default.
+    pub center: Option<(String, String)>,
+    /// Magnification to descend to.
+    pub depth: f64,
+    /// Iteration budget of the first probe grid.
+    pub max_iter: u32,
+    pub seed: u64,
+    /// Where to save the image of every probe grid evaluated.
+    pub contact: String,
+    /// The location file the target is added to, under `location_name`.
+    pub save_location: Option<String>,
+    pub location_name: Option<String>,
+}
+
+/// The options of the `survey` subcommand.
+#[cfg(feature = "bigfloat")]
+pub struct SurveyArgs {
+    pub fractal: FractalKind,
+    /// Center of the region surveyed, the origin by default.
+    pub center: Option<(String, String)>,
+    /// Half the width of the region, the fractal's default extent by
+    /// default.
+    pub radius: Option<f64>,
+    /// Cells across and down every level.
+    pub grid: (usize, usize),
+    /// Levels surveyed, each of the best cell of the one before.
+    pub depth: usize,
+    /// Iteration budget of the first level.
+    pub max_iter: u32,
+    /// Width of the thumbnail of every cell, in pixels.
+    pub thumbnail: usize,
+    /// The directory the contact sheets and `survey.json` are written to.
+    pub output_dir: String,
+}
+
+/// The options of the `find-nucleus` subcommand.
+#[cfg(feature = "bigfloat")]
+pub struct NucleusArgs {
+    /// Where to start looking for the nucleus, as written.
+    pub near: (String, String),
+    /// Radius of the disk around `near` the nucleus is looked for in.
+    pub radius: f64,
+    /// The period of the nucleus, found from the disk if not given.
+    pub period: Option<u32>,
+    /// The location file the nucleus is saved to, under `location_name`.
+    pub save_location: Option<String>,
+    pub location_name: Option<String>,
+}
+
+/// The options of the `orbit` subcommand.
+pub struct OrbitArgs {
+    pub fractal: FractalKind,
+    /// The point traced, as written.
+    pub point: (String, String),
+    /// The precision the orbit is iterated in, which is never auto.
+    pub precision: Precision,
+    /// The reference orbit of perturbation, the point itself by default.
+    pub reference: Option<(String, String)>,
+    pub max_iter: u32,
+    pub bailout: f64,
+    /// The file the orbit is written to, and how, instead of printing it.
+    pub output: Option<(String, orbit::Format)>,
+    /// The image the orbit is plotted in, and its size.
+    pub plot: Option<String>,
+    pub width: u32,
+    pub height: u32,
+    pub overwrite: bool,
+}
+
+/// The options of the `serve` subcommand.
+#[cfg(feature = "serve")]
+pub struct ServeArgs {
+    pub fractal: FractalKind,
+    /// The address and port the server listens on.
+    pub bind: String,
+    pub port: u16,
+    /// Center of the view of the tile at zoom level 0, the origin by
+    /// default, or the view itself.
+    pub center: Option<(String, String)>,
+    pub ranges: Option<((f64, f64), (f64, f64))>,
+    /// The iteration limit at zoom level 0, and how fast it grows with
+    /// magnification, if it does.
+    pub max_iter: u32,
+    pub auto_iter: Option<f64>,
+    pub coloring: Coloring,
+    pub colors: ColorArgs,
+    /// Tiles rendered at once.
+    pub workers: usize,
+    /// Tiles kept in memory.
+    pub cache_tiles: usize,
+    /// Where tiles are also kept between runs.
+    pub cache_dir: Option<String>,
+    /// The deepest zoom level served, or `None` for as deep as f64 goes at
+    /// the center.
+    pub max_zoom: Option<u32>,
+    /// The size of the window the view is shown in instead, with
+    /// `--window`.
+    #[cfg(feature = "window")]
+    pub window: Option<(u32, u32)>,
+    /// The location file views saved from the window are added to.
+    #[cfg(feature = "window")]
+    pub bookmarks: Option<String>,
+}
+
+/// The options of the `still` subcommand.
+pub struct StillArgs {
+    pub fractal: FractalKind,
+    /// Auto, f32 or f64. Tiles are planned in one precision for the whole
+    /// image.
+    pub precision: Precision,
+    /// Center of the view, the fractal's default by default, or the view
+    /// itself.
+    pub center: Option<(String, String)>,
+    pub ranges: Option<((f64, f64), (f64, f64))>,
+    /// The corners of `--view` the ranges were given as, if they were.
+    pub view: Option<Corners>,
+    /// How far in the view about the center is from that of a zoom's first
+    /// frame.
+    pub magnification: f64,
+    /// How far the view is turned, in degrees counterclockwise, as a
+    /// location may give it.
+    pub rotation: f64,
+    pub fit: Fit,
+    /// The image size in pixels.
+    pub width: u32,
+    pub height: u32,
+    /// Samples along each side of a pixel.
+    pub supersample: u32,
+    /// Pixels along each side of the tiles the image is rendered in.
+    pub tile_size: u32,
+    /// Rows of pixels in each band of tiles streamed into one PNG, the
+    /// tile size unless `--band-height` says otherwise.
+    pub band_height: u32,
+    /// The most a band of an `--output` PNG may take, checked before
+    /// rendering.
+    pub max_memory: u64,
+    pub max_iter: u32,
+    pub coloring: Coloring,
+    pub colors: ColorArgs,
+    pub output: StillOutput,
+    /// Replace the files of an earlier still.
+    pub overwrite: bool,
+}
+
+/// Where a still is written.
+pub enum StillOutput {
+    /// One PNG at the path, which the rows of the tiles are streamed into.
+    Png(String),
+    /// A PNG of every tile in the directory, with a manifest for stitching
+    /// them.
+    Tiles(String),
+}
+
+/// The options of the `animate-palette` subcommand.
+pub struct AnimatePaletteArgs {
+    /// The view, its size and its colors, as `still` takes them.
+    pub view: StillArgs,
+    /// Frames of the loop, over which the palette cycles round once.
+    pub frames: u32,
+    /// The `.npy` or `.json` of a frame dumped with `--dump-iterations`,
+    /// whose escape times are colored in place of rendering the view.
+    pub from: Option<String>,
+    /// The `--coloring` given, which a dumped frame is colored as in place
+    /// of the one it was dumped with.
+    pub coloring: Option<Coloring>,
+    pub output_dir: String,
+    pub encoder: Option<EncoderKind>,
+    pub video: VideoOptions,
+    pub no_video: bool,
+}
+
+/// The options of the `animate-iter` subcommand.
+pub struct AnimateIterArgs {
+    /// The view, its size and its colors, as `still` takes them. Its
+    /// `max_iter` is the limit of the last frame, which the palette is
+    /// spread over in every frame.
+    pub view: StillArgs,
+    pub frames: u32,
+    /// The limit of the first frame.
+    pub iter_start: u32,
+    pub curve: IterCurve,
+    pub output_dir: String,
+    pub encoder: Option<EncoderKind>,
+    pub video: VideoOptions,
+    pub no_video: bool,
+}
+
+/// The options of the `export-dzi` subcommand.
+pub struct DziArgs {
+    /// The view, its size and its colors, as `still` takes them.
+    pub view: StillArgs,
+    /// The descriptor, next to which the tiles go in a directory named
+    /// after it.
+    pub out: String,
+    /// Pixels along each side of a tile, not counting the overlap.
+    pub tile_size: u32,
+    /// Pixels every tile shares with each of its neighbors.
+    pub overlap: u32,
+    /// PNG, or JPEG at a quality.
+    pub format: ImageFormat,
+    /// Carry on building the pyramid at `out`, keeping the tiles it has.
+    pub resume: bool,
+}
+
+/// The options of the `export-mesh` subcommand.
+pub struct MeshArgs {
+    /// The view, its size and its colors, as `still` takes them, at 512 by
+    /// 512 unless given.
+    pub view: StillArgs,
+    /// The files the mesh is written to, in the format of their extension.
+    pub out: Vec<(String, mesh::Format)>,
+    /// The height of the highest point above the lowest, with the longer
+    /// side of the mesh 1 long.
+    pub z_scale: f64,
+    /// Passes of a 3x3 blur over the heights.
+    pub smooth: u32,
+    pub plateau: Plateau,
+    /// How thick the solid under the surface is, if it's closed into one.
+    pub solid_base: Option<f64>,
+    /// A PNG of the coloring, which the OBJ maps onto the mesh in place of
+    /// coloring its vertices.
+    pub texture: Option<String>,
+}
+
+/// The options of the `export-svg` subcommand.
+pub struct SvgArgs {
+    /// The view, its size and its colors, as `still` takes them, in smooth
+    /// coloring unless given.
+    pub view: StillArgs,
+    /// The SVG file the isolines are written to.
+    pub out: String,
+    /// The escape times the isolines are traced at.
+    pub levels: Levels,
+    /// How far a simplified line may stray from the one traced, in pixels.
+    pub tolerance: f64,
+    /// The width of the lines at the lowest level and at the highest, in
+    /// pixels, and in between as far as the level is between them.
+    pub stroke_width: (f64, f64),
+    /// The color of every line, if not the palette's at its level.
+    pub stroke_color: Option<(u8, u8, u8)>,
+    /// The color the set is filled with under the lines, if it's drawn.
+    pub set_fill: Option<(u8, u8, u8)>,
+}
+
+/// The levels of `export-svg`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Levels {
+    /// So many spread over the escape times of the view.
+    Count(usize, Spacing),
+    /// As given with `--level`.
+    Given(Vec<f64>),
+}
+
+/// The options of the `render-batch` subcommand.
+pub struct BatchArgs {
+    /// The batch file of the stills.
+    pub input: String,
+    /// The most the bands of the stills rendered at once may take
+    /// together.
+    pub max_memory: u64,
+    pub overwrite: bool,
+}
+
+/// The options of the `daemon` subcommand.
+#[cfg(feature = "daemon")]
+pub struct DaemonArgs {
+    pub endpoint: Endpoint,
+    /// The file the jobs are kept in.
+    pub queue: String,
+}
+
+/// The options of the `submit` subcommand, the client of `daemon`.
+#[cfg(feature = "daemon")]
+pub struct SubmitArgs {
+    pub endpoint: Endpoint,
+    pub action: SubmitAction,
+    /// Print the daemon's answer as the JSON it sent.
+    pub json: bool,
+}
+
+/// What `submit` asks of the daemon.
+#[cfg(feature = "daemon")]
+pub enum SubmitAction {
+    /// Queue the job of the file at the path.
+    Job(String),
+    Status,
+    Cancel(u64),
+}
+
+/// The options of the `bench` subcommand.
+pub struct BenchArgs {
+    /// The scenes to render by name, all of them when empty.
+    pub scenes: Vec<&'static str>,
+    /// Renders of each scene to take the median of.
+    pub repeats: u32,
+    /// Render threads, or `None` for one per CPU.
+    pub threads: Option<usize>,
+    /// How the scenes are cut into units of work for the threads.
+    pub work_units: WorkUnits,
+    /// Print the report as JSON instead of a table.
+    pub json: bool,
+    /// Run in a debug build anyway, whose numbers are only good for
+    /// checking that the benchmark runs.
+    pub allow_debug: bool,
+    /// A formula to render every scene with as well, to measure what it
+    /// costs next to the built-in kernel.
+    pub formula: Option<Formula>,
+}
+
+/// Parses the options of `render`, which are also those of a zoom given
+/// the old way without a subcommand and of a job, not including the program
+/// name or the subcommand.
+///
+/// The frames are given either as four positional arguments, in their
+/// original order, or with `--max-iter`, `--zoom-start`, `--zoom-end` and
+/// `--zoom-factor`. Flags may appear anywhere, either as `--flag value` or
+/// `--flag=value`. Returns a message suitable for printing after `Error: `
+/// when something is wrong.
+pub fn parse_args(args: &[String]) -> Result<Args, String> {
+    let mut fractal = FractalKind::Mandelbrot;
+    let mut poly = None;
+    let mut formula = None;
+    let mut formula_log_base = None;
+    let mut c_path = None;
+    let mut c_easing = None;
+    let mut sequence = None;
+    let mut warmup = None;
+    let mut precision = Precision::Auto;
+    let mut series_terms = 16;
+    let mut periodicity = true;
+    let mut subdivision = Subdivision::Off;
+    let mut work_unit = None;
+    let mut chunk_size = None;
+    let mut incremental = false;
+    let mut incremental_threshold = 0.5;
+    let mut keyframe_every = None;
+    let mut supersample = None;
+    let mut adaptive = false;
+    let mut adaptive_threshold = 0.1;
+    let mut coloring = Coloring::EscapeTime;
+    let mut interior_coloring = None;
+    let mut colors = ColorArgs::default();
+    let mut mode = Mode::Escape;
+    let mut samples = 10_000_000;
+    let mut sampler = Sampler::Uniform;
+    let mut seed = 0;
+    let mut mutation_scale = None;
+    let mut burn_in = None;
+    let mut min_iter = 0;
+    let mut tone = ToneMap::Sqrt;
+    let mut bands = None;
+    let mut auto_iter = false;
+    let mut iter_growth = 1.0;
+    let mut iter_schedule = None;
+    let mut time_budget = None;
+    let mut target_magnification = None;
+    let mut duration = None;
+    let mut dry_run = false;
+    let mut yes = false;
+    let mut bailout: Option<f64> = None;
+    let mut stripe_density: Option<f64> = None;
+    let mut color_expr = None;
+    let mut center = None;
+    let mut preset: Option<&Preset> = None;
+    let mut quality = None;
+    let mut location_path = None;
+    let mut location_name = None;
+    let mut save_location = None;
+    let mut keyframes = None;
+    let mut camera_path = None;
+    let mut pan_from = None;
+    let mut pan_via = Vec::new();
+    let mut pan_to = None;
+    let mut view_width = None;
+    let mut refresh_band = None;
+    let mut easing = Easing::Linear;
+    let mut direction = Direction::In;
+    let mut motion_blur: Option<u32> = None;
+    let mut shutter_angle = None;
+    let mut expmap = false;
+    let mut initial_rotation = 0.0;
+    let mut rotation_per_frame = 0.0;
+    let mut x_range = None;
+    let mut y_range = None;
+    let mut view = None;
+    let mut zoom_to = None;
+    let mut fit = Fit::Contain;
+    let mut width = None;
+    let mut height = None;
+    let mut roi = None;
+    let mut roi_fill = false;
+    let mut outputs = Vec::new();
+    let mut export = Export {
+        png: true,
+        exr: false,
+    };
+    let mut image_format = ImageFormat::Png;
+    let mut alpha = Alpha::None;
+    let mut jpeg_quality = None;
+    let mut dump_iterations = false;
+    let mut frame_stats = false;
+    let mut measure_dimension = false;
+    let mut debug_channels = DebugChannels::default();
+    let mut early_stop = true;
+    let mut early_stop_frames: u32 = 30;
+    let mut early_stop_spread: f64 = 0.5;
+    let mut pipe_video = false;
+    let mut no_video = false;
+    let mut output_dir = "rust_data".to_string();
+    let mut run_name = None;
+    let mut filenames = FilenameTemplate::default();
+    let mut resume = false;
+    let mut progress_format = ProgressFormat::Human;
+    let mut frame_parallelism = 1;
+    let mut stabilize_colors = None;
+    let mut max_memory = 4 << 30;
+    let mut threads = None;
+    let mut allow_precision_loss = false;
+    let mut force_precision = None;
+    let mut flip_y = false;
+    let mut background = false;
+    let mut preview_every = None;
+    let mut preview_progressive = None;
+    let mut term_preview = None;
+    let mut dashboard = None;
+    let mut term_protocol = None;
+    let mut post = None;
+    let mut hud: Option<Hud> = None;
+    let mut julia_inset = None;
+    let mut rays = Vec::new();
+    let mut encoder = None;
+    let mut format = "video";
+    let mut gif_options = GifOptions {
+        colors: 256,
+        delay: 3,
+        repeat: None,
+    };
+    let mut video = VideoOptions::default();
+    let mut shard_index = None;
+    let mut shard_count = None;
+    let mut assemble = false;
+    // The frames as flags, in the order of the positional arguments.
+    let mut frame_flags: [Option<String>; 4] = Default::default();
+
+    let positional = split_args(args, |name, value| {
+        match name {
+            "max-iter" => frame_flags[0] = Some(value()?),
+            "zoom-start" => frame_flags[1] = Some(value()?),
+            "zoom-end" => frame_flags[2] = Some(value()?),
+            "zoom-factor" => frame_flags[3] = Some(value()?),
+            "target-magnification" => {
+                let value = value()?;
+                target_magnification = Some(
+                    value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|magnification| *magnification > 0.0 && magnification.is_finite())
+                        .ok_or_else(|| {
+                            format!("target-magnification should be positive, got '{}'", value)
+                        })?,
+                );
+            }
+            "duration" => {
+                let value = value()?;
+                duration = Some(
+                    parse_duration(&value)
+                        .filter(|seconds| *seconds > 0.0 && seconds.is_finite())
+                        .ok_or_else(|| {
+                            format!(
+                                "duration should be a duration like 90s or 1m30s, got '{}'",
+                                value
+                            )
+                        })?,
+                );
+            }
+            "fractal" => {
+                let value = value()?;
+                fractal = FractalKind::from_name(&value)
+                    .ok_or_else(|| format!("unknown fractal '{}'", value))?;
+            }
+            "poly" => poly = Some(Newton::from_spec(&value()?)?),
+            "formula" => {
+                let value = value()?;
+                formula = Some(
+                    Formula::parse(&value).map_err(|e| format!("--formula '{}': {}", value, e))?,
+                );
+            }
+            "c-path" => c_path = Some(CPath::from_spec(&value()?)?),
+            "c-easing" => {
+                let value = value()?;
+                c_easing = Some(
+                    Easing::from_name(&value)
+                        .ok_or_else(|| format!("unknown easing '{}'", value))?,
+                );
+            }
+            "sequence" => sequence = Some(value()?),
+            "warmup" => {
+                warmup = Some(
+                    value()?
+                        .parse::<u32>()
+                        .map_err(|_| "warmup should be an integer".to_string())?,
+                );
+            }
+            "formula-log-base" => {
+                let base: f64 = value()?
+                    .parse()
+                    .map_err(|_| "--formula-log-base must be a number".to_string())?;
+                if !(base > 1.0 && base.is_finite()) {
+                    return Err("--formula-log-base must be greater than 1".to_string());
+                }
+                formula_log_base = Some(base);
+            }
+            "precision" => {
+                let value = value()?;
+                precision = Precision::from_name(&value)
+                    .ok_or_else(|| format!("unknown precision '{}'", value))?;
+                if let Some(feature) = precision.missing_feature() {
+                    let wanted = format!("--precision {}", value);
+                    return Err(RustlebrotError::missing_feature(feature, wanted).to_string());
+                }
+            }
+            "series-terms" => {
+                series_terms = value()?
+                    .parse()
+                    .map_err(|_| "series-terms should be an integer".to_string())?;
+                // A single term is its own last term, so it never converges.
+                if series_terms == 1 {
+                    return Err("series-terms should be at least 2, or 0 to skip no iterations"
+                        .to_string());
+                }
+            }
+            "no-periodicity" => periodicity = false,
+            "subdivide" => {
+                if subdivision == Subdivision::Off {
+                    subdivision = Subdivision::On;
+                }
+            }
+            "show-subdivision" => subdivision = Subdivision::Show,
+            "work-unit" => work_unit = Some(value()?),
+            "chunk-size" => {
+                chunk_size = Some(
+                    value()?
+                        .parse()
+                        .map_err(|_| "chunk-size should be an integer".to_string())?,
+                );
+            }
+            "incremental" => incremental = true,
+            "incremental-threshold" => {
+                incremental_threshold = value()?
+                    .parse()
+                    .map_err(|_| "incremental-threshold should be a float".to_string())?;
+                incremental = true;
+            }
+            "keyframe-every" => {
+                keyframe_every = Some(
+                    value()?
+                        .parse()
+                        .map_err(|_| "keyframe-every should be an integer".to_string())?,
+                );
+            }
+            "supersample" => {
+                supersample = Some(
+                    value()?
+                        .parse()
+                        .map_err(|_| "supersample should be an integer".to_string())?,
+                );
+            }
+            "adaptive" => adaptive = true,
+            "adaptive-threshold" => {
+                adaptive_threshold = value()?
+                    .parse()
+                    .map_err(|_| "adaptive-threshold should be a float".to_string())?;
+                adaptive = true;
+            }
+            "coloring" => coloring = parse_coloring(&value()?)?,
+            "interior-coloring" => {
+                let value = value()?;
+                interior_coloring = Some(InteriorColoring::from_name(&value).ok_or_else(|| {
+                    format!(
+                        "interior-coloring should be period, derivative or both, got '{}'",
+                        value
+                    )
+                })?);
+            }
+            "stabilize-colors" => {
+                let weight: f64 = value()?
+                    .parse()
+                    .map_err(|_| "stabilize-colors should be a float".to_string())?;
+                if !(weight > 0.0 && weight <= 1.0) {
+                    return Err(format!(
+                        "stabilize-colors is the weight of each frame, above 0 and up to 1, got \
+                         {}",
+                        weight
+                    ));
+                }
+                stabilize_colors = Some(weight);
+            }
+            "trap" => coloring = Coloring::Trap(Trap::from_spec(&value()?)?),
+            "color-expr" => color_expr = Some(read_script(&value()?)?),
+            "stripe-density" => {
+                let density: f64 = value()?
+                    .parse()
+                    .map_err(|_| "stripe-density should be a float".to_string())?;
+                if !(density > 0.0 && density.is_finite()) {
+                    return Err(format!("stripe-density should be positive, got {}", density));
+                }
+                stripe_density = Some(density);
+            }
+            "mode" => {
+                let value = value()?;
+                mode = Mode::from_name(&value).ok_or_else(|| format!("unknown mode '{}'", value))?;
+            }
+            "samples" => {
+                samples = value()?
+                    .parse()
+                    .map_err(|_| "samples should be an integer".to_string())?;
+                if samples == 0 {
+                    return Err("samples should be at least 1, or no orbit is plotted".to_string());
+                }
+            }
+            "sampler" => {
+                let value = value()?;
+                sampler = Sampler::from_name(&value).ok_or_else(|| {
+                    format!("sampler should be uniform or metropolis, got '{}'", value)
+                })?;
+            }
+            "seed" => {
+                seed = value()?
+                    .parse()
+                    .map_err(|_| "seed should be an integer".to_string())?;
+            }
+            "mutation-scale" => {
+                let value = value()?;
+                let scale: f64 = value
+                    .parse()
+                    .ok()
+                    .filter(|scale: &f64| *scale > 0.0 && scale.is_finite())
+                    .ok_or_else(|| {
+                        format!("mutation-scale should be a positive number, got '{}'", value)
+                    })?;
+                mutation_scale = Some(scale);
+            }
+            "burn-in" => {
+                burn_in = Some(
+                    value()?
+                        .parse()
+                        .map_err(|_| "burn-in should be an integer".to_string())?,
+                );
+            }
+            "min-iter" => {
+                min_iter = value()?
+                    .parse()
+                    .map_err(|_| "min-iter should be an integer".to_string())?;
+            }
+            "tone" => {
+                let value = value()?;
+                tone = ToneMap::from_name(&value)
+                    .ok_or_else(|| format!("unknown tone mapping '{}'", value))?;
+            }
+            "bands" => {
+                let value = value()?;
+                let limits = value
+                    .split(',')
+                    .map(|n| n.trim().parse())
+                    .collect::<Result<Vec<u32>, _>>()
+                    .ok()
+                    .and_then(|limits| <[u32; 3]>::try_from(limits).ok())
+                    .ok_or_else(|| format!("bands should be three integers, got '{}'", value))?;
+                bands = Some(limits);
+            }
+            "auto-iter" => auto_iter = true,
+            "iter-growth" => {
+                iter_growth = value()?
+                    .parse()
+                    .map_err(|_| "iter-growth should be a float".to_string())?;
+                auto_iter = true;
+            }
+            "iter-schedule" => iter_schedule = Some(camera::read_iter_schedule(&value()?)?),
+            "dry-run" => dry_run = true,
+            "yes" => yes = true,
+            "bailout" => {
+                let radius: f64 = value()?
+                    .parse()
+                    .map_err(|_| "bailout should be a float".to_string())?;
+                // Every orbit that leaves the radius 2 disk escapes, but orbits
+                // of points in the set can wander outside smaller radii.
+                if radius.is_nan() || radius < 2.0 {
+                    return Err(format!(
+                        "bailout should be at least 2, got {}, or points in the set would \
+                         count as escaped",
+                        radius
+                    ));
+                }
+                if radius.is_infinite() {
+                    return Err("bailout should be finite, or no orbit would escape".to_string());
+                }
+                bailout = Some(radius);
+            }
+            "center" => center = Some(parse_center(&value()?)?),
+            "preset" => {
+                let value = value()?;
+                preset = Some(Preset::from_name(&value).ok_or_else(|| {
+                    format!("unknown preset '{}', see --list-presets", value)
+                })?);
+            }
+            "quality" => {
+                let value = value()?;
+                quality = Some(Quality::from_name(&value).ok_or_else(|| {
+                    format!(
+                        "quality should be draft, preview, standard, high or insane, got '{}'",
+                        value
+                    )
+                })?);
+            }
+            "location" => location_path = Some(value()?),
+            "location-name" => location_name = Some(value()?),
+            "save-location" => save_location = Some(value()?),
+            "keyframes" => keyframes = Some(camera::read_keyframes(&value()?)?),
+            "camera-path" => camera_path = Some(camera::read_camera_path(&value()?)?),
+            "pan-from" => pan_from = Some(parse_center(&value()?)?),
+            "pan-via" => pan_via.push(parse_center(&value()?)?),
+            "pan-to" => pan_to = Some(parse_center(&value()?)?),
+            "view-width" => {
+                let width: f64 = value()?
+                    .parse()
+                    .map_err(|_| "view-width should be a float".to_string())?;
+                if !(width > 0.0 && width.is_finite()) {
+                    return Err(format!("view-width should be positive, got {}", width));
+                }
+                view_width = Some(width);
+            }
+            "refresh-band" => {
+                refresh_band = Some(
+                    value()?
+                        .parse()
+                        .map_err(|_| "refresh-band should be an integer".to_string())?,
+                );
+            }
+            "direction" => {
+                let value = value()?;
+                direction = Direction::from_name(&value).ok_or_else(|| {
+                    format!("direction should be in, out or in-out, got '{}'", value)
+                })?;
+            }
+            "easing" => {
+                let value = value()?;
+                easing = Easing::from_name(&value)
+                    .ok_or_else(|| format!("unknown easing '{}'", value))?;
+            }
+            "motion-blur" => {
+                motion_blur = Some(
+                    value()?
+                        .parse()
+                        .ok()
+                        .filter(|&sub_frames| sub_frames > 0)
+                        .ok_or_else(|| "motion-blur should be a positive integer".to_string())?,
+                );
+            }
+            "shutter-angle" => {
+                shutter_angle = Some(
+                    value()?
+                        .parse()
+                        .ok()
+                        .filter(|angle: &f64| *angle > 0.0 && *angle <= 360.0)
+                        .ok_or_else(|| {
+                            "shutter-angle should be a number of degrees in (0, 360]".to_string()
+                        })?,
+                );
+            }
+            "expmap" => expmap = true,
+            "initial-rotation" | "rotation-per-frame" => {
+                let angle: f64 = value()?
+                    .parse()
+                    .ok()
+                    .filter(|angle: &f64| angle.is_finite())
+                    .ok_or_else(|| format!("{} should be a finite number of degrees", name))?;
+                match name {
+                    "initial-rotation" => initial_rotation = angle,
+                    _ => rotation_per_frame = angle,
+                }
+            }
+            "x-range" => x_range = Some(parse_range("x-range", &value()?)?),
+            "y-range" => y_range = Some(parse_range("y-range", &value()?)?),
+            "view" => view = Some(Corners::from_spec(&value()?)?),
+            "zoom-to" => zoom_to = Some(parse_center(&value()?)?),
+            "fit" => {
+                let value = value()?;
+                fit = Fit::from_name(&value).ok_or_else(|| format!("unknown fit '{}'", value))?;
+            }
+            "width" => {
+                width = Some(
+                    value()?
+                        .parse()
+                        .map_err(|_| "width should be an integer".to_string())?,
+                );
+            }
+            "height" => {
+                height = Some(
+                    value()?
+                        .parse()
+                        .map_err(|_| "height should be an integer".to_string())?,
+                );
+            }
+            "roi" => roi = Some(parse_roi(&value()?)?),
+            "roi-fill" => roi_fill = true,
+            "outputs" => outputs = Output::list_from_spec(&value()?)?,
+            "export" => export = Export::from_spec(&value()?)?,
+            "image-format" => {
+                let value = value()?;
+                image_format = ImageFormat::from_name(&value).ok_or_else(|| {
+                    format!("image-format should be png, jpeg, webp, tiff or bmp, got '{}'", value)
+                })?;
+            }
+            "alpha" => alpha = parse_alpha(&value()?)?,
+            "jpeg-quality" => {
+                let quality: u8 = value()?
+                    .parse()
+                    .map_err(|_| "jpeg-quality should be an integer".to_string())?;
+                if !(1..=100).contains(&quality) {
+                    return Err(format!("jpeg-quality should be from 1 to 100, got {}", quality));
+                }
+                jpeg_quality = Some(quality);
+            }
+            "webp-lossless" => {}
+            "webp-quality" => {
+                return Err("lossy WebP needs libwebp, which this build doesn't link, so WebP \
+                            frames are always lossless; use --webp-lossless, or --image-format \
+                            jpeg for lossy frames"
+                    .to_string())
+            }
+            "dump-iterations" => dump_iterations = true,
+            "frame-stats" => frame_stats = true,
+            "measure-dimension" => measure_dimension = true,
+            "debug-channels" => debug_channels = DebugChannels::from_spec(&value()?)?,
+            "no-early-stop" => early_stop = false,
+            "early-stop-frames" => {
+                early_stop_frames = value()?
+                    .parse()
+                    .map_err(|_| "early-stop-frames should be an integer".to_string())?;
+            }
+            "early-stop-spread" => {
+                early_stop_spread = value()?
+                    .parse()
+                    .map_err(|_| "early-stop-spread should be a float".to_string())?;
+            }
+            "pipe-video" => pipe_video = true,
+            "no-video" => no_video = true,
+            "output-dir" => output_dir = value()?,
+            "filename-template" => filenames = FilenameTemplate::parse(&value()?)?,
+            "resume" => resume = true,
+            "progress-format" => {
+                let value = value()?;
+                progress_format = ProgressFormat::from_name(&value).ok_or_else(|| {
+                    format!("progress-format should be human or json, got '{}'", value)
+                })?;
+            }
+            "frame-parallelism" => {
+                frame_parallelism = value()?
+                    .parse()
+                    .map_err(|_| "frame-parallelism should be an integer".to_string())?;
+            }
+            "max-memory" => {
+                let value = value()?;
+                max_memory = parse_size(&value).ok_or_else(|| {
+                    format!("max-memory should be a size like 512M or 8G, got '{}'", value)
+                })?;
+            }
+            "threads" => {
+                threads = Some(
+                    value()?
+                        .parse()
+                        .map_err(|_| "threads should be an integer".to_string())?,
+                );
+            }
+            "background" => background = true,
+            "time-budget" => {
+                let value = value()?;
+                time_budget = Some(
+                    parse_duration(&value)
+                        .filter(|seconds| *seconds > 0.0 && seconds.is_finite())
+                        .ok_or_else(|| {
+                            format!(
+                                "time-budget should be a duration like 8h, 90m or 1h30m, got \
+                                 '{}'",
+                                value
+                            )
+                        })?,
+                );
+            }
+            "shard-index" => {
+                shard_index = Some(
+                    value()?
+                        .parse::<u32>()
+                        .map_err(|_| "shard-index should be an integer".to_string())?,
+                );
+            }
+            "shard-count" => {
+                shard_count = Some(
+                    value()?
+                        .parse::<u32>()
+                        .map_err(|_| "shard-count should be an integer".to_string())?,
+                );
+            }
+            "assemble" => assemble = true,
+            "allow-precision-loss" => allow_precision_loss = true,
+            "force-precision" => {
+                let value = value()?;
+                force_precision = match Precision::from_name(&value) {
+                    Some(Precision::Auto) | None => {
+                        return Err(format!(
+                            "--force-precision takes f32, f64, dd, perturb or big, got '{}'",
+                            value
+                        ))
+                    }
+                    forced => forced,
+                };
+                if let Some(feature) = force_precision.and_then(Precision::missing_feature) {
+                    let wanted = format!("--force-precision {}", value);
+                    return Err(RustlebrotError::missing_feature(feature, wanted).to_string());
+                }
+            }
+            "flip-y" => flip_y = true,
+            "run-name" => {
+                let name = value()?;
+                if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+                    return Err(format!(
+                        "run-name should be a plain directory name, got '{}'",
+                        name
+                    ));
+                }
+                run_name = Some(name);
+            }
+            "preview-every" => {
+                let every: u32 = value()?
+                    .parse()
+                    .map_err(|_| "preview-every should be an integer".to_string())?;
+                if every == 0 {
+                    return Err("preview-every should be at least 1".to_string());
+                }
+                preview_every = Some(every);
+            }
+            "preview-progressive" => preview_progressive = Some(value()?),
+            "term-preview" => {
+                term_preview.get_or_insert(1);
+            }
+            "term-preview-every" => {
+                let every = value()?.parse().ok().filter(|&every| every > 0);
+                term_preview = Some(every.ok_or_else(|| {
+                    "term-preview-every should be a positive integer".to_string()
+                })?);
+            }
+            "term-protocol" => {
+                let value = value()?;
+                term_protocol = Some(Protocol::from_name(&value).ok_or_else(|| {
+                    format!("term-protocol should be kitty, sixel or blocks, got '{}'", value)
+                })?);
+                term_preview.get_or_insert(1);
+            }
+            "dashboard" => {
+                let value = value()?;
+                if !cfg!(feature = "dashboard") {
+                    let wanted = format!("--dashboard {}", value);
+                    return Err(RustlebrotError::missing_feature("dashboard", wanted).to_string());
+                }
+                dashboard = Some(value.parse().map_err(|_| {
+                    format!(
+                        "dashboard should be an address and port like 127.0.0.1:8080, got '{}'",
+                        value
+                    )
+                })?);
+            }
+            "post" => post = Some(Chain::from_spec(&value()?)?),
+            "hud" => {
+                hud.get_or_insert_default();
+            }
+            "hud-position" => {
+                let value = value()?;
+                hud.get_or_insert_default().corner = Corner::from_name(&value).ok_or_else(|| {
+                    format!(
+                        "hud-position should be top-left, top-right, bottom-left or \
+                         bottom-right, got '{}'",
+                        value
+                    )
+                })?;
+            }
+            "hud-size" => {
+                let size = value()?.parse().ok().filter(|&size| size > 0);
+                hud.get_or_insert_default().size =
+                    Some(size.ok_or_else(|| "hud-size should be a positive integer".to_string())?);
+            }
+            "hud-scale-bar" => hud.get_or_insert_default().scale_bar = true,
+            "hud-only-video" => hud.get_or_insert_default().only_video = true,
+            "julia-inset" => julia_inset = Some(JuliaInset::from_spec(&value()?)?),
+            "ray" => rays.push(ExternalAngle::from_spec(&value()?)?),
+            "encoder" => encoder = Some(parse_encoder(&value()?)?),
+            "format" => {
+                format = match value()?.as_str() {
+                    "video" => "video",
+                    "gif" => "gif",
+                    "apng" => "apng",
+                    other => {
+                        return Err(format!("format should be video, gif or apng, got '{}'", other))
+                    }
+                };
+            }
+            "gif-colors" => {
+                gif_options.colors = value()?
+                    .parse()
+                    .map_err(|_| "gif-colors should be an integer".to_string())?;
+                if !(2..=256).contains(&gif_options.colors) {
+                    return Err(format!(
+                        "gif-colors should be from 2 to 256, got {}",
+                        gif_options.colors
+                    ));
+                }
+            }
+            "gif-delay" => {
+                let delay: u32 = value()?
+                    .parse()
+                    .map_err(|_| "gif-delay should be an integer".to_string())?;
+                // Browsers slow shorter delays down to a tenth of a second.
+                if delay < 20 {
+                    return Err(format!("gif-delay should be at least 20 ms, got {}", delay));
+                }
+                gif_options.delay = u16::try_from((delay + 5) / 10)
+                    .map_err(|_| format!("gif-delay {} ms is too long", delay))?;
+            }
+            "gif-loop" => {
+                let value = value()?;
+                gif_options.repeat = match value.as_str() {
+                    "forever" => None,
+                    count => Some(count.parse().map_err(|_| {
+                        format!("gif-loop should be a count or forever, got '{}'", count)
+                    })?),
+                };
+            }
+            _ => {
+                if !colors.flag(name, value)? && !video_flag(&mut video, name, value)? {
+                    return Err(format!("unknown flag --{}", name));
+                }
+            }
+        }
+        Ok(())
+    })?;
+
+    if uses_flag(args, &["gif-"]) && format != "gif" {
+        return Err("--gif-* flags need --format gif".to_string());
+    }
+    if format != "video" {
+        if encoder.is_some() {
+            return Err("--encoder only applies to --format video".to_string());
+        }
+        encoder = Some(match format {
+            "gif" => EncoderKind::Gif(gif_options),
+            _ => EncoderKind::Apng,
+        });
+    }
+    check_video_flags(args, encoder, no_video)?;
+    video.background = colors.interior;
+    if export.exr && mode != Mode::Escape {
+        return Err("exr export is only available with --mode escape".to_string());
+    }
+    // What the flags leave out comes from --quality, and the rest from the
+    // built-in defaults. Its anti-aliasing is left out of runs that would
+    // refuse it as flags, and to --time-budget, which picks the samples
+    // itself; so are 16-bit channels, from formats that don't have them.
+    if let Some(largest) = outputs.first() {
+        if width.is_some() || height.is_some() {
+            return Err("--outputs renders the frames at the largest of its sizes, so it can't \
+                        be used with --width or --height"
+                .to_string());
+        }
+        (width, height) = (Some(largest.width), Some(largest.height));
+    }
+    let flags = quality::Settings {
+        width,
+        height,
+        supersample,
+        adaptive: adaptive.then_some(true),
+        jpeg_quality,
+        bit_depth: uses_flag(args, &["bit-depth"]).then_some(colors.bit_depth),
+    };
+    let mut settings = quality::resolve(flags, quality, BUILT_IN);
+    let anti_aliasing = flags.supersample.is_some() || flags.adaptive.is_some();
+    let takes_anti_aliasing = mode == Mode::Escape
+        && color_expr.is_none()
+        && motion_blur.is_none()
+        && !expmap
+        && time_budget.is_none();
+    if !anti_aliasing && !takes_anti_aliasing {
+        (settings.supersample, settings.adaptive) = (None, None);
+    }
+    if flags.bit_depth.is_none() && !image_format.holds(BitDepth::Sixteen) {
+        settings.bit_depth = Some(BitDepth::Eight);
+    }
+    let (width, height) = (settings.width.unwrap(), settings.height.unwrap());
+    let (supersample, adaptive) = (settings.supersample, settings.adaptive.unwrap_or(false));
+    colors.bit_depth = settings.bit_depth.unwrap();
+    match &mut image_format {
+        ImageFormat::Jpeg { quality } => *quality = settings.jpeg_quality.unwrap(),
+        _ if jpeg_quality.is_some() => {
+            return Err("--jpeg-quality only applies to --image-format jpeg".to_string())
+        }
+        _ => {}
+    }
+    if uses_flag(args, &["webp-"]) && image_format != ImageFormat::WebP {
+        return Err("--webp-lossless only applies to --image-format webp".to_string());
+    }
+    if !image_format.holds(colors.bit_depth) {
+        return Err(format!(
+            "{} frames have 8-bit channels; --bit-depth 16 needs --image-format png or tiff",
+            image_format.name()
+        ));
+    }
+    if colors.lineart && mode != Mode::Escape {
+        return Err("--style lineart is only available with --mode escape".to_string());
+    }
+    if colors.line_art().is_some_and(|lines| lines.paper == Paper::Transparent) {
+        if alpha != Alpha::None {
+            return Err("--line-paper transparent makes the paper transparent, so it can't be \
+                        used with --alpha"
+                .to_string());
+        }
+        alpha = Alpha::Lines;
+    }
+    if alpha != Alpha::None {
+        if mode != Mode::Escape {
+            return Err("--alpha is only available with --mode escape".to_string());
+        }
+        if !image_format.has_alpha() {
+            let flag = match alpha {
+                Alpha::Lines => "--line-paper transparent",
+                _ => "--alpha",
+            };
+            return Err(format!(
+                "{} frames have no alpha channel; {} needs --image-format png, webp or tiff",
+                image_format.name(),
+                flag
+            ));
+        }
+        if matches!(alpha, Alpha::Threshold(_)) && coloring == Coloring::Distance {
+            return Err("--coloring distance fades the edge of the set in by its distance, so \
+                        it can't be used with --alpha threshold"
+                .to_string());
+        }
+    }
+    if dump_iterations && mode != Mode::Escape {
+        return Err("--dump-iterations is only available with --mode escape".to_string());
+    }
+    if dump_iterations && matches!(coloring, Coloring::Phase | Coloring::Binary(_)) {
+        return Err(format!(
+            "--dump-iterations saves one value per sample, so it can't keep the angles of {} \
+             coloring",
+            coloring.name()
+        ));
+    }
+    if let Some(density) = stripe_density {
+        match coloring {
+            Coloring::Stripes(_) => coloring = Coloring::Stripes(density),
+            _ => {
+                return Err("--stripe-density is only available with --coloring stripes".to_string())
+            }
+        }
+    }
+    let bailout = bailout.unwrap_or(coloring.default_bailout());
+    let location = match location_path {
+        Some(path) => {
+            let (locations, ignored) = bookmarks::read(&path)?;
+            let location = bookmarks::select(&path, locations, location_name.as_deref())?;
+            Some(LocationArg { path, location, ignored })
+        }
+        None if location_name.is_some() && save_location.is_none() => {
+            return Err("--location-name names the location of --location or --save-location"
+                .to_string())
+        }
+        None => None,
+    };
+    if let Some(LocationArg { location, .. }) = &location {
+        if preset.is_some() {
+            return Err("--location and --preset can't be used together".to_string());
+        }
+        if uses_flag(args, &["fractal", "poly"]) {
+            return Err("--location gives the fractal, so it can't be used with --fractal or \
+                        --poly"
+                .to_string());
+        }
+        let placed = center.is_some() || x_range.is_some() || y_range.is_some();
+        if placed || view.is_some() || keyframes.is_some() {
+            return Err("--location gives the center, so it can't be used with --center, the \
+                        ranges, --view or --keyframes"
+                .to_string());
+        }
+        fractal = location.fractal;
+        poly = location.newton.clone();
+        center = Some(location.center.clone());
+        if !uses_flag(args, &["initial-rotation"]) {
+            initial_rotation = location.rotation;
+        }
+    }
+    // Forcing a precision renders every frame in it, even past its
+    // resolution, to see what the selector saves them from.
+    if let Some(forced) = force_precision {
+        if uses_flag(args, &["precision"]) {
+            return Err("--force-precision and --precision can't be used together".to_string());
+        }
+        precision = forced;
+        allow_precision_loss = true;
+    }
+    if let Some(formula) = &mut formula {
+        if uses_flag(args, &["fractal", "poly", "preset", "location", "save-location"]) {
+            return Err("--formula is the fractal, so it can't be used with --fractal, --poly, \
+                        --preset or the location flags"
+                .to_string());
+        }
+        // Formulas have no delta iteration, derivative or f32 kernel.
+        if !matches!(precision, Precision::Auto | Precision::F64) {
+            return Err("--formula is only rendered in f64".to_string());
+        }
+        if mode != Mode::Escape {
+            return Err("--formula is only available with --mode escape".to_string());
+        }
+        if coloring == Coloring::Distance {
+            return Err("--formula has no distance estimates to color by".to_string());
+        }
+        if let Some(base) = formula_log_base {
+            formula.log_base = base;
+        }
+        fractal = FractalKind::Formula;
+    } else if formula_log_base.is_some() {
+        return Err("--formula-log-base needs --formula".to_string());
+    }
+    if c_path.is_some() {
+        if fractal != FractalKind::Julia && (uses_flag(args, &["fractal"]) || formula.is_some()) {
+            return Err("--c-path is the c of --fractal julia, so it can't be used with another \
+                        --fractal or --formula"
+                .to_string());
+        }
+        fractal = FractalKind::Julia;
+    }
+    if fractal == FractalKind::Julia {
+        if c_path.is_none() {
+            return Err("--fractal julia needs --c-path".to_string());
+        }
+        if !matches!(precision, Precision::Auto | Precision::F64) {
+            return Err("--fractal julia is only rendered in f64".to_string());
+        }
+        if mode != Mode::Escape {
+            return Err("--fractal julia is only available with --mode escape".to_string());
+        }
+        if coloring == Coloring::Distance {
+            return Err("--fractal julia has no distance estimates to color by".to_string());
+        }
+        // Every frame is of a set of its own, so none can be taken from
+        // another.
+        if incremental || motion_blur.is_some() {
+            return Err("--fractal julia changes the set every frame, so it can't be used with \
+                        --incremental or --motion-blur"
+                .to_string());
+        }
+    } else if c_easing.is_some() {
+        return Err("--c-easing needs --c-path".to_string());
+    }
+    let newton = match (fractal, poly) {
+        (FractalKind::Newton, poly) => Some(poly.unwrap_or_default()),
+        (_, Some(_)) => return Err("--poly is only available with --fractal newton".to_string()),
+        (_, None) => None,
+    };
+    if fractal == FractalKind::Newton {
+        // Points are colored by the root they converge to, in f64, so the
+        // options of escape time colorings and deeper precisions don't
+        // apply.
+        let unused = [
+            "coloring",
+            "color-expr",
+            "transfer",
+            "trap",
+            "stripe-density",
+            "bailout",
+            "series-terms",
+        ];
+        if let Some(flag) = unused.iter().find(|flag| uses_flag(args, &[flag])) {
+            return Err(format!("--{} doesn't apply to --fractal newton", flag));
+        }
+        if !matches!(precision, Precision::Auto | Precision::F64) {
+            return Err("--fractal newton is only rendered in f64".to_string());
+        }
+        if mode != Mode::Escape {
+            return Err("--fractal newton is only available with --mode escape".to_string());
+        }
+        if subdivision != Subdivision::Off || incremental {
+            return Err("--subdivide and --incremental aren't available with --fractal newton"
+                .to_string());
+        }
+        if dump_iterations || export.exr {
+            return Err("--dump-iterations and exr export save escape times, which --fractal \
+                        newton doesn't have"
+                .to_string());
+        }
+    }
+    let lyapunov = match fractal {
+        FractalKind::Lyapunov => {
+            // There are no points to sample, so --samples counts the
+            // iterations the exponent is averaged over instead.
+            let measured = match uses_flag(args, &["samples"]) {
+                true => u32::try_from(samples).map_err(|_| {
+                    format!("samples should be at most {} with --fractal lyapunov", u32::MAX)
+                })?,
+                false => lyapunov::DEFAULT_SAMPLES,
+            };
+            Some(Lyapunov::new(
+                sequence.as_deref().unwrap_or(lyapunov::DEFAULT_SEQUENCE),
+                warmup.unwrap_or(lyapunov::DEFAULT_WARMUP),
+                measured,
+            )?)
+        }
+        _ if sequence.is_some() || warmup.is_some() => {
+            return Err("--sequence and --warmup are only available with --fractal lyapunov"
+                .to_string());
+        }
+        _ => None,
+    };
+    if fractal == FractalKind::Lyapunov {
+        // Points are colored by their exponent, in f64, after a set number
+        // of iterations, so the options of escape time colorings, deeper
+        // precisions and iteration limits don't apply.
+        let unused = [
+            "coloring",
+            "color-expr",
+            "transfer",
+            "trap",
+            "stripe-density",
+            "bailout",
+            "series-terms",
+            "auto-iter",
+            "iter-growth",
+            "iter-schedule",
+        ];
+        if let Some(flag) = unused.iter().find(|flag| uses_flag(args, &[flag])) {
+            return Err(format!("--{} doesn't apply to --fractal lyapunov", flag));
+        }
+        if !matches!(precision, Precision::Auto | Precision::F64) {
+            return Err("--fractal lyapunov is only rendered in f64".to_string());
+        }
+        if mode != Mode::Escape {
+            return Err("--fractal lyapunov is only available with --mode escape".to_string());
+        }
+        if subdivision != Subdivision::Off || incremental {
+            return Err("--subdivide and --incremental aren't available with --fractal lyapunov"
+                .to_string());
+        }
+        if dump_iterations || export.exr {
+            return Err("--dump-iterations and exr export save escape times, which --fractal \
+                        lyapunov doesn't have"
+                .to_string());
+        }
+    }
+    if let Some(ScriptArg { script, .. }) = &color_expr {
+        if uses_flag(args, &["coloring"]) {
+            return Err("--color-expr colors the frames itself, so it can't be used with \
+                        --coloring"
+                .to_string());
+        }
+        if mode != Mode::Escape {
+            return Err("--color-expr is only available with --mode escape".to_string());
+        }
+        if precision == Precision::Big {
+            return Err("--color-expr isn't available with --precision big, whose frames don't \
+                        keep the orbits it reads"
+                .to_string());
+        }
+        if adaptive {
+            return Err("--adaptive adds samples without their orbits, so it can't be used with \
+                        --color-expr"
+                .to_string());
+        }
+        if dump_iterations {
+            return Err("--dump-iterations saves the escape times alone, which a script of \
+                        --color-expr can't be run again from"
+                .to_string());
+        }
+        if colors.transfer != Transfer::Linear {
+            return Err("--transfer spreads escape times, which a script of --color-expr \
+                        spreads itself"
+                .to_string());
+        }
+        // The script measures the orbit against the trap of `--trap`, or
+        // the origin.
+        let trap = match coloring {
+            Coloring::Trap(trap) => trap,
+            _ => Trap::Point(0.0, 0.0),
+        };
+        coloring = Coloring::Script(script.needs(trap));
+    }
+    match &mut sampler {
+        Sampler::Metropolis(metropolis) => {
+            if mode == Mode::Escape {
+                return Err("--sampler metropolis draws the orbits of --mode buddhabrot and \
+                            nebulabrot"
+                    .to_string());
+            }
+            metropolis.mutation = mutation_scale.unwrap_or(metropolis.mutation);
+            metropolis.burn_in = burn_in.unwrap_or(metropolis.burn_in);
+        }
+        Sampler::Uniform => {
+            if mutation_scale.is_some() || burn_in.is_some() {
+                return Err(
+                    "--mutation-scale and --burn-in are settings of --sampler metropolis"
+                        .to_string(),
+                );
+            }
+        }
+    }
+    if colors.dither != Dither::None && mode != Mode::Escape {
+        return Err("--dither is only available with --mode escape".to_string());
+    }
+    if colors.lighting.is_some() && mode != Mode::Escape {
+        return Err("--lighting is only available with --mode escape".to_string());
+    }
+    if colors.contours.is_some() && mode != Mode::Escape {
+        return Err("--contours is only available with --mode escape".to_string());
+    }
+    if colors.silhouette.is_some() && mode != Mode::Escape {
+        return Err("--silhouette is only available with --mode escape".to_string());
+    }
+    if colors.transfer != Transfer::Linear && mode != Mode::Escape {
+        return Err("--transfer is only available with --mode escape".to_string());
+    }
+    colors.check_transfer(coloring)?;
+    colors.check_map_interpolate()?;
+    colors.check_line_art()?;
+    if colors.palettes().len() > 1 {
+        if mode != Mode::Escape {
+            return Err("several palettes are only available with --mode escape".to_string());
+        }
+        if pipe_video {
+            return Err("--pipe-video streams one video, so it can't be used with several \
+                        palettes"
+                .to_string());
+        }
+        if video.output.is_some() {
+            return Err("--video-out names one video, but several palettes make one each; \
+                        they're saved with their frames"
+                .to_string());
+        }
+    }
+    let escape_times = coloring.uses_escape_times();
+    if early_stop_frames == 0 {
+        return Err("early-stop-frames should be at least 1".to_string());
+    }
+    if !(early_stop_spread > 0.0 && early_stop_spread.is_finite()) {
+        return Err(format!("early-stop-spread should be positive, got {}", early_stop_spread));
+    }
+    let shard = match (shard_index, shard_count) {
+        (Some(index), Some(count)) if index < count => Some(Shard { index, count }),
+        (Some(index), Some(count)) => {
+            return Err(format!(
+                "shard-index counts from 0, so it should be less than the shard-count of {}, got \
+                 {}",
+                count, index
+            ))
+        }
+        (None, None) => None,
+        _ => return Err("--shard-index and --shard-count have to be given together".to_string()),
+    };
+    if shard.is_some() {
+        if time_budget.is_some() {
+            return Err("--time-budget fits the whole zoom into the time, so it can't be used \
+                        with --shard-index"
+                .to_string());
+        }
+        if incremental {
+            return Err("--incremental renders every frame from the one before, so it can't be \
+                        used with --shard-index"
+                .to_string());
+        }
+        if !assemble && !no_video && uses_flag(args, &VIDEO_FLAGS) {
+            return Err("a shard only saves its frames, which merge encodes, so the options of \
+                        the video need --assemble"
+                .to_string());
+        }
+    } else if assemble {
+        return Err("--assemble is only available with --shard-index".to_string());
+    }
+    // A shard can't tell the frames after its own are uniform too, so
+    // stopping early would leave a hole in the zoom.
+    let early_stop = early_stop && shard.is_none();
+    // Julia sets don't stay uniform once they are, as the zoom into the
+    // interior of the Mandelbrot set does.
+    let early_stop = early_stop && fractal != FractalKind::Julia;
+    // Nor are Lyapunov exponents iterations, which the spread is in.
+    let early_stop = early_stop && fractal != FractalKind::Lyapunov;
+    let early_stop = early_stop.then_some(EarlyStop {
+        frames: early_stop_frames,
+        spread: early_stop_spread,
+    });
+    if frame_stats && (mode != Mode::Escape || !escape_times) {
+        return Err(
+            "--frame-stats is only available with --mode escape and a coloring of escape \
+             times, not distance or trap"
+                .to_string(),
+        );
+    }
+    if measure_dimension
+        && (mode != Mode::Escape || matches!(fractal, FractalKind::Newton | FractalKind::Lyapunov))
+    {
+        return Err("--measure-dimension measures the boundary of the set, so it's only \
+                    available with --mode escape, and not with --fractal newton or lyapunov"
+            .to_string());
+    }
+    if pipe_video && mode == Mode::Escape && !export.png {
+        return Err("--pipe-video needs png in --export for the colored frames".to_string());
+    }
+    if post.is_some() && mode == Mode::Escape && !export.png {
+        return Err("--post filters the colored frames, so it needs png in --export".to_string());
+    }
+    if hud.is_some() && mode == Mode::Escape && !export.png {
+        return Err("--hud writes on the colored frames, so it needs png in --export".to_string());
+    }
+    if julia_inset.is_some() {
+        if fractal != FractalKind::Mandelbrot {
+            return Err(format!(
+                "--julia-inset shows the Julia set of the center of a Mandelbrot zoom, so it \
+                 can't be used with --fractal {}",
+                fractal.name()
+            ));
+        }
+        if mode == Mode::Escape && !export.png {
+            return Err(
+                "--julia-inset draws on the colored frames, so it needs png in --export".to_string()
+            );
+        }
+    }
+    if !rays.is_empty() {
+        if fractal != FractalKind::Mandelbrot {
+            return Err(format!(
+                "--ray traces the external rays of the Mandelbrot set, so it can't be used with \
+                 --fractal {}",
+                fractal.name()
+            ));
+        }
+        if mode == Mode::Escape && !export.png {
+            return Err(
+                "--ray draws on the colored frames, so it needs png in --export".to_string()
+            );
+        }
+    }
+    if colors.line_art().is_some_and(|lines| lines.interior)
+        && !matches!(fractal, FractalKind::Mandelbrot | FractalKind::Tricorn)
+    {
+        return Err(format!(
+            "--line-interior draws between the cycles of the Mandelbrot and Tricorn sets, so it \
+             can't be used with --fractal {}",
+            fractal.name()
+        ));
+    }
+    if interior_coloring.is_some() {
+        if !matches!(fractal, FractalKind::Mandelbrot | FractalKind::Tricorn) {
+            return Err(format!(
+                "--interior-coloring finds the cycles of the Mandelbrot and Tricorn sets, so it \
+                 can't be used with --fractal {}",
+                fractal.name()
+            ));
+        }
+        if mode != Mode::Escape || !coloring.uses_escape_times() {
+            return Err("--interior-coloring colors the interior of frames of escape times, so \
+                        it needs --mode escape and a coloring of them"
+                .to_string());
+        }
+    }
+    if hud.is_some_and(|hud| hud.only_video) && !pipe_video {
+        return Err("--hud-only-video writes on the frames piped to the video, so it needs \
+                    --pipe-video"
+            .to_string());
+    }
+    if precision == Precision::F32 {
+        if !cfg!(feature = "simd") {
+            return Err("--precision f32 needs the simd feature".to_string());
+        }
+        if mode != Mode::Escape || !escape_times {
+            return Err("--precision f32 only works with colorings of escape times in --mode \
+                        escape, not distance or trap"
+                .to_string());
+        }
+    }
+    if precision == Precision::DoubleDouble && (mode != Mode::Escape || !escape_times) {
+        return Err("--precision dd only works with colorings of escape times in --mode escape, \
+                    not distance, trap, stripes or --color-expr"
+            .to_string());
+    }
+    if subdivision != Subdivision::Off && (mode != Mode::Escape || !escape_times) {
+        return Err("--subdivide only works with colorings of escape times in --mode escape, \
+                    not distance or trap"
+            .to_string());
+    }
+    let work_units = work_units(work_unit.as_deref(), chunk_size)?;
+    if work_units != WorkUnits::Auto {
+        if mode != Mode::Escape {
+            return Err(format!(
+                "--work-unit and --chunk-size don't apply to --mode {}",
+                mode.name()
+            ));
+        }
+        if subdivision != Subdivision::Off {
+            return Err("--subdivide always works in tiles of 64 pixels, which keeps the pixels \
+                        it fills in the same, so it takes no --work-unit or --chunk-size"
+                .to_string());
+        }
+    }
+    if !(incremental_threshold > 0.0 && incremental_threshold <= 1.0) {
+        return Err("incremental-threshold should be in (0, 1]".to_string());
+    }
+    if keyframe_every == Some(0) {
+        return Err("keyframe-every should be at least 1".to_string());
+    }
+    if keyframe_every.is_some() && !incremental {
+        return Err("--keyframe-every is only available with --incremental".to_string());
+    }
+    let incremental = incremental.then_some(Incremental {
+        threshold: incremental_threshold,
+        keyframe_every: keyframe_every.unwrap_or(10),
+    });
+    if incremental.is_some() {
+        if mode != Mode::Escape || !escape_times {
+            return Err("--incremental only works with colorings of escape times in --mode \
+                        escape, not distance or trap"
+                .to_string());
+        }
+        if subdivision != Subdivision::Off {
+            return Err("--incremental and --subdivide can't be used together".to_string());
+        }
+    }
+    if time_budget.is_some() {
+        if mode != Mode::Escape {
+            return Err("--time-budget is only available with --mode escape".to_string());
+        }
+        if iter_schedule.is_some() {
+            return Err("--time-budget chooses every frame's max_iter, so it can't be used with \
+                        --iter-schedule"
+                .to_string());
+        }
+        if keyframes.iter().flatten().any(|keyframe| keyframe.max_iter.is_some()) {
+            return Err("--time-budget chooses every frame's max_iter, so it can't be used with \
+                        keyframes that set max_iter"
+                .to_string());
+        }
+        if camera_path.iter().flat_map(|path| &path.rows).any(|row| row.max_iter.is_some()) {
+            return Err("--time-budget chooses every frame's max_iter, so it can't be used with \
+                        a camera path that sets max_iter"
+                .to_string());
+        }
+    }
+    let fit_supersample = supersample.is_none() && !adaptive;
+    // Adaptive anti-aliasing refines pixels up to 3x3 unless told otherwise.
+    let per_side = supersample.unwrap_or(if adaptive { 3 } else { 1 });
+    if !(1..=4).contains(&per_side) {
+        return Err("supersample should be between 1 and 4".to_string());
+    }
+    if (per_side > 1 || adaptive) && mode != Mode::Escape {
+        return Err("--supersample and --adaptive only work in --mode escape; Buddhabrot \
+                    frames are smoothed with more --samples"
+            .to_string());
+    }
+    if adaptive && per_side == 1 {
+        return Err("--adaptive needs --supersample 2 or more".to_string());
+    }
+    if !(adaptive_threshold > 0.0 && adaptive_threshold <= 1.0) {
+        return Err("adaptive-threshold should be in (0, 1]".to_string());
+    }
+    let adaptive = adaptive.then_some(Adaptive {
+        threshold: adaptive_threshold,
+        samples: per_side,
+    });
+    let supersample = if adaptive.is_some() { 1 } else { per_side };
+    if width == 0 || height == 0 {
+        return Err("width and height should be at least 1".to_string());
+    }
+    let placing = [
+        ("center", center.is_some()),
+        ("keyframes", keyframes.is_some()),
+        ("camera-path", camera_path.is_some()),
+        ("pan-from", pan_from.is_some()),
+    ];
+    let ranges = ranges_of(x_range, y_range, view, &placing)?;
+    if let Some(target) = &zoom_to {
+        let Some((x_range, y_range)) = ranges else {
+            return Err("--zoom-to is the point a zoom from --view or the ranges closes in on, \
+                        so it needs one of them"
+                .to_string());
+        };
+        let parse = |digits: &str| digits.parse().expect("centers are validated when read");
+        let (x, y): (f64, f64) = (parse(&target.0), parse(&target.1));
+        if !(x_range.0..=x_range.1).contains(&x) || !(y_range.0..=y_range.1).contains(&y) {
+            return Err(format!(
+                "--zoom-to {},{} is outside the view, so the zoom would never show it",
+                target.0, target.1
+            ));
+        }
+        // The point only stays put in frames that don't turn, and the
+        // center moves every frame.
+        let unmoving = [
+            ("incremental", incremental.is_some()),
+            ("expmap", expmap),
+            ("rotation-per-frame", rotation_per_frame != 0.0),
+        ];
+        if let Some((flag, _)) = unmoving.iter().find(|(_, used)| *used) {
+            return Err(format!(
+                "--zoom-to moves the center every frame, so it can't be used with --{}",
+                flag
+            ));
+        }
+    }
+    if keyframes.is_some() && (center.is_some() || ranges.is_some()) {
+        return Err("--keyframes gives the centers, so --center, --x-range and --y-range can't \
+                    be used with it"
+            .to_string());
+    }
+    if ranges.is_some() && center.is_some() {
+        return Err("--center can't be used with --x-range and --y-range, whose middle is the \
+                    center"
+            .to_string());
+    }
+    if camera_path.is_some() {
+        // The rows place the camera in every frame, and sub-frames would
+        // come between them.
+        let placing = [
+            "keyframes",
+            "center",
+            "x-range",
+            "y-range",
+            "preset",
+            "location",
+            "target-magnification",
+            "easing",
+            "motion-blur",
+            "shutter-angle",
+        ];
+        if let Some(flag) = placing.iter().find(|flag| uses_flag(args, &[flag])) {
+            return Err(format!(
+                "--camera-path gives the view of every frame, so it can't be used with --{}",
+                flag
+            ));
+        }
+    }
+    if let Some(preset) = preset {
+        if fractal != FractalKind::Mandelbrot {
+            return Err(format!(
+                "presets are places in the Mandelbrot set, so --preset can't be used with \
+                 --fractal {}",
+                fractal.name()
+            ));
+        }
+        // A view given some other way takes the place of the preset's.
+        if center.is_none() && ranges.is_none() && keyframes.is_none() {
+            center = Some((preset.center.0.to_string(), preset.center.1.to_string()));
+        }
+    }
+    if threads == Some(0) {
+        return Err("threads should be at least 1".to_string());
+    }
+    if frame_parallelism == 0 {
+        return Err("frame-parallelism should be at least 1".to_string());
+    }
+    if frame_parallelism > 1 && incremental.is_some() {
+        return Err("--incremental renders every frame from the one before, so it can't be used \
+                    with --frame-parallelism"
+            .to_string());
+    }
+    if stabilize_colors.is_some() {
+        if coloring != Coloring::Histogram || mode != Mode::Escape {
+            return Err("--stabilize-colors steadies the histogram of --coloring histogram; the \
+                        other colorings spread escape times the same way in every frame"
+                .to_string());
+        }
+        if frame_parallelism > 1 {
+            return Err("--stabilize-colors colors every frame from the ones before, so it \
+                        can't be used with --frame-parallelism"
+                .to_string());
+        }
+        if shard.is_some() {
+            return Err("--stabilize-colors colors every frame from the ones before, so it \
+                        can't be used with --shard-index"
+                .to_string());
+        }
+    }
+    if preview_every.is_some() && !pipe_video {
+        return Err("--preview-every is only available with --pipe-video".to_string());
+    }
+    if preview_progressive.is_some() {
+        if mode != Mode::Escape {
+            return Err("--preview-progressive is only available with --mode escape".to_string());
+        }
+        if !export.png {
+            return Err("--preview-progressive shows the colored frame, so it needs png in \
+                        --export"
+                .to_string());
+        }
+        if frame_parallelism > 1 {
+            return Err("--preview-progressive shows one frame at a time, so it can't be used \
+                        with --frame-parallelism"
+                .to_string());
+        }
+    }
+    if term_preview.is_some() && mode == Mode::Escape && !export.png {
+        return Err("--term-preview shows the colored frame, so it needs png in --export"
+            .to_string());
+    }
+    if shutter_angle.is_some() && motion_blur.is_none() {
+        return Err("--shutter-angle is only available with --motion-blur".to_string());
+    }
+    let motion_blur = motion_blur.map(|sub_frames| MotionBlur {
+        sub_frames,
+        shutter_angle: shutter_angle.unwrap_or(180.0),
+    });
+    if motion_blur.is_some() {
+        if mode != Mode::Escape {
+            return Err("--motion-blur is only available with --mode escape".to_string());
+        }
+        // Each of them keeps a single buffer of samples, where a blurred
+        // frame is made from several.
+        if adaptive.is_some() || dump_iterations || debug_channels.any() || export.exr {
+            return Err("--motion-blur averages the colors of its sub-frames, so it can't be used \
+                        with --adaptive, --dump-iterations, --debug-channels or exr in --export"
+                .to_string());
+        }
+    }
+    if debug_channels.any() && mode != Mode::Escape {
+        return Err("--debug-channels is only available with --mode escape".to_string());
+    }
+    let iterations = escape_times || matches!(coloring, Coloring::Script(_));
+    if debug_channels.iter && (!iterations || fractal == FractalKind::Lyapunov) {
+        return Err("--debug-channels iter needs escape times, so it can't be used with --fractal \
+                    lyapunov or distance, trap or stripes coloring"
+            .to_string());
+    }
+    if debug_channels.samples && adaptive.is_none() {
+        return Err("--debug-channels samples is only available with --adaptive".to_string());
+    }
+    if expmap {
+        // Strips are rendered in f64 from the one center, and frames are
+        // resampled from their colors, so only what works on one pixel at
+        // a time of a frame along a plain zoom goes with it.
+        let unused = [
+            "keyframes",
+            "camera-path",
+            "motion-blur",
+            "incremental",
+            "keyframe-every",
+            "adaptive",
+            "supersample",
+            "subdivide",
+            "show-subdivision",
+            "work-unit",
+            "chunk-size",
+            "lighting",
+            "contours",
+            "silhouette",
+            "style",
+            "palette-drift",
+            "stabilize-colors",
+            "iter-schedule",
+            "time-budget",
+            "dump-iterations",
+            "debug-channels",
+            "frame-stats",
+            "measure-dimension",
+            "preview-progressive",
+            "flip-y",
+            "force-precision",
+            "allow-precision-loss",
+            "series-terms",
+            "alpha",
+        ];
+        if let Some(flag) = unused.iter().find(|flag| uses_flag(args, &[flag])) {
+            return Err(format!("--{} doesn't apply to --expmap", flag));
+        }
+        let built_in = matches!(fractal, FractalKind::Mandelbrot | FractalKind::Tricorn);
+        if !built_in || mode != Mode::Escape {
+            return Err("--expmap renders escape times, so it's only available with --mode \
+                        escape and the Mandelbrot or Tricorn set"
+                .to_string());
+        }
+        if !matches!(
+            coloring,
+            Coloring::EscapeTime | Coloring::Smooth | Coloring::Phase | Coloring::Binary(_)
+        ) {
+            return Err(format!(
+                "--expmap colors every point on its own, so it can't be used with {} coloring",
+                coloring.name()
+            ));
+        }
+        if !matches!(precision, Precision::Auto | Precision::F64) {
+            return Err("--expmap renders its strips in f64".to_string());
+        }
+        if colors.palettes().len() > 1 || export.exr {
+            return Err("--expmap saves one colored frame, so it can't be used with several \
+                        palettes or exr in --export"
+                .to_string());
+        }
+    }
+    if roi_fill && roi.is_none() {
+        return Err("--roi-fill is only available with --roi".to_string());
+    }
+    let roi = roi.map(|roi| Roi {
+        fill: roi_fill,
+        ..roi
+    });
+    if let Some(roi) = roi {
+        let right = roi.x.checked_add(roi.width).filter(|&right| right <= width);
+        let bottom = roi.y.checked_add(roi.height).filter(|&bottom| bottom <= height);
+        if right.is_none() || bottom.is_none() {
+            return Err(format!(
+                "roi {},{},{},{} reaches past the {}x{} frame",
+                roi.x, roi.y, roi.width, roi.height, width, height
+            ));
+        }
+        if mode != Mode::Escape {
+            return Err("--roi computes a region of the escape times, so it's only available \
+                        with --mode escape"
+                .to_string());
+        }
+        // Each of them works on whole frames: taking samples from the one
+        // before, previewing, calibrating, resampling or putting shards
+        // together.
+        let whole = [
+            ("incremental", incremental.is_some()),
+            ("preview-progressive", preview_progressive.is_some()),
+            ("time-budget", time_budget.is_some()),
+            ("expmap", expmap),
+            ("shard-index", shard.is_some()),
+        ];
+        if let Some((flag, _)) = whole.iter().find(|(_, used)| *used) {
+            return Err(format!("--roi can't be used with --{}, which works on whole frames", flag));
+        }
+    }
+    if !outputs.is_empty() {
+        // The other outputs are made from the colored frames as they're
+        // saved, whole, into a directory of their own.
+        let whole = [
+            ("roi", roi.is_some()),
+            ("pipe-video", pipe_video),
+            ("shard-index", shard.is_some()),
+        ];
+        if let Some((flag, _)) = whole.iter().find(|(_, used)| *used) {
+            return Err(format!(
+                "--outputs can't be used with --{}, as it makes its sizes from whole frames \
+                 as they're saved",
+                flag
+            ));
+        }
+        if colors.palettes().len() > 1 {
+            return Err("--outputs can't be used with several palettes".to_string());
+        }
+        if outputs.len() > 1 && video.output.is_some() {
+            return Err("--video-out names one video, but --outputs makes one of every size; \
+                        they're saved with their frames"
+                .to_string());
+        }
+        if mode == Mode::Escape && !export.png {
+            return Err("--outputs makes its sizes from the colored frames, so it needs png in \
+                        --export"
+                .to_string());
+        }
+    }
+    if resume {
+        // Frames are only kept as PNGs, and piped frames aren't saved.
+        if pipe_video || (mode == Mode::Escape && !export.png) {
+            return Err("--resume needs the frames saved as PNGs, without --pipe-video".to_string());
+        }
+        if video.overwrite {
+            return Err("--resume keeps the frames saved already, so it can't --overwrite them"
+                .to_string());
+        }
+        // The video is encoded again with every frame.
+        video.overwrite = true;
+    }
+    // A length and a depth give the frames in place of zoom_start, zoom_end
+    // and zoom_factor, and the video plays them at --fps.
+    let target = match (target_magnification, duration) {
+        (Some(magnification), Some(seconds)) => {
+            if !positional.is_empty() || frame_flags[1..].iter().any(Option::is_some) {
+                return Err("--target-magnification and --duration give the frames, so they \
+                            can't be used with --zoom-start, --zoom-end, --zoom-factor or the \
+                            positional arguments"
+                    .to_string());
+            }
+            if keyframes.is_some() {
+                return Err("--keyframes gives the magnifications, so it can't be used with \
+                            --target-magnification"
+                    .to_string());
+            }
+            let fps = encoder.unwrap_or(EncoderKind::Ffmpeg).frame_rate(&video);
+            // The video of --direction in-out plays the frames back without
+            // the last twice, so its frames only take half the duration,
+            // and a boomerang leaves out the first too. A hold takes its
+            // seconds off the rest.
+            let sequence = video.sequence.unwrap_or_default();
+            let shown = (seconds * fps).round() - sequence.held(fps) as f64;
+            let frames = match (direction, sequence) {
+                (Direction::InOut, _) => ((shown + 1.0) / 2.0).floor(),
+                (_, Sequence::Boomerang) => ((shown + 2.0) / 2.0).floor(),
+                _ => shown,
+            };
+            if frames < 2.0 {
+                return Err(format!(
+                    "--duration {}s at {} fps leaves fewer than 2 frames to zoom over",
+                    seconds, fps
+                ));
+            }
+            if frames > u32::MAX as f64 {
+                return Err(format!(
+                    "--duration {}s at {} fps is too many frames",
+                    seconds, fps
+                ));
+            }
+            let frames = frames as u32;
+            Some(ZoomTarget {
+                magnification,
+                seconds,
+                fps,
+                frames,
+                zoom_factor: magnification.powf(1.0 / (frames - 1) as f64),
+            })
+        }
+        (None, None) => None,
+        _ => {
+            return Err("--target-magnification and --duration give the frames together, so \
+                        each needs the other"
+                .to_string());
+        }
+    };
+    let given_as_flags = frame_flags.iter().any(Option::is_some) && target.is_none();
+    if !positional.is_empty() && given_as_flags {
+        return Err("the frames are given either as the four positional arguments or with \
+                    --max-iter, --zoom-start, --zoom-end and --zoom-factor, not both"
+            .to_string());
+    }
+    let frames: Option<Vec<&str>> = match given_as_flags {
+        true => {
+            let names = ["max-iter", "zoom-start", "zoom-end", "zoom-factor"];
+            if let Some(missing) = frame_flags.iter().position(Option::is_none) {
+                return Err(format!(
+                    "--{} is missing; the frames are given with all of --max-iter, --zoom-start, \
+                     --zoom-end and --zoom-factor",
+                    names[missing]
+                ));
+            }
+            Some(frame_flags.iter().flatten().map(String::as_str).collect())
+        }
+        false => (!positional.is_empty()).then(|| positional.clone()),
+    };
+    // The limit of a place zoomed into, as --quality caps it.
+    let place_max_iter = |limit| quality.map_or(limit, |quality| quality.max_iter(Some(limit)));
+    let (max_iter, zoom_start, zoom_end, zoom_factor) = match (&target, preset, &location, frames) {
+        // The frames of the target, the first at magnification 1, and the
+        // limit of --max-iter or else of the preset or location.
+        (Some(target), _, _, _) => {
+            let max_iter = match (&frame_flags[0], preset, &location) {
+                (Some(max_iter), _, _) => max_iter
+                    .parse()
+                    .map_err(|_| "max_iter should be an integer".to_string())?,
+                (None, Some(preset), _) => place_max_iter(preset.max_iter),
+                (None, _, Some(LocationArg { location, .. })) => place_max_iter(location.max_iter),
+                (None, None, None) => match quality {
+                    Some(quality) => quality.max_iter(None),
+                    None => {
+                        return Err("--max-iter is missing; it's needed with \
+                                    --target-magnification unless --preset, --location or \
+                                    --quality gives it"
+                            .to_string());
+                    }
+                },
+            };
+            (max_iter, 0, target.frames, target.zoom_factor)
+        }
+        // The preset's limit, and frames enough to reach its depth.
+        (None, Some(preset), _, None) => {
+            let frames = preset.frames(preset::ZOOM_FACTOR);
+            (place_max_iter(preset.max_iter), 0, frames, preset::ZOOM_FACTOR)
+        }
+        (None, _, Some(LocationArg { location, .. }), None) => {
+            let frames = location.frames(preset::ZOOM_FACTOR);
+            (place_max_iter(location.max_iter), 0, frames, preset::ZOOM_FACTOR)
+        }
+        (None, _, _, frames) => {
+            let frames = frames.unwrap_or_default();
+            if frames.len() != 4 {
+                let optional = match (preset, &location) {
+                    (Some(_), _) => ", or none with --preset",
+                    (_, Some(_)) => ", or none with --location",
+                    _ => "",
+                };
+                return Err(format!(
+                    "expected the frames, as --max-iter N --zoom-start A --zoom-end B \
+                     --zoom-factor F or as 4 positional arguments{}, got {} positional \
+                     arguments\n{}",
+                    optional,
+                    frames.len(),
+                    USAGE
+                ));
+            }
+            let max_iter: u32 = frames[0]
+                .parse()
+                .map_err(|_| "max_iter should be an integer".to_string())?;
+            let zoom_start: u32 = frames[1]
+                .parse()
+                .map_err(|_| "zoom_start should be an integer".to_string())?;
+            let zoom_end: u32 = frames[2]
+                .parse()
+                .map_err(|_| "zoom_end should be an integer".to_string())?;
+            let zoom_factor: f64 = frames[3]
+                .parse()
+                .map_err(|_| "zoom_factor should be a float".to_string())?;
+            (max_iter, zoom_start, zoom_end, zoom_factor)
+        }
+    };
+    // Frames run from zoom_start up to zoom_end either way the camera goes,
+    // so a zoom out isn't given backwards.
+    if zoom_start > zoom_end {
+        return Err(format!(
+            "zoom_start {} is after zoom_end {}; the frames go from zoom_start up to zoom_end, \
+             so swap them, and zoom out with --direction out or a zoom_factor below 1",
+            zoom_start, zoom_end
+        ));
+    }
+    if zoom_start == zoom_end {
+        return Err(format!(
+            "zoom_start and zoom_end are both {}, which leaves no frames; zoom_end is the frame \
+             after the last",
+            zoom_start
+        ));
+    }
+    if !(zoom_factor > 0.0 && zoom_factor.is_finite()) {
+        return Err(format!(
+            "zoom_factor should be positive, and below 1 to zoom out, got {}",
+            zoom_factor
+        ));
+    }
+    // Only orbits escaping from min_iter on and before the limit are
+    // plotted, so a limit at or below min_iter leaves the frame black.
+    let (limit, limited_by) = match bands {
+        Some([r, g, b]) => (r.min(g).min(b), "the lowest of --bands"),
+        None => (max_iter, "max_iter"),
+    };
+    if mode != Mode::Escape && min_iter >= limit {
+        return Err(format!(
+            "min-iter {} should be below {} {}, or no orbit is plotted",
+            min_iter, limited_by, limit
+        ));
+    }
+    // Zooming out runs into the interior of the set only at the start, if
+    // at all, and uniform frames there mean nothing about the rest.
+    let outward = (zoom_factor < 1.0) != (direction == Direction::Out);
+    let early_stop = early_stop.filter(|_| !outward);
+    if direction == Direction::Out && keyframes.is_some() {
+        return Err("--keyframes gives the camera path, which can go out as well as in, so \
+                    --direction out can't be used with it"
+            .to_string());
+    }
+    if direction == Direction::Out && camera_path.is_some() {
+        return Err("--camera-path gives the view of every frame, which can go out as well as \
+                    in, so --direction out can't be used with it"
+            .to_string());
+    }
+    if let Some(path) = &camera_path {
+        let rows = path.frames();
+        if rows.start > zoom_start || rows.end < zoom_end {
+            let missing = match rows.start > zoom_start {
+                true => zoom_start,
+                false => rows.end,
+            };
+            return Err(format!(
+                "{} has rows for frames {} to {}, but the run renders frames {} to {}, so the \
+                 row for frame {} is missing",
+                path.file,
+                rows.start,
+                rows.end - 1,
+                zoom_start,
+                zoom_end - 1,
+                missing
+            ));
+        }
+    }
+    let pan = match (pan_from, pan_to) {
+        (Some(from), Some(to)) => {
+            // The waypoints place the camera in every frame at the one
+            // magnification, and the view only ever moves by whole pixels.
+            let placing = [
+                "keyframes",
+                "camera-path",
+                "center",
+                "x-range",
+                "y-range",
+                "preset",
+                "location",
+                "target-magnification",
+                "motion-blur",
+                "initial-rotation",
+                "rotation-per-frame",
+                "incremental-threshold",
+                "time-budget",
+                "expmap",
+            ];
+            if let Some(flag) = placing.iter().find(|flag| uses_flag(args, &[flag])) {
+                return Err(format!(
+                    "--pan-from and --pan-to give the view of every frame, so they can't be \
+                     used with --{}",
+                    flag
+                ));
+            }
+            if zoom_factor != 1.0 {
+                return Err(format!(
+                    "a pan keeps the magnification, so zoom_factor should be 1, got {}",
+                    zoom_factor
+                ));
+            }
+            if direction == Direction::Out {
+                return Err("a pan goes from --pan-from to --pan-to, so --direction out can't be \
+                            used with it; swap them instead"
+                    .to_string());
+            }
+            let view_width = view_width.ok_or("a pan needs --view-width, the width of its view")?;
+            let mut waypoints = vec![from];
+            waypoints.extend(pan_via);
+            waypoints.push(to);
+            Some(Pan {
+                waypoints,
+                view_width,
+                refresh: refresh_band.unwrap_or(2),
+            })
+        }
+        (None, None) => {
+            if !pan_via.is_empty() || view_width.is_some() || refresh_band.is_some() {
+                return Err("--pan-via, --view-width and --refresh-band are only available with \
+                            --pan-from and --pan-to"
+                    .to_string());
+            }
+            None
+        }
+        _ => {
+            return Err("--pan-from and --pan-to give the ends of a pan together, so each needs \
+                        the other"
+                .to_string());
+        }
+    };
+    if direction == Direction::InOut {
+        if video.sequence == Some(Sequence::Boomerang) {
+            return Err("--direction in-out plays the frames back already, so it can't be used \
+                        with --video-sequence boomerang"
+                .to_string());
+        }
+        if pipe_video {
+            return Err("--direction in-out plays the saved frames back, so it can't be used \
+                        with --pipe-video"
+                .to_string());
+        }
+        video.there_and_back = true;
+    }
+    if let Some(schedule) = &iter_schedule {
+        if auto_iter {
+            return Err("--iter-schedule gives every frame's max_iter, so it can't be used with \
+                        --auto-iter"
+                .to_string());
+        }
+        if keyframes.iter().flatten().any(|keyframe| keyframe.max_iter.is_some()) {
+            return Err("--iter-schedule gives every frame's max_iter, so it can't be used with \
+                        keyframes that set max_iter"
+                .to_string());
+        }
+        if camera_path.iter().flat_map(|path| &path.rows).any(|row| row.max_iter.is_some()) {
+            return Err("--iter-schedule gives every frame's max_iter, so it can't be used with \
+                        a camera path that sets max_iter"
+                .to_string());
+        }
+        if schedule.first_frame() > zoom_start {
+            return Err(format!(
+                "the iteration schedule starts at frame {}, after the first frame {}; it needs a \
+                 row for frame {} or before",
+                schedule.first_frame(),
+                zoom_start,
+                zoom_start
+            ));
+        }
+    }
+
+    if zoom_end.saturating_sub(zoom_start) > 1 && !filenames.has_frame() {
+        return Err(format!(
+            "--filename-template '{}' needs {{frame}} to tell the files of the {} frames apart",
+            filenames,
+            zoom_end - zoom_start
+        ));
+    }
+    if filenames.has_timestamp() && (resume || shard.is_some()) {
+        return Err("{timestamp} in --filename-template differs from run to run, so it can't be \
+                    used with --resume or --shard-index"
+            .to_string());
+    }
+    let started = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs());
+    let name = run_name.as_deref().unwrap_or(fractal.name());
+    let filenames = filenames.bind(name, &template::timestamp(started));
+
+    Ok(Args {
+        max_iter,
+        zoom_start,
+        zoom_end,
+        zoom_factor,
+        target,
+        fractal,
+        newton,
+        lyapunov,
+        formula,
+        c_path,
+        c_easing: c_easing.unwrap_or(Easing::Linear),
+        precision,
+        series_terms,
+        periodicity,
+        subdivision,
+        work_units,
+        incremental,
+        supersample,
+        adaptive,
+        coloring,
+        interior_coloring,
+        color_expr,
+        colors,
+        stabilize_colors,
+        mode,
+        samples,
+        sampler,
+        seed,
+        min_iter,
+        tone,
+        bands,
+        // Budgeted limits grow with depth as auto-iter's do.
+        auto_iter: (auto_iter || time_budget.is_some()).then_some(iter_growth),
+        iter_schedule,
+        dry_run,
+        yes,
+        bailout,
+        center,
+        keyframes,
+        camera_path,
+        pan,
+        location,
+        save_location,
+        location_name,
+        quality,
+        easing,
+        direction,
+        motion_blur,
+        expmap,
+        initial_rotation,
+        rotation_per_frame,
+        ranges,
+        view,
+        zoom_to,
+        fit,
+        width,
+        height,
+        roi,
+        outputs,
+        flip_y,
+        export,
+        image_format,
+        alpha,
+        dump_iterations,
+        frame_stats,
+        measure_dimension,
+        debug_channels,
+        early_stop,
+        pipe_video,
+        preview_every,
+        preview_progressive,
+        term_preview,
+        term_protocol,
+        dashboard,
+        post,
+        hud,
+        julia_inset,
+        rays,
+        encoder,
+        video,
+        no_video,
+        output_dir: match run_name {
+            Some(name) => format!("{}/{}", output_dir.trim_end_matches('/'), name),
+            None => output_dir,
+        },
+        filenames,
+        resume,
+        progress_format,
+        frame_parallelism,
+        max_memory,
+        allow_precision_loss,
+        threads,
+        background,
+        time_budget,
+        fit_supersample,
+        shard,
+        assemble,
+    })
+}
+
+/// Parses the coloring of `--coloring`, a name or `binary:K` for binary
+/// decomposition into `2^K` sectors.
+fn parse_coloring(spec: &str) -> Result<Coloring, String> {
+    if let Some(sectors) = spec.strip_prefix("binary:") {
+        let sectors: u32 = sectors
+            .parse()
+            .map_err(|_| format!("binary coloring takes binary:K, got '{}'", spec))?;
+        if !(1..=MAX_BINARY_SECTORS).contains(&sectors) {
+            return Err(format!(
+                "binary:K splits the angles into 2^K sectors, K from 1 to {}, got {}",
+                MAX_BINARY_SECTORS, sectors
+            ));
+        }
+        return Ok(Coloring::Binary(sectors));
+    }
+    Coloring::from_name(spec).ok_or_else(|| format!("unknown coloring '{}'", spec))
+}
+
+/// Parses `--alpha`, one of `none`, `interior` or `threshold:V`.
+fn parse_alpha(spec: &str) -> Result<Alpha, String> {
+    if let Some(threshold) = spec.strip_prefix("threshold:") {
+        return match threshold.parse::<f64>() {
+            Ok(threshold) if threshold.is_finite() && threshold >= 0.0 => {
+                Ok(Alpha::Threshold(threshold))
+            }
+            _ => Err(format!(
+                "alpha threshold:V takes an iteration count of at least 0, got '{}'",
+                spec
+            )),
+        };
+    }
+    match spec {
+        "none" => Ok(Alpha::None),
+        "interior" => Ok(Alpha::Interior),
+        _ => Err(format!("alpha should be none, interior or threshold:V, got '{}'", spec)),
+    }
+}
+
+/// Parses the options of `recolor`, not including the subcommand, whose
+/// directory is `default_dir` unless given.
+pub fn parse_recolor(args: &[String], default_dir: &str) -> Result<RecolorArgs, String> {
+    let mut coloring = None;
+    let mut colors = ColorArgs::default();
+    let mut encoder = None;
+    let mut video = VideoOptions::default();
+    let mut no_video = false;
+
+    let positional = split_args(args, |name, value| {
+        match name {
+            "coloring" => {
+                let value = value()?;
+                let parsed = Coloring::from_name(&value)
+                    .ok_or_else(|| format!("unknown coloring '{}'", value))?;
+                coloring = Some(parsed);
+            }
+            "encoder" => encoder = Some(parse_encoder(&value()?)?),
+            "no-video" => no_video = true,
+            _ => {
+                if !colors.flag(name, value)? && !video_flag(&mut video, name, value)? {
+                    return Err(format!("unknown flag --{}", name));
+                }
+            }
+        }
+        Ok(())
+    })?;
+    check_video_flags(args, encoder, no_video)?;
+    colors.single_palette("recolor")?;
+    colors.check_map_interpolate()?;
+    colors.check_line_art()?;
+    if colors.line_art().is_some_and(|lines| lines.interior) {
+        return Err("the dumped values don't keep the cycles --line-interior draws between"
+            .to_string());
+    }
+    if colors.line_art().is_some_and(|lines| lines.paper == Paper::Transparent) {
+        return Err("recolor saves frames without an alpha channel, so --line-paper can't be \
+                    transparent"
+            .to_string());
+    }
+    video.background = colors.interior;
+
+    let dir = match positional[..] {
+        [] => default_dir.to_string(),
+        [dir] => dir.to_string(),
+        _ => {
+            return Err(format!(
+                "recolor takes at most one directory, got {} positional arguments\n{}",
+                positional.len(),
+                USAGE
+            ))
+        }
+    };
+    Ok(RecolorArgs {
+        dir,
+        coloring,
+        colors,
+        encoder,
+        video,
+        no_video,
+    })
+}
+
+/// Parses the options of `assemble`, not including the subcommand, whose
+/// directory is `default_dir` unless given.
+pub fn parse_assemble(args: &[String], default_dir: &str) -> Result<AssembleArgs, String> {
+    let mut palette = None;
+    let mut encoder = None;
+    let mut video = VideoOptions::default();
+    let mut check = FrameCheck::default();
+    let mut allow_gaps = false;
+
+    let positional = split_args(args, |name, value| {
+        match name {
+            "palette" => palette = Some(value()?),
+            "encoder" => encoder = Some(parse_encoder(&value()?)?),
+            "allow-gaps" => allow_gaps = true,
+            _ => {
+                if !check.flag(name) && !video_flag(&mut video, name, value)? {
+                    return Err(format!("unknown flag --{}", name));
+                }
+            }
+        }
+        Ok(())
+    })?;
+    check_video_flags(args, encoder, false)?;
+    let dir = match positional[..] {
+        [] => default_dir.to_string(),
+        [dir] => dir.to_string(),
+        _ => {
+            return Err(format!(
+                "assemble takes the directory of one render, got {} positional arguments\n{}",
+                positional.len(),
+                USAGE
+            ))
+        }
+    };
+    Ok(AssembleArgs {
+        dir,
+        palette,
+        encoder,
+        video,
+        check,
+        allow_gaps,
+    })
+}
+
+/// Parses the options of `verify`, not including the subcommand, whose
+/// directory is `default_dir` unless given.
+pub fn parse_verify(args: &[String], default_dir: &str) -> Result<VerifyArgs, String> {
+    let mut palette = None;
+    let mut check = FrameCheck::default();
+
+    let positional = split_args(args, |name, value| {
+        match name {
+            "palette" => palette = Some(value()?),
+            _ => {
+                if !check.flag(name) {
+                    return Err(format!("unknown flag --{}", name));
+                }
+            }
+        }
+        Ok(())
+    })?;
+    let dir = match positional[..] {
+        [] => default_dir.to_string(),
+        [dir] => dir.to_string(),
+        _ => {
+            return Err(format!(
+                "verify takes the directory of one render, got {} positional arguments\n{}",
+                positional.len(),
+                USAGE
+            ))
+        }
+    };
+    Ok(VerifyArgs {
+        dir,
+        palette,
+        check,
+    })
+}
+
+/// The most thumbnails across a row of a montage.
+const MAX_MONTAGE_COLUMNS: u32 = 64;
+
+/// Parses the options of `montage`, not including the subcommand.
+pub fn parse_montage(args: &[String], default_dir: &str) -> Result<MontageArgs, String> {
+    let mut manifest = None;
+    let mut palette = None;
+    let mut every = None;
+    let mut columns = 8;
+    let mut thumbnail = 160;
+    let mut max_size = 4096;
+    let mut output = None;
+    let mut overwrite = false;
+
+    let positional = split_args(args, |name, value| {
+        let mut integer = |range: std::ops::RangeInclusive<u32>| {
+            let value = value()?;
+            value.parse().ok().filter(|parsed| range.contains(parsed)).ok_or_else(|| {
+                format!(
+                    "{} should be an integer from {} to {}, got '{}'",
+                    name,
+                    range.start(),
+                    range.end(),
+                    value
+                )
+            })
+        };
+        match name {
+            "every" => every = Some(integer(1..=u32::MAX)?),
+            "columns" => columns = integer(1..=MAX_MONTAGE_COLUMNS)?,
+            "thumbnail" => thumbnail = integer(16..=1024)?,
+            "max-size" => max_size = integer(64..=16384)?,
+            "manifest" => manifest = Some(value()?),
+            "palette" => palette = Some(value()?),
+            "output" => output = Some(value()?),
+            "overwrite" => overwrite = true,
+            _ => return Err(format!("unknown flag --{}", name)),
+        }
+        Ok(())
+    })?;
+
+    let dir = match (&positional[..], &manifest) {
+        ([], None) => default_dir.to_string(),
+        ([], Some(manifest)) => match Path::new(manifest).parent().and_then(Path::to_str) {
+            Some("") | None => ".".to_string(),
+            Some(dir) => dir.to_string(),
+        },
+        ([dir], None) => dir.to_string(),
+        ([_], Some(_)) => {
+            return Err("montage takes the directory of the frames or --manifest, not both"
+                .to_string())
+        }
+        _ => {
+            return Err(format!(
+                "montage takes one directory of frames, got {} positional arguments\n{}",
+                positional.len(),
+                USAGE
+            ))
+        }
+    };
+    if output.as_ref().is_some_and(|output| !output.ends_with(".png")) {
+        return Err("--output is where the montage is saved as a PNG, so it should end in .png"
+            .to_string());
+    }
+    Ok(MontageArgs {
+        dir,
+        manifest,
+        palette,
+        every,
+        columns,
+        thumbnail,
+        max_size,
+        output,
+        overwrite,
+    })
+}
+
+/// Parses the options of `render-frame`, not including the subcommand.
+pub fn parse_render_frame(args: &[String]) -> Result<RenderFrameArgs, String> {
+    let mut manifest = None;
+    let mut frame = None;
+    let mut scale = 1;
+    let mut samples = None;
+    let mut output = None;
+    let mut overwrite = false;
+
+    let positional = split_args(args, |name, value| {
+        match name {
+            "manifest" => manifest = Some(value()?),
+            "frame" => {
+                frame = Some(
+                    value()?
+                        .parse()
+                        .map_err(|_| "frame should be an integer".to_string())?,
+                );
+            }
+            "scale" => {
+                scale = value()?
+                    .parse()
+                    .map_err(|_| "scale should be an integer".to_string())?;
+                if !(1..=16).contains(&scale) {
+                    return Err("scale should be between 1 and 16".to_string());
+                }
+            }
+            "samples" => {
+                let parsed = value()?
+                    .parse()
+                    .map_err(|_| "samples should be an integer".to_string())?;
+                if !(1..=4).contains(&parsed) {
+                    return Err("samples should be between 1 and 4".to_string());
+                }
+                samples = Some(parsed);
+            }
+            "output" => output = Some(value()?),
+            "overwrite" => overwrite = true,
+            _ => return Err(format!("unknown flag --{}", name)),
+        }
+        Ok(())
+    })?;
+
+    if !positional.is_empty() {
+        return Err(format!(
+            "render-frame takes no positional arguments, got {}\n{}",
+            positional.len(),
+            USAGE
+        ));
+    }
+    let (Some(manifest), Some(frame)) = (manifest, frame) else {
+        return Err("render-frame needs the run's manifest and the frame, like --manifest \
+                    rust_data/manifest.json --frame 12"
+            .to_string());
+    };
+    if output.as_ref().is_some_and(|output| !output.ends_with(".png")) {
+        return Err("--output is where the frame is saved as a PNG, so it should end in .png"
+            .to_string());
+    }
+    if overwrite && output.is_none() {
+        return Err("--overwrite is for --output; the frame is written over the run's own \
+                    without it"
+            .to_string());
+    }
+    Ok(RenderFrameArgs {
+        manifest,
+        frame,
+        scale,
+        samples,
+        output,
+        overwrite,
+    })
+}
+
+/// Parses the options of `validate`, not including the subcommand. The
+/// arguments it doesn't know are those of the render the frame is of.
+pub fn parse_validate(args: &[String]) -> Result<ValidateArgs, String> {
+    let mut manifest = None;
+    let mut frame = None;
+    let mut backends = Vec::new();
+    let mut threshold = difference::DEFAULT_THRESHOLD;
+    let mut tolerance = difference::DEFAULT_TOLERANCE;
+    let mut heatmaps = ".".to_string();
+    let mut overwrite = false;
+
+    let mut own = Vec::new();
+    let mut render = Vec::new();
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        let name = arg.strip_prefix("--").map(|name| name.split('=').next().unwrap_or(name));
+        match name {
+            Some("manifest" | "frame" | "backends" | "threshold" | "tolerance" | "heatmaps") => {
+                own.push(arg.clone());
+                if !arg.contains('=') {
+                    own.extend(rest.next().cloned());
+                }
+            }
+            Some("overwrite") => own.push(arg.clone()),
+            _ => render.push(arg.clone()),
+        }
+    }
+    split_args(&own, |name, value| {
+        match name {
+            "manifest" => manifest = Some(value()?),
+            "frame" => {
+                frame = Some(
+                    value()?
+                        .parse()
+                        .map_err(|_| "frame should be an integer".to_string())?,
+                );
+            }
+            "backends" => {
+                for name in value()?.split(',') {
+                    let backend = match Precision::from_name(name) {
+                        Some(Precision::Auto) => {
+                            return Err("validate compares the backends it's given, and auto \
+                                        isn't one; name them, like --backends f64,dd"
+                                .to_string())
+                        }
+                        Some(backend) => backend,
+                        None => {
+                            return Err(format!(
+                                "unknown backend '{}', the backends are f32, f64, dd, perturb \
+                                 and big",
+                                name
+                            ))
+                        }
+                    };
+                    if backends.contains(&backend) {
+                        return Err(format!("--backends names {} twice", backend.name()));
+                    }
+                    backends.push(backend);
+                }
+            }
+            "threshold" => {
+                let value = value()?;
+                let iterations = value.parse().ok().filter(|iterations: &f64| *iterations > 0.0);
+                threshold = iterations.ok_or_else(|| {
+                    format!("threshold should be a positive number of iterations, got '{}'", value)
+                })?;
+            }
+            "tolerance" => {
+                let value = value()?;
+                let share = value.parse().ok().filter(|share| (0.0..=1.0).contains(share));
+                tolerance = share.ok_or_else(|| {
+                    format!("tolerance should be a share of samples from 0 to 1, got '{}'", value)
+                })?;
+            }
+            "heatmaps" => heatmaps = value()?,
+            "overwrite" => overwrite = true,
+            _ => unreachable!("only validate's own flags are split off"),
+        }
+        Ok(())
+    })?;
+
+    let Some(frame) = frame else {
+        return Err("validate needs the frame to compare, like --manifest rust_data/manifest.json \
+                    --frame 12, or --frame 12 and the arguments of the render it's of"
+            .to_string());
+    };
+    let source = match (manifest, render.is_empty()) {
+        (Some(manifest), true) => FrameSource::Manifest(manifest),
+        (None, false) => FrameSource::Render(render),
+        (Some(_), false) => {
+            return Err(format!(
+                "validate takes the frame from --manifest or from the arguments of a render, \
+                 not both, got {}",
+                render.join(" ")
+            ))
+        }
+        (None, true) => {
+            return Err("validate needs the run the frame is of, as its --manifest or the \
+                        arguments of its render"
+                .to_string())
+        }
+    };
+    if backends.len() < 2 {
+        return Err("validate needs at least two backends to compare, like --backends \
+                    f64,dd,perturb"
+            .to_string());
+    }
+    Ok(ValidateArgs {
+        source,
+        frame,
+        backends,
+        threshold,
+        tolerance,
+        heatmaps,
+        overwrite,
+    })
+}
+
+/// Parses the options of `merge`, not including the subcommand.
+pub fn parse_merge(args: &[String]) -> Result<MergeArgs, String> {
+    let mut output_dir = "rust_data".to_string();
+    let mut encoder = None;
+    let mut video = VideoOptions::default();
+    let mut no_video = false;
+
+    let positional = split_args(args, |name, value| {
+        match name {
+            "output-dir" => output_dir = value()?,
+            "encoder" => encoder = Some(parse_encoder(&value()?)?),
+            "no-video" => no_video = true,
+            _ => {
+                if !video_flag(&mut video, name, value)? {
+                    return Err(format!("unknown flag --{}", name));
+                }
+            }
+        }
+        Ok(())
+    })?;
+    check_video_flags(args, encoder, no_video)?;
+    if positional.is_empty() {
+        return Err(format!(
+            "merge takes the output directories or manifests of the shards\n{}",
+            USAGE
+        ));
+    }
+    Ok(MergeArgs {
+        shards: positional.into_iter().map(str::to_string).collect(),
+        output_dir,
+        encoder,
+        video,
+        no_video,
+    })
+}
+
+/// Parses the options of `find-target`, not including the subcommand.
+#[cfg(feature = "bigfloat")]
+pub fn parse_find_target(args: &[String]) -> Result<TargetArgs, String> {
+    let mut fractal = FractalKind::Mandelbrot;
+    let mut center = None;
+    let mut depth = 1e6;
+    let mut max_iter = 500;
+    let mut seed = 0;
+    let mut contact = "rust_data/find_target.png".to_string();
+    let mut save_location = None;
+    let mut location_name = None;
+
+    let positional = split_args(args, |name, value| {
+        match name {
+            "fractal" => fractal = escape_time_fractal(&value()?, "find-target")?,
+            "center" => center = Some(parse_center(&value()?)?),
+            "depth" => {
+                depth = value()?
+                    .parse()
+                    .map_err(|_| "depth should be a float".to_string())?;
+            }
+            "max-iter" => {
+                max_iter = value()?
+                    .parse()
+                    .map_err(|_| "max-iter should be an integer".to_string())?;
+            }
+            "seed" => {
+                seed = value()?
+                    .parse()
+                    .map_err(|_| "seed should be an integer".to_string())?;
+            }
+            "contact" => contact = value()?,
+            "save-location" => save_location = Some(value()?),
+            "location-name" => location_name = Some(value()?),
+            _ => return Err(format!("unknown flag --{}", name)),
+        }
+        Ok(())
+    })?;
+
+    if !positional.is_empty() {
+        return Err(format!(
+            "find-target takes no positional arguments, got {}\n{}",
+            positional.len(),
+            USAGE
+        ));
+    }
+    if location_name.is_some() && save_location.is_none() {
+        return Err("--location-name names the location of --save-location".to_string());
+    }
+    Ok(TargetArgs {
+        fractal,
+        center,
+        depth,
+        max_iter,
+        seed,
+        contact,
+        save_location,
+        location_name,
+    })
+}
+
+/// The most cells across or down a level of `survey`.
+#[cfg(feature = "bigfloat")]
+const MAX_SURVEY_GRID: usize = 16;
+
+/// Parses the options of `survey`, not including the subcommand.
+#[cfg(feature = "bigfloat")]
+pub fn parse_survey(args: &[String]) -> Result<SurveyArgs, String> {
+    let mut fractal = FractalKind::Mandelbrot;
+    let mut center = None;
+    let mut radius = None;
+    let mut grid = (4, 4);
+    let mut depth = 1;
+    let mut max_iter = 500;
+    let mut thumbnail = 96;
+    let mut output_dir = "rust_data".to_string();
+
+    let positional = split_args(args, |name, value| {
+        match name {
+            "fractal" => fractal = escape_time_fractal(&value()?, "survey")?,
+            "center" => center = Some(parse_center(&value()?)?),
+            "radius" => {
+                let value = value()?;
+                radius = Some(
+                    value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|radius| *radius > 0.0 && radius.is_finite())
+                        .ok_or_else(|| {
+                            format!("radius should be a positive number, got '{}'", value)
+                        })?,
+                );
+            }
+            "grid" => {
+                let value = value()?;
+                let parsed = value.split_once('x').and_then(|(columns, rows)| {
+                    Some((columns.trim().parse().ok()?, rows.trim().parse().ok()?))
+                });
+                grid = parsed
+                    .filter(|&(columns, rows)| {
+                        (1..=MAX_SURVEY_GRID).contains(&columns)
+                            && (1..=MAX_SURVEY_GRID).contains(&rows)
+                    })
+                    .ok_or_else(|| {
+                        format!(
+                            "grid should be COLUMNSxROWS of 1 to {} each, like 6x6, got '{}'",
+                            MAX_SURVEY_GRID, value
+                        )
+                    })?;
+            }
+            "depth" => {
+                depth = value()?
+                    .parse()
+                    .ok()
+                    .filter(|&depth| depth > 0)
+                    .ok_or_else(|| "depth should be a positive integer".to_string())?;
+            }
+            "max-iter" => {
+                max_iter = value()?
+                    .parse()
+                    .ok()
+                    .filter(|&max_iter| max_iter > 0)
+                    .ok_or_else(|| "max-iter should be a positive integer".to_string())?;
+            }
+            "thumbnail" => {
+                thumbnail = value()?
+                    .parse()
+                    .ok()
+                    .filter(|thumbnail| (16..=512).contains(thumbnail))
+                    .ok_or_else(|| "thumbnail should be 16 to 512 pixels".to_string())?;
+            }
+            "output-dir" => output_dir = value()?,
+            _ => return Err(format!("unknown flag --{}", name)),
+        }
+        Ok(())
+    })?;
+
+    if !positional.is_empty() {
+        return Err(format!(
+            "survey takes no positional arguments, got {}\n{}",
+            positional.len(),
+            USAGE
+        ));
+    }
+    Ok(SurveyArgs {
+        fractal,
+        center,
+        radius,
+        grid,
+        depth,
+        max_iter,
+        thumbnail,
+        output_dir,
+    })
+}
+
+/// The `render` options `animate-julia` stands for, from its own, not
+/// including the subcommand: `--frames N` becomes the frames 0 to N of a
+/// zoom that holds still unless given a `--zoom-factor`.
+pub fn animate_julia_args(args: &[String]) -> Result<Vec<String>, String> {
+    let mut render = Vec::new();
+    let mut frames = None;
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        match arg.strip_prefix("--frames") {
+            Some("") => frames = Some(rest.next().ok_or("--frames needs a value")?.clone()),
+            Some(value) if value.starts_with('=') => frames = Some(value[1..].to_string()),
+            _ => render.push(arg.clone()),
+        }
+    }
+    let frames = frames.ok_or("animate-julia needs --frames")?;
+    if !frames.parse::<u32>().is_ok_and(|frames| frames > 0) {
+        return Err(format!("frames should be a positive integer, got '{}'", frames));
+    }
+    if !uses_flag(&render, &["c-path"]) {
+        return Err("animate-julia needs --c-path".to_string());
+    }
+    if uses_flag(&render, &["zoom-start", "zoom-end"]) {
+        return Err("animate-julia renders --frames from frame 0, so it doesn't take \
+                    --zoom-start or --zoom-end"
+            .to_string());
+    }
+    render.extend(["--zoom-start", "0", "--zoom-end", &frames].map(String::from));
+    if !uses_flag(&render, &["zoom-factor"]) {
+        render.extend(["--zoom-factor", "1"].map(String::from));
+    }
+    if !uses_flag(&render, &["max-iter"]) {
+        render.extend(["--max-iter", "1000"].map(String::from));
+    }
+    Ok(render)
+}
+
+/// The `render` options `pan` stands for, from its own, not including the
+/// subcommand: `--from`, `--via` and `--to` become the waypoints of
+/// `--pan-from`, `--pan-via` and `--pan-to`, and `--frames N` the frames 0
+/// to N, each taken from the one before with `--incremental`.
+pub fn pan_args(args: &[String]) -> Result<Vec<String>, String> {
+    let mut render = Vec::new();
+    let mut frames = None;
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        let Some((name, inline)) = arg.strip_prefix("--").map(|flag| match flag.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (flag, None),
+        }) else {
+            render.push(arg.clone());
+            continue;
+        };
+        let mut value = || match &inline {
+            Some(value) => Ok(value.clone()),
+            None => rest.next().cloned().ok_or(format!("--{} needs a value", name)),
+        };
+        match name {
+            "frames" => frames = Some(value()?),
+            "from" | "via" | "to" => render.extend([format!("--pan-{}", name), value()?]),
+            _ => render.push(arg.clone()),
+        }
+    }
+    let frames = frames.ok_or("pan needs --frames")?;
+    if !frames.parse::<u32>().is_ok_and(|frames| frames > 0) {
+        return Err(format!("frames should be a positive integer, got '{}'", frames));
+    }
+    if !uses_flag(&render, &["pan-from"]) || !uses_flag(&render, &["pan-to"]) {
+        return Err("pan needs --from and --to".to_string());
+    }
+    if uses_flag(&render, &["zoom-start", "zoom-end", "zoom-factor"]) {
+        return Err("pan renders --frames from frame 0 at the one magnification, so it doesn't \
+                    take --zoom-start, --zoom-end or --zoom-factor"
+            .to_string());
+    }
+    let frames = ["--zoom-start", "0", "--zoom-end", &frames, "--zoom-factor", "1"];
+    render.extend(frames.map(String::from));
+    if !uses_flag(&render, &["incremental"]) {
+        render.push("--incremental".to_string());
+    }
+    if !uses_flag(&render, &["max-iter"]) {
+        render.extend(["--max-iter", "1000"].map(String::from));
+    }
+    Ok(render)
+}
+
+/// Parses the options of `find-nucleus`, not including the subcommand.
+#[cfg(feature = "bigfloat")]
+pub fn parse_find_nucleus(args: &[String]) -> Result<NucleusArgs, String> {
+    let mut near = None;
+    let mut radius = None;
+    let mut period = None;
+    let mut save_location = None;
+    let mut location_name = None;
+
+    let positional = split_args(args, |name, value| {
+        match name {
+            "near" => near = Some(parse_center(&value()?)?),
+            "radius" => {
+                let r: f64 = value()?
+                    .parse()
+                    .map_err(|_| "radius should be a float".to_string())?;
+                if !(r > 0.0 && r.is_finite()) {
+                    return Err(format!("radius should be positive, got {}", r));
+                }
+                radius = Some(r);
+            }
+            "period" => {
+                let p: u32 = value()?
+                    .parse()
+                    .map_err(|_| "period should be an integer".to_string())?;
+                if p == 0 {
+                    return Err("period should be at least 1".to_string());
+                }
+                period = Some(p);
+            }
+            "save-location" => save_location = Some(value()?),
+            "location-name" => location_name = Some(value()?),
+            _ => return Err(format!("unknown flag --{}", name)),
+        }
+        Ok(())
+    })?;
+
+    if !positional.is_empty() {
+        return Err(format!(
+            "find-nucleus takes no positional arguments, got {}\n{}",
+            positional.len(),
+            USAGE
+        ));
+    }
+    let near = near.ok_or_else(|| format!("find-nucleus needs --near\n{}", USAGE))?;
+    let radius = radius.ok_or_else(|| format!("find-nucleus needs --radius\n{}", USAGE))?;
+    if location_name.is_some() && save_location.is_none() {
+        return Err("--location-name names the location of --save-location".to_string());
+    }
+    Ok(NucleusArgs {
+        near,
+        radius,
+        period,
+        save_location,
+        location_name,
+    })
+}
+
+/// Parses the options of `orbit`, not including the subcommand.
+pub fn parse_orbit(args: &[String]) -> Result<OrbitArgs, String> {
+    let mut fractal = FractalKind::Mandelbrot;
+    let mut point = None;
+    let mut precision = Precision::F64;
+    let mut reference = None;
+    let mut max_iter = 1000;
+    let mut bailout = 2.0;
+    let mut output = None;
+    let mut plot = None;
+    let mut width = 512;
+    let mut height = 512;
+    let mut overwrite = false;
+
+    let positional = split_args(args, |name, value| {
+        match name {
+            "fractal" => {
+                fractal = match escape_time_fractal(&value()?, "orbit")? {
+                    FractalKind::Formula => {
+                        return Err("orbit traces the mandelbrot and tricorn only".to_string())
+                    }
+                    fractal => fractal,
+                }
+            }
+            // Either `--point RE IM` or `--point RE,IM`.
+            "point" => {
+                let re = value()?;
+                point = Some(match re.contains(',') {
+                    true => parse_center(&re)?,
+                    false => parse_center(&format!("{},{}", re, value()?))?,
+                });
+            }
+            "precision" => {
+                let value = value()?;
+                precision = Precision::from_name(&value)
+                    .filter(|precision| *precision != Precision::Auto)
+                    .ok_or_else(|| {
+                        format!("precision should be f32, f64, dd, perturb or big, got '{}'", value)
+                    })?;
+                if let Some(feature) = precision.missing_feature() {
+                    let wanted = format!("--precision {}", value);
+                    return Err(RustlebrotError::missing_feature(feature, wanted).to_string());
+                }
+            }
+            "reference" => reference = Some(parse_center(&value()?)?),
+            "max-iter" => {
+                max_iter = value()?
+                    .parse()
+                    .map_err(|_| "max-iter should be an integer".to_string())?;
+            }
+            "bailout" => {
+                let radius: f64 = value()?
+                    .parse()
+                    .map_err(|_| "bailout should be a float".to_string())?;
+                if !(radius >= 2.0 && radius.is_finite()) {
+                    return Err(format!("bailout should be at least 2, got {}", radius));
+                }
+                bailout = radius;
+            }
+            "output" => {
+                let path = value()?;
+                let format = orbit::Format::from_path(&path).ok_or_else(|| {
+                    format!("output should be a .csv or .json file, got '{}'", path)
+                })?;
+                output = Some((path, format));
+            }
+            "plot" => plot = Some(value()?),
+            "width" => {
+                width = value()?
+                    .parse()
+                    .map_err(|_| "width should be an integer".to_string())?;
+            }
+            "height" => {
+                height = value()?
+                    .parse()
+                    .map_err(|_| "height should be an integer".to_string())?;
+            }
+            "overwrite" => overwrite = true,
+            _ => return Err(format!("unknown flag --{}", name)),
+        }
+        Ok(())
+    })?;
+
+    if !positional.is_empty() {
+        return Err(format!(
+            "orbit takes no positional arguments, got {}\n{}",
+            positional.len(),
+            USAGE
+        ));
+    }
+    let point = point.ok_or_else(|| format!("orbit needs --point\n{}", USAGE))?;
+    if reference.is_some() && precision != Precision::Perturbation {
+        return Err("--reference is the reference orbit of --precision perturb".to_string());
+    }
+    if plot.is_none() && uses_flag(args, &["width", "height"]) {
+        return Err("--width and --height are the size of the image of --plot".to_string());
+    }
+    RustlebrotError::check_size(width, height).map_err(|e| e.to_string())?;
+    Ok(OrbitArgs {
+        fractal,
+        point,
+        precision,
+        reference,
+        max_iter,
+        bailout,
+        output,
+        plot,
+        width,
+        height,
+        overwrite,
+    })
+}
+
+/// Parses the options of `serve`, not including the subcommand.
+#[cfg(feature = "serve")]
+pub fn parse_serve(args: &[String]) -> Result<ServeArgs, String> {
+    let mut fractal = FractalKind::Mandelbrot;
+    let mut bind = "127.0.0.1".to_string();
+    let mut port = 8080;
+    let mut center = None;
+    let (mut x_range, mut y_range) = (None, None);
+    let mut max_iter = 1000;
+    let mut auto_iter = false;
+    let mut iter_growth = 1.0;
+    let mut coloring = Coloring::Smooth;
+    let mut colors = ColorArgs::default();
+    let mut workers = 4;
+    let mut cache_tiles = 4096;
+    let mut cache_dir = None;
+    let mut max_zoom = None;
+    #[cfg(feature = "window")]
+    let (mut window, mut width, mut height, mut bookmarks) = (false, 800, 600, None);
+    // The flags of one of the two, which the other doesn't take.
+    #[cfg(feature = "window")]
+    let (mut server_flags, mut window_flags) = (Vec::new(), Vec::new());
+
+    let positional = split_args(args, |name, value| {
+        #[cfg(feature = "window")]
+        match name {
+            "bind" | "port" | "workers" | "cache-tiles" | "cache-dir" | "max-zoom" => {
+                server_flags.push(name.to_string())
+            }
+            "width" | "height" | "bookmarks" => window_flags.push(name.to_string()),
+            _ => {}
+        }
+        match name {
+            "fractal" => fractal = escape_time_fractal(&value()?, "serve")?,
+            #[cfg(not(feature = "window"))]
+            "window" | "width" | "height" | "bookmarks" => {
+                let wanted = format!("--{}", name);
+                return Err(RustlebrotError::missing_feature("window", wanted).to_string());
+            }
+            #[cfg(feature = "window")]
+            "window" => window = true,
+            #[cfg(feature = "window")]
+            "width" => {
+                width = value()?
+                    .parse()
+                    .map_err(|_| "width should be an integer".to_string())?;
+            }
+            #[cfg(feature = "window")]
+            "height" => {
+                height = value()?
+                    .parse()
+                    .map_err(|_| "height should be an integer".to_string())?;
+            }
+            #[cfg(feature = "window")]
+            "bookmarks" => bookmarks = Some(value()?),
+            "bind" => bind = value()?,
+            "port" => {
+                port = value()?
+                    .parse()
+                    .map_err(|_| "port should be an integer up to 65535".to_string())?;
+            }
+            "center" => center = Some(parse_center(&value()?)?),
+            "x-range" => x_range = Some(parse_range("x-range", &value()?)?),
+            "y-range" => y_range = Some(parse_range("y-range", &value()?)?),
+            "max-iter" => {
+                max_iter = value()?
+                    .parse()
+                    .map_err(|_| "max-iter should be an integer".to_string())?;
+            }
+            "auto-iter" => auto_iter = true,
+            "iter-growth" => {
+                iter_growth = value()?
+                    .parse()
+                    .map_err(|_| "iter-growth should be a float".to_string())?;
+                auto_iter = true;
+            }
+            "coloring" => {
+                let value = value()?;
+                coloring = match Coloring::from_name(&value) {
+                    Some(
+                        coloring @ (Coloring::EscapeTime | Coloring::Smooth | Coloring::Distance),
+                    ) => coloring,
+                    // Histograms are spread over one image, so neighboring
+                    // tiles wouldn't match.
+                    _ => {
+                        return Err(format!(
+                            "serve colors tiles as escape, smooth or distance, got '{}'",
+                            value
+                        ))
+                    }
+                };
+            }
+            "workers" => {
+                workers = value()?
+                    .parse()
+                    .map_err(|_| "workers should be an integer".to_string())?;
+                if workers == 0 {
+                    return Err("workers should be at least 1".to_string());
+                }
+            }
+            "cache-tiles" => {
+                cache_tiles = value()?
+                    .parse()
+                    .map_err(|_| "cache-tiles should be an integer".to_string())?;
+            }
+            "cache-dir" => cache_dir = Some(value()?),
+            "max-zoom" => {
+                max_zoom = Some(
+                    value()?
+                        .parse()
+                        .map_err(|_| "max-zoom should be an integer".to_string())?,
+                );
+            }
+            _ => {
+                if !colors.flag(name, value)? {
+                    return Err(format!("unknown flag --{}", name));
+                }
+            }
+        }
+        Ok(())
+    })?;
+
+    colors.single_palette("serve")?;
+    colors.check_transfer(coloring)?;
+    colors.check_map_interpolate()?;
+    colors.check_line_art()?;
+    colors.whole_frames("serve")?;
+    if !positional.is_empty() {
+        return Err(format!(
+            "serve takes no positional arguments, got {}\n{}",
+            positional.len(),
+            USAGE
+        ));
+    }
+    let ranges = match (x_range, y_range) {
+        (Some(x_range), Some(y_range)) => Some((x_range, y_range)),
+        (None, None) => None,
+        _ => return Err("--x-range and --y-range have to be given together".to_string()),
+    };
+    if ranges.is_some() && center.is_some() {
+        return Err("--center can't be used with --x-range and --y-range, whose middle is the \
+                    center"
+            .to_string());
+    }
+    #[cfg(feature = "window")]
+    match (window, server_flags.first(), window_flags.first()) {
+        (true, Some(flag), _) => {
+            return Err(format!("--{} is for serving tiles, which --window doesn't", flag));
+        }
+        (false, _, Some(flag)) => return Err(format!("--{} needs --window", flag)),
+        (true, ..) if width == 0 || height == 0 => {
+            return Err("width and height should be at least 1".to_string());
+        }
+        _ => {}
+    }
+    Ok(ServeArgs {
+        fractal,
+        bind,
+        port,
+        center,
+        ranges,
+        max_iter,
+        auto_iter: auto_iter.then_some(iter_growth),
+        coloring,
+        colors,
+        workers,
+        cache_tiles,
+        cache_dir,
+        max_zoom,
+        #[cfg(feature = "window")]
+        window: window.then_some((width, height)),
+        #[cfg(feature = "window")]
+        bookmarks,
+    })
+}
+
+/// Parses the arguments of the `still` subcommand, the ones after `still`.
+pub fn parse_still(args: &[String]) -> Result<StillArgs, String> {
+    let still = parse_view(args, "still", |_, _| Ok(false))?;
+    still.colors.whole_frames("still")?;
+    Ok(still)
+}
+
+/// Parses the options of the one view `command` renders, as `still` takes
+/// them, passing the flags of its own to `extra` first, which tells
+/// whether it took them.
+fn parse_view<H>(args: &[String], command: &str, mut extra: H) -> Result<StillArgs, String>
+where
+    H: FnMut(&str, &mut dyn FnMut() -> Result<String, String>) -> Result<bool, String>,
+{
+    let mut fractal = FractalKind::Mandelbrot;
+    let mut precision = Precision::Auto;
+    let mut center = None;
+    let (mut x_range, mut y_range) = (None, None);
+    let mut view = None;
+    let mut magnification = None;
+    let mut preset: Option<&Preset> = None;
+    let (mut location_path, mut location_name) = (None, None);
+    let mut fit = Fit::Contain;
+    let (mut width, mut height) = (1920, 1080);
+    let mut supersample = 2;
+    let mut tile_size = 2048;
+    let mut band_height = None;
+    let mut max_memory = None;
+    let mut max_iter = None;
+    let mut coloring = Coloring::Smooth;
+    let mut colors = ColorArgs::default();
+    let mut output = None;
+    let mut tiles = None;
+    let mut overwrite = false;
+
+    let positional = split_args(args, |name, value| {
+        if extra(name, value)? {
+            return Ok(());
+        }
+        match name {
+            "fractal" => fractal = escape_time_fractal(&value()?, command)?,
+            "precision" => {
+                let value = value()?;
+                precision = match Precision::from_name(&value) {
+                    Some(precision @ (Precision::Auto | Precision::F32 | Precision::F64)) => {
+                        precision
+                    }
+                    _ => {
+                        return Err(format!(
+                            "{} renders in auto, f32 or f64 precision, got '{}'",
+                            command, value
+                        ))
+                    }
+                };
+            }
+            "center" => center = Some(parse_center(&value()?)?),
+            "x-range" => x_range = Some(parse_range("x-range", &value()?)?),
+            "y-range" => y_range = Some(parse_range("y-range", &value()?)?),
+            "view" => view = Some(Corners::from_spec(&value()?)?),
+            "magnification" => {
+                let value: f64 = value()?
+                    .parse()
+                    .map_err(|_| "magnification should be a float".to_string())?;
+                if !(value > 0.0 && value.is_finite()) {
+                    return Err(format!("magnification should be positive, got {}", value));
+                }
+                magnification = Some(value);
+            }
+            "preset" => {
+                let value = value()?;
+                preset = Some(Preset::from_name(&value).ok_or_else(|| {
+                    format!("unknown preset '{}', see --list-presets", value)
+                })?);
+            }
+            "location" => location_path = Some(value()?),
+            "location-name" => location_name = Some(value()?),
+            "fit" => {
+                let value = value()?;
+                fit = Fit::from_name(&value).ok_or_else(|| format!("unknown fit '{}'", value))?;
+            }
+            "width" => {
+                width = value()?
+                    .parse()
+                    .map_err(|_| "width should be an integer".to_string())?;
+            }
+            "height" => {
+                height = value()?
+                    .parse()
+                    .map_err(|_| "height should be an integer".to_string())?;
+            }
+            "supersample" => {
+                supersample = value()?
+                    .parse()
+                    .map_err(|_| "supersample should be an integer".to_string())?;
+                if !(1..=4).contains(&supersample) {
+                    return Err("supersample should be between 1 and 4".to_string());
+                }
+            }
+            "tile-size" => {
+                tile_size = value()?
+                    .parse()
+                    .map_err(|_| "tile-size should be an integer".to_string())?;
+                if tile_size == 0 {
+                    return Err("tile-size should be at least 1".to_string());
+                }
+            }
+            "band-height" => {
+                let rows: u32 = value()?
+                    .parse()
+                    .map_err(|_| "band-height should be an integer".to_string())?;
+                if rows == 0 {
+                    return Err("band-height should be at least 1".to_string());
+                }
+                band_height = Some(rows);
+            }
+            "max-memory" => {
+                let value = value()?;
+                let size = parse_size(&value).ok_or_else(|| {
+                    format!("max-memory should be a size like 512M or 8G, got '{}'", value)
+                })?;
+                max_memory = Some(size);
+            }
+            "max-iter" => {
+                max_iter = Some(
+                    value()?
+                        .parse()
+                        .map_err(|_| "max-iter should be an integer".to_string())?,
+                );
+            }
+            "coloring" => {
+                let value = value()?;
+                coloring = match Coloring::from_name(&value) {
+                    Some(
+                        coloring @ (Coloring::EscapeTime | Coloring::Smooth | Coloring::Distance),
+                    ) => coloring,
+                    // Histograms are spread over one image, so neighboring
+                    // tiles wouldn't match.
+                    _ => {
+                        return Err(format!(
+                            "{} colors tiles as escape, smooth or distance, got '{}'",
+                            command, value
+                        ))
+                    }
+                };
+            }
+            "output" => output = Some(value()?),
+            "tiles" => tiles = Some(value()?),
+            "overwrite" => overwrite = true,
+            _ if SEQUENCE_FLAGS.iter().chain(&VIDEO_FLAGS).any(|flag| name.starts_with(flag)) => {
+                return Err(format!(
+                    "--{} is an option of a zoom, and {} renders one image; render a zoom \
+                     with `rustlebrot render`",
+                    name, command
+                ))
+            }
+            _ => {
+                if !colors.flag(name, value)? {
+                    return Err(format!("unknown flag --{}", name));
+                }
+            }
+        }
+        Ok(())
+    })?;
+
+    colors.single_palette(command)?;
+    colors.check_transfer(coloring)?;
+    colors.check_map_interpolate()?;
+    colors.check_line_art()?;
+    if !positional.is_empty() {
+        return Err(format!(
+            "{} takes no positional arguments, got {}; give the view with --center and \
+             --magnification and the limit with --max-iter, or render a zoom with `rustlebrot \
+             render`",
+            command,
+            positional.len()
+        ));
+    }
+    if width == 0 || height == 0 {
+        return Err("width and height should be at least 1".to_string());
+    }
+    // The dither patterns repeat every 64 pixels, so with tiles starting on
+    // multiples of that they carry on across the seams.
+    if colors.dither != Dither::None && tile_size % 64 != 0 {
+        return Err(format!(
+            "tile-size should be a multiple of 64 with --dither, so the pattern lines up across \
+             tiles, got {}",
+            tile_size
+        ));
+    }
+    let dithered = colors.dither != Dither::None;
+    if let Some(rows) = band_height.filter(|rows| dithered && rows % 64 != 0) {
+        return Err(format!(
+            "band-height should be a multiple of 64 with --dither, so the pattern lines up \
+             across bands, got {}",
+            rows
+        ));
+    }
+    let placing = [
+        ("center", center.is_some()),
+        ("magnification", magnification.is_some()),
+        ("preset", preset.is_some()),
+        ("location", location_path.is_some()),
+    ];
+    let ranges = ranges_of(x_range, y_range, view, &placing)?;
+    if ranges.is_some() && center.is_some() {
+        return Err("--center can't be used with --x-range and --y-range, whose middle is the \
+                    center"
+            .to_string());
+    }
+    let mut rotation = 0.0;
+    match location_path {
+        Some(path) => {
+            let (locations, _) = bookmarks::read(&path)?;
+            let location = bookmarks::select(&path, locations, location_name.as_deref())?;
+            if preset.is_some() {
+                return Err("--location and --preset can't be used together".to_string());
+            }
+            if uses_flag(args, &["fractal"]) {
+                return Err("--location gives the fractal, so it can't be used with --fractal"
+                    .to_string());
+            }
+            if center.is_some() || ranges.is_some() {
+                return Err("--location gives the center, so it can't be used with --center or \
+                            the ranges"
+                    .to_string());
+            }
+            fractal = escape_time_fractal(location.fractal.name(), command)?;
+            center = Some(location.center);
+            magnification = magnification.or(Some(location.magnification));
+            max_iter = max_iter.or(Some(location.max_iter));
+            rotation = location.rotation;
+        }
+        None if location_name.is_some() => {
+            return Err("--location-name names the location of --location".to_string())
+        }
+        None => (),
+    }
+    if let Some(preset) = preset {
+        if fractal != FractalKind::Mandelbrot {
+            return Err(format!(
+                "presets are places in the Mandelbrot set, so --preset can't be used with \
+                 --fractal {}",
+                fractal.name()
+            ));
+        }
+        if center.is_some() || ranges.is_some() {
+            return Err("--preset gives the center, so it can't be used with --center or the \
+                        ranges"
+                .to_string());
+        }
+        center = Some((preset.center.0.to_string(), preset.center.1.to_string()));
+        max_iter = max_iter.or(Some(preset.max_iter));
+    }
+    if ranges.is_some() && magnification.is_some() {
+        return Err("--magnification zooms into the center, so it can't be used with \
+                    --x-range and --y-range"
+            .to_string());
+    }
+    let output = match (output, tiles) {
+        (Some(_), Some(_)) => {
+            return Err("--output and --tiles can't be used together, the image is written \
+                        either whole or as tiles"
+                .to_string())
+        }
+        (output, None) => StillOutput::Png(output.unwrap_or_else(|| "still.png".to_string())),
+        (None, Some(dir)) => StillOutput::Tiles(dir),
+    };
+    if matches!(output, StillOutput::Tiles(_)) {
+        if band_height.is_some() {
+            return Err("--band-height only applies to --output, tiles saved with --tiles \
+                        are square"
+                .to_string());
+        }
+        if max_memory.is_some() {
+            return Err("--max-memory only applies to --output, --tiles holds one tile at a \
+                        time"
+                .to_string());
+        }
+    }
+    Ok(StillArgs {
+        fractal,
+        precision,
+        center,
+        ranges,
+        view,
+        magnification: magnification.unwrap_or(1.0),
+        rotation,
+        fit,
+        width,
+        height,
+        supersample,
+        tile_size,
+        band_height: band_height.unwrap_or(tile_size),
+        max_memory: max_memory.unwrap_or(4 << 30),
+        max_iter: max_iter.unwrap_or(1000),
+        coloring,
+        colors,
+        output,
+        overwrite,
+    })
+}
+
+/// Parses the options of `animate-palette`, not including the subcommand.
+pub fn parse_animate_palette(args: &[String]) -> Result<AnimatePaletteArgs, String> {
+    let mut frames = None;
+    let mut from = None;
+    let mut output_dir = "rust_data".to_string();
+    let mut encoder = None;
+    let mut video = VideoOptions::default();
+    let mut no_video = false;
+
+    // The rest are the flags of the view, which are those of `still` but
+    // for how it's written.
+    for flag in ["output", "tiles", "tile-size", "band-height", "max-memory"] {
+        if args.iter().any(|arg| arg == &format!("--{}", flag)) {
+            return Err(format!(
+                "--{} is an option of still; animate-palette writes its frames to --output-dir",
+                flag
+            ));
+        }
+    }
+    let view = parse_view(args, "animate-palette", |name, value| {
+        match name {
+            "frames" => {
+                let value = value()?;
+                frames = Some(value.parse().ok().filter(|&frames| frames > 0).ok_or_else(
+                    || format!("frames should be a positive integer, got '{}'", value),
+                )?);
+            }
+            "from" => from = Some(value()?),
+            "output-dir" => output_dir = value()?,
+            "encoder" => encoder = Some(parse_encoder(&value()?)?),
+            "no-video" => no_video = true,
+            // --overwrite is the view's, for the frames and the video.
+            "overwrite" => return Ok(false),
+            _ => return video_flag(&mut video, name, value),
+        }
+        Ok(true)
+    })?;
+    check_video_flags(args, encoder, no_video)?;
+    let frames = frames.ok_or("animate-palette needs --frames")?;
+    if view.colors.lineart {
+        return Err(
+            "animate-palette cycles the palette, which --style lineart draws without".to_string()
+        );
+    }
+    let view_flags = [
+        "fractal",
+        "precision",
+        "center",
+        "x-range",
+        "y-range",
+        "view",
+        "magnification",
+        "preset",
+        "location",
+        "fit",
+        "width",
+        "height",
+        "supersample",
+        "max-iter",
+    ];
+    if from.is_some() && uses_flag(args, &view_flags) {
+        return Err("--from colors the escape times of a dumped frame, which has its view, \
+                    size and limit, so it can't be used with the options of the view"
+            .to_string());
+    }
+    video.overwrite = view.overwrite;
+    video.background = view.colors.interior;
+    Ok(AnimatePaletteArgs {
+        coloring: uses_flag(args, &["coloring"]).then_some(view.coloring),
+        view,
+        frames,
+        from,
+        output_dir,
+        encoder,
+        video,
+        no_video,
+    })
+}
+
+/// Parses the options of `animate-iter`, not including the subcommand.
+pub fn parse_animate_iter(args: &[String]) -> Result<AnimateIterArgs, String> {
+    let mut frames = None;
+    let (mut iter_start, mut iter_end) = (None, None);
+    let mut curve = IterCurve::Linear;
+    let mut output_dir = "rust_data".to_string();
+    let mut encoder = None;
+    let mut video = VideoOptions::default();
+    let mut no_video = false;
+
+    for flag in ["output", "tiles", "tile-size", "band-height", "max-memory", "max-iter"] {
+        if args.iter().any(|arg| arg == &format!("--{}", flag)) {
+            return Err(format!(
+                "--{} is an option of still; animate-iter writes its frames to --output-dir, \
+                 with limits from --iter-start to --iter-end",
+                flag
+            ));
+        }
+    }
+    let mut view = parse_view(args, "animate-iter", |name, value| {
+        match name {
+            "frames" => {
+                let value = value()?;
+                frames = Some(value.parse().ok().filter(|&frames| frames > 1).ok_or_else(
+                    || format!("frames should be an integer of at least 2, got '{}'", value),
+                )?);
+            }
+            "iter-start" | "iter-end" => {
+                let value = value()?;
+                let limit = value.parse().ok().filter(|&limit| limit > 0).ok_or_else(|| {
+                    format!("{} should be a positive integer, got '{}'", name, value)
+                })?;
+                match name {
+                    "iter-start" => iter_start = Some(limit),
+                    _ => iter_end = Some(limit),
+                }
+            }
+            "curve" => {
+                let value = value()?;
+                curve = IterCurve::from_name(&value)
+                    .ok_or_else(|| format!("curve should be linear or log, got '{}'", value))?;
+            }
+            "output-dir" => output_dir = value()?,
+            "encoder" => encoder = Some(parse_encoder(&value()?)?),
+            "no-video" => no_video = true,
+            // --overwrite is the view's, for the frames and the video.
+            "overwrite" => return Ok(false),
+            _ => return video_flag(&mut video, name, value),
+        }
+        Ok(true)
+    })?;
+    check_video_flags(args, encoder, no_video)?;
+    let frames = frames.ok_or("animate-iter needs --frames")?;
+    let (Some(iter_start), Some(iter_end)) = (iter_start, iter_end) else {
+        return Err("animate-iter needs the limits of its first and last frames, like \
+                    --iter-start 5 --iter-end 2000"
+            .to_string());
+    };
+    if iter_start >= iter_end {
+        return Err(format!(
+            "animate-iter raises the limit, so --iter-end should be above --iter-start, got {} \
+             and {}",
+            iter_start, iter_end
+        ));
+    }
+    if view.coloring == Coloring::Distance {
+        return Err("animate-iter shows the bands of escape times move in as the limit grows, \
+                    which --coloring distance doesn't have"
+            .to_string());
+    }
+    view.max_iter = iter_end;
+    video.overwrite = view.overwrite;
+    video.background = view.colors.interior;
+    Ok(AnimateIterArgs {
+        view,
+        frames,
+        iter_start,
+        curve,
+        output_dir,
+        encoder,
+        video,
+        no_video,
+    })
+}
+
+/// Parses the options of `export-mesh`, not including the subcommand.
+pub fn parse_export_mesh(args: &[String]) -> Result<MeshArgs, String> {
+    let mut out = Vec::new();
+    let mut z_scale = 0.2;
+    let mut smooth = 0;
+    let mut plateau = Plateau::Top;
+    let mut solid_base = None;
+    let mut texture = None;
+
+    for flag in ["output", "tiles", "band-height", "max-memory"] {
+        if args.iter().any(|arg| arg == &format!("--{}", flag)) {
+            return Err(format!(
+                "--{} is an option of still; export-mesh writes the mesh to --out",
+                flag
+            ));
+        }
+    }
+    let mut view = parse_view(args, "export-mesh", |name, value| {
+        match name {
+            "out" => {
+                let path = value()?;
+                let format = mesh::Format::from_path(&path).ok_or_else(|| {
+                    format!("--out should be a .obj or .stl file, got '{}'", path)
+                })?;
+                out.push((path, format));
+            }
+            "z-scale" => {
+                let value = value()?;
+                z_scale = value.parse().ok().filter(|scale: &f64| scale.is_finite()).ok_or_else(
+                    || format!("z-scale should be a float, got '{}'", value),
+                )?;
+            }
+            "smooth" => {
+                smooth = value()?
+                    .parse()
+                    .map_err(|_| "smooth should be an integer".to_string())?;
+            }
+            "plateau" => {
+                let value = value()?;
+                plateau = Plateau::from_name(&value)
+                    .ok_or_else(|| format!("plateau should be top or base, got '{}'", value))?;
+            }
+            "solid-base" => {
+                let value = value()?;
+                let thickness = value.parse().ok().filter(|thickness: &f64| *thickness > 0.0);
+                solid_base = Some(thickness.filter(|thickness| thickness.is_finite()).ok_or_else(
+                    || format!("solid-base should be a positive thickness, got '{}'", value),
+                )?);
+            }
+            "texture" => texture = Some(value()?),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    })?;
+    // A vertex a pixel adds up fast, so meshes are smaller than stills.
+    if !uses_flag(args, &["width", "height"]) {
+        (view.width, view.height) = (512, 512);
+    }
+    if view.width < 2 || view.height < 2 {
+        return Err("export-mesh needs a width and height of at least 2".to_string());
+    }
+    if view.coloring == Coloring::Distance {
+        return Err("export-mesh raises the escape times into heights, which --coloring \
+                    distance doesn't keep"
+            .to_string());
+    }
+    if out.is_empty() {
+        out.push(("mesh.obj".to_string(), mesh::Format::Obj));
+    }
+    if let Some(texture) = &texture {
+        if !texture.ends_with(".png") {
+            return Err(format!("--texture should be a .png file, got '{}'", texture));
+        }
+        if !out.iter().any(|(_, format)| *format == mesh::Format::Obj) {
+            return Err("--texture is mapped onto the mesh of an .obj --out; STL has no \
+                        colors"
+                .to_string());
+        }
+    }
+    Ok(MeshArgs {
+        view,
+        out,
+        z_scale,
+        smooth,
+        plateau,
+        solid_base,
+        texture,
+    })
+}
+
+/// Parses the options of `export-svg`, not including the subcommand.
+pub fn parse_export_svg(args: &[String]) -> Result<SvgArgs, String> {
+    let mut out = "set.svg".to_string();
+    let mut count = None;
+    let mut spacing = None;
+    let mut given = Vec::new();
+    let mut tolerance = 0.25;
+    let mut stroke_width = (1.0, 1.0);
+    let mut stroke_color = None;
+    let mut set_fill = None;
+
+    for flag in ["output", "tiles", "band-height", "max-memory"] {
+        if args.iter().any(|arg| arg == &format!("--{}", flag)) {
+            return Err(format!(
+                "--{} is an option of still; export-svg writes the isolines to --out",
+                flag
+            ));
+        }
+    }
+    let mut view = parse_view(args, "export-svg", |name, value| {
+        match name {
+            "out" => {
+                out = value()?;
+                if !out.ends_with(".svg") {
+                    return Err(format!("--out should be an .svg file, got '{}'", out));
+                }
+            }
+            "levels" => {
+                count = Some(
+                    value()?.parse().map_err(|_| "levels should be an integer".to_string())?,
+                );
+            }
+            "spacing" => {
+                let value = value()?;
+                spacing = Some(
+                    Spacing::from_name(&value)
+                        .ok_or_else(|| format!("spacing should be even or log, got '{}'", value))?,
+                );
+            }
+            "level" => {
+                let value = value()?;
+                given.push(value.parse().ok().filter(|level: &f64| level.is_finite()).ok_or_else(
+                    || format!("level should be an escape time, got '{}'", value),
+                )?);
+            }
+            "tolerance" => {
+                let value = value()?;
+                tolerance = value
+                    .parse()
+                    .ok()
+                    .filter(|tolerance: &f64| *tolerance >= 0.0 && tolerance.is_finite())
+                    .ok_or_else(|| {
+                        format!("tolerance should be a distance in pixels, got '{}'", value)
+                    })?;
+            }
+            "stroke-width" => {
+                let value = value()?;
+                let width = |width: &str| {
+                    let width = width.trim().parse().ok();
+                    width.filter(|width: &f64| *width > 0.0 && width.is_finite())
+                };
+                let widths = match value.split_once('-') {
+                    Some((low, high)) => width(low).zip(width(high)),
+                    None => width(&value).map(|width| (width, width)),
+                };
+                stroke_width = widths.ok_or_else(|| {
+                    format!(
+                        "stroke-width should be a width in pixels, or the widths at the lowest \
+                         and highest levels like 0.5-2, got '{}'",
+                        value
+                    )
+                })?;
+            }
+            "stroke-color" => stroke_color = Some(palette::parse_color(&value()?)?),
+            "set-fill" => set_fill = Some(palette::parse_color(&value()?)?),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    })?;
+    // Escape times that step from one iteration to the next trace lines
+    // along the steps.
+    if !uses_flag(args, &["coloring"]) {
+        view.coloring = Coloring::Smooth;
+    }
+    if !matches!(view.coloring, Coloring::EscapeTime | Coloring::Smooth) {
+        return Err(format!(
+            "export-svg traces the escape times, which --coloring {} doesn't keep; use smooth \
+             or escape",
+            view.coloring.name()
+        ));
+    }
+    if view.width < 2 || view.height < 2 {
+        return Err("export-svg needs a width and height of at least 2".to_string());
+    }
+    let levels = match (given.is_empty(), count, spacing) {
+        (true, count, spacing) => {
+            Levels::Count(count.unwrap_or(12), spacing.unwrap_or(Spacing::Even))
+        }
+        (false, None, None) => Levels::Given(given),
+        (false, _, _) => {
+            return Err("--level gives the levels, so it can't be used with --levels or \
+                        --spacing"
+                .to_string())
+        }
+    };
+    if matches!(levels, Levels::Count(0, _)) && set_fill.is_none() {
+        return Err("export-svg has nothing to draw without levels or --set-fill".to_string());
+    }
+    Ok(SvgArgs {
+        view,
+        out,
+        levels,
+        tolerance,
+        stroke_width,
+        stroke_color,
+        set_fill,
+    })
+}
+
+/// Parses the options of `export-dzi`, not including the subcommand.
+pub fn parse_export_dzi(args: &[String]) -> Result<DziArgs, String> {
+    let mut out = "still.dzi".to_string();
+    let mut tile_size = 254;
+    let mut overlap = 1;
+    let mut format = ImageFormat::Jpeg {
+        quality: ImageFormat::JPEG_QUALITY,
+    };
+    let mut jpeg_quality = None;
+    let mut resume = false;
+
+    // The rest are the flags of the view, which are those of `still` but
+    // for how it's written.
+    for flag in ["output", "tiles", "band-height", "max-memory"] {
+        if args.iter().any(|arg| arg == &format!("--{}", flag)) {
+            return Err(format!(
+                "--{} is an option of still; export-dzi writes its tiles next to --out",
+                flag
+            ));
+        }
+    }
+    let view = parse_view(args, "export-dzi", |name, value| {
+        match name {
+            "out" => out = value()?,
+            "tile-size" => {
+                let value = value()?;
+                tile_size = value.parse().ok().filter(|&size| size > 0).ok_or_else(|| {
+                    format!("tile-size should be a positive integer, got '{}'", value)
+                })?;
+            }
+            "overlap" => {
+                let value = value()?;
+                overlap = value
+                    .parse()
+                    .map_err(|_| format!("overlap should be an integer, got '{}'", value))?;
+            }
+            "format" => {
+                let value = value()?;
+                format = match ImageFormat::from_name(&value) {
+                    Some(format @ (ImageFormat::Png | ImageFormat::Jpeg { .. })) => format,
+                    _ => return Err(format!("format should be jpg or png, got '{}'", value)),
+                };
+            }
+            "jpeg-quality" => {
+                let quality: u8 = value()?
+                    .parse()
+                    .map_err(|_| "jpeg-quality should be an integer".to_string())?;
+                if !(1..=100).contains(&quality) {
+                    return Err(format!("jpeg-quality should be from 1 to 100, got {}", quality));
+                }
+                jpeg_quality = Some(quality);
+            }
+            "resume" => resume = true,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    })?;
+    view.colors.whole_frames("export-dzi")?;
+    if !out.ends_with(".dzi") {
+        return Err(format!("--out should be a .dzi descriptor, got '{}'", out));
+    }
+    if overlap >= tile_size {
+        return Err(format!(
+            "overlap should be less than the tile size of {}, got {}",
+            tile_size, overlap
+        ));
+    }
+    if let Some(quality) = jpeg_quality {
+        if format == ImageFormat::Png {
+            return Err("--jpeg-quality only applies to --format jpg".to_string());
+        }
+        format = ImageFormat::Jpeg { quality };
+    }
+    if !format.holds(view.colors.bit_depth) {
+        return Err("jpg tiles are 8-bit; use --format png for --bit-depth 16".to_string());
+    }
+    // The levels below the first are averaged from the one above, which
+    // would blur a dither pattern into noise.
+    if view.colors.dither != Dither::None {
+        return Err("export-dzi averages its lower levels from the tiles, so it can't be used \
+                    with --dither"
+            .to_string());
+    }
+    if resume && view.overwrite {
+        return Err("--resume keeps the tiles that are there and --overwrite replaces them, so \
+                    they can't be used together"
+            .to_string());
+    }
+    Ok(DziArgs {
+        view,
+        out,
+        tile_size,
+        overlap,
+        format,
+        resume,
+    })
+}
+
+/// Parses the options of `render-batch`, not including the subcommand.
+pub fn parse_batch(args: &[String]) -> Result<BatchArgs, String> {
+    let mut input = None;
+    let mut max_memory = 4 << 30;
+    let mut overwrite = false;
+
+    let positional = split_args(args, |name, value| {
+        match name {
+            "input" => input = Some(value()?),
+            "max-memory" => {
+                let value = value()?;
+                max_memory = parse_size(&value).ok_or_else(|| {
+                    format!("max-memory should be a size like 512M or 8G, got '{}'", value)
+                })?;
+            }
+            "overwrite" => overwrite = true,
+            _ => return Err(format!("unknown flag --{}", name)),
+        }
+        Ok(())
+    })?;
+
+    if !positional.is_empty() {
+        return Err(format!(
+            "render-batch takes no positional arguments, got {}\n{}",
+            positional.len(),
+            USAGE
+        ));
+    }
+    let input = input.ok_or_else(|| format!("render-batch needs --input PATH\n{}", USAGE))?;
+    Ok(BatchArgs {
+        input,
+        max_memory,
+        overwrite,
+    })
+}
+
+/// Parses the options of `daemon`, not including the subcommand.
+#[cfg(feature = "daemon")]
+pub fn parse_daemon(args: &[String]) -> Result<DaemonArgs, String> {
+    let mut socket = None;
+    let mut listen = None;
+    let mut queue = daemon::DEFAULT_QUEUE.to_string();
+
+    let positional = split_args(args, |name, value| {
+        match name {
+            "socket" => socket = Some(value()?),
+            "listen" => listen = Some(value()?),
+            "queue" => queue = value()?,
+            _ => return Err(format!("unknown flag --{}", name)),
+        }
+        Ok(())
+    })?;
+
+    if !positional.is_empty() {
+        return Err(format!(
+            "daemon takes no positional arguments, got {}\n{}",
+            positional.len(),
+            USAGE
+        ));
+    }
+    Ok(DaemonArgs {
+        endpoint: endpoint(socket, listen, "--listen")?,
+        queue,
+    })
+}
+
+/// Parses the options of `submit`, not including the subcommand.
+#[cfg(feature = "daemon")]
+pub fn parse_submit(args: &[String]) -> Result<SubmitArgs, String> {
+    let mut socket = None;
+    let mut connect = None;
+    let mut status = false;
+    let mut cancel = None;
+    let mut json = false;
+
+    let positional = split_args(args, |name, value| {
+        match name {
+            "socket" => socket = Some(value()?),
+            "connect" => connect = Some(value()?),
+            "status" => status = true,
+            "cancel" => {
+                cancel = Some(
+                    value()?
+                        .parse()
+                        .map_err(|_| "cancel should be the number of a job".to_string())?,
+                );
+            }
+            "json" => json = true,
+            _ => return Err(format!("unknown flag --{}", name)),
+        }
+        Ok(())
+    })?;
+
+    let action = match (positional.as_slice(), status, cancel) {
+        ([job], false, None) => SubmitAction::Job(job.to_string()),
+        ([], true, None) => SubmitAction::Status,
+        ([], false, Some(id)) => SubmitAction::Cancel(id),
+        ([], false, None) => {
+            return Err(format!(
+                "submit needs a job file, --status or --cancel ID\n{}",
+                USAGE
+            ))
+        }
+        _ => {
+            return Err("submit takes one of a job file, --status and --cancel ID".to_string())
+        }
+    };
+    Ok(SubmitArgs {
+        endpoint: endpoint(socket, connect, "--connect")?,
+        action,
+        json,
+    })
+}
+
+/// The endpoint of a daemon given by `--socket` or by the TCP flag `tcp`,
+/// the default socket if neither is.
+#[cfg(feature = "daemon")]
+fn endpoint(socket: Option<String>, tcp: Option<String>, flag: &str) -> Result<Endpoint, String> {
+    match (socket, tcp) {
+        (Some(_), Some(_)) => Err(format!("--socket and {} are two places to listen", flag)),
+        (Some(path), None) => Ok(Endpoint::Socket(path)),
+        (None, Some(address)) => Ok(Endpoint::Tcp(address)),
+        (None, None) => Ok(Endpoint::Socket(daemon::DEFAULT_SOCKET.to_string())),
+    }
+}
+
+/// Parses the options of `bench`, not including the subcommand.
+pub fn parse_bench(args: &[String]) -> Result<BenchArgs, String> {
+    let mut scenes = Vec::new();
+    let mut repeats = bench::DEFAULT_REPEATS;
+    let mut threads = None;
+    let mut work_unit = None;
+    let mut chunk_size = None;
+    let mut json = false;
+    let mut allow_debug = false;
+    let mut formula = None;
+
+    let positional = split_args(args, |name, value| {
+        match name {
+            "scene" => {
+                let name = value()?;
+                let scene = bench::SCENES.iter().find(|scene| scene.name == name);
+                let names = || bench::SCENES.map(|scene| scene.name).join(", ");
+                let scene = scene.ok_or_else(|| {
+                    format!("unknown scene '{}', the scenes are {}", name, names())
+                })?;
+                if !scenes.contains(&scene.name) {
+                    scenes.push(scene.name);
+                }
+            }
+            "repeats" => {
+                repeats = value()?
+                    .parse()
+                    .map_err(|_| "repeats should be an integer".to_string())?;
+            }
+            "threads" => {
+                threads = Some(
+                    value()?
+                        .parse()
+                        .map_err(|_| "threads should be an integer".to_string())?,
+                );
+            }
+            "work-unit" => work_unit = Some(value()?),
+            "chunk-size" => {
+                chunk_size = Some(
+                    value()?
+                        .parse()
+                        .map_err(|_| "chunk-size should be an integer".to_string())?,
+                );
+            }
+            "json" => json = true,
+            "allow-debug" => allow_debug = true,
+            "formula" => {
+                let value = value()?;
+                formula = Some(
+                    Formula::parse(&value).map_err(|e| format!("--formula '{}': {}", value, e))?,
+                );
+            }
+            _ => return Err(format!("unknown flag --{}", name)),
+        }
+        Ok(())
+    })?;
+
+    if !positional.is_empty() {
+        return Err(format!(
+            "bench takes no positional arguments, got {}\n{}",
+            positional.len(),
+            USAGE
+        ));
+    }
+    if repeats == 0 {
+        return Err("repeats should be at least 1".to_string());
+    }
+    if threads == Some(0) {
+        return Err("threads should be at least 1".to_string());
+    }
+    Ok(BenchArgs {
+        scenes,
+        repeats,
+        threads,
+        work_units: work_units(work_unit.as_deref(), chunk_size)?,
+        json,
+        allow_debug,
+        formula,
+    })
+}
+
+/// Splits `args` into positional arguments and flags.
+///
+/// Flags may appear anywhere, either as `--flag value` or `--flag=value`.
+/// `flag` is called with each flag's name and a function that fetches its
+/// value, which only consumes the next argument when called.
+fn split_args<H>(args: &[String], mut flag: H) -> Result<Vec<&str>, String>
+where
+    H: FnMut(&str, &mut dyn FnMut() -> Result<String, String>) -> Result<(), String>,
+{
+    let mut positional: Vec<&str> = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let Some(name) = arg.strip_prefix("--") else {
+            positional.push(arg);
+            continue;
+        };
+        let (name, inline_value) = match name.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (name, None),
+        };
+        let mut value = || match &inline_value {
+            Some(value) => Ok(value.clone()),
+            None => match iter.next() {
+                // Most likely the value was left out.
+                Some(next) if next.starts_with("--") => {
+                    Err(format!("--{} needs a value, but is followed by {}", name, next))
+                }
+                Some(next) => Ok(next.clone()),
+                None => Err(format!("--{} needs a value", name)),
+            },
+        };
+        flag(name, &mut value)?;
+    }
+    Ok(positional)
+}
+
+/// Handles `--name` if it is one of the options of the video, returning
+/// whether it was.
+fn video_flag(
+    video: &mut VideoOptions,
+    name: &str,
+    value: &mut dyn FnMut() -> Result<String, String>,
+) -> Result<bool, String> {
+    match name {
+        "fps" => {
+            video.fps = value()?
+                .parse()
+                .map_err(|_| "fps should be an integer".to_string())?;
+            if video.fps == 0 {
+                return Err("fps should be positive".to_string());
+            }
+        }
+        "codec" => {
+            let value = value()?;
+            video.codec = match value.as_str() {
+                "x264" => "libx264".to_string(),
+                "x265" => "libx265".to_string(),
+                "vp9" => "libvpx-vp9".to_string(),
+                "av1" => "libaom-av1".to_string(),
+                // Any other encoder ffmpeg knows, such as h264_nvenc.
+                _ => value,
+            };
+        }
+        "crf" => {
+            let crf = value()?
+                .parse()
+                .map_err(|_| "crf should be an integer".to_string())?;
+            // 51 is the highest for x264 and x265, 63 for vp9 and av1.
+            if crf > 63 {
+                return Err(format!("crf should be from 0 to 63, got {}", crf));
+            }
+            video.crf = Some(crf);
+        }
+        "ffmpeg-arg" => video.ffmpeg_args.push(value()?),
+        "video-out" => video.output = Some(value()?),
+        "overwrite" => video.overwrite = true,
+        "pad-to-even" => video.pad_to_even = true,
+        "video-sequence" => video.sequence = Some(Sequence::from_spec(&value()?)?),
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+/// The units of work of `--work-unit` and `--chunk-size`, as given to
+/// `render` and `bench`: tiles unless `unit` says rows, automatic tiles
+/// without a `chunk_size`, and bands of single rows without one.
+fn work_units(unit: Option<&str>, chunk_size: Option<u32>) -> Result<WorkUnits, String> {
+    if chunk_size == Some(0) {
+        return Err("chunk-size should be at least 1".to_string());
+    }
+    match (unit, chunk_size) {
+        (None | Some("tiles"), None) => Ok(WorkUnits::Auto),
+        (None | Some("tiles"), Some(side)) => Ok(WorkUnits::Tiles(side)),
+        (Some("rows"), rows) => Ok(WorkUnits::Rows(rows.unwrap_or(1))),
+        (Some(unit), _) => Err(format!("unknown work unit '{}', expected rows or tiles", unit)),
+    }
+}
+
+/// Whether `args` has a flag whose name starts with one of `prefixes`.
+fn uses_flag(args: &[String], prefixes: &[&str]) -> bool {
+    args.iter()
+        .filter_map(|arg| arg.strip_prefix("--"))
+        .any(|name| prefixes.iter().any(|prefix| name.starts_with(prefix)))
+}
+
+/// Parses the `--fractal` of a subcommand that only renders escape time
+/// fractals.
+fn escape_time_fractal(value: &str, command: &str) -> Result<FractalKind, String> {
+    match FractalKind::from_name(value) {
+        Some(FractalKind::Newton) => {
+            Err(format!("{} only renders escape time fractals, not newton", command))
+        }
+        Some(FractalKind::Julia) => {
+            Err(format!("{} doesn't render julia sets, which animate-julia does", command))
+        }
+        Some(FractalKind::Lyapunov) => {
+            Err(format!("{} only renders escape time fractals, not lyapunov", command))
+        }
+        Some(fractal) => Ok(fractal),
+        None => Err(format!("unknown fractal '{}'", value)),
+    }
+}
+
+/// The flags, or their prefixes, of the options of a zoom's sequence of
+/// frames, which `still` refuses along with those of the video.
+const SEQUENCE_FLAGS: [&str; 17] = [
+    "zoom-",
+    "no-video",
+    "output-dir",
+    "run-name",
+    "resume",
+    "filename-template",
+    "keyframes",
+    "camera-path",
+    "easing",
+    "direction",
+    "initial-rotation",
+    "rotation-per-frame",
+    "motion-blur",
+    "shutter-angle",
+    "frame-parallelism",
+    "early-stop",
+    "no-early-stop",
+];
+
+/// The flags, or their prefixes, of the options of the video.
+const VIDEO_FLAGS: [&str; 11] = [
+    "encoder",
+    "format",
+    "pipe-video",
+    "fps",
+    "codec",
+    "crf",
+    "ffmpeg-arg",
+    "pad-to-even",
+    "video-out",
+    "gif-",
+    "video-sequence",
+];
+
+/// Rejects the video flags in `args` that don't apply to `encoder`, or to
+/// no video at all with `--no-video`.
+fn check_video_flags(
+    args: &[String],
+    encoder: Option<EncoderKind>,
+    no_video: bool,
+) -> Result<(), String> {
+    let used = |prefixes: &[&str]| uses_flag(args, prefixes);
+    // --fps also counts the frames of --duration, video or not.
+    let video_flags: Vec<&str> = VIDEO_FLAGS
+        .into_iter()
+        .filter(|&flag| flag != "fps" || !used(&["duration"]))
+        .collect();
+    if no_video && used(&video_flags) {
+        return Err("--no-video can't be combined with --encoder, --format or the other \
+                    options of the video"
+            .to_string());
+    }
+    match encoder {
+        Some(EncoderKind::Gif(_)) if used(&["fps"]) => {
+            Err("--fps doesn't apply to --format gif, use --gif-delay".to_string())
+        }
+        Some(EncoderKind::Internal | EncoderKind::Gif(_) | EncoderKind::Apng)
+            if used(&["codec", "crf", "ffmpeg-arg", "pad-to-even"]) =>
+        {
+            Err("--codec, --crf, --ffmpeg-arg and --pad-to-even only apply to the ffmpeg encoder"
+                .to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Parses the video encoder given with `--encoder`.
+fn parse_encoder(value: &str) -> Result<EncoderKind, String> {
+    EncoderKind::from_name(value)
+        .ok_or_else(|| format!("encoder should be ffmpeg or internal, got '{}'", value))
+}
+
+/// Parses a center given as `x,y`. The digits are kept as written so deep
+/// zooms get every one of them.
+fn parse_center(value: &str) -> Result<(String, String), String> {
+    let invalid = || format!("center should be two numbers separated by a comma, got '{}'", value);
+    let (x, y) = value.split_once(',').ok_or_else(invalid)?;
+    let (x, y) = (x.trim(), y.trim());
+    if Decimal::parse(x).is_err() || Decimal::parse(y).is_err() {
+        return Err(invalid());
+    }
+    Ok((x.to_string(), y.to_string()))
+}
+
+/// Parses the `x,y,w,h` region of `--roi`, in pixels of the frame, which
+/// is written on its own until `--roi-fill` says otherwise.
+fn parse_roi(value: &str) -> Result<Roi, String> {
+    let invalid = || {
+        format!(
+            "roi should be four integers x,y,w,h with a positive width and height, got '{}'",
+            value
+        )
+    };
+    let parts: Vec<u32> = value
+        .split(',')
+        .map(|part| part.trim().parse().ok())
+        .collect::<Option<_>>()
+        .ok_or_else(invalid)?;
+    match parts[..] {
+        [x, y, width, height] if width > 0 && height > 0 => Ok(Roi {
+            x,
+            y,
+            width,
+            height,
+            fill: false,
+        }),
+        _ => Err(invalid()),
+    }
+}
+
+/// Parses the `MIN,MAX` range given to `--<flag>`.
+fn parse_range(flag: &str, value: &str) -> Result<(f64, f64), String> {
+    let invalid = || {
+        format!("{} should be two numbers MIN,MAX with MIN < MAX, got '{}'", flag, value)
+    };
+    let (min, max) = value.split_once(',').ok_or_else(invalid)?;
+    let (min, max) = (min.trim().parse::<f64>(), max.trim().parse::<f64>());
+    match (min, max) {
+        (Ok(min), Ok(max)) if min < max && min.is_finite() && max.is_finite() => Ok((min, max)),
+        _ => Err(invalid()),
+    }
+}
+
+/// The x and y ranges of a view.
+type Ranges = ((f64, f64), (f64, f64));
+
+/// The ranges of `--x-range` and `--y-range`, which go together, or of the
+/// corners of `--view` in their place. The view can't be used with those
+/// of the flags of `placing` that were given, which place it too.
+fn ranges_of(
+    x_range: Option<(f64, f64)>,
+    y_range: Option<(f64, f64)>,
+    view: Option<Corners>,
+    placing: &[(&str, bool)],
+) -> Result<Option<Ranges>, String> {
+    if let Some(view) = view {
+        let ranges = [("x-range", x_range.is_some()), ("y-range", y_range.is_some())];
+        if let Some((flag, _)) = ranges.iter().chain(placing).find(|(_, used)| *used) {
+            return Err(format!(
+                "--view gives the corners of the view, so it can't be used with --{}",
+                flag
+            ));
+        }
+        return Ok(Some((view.x_range, view.y_range)));
+    }
+    match (x_range, y_range) {
+        (Some(x_range), Some(y_range)) => Ok(Some((x_range, y_range))),
+        (None, None) => Ok(None),
+        _ => Err("--x-range and --y-range have to be given together".to_string()),
+    }
+}
+
+/// Reads the color stops of the gradient file at `path`, named after it.
+fn read_gradient_file(path: &str) -> Result<PaletteSource, String> {
+    let spec = std::fs::read_to_string(path)
+        .map_err(|e| format!("can't read gradient file '{}': {}", path, e))?;
+    let stops = palette::parse_stops(&spec).map_err(|e| format!("{}: {}", path, e))?;
+    let name = Path::new(path).file_stem().and_then(|stem| stem.to_str()).unwrap_or("custom");
+    Ok(PaletteSource::Stops {
+        name: name.to_string(),
+        stops,
+    })
+}
+
+/// Reads and compiles the coloring script of `--color-expr`.
+fn read_script(path: &str) -> Result<ScriptArg, String> {
+    let source = std::fs::read_to_string(path)
+        .map_err(|e| format!("can't read color script '{}': {}", path, e))?;
+    let script = Script::compile(&source).map_err(|e| format!("{}: {}", path, e))?;
+    Ok(ScriptArg {
+        path: path.to_string(),
+        script,
+    })
+}
+
+/// Reads the palette of `--palette-image` from an image strip, ignoring any
+/// alpha channel, named after the file.
+fn read_palette_image(path: &str) -> Result<PaletteSource, String> {
+    let strip = image::open(path)
+        .map_err(|e| format!("can't read palette image '{}': {}", path, e))?
+        .to_rgb8();
+    let stops = palette::strip_stops(&strip).map_err(|e| format!("{}: {}", path, e))?;
+    let name = Path::new(path).file_stem().and_then(|stem| stem.to_str()).unwrap_or("custom");
+    Ok(PaletteSource::Stops {
+        name: name.to_string(),
+        stops,
+    })
+}
+
+/// Reads the palette of `--palette-map` from a Fractint `.map` file, named
+/// after it, saying what had to be fixed up in it.
+fn read_palette_map(path: &str) -> Result<PaletteSource, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("can't read palette map '{}': {}", path, e))?;
+    let (colors, warnings) = palette::parse_map(&text).map_err(|e| format!("{}: {}", path, e))?;
+    for warning in warnings {
+        events::say(format!("Warning: {}: {}", path, warning));
+    }
+    let name = Path::new(path).file_stem().and_then(|stem| stem.to_str()).unwrap_or("custom");
+    Ok(PaletteSource::Map {
+        name: name.to_string(),
+        colors,
+    })
+}
+
+/// Parses a size in bytes, with an optional K, M, G or T suffix for powers
+/// of 1024.
+fn parse_size(value: &str) -> Option<u64> {
+    let value = value.to_ascii_uppercase();
+    let (number, shift) = [("K", 10), ("M", 20), ("G", 30), ("T", 40)]
+        .into_iter()
+        .find_map(|(unit, shift)| Some((value.strip_suffix(unit)?, shift)))
+        .unwrap_or((&value, 0));
+    number.parse::<u64>().ok()?.checked_mul(1 << shift)
+}
+
+/// Parses a duration in seconds, given as a number of seconds or in hours,
+/// minutes and seconds like 8h, 90m or 1h30m.
+fn parse_duration(value: &str) -> Option<f64> {
+    if let Ok(seconds) = value.parse() {
+        return Some(seconds);
+    }
+    let (mut seconds, mut rest) = (0.0, value);
+    while !rest.is_empty() {
+        let end = rest.find(['h', 'm', 's'])?;
+        let unit = match &rest[end..end + 1] {
+            "h" => 3600.0,
+            "m" => 60.0,
+            _ => 1.0,
+        };
+        seconds += rest[..end].parse::<f64>().ok()? * unit;
+        rest = &rest[end + 1..];
+    }
+    Some(seconds)
+}
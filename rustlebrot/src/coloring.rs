@@ -0,0 +1,211 @@
+use crate::script::Needs;
+use crate::stripes::DEFAULT_DENSITY;
+use crate::trap::Trap;
+
+/// The per-pixel value mapped onto the gradient, as chosen with `--coloring`
+/// or `--trap`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Coloring {
+    /// Iterations until escape, relative to max_iter.
+    EscapeTime,
+    /// Escape time interpolated between iterations, without the bands.
+    Smooth,
+    /// Escape time equalized over the frame, so every color covers about
+    /// as many exterior pixels.
+    Histogram,
+    /// Exterior distance estimate, log-scaled relative to the pixel size.
+    /// Points within half a pixel of the set are drawn as boundary black.
+    Distance,
+    /// The closest the orbit comes to a trap shape. Unlike the other modes
+    /// this colors the interior as well.
+    Trap(Trap),
+    /// Smooth escape time along with the angle of `z` as it escaped, whose
+    /// colors are blended as `Phase` says. The angle jumps where the
+    /// orbit's last step crosses the negative real axis, which draws bands
+    /// across every escape time band.
+    Phase,
+    /// Binary decomposition: smooth escape time, with the pixels whose `z`
+    /// escaped in every other of `2^k` sectors of angle shaded dark, for
+    /// `Binary(k)`. The tone flips from each escape time band to the next,
+    /// so the sector edges line up into the external field lines.
+    Binary(u32),
+    /// The stripe average of the orbit at the given density, see
+    /// `StripeAverage`, which draws flowing lines along the escape time
+    /// bands without their edges.
+    Stripes(f64),
+    /// Colored by the script of `--color-expr`, from the smooth escape time
+    /// and the orbit, with what it `Needs` worked out as well.
+    Script(Needs),
+}
+
+/// The most sectors binary decomposition splits the angles into, as a
+/// power of 2.
+pub const MAX_BINARY_SECTORS: u32 = 16;
+
+/// The bailout of the colorings that look at the angle of `z`, binary
+/// decomposition and stripes, unless `--bailout` says otherwise. Angles
+/// only settle into clean lines once `|z|` is large enough that the last
+/// steps hardly bend them.
+pub const ANGLE_BAILOUT: f64 = 1000.0;
+
+/// How phase coloring blends the color of the escape time with the color
+/// of the angle, as set with `--phase-weight` and `--phase-turns`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Phase {
+    /// The share of the angle's color, from 0 for the escape time's alone
+    /// to 1 for the angle's alone.
+    pub weight: f64,
+    /// How many times the gradient runs around a full turn of the angle.
+    pub turns: f64,
+}
+
+impl Default for Phase {
+    fn default() -> Self {
+        Phase {
+            weight: 0.5,
+            turns: 1.0,
+        }
+    }
+}
+
+/// How escape times are mapped onto the gradient before it cycles, as
+/// chosen with `--transfer`, relative to the escape time the gradient is
+/// spread over.
+///
+/// Every transfer takes 0 to the first color and the palette's escape time
+/// to the last, and keeps the order of escape times in between. Interior
+/// points are told apart before any transfer, so it never moves the edge
+/// of the set. Histogram coloring spreads escape times by their
+/// distribution instead, so it takes no transfer.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Transfer {
+    /// In proportion to the escape time, which leaves most of the exterior
+    /// in the first few colors at high max_iter.
+    Linear,
+    /// The square root of the linear position.
+    Sqrt,
+    /// The logarithm of one more than the escape time, which spreads the
+    /// bands of the lowest escape times out the most.
+    Log,
+    /// The linear position to the given power: below 1 spreads the low
+    /// escape times out, above 1 the high ones.
+    Power(f64),
+}
+
+impl Transfer {
+    /// Parses `linear`, `sqrt`, `log` or `power:GAMMA`.
+    pub fn from_spec(spec: &str) -> Result<Transfer, String> {
+        match spec {
+            "linear" => Ok(Transfer::Linear),
+            "sqrt" => Ok(Transfer::Sqrt),
+            "log" => Ok(Transfer::Log),
+            _ => match spec.strip_prefix("power:") {
+                Some(gamma) => {
+                    let gamma: f64 = gamma.parse().map_err(|_| {
+                        format!("the gamma of power should be a float, got '{}'", gamma)
+                    })?;
+                    if !(gamma > 0.0 && gamma.is_finite()) {
+                        return Err(format!("the gamma of power should be positive, got {}", gamma));
+                    }
+                    Ok(Transfer::Power(gamma))
+                }
+                None => Err(format!(
+                    "transfer should be linear, sqrt, log or power:GAMMA, got '{}'",
+                    spec
+                )),
+            },
+        }
+    }
+
+    /// The gradient position of the escape time `value`, which runs from 0
+    /// at an escape time of 0 to 1 at an escape time of `span`.
+    #[inline]
+    pub fn position(self, value: f64, span: f64) -> f64 {
+        match self {
+            Transfer::Linear => value / span,
+            Transfer::Sqrt => (value.max(0.0) / span).sqrt(),
+            Transfer::Log => value.max(0.0).ln_1p() / span.ln_1p(),
+            Transfer::Power(gamma) => (value.max(0.0) / span).powf(gamma),
+        }
+    }
+}
+
+impl Coloring {
+    pub fn from_name(name: &str) -> Option<Coloring> {
+        match name {
+            "escape" => Some(Coloring::EscapeTime),
+            "smooth" => Some(Coloring::Smooth),
+            "histogram" => Some(Coloring::Histogram),
+            "distance" => Some(Coloring::Distance),
+            "trap" => Some(Coloring::Trap(Trap::Point(0.0, 0.0))),
+            "phase" => Some(Coloring::Phase),
+            "binary" => Some(Coloring::Binary(1)),
+            "stripes" => Some(Coloring::Stripes(DEFAULT_DENSITY)),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Coloring::EscapeTime => "escape",
+            Coloring::Smooth => "smooth",
+            Coloring::Histogram => "histogram",
+            Coloring::Distance => "distance",
+            Coloring::Trap(_) => "trap",
+            Coloring::Phase => "phase",
+            Coloring::Binary(_) => "binary",
+            Coloring::Stripes(_) => "stripes",
+            Coloring::Script(_) => "script",
+        }
+    }
+
+    /// Whether the values are computed from escape times alone, which is
+    /// what the vectorized loops, the series approximation, subdivision
+    /// and reuse between frames are made for.
+    pub fn uses_escape_times(self) -> bool {
+        match self {
+            Coloring::EscapeTime
+            | Coloring::Smooth
+            | Coloring::Histogram
+            | Coloring::Phase
+            | Coloring::Binary(_) => true,
+            Coloring::Distance
+            | Coloring::Trap(_)
+            | Coloring::Stripes(_)
+            | Coloring::Script(_) => false,
+        }
+    }
+
+    /// Whether the values are escape times spread over the gradient in
+    /// proportion, which a `Transfer` can spread otherwise.
+    pub fn takes_transfer(self) -> bool {
+        match self {
+            Coloring::EscapeTime | Coloring::Smooth | Coloring::Phase | Coloring::Binary(_) => true,
+            Coloring::Histogram
+            | Coloring::Distance
+            | Coloring::Trap(_)
+            | Coloring::Stripes(_)
+            | Coloring::Script(_) => false,
+        }
+    }
+
+    /// The bailout this coloring is rendered with when none is given.
+    pub fn default_bailout(self) -> f64 {
+        match self {
+            Coloring::Binary(_) | Coloring::Stripes(_) => ANGLE_BAILOUT,
+            _ => 2.0,
+        }
+    }
+
+    /// Whether values computed for this coloring can be colored as `other`
+    /// without computing them again. Histogram coloring only ranks escape
+    /// times, so it can take either kind, and its whole escape times can be
+    /// spread linearly instead.
+    pub fn recolors_as(self, other: Coloring) -> bool {
+        match (self, other) {
+            (Coloring::EscapeTime | Coloring::Smooth, Coloring::Histogram) => true,
+            (Coloring::Histogram, Coloring::EscapeTime) => true,
+            _ => self.name() == other.name(),
+        }
+    }
+}
@@ -0,0 +1,182 @@
+//! Complex numbers, as the generic `Complex<T>` the kernels iterate in.
+//! The `Fractal` traits take and hand back `(re, im)` tuples, which convert
+//! to and from `Complex<f64>` with `From`.
+
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+/// The real numbers a `Complex` is made of: f32, f64 or `DoubleDouble`.
+///
+/// Every one is `Copy`, so the kernels written for `Complex<T>` compile to
+/// the same loop over plain floats as written out by hand. Arbitrary
+/// precision numbers are too large to copy around and keep to their own
+/// loops in `bigfloat` and `perturbation`.
+pub trait Scalar:
+    Copy + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> + Neg<Output = Self>
+{
+    const ZERO: Self;
+    const ONE: Self;
+
+    /// The square, which some scalars work out faster than `self * self`.
+    #[inline(always)]
+    fn sqr(self) -> Self {
+        self * self
+    }
+
+    /// Twice the number, which some scalars work out faster than
+    /// `self + self`.
+    #[inline(always)]
+    fn double(self) -> Self {
+        self + self
+    }
+
+    /// The number as near as f64 gets it for the escape test and the
+    /// smooth value, which only need its leading digits.
+    fn leading(self) -> f64;
+}
+
+impl Scalar for f32 {
+    const ZERO: f32 = 0.0;
+    const ONE: f32 = 1.0;
+
+    #[inline(always)]
+    fn leading(self) -> f64 {
+        self as f64
+    }
+}
+
+impl Scalar for f64 {
+    const ZERO: f64 = 0.0;
+    const ONE: f64 = 1.0;
+
+    #[inline(always)]
+    fn leading(self) -> f64 {
+        self
+    }
+}
+
+/// A complex number `re + im i`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Complex<T> {
+    pub re: T,
+    pub im: T,
+}
+
+impl<T: Scalar> Complex<T> {
+    pub const ZERO: Complex<T> = Complex::new(T::ZERO, T::ZERO);
+    pub const ONE: Complex<T> = Complex::new(T::ONE, T::ZERO);
+
+    #[inline(always)]
+    pub const fn new(re: T, im: T) -> Complex<T> {
+        Complex { re, im }
+    }
+
+    #[inline(always)]
+    pub fn conj(self) -> Complex<T> {
+        Complex::new(self.re, -self.im)
+    }
+
+    /// The squared magnitude, `re^2 + im^2`.
+    #[inline(always)]
+    pub fn norm_sqr(self) -> T {
+        self.re.sqr() + self.im.sqr()
+    }
+
+    /// The square, `(re^2 - im^2) + 2 re im i`, in three products where
+    /// `self * self` takes four.
+    #[inline(always)]
+    pub fn sqr(self) -> Complex<T> {
+        Complex::new(self.re.sqr() - self.im.sqr(), self.re.double() * self.im)
+    }
+
+    /// `self` to the power `n`, by repeated squaring.
+    #[inline]
+    pub fn powi(self, n: u32) -> Complex<T> {
+        let mut result = Complex::ONE;
+        let mut base = self;
+        let mut exponent = n;
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result * base;
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = base.sqr();
+            }
+        }
+        result
+    }
+
+    /// The number times the real `factor`.
+    #[inline(always)]
+    pub fn scale(self, factor: T) -> Complex<T> {
+        Complex::new(self.re * factor, self.im * factor)
+    }
+}
+
+impl<T: Scalar> Add for Complex<T> {
+    type Output = Complex<T>;
+
+    #[inline(always)]
+    fn add(self, other: Complex<T>) -> Complex<T> {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl<T: Scalar> Sub for Complex<T> {
+    type Output = Complex<T>;
+
+    #[inline(always)]
+    fn sub(self, other: Complex<T>) -> Complex<T> {
+        Complex::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl<T: Scalar> Mul for Complex<T> {
+    type Output = Complex<T>;
+
+    #[inline(always)]
+    fn mul(self, other: Complex<T>) -> Complex<T> {
+        Complex::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+}
+
+/// Division, only for f64: the kernels that iterate in the other scalars
+/// never divide.
+impl Div for Complex<f64> {
+    type Output = Complex<f64>;
+
+    #[inline]
+    fn div(self, other: Complex<f64>) -> Complex<f64> {
+        let denominator = other.norm_sqr();
+        Complex::new(
+            (self.re * other.re + self.im * other.im) / denominator,
+            (self.im * other.re - self.re * other.im) / denominator,
+        )
+    }
+}
+
+impl<T: Scalar> Neg for Complex<T> {
+    type Output = Complex<T>;
+
+    #[inline(always)]
+    fn neg(self) -> Complex<T> {
+        Complex::new(-self.re, -self.im)
+    }
+}
+
+impl<T> From<(T, T)> for Complex<T> {
+    #[inline(always)]
+    fn from((re, im): (T, T)) -> Complex<T> {
+        Complex { re, im }
+    }
+}
+
+impl<T> From<Complex<T>> for (T, T) {
+    #[inline(always)]
+    fn from(z: Complex<T>) -> (T, T) {
+        (z.re, z.im)
+    }
+}
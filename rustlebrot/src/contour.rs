@@ -0,0 +1,282 @@
+use crate::coloring::Coloring;
+use crate::palette;
+use crate::render::{from_linear, to_linear, EscapeBuffer, Sample};
+use rayon::prelude::*;
+
+/// A line drawn over the coloring of a frame, as `--contours` and
+/// `--silhouette` style them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Line {
+    /// How wide the line is, in pixels whatever the supersampling.
+    pub width: f64,
+    pub color: (u8, u8, u8),
+}
+
+/// Contour lines, as given with `--contours`: the boundaries between bands
+/// of values drawn as lines, like the isolines of a topographic map of the
+/// escape time.
+///
+/// The lines are found from the values of the samples, so they go with
+/// any palette, and are placed between two samples where the value they
+/// cross would fall between them, which keeps them smooth with smooth
+/// coloring.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Contours {
+    /// The values between one line and the next: escape times, or the
+    /// distance in pixels with distance coloring.
+    pub every: f64,
+    pub line: Line,
+    /// The color the points outside the set are filled with in place of
+    /// their coloring, to draw the lines alone, if any.
+    pub background: Option<(u8, u8, u8)>,
+}
+
+impl Default for Contours {
+    fn default() -> Self {
+        Contours {
+            every: 5.0,
+            line: Line {
+                width: 1.0,
+                color: (0, 0, 0),
+            },
+            background: None,
+        }
+    }
+}
+
+impl Contours {
+    /// Parses contours such as `every=5,width=1,color=#000000`, or `on`
+    /// for the default ones. Fields that aren't given keep their defaults.
+    pub fn from_spec(spec: &str) -> Result<Contours, String> {
+        let mut contours = Contours::default();
+        if spec == "on" {
+            return Ok(contours);
+        }
+        for field in spec.split(',') {
+            let invalid = || format!("contours takes fields like every=5, got '{}'", field);
+            let (name, value) = field.split_once('=').ok_or_else(invalid)?;
+            let value = value.trim();
+            match name.trim() {
+                "every" => {
+                    let every = value.parse().ok().filter(|&every: &f64| every > 0.0);
+                    let every = every.filter(|every| every.is_finite());
+                    contours.every = every.ok_or_else(|| {
+                        format!("contours every should be positive, got '{}'", value)
+                    })?
+                }
+                "background" => contours.background = Some(palette::parse_color(value)?),
+                other => {
+                    if !contours.line.field("contours", other, value)? {
+                        return Err(format!(
+                            "unknown contours field '{}', expected every, width, color or \
+                             background",
+                            other
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(contours)
+    }
+
+    /// How much of every sample of `buffer` the lines cover, in the order
+    /// of its values, from 0 for none of it to 1 for all.
+    pub fn coverage(&self, buffer: &EscapeBuffer) -> Vec<f64> {
+        let unit = match buffer.coloring {
+            Coloring::Distance => buffer.samples as f64,
+            _ => 1.0,
+        };
+        let every = self.every;
+        let crossings = crossings(buffer, |here, there| {
+            let (here, there) = (level(here)? / unit, level(there)? / unit);
+            let band = (here / every).floor();
+            if band == (there / every).floor() {
+                return None;
+            }
+            // The line nearest to this sample of those between the two.
+            let value = match there > here {
+                true => (band + 1.0) * every,
+                false => band * every,
+            };
+            Some(((value - here) / (there - here)).clamp(0.0, 1.0))
+        });
+        cover(buffer, &crossings, self.line.width * buffer.samples as f64 / 2.0)
+    }
+
+    /// `rgb` of `sample`, with channels between 0 and 1, with the lines
+    /// over `coverage` of it and the background under them.
+    #[inline]
+    pub fn apply(&self, sample: Sample, coverage: f64, rgb: [f64; 3]) -> [f64; 3] {
+        let rgb = match (self.background, level(sample)) {
+            (Some(background), Some(_)) => channels(background),
+            _ => rgb,
+        };
+        self.line.over(coverage, rgb)
+    }
+}
+
+impl Line {
+    /// Parses the line of `--silhouette`, such as `width=1,color=#ffffff`,
+    /// or `on` for a white line a pixel wide.
+    pub fn silhouette_from_spec(spec: &str) -> Result<Line, String> {
+        let mut line = Line {
+            width: 1.0,
+            color: (255, 255, 255),
+        };
+        if spec == "on" {
+            return Ok(line);
+        }
+        for field in spec.split(',') {
+            let invalid = || format!("silhouette takes fields like width=1, got '{}'", field);
+            let (name, value) = field.split_once('=').ok_or_else(invalid)?;
+            if !line.field("silhouette", name.trim(), value.trim())? {
+                return Err(format!(
+                    "unknown silhouette field '{}', expected width or color",
+                    name.trim()
+                ));
+            }
+        }
+        Ok(line)
+    }
+
+    /// Sets the field `name` of the line of `flag` to `value`, returning
+    /// whether it is one.
+    fn field(&mut self, flag: &str, name: &str, value: &str) -> Result<bool, String> {
+        match name {
+            "width" => {
+                self.width = value
+                    .parse()
+                    .ok()
+                    .filter(|&width: &f64| width > 0.0 && width.is_finite())
+                    .ok_or_else(|| format!("{} width should be positive, got '{}'", flag, value))?
+            }
+            "color" => self.color = palette::parse_color(value)?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// How much of every sample of `buffer` the line around the set
+    /// covers, in the order of its values. The line runs along the outside
+    /// of the set, so it leaves the shape of the set as it is.
+    pub fn silhouette(&self, buffer: &EscapeBuffer) -> Vec<f64> {
+        // The edge of the set is halfway to the samples in it.
+        let crossings = crossings(buffer, |here, there| {
+            (level(here).is_some() && level(there).is_none()).then_some(0.5)
+        });
+        let mut coverage = cover(buffer, &crossings, self.width * buffer.samples as f64);
+        for (coverage, &sample) in coverage.iter_mut().zip(&buffer.values) {
+            if level(sample).is_none() {
+                *coverage = 0.0;
+            }
+        }
+        coverage
+    }
+
+    /// `rgb`, with channels between 0 and 1, under `coverage` of the line,
+    /// mixed in linear light as the samples of a pixel are.
+    #[inline]
+    pub fn over(&self, coverage: f64, rgb: [f64; 3]) -> [f64; 3] {
+        if coverage <= 0.0 {
+            return rgb;
+        }
+        let line = channels(self.color);
+        let mut mixed = rgb;
+        for (channel, line) in mixed.iter_mut().zip(line) {
+            let intensity = to_linear(*channel) * (1.0 - coverage) + to_linear(line) * coverage;
+            *channel = from_linear(intensity);
+        }
+        mixed
+    }
+}
+
+/// The value of a sample outside the set, which the contours are levels
+/// of.
+pub(crate) fn level(sample: Sample) -> Option<f64> {
+    match sample {
+        Sample::Value(value)
+        | Sample::Phase { value, .. }
+        | Sample::Root { iterations: value, .. }
+        | Sample::Exponent(value) => Some(value),
+        Sample::Interior | Sample::Attractor { .. } | Sample::Boundary => None,
+    }
+}
+
+fn channels((r, g, b): (u8, u8, u8)) -> [f64; 3] {
+    [r, g, b].map(|channel| channel as f64 / 255.0)
+}
+
+/// The nearest point to every sample of `buffer` of a line that
+/// `crossing` puts between it and a neighbor, from the sample, in samples,
+/// for the samples with one next to them.
+///
+/// `crossing` is given a sample and its neighbor and tells how far along
+/// the way to the neighbor a line crosses, if one does. A line crossing
+/// both across and down is taken as straight through both. The diagonal
+/// neighbors are looked at too, for lines that pass close by slanting
+/// between the others.
+fn crossings<C>(buffer: &EscapeBuffer, crossing: C) -> Vec<Option<(f64, f64)>>
+where
+    C: Fn(Sample, Sample) -> Option<f64> + Sync,
+{
+    let (width, height) = (buffer.width as usize, buffer.height as usize);
+    (0..width * height)
+        .into_par_iter()
+        .map(|index| {
+            let (x, y) = (index % width, index / width);
+            let here = buffer.values[index];
+            let neighbor = |(dx, dy): (isize, isize)| {
+                let (x, y) = (x.checked_add_signed(dx)?, y.checked_add_signed(dy)?);
+                (x < width && y < height).then(|| buffer.values[y * width + x])
+            };
+            // The nearest crossing towards either of `offsets`, and which.
+            let nearest = |offsets: [(isize, isize); 2]| {
+                let crossings = offsets.into_iter().filter_map(|offset| {
+                    Some((crossing(here, neighbor(offset)?)?, offset))
+                });
+                crossings.min_by(|a, b| a.0.total_cmp(&b.0))
+            };
+            let along = |t: f64, (dx, dy): (isize, isize)| (t * dx as f64, t * dy as f64);
+            let straight = match (nearest([(-1, 0), (1, 0)]), nearest([(0, -1), (0, 1)])) {
+                (Some((a, (dx, _))), Some((b, (_, dy)))) => {
+                    // The foot of the sample on the line through both.
+                    let square = (a * a + b * b).max(f64::MIN_POSITIVE);
+                    Some((dx as f64 * a * b * b / square, dy as f64 * a * a * b / square))
+                }
+                (Some((t, offset)), None) | (None, Some((t, offset))) => Some(along(t, offset)),
+                (None, None) => None,
+            };
+            let slanting = [nearest([(-1, -1), (1, 1)]), nearest([(-1, 1), (1, -1)])];
+            let slanting = slanting.into_iter().flatten().map(|(t, offset)| along(t, offset));
+            let points = straight.into_iter().chain(slanting);
+            points.min_by(|a, b| a.0.hypot(a.1).total_cmp(&b.0.hypot(b.1)))
+        })
+        .collect()
+}
+
+/// How much of every sample of `buffer` lines cover that reach as far as
+/// `reach` samples either side of where `crossings` puts them, with the
+/// sample at the edge of the reach covered as far as it is inside it.
+fn cover(buffer: &EscapeBuffer, crossings: &[Option<(f64, f64)>], reach: f64) -> Vec<f64> {
+    let (width, height) = (buffer.width as usize, buffer.height as usize);
+    // The samples next to a line are within a sample of it.
+    let radius = (reach + 1.5).ceil() as usize;
+    (0..width * height)
+        .into_par_iter()
+        .map(|index| {
+            let (x, y) = (index % width, index / width);
+            let mut distance = f64::INFINITY;
+            for other_y in y.saturating_sub(radius)..(y + radius + 1).min(height) {
+                for other_x in x.saturating_sub(radius)..(x + radius + 1).min(width) {
+                    // The line runs through the point nearest the other
+                    // sample, which is no nearer this one than the line.
+                    if let Some((dx, dy)) = crossings[other_y * width + other_x] {
+                        let point = (other_x as f64 + dx, other_y as f64 + dy);
+                        distance = distance.min((point.0 - x as f64).hypot(point.1 - y as f64));
+                    }
+                }
+            }
+            (reach + 0.5 - distance).clamp(0.0, 1.0)
+        })
+        .collect()
+}
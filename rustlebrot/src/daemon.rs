@@ -0,0 +1,609 @@
+use crate::error::RustlebrotError;
+use crate::interrupt;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Version of the queue file schema. It changes whenever a field is
+/// removed or changes meaning.
+pub const QUEUE_VERSION: u32 = 1;
+
+/// The socket the daemon listens on, and clients connect to, unless told
+/// otherwise.
+pub const DEFAULT_SOCKET: &str = "rust_data/daemon.sock";
+
+/// Where the daemon keeps its jobs unless told otherwise.
+pub const DEFAULT_QUEUE: &str = "rust_data/daemon_queue.json";
+
+/// The longest request read, far more than the arguments of any job.
+const MAX_REQUEST: u64 = 1 << 20;
+
+/// How often the daemon looks for Ctrl-C while it waits.
+const POLL: Duration = Duration::from_millis(200);
+
+/// Where a daemon listens and its clients connect.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Endpoint {
+    /// A Unix socket at the path.
+    Socket(String),
+    /// A TCP address like `127.0.0.1:7878`.
+    Tcp(String),
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Endpoint::Socket(path) => write!(f, "{}", path),
+            Endpoint::Tcp(address) => write!(f, "tcp://{}", address),
+        }
+    }
+}
+
+/// A render to queue: the arguments of a zoom, as they would follow the
+/// program name on the command line. Paths in them are relative to the
+/// directory the daemon runs in.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct JobSpec {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub args: Vec<String>,
+}
+
+/// What a client asks of a daemon, as a line of JSON with a `request`
+/// field naming it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "request", rename_all = "snake_case")]
+pub enum Request {
+    /// Queue the job, behind the ones queued already.
+    Submit { job: JobSpec },
+    /// Every job the daemon knows of, or the one of `id`.
+    Status {
+        #[serde(default)]
+        id: Option<u64>,
+    },
+    /// Take a queued job off the queue, or stop a running one the way
+    /// Ctrl-C stops a run, after the frames in progress.
+    Cancel { id: u64 },
+}
+
+/// What a daemon answers a request with, as a line of JSON with a
+/// `response` field naming it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "response", rename_all = "snake_case")]
+pub enum Response {
+    Submitted { id: u64 },
+    Status { jobs: Vec<Job> },
+    /// The job is off the queue, or has been asked to stop when it is
+    /// running.
+    Cancelled { id: u64 },
+    Error { message: String },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum State {
+    Queued,
+    Running,
+    Finished,
+    Failed,
+    Cancelled,
+}
+
+impl State {
+    pub fn name(self) -> &'static str {
+        match self {
+            State::Queued => "queued",
+            State::Running => "running",
+            State::Finished => "finished",
+            State::Failed => "failed",
+            State::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// A job of a daemon, as it is reported and kept in the queue file.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Job {
+    pub id: u64,
+    #[serde(flatten)]
+    pub spec: JobSpec,
+    pub state: State,
+    /// The frames saved so far, including those kept by `--resume`.
+    #[serde(default)]
+    pub frames_done: u32,
+    /// The frames of the zoom, once it has started.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frames: Option<u32>,
+    /// The files written: the frames, then the videos.
+    #[serde(default)]
+    pub paths: Vec<String>,
+    /// Why the job failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Whether a run of the job was cut short by the daemon stopping, so
+    /// that the next one replaces the frames it left.
+    #[serde(default)]
+    pub interrupted: bool,
+}
+
+/// The queue file.
+#[derive(Serialize, Deserialize)]
+struct QueueFile {
+    version: u32,
+    next_id: u64,
+    jobs: Vec<Job>,
+}
+
+/// Checks a job before it is queued.
+pub type Check = fn(&JobSpec) -> Result<(), String>;
+
+/// The jobs of a daemon, queued and done, kept in a file so a restart
+/// picks up where the daemon stopped.
+pub struct Queue {
+    path: String,
+    next_id: u64,
+    jobs: Vec<Job>,
+    /// The process of the running job.
+    running: Option<u32>,
+    /// The running job a cancel was sent to.
+    cancelling: Option<u64>,
+    check: Check,
+}
+
+impl Queue {
+    /// Opens the queue file at `path`, or starts an empty queue when there
+    /// is none. Jobs that were running go back to the queue. `check` is
+    /// what jobs are checked with when they are submitted.
+    pub fn open(path: &str, check: Check) -> Result<Queue, RustlebrotError> {
+        let mut queue = Queue {
+            path: path.to_string(),
+            next_id: 1,
+            jobs: Vec::new(),
+            running: None,
+            cancelling: None,
+            check,
+        };
+        if !Path::new(path).exists() {
+            return Ok(queue);
+        }
+        let json = fs::read_to_string(path).map_err(|e| RustlebrotError::read(path, e))?;
+        let file: QueueFile =
+            serde_json::from_str(&json).map_err(|e| RustlebrotError::format(path, e))?;
+        if file.version > QUEUE_VERSION {
+            return Err(RustlebrotError::format(
+                path,
+                format!(
+                    "the queue is of version {}, newer than this build reads, {}",
+                    file.version, QUEUE_VERSION
+                ),
+            ));
+        }
+        queue.next_id = file.next_id.max(file.jobs.iter().map(|job| job.id + 1).max().unwrap_or(1));
+        queue.jobs = file.jobs;
+        for job in queue.jobs.iter_mut().filter(|job| job.state == State::Running) {
+            job.requeue();
+        }
+        Ok(queue)
+    }
+
+    /// The jobs waiting to run.
+    pub fn queued(&self) -> usize {
+        self.jobs.iter().filter(|job| job.state == State::Queued).count()
+    }
+
+    /// Writes the queue file, next to it first, so a failed write leaves
+    /// the old one.
+    fn save(&self) -> Result<(), RustlebrotError> {
+        let file = QueueFile {
+            version: QUEUE_VERSION,
+            next_id: self.next_id,
+            jobs: self.jobs.clone(),
+        };
+        let json = serde_json::to_string_pretty(&file).expect("queues serialize to JSON") + "\n";
+        let partial = format!("{}.part", self.path);
+        fs::write(&partial, json)
+            .and_then(|_| fs::rename(&partial, &self.path))
+            .map_err(|e| RustlebrotError::write(&self.path, e))
+    }
+
+    /// Saves the queue, warning instead of failing, as a daemon that can't
+    /// save its queue can still run it.
+    fn keep(&self) {
+        if let Err(e) = self.save() {
+            eprintln!("Warning: {}", e);
+        }
+    }
+
+    fn job_mut(&mut self, id: u64) -> &mut Job {
+        self.jobs.iter_mut().find(|job| job.id == id).expect("jobs are never removed")
+    }
+
+    fn answer(&mut self, request: Request) -> Response {
+        let error = |message: String| Response::Error { message };
+        match request {
+            Request::Submit { job } => {
+                if let Err(e) = (self.check)(&job) {
+                    return error(e);
+                }
+                let id = self.next_id;
+                self.next_id += 1;
+                self.jobs.push(Job {
+                    id,
+                    spec: job,
+                    state: State::Queued,
+                    frames_done: 0,
+                    frames: None,
+                    paths: Vec::new(),
+                    error: None,
+                    interrupted: false,
+                });
+                self.keep();
+                Response::Submitted { id }
+            }
+            Request::Status { id: None } => Response::Status {
+                jobs: self.jobs.clone(),
+            },
+            Request::Status { id: Some(id) } => match self.jobs.iter().find(|job| job.id == id) {
+                Some(job) => Response::Status {
+                    jobs: vec![job.clone()],
+                },
+                None => error(format!("there is no job {}", id)),
+            },
+            Request::Cancel { id } => {
+                let Some(job) = self.jobs.iter_mut().find(|job| job.id == id) else {
+                    return error(format!("there is no job {}", id));
+                };
+                match job.state {
+                    State::Queued => {
+                        job.state = State::Cancelled;
+                        self.keep();
+                        Response::Cancelled { id }
+                    }
+                    State::Running => {
+                        let pid = self.running.expect("running jobs have a process");
+                        match interrupt_process(pid) {
+                            Ok(()) => {
+                                self.cancelling = Some(id);
+                                Response::Cancelled { id }
+                            }
+                            Err(e) => error(format!("can't stop job {}: {}", id, e)),
+                        }
+                    }
+                    state => error(format!("job {} is {} already", id, state.name())),
+                }
+            }
+        }
+    }
+}
+
+impl Job {
+    /// Puts the job back on the queue after its run was cut short.
+    fn requeue(&mut self) {
+        self.state = State::Queued;
+        self.frames_done = 0;
+        self.frames = None;
+        self.paths.clear();
+        self.interrupted = true;
+    }
+
+    /// Takes in a line the run of the job printed, an event of
+    /// `--progress-format json`.
+    fn progress(&mut self, line: &str) {
+        let Ok(event) = serde_json::from_str::<serde_json::Value>(line) else {
+            return;
+        };
+        match event["event"].as_str() {
+            Some("run_started") => {
+                let frame = |key: &str| event[key].as_u64().unwrap_or(0) as u32;
+                self.frames = Some(frame("zoom_end").saturating_sub(frame("zoom_start")));
+                let resumed = event["resumed"].as_array().map_or(0, |resumed| resumed.len());
+                self.frames_done = resumed as u32;
+            }
+            Some("frame_completed") => {
+                self.frames_done += 1;
+                let paths = event["paths"].as_array().into_iter().flatten();
+                self.paths.extend(paths.filter_map(|path| path.as_str().map(String::from)));
+            }
+            Some("video_completed") => {
+                self.paths.extend(event["path"].as_str().map(String::from));
+            }
+            Some("error") => self.error = event["message"].as_str().map(String::from),
+            _ => {}
+        }
+    }
+}
+
+/// A daemon's queue, shared by the connections and the jobs.
+struct Daemon {
+    queue: Mutex<Queue>,
+    /// Signalled when a job is submitted.
+    submitted: Condvar,
+}
+
+/// A bound endpoint.
+pub enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Socket(UnixListener, String),
+}
+
+impl Listener {
+    /// Listens on `endpoint`. A socket file left by a daemon that is gone is
+    /// replaced, one a daemon still answers on is not.
+    pub fn bind(endpoint: &Endpoint) -> Result<Listener, RustlebrotError> {
+        let failed = |e: io::Error| {
+            RustlebrotError::System(format!("can't listen on {}: {}", endpoint, e))
+        };
+        match endpoint {
+            Endpoint::Tcp(address) => {
+                Ok(Listener::Tcp(TcpListener::bind(address).map_err(failed)?))
+            }
+            #[cfg(unix)]
+            Endpoint::Socket(path) => {
+                if Path::new(path).exists() {
+                    if UnixStream::connect(path).is_ok() {
+                        return Err(RustlebrotError::System(format!(
+                            "a daemon is listening on {} already",
+                            path
+                        )));
+                    }
+                    fs::remove_file(path).map_err(failed)?;
+                }
+                Ok(Listener::Socket(UnixListener::bind(path).map_err(failed)?, path.clone()))
+            }
+            #[cfg(not(unix))]
+            Endpoint::Socket(_) => Err(RustlebrotError::Argument(
+                "Unix sockets are only on unix; listen on TCP with --listen ADDR:PORT".to_string(),
+            )),
+        }
+    }
+
+    /// Answers every connection, each on a thread of its own, until the
+    /// process ends.
+    fn accept(self, daemon: Arc<Daemon>) {
+        let connected = move |stream: io::Result<Box<dyn Stream>>| match stream {
+            Ok(stream) => {
+                let daemon = Arc::clone(&daemon);
+                // A client that hangs up early missed its answer, which is
+                // nothing to report.
+                thread::spawn(move || answer(&daemon, stream).ok());
+            }
+            Err(e) => eprintln!("Warning: failed to accept a connection: {}", e),
+        };
+        match self {
+            Listener::Tcp(listener) => {
+                for stream in listener.incoming() {
+                    connected(stream.map(|stream| Box::new(stream) as Box<dyn Stream>));
+                }
+            }
+            #[cfg(unix)]
+            Listener::Socket(listener, _) => {
+                for stream in listener.incoming() {
+                    connected(stream.map(|stream| Box::new(stream) as Box<dyn Stream>));
+                }
+            }
+        }
+    }
+}
+
+/// A connection of a client.
+trait Stream: Read + Write + Send {}
+
+impl<S: Read + Write + Send> Stream for S {}
+
+/// Answers the one request on `stream`.
+fn answer(daemon: &Daemon, mut stream: Box<dyn Stream>) -> io::Result<()> {
+    let mut line = String::new();
+    BufReader::new((&mut stream).take(MAX_REQUEST)).read_line(&mut line)?;
+    let response = match serde_json::from_str::<Request>(&line) {
+        Ok(request) => {
+            let submit = matches!(request, Request::Submit { .. });
+            let response = daemon.queue.lock().unwrap().answer(request);
+            if submit {
+                daemon.submitted.notify_all();
+            }
+            response
+        }
+        Err(e) => Response::Error {
+            message: format!("can't read the request: {}", e),
+        },
+    };
+    let json = serde_json::to_string(&response).expect("responses serialize to JSON");
+    stream.write_all(format!("{}\n", json).as_bytes())
+}
+
+/// Runs the jobs of `queue` one after the other as they come in on
+/// `listener`, until Ctrl-C. A job running then is stopped the way Ctrl-C
+/// stops a run and goes back to the queue.
+pub fn run(listener: Listener, queue: Queue) -> Result<(), RustlebrotError> {
+    #[cfg(unix)]
+    let socket = match &listener {
+        Listener::Socket(_, path) => Some(path.clone()),
+        Listener::Tcp(_) => None,
+    };
+    let daemon = Arc::new(Daemon {
+        queue: Mutex::new(queue),
+        submitted: Condvar::new(),
+    });
+    let accepting = Arc::clone(&daemon);
+    thread::spawn(move || listener.accept(accepting));
+
+    let program = std::env::current_exe()
+        .map_err(|e| RustlebrotError::System(format!("can't find the program to run: {}", e)))?;
+    loop {
+        let next = {
+            let mut queue = daemon.queue.lock().unwrap();
+            loop {
+                if interrupt::requested() {
+                    break None;
+                }
+                if let Some(job) = queue.jobs.iter().find(|job| job.state == State::Queued) {
+                    break Some(job.clone());
+                }
+                queue = daemon.submitted.wait_timeout(queue, POLL).unwrap().0;
+            }
+        };
+        let Some(job) = next else {
+            break;
+        };
+        run_job(&daemon, &program, job);
+    };
+    #[cfg(unix)]
+    if let Some(path) = socket {
+        let _ = fs::remove_file(path);
+    }
+    Ok(())
+}
+
+/// Runs `job` in a process of `program` of its own, following its progress
+/// by the events it prints.
+fn run_job(daemon: &Daemon, program: &Path, job: Job) {
+    let mut command = Command::new(program);
+    command.arg("render").args(&job.spec.args);
+    // What the interrupted run left is the job's own.
+    let redo = ["--overwrite", "--resume"];
+    if job.interrupted && !job.spec.args.iter().any(|arg| redo.contains(&arg.as_str())) {
+        command.arg("--overwrite");
+    }
+    command.args(["--progress-format", "json"]).stdin(Stdio::null()).stdout(Stdio::piped());
+    // Out of the daemon's process group, so Ctrl-C on its terminal reaches
+    // the job once, from the daemon, instead of twice, which would stop it
+    // right away.
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+
+    let name = match &job.spec.name {
+        Some(name) => format!("Job {} ({})", job.id, name),
+        None => format!("Job {}", job.id),
+    };
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            let mut queue = daemon.queue.lock().unwrap();
+            let failed = queue.job_mut(job.id);
+            failed.state = State::Failed;
+            failed.error = Some(format!("can't start the render: {}", e));
+            println!("{} failed: can't start the render: {}", name, e);
+            return queue.keep();
+        }
+    };
+    {
+        let mut queue = daemon.queue.lock().unwrap();
+        queue.running = Some(child.id());
+        let running = queue.job_mut(job.id);
+        running.state = State::Running;
+        running.error = None;
+        queue.keep();
+    }
+    println!("{} started", name);
+
+    let events = child.stdout.take().expect("the events are piped");
+    let done = AtomicBool::new(false);
+    let pid = child.id();
+    let status = thread::scope(|scope| {
+        scope.spawn(|| {
+            while !done.load(Ordering::Relaxed) {
+                if interrupt::requested() {
+                    if let Err(e) = interrupt_process(pid) {
+                        eprintln!("Warning: can't stop {}: {}", name, e);
+                    }
+                    break;
+                }
+                thread::sleep(POLL);
+            }
+        });
+        for line in BufReader::new(events).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            daemon.queue.lock().unwrap().job_mut(job.id).progress(&line);
+        }
+        let status = child.wait();
+        done.store(true, Ordering::Relaxed);
+        status
+    });
+
+    let mut queue = daemon.queue.lock().unwrap();
+    queue.running = None;
+    let cancelled = queue.cancelling.take() == Some(job.id);
+    let ended = queue.job_mut(job.id);
+    let stopped = status.as_ref().ok().and_then(ExitStatus::code) == Some(interrupt::EXIT_STATUS);
+    match &status {
+        Ok(status) if status.success() => {
+            ended.state = State::Finished;
+            ended.interrupted = false;
+            println!("{} finished, {} files written", name, ended.paths.len());
+        }
+        _ if cancelled && stopped => {
+            ended.state = State::Cancelled;
+            println!("{} cancelled, {} files written", name, ended.paths.len());
+        }
+        _ if interrupt::requested() && stopped => {
+            ended.requeue();
+            println!("{} stopped, it goes back to the queue", name);
+        }
+        Ok(status) => {
+            ended.state = State::Failed;
+            let error = ended.error.get_or_insert_with(|| format!("the render {}", status));
+            println!("{} failed: {}", name, error);
+        }
+        Err(e) => {
+            ended.state = State::Failed;
+            ended.error = Some(format!("can't wait for the render: {}", e));
+            println!("{} failed: can't wait for the render: {}", name, e);
+        }
+    }
+    queue.keep();
+}
+
+/// Asks the process `pid` to stop the way Ctrl-C does.
+#[cfg(unix)]
+fn interrupt_process(pid: u32) -> Result<(), String> {
+    // SAFETY: kill only reads its arguments.
+    match unsafe { libc::kill(pid as libc::pid_t, libc::SIGINT) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error().to_string()),
+    }
+}
+
+#[cfg(not(unix))]
+fn interrupt_process(_pid: u32) -> Result<(), String> {
+    Err("only unix can stop a running job the way Ctrl-C does".to_string())
+}
+
+/// Sends `request` to the daemon at `endpoint` and waits for its answer.
+pub fn request(endpoint: &Endpoint, request: &Request) -> Result<Response, RustlebrotError> {
+    let failed = |e: io::Error| {
+        RustlebrotError::System(format!("can't reach the daemon at {}: {}", endpoint, e))
+    };
+    let mut stream: Box<dyn Stream> = match endpoint {
+        Endpoint::Tcp(address) => Box::new(TcpStream::connect(address).map_err(failed)?),
+        #[cfg(unix)]
+        Endpoint::Socket(path) => Box::new(UnixStream::connect(path).map_err(failed)?),
+        #[cfg(not(unix))]
+        Endpoint::Socket(_) => {
+            return Err(RustlebrotError::Argument(
+                "Unix sockets are only on unix; connect over TCP with --connect ADDR:PORT"
+                    .to_string(),
+            ))
+        }
+    };
+    let json = serde_json::to_string(request).expect("requests serialize to JSON");
+    stream.write_all(format!("{}\n", json).as_bytes()).map_err(failed)?;
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).map_err(failed)?;
+    serde_json::from_str(&line).map_err(|e| {
+        let answer = line.trim();
+        RustlebrotError::System(format!("the daemon at {} answered '{}': {}", endpoint, answer, e))
+    })
+}
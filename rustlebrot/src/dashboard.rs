@@ -0,0 +1,251 @@
+use crate::error::RustlebrotError;
+use crate::events;
+use crate::interrupt;
+use image::codecs::jpeg::JpegEncoder;
+use image::{ColorType, DynamicImage};
+use serde_json::{json, Value};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// The page served at `/`, which follows the run from `/events`.
+const PAGE: &str = include_str!("dashboard.html");
+
+/// Pixels along the longer side of the frames shown on the page.
+const THUMBNAIL_SIZE: u32 = 480;
+
+const JPEG_QUALITY: u8 = 80;
+
+/// Events waiting for the dashboard, past which the run drops them rather
+/// than wait.
+const EVENT_BACKLOG: usize = 256;
+
+/// Messages waiting to be sent to a page, past which it misses the next
+/// ones.
+const CLIENT_BACKLOG: usize = 256;
+
+/// What the dashboard knows of the run, for the pages that connect later.
+#[derive(Default)]
+struct Shared {
+    /// The `run_started` event.
+    run: Option<Value>,
+    /// The latest `progress` event.
+    progress: Option<Value>,
+    /// The number and render time in seconds of every frame completed.
+    times: Vec<(u32, f64)>,
+    /// The number and JPEG of the latest frame shown.
+    frame: Option<(u32, Arc<Vec<u8>>)>,
+    /// Where the messages for every page connected to `/events` go.
+    clients: Vec<SyncSender<String>>,
+}
+
+impl Shared {
+    /// Sends `message` to every page, forgetting those that went away. A
+    /// page that is behind misses it.
+    fn broadcast(&mut self, message: &str) {
+        self.clients.retain(|client| match client.try_send(message.to_string()) {
+            Ok(()) | Err(TrySendError::Full(_)) => true,
+            Err(TrySendError::Disconnected(_)) => false,
+        });
+    }
+
+    /// The run so far, which a page starts from.
+    fn snapshot(&self) -> Value {
+        json!({
+            "run": self.run,
+            "progress": self.progress,
+            "times": self.times,
+            "frame": self.frame.as_ref().map(|(frame, _)| frame),
+        })
+    }
+}
+
+/// Serves a page following the run on `address`, from threads of its own,
+/// and forwards the events of the run to it. Returns where the frames to
+/// show on it are sent, which drops those that come while the one before
+/// is still being scaled down, so the dashboard never holds up the frames.
+///
+/// The page is at `/`, the events at `/events` as server-sent events, the
+/// latest frame at `/frame.jpg`, and `POST /stop` and `POST /skip-video` do
+/// what the buttons on the page say.
+pub fn start(address: SocketAddr) -> Result<SyncSender<(u32, DynamicImage)>, RustlebrotError> {
+    let failed = |e: io::Error| {
+        RustlebrotError::System(format!("can't serve the dashboard on {}: {}", address, e))
+    };
+    let listener = TcpListener::bind(address).map_err(failed)?;
+    // With port 0 the system picks one.
+    let address = listener.local_addr().map_err(failed)?;
+    events::say(format!("Dashboard at http://{}/", address));
+
+    let shared = Arc::new(Mutex::new(Shared::default()));
+    let (sender, events) = mpsc::sync_channel(EVENT_BACKLOG);
+    events::forward(move |event| {
+        let _ = sender.try_send(event);
+    });
+    let (frames, shown) = mpsc::sync_channel(1);
+    let state = shared.clone();
+    thread::spawn(move || follow_events(events, &state));
+    let state = shared.clone();
+    thread::spawn(move || follow_frames(shown, &state));
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let shared = shared.clone();
+                    // Pages that close early are nothing to report.
+                    thread::spawn(move || {
+                        let _ = handle(stream, &shared);
+                    });
+                }
+                Err(e) => eprintln!("Warning: failed to accept a connection: {}", e),
+            }
+        }
+    });
+    Ok(frames)
+}
+
+/// Keeps what the pages start from up to date with `events`, and sends
+/// every event on to them by its name.
+fn follow_events(events: Receiver<Value>, shared: &Mutex<Shared>) {
+    for event in events {
+        let name = event["event"].as_str().unwrap_or("message").to_string();
+        let mut shared = shared.lock().unwrap();
+        match name.as_str() {
+            "run_started" => shared.run = Some(event.clone()),
+            "progress" => shared.progress = Some(event.clone()),
+            "frame_completed" => {
+                if let (Some(frame), Some(seconds)) =
+                    (event["frame"].as_u64(), event["seconds"].as_f64())
+                {
+                    shared.times.push((frame as u32, seconds));
+                }
+            }
+            _ => {}
+        }
+        shared.broadcast(&sse_message(&name, &event.to_string()));
+    }
+}
+
+/// Scales down every frame `shown` for `/frame.jpg`, and tells the pages.
+fn follow_frames(shown: Receiver<(u32, DynamicImage)>, shared: &Mutex<Shared>) {
+    for (frame, img) in shown {
+        // Smaller frames are shown as they are, `thumbnail` would scale them
+        // up.
+        let thumbnail = match img.width().max(img.height()) > THUMBNAIL_SIZE {
+            true => img.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE).to_rgb8(),
+            false => img.to_rgb8(),
+        };
+        let mut jpeg = Vec::new();
+        let encoded = JpegEncoder::new_with_quality(&mut jpeg, JPEG_QUALITY).encode(
+            &thumbnail,
+            thumbnail.width(),
+            thumbnail.height(),
+            ColorType::Rgb8,
+        );
+        if let Err(e) = encoded {
+            eprintln!("Warning: can't show frame {} on the dashboard: {}", frame, e);
+            continue;
+        }
+        let mut shared = shared.lock().unwrap();
+        shared.frame = Some((frame, Arc::new(jpeg)));
+        shared.broadcast(&sse_message("frame", &json!({ "frame": frame }).to_string()));
+    }
+}
+
+/// `data` as a server-sent event named `event`. The format has no escapes,
+/// so every line of `data` goes in a field of its own.
+fn sse_message(event: &str, data: &str) -> String {
+    let mut message = format!("event: {}\n", event);
+    for line in data.split('\n') {
+        message.push_str(&format!("data: {}\n", line));
+    }
+    message.push('\n');
+    message
+}
+
+/// Answers the one request on `stream`.
+fn handle(mut stream: TcpStream, shared: &Mutex<Shared>) -> io::Result<()> {
+    // Requests are small, so anything longer is cut off.
+    let mut reader = BufReader::new(stream.try_clone()?.take(16 << 10));
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // The headers say nothing the dashboard needs, but are read so the
+    // client isn't cut off sending them.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && !header.trim().is_empty() {
+        header.clear();
+    }
+    let mut words = request.split_whitespace();
+    let (method, target) = (words.next().unwrap_or(""), words.next().unwrap_or(""));
+    let path = target.split_once('?').map_or(target, |(path, _)| path);
+    match (method, path) {
+        ("GET", "/" | "/index.html") => {
+            respond(&mut stream, "200 OK", "text/html; charset=utf-8", PAGE.as_bytes())
+        }
+        ("GET", "/events") => stream_events(stream, shared),
+        ("GET", "/frame.jpg") => {
+            let frame = shared.lock().unwrap().frame.clone();
+            match frame {
+                Some((_, jpeg)) => respond(&mut stream, "200 OK", "image/jpeg", &jpeg),
+                None => respond(&mut stream, "404 Not Found", "text/plain", b"no frame yet\n"),
+            }
+        }
+        ("POST", "/stop") => {
+            interrupt::request("the dashboard");
+            respond(&mut stream, "200 OK", "text/plain", b"stopping\n")
+        }
+        ("POST", "/skip-video") => {
+            interrupt::skip_video("the dashboard");
+            respond(&mut stream, "200 OK", "text/plain", b"skipping the video\n")
+        }
+        (_, "/" | "/index.html" | "/events" | "/frame.jpg" | "/stop" | "/skip-video") => {
+            respond(&mut stream, "405 Method Not Allowed", "text/plain", b"wrong method\n")
+        }
+        _ => respond(&mut stream, "404 Not Found", "text/plain", b"no such page\n"),
+    }
+}
+
+/// Sends the run so far on `stream`, then every event and frame as it
+/// comes, until the page goes away.
+fn stream_events(mut stream: TcpStream, shared: &Mutex<Shared>) -> io::Result<()> {
+    let (client, messages) = mpsc::sync_channel(CLIENT_BACKLOG);
+    // The page is added under the same lock the snapshot is taken under, so
+    // it gets every event after it exactly once.
+    let snapshot = {
+        let mut shared = shared.lock().unwrap();
+        shared.clients.push(client);
+        sse_message("snapshot", &shared.snapshot().to_string())
+    };
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\n\
+         Connection: close\r\n\r\n{}",
+        snapshot
+    )?;
+    stream.flush()?;
+    for message in messages {
+        stream.write_all(message.as_bytes())?;
+        stream.flush()?;
+    }
+    Ok(())
+}
+
+/// Writes a response with `body` to `stream`.
+fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    stream.flush()
+}
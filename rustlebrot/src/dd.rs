@@ -0,0 +1,270 @@
+use crate::complex::{Complex, Scalar};
+use crate::fractal::{self, Escape};
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+/// The relative precision of a `DoubleDouble`, 2^-104, which leaves a bit
+/// or two of its 106 bits of mantissa to the rounding of every operation.
+pub const EPSILON: f64 = f64::EPSILON * f64::EPSILON;
+
+/// Splits the mantissa of an f64 in halves of 26 bits, see `split`.
+const SPLITTER: f64 = 134_217_729.0;
+
+/// Significant digits of a decimal that go into a `DoubleDouble`, a few
+/// more than it holds so the last ones round correctly.
+const DECIMAL_DIGITS: usize = 36;
+
+/// A number kept as the unevaluated sum of two f64s, `hi` and a `lo` of at
+/// most half an ulp of `hi`, for about 106 bits of mantissa or 32
+/// significant digits in the exponent range of f64.
+///
+/// The arithmetic is that of Dekker and Knuth: every operation works out
+/// the rounding error of the f64 operation on the leading parts exactly,
+/// and carries it on in `lo`. It costs ten to twenty times as much as f64,
+/// but a hundredth of arbitrary precision at the same depth, and is exact
+/// everywhere, without the reference orbit of perturbation.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DoubleDouble {
+    pub hi: f64,
+    pub lo: f64,
+}
+
+impl DoubleDouble {
+    pub const ZERO: DoubleDouble = DoubleDouble { hi: 0.0, lo: 0.0 };
+    pub const ONE: DoubleDouble = DoubleDouble { hi: 1.0, lo: 0.0 };
+
+    /// The number rounded to f64.
+    #[inline]
+    pub fn to_f64(self) -> f64 {
+        self.hi + self.lo
+    }
+
+    /// The square, which needs one product less than `self * self`.
+    #[inline]
+    pub fn sqr(self) -> DoubleDouble {
+        let (p, e) = two_prod(self.hi, self.hi);
+        let e = e + 2.0 * self.hi * self.lo;
+        let (hi, lo) = quick_two_sum(p, e);
+        DoubleDouble { hi, lo }
+    }
+
+    /// `self` to the power `n`, by repeated squaring.
+    pub fn powi(self, n: i32) -> DoubleDouble {
+        let mut result = DoubleDouble::ONE;
+        let mut base = self;
+        let mut exponent = n.unsigned_abs();
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result * base;
+            }
+            base = base.sqr();
+            exponent >>= 1;
+        }
+        match n < 0 {
+            true => DoubleDouble::ONE / result,
+            false => result,
+        }
+    }
+
+    /// The number `0.digits` times ten to `exponent`, with `digits` the
+    /// decimal digits from the first, as `Decimal` keeps them.
+    pub fn from_digits(negative: bool, digits: &[u8], exponent: i64) -> DoubleDouble {
+        let digits = &digits[..digits.len().min(DECIMAL_DIGITS)];
+        let mut mantissa = DoubleDouble::ZERO;
+        for &digit in digits {
+            mantissa = mantissa * 10.0 + DoubleDouble::from(digit as f64);
+        }
+        // Decimals are finite in f64, so the exponent is well inside i32.
+        let scale = exponent - digits.len() as i64;
+        let value = mantissa * DoubleDouble::from(10.0).powi(scale as i32);
+        match negative {
+            true => -value,
+            false => value,
+        }
+    }
+}
+
+impl Scalar for DoubleDouble {
+    const ZERO: DoubleDouble = DoubleDouble::ZERO;
+    const ONE: DoubleDouble = DoubleDouble::ONE;
+
+    #[inline(always)]
+    fn sqr(self) -> DoubleDouble {
+        DoubleDouble::sqr(self)
+    }
+
+    /// The leading part alone, which is all the escape test needs.
+    #[inline(always)]
+    fn leading(self) -> f64 {
+        self.hi
+    }
+}
+
+impl From<f64> for DoubleDouble {
+    #[inline]
+    fn from(value: f64) -> DoubleDouble {
+        DoubleDouble { hi: value, lo: 0.0 }
+    }
+}
+
+/// `a + b` and its rounding error, exactly.
+#[inline]
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let s = a + b;
+    let b_virtual = s - a;
+    let a_virtual = s - b_virtual;
+    (s, (a - a_virtual) + (b - b_virtual))
+}
+
+/// Same as `two_sum`, for `|a| >= |b|`, in fewer operations.
+#[inline]
+fn quick_two_sum(a: f64, b: f64) -> (f64, f64) {
+    let s = a + b;
+    (s, b - (s - a))
+}
+
+/// `a * b` and its rounding error, exactly. A fused multiply-add gets the
+/// error in one operation where the target has one; elsewhere it would be
+/// emulated in software, so Dekker's product of the split halves is used
+/// instead.
+#[inline]
+fn two_prod(a: f64, b: f64) -> (f64, f64) {
+    let p = a * b;
+    if cfg!(target_feature = "fma") {
+        return (p, a.mul_add(b, -p));
+    }
+    let ((a_hi, a_lo), (b_hi, b_lo)) = (split(a), split(b));
+    (p, ((a_hi * b_hi - p) + a_hi * b_lo + a_lo * b_hi) + a_lo * b_lo)
+}
+
+/// Splits `a` into two halves whose products with another half are exact
+/// in f64.
+#[inline]
+fn split(a: f64) -> (f64, f64) {
+    let t = SPLITTER * a;
+    let hi = t - (t - a);
+    (hi, a - hi)
+}
+
+impl Add for DoubleDouble {
+    type Output = DoubleDouble;
+
+    #[inline]
+    fn add(self, other: DoubleDouble) -> DoubleDouble {
+        let (s, e) = two_sum(self.hi, other.hi);
+        let (t, f) = two_sum(self.lo, other.lo);
+        let (s, e) = quick_two_sum(s, e + t);
+        let (hi, lo) = quick_two_sum(s, e + f);
+        DoubleDouble { hi, lo }
+    }
+}
+
+impl Sub for DoubleDouble {
+    type Output = DoubleDouble;
+
+    #[inline]
+    fn sub(self, other: DoubleDouble) -> DoubleDouble {
+        self + -other
+    }
+}
+
+impl Neg for DoubleDouble {
+    type Output = DoubleDouble;
+
+    #[inline]
+    fn neg(self) -> DoubleDouble {
+        DoubleDouble {
+            hi: -self.hi,
+            lo: -self.lo,
+        }
+    }
+}
+
+impl Mul for DoubleDouble {
+    type Output = DoubleDouble;
+
+    #[inline]
+    fn mul(self, other: DoubleDouble) -> DoubleDouble {
+        let (p, e) = two_prod(self.hi, other.hi);
+        let e = e + (self.hi * other.lo + self.lo * other.hi);
+        let (hi, lo) = quick_two_sum(p, e);
+        DoubleDouble { hi, lo }
+    }
+}
+
+impl Mul<f64> for DoubleDouble {
+    type Output = DoubleDouble;
+
+    #[inline]
+    fn mul(self, other: f64) -> DoubleDouble {
+        let (p, e) = two_prod(self.hi, other);
+        let (hi, lo) = quick_two_sum(p, e + self.lo * other);
+        DoubleDouble { hi, lo }
+    }
+}
+
+impl Div for DoubleDouble {
+    type Output = DoubleDouble;
+
+    /// Long division, one f64 digit of the quotient at a time.
+    fn div(self, other: DoubleDouble) -> DoubleDouble {
+        let q1 = self.hi / other.hi;
+        let r = self - other * q1;
+        let q2 = r.hi / other.hi;
+        let r = r - other * q2;
+        let q3 = r.hi / other.hi;
+        let (hi, lo) = quick_two_sum(q1, q2);
+        DoubleDouble { hi, lo } + DoubleDouble::from(q3)
+    }
+}
+
+/// Checks whether `c` lies in the main cardioid or the period-2 bulb of the
+/// Mandelbrot set, like `fractal::in_cardioid_or_bulb` but in double-double,
+/// so the points of a frame just outside either aren't taken for inside.
+#[inline]
+pub fn in_cardioid_or_bulb(c: (DoubleDouble, DoubleDouble)) -> bool {
+    let (x, y) = c;
+    let quarter = DoubleDouble::from(0.25);
+    let y_sqr = y.sqr();
+    let q = (x - quarter).sqr() + y_sqr;
+    if (q * (q + (x - quarter)) - y_sqr * 0.25).hi < 0.0 {
+        return true;
+    }
+    ((x + DoubleDouble::ONE).sqr() + y_sqr - DoubleDouble::from(0.0625)).hi < 0.0
+}
+
+/// Computes the escape time of `c` with every operation carried out in
+/// double-double.
+///
+/// This runs the same kernel as the f64 escape times in `fractal`,
+/// periodicity checking included. Near the bailout only the leading parts
+/// of `z` matter, so the escape test and the smooth value are worked out
+/// from those. With `conjugate` set the Tricorn iteration `conj(z)^2 + c`
+/// is used instead of `z^2 + c`.
+pub fn escape_time(
+    c: (DoubleDouble, DoubleDouble),
+    max_iter: u32,
+    bailout: f64,
+    periodicity: Option<f64>,
+    conjugate: bool,
+) -> Escape {
+    let c = Complex::from(c);
+    match (conjugate, periodicity) {
+        (false, Some(eps)) => kernel::<false, true>(c, max_iter, bailout, eps),
+        (false, None) => kernel::<false, false>(c, max_iter, bailout, 0.0),
+        (true, Some(eps)) => kernel::<true, true>(c, max_iter, bailout, eps),
+        (true, None) => kernel::<true, false>(c, max_iter, bailout, 0.0),
+    }
+}
+
+/// The kernel of `escape_time` for one iteration and periodicity check.
+/// Each is kept to a function of its own, as the double-double arithmetic
+/// of all four inlined together runs out of registers and slows down.
+#[inline(never)]
+fn kernel<const CONJUGATE: bool, const PERIODIC: bool>(
+    c: Complex<DoubleDouble>,
+    max_iter: u32,
+    bailout: f64,
+    eps: f64,
+) -> Escape {
+    fractal::escape_time::<_, CONJUGATE, PERIODIC>(c, max_iter, bailout, eps, None)
+}
@@ -0,0 +1,210 @@
+use crate::render::{EscapeBuffer, Sample};
+use image::{DynamicImage, ImageBuffer, Luma};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// The fraction of the pixels of the time channel that are white.
+const SLOWEST: f64 = 0.01;
+
+/// The diagnostic images written beside every frame, as chosen with
+/// `--debug-channels`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DebugChannels {
+    /// The iterations of every pixel, from none in black to max_iter in
+    /// white.
+    pub iter: bool,
+    /// The time every pixel took to compute, from none in black to the
+    /// slowest pixels in white.
+    pub time: bool,
+    /// The samples adaptive anti-aliasing took of every pixel, from one in
+    /// black to as many as it takes in white.
+    pub samples: bool,
+}
+
+impl DebugChannels {
+    /// Parses a comma separated list of channels, like `iter,time`.
+    pub fn from_spec(spec: &str) -> Result<DebugChannels, String> {
+        let mut channels = DebugChannels::default();
+        for channel in spec.split(',').map(str::trim) {
+            match channel {
+                "iter" => channels.iter = true,
+                "time" => channels.time = true,
+                "samples" => channels.samples = true,
+                _ => return Err(format!("unknown debug channel '{}'", channel)),
+            }
+        }
+        Ok(channels)
+    }
+
+    /// Whether any channel is written.
+    pub fn any(self) -> bool {
+        self.iter || self.time || self.samples
+    }
+}
+
+/// A diagnostic image of a frame, the size of the frame.
+pub struct Diagnostic {
+    /// The name of the channel, which goes in the name of its file.
+    pub name: &'static str,
+    /// A 16-bit grayscale image.
+    pub image: DynamicImage,
+    /// What white stands for, such as `1000 iterations`.
+    pub white: String,
+}
+
+/// The wall time spent computing the samples of a frame, for
+/// `--debug-channels time`.
+///
+/// Every render thread keeps the times of the work it does in a list of
+/// its own, so they don't wait on each other to record them, and the lists
+/// are only put together once the frame is done. Work given to a batch of
+/// samples at once is shared out evenly between them, so the times are
+/// those of rows or rectangles rather than of single samples where the
+/// escape times are computed that way.
+pub struct Timing {
+    /// The samples computed and the nanoseconds each took, in a list for
+    /// every thread of the rayon pool and the last for any other thread.
+    threads: Vec<Mutex<Vec<(u32, u32, f32)>>>,
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        let threads = (0..=rayon::current_num_threads()).map(|_| Mutex::default()).collect();
+        Timing { threads }
+    }
+}
+
+impl Timing {
+    /// Records that computing `samples`, at their positions in the buffer,
+    /// took `elapsed` together.
+    pub fn record(&self, samples: &[(u32, u32)], elapsed: Duration) {
+        if samples.is_empty() {
+            return;
+        }
+        let share = elapsed.as_nanos() as f32 / samples.len() as f32;
+        // A thread of a larger pool than the one this was made in shares a
+        // list with another.
+        let pooled = self.threads.len() - 1;
+        let thread = rayon::current_thread_index().map_or(pooled, |index| index % pooled);
+        let mut times = self.threads[thread].lock().unwrap();
+        times.extend(samples.iter().map(|&(x, y)| (x, y, share)));
+    }
+
+    /// The nanoseconds spent on every sample of a buffer of `width` by
+    /// `height` samples, row by row, adding up the passes that computed
+    /// the same one.
+    pub fn per_sample(&self, width: u32, height: u32) -> Vec<f64> {
+        let mut nanos = vec![0.0; width as usize * height as usize];
+        for times in &self.threads {
+            for &(x, y, share) in times.lock().unwrap().iter() {
+                nanos[y as usize * width as usize + x as usize] += share as f64;
+            }
+        }
+        nanos
+    }
+}
+
+/// The iterations of the pixels of `buffer`, averaged over their samples,
+/// with the interior at max_iter.
+///
+/// Only buffers of escape times or Newton's method have iterations; other
+/// samples are black.
+pub fn iterations(buffer: &EscapeBuffer) -> Diagnostic {
+    let max_iter = buffer.max_iter as f64;
+    let iterations = |sample: &Sample| match *sample {
+        Sample::Value(value) | Sample::Phase { value, .. } => value.clamp(0.0, max_iter),
+        Sample::Root { iterations, .. } => iterations.clamp(0.0, max_iter),
+        Sample::Interior | Sample::Attractor { .. } => max_iter,
+        Sample::Exponent(_) | Sample::Boundary => 0.0,
+    };
+    let values = per_pixel(buffer, |samples| {
+        samples.iter().map(iterations).sum::<f64>() / samples.len() as f64
+    });
+    Diagnostic {
+        name: "iter",
+        image: gray(buffer, &values, max_iter),
+        white: format!("{} iterations", buffer.max_iter),
+    }
+}
+
+/// The time the pixels of `buffer` took to compute as `timing` recorded
+/// it, adding up their samples.
+///
+/// The slowest `SLOWEST` of the pixels are white, so a few that were held
+/// up by the rest of the machine don't leave all the others black.
+pub fn times(buffer: &EscapeBuffer, timing: &Timing) -> Diagnostic {
+    let nanos = timing.per_sample(buffer.width, buffer.height);
+    let width = buffer.width as usize;
+    let values: Vec<f64> = pixels(buffer)
+        .map(|(x, y)| {
+            let samples = samples_of(buffer, x, y);
+            samples.map(|(x, y)| nanos[y * width + x]).sum()
+        })
+        .collect();
+    let mut sorted = values.clone();
+    sorted.sort_by(f64::total_cmp);
+    let index = (sorted.len() as f64 * (1.0 - SLOWEST)) as usize;
+    let slowest = sorted[index.min(sorted.len() - 1)];
+    Diagnostic {
+        name: "time",
+        image: gray(buffer, &values, slowest),
+        white: format!("{:.1} microseconds", slowest / 1e3),
+    }
+}
+
+/// The samples every pixel of `buffer` was given, from one in black to
+/// `most` in white.
+pub fn samples(buffer: &EscapeBuffer, most: u32) -> Diagnostic {
+    let values = per_pixel(buffer, |samples| (samples.len() - 1) as f64);
+    Diagnostic {
+        name: "samples",
+        image: gray(buffer, &values, most.saturating_sub(1) as f64),
+        white: format!("{} samples", most),
+    }
+}
+
+/// The pixels of `buffer`, row by row, as the positions of their first
+/// samples.
+fn pixels(buffer: &EscapeBuffer) -> impl Iterator<Item = (usize, usize)> {
+    let samples = buffer.samples as usize;
+    let (width, height) = (buffer.width as usize / samples, buffer.height as usize / samples);
+    (0..height).flat_map(move |y| (0..width).map(move |x| (x * samples, y * samples)))
+}
+
+/// The positions of the samples of the pixel whose first sample is at
+/// `(x, y)`.
+fn samples_of(buffer: &EscapeBuffer, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> {
+    let samples = buffer.samples as usize;
+    (y..y + samples).flat_map(move |y| (x..x + samples).map(move |x| (x, y)))
+}
+
+/// `value` of all the samples of every pixel of `buffer`, including those
+/// adaptive anti-aliasing added.
+fn per_pixel(buffer: &EscapeBuffer, value: impl Fn(&[Sample]) -> f64) -> Vec<f64> {
+    let width = buffer.width as usize;
+    pixels(buffer)
+        .map(|(x, y)| {
+            let mut samples: Vec<Sample> =
+                samples_of(buffer, x, y).map(|(x, y)| buffer.values[y * width + x]).collect();
+            if let Some(refined) = buffer.refined.get(&(y * width + x)) {
+                samples.extend_from_slice(refined);
+            }
+            value(&samples)
+        })
+        .collect()
+}
+
+/// A grayscale image of the pixels of `buffer` from `values`, with 0 black
+/// and `white` white.
+fn gray(buffer: &EscapeBuffer, values: &[f64], white: f64) -> DynamicImage {
+    let (width, height) = (buffer.width / buffer.samples, buffer.height / buffer.samples);
+    let scale = if white > 0.0 { u16::MAX as f64 / white } else { 0.0 };
+    let levels = values.iter().map(|value| (value * scale).round().clamp(0.0, u16::MAX as f64));
+    let image = ImageBuffer::<Luma<u16>, Vec<u16>>::from_raw(
+        width,
+        height,
+        levels.map(|level| level as u16).collect(),
+    )
+    .expect("a value for every pixel");
+    DynamicImage::ImageLuma16(image)
+}
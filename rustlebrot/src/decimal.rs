@@ -0,0 +1,142 @@
+use crate::dd::DoubleDouble;
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+/// A number as written in decimal, like a coordinate of a zoom center.
+///
+/// Centers of deep zooms have far more digits than any float type keeps,
+/// so they are kept as they were written, which every precision then reads
+/// as many digits of as it can carry. Numbers compare by value, so `0.50`
+/// equals `.5`, while `Display` gives them back digit for digit.
+#[derive(Clone, Debug)]
+pub struct Decimal {
+    /// The number as written, without the whitespace around it.
+    text: String,
+    negative: bool,
+    /// The significant digits, without leading or trailing zeros, so empty
+    /// for zero.
+    digits: Vec<u8>,
+    /// The value is `0.digits` times ten to this.
+    exponent: i64,
+}
+
+impl Decimal {
+    /// Parses a number like `-1.25`, `.5` or `3e-20`. Infinities, NaN and
+    /// numbers too large for f64 are rejected, as every frame needs the
+    /// center in f64 as well.
+    pub fn parse(text: &str) -> Result<Decimal, String> {
+        let text = text.trim();
+        let invalid = || format!("'{}' isn't a decimal number", text);
+        let (negative, unsigned) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => {
+                (mantissa, exponent.parse::<i32>().map_err(|_| invalid())?)
+            }
+            None => (unsigned, 0),
+        };
+        let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let all_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+        if whole.len() + fraction.len() == 0 || !all_digits(whole) || !all_digits(fraction) {
+            return Err(invalid());
+        }
+        let mut digits: Vec<u8> = whole.bytes().chain(fraction.bytes()).map(|b| b - b'0').collect();
+        let leading = digits.iter().take_while(|&&digit| digit == 0).count();
+        digits.drain(..leading);
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        let decimal = Decimal {
+            text: text.to_string(),
+            negative,
+            exponent: whole.len() as i64 - leading as i64 + exponent as i64,
+            digits,
+        };
+        match decimal.to_f64().is_finite() {
+            true => Ok(decimal),
+            false => Err(format!("'{}' is too large for a coordinate", text)),
+        }
+    }
+
+    /// The number as it was written.
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// The digits from the first that isn't zero to the last, which are
+    /// the ones a precision has to carry to keep the number whole.
+    pub fn significant_digits(&self) -> usize {
+        self.digits.len()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    /// The number rounded to f64.
+    pub fn to_f64(&self) -> f64 {
+        self.text.parse().expect("decimals are parsed as f64 numbers")
+    }
+
+    /// The number rounded to double-double.
+    pub fn to_double_double(&self) -> DoubleDouble {
+        DoubleDouble::from_digits(self.negative, &self.digits, self.exponent)
+    }
+}
+
+/// The decimal digits `bits` of binary mantissa are sure to carry, 15 for
+/// an f64.
+pub fn digits_of_bits(bits: usize) -> usize {
+    (bits.saturating_sub(1) as f64 * std::f64::consts::LOG10_2).floor() as usize
+}
+
+impl FromStr for Decimal {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Decimal, String> {
+        Decimal::parse(text)
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Decimal) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Decimal) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Decimal) -> Ordering {
+        // Zero has no sign, -0 being 0.
+        let sign = |decimal: &Decimal| match (decimal.is_zero(), decimal.negative) {
+            (true, _) => 0,
+            (false, true) => -1,
+            (false, false) => 1,
+        };
+        let magnitude = || {
+            (self.exponent.cmp(&other.exponent)).then_with(|| self.digits.cmp(&other.digits))
+        };
+        match sign(self).cmp(&sign(other)) {
+            Ordering::Equal if sign(self) == 0 => Ordering::Equal,
+            Ordering::Equal if self.negative => magnitude().reverse(),
+            Ordering::Equal => magnitude(),
+            ordering => ordering,
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use crate::contour;
+use crate::render::{EscapeBuffer, Sample};
+use image::RgbImage;
+use rayon::prelude::*;
+
+/// The difference of smooth escape times, in iterations, past which two
+/// renders of a frame can show bands where the other has none. Palettes
+/// spread over a few hundred iterations move by about a level of 8-bit
+/// color an iteration, so this keeps them within a hundredth of one.
+pub const DEFAULT_THRESHOLD: f64 = 0.01;
+
+/// The share of samples two renders may differ in by more than the
+/// threshold and still agree. Near the boundary, orbits that take most of
+/// the iteration limit pick up the rounding of any backend, so a few
+/// samples there differ wherever the rest is right.
+pub const DEFAULT_TOLERANCE: f64 = 0.001;
+
+/// The smallest difference the heatmap tells from none, at the bottom of
+/// its scale.
+const HEAT_FLOOR: f64 = 1e-12;
+
+/// How the samples of two renders of the same frame differ, as `validate`
+/// compares the backends it renders the frame in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Difference {
+    pub samples: usize,
+    /// The samples that escaped in both renders, which the differences
+    /// are of.
+    pub escaped: usize,
+    /// The largest difference of the smooth escape times of a sample, in
+    /// iterations.
+    pub max: f64,
+    pub mean: f64,
+    /// The samples that escaped in one render and not in the other.
+    pub disagreeing: usize,
+    /// The samples whose escape times differ by more than the threshold,
+    /// and those that disagree.
+    pub over: usize,
+}
+
+impl Difference {
+    /// The difference between `a` and `b`, renders of one frame in
+    /// escape times of the same size, counting those more than
+    /// `threshold` apart.
+    pub fn of(a: &EscapeBuffer, b: &EscapeBuffer, threshold: f64) -> Difference {
+        let pairs = || a.values.par_iter().zip(&b.values);
+        let escaped: Vec<f64> = pairs()
+            .filter_map(|(a, b)| Some((contour::level(*a)? - contour::level(*b)?).abs()))
+            .collect();
+        let disagreeing = pairs().filter(|(a, b)| apart(**a, **b).is_none()).count();
+        let over = escaped.iter().filter(|&&difference| difference > threshold).count();
+        Difference {
+            samples: a.values.len(),
+            escaped: escaped.len(),
+            max: escaped.iter().copied().fold(0.0, f64::max),
+            mean: escaped.iter().sum::<f64>() / escaped.len().max(1) as f64,
+            disagreeing,
+            over: over + disagreeing,
+        }
+    }
+
+    /// The share of the samples counted in `over`, from 0 to 1.
+    pub fn share_over(&self) -> f64 {
+        self.over as f64 / self.samples.max(1) as f64
+    }
+}
+
+/// How far apart the escape times of samples `a` and `b` are, 0 when
+/// neither escaped, or `None` when only one did.
+fn apart(a: Sample, b: Sample) -> Option<f64> {
+    match (contour::level(a), contour::level(b)) {
+        (Some(a), Some(b)) => Some((a - b).abs()),
+        (None, None) => Some(0.0),
+        _ => None,
+    }
+}
+
+/// The differences between `a` and `b` as an image of their size in
+/// samples: black where they agree, blue to yellow on a logarithmic scale
+/// from `HEAT_FLOOR` up to `threshold`, red past it, brighter up to a
+/// hundred times it, and white where only one of them escaped.
+pub fn heatmap(a: &EscapeBuffer, b: &EscapeBuffer, threshold: f64) -> RgbImage {
+    let span = (threshold / HEAT_FLOOR).log10();
+    let colors: Vec<u8> = a
+        .values
+        .par_iter()
+        .zip(&b.values)
+        .flat_map_iter(|(a, b)| match apart(*a, *b) {
+            None => [255, 255, 255],
+            Some(difference) if difference <= HEAT_FLOOR => [0, 0, 0],
+            Some(difference) if difference <= threshold => {
+                let t = (difference / HEAT_FLOOR).log10() / span;
+                let (low, high) = ([0.0, 0.0, 96.0], [255.0, 224.0, 0.0]);
+                [0, 1, 2].map(|i| (low[i] + t * (high[i] - low[i])).round() as u8)
+            }
+            Some(difference) => {
+                let t = ((difference / threshold).log10() / 2.0).min(1.0);
+                [(128.0 + t * 127.0).round() as u8, 0, 0]
+            }
+        })
+        .collect();
+    RgbImage::from_raw(a.width, a.height, colors).expect("a color for every sample")
+}
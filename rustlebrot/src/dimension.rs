@@ -0,0 +1,146 @@
+use crate::coloring::Coloring;
+use crate::contour;
+use crate::render::{EscapeBuffer, Sample};
+use rayon::prelude::*;
+
+/// Boundary samples a frame needs for its dimension to be estimated. With
+/// fewer, the fit is through a handful of boxes and says nothing.
+pub const MIN_BOUNDARY: usize = 64;
+
+/// Scales a fit needs at least, the smallest boxes a sample wide and each
+/// size twice the one before.
+const MIN_SCALES: usize = 3;
+
+/// How close to the set a distance estimate puts a sample on the boundary,
+/// in samples.
+const DISTANCE_THRESHOLD: f64 = 1.0;
+
+/// The box-counting dimension of the boundary of the set in a frame, as
+/// `--measure-dimension` estimates it.
+///
+/// The boundary is cut into boxes at every scale from a sample up to a
+/// quarter of the shorter side of the frame, doubling in size, and the
+/// dimension is the slope of the logarithm of the boxes it touches against
+/// the logarithm of their size. A line comes out at 1 and a filled region
+/// at 2, and the boundary of the Mandelbrot set in between, though a frame
+/// only shows a few scales of it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Dimension {
+    /// The samples found on the boundary.
+    pub boundary_samples: usize,
+    /// The slope of the fit, unless there were too few boundary samples or
+    /// scales for one.
+    pub estimate: Option<f64>,
+    /// How well a line fits the box counts, from 0 to 1, along with
+    /// `estimate`. Far from 1, the boundary isn't self-similar over the
+    /// scales of the frame.
+    pub r_squared: Option<f64>,
+}
+
+impl Dimension {
+    /// The dimension of the boundary of the set in `buffer`, or `None` when
+    /// its samples don't tell the set apart, as those of Newton's method
+    /// and Lyapunov exponents don't.
+    pub fn of(buffer: &EscapeBuffer) -> Option<Dimension> {
+        let boundary = boundary(buffer)?;
+        Some(Dimension::measure(&boundary, buffer.width as usize, buffer.height as usize))
+    }
+
+    /// The dimension of the samples set in `boundary`, `width` to a row.
+    pub fn measure(boundary: &[bool], width: usize, height: usize) -> Dimension {
+        let boundary_samples = boundary.iter().filter(|&&on| on).count();
+        let not_fitted = Dimension {
+            boundary_samples,
+            estimate: None,
+            r_squared: None,
+        };
+        let sizes: Vec<usize> = (0..)
+            .map(|power| 1 << power)
+            .take_while(|&size| size * 4 <= width.min(height))
+            .collect();
+        if boundary_samples < MIN_BOUNDARY || sizes.len() < MIN_SCALES {
+            return not_fitted;
+        }
+        let points: Vec<(f64, f64)> = sizes
+            .iter()
+            .map(|&size| {
+                let boxes = boxes(boundary, width, height, size);
+                (-(size as f64).ln(), (boxes as f64).ln())
+            })
+            .collect();
+        let (slope, r_squared) = fit(&points);
+        Dimension {
+            estimate: Some(slope),
+            r_squared: Some(r_squared),
+            ..not_fitted
+        }
+    }
+}
+
+/// Whether every sample of `buffer` is on the boundary of the set: outside
+/// it, next to a sample inside it, or with a distance estimate within
+/// `DISTANCE_THRESHOLD` of it. `None` when the samples don't tell the
+/// inside of the set apart.
+pub fn boundary(buffer: &EscapeBuffer) -> Option<Vec<bool>> {
+    let inside = |sample: &Sample| contour::level(*sample).is_none();
+    let tells_inside = buffer
+        .values
+        .iter()
+        .all(|sample| !matches!(sample, Sample::Root { .. } | Sample::Exponent(_)));
+    if !tells_inside {
+        return None;
+    }
+    let (width, height) = (buffer.width as usize, buffer.height as usize);
+    let distance = buffer.coloring == Coloring::Distance;
+    let boundary = (0..width * height)
+        .into_par_iter()
+        .map(|index| {
+            let sample = &buffer.values[index];
+            if inside(sample) {
+                return false;
+            }
+            if distance && matches!(sample, Sample::Value(value) if *value < DISTANCE_THRESHOLD) {
+                return true;
+            }
+            let (x, y) = (index % width, index / width);
+            let neighbors = [
+                (x > 0).then(|| index - 1),
+                (x + 1 < width).then(|| index + 1),
+                (y > 0).then(|| index - width),
+                (y + 1 < height).then(|| index + width),
+            ];
+            neighbors.into_iter().flatten().any(|other| inside(&buffer.values[other]))
+        })
+        .collect();
+    Some(boundary)
+}
+
+/// The boxes `size` samples wide the boundary touches, those at the right
+/// and bottom edges counted whole.
+fn boxes(boundary: &[bool], width: usize, height: usize, size: usize) -> usize {
+    let columns = width.div_ceil(size);
+    let mut touched = vec![false; columns * height.div_ceil(size)];
+    for (index, _) in boundary.iter().enumerate().filter(|(_, &on)| on) {
+        let (x, y) = (index % width, index / width);
+        touched[y / size * columns + x / size] = true;
+    }
+    touched.iter().filter(|&&touched| touched).count()
+}
+
+/// The slope of the least-squares line through `points`, and its
+/// coefficient of determination.
+fn fit(points: &[(f64, f64)]) -> (f64, f64) {
+    let count = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / count;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / count;
+    let sxx: f64 = points.iter().map(|(x, _)| (x - mean_x) * (x - mean_x)).sum();
+    let sxy: f64 = points.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    let syy: f64 = points.iter().map(|(_, y)| (y - mean_y) * (y - mean_y)).sum();
+    let slope = sxy / sxx;
+    // Counts that don't change with the scale fit a flat line exactly.
+    let r_squared = match syy > 0.0 {
+        true => (sxy * sxy / (sxx * syy)).min(1.0),
+        false => 1.0,
+    };
+    (slope, r_squared)
+}
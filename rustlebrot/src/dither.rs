@@ -0,0 +1,161 @@
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use std::sync::OnceLock;
+
+/// How channels are rounded to their bit depth, as chosen with `--dither`.
+///
+/// Smooth gradients rounded to 8 bits break into bands a few pixels wide,
+/// stepping by one level at a time. Dithering adds less than a level to
+/// every pixel before rounding, by a pattern that depends only on where the
+/// pixel is, so the rounding error becomes fine noise instead of steps. The
+/// pattern stays put from frame to frame, so a zoom doesn't shimmer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dither {
+    None,
+    /// An 8×8 Bayer matrix, regular and cheap but with a visible grid.
+    Ordered,
+    /// A 64×64 void-and-cluster mask, whose noise has no low frequencies to
+    /// show up as patterns.
+    BlueNoise,
+}
+
+impl Dither {
+    pub fn from_name(name: &str) -> Option<Dither> {
+        match name {
+            "none" => Some(Dither::None),
+            "ordered" => Some(Dither::Ordered),
+            "blue-noise" => Some(Dither::BlueNoise),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Dither::None => "none",
+            Dither::Ordered => "ordered",
+            Dither::BlueNoise => "blue-noise",
+        }
+    }
+
+    /// What's added to the channels of pixel `(x, y)` before rounding, in
+    /// levels of the output, between -0.5 and 0.5. Every pattern averages
+    /// to nothing, so areas keep their brightness.
+    #[inline]
+    pub fn offset(self, x: u32, y: u32) -> f64 {
+        let (rank, ranks) = match self {
+            Dither::None => return 0.0,
+            Dither::Ordered => (BAYER[(y % 8) as usize][(x % 8) as usize] as u32, 64),
+            Dither::BlueNoise => {
+                (blue_noise()[(y % SIZE) as usize][(x % SIZE) as usize] as u32, SIZE * SIZE)
+            }
+        };
+        (rank as f64 + 0.5) / ranks as f64 - 0.5
+    }
+}
+
+/// The order the pixels of an 8×8 tile are switched on in a Bayer matrix.
+const BAYER: [[u8; 8]; 8] = [
+    [0, 32, 8, 40, 2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44, 4, 36, 14, 46, 6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [3, 35, 11, 43, 1, 33, 9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47, 7, 39, 13, 45, 5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+/// The side of the blue noise mask, which tiles the frame.
+const SIZE: u32 = 64;
+
+type Mask = [[u16; SIZE as usize]; SIZE as usize];
+
+/// The blue noise mask, built on first use.
+fn blue_noise() -> &'static Mask {
+    static MASK: OnceLock<Box<Mask>> = OnceLock::new();
+    MASK.get_or_init(void_and_cluster)
+}
+
+/// Ranks the pixels of a tile by Ulichney's void-and-cluster method.
+///
+/// Each pixel's energy is the sum of a Gaussian over the pixels switched on
+/// around it, wrapping at the edges so the mask tiles. A random starting
+/// pattern is evened out by moving its tightest cluster to its largest
+/// void until that changes nothing. From there, pixels are switched off
+/// from the tightest cluster for the ranks below, and on in the largest
+/// void for the ranks above, so every threshold of the mask leaves the
+/// pixels it lets through as evenly spread as it can.
+fn void_and_cluster() -> Box<Mask> {
+    const N: usize = (SIZE * SIZE) as usize;
+    let side = SIZE as usize;
+    let kernel: Vec<f64> = (0..N)
+        .map(|index| {
+            let wrap = |d: usize| d.min(side - d) as f64;
+            let (dx, dy) = (wrap(index % side), wrap(index / side));
+            (-(dx * dx + dy * dy) / (2.0 * 1.5 * 1.5)).exp()
+        })
+        .collect();
+    let toggle = |energy: &mut [f64], at: usize, sign: f64| {
+        let (ax, ay) = (at % side, at / side);
+        for (index, energy) in energy.iter_mut().enumerate() {
+            let (dx, dy) = ((index % side + side - ax) % side, (index / side + side - ay) % side);
+            *energy += sign * kernel[dy * side + dx];
+        }
+    };
+    // The pixel that's on with the most energy, or off with the least.
+    let extreme = |on: &[bool], energy: &[f64], state: bool| {
+        let candidates = (0..N).filter(|&index| on[index] == state);
+        let key = |index: &usize| match state {
+            true => energy[*index],
+            false => -energy[*index],
+        };
+        candidates.max_by(|a, b| key(a).total_cmp(&key(b))).unwrap()
+    };
+
+    let mut rng = SmallRng::seed_from_u64(0x5eed);
+    let mut on = vec![false; N];
+    let mut energy = vec![0.0; N];
+    let mut ones = 0;
+    while ones < N / 10 {
+        let index = rng.gen_range(0..N);
+        if !on[index] {
+            on[index] = true;
+            toggle(&mut energy, index, 1.0);
+            ones += 1;
+        }
+    }
+    // Moving a pixel can undo the move before it, so this stops after a
+    // pass's worth of moves if the pattern doesn't settle first.
+    for _ in 0..N {
+        let cluster = extreme(&on, &energy, true);
+        on[cluster] = false;
+        toggle(&mut energy, cluster, -1.0);
+        let void = extreme(&on, &energy, false);
+        on[void] = true;
+        toggle(&mut energy, void, 1.0);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut ranks = vec![0u16; N];
+    let (mut below, mut below_energy) = (on.clone(), energy.clone());
+    for rank in (0..ones).rev() {
+        let cluster = extreme(&below, &below_energy, true);
+        below[cluster] = false;
+        toggle(&mut below_energy, cluster, -1.0);
+        ranks[cluster] = rank as u16;
+    }
+    for rank in ones..N {
+        let void = extreme(&on, &energy, false);
+        on[void] = true;
+        toggle(&mut energy, void, 1.0);
+        ranks[void] = rank as u16;
+    }
+
+    let mut mask = Box::new([[0; SIZE as usize]; SIZE as usize]);
+    for (index, rank) in ranks.into_iter().enumerate() {
+        mask[index / side][index % side] = rank;
+    }
+    mask
+}
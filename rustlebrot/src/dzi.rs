@@ -0,0 +1,435 @@
+use crate::error::RustlebrotError;
+use crate::export::{self, ImageFormat};
+use crate::fractal::Fractal;
+use crate::render::{from_linear, to_linear, BitDepth};
+use crate::still::{self, Still, TileRect};
+use image::{imageops, DynamicImage, ImageBuffer, Rgb};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
+/// The version of the settings saved beside the tiles.
+const SETTINGS_VERSION: u32 = 1;
+
+/// The OpenSeadragon the page next to the descriptor loads.
+const OPENSEADRAGON: &str = "https://cdn.jsdelivr.net/npm/openseadragon@4.1.1/build/openseadragon";
+
+/// Pixels of a band of a level, at 16 bits a channel whatever the tiles are
+/// saved in, so averaging them into the level below loses nothing.
+type Pixels = ImageBuffer<Rgb<u16>, Vec<u16>>;
+
+/// How a still is cut into a Deep Zoom Image, the pyramid of tiles web
+/// viewers like OpenSeadragon pan and zoom through, as `export-dzi` writes
+/// it.
+///
+/// Level 0 is a single pixel, and every level is twice the size of the one
+/// before, up to the still itself. Only the top level is rendered; every
+/// level below is averaged from the one above, two pixels by two in linear
+/// light, which is far cheaper and what rendering it smaller with
+/// supersampling would come to anyway.
+pub struct Dzi {
+    /// Pixels along each side of a tile, not counting the overlap.
+    pub tile_size: u32,
+    /// Pixels each tile takes in from each of its neighbors, which the
+    /// viewer draws the tiles over each other by.
+    pub overlap: u32,
+    pub format: ImageFormat,
+}
+
+/// What the tiles of a pyramid were rendered from, saved beside them so a
+/// resumed build can tell whether the tiles it keeps still belong.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Settings {
+    version: u32,
+    width: u32,
+    height: u32,
+    tile_size: u32,
+    overlap: u32,
+    format: String,
+    x_range: (f64, f64),
+    y_range: (f64, f64),
+    max_iter: u32,
+    samples: u32,
+}
+
+/// The rows of tiles of a level, from the top, as they're finished.
+struct Band {
+    row: u32,
+    pixels: Pixels,
+}
+
+/// A level of the pyramid being built, which holds a band at a time.
+struct Level {
+    width: u32,
+    height: u32,
+    /// The last rows of the band before `current`, as many as the overlap,
+    /// which its tiles reach up into.
+    above: Option<Pixels>,
+    /// The band whose tiles are written once the one below it is there,
+    /// since they reach down into it too.
+    current: Option<Band>,
+}
+
+/// The pyramid of `dzi` while it is being built, a band of every level at
+/// a time.
+struct Builder<'a> {
+    dzi: &'a Dzi,
+    /// The directory of the levels.
+    dir: String,
+    levels: Vec<Level>,
+    depth: BitDepth,
+    resume: bool,
+    /// The linear intensity of every 16-bit sRGB channel.
+    linear: Vec<f32>,
+    written: u64,
+    kept: u64,
+}
+
+/// How many tiles a build wrote, and how many it kept from before.
+pub struct Built {
+    pub written: u64,
+    pub kept: u64,
+}
+
+/// Renders `still` into the Deep Zoom Image `dzi` at `out`, a `.dzi`
+/// descriptor, with its tiles in the directory `_files` next to it, and a
+/// page to view it in as `.html`.
+///
+/// The still is rendered a row of tiles at a time, and every level only
+/// holds a band of rows, so the image as a whole never is. With `resume`,
+/// the tiles there from an earlier build of the same pyramid are kept, and
+/// those of the top level are read back in place of rendering them again.
+pub fn write<F: Fractal>(
+    fractal: &F,
+    still: &Still,
+    dzi: &Dzi,
+    out: &str,
+    resume: bool,
+    overwrite: bool,
+) -> Result<Built, RustlebrotError> {
+    let stem = out.strip_suffix(".dzi").expect("--out is checked to be a .dzi");
+    let (dir, page) = (format!("{}_files", stem), format!("{}.html", stem));
+    let settings = Settings {
+        version: SETTINGS_VERSION,
+        width: still.width,
+        height: still.height,
+        tile_size: dzi.tile_size,
+        overlap: dzi.overlap,
+        format: match dzi.format {
+            ImageFormat::Jpeg { quality } => format!("jpg at quality {}", quality),
+            format => format.extension().to_string(),
+        },
+        x_range: still.x_range,
+        y_range: still.y_range,
+        max_iter: still.options.max_iter,
+        samples: still.samples,
+    };
+    let settings_path = format!("{}/settings.json", dir);
+    if resume && Path::new(&dir).exists() {
+        let failed = |e| RustlebrotError::read(&settings_path, e);
+        let saved = fs::read_to_string(&settings_path).map_err(failed)?;
+        let saved: Settings = serde_json::from_str(&saved)
+            .map_err(|e| RustlebrotError::format(&settings_path, e))?;
+        if saved != settings {
+            return Err(RustlebrotError::Argument(format!(
+                "the tiles in {} were rendered with other settings than these, so they can't be \
+                 resumed; pass the ones they were, or --overwrite to replace them",
+                dir
+            )));
+        }
+    } else if !resume {
+        for path in [out, &page, &dir] {
+            still::check_new(path, overwrite)?;
+        }
+    }
+
+    let mut sizes = vec![(still.width, still.height)];
+    while let Some(&(width, height)) = sizes.last().filter(|&&size| size != (1, 1)) {
+        sizes.push((width.div_ceil(2), height.div_ceil(2)));
+    }
+    sizes.reverse();
+    for level in 0..sizes.len() {
+        let level_dir = format!("{}/{}", dir, level);
+        fs::create_dir_all(&level_dir).map_err(|e| RustlebrotError::write(&level_dir, e))?;
+    }
+    let json = serde_json::to_string_pretty(&settings)
+        .map_err(|e| RustlebrotError::encode(&settings_path, e))?;
+    fs::write(&settings_path, json + "\n")
+        .map_err(|e| RustlebrotError::write(&settings_path, e))?;
+
+    let mut builder = Builder {
+        dzi,
+        dir,
+        levels: sizes
+            .iter()
+            .map(|&(width, height)| Level {
+                width,
+                height,
+                above: None,
+                current: None,
+            })
+            .collect(),
+        depth: still.colors.bit_depth,
+        resume,
+        linear: (0..=u16::MAX).map(|channel| to_linear(channel as f64 / 65535.0) as f32).collect(),
+        written: 0,
+        kept: 0,
+    };
+    let top = sizes.len() - 1;
+    let rows = still.height.div_ceil(dzi.tile_size);
+    for row in 0..rows {
+        let start = Instant::now();
+        let band = builder.render_band(fractal, still, row)?;
+        builder.add(top, band)?;
+        println!("Rendered row {} of {} of tiles in {:.2?}", row + 1, rows, start.elapsed());
+    }
+    builder.finish(top)?;
+
+    let descriptor = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <Image xmlns=\"http://schemas.microsoft.com/deepzoom/2008\" Format=\"{}\" \
+         Overlap=\"{}\" TileSize=\"{}\">\n  <Size Width=\"{}\" Height=\"{}\"/>\n</Image>\n",
+        dzi.format.extension(),
+        dzi.overlap,
+        dzi.tile_size,
+        still.width,
+        still.height
+    );
+    fs::write(out, descriptor).map_err(|e| RustlebrotError::write(out, e))?;
+    fs::write(&page, viewer_page(stem, dzi, (still.width, still.height)))
+        .map_err(|e| RustlebrotError::write(&page, e))?;
+    Ok(Built {
+        written: builder.written,
+        kept: builder.kept,
+    })
+}
+
+/// A page showing the pyramid at `stem` in OpenSeadragon. The descriptor
+/// is given inline, as a browser won't let a page opened from a file fetch
+/// it, though it does load the tiles.
+fn viewer_page(stem: &str, dzi: &Dzi, (width, height): (u32, u32)) -> String {
+    let name = Path::new(stem).file_name().map_or(stem.into(), |name| name.to_string_lossy());
+    let title = name.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+    let url = serde_json::to_string(&format!("{}_files/", name)).expect("strings serialize");
+    format!(
+        "<!DOCTYPE html>\n\
+         <html>\n\
+         <head>\n\
+         <meta charset=\"utf-8\">\n\
+         <title>{title}</title>\n\
+         <script src=\"{osd}/openseadragon.min.js\"></script>\n\
+         <style>html, body, #viewer {{ margin: 0; width: 100%; height: 100%; background: #000; }}\
+         </style>\n\
+         </head>\n\
+         <body>\n\
+         <div id=\"viewer\"></div>\n\
+         <script>\n\
+         OpenSeadragon({{\n\
+         \x20 id: \"viewer\",\n\
+         \x20 prefixUrl: \"{osd}/images/\",\n\
+         \x20 tileSources: {{\n\
+         \x20   Image: {{\n\
+         \x20     xmlns: \"http://schemas.microsoft.com/deepzoom/2008\",\n\
+         \x20     Url: {url},\n\
+         \x20     Format: \"{format}\",\n\
+         \x20     Overlap: \"{overlap}\",\n\
+         \x20     TileSize: \"{tile_size}\",\n\
+         \x20     Size: {{ Width: \"{width}\", Height: \"{height}\" }}\n\
+         \x20   }}\n\
+         \x20 }}\n\
+         }});\n\
+         </script>\n\
+         </body>\n\
+         </html>\n",
+        title = title,
+        osd = OPENSEADRAGON,
+        url = url,
+        format = dzi.format.extension(),
+        overlap = dzi.overlap,
+        tile_size = dzi.tile_size,
+        width = width,
+        height = height,
+    )
+}
+
+impl Builder<'_> {
+    /// The pixels of the tile at `index` along a side of a level `size`
+    /// pixels long: those it takes in from before, its own, and those it
+    /// takes in from after.
+    fn span(&self, index: u32, size: u32) -> (u32, u32, u32) {
+        let (tile_size, overlap) = (self.dzi.tile_size, self.dzi.overlap);
+        let start = index * tile_size;
+        let own = tile_size.min(size - start);
+        let before = if index > 0 { overlap } else { 0 };
+        (before, own, overlap.min(size - start - own))
+    }
+
+    fn tile_path(&self, level: usize, column: u32, row: u32) -> String {
+        let extension = self.dzi.format.extension();
+        format!("{}/{}/{}_{}.{}", self.dir, level, column, row, extension)
+    }
+
+    /// Renders the band `row` of the top level, or reads back the tiles of
+    /// it a build before this one saved.
+    fn render_band<F: Fractal>(
+        &self,
+        fractal: &F,
+        still: &Still,
+        row: u32,
+    ) -> Result<Band, RustlebrotError> {
+        let top = self.levels.len() - 1;
+        let tile_size = self.dzi.tile_size;
+        let (up, height, down) = self.span(row, still.height);
+        let mut pixels = Pixels::new(still.width, height);
+        for column in 0..still.width.div_ceil(tile_size) {
+            let (left, width, right) = self.span(column, still.width);
+            let tile = TileRect {
+                column,
+                row,
+                x: column * tile_size,
+                y: row * tile_size,
+                width,
+                height,
+            };
+            let path = self.tile_path(top, column, row);
+            let saved = match self.resume && Path::new(&path).exists() {
+                true => image::open(&path).ok().filter(|img| {
+                    (img.width(), img.height()) == (left + width + right, up + height + down)
+                }),
+                false => None,
+            };
+            let img = match saved {
+                Some(saved) => imageops::crop_imm(&saved.to_rgb16(), left, up, width, height)
+                    .to_image(),
+                None => {
+                    // Whatever is there isn't the tile, which is written
+                    // again below.
+                    if self.resume && Path::new(&path).exists() {
+                        println!("Rendering {} again, which can't be read back", path);
+                        fs::remove_file(&path).map_err(|e| RustlebrotError::write(&path, e))?;
+                    }
+                    still.render_tile(fractal, &tile).to_rgb16()
+                }
+            };
+            imageops::replace(&mut pixels, &img, tile.x as i64, 0);
+        }
+        Ok(Band { row, pixels })
+    }
+
+    /// Adds the next band of `level`, which finishes the one before it: its
+    /// tiles are written, and with the band above it, it is averaged into
+    /// the level below.
+    fn add(&mut self, level: usize, band: Band) -> Result<(), RustlebrotError> {
+        if let Some(current) = self.levels[level].current.take() {
+            self.write_tiles(level, &current, Some(&band.pixels))?;
+            if band.row % 2 == 1 && level > 0 {
+                let half = self.halve(&current.pixels, Some(&band.pixels));
+                self.add(level - 1, Band { row: band.row / 2, pixels: half })?;
+            }
+            let (width, height) = current.pixels.dimensions();
+            let overlap = self.dzi.overlap.min(height);
+            let above = imageops::crop_imm(&current.pixels, 0, height - overlap, width, overlap);
+            self.levels[level].above = Some(above.to_image());
+        }
+        self.levels[level].current = Some(band);
+        Ok(())
+    }
+
+    /// Finishes the last band of `level` and of every level below it.
+    fn finish(&mut self, level: usize) -> Result<(), RustlebrotError> {
+        let current = self.levels[level].current.take().expect("every level has a band");
+        self.write_tiles(level, &current, None)?;
+        if level == 0 {
+            return Ok(());
+        }
+        // A band left over from the pairs is averaged alone.
+        if current.row.is_multiple_of(2) {
+            let half = self.halve(&current.pixels, None);
+            self.add(level - 1, Band { row: current.row / 2, pixels: half })?;
+        }
+        self.finish(level - 1)
+    }
+
+    /// Writes the tiles of `band` of `level`, which take in the rows of the
+    /// band above and of `below` the overlap reaches.
+    fn write_tiles(
+        &mut self,
+        level: usize,
+        band: &Band,
+        below: Option<&Pixels>,
+    ) -> Result<(), RustlebrotError> {
+        let (level_width, level_height) = (self.levels[level].width, self.levels[level].height);
+        let (up, height, down) = self.span(band.row, level_height);
+        // The rows of the tiles, from the bands they're in. Bands but the
+        // last are whole, so the one above has all the rows of the overlap.
+        let above = self.levels[level].above.as_ref();
+        let rows: Vec<(&Pixels, u32)> = (0..up)
+            .map(|y| (above.expect("bands after the first have one above"), y))
+            .chain((0..height).map(|y| (&band.pixels, y)))
+            .chain((0..down).map(|y| (below.expect("bands before the last have one below"), y)))
+            .collect();
+        for column in 0..level_width.div_ceil(self.dzi.tile_size) {
+            let path = self.tile_path(level, column, band.row);
+            if self.resume && Path::new(&path).exists() {
+                self.kept += 1;
+                continue;
+            }
+            let (left, width, right) = self.span(column, level_width);
+            let x = column * self.dzi.tile_size - left;
+            let across = left + width + right;
+            let mut tile = Pixels::new(across, rows.len() as u32);
+            for (y, &(pixels, from)) in rows.iter().enumerate() {
+                let start = (from as usize * pixels.width() as usize + x as usize) * 3;
+                let row = &pixels.as_raw()[start..start + across as usize * 3];
+                let into = y * across as usize * 3;
+                tile.as_mut()[into..into + across as usize * 3].copy_from_slice(row);
+            }
+            let tile = match self.depth {
+                BitDepth::Eight => DynamicImage::ImageRgb16(tile).into_rgb8().into(),
+                BitDepth::Sixteen => DynamicImage::ImageRgb16(tile),
+            };
+            export::save_image(&path, &tile, self.dzi.format)?;
+            self.written += 1;
+        }
+        Ok(())
+    }
+
+    /// The band of the level below made of `upper` and the band under it,
+    /// if there is one, two pixels by two averaged into one in linear
+    /// light. Pixels past the edge of an odd width or height are averaged
+    /// from those that are there.
+    fn halve(&self, upper: &Pixels, lower: Option<&Pixels>) -> Pixels {
+        let width = upper.width() as usize;
+        let height = upper.height() + lower.map_or(0, |lower| lower.height());
+        let row = |y: u32| {
+            let (pixels, y) = match y < upper.height() {
+                true => (upper, y),
+                false => (lower.expect("the rest are in the lower band"), y - upper.height()),
+            };
+            let start = y as usize * width * 3;
+            &pixels.as_raw()[start..start + width * 3]
+        };
+        let (half_width, half_height) = (width.div_ceil(2), height.div_ceil(2));
+        let mut half = Pixels::new(half_width as u32, half_height);
+        half.par_chunks_mut(half_width * 3).enumerate().for_each(|(y, out)| {
+            let y = 2 * y as u32;
+            let rows: Vec<&[u16]> = (y..(y + 2).min(height)).map(row).collect();
+            for x in 0..half_width {
+                let columns = 2 * x..(2 * x + 2).min(width);
+                let count = (rows.len() * columns.len()) as f64;
+                for channel in 0..3 {
+                    let sum: f64 = rows
+                        .iter()
+                        .flat_map(|row| columns.clone().map(move |x| row[x * 3 + channel]))
+                        .map(|value| self.linear[value as usize] as f64)
+                        .sum();
+                    let srgb = from_linear(sum / count).clamp(0.0, 1.0);
+                    out[x * 3 + channel] = (srgb * 65535.0).round() as u16;
+                }
+            }
+        });
+        half
+    }
+}
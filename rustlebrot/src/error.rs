@@ -0,0 +1,177 @@
+use crate::precision::Precision;
+use std::fmt;
+use std::io;
+
+/// Everything that can go wrong in rustlebrot, from a bad command line to a
+/// frame that can't be saved.
+///
+/// The functions that fail return one of these up to `main`, which alone
+/// reports it and picks the exit status, so none of them end the process
+/// and they can be called from elsewhere.
+#[derive(Debug)]
+pub enum RustlebrotError {
+    /// The command line, or a file it names, asks for something that can't
+    /// be done.
+    Argument(String),
+    /// An image without pixels was asked for.
+    EmptyImage { width: u32, height: u32 },
+    /// Reading `path` failed.
+    Read { path: String, source: io::Error },
+    /// Writing `path` failed.
+    Write { path: String, source: io::Error },
+    /// The file at `path` was read, but doesn't hold what it should.
+    Format { path: String, message: String },
+    /// Encoding an image or video into `path` failed.
+    Encode { path: String, message: String },
+    /// ffmpeg couldn't be started.
+    FfmpegSpawn(io::Error),
+    /// ffmpeg stopped with an error while encoding `path`. `message` is how
+    /// it exited, with the end of what it printed.
+    FfmpegExit { path: String, message: String },
+    /// The pixels of `frame`, the first of the run, can't be told apart in
+    /// `precision`, so there is nothing to render.
+    PrecisionExhausted { frame: u32, precision: Precision },
+    /// The system wouldn't give the run something it needs, like threads
+    /// or a socket.
+    System(String),
+    /// `source` happened while `frame` was rendered or saved.
+    Frame { frame: u32, source: Box<RustlebrotError> },
+    /// Encoding the saved frames into a video failed, and `command` would
+    /// encode them by hand.
+    Video { source: Box<RustlebrotError>, command: String },
+    /// `wanted`, a flag or subcommand, needs the cargo feature `feature`,
+    /// which this build was made without.
+    MissingFeature { feature: &'static str, wanted: String },
+    /// The run was stopped with Ctrl-C, after saying so.
+    Interrupted,
+}
+
+impl RustlebrotError {
+    pub fn read(path: &str, source: io::Error) -> RustlebrotError {
+        RustlebrotError::Read {
+            path: path.to_string(),
+            source,
+        }
+    }
+
+    pub fn write(path: &str, source: io::Error) -> RustlebrotError {
+        RustlebrotError::Write {
+            path: path.to_string(),
+            source,
+        }
+    }
+
+    pub fn format(path: &str, message: impl fmt::Display) -> RustlebrotError {
+        RustlebrotError::Format {
+            path: path.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    pub fn encode(path: &str, message: impl fmt::Display) -> RustlebrotError {
+        RustlebrotError::Encode {
+            path: path.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    pub fn missing_feature(feature: &'static str, wanted: impl fmt::Display) -> RustlebrotError {
+        RustlebrotError::MissingFeature {
+            feature,
+            wanted: wanted.to_string(),
+        }
+    }
+
+    /// This error, as it happened in `frame`.
+    pub fn in_frame(self, frame: u32) -> RustlebrotError {
+        RustlebrotError::Frame {
+            frame,
+            source: Box::new(self),
+        }
+    }
+
+    /// Fails unless an image of `width` by `height` has any pixels.
+    pub fn check_size(width: u32, height: u32) -> Result<(), RustlebrotError> {
+        match width == 0 || height == 0 {
+            true => Err(RustlebrotError::EmptyImage { width, height }),
+            false => Ok(()),
+        }
+    }
+
+    /// The exit status of a run that ends with this error. A video that
+    /// failed leaves its frames saved, so it exits with 2 instead of the 1
+    /// of every other error, and Ctrl-C with 128 plus the number of SIGINT,
+    /// as shells report it.
+    pub fn exit_status(&self) -> i32 {
+        match self {
+            RustlebrotError::Video { .. } => 2,
+            RustlebrotError::Interrupted => 130,
+            _ => 1,
+        }
+    }
+}
+
+impl fmt::Display for RustlebrotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RustlebrotError::Argument(message) | RustlebrotError::System(message) => {
+                write!(f, "{}", message)
+            }
+            RustlebrotError::EmptyImage { width, height } => write!(
+                f,
+                "a {}x{} image has no pixels, width and height should be at least 1",
+                width, height
+            ),
+            RustlebrotError::Read { path, source } => write!(f, "can't read {}: {}", path, source),
+            RustlebrotError::Write { path, source } => {
+                write!(f, "failed to write {}: {}", path, source)
+            }
+            RustlebrotError::Format { path, message } => write!(f, "{}: {}", path, message),
+            RustlebrotError::Encode { path, message } => {
+                write!(f, "failed to encode {}: {}", path, message)
+            }
+            RustlebrotError::FfmpegSpawn(source) => write!(f, "failed to start ffmpeg: {}", source),
+            RustlebrotError::FfmpegExit { path, message } => {
+                write!(f, "ffmpeg failed to encode {}: {}", path, message)
+            }
+            RustlebrotError::PrecisionExhausted { frame, precision } => write!(
+                f,
+                "pixels can't be told apart in {} from frame {} on, the first of the zoom; {}\
+                 pass --allow-precision-loss to render anyway",
+                precision.name(),
+                frame,
+                match cfg!(feature = "bigfloat") {
+                    true => "try --precision auto, or ",
+                    false => "precisions past double-double need the bigfloat feature, or ",
+                }
+            ),
+            RustlebrotError::Frame { frame, source } => write!(f, "frame {}: {}", frame, source),
+            RustlebrotError::Video { source, command } => write!(
+                f,
+                "{}\nThe frames are saved. To encode them by hand, run:\n  {}",
+                source, command
+            ),
+            RustlebrotError::MissingFeature { feature, wanted } => write!(
+                f,
+                "{} isn't available, rustlebrot was built without the {} feature; rebuild it \
+                 with --features {}",
+                wanted, feature, feature
+            ),
+            RustlebrotError::Interrupted => write!(f, "stopped with Ctrl-C"),
+        }
+    }
+}
+
+impl std::error::Error for RustlebrotError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RustlebrotError::Read { source, .. }
+            | RustlebrotError::Write { source, .. }
+            | RustlebrotError::FfmpegSpawn(source) => Some(source),
+            RustlebrotError::Frame { source, .. } | RustlebrotError::Video { source, .. } => {
+                Some(source.as_ref())
+            }
+            _ => None,
+        }
+    }
+}
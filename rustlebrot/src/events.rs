@@ -0,0 +1,253 @@
+use serde::Serialize;
+use std::fmt::Display;
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+#[cfg(feature = "dashboard")]
+use std::sync::OnceLock;
+
+/// Version of the event schema, sent with `run_started`. It changes
+/// whenever an event or field is removed or changes meaning; new events and
+/// fields can be added without changing it.
+pub const EVENTS_VERSION: u32 = 1;
+
+static JSON: AtomicBool = AtomicBool::new(false);
+
+/// Where events are handed as well, once `forward` is called.
+#[cfg(feature = "dashboard")]
+static FORWARD: OnceLock<Box<dyn Fn(serde_json::Value) + Send + Sync>> = OnceLock::new();
+
+/// How much is said for people to read, as set by `set_verbosity`.
+static VERBOSITY: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+
+/// How much a run says for people to read, set with the global `--quiet`
+/// and `--verbose` flags. Errors and events are sent either way.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub enum Verbosity {
+    /// Nothing but errors.
+    Quiet,
+    Normal,
+    /// Also the lines of `detail`, like the settings a render resolved to.
+    Verbose,
+}
+
+/// How a render reports its progress.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProgressFormat {
+    /// Lines for people to read on stdout.
+    Human,
+    /// One JSON event per line on stdout, with the lines for people moved
+    /// to stderr.
+    Json,
+}
+
+impl ProgressFormat {
+    pub fn from_name(name: &str) -> Option<ProgressFormat> {
+        match name {
+            "human" => Some(ProgressFormat::Human),
+            "json" => Some(ProgressFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// A step of a run, as sent with `--progress-format json`.
+///
+/// Every event is an object with an `event` field naming it, followed by
+/// its fields.
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    /// Sent first, with the parameters the run resolved to.
+    RunStarted {
+        version: u32,
+        software: String,
+        fractal: &'a str,
+        mode: &'a str,
+        precision: &'a str,
+        coloring: &'a str,
+        center: &'a (String, String),
+        zoom_factor: f64,
+        max_iter: u32,
+        width: u32,
+        height: u32,
+        palette: &'a str,
+        zoom_start: u32,
+        zoom_end: u32,
+        output_dir: &'a str,
+        /// The frames kept from the run being resumed.
+        resumed: &'a [u32],
+    },
+    /// Sent for every frame with `--dry-run` in place of rendering it.
+    FramePlanned {
+        frame: u32,
+        center: &'a (String, String),
+        x_range: (f64, f64),
+        y_range: (f64, f64),
+        /// The width of a pixel in the complex plane.
+        pixel_size: f64,
+        /// The scale of the view relative to the first frame of a plain zoom.
+        magnification: f64,
+        /// The turn of the frame, in degrees counterclockwise.
+        rotation: f64,
+        max_iter: u32,
+        precision: &'a str,
+        /// Steps of the precision's resolution a sample spans, when it can
+        /// run out.
+        ulps_per_pixel: Option<f64>,
+        /// Whether a sample spans so few ulps of the center in f64 that
+        /// plain f64 frames turn blocky.
+        f64_questionable: bool,
+        /// Whether the width of the view is below the smallest normal f64,
+        /// past which pixel offsets lose bits in every precision.
+        underflow: bool,
+    },
+    FrameStarted {
+        frame: u32,
+    },
+    /// Sent for every pass of `--preview-progressive`, once it has been
+    /// written to `path`.
+    FramePreviewed {
+        frame: u32,
+        /// Pixels between the samples of the pass along both axes, 1 for
+        /// the frame itself.
+        stride: u32,
+        /// Wall time of the pass, in seconds.
+        seconds: f64,
+        path: &'a str,
+    },
+    FrameCompleted {
+        frame: u32,
+        /// Wall time of the frame, in seconds.
+        seconds: f64,
+        /// The files written for the frame, none when it only went to the
+        /// video.
+        paths: Vec<String>,
+        /// The mean iteration count of the pixels that escaped, for escape
+        /// time colorings.
+        mean_iterations: Option<f64>,
+        /// The fraction of the pixels anti-aliased with `--adaptive`.
+        refined: Option<f64>,
+    },
+    /// Sent after every frame is written, with how far the run has got.
+    Progress {
+        /// Frames written so far, of `frames` in all.
+        done: usize,
+        frames: usize,
+        /// Wall time since the frames started, in seconds.
+        elapsed: f64,
+        /// The rate over the latest frames, once there are two.
+        frames_per_minute: Option<f64>,
+        /// The predicted seconds until the frames are done.
+        eta: Option<f64>,
+    },
+    /// The frames have been uniform for long enough that the run stops
+    /// early, after the frames in progress.
+    StoppedEarly {
+        /// The last of the uniform frames.
+        frame: u32,
+        /// Uniform frames in a row that stop a run.
+        frames: u32,
+        /// The spread under which a frame counts as uniform, in iterations.
+        spread: f64,
+    },
+    VideoStarted {
+        path: &'a str,
+        encoder: &'a str,
+    },
+    VideoCompleted {
+        path: &'a str,
+    },
+    Error {
+        message: &'a str,
+    },
+}
+
+/// Sets how progress is reported for the rest of the run.
+pub fn set_format(format: ProgressFormat) {
+    JSON.store(format == ProgressFormat::Json, Ordering::Relaxed);
+}
+
+/// Sets how much is said for the rest of the run.
+pub fn set_verbosity(verbosity: Verbosity) {
+    VERBOSITY.store(verbosity as u8, Ordering::Relaxed);
+}
+
+fn verbosity() -> u8 {
+    VERBOSITY.load(Ordering::Relaxed)
+}
+
+/// Sends `event` as a line of JSON on stdout, if events were asked for,
+/// and to where they are forwarded.
+pub fn emit(event: &Event) {
+    if JSON.load(Ordering::Relaxed) {
+        let json = serde_json::to_string(event).expect("events serialize to JSON");
+        println!("{}", json);
+    }
+    #[cfg(feature = "dashboard")]
+    if let Some(forward) = FORWARD.get() {
+        forward(serde_json::to_value(event).expect("events serialize to JSON"));
+    }
+}
+
+/// Hands every event from now on to `forward` as well, as JSON, whatever
+/// the progress format. Only the first call has any effect.
+#[cfg(feature = "dashboard")]
+pub fn forward(forward: impl Fn(serde_json::Value) + Send + Sync + 'static) {
+    let _ = FORWARD.set(Box::new(forward));
+}
+
+/// Prints a line for people to read. It goes to stdout, unless stdout
+/// carries events.
+pub fn say(message: impl Display) {
+    if verbosity() == Verbosity::Quiet as u8 {
+        return;
+    }
+    if JSON.load(Ordering::Relaxed) {
+        eprintln!("{}", message);
+    } else {
+        println!("{}", message);
+    }
+}
+
+/// Prints `text` for people to read without ending the line, where `say`
+/// would.
+pub fn say_partial(text: &str) {
+    if verbosity() == Verbosity::Quiet as u8 {
+        return;
+    }
+    if JSON.load(Ordering::Relaxed) {
+        eprint!("{}", text);
+        let _ = std::io::stderr().flush();
+    } else {
+        print!("{}", text);
+        let _ = std::io::stdout().flush();
+    }
+}
+
+/// Prints a line for people to read with `--verbose`, where `say` would.
+pub fn detail(message: impl Display) {
+    if verbosity() == Verbosity::Verbose as u8 {
+        say(message);
+    }
+}
+
+/// Whether the lines for people to read go to a terminal.
+pub fn says_to_terminal() -> bool {
+    match JSON.load(Ordering::Relaxed) {
+        true => std::io::stderr().is_terminal(),
+        false => std::io::stdout().is_terminal(),
+    }
+}
+
+/// Whether the person running the program can be asked something: the
+/// lines for people to read go to a terminal, and answers come from one.
+pub fn asks() -> bool {
+    verbosity() != Verbosity::Quiet as u8 && says_to_terminal() && std::io::stdin().is_terminal()
+}
+
+/// Reports `message` as the error a run ended with, on stderr and as an
+/// `error` event.
+pub fn report(message: &str) {
+    eprintln!("Error: {}", message);
+    emit(&Event::Error { message });
+}
@@ -0,0 +1,220 @@
+use crate::dither::Dither;
+use crate::render::{from_linear, to_linear, Channel, Rotation};
+use image::DynamicImage;
+use rayon::prelude::*;
+use std::f64::consts::TAU;
+
+/// Rings of the map in every strip, the rows of its image.
+pub const STRIP_RINGS: u32 = 256;
+
+/// The most taps along each side of a pixel when it is resampled from the
+/// map. Towards the center a pixel covers more and more of the map, so its
+/// taps supersample it, as many as 4x4 take in.
+pub const MAX_TAPS: u32 = 4;
+
+/// The exponential map of a zoom, as rendered with `--expmap`: the plane
+/// around `center` sampled on circles whose radii grow by the same factor
+/// from one to the next, `angles` points around each.
+///
+/// Ring `k` has a radius of `exp(k * step)` and its samples are `step`
+/// radians apart, so the samples of every ring are as far apart around it
+/// as from the next ring out, and a zoom only slides along the rings. The
+/// rings are numbered from the unit circle, so maps of the same center and
+/// angles share rings at every depth, and the strips of one zoom serve
+/// another that goes through the same radii.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ExpMap {
+    pub center: (f64, f64),
+    pub angles: u32,
+}
+
+/// What a frame resampled from the map shows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct View {
+    pub width: u32,
+    pub height: u32,
+    /// The width and height of a pixel in the plane.
+    pub pixel_size: (f64, f64),
+    /// The turn of the frame about the center of the map.
+    pub rotation: Rotation,
+}
+
+impl View {
+    /// The distance from the center to the corners of the frame.
+    fn outer(&self) -> f64 {
+        let half = (self.width as f64 * self.pixel_size.0, self.height as f64 * self.pixel_size.1);
+        half.0.hypot(half.1) / 2.0
+    }
+
+    /// The radius inside of which pixels are all taken from the rings
+    /// there, half a pixel.
+    fn inner(&self) -> f64 {
+        self.pixel_size.0.min(self.pixel_size.1) / 2.0
+    }
+}
+
+/// The colors of `STRIP_RINGS` rings of a map, from its strip `index` on.
+pub struct Strip {
+    pub index: i32,
+    angles: u32,
+    /// The channels of every sample, row by row, a row for every ring.
+    channels: Vec<u16>,
+}
+
+impl ExpMap {
+    /// The map frames of `width` by `height` samples around `center` are
+    /// resampled from, whose samples are as far apart on a circle through
+    /// the corners of a frame as the samples of the frame are.
+    pub fn for_frames(center: (f64, f64), width: u32, height: u32) -> ExpMap {
+        let corner = (width as f64).hypot(height as f64) / 2.0;
+        ExpMap {
+            center,
+            angles: ((TAU * corner).ceil() as u32).max(8),
+        }
+    }
+
+    /// The angle between neighboring samples of a ring, which is also the
+    /// log of the factor from one ring to the next.
+    pub fn step(&self) -> f64 {
+        TAU / self.angles as f64
+    }
+
+    /// The radius of ring `ring`, which needn't be a whole ring.
+    pub fn radius(&self, ring: f64) -> f64 {
+        (ring * self.step()).exp()
+    }
+
+    /// The ring at `radius`, which needn't be a whole ring.
+    pub fn ring(&self, radius: f64) -> f64 {
+        radius.ln() / self.step()
+    }
+
+    /// The point of the plane at `angle`, in samples counterclockwise from
+    /// the positive real axis, of `ring`.
+    pub fn point(&self, ring: f64, angle: f64) -> (f64, f64) {
+        let (sin, cos) = (angle * self.step()).sin_cos();
+        let radius = self.radius(ring);
+        (self.center.0 + radius * cos, self.center.1 + radius * sin)
+    }
+
+    /// The strip ring `ring` is a row of.
+    pub fn strip_of(ring: i64) -> i32 {
+        ring.div_euclid(STRIP_RINGS as i64) as i32
+    }
+
+    /// The strips a frame of `view` is resampled from, in order.
+    pub fn strips(&self, view: &View) -> std::ops::RangeInclusive<i32> {
+        let inner = self.ring(view.inner()).floor() as i64;
+        let outer = self.ring(view.outer()).ceil() as i64 + 1;
+        ExpMap::strip_of(inner)..=ExpMap::strip_of(outer)
+    }
+
+    /// Resamples the frame of `view` from `strips`, which are the strips
+    /// `strips(view)` names, in order, into an image with channels of type
+    /// `T`, rounded as `dither` says.
+    ///
+    /// Every pixel is the mean of a square of taps over it, as many along
+    /// each side as the map has samples across the pixel, up to
+    /// `MAX_TAPS`, and each tap interpolates between the four samples
+    /// around it. Colors are averaged in linear light, as the samples of a
+    /// rendered frame are. Pixels within half a pixel of the center take
+    /// the rings there, which are as close as the map goes.
+    pub fn reproject<T: Channel>(
+        &self,
+        view: &View,
+        strips: &[&Strip],
+        dither: Dither,
+    ) -> DynamicImage {
+        let intensities: Vec<f64> =
+            (0..=u16::MAX).map(|channel| to_linear(channel as f64 / 65535.0)).collect();
+        let first = strips[0].index as i64 * STRIP_RINGS as i64;
+        let rings = (first, first + (strips.len() as u32 * STRIP_RINGS) as i64 - 1);
+        let lowest = (rings.0 as f64).max(self.ring(view.inner()));
+        let texel = |ring: i64, angle: i64| {
+            let ring = ring.clamp(rings.0, rings.1) - first;
+            let strip = strips[(ring / STRIP_RINGS as i64) as usize];
+            let row = (ring % STRIP_RINGS as i64) as usize;
+            let angle = angle.rem_euclid(self.angles as i64) as usize;
+            let at = (row * self.angles as usize + angle) * 3;
+            [0, 1, 2].map(|channel| intensities[strip.channels[at + channel] as usize])
+        };
+        // The color at a plane offset from the center, between the samples
+        // around it.
+        let tap = |offset: (f64, f64)| {
+            let (dx, dy) = view.rotation.apply(offset);
+            let ring = self.ring(dx.hypot(dy)).max(lowest);
+            let angle = dy.atan2(dx).rem_euclid(TAU) / self.step();
+            let (k, j) = (ring.floor(), angle.floor());
+            let (t, s) = (ring - k, angle - j);
+            let (k, j) = (k as i64, j as i64);
+            let corners = [
+                ((k, j), (1.0 - t) * (1.0 - s)),
+                ((k, j + 1), (1.0 - t) * s),
+                ((k + 1, j), t * (1.0 - s)),
+                ((k + 1, j + 1), t * s),
+            ];
+            let mut color = [0.0; 3];
+            for ((ring, angle), weight) in corners {
+                let sample = texel(ring, angle);
+                for (channel, value) in color.iter_mut().zip(sample) {
+                    *channel += weight * value;
+                }
+            }
+            color
+        };
+        let (width, height) = (view.width as usize, view.height as usize);
+        let (sx, sy) = view.pixel_size;
+        let half = (view.width as f64 * sx / 2.0, view.height as f64 * sy / 2.0);
+        let mut data = vec![T::from_unit(0.0); width * height * 3];
+        data.par_chunks_mut(3).enumerate().for_each(|(pixel, chunk)| {
+            let (x, y) = ((pixel % width) as f64, (pixel / width) as f64);
+            // Taps start at the corner of the pixel, where the one sample
+            // of a rendered frame is taken.
+            let middle = ((x + 0.5) * sx - half.0, half.1 - (y + 0.5) * sy);
+            let across = sx.max(sy) / (middle.0.hypot(middle.1) * self.step());
+            let taps = (across.ceil() as u32).clamp(1, MAX_TAPS);
+            let mut sum = [0.0; 3];
+            for i in 0..taps {
+                for j in 0..taps {
+                    let (u, v) = (i as f64 / taps as f64, j as f64 / taps as f64);
+                    let color = tap(((x + u) * sx - half.0, half.1 - (y + v) * sy));
+                    for (sum, channel) in sum.iter_mut().zip(color) {
+                        *sum += channel;
+                    }
+                }
+            }
+            let count = (taps * taps) as f64;
+            let offset = dither.offset((pixel % width) as u32, (pixel / width) as u32);
+            let rgb = sum.map(|sum| from_linear(sum / count));
+            chunk.copy_from_slice(&rgb.map(|channel| T::dithered(channel, offset)));
+        });
+        T::image(view.width, view.height, data)
+    }
+}
+
+impl Strip {
+    /// The strip `index` of a map of `angles` samples around, from its
+    /// colored image, a column for every angle and a row for every ring
+    /// from the innermost down.
+    pub fn new(index: i32, angles: u32, image: &DynamicImage) -> Result<Strip, String> {
+        if (image.width(), image.height()) != (angles, STRIP_RINGS) {
+            return Err(format!(
+                "a strip is {}x{}, got {}x{}",
+                angles,
+                STRIP_RINGS,
+                image.width(),
+                image.height()
+            ));
+        }
+        Ok(Strip {
+            index,
+            angles,
+            channels: image.to_rgb16().into_raw(),
+        })
+    }
+
+    /// The samples around each ring.
+    pub fn angles(&self) -> u32 {
+        self.angles
+    }
+}
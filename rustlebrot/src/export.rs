@@ -0,0 +1,749 @@
+use crate::coloring::Coloring;
+use crate::error::RustlebrotError;
+use crate::render::{BitDepth, EscapeBuffer, Sample};
+use image::codecs::bmp::BmpEncoder;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::tiff::TiffEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::DynamicImage;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufWriter, Cursor, Seek, Write};
+use exr::prelude::{
+    AnyChannel, AnyChannels, Encoding, FlatSamples, Image, Layer, LayerAttributes, WritableImage,
+};
+
+/// The files written for every frame, as chosen with `--export`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Export {
+    /// The colored frame.
+    pub png: bool,
+    /// The raw escape values as 32-bit floats, for compositing.
+    pub exr: bool,
+}
+
+impl Export {
+    /// Parses a comma separated list of formats, like `png,exr`.
+    pub fn from_spec(spec: &str) -> Result<Export, String> {
+        let mut export = Export {
+            png: false,
+            exr: false,
+        };
+        for format in spec.split(',').map(str::trim) {
+            match format {
+                "png" => export.png = true,
+                "exr" => export.exr = true,
+                _ => return Err(format!("unknown export format '{}'", format)),
+            }
+        }
+        Ok(export)
+    }
+}
+
+/// The file format colored frames are saved in, as chosen with
+/// `--image-format`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImageFormat {
+    Png,
+    /// Lossy, at a quality from 1 to 100.
+    Jpeg { quality: u8 },
+    /// Always lossless, since lossy WebP needs libwebp, which isn't linked.
+    WebP,
+    Tiff,
+    Bmp,
+}
+
+impl ImageFormat {
+    /// The quality of JPEG frames without `--jpeg-quality`.
+    pub const JPEG_QUALITY: u8 = 90;
+
+    pub fn from_name(name: &str) -> Option<ImageFormat> {
+        match name {
+            "png" => Some(ImageFormat::Png),
+            "jpeg" | "jpg" => Some(ImageFormat::Jpeg {
+                quality: ImageFormat::JPEG_QUALITY,
+            }),
+            "webp" => Some(ImageFormat::WebP),
+            "tiff" | "tif" => Some(ImageFormat::Tiff),
+            "bmp" => Some(ImageFormat::Bmp),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg { .. } => "jpeg",
+            ImageFormat::WebP => "webp",
+            ImageFormat::Tiff => "tiff",
+            ImageFormat::Bmp => "bmp",
+        }
+    }
+
+    /// The extension of the frame files, which ffmpeg tells the format by.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Jpeg { .. } => "jpg",
+            format => format.name(),
+        }
+    }
+
+    /// The format of a file with `extension`.
+    pub fn from_extension(extension: &str) -> Option<ImageFormat> {
+        ImageFormat::from_name(&extension.to_ascii_lowercase())
+    }
+
+    /// Whether frames read back have the colors they were saved with.
+    pub fn lossless(self) -> bool {
+        !matches!(self, ImageFormat::Jpeg { .. })
+    }
+
+    /// Whether the format holds frames of `depth`. Only PNG and TIFF have
+    /// 16-bit channels.
+    pub fn holds(self, depth: BitDepth) -> bool {
+        depth == BitDepth::Eight || matches!(self, ImageFormat::Png | ImageFormat::Tiff)
+    }
+
+    /// Whether the format has an alpha channel, which JPEG and BMP frames
+    /// are saved without.
+    pub fn has_alpha(self) -> bool {
+        matches!(self, ImageFormat::Png | ImageFormat::WebP | ImageFormat::Tiff)
+    }
+
+    /// Roughly the bytes per pixel of a detailed frame of `depth` in this
+    /// format, on the high side, for the estimate of how much room a run
+    /// needs. The compressed formats are measured on the Seahorse valley.
+    pub fn bytes_per_pixel(self, depth: BitDepth, alpha: bool) -> f64 {
+        let channels = if alpha && self.has_alpha() { 4.0 } else { 3.0 };
+        let bytes = match depth {
+            BitDepth::Eight => 1.0,
+            BitDepth::Sixteen => 2.0,
+        };
+        match self {
+            ImageFormat::Png => bytes,
+            ImageFormat::Jpeg { quality } => quality as f64 / 100.0,
+            ImageFormat::WebP => 1.1,
+            ImageFormat::Tiff => channels * bytes,
+            ImageFormat::Bmp => channels,
+        }
+    }
+
+    /// The ffmpeg decoder of frames piped in this format.
+    pub fn ffmpeg_codec(self) -> &'static str {
+        match self {
+            ImageFormat::Jpeg { .. } => "mjpeg",
+            format => format.name(),
+        }
+    }
+}
+
+/// Saves `img` at `path` in `format`. Only PNGs have room for `metadata`,
+/// which other formats go without.
+pub fn save_frame(
+    path: &str,
+    img: &DynamicImage,
+    format: ImageFormat,
+    metadata: &Metadata,
+) -> Result<(), RustlebrotError> {
+    write_file(path, &encode_frame(path, img, format, metadata)?)
+}
+
+/// `img` encoded in `format` as `save_frame` saves it at `path`, without
+/// writing it there.
+pub fn encode_frame(
+    path: &str,
+    img: &DynamicImage,
+    format: ImageFormat,
+    metadata: &Metadata,
+) -> Result<Vec<u8>, RustlebrotError> {
+    let mut bytes = Cursor::new(Vec::new());
+    match format {
+        ImageFormat::Png => encode_png(&mut bytes, img, &metadata.text())
+            .map_err(|e| RustlebrotError::encode(path, e))?,
+        format => encode_image(&mut bytes, img, format)
+            .map_err(|e| RustlebrotError::encode(path, e))?,
+    }
+    Ok(bytes.into_inner())
+}
+
+/// Writes `bytes` to `path`, beside it until complete like frames are.
+pub fn write_file(path: &str, bytes: &[u8]) -> Result<(), RustlebrotError> {
+    let partial = format!("{}.part", path);
+    fs::write(&partial, bytes).map_err(|e| RustlebrotError::write(&partial, e))?;
+    fs::rename(&partial, path).map_err(|e| RustlebrotError::write(path, e))
+}
+
+/// Saves `img` at `path` in `format`, without metadata.
+pub fn save_image(
+    path: &str,
+    img: &DynamicImage,
+    format: ImageFormat,
+) -> Result<(), RustlebrotError> {
+    if format == ImageFormat::Png {
+        return save_png_text(path, img, &[]);
+    }
+    // Like PNGs, written beside `path` until complete.
+    let partial = format!("{}.part", path);
+    let file = fs::File::create(&partial).map_err(|e| RustlebrotError::write(&partial, e))?;
+    let mut writer = BufWriter::new(file);
+    encode_image(&mut writer, img, format).map_err(|e| RustlebrotError::encode(path, e))?;
+    writer.flush().map_err(|e| RustlebrotError::write(&partial, e))?;
+    fs::rename(&partial, path).map_err(|e| RustlebrotError::write(path, e))
+}
+
+/// Writes `img` to `writer` in `format`, which isn't PNG.
+fn encode_image<W: Write + Seek>(
+    mut writer: W,
+    img: &DynamicImage,
+    format: ImageFormat,
+) -> image::ImageResult<()> {
+    match format {
+        ImageFormat::Png => unreachable!("PNGs are encoded with their text chunks"),
+        ImageFormat::Jpeg { quality } => {
+            img.write_with_encoder(JpegEncoder::new_with_quality(writer, quality))
+        }
+        ImageFormat::WebP => img.write_with_encoder(WebPEncoder::new_lossless(writer)),
+        ImageFormat::Tiff => img.write_with_encoder(TiffEncoder::new(writer)),
+        ImageFormat::Bmp => img.write_with_encoder(BmpEncoder::new(&mut writer)),
+    }
+}
+
+/// The bit depth of the frame saved at `path`, in any `ImageFormat`.
+pub fn read_bit_depth(path: &str) -> Result<BitDepth, RustlebrotError> {
+    let sixteen = match path.ends_with(".png") {
+        true => read_png(path)?.bit_depth == png::BitDepth::Sixteen,
+        false => {
+            let img = image::open(path).map_err(|e| RustlebrotError::format(path, e))?;
+            img.color().bytes_per_pixel() > img.color().channel_count()
+        }
+    };
+    Ok(if sixteen { BitDepth::Sixteen } else { BitDepth::Eight })
+}
+
+/// Writes the raw values of a frame to an OpenEXR file at `path`.
+///
+/// The `escape` channel holds the smooth escape time of every pixel, or
+/// infinity for points that never escape. With `distance` there is also a
+/// `distance` channel with the distance estimate in pixels, which is zero for
+/// points inside the set or within half a pixel of it.
+pub fn write_exr(
+    path: &str,
+    escape: &EscapeBuffer,
+    distance: Option<&EscapeBuffer>,
+) -> Result<(), RustlebrotError> {
+    let channel = |name: &str, buffer: &EscapeBuffer, interior: f32| {
+        let values = buffer
+            .values
+            .iter()
+            .map(|sample| match *sample {
+                Sample::Value(value)
+                | Sample::Phase { value, .. }
+                | Sample::Root { iterations: value, .. }
+                | Sample::Exponent(value) => value as f32,
+                Sample::Interior | Sample::Attractor { .. } | Sample::Boundary => interior,
+            })
+            .collect();
+        AnyChannel::new(name, FlatSamples::F32(values))
+    };
+    let mut channels = vec![channel("escape", escape, f32::INFINITY)];
+    if let Some(distance) = distance {
+        channels.push(channel("distance", distance, 0.0));
+    }
+
+    let size = (escape.width as usize, escape.height as usize);
+    let layer = Layer::new(
+        size,
+        LayerAttributes::named("rustlebrot"),
+        Encoding::FAST_LOSSLESS,
+        AnyChannels::sort(channels.into()),
+    );
+    Image::from_layer(layer)
+        .write()
+        .to_file(path)
+        .map_err(|e| RustlebrotError::encode(path, e))
+}
+
+/// Version of the header written next to every dumped frame. It changes
+/// whenever the meaning of the header or the `.npy` data does, so caches from
+/// other versions are rejected instead of being misread.
+pub const CACHE_VERSION: u32 = 3;
+
+/// The `.npy` magic string and format version 1.0.
+const NPY_MAGIC: &[u8] = b"\x93NUMPY\x01\x00";
+
+/// Writes the sample values of a frame to a NumPy `.npy` file at `path`, as
+/// a row-major float64 array of shape `[height, width]`.
+///
+/// Points that never escape are stored as infinity, and points that are too
+/// close to the set for distance estimation as zero.
+pub fn write_npy(path: &str, buffer: &EscapeBuffer) -> Result<(), RustlebrotError> {
+    let mut header = format!(
+        "{{'descr': '<f8', 'fortran_order': False, 'shape': ({}, {}), }}",
+        buffer.height, buffer.width
+    );
+    // The magic, version and header length take 10 bytes, and the header is
+    // padded with spaces and a newline so the data starts 64-byte aligned.
+    let padding = 63 - (10 + header.len()) % 64;
+    header.push_str(&" ".repeat(padding));
+    header.push('\n');
+
+    let mut data = Vec::with_capacity(10 + header.len() + buffer.values.len() * 8);
+    data.extend_from_slice(NPY_MAGIC);
+    data.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    data.extend_from_slice(header.as_bytes());
+    for sample in &buffer.values {
+        let value = match *sample {
+            Sample::Value(value)
+            | Sample::Phase { value, .. }
+            | Sample::Root { iterations: value, .. }
+            | Sample::Exponent(value) => value,
+            Sample::Interior | Sample::Attractor { .. } => f64::INFINITY,
+            Sample::Boundary => 0.0,
+        };
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    fs::write(path, data).map_err(|e| RustlebrotError::write(path, e))
+}
+
+/// Reads back a frame written by `write_npy`, checking that the array has
+/// the dimensions `header` says.
+pub fn read_npy(path: &str, header: &Header) -> Result<EscapeBuffer, RustlebrotError> {
+    let data = fs::read(path).map_err(|e| RustlebrotError::read(path, e))?;
+    let invalid = |problem: &str| {
+        let problem = format!("not a frame dumped by --dump-iterations, {}", problem);
+        RustlebrotError::format(path, problem)
+    };
+    if data.len() < 10 || &data[..8] != NPY_MAGIC {
+        return Err(invalid("no .npy version 1.0 header"));
+    }
+    let length = u16::from_le_bytes([data[8], data[9]]) as usize;
+    let dict = data
+        .get(10..10 + length)
+        .and_then(|dict| std::str::from_utf8(dict).ok())
+        .ok_or_else(|| invalid("truncated header"))?;
+    if !dict.contains("'descr': '<f8'") || !dict.contains("'fortran_order': False") {
+        return Err(invalid("expected a row-major float64 array"));
+    }
+    let shape = format!("'shape': ({}, {})", header.height, header.width);
+    if !dict.contains(&shape) {
+        return Err(RustlebrotError::format(
+            path,
+            format!(
+                "doesn't match its header, which says {}x{}; the cache is stale",
+                header.width, header.height
+            ),
+        ));
+    }
+    let values = &data[10 + length..];
+    let expected = header.width as usize * header.height as usize * 8;
+    if values.len() != expected {
+        return Err(invalid(&format!("expected {} bytes of data, got {}", expected, values.len())));
+    }
+
+    let values = values
+        .chunks_exact(8)
+        .map(|bytes| match f64::from_le_bytes(bytes.try_into().unwrap()) {
+            value if value == f64::INFINITY => Sample::Interior,
+            // Only distance estimation leaves boundary pixels.
+            value if value == 0.0 && header.coloring == Coloring::Distance => Sample::Boundary,
+            value => Sample::Value(value),
+        })
+        .collect();
+    Ok(EscapeBuffer {
+        width: header.width,
+        height: header.height,
+        samples: header.samples,
+        refined: HashMap::new(),
+        max_iter: header.max_iter,
+        coloring: header.coloring,
+        values,
+        orbits: Vec::new(),
+    })
+}
+
+/// Describes a dumped frame, written as JSON next to its `.npy` file so the
+/// array can be mapped back onto the plane and colored again.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Header {
+    pub frame: u32,
+    /// Samples per row of the array, see `EscapeBuffer::width`.
+    pub width: u32,
+    pub height: u32,
+    /// Samples along each side of a pixel.
+    pub samples: u32,
+    pub x_range: (f64, f64),
+    pub y_range: (f64, f64),
+    /// Whether the first row of the array is at `y_range.0`. Otherwise it is
+    /// at `y_range.1`, the top of the frame.
+    pub flip_y: bool,
+    /// How far the frame is turned about its center, in degrees
+    /// counterclockwise. Headers from before rotation have 0.
+    pub rotation: f64,
+    /// The center as given, which keeps the digits the ranges lose at depth.
+    pub center: (String, String),
+    pub zoom_factor: f64,
+    pub max_iter: u32,
+    /// The escape time the gradient was spread over, which recoloring needs
+    /// to give escape times the same positions.
+    pub palette_iter: u32,
+    /// What the values are, such as escape times or distances.
+    pub coloring: Coloring,
+}
+
+/// Writes `header` as JSON to `path`.
+pub fn write_header(path: &str, header: &Header) -> Result<(), RustlebrotError> {
+    let json = format!(
+        concat!(
+            "{{\n",
+            "  \"version\": {},\n",
+            "  \"frame\": {},\n",
+            "  \"width\": {},\n",
+            "  \"height\": {},\n",
+            "  \"samples\": {},\n",
+            "  \"x_min\": {:?},\n",
+            "  \"x_max\": {:?},\n",
+            "  \"y_min\": {:?},\n",
+            "  \"y_max\": {:?},\n",
+            "  \"flip_y\": {},\n",
+            "  \"rotation\": {:?},\n",
+            "  \"center\": [\"{}\", \"{}\"],\n",
+            "  \"zoom_factor\": {:?},\n",
+            "  \"max_iter\": {},\n",
+            "  \"palette_iter\": {},\n",
+            "  \"values\": \"{}\"\n",
+            "}}\n"
+        ),
+        CACHE_VERSION,
+        header.frame,
+        header.width,
+        header.height,
+        header.samples,
+        header.x_range.0,
+        header.x_range.1,
+        header.y_range.0,
+        header.y_range.1,
+        header.flip_y,
+        header.rotation,
+        header.center.0,
+        header.center.1,
+        header.zoom_factor,
+        header.max_iter,
+        header.palette_iter,
+        header.coloring.name(),
+    );
+    fs::write(path, json).map_err(|e| RustlebrotError::write(path, e))
+}
+
+/// Reads a header written by `write_header`.
+///
+/// This only understands the flat layout `write_header` produces, one field
+/// per line. Headers without a version are from before caches were
+/// versioned, and like those of any other version are rejected.
+pub fn read_header(path: &str) -> Result<Header, RustlebrotError> {
+    let json = fs::read_to_string(path).map_err(|e| RustlebrotError::read(path, e))?;
+    let invalid = |problem: String| RustlebrotError::format(path, problem);
+    let fields: HashMap<&str, &str> = json
+        .lines()
+        .filter_map(|line| line.trim().trim_end_matches(',').split_once(':'))
+        .map(|(key, value)| (key.trim().trim_matches('"'), value.trim()))
+        .collect();
+    let field = |key: &str| {
+        fields
+            .get(key)
+            .copied()
+            .ok_or_else(|| invalid(format!("no '{}' field", key)))
+    };
+    let number = |key: &str| {
+        field(key)?
+            .parse::<f64>()
+            .map_err(|_| invalid(format!("'{}' should be a number", key)))
+    };
+    let integer = |key: &str| {
+        field(key)?
+            .parse::<u32>()
+            .map_err(|_| invalid(format!("'{}' should be an integer", key)))
+    };
+
+    let version = fields.get("version").map_or(Ok(0), |_| integer("version"))?;
+    if version != CACHE_VERSION {
+        return Err(invalid(format!(
+            "a version {} cache, but this build reads version {}; dump the frames again with \
+             --dump-iterations",
+            version, CACHE_VERSION
+        )));
+    }
+    // Frames dumped before supersampling have a sample per pixel.
+    let samples = fields.get("samples").map_or(Ok(1), |_| integer("samples"))?;
+    if samples == 0 {
+        return Err(invalid("'samples' should be at least 1".to_string()));
+    }
+    let string = |value: &str| value.trim().trim_matches('"').to_string();
+    let center = field("center")?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .split_once(',')
+        .map(|(x, y)| (string(x), string(y)))
+        .ok_or_else(|| invalid("'center' should be two strings".to_string()))?;
+    let flip_y = match field("flip_y")? {
+        "true" => true,
+        "false" => false,
+        _ => return Err(invalid("'flip_y' should be true or false".to_string())),
+    };
+    let rotation = fields.get("rotation").map_or(Ok(0.0), |_| number("rotation"))?;
+    let values = field("values")?.trim_matches('"');
+    let coloring = Coloring::from_name(values)
+        .ok_or_else(|| invalid(format!("unknown values '{}'", values)))?;
+    Ok(Header {
+        frame: integer("frame")?,
+        width: integer("width")?,
+        height: integer("height")?,
+        samples,
+        x_range: (number("x_min")?, number("x_max")?),
+        y_range: (number("y_min")?, number("y_max")?),
+        flip_y,
+        rotation,
+        center,
+        zoom_factor: number("zoom_factor")?,
+        max_iter: integer("max_iter")?,
+        palette_iter: integer("palette_iter")?,
+        coloring,
+    })
+}
+
+/// How a frame was rendered, embedded in its PNG so the frame can be found
+/// again from the file alone.
+pub struct Metadata<'a> {
+    pub frame: u32,
+    /// The center as given, with every digit.
+    pub center: &'a (String, String),
+    pub x_range: (f64, f64),
+    pub y_range: (f64, f64),
+    /// The turn of the frame in degrees, only saved when there is one.
+    pub rotation: f64,
+    pub zoom_factor: f64,
+    pub max_iter: u32,
+    pub palette: &'a str,
+}
+
+impl Metadata<'_> {
+    /// The keywords and texts of the chunks the metadata is saved in.
+    pub fn text(&self) -> Vec<(&'static str, String)> {
+        let (x, y) = self.center;
+        let mut text = vec![
+            ("Software", format!("rustlebrot {}", env!("CARGO_PKG_VERSION"))),
+            ("Frame", self.frame.to_string()),
+            ("Center", format!("{},{}", x, y)),
+            ("X Range", format!("{:?},{:?}", self.x_range.0, self.x_range.1)),
+            ("Y Range", format!("{:?},{:?}", self.y_range.0, self.y_range.1)),
+            ("Zoom Factor", format!("{:?}", self.zoom_factor)),
+            ("Max Iter", self.max_iter.to_string()),
+            ("Palette", self.palette.to_string()),
+        ];
+        if self.rotation != 0.0 {
+            text.insert(5, ("Rotation", format!("{:?}", self.rotation)));
+        }
+        text
+    }
+}
+
+/// Saves `img` as a PNG at `path`, with `text` in text chunks.
+pub fn save_png_text(
+    path: &str,
+    img: &DynamicImage,
+    text: &[(&str, String)],
+) -> Result<(), RustlebrotError> {
+    // Written beside `path` and moved there once complete, so a run that
+    // is stopped can't leave a frame cut short.
+    let partial = format!("{}.part", path);
+    let file = fs::File::create(&partial).map_err(|e| RustlebrotError::write(&partial, e))?;
+    encode_png(BufWriter::new(file), img, text).map_err(|e| RustlebrotError::encode(path, e))?;
+    fs::rename(&partial, path).map_err(|e| RustlebrotError::write(path, e))
+}
+
+/// Writes `img` to `writer` as a PNG, with `text` in text chunks.
+///
+/// Pixels are encoded with the same settings `DynamicImage::save` uses.
+pub fn encode_png(
+    writer: impl Write,
+    img: &DynamicImage,
+    text: &[(&str, String)],
+) -> Result<(), png::EncodingError> {
+    let (depth, data) = match img {
+        DynamicImage::ImageRgb8(img) => (BitDepth::Eight, img.as_raw().clone()),
+        DynamicImage::ImageRgb16(img) => (BitDepth::Sixteen, big_endian(img.as_raw())),
+        DynamicImage::ImageRgba8(img) => (BitDepth::Eight, img.as_raw().clone()),
+        DynamicImage::ImageRgba16(img) => (BitDepth::Sixteen, big_endian(img.as_raw())),
+        // The diagnostic images of `--debug-channels`.
+        DynamicImage::ImageLuma16(img) => (BitDepth::Sixteen, big_endian(img.as_raw())),
+        _ => unreachable!("frames are rendered as RGB or RGBA"),
+    };
+    let color = match (img.color().has_color(), img.color().has_alpha()) {
+        (false, _) => png::ColorType::Grayscale,
+        (true, true) => png::ColorType::Rgba,
+        (true, false) => png::ColorType::Rgb,
+    };
+    let mut writer = png_writer(writer, img.width(), img.height(), depth, color, text)?;
+    writer.write_image_data(&data)?;
+    writer.finish()
+}
+
+/// 16-bit channels as PNG stores them, big-endian.
+fn big_endian(channels: &[u16]) -> Vec<u8> {
+    channels.iter().flat_map(|c| c.to_be_bytes()).collect()
+}
+
+/// A PNG encoder into `writer`, with its header and `text` written.
+fn png_writer<W: Write>(
+    writer: W,
+    width: u32,
+    height: u32,
+    depth: BitDepth,
+    color: png::ColorType,
+    text: &[(&str, String)],
+) -> Result<png::Writer<W>, png::EncodingError> {
+    let mut encoder = png::Encoder::new(writer, width, height);
+    encoder.set_color(color);
+    encoder.set_depth(match depth {
+        BitDepth::Eight => png::BitDepth::Eight,
+        BitDepth::Sixteen => png::BitDepth::Sixteen,
+    });
+    encoder.set_compression(png::Compression::Default);
+    encoder.set_filter(png::FilterType::Sub);
+    encoder.set_adaptive_filter(png::AdaptiveFilterType::Adaptive);
+
+    for (keyword, text) in text {
+        encoder.add_text_chunk(keyword.to_string(), text.clone())?;
+    }
+    encoder.write_header()
+}
+
+/// A PNG written a band of rows at a time, for images too large to hold in
+/// memory whole.
+pub struct PngStream {
+    writer: png::StreamWriter<'static, BufWriter<fs::File>>,
+    path: String,
+    partial: String,
+}
+
+impl PngStream {
+    /// Starts the PNG of a `width` by `height` image at `path`, with `text`
+    /// in text chunks. As with `save_png`, it only gets there once
+    /// `finish` is called.
+    pub fn create(
+        path: &str,
+        width: u32,
+        height: u32,
+        depth: BitDepth,
+        text: &[(&str, String)],
+    ) -> Result<PngStream, RustlebrotError> {
+        let partial = format!("{}.part", path);
+        let file = fs::File::create(&partial).map_err(|e| RustlebrotError::write(&partial, e))?;
+        let color = png::ColorType::Rgb;
+        let writer = png_writer(BufWriter::new(file), width, height, depth, color, text)
+            .and_then(png::Writer::into_stream_writer)
+            .map_err(|e| RustlebrotError::encode(path, e))?;
+        Ok(PngStream {
+            writer,
+            path: path.to_string(),
+            partial,
+        })
+    }
+
+    /// Writes the next band of rows, whose pixels are in `tiles` from left
+    /// to right. The tiles are as high as the band and as wide as the
+    /// image together.
+    pub fn write_band(&mut self, tiles: &[DynamicImage]) -> Result<(), RustlebrotError> {
+        let rows = tiles.first().map_or(0, |tile| tile.height() as usize);
+        for y in 0..rows {
+            for tile in tiles {
+                let row_len = tile.width() as usize * 3;
+                let written = match tile {
+                    DynamicImage::ImageRgb8(img) => {
+                        self.writer.write_all(&img.as_raw()[y * row_len..][..row_len])
+                    }
+                    DynamicImage::ImageRgb16(img) => {
+                        let row = &img.as_raw()[y * row_len..][..row_len];
+                        self.writer.write_all(&big_endian(row))
+                    }
+                    _ => unreachable!("frames are rendered as RGB"),
+                };
+                written.map_err(|e| RustlebrotError::write(&self.partial, e))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Ends the PNG once every row is written, and moves it to its path.
+    pub fn finish(self) -> Result<(), RustlebrotError> {
+        self.writer.finish().map_err(|e| RustlebrotError::encode(&self.path, e))?;
+        fs::rename(&self.partial, &self.path).map_err(|e| RustlebrotError::write(&self.path, e))
+    }
+}
+
+/// What `read_png` finds in a PNG.
+pub struct PngFrame {
+    pub width: u32,
+    pub height: u32,
+    pub bit_depth: png::BitDepth,
+    /// The text chunks as keyword and text pairs.
+    pub text: Vec<(String, String)>,
+}
+
+/// Reads the PNG at `path`. All of the image data is decoded, so this fails
+/// for a file that was cut short.
+pub fn read_png(path: &str) -> Result<PngFrame, RustlebrotError> {
+    let failed = |e: png::DecodingError| RustlebrotError::format(path, e);
+    let file = fs::File::open(path).map_err(|e| RustlebrotError::read(path, e))?;
+    let mut reader = png::Decoder::new(file).read_info().map_err(failed)?;
+    // Chunks after the image data are only seen once it has been read.
+    let mut data = vec![0; reader.output_buffer_size()];
+    reader.next_frame(&mut data).map_err(failed)?;
+    reader.finish().map_err(failed)?;
+
+    let info = reader.info();
+    let mut text = Vec::new();
+    for chunk in &info.uncompressed_latin1_text {
+        text.push((chunk.keyword.clone(), chunk.text.clone()));
+    }
+    for chunk in &info.compressed_latin1_text {
+        text.push((chunk.keyword.clone(), chunk.get_text().map_err(failed)?));
+    }
+    for chunk in &info.utf8_text {
+        text.push((chunk.keyword.clone(), chunk.get_text().map_err(failed)?));
+    }
+    Ok(PngFrame {
+        width: info.width,
+        height: info.height,
+        bit_depth: info.bit_depth,
+        text,
+    })
+}
+
+/// Fails unless files can be written in `dir`, which a directory that
+/// exists already but isn't writable would otherwise only show once the
+/// first frame is done.
+pub fn check_writable(dir: &str) -> Result<(), RustlebrotError> {
+    let path = format!("{}/.rustlebrot-preflight", dir);
+    fs::write(&path, b"")
+        .and_then(|_| fs::remove_file(&path))
+        .map_err(|e| RustlebrotError::write(dir, e))
+}
+
+/// The bytes free for files in `dir`, where that can be found out.
+#[cfg(unix)]
+pub fn free_bytes(dir: &str) -> Option<u64> {
+    let path = std::ffi::CString::new(dir).ok()?;
+    // SAFETY: statvfs is plain integers, which statvfs only writes, and
+    // the path is a string ended by a nul.
+    let mut stats: libc::statvfs = unsafe { std::mem::zeroed() };
+    match unsafe { libc::statvfs(path.as_ptr(), &mut stats) } {
+        0 => Some(stats.f_bavail as u64 * stats.f_frsize as u64),
+        _ => None,
+    }
+}
+
+/// The bytes free for files in `dir`, where that can be found out.
+#[cfg(not(unix))]
+pub fn free_bytes(_dir: &str) -> Option<u64> {
+    None
+}
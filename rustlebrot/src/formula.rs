@@ -0,0 +1,790 @@
+use crate::complex::Complex;
+use crate::fractal::EscapeTimeFractal;
+
+/// The radius past which the orbits of a formula count as escaped, unless
+/// it's a power of `z` plus `c` or `--bailout` asks for more. Past it, the
+/// polynomials of `z` and `c` people try grow faster than `c` pulls them
+/// back, and going out further only costs an iteration or two.
+pub const FORMULA_BAILOUT: f64 = 16.0;
+
+/// The most values the stack of a formula's program holds at once.
+const MAX_STACK: usize = 16;
+
+/// An iteration formula over complex `z` and `c`, as given with
+/// `--formula`, which is parsed at runtime rather than compiled in like an
+/// `EscapeTimeFractal` of one's own.
+///
+/// Formulas are written with `+ - * /`, `^` for powers, the imaginary unit
+/// `i`, the constants `pi` and `e`, and the functions `conj`, `abs` (of
+/// the real and imaginary parts apart, as in the Burning Ship), `sqrt`,
+/// `exp`, `ln`, `sin`, `cos`, `sinh` and `cosh`. Orbits start at `z = 0`.
+///
+/// A formula is simplified once, folding its constants and gathering
+/// products of `z` into powers, and then specialized:
+///
+/// * `z^n + c` iterates `z^n` by repeated squaring, as fast as the same
+///   formula written as an `EscapeTimeFractal`. It's still scalar, so at
+///   `n = 2` it's slower than the built-in Mandelbrot set, which is
+///   vectorized and skips the cardioid.
+/// * Anything else runs as a program for a small stack machine over
+///   complex numbers, whose instructions fuse the common steps: squaring,
+///   multiplying by `z`, adding `c` or a constant. `z*z*z + c*z + c` takes
+///   about 3 times as long per iteration as the same formula written as an
+///   `EscapeTimeFractal`, going by the `escape_time_formula_program` and
+///   `escape_time_cubic` benchmarks.
+///
+/// `bench --formula` measures the cost of a formula next to the built-in
+/// kernel on the machine it runs on.
+///
+/// Smooth coloring interpolates escape times with the logarithm in the
+/// base of the formula's degree in `z`, since `|z|` is raised to about
+/// that power each step near the bailout. Formulas without a degree, like
+/// those of `exp` or `sin`, take base 2 unless `--formula-log-base` says
+/// otherwise.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Formula {
+    source: String,
+    kernel: Kernel,
+    /// The degree of the formula in `z`, if it's a polynomial of it.
+    degree: Option<f64>,
+    bailout: f64,
+    symmetric: bool,
+    /// The base of the logarithm smooth escape times are interpolated with.
+    pub log_base: f64,
+}
+
+/// How a formula is iterated, see `Formula`.
+#[derive(Clone, Debug, PartialEq)]
+enum Kernel {
+    /// `z^n + c`.
+    Power(i32),
+    Program(Vec<Op>),
+}
+
+/// An instruction of a formula's program. Each pushes a value onto the
+/// stack, or replaces the values on top with what it gives.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Op {
+    Z,
+    C,
+    Const(Complex<f64>),
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+    Neg,
+    Apply(Function),
+    /// The value on top to a whole power, or one of a constant exponent.
+    PowInt(i32),
+    PowConst(Complex<f64>),
+    // Fused instructions, which save pushing `z`, `c` or a constant only to
+    // take it off again.
+    Square,
+    MulZ,
+    AddC,
+    SubC,
+    AddConst(Complex<f64>),
+    MulConst(Complex<f64>),
+}
+
+/// A function formulas can call.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Function {
+    Conj,
+    Abs,
+    Sqrt,
+    Exp,
+    Ln,
+    Sin,
+    Cos,
+    Sinh,
+    Cosh,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Function> {
+        Some(match name {
+            "conj" => Function::Conj,
+            "abs" => Function::Abs,
+            "sqrt" => Function::Sqrt,
+            "exp" => Function::Exp,
+            "ln" | "log" => Function::Ln,
+            "sin" => Function::Sin,
+            "cos" => Function::Cos,
+            "sinh" => Function::Sinh,
+            "cosh" => Function::Cosh,
+            _ => return None,
+        })
+    }
+
+    #[inline]
+    fn apply(self, z: Complex<f64>) -> Complex<f64> {
+        let Complex { re: x, im: y } = z;
+        match self {
+            Function::Conj => z.conj(),
+            Function::Abs => Complex::new(x.abs(), y.abs()),
+            Function::Sqrt => {
+                let r = x.hypot(y);
+                Complex::new(((r + x) / 2.0).sqrt(), ((r - x) / 2.0).sqrt().copysign(y))
+            }
+            Function::Exp => Complex::new(y.cos(), y.sin()).scale(x.exp()),
+            Function::Ln => ln(z),
+            Function::Sin => Complex::new(x.sin() * y.cosh(), x.cos() * y.sinh()),
+            Function::Cos => Complex::new(x.cos() * y.cosh(), -x.sin() * y.sinh()),
+            Function::Sinh => Complex::new(x.sinh() * y.cos(), x.cosh() * y.sin()),
+            Function::Cosh => Complex::new(x.cosh() * y.cos(), x.sinh() * y.sin()),
+        }
+    }
+}
+
+/// The principal natural logarithm.
+#[inline]
+fn ln(z: Complex<f64>) -> Complex<f64> {
+    Complex::new(0.5 * z.norm_sqr().ln(), z.im.atan2(z.re))
+}
+
+/// `z` to the whole power `n`, by repeated squaring.
+#[inline]
+fn pow_int(z: Complex<f64>, n: i32) -> Complex<f64> {
+    let mut result = Complex::ONE;
+    let mut base = z;
+    let mut exponent = n.unsigned_abs();
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = result * base;
+        }
+        base = base * base;
+        exponent >>= 1;
+    }
+    match n < 0 {
+        true => Complex::ONE / result,
+        false => result,
+    }
+}
+
+/// `z` to the power `w`, on the principal branch, with `0^w = 0`.
+#[inline]
+fn pow(z: Complex<f64>, w: Complex<f64>) -> Complex<f64> {
+    if z == Complex::ZERO {
+        return Complex::ZERO;
+    }
+    Function::Exp.apply(w * ln(z))
+}
+
+/// A formula as parsed, before it's simplified and compiled.
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Z,
+    C,
+    Const(Complex<f64>),
+    Neg(Box<Node>),
+    Add(Box<Node>, Box<Node>),
+    Sub(Box<Node>, Box<Node>),
+    Mul(Box<Node>, Box<Node>),
+    Div(Box<Node>, Box<Node>),
+    Pow(Box<Node>, Box<Node>),
+    Call(Function, Box<Node>),
+}
+
+impl Node {
+    /// The value of the node for `z` and `c`, worked out directly.
+    fn eval(&self, z: Complex<f64>, c: Complex<f64>) -> Complex<f64> {
+        match self {
+            Node::Z => z,
+            Node::C => c,
+            Node::Const(value) => *value,
+            Node::Neg(a) => -a.eval(z, c),
+            Node::Add(a, b) => a.eval(z, c) + b.eval(z, c),
+            Node::Sub(a, b) => a.eval(z, c) - b.eval(z, c),
+            Node::Mul(a, b) => a.eval(z, c) * b.eval(z, c),
+            Node::Div(a, b) => a.eval(z, c) / b.eval(z, c),
+            Node::Pow(a, b) => match whole(b.value()) {
+                Some(n) => pow_int(a.eval(z, c), n),
+                None => pow(a.eval(z, c), b.eval(z, c)),
+            },
+            Node::Call(function, a) => function.apply(a.eval(z, c)),
+        }
+    }
+
+    /// The nodes right below this one.
+    fn children(&self) -> Vec<&Node> {
+        match self {
+            Node::Z | Node::C | Node::Const(_) => Vec::new(),
+            Node::Neg(a) | Node::Call(_, a) => vec![a],
+            Node::Add(a, b) | Node::Sub(a, b) | Node::Mul(a, b) | Node::Div(a, b) | Node::Pow(a, b) => {
+                vec![a, b]
+            }
+        }
+    }
+
+    /// Whether `leaf` is this node or one below it.
+    fn uses(&self, leaf: &Node) -> bool {
+        self == leaf || self.children().iter().any(|child| child.uses(leaf))
+    }
+
+    /// The value of a constant node.
+    fn value(&self) -> Option<Complex<f64>> {
+        match self {
+            Node::Const(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The whole power of `z` the node is, if it's one.
+    fn power_of_z(&self) -> Option<i32> {
+        match self {
+            Node::Z => Some(1),
+            Node::Pow(base, exponent) if **base == Node::Z => whole(exponent.value()),
+            _ => None,
+        }
+    }
+
+    /// The node with its constant parts worked out, and products of powers
+    /// of `z` gathered into one power.
+    fn simplify(self) -> Node {
+        let simplified = |node: Box<Node>| Box::new(node.simplify());
+        let node = match self {
+            Node::Neg(a) => Node::Neg(simplified(a)),
+            Node::Add(a, b) => Node::Add(simplified(a), simplified(b)),
+            Node::Sub(a, b) => Node::Sub(simplified(a), simplified(b)),
+            Node::Mul(a, b) => Node::Mul(simplified(a), simplified(b)),
+            Node::Div(a, b) => Node::Div(simplified(a), simplified(b)),
+            Node::Pow(a, b) => Node::Pow(simplified(a), simplified(b)),
+            Node::Call(function, a) => Node::Call(function, simplified(a)),
+            leaf => leaf,
+        };
+        if !node.uses(&Node::Z) && !node.uses(&Node::C) {
+            return Node::Const(node.eval(Complex::ZERO, Complex::ZERO));
+        }
+        match node {
+            Node::Mul(a, b) => match (a.power_of_z(), b.power_of_z()) {
+                (Some(m), Some(n)) => Node::Pow(Box::new(Node::Z), Box::new(whole_const(m + n))),
+                _ => Node::Mul(a, b),
+            },
+            node => node,
+        }
+    }
+
+    /// The degree of the node as a polynomial of `z`, if it is one.
+    fn degree(&self) -> Option<f64> {
+        match self {
+            Node::Z => Some(1.0),
+            Node::C | Node::Const(_) => Some(0.0),
+            Node::Neg(a) => a.degree(),
+            Node::Call(Function::Conj | Function::Abs, a) => a.degree(),
+            Node::Add(a, b) | Node::Sub(a, b) => Some(a.degree()?.max(b.degree()?)),
+            Node::Mul(a, b) => Some(a.degree()? + b.degree()?),
+            Node::Div(a, b) => match b.degree()? {
+                0.0 => a.degree(),
+                _ => None,
+            },
+            Node::Pow(a, b) => match b.value() {
+                Some(Complex { re: power, im: 0.0 }) => Some(a.degree()? * power),
+                _ => None,
+            },
+            // Functions of `c` alone are constants of the iteration.
+            Node::Call(_, a) if !a.uses(&Node::Z) => Some(0.0),
+            Node::Call(..) => None,
+        }
+    }
+
+    /// Whether the node gives the conjugate for the conjugates of `z` and
+    /// `c`, which mirrors its images across the real axis.
+    fn commutes_with_conj(&self) -> bool {
+        match self {
+            Node::Const(value) => value.im == 0.0,
+            Node::Call(Function::Abs, _) => false,
+            node => node.children().iter().all(|child| child.commutes_with_conj()),
+        }
+    }
+}
+
+/// The whole number `value` is, if it's a real one that fits.
+fn whole(value: Option<Complex<f64>>) -> Option<i32> {
+    match value? {
+        Complex { re: n, im: 0.0 } if n.fract() == 0.0 && n.abs() <= i32::MAX as f64 => {
+            Some(n as i32)
+        }
+        _ => None,
+    }
+}
+
+fn whole_const(n: i32) -> Node {
+    Node::Const(Complex::new(n as f64, 0.0))
+}
+
+/// A token of a formula, with the character it starts at, from 1.
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Symbol(char),
+    End,
+}
+
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut next = 0;
+    while next < chars.len() {
+        let (start, first) = (next, chars[next]);
+        let token = if first.is_whitespace() {
+            next += 1;
+            continue;
+        } else if first.is_ascii_digit() || first == '.' {
+            while next < chars.len() && (chars[next].is_ascii_digit() || chars[next] == '.') {
+                next += 1;
+            }
+            // An exponent, with its sign.
+            if next < chars.len() && matches!(chars[next], 'e' | 'E') {
+                let digits = match chars.get(next + 1) {
+                    Some('+' | '-') => next + 2,
+                    _ => next + 1,
+                };
+                if chars.get(digits).is_some_and(|c| c.is_ascii_digit()) {
+                    next = digits;
+                    while next < chars.len() && chars[next].is_ascii_digit() {
+                        next += 1;
+                    }
+                }
+            }
+            let text: String = chars[start..next].iter().collect();
+            let number =
+                text.parse().map_err(|_| at(start, format!("'{}' isn't a number", text)))?;
+            Token::Number(number)
+        } else if first.is_alphabetic() || first == '_' {
+            while next < chars.len() && (chars[next].is_alphanumeric() || chars[next] == '_') {
+                next += 1;
+            }
+            Token::Name(chars[start..next].iter().collect())
+        } else if "+-*/^()".contains(first) {
+            next += 1;
+            Token::Symbol(first)
+        } else {
+            return Err(at(start, format!("unexpected '{}'", first)));
+        };
+        tokens.push((token, start + 1));
+    }
+    tokens.push((Token::End, chars.len() + 1));
+    Ok(tokens)
+}
+
+/// `message` about the character at `index`, from 0.
+fn at(index: usize, message: String) -> String {
+    format!("character {}: {}", index + 1, message)
+}
+
+/// How a token is called in errors.
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Number(number) => format!("the number {}", number),
+        Token::Name(name) => format!("'{}'", name),
+        Token::Symbol(symbol) => format!("'{}'", symbol),
+        Token::End => "the end of the formula".to_string(),
+    }
+}
+
+/// Parses tokens into a tree by recursive descent.
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    next: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.next].0
+    }
+
+    /// The character the next token starts at, from 1.
+    fn position(&self) -> usize {
+        self.tokens[self.next].1
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.peek().clone();
+        if token != Token::End {
+            self.next += 1;
+        }
+        token
+    }
+
+    fn error(&self, message: String) -> String {
+        at(self.position() - 1, message)
+    }
+
+    /// A sum or difference of terms.
+    fn expression(&mut self) -> Result<Node, String> {
+        let mut node = self.term()?;
+        loop {
+            node = match self.peek() {
+                Token::Symbol('+') => {
+                    self.advance();
+                    Node::Add(Box::new(node), Box::new(self.term()?))
+                }
+                Token::Symbol('-') => {
+                    self.advance();
+                    Node::Sub(Box::new(node), Box::new(self.term()?))
+                }
+                _ => return Ok(node),
+            }
+        }
+    }
+
+    /// A product or quotient of factors.
+    fn term(&mut self) -> Result<Node, String> {
+        let mut node = self.unary()?;
+        loop {
+            node = match self.peek() {
+                Token::Symbol('*') => {
+                    self.advance();
+                    Node::Mul(Box::new(node), Box::new(self.unary()?))
+                }
+                Token::Symbol('/') => {
+                    self.advance();
+                    Node::Div(Box::new(node), Box::new(self.unary()?))
+                }
+                _ => return Ok(node),
+            }
+        }
+    }
+
+    /// A negated power, so `-z^2` is `-(z^2)`.
+    fn unary(&mut self) -> Result<Node, String> {
+        match self.peek() {
+            Token::Symbol('-') => {
+                self.advance();
+                Ok(Node::Neg(Box::new(self.unary()?)))
+            }
+            Token::Symbol('+') => {
+                self.advance();
+                self.unary()
+            }
+            _ => self.power(),
+        }
+    }
+
+    /// An atom, raised to a power. Powers go right to left, and their
+    /// exponents can be negated.
+    fn power(&mut self) -> Result<Node, String> {
+        let base = self.atom()?;
+        match self.peek() {
+            Token::Symbol('^') => {
+                self.advance();
+                Ok(Node::Pow(Box::new(base), Box::new(self.unary()?)))
+            }
+            _ => Ok(base),
+        }
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        let position = self.position() - 1;
+        match self.advance() {
+            Token::Number(number) => Ok(Node::Const(Complex::new(number, 0.0))),
+            Token::Symbol('(') => {
+                let node = self.expression()?;
+                self.expect(')')?;
+                Ok(node)
+            }
+            Token::Name(name) => match name.as_str() {
+                "z" => Ok(Node::Z),
+                "c" => Ok(Node::C),
+                "i" => Ok(Node::Const(Complex::new(0.0, 1.0))),
+                "pi" => Ok(Node::Const(Complex::new(std::f64::consts::PI, 0.0))),
+                "e" => Ok(Node::Const(Complex::new(std::f64::consts::E, 0.0))),
+                name => {
+                    let function = Function::from_name(name).ok_or_else(|| {
+                        at(position, format!("unknown name '{}'; formulas are of z and c", name))
+                    })?;
+                    self.expect('(')?;
+                    let argument = self.expression()?;
+                    self.expect(')')?;
+                    Ok(Node::Call(function, Box::new(argument)))
+                }
+            },
+            token => Err(at(
+                position,
+                format!("expected a number, z, c, a function or '(', found {}", describe(&token)),
+            )),
+        }
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<(), String> {
+        match self.peek() {
+            Token::Symbol(found) if *found == symbol => {
+                self.advance();
+                Ok(())
+            }
+            found => Err(self.error(format!("expected '{}', found {}", symbol, describe(found)))),
+        }
+    }
+}
+
+/// Compiles a simplified tree into a program, fusing the instructions it
+/// can.
+fn compile(node: &Node, program: &mut Vec<Op>) {
+    let binary = |a: &Node, b: &Node, op: Op, program: &mut Vec<Op>| {
+        compile(a, program);
+        compile(b, program);
+        program.push(op);
+    };
+    match node {
+        Node::Z => program.push(Op::Z),
+        Node::C => program.push(Op::C),
+        Node::Const(value) => program.push(Op::Const(*value)),
+        Node::Neg(a) => {
+            compile(a, program);
+            program.push(Op::Neg);
+        }
+        Node::Add(a, b) => match (&**a, &**b) {
+            (a, Node::C) | (Node::C, a) => {
+                compile(a, program);
+                program.push(Op::AddC);
+            }
+            (a, Node::Const(value)) | (Node::Const(value), a) => {
+                compile(a, program);
+                program.push(Op::AddConst(*value));
+            }
+            (a, b) => binary(a, b, Op::Add, program),
+        },
+        Node::Sub(a, b) => match (&**a, &**b) {
+            (a, Node::C) => {
+                compile(a, program);
+                program.push(Op::SubC);
+            }
+            (a, Node::Const(value)) => {
+                compile(a, program);
+                program.push(Op::AddConst(-*value));
+            }
+            (a, b) => binary(a, b, Op::Sub, program),
+        },
+        Node::Mul(a, b) => match (&**a, &**b) {
+            (a, Node::Z) | (Node::Z, a) => {
+                compile(a, program);
+                program.push(Op::MulZ);
+            }
+            (a, Node::Const(value)) | (Node::Const(value), a) => {
+                compile(a, program);
+                program.push(Op::MulConst(*value));
+            }
+            (a, b) => binary(a, b, Op::Mul, program),
+        },
+        Node::Div(a, b) => match b.value() {
+            Some(value) => {
+                compile(a, program);
+                program.push(Op::MulConst(Complex::ONE / value));
+            }
+            None => binary(a, b, Op::Div, program),
+        },
+        Node::Pow(a, b) => {
+            compile(a, program);
+            match (whole(b.value()), b.value()) {
+                (Some(2), _) => program.push(Op::Square),
+                (Some(n), _) => program.push(Op::PowInt(n)),
+                (None, Some(value)) => program.push(Op::PowConst(value)),
+                (None, None) => {
+                    compile(b, program);
+                    program.push(Op::Pow);
+                }
+            }
+        }
+        Node::Call(function, a) => {
+            compile(a, program);
+            program.push(Op::Apply(*function));
+        }
+    }
+}
+
+/// The most values `program` has on the stack at once.
+fn stack_depth(program: &[Op]) -> usize {
+    let (mut depth, mut most) = (0usize, 0);
+    for op in program {
+        match op {
+            Op::Z | Op::C | Op::Const(_) => depth += 1,
+            Op::Add | Op::Sub | Op::Mul | Op::Div | Op::Pow => depth -= 1,
+            _ => {}
+        }
+        most = most.max(depth);
+    }
+    most
+}
+
+impl Formula {
+    /// Parses and compiles `source`, failing with the character the
+    /// mistake is at.
+    pub fn parse(source: &str) -> Result<Formula, String> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            next: 0,
+        };
+        if *parser.peek() == Token::End {
+            return Err("the formula is empty".to_string());
+        }
+        let node = parser.expression()?;
+        if *parser.peek() != Token::End {
+            return Err(parser.error(format!("unexpected {}", describe(parser.peek()))));
+        }
+        if !node.uses(&Node::Z) {
+            return Err("the formula should use z, or every orbit stops after a step".to_string());
+        }
+        let node = node.simplify();
+        let degree = node.degree();
+        let symmetric = node.commutes_with_conj();
+        let power = match &node {
+            Node::Add(a, b) => match (a.power_of_z(), &**a, &**b, b.power_of_z()) {
+                (Some(n), _, Node::C, _) | (_, Node::C, _, Some(n)) if n >= 2 => Some(n),
+                _ => None,
+            },
+            _ => None,
+        };
+        let kernel = match power {
+            Some(n) => Kernel::Power(n),
+            None => {
+                let mut program = Vec::new();
+                compile(&node, &mut program);
+                if stack_depth(&program) > MAX_STACK {
+                    return Err("the formula is nested too deeply".to_string());
+                }
+                Kernel::Program(program)
+            }
+        };
+        Ok(Formula {
+            source: source.to_string(),
+            bailout: match kernel {
+                Kernel::Power(_) => 2.0,
+                Kernel::Program(_) => FORMULA_BAILOUT,
+            },
+            kernel,
+            degree,
+            symmetric,
+            log_base: degree.filter(|&degree| degree > 1.0).unwrap_or(2.0),
+        })
+    }
+
+    /// The formula as it was given.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The degree of the formula in `z`, if it's a polynomial of it.
+    pub fn degree(&self) -> Option<f64> {
+        self.degree
+    }
+
+    /// How the formula is iterated, as `bench` reports it.
+    pub fn kernel(&self) -> String {
+        match &self.kernel {
+            Kernel::Power(n) => format!("z^{} + c, by repeated squaring", n),
+            Kernel::Program(program) => {
+                format!("a program of {} instructions", program.len())
+            }
+        }
+    }
+
+    /// Runs the program of the formula on `z` and `c`.
+    ///
+    /// The value on top of the stack is kept apart from the ones under it,
+    /// so the fused instructions, which most of a program is, never touch
+    /// memory.
+    #[inline]
+    fn run(program: &[Op], z: Complex<f64>, c: Complex<f64>) -> Complex<f64> {
+        let mut under = [Complex::ZERO; MAX_STACK];
+        let mut depth = 0;
+        let mut top = Complex::ZERO;
+        for op in program {
+            let value = match *op {
+                Op::Z => z,
+                Op::C => c,
+                Op::Const(value) => value,
+                Op::Add | Op::Sub | Op::Mul | Op::Div | Op::Pow => {
+                    // The value under the top is the left operand.
+                    depth -= 1;
+                    let left = under[depth];
+                    top = match op {
+                        Op::Add => left + top,
+                        Op::Sub => left - top,
+                        Op::Mul => left * top,
+                        Op::Div => left / top,
+                        _ => pow(left, top),
+                    };
+                    continue;
+                }
+                Op::Neg => {
+                    top = -top;
+                    continue;
+                }
+                Op::Apply(function) => {
+                    top = function.apply(top);
+                    continue;
+                }
+                Op::PowInt(n) => {
+                    top = pow_int(top, n);
+                    continue;
+                }
+                Op::PowConst(exponent) => {
+                    top = pow(top, exponent);
+                    continue;
+                }
+                Op::Square => {
+                    top = top * top;
+                    continue;
+                }
+                Op::MulZ => {
+                    top = top * z;
+                    continue;
+                }
+                Op::AddC => {
+                    top = top + c;
+                    continue;
+                }
+                Op::SubC => {
+                    top = top - c;
+                    continue;
+                }
+                Op::AddConst(constant) => {
+                    top = top + constant;
+                    continue;
+                }
+                Op::MulConst(constant) => {
+                    top = top * constant;
+                    continue;
+                }
+            };
+            // Pushing a value moves the top under it. The first push moves
+            // nothing that's used.
+            under[depth] = top;
+            depth += 1;
+            top = value;
+        }
+        top
+    }
+}
+
+impl EscapeTimeFractal for Formula {
+    fn init(&self, _c: (f64, f64)) -> (f64, f64) {
+        (0.0, 0.0)
+    }
+
+    #[inline(always)]
+    fn step(&self, z: (f64, f64), c: (f64, f64)) -> (f64, f64) {
+        let (z, c) = (Complex::from(z), Complex::from(c));
+        let next = match &self.kernel {
+            Kernel::Power(2) => z * z + c,
+            Kernel::Power(n) => pow_int(z, *n) + c,
+            Kernel::Program(program) => Formula::run(program, z, c),
+        };
+        next.into()
+    }
+
+    fn bailout(&self) -> f64 {
+        self.bailout
+    }
+
+    fn symmetric(&self) -> bool {
+        self.symmetric
+    }
+
+    fn log_base(&self) -> f64 {
+        self.log_base
+    }
+}
@@ -0,0 +1,1118 @@
+use crate::bigfloat::{self, Big};
+use crate::complex::{Complex, Scalar};
+use crate::dd::{self, DoubleDouble};
+use crate::perturbation::{self, ReferenceOrbit};
+use crate::series::Series;
+#[cfg(feature = "simd")]
+use crate::simd;
+use crate::stripes::StripeAverage;
+#[cfg(feature = "simd")]
+use wide::{f32x8, f64x4};
+use crate::trap::Trap;
+
+/// An escape-time fractal that can be plugged into the renderer.
+///
+/// Implementors are zero-sized marker types so `render_mandelbrot` can be
+/// monomorphized per fractal, keeping the per-pixel loop free of any
+/// dispatch on the fractal kind. A formula of one's own is easier plugged
+/// in as an `EscapeTimeFractal`, which implements this trait.
+pub trait Fractal: Sync {
+    /// Computes the escape time for the point `c`, capped at `max_iter`.
+    ///
+    /// Points escape once `|z|` exceeds `bailout`. `periodicity` is the
+    /// distance under which two points of the orbit are considered the same
+    /// cycle, or `None` to iterate all the way. With a `trap`, the closest
+    /// the orbit comes to it is tracked as well.
+    fn escape_time(
+        &self,
+        c: (f64, f64),
+        max_iter: u32,
+        bailout: f64,
+        periodicity: Option<f64>,
+        trap: Option<&Trap>,
+    ) -> Escape;
+
+    /// Computes `escape_time` without a trap for every point of `points`
+    /// into `escapes`. Fractals with a vectorized loop iterate several
+    /// points at once here.
+    fn escape_times(
+        &self,
+        points: &[(f64, f64)],
+        max_iter: u32,
+        bailout: f64,
+        periodicity: Option<f64>,
+        escapes: &mut [Escape],
+    ) {
+        for (escape, &c) in escapes.iter_mut().zip(points) {
+            *escape = self.escape_time(c, max_iter, bailout, periodicity, None);
+        }
+    }
+
+    /// Same as `escape_times`, iterating in f32, which is only precise enough
+    /// for shallow frames but fits twice as many points in a vector. Fractals
+    /// without a vectorized loop iterate in f64 here.
+    fn escape_times_f32(
+        &self,
+        points: &[(f64, f64)],
+        max_iter: u32,
+        bailout: f64,
+        periodicity: Option<f64>,
+        escapes: &mut [Escape],
+    ) {
+        self.escape_times(points, max_iter, bailout, periodicity, escapes)
+    }
+
+    /// Whether `c` and its conjugate always escape alike, which makes every
+    /// image of the fractal mirrored across the real axis.
+    fn symmetric(&self) -> bool {
+        false
+    }
+
+    /// Calls `visit` with every point of the orbit of `c` that stays within
+    /// the escape radius, given the `iterations` it took to escape.
+    fn orbit<V: FnMut((f64, f64))>(&self, c: (f64, f64), iterations: u32, visit: V);
+
+    /// Computes the exterior distance estimate from `c` to the set, or
+    /// `None` if `c` doesn't escape within `max_iter` iterations. Orbits
+    /// escape past `bailout`, or past `DISTANCE_BAILOUT` if that's farther,
+    /// since the estimate needs a large radius to converge.
+    fn distance(&self, c: (f64, f64), max_iter: u32, bailout: f64) -> Option<f64>;
+
+    /// Same as `distance`, iterating by perturbation like
+    /// `escape_time_perturbed` but always from the start of the orbit.
+    fn distance_perturbed(
+        &self,
+        orbit: &[(f64, f64)],
+        dc: (f64, f64),
+        max_iter: u32,
+        bailout: f64,
+    ) -> Option<f64>;
+
+    /// Computes the stripe average of the orbit of `c` at `density`, see
+    /// `StripeAverage`, or `None` if `c` doesn't escape within `max_iter`
+    /// iterations. This has a loop of its own, so the escape time loops
+    /// don't carry the sum.
+    fn stripes(&self, c: (f64, f64), max_iter: u32, bailout: f64, density: f64) -> Option<f64>;
+
+    /// Same as `stripes`, iterating by perturbation like
+    /// `escape_time_perturbed` but always from the start of the orbit.
+    fn stripes_perturbed(
+        &self,
+        orbit: &[(f64, f64)],
+        dc: (f64, f64),
+        max_iter: u32,
+        bailout: f64,
+        density: f64,
+    ) -> Option<f64>;
+
+    /// The attracting cycle the orbit of `c` settles on, if it stays
+    /// bounded and comes back around within `max_iter` iterations, for
+    /// coloring the interior by it. Fractals that can't tell return `None`,
+    /// and their interior is drawn plain.
+    fn attractor(&self, _c: (f64, f64), _max_iter: u32) -> Option<Attractor> {
+        None
+    }
+
+    /// Same as `escape_time`, but iterating at the precision of `c`, without
+    /// periodicity checking or traps.
+    fn escape_time_big(&self, c: &(Big, Big), max_iter: u32, bailout: f64) -> f64;
+
+    /// Same as `escape_time`, but iterating in double-double, without
+    /// traps.
+    fn escape_time_dd(
+        &self,
+        c: (DoubleDouble, DoubleDouble),
+        max_iter: u32,
+        bailout: f64,
+        periodicity: Option<f64>,
+    ) -> Escape;
+
+    /// Computes the high-precision orbit of `center` for perturbation.
+    fn reference_orbit(
+        &self,
+        center: &(Big, Big),
+        bits: usize,
+        max_iter: u32,
+        bailout: f64,
+    ) -> ReferenceOrbit;
+
+    /// Computes the escape time of the point `dc` away from the center of
+    /// `orbit`, by perturbation, starting where `series` leaves off if
+    /// given. A `trap` only sees the iterations after that.
+    fn escape_time_perturbed(
+        &self,
+        orbit: &[(f64, f64)],
+        dc: (f64, f64),
+        series: Option<&Series>,
+        max_iter: u32,
+        bailout: f64,
+        trap: Option<&Trap>,
+    ) -> Escape;
+
+    /// Fits a series approximation to skip the first iterations of every
+    /// pixel in a frame of the given `radius`, if this fractal supports it.
+    fn series(
+        &self,
+        orbit: &[(f64, f64)],
+        radius: f64,
+        probes: &[(f64, f64)],
+        terms: usize,
+        max_iter: u32,
+        bailout: f64,
+    ) -> Option<Series>;
+}
+
+/// The outcome of iterating a single point.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Escape {
+    /// Iterations until the orbit escaped, or max_iter if it didn't.
+    pub iterations: f64,
+    /// The smallest distance from the orbit to the trap, or infinity when
+    /// iterating without one.
+    pub trap: f64,
+    /// The escape time interpolated from how far past the bailout the orbit
+    /// landed, which removes the banding of whole iterations. It lies between
+    /// `iterations` and the next iteration, up to the small pull of `c`.
+    pub smooth: f64,
+    /// The first `z` past the bailout, or the origin for a point that
+    /// didn't escape.
+    pub z: (f64, f64),
+}
+
+impl Escape {
+    /// The result for a point that didn't escape within `max_iter`.
+    #[inline]
+    pub fn interior(max_iter: u32, trap: f64) -> Self {
+        Escape {
+            iterations: max_iter as f64,
+            trap,
+            smooth: max_iter as f64,
+            z: (0.0, 0.0),
+        }
+    }
+
+    /// The result for a point whose orbit reached `z` outside `bailout` on
+    /// iteration `i`.
+    ///
+    /// Each iteration near escape roughly squares `|z|`, so
+    /// `log2(ln|z| / ln(bailout))` measures the fraction of an iteration
+    /// the orbit overshot the bailout by. With a larger bailout the orbit
+    /// takes about `log2(ln(bailout) / ln 2)` more iterations to get there,
+    /// and the smooth value shifts by the same amount everywhere, while the
+    /// interpolation itself becomes more accurate.
+    #[inline]
+    pub fn escaped(i: u32, z: (f64, f64), bailout: f64, trap: f64) -> Self {
+        let overshoot = (Complex::from(z).norm_sqr().ln() / (2.0 * bailout.ln())).log2();
+        Escape {
+            iterations: i as f64,
+            trap,
+            smooth: i as f64 + 1.0 - overshoot,
+            z,
+        }
+    }
+
+    /// Same as `escaped`, for an orbit whose `|z|` is raised to about the
+    /// power `base` each step near the bailout rather than squared.
+    #[inline]
+    pub fn escaped_in_base(i: u32, z: (f64, f64), bailout: f64, trap: f64, base: f64) -> Self {
+        if base == 2.0 {
+            return Escape::escaped(i, z, bailout, trap);
+        }
+        let norm = Complex::from(z).norm_sqr();
+        let overshoot = (norm.ln() / (2.0 * bailout.ln())).ln() / base.ln();
+        Escape {
+            iterations: i as f64,
+            trap,
+            smooth: i as f64 + 1.0 - overshoot,
+            z,
+        }
+    }
+
+    /// The angle of `z` from the positive real axis, in radians from `-π`
+    /// to `π`.
+    #[inline]
+    pub fn angle(&self) -> f64 {
+        self.z.1.atan2(self.z.0)
+    }
+}
+
+/// The cycle the orbit of an interior point is drawn into.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Attractor {
+    /// The number of points of the cycle.
+    pub period: u32,
+    /// How much a trip around the cycle stretches a small step off it,
+    /// `|dz/dz0|`, from 0 at the nucleus of its component to 1 at the edge,
+    /// where the cycle stops attracting.
+    pub derivative: f64,
+}
+
+/// An escape-time formula of one's own, which renders like the built-in
+/// fractals in every coloring, supersampled and zoomed, through the
+/// `Fractal` implementation all of them get.
+///
+/// The orbit of `c` starts at `init(c)` and goes on with `step` until it
+/// leaves the circle of radius `bailout`. Both get inlined into the pixel
+/// loops, which are monomorphized for each formula, so none of it is
+/// dispatched dynamically. Smooth coloring assumes `|z|` is raised to the
+/// power `log_base` with each step near the bailout, which is 2 unless the
+/// formula says otherwise.
+///
+/// Formulas have no derivative and no delta iteration, so distance
+/// estimation finds nothing, and the perturbation and arbitrary precision
+/// methods iterate the pixels in f64 directly. Deep zooms still render,
+/// but only with the detail of f64.
+pub trait EscapeTimeFractal: Sync {
+    /// The first point of the orbit of `c`.
+    fn init(&self, c: (f64, f64)) -> (f64, f64);
+
+    /// The point of the orbit of `c` after `z`.
+    fn step(&self, z: (f64, f64), c: (f64, f64)) -> (f64, f64);
+
+    /// The radius past which every orbit escapes. Renders use the larger of
+    /// this and the bailout they ask for.
+    fn bailout(&self) -> f64 {
+        2.0
+    }
+
+    /// Same as `Fractal::symmetric`.
+    fn symmetric(&self) -> bool {
+        false
+    }
+
+    /// The power `|z|` is raised to about each step near the bailout, the
+    /// degree of the formula in `z`, which smooth escape times are
+    /// interpolated with.
+    fn log_base(&self) -> f64 {
+        2.0
+    }
+}
+
+/// The standard Mandelbrot set, iterating `z^2 + c`.
+pub struct Mandelbrot;
+
+/// The Tricorn (Mandelbar) set, iterating `conj(z)^2 + c`.
+pub struct Tricorn;
+
+impl Fractal for Mandelbrot {
+    #[inline]
+    fn escape_time(
+        &self,
+        c: (f64, f64),
+        max_iter: u32,
+        bailout: f64,
+        periodicity: Option<f64>,
+        trap: Option<&Trap>,
+    ) -> Escape {
+        mandelbrot(c, max_iter, bailout, periodicity, trap)
+    }
+
+    // The orbit of the conjugate of `c` is the conjugate of its orbit, and
+    // conjugating only flips signs, so this holds exactly in floating point.
+    fn symmetric(&self) -> bool {
+        true
+    }
+
+    #[cfg(feature = "simd")]
+    fn escape_times(
+        &self,
+        points: &[(f64, f64)],
+        max_iter: u32,
+        bailout: f64,
+        periodicity: Option<f64>,
+        escapes: &mut [Escape],
+    ) {
+        let inside = in_cardioid_or_bulb;
+        simd_escape_times::<f64x4, false>(points, max_iter, bailout, periodicity, inside, escapes)
+    }
+
+    #[cfg(feature = "simd")]
+    fn escape_times_f32(
+        &self,
+        points: &[(f64, f64)],
+        max_iter: u32,
+        bailout: f64,
+        periodicity: Option<f64>,
+        escapes: &mut [Escape],
+    ) {
+        let inside = in_cardioid_or_bulb;
+        simd_escape_times::<f32x8, false>(points, max_iter, bailout, periodicity, inside, escapes)
+    }
+
+    fn orbit<V: FnMut((f64, f64))>(&self, c: (f64, f64), iterations: u32, visit: V) {
+        orbit::<false, V>(c, iterations, visit)
+    }
+
+    fn distance(&self, c: (f64, f64), max_iter: u32, bailout: f64) -> Option<f64> {
+        distance_estimate::<false>(c, max_iter, bailout)
+    }
+
+    fn distance_perturbed(
+        &self,
+        orbit: &[(f64, f64)],
+        dc: (f64, f64),
+        max_iter: u32,
+        bailout: f64,
+    ) -> Option<f64> {
+        perturbation::distance::<false>(orbit, dc, max_iter, bailout)
+    }
+
+    fn stripes(&self, c: (f64, f64), max_iter: u32, bailout: f64, density: f64) -> Option<f64> {
+        stripe_average::<false>(c, max_iter, bailout, density)
+    }
+
+    fn stripes_perturbed(
+        &self,
+        orbit: &[(f64, f64)],
+        dc: (f64, f64),
+        max_iter: u32,
+        bailout: f64,
+        density: f64,
+    ) -> Option<f64> {
+        perturbation::stripes::<false>(orbit, dc, max_iter, bailout, density)
+    }
+
+    fn attractor(&self, c: (f64, f64), max_iter: u32) -> Option<Attractor> {
+        attractor::<false>(c, max_iter)
+    }
+
+    fn escape_time_big(&self, c: &(Big, Big), max_iter: u32, bailout: f64) -> f64 {
+        bigfloat::escape_time(c, max_iter, bailout, false)
+    }
+
+    fn escape_time_dd(
+        &self,
+        c: (DoubleDouble, DoubleDouble),
+        max_iter: u32,
+        bailout: f64,
+        periodicity: Option<f64>,
+    ) -> Escape {
+        if dd::in_cardioid_or_bulb(c) {
+            return Escape::interior(max_iter, f64::INFINITY);
+        }
+        dd::escape_time(c, max_iter, bailout, periodicity, false)
+    }
+
+    fn reference_orbit(
+        &self,
+        center: &(Big, Big),
+        bits: usize,
+        max_iter: u32,
+        bailout: f64,
+    ) -> ReferenceOrbit {
+        ReferenceOrbit::compute(center, bits, max_iter, bailout, false)
+    }
+
+    #[inline]
+    fn escape_time_perturbed(
+        &self,
+        orbit: &[(f64, f64)],
+        dc: (f64, f64),
+        series: Option<&Series>,
+        max_iter: u32,
+        bailout: f64,
+        trap: Option<&Trap>,
+    ) -> Escape {
+        perturbation::escape_time::<false>(orbit, dc, series, max_iter, bailout, trap)
+    }
+
+    fn series(
+        &self,
+        orbit: &[(f64, f64)],
+        radius: f64,
+        probes: &[(f64, f64)],
+        terms: usize,
+        max_iter: u32,
+        bailout: f64,
+    ) -> Option<Series> {
+        Series::compute(orbit, radius, probes, terms, max_iter, bailout)
+    }
+}
+
+impl Fractal for Tricorn {
+    #[inline]
+    fn escape_time(
+        &self,
+        c: (f64, f64),
+        max_iter: u32,
+        bailout: f64,
+        periodicity: Option<f64>,
+        trap: Option<&Trap>,
+    ) -> Escape {
+        tricorn(c, max_iter, bailout, periodicity, trap)
+    }
+
+    // Same as for `Mandelbrot`, since conjugation commutes with squaring.
+    fn symmetric(&self) -> bool {
+        true
+    }
+
+    #[cfg(feature = "simd")]
+    fn escape_times(
+        &self,
+        points: &[(f64, f64)],
+        max_iter: u32,
+        bailout: f64,
+        periodicity: Option<f64>,
+        escapes: &mut [Escape],
+    ) {
+        let inside = |_| false;
+        simd_escape_times::<f64x4, true>(points, max_iter, bailout, periodicity, inside, escapes)
+    }
+
+    #[cfg(feature = "simd")]
+    fn escape_times_f32(
+        &self,
+        points: &[(f64, f64)],
+        max_iter: u32,
+        bailout: f64,
+        periodicity: Option<f64>,
+        escapes: &mut [Escape],
+    ) {
+        let inside = |_| false;
+        simd_escape_times::<f32x8, true>(points, max_iter, bailout, periodicity, inside, escapes)
+    }
+
+    fn orbit<V: FnMut((f64, f64))>(&self, c: (f64, f64), iterations: u32, visit: V) {
+        orbit::<true, V>(c, iterations, visit)
+    }
+
+    fn distance(&self, c: (f64, f64), max_iter: u32, bailout: f64) -> Option<f64> {
+        distance_estimate::<true>(c, max_iter, bailout)
+    }
+
+    fn distance_perturbed(
+        &self,
+        orbit: &[(f64, f64)],
+        dc: (f64, f64),
+        max_iter: u32,
+        bailout: f64,
+    ) -> Option<f64> {
+        perturbation::distance::<true>(orbit, dc, max_iter, bailout)
+    }
+
+    fn stripes(&self, c: (f64, f64), max_iter: u32, bailout: f64, density: f64) -> Option<f64> {
+        stripe_average::<true>(c, max_iter, bailout, density)
+    }
+
+    fn stripes_perturbed(
+        &self,
+        orbit: &[(f64, f64)],
+        dc: (f64, f64),
+        max_iter: u32,
+        bailout: f64,
+        density: f64,
+    ) -> Option<f64> {
+        perturbation::stripes::<true>(orbit, dc, max_iter, bailout, density)
+    }
+
+    fn attractor(&self, c: (f64, f64), max_iter: u32) -> Option<Attractor> {
+        attractor::<true>(c, max_iter)
+    }
+
+    fn escape_time_big(&self, c: &(Big, Big), max_iter: u32, bailout: f64) -> f64 {
+        bigfloat::escape_time(c, max_iter, bailout, true)
+    }
+
+    fn escape_time_dd(
+        &self,
+        c: (DoubleDouble, DoubleDouble),
+        max_iter: u32,
+        bailout: f64,
+        periodicity: Option<f64>,
+    ) -> Escape {
+        dd::escape_time(c, max_iter, bailout, periodicity, true)
+    }
+
+    fn reference_orbit(
+        &self,
+        center: &(Big, Big),
+        bits: usize,
+        max_iter: u32,
+        bailout: f64,
+    ) -> ReferenceOrbit {
+        ReferenceOrbit::compute(center, bits, max_iter, bailout, true)
+    }
+
+    #[inline]
+    fn escape_time_perturbed(
+        &self,
+        orbit: &[(f64, f64)],
+        dc: (f64, f64),
+        series: Option<&Series>,
+        max_iter: u32,
+        bailout: f64,
+        trap: Option<&Trap>,
+    ) -> Escape {
+        perturbation::escape_time::<true>(orbit, dc, series, max_iter, bailout, trap)
+    }
+
+    /// The conjugate makes the delta non-holomorphic in dc, so a power
+    /// series in dc alone can't describe it.
+    fn series(
+        &self,
+        _orbit: &[(f64, f64)],
+        _radius: f64,
+        _probes: &[(f64, f64)],
+        _terms: usize,
+        _max_iter: u32,
+        _bailout: f64,
+    ) -> Option<Series> {
+        None
+    }
+}
+
+impl<F: EscapeTimeFractal> Fractal for F {
+    #[inline]
+    fn escape_time(
+        &self,
+        c: (f64, f64),
+        max_iter: u32,
+        bailout: f64,
+        periodicity: Option<f64>,
+        trap: Option<&Trap>,
+    ) -> Escape {
+        let bailout = bailout.max(EscapeTimeFractal::bailout(self));
+        match periodicity {
+            Some(eps) => formula_escape_time::<F, true>(self, c, max_iter, bailout, eps, trap),
+            None => formula_escape_time::<F, false>(self, c, max_iter, bailout, 0.0, trap),
+        }
+    }
+
+    fn symmetric(&self) -> bool {
+        EscapeTimeFractal::symmetric(self)
+    }
+
+    fn orbit<V: FnMut((f64, f64))>(&self, c: (f64, f64), iterations: u32, mut visit: V) {
+        let mut z = self.init(c);
+        for _ in 0..iterations {
+            z = self.step(z, c);
+            visit(z);
+        }
+    }
+
+    /// Formulas have no derivative to estimate the distance with.
+    fn distance(&self, _c: (f64, f64), _max_iter: u32, _bailout: f64) -> Option<f64> {
+        None
+    }
+
+    fn distance_perturbed(
+        &self,
+        _orbit: &[(f64, f64)],
+        _dc: (f64, f64),
+        _max_iter: u32,
+        _bailout: f64,
+    ) -> Option<f64> {
+        None
+    }
+
+    fn stripes(&self, c: (f64, f64), max_iter: u32, bailout: f64, density: f64) -> Option<f64> {
+        let bailout = bailout.max(EscapeTimeFractal::bailout(self));
+        let bailout_sqr = bailout * bailout;
+        let mut stripes = StripeAverage::new(density);
+        let mut z = self.init(c);
+        for i in 0..max_iter {
+            z = self.step(z, c);
+            if i > 0 {
+                stripes.add(z);
+            }
+            if Complex::from(z).norm_sqr() > bailout_sqr {
+                return Some(stripes.escaped(i, z, bailout));
+            }
+        }
+        None
+    }
+
+    fn stripes_perturbed(
+        &self,
+        orbit: &[(f64, f64)],
+        dc: (f64, f64),
+        max_iter: u32,
+        bailout: f64,
+        density: f64,
+    ) -> Option<f64> {
+        let c = Complex::from(orbit[0]) + dc.into();
+        self.stripes(c.into(), max_iter, bailout, density)
+    }
+
+    fn escape_time_big(&self, c: &(Big, Big), max_iter: u32, bailout: f64) -> f64 {
+        let c = (c.0.to_f64().value(), c.1.to_f64().value());
+        self.escape_time(c, max_iter, bailout, None, None).smooth
+    }
+
+    fn escape_time_dd(
+        &self,
+        c: (DoubleDouble, DoubleDouble),
+        max_iter: u32,
+        bailout: f64,
+        periodicity: Option<f64>,
+    ) -> Escape {
+        self.escape_time((c.0.to_f64(), c.1.to_f64()), max_iter, bailout, periodicity, None)
+    }
+
+    /// The orbit only holds the center, rounded to f64, for
+    /// `escape_time_perturbed` to iterate the pixels from directly.
+    fn reference_orbit(
+        &self,
+        center: &(Big, Big),
+        bits: usize,
+        max_iter: u32,
+        _bailout: f64,
+    ) -> ReferenceOrbit {
+        ReferenceOrbit {
+            z: vec![(center.0.to_f64().value(), center.1.to_f64().value())],
+            bits,
+            max_iter,
+        }
+    }
+
+    #[inline]
+    fn escape_time_perturbed(
+        &self,
+        orbit: &[(f64, f64)],
+        dc: (f64, f64),
+        _series: Option<&Series>,
+        max_iter: u32,
+        bailout: f64,
+        trap: Option<&Trap>,
+    ) -> Escape {
+        let c = Complex::from(orbit[0]) + dc.into();
+        self.escape_time(c.into(), max_iter, bailout, None, trap)
+    }
+
+    fn series(
+        &self,
+        _orbit: &[(f64, f64)],
+        _radius: f64,
+        _probes: &[(f64, f64)],
+        _terms: usize,
+        _max_iter: u32,
+        _bailout: f64,
+    ) -> Option<Series> {
+        None
+    }
+}
+
+/// Runs `simd::escape_times` on the lanes of `L`, with or without
+/// periodicity checking.
+#[cfg(feature = "simd")]
+#[inline(always)]
+fn simd_escape_times<L: simd::Lanes, const CONJUGATE: bool>(
+    points: &[(f64, f64)],
+    max_iter: u32,
+    bailout: f64,
+    periodicity: Option<f64>,
+    inside: impl Fn((f64, f64)) -> bool,
+    escapes: &mut [Escape],
+) {
+    match periodicity {
+        Some(eps) => simd::escape_times::<L, CONJUGATE, true>(
+            points, max_iter, bailout, eps, inside, escapes,
+        ),
+        None => simd::escape_times::<L, CONJUGATE, false>(
+            points, max_iter, bailout, 0.0, inside, escapes,
+        ),
+    }
+}
+
+/// Selects which fractal gets rendered, as chosen with `--fractal`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FractalKind {
+    Mandelbrot,
+    Tricorn,
+    /// The basins of Newton's method for the roots of a polynomial, see
+    /// `Newton`. It isn't an escape time fractal, so it has no `Fractal`
+    /// of its own.
+    Newton,
+    /// A formula given with `--formula`, see `Formula`. It's never named
+    /// with `--fractal`, so `from_name` doesn't know it.
+    Formula,
+    /// The Julia set of a `c` that moves along `--c-path`, see `Julia`.
+    Julia,
+    /// The Lyapunov exponents of the logistic map with the growth rate
+    /// taking turns between the coordinates, see `Lyapunov`. Like Newton
+    /// fractals it has a kernel of its own.
+    Lyapunov,
+}
+
+impl FractalKind {
+    /// Parses the name given on the command line.
+    pub fn from_name(name: &str) -> Option<FractalKind> {
+        match name {
+            "mandelbrot" => Some(FractalKind::Mandelbrot),
+            "tricorn" | "mandelbar" => Some(FractalKind::Tricorn),
+            "newton" => Some(FractalKind::Newton),
+            "julia" => Some(FractalKind::Julia),
+            "lyapunov" => Some(FractalKind::Lyapunov),
+            _ => None,
+        }
+    }
+
+    /// The name used on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            FractalKind::Mandelbrot => "mandelbrot",
+            FractalKind::Tricorn => "tricorn",
+            FractalKind::Newton => "newton",
+            FractalKind::Formula => "formula",
+            FractalKind::Julia => "julia",
+            FractalKind::Lyapunov => "lyapunov",
+        }
+    }
+
+    /// The point the zoom closes in on when no other center is given.
+    ///
+    /// Kept as decimal strings so the arbitrary-precision path can use
+    /// every digit; the f64 path parses them down to the nearest double.
+    pub const fn default_center(self) -> (&'static str, &'static str) {
+        match self {
+            // let zoom_point = (-0.75, 0.109); // The point to zoom in on
+            // let zoom_point = (-0.10109636384562, 0.95628651080914);
+            // let zoom_point = (-0.77568377, 0.13646737);
+            FractalKind::Mandelbrot => (
+                "-1.74999841099374081749002483162428393452822172335808534616943930976364725846655540417646727085571962736578151132907961927190726789896685696750162524460775546580822744596887978637416593715319388030232414667046419863755743802804780843375",
+                "-0.00000000000000165712469295418692325810961981279189026504290127375760405334498110850956047368308707050735960323397389547038231194872482690340369921750514146922400928554011996123112902000856666847088788158433995358406779259404221904755",
+            ),
+            // The Misiurewicz point on the real-axis antenna. The orbit stays
+            // real there, so it is shared with the Mandelbrot set.
+            FractalKind::Tricorn => ("-1.5436890126920764", "0.0"),
+            // Newton's method throws the origin to infinity for z³ - 1, so
+            // every basin meets there, and keeps meeting all the way in.
+            FractalKind::Newton => ("0.0", "0.0"),
+            // Formulas can be anything, so their zooms close in on the
+            // origin, where the orbits start.
+            FractalKind::Formula => ("0.0", "0.0"),
+            // Julia sets of z^2 + c are symmetric through the origin.
+            FractalKind::Julia => ("0.0", "0.0"),
+            // The middle of the square of growth rates from 2 to 4, where
+            // the logistic map goes from settling down to chaos.
+            FractalKind::Lyapunov => ("3.0", "3.0"),
+        }
+    }
+
+    /// Half the width of the square view the zoom starts from.
+    ///
+    /// The Tricorn default center sits at the far end of the set, so its
+    /// first frame is widened enough to show all three lobes. Lyapunov
+    /// fractals start from the square of growth rates from 2 to 4.
+    pub fn default_half_width(self) -> f64 {
+        match self {
+            FractalKind::Mandelbrot
+            | FractalKind::Newton
+            | FractalKind::Formula
+            | FractalKind::Julia => 2.0,
+            FractalKind::Tricorn => 2.5,
+            FractalKind::Lyapunov => 1.0,
+        }
+    }
+}
+
+/// Computes the escape time for a point in the Mandelbrot set.
+///
+/// `c` is the complex number for the point and `max_iter` is the maximum
+/// number of iterations to compute, and the point escapes once `|z|`
+/// exceeds `bailout`. With `periodicity` set to a distance, orbits that
+/// come back within that distance of an earlier point are treated as
+/// periodic and stop early. Returns the escape time as a floating point
+/// number, along with the closest approach to `trap` if given.
+///
+/// Points in the cardioid and bulb are only skipped without a trap, since
+/// their trap distance needs the full orbit.
+fn mandelbrot(
+    c: (f64, f64),
+    max_iter: u32,
+    bailout: f64,
+    periodicity: Option<f64>,
+    trap: Option<&Trap>,
+) -> Escape {
+    if trap.is_none() && in_cardioid_or_bulb(c) {
+        return Escape::interior(max_iter, f64::INFINITY);
+    }
+    match periodicity {
+        Some(eps) => escape_time::<_, false, true>(c.into(), max_iter, bailout, eps, trap),
+        None => escape_time::<_, false, false>(c.into(), max_iter, bailout, 0.0, trap),
+    }
+}
+
+/// Checks whether `c` lies in the main cardioid or the period-2 bulb of the
+/// Mandelbrot set, which never escape.
+///
+/// Both regions are in the set in closed form, so points inside can skip
+/// the full `max_iter` iterations they would otherwise burn. This only holds
+/// for `z^2 + c`.
+#[inline]
+pub fn in_cardioid_or_bulb(c: (f64, f64)) -> bool {
+    let (x, y) = c;
+    let q = (x - 0.25) * (x - 0.25) + y * y;
+    if q * (q + (x - 0.25)) < 0.25 * y * y {
+        return true;
+    }
+    (x + 1.0) * (x + 1.0) + y * y < 0.0625
+}
+
+/// Computes the escape time for a point in the Tricorn set.
+///
+/// Same as `mandelbrot`, except that `z` is conjugated before squaring,
+/// which flips the sign of the imaginary cross term.
+fn tricorn(
+    c: (f64, f64),
+    max_iter: u32,
+    bailout: f64,
+    periodicity: Option<f64>,
+    trap: Option<&Trap>,
+) -> Escape {
+    match periodicity {
+        Some(eps) => escape_time::<_, true, true>(c.into(), max_iter, bailout, eps, trap),
+        None => escape_time::<_, true, false>(c.into(), max_iter, bailout, 0.0, trap),
+    }
+}
+
+/// One step of the orbit of `c`, `z^2 + c`, or `conj(z)^2 + c` for the
+/// Tricorn.
+#[inline(always)]
+pub fn step<T: Scalar, const CONJUGATE: bool>(z: Complex<T>, c: Complex<T>) -> Complex<T> {
+    match CONJUGATE {
+        true => z.conj().sqr() + c,
+        false => z.sqr() + c,
+    }
+}
+
+/// The escape-time loop shared by `mandelbrot` and `tricorn`, in f64 and in
+/// double-double alike. The escape test and the periodicity check are made
+/// on the leading digits of `z`, which are all they need.
+///
+/// Both switches are const generics so every combination compiles to its
+/// own loop. With `PERIODIC` the orbit is checked for cycles using Brent's
+/// method: a saved point is compared against every new `z` and replaced at
+/// exponentially growing intervals, so a cycle of any period is caught
+/// within about twice its length once the orbit has settled. By then the
+/// whole cycle has been visited, so stopping early doesn't noticeably change
+/// the trap distance either.
+#[inline(always)]
+pub fn escape_time<T: Scalar, const CONJUGATE: bool, const PERIODIC: bool>(
+    c: Complex<T>,
+    max_iter: u32,
+    bailout: f64,
+    eps: f64,
+    trap: Option<&Trap>,
+) -> Escape {
+    let bailout_sqr = bailout * bailout;
+    let eps_sqr = eps * eps;
+    let mut trap_distance = f64::INFINITY;
+    let mut saved = Complex::ZERO;
+    let mut interval: u32 = 8;
+    let mut next_save: u32 = interval;
+
+    let mut z = Complex::ZERO;
+    for i in 0..max_iter {
+        z = step::<T, CONJUGATE>(z, c);
+        let (x, y) = (z.re.leading(), z.im.leading());
+        if let Some(trap) = trap {
+            trap_distance = trap_distance.min(trap.distance((x, y)));
+        }
+        if x * x + y * y > bailout_sqr {
+            return Escape::escaped(i, (x, y), bailout, trap_distance);
+        }
+
+        if PERIODIC {
+            let (dx, dy) = ((z.re - saved.re).leading(), (z.im - saved.im).leading());
+            if dx * dx + dy * dy < eps_sqr {
+                break;
+            }
+            if i == next_save {
+                saved = z;
+                interval *= 2;
+                next_save = i + interval;
+            }
+        }
+    }
+    Escape::interior(max_iter, trap_distance)
+}
+
+/// The escape-time loop of an `EscapeTimeFractal`, the same as
+/// `escape_time` but with the steps of the formula.
+#[inline(always)]
+fn formula_escape_time<F: EscapeTimeFractal, const PERIODIC: bool>(
+    formula: &F,
+    c: (f64, f64),
+    max_iter: u32,
+    bailout: f64,
+    eps: f64,
+    trap: Option<&Trap>,
+) -> Escape {
+    let bailout_sqr = bailout * bailout;
+    let eps_sqr = eps * eps;
+    let mut trap_distance = f64::INFINITY;
+    let mut z = formula.init(c);
+    let mut saved = z;
+    let mut interval: u32 = 8;
+    let mut next_save: u32 = interval;
+
+    for i in 0..max_iter {
+        let (x, y) = formula.step(z, c);
+        if let Some(trap) = trap {
+            trap_distance = trap_distance.min(trap.distance((x, y)));
+        }
+        if x * x + y * y > bailout_sqr {
+            return Escape::escaped_in_base(i, (x, y), bailout, trap_distance, formula.log_base());
+        }
+        z = (x, y);
+
+        if PERIODIC {
+            let (dx, dy) = (z.0 - saved.0, z.1 - saved.1);
+            if dx * dx + dy * dy < eps_sqr {
+                break;
+            }
+            if i == next_save {
+                saved = z;
+                interval *= 2;
+                next_save = i + interval;
+            }
+        }
+    }
+    Escape::interior(max_iter, trap_distance)
+}
+
+/// Replays the first `iterations` steps of the orbit of `c`, feeding each
+/// new `z` to `visit`.
+#[inline(always)]
+fn orbit<const CONJUGATE: bool, V: FnMut((f64, f64))>(c: (f64, f64), iterations: u32, mut visit: V) {
+    let c = Complex::from(c);
+    let mut z = Complex::ZERO;
+    for _ in 0..iterations {
+        z = step::<f64, CONJUGATE>(z, c);
+        visit(z.into());
+    }
+}
+
+/// Iterates `c` and returns the stripe average of its orbit at escape.
+/// Mandelbrot points in the cardioid and bulb are skipped, as they never
+/// escape.
+fn stripe_average<const CONJUGATE: bool>(
+    c: (f64, f64),
+    max_iter: u32,
+    bailout: f64,
+    density: f64,
+) -> Option<f64> {
+    if !CONJUGATE && in_cardioid_or_bulb(c) {
+        return None;
+    }
+    let bailout_sqr = bailout * bailout;
+    let mut stripes = StripeAverage::new(density);
+    let c = Complex::from(c);
+    let mut z = Complex::ZERO;
+    for i in 0..max_iter {
+        z = step::<f64, CONJUGATE>(z, c);
+        if i > 0 {
+            stripes.add(z.into());
+        }
+        if z.norm_sqr() > bailout_sqr {
+            return Some(stripes.escaped(i, z.into(), bailout));
+        }
+    }
+    None
+}
+
+/// How close the orbit has to come back to a point it passed for the two
+/// to count as the same point of an attracting cycle. This is in the plane
+/// of `z` rather than of `c`, so it doesn't shrink with the pixels.
+pub const ATTRACTOR_EPSILON: f64 = 1e-10;
+
+/// Iterates `c` until its orbit comes back within `ATTRACTOR_EPSILON` of a
+/// point it passed, saving points by Brent's method as `escape_time` does,
+/// and returns the cycle it settled on. The derivative is taken over one
+/// more trip around the cycle; both `z^2` and its conjugate stretch steps
+/// by `2|z|`, so the Tricorn takes the same product.
+fn attractor<const CONJUGATE: bool>(c: (f64, f64), max_iter: u32) -> Option<Attractor> {
+    let c = Complex::from(c);
+    let eps_sqr = ATTRACTOR_EPSILON * ATTRACTOR_EPSILON;
+    let mut z = Complex::ZERO;
+    let mut saved = z;
+    let mut since_saved: u32 = 0;
+    let mut interval: u32 = 8;
+    for _ in 0..max_iter {
+        z = step::<f64, CONJUGATE>(z, c);
+        if z.norm_sqr() > 4.0 {
+            return None;
+        }
+        since_saved += 1;
+        if (z - saved).norm_sqr() < eps_sqr {
+            let mut derivative = 1.0;
+            for _ in 0..since_saved {
+                derivative *= 2.0 * z.norm_sqr().sqrt();
+                z = step::<f64, CONJUGATE>(z, c);
+            }
+            return Some(Attractor {
+                period: since_saved,
+                derivative,
+            });
+        }
+        if since_saved == interval {
+            saved = z;
+            since_saved = 0;
+            interval *= 2;
+        }
+    }
+    None
+}
+
+/// The smallest squared escape radius used for distance estimation. The
+/// estimate only converges once `|z|` is large, so this is much larger than
+/// the usual 2; a larger `--bailout` is kept.
+pub const DISTANCE_BAILOUT: f64 = 1e6;
+
+/// The squared escape radius distance estimation uses at `bailout`.
+#[inline]
+pub fn distance_bailout(bailout: f64) -> f64 {
+    (bailout * bailout).max(DISTANCE_BAILOUT)
+}
+
+/// Iterates `c` along with its derivative and returns the exterior distance
+/// estimate `|z| ln|z| / |dz/dc|` at escape past `bailout`.
+///
+/// For the Tricorn `z` isn't holomorphic in `c`, so the derivatives with
+/// respect to `c` and `conj(c)` are carried separately and their magnitudes
+/// summed, which is the largest directional derivative of `z`.
+fn distance_estimate<const CONJUGATE: bool>(
+    c: (f64, f64),
+    max_iter: u32,
+    bailout: f64,
+) -> Option<f64> {
+    let bailout_sqr = distance_bailout(bailout);
+    let c = Complex::from(c);
+    let mut z = Complex::ZERO;
+    let mut dz = Complex::ZERO;
+    let mut dz_conj = Complex::ZERO;
+    for _ in 0..max_iter {
+        (dz, dz_conj) = derivative_step::<CONJUGATE>(z, dz, dz_conj);
+        z = step::<f64, CONJUGATE>(z, c);
+        if z.norm_sqr() > bailout_sqr {
+            return Some(estimate_distance(z, dz, dz_conj));
+        }
+    }
+    None
+}
+
+/// Advances the derivatives of `z` with respect to `c` and `conj(c)` by one
+/// iteration, given the `z` before the step.
+#[inline(always)]
+pub fn derivative_step<const CONJUGATE: bool>(
+    z: Complex<f64>,
+    dz: Complex<f64>,
+    dz_conj: Complex<f64>,
+) -> (Complex<f64>, Complex<f64>) {
+    let two_z = z.scale(2.0);
+    if CONJUGATE {
+        let two_w = two_z.conj();
+        (two_w * dz_conj.conj() + Complex::ONE, two_w * dz.conj())
+    } else {
+        (two_z * dz + Complex::ONE, Complex::ZERO)
+    }
+}
+
+/// The distance estimate for an escaped `z` and its derivatives.
+#[inline]
+pub fn estimate_distance(z: Complex<f64>, dz: Complex<f64>, dz_conj: Complex<f64>) -> f64 {
+    let r = z.norm_sqr().sqrt();
+    r * r.ln() / (dz.norm_sqr().sqrt() + dz_conj.norm_sqr().sqrt())
+}
@@ -0,0 +1,129 @@
+use crate::render::{from_linear, to_linear};
+use image::{Rgb, RgbImage};
+
+/// Pixels between the tiles of a grid, and around them.
+pub const GAP: u32 = 4;
+
+/// The color behind the tiles and labels.
+pub const BACKGROUND: [u8; 3] = [24, 24, 24];
+
+/// Where the tiles of a contact sheet or montage go, row by row from the
+/// top left, each an image with room under it for a label, below room for
+/// a line about the whole grid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Grid {
+    pub columns: u32,
+    pub rows: u32,
+    /// The pixels across and down the image of every tile.
+    pub tile: (u32, u32),
+    /// The pixels under every tile for its label.
+    pub label: u32,
+    /// The pixels over the tiles for a line about them, or 0 for none.
+    pub header: u32,
+}
+
+impl Grid {
+    /// The grid of `count` tiles, `columns` to a row.
+    pub fn new(count: usize, columns: u32, tile: (u32, u32), label: u32, header: u32) -> Grid {
+        let columns = columns.max(1);
+        Grid {
+            columns,
+            rows: (count as u32).div_ceil(columns),
+            tile,
+            label,
+            header,
+        }
+    }
+
+    /// The pixels across and down the whole grid.
+    pub fn size(&self) -> (u32, u32) {
+        let (width, height) = self.pitch();
+        (GAP + self.columns * width, self.top() + self.rows * height)
+    }
+
+    /// The top left of the image of the tile in `column` and `row`.
+    pub fn tile_at(&self, column: u32, row: u32) -> (u32, u32) {
+        let (width, height) = self.pitch();
+        (GAP + column * width, self.top() + row * height)
+    }
+
+    /// The top left of the label of the tile in `column` and `row`, a
+    /// pixel under its image.
+    pub fn label_at(&self, column: u32, row: u32) -> (u32, u32) {
+        let (left, top) = self.tile_at(column, row);
+        (left, top + self.tile.1 + 1)
+    }
+
+    /// The column and row of the `index`th tile.
+    pub fn place(&self, index: usize) -> (u32, u32) {
+        (index as u32 % self.columns, index as u32 / self.columns)
+    }
+
+    /// This grid with its tiles shrunk, keeping their shape, as far as it
+    /// takes for the grid to fit in `max` pixels across and down. `None`
+    /// if even tiles a pixel wide wouldn't, for the room the labels and
+    /// gaps take.
+    pub fn shrunk_to(self, max: (u32, u32)) -> Option<Grid> {
+        let width = (max.0.checked_sub(GAP)? / self.columns).checked_sub(GAP)?;
+        let rows = self.rows.max(1);
+        let height = (max.1.checked_sub(self.top())? / rows).checked_sub(self.label + GAP)?;
+        if width == 0 || height == 0 {
+            return None;
+        }
+        Some(Grid {
+            tile: fit(self.tile.0, self.tile.1, width, height),
+            ..self
+        })
+    }
+
+    /// The pixels from one tile to the next, across and down.
+    fn pitch(&self) -> (u32, u32) {
+        (self.tile.0 + GAP, self.tile.1 + self.label + GAP)
+    }
+
+    /// The pixels over the first row of tiles.
+    fn top(&self) -> u32 {
+        match self.header {
+            0 => GAP,
+            header => GAP + header + GAP,
+        }
+    }
+}
+
+/// The size of `width` by `height` shrunk to fit in `max_width` by
+/// `max_height`, keeping its shape. Images that fit already keep their
+/// size.
+pub fn fit(width: u32, height: u32, max_width: u32, max_height: u32) -> (u32, u32) {
+    let scale = (max_width as f64 / width as f64).min(max_height as f64 / height as f64).min(1.0);
+    let scaled = |side: u32| ((side as f64 * scale).round() as u32).max(1);
+    (scaled(width), scaled(height))
+}
+
+/// `img` shrunk to `width` by `height`, every pixel the mean of the ones
+/// it covers, taken in linear light so fine filaments keep their
+/// brightness.
+pub fn downscale(img: &RgbImage, width: u32, height: u32) -> RgbImage {
+    let (from_width, from_height) = img.dimensions();
+    if (width, height) == (from_width, from_height) {
+        return img.clone();
+    }
+    // The rows or columns of the original the pixel at `at` of `to` covers.
+    let span = |at: u32, to: u32, from: u32| {
+        let start = (at as u64 * from as u64 / to as u64) as u32;
+        let end = ((at as u64 + 1) * from as u64 / to as u64) as u32;
+        start..end.max(start + 1)
+    };
+    RgbImage::from_fn(width, height, |x, y| {
+        let (mut sum, mut count) = ([0.0; 3], 0.0);
+        for from_y in span(y, height, from_height) {
+            for from_x in span(x, width, from_width) {
+                let pixel = img.get_pixel(from_x, from_y);
+                for (sum, channel) in sum.iter_mut().zip(pixel.0) {
+                    *sum += to_linear(channel as f64 / 255.0);
+                }
+                count += 1.0;
+            }
+        }
+        Rgb(sum.map(|sum| (from_linear(sum / count) * 255.0).round() as u8))
+    })
+}
@@ -0,0 +1,57 @@
+/// The distribution of escape times over a frame, used to spread them
+/// evenly over the gradient.
+///
+/// At high max_iter almost every exterior point escapes within a narrow
+/// slice of the iteration range, so dividing by max_iter leaves most of the
+/// gradient unused. Mapping each escape time to the fraction of pixels that
+/// escaped at or before it instead gives every color about the same area.
+pub struct Histogram {
+    sorted: Vec<f64>,
+    low: f64,
+    high: f64,
+    /// Pixels clipped off the bottom of the distribution.
+    below: usize,
+    /// Pixels between `low` and `high`, inclusive.
+    total: usize,
+}
+
+impl Histogram {
+    /// Builds the histogram of `values`, the escape times of the exterior
+    /// pixels.
+    ///
+    /// `clip` is the percentage of pixels at either end of the distribution
+    /// that are clamped to the first and last color, so a few outliers can't
+    /// squeeze everything else into a small part of the gradient.
+    pub fn new<I: Iterator<Item = f64>>(values: I, clip: f64) -> Self {
+        let mut sorted: Vec<f64> = values.collect();
+        sorted.sort_unstable_by(f64::total_cmp);
+        let (low, high) = match sorted.len() {
+            0 => (0.0, 0.0),
+            n => {
+                let cut = ((n as f64 * clip / 100.0) as usize).min((n - 1) / 2);
+                (sorted[cut], sorted[n - 1 - cut])
+            }
+        };
+        let below = sorted.partition_point(|&v| v < low);
+        let total = sorted.partition_point(|&v| v <= high) - below;
+        Histogram {
+            sorted,
+            low,
+            high,
+            below,
+            total,
+        }
+    }
+
+    /// The gradient position of a pixel that escaped after `value`
+    /// iterations: the fraction of the unclipped pixels that escaped at or
+    /// before it.
+    pub fn position(&self, value: f64) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        let value = value.clamp(self.low, self.high);
+        let at_or_below = self.sorted.partition_point(|&v| v <= value) - self.below;
+        at_or_below as f64 / self.total as f64
+    }
+}
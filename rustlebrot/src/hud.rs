@@ -0,0 +1,326 @@
+use crate::manifest::FrameRecord;
+use image::DynamicImage;
+use crate::grid::Grid;
+
+/// Glyphs of the font the overlay is written in, 5 pixels wide and 7 high,
+/// a row to a byte from the top, with the leftmost pixel in bit 4.
+const GLYPHS: &[(char, [u8; 7])] = &[
+    (' ', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000]),
+    ('0', [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110]),
+    ('1', [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+    ('2', [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111]),
+    ('3', [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110]),
+    ('4', [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010]),
+    ('5', [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110]),
+    ('6', [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110]),
+    ('7', [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000]),
+    ('8', [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110]),
+    ('9', [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100]),
+    ('.', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100]),
+    (',', [0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b00100, 0b01000]),
+    (':', [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000]),
+    ('-', [0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000]),
+    ('+', [0b00000, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0b00000]),
+    ('=', [0b00000, 0b00000, 0b11111, 0b00000, 0b11111, 0b00000, 0b00000]),
+    ('^', [0b00100, 0b01010, 0b10001, 0b00000, 0b00000, 0b00000, 0b00000]),
+    ('×', [0b00000, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b00000]),
+    ('?', [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100]),
+    ('a', [0b00000, 0b00000, 0b01110, 0b00001, 0b01111, 0b10001, 0b01111]),
+    ('b', [0b10000, 0b10000, 0b10110, 0b11001, 0b10001, 0b10001, 0b11110]),
+    ('c', [0b00000, 0b00000, 0b01110, 0b10000, 0b10000, 0b10001, 0b01110]),
+    ('d', [0b00001, 0b00001, 0b01101, 0b10011, 0b10001, 0b10001, 0b01111]),
+    ('e', [0b00000, 0b00000, 0b01110, 0b10001, 0b11111, 0b10000, 0b01110]),
+    ('f', [0b00110, 0b01001, 0b01000, 0b11100, 0b01000, 0b01000, 0b01000]),
+    ('g', [0b00000, 0b01111, 0b10001, 0b10001, 0b01111, 0b00001, 0b01110]),
+    ('h', [0b10000, 0b10000, 0b10110, 0b11001, 0b10001, 0b10001, 0b10001]),
+    ('i', [0b00100, 0b00000, 0b01100, 0b00100, 0b00100, 0b00100, 0b01110]),
+    ('j', [0b00010, 0b00000, 0b00110, 0b00010, 0b00010, 0b10010, 0b01100]),
+    ('k', [0b10000, 0b10000, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010]),
+    ('l', [0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+    ('m', [0b00000, 0b00000, 0b11010, 0b10101, 0b10101, 0b10001, 0b10001]),
+    ('n', [0b00000, 0b00000, 0b10110, 0b11001, 0b10001, 0b10001, 0b10001]),
+    ('o', [0b00000, 0b00000, 0b01110, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('p', [0b00000, 0b00000, 0b11110, 0b10001, 0b11110, 0b10000, 0b10000]),
+    ('q', [0b00000, 0b00000, 0b01101, 0b10011, 0b01111, 0b00001, 0b00001]),
+    ('r', [0b00000, 0b00000, 0b10110, 0b11001, 0b10000, 0b10000, 0b10000]),
+    ('s', [0b00000, 0b00000, 0b01110, 0b10000, 0b01110, 0b00001, 0b11110]),
+    ('t', [0b01000, 0b01000, 0b11100, 0b01000, 0b01000, 0b01001, 0b00110]),
+    ('u', [0b00000, 0b00000, 0b10001, 0b10001, 0b10001, 0b10011, 0b01101]),
+    ('v', [0b00000, 0b00000, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100]),
+    ('w', [0b00000, 0b00000, 0b10001, 0b10001, 0b10101, 0b10101, 0b01010]),
+    ('x', [0b00000, 0b00000, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001]),
+    ('y', [0b00000, 0b00000, 0b10001, 0b10001, 0b01111, 0b00001, 0b01110]),
+    ('z', [0b00000, 0b00000, 0b11111, 0b00010, 0b00100, 0b01000, 0b11111]),
+];
+
+/// Font pixels across a character and down a line, with the space between
+/// them.
+pub const CELL: (u32, u32) = (6, 9);
+
+/// Font pixels between the edge of the frame and the box, and between the
+/// box and what's in it.
+const MARGIN: u32 = 4;
+const PADDING: u32 = 3;
+
+/// Font pixels down the scale bar.
+const BAR_HEIGHT: u32 = 2;
+
+/// How much of what's behind it the box darkens.
+const BOX_OPACITY: f32 = 0.55;
+
+/// Frame rows per font pixel when `--hud-size` doesn't say, so the overlay
+/// keeps to the same share of the frame.
+const ROWS_PER_SIZE: u32 = 360;
+
+/// The corner of the frame `--hud-position` puts the overlay in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Corner {
+    pub fn from_name(name: &str) -> Option<Corner> {
+        match name {
+            "top-left" => Some(Corner::TopLeft),
+            "top-right" => Some(Corner::TopRight),
+            "bottom-left" => Some(Corner::BottomLeft),
+            "bottom-right" => Some(Corner::BottomRight),
+            _ => None,
+        }
+    }
+}
+
+/// The overlay `--hud` writes on every frame: the center, magnification and
+/// iteration limit of the frame, and optionally a scale bar, white on a
+/// darkened box in a corner.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hud {
+    pub corner: Corner,
+    /// Frame pixels along the side of a pixel of the font, or `None` to
+    /// scale with the frame.
+    pub size: Option<u32>,
+    /// Whether a bar of a round length of the plane is drawn under the
+    /// text.
+    pub scale_bar: bool,
+    /// Whether only the piped video has the overlay, as with
+    /// `--hud-only-video`, and the saved frames are left clean.
+    pub only_video: bool,
+}
+
+impl Default for Hud {
+    fn default() -> Self {
+        Hud {
+            corner: Corner::TopLeft,
+            size: None,
+            scale_bar: false,
+            only_video: false,
+        }
+    }
+}
+
+impl Hud {
+    /// The lines of text of the frame `record` describes, which is what
+    /// the manifest records of it, so the two can't disagree.
+    pub fn lines(&self, record: &FrameRecord) -> Vec<String> {
+        let (x, y) = (
+            (record.x_range.0 + record.x_range.1) / 2.0,
+            (record.y_range.0 + record.y_range.1) / 2.0,
+        );
+        // Enough digits to place the center to a thousandth of the view.
+        let width = record.x_range.1 - record.x_range.0;
+        let digits = ((3.0 - width.log10()).ceil() as usize).clamp(3, 15);
+        let mut lines = vec![
+            format!("x    {:.*}", digits, x),
+            format!("y    {:.*}", digits, y),
+            format!("zoom {}", scientific(record.magnification)),
+            format!("iter {}", record.max_iter),
+        ];
+        if let Some((re, im)) = record.julia_c {
+            lines.push(format!("c    {:.6}{:+.6}i", re, im));
+        }
+        lines
+    }
+
+    /// Writes the overlay of the frame `record` describes on `img`, cut
+    /// off where it doesn't fit.
+    pub fn draw(&self, img: &mut DynamicImage, record: &FrameRecord) {
+        let (width, height) = (img.width(), img.height());
+        let size = self.size.unwrap_or((height / ROWS_PER_SIZE).max(1));
+        let mut lines = self.lines(record);
+        // The bar is the longest length of 1, 2 or 5 times a power of 10
+        // that fits in a fifth of the frame.
+        let bar = self.scale_bar.then(|| {
+            let most = record.pixel_size * (width as f64 / 5.0).max(1.0);
+            let power = 10f64.powf(most.log10().floor());
+            let steps = [5.0, 2.0, 1.0].map(|step| step * power);
+            let length = steps.into_iter().find(|&length| length <= most).unwrap_or(power);
+            lines.push(scientific(length));
+            ((length / record.pixel_size).round() as u32).max(1)
+        });
+        let text_width = lines.iter().map(|line| line.chars().count() as u32).max().unwrap_or(0);
+        let inner = (
+            (text_width * CELL.0 * size).max(bar.unwrap_or(0)),
+            lines.len() as u32 * CELL.1 * size + bar.map_or(0, |_| BAR_HEIGHT * size),
+        );
+        let outer = (inner.0 + 2 * PADDING * size, inner.1 + 2 * PADDING * size);
+        let margin = MARGIN * size;
+        let left = match self.corner {
+            Corner::TopLeft | Corner::BottomLeft => margin as i64,
+            Corner::TopRight | Corner::BottomRight => {
+                width as i64 - margin as i64 - outer.0 as i64
+            }
+        };
+        let top = match self.corner {
+            Corner::TopLeft | Corner::TopRight => margin as i64,
+            Corner::BottomLeft | Corner::BottomRight => {
+                height as i64 - margin as i64 - outer.1 as i64
+            }
+        };
+        fill(img, left, top, outer, Paint::Box);
+        let text_left = left + (PADDING * size) as i64;
+        let text_top = top + (PADDING * size) as i64;
+        let line_height = (CELL.1 * size) as i64;
+        for (row, line) in lines.iter().enumerate() {
+            write(img, text_left, text_top + row as i64 * line_height, size, line);
+        }
+        if let Some(bar) = bar {
+            let bar_top = text_top + lines.len() as i64 * line_height;
+            fill(img, text_left, bar_top, (bar, BAR_HEIGHT * size), Paint::Text);
+        }
+    }
+}
+
+/// Writes `line` on `img` in white, with the top left of its first
+/// character at `(left, top)` and font pixels `size` pixels wide, cut off
+/// where it doesn't fit.
+pub fn write(img: &mut DynamicImage, left: i64, top: i64, size: u32, line: &str) {
+    for (column, c) in line.chars().enumerate() {
+        let glyph_left = left + (column as u32 * CELL.0 * size) as i64;
+        for (y, bits) in glyph(c).into_iter().enumerate() {
+            for x in (0..5).filter(|x| bits & (0b10000 >> x) != 0) {
+                let pixel_left = glyph_left + (x * size) as i64;
+                let pixel_top = top + (y as u32 * size) as i64;
+                fill(img, pixel_left, pixel_top, (size, size), Paint::Text);
+            }
+        }
+    }
+}
+
+/// Writes `lines` under the tile of `grid` in `column` and `row`, each cut
+/// off at the edge of the tile rather than running into the next one.
+pub fn label(img: &mut DynamicImage, grid: &Grid, (column, row): (u32, u32), lines: &[String]) {
+    let (left, top) = grid.label_at(column, row);
+    let fits = (grid.tile.0 / CELL.0) as usize;
+    for (index, line) in lines.iter().enumerate() {
+        let line: String = line.chars().take(fits).collect();
+        write(img, left as i64, (top + index as u32 * CELL.1) as i64, 1, &line);
+    }
+}
+
+/// `value` written for reading at a glance, like `2.4 × 10^17`, or as it
+/// is when it's near 1.
+pub fn scientific(value: f64) -> String {
+    if (0.01..1000.0).contains(&value) {
+        return format!("{}", (value * 100.0).round() / 100.0);
+    }
+    let mut exponent = value.log10().floor() as i32;
+    let mut mantissa = (value / 10f64.powi(exponent) * 10.0).round() / 10.0;
+    if mantissa >= 10.0 {
+        mantissa /= 10.0;
+        exponent += 1;
+    }
+    let mantissa = format!("{:.1}", mantissa);
+    format!("{} × 10^{}", mantissa.trim_end_matches(".0"), exponent)
+}
+
+/// What a pixel of the overlay is painted with.
+#[derive(Clone, Copy)]
+enum Paint {
+    /// Darkened, or made opaque enough to darken the frame behind it.
+    Box,
+    /// Opaque white.
+    Text,
+}
+
+/// A channel of a pixel of a frame, 8 or 16 bits.
+trait Channel: Copy {
+    const MAX: f32;
+    fn to_f32(self) -> f32;
+    fn from_f32(value: f32) -> Self;
+}
+
+impl Channel for u8 {
+    const MAX: f32 = u8::MAX as f32;
+    fn to_f32(self) -> f32 {
+        self as f32
+    }
+    fn from_f32(value: f32) -> Self {
+        value.round() as u8
+    }
+}
+
+impl Channel for u16 {
+    const MAX: f32 = u16::MAX as f32;
+    fn to_f32(self) -> f32 {
+        self as f32
+    }
+    fn from_f32(value: f32) -> Self {
+        value.round() as u16
+    }
+}
+
+/// Paints the rectangle of `img` at `(left, top)`, as much of it as is in
+/// the frame.
+fn fill(img: &mut DynamicImage, left: i64, top: i64, size: (u32, u32), paint: Paint) {
+    let (width, height) = (img.width() as i64, img.height() as i64);
+    let xs = left.max(0)..(left + size.0 as i64).min(width);
+    let ys = top.max(0)..(top + size.1 as i64).min(height);
+    let pixels = ys.flat_map(|y| xs.clone().map(move |x| (y * width + x) as usize));
+    match img {
+        DynamicImage::ImageRgb8(img) => paint_pixels(img, 3, pixels, paint),
+        DynamicImage::ImageRgba8(img) => paint_pixels(img, 4, pixels, paint),
+        DynamicImage::ImageRgb16(img) => paint_pixels(img, 3, pixels, paint),
+        DynamicImage::ImageRgba16(img) => paint_pixels(img, 4, pixels, paint),
+        _ => unreachable!("frames are colored as RGB or RGBA"),
+    }
+}
+
+/// Paints `pixels`, indices into the `channels` channels of `raw` each.
+fn paint_pixels<C: Channel>(
+    raw: &mut [C],
+    channels: usize,
+    pixels: impl Iterator<Item = usize>,
+    paint: Paint,
+) {
+    for i in pixels {
+        paint_pixel(&mut raw[channels * i..][..channels], paint);
+    }
+}
+
+/// The glyph of `c`, or a question mark for a character the font doesn't
+/// have.
+fn glyph(c: char) -> [u8; 7] {
+    let find = |c| GLYPHS.iter().find(|(glyph, _)| *glyph == c).map(|(_, rows)| *rows);
+    find(c).or_else(|| find('?')).expect("the font has a question mark")
+}
+
+/// Paints one pixel of RGB or RGBA `channels`. The box goes over what's
+/// behind it, so a transparent pixel comes out partly opaque and dark.
+fn paint_pixel<C: Channel>(channels: &mut [C], paint: Paint) {
+    let alpha = channels.get(3).map_or(1.0, |alpha| alpha.to_f32() / C::MAX);
+    let (color, opacity) = match paint {
+        Paint::Box => (0.0, BOX_OPACITY),
+        Paint::Text => (C::MAX, 1.0),
+    };
+    let covered = alpha + opacity * (1.0 - alpha);
+    for channel in channels.iter_mut().take(3) {
+        let value = (channel.to_f32() * alpha * (1.0 - opacity) + color * opacity) / covered;
+        *channel = C::from_f32(value);
+    }
+    if let Some(alpha) = channels.get_mut(3) {
+        *alpha = C::from_f32(covered * C::MAX);
+    }
+}
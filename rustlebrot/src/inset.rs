@@ -0,0 +1,190 @@
+use crate::coloring::Coloring;
+use crate::fractal::EscapeTimeFractal;
+use crate::hud::Corner;
+use crate::julia::Julia;
+use crate::render::{
+    self, Alpha, ColorOptions, EscapeBuffer, RenderOptions, Rotation, Subdivision, WorkUnits,
+};
+use image::{imageops, DynamicImage, ImageBuffer, Rgb};
+
+/// Half the plane the shorter side of the inset shows, around the origin,
+/// which takes in all but the tips of the connected Julia sets.
+const REACH: f64 = 1.5;
+
+/// Frame rows per pixel of the border, so it keeps to the same share of
+/// the frame, as the overlay of `--hud` does.
+const ROWS_PER_BORDER: u32 = 360;
+
+/// Border widths between the edge of the frame and the inset.
+const MARGIN: u32 = 4;
+
+/// The picture in picture `--julia-inset` puts in a corner of every frame
+/// of a Mandelbrot zoom: the Julia set of the point the frame is centered
+/// on, in a white border.
+///
+/// The set is rendered in f64 whatever precision the frame takes, so from
+/// the depth where the centers of frames round to the same f64 it holds
+/// still, as the Julia sets of points closer than that can't be told
+/// apart at its size anyway.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct JuliaInset {
+    /// The width of the inset as a share of the frame's.
+    pub size: f64,
+    pub corner: Corner,
+    /// The iteration limit of the Julia sets, kept low as they're small.
+    pub max_iter: u32,
+}
+
+impl Default for JuliaInset {
+    fn default() -> Self {
+        JuliaInset {
+            size: 0.25,
+            corner: Corner::BottomRight,
+            max_iter: 200,
+        }
+    }
+}
+
+impl JuliaInset {
+    /// The inset of `spec`, as `size=25%,corner=bottom-right,iter=200`,
+    /// with any field left out as it is by default, or `on` for all of
+    /// them.
+    pub fn from_spec(spec: &str) -> Result<JuliaInset, String> {
+        let mut inset = JuliaInset::default();
+        if spec == "on" {
+            return Ok(inset);
+        }
+        for field in spec.split(',') {
+            let invalid = || format!("julia-inset takes fields like size=25%, got '{}'", field);
+            let (name, value) = field.split_once('=').ok_or_else(invalid)?;
+            let value = value.trim();
+            match name.trim() {
+                "size" => {
+                    let percent = value.strip_suffix('%').and_then(|p| p.parse::<f64>().ok());
+                    inset.size = match percent {
+                        Some(percent) if percent > 0.0 && percent <= 50.0 => percent / 100.0,
+                        _ => {
+                            return Err(format!(
+                                "julia-inset size should be a percentage of the frame's width \
+                                 up to 50%, got '{}'",
+                                value
+                            ))
+                        }
+                    }
+                }
+                "corner" => {
+                    inset.corner = Corner::from_name(value).ok_or_else(|| {
+                        format!(
+                            "julia-inset corner should be top-left, top-right, bottom-left or \
+                             bottom-right, got '{}'",
+                            value
+                        )
+                    })?
+                }
+                "iter" => {
+                    inset.max_iter = value.parse().ok().filter(|&iter| iter > 0).ok_or_else(|| {
+                        format!("julia-inset iter should be a positive integer, got '{}'", value)
+                    })?
+                }
+                other => {
+                    return Err(format!(
+                        "unknown julia-inset field '{}', expected size, corner or iter",
+                        other
+                    ))
+                }
+            }
+        }
+        Ok(inset)
+    }
+
+    /// The pixels across and down the Julia set in a frame of `width` by
+    /// `height`, which has the frame's shape.
+    pub fn image_size(&self, (width, height): (u32, u32)) -> (u32, u32) {
+        let across = ((width as f64 * self.size).round() as u32).max(1);
+        let down = ((across as f64 * height as f64 / width as f64).round() as u32).max(1);
+        (across, down)
+    }
+
+    /// The left, top, width and height of the rectangle the inset covers
+    /// in a frame of `width` by `height`, border and all.
+    pub fn rect(&self, (width, height): (u32, u32)) -> (u32, u32, u32, u32) {
+        let border = (height / ROWS_PER_BORDER).max(1);
+        let (across, down) = self.image_size((width, height));
+        let outer = (across + 2 * border, down + 2 * border);
+        let margin = MARGIN * border;
+        let left = match self.corner {
+            Corner::TopLeft | Corner::BottomLeft => margin,
+            Corner::TopRight | Corner::BottomRight => width.saturating_sub(margin + outer.0),
+        };
+        let top = match self.corner {
+            Corner::TopLeft | Corner::TopRight => margin,
+            Corner::BottomLeft | Corner::BottomRight => height.saturating_sub(margin + outer.1),
+        };
+        (left, top, outer.0, outer.1)
+    }
+
+    /// Renders the Julia set of `c` for the inset of a frame of `size`,
+    /// once for the frame however many palettes `draw` colors it in.
+    pub fn render(&self, c: (f64, f64), size: (u32, u32)) -> EscapeBuffer {
+        let (across, down) = self.image_size(size);
+        let julia = Julia { c };
+        let options = RenderOptions {
+            max_iter: self.max_iter,
+            periodicity: true,
+            bailout: julia.bailout(),
+            coloring: Coloring::Smooth,
+            single_precision: false,
+            subdivision: Subdivision::Off,
+            work_units: WorkUnits::Auto,
+            reuse: None,
+            refine: None,
+            rotation: Rotation::NONE,
+            window: None,
+            timing: None,
+            attractors: false,
+        };
+        let scale = REACH / across.min(down) as f64 * 2.0;
+        let (half_across, half_down) = (across as f64 * scale / 2.0, down as f64 * scale / 2.0);
+        let x_range = (-half_across, half_across);
+        let y_range = (-half_down, half_down);
+        render::compute_escape(&julia, across, down, x_range, y_range, &options)
+    }
+
+    /// Draws the Julia set `buffer` on `img` in its corner, colored as the
+    /// frame is by `colors` but spread over the inset's own limit. The
+    /// inset is opaque, and the pixels outside it are left as they are.
+    pub fn draw(&self, img: &mut DynamicImage, buffer: &EscapeBuffer, colors: &ColorOptions) {
+        let colors = ColorOptions {
+            palette_iter: self.max_iter,
+            reference: None,
+            alpha: Alpha::None,
+            lighting: None,
+            script: None,
+            contours: None,
+            silhouette: None,
+            line_art: None,
+            ..*colors
+        };
+        let julia = render::colorize(buffer, &colors);
+        let (left, top, width, height) = self.rect((img.width(), img.height()));
+        let border = (width - julia.width()) / 2;
+        // The border is drawn at 16 bits, so a frame of 16 keeps them.
+        let white = Rgb([u16::MAX; 3]);
+        let mut inset = DynamicImage::ImageRgb16(ImageBuffer::from_pixel(width, height, white));
+        replace(&mut inset, &julia, border, border);
+        replace(img, &inset, left, top);
+    }
+}
+
+/// Copies `from` over `img` with its top left at `(left, top)`, in the
+/// color type and depth of `img`, cut off where it doesn't fit.
+fn replace(img: &mut DynamicImage, from: &DynamicImage, left: u32, top: u32) {
+    let (left, top) = (left as i64, top as i64);
+    match img {
+        DynamicImage::ImageRgb8(img) => imageops::replace(img, &from.to_rgb8(), left, top),
+        DynamicImage::ImageRgba8(img) => imageops::replace(img, &from.to_rgba8(), left, top),
+        DynamicImage::ImageRgb16(img) => imageops::replace(img, &from.to_rgb16(), left, top),
+        DynamicImage::ImageRgba16(img) => imageops::replace(img, &from.to_rgba16(), left, top),
+        _ => unreachable!("frames are colored as RGB or RGBA"),
+    }
+}
@@ -0,0 +1,83 @@
+use colorgrad::Color;
+
+/// Periods the legend of `--interior-coloring` lists colors for. Periods
+/// past these have colors too; they just cover too little of the set to
+/// look up.
+pub const LEGEND_PERIODS: u32 = 16;
+
+/// Degrees of hue from one period to the next: the golden angle, so every
+/// period lands far from the hues of the ones before it and nearby bulbs,
+/// whose periods are close, don't share a color.
+const HUE_STEP: f64 = 137.507_764;
+
+/// The saturation and value of the colors of the periods.
+const SATURATION: f64 = 0.75;
+const VALUE: f64 = 1.0;
+
+/// How the interior of the set is colored from the attracting cycles its
+/// orbits settle on, as chosen with `--interior-coloring`. Points whose
+/// cycle isn't found within max_iter keep the plain interior color.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InteriorColoring {
+    /// A hue for every period of cycle, so each component of the interior
+    /// is colored by the period of its bulb.
+    Period,
+    /// Gray as bright as the derivative of the cycle, dark at the nucleus
+    /// of every component and light towards its edge.
+    Derivative,
+    /// The hue of the period, as bright as the derivative.
+    Both,
+}
+
+impl InteriorColoring {
+    pub fn from_name(name: &str) -> Option<InteriorColoring> {
+        match name {
+            "period" => Some(InteriorColoring::Period),
+            "derivative" => Some(InteriorColoring::Derivative),
+            "both" => Some(InteriorColoring::Both),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            InteriorColoring::Period => "period",
+            InteriorColoring::Derivative => "derivative",
+            InteriorColoring::Both => "both",
+        }
+    }
+
+    /// Whether the hue tells the period, which the legend is for.
+    pub fn shows_period(self) -> bool {
+        self != InteriorColoring::Derivative
+    }
+
+    /// The color of a point whose orbit settles on a cycle of `period`
+    /// with `derivative`, with channels between 0 and 1.
+    pub fn rgb(self, period: u32, derivative: f64) -> [f64; 3] {
+        let brightness = derivative.clamp(0.0, 1.0);
+        match self {
+            InteriorColoring::Period => period_color(period),
+            InteriorColoring::Derivative => [brightness; 3],
+            InteriorColoring::Both => period_color(period).map(|channel| channel * brightness),
+        }
+    }
+}
+
+/// The color of the cycles of `period`.
+pub fn period_color(period: u32) -> [f64; 3] {
+    let hue = (period.saturating_sub(1) as f64 * HUE_STEP) % 360.0;
+    let color = Color::from_hsva(hue, SATURATION, VALUE, 1.0);
+    [color.r, color.g, color.b]
+}
+
+/// The colors of the periods from 1 to `LEGEND_PERIODS`, in order, as
+/// `#rrggbb`.
+pub fn legend() -> Vec<String> {
+    (1..=LEGEND_PERIODS)
+        .map(|period| {
+            let [r, g, b] = period_color(period).map(|channel| (channel * 255.0).round() as u8);
+            format!("#{:02x}{:02x}{:02x}", r, g, b)
+        })
+        .collect()
+}
@@ -0,0 +1,50 @@
+use crate::error::RustlebrotError;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The exit status of a run stopped with Ctrl-C, 128 plus the number of
+/// SIGINT as shells report it.
+pub const EXIT_STATUS: i32 = 130;
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+static SKIP_VIDEO: AtomicBool = AtomicBool::new(false);
+
+/// Handles Ctrl-C by asking the run to stop once the frames in progress
+/// are saved. A second Ctrl-C exits right away.
+pub fn install() -> Result<(), RustlebrotError> {
+    ctrlc::set_handler(|| {
+        if REQUESTED.swap(true, Ordering::SeqCst) {
+            std::process::exit(EXIT_STATUS);
+        }
+        eprintln!("Stopping after the frames in progress, press Ctrl-C again to stop now");
+    })
+    .map_err(|e| RustlebrotError::System(format!("can't handle Ctrl-C: {}", e)))
+}
+
+/// Asks the run to stop once the frames in progress are saved, as Ctrl-C
+/// does, from elsewhere than the terminal.
+#[cfg(feature = "dashboard")]
+pub fn request(from: &str) {
+    if !REQUESTED.swap(true, Ordering::SeqCst) {
+        eprintln!("Stopping after the frames in progress, as asked from {}", from);
+    }
+}
+
+/// Whether Ctrl-C was pressed, or a stop requested otherwise.
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
+
+/// Asks the run to leave the video out once the frames are saved. Frames
+/// already piped to the encoder make their video either way.
+#[cfg(feature = "dashboard")]
+pub fn skip_video(from: &str) {
+    if !SKIP_VIDEO.swap(true, Ordering::SeqCst) {
+        eprintln!("Leaving out the video once the frames are saved, as asked from {}", from);
+    }
+}
+
+/// Whether the video was asked to be left out.
+pub fn video_skipped() -> bool {
+    SKIP_VIDEO.load(Ordering::SeqCst)
+}
@@ -0,0 +1,395 @@
+use crate::contour;
+use crate::render::EscapeBuffer;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// How the levels `--levels` asks for are spread over the escape times of
+/// a view, as chosen with `--spacing`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Spacing {
+    /// The same escape time apart.
+    Even,
+    /// The same ratio apart in the escape time past the lowest, so the
+    /// levels crowd in toward the outside, where the escape times change
+    /// slowly, and thin out toward the set.
+    Log,
+}
+
+impl Spacing {
+    pub fn from_name(name: &str) -> Option<Spacing> {
+        match name {
+            "even" => Some(Spacing::Even),
+            "log" => Some(Spacing::Log),
+            _ => None,
+        }
+    }
+
+    /// `count` levels between the lowest and highest escape times of
+    /// `range`, neither of them included, in order.
+    pub fn levels(self, range: (f64, f64), count: usize) -> Vec<f64> {
+        let (low, high) = range;
+        (1..=count)
+            .map(|step| {
+                let t = step as f64 / (count + 1) as f64;
+                match self {
+                    Spacing::Even => low + t * (high - low),
+                    Spacing::Log => low - 1.0 + (t * (high - low + 1.0).ln()).exp(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Values at the samples of a grid, a row at a time from the top, which
+/// isolines are traced through.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Field {
+    pub width: usize,
+    pub height: usize,
+    pub values: Vec<f64>,
+    /// The value taken past the edges, if any, which closes every line
+    /// that would leave the grid along its edge.
+    pub outside: Option<f64>,
+}
+
+impl Field {
+    /// The smooth escape times of `buffer`, with its interior at the
+    /// iteration limit, which it didn't escape by.
+    pub fn escape_times(buffer: &EscapeBuffer) -> Field {
+        let values = buffer
+            .values
+            .iter()
+            .map(|&sample| contour::level(sample).unwrap_or(buffer.max_iter as f64))
+            .collect();
+        Field {
+            width: buffer.width as usize,
+            height: buffer.height as usize,
+            values,
+            outside: None,
+        }
+    }
+
+    /// 1 in the set and 0 outside it, and outside past the edges, so the
+    /// isoline at a half goes around every part of the set in the view.
+    pub fn interior(buffer: &EscapeBuffer) -> Field {
+        let values = buffer
+            .values
+            .iter()
+            .map(|&sample| if contour::level(sample).is_some() { 0.0 } else { 1.0 })
+            .collect();
+        Field {
+            width: buffer.width as usize,
+            height: buffer.height as usize,
+            values,
+            outside: Some(0.0),
+        }
+    }
+
+    fn at(&self, x: isize, y: isize) -> f64 {
+        let (width, height) = (self.width as isize, self.height as isize);
+        let inside = (0..width).contains(&x) && (0..height).contains(&y);
+        match (inside, self.outside) {
+            (true, _) | (false, None) => self.values[y as usize * self.width + x as usize],
+            (false, Some(outside)) => outside,
+        }
+    }
+}
+
+/// The lowest and highest escape times of the samples of `buffer` that
+/// escaped, if any did.
+pub fn escaped_range(buffer: &EscapeBuffer) -> Option<(f64, f64)> {
+    let mut escaped = buffer.values.iter().filter_map(|&sample| contour::level(sample));
+    let first = escaped.next()?;
+    Some(escaped.fold((first, first), |(low, high), value| (low.min(value), high.max(value))))
+}
+
+/// A line through points of a field, in samples from the first, which goes
+/// back to its first point from its last when `closed`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Polyline {
+    pub points: Vec<(f64, f64)>,
+    pub closed: bool,
+}
+
+impl Polyline {
+    /// The line with its points in pixels of a frame `samples` to the side
+    /// of a pixel, from the corner of the frame.
+    pub fn in_pixels(&self, samples: u32) -> Polyline {
+        let samples = samples.max(1) as f64;
+        Polyline {
+            points: self
+                .points
+                .iter()
+                .map(|&(x, y)| ((x + 0.5) / samples, (y + 0.5) / samples))
+                .collect(),
+            closed: self.closed,
+        }
+    }
+
+    /// The line with the points it can do without to stay within
+    /// `tolerance` of where it was, by Douglas-Peucker. A closed line is
+    /// simplified as one from its first point around to it again.
+    pub fn simplified(&self, tolerance: f64) -> Polyline {
+        let points = match self.closed {
+            true => {
+                let mut around = self.points.clone();
+                around.push(self.points[0]);
+                let mut points = simplify(&around, tolerance);
+                points.pop();
+                points
+            }
+            false => simplify(&self.points, tolerance),
+        };
+        Polyline {
+            points,
+            closed: self.closed,
+        }
+    }
+
+    /// The line as the data of an SVG path, to a hundredth.
+    fn path_data(&self) -> String {
+        let mut data = String::new();
+        for (index, (x, y)) in self.points.iter().enumerate() {
+            let command = if index == 0 { 'M' } else { 'L' };
+            write!(data, "{}{}", command, number(*x)).expect("strings take writes");
+            write!(data, " {}", number(*y)).expect("strings take writes");
+        }
+        if self.closed {
+            data.push('Z');
+        }
+        data
+    }
+}
+
+/// The isolines of `field` at `level`, by marching squares: every square
+/// between four samples the level passes through gets a segment between
+/// the points where it crosses the sides, placed linearly between the
+/// samples, and the segments are joined into lines through the sides they
+/// share.
+///
+/// Where the samples at opposite corners of a square are on the same side
+/// of the level and the other two aren't, the middle of the square, the
+/// mean of the four, tells which of them the lines keep apart. Values at
+/// the level count as above it.
+pub fn trace(field: &Field, level: f64) -> Vec<Polyline> {
+    let (width, height) = (field.width as isize, field.height as isize);
+    // With a value outside, the squares reach a sample past every edge.
+    let margin = field.outside.is_some() as isize;
+    let (columns, rows) = (width - 1 + 2 * margin, height - 1 + 2 * margin);
+    if columns < 1 || rows < 1 {
+        return Vec::new();
+    }
+    // The sides of the squares, by the sample they go right or down from.
+    let key = |x: isize, y: isize, down: bool| {
+        ((y + margin) * (columns + 1) + x + margin) as usize * 2 + down as usize
+    };
+    let mut crossings: HashMap<usize, (f64, f64)> = HashMap::new();
+    let mut segments: Vec<[usize; 2]> = Vec::new();
+    for y in -margin..rows - margin {
+        for x in -margin..columns - margin {
+            let corners = [(x, y), (x + 1, y), (x + 1, y + 1), (x, y + 1)];
+            let values = corners.map(|(x, y)| field.at(x, y));
+            let above = values.map(|value| value >= level);
+            // The sides clockwise from the top, each from a corner to the
+            // next.
+            let sides =
+                [key(x, y, false), key(x + 1, y, true), key(x, y + 1, false), key(x, y, true)];
+            let crossed: Vec<usize> =
+                (0..4).filter(|&side| above[side] != above[(side + 1) % 4]).collect();
+            for &side in &crossed {
+                let (from, to) = (side, (side + 1) % 4);
+                let t = (level - values[from]) / (values[to] - values[from]);
+                let ((x0, y0), (x1, y1)) = (corners[from], corners[to]);
+                let point = (x0 as f64 + t * (x1 - x0) as f64, y0 as f64 + t * (y1 - y0) as f64);
+                crossings.entry(sides[side]).or_insert(point);
+            }
+            match crossed[..] {
+                [a, b] => segments.push([sides[a], sides[b]]),
+                [_, _, _, _] => {
+                    let middle = values.iter().sum::<f64>() / 4.0 >= level;
+                    // The corners on the other side from the middle are
+                    // cut off on their own.
+                    let pairs = match middle == above[0] {
+                        true => [[0, 1], [2, 3]],
+                        false => [[3, 0], [1, 2]],
+                    };
+                    segments.extend(pairs.map(|[a, b]| [sides[a], sides[b]]));
+                }
+                _ => {}
+            }
+        }
+    }
+    join(&segments)
+        .into_iter()
+        .map(|(sides, closed)| {
+            let points = sides.iter().map(|side| clamped(field, crossings[side])).collect();
+            Polyline { points, closed }
+        })
+        .collect()
+}
+
+/// The point of a line past the edges of `field` moved back onto them.
+fn clamped(field: &Field, (x, y): (f64, f64)) -> (f64, f64) {
+    let (width, height) = ((field.width - 1) as f64, (field.height - 1) as f64);
+    (x.clamp(0.0, width), y.clamp(0.0, height))
+}
+
+/// The chains of `segments` through the sides they share, and whether each
+/// comes back to where it started. A side is shared by two segments at
+/// most, so the chains that end do so at sides of one, and those are
+/// followed first.
+fn join(segments: &[[usize; 2]]) -> Vec<(Vec<usize>, bool)> {
+    let mut touching: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (index, segment) in segments.iter().enumerate() {
+        for side in segment {
+            touching.entry(*side).or_default().push(index);
+        }
+    }
+    let mut used = vec![false; segments.len()];
+    let follow = |start: usize, used: &mut Vec<bool>| {
+        let mut chain = vec![start];
+        let mut at = start;
+        while let Some(&next) = touching[&at].iter().find(|&&segment| !used[segment]) {
+            used[next] = true;
+            at = if segments[next][0] == at { segments[next][1] } else { segments[next][0] };
+            chain.push(at);
+        }
+        chain
+    };
+    let mut chains = Vec::new();
+    let mut ends: Vec<usize> = touching
+        .iter()
+        .filter(|(_, touching)| touching.len() == 1)
+        .map(|(side, _)| *side)
+        .collect();
+    // Lines in the order they're found in the field, whatever the hashing.
+    ends.sort_unstable();
+    for end in ends {
+        if !used[touching[&end][0]] {
+            chains.push((follow(end, &mut used), false));
+        }
+    }
+    for index in 0..segments.len() {
+        if !used[index] {
+            let mut chain = follow(segments[index][0], &mut used);
+            chain.pop();
+            chains.push((chain, true));
+        }
+    }
+    chains
+}
+
+/// The points of the line through `points` that Douglas-Peucker keeps for
+/// it to stay within `tolerance` of every point: the ends, and the point
+/// farthest from the line between them if it's farther than `tolerance`,
+/// with the same done to either side of it.
+pub fn simplify(points: &[(f64, f64)], tolerance: f64) -> Vec<(f64, f64)> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+    let mut kept = vec![false; points.len()];
+    let last = points.len() - 1;
+    (kept[0], kept[last]) = (true, true);
+    let mut spans = vec![(0, last)];
+    while let Some((first, last)) = spans.pop() {
+        let farthest = (first + 1..last)
+            .map(|index| (index, distance(points[index], points[first], points[last])))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((index, distance)) = farthest {
+            if distance > tolerance {
+                kept[index] = true;
+                spans.extend([(first, index), (index, last)]);
+            }
+        }
+    }
+    points.iter().zip(kept).filter(|(_, kept)| *kept).map(|(point, _)| *point).collect()
+}
+
+/// How far `point` is from the segment from `a` to `b`.
+fn distance(point: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length = dx * dx + dy * dy;
+    let t = match length > 0.0 {
+        true => (((point.0 - a.0) * dx + (point.1 - a.1) * dy) / length).clamp(0.0, 1.0),
+        false => 0.0,
+    };
+    (point.0 - a.0 - t * dx).hypot(point.1 - a.1 - t * dy)
+}
+
+/// A group of lines of an SVG drawing.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Layer {
+    /// The isolines at an escape time, stroked in a color `width` pixels
+    /// wide.
+    Level {
+        level: f64,
+        color: (u8, u8, u8),
+        width: f64,
+        lines: Vec<Polyline>,
+    },
+    /// The outline of the set, filled in a color, with the holes the
+    /// lines inside others cut out of it.
+    Set { color: (u8, u8, u8), lines: Vec<Polyline> },
+}
+
+/// An SVG drawing of `width` by `height` pixels of `layers`, whose lines
+/// are in pixels, each layer a group of paths drawn over those before it.
+pub fn to_svg(width: u32, height: u32, layers: &[Layer]) -> String {
+    let mut svg = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" \
+         viewBox=\"0 0 {w} {h}\">\n\
+         <!-- rustlebrot {} isolines -->\n",
+        env!("CARGO_PKG_VERSION"),
+        w = width,
+        h = height
+    );
+    let hex = |(r, g, b): (u8, u8, u8)| format!("#{:02x}{:02x}{:02x}", r, g, b);
+    for layer in layers {
+        let lines = match layer {
+            Layer::Level {
+                level,
+                color,
+                width,
+                lines,
+            } => {
+                writeln!(
+                    svg,
+                    "<g class=\"level\" data-level=\"{}\" fill=\"none\" stroke=\"{}\" \
+                     stroke-width=\"{}\" stroke-linejoin=\"round\" stroke-linecap=\"round\">",
+                    level,
+                    hex(*color),
+                    width
+                )
+                .expect("strings take writes");
+                lines.iter().map(Polyline::path_data).collect::<Vec<_>>()
+            }
+            // One path, so the lines inside others are holes in them.
+            Layer::Set { color, lines } => {
+                writeln!(svg, "<g class=\"set\" fill=\"{}\" stroke=\"none\">", hex(*color))
+                    .expect("strings take writes");
+                vec![lines.iter().map(Polyline::path_data).collect::<String>()]
+            }
+        };
+        let rule = match layer {
+            Layer::Set { .. } => " fill-rule=\"evenodd\"",
+            Layer::Level { .. } => "",
+        };
+        for data in lines.iter().filter(|data| !data.is_empty()) {
+            writeln!(svg, "<path{} d=\"{}\"/>", rule, data).expect("strings take writes");
+        }
+        svg.push_str("</g>\n");
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// `value` to a hundredth, without the zeros at the end.
+fn number(value: f64) -> String {
+    let rounded = format!("{:.2}", value);
+    let trimmed = rounded.trim_end_matches('0').trim_end_matches('.');
+    match trimmed {
+        "-0" => "0".to_string(),
+        trimmed => trimmed.to_string(),
+    }
+}
@@ -0,0 +1,151 @@
+use crate::fractal::{self, EscapeTimeFractal};
+use std::f64::consts::TAU;
+
+/// The filled Julia set of `z^2 + c` for one `c`, whose orbits start at the
+/// point of the plane instead of at 0.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Julia {
+    pub c: (f64, f64),
+}
+
+impl EscapeTimeFractal for Julia {
+    fn init(&self, point: (f64, f64)) -> (f64, f64) {
+        point
+    }
+
+    fn step(&self, z: (f64, f64), _point: (f64, f64)) -> (f64, f64) {
+        fractal::step::<f64, false>(z.into(), self.c.into()).into()
+    }
+
+    /// Orbits past the larger of 2 and `|c|` escape, as the first step
+    /// from there takes them further out.
+    fn bailout(&self) -> f64 {
+        self.c.0.hypot(self.c.1).max(2.0)
+    }
+
+    /// The set of a real `c` is mirrored across the real axis. Every set
+    /// is mirrored through the origin, which the renderer can't use.
+    fn symmetric(&self) -> bool {
+        self.c.1 == 0.0
+    }
+}
+
+/// The path the `c` of a Julia set takes over the frames of a run, as
+/// given with `--c-path`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CPath {
+    /// Around a circle counterclockwise, `turns` times, from the point
+    /// right of its center. The path ends where it starts, so the frames
+    /// loop.
+    Circle {
+        center: (f64, f64),
+        radius: f64,
+        turns: f64,
+    },
+    /// Through the keyframes in order, one after another at equal
+    /// intervals, in straight lines. A single keyframe holds `c` still.
+    Keyframes(Vec<(f64, f64)>),
+}
+
+impl CPath {
+    /// Parses `circle:center=C,radius=R[,turns=N]` or `keyframes:C,C,...`,
+    /// where every `C` is a complex number like `-0.1+0.65i`.
+    pub fn from_spec(spec: &str) -> Result<CPath, String> {
+        if let Some(params) = spec.strip_prefix("circle:") {
+            let (mut center, mut radius, mut turns) = (None, None, 1.0);
+            for param in params.split(',') {
+                let (key, value) = param.split_once('=').ok_or_else(|| {
+                    format!("circle takes center=C,radius=R[,turns=N], got '{}'", param)
+                })?;
+                let number = || {
+                    value.parse::<f64>().ok().filter(|value| value.is_finite()).ok_or_else(|| {
+                        format!("circle {} should be a number, got '{}'", key, value)
+                    })
+                };
+                match key {
+                    "center" => center = Some(parse_complex(value)?),
+                    "radius" => radius = Some(number()?),
+                    "turns" => turns = number()?,
+                    _ => return Err(format!("unknown circle parameter '{}'", key)),
+                }
+            }
+            let (Some(center), Some(radius)) = (center, radius) else {
+                return Err("circle needs a center and a radius, like \
+                            circle:center=-0.1+0.65i,radius=0.05"
+                    .to_string());
+            };
+            if radius <= 0.0 {
+                return Err(format!("circle radius should be positive, got {}", radius));
+            }
+            return Ok(CPath::Circle {
+                center,
+                radius,
+                turns,
+            });
+        }
+        if let Some(points) = spec.strip_prefix("keyframes:") {
+            let points = points.split(',').map(parse_complex).collect::<Result<_, _>>()?;
+            return Ok(CPath::Keyframes(points));
+        }
+        Err(format!(
+            "c-path should be circle:center=C,radius=R[,turns=N] or keyframes:C,C,..., got '{}'",
+            spec
+        ))
+    }
+
+    /// The `c` of frame `frame` of a run of `frames`, with the time eased
+    /// by `ease` over the whole circle or between every two keyframes.
+    ///
+    /// The frames of a circle stop one short of its start, which the next
+    /// frame of the loop would repeat. Those of keyframes end on the last.
+    pub fn at(&self, frame: u32, frames: u32, ease: impl Fn(f64) -> f64) -> (f64, f64) {
+        match self {
+            CPath::Circle {
+                center,
+                radius,
+                turns,
+            } => {
+                let u = frame as f64 / frames.max(1) as f64;
+                let angle = TAU * turns * ease(u);
+                (center.0 + radius * angle.cos(), center.1 + radius * angle.sin())
+            }
+            CPath::Keyframes(points) => {
+                let steps = frames.saturating_sub(1).max(1) as f64;
+                let t = (frame as f64 / steps).min(1.0) * (points.len() - 1) as f64;
+                let segment = (t.floor() as usize).min(points.len().saturating_sub(2));
+                let u = ease(t - segment as f64);
+                let (from, to) = (points[segment], points[(segment + 1).min(points.len() - 1)]);
+                (from.0 + (to.0 - from.0) * u, from.1 + (to.1 - from.1) * u)
+            }
+        }
+    }
+}
+
+/// Parses a complex number written like `-0.1+0.65i`, `0.3i` or `-1`.
+pub fn parse_complex(value: &str) -> Result<(f64, f64), String> {
+    let invalid = || format!("complex numbers are written like -0.1+0.65i, got '{}'", value);
+    let finite = |part: &str| part.parse::<f64>().ok().filter(|part| part.is_finite());
+    let Some(imaginary) = value.strip_suffix('i') else {
+        return finite(value).map(|re| (re, 0.0)).ok_or_else(invalid);
+    };
+    // The sign between the parts, past any sign of the real part or of an
+    // exponent.
+    let split = imaginary
+        .char_indices()
+        .skip(1)
+        .filter(|&(i, sign)| {
+            matches!(sign, '+' | '-') && !imaginary[..i].ends_with(['e', 'E'])
+        })
+        .map(|(i, _)| i)
+        .last();
+    let (re, im) = match split {
+        Some(i) => (finite(&imaginary[..i]), &imaginary[i..]),
+        None => (Some(0.0), imaginary),
+    };
+    let im = match im {
+        "" | "+" => Some(1.0),
+        "-" => Some(-1.0),
+        im => finite(im),
+    };
+    re.zip(im).ok_or_else(invalid)
+}
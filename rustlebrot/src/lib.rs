@@ -0,0 +1,173 @@
+//! The rendering core of rustlebrot: escape-time iteration in f32, f64,
+//! double-double, perturbation and arbitrary precision, coloring, and
+//! Buddhabrot sampling. None of it touches files or spawns processes, which
+//! is left to the command line program.
+
+pub mod bigfloat;
+pub mod budget;
+pub mod buddhabrot;
+pub mod coloring;
+pub mod complex;
+pub mod contour;
+pub mod dd;
+pub mod debug;
+pub mod decimal;
+pub mod difference;
+pub mod dimension;
+pub mod dither;
+pub mod error;
+pub mod expmap;
+pub mod formula;
+pub mod fractal;
+pub mod grid;
+pub mod histogram;
+pub mod interior;
+pub mod isoline;
+pub mod julia;
+pub mod lighting;
+pub mod lineart;
+pub mod location;
+pub mod lyapunov;
+pub mod mesh;
+pub mod mode;
+pub mod newton;
+pub mod nucleus;
+pub mod outputs;
+pub mod palette;
+pub mod perturbation;
+pub mod post;
+pub mod precision;
+pub mod preflight;
+pub mod preset;
+pub mod quality;
+pub mod ray;
+pub mod render;
+pub mod script;
+pub mod series;
+#[cfg(feature = "simd")]
+pub mod simd;
+pub mod stabilize;
+pub mod stats;
+#[cfg(feature = "stream")]
+pub mod stream;
+pub mod stripes;
+pub mod survey;
+pub mod target;
+pub mod template;
+pub mod throttle;
+pub mod trace;
+pub mod trap;
+pub mod view;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+use coloring::{Coloring, Phase, Transfer};
+use dither::Dither;
+use error::RustlebrotError;
+use fractal::Mandelbrot;
+use image::RgbImage;
+use palette::{Adjust, Colormap, Cycle, Palette};
+use render::{Alpha, BitDepth, ColorOptions, RenderOptions, Rotation, Subdivision, WorkUnits};
+
+/// Renders the Mandelbrot set around (`cx`, `cy`), `scale` apart between
+/// pixels, in smooth coloring and the default palette, and returns its
+/// pixels as RGBA, row by row from the top. Fails if the region has no
+/// pixels.
+pub fn render_region(
+    width: u32,
+    height: u32,
+    max_iter: u32,
+    cx: f64,
+    cy: f64,
+    scale: f64,
+) -> Result<Vec<u8>, RustlebrotError> {
+    RustlebrotError::check_size(width, height)?;
+    let (half_width, half_height) = (width as f64 * scale / 2.0, height as f64 * scale / 2.0);
+    let buffer = render::compute_escape(
+        &Mandelbrot,
+        width,
+        height,
+        (cx - half_width, cx + half_width),
+        (cy - half_height, cy + half_height),
+        &smooth_options(max_iter),
+    );
+    let (colormap, cycle) = default_colormap();
+    let colors = default_colors(&colormap, cycle, max_iter);
+    Ok(render::colorize(&buffer, &colors).to_rgba8().into_raw())
+}
+
+/// Renders the Mandelbrot set over `x_range` by `y_range` as the command
+/// line program colors a frame by default, escape times spread over
+/// `max_iter` in the default palette.
+///
+/// This is `render::compute_escape` followed by `render::colorize`, for
+/// callers that only want the image.
+pub fn render_mandelbrot(
+    width: u32,
+    height: u32,
+    max_iter: u32,
+    x_range: (f64, f64),
+    y_range: (f64, f64),
+) -> RgbImage {
+    let options = RenderOptions {
+        coloring: Coloring::EscapeTime,
+        ..smooth_options(max_iter)
+    };
+    let buffer = render::compute_escape(&Mandelbrot, width, height, x_range, y_range, &options);
+    let (colormap, cycle) = default_colormap();
+    let colors = default_colors(&colormap, cycle, max_iter);
+    render::colorize(&buffer, &colors).to_rgb8()
+}
+
+/// The options `render_region` computes with: smooth escape times in f64.
+fn smooth_options(max_iter: u32) -> RenderOptions<'static> {
+    RenderOptions {
+        max_iter,
+        periodicity: true,
+        bailout: 2.0,
+        coloring: Coloring::Smooth,
+        single_precision: false,
+        subdivision: Subdivision::Off,
+        work_units: WorkUnits::Auto,
+        reuse: None,
+        refine: None,
+        rotation: Rotation::NONE,
+        window: None,
+        timing: None,
+        attractors: false,
+    }
+}
+
+/// The command line program's default palette, and how it cycles.
+fn default_colormap() -> (Colormap, Cycle) {
+    let gradient = Palette::Sinebow.gradient();
+    let adjust = Adjust {
+        invert: true,
+        ..Adjust::default()
+    };
+    (Colormap::new(&gradient, &adjust), Cycle::new(&gradient, 4.0, 0.0, false))
+}
+
+/// The colors `render_region` gives escape times of up to `max_iter`, in
+/// `colormap`.
+fn default_colors(colormap: &Colormap, cycle: Cycle, max_iter: u32) -> ColorOptions<'_> {
+    ColorOptions {
+        palette_iter: max_iter,
+        histogram_clip: 0.0,
+        transfer: Transfer::Linear,
+        reference: None,
+        colormap,
+        cycle,
+        interior: (0, 0, 0),
+        bit_depth: BitDepth::Eight,
+        dither: Dither::None,
+        alpha: Alpha::None,
+        phase: Phase::default(),
+        lighting: None,
+        script: None,
+        interior_coloring: None,
+        contours: None,
+        silhouette: None,
+        line_art: None,
+    }
+}
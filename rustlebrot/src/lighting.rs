@@ -0,0 +1,179 @@
+use crate::render::{EscapeBuffer, Sample};
+use rayon::prelude::*;
+
+/// How sharp the specular highlight is, as the exponent of its Blinn-Phong
+/// term.
+const SHININESS: i32 = 32;
+
+/// Slope shading, as given with `--lighting`: a light shining onto the
+/// samples of a frame as if their values were the height of a surface.
+///
+/// The slope of every sample is taken from the differences to its
+/// neighbors, so it works for every coloring and precision without
+/// anything more from the iteration loop.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Lighting {
+    /// Where the light comes from, in degrees counterclockwise from the
+    /// right of the image.
+    pub angle: f64,
+    /// How high the light is over the image, in degrees from 0 at the
+    /// horizon to 90 straight above.
+    pub elevation: f64,
+    /// How much of a color the light takes away where it doesn't shine,
+    /// from 0 for none to 1 for all of it.
+    pub strength: f64,
+    /// How bright the highlight is where the surface reflects the light
+    /// straight up, or 0 for none.
+    pub specular: f64,
+    /// Degrees the light turns by every frame of a zoom.
+    pub spin: f64,
+}
+
+impl Default for Lighting {
+    fn default() -> Self {
+        Lighting {
+            angle: 45.0,
+            elevation: 45.0,
+            strength: 0.6,
+            specular: 0.0,
+            spin: 0.0,
+        }
+    }
+}
+
+/// How a sample is lit: the factor its color is scaled by, and the white
+/// of the highlight added on top.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Shade {
+    pub diffuse: f64,
+    pub specular: f64,
+}
+
+impl Shade {
+    /// The shade of samples the light doesn't touch.
+    pub const UNLIT: Shade = Shade {
+        diffuse: 1.0,
+        specular: 0.0,
+    };
+
+    /// `rgb` lit by this shade, with channels between 0 and 1.
+    #[inline]
+    pub fn apply(self, rgb: [f64; 3]) -> [f64; 3] {
+        rgb.map(|channel| (channel * self.diffuse + self.specular).min(1.0))
+    }
+}
+
+impl Lighting {
+    /// Parses a light such as `angle=45,elevation=60,strength=0.6`, or
+    /// `on` for the default one. Fields that aren't given keep their
+    /// defaults.
+    pub fn from_spec(spec: &str) -> Result<Lighting, String> {
+        let mut lighting = Lighting::default();
+        if spec == "on" {
+            return Ok(lighting);
+        }
+        for field in spec.split(',') {
+            let invalid = || format!("lighting takes fields like angle=45, got '{}'", field);
+            let (name, value) = field.split_once('=').ok_or_else(invalid)?;
+            let value: f64 = value.trim().parse().map_err(|_| invalid())?;
+            if !value.is_finite() {
+                return Err(invalid());
+            }
+            match name.trim() {
+                "angle" => lighting.angle = value,
+                "elevation" if (0.0..=90.0).contains(&value) => lighting.elevation = value,
+                "elevation" => {
+                    return Err(format!("lighting elevation should be from 0 to 90, got {}", value))
+                }
+                "strength" if (0.0..=1.0).contains(&value) => lighting.strength = value,
+                "strength" => {
+                    return Err(format!("lighting strength should be from 0 to 1, got {}", value))
+                }
+                "specular" if value >= 0.0 => lighting.specular = value,
+                "specular" => {
+                    return Err(format!("lighting specular should not be negative, got {}", value))
+                }
+                "spin" => lighting.spin = value,
+                other => {
+                    return Err(format!(
+                        "unknown lighting field '{}', expected angle, elevation, strength, \
+                         specular or spin",
+                        other
+                    ))
+                }
+            }
+        }
+        Ok(lighting)
+    }
+
+    /// The light of frame number `frame` of a zoom, turned by `spin` every
+    /// frame. It only depends on the frame number, as the palette drift
+    /// does.
+    pub fn at_frame(&self, frame: u32) -> Lighting {
+        Lighting {
+            angle: self.angle + self.spin * frame as f64,
+            ..*self
+        }
+    }
+
+    /// The shade of every sample of `buffer`, in the order of its values.
+    ///
+    /// A sample's height is its value, and its slope the differences to the
+    /// samples on either side, scaled to pixels so supersampling leaves the
+    /// relief as it is. Neighbors that have no value, past the edge or in
+    /// the interior, count as level with the sample. Interior and boundary
+    /// samples stay unlit.
+    pub fn shades(&self, buffer: &EscapeBuffer) -> Vec<Shade> {
+        let (width, height) = (buffer.width as usize, buffer.height as usize);
+        let scale = buffer.samples as f64;
+        let unit = match buffer.coloring {
+            crate::coloring::Coloring::Distance => scale,
+            _ => 1.0,
+        };
+        let level = |sample: Sample| match sample {
+            Sample::Value(value)
+            | Sample::Phase { value, .. }
+            | Sample::Root { iterations: value, .. }
+            | Sample::Exponent(value) => Some(value / unit),
+            Sample::Interior | Sample::Attractor { .. } | Sample::Boundary => None,
+        };
+        let (azimuth, elevation) = (self.angle.to_radians(), self.elevation.to_radians());
+        let light = (
+            elevation.cos() * azimuth.cos(),
+            elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+        );
+        let half = normalize((light.0, light.1, light.2 + 1.0));
+        (0..width * height)
+            .into_par_iter()
+            .map(|index| {
+                let Some(here) = level(buffer.values[index]) else {
+                    return Shade::UNLIT;
+                };
+                let (x, y) = (index % width, index / width);
+                let at = |x: usize, y: usize| level(buffer.values[y * width + x]).unwrap_or(here);
+                let left = if x > 0 { at(x - 1, y) } else { here };
+                let right = if x + 1 < width { at(x + 1, y) } else { here };
+                let up = if y > 0 { at(x, y - 1) } else { here };
+                let down = if y + 1 < height { at(x, y + 1) } else { here };
+                // Rows run down the image, and the light's angle up it.
+                let slope = ((right - left) / 2.0 * scale, (up - down) / 2.0 * scale);
+                let normal = normalize((-slope.0, -slope.1, 1.0));
+                let lambert = dot(normal, light).max(0.0);
+                Shade {
+                    diffuse: 1.0 - self.strength + self.strength * lambert,
+                    specular: self.specular * dot(normal, half).max(0.0).powi(SHININESS),
+                }
+            })
+            .collect()
+    }
+}
+
+fn dot(a: (f64, f64, f64), b: (f64, f64, f64)) -> f64 {
+    a.0 * b.0 + a.1 * b.1 + a.2 * b.2
+}
+
+fn normalize(v: (f64, f64, f64)) -> (f64, f64, f64) {
+    let length = dot(v, v).sqrt();
+    (v.0 / length, v.1 / length, v.2 / length)
+}
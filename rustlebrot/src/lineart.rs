@@ -0,0 +1,228 @@
+use crate::contour;
+use crate::render::{EscapeBuffer, Sample};
+use rayon::prelude::*;
+
+/// What line art is drawn on, as chosen with `--line-paper`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Paper {
+    /// Black lines on white.
+    White,
+    /// White lines on nothing, in frames with an alpha channel.
+    Transparent,
+}
+
+impl Paper {
+    pub fn from_name(name: &str) -> Option<Paper> {
+        match name {
+            "white" => Some(Paper::White),
+            "transparent" => Some(Paper::Transparent),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Paper::White => "white",
+            Paper::Transparent => "transparent",
+        }
+    }
+}
+
+/// Line art, as `--style lineart` draws it in place of the coloring: the
+/// edges of the escape field, where it changes steeply, inked on plain
+/// paper.
+///
+/// The field is the logarithm of the values of the samples outside the set,
+/// so an edge is as steep at a thousand iterations as at ten, and the edges
+/// are found with a Sobel filter. Everything is worked out from the values
+/// of the samples, so any frame of any zoom can be drawn this way, and a
+/// run recolored into it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LineArt {
+    /// How steep the field has to be for a sample to be on a line, as the
+    /// change of its logarithm across a pixel.
+    pub threshold: f64,
+    /// Pixels the lines are widened by on either side.
+    pub weight: u32,
+    /// Whether the outline of the set is inked too.
+    pub silhouette: bool,
+    /// Whether the edges between the components of the interior are inked,
+    /// where its samples carry the cycles they settle on.
+    pub interior: bool,
+    /// Whether the lines are thinned down to a sample wide before they're
+    /// widened by `weight`.
+    pub thin: bool,
+    pub paper: Paper,
+}
+
+impl Default for LineArt {
+    fn default() -> Self {
+        LineArt {
+            threshold: 0.1,
+            weight: 0,
+            silhouette: false,
+            interior: false,
+            thin: false,
+            paper: Paper::White,
+        }
+    }
+}
+
+impl LineArt {
+    /// Whether every sample of `buffer` is inked, in the order of its
+    /// values.
+    pub fn ink(&self, buffer: &EscapeBuffer) -> Vec<bool> {
+        let (width, height) = (buffer.width as usize, buffer.height as usize);
+        let field: Vec<Option<f64>> = buffer
+            .values
+            .iter()
+            .map(|&sample| contour::level(sample).map(|value| value.max(0.0).ln_1p()))
+            .collect();
+        // Across a pixel rather than a sample, so the lines are the same
+        // whatever the supersampling.
+        let threshold = self.threshold / buffer.samples as f64;
+        let mut ink: Vec<bool> = (0..width * height)
+            .into_par_iter()
+            .map(|index| {
+                let (x, y) = (index % width, index / width);
+                let Some(here) = field[index] else {
+                    return self.interior && interior_edge(buffer, x, y);
+                };
+                // Samples off the frame or in the set take the value here,
+                // which leaves the edge of the set to the silhouette.
+                let at = |dx: isize, dy: isize| {
+                    let x = x.checked_add_signed(dx).filter(|&x| x < width);
+                    let y = y.checked_add_signed(dy).filter(|&y| y < height);
+                    x.zip(y).and_then(|(x, y)| field[y * width + x]).unwrap_or(here)
+                };
+                let across = at(1, -1) + 2.0 * at(1, 0) + at(1, 1)
+                    - at(-1, -1)
+                    - 2.0 * at(-1, 0)
+                    - at(-1, 1);
+                let down = at(-1, 1) + 2.0 * at(0, 1) + at(1, 1)
+                    - at(-1, -1)
+                    - 2.0 * at(0, -1)
+                    - at(1, -1);
+                // The filter weighs in 8 samples' worth of differences
+                // across two samples.
+                let slope = across.hypot(down) / 8.0;
+                slope > threshold || (self.silhouette && outline(&field, width, height, x, y))
+            })
+            .collect();
+        if self.thin {
+            thin(&mut ink, width, height);
+        }
+        match self.weight {
+            0 => ink,
+            weight => widen(&ink, width, height, (weight * buffer.samples) as f64),
+        }
+    }
+
+    /// The color of a sample, with channels between 0 and 1, and its
+    /// opacity.
+    #[inline]
+    pub fn rgba(&self, inked: bool) -> [f64; 4] {
+        match (self.paper, inked) {
+            (Paper::White, true) => [0.0, 0.0, 0.0, 1.0],
+            (Paper::White, false) => [1.0, 1.0, 1.0, 1.0],
+            (Paper::Transparent, inked) => [1.0, 1.0, 1.0, inked as u8 as f64],
+        }
+    }
+}
+
+/// Whether the sample at `(x, y)`, outside the set, is next to one in it.
+fn outline(field: &[Option<f64>], width: usize, height: usize, x: usize, y: usize) -> bool {
+    let inside = |x: Option<usize>, y: Option<usize>| match (x, y) {
+        (Some(x), Some(y)) if x < width && y < height => field[y * width + x].is_none(),
+        _ => false,
+    };
+    let (left, up) = (x.checked_sub(1), y.checked_sub(1));
+    inside(left, Some(y)) || inside(Some(x + 1), Some(y)) || inside(Some(x), up)
+        || inside(Some(x), Some(y + 1))
+}
+
+/// Whether the sample at `(x, y)`, in the set, settled on another cycle
+/// than the one to its right or below. Only one side of an edge is inked,
+/// so it is a sample wide.
+fn interior_edge(buffer: &EscapeBuffer, x: usize, y: usize) -> bool {
+    let (width, height) = (buffer.width as usize, buffer.height as usize);
+    let period = |sample: Sample| match sample {
+        Sample::Attractor { period, .. } => Some(period),
+        _ => None,
+    };
+    let here = period(buffer.values[y * width + x]);
+    let differs = |x: usize, y: usize| {
+        let there = buffer.values[y * width + x];
+        contour::level(there).is_none() && period(there) != here
+    };
+    (x + 1 < width && differs(x + 1, y)) || (y + 1 < height && differs(x, y + 1))
+}
+
+/// Thins the lines of `ink` down to a sample wide, keeping them connected,
+/// by the method of Zhang and Suen: samples at the edges of the lines are
+/// peeled off, from the bottom right and the top left in turn, until none
+/// is left that a line doesn't need to stay in one piece.
+fn thin(ink: &mut [bool], width: usize, height: usize) {
+    loop {
+        let mut peeled = false;
+        for pass in 0..2 {
+            let peel: Vec<usize> = (0..width * height)
+                .into_par_iter()
+                .filter(|&index| ink[index] && peelable(ink, width, height, index, pass))
+                .collect();
+            for &index in &peel {
+                ink[index] = false;
+            }
+            peeled |= !peel.is_empty();
+        }
+        if !peeled {
+            return;
+        }
+    }
+}
+
+/// Whether the inked sample at `index` comes off in `pass` of `thin`.
+fn peelable(ink: &[bool], width: usize, height: usize, index: usize, pass: usize) -> bool {
+    let (x, y) = (index % width, index / width);
+    let at = |dx: isize, dy: isize| {
+        let x = x.checked_add_signed(dx).filter(|&x| x < width);
+        let y = y.checked_add_signed(dy).filter(|&y| y < height);
+        x.zip(y).is_some_and(|(x, y)| ink[y * width + x])
+    };
+    // The neighbors clockwise from the one above.
+    let around = [
+        at(0, -1),
+        at(1, -1),
+        at(1, 0),
+        at(1, 1),
+        at(0, 1),
+        at(-1, 1),
+        at(-1, 0),
+        at(-1, -1),
+    ];
+    let neighbors = around.iter().filter(|&&inked| inked).count();
+    let runs = (0..8).filter(|&i| !around[i] && around[(i + 1) % 8]).count();
+    let [up, _, right, _, down, _, left, _] = around;
+    let open = match pass {
+        0 => !(right && down && (up || left)),
+        _ => !(up && left && (right || down)),
+    };
+    (2..=6).contains(&neighbors) && runs == 1 && open
+}
+
+/// The samples within `reach` samples of an inked one of `ink`.
+fn widen(ink: &[bool], width: usize, height: usize, reach: f64) -> Vec<bool> {
+    let radius = reach.floor() as usize;
+    (0..width * height)
+        .into_par_iter()
+        .map(|index| {
+            let (x, y) = (index % width, index / width);
+            (y.saturating_sub(radius)..(y + radius + 1).min(height)).any(|other_y| {
+                (x.saturating_sub(radius)..(x + radius + 1).min(width)).any(|other_x| {
+                    let (dx, dy) = (other_x as f64 - x as f64, other_y as f64 - y as f64);
+                    ink[other_y * width + other_x] && dx.hypot(dy) <= reach
+                })
+            })
+        })
+        .collect()
+}
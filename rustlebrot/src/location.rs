@@ -0,0 +1,109 @@
+use crate::decimal::Decimal;
+use crate::fractal::FractalKind;
+use crate::newton::Newton;
+use crate::preset;
+
+/// A place in a fractal, as saved with `--save-location` and zoomed into
+/// with `--location`.
+///
+/// The center is kept as written, so a location read back is the one
+/// saved digit for digit. The magnification is relative to the first frame
+/// of a zoom without ranges, whose shorter side spans twice the fractal's
+/// default half width.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Location {
+    /// What to pick the location by in a file of several.
+    pub name: Option<String>,
+    pub notes: Option<String>,
+    pub fractal: FractalKind,
+    /// The polynomial of `--fractal newton`.
+    pub newton: Option<Newton>,
+    pub center: (String, String),
+    pub magnification: f64,
+    /// An iteration limit that resolves the location.
+    pub max_iter: u32,
+    /// How far the view is turned, in degrees counterclockwise.
+    pub rotation: f64,
+}
+
+impl Location {
+    /// Parses the text of a `.kfr` file of Kalles Fraktaler, with the keys
+    /// of it this doesn't use, in the order they came in. Malformed lines
+    /// are errors that name them by number.
+    ///
+    /// Only the center, the zoom and the iteration limit are taken from
+    /// it. Kalles Fraktaler shows a height of 4 at zoom 1, as the first
+    /// frame of a zoom into the Mandelbrot set does on its shorter side, so
+    /// the zoom is the magnification.
+    pub fn parse_kfr(text: &str) -> Result<(Location, Vec<String>), String> {
+        let (mut re, mut im, mut zoom, mut max_iter) = (None, None, None, None);
+        let mut ignored = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let invalid = |problem: String| format!("line {}: {}", number + 1, problem);
+            let (key, value) = line
+                .split_once(':')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| invalid(format!("expected 'Key: value', got '{}'", line)))?;
+            let value = value.to_string();
+            let twice = || invalid(format!("{} is given twice", key));
+            match key {
+                "Re" | "Im" => {
+                    let coordinate = Decimal::parse(&value).map_err(invalid)?;
+                    let slot = if key == "Re" { &mut re } else { &mut im };
+                    if slot.replace(coordinate.as_str().to_string()).is_some() {
+                        return Err(twice());
+                    }
+                }
+                "Zoom" => {
+                    let parsed = value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|zoom| *zoom > 0.0 && zoom.is_finite())
+                        .ok_or_else(|| {
+                            invalid(format!(
+                                "Zoom should be a positive number up to about 1e308, as \
+                                 magnifications are kept in f64, got '{}'",
+                                value
+                            ))
+                        })?;
+                    if zoom.replace(parsed).is_some() {
+                        return Err(twice());
+                    }
+                }
+                "Iterations" => {
+                    let parsed = value.parse::<u32>().ok().filter(|&limit| limit > 0);
+                    let parsed = parsed.ok_or_else(|| {
+                        invalid(format!("Iterations should be a positive integer, got '{}'", value))
+                    })?;
+                    if max_iter.replace(parsed).is_some() {
+                        return Err(twice());
+                    }
+                }
+                _ => ignored.push(key.to_string()),
+            }
+        }
+        let missing = |key: &str| format!("there is no {}: line", key);
+        let location = Location {
+            name: None,
+            notes: None,
+            fractal: FractalKind::Mandelbrot,
+            newton: None,
+            center: (re.ok_or_else(|| missing("Re"))?, im.ok_or_else(|| missing("Im"))?),
+            magnification: zoom.ok_or_else(|| missing("Zoom"))?,
+            max_iter: max_iter.ok_or_else(|| missing("Iterations"))?,
+            rotation: 0.0,
+        };
+        Ok((location, ignored))
+    }
+
+    /// The frames of a zoom by `zoom_factor` from the first frame to the
+    /// location, both included. A location no deeper than the first frame
+    /// is a single frame.
+    pub fn frames(&self, zoom_factor: f64) -> u32 {
+        preset::frames_to(self.magnification, zoom_factor)
+    }
+}
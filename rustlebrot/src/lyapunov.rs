@@ -0,0 +1,118 @@
+/// The sequence of `--fractal lyapunov` unless `--sequence` says otherwise.
+pub const DEFAULT_SEQUENCE: &str = "AB";
+
+/// Iterations of `--fractal lyapunov` before the exponent is measured,
+/// unless `--warmup` says otherwise.
+pub const DEFAULT_WARMUP: u32 = 100;
+
+/// Iterations the exponent of `--fractal lyapunov` is averaged over,
+/// unless `--samples` says otherwise.
+pub const DEFAULT_SAMPLES: u32 = 1000;
+
+/// The longest sequence `--sequence` takes.
+pub const MAX_SEQUENCE: usize = 256;
+
+/// Terms of the derivative multiplied together before their logarithm is
+/// taken. Every term is at most 4, so the product can't overflow, and
+/// taking one logarithm in place of this many is most of the speed.
+const LOG_EVERY: u32 = 16;
+
+/// A Markus-Lyapunov fractal, for rendering with `--fractal lyapunov`.
+///
+/// Every point `(a, b)` iterates the logistic map `x -> r x (1 - x)` from
+/// `x = 1/2`, with the growth rate `r` taking turns between `a` and `b` as
+/// the sequence says, and is colored by the Lyapunov exponent of the orbit:
+/// negative where it settles into a cycle, positive where it's chaotic. The
+/// orbit is real and never escapes, so it has a kernel of its own instead
+/// of implementing `Fractal`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lyapunov {
+    /// Which growth rate each iteration takes, `b` where true, repeated.
+    sequence: Vec<bool>,
+    warmup: u32,
+    samples: u32,
+}
+
+impl Default for Lyapunov {
+    fn default() -> Self {
+        Lyapunov::new(DEFAULT_SEQUENCE, DEFAULT_WARMUP, DEFAULT_SAMPLES)
+            .expect("the default sequence is valid")
+    }
+}
+
+impl Lyapunov {
+    /// The fractal of `sequence`, a string of `A` and `B` such as `AABAB`,
+    /// whose exponent is averaged over `samples` iterations after the
+    /// first `warmup`.
+    pub fn new(sequence: &str, warmup: u32, samples: u32) -> Result<Lyapunov, String> {
+        let parsed = sequence
+            .chars()
+            .map(|step| match step.to_ascii_uppercase() {
+                'A' => Some(false),
+                'B' => Some(true),
+                _ => None,
+            })
+            .collect::<Option<Vec<bool>>>()
+            .filter(|parsed| (1..=MAX_SEQUENCE).contains(&parsed.len()))
+            .ok_or_else(|| {
+                format!(
+                    "sequence should be 1 to {} of the letters A and B, like AABAB, got '{}'",
+                    MAX_SEQUENCE, sequence
+                )
+            })?;
+        if samples == 0 {
+            return Err("samples should be positive".to_string());
+        }
+        Ok(Lyapunov {
+            sequence: parsed,
+            warmup,
+            samples,
+        })
+    }
+
+    /// The sequence as the letters it was given in.
+    pub fn sequence(&self) -> String {
+        self.sequence.iter().map(|&b| if b { 'B' } else { 'A' }).collect()
+    }
+
+    pub fn warmup(&self) -> u32 {
+        self.warmup
+    }
+
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    /// The Lyapunov exponent of the orbit of `(a, b)`, the average of
+    /// `ln |r (1 - 2x)|` over the samples, or `None` if the orbit runs off
+    /// to infinity, as it does once a growth rate is past 4.
+    ///
+    /// A superstable orbit, which passes through `x = 1/2` exactly, has an
+    /// exponent of negative infinity.
+    pub fn exponent(&self, a: f64, b: f64) -> Option<f64> {
+        let rate = |i: u32| match self.sequence[i as usize % self.sequence.len()] {
+            false => a,
+            true => b,
+        };
+        let mut x = 0.5;
+        for i in 0..self.warmup {
+            let r = rate(i);
+            x = r * x * (1.0 - x);
+        }
+        let mut sum = 0.0;
+        let mut product = 1.0;
+        for i in self.warmup..self.warmup.saturating_add(self.samples) {
+            let r = rate(i);
+            product *= (r * (1.0 - 2.0 * x)).abs();
+            x = r * x * (1.0 - x);
+            if (i - self.warmup) % LOG_EVERY == LOG_EVERY - 1 {
+                sum += product.ln();
+                product = 1.0;
+            }
+        }
+        if !x.is_finite() {
+            return None;
+        }
+        Some((sum + product.ln()) / self.samples as f64)
+    }
+}
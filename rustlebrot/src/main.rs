@@ -1,266 +1,6108 @@
-use colorgrad::sinebow;
-use image::imageops::invert;
-use image::{ImageBuffer, Rgb};
+mod batch;
+mod bench;
+mod bookmarks;
+mod camera;
+#[cfg(feature = "daemon")]
+mod daemon;
+#[cfg(feature = "dashboard")]
+mod dashboard;
+mod cli;
+mod dzi;
+mod events;
+mod hud;
+mod export;
+mod inset;
+mod interrupt;
+mod manifest;
+mod montage;
+mod orbit;
+mod performance;
+mod progress;
+#[cfg(feature = "serve")]
+mod serve;
+#[cfg(feature = "bigfloat")]
+mod sheet;
+mod still;
+mod strips;
+mod terminal;
+mod verify;
+mod video;
+#[cfg(feature = "window")]
+mod window;
+
+use rustlebrot::{
+    bigfloat, buddhabrot, budget, coloring, contour, debug, decimal, difference, dimension, dither, error, expmap, formula, fractal, grid,
+    interior, isoline, julia, lighting, lineart, location, lyapunov, mesh, mode, newton, outputs, palette, perturbation, post, precision, preflight, preset, quality, ray, render, script, stabilize, stats,
+    template, throttle, trace, trap, view,
+};
+#[cfg(feature = "bigfloat")]
+use rustlebrot::{nucleus, survey, target};
+
+use buddhabrot::{render_buddhabrot, render_nebulabrot, BuddhabrotOptions, Sampler};
+use budget::{CostModel, Probe, TimeBudget};
+use camera::{Camera, CameraPath, Direction, Easing, FramePath, IterSchedule, MotionBlur, PanPlan};
+use cli::{ColorArgs, PaletteSource};
+use coloring::Coloring;
+use debug::{DebugChannels, Timing};
+use decimal::Decimal;
+use difference::Difference;
+use dimension::Dimension;
+use error::RustlebrotError;
+use events::Event;
+use export::{Export, Header, ImageFormat, Metadata};
+use expmap::{ExpMap, View};
+use location::Location;
+use formula::Formula;
+use fractal::{Fractal, FractalKind, Mandelbrot, Tricorn};
+use hud::Hud;
+use inset::JuliaInset;
+use julia::{CPath, Julia};
+use image::imageops::FilterType;
+use image::DynamicImage;
+use mesh::{Heightfield, Mesh};
+use manifest::{
+    BudgetAdjustment, BudgetRecord, CameraPathRecord, DimensionRecord, FrameRecord, Manifest,
+    ManifestWriter, QualityRecord, SequenceRecord, Settings, Shard, ShardRecord, Shutter,
+    StatsWriter, StoppedEarly, ViewRecord, MANIFEST_VERSION,
+};
+use lyapunov::Lyapunov;
+use mode::Mode;
+use newton::Newton;
+use outputs::Output;
+use performance::{FrameTimes, Stage};
+use perturbation::OrbitCache;
+use palette::{Colormap, Cycle, Palette};
+use post::Chain;
+use precision::{Precision, WARN_ULPS};
+use preflight::{Estimate, Footprint, Times};
+use preset::PRESETS;
+use progress::Progress;
+use ray::Ray;
+use script::Orbit;
 use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use render::{
+    colorize, colorize_blurred, compute_basins, compute_escape, compute_escape_big, compute_escape_dd,
+    compute_escape_perturbed, compute_lyapunov, Adaptive, Alpha, ColorOptions, BitDepth, EscapeBuffer, Incremental,
+    Refine, RenderOptions, Reuse, Roi, Rotation, Sample, Scripted, Shift,
+};
+use stabilize::Reference;
+use stats::{EarlyStop, FrameStats};
+use std::collections::{BTreeMap, BTreeSet};
 use std::env;
+use std::ops::Range;
 use std::path::Path;
-use std::process::Command;
-use std::time::Instant;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+use dzi::Dzi;
+use still::Still;
+use strips::Strips;
+#[cfg(feature = "bigfloat")]
+use survey::SurveyOptions;
+#[cfg(feature = "bigfloat")]
+use target::TargetOptions;
+use template::{FilenameTemplate, FrameName};
+use terminal::{Protocol, TermPreview};
+use verify::Fault;
+use video::{Encoder, EncoderKind, Order, Sequence};
+
+/// The most samples along each side of a pixel `--time-budget` picks, as
+/// many as `--supersample` takes.
+const MAX_BUDGET_SUPERSAMPLE: u32 = 4;
+
+/// The passes of `--preview-progressive` before a frame, as every this many
+/// pixels along both axes.
+const PREVIEW_STRIDES: [u32; 3] = [8, 4, 2];
+
+/// The parameters shared by every frame of a zoom.
+struct Zoom<'a> {
+    fractal: FractalKind,
+    /// The polynomial of a zoom of `FractalKind::Newton`.
+    newton: Option<Newton>,
+    /// The formula of a zoom of `FractalKind::Formula`.
+    formula: Option<Formula>,
+    /// The sequence and iterations of a zoom of `FractalKind::Lyapunov`.
+    lyapunov: Option<Lyapunov>,
+    /// The path `c` takes over `frames` in a zoom of `FractalKind::Julia`,
+    /// and how it speeds up and slows down along it.
+    c_path: Option<CPath>,
+    c_easing: Easing,
+    width: u32,
+    height: u32,
+    /// The region of every frame computed, in place of all of it.
+    roi: Option<Roi>,
+    /// The sizes of `--outputs`, the first the one the frames are rendered
+    /// at and the others made from them as they're saved.
+    outputs: Vec<Output>,
+    /// The view at magnification 1, whose extents every frame's are scaled
+    /// from.
+    x_range_initial: (f64, f64),
+    y_range_initial: (f64, f64),
+    /// Where the camera is in every frame.
+    path: CameraPath,
+    /// With a pan, how far every frame is from the first, which the path
+    /// has the centers of.
+    pan: Option<PanPlan>,
+    /// How the zoom speeds up and slows down over `frames`, the frames of
+    /// the run as asked for.
+    easing: Easing,
+    frames: Range<u32>,
+    /// With `Direction::Out`, the camera goes over `frames` backwards.
+    direction: Direction,
+    /// The turn of the first frame and how much every frame adds to it, in
+    /// degrees.
+    initial_rotation: f64,
+    rotation_per_frame: f64,
+    zoom_factor: f64,
+    precision: Precision,
+    series_terms: usize,
+    mode: Mode,
+    options: RenderOptions<'a>,
+    colors: ColorOptions<'a>,
+    /// The files written for every frame.
+    export: Export,
+    /// The format the colored frames are saved in.
+    image_format: ImageFormat,
+    /// Pipe the frames to the video with their alpha channel, which is
+    /// dropped otherwise.
+    video_alpha: bool,
+    /// Also write every frame's samples as a `.npy` array.
+    dump_iterations: bool,
+    /// Print the statistics of every frame after it.
+    frame_stats: bool,
+    /// Estimate the dimension of the boundary in every frame.
+    measure_dimension: bool,
+    /// The diagnostic images written beside every frame.
+    debug_channels: DebugChannels,
+    /// When the zoom ends once its frames turn uniform. Only frames of
+    /// escape times can tell.
+    early_stop: Option<EarlyStop>,
+    buddhabrot: BuddhabrotOptions,
+    /// How fast max_iter grows with magnification, if it does.
+    auto_iter: Option<f64>,
+    /// The iteration limit of every frame, in place of the one in `options`
+    /// and auto-iter.
+    iter_schedule: Option<IterSchedule>,
+    /// With `--time-budget`, how far the iteration limits are scaled to fit
+    /// it, as the run goes.
+    time_budget: Option<Mutex<TimeBudget>>,
+    /// Palette cycles the coloring phase advances by every frame.
+    palette_drift: f64,
+    /// With `--stabilize-colors`, how far each frame moves the reference
+    /// the frames are colored by.
+    stabilize_colors: Option<f64>,
+    /// The palettes every frame is colored with, the first of which
+    /// `colors` holds.
+    palettes: Vec<PaletteSet<'a>>,
+    /// With `--pipe-video`, how often a frame is still saved as a PNG.
+    preview_every: Option<u32>,
+    /// Where every frame is previewed in passes as it renders.
+    preview_progressive: Option<&'a str>,
+    /// How often frames are drawn on the terminal, and how, once they're
+    /// found to be showable there.
+    term_preview: Option<TermPreview>,
+    /// Where frames are sent to be shown on `--dashboard`, if it's served.
+    dashboard: Option<SyncSender<(u32, DynamicImage)>>,
+    /// The filters every colored frame goes through, if any.
+    post: Option<Chain>,
+    /// The overlay written on every frame, if any.
+    hud: Option<Hud>,
+    /// The Julia set of the center drawn in a corner of every frame, if
+    /// it is.
+    julia_inset: Option<JuliaInset>,
+    /// The external rays drawn on every frame, traced as far in as the
+    /// frames so far have needed.
+    rays: Vec<Mutex<Ray>>,
+    /// The directory the frames are written to.
+    output_dir: &'a str,
+    /// Where in `output_dir`, or the directory of a palette.
+    filenames: &'a FilenameTemplate,
+    /// Whether frames are taken from the frame before where they can be.
+    incremental: Option<Incremental>,
+    /// The sub-frames every frame is averaged from, if it is.
+    motion_blur: Option<MotionBlur>,
+    /// Samples along each side of a pixel, see `EscapeBuffer::samples`.
+    supersample: u32,
+    /// Whether row 0 is at the bottom of the view instead of the top.
+    flip_y: bool,
+    /// Whether the pixels that stand out from their neighbors are
+    /// anti-aliased, see `render::refine`.
+    adaptive: Option<Adaptive>,
+    /// The last reference orbit computed for perturbation frames.
+    orbits: OrbitCache,
+    /// With `--expmap`, the strips every frame is resampled from in place
+    /// of rendering it.
+    expmap: Option<Strips>,
+    /// The file of `--color-expr`, which the pixels its script fails on are
+    /// reported against.
+    color_expr: Option<&'a str>,
+    /// With `render-frame`, the frame rendered again and the iteration
+    /// limit its run rendered it with, which it keeps whatever the camera
+    /// path and time budget would give.
+    recorded_limit: Option<(u32, u32)>,
+}
+
+/// One of the palettes the frames of a run are colored with, and where
+/// they go.
+struct PaletteSet<'a> {
+    /// The name of the palette, for the frame metadata.
+    name: &'a str,
+    colormap: &'a Colormap,
+    cycle: Cycle,
+    /// The directory the PNG frames in this palette are written to, a
+    /// directory of the output directory named after the palette when a run
+    /// has several.
+    dir: String,
+}
+
+impl<'a> Zoom<'a> {
+    /// The path of the file of `frame` with extension `ext`, in the
+    /// directory of `set`. Files that aren't colored go in the output
+    /// directory, as in the first palette.
+    fn frame_path(&self, set: Option<&PaletteSet>, frame: u32, ext: &str) -> String {
+        let (dir, palette) = match set {
+            Some(set) => (set.dir.as_str(), set.name),
+            None => (self.output_dir, self.palettes[0].name),
+        };
+        let name = FrameName {
+            frame,
+            magnification: self.camera(frame).magnification,
+            palette,
+            ext,
+        };
+        frame_file(dir, self.filenames, &name)
+    }
+
+    /// The path of the colored image of `frame` at `output`, one of
+    /// `outputs`, in a directory of the output directory named after it.
+    fn output_path(&self, output: &Output, frame: u32) -> String {
+        let name = FrameName {
+            frame,
+            magnification: self.camera(frame).magnification,
+            palette: self.palettes[0].name,
+            ext: self.image_format.extension(),
+        };
+        frame_file(&format!("{}/{}", self.output_dir, output.name()), self.filenames, &name)
+    }
+
+    /// Where the camera is in `frame`.
+    fn camera(&self, frame: u32) -> Camera {
+        self.camera_at(frame, frame as f64)
+    }
+
+    /// Where the camera is at `time`, in frames, which motion blur puts
+    /// around `frame`. It keeps the iteration limit of `frame`, so the
+    /// sub-frames of a frame only differ in their view.
+    fn camera_at(&self, frame: u32, time: f64) -> Camera {
+        let at = |time| self.path.at(self.position(time), |m| self.budget(m));
+        let mut camera = at(time);
+        if time != frame as f64 {
+            camera.max_iter = at(frame as f64).max_iter;
+        }
+        if let Some(schedule) = &self.iter_schedule {
+            camera.max_iter = schedule.at(frame);
+        }
+        if let Some(budget) = &self.time_budget {
+            camera.max_iter = budget.lock().unwrap().max_iter(camera.max_iter);
+        }
+        if let Some((_, max_iter)) = self.recorded_limit.filter(|(recorded, _)| *recorded == frame) {
+            camera.max_iter = max_iter;
+        }
+        camera
+    }
+
+    /// The point along the camera path the frame at `time` is at, which
+    /// with easing is somewhere between the frames of the run before or
+    /// after it.
+    ///
+    /// Going out, the frames are eased as they're shown, from the last
+    /// point to the first.
+    fn position(&self, time: f64) -> f64 {
+        let (first, last) = (self.frames.start as f64, self.frames.end as f64 - 1.0);
+        let u = (time - first) / (last - first);
+        let position = match self.easing {
+            Easing::Linear => time,
+            _ if !(0.0..=1.0).contains(&u) => time,
+            easing => first + easing.apply(u) * (last - first),
+        };
+        match self.direction {
+            Direction::Out => first + last - position,
+            Direction::In | Direction::InOut => position,
+        }
+    }
+
+    /// Which way the camera moves at `frame`, `in` unless the frame after
+    /// it, or for the last the one before, is less magnified.
+    fn direction(&self, frame: u32) -> &'static str {
+        let (from, to) = match frame + 1 < self.frames.end {
+            true => (frame, frame + 1),
+            false => (frame.saturating_sub(1), frame),
+        };
+        match self.camera(to).magnification < self.camera(from).magnification {
+            true => "out",
+            false => "in",
+        }
+    }
+
+    /// What `frame` is rendered from.
+    ///
+    /// This only depends on the parameters of the zoom, so every frame of a
+    /// run can be planned ahead, as `--dry-run` does.
+    fn plan(&self, frame: u32) -> FramePlan {
+        self.plan_at(frame, frame as f64)
+    }
+
+    /// What the sub-frame of `frame` at `time`, in frames, is rendered
+    /// from, see `camera_at`.
+    fn plan_at(&self, frame: u32, time: f64) -> FramePlan {
+        let camera = self.camera_at(frame, time);
+        let (x_center, y_center) = camera.approx_center();
+        let range_widths = (
+            (self.x_range_initial.1 - self.x_range_initial.0) / camera.magnification,
+            (self.y_range_initial.1 - self.y_range_initial.0) / camera.magnification,
+        );
+        let (x_range_width, y_range_width) = range_widths;
+        let size = |samples: u32| {
+            let (width, height) = (self.width * samples, self.height * samples);
+            (x_range_width / width as f64).min(y_range_width / height as f64)
+        };
+        let sample_size = size(self.supersample);
+        let precision =
+            self.resolve_precision(camera.approx_center(), sample_size, camera.max_iter);
+        // Auto precision moves on from f32, f64 and double-double before
+        // they run out, so frames rendered in them count as spanning any
+        // number of ulps. Without the bigfloat feature there is nothing to
+        // move on to from double-double.
+        let moves_on = self.precision == Precision::Auto && self.mode == Mode::Escape;
+        let deeper = matches!(precision, Precision::F32 | Precision::F64)
+            || (precision == Precision::DoubleDouble && cfg!(feature = "bigfloat"));
+        let ulps = match moves_on && deeper {
+            true => f64::INFINITY,
+            false => sample_size / precision.resolution(camera.approx_center()),
+        };
+        FramePlan {
+            frame,
+            size: (self.width, self.height),
+            samples: self.supersample,
+            x_range: (x_center - x_range_width / 2.0, x_center + x_range_width / 2.0),
+            y_range: (y_center - y_range_width / 2.0, y_center + y_range_width / 2.0),
+            range_widths,
+            pixel_size: size(1),
+            sample_size,
+            rotation: self.rotation(time),
+            precision,
+            ulps,
+            camera,
+        }
+    }
+
+    /// The iteration limit at `magnification`, unless keyframes say
+    /// otherwise.
+    ///
+    /// With auto-iter this is `max_iter * (1 + k * log10(magnification))`, so
+    /// every tenfold zoom adds another `k` times the base budget.
+    fn budget(&self, magnification: f64) -> u32 {
+        let base = self.options.max_iter;
+        match self.auto_iter {
+            Some(k) => (base as f64 * (1.0 + k * magnification.log10().max(0.0))).round() as u32,
+            None => base,
+        }
+    }
+
+    /// The per-pixel settings of the frame `plan` is for.
+    fn frame_options(&self, plan: &FramePlan) -> RenderOptions<'a> {
+        RenderOptions {
+            max_iter: plan.camera.max_iter,
+            rotation: Rotation::degrees(plan.rotation),
+            window: self.roi.map(|roi| roi.window(plan.size.0, plan.size.1, plan.samples)),
+            ..self.options
+        }
+    }
+
+    /// The samples computed of the frame `plan` is for, across and down:
+    /// those of the whole frame, or of the region of `--roi`.
+    fn computed_size(&self, plan: &FramePlan) -> (u32, u32) {
+        let (width, height) = self.roi.map_or(plan.size, |roi| (roi.width, roi.height));
+        (width * plan.samples, height * plan.samples)
+    }
+
+    /// The size of the frames written, which is that of the region of
+    /// `--roi` unless it's filled in to the whole frame.
+    fn frame_size(&self) -> (u32, u32) {
+        match self.roi.filter(|roi| !roi.fill) {
+            Some(roi) => (roi.width, roi.height),
+            None => (self.width, self.height),
+        }
+    }
+
+    /// A colored frame as it's written: the region of `--roi-fill` in
+    /// place in a frame of full size, or as it is.
+    fn placed(&self, img: DynamicImage) -> DynamicImage {
+        match self.roi.filter(|roi| roi.fill) {
+            Some(roi) => render::fill_around(&img, &roi, self.width, self.height),
+            None => img,
+        }
+    }
+
+    /// A colored frame through the filters of `--post`, if it was given.
+    fn posted(&self, img: DynamicImage) -> DynamicImage {
+        match &self.post {
+            Some(chain) => chain.apply(&img),
+            None => img,
+        }
+    }
+
+    /// The angle the frame at `time` is turned by, in degrees
+    /// counterclockwise, as the row of a camera path gives it or as it
+    /// turns on from the first frame.
+    fn rotation(&self, time: f64) -> f64 {
+        let turning = || self.initial_rotation + self.rotation_per_frame * time;
+        self.path.rotation(time).unwrap_or_else(turning)
+    }
+
+    /// What the sub-frames of the frame `plan` is for are rendered from,
+    /// in the order they're taken, or `plan` alone without motion blur.
+    fn exposures(&self, plan: &FramePlan) -> Vec<FramePlan> {
+        match self.motion_blur {
+            Some(blur) => {
+                blur.times(plan.frame).map(|time| self.plan_at(plan.frame, time)).collect()
+            }
+            None => vec![plan.clone()],
+        }
+    }
+
+    /// The views every frame is rendered from, 1 unless it is motion
+    /// blurred.
+    fn sub_frames(&self) -> u32 {
+        self.motion_blur.map_or(1, |blur| blur.sub_frames)
+    }
+
+    /// The color settings of `frame` in the palette of `set`, which only
+    /// differ in their palette cycling and the angle of the light, and in
+    /// palette_iter with an iteration schedule.
+    ///
+    /// Positions are relative to palette_iter rather than max_iter, so the
+    /// drift stays smooth when auto-iter raises the limit. A schedule sets
+    /// the limit by hand, so the palette is spread over each frame's own.
+    fn frame_colors(&self, frame: u32, set: &PaletteSet<'a>) -> ColorOptions<'a> {
+        ColorOptions {
+            palette_iter: match &self.iter_schedule {
+                Some(schedule) => schedule.at(frame),
+                None => self.colors.palette_iter,
+            },
+            colormap: set.colormap,
+            cycle: set.cycle.at_frame(self.palette_drift, frame),
+            lighting: self.colors.lighting.map(|lighting| lighting.at_frame(frame)),
+            script: self.colors.script.map(|script| Scripted {
+                frame,
+                magnification: self.camera(frame).magnification,
+                ..script
+            }),
+            ..self.colors
+        }
+    }
+
+    /// `buffers` colored with `colors` as `render::colorize_blurred` does,
+    /// with the pixel a script of `--color-expr` fails on reported against
+    /// its file.
+    fn colorize(
+        &self,
+        buffers: &[&EscapeBuffer],
+        colors: &ColorOptions,
+    ) -> Result<DynamicImage, RustlebrotError> {
+        colorize_blurred(buffers, colors).map_err(|failure| {
+            RustlebrotError::format(self.color_expr.unwrap_or("--color-expr"), failure)
+        })
+    }
+
+    /// The Julia set of `frame` of a zoom of `FractalKind::Julia`.
+    fn julia(&self, frame: u32) -> Option<Julia> {
+        let path = self.c_path.as_ref()?;
+        let frames = self.frames.len() as u32;
+        let frame = frame.saturating_sub(self.frames.start);
+        Some(Julia {
+            c: path.at(frame, frames, |u| self.c_easing.apply(u)),
+        })
+    }
+
+    /// Whether `frame` is computed in full with `--incremental`.
+    fn is_keyframe(&self, frame: u32) -> bool {
+        self.incremental.is_some_and(|incremental| frame.is_multiple_of(incremental.keyframe_every))
+    }
+
+    /// The precision frames around `center` with the given pixel size and
+    /// iteration limit are rendered at.
+    fn resolve_precision(&self, center: (f64, f64), pixel_size: f64, max_iter: u32) -> Precision {
+        // Newton's method, formulas, Julia sets and Lyapunov exponents only
+        // have f64 kernels.
+        if matches!(
+            self.fractal,
+            FractalKind::Newton | FractalKind::Formula | FractalKind::Julia | FractalKind::Lyapunov
+        ) {
+            return Precision::F64;
+        }
+        match self.mode {
+            Mode::Escape => {
+                let precision = self.precision.resolve(center, pixel_size);
+                // The f32 loop counts iterations in f32, which is exact up
+                // to 2^24, and has no distance estimates or traps. Nor has
+                // the double-double loop, which perturbation takes over
+                // from, or f64 without the bigfloat feature.
+                let escape_times = self.options.coloring.uses_escape_times();
+                match precision {
+                    Precision::F32 if max_iter > 1 << 24 || !escape_times => Precision::F64,
+                    Precision::DoubleDouble if !escape_times => match cfg!(feature = "bigfloat") {
+                        true => Precision::Perturbation,
+                        false => Precision::F64,
+                    },
+                    precision => precision,
+                }
+            }
+            // Buddhabrot orbits are plotted as f64 points, so deeper
+            // precisions have nothing to offer.
+            Mode::Buddhabrot | Mode::Nebulabrot => Precision::F64,
+        }
+    }
+}
+
+/// The parameters of one frame of a zoom, see `Zoom::plan`.
+#[derive(Clone)]
+struct FramePlan {
+    frame: u32,
+    camera: Camera,
+    /// The width and height of the frame in pixels, and the samples along
+    /// each side of a pixel, which the zoom's unless the frame is rendered
+    /// smaller.
+    size: (u32, u32),
+    samples: u32,
+    /// The ranges shown, as far as f64 can tell.
+    x_range: (f64, f64),
+    y_range: (f64, f64),
+    /// The widths of the ranges, which stay accurate where the ranges run
+    /// out of digits.
+    range_widths: (f64, f64),
+    /// The width of a pixel, along the smaller axis.
+    pixel_size: f64,
+    /// The distance between neighboring samples, which is `pixel_size`
+    /// unless the frame is supersampled.
+    sample_size: f64,
+    /// The turn of the frame, in degrees counterclockwise.
+    rotation: f64,
+    precision: Precision,
+    /// How many steps of `Precision::resolution` a sample spans. Under 1,
+    /// neighboring samples land on the same point.
+    ulps: f64,
+}
+
+impl FramePlan {
+    /// The plan of the same view at `width` by `height` pixels, of a
+    /// sample each. Precision and the bits of the center stay those of the
+    /// frame.
+    fn resized(self, width: u32, height: u32) -> FramePlan {
+        FramePlan {
+            size: (width, height),
+            samples: 1,
+            ..self
+        }
+    }
+}
+
+/// How a frame was rendered, for the frame log.
+struct FrameInfo {
+    precision: Precision,
+    max_iter: u32,
+    /// Bits of precision the center needs at this depth.
+    bits: usize,
+    /// Iterations per pixel skipped by series approximation.
+    skipped: usize,
+}
+
+/// What rendering a frame produced.
+enum Rendered {
+    /// An escape buffer, still to be colorized or exported.
+    Escape(EscapeBuffer),
+    /// A finished Buddhabrot or Nebulabrot image.
+    Image(DynamicImage),
+}
+
+/// Renders one frame of `zoom` like `render_view`, of the fractal the zoom
+/// is of.
+fn render_fractal(
+    zoom: &Zoom,
+    plan: &FramePlan,
+    coloring: Coloring,
+    reuse: Option<Reuse>,
+    refine: Option<Refine>,
+    timing: Option<&Timing>,
+) -> (Rendered, FrameInfo) {
+    match (zoom.fractal, &zoom.newton) {
+        (FractalKind::Mandelbrot, _) => {
+            render_view(&Mandelbrot, zoom, plan, coloring, reuse, refine, timing)
+        }
+        (FractalKind::Tricorn, _) => {
+            render_view(&Tricorn, zoom, plan, coloring, reuse, refine, timing)
+        }
+        (FractalKind::Newton, Some(newton)) => render_basins(newton, zoom, plan, refine, timing),
+        (FractalKind::Newton, None) => unreachable!("newton zooms are given a polynomial"),
+        (FractalKind::Formula, _) => match &zoom.formula {
+            Some(formula) => render_view(formula, zoom, plan, coloring, reuse, refine, timing),
+            None => unreachable!("formula zooms are given a formula"),
+        },
+        (FractalKind::Julia, _) => match zoom.julia(plan.frame) {
+            Some(julia) => render_view(&julia, zoom, plan, coloring, reuse, refine, timing),
+            None => unreachable!("julia zooms are given a c path"),
+        },
+        (FractalKind::Lyapunov, _) => match &zoom.lyapunov {
+            Some(lyapunov) => render_exponents(lyapunov, zoom, plan, refine, timing),
+            None => unreachable!("lyapunov zooms are given a sequence"),
+        },
+    }
+}
+
+/// Renders one frame of the basins of `newton`, or with `refine` one pass
+/// of refining it. Newton's method only has an f64 kernel, so every frame
+/// is in f64.
+fn render_basins(
+    newton: &Newton,
+    zoom: &Zoom,
+    plan: &FramePlan,
+    refine: Option<Refine>,
+    timing: Option<&Timing>,
+) -> (Rendered, FrameInfo) {
+    let options = RenderOptions {
+        refine,
+        timing,
+        ..zoom.frame_options(plan)
+    };
+    let samples = plan.samples;
+    let (width, height) = zoom.computed_size(plan);
+    let y_range = match zoom.flip_y {
+        true => (plan.y_range.1, plan.y_range.0),
+        false => plan.y_range,
+    };
+    let buffer = compute_basins(newton, width, height, plan.x_range, y_range, &options);
+    let info = FrameInfo {
+        precision: Precision::F64,
+        max_iter: options.max_iter,
+        bits: 0,
+        skipped: 0,
+    };
+    (Rendered::Escape(EscapeBuffer { samples, ..buffer }), info)
+}
+
+/// Renders one frame of the Lyapunov exponents of `lyapunov`, or with
+/// `refine` one pass of refining it, in f64 like `render_basins`.
+fn render_exponents(
+    lyapunov: &Lyapunov,
+    zoom: &Zoom,
+    plan: &FramePlan,
+    refine: Option<Refine>,
+    timing: Option<&Timing>,
+) -> (Rendered, FrameInfo) {
+    let options = RenderOptions {
+        refine,
+        timing,
+        ..zoom.frame_options(plan)
+    };
+    let samples = plan.samples;
+    let (width, height) = zoom.computed_size(plan);
+    let y_range = match zoom.flip_y {
+        true => (plan.y_range.1, plan.y_range.0),
+        false => plan.y_range,
+    };
+    let buffer = compute_lyapunov(lyapunov, width, height, plan.x_range, y_range, &options);
+    let info = FrameInfo {
+        precision: Precision::F64,
+        max_iter: options.max_iter,
+        bits: 0,
+        skipped: 0,
+    };
+    (Rendered::Escape(EscapeBuffer { samples, ..buffer }), info)
+}
+
+/// Renders one frame of `zoom`, computing the values for `coloring`, or
+/// with `refine` one pass of refining it, recording the time every pixel
+/// takes in `timing` if given.
+fn render_view<F: Fractal>(
+    fractal: &F,
+    zoom: &Zoom,
+    plan: &FramePlan,
+    coloring: Coloring,
+    reuse: Option<Reuse>,
+    refine: Option<Refine>,
+    timing: Option<&Timing>,
+) -> (Rendered, FrameInfo) {
+    let camera = &plan.camera;
+    let (x_range_width, y_range_width) = plan.range_widths;
+    let options = RenderOptions {
+        coloring,
+        reuse,
+        refine,
+        timing,
+        ..zoom.frame_options(plan)
+    };
+    let max_iter = options.max_iter;
+    let samples = plan.samples;
+    let (width, height) = zoom.computed_size(plan);
+    let center = camera.approx_center();
+    let pixel_size = plan.sample_size;
+    // The render functions put the top of their y range on row 0, and
+    // turn the image over for a range given the other way around.
+    let flip = |(bottom, top)| if zoom.flip_y { (top, bottom) } else { (bottom, top) };
+    let y_range_width = if zoom.flip_y { -y_range_width } else { y_range_width };
+    let range_width = (x_range_width, y_range_width);
+    let bits = bigfloat::required_bits(center, pixel_size);
+    let center_big = || {
+        let parse = |digits: &str| {
+            bigfloat::parse_decimal(digits, bits).expect("centers are validated when read")
+        };
+        (parse(&camera.center.0), parse(&camera.center.1))
+    };
+
+    let precision = plan.precision;
+    let mut skipped = 0;
+    let rendered = match precision {
+        Precision::F32 | Precision::F64 | Precision::Auto => {
+            let options = RenderOptions {
+                single_precision: precision == Precision::F32,
+                ..options
+            };
+            let (x_range, y_range) = (plan.x_range, flip(plan.y_range));
+            match zoom.mode {
+                Mode::Escape => Rendered::Escape(compute_escape(
+                    fractal, width, height, x_range, y_range, &options,
+                )),
+                Mode::Buddhabrot => Rendered::Image(render_buddhabrot(
+                    fractal,
+                    width,
+                    height,
+                    x_range,
+                    y_range,
+                    &options,
+                    &zoom.buddhabrot,
+                )),
+                Mode::Nebulabrot => Rendered::Image(render_nebulabrot(
+                    fractal,
+                    width,
+                    height,
+                    x_range,
+                    y_range,
+                    &options,
+                    &zoom.buddhabrot,
+                )),
+            }
+        }
+        Precision::Perturbation => {
+            let orbit = zoom.orbits.get_or_compute(&camera.center, bits, max_iter, || {
+                fractal.reference_orbit(&center_big(), bits, max_iter, options.bailout)
+            });
+            let (half_x, half_y) = (x_range_width / 2.0, y_range_width / 2.0);
+            let probes = [
+                (-half_x, -half_y),
+                (0.0, -half_y),
+                (half_x, -half_y),
+                (-half_x, 0.0),
+                (half_x, 0.0),
+                (-half_x, half_y),
+                (0.0, half_y),
+                (half_x, half_y),
+            ]
+            .map(|probe| options.rotation.apply(probe));
+            let radius = half_x.hypot(half_y);
+            // Only escape time coloring can start pixels partway into the orbit.
+            let series = match options.coloring.uses_escape_times() {
+                true => fractal.series(
+                    &orbit.z,
+                    radius,
+                    &probes,
+                    zoom.series_terms,
+                    max_iter,
+                    options.bailout,
+                ),
+                false => None,
+            };
+            skipped = series.as_ref().map_or(0, |series| series.skip);
+            Rendered::Escape(compute_escape_perturbed(
+                fractal,
+                width,
+                height,
+                &orbit,
+                series.as_ref(),
+                range_width,
+                &options,
+            ))
+        }
+        Precision::DoubleDouble => {
+            let center = (
+                Decimal::parse(&camera.center.0).expect("centers are validated when read"),
+                Decimal::parse(&camera.center.1).expect("centers are validated when read"),
+            );
+            Rendered::Escape(compute_escape_dd(
+                fractal,
+                width,
+                height,
+                (center.0.to_double_double(), center.1.to_double_double()),
+                range_width,
+                &options,
+            ))
+        }
+        Precision::Big => Rendered::Escape(compute_escape_big(
+            fractal,
+            width,
+            height,
+            &center_big(),
+            range_width,
+            bits,
+            &options,
+        )),
+    };
+    let rendered = match rendered {
+        Rendered::Escape(buffer) => Rendered::Escape(EscapeBuffer { samples, ..buffer }),
+        Rendered::Image(img) => Rendered::Image(img),
+    };
+    (
+        rendered,
+        FrameInfo {
+            precision,
+            max_iter,
+            bits,
+            skipped,
+        },
+    )
+}
+
+/// The line `--frame-stats` prints after a frame with `stats`.
+fn stats_summary(stats: &FrameStats) -> String {
+    let interior = format!("{:.1}% interior", 100.0 * stats.interior);
+    match stats.escape {
+        Some(escape) => format!(
+            "  Iterations {} to {}, mean {:.1}, std dev {:.1}; {}, {:.1}% escaped within {}.",
+            escape.min,
+            escape.max,
+            escape.mean,
+            escape.std_dev,
+            interior,
+            100.0 * escape.fast,
+            stats::FAST_ESCAPE
+        ),
+        None => format!("  {}.", interior),
+    }
+}
+
+/// A frame that has been rendered and saved, waiting to be written to the
+/// log, the manifest and the video after the frames before it.
+struct Finished {
+    record: FrameRecord,
+    /// The line logged for the frame.
+    message: String,
+    /// The files written for the frame.
+    paths: Vec<String>,
+    /// The statistics of the escape buffer, when it holds escape times.
+    stats: Option<FrameStats>,
+    /// The fraction of pixels refined with `--adaptive`.
+    refined: Option<f64>,
+    /// The raw frame for the video encoder, when frames are piped to it.
+    video_frame: Option<Vec<u8>>,
+    /// The frame to draw on the terminal, when it's one of the previews.
+    preview: Option<DynamicImage>,
+    /// The reference the frame was colored by, with `--stabilize-colors`.
+    reference: Option<Reference>,
+    /// How long the stages of the frame took so far.
+    times: FrameTimes,
+}
+
+/// A frame that has been rendered and colored, waiting for its images to
+/// be encoded and saved on a thread of their own, so the next frame can be
+/// computed meanwhile.
+struct Unsaved<'a> {
+    /// The frame as it is once saved, but for its message and the paths of
+    /// its images.
+    finished: Finished,
+    /// The colored images, with where they go and the palette they are in.
+    images: Vec<(String, DynamicImage, &'a str)>,
+    /// The center of the frame, for the metadata of its images.
+    center: (String, String),
+    /// How long the frame took to compute and color.
+    computed: Duration,
+    /// What is logged of the frame besides its times.
+    details: Vec<String>,
+}
+
+impl Unsaved<'_> {
+    /// Encodes and saves the images of the frame, and logs the times both
+    /// stages took in its message.
+    fn save(self, zoom: &Zoom) -> Result<Finished, RustlebrotError> {
+        let Unsaved {
+            mut finished,
+            images,
+            center,
+            computed,
+            mut details,
+        } = self;
+        let record = &finished.record;
+        // The other outputs go first, so a frame that's there to --resume
+        // has them all. Their views are the regions they show.
+        let start = Instant::now();
+        let mut saved = Vec::new();
+        if let Some((_, img, palette)) = images.first().filter(|_| zoom.outputs.len() > 1) {
+            for output in &zoom.outputs[1..] {
+                let (_, _, width, height) = output.region(img.width(), img.height());
+                let shown = (
+                    width as f64 / img.width() as f64,
+                    height as f64 / img.height() as f64,
+                );
+                let path = zoom.output_path(output, record.frame);
+                saved.push((path, output.of(img), *palette, shown));
+            }
+        }
+        let resampled = start.elapsed();
+        finished.times.add(Stage::Colorize, resampled);
+        let outputs = saved.len();
+        let whole = images.into_iter().map(|(path, img, palette)| (path, img, palette, (1.0, 1.0)));
+        saved.extend(whole);
+        let start = Instant::now();
+        let mut paths = Vec::new();
+        for (path, img, palette, (x_shown, y_shown)) in saved {
+            // Whole frames keep their ranges to the bit, which --resume
+            // checks.
+            let shown = |(from, to): (f64, f64), share: f64| match share {
+                1.0 => (from, to),
+                _ => {
+                    let (middle, half) = ((from + to) / 2.0, (to - from) / 2.0 * share);
+                    (middle - half, middle + half)
+                }
+            };
+            let metadata = Metadata {
+                frame: record.frame,
+                center: &center,
+                x_range: shown(record.x_range, x_shown),
+                y_range: shown(record.y_range, y_shown),
+                rotation: record.rotation,
+                zoom_factor: zoom.zoom_factor,
+                max_iter: record.max_iter,
+                palette,
+            };
+            let encoding = Instant::now();
+            let bytes = export::encode_frame(&path, &img, zoom.image_format, &metadata)?;
+            finished.times.add(Stage::Encode, encoding.elapsed());
+            let writing = Instant::now();
+            create_parents([path.as_str()])?;
+            export::write_file(&path, &bytes)?;
+            finished.times.add(Stage::Write, writing.elapsed());
+            paths.push(path);
+        }
+        let encoded = start.elapsed();
+        if !paths.is_empty() {
+            let palettes = match zoom.palettes.len() {
+                1 => String::new(),
+                n => format!(" in {} palettes", n),
+            };
+            details.push(format!("computed in {:.2?} seconds", computed.as_secs_f64()));
+            if outputs > 0 {
+                details.push(format!(
+                    "resampled to {} more outputs in {:.2?} seconds",
+                    outputs,
+                    resampled.as_secs_f64()
+                ));
+            }
+            details.push(format!(
+                "encoded as {}{} in {:.2?} seconds",
+                zoom.image_format.name(),
+                palettes,
+                encoded.as_secs_f64()
+            ));
+        }
+        let seconds = (computed + resampled + encoded).as_secs_f64();
+        let mut message = format!(
+            "Frame {} saved in {:.2?} seconds ({}).",
+            record.frame,
+            seconds,
+            details.join(", "),
+        );
+        if let Some(stats) = finished.stats.as_ref().filter(|_| zoom.frame_stats) {
+            message = format!("{}\n{}", message, stats_summary(stats));
+        }
+        finished.record.seconds = seconds;
+        finished.message = message;
+        paths.append(&mut finished.paths);
+        finished.paths = paths;
+        Ok(finished)
+    }
+}
+
+/// The external rays of `zoom` where they are in the frame of `plan`, in
+/// its pixels, traced on first as far as the frame resolves them.
+fn ray_pixels(zoom: &Zoom, plan: &FramePlan) -> Vec<Vec<(f64, f64)>> {
+    if zoom.rays.is_empty() {
+        return Vec::new();
+    }
+    let camera = &plan.camera;
+    let bits = bigfloat::required_bits(camera.approx_center(), plan.pixel_size);
+    let parse = |digits: &str| {
+        bigfloat::parse_decimal(digits, bits).expect("centers are validated when read")
+    };
+    let (x_range_width, y_range_width) = plan.range_widths;
+    let view = ray::View {
+        center: (parse(&camera.center.0), parse(&camera.center.1)),
+        range_widths: match zoom.flip_y {
+            true => (x_range_width, -y_range_width),
+            false => (x_range_width, y_range_width),
+        },
+        rotation: Rotation::degrees(plan.rotation),
+    };
+    let resolution = plan.pixel_size / ray::STEPS_PER_PIXEL;
+    zoom.rays
+        .iter()
+        .map(|ray| {
+            let mut ray = ray.lock().unwrap();
+            ray.extend(resolution, camera.max_iter);
+            view.pixels(ray.points(), zoom.frame_size())
+        })
+        .collect()
+}
+
+/// Renders and colors `frame`, returning it for `Unsaved::save` and then
+/// `write_frame`, and with `--incremental` its last escape buffer for the
+/// next frame, with the plan it was rendered from, or the first error
+/// writing one of its other files.
+///
+/// When frames are `piped` to the video encoder, the frame is kept for it
+/// instead, and only saved as a PNG if it is one of the previews. With the
+/// `previous` frame, the pixels it covers are taken from it where they can
+/// be, as they are from each sub-frame of a motion blurred frame for the
+/// next. With `--stabilize-colors`, the frame is colored by the reference
+/// of the frame before moved towards its own, which is recorded.
+fn render_frame<'a>(
+    frame: u32,
+    zoom: &Zoom<'a>,
+    piped: bool,
+    previous: Option<(&FramePlan, &EscapeBuffer)>,
+    reference: Option<&Reference>,
+    progress: &Progress,
+) -> Result<(Unsaved<'a>, Option<(FramePlan, EscapeBuffer)>), RustlebrotError> {
+    progress.frame_started();
+    events::emit(&Event::FrameStarted { frame });
+    let start_time: Instant = Instant::now();
+    let plan = zoom.plan(frame);
+    let view = |coloring, reuse| render_fractal(zoom, &plan, coloring, reuse, None, None);
+    let escape_buffer = |rendered| match rendered {
+        Rendered::Escape(buffer) => buffer,
+        Rendered::Image(_) => unreachable!("escape mode renders escape buffers"),
+    };
+    let coloring = zoom.options.coloring;
+    let camera = &plan.camera;
+    if let Some(preview) = zoom.preview_progressive {
+        render_previews(zoom, &plan, preview, progress)?;
+    }
+    let pass_start = Instant::now();
+    let timing = zoom.debug_channels.time.then(Timing::default);
+    let mut exposures = zoom.exposures(&plan);
+    let last = exposures.pop().expect("every frame has a view");
+    // The sub-frames before the last, each taking what it can from the one
+    // before it.
+    let mut earlier: Vec<EscapeBuffer> = Vec::new();
+    for (index, exposure) in exposures.iter().enumerate() {
+        let before = match index {
+            0 => previous,
+            _ => Some((&exposures[index - 1], &earlier[index - 1])),
+        };
+        let reuse = reuse(zoom, before, exposure);
+        let rendered = render_fractal(zoom, exposure, coloring, reuse, None, timing.as_ref());
+        earlier.push(escape_buffer(rendered.0));
+    }
+    let before = exposures.last().zip(earlier.last()).or(previous);
+    let (rendered, info) = match &zoom.expmap {
+        Some(strips) => {
+            let img = strips.frame(&expmap_view(&last), zoom.colors.bit_depth, zoom.colors.dither)?;
+            let info = FrameInfo {
+                precision: Precision::F64,
+                max_iter: last.camera.max_iter,
+                bits: 0,
+                skipped: 0,
+            };
+            (Rendered::Image(img), info)
+        }
+        None => {
+            let reuse = reuse(zoom, before, &last);
+            render_fractal(zoom, &last, coloring, reuse, None, timing.as_ref())
+        }
+    };
+    let mut times = FrameTimes::new(frame, zoom.width as u64 * zoom.height as u64);
+    times.add(Stage::Compute, start_time.elapsed());
+    times.precision = info.precision.name();
+
+    let (x_range, y_range) = (plan.x_range, plan.y_range);
+    // What the manifest records of the view, which the overlay is written
+    // from. The rest is filled in once the frame is done.
+    let viewed = FrameRecord {
+        frame,
+        x_range,
+        y_range,
+        pixel_size: plan.pixel_size,
+        magnification: camera.magnification,
+        rotation: plan.rotation,
+        direction: zoom.direction(frame).to_string(),
+        max_iter: info.max_iter,
+        precision: Some(info.precision.name().to_string()),
+        shutter_magnifications: (!exposures.is_empty())
+            .then(|| (exposures[0].camera.magnification, last.camera.magnification)),
+        seconds: 0.0,
+        spread: None,
+        color_reference: None,
+        julia_c: zoom.julia(frame).map(|julia| julia.c),
+        dimension: None,
+        center: (zoom.pan.is_some() || zoom.path.target().is_some())
+            .then(|| camera.center.clone()),
+    };
+    let mut paths = Vec::new();
+    let mut images = Vec::new();
+    let mut video_frame = None;
+    // The Julia set is rendered once, and colored in every palette.
+    let inset = zoom
+        .julia_inset
+        .map(|inset| (inset, inset.render(camera.approx_center(), zoom.frame_size())));
+    let rays = ray_pixels(zoom, &plan);
+    let mut save = |img: &DynamicImage, set: &PaletteSet<'a>| {
+        let rayed = (!rays.is_empty()).then(|| {
+            let mut img = img.clone();
+            rays.iter().for_each(|pixels| ray::draw(&mut img, pixels));
+            img
+        });
+        let img = rayed.as_ref().unwrap_or(img);
+        let inset = inset.as_ref().map(|(inset, julia)| {
+            let mut img = img.clone();
+            inset.draw(&mut img, julia, &zoom.frame_colors(frame, set));
+            img
+        });
+        let img = inset.as_ref().unwrap_or(img);
+        let overlaid = zoom.hud.map(|hud| {
+            let mut img = img.clone();
+            hud.draw(&mut img, &viewed);
+            (img, hud.only_video)
+        });
+        let (img, video_img) = match &overlaid {
+            Some((overlaid, true)) => (img, overlaid),
+            Some((overlaid, false)) => (overlaid, overlaid),
+            None => (img, img),
+        };
+        if piped {
+            video_frame = Some(video::raw_frame(video_img, zoom.video_alpha));
+            if !zoom.preview_every.is_some_and(|every| frame.is_multiple_of(every)) {
+                return;
+            }
+        }
+        let path = zoom.frame_path(Some(set), frame, zoom.image_format.extension());
+        images.push((path, img.clone(), set.name));
+    };
+    let mut refined = None;
+    let mut dimension = None;
+    let previewed = zoom.term_preview.is_some_and(|preview| frame.is_multiple_of(preview.every));
+    let mut preview = None;
+    let mut shown = None;
+    let mut stabilized = None;
+    let (stats, kept) = match rendered {
+        Rendered::Image(img) => {
+            let img = zoom.posted(img);
+            save(&img, &zoom.palettes[0]);
+            shown = zoom.dashboard.is_some().then(|| img.clone());
+            preview = previewed.then_some(img);
+            (None, None)
+        }
+        Rendered::Escape(mut buffer) => {
+            let stats = FrameStats::of(&buffer);
+            if zoom.measure_dimension {
+                dimension = Dimension::of(&buffer).map(DimensionRecord::from);
+            }
+            // A frame with nothing outside the set leaves the reference as
+            // it was.
+            stabilized = zoom.stabilize_colors.and_then(|weight| {
+                let exterior = buffer.values.iter().filter_map(|sample| match sample {
+                    Sample::Value(value) => Some(*value),
+                    _ => None,
+                });
+                let own = Reference::of(exterior, zoom.colors.histogram_clip);
+                match (reference, own) {
+                    (Some(reference), Some(own)) => Some(reference.follow(&own, weight)),
+                    (reference, own) => own.or(reference.cloned()),
+                }
+            });
+            let colored_by = stabilized.as_ref();
+            if let Some(adaptive) = zoom.adaptive.filter(|_| zoom.export.png) {
+                let colors = ColorOptions {
+                    reference: colored_by,
+                    ..zoom.frame_colors(frame, &zoom.palettes[0])
+                };
+                let refining = Instant::now();
+                refined = Some(render::refine(&mut buffer, &colors, &adaptive, |refine| {
+                    let rendered =
+                        render_fractal(zoom, &plan, coloring, None, Some(refine), timing.as_ref());
+                    escape_buffer(rendered.0)
+                }));
+                times.add(Stage::Refine, refining.elapsed());
+            }
+            times.iterations = performance::iterations(&buffer);
+            let kept = zoom.incremental.is_some().then(|| (last.clone(), buffer.clone()));
+            if zoom.export.png {
+                let colorizing = Instant::now();
+                let buffers: Vec<&EscapeBuffer> = earlier.iter().chain([&buffer]).collect();
+                for (index, set) in zoom.palettes.iter().enumerate() {
+                    let colors = ColorOptions {
+                        reference: colored_by,
+                        ..zoom.frame_colors(frame, set)
+                    };
+                    let img = zoom.posted(zoom.placed(zoom.colorize(&buffers, &colors)?));
+                    if let Some(preview) = zoom.preview_progressive.filter(|_| index == 0) {
+                        write_preview(preview, frame, 1, &img, pass_start, progress)?;
+                    }
+                    save(&img, set);
+                    if index == 0 && zoom.dashboard.is_some() {
+                        shown = Some(img.clone());
+                    }
+                    if index == 0 && previewed {
+                        preview = Some(img);
+                    }
+                }
+                times.add(Stage::Colorize, colorizing.elapsed());
+            }
+            if zoom.dump_iterations {
+                let header = Header {
+                    frame,
+                    width: buffer.width,
+                    height: buffer.height,
+                    samples: buffer.samples,
+                    x_range,
+                    y_range,
+                    flip_y: zoom.flip_y,
+                    rotation: plan.rotation,
+                    center: camera.center.clone(),
+                    zoom_factor: zoom.zoom_factor,
+                    max_iter: buffer.max_iter,
+                    palette_iter: zoom.frame_colors(frame, &zoom.palettes[0]).palette_iter,
+                    coloring: buffer.coloring,
+                };
+                let npy = zoom.frame_path(None, frame, "npy");
+                let json = zoom.frame_path(None, frame, "json");
+                create_parents([npy.as_str(), json.as_str()])?;
+                export::write_npy(&npy, &buffer)?;
+                export::write_header(&json, &header)?;
+                paths.extend([npy, json]);
+            }
+            let channels = zoom.debug_channels;
+            let diagnostics = [
+                channels.iter.then(|| debug::iterations(&buffer)),
+                timing.as_ref().map(|timing| debug::times(&buffer, timing)),
+                zoom.adaptive
+                    .filter(|_| channels.samples)
+                    .map(|adaptive| debug::samples(&buffer, adaptive.samples * adaptive.samples)),
+            ];
+            for diagnostic in diagnostics.into_iter().flatten() {
+                let path = zoom.frame_path(None, frame, &format!("{}.png", diagnostic.name));
+                let text = [
+                    ("Software", format!("rustlebrot {}", env!("CARGO_PKG_VERSION"))),
+                    ("Frame", frame.to_string()),
+                    ("White", diagnostic.white),
+                ];
+                create_parents([path.as_str()])?;
+                export::save_png_text(&path, &zoom.placed(diagnostic.image), &text)?;
+                paths.push(path);
+            }
+            if zoom.export.exr {
+                // The escape channel is always smooth, so other colorings
+                // need a second pass.
+                let smooth = || escape_buffer(view(Coloring::Smooth, None).0);
+                let (escape, distance) = match coloring {
+                    Coloring::Smooth => (buffer, None),
+                    Coloring::Distance => (smooth(), Some(buffer)),
+                    _ => (smooth(), None),
+                };
+                let path = zoom.frame_path(None, frame, "exr");
+                create_parents([path.as_str()])?;
+                export::write_exr(&path, &escape, distance.as_ref())?;
+                paths.push(path);
+            }
+            (stats, kept)
+        }
+    };
+
+    let elapsed_time = start_time.elapsed();
+    let mut details = vec![info.precision.name().to_string()];
+    if let Some(julia) = zoom.julia(frame) {
+        details.push(format!("c = {:.6}{:+.6}i", julia.c.0, julia.c.1));
+    }
+    if matches!(info.precision, Precision::Perturbation | Precision::Big) {
+        details.push(format!("{} bits", info.bits));
+    }
+    if info.precision == Precision::Perturbation {
+        details.push(format!("skipped {} iterations", info.skipped));
+    }
+    if zoom.auto_iter.is_some() || zoom.iter_schedule.is_some() {
+        details.push(format!("max_iter {}", info.max_iter));
+    }
+    if let Some(refined) = refined {
+        details.push(format!("refined {:.1}% of pixels", 100.0 * refined));
+    }
+    if !exposures.is_empty() {
+        details.push(format!("blurred over {} sub-frames", exposures.len() + 1));
+    }
+    if plan.ulps < WARN_ULPS {
+        details.push(format!("only {:.1} ulps per pixel", plan.ulps));
+    }
+    let record = FrameRecord {
+        seconds: elapsed_time.as_secs_f64(),
+        spread: stats.map(|stats| stats.spread),
+        dimension,
+        color_reference: stabilized.as_ref().map(|reference| reference.quantiles().to_vec()),
+        ..viewed
+    };
+    // The dashboard takes frames as they are done, whatever the order they
+    // are written in.
+    if let (Some(dashboard), Some(shown)) = (&zoom.dashboard, shown) {
+        let _ = dashboard.try_send((frame, shown));
+    }
+    let finished = Finished {
+        record,
+        message: String::new(),
+        paths,
+        stats,
+        refined,
+        video_frame,
+        preview,
+        reference: stabilized,
+        times,
+    };
+    let unsaved = Unsaved {
+        finished,
+        images,
+        center: camera.center.clone(),
+        computed: elapsed_time,
+        details,
+    };
+    Ok((unsaved, kept))
+}
+
+/// What the frame `plan` is for can take from the one rendered from
+/// `before`, which it can only with `--incremental` and the same center,
+/// or along a pan.
+fn reuse<'b>(
+    zoom: &Zoom,
+    before: Option<(&FramePlan, &'b EscapeBuffer)>,
+    plan: &FramePlan,
+) -> Option<Reuse<'b>> {
+    let ((before, previous), incremental) = before.zip(zoom.incremental)?;
+    let shift = match &zoom.pan {
+        Some(pan) => {
+            let (right, up) = pan.shift(before.frame, plan.frame)?;
+            // Rows run down the plane unless they're flipped.
+            let samples = plan.samples as i64;
+            let down = match zoom.flip_y {
+                true => up,
+                false => -up,
+            };
+            Some(Shift {
+                x: right * samples,
+                y: down * samples,
+                refresh: pan.refresh,
+            })
+        }
+        None if before.camera.center != plan.camera.center => return None,
+        None => None,
+    };
+    Some(Reuse {
+        previous,
+        scale: before.camera.magnification / plan.camera.magnification,
+        rotation: Rotation::degrees(plan.rotation - before.rotation),
+        threshold: incremental.threshold,
+        raised_limit: false,
+        shift,
+    })
+}
+
+/// Renders the passes of `--preview-progressive` before the frame `plan` is
+/// for, every `PREVIEW_STRIDES` pixels along both axes, coarsest first, and
+/// writes each to `path` with its pixels grown into blocks. They're
+/// rendered apart from the frame, which comes out as it does without them.
+fn render_previews(
+    zoom: &Zoom,
+    plan: &FramePlan,
+    path: &str,
+    progress: &Progress,
+) -> Result<(), RustlebrotError> {
+    let colors = zoom.frame_colors(plan.frame, &zoom.palettes[0]);
+    for stride in PREVIEW_STRIDES {
+        let start = Instant::now();
+        let (width, height) = (zoom.width.div_ceil(stride), zoom.height.div_ceil(stride));
+        let coarse = plan.clone().resized(width, height);
+        let (rendered, _) = render_fractal(zoom, &coarse, zoom.options.coloring, None, None, None);
+        let Rendered::Escape(buffer) = rendered else {
+            unreachable!("previews are only taken in escape mode")
+        };
+        let img = zoom.colorize(&[&buffer], &colors)?;
+        let img = img.resize_exact(zoom.width, zoom.height, FilterType::Nearest);
+        write_preview(path, plan.frame, stride, &img, start, progress)?;
+    }
+    Ok(())
+}
+
+/// Writes `img`, the pass of `--preview-progressive` every `stride` pixels
+/// that started at `start`, to `path`, and reports how long it took.
+fn write_preview(
+    path: &str,
+    frame: u32,
+    stride: u32,
+    img: &DynamicImage,
+    start: Instant,
+    progress: &Progress,
+) -> Result<(), RustlebrotError> {
+    let text = [
+        ("Software", format!("rustlebrot {}", env!("CARGO_PKG_VERSION"))),
+        ("Frame", frame.to_string()),
+        ("Stride", stride.to_string()),
+    ];
+    create_parents([path])?;
+    export::save_png_text(path, img, &text)?;
+    let seconds = start.elapsed().as_secs_f64();
+    let pass = match stride {
+        1 => "full resolution".to_string(),
+        stride => format!("1/{} resolution", stride),
+    };
+    progress.say(&format!("  Frame {} previewed at {} in {:.2} seconds.", frame, pass, seconds));
+    events::emit(&Event::FramePreviewed {
+        frame,
+        stride,
+        seconds,
+        path,
+    });
+    Ok(())
+}
+
+/// Sends a `finished` frame to the `video` encoder and reports it, returning
+/// its record for the manifest, its statistics and the times of its
+/// stages. Its preview is handed to the thread drawing `previews`, unless
+/// that is still busy with the one before, so the terminal never holds up
+/// the frames.
+fn write_frame(
+    finished: Finished,
+    video: Option<&mut dyn Encoder>,
+    previews: Option<&SyncSender<DynamicImage>>,
+    progress: &Progress,
+) -> Result<(FrameRecord, Option<FrameStats>, FrameTimes), RustlebrotError> {
+    let (record, mut times) = (finished.record, finished.times);
+    if let (Some(video), Some(video_frame)) = (video, finished.video_frame) {
+        let start = Instant::now();
+        video.push_frame(&video_frame)?;
+        times.add(Stage::Video, start.elapsed());
+    }
+    progress.frame_finished(record.frame, record.seconds, &finished.message);
+    if let (Some(previews), Some(preview)) = (previews, finished.preview) {
+        let _ = previews.try_send(preview);
+    }
+    events::emit(&Event::FrameCompleted {
+        frame: record.frame,
+        seconds: record.seconds,
+        paths: finished.paths,
+        mean_iterations: finished.stats.and_then(|stats| stats.escape).map(|escape| escape.mean),
+        refined: finished.refined,
+    });
+    progress.emit();
+    Ok((record, finished.stats, times))
+}
+
+/// What a frame of `plan` resampled from the exponential map shows.
+fn expmap_view(plan: &FramePlan) -> View {
+    let (width, height) = plan.size;
+    View {
+        width,
+        height,
+        pixel_size: (plan.range_widths.0 / width as f64, plan.range_widths.1 / height as f64),
+        rotation: Rotation::degrees(plan.rotation),
+    }
+}
+
+/// The strips of the exponential map `frames` of `zoom` are resampled from
+/// with `--expmap`, in the `expmap` directory of the run, rendering those
+/// that aren't saved there already.
+///
+/// Every strip is iterated as far as the deepest of the frames it's in, so
+/// no pixel is iterated less than it would be rendering its frame. Strips
+/// are only rendered in f64, so this fails for a zoom that goes deeper
+/// than f64 can, where auto precision would move on.
+fn expmap_strips(zoom: &Zoom, frames: &[u32], args: &cli::Args) -> Result<Strips, RustlebrotError> {
+    let center = zoom.path.first_center();
+    let parse = |digits: &str| digits.parse().expect("centers are validated when read");
+    let map = ExpMap::for_frames((parse(&center.0), parse(&center.1)), zoom.width, zoom.height);
+    let mut budgets = BTreeMap::new();
+    let mut most = 0;
+    for &frame in frames {
+        let plan = zoom.plan(frame);
+        if !matches!(plan.precision, Precision::F32 | Precision::F64) {
+            return Err(RustlebrotError::Argument(format!(
+                "--expmap renders in f64, which frame {} is too deep for; end the zoom before it",
+                frame
+            )));
+        }
+        let strips = map.strips(&expmap_view(&plan));
+        most = most.max(strips.clone().count());
+        for index in strips {
+            let budget = budgets.entry(index).or_insert(0);
+            *budget = plan.camera.max_iter.max(*budget);
+        }
+    }
+    // The names of the settings the colors of the strips depend on.
+    let colored = [
+        "coloring",
+        "transfer",
+        "phase",
+        "palette_sources",
+        "interior",
+        "palette_cycles",
+        "palette_offset",
+        "palette_reverse",
+        "adjust",
+        "blending",
+        "bailout",
+        "periodicity",
+    ];
+    let mut settings = args.settings().by_name();
+    settings.retain(|name, _| colored.contains(&name.as_str()));
+    settings.insert("fractal".to_string(), args.fractal.name().into());
+    settings.insert("palette_iter".to_string(), zoom.colors.palette_iter.into());
+    let dir = format!("{}/expmap", zoom.output_dir);
+    let colors = zoom.frame_colors(zoom.frames.start, &zoom.palettes[0]);
+    // Frames rendered at once each hold the strips they're in, and the one
+    // the zoom moves on to.
+    let capacity = (most + 1) * args.frame_parallelism;
+    match zoom.fractal {
+        FractalKind::Mandelbrot => Strips::prepare(
+            &Mandelbrot, map, center, &dir, settings, &budgets, &zoom.options, &colors, capacity,
+        ),
+        FractalKind::Tricorn => Strips::prepare(
+            &Tricorn, map, center, &dir, settings, &budgets, &zoom.options, &colors, capacity,
+        ),
+        FractalKind::Newton
+        | FractalKind::Formula
+        | FractalKind::Julia
+        | FractalKind::Lyapunov => {
+            unreachable!("--expmap is refused for newton, formula, julia and lyapunov zooms")
+        }
+    }
+}
+
+/// Frames rendered and colored that wait for a thread to save them, at
+/// most, besides the ones being saved.
+const SAVE_QUEUE: usize = 1;
+
+/// Threads encoding and saving frames while the next ones are rendered.
+const SAVERS: usize = 2;
+
+/// Threads each of `parallelism` frames rendered at once gets, sharing the
+/// threads of the rayon pool rather than adding to them.
+fn frame_threads(parallelism: usize) -> usize {
+    (rayon::current_num_threads() / parallelism).max(1)
+}
+
+/// A rough bound of the memory a frame of `zoom` takes up while it is
+/// rendered on `threads` threads and saved, in bytes.
+fn frame_memory(zoom: &Zoom, threads: usize, piped: bool) -> u64 {
+    let pixels = zoom.width as u64 * zoom.height as u64;
+    // The colored frame, and its copy for the encoder.
+    let image = image_memory(zoom) * if piped { 2 } else { 1 };
+    let compute = match zoom.mode {
+        // The escape buffer, those of the other sub-frames with motion
+        // blur, the previous one with --incremental, and the smooth pass
+        // for the EXR.
+        Mode::Escape => {
+            let buffers = zoom.sub_frames() as u64
+                + zoom.incremental.is_some() as u64
+                + zoom.export.exr as u64;
+            let samples = (zoom.supersample * zoom.supersample) as u64;
+            // Adaptive anti-aliasing keeps the colors of the frame, and the
+            // samples it adds, which on a busy frame are about as many.
+            let adaptive = match zoom.adaptive {
+                Some(_) => (size_of::<[f64; 3]>() + size_of::<Sample>()) as u64 * pixels,
+                None => 0,
+            };
+            // Script coloring keeps the orbit of every sample beside it.
+            let sample = match zoom.options.coloring {
+                Coloring::Script(_) => size_of::<Sample>() + size_of::<Orbit>(),
+                _ => size_of::<Sample>(),
+            };
+            buffers * sample as u64 * samples * pixels + adaptive
+        }
+        // Every thread counts orbits on grids of its own, weighted ones
+        // with Metropolis sampling, which are summed to a grid of weights.
+        Mode::Buddhabrot | Mode::Nebulabrot => {
+            let bands = if zoom.mode == Mode::Nebulabrot { 3 } else { 1 };
+            let grids = match zoom.buddhabrot.sampler {
+                Sampler::Uniform => 4 * threads as u64 + 8,
+                Sampler::Metropolis(_) => 8 * (threads as u64 + 1),
+            };
+            bands * grids * pixels
+        }
+    };
+    image + compute
+}
+
+/// The bytes of a colored frame of `zoom`, which frames waiting to be
+/// saved hold one of in every palette.
+fn image_memory(zoom: &Zoom) -> u64 {
+    let pixels = zoom.width as u64 * zoom.height as u64;
+    let channel = match zoom.colors.bit_depth {
+        BitDepth::Eight => 1,
+        BitDepth::Sixteen => 2,
+    };
+    let channels = if zoom.colors.alpha == Alpha::None { 3 } else { 4 };
+    channels * channel * pixels
+}
+
+/// The frames of a run, handed out to the threads rendering them and
+/// written in order as they are finished.
+struct FrameQueue<'a> {
+    frames: &'a [u32],
+    /// Frames rendered, saved or waiting to be written at once, at most.
+    ahead: usize,
+    early_stop: Option<EarlyStop>,
+    /// The time budget, and when its frames started.
+    budget: Option<(&'a Mutex<TimeBudget>, Instant)>,
+    state: Mutex<QueueState>,
+    /// Signaled whenever frames are written, for the threads waiting to
+    /// start one.
+    written: Condvar,
+}
+
+struct QueueState {
+    /// Frames handed out so far, from the start of `frames`.
+    started: usize,
+    /// Frames written so far, likewise.
+    written: usize,
+    /// Frames finished before one ahead of them, by index in `frames`.
+    waiting: BTreeMap<usize, Finished>,
+    manifest: ManifestWriter,
+    stats: StatsWriter,
+    video: Option<Box<dyn Encoder>>,
+    /// Where frames are sent to be drawn on the terminal, until the frames
+    /// are done.
+    previews: Option<SyncSender<DynamicImage>>,
+    /// The frames written, with how long their stages took.
+    finished: Vec<FrameTimes>,
+    /// The last frame written, if it was uniform, and how many uniform
+    /// frames in a row led up to it.
+    uniform: Option<(u32, u32)>,
+    /// Set once the frames have been uniform for long enough to stop.
+    stopped_early: Option<StoppedEarly>,
+    /// The first error of a frame, after which no more are started.
+    failed: Option<RustlebrotError>,
+}
+
+impl QueueState {
+    /// Counts the frame of `record` towards an early stop if its `stats`
+    /// are uniform, and records the stop once there are enough in a row.
+    fn count_uniform(
+        &mut self,
+        early_stop: &EarlyStop,
+        record: &FrameRecord,
+        stats: Option<&FrameStats>,
+    ) -> Result<(), RustlebrotError> {
+        let uniform = stats.is_some_and(|stats| early_stop.is_uniform(stats));
+        let streak = match self.uniform {
+            Some((last, streak)) if last + 1 == record.frame => streak + 1,
+            _ => 1,
+        };
+        self.uniform = uniform.then_some((record.frame, streak));
+        if !uniform || streak < early_stop.frames || self.stopped_early.is_some() {
+            return Ok(());
+        }
+        let stop = StoppedEarly {
+            frame: record.frame,
+            frames: early_stop.frames,
+            spread: early_stop.spread,
+        };
+        events::emit(&Event::StoppedEarly {
+            frame: stop.frame,
+            frames: stop.frames,
+            spread: stop.spread,
+        });
+        self.manifest.stop_early(&stop)?;
+        self.stopped_early = Some(stop);
+        Ok(())
+    }
+
+    /// Reports that the time budget rescaled the limits of the frames still
+    /// to come, and records it in the manifest.
+    fn adjust_budget(&mut self, adjustment: &budget::Adjustment) -> Result<(), RustlebrotError> {
+        events::say(format!(
+            "Frames so far took {:.2}x the predicted time; scaling max_iter by {:.3} from here.",
+            adjustment.ratio, adjustment.scale
+        ));
+        self.manifest.adjust_budget(&BudgetAdjustment {
+            frame: adjustment.frame,
+            ratio: adjustment.ratio,
+            scale: adjustment.scale,
+        })
+    }
+}
+
+impl FrameQueue<'_> {
+    /// Hands out the index of the next frame to render, once there is room
+    /// for it, or `None` when every frame has been handed out, after Ctrl-C,
+    /// once the frames turned uniform or once one failed.
+    fn take(&self) -> Option<usize> {
+        let mut state = self.state.lock().unwrap();
+        let ended = |state: &QueueState| {
+            interrupt::requested() || state.stopped_early.is_some() || state.failed.is_some()
+        };
+        // The frame being written next is always in progress while this
+        // waits, so it is woken up.
+        while state.started < self.frames.len()
+            && state.started >= state.written + self.ahead
+            && !ended(&state)
+        {
+            state = self.written.wait(state).unwrap();
+        }
+        if ended(&state) || state.started == self.frames.len() {
+            return None;
+        }
+        state.started += 1;
+        Some(state.started - 1)
+    }
+
+    /// Takes the `finished` frame at `index`, and writes it along with the
+    /// frames waiting after it if every frame before it is written.
+    fn finish(&self, index: usize, finished: Finished, progress: &Progress) {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        state.waiting.insert(index, finished);
+        while let Some(finished) = state.waiting.remove(&state.written) {
+            let frame = finished.record.frame;
+            let video = state.video.as_mut().map(|video| video.as_mut() as &mut dyn Encoder);
+            let written = write_frame(finished, video, state.previews.as_ref(), progress);
+            let appended = written.and_then(|(record, stats, times)| {
+                self.record_frame(state, &record, stats.as_ref())?;
+                Ok(times)
+            });
+            match appended {
+                Ok(times) => state.finished.push(times),
+                Err(e) => {
+                    state.failed.get_or_insert(e.in_frame(frame));
+                    break;
+                }
+            }
+            state.written += 1;
+        }
+        self.written.notify_all();
+    }
+
+    /// Ends the run after `error` in `frame`, once the frames in progress
+    /// are done. Only the first error is kept.
+    fn fail(&self, frame: u32, error: RustlebrotError) {
+        self.state.lock().unwrap().failed.get_or_insert(error.in_frame(frame));
+        self.written.notify_all();
+    }
+
+    /// Adds the written frame of `record` to the manifest and the table of
+    /// statistics, and counts it towards an early stop and the time budget.
+    fn record_frame(
+        &self,
+        state: &mut QueueState,
+        record: &FrameRecord,
+        stats: Option<&FrameStats>,
+    ) -> Result<(), RustlebrotError> {
+        state.manifest.append(record)?;
+        state.stats.append(record, stats)?;
+        if let Some(early_stop) = &self.early_stop {
+            state.count_uniform(early_stop, record, stats)?;
+        }
+        if let Some((budget, started)) = self.budget {
+            let elapsed = started.elapsed().as_secs_f64();
+            let adjustment = budget.lock().unwrap().written(record.frame, record.max_iter, elapsed);
+            if let Some(adjustment) = adjustment {
+                state.adjust_budget(&adjustment)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Prints the plan of every frame without rendering anything, as a table
+/// and with `--progress-format json` as `frame_planned` events, noting the
+/// frames where f64 starts to run short and the view width underflows.
+fn print_schedule(zoom_start: u32, zoom_end: u32, zoom: &Zoom) {
+    let (mut questionable, mut underflow) = (None, None);
+    events::say(
+        "frame  magnification  pixel size  max_iter  precision     center, x range, y range",
+    );
+    for plan in (zoom_start..zoom_end).map(|frame| zoom.plan(frame)) {
+        let camera = &plan.camera;
+        let center = camera.approx_center();
+        let f64_ulps = plan.sample_size / Precision::F64.resolution(center);
+        let flags = (
+            f64_ulps < WARN_ULPS,
+            plan.range_widths.0.min(plan.range_widths.1) < f64::MIN_POSITIVE,
+        );
+        let mut notes = Vec::new();
+        if plan.ulps < WARN_ULPS {
+            notes.push(format!("{:.1} ulps per pixel", plan.ulps));
+        }
+        if flags.0 && questionable.is_none() {
+            questionable = Some(plan.frame);
+            notes.push(format!("f64 spans only {:.1} ulps per pixel from here", f64_ulps));
+        }
+        if flags.1 && underflow.is_none() {
+            underflow = Some(plan.frame);
+            notes.push("the view width underflows f64 from here".to_string());
+        }
+        events::say(format!(
+            "{:>5}  {:>13.3e}  {:>10.3e}  {:>8}  {:<12}  {:?},{:?}  x {:?}..{:?}  y {:?}..{:?}{}",
+            plan.frame,
+            camera.magnification,
+            plan.pixel_size,
+            camera.max_iter,
+            plan.precision.name(),
+            center.0,
+            center.1,
+            plan.x_range.0,
+            plan.x_range.1,
+            plan.y_range.0,
+            plan.y_range.1,
+            match notes.is_empty() {
+                true => String::new(),
+                false => format!("  ({})", notes.join(", ")),
+            },
+        ));
+        events::emit(&Event::FramePlanned {
+            frame: plan.frame,
+            center: &camera.center,
+            x_range: plan.x_range,
+            y_range: plan.y_range,
+            pixel_size: plan.pixel_size,
+            magnification: camera.magnification,
+            rotation: plan.rotation,
+            max_iter: camera.max_iter,
+            precision: plan.precision.name(),
+            ulps_per_pixel: plan.ulps.is_finite().then_some(plan.ulps),
+            f64_questionable: flags.0,
+            underflow: flags.1,
+        });
+    }
+    if let Some(frame) = questionable {
+        events::say(format!(
+            "From frame {} on, pixels span fewer than {} ulps of the center in f64.",
+            frame, WARN_ULPS
+        ));
+    }
+    if let Some(frame) = underflow {
+        events::say(format!(
+            "From frame {} on, the view is narrower than the smallest normal f64.",
+            frame
+        ));
+    }
+}
+
+/// Warns about the frames of `zoom_start..zoom_end` that come close to the
+/// resolution of their precision, and returns the frame the zoom stops at:
+/// the first one whose neighboring pixels land on the same point, unless
+/// `allow_loss` says to render those anyway. Fails if that is the first
+/// frame, which would leave nothing to render, or if the frames follow the
+/// rows of `camera_path`, which ask for every one of them.
+fn precision_end(
+    zoom: &Zoom,
+    zoom_start: u32,
+    zoom_end: u32,
+    allow_loss: bool,
+    camera_path: Option<&FramePath>,
+) -> Result<u32, RustlebrotError> {
+    let mut plans = (zoom_start..zoom_end).map(|frame| zoom.plan(frame));
+    if let Some(plan) = plans.clone().find(|plan| plan.ulps < WARN_ULPS) {
+        events::say(format!(
+            "Warning: from frame {} on, pixels span fewer than {} ulps of the center in {}, \
+             so frames turn blocky; {}",
+            plan.frame,
+            WARN_ULPS,
+            plan.precision.name(),
+            match cfg!(feature = "bigfloat") {
+                true => "try --precision auto to go deeper",
+                false => "going deeper needs the bigfloat feature",
+            }
+        ));
+    }
+    match (plans.find(|plan| plan.ulps < 1.0), camera_path) {
+        (Some(plan), _) if !allow_loss && plan.frame == zoom_start => {
+            Err(RustlebrotError::PrecisionExhausted {
+                frame: plan.frame,
+                precision: plan.precision,
+            })
+        }
+        (Some(plan), Some(path)) if !allow_loss => Err(RustlebrotError::format(
+            &path.file,
+            format!(
+                "the row for frame {} is too deep for {}, whose pixels can't be told apart \
+                 there; {}",
+                plan.frame,
+                plan.precision.name(),
+                match cfg!(feature = "bigfloat") {
+                    true => "try --precision auto, or pass --allow-precision-loss",
+                    false => "going deeper needs the bigfloat feature, or pass \
+                              --allow-precision-loss",
+                }
+            ),
+        )),
+        (Some(plan), None) if !allow_loss => {
+            events::say(format!(
+                "Warning: stopping the zoom at frame {}, where pixels can't be told apart in {}; \
+                 pass --allow-precision-loss to render the rest anyway",
+                plan.frame,
+                plan.precision.name(),
+            ));
+            Ok(plan.frame)
+        }
+        _ => Ok(zoom_end),
+    }
+}
+
+/// Warns when the centers `given` have more significant digits than even
+/// the deepest of `frames` keeps, since the digits past those are silently
+/// dropped by every frame.
+fn warn_truncated_centers(zoom: &Zoom, given: &[&(String, String)], frames: Range<u32>) {
+    let significant = |digits: &str| {
+        let decimal = Decimal::parse(digits).expect("centers are validated when read");
+        decimal.significant_digits()
+    };
+    let supplied = given.iter().flat_map(|(x, y)| [significant(x), significant(y)]).max();
+    let kept = |plan: &FramePlan| {
+        let bits = match plan.precision {
+            Precision::F32 => f32::MANTISSA_DIGITS as usize,
+            Precision::F64 | Precision::Auto => f64::MANTISSA_DIGITS as usize,
+            Precision::DoubleDouble => 2 * f64::MANTISSA_DIGITS as usize,
+            // The center is read in as many bits as the pixels need.
+            Precision::Perturbation | Precision::Big => {
+                bigfloat::required_bits(plan.camera.approx_center(), plan.sample_size)
+            }
+        };
+        decimal::digits_of_bits(bits)
+    };
+    let deepest = frames.map(|frame| zoom.plan(frame)).max_by_key(kept);
+    if let (Some(supplied), Some(plan)) = (supplied, deepest) {
+        let kept = kept(&plan);
+        if supplied > kept {
+            events::say(format!(
+                "Warning: the center is given to {} significant digits, but even frame {}, in \
+                 {}, keeps only {} of them; the rest don't change the frames, only deeper ones \
+                 would need them",
+                supplied,
+                plan.frame,
+                plan.precision.name(),
+                kept
+            ));
+        }
+    }
+}
+
+/// Says how the corners of `--view` were fitted to a frame of `width` by
+/// `height` pixels, when their aspect ratio isn't the frame's.
+fn report_fit(corners: view::Corners, width: u32, height: u32, fit: view::Fit) {
+    if corners.fits(width, height) {
+        return;
+    }
+    let (x_range, y_range) = corners.fitted(width, height, fit);
+    events::say(format!(
+        "The view {} isn't shaped like the {}x{} frame, so --fit {} makes it {},{},{},{}",
+        corners,
+        width,
+        height,
+        fit.name(),
+        x_range.0,
+        x_range.1,
+        y_range.0,
+        y_range.1
+    ));
+}
+
+/// Adds the deepest of `frames` to the location file at `path`, for
+/// `--save-location`.
+fn save_location(
+    args: &cli::Args,
+    zoom: &Zoom,
+    frames: &[u32],
+    path: &str,
+) -> Result<(), RustlebrotError> {
+    let plans = frames.iter().map(|&frame| zoom.plan(frame));
+    let deepest = plans.max_by(|a, b| a.camera.magnification.total_cmp(&b.camera.magnification));
+    let Some(plan) = deepest else {
+        return Ok(());
+    };
+    // Relative to the fractal's default extent on the shorter side, which
+    // the ranges of the first frame needn't have been.
+    let extent = plan.range_widths.0.min(plan.range_widths.1);
+    let location = Location {
+        name: args.location_name.clone(),
+        notes: None,
+        fractal: args.fractal,
+        newton: args.newton.clone(),
+        center: plan.camera.center.clone(),
+        magnification: 2.0 * args.fractal.default_half_width() / extent,
+        max_iter: plan.camera.max_iter,
+        rotation: plan.rotation,
+    };
+    bookmarks::save(path, &location)?;
+    events::say(format!("Saved the location of frame {} to {}", plan.frame, path));
+    Ok(())
+}
+
+/// Fits the cost model of `--time-budget` to probes of the first, middle
+/// and last of `frames`, each rendered at a quarter of the width and
+/// height, at its limit and four times that. Probes are at least 128
+/// pixels a side, as far as the frames are, or the time every frame takes
+/// whatever its size would swamp them.
+///
+/// The probes are planned at the frame's full size, so they're rendered
+/// in the precision the frame will be.
+fn calibrate(zoom: &Zoom, frames: Range<u32>) -> CostModel {
+    let last = frames.end.saturating_sub(1).max(frames.start);
+    let mut probed = vec![frames.start, frames.start + (last - frames.start) / 2, last];
+    probed.dedup();
+    let (width, height) = (
+        (zoom.width / 4).max(zoom.width.min(128)),
+        (zoom.height / 4).max(zoom.height.min(128)),
+    );
+    let plans = probed.into_iter().map(|frame| zoom.plan(frame).resized(width, height));
+    let mut probes = Vec::new();
+    for mut plan in plans {
+        let max_iter = plan.camera.max_iter;
+        for factor in [1, 4] {
+            plan.camera.max_iter = max_iter.saturating_mul(factor);
+            let start = Instant::now();
+            let coloring = zoom.options.coloring;
+            let (rendered, _) = render_fractal(zoom, &plan, coloring, None, None, None);
+            let Rendered::Escape(buffer) = rendered else {
+                unreachable!("time budgets are only taken in escape mode")
+            };
+            probes.push(Probe {
+                frame: plan.frame,
+                max_iter: plan.camera.max_iter,
+                samples: buffer.values.len() as u64,
+                seconds: start.elapsed().as_secs_f64(),
+            });
+        }
+    }
+    CostModel::new(&probes)
+}
+
+/// `frames` with the iteration limits the zoom gives them, before any time
+/// budget scales them.
+fn unscaled_limits(zoom: &mut Zoom, frames: &[u32]) -> Vec<(u32, u32)> {
+    let budget = zoom.time_budget.take();
+    let limits = frames.iter().map(|&frame| (frame, zoom.camera(frame).max_iter)).collect();
+    zoom.time_budget = budget;
+    limits
+}
+
+/// Fits `frames` into the `seconds` left of `--time-budget` by the cost
+/// `model`, and reports the limits they get.
+fn fit_budget(zoom: &mut Zoom, model: CostModel, frames: &[u32], seconds: f64) {
+    let limits = unscaled_limits(zoom, frames);
+    let samples = (zoom.width * zoom.supersample) as u64
+        * (zoom.height * zoom.supersample) as u64
+        * zoom.sub_frames() as u64;
+    let budget = TimeBudget::new(model, limits, samples, seconds);
+    let schedule = budget.schedule();
+    let (low, high) = schedule
+        .iter()
+        .fold((u32::MAX, 0), |(low, high), &(_, max_iter)| (low.min(max_iter), high.max(max_iter)));
+    let predicted = budget.predicted_seconds();
+    let format = |seconds: f64| progress::format_duration(Duration::from_secs_f64(seconds));
+    events::say(format!(
+        "Fitting {} frames into {}: supersample {}, max_iter scaled by {:.3} to {}..{}, predicted \
+         to take {}.",
+        frames.len(),
+        format(seconds),
+        zoom.supersample,
+        budget.scale(),
+        low,
+        high,
+        format(predicted),
+    ));
+    if predicted > seconds {
+        events::say(format!(
+            "Warning: even at the lowest max_iter the frames are predicted to take {}, past the \
+             budget; rendering all of them anyway",
+            format(predicted)
+        ));
+    }
+    zoom.time_budget = Some(Mutex::new(budget));
+}
+
+/// Bytes per pixel the files of a run of `zoom` come to, for every one of
+/// its `frames` and for the video `encoder` makes of them.
+fn footprint(
+    args: &cli::Args,
+    zoom: &Zoom,
+    encoder: Option<EncoderKind>,
+    frames: usize,
+) -> Footprint {
+    // Every palette has frames and a video of its own, and so does every
+    // output, with its share of the pixels.
+    let area = |output: &Output| output.width as f64 * output.height as f64;
+    let images = match zoom.outputs.first() {
+        Some(largest) => zoom.outputs.iter().map(area).sum::<f64>() / area(largest),
+        None => zoom.palettes.len() as f64,
+    };
+    // Piped frames are only saved every `preview_every`, if at all.
+    let saved = match (args.pipe_video, zoom.preview_every) {
+        (false, _) => 1.0,
+        (true, Some(every)) => 1.0 / every as f64,
+        (true, None) => 0.0,
+    };
+    let mut frame = 0.0;
+    if zoom.mode != Mode::Escape || zoom.export.png {
+        let alpha = args.alpha != Alpha::None;
+        let colored = args.image_format.bytes_per_pixel(args.colors.bit_depth, alpha);
+        frame += saved * images * colored;
+    }
+    // An f32 channel of escape values, and another of distances.
+    if zoom.export.exr {
+        frame += match zoom.options.coloring {
+            Coloring::Distance => 8.0,
+            _ => 4.0,
+        };
+    }
+    // An f64 for every sample.
+    if args.dump_iterations {
+        frame += 8.0 * (zoom.supersample * zoom.supersample) as f64;
+    }
+    // Piped frames are spooled for the way back of a boomerang, uncompressed,
+    // until the video is done.
+    if args.pipe_video && args.video.sequence == Some(Sequence::Boomerang) {
+        let channels = if zoom.video_alpha { 4.0 } else { 3.0 };
+        frame += match args.colors.bit_depth {
+            BitDepth::Eight => channels,
+            BitDepth::Sixteen => 2.0 * channels,
+        };
+    }
+    let video = match (encoder, args.pipe_video) {
+        (Some(encoder), false) => encoder.bytes_per_pixel() * images,
+        (_, true) => EncoderKind::Ffmpeg.bytes_per_pixel(),
+        (None, false) => 0.0,
+    };
+    // Some frames are shown more than once, with --direction in-out or
+    // --video-sequence.
+    let fps = encoder.unwrap_or(EncoderKind::Ffmpeg).frame_rate(&args.video);
+    let shown = Order::new(frames, fps, &args.video).shown();
+    Footprint {
+        frame,
+        video: video * shown as f64 / frames.max(1) as f64,
+    }
+}
+
+/// The settings `--quality` gives defaults for, as the run has them, if
+/// it was given.
+fn quality_record(args: &cli::Args) -> Option<QualityRecord> {
+    let quality = args.quality?;
+    Some(QualityRecord {
+        quality: quality.name.to_string(),
+        width: args.width,
+        height: args.height,
+        supersample: args.adaptive.map_or(args.supersample, |adaptive| adaptive.samples),
+        adaptive: args.adaptive.is_some(),
+        max_iter: args.max_iter,
+        jpeg_quality: match args.image_format {
+            ImageFormat::Jpeg { quality } => Some(quality),
+            _ => None,
+        },
+        bit_depth: match args.colors.bit_depth {
+            BitDepth::Eight => 8,
+            BitDepth::Sixteen => 16,
+        },
+    })
+}
+
+/// The order the video of `frames` is encoded in by `encoder`, for the
+/// manifest, if `--video-sequence` gave one.
+fn sequence_record(
+    options: &video::VideoOptions,
+    encoder: Option<EncoderKind>,
+    frames: Range<u32>,
+) -> Option<SequenceRecord> {
+    let sequence = options.sequence?;
+    let fps = encoder.unwrap_or(EncoderKind::Ffmpeg).frame_rate(options);
+    let order = Order::new(frames.len(), fps, options);
+    let number = |index: usize| frames.start + index as u32;
+    Some(SequenceRecord {
+        sequence: sequence.spec(),
+        spans: order.spans.iter().map(|&(first, last)| (number(first), number(last))).collect(),
+        held: order.held,
+    })
+}
+
+/// The precisions `frames` are rendered in, as `f64, then double-double
+/// from frame 212`.
+fn precision_regimes(zoom: &Zoom, frames: &[u32]) -> String {
+    let mut regimes: Vec<(u32, Precision)> = Vec::new();
+    for &frame in frames {
+        let precision = zoom.plan(frame).precision;
+        if regimes.last().is_none_or(|&(_, last)| last != precision) {
+            regimes.push((frame, precision));
+        }
+    }
+    let mut described = Vec::new();
+    for (index, (frame, precision)) in regimes.into_iter().enumerate() {
+        described.push(match index {
+            0 => precision.name().to_string(),
+            _ => format!("then {} from frame {}", precision.name(), frame),
+        });
+    }
+    described.join(", ")
+}
+
+/// Checks that the run of `frames` can go ahead: that its directories take
+/// files, and that the disk has room for them. Where someone can read it,
+/// or with `--dry-run`, it also sums up the time and room the run takes
+/// and the precisions it goes through, and asks whether to go ahead unless
+/// `--yes` says to. Returns whether to go ahead.
+///
+/// The time is predicted like a time budget's, by the cost `model` of
+/// `--time-budget` with the seconds `left` of it if there is one, or else
+/// by probing the frames, which only escape mode can be.
+fn preflight(
+    args: &cli::Args,
+    zoom: &mut Zoom,
+    frames: &[u32],
+    encoder: Option<EncoderKind>,
+    model: Option<(&CostModel, f64)>,
+) -> Result<bool, RustlebrotError> {
+    // A dry run leaves the directories to the run.
+    if !args.dry_run {
+        for set in &zoom.palettes {
+            export::check_writable(&set.dir)?;
+        }
+    }
+    let summarized = args.dry_run || events::asks();
+    let (width, height) = zoom.frame_size();
+    let samples = (zoom.width * zoom.supersample) as u64
+        * (zoom.height * zoom.supersample) as u64
+        * zoom.sub_frames() as u64;
+    let times = match (frames.first(), frames.last()) {
+        (Some(&first), Some(&last)) if summarized && zoom.mode == Mode::Escape => Some(match model {
+            Some((model, left)) => {
+                let schedule = match &zoom.time_budget {
+                    Some(budget) => budget.lock().unwrap().schedule(),
+                    None => {
+                        let limits = unscaled_limits(zoom, frames);
+                        TimeBudget::new(model.clone(), limits, samples, left).schedule()
+                    }
+                };
+                Times::new(model, &schedule, samples)
+            }
+            None => {
+                let model = calibrate(zoom, first..last + 1);
+                Times::new(&model, &unscaled_limits(zoom, frames), samples)
+            }
+        }),
+        _ => None,
+    };
+    let estimate = Estimate::new(
+        frames.len(),
+        times,
+        width as u64 * height as u64,
+        footprint(args, zoom, encoder, frames.len()),
+    );
+    // The directories of a dry run may not be there yet, so the room is
+    // looked up where they would be made.
+    let free_bytes = Path::new(&zoom.palettes[0].dir)
+        .ancestors()
+        .find(|dir| dir.is_dir())
+        .and_then(|dir| export::free_bytes(dir.to_str()?));
+    let animated = args.keyframes.is_some()
+        || args.camera_path.is_some()
+        || args.pan.is_some()
+        || args.rotation_per_frame != 0.0
+        || args.c_path.is_some()
+        || args.colors.palette_drift != 0.0
+        || args.iter_schedule.is_some();
+    let findings = preflight::check(&preflight::Run {
+        zoom_factor: args.zoom_factor,
+        animated,
+        bytes: estimate.bytes(),
+        free_bytes,
+    });
+    for finding in &findings {
+        match finding.is_error() && !args.dry_run {
+            true => {
+                return Err(RustlebrotError::System(format!(
+                    "not enough room in {}: {}",
+                    zoom.palettes[0].dir, finding
+                )))
+            }
+            false => events::say(format!("Warning: {}", finding)),
+        }
+    }
+    if !summarized {
+        return Ok(true);
+    }
+    let format = |seconds: f64| progress::format_duration(Duration::from_secs_f64(seconds));
+    // Frames under a minute are told apart to the hundredth of a second.
+    let per_frame = |seconds: f64| match seconds < 60.0 {
+        true => format!("{:.2}s", seconds),
+        false => format(seconds),
+    };
+    events::say(match estimate.times {
+        Some(times) => format!(
+            "Preflight: {} frames of {}x{}, predicted to take {} ({} for the first frame, {} for \
+             the last)",
+            estimate.frames,
+            width,
+            height,
+            format(times.total),
+            per_frame(times.first),
+            per_frame(times.last)
+        ),
+        None => format!(
+            "Preflight: {} frames of {}x{}, whose time is only predicted in escape mode",
+            estimate.frames, width, height
+        ),
+    });
+    events::say(format!(
+        "Preflight: about {} of frames and {} of video{}",
+        preflight::format_bytes(estimate.frame_bytes),
+        preflight::format_bytes(estimate.video_bytes),
+        match free_bytes {
+            Some(free_bytes) => format!(", with {} free", preflight::format_bytes(free_bytes)),
+            None => String::new(),
+        }
+    ));
+    events::say(format!("Preflight: rendered in {}", precision_regimes(zoom, frames)));
+    if args.dry_run || args.yes || !events::asks() {
+        return Ok(true);
+    }
+    events::say_partial("Go ahead? [y/N] ");
+    let mut answer = String::new();
+    std::io::stdin()
+        .read_line(&mut answer)
+        .map_err(|e| RustlebrotError::System(format!("can't read the answer: {}", e)))?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Renders and saves `frames`, `parallelism` at a time, returning the times
+/// of the ones that were finished, and the early stop if the zoom ended in
+/// one.
+///
+/// Rendered frames are handed to `SAVERS` threads of their own to be
+/// encoded and saved, so the next frames are computed meanwhile; once
+/// `SAVE_QUEUE` are waiting for them, the threads rendering wait too.
+/// Frames can finish out of order, but are logged, recorded and sent to
+/// the `video` encoder in order. After Ctrl-C, an early stop or an error,
+/// frames that haven't been started are left out, so this returns once the
+/// frames in progress are saved, with the first error if there was one.
+fn generate_frames<'a>(
+    frames: Vec<u32>,
+    zoom: &Zoom<'a>,
+    manifest: ManifestWriter,
+    stats: StatsWriter,
+    video: Option<Box<dyn Encoder>>,
+    parallelism: usize,
+    references: BTreeMap<u32, Reference>,
+) -> Result<(Vec<FrameTimes>, Option<StoppedEarly>), RustlebrotError> {
+    // The passes of a progressive preview are computed along with the frame.
+    let previews = match zoom.preview_progressive {
+        Some(_) => PREVIEW_STRIDES.iter().map(|&stride| zoom.height.div_ceil(stride)).sum(),
+        None => 0,
+    };
+    // Resampled frames have no rows to count.
+    let height = zoom.roi.map_or(zoom.height, |roi| roi.height);
+    let rows_per_frame = (zoom.mode == Mode::Escape && zoom.expmap.is_none()).then_some(
+        (height * zoom.supersample) as u64 * zoom.sub_frames() as u64 + previews as u64,
+    );
+    let progress = Progress::new(&frames, rows_per_frame);
+    let piped = video.is_some();
+    // Previews are drawn on a thread of their own, one at a time.
+    let (previews, drawn) = match zoom.term_preview {
+        Some(preview) => {
+            let (previews, drawn) = mpsc::sync_channel(1);
+            (Some(previews), Some((preview.protocol, drawn)))
+        }
+        None => (None, None),
+    };
+    let queue = FrameQueue {
+        frames: &frames,
+        ahead: parallelism + SAVE_QUEUE + SAVERS,
+        early_stop: zoom.early_stop,
+        budget: zoom.time_budget.as_ref().map(|budget| (budget, Instant::now())),
+        state: Mutex::new(QueueState {
+            started: 0,
+            written: 0,
+            waiting: BTreeMap::new(),
+            manifest,
+            stats,
+            video,
+            previews,
+            finished: Vec::new(),
+            uniform: None,
+            stopped_early: None,
+            failed: None,
+        }),
+        written: Condvar::new(),
+    };
+    let (unsaved, saving) = mpsc::sync_channel::<(usize, Unsaved<'a>)>(SAVE_QUEUE);
+    let saving = Mutex::new(saving);
+    // Takes frames from the queue until it runs out, and hands them to the
+    // savers. Incremental frames need the one before, which only works with
+    // one frame at a time, as does the reference of stabilized colors. Its
+    // frames carry on from the reference of the one before, whether that
+    // was rendered or kept.
+    let render = |pool: Option<&ThreadPool>, unsaved: &SyncSender<(usize, Unsaved<'a>)>| {
+        let mut previous: Option<(u32, FramePlan, EscapeBuffer)> = None;
+        let mut references = references.clone();
+        while let Some(index) = queue.take() {
+            let frame = frames[index];
+            let reuse = previous
+                .as_ref()
+                .filter(|(last, ..)| last + 1 == frame && !zoom.is_keyframe(frame))
+                .map(|(_, plan, buffer)| (plan, buffer));
+            let reference = frame.checked_sub(1).and_then(|before| references.get(&before));
+            let render = || render_frame(frame, zoom, piped, reuse, reference, &progress);
+            let rendered = match pool {
+                Some(pool) => pool.install(render),
+                None => render(),
+            };
+            match rendered {
+                Ok((mut rendered, kept)) => {
+                    if let Some(reference) = rendered.finished.reference.take() {
+                        references.insert(frame, reference);
+                    }
+                    // The savers only stop once this hangs up, so they're
+                    // there to take it.
+                    unsaved.send((index, rendered)).expect("frames are saved until rendered");
+                    previous = kept.map(|(plan, buffer)| (frame, plan, buffer));
+                }
+                Err(e) => queue.fail(frame, e),
+            }
+        }
+    };
+    // Saves rendered frames until the threads rendering them are done. An
+    // error stops frames from being started, and the ones rendered already
+    // are still saved.
+    let save = || loop {
+        let next = saving.lock().unwrap().recv();
+        let Ok((index, unsaved)) = next else {
+            break;
+        };
+        let frame = unsaved.finished.record.frame;
+        match unsaved.save(zoom) {
+            Ok(finished) => queue.finish(index, finished, &progress),
+            Err(e) => queue.fail(frame, e),
+        }
+    };
+    // With several frames at once, every frame renders on a pool of its
+    // share of the threads.
+    let pool = || {
+        ThreadPoolBuilder::new()
+            .num_threads(frame_threads(parallelism))
+            .build()
+            .map_err(|e| RustlebrotError::System(format!("can't start render threads: {}", e)))
+    };
+    let pools = match parallelism {
+        1 => Vec::new(),
+        _ => (0..parallelism).map(|_| pool()).collect::<Result<Vec<ThreadPool>, _>>()?,
+    };
+    let rendering = AtomicBool::new(true);
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            while rendering.load(Ordering::Relaxed) {
+                progress.tick();
+                std::thread::sleep(progress::REDRAW_INTERVAL);
+            }
+        });
+        let drawer = drawn.map(|(protocol, drawn)| {
+            let progress = &progress;
+            scope.spawn(move || {
+                for preview in drawn {
+                    progress.say(&protocol.encode(&preview, terminal::columns()));
+                }
+            })
+        });
+        let savers: Vec<_> = (0..SAVERS).map(|_| scope.spawn(save)).collect();
+        if pools.is_empty() {
+            render(None, &unsaved);
+        } else {
+            std::thread::scope(|workers| {
+                for pool in &pools {
+                    workers.spawn(|| render(Some(pool), &unsaved));
+                }
+            });
+        }
+        // Hanging up lets the savers finish the frames left and stop.
+        drop(unsaved);
+        for saver in savers {
+            saver.join().expect("saving frames doesn't panic");
+        }
+        // Hanging up lets the drawer finish the last preview and stop.
+        queue.state.lock().unwrap().previews = None;
+        if let Some(drawer) = drawer {
+            drawer.join().expect("drawing previews doesn't panic");
+        }
+        rendering.store(false, Ordering::Relaxed);
+        progress.finish();
+    });
+    let state = queue.state.into_inner().unwrap();
+    // A video of the frames so far is still worth having, even after an
+    // error.
+    let video = state.video.map(|video| video.finish());
+    if let Some(Ok(output)) = &video {
+        video_saved(output);
+    }
+    match (state.failed, video) {
+        (Some(e), _) | (None, Some(Err(e))) => Err(e),
+        _ => Ok((state.finished, state.stopped_early)),
+    }
+}
+
+/// The references the frames of `records`, from the manifest at `path`,
+/// were colored by with `--stabilize-colors`, by frame.
+fn color_references<'r>(
+    path: &str,
+    records: impl IntoIterator<Item = &'r FrameRecord>,
+) -> Result<BTreeMap<u32, Reference>, RustlebrotError> {
+    let mut references = BTreeMap::new();
+    for record in records {
+        if let Some(quantiles) = &record.color_reference {
+            let reference = Reference::from_quantiles(quantiles.clone()).map_err(|e| {
+                RustlebrotError::format(path, format!("frame {}: {}", record.frame, e))
+            })?;
+            references.insert(record.frame, reference);
+        }
+    }
+    Ok(references)
+}
+
+/// Reports that the video was saved to `output`.
+fn video_saved(output: &str) {
+    events::say(format!("Zoom saved to {}", output));
+    events::emit(&Event::VideoCompleted { path: output });
+}
+
+/// Runs the `survey` subcommand, which writes a contact sheet of every
+/// level and the JSON of their cells, and prints the best cell of the last.
+#[cfg(feature = "bigfloat")]
+fn survey(args: &[String]) -> Result<(), RustlebrotError> {
+    let args = cli::parse_survey(args).map_err(RustlebrotError::Argument)?;
+    let (x_digits, y_digits) = args
+        .center
+        .clone()
+        .unwrap_or_else(|| ("0".to_string(), "0".to_string()));
+    // Keep every digit given, like find-target does.
+    let parse = |digits: &str| {
+        let bits = (digits.len() as f64 * std::f64::consts::LOG2_10) as usize + 64;
+        bigfloat::parse_decimal(digits, bits).expect("centers are validated when read")
+    };
+    let center = (parse(&x_digits), parse(&y_digits));
+    let radius = args.radius.unwrap_or_else(|| args.fractal.default_half_width());
+    let options = SurveyOptions {
+        grid: args.grid,
+        depth: args.depth,
+        max_iter: args.max_iter,
+        thumbnail: args.thumbnail,
+    };
+    let levels = match args.fractal {
+        FractalKind::Mandelbrot => survey::survey(&Mandelbrot, center, radius, &options),
+        FractalKind::Tricorn => survey::survey(&Tricorn, center, radius, &options),
+        FractalKind::Newton
+        | FractalKind::Formula
+        | FractalKind::Julia
+        | FractalKind::Lyapunov => {
+            unreachable!("survey rejects --fractal newton, julia and lyapunov")
+        }
+    };
+    let paths = sheet::write_survey(&levels, &options, args.fractal.name(), &args.output_dir)?;
+    for path in &paths {
+        events::say(format!("Saved {}", path));
+    }
+
+    let last = levels.last().expect("a survey has at least one level");
+    let best = &last.cells[last.best];
+    let (width, height) = last.cell_size(args.grid);
+    let x = sheet::decimal(&best.center.0, width / args.thumbnail as f64);
+    let y = sheet::decimal(&best.center.1, width / args.thumbnail as f64);
+    let magnification = args.fractal.default_half_width() / (width.max(height) / 2.0);
+    let frames = magnification.log2().ceil().max(0.0) as u32;
+    println!("Best cell: {}, {} (score {:.3})", x, y, best.score.abs());
+    println!(
+        "Render with: rustlebrot {} 0 {} 2 --fractal {} --center {},{}",
+        last.max_iter,
+        frames + 1,
+        args.fractal.name(),
+        x,
+        y,
+    );
+    Ok(())
+}
+
+/// Runs the `find-target` subcommand and prints the center it settles on.
+#[cfg(feature = "bigfloat")]
+fn find_target(args: &[String]) -> Result<(), RustlebrotError> {
+    let args = cli::parse_find_target(args).map_err(RustlebrotError::Argument)?;
+    let (x_digits, y_digits) = args
+        .center
+        .clone()
+        .unwrap_or_else(|| ("0".to_string(), "0".to_string()));
+    // Keep every digit given, since the search may start deep already.
+    let parse = |digits: &str| {
+        let bits = (digits.len() as f64 * std::f64::consts::LOG2_10) as usize + 64;
+        bigfloat::parse_decimal(digits, bits).expect("centers are validated when read")
+    };
+    let center = (parse(&x_digits), parse(&y_digits));
+    let options = TargetOptions {
+        depth: args.depth,
+        max_iter: args.max_iter,
+        seed: args.seed,
+    };
+    let half_width = args.fractal.default_half_width();
+    let target = match args.fractal {
+        FractalKind::Mandelbrot => target::find_target(&Mandelbrot, center, half_width, &options),
+        FractalKind::Tricorn => target::find_target(&Tricorn, center, half_width, &options),
+        FractalKind::Newton
+        | FractalKind::Formula
+        | FractalKind::Julia
+        | FractalKind::Lyapunov => {
+            unreachable!("find-target rejects --fractal newton, julia and lyapunov")
+        }
+    };
+
+    target.contact.save(&args.contact).map_err(|e| RustlebrotError::encode(&args.contact, e))?;
+
+    // Enough digits to place the center well within a pixel of a 1200 pixel
+    // frame at the final depth.
+    let bits = bigfloat::required_bits((0.0, 0.0), target.half_width / 1200.0);
+    let digits = (bits as f64 * std::f64::consts::LOG10_2).ceil() as usize;
+    let x = bigfloat::to_decimal(&target.center.0, digits);
+    let y = bigfloat::to_decimal(&target.center.1, digits);
+    let frames = target.magnification.log2().ceil() as u32;
+    println!("Center: {}, {}", x, y);
+    println!("Magnification: {:.3e}", target.magnification);
+    println!("Suggested max_iter: {}", target.max_iter);
+    println!("Probes saved to {}", args.contact);
+    let Some(path) = &args.save_location else {
+        println!(
+            "Render with: rustlebrot {} 0 {} 2 --fractal {} --center {},{}",
+            target.max_iter,
+            frames + 1,
+            args.fractal.name(),
+            x,
+            y,
+        );
+        return Ok(());
+    };
+    let location = Location {
+        name: args.location_name.clone(),
+        notes: Some(format!("found by find-target with seed {}", args.seed)),
+        fractal: args.fractal,
+        newton: None,
+        center: (x, y),
+        magnification: target.magnification,
+        max_iter: target.max_iter,
+        rotation: 0.0,
+    };
+    bookmarks::save(path, &location)?;
+    println!("Location saved to {}", path);
+    let name = match &args.location_name {
+        Some(name) => format!(" --location-name {}", name),
+        None => String::new(),
+    };
+    println!("Render with: rustlebrot render --location {}{}", path, name);
+    Ok(())
+}
+
+/// Runs the `find-nucleus` subcommand and prints the nucleus it settles on
+/// as a location.
+#[cfg(feature = "bigfloat")]
+fn find_nucleus(args: &[String]) -> Result<(), RustlebrotError> {
+    let args = cli::parse_find_nucleus(args).map_err(RustlebrotError::Argument)?;
+    // Keep every digit given, like find-target does.
+    let parse = |digits: &str| {
+        let bits = (digits.len() as f64 * std::f64::consts::LOG2_10) as usize + 64;
+        bigfloat::parse_decimal(digits, bits).expect("centers are validated when read")
+    };
+    let near = (parse(&args.near.0), parse(&args.near.1));
+    let period = match args.period {
+        Some(period) => period,
+        None => nucleus::find_period(&near, args.radius, nucleus::MAX_PERIOD).ok_or_else(|| {
+            RustlebrotError::Argument(format!(
+                "found no nucleus within --radius {:e} of {},{} up to period {}; try a \
+                 larger --radius or give --period",
+                args.radius,
+                args.near.0,
+                args.near.1,
+                nucleus::MAX_PERIOD
+            ))
+        })?,
+    };
+    events::detail(format!("Looking for a nucleus of period {}", period));
+    let found = nucleus::find_nucleus(&near, args.radius, period)
+        .map_err(RustlebrotError::Argument)?;
+
+    let digits = decimal::digits_of_bits(found.bits);
+    let location = Location {
+        name: args.location_name.clone(),
+        notes: Some(format!(
+            "the period {} nucleus of a minibrot of size {:.3e}, found by find-nucleus",
+            found.period, found.size
+        )),
+        fractal: FractalKind::Mandelbrot,
+        newton: None,
+        center: (
+            bigfloat::to_decimal(&found.center.0, digits),
+            bigfloat::to_decimal(&found.center.1, digits),
+        ),
+        magnification: found.magnification(),
+        max_iter: found.max_iter(),
+        rotation: 0.0,
+    };
+    let Some(path) = &args.save_location else {
+        println!("{}", bookmarks::to_json(&[location]));
+        return Ok(());
+    };
+    bookmarks::save(path, &location)?;
+    println!("Location saved to {}", path);
+    println!("Period: {}", found.period);
+    println!("Size: {:.3e}", found.size);
+    let name = match &args.location_name {
+        Some(name) => format!(" --location-name {}", name),
+        None => String::new(),
+    };
+    println!("Render with: rustlebrot render --location {}{}", path, name);
+    Ok(())
+}
+
+/// Runs the `orbit` subcommand, which traces the orbit of one point and
+/// prints it, or writes it to `--output`, along with what became of it.
+fn orbit(args: &[String]) -> Result<(), RustlebrotError> {
+    let args = cli::parse_orbit(args).map_err(RustlebrotError::Argument)?;
+    for path in args.output.iter().map(|(path, _)| path).chain(&args.plot) {
+        still::check_new(path, args.overwrite)?;
+    }
+    let decimals = |point: &(String, String)| {
+        let parse = |digits: &str| Decimal::parse(digits).expect("points are validated when read");
+        (parse(&point.0), parse(&point.1))
+    };
+    let point = decimals(&args.point);
+    let reference = decimals(args.reference.as_ref().unwrap_or(&args.point));
+    let conjugate = args.fractal == FractalKind::Tricorn;
+    let (max_iter, bailout) = (args.max_iter, args.bailout);
+    let traced = trace::trace(&point, &reference, args.precision, conjugate, max_iter, bailout);
+
+    match &args.output {
+        Some((path, format)) => {
+            let settings = orbit::Settings {
+                fractal: args.fractal.name(),
+                precision: args.precision.name(),
+                point: &args.point,
+                reference: args.reference.as_ref(),
+                max_iter,
+                bailout,
+            };
+            orbit::write(path, *format, &traced, &settings)?;
+            events::say(format!("Orbit of {} points saved to {}", traced.points.len(), path));
+        }
+        None => print!("{}", orbit::to_csv(&traced)),
+    }
+    if let Some(path) = &args.plot {
+        let size = (args.width, args.height);
+        let image = match args.fractal {
+            FractalKind::Tricorn => trace::plot(&traced, &Tricorn, max_iter, bailout, size),
+            _ => trace::plot(&traced, &Mandelbrot, max_iter, bailout, size),
+        };
+        image.save(path).map_err(|e| RustlebrotError::encode(path, e))?;
+        events::say(format!("Plot saved to {}", path));
+    }
+    println!("{}", orbit::verdict(&traced));
+    Ok(())
+}
+
+/// Runs the `recolor` subcommand.
+fn recolor(args: &[String], default_dir: &str) -> Result<(), RustlebrotError> {
+    let start_time = Instant::now();
+    let mut args = cli::parse_recolor(args, default_dir).map_err(RustlebrotError::Argument)?;
+    // The video goes the way the run's did, in its order unless another
+    // is given.
+    let manifest = Manifest::read(&format!("{}/manifest.json", args.dir)).ok();
+    let direction = manifest.as_ref().map(|manifest| manifest.direction.as_str());
+    args.video.there_and_back = direction == Some(Direction::InOut.name());
+    args.video.sequence = args.video.sequence.or(manifest.as_ref().and_then(Manifest::sequence));
+    let stem = format!("{}/rust_out", args.dir);
+    let encoder = match args.no_video {
+        true => None,
+        false => {
+            let encoder = EncoderKind::resolve(args.encoder)?;
+            Some((encoder, encoder.output(&stem, &args.video)?))
+        }
+    };
+    let filenames = manifest.as_ref().map(recorded_filenames).transpose()?.unwrap_or_default();
+    // The frames are colored again in the format the run saved them in.
+    let format = manifest.as_ref().map(recorded_image_format).transpose()?;
+    let format = format.unwrap_or(ImageFormat::Png);
+    if !format.holds(args.colors.bit_depth) {
+        return Err(RustlebrotError::Argument(format!(
+            "the frames of {} are {} frames, which have 8-bit channels; --bit-depth 16 needs \
+             a run saved as png or tiff",
+            args.dir,
+            format.name()
+        )));
+    }
+    let frames = recolor_frames(&args, manifest.as_ref(), &filenames, format)?;
+    events::say(format!("Recolored {} frames in {:.2?}.", frames.len(), start_time.elapsed()));
+    let Some((encoder, output)) = encoder else {
+        return Ok(());
+    };
+    let start = frames[0].0;
+    let paths: Vec<String> = frames.into_iter().map(|(_, path)| path).collect();
+    let bit_depth = args.colors.bit_depth;
+    let output = video::encode_frames(&paths, &output, bit_depth, encoder, &args.video)
+        .map_err(|e| {
+            let name = args.colors.palettes()[0].name();
+            let pattern = filenames.ffmpeg_pattern(name, format.extension());
+            let pattern = pattern.map(|pattern| format!("{}/{}", args.dir, pattern));
+            encoding_failed(e, pattern, &paths, start, &stem, bit_depth, &args.video)
+        })?;
+    video_saved(&output);
+    Ok(())
+}
+
+/// Runs the `animate-palette` subcommand, which colors a fixed view over
+/// and over with the palette cycled a little further every frame, round
+/// once over the loop so it plays on without a seam. The escape times are
+/// rendered once, or read from a frame dumped with `--dump-iterations`, so
+/// every frame only takes coloring and saving.
+fn animate_palette(args: &[String]) -> Result<(), RustlebrotError> {
+    let start_time = Instant::now();
+    let args = cli::parse_animate_palette(args).map_err(RustlebrotError::Argument)?;
+    let view = &args.view;
+    let stem = format!("{}/rust_out", args.output_dir);
+    let encoder = match args.no_video {
+        true => None,
+        false => {
+            let encoder = EncoderKind::resolve(args.encoder)?;
+            Some((encoder, encoder.output(&stem, &args.video)?))
+        }
+    };
+    let source = &view.colors.palettes()[0];
+    let (colormap, cycle) = palette(&view.colors, source);
+    let still = plan_still(view, &colormap, cycle);
+
+    let filenames = FilenameTemplate::default();
+    let name = source.name();
+    let paths: Vec<String> = (0..args.frames)
+        .map(|frame| {
+            let name = FrameName {
+                frame,
+                magnification: view.magnification,
+                palette: name,
+                ext: "png",
+            };
+            frame_file(&args.output_dir, &filenames, &name)
+        })
+        .collect();
+    if !view.overwrite {
+        if let Some(path) = paths.iter().find(|path| Path::new(path).exists()) {
+            return Err(RustlebrotError::Argument(format!(
+                "{} exists already; pass --overwrite to replace the frames, or write them \
+                 somewhere else with --output-dir",
+                path
+            )));
+        }
+    }
+    create_parents(paths.iter().map(String::as_str))?;
+
+    let start = Instant::now();
+    let (buffer, header) = match &args.from {
+        Some(from) => {
+            let dump = from.strip_suffix(".npy").or_else(|| from.strip_suffix(".json"));
+            let dump = dump.unwrap_or(from);
+            let (json, npy) = (format!("{}.json", dump), format!("{}.npy", dump));
+            let header = export::read_header(&json)?;
+            if let Some(coloring) = args.coloring {
+                if !header.coloring.recolors_as(coloring) {
+                    return Err(RustlebrotError::Argument(format!(
+                        "{} holds {} values, which can't be colored as {}",
+                        json,
+                        header.coloring.name(),
+                        coloring.name()
+                    )));
+                }
+            }
+            let mut buffer = export::read_npy(&npy, &header)?;
+            buffer.coloring = args.coloring.unwrap_or(header.coloring);
+            view.colors.check_transfer(buffer.coloring).map_err(RustlebrotError::Argument)?;
+            events::say(format!("Read the escape times of {} in {:.2?}", npy, start.elapsed()));
+            (buffer, header)
+        }
+        None => {
+            let still = still?;
+            let buffer = match view.fractal {
+                FractalKind::Mandelbrot => still.compute(&Mandelbrot),
+                FractalKind::Tricorn => still.compute(&Tricorn),
+                FractalKind::Newton
+                | FractalKind::Formula
+                | FractalKind::Julia
+                | FractalKind::Lyapunov => {
+                    unreachable!("animate-palette rejects --fractal newton, julia and lyapunov")
+                }
+            };
+            events::say(format!("Rendered the view once in {:.2?}", start.elapsed()));
+            let center = match &view.center {
+                Some(center) => center.clone(),
+                None => {
+                    let (x, y) = view.fractal.default_center();
+                    (x.to_string(), y.to_string())
+                }
+            };
+            let header = Header {
+                frame: 0,
+                width: buffer.width,
+                height: buffer.height,
+                samples: still.samples,
+                x_range: still.x_range,
+                y_range: still.y_range,
+                flip_y: false,
+                rotation: view.rotation,
+                center,
+                zoom_factor: 1.0,
+                max_iter: view.max_iter,
+                palette_iter: still.colors.palette_iter,
+                coloring: view.coloring,
+            };
+            (buffer, header)
+        }
+    };
+
+    let colors = ColorOptions {
+        palette_iter: header.palette_iter,
+        histogram_clip: view.colors.histogram_clip,
+        transfer: view.colors.transfer,
+        reference: None,
+        colormap: &colormap,
+        cycle,
+        interior: view.colors.interior,
+        bit_depth: view.colors.bit_depth,
+        dither: view.colors.dither,
+        alpha: Alpha::None,
+        phase: view.colors.phase,
+        lighting: view.colors.lighting,
+        script: None,
+        interior_coloring: None,
+        contours: view.colors.contours,
+        silhouette: view.colors.silhouette,
+        line_art: view.colors.line_art(),
+    };
+    for (frame, path) in (0..args.frames).zip(&paths) {
+        let start = Instant::now();
+        let colors = ColorOptions {
+            cycle: cycle.looped(frame, args.frames),
+            ..colors
+        };
+        let img = colorize(&buffer, &colors);
+        let colored = start.elapsed();
+        let metadata = Metadata {
+            frame,
+            center: &header.center,
+            x_range: header.x_range,
+            y_range: header.y_range,
+            rotation: header.rotation,
+            zoom_factor: header.zoom_factor,
+            max_iter: header.max_iter,
+            palette: name,
+        };
+        export::save_frame(path, &img, ImageFormat::Png, &metadata)
+            .map_err(|e| e.in_frame(frame))?;
+        events::say(format!(
+            "Frame {} colored in {:.2?} and saved in {:.2?}",
+            frame,
+            colored,
+            start.elapsed() - colored
+        ));
+    }
+    events::say(format!("Colored {} frames in {:.2?}.", args.frames, start_time.elapsed()));
+    let Some((encoder, output)) = encoder else {
+        return Ok(());
+    };
+    let bit_depth = view.colors.bit_depth;
+    let output = video::encode_frames(&paths, &output, bit_depth, encoder, &args.video)
+        .map_err(|e| {
+            let pattern = filenames.ffmpeg_pattern(name, "png");
+            let pattern = pattern.map(|pattern| format!("{}/{}", args.output_dir, pattern));
+            encoding_failed(e, pattern, &paths, 0, &stem, bit_depth, &args.video)
+        })?;
+    events::say(format!("Loop saved to {}", output));
+    events::emit(&Event::VideoCompleted { path: &output });
+    Ok(())
+}
+
+/// Runs the `animate-iter` subcommand, which renders one view at a limit
+/// rising from frame to frame and encodes the frames into a video.
+///
+/// Every frame takes the samples that escaped in the one before, where
+/// they escape at the same iteration, and only iterates those that hadn't.
+/// The palette is spread over the limit of the last frame throughout, so a
+/// sample keeps its color from the frame it escapes in on.
+fn animate_iter(args: &[String]) -> Result<(), RustlebrotError> {
+    let start_time = Instant::now();
+    let args = cli::parse_animate_iter(args).map_err(RustlebrotError::Argument)?;
+    let view = &args.view;
+    let stem = format!("{}/rust_out", args.output_dir);
+    let encoder = match args.no_video {
+        true => None,
+        false => {
+            let encoder = EncoderKind::resolve(args.encoder)?;
+            Some((encoder, encoder.output(&stem, &args.video)?))
+        }
+    };
+    let source = &view.colors.palettes()[0];
+    let (colormap, cycle) = palette(&view.colors, source);
+    let still = plan_still(view, &colormap, cycle)?;
+
+    let filenames = FilenameTemplate::default();
+    let name = source.name();
+    let paths: Vec<String> = (0..args.frames)
+        .map(|frame| {
+            let name = FrameName {
+                frame,
+                magnification: view.magnification,
+                palette: name,
+                ext: "png",
+            };
+            frame_file(&args.output_dir, &filenames, &name)
+        })
+        .collect();
+    if !view.overwrite {
+        if let Some(path) = paths.iter().find(|path| Path::new(path).exists()) {
+            return Err(RustlebrotError::Argument(format!(
+                "{} exists already; pass --overwrite to replace the frames, or write them \
+                 somewhere else with --output-dir",
+                path
+            )));
+        }
+    }
+    create_parents(paths.iter().map(String::as_str))?;
+
+    let center = match &view.center {
+        Some(center) => center.clone(),
+        None => {
+            let (x, y) = view.fractal.default_center();
+            (x.to_string(), y.to_string())
+        }
+    };
+    let mut previous: Option<EscapeBuffer> = None;
+    for (frame, path) in (0..args.frames).zip(&paths) {
+        let start = Instant::now();
+        let max_iter = args.curve.at(args.iter_start, view.max_iter, frame, args.frames);
+        let reuse = previous.as_ref().map(|previous| Reuse {
+            previous,
+            scale: 1.0,
+            rotation: Rotation::degrees(0.0),
+            threshold: 0.0,
+            raised_limit: true,
+            shift: None,
+        });
+        let frame_still = Still {
+            options: RenderOptions {
+                max_iter,
+                reuse,
+                ..still.options
+            },
+            ..still
+        };
+        let buffer = match view.fractal {
+            FractalKind::Mandelbrot => frame_still.compute(&Mandelbrot),
+            FractalKind::Tricorn => frame_still.compute(&Tricorn),
+            FractalKind::Newton
+            | FractalKind::Formula
+            | FractalKind::Julia
+            | FractalKind::Lyapunov => {
+                unreachable!("animate-iter rejects --fractal newton, julia and lyapunov")
+            }
+        };
+        let computed = start.elapsed();
+        // The samples taken are those that escaped in the frame before.
+        let escaped = |sample: &&Sample| matches!(sample, Sample::Value(_) | Sample::Phase { .. });
+        let taken = previous.iter().flat_map(|previous| &previous.values).filter(escaped).count();
+        let img = colorize(&buffer, &still.colors);
+        let metadata = Metadata {
+            frame,
+            center: &center,
+            x_range: still.x_range,
+            y_range: still.y_range,
+            rotation: view.rotation,
+            zoom_factor: 1.0,
+            max_iter,
+            palette: name,
+        };
+        export::save_frame(path, &img, ImageFormat::Png, &metadata)
+            .map_err(|e| e.in_frame(frame))?;
+        events::say(format!(
+            "Frame {} at max_iter {}: {:.1}% of the samples taken from the frame before, the \
+             rest computed in {:.2?}, colored and saved in {:.2?}",
+            frame,
+            max_iter,
+            100.0 * taken as f64 / buffer.values.len() as f64,
+            computed,
+            start.elapsed() - computed
+        ));
+        previous = Some(buffer);
+    }
+    events::say(format!("Rendered {} frames in {:.2?}.", args.frames, start_time.elapsed()));
+    let Some((encoder, output)) = encoder else {
+        return Ok(());
+    };
+    let bit_depth = view.colors.bit_depth;
+    let output = video::encode_frames(&paths, &output, bit_depth, encoder, &args.video)
+        .map_err(|e| {
+            let pattern = filenames.ffmpeg_pattern(name, "png");
+            let pattern = pattern.map(|pattern| format!("{}/{}", args.output_dir, pattern));
+            encoding_failed(e, pattern, &paths, 0, &stem, bit_depth, &args.video)
+        })?;
+    events::say(format!("Video saved to {}", output));
+    events::emit(&Event::VideoCompleted { path: &output });
+    Ok(())
+}
+
+/// Runs the `merge` subcommand, which puts the frames of the shards of a
+/// zoom together in one directory and encodes them into its video.
+fn merge(args: &[String]) -> Result<(), RustlebrotError> {
+    let start_time = Instant::now();
+    let mut args = cli::parse_merge(args).map_err(RustlebrotError::Argument)?;
+    let shards = read_shards(&args.shards)?;
+    let first = &shards[0].2;
+    args.video.there_and_back = first.direction == Direction::InOut.name();
+    args.video.sequence = args.video.sequence.or(first.sequence());
+    let names = match first.palettes.is_empty() {
+        true => vec![first.palette.clone()],
+        false => first.palettes.clone(),
+    };
+    let palette_dir = |dir: &str, name: &str| match names.len() {
+        1 => dir.to_string(),
+        _ => format!("{}/{}", dir, name),
+    };
+    let dir = args.output_dir.trim_end_matches('/');
+    // The merged manifest would take the place of the shard's, which
+    // couldn't be merged again.
+    let output = std::fs::canonicalize(dir).ok();
+    let inside = shards.iter().find(|(shard_dir, _, _)| {
+        output.is_some() && std::fs::canonicalize(shard_dir).ok() == output
+    });
+    if let Some((_, record, _)) = inside {
+        return Err(RustlebrotError::Argument(format!(
+            "{} is the directory of shard {}; merge into a directory of its own with --output-dir",
+            dir, record.shard.index
+        )));
+    }
+    let stems: Vec<String> =
+        names.iter().map(|name| format!("{}/rust_out", palette_dir(dir, name))).collect();
+    if names.len() > 1 && args.video.output.is_some() {
+        return Err(RustlebrotError::Argument(
+            "--video-out names one video, but several palettes make one each; they're saved \
+             with their frames"
+                .to_string(),
+        ));
+    }
+    let encoder = match args.no_video {
+        true => None,
+        false => {
+            let encoder = EncoderKind::resolve(args.encoder)?;
+            let outputs = stems.iter().map(|stem| encoder.output(stem, &args.video));
+            Some((encoder, outputs.collect::<Result<Vec<_>, _>>()?))
+        }
+    };
+
+    // Every file of every frame, from the directories of the shards to the
+    // same place in the merged one. The shards agree on the template, as on
+    // every other setting.
+    let filenames = recorded_filenames(first)?;
+    let image_ext = recorded_image_format(first)?.extension();
+    let file = |dir: &str, record: &FrameRecord, palette: &str, ext: &str| {
+        let name = FrameName {
+            frame: record.frame,
+            magnification: record.magnification,
+            palette,
+            ext,
+        };
+        frame_file(dir, &filenames, &name)
+    };
+    let mut records: Vec<&FrameRecord> = Vec::new();
+    let mut files = Vec::new();
+    for (shard_dir, record, manifest) in &shards {
+        let frames = record.shard.frames(record.zoom_start..record.zoom_end);
+        for record in manifest.frames.iter().filter(|record| frames.contains(&record.frame)) {
+            for name in &names {
+                let from = file(&palette_dir(shard_dir, name), record, name, image_ext);
+                files.push((from, file(&palette_dir(dir, name), record, name, image_ext)));
+            }
+            for extension in ["exr", "npy", "json"] {
+                let from = file(shard_dir, record, &names[0], extension);
+                files.push((from, file(dir, record, &names[0], extension)));
+            }
+            records.push(record);
+        }
+    }
+    files.retain(|(from, _)| Path::new(from).exists());
+    records.sort_by_key(|record| record.frame);
+    let existing = files.iter().find(|(_, to)| !args.video.overwrite && Path::new(to).exists());
+    if let Some((_, to)) = existing {
+        return Err(RustlebrotError::Argument(format!(
+            "{} exists already; pass --overwrite to replace the frames, or merge them somewhere \
+             else with --output-dir",
+            to
+        )));
+    }
+    for name in &names {
+        let dir = palette_dir(dir, name);
+        std::fs::create_dir_all(&dir).map_err(|e| RustlebrotError::write(&dir, e))?;
+    }
+    create_parents(files.iter().map(|(_, to)| to.as_str()))?;
+    for (from, to) in &files {
+        link_or_copy(from, to)?;
+    }
+
+    // The order of the video is of the frames of the whole zoom now.
+    let kind = encoder.as_ref().map(|(encoder, _)| *encoder);
+    let frames = match (records.first(), records.last()) {
+        (Some(first), Some(last)) => first.frame..last.frame + 1,
+        _ => 0..0,
+    };
+    let merged = Manifest {
+        shard: None,
+        time_budget: None,
+        sequence: sequence_record(&args.video, kind, frames),
+        ..first.clone()
+    };
+    let mut manifest = ManifestWriter::create(&format!("{}/manifest.json", dir), &merged)?;
+    for record in &records {
+        manifest.append(record)?;
+    }
+    let tables: Vec<(String, Vec<u32>)> = shards
+        .iter()
+        .map(|(shard_dir, _, manifest)| {
+            let frames = manifest.frames.iter().map(|record| record.frame).collect();
+            (format!("{}/stats.csv", shard_dir), frames)
+        })
+        .collect();
+    StatsWriter::merge(&format!("{}/stats.csv", dir), &tables)?;
+    events::say(format!(
+        "Merged {} frames of {} shards into {} in {:.2?}.",
+        records.len(),
+        shards.len(),
+        dir,
+        start_time.elapsed()
+    ));
+
+    let image = |name: &str, record| file(&palette_dir(dir, name), record, name, image_ext);
+    // Without colored frames there is nothing to encode.
+    let encoder = encoder.filter(|_| Path::new(&image(&names[0], records[0])).exists());
+    let Some((encoder, outputs)) = encoder else {
+        return Ok(());
+    };
+    let bit_depth = export::read_bit_depth(&image(&names[0], records[0]))?;
+    for ((name, output), stem) in names.iter().zip(&outputs).zip(&stems) {
+        let paths: Vec<String> = records.iter().map(|record| image(name, record)).collect();
+        events::emit(&Event::VideoStarted {
+            path: output,
+            encoder: encoder.name(),
+        });
+        let output = video::encode_frames(&paths, output, bit_depth, encoder, &args.video)
+            .map_err(|e| {
+                let pattern = filenames.ffmpeg_pattern(name, image_ext);
+                let pattern = pattern.map(|pattern| {
+                    format!("{}/{}", palette_dir(dir, name), pattern)
+                });
+                let start = records[0].frame;
+                encoding_failed(e, pattern, &paths, start, stem, bit_depth, &args.video)
+            })?;
+        video_saved(&output);
+    }
+    Ok(())
+}
+
+/// Runs the `assemble` subcommand, which encodes the frames a render saved
+/// into its video again, without rendering any.
+fn assemble(args: &[String], default_dir: &str) -> Result<(), RustlebrotError> {
+    let mut args = cli::parse_assemble(args, default_dir).map_err(RustlebrotError::Argument)?;
+    let dir = args.dir.trim_end_matches('/');
+    let path = format!("{}/manifest.json", dir);
+    if !Path::new(&path).exists() {
+        return Err(RustlebrotError::Argument(format!(
+            "assemble encodes the frames of a render, but {} has no manifest.json; give the \
+             directory the render wrote to",
+            dir
+        )));
+    }
+    let manifest = Manifest::read(&path)?;
+    args.video.there_and_back = manifest.direction == Direction::InOut.name();
+    args.video.sequence = args.video.sequence.or(manifest.sequence());
+    let names = match manifest.palettes.is_empty() {
+        true => vec![manifest.palette.clone()],
+        false => manifest.palettes.clone(),
+    };
+    let name = args.palette.clone().unwrap_or_else(|| names[0].clone());
+    if !names.contains(&name) {
+        return Err(RustlebrotError::Argument(format!(
+            "the frames of {} are colored with {}, not {}",
+            dir,
+            names.join(", "),
+            name
+        )));
+    }
+    let palette_dir = manifest.frames_dir(dir, &name);
+    let (check, palettes) = (args.check, std::slice::from_ref(&name));
+    let mut gaps = check_frames(&manifest, &path, dir, palettes, check.full_decode)?;
+    if !gaps.is_empty() && check.repair {
+        repair(&manifest, &path, &gaps)?;
+        gaps = check_frames(&manifest, &path, dir, palettes, check.full_decode)?;
+    }
+    if !gaps.is_empty() {
+        if !args.allow_gaps {
+            return Err(RustlebrotError::Argument(format!(
+                "{} of the {} frames of {} are missing or corrupt, which would leave gaps in the \
+                 video; render them again with --repair, or pass --allow-gaps to leave them out",
+                gaps.len(),
+                gaps.checked,
+                dir
+            )));
+        }
+        events::say(format!("Leaving out {} missing or corrupt frames", gaps.len()));
+    }
+    // A resumed run can record a frame again; the last record is the one on
+    // disk.
+    let records: BTreeMap<u32, &FrameRecord> = manifest
+        .frames
+        .iter()
+        .filter(|record| !gaps.faulty.contains(&record.frame))
+        .map(|record| (record.frame, record))
+        .collect();
+    let Some(&first) = records.values().next() else {
+        return Err(RustlebrotError::Argument(format!("{} has no frames to encode", dir)));
+    };
+    let filenames = recorded_filenames(&manifest)?;
+    let ext = recorded_image_format(&manifest)?.extension();
+    let paths: Vec<String> = records
+        .values()
+        .map(|record| {
+            let name = FrameName {
+                frame: record.frame,
+                magnification: record.magnification,
+                palette: &name,
+                ext,
+            };
+            frame_file(&palette_dir, &filenames, &name)
+        })
+        .collect();
+    let bit_depth = export::read_bit_depth(&paths[0])?;
+    let stem = format!("{}/rust_out", palette_dir);
+    let encoder = EncoderKind::resolve(args.encoder)?;
+    let output = encoder.output(&stem, &args.video)?;
+    events::emit(&Event::VideoStarted {
+        path: &output,
+        encoder: encoder.name(),
+    });
+    let output = video::encode_frames(&paths, &output, bit_depth, encoder, &args.video)
+        .map_err(|e| {
+            let pattern = filenames.ffmpeg_pattern(&name, ext);
+            let pattern = pattern.map(|pattern| format!("{}/{}", palette_dir, pattern));
+            encoding_failed(e, pattern, &paths, first.frame, &stem, bit_depth, &args.video)
+        })?;
+    video_saved(&output);
+    Ok(())
+}
+
+/// Runs the `verify` subcommand, which checks that every frame of a run is
+/// there and sound, at the size of the run, and with `--repair` renders the
+/// ones that aren't again.
+fn verify(args: &[String], default_dir: &str) -> Result<(), RustlebrotError> {
+    let args = cli::parse_verify(args, default_dir).map_err(RustlebrotError::Argument)?;
+    let dir = args.dir.trim_end_matches('/');
+    let path = format!("{}/manifest.json", dir);
+    if !Path::new(&path).exists() {
+        return Err(RustlebrotError::Argument(format!(
+            "verify checks the frames a render recorded, but {} has no manifest.json; give the \
+             directory the render wrote to",
+            dir
+        )));
+    }
+    let manifest = Manifest::read(&path)?;
+    let names = match manifest.palettes.is_empty() {
+        true => vec![manifest.palette.clone()],
+        false => manifest.palettes.clone(),
+    };
+    let palettes = match &args.palette {
+        Some(name) if !names.contains(name) => {
+            return Err(RustlebrotError::Argument(format!(
+                "the frames of {} are colored with {}, not {}",
+                dir,
+                names.join(", "),
+                name
+            )))
+        }
+        Some(name) => vec![name.clone()],
+        None => names,
+    };
+    let check = args.check;
+    let mut gaps = check_frames(&manifest, &path, dir, &palettes, check.full_decode)?;
+    if !gaps.is_empty() && check.repair {
+        repair(&manifest, &path, &gaps)?;
+        gaps = check_frames(&manifest, &path, dir, &palettes, check.full_decode)?;
+    }
+    if !gaps.is_empty() {
+        return Err(RustlebrotError::Argument(format!(
+            "{} of the {} frames of {} are missing or corrupt{}",
+            gaps.len(),
+            gaps.checked,
+            dir,
+            match check.repair {
+                true => "",
+                false => "; pass --repair to render them again",
+            }
+        )));
+    }
+    events::say(format!("All {} frames of {} are there and sound", gaps.checked, dir));
+    Ok(())
+}
+
+/// The frames of a run `check_frames` found wanting.
+struct Gaps {
+    /// How many frames were looked for.
+    checked: usize,
+    /// The frames the run recorded whose files are missing or corrupt, in
+    /// the colors of any of the palettes checked.
+    faulty: BTreeSet<u32>,
+    /// The frames the run has no record of, as a run stopped partway
+    /// leaves between the ones it finished.
+    unrecorded: Vec<u32>,
+}
+
+impl Gaps {
+    fn len(&self) -> usize {
+        self.faulty.len() + self.unrecorded.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Checks the frames of the run of `manifest` in `dir`, in the colors of
+/// `palettes`, and reports the ones missing or corrupt.
+///
+/// The frames looked for are those of its shard for a shard of a zoom,
+/// and otherwise those from the first of the run up to the last it
+/// recorded, as the ones after that can't be told from ones it stopped
+/// before.
+fn check_frames(
+    manifest: &Manifest,
+    manifest_path: &str,
+    dir: &str,
+    palettes: &[String],
+    full_decode: bool,
+) -> Result<Gaps, RustlebrotError> {
+    // A resumed run can record a frame again; the last record is the one on
+    // disk.
+    let records: BTreeMap<u32, &FrameRecord> =
+        manifest.frames.iter().map(|record| (record.frame, record)).collect();
+    let (Some(&first), Some(&last)) = (records.keys().next(), records.keys().next_back()) else {
+        return Err(RustlebrotError::Argument(format!("{} has no frames yet", dir)));
+    };
+    // Runs from before render-frame don't record their arguments, which
+    // the region of a --roi is only found in.
+    let run = match manifest.args.is_empty() {
+        true => None,
+        false => Some(
+            cli::parse_args(&manifest.args)
+                .map_err(|e| RustlebrotError::format(manifest_path, e))?,
+        ),
+    };
+    let expected = match (&manifest.shard, &run) {
+        (Some(record), _) => record.shard.frames(record.zoom_start..record.zoom_end),
+        (None, Some(run)) => run.zoom_start.min(first)..last + 1,
+        (None, None) => first..last + 1,
+    };
+    let size = match run.as_ref().and_then(|run| run.roi).filter(|roi| !roi.fill) {
+        Some(roi) => (roi.width, roi.height),
+        None => (manifest.width, manifest.height),
+    };
+    let unrecorded: Vec<u32> =
+        expected.clone().filter(|frame| !records.contains_key(frame)).collect();
+    for frame in &unrecorded {
+        events::say(format!("Frame {} isn't recorded in {}", frame, manifest_path));
+    }
+    let checked = expected.len();
+    let recorded: Vec<&FrameRecord> =
+        expected.filter_map(|frame| records.get(&frame).copied()).collect();
+    let filenames = recorded_filenames(manifest)?;
+    let format = recorded_image_format(manifest)?;
+    let mut faulty = BTreeSet::new();
+    for palette in palettes {
+        let palette_dir = manifest.frames_dir(dir, palette);
+        let paths: Vec<String> = recorded
+            .iter()
+            .map(|record| {
+                let name = FrameName {
+                    frame: record.frame,
+                    magnification: record.magnification,
+                    palette,
+                    ext: format.extension(),
+                };
+                frame_file(&palette_dir, &filenames, &name)
+            })
+            .collect();
+        let faults = verify::check(&paths, format, size, full_decode)?;
+        for ((record, path), fault) in recorded.iter().zip(&paths).zip(faults) {
+            match fault {
+                Some(Fault::Missing) => {
+                    events::say(format!("Frame {} is missing, at {}", record.frame, path))
+                }
+                Some(Fault::Corrupt(e)) => {
+                    events::say(format!("Frame {} is corrupt, {}", record.frame, e))
+                }
+                None => continue,
+            }
+            faulty.insert(record.frame);
+        }
+    }
+    Ok(Gaps {
+        checked,
+        faulty,
+        unrecorded,
+    })
+}
+
+/// Renders the frames `gaps` found missing or corrupt again from the run
+/// of `manifest` at `manifest_path`, as `--repair` does. Frames the run
+/// never recorded are only rendered by resuming it.
+fn repair(manifest: &Manifest, manifest_path: &str, gaps: &Gaps) -> Result<(), RustlebrotError> {
+    if let Some(frame) = gaps.unrecorded.first() {
+        return Err(RustlebrotError::Argument(format!(
+            "{} frames, from frame {} on, aren't recorded in {}, which only resuming the run \
+             renders; render it again with --resume",
+            gaps.unrecorded.len(),
+            frame,
+            manifest_path
+        )));
+    }
+    let frames: Vec<u32> = gaps.faulty.iter().copied().collect();
+    events::say(format!("Rendering {} frames again", frames.len()));
+    let args = cli::RenderFrameArgs {
+        manifest: manifest_path.to_string(),
+        frame: frames[0],
+        scale: 1,
+        samples: None,
+        output: None,
+        overwrite: false,
+    };
+    render_again(manifest, &frames, &args)
+}
+
+/// Runs the `montage` subcommand, which puts thumbnails of the frames of a
+/// run together in one image. The frames are the ones its manifest
+/// records, or found by their names in a directory without one.
+fn montage(args: &[String], default_dir: &str) -> Result<(), RustlebrotError> {
+    let args = cli::parse_montage(args, default_dir).map_err(RustlebrotError::Argument)?;
+    let dir = args.dir.trim_end_matches('/');
+    let manifest_path = args.manifest.clone().unwrap_or_else(|| format!("{}/manifest.json", dir));
+    let frames = match args.manifest.is_some() || Path::new(&manifest_path).exists() {
+        true => recorded_frames(&Manifest::read(&manifest_path)?, dir, args.palette.as_deref())?,
+        false => {
+            if args.palette.is_some() {
+                return Err(RustlebrotError::Argument(format!(
+                    "{} has no manifest.json to find the frames of a palette by; give the \
+                     directory of the palette's frames",
+                    dir
+                )));
+            }
+            montage::frames_in(dir)?
+        }
+    };
+    if frames.is_empty() {
+        return Err(RustlebrotError::Argument(format!("{} has no frames yet", dir)));
+    }
+    let output = args.output.clone().unwrap_or_else(|| format!("{}/montage.png", dir));
+    if !args.overwrite && Path::new(&output).exists() {
+        return Err(RustlebrotError::Argument(format!(
+            "{} exists already, pass --overwrite to replace it",
+            output
+        )));
+    }
+    let shown = montage::sample(&frames, args.every);
+    let montage = montage::compose(&shown, args.columns, args.thumbnail, args.max_size)?;
+    montage.image.save(&output).map_err(|e| RustlebrotError::encode(&output, e))?;
+    events::say(format!(
+        "Montage of {} frames saved to {} ({}x{}){}",
+        montage.shown,
+        output,
+        montage.image.width(),
+        montage.image.height(),
+        match montage.missing {
+            0 => String::new(),
+            missing => format!(", {} of them missing", missing),
+        }
+    ));
+    Ok(())
+}
+
+/// The frames the run of `manifest` in `dir` recorded, in the colors of
+/// `palette` or its first, for `montage`.
+fn recorded_frames(
+    manifest: &Manifest,
+    dir: &str,
+    palette: Option<&str>,
+) -> Result<Vec<montage::Frame>, RustlebrotError> {
+    let names = match manifest.palettes.is_empty() {
+        true => vec![manifest.palette.clone()],
+        false => manifest.palettes.clone(),
+    };
+    let name = palette.map_or_else(|| names[0].clone(), str::to_string);
+    if !names.contains(&name) {
+        return Err(RustlebrotError::Argument(format!(
+            "the frames of {} are colored with {}, not {}",
+            dir,
+            names.join(", "),
+            name
+        )));
+    }
+    let palette_dir = manifest.frames_dir(dir, &name);
+    let filenames = recorded_filenames(manifest)?;
+    let ext = recorded_image_format(manifest)?.extension();
+    // A resumed run can record a frame again; the last record is the one on
+    // disk.
+    let records: BTreeMap<u32, &FrameRecord> =
+        manifest.frames.iter().map(|record| (record.frame, record)).collect();
+    let frames = records.into_values().map(|record| {
+        let name = FrameName {
+            frame: record.frame,
+            magnification: record.magnification,
+            palette: &name,
+            ext,
+        };
+        montage::Frame {
+            frame: record.frame,
+            path: frame_file(&palette_dir, &filenames, &name),
+            // Manifests from before it was recorded have 0.
+            magnification: Some(record.magnification).filter(|&zoom| zoom > 0.0),
+        }
+    });
+    Ok(frames.collect())
+}
+
+/// Runs the `render-frame` subcommand, which renders one frame of a run
+/// again from the arguments and records in its manifest. Unless it is made
+/// larger or sampled more, the frame comes out as the run saved it.
+fn rerender_frame(args: &[String]) -> Result<(), RustlebrotError> {
+    let args = cli::parse_render_frame(args).map_err(RustlebrotError::Argument)?;
+    let manifest = Manifest::read(&args.manifest)?;
+    render_again(&manifest, &[args.frame], &args)
+}
+
+/// Renders `frames` of the run of `manifest` again and saves them over
+/// their files, or as `args` says otherwise, as `render-frame` does one.
+fn render_again(
+    manifest: &Manifest,
+    frames: &[u32],
+    args: &cli::RenderFrameArgs,
+) -> Result<(), RustlebrotError> {
+    if manifest.args.is_empty() {
+        return Err(RustlebrotError::Argument(format!(
+            "{} doesn't record the arguments of its run, which is older than render-frame; \
+             delete the frame and render the run again with --resume to replace it",
+            args.manifest
+        )));
+    }
+    let recorded: BTreeSet<u32> = manifest.frames.iter().map(|record| record.frame).collect();
+    let mut records = Vec::new();
+    for &frame in frames {
+        // A resumed run can record a frame again; the last record is the
+        // one on disk.
+        let record = manifest.frames.iter().rev().find(|record| record.frame == frame);
+        let Some(record) = record else {
+            let rendered = match (recorded.first(), recorded.last()) {
+                (Some(first), Some(last)) => format!("frames {} to {}", first, last),
+                _ => "no frames yet".to_string(),
+            };
+            return Err(RustlebrotError::Argument(format!(
+                "{} has no frame {}; its run rendered {}",
+                args.manifest, frame, rendered
+            )));
+        };
+        records.push(record);
+    }
+    let mut run =
+        cli::parse_args(&manifest.args).map_err(|e| RustlebrotError::format(&args.manifest, e))?;
+    if let (Some(recorded), Some(path)) = (&manifest.camera_path, &run.camera_path) {
+        if recorded.crc32 != format!("{:08x}", path.crc32) {
+            return Err(RustlebrotError::format(
+                &path.file,
+                "the camera path has changed since the run followed it, so frames rendered \
+                 from it now wouldn't match the others; put back the file the run had",
+            ));
+        }
+    }
+    let resized = args.scale > 1 || args.samples.is_some();
+    if run.expmap && resized {
+        return Err(RustlebrotError::Argument(
+            "the frames of an --expmap run are resampled from its strips, which only make them \
+             as the run did, without --scale or --samples"
+                .to_string(),
+        ));
+    }
+    // The frames are where the manifest is, wherever the run was started.
+    let dir = Path::new(&args.manifest).parent().and_then(Path::to_str);
+    run.output_dir = dir.filter(|dir| !dir.is_empty()).unwrap_or(".").to_string();
+    run.width *= args.scale;
+    run.height *= args.scale;
+    run.roi = run.roi.map(|roi| Roi {
+        x: roi.x * args.scale,
+        y: roi.y * args.scale,
+        width: roi.width * args.scale,
+        height: roi.height * args.scale,
+        ..roi
+    });
+    if let Some(samples) = args.samples {
+        run.supersample = samples;
+    }
+    let colormaps: Vec<(Colormap, Cycle)> =
+        run.colors.palettes().iter().map(|source| palette(&run.colors, source)).collect();
+    let filenames = recorded_filenames(manifest)?;
+    // A file of its own is named by a template without placeholders but
+    // the extension.
+    let output = match &args.output {
+        Some(output) => {
+            if !args.overwrite && Path::new(output).exists() {
+                return Err(RustlebrotError::Argument(format!(
+                    "{} exists already, pass --overwrite to replace it",
+                    output
+                )));
+            }
+            let path = Path::new(output);
+            let dir = path.parent().and_then(Path::to_str).filter(|dir| !dir.is_empty());
+            let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default();
+            let stem = stem.replace('{', "{{").replace('}', "}}");
+            let template = FilenameTemplate::parse(&format!("{}.{{ext}}", stem))
+                .map_err(RustlebrotError::Argument)?;
+            Some((dir.unwrap_or(".").to_string(), template))
+        }
+        None => None,
+    };
+
+    let mut zoom = zoom_of(&run, &colormaps);
+    zoom.filenames = &filenames;
+    zoom.export = Export {
+        png: true,
+        exr: false,
+    };
+    zoom.dump_iterations = false;
+    zoom.preview_progressive = None;
+    zoom.recorded_limit = records.first().map(|record| (record.frame, record.max_iter));
+    if let Some(budget) = manifest.time_budget.as_ref().filter(|_| args.samples.is_none()) {
+        zoom.supersample = budget.supersample;
+    }
+    if zoom.incremental.take().is_some() {
+        events::say(
+            "The run took what it could of its frames from the ones before with --incremental; \
+             this one is rendered in full, so it can differ slightly.",
+        );
+    }
+    let software = format!("rustlebrot {}", env!("CARGO_PKG_VERSION"));
+    if manifest.software != software {
+        events::say(format!(
+            "Warning: the run was rendered by {}, which may render the frame differently than \
+             {} does.",
+            manifest.software, software
+        ));
+    }
+    throttle::configure(run.threads, run.background)?;
+    if run.expmap {
+        let frames: Vec<u32> = recorded.into_iter().collect();
+        zoom.expmap = Some(expmap_strips(&zoom, &frames, &run)?);
+    }
+    if let Some((dir, template)) = &output {
+        std::fs::create_dir_all(dir).map_err(|e| RustlebrotError::write(dir, e))?;
+        zoom.palettes.truncate(1);
+        zoom.palettes[0].dir = dir.clone();
+        zoom.output_dir = dir;
+        zoom.filenames = template;
+    }
+    for record in records {
+        zoom.recorded_limit = Some((record.frame, record.max_iter));
+        // Stabilized colors go on from the reference of the frame before.
+        let before = manifest.frames.iter().filter(|before| before.frame + 1 == record.frame);
+        let references = color_references(&args.manifest, before)?;
+        let reference = record.frame.checked_sub(1).and_then(|before| references.get(&before));
+        let progress = Progress::new(&[record.frame], None);
+        let (unsaved, _) = render_frame(record.frame, &zoom, false, None, reference, &progress)?;
+        let finished = unsaved.save(&zoom)?;
+        events::say(&finished.message);
+        for path in &finished.paths {
+            events::say(format!("Saved {}", path));
+        }
+    }
+    Ok(())
+}
+
+/// Runs the `validate` subcommand, which renders one frame in each of the
+/// backends it's given and compares their smooth escape times, a pair at a
+/// time, failing if any pair differs in more of its samples than the
+/// tolerance allows.
+fn validate(args: &[String]) -> Result<(), RustlebrotError> {
+    let args = cli::parse_validate(args).map_err(RustlebrotError::Argument)?;
+    let (run, recorded) = match &args.source {
+        cli::FrameSource::Manifest(path) => {
+            let manifest = Manifest::read(path)?;
+            if manifest.args.is_empty() {
+                return Err(RustlebrotError::Argument(format!(
+                    "{} doesn't record the arguments of its run, which is older than \
+                     validate; give the arguments of the render instead",
+                    path
+                )));
+            }
+            let run =
+                cli::parse_args(&manifest.args).map_err(|e| RustlebrotError::format(path, e))?;
+            let record = manifest.frames.iter().rev().find(|record| record.frame == args.frame);
+            (run, record.map(|record| record.max_iter))
+        }
+        cli::FrameSource::Render(render) => {
+            (cli::parse_args(render).map_err(RustlebrotError::Argument)?, None)
+        }
+    };
+    let colormaps: Vec<(Colormap, Cycle)> =
+        run.colors.palettes().iter().map(|source| palette(&run.colors, source)).collect();
+    let mut zoom = zoom_of(&run, &colormaps);
+    if !zoom.frames.contains(&args.frame) {
+        return Err(RustlebrotError::Argument(format!(
+            "the run has no frame {}; it renders frames {} to {}",
+            args.frame,
+            zoom.frames.start,
+            zoom.frames.end.saturating_sub(1)
+        )));
+    }
+    if zoom.mode != Mode::Escape {
+        return Err(RustlebrotError::Argument(format!(
+            "validate compares escape times, which --mode {} doesn't render",
+            zoom.mode.name()
+        )));
+    }
+    if matches!(
+        zoom.fractal,
+        FractalKind::Newton | FractalKind::Formula | FractalKind::Julia | FractalKind::Lyapunov
+    ) {
+        return Err(RustlebrotError::Argument(format!(
+            "--fractal {} only renders in f64, so there are no backends to compare",
+            zoom.fractal.name()
+        )));
+    }
+    zoom.recorded_limit = recorded.map(|max_iter| (args.frame, max_iter));
+    // Every sample is computed, in escape times whatever the run colored.
+    zoom.options.subdivision = render::Subdivision::Off;
+    zoom.options.attractors = false;
+    let plan = zoom.plan(args.frame);
+    let center = plan.camera.approx_center();
+    for &backend in &args.backends {
+        let wanted = format!("--backends {}", backend.name());
+        if let Some(feature) = backend.missing_feature() {
+            return Err(RustlebrotError::missing_feature(feature, wanted));
+        }
+        if backend == Precision::F32 {
+            if !cfg!(feature = "simd") {
+                return Err(RustlebrotError::missing_feature("simd", wanted));
+            }
+            if plan.camera.max_iter > 1 << 24 {
+                return Err(RustlebrotError::Argument(format!(
+                    "frame {} iterates up to {} times, past the 2^24 f32 counts exactly, so \
+                     comparing it in f32 says nothing of the backend",
+                    args.frame, plan.camera.max_iter
+                )));
+            }
+        }
+        // A backend that can't tell the samples apart renders the frame
+        // in blocks, which it would fail for without being wrong.
+        if !backend.resolves(center, plan.sample_size) {
+            return Err(RustlebrotError::Argument(format!(
+                "frame {} is too deep to compare in {}: its samples are {:.3e} apart, and {} \
+                 only tells points {:.3e} apart around its center, so it renders in blocks \
+                 however right it is; compare {} at a shallower frame",
+                args.frame,
+                backend.name(),
+                plan.sample_size,
+                backend.name(),
+                backend.resolution(center),
+                backend.name()
+            )));
+        }
+    }
+    let mut pairs = Vec::new();
+    for (index, &a) in args.backends.iter().enumerate() {
+        for &b in &args.backends[index + 1..] {
+            let path = format!("{}/difference_{}_{}.png", args.heatmaps, a.name(), b.name());
+            still::check_new(&path, args.overwrite)?;
+            pairs.push((a, b, path));
+        }
+    }
+    std::fs::create_dir_all(&args.heatmaps)
+        .map_err(|e| RustlebrotError::write(&args.heatmaps, e))?;
+    throttle::configure(run.threads, run.background)?;
+
+    let mut buffers = BTreeMap::new();
+    for &backend in &args.backends {
+        let start = Instant::now();
+        let plan = FramePlan {
+            precision: backend,
+            ..plan.clone()
+        };
+        let (rendered, _) = render_fractal(&zoom, &plan, Coloring::Smooth, None, None, None);
+        let Rendered::Escape(buffer) = rendered else {
+            unreachable!("validate only renders --mode escape")
+        };
+        events::say(format!(
+            "Rendered frame {} in {} in {:.2?}",
+            args.frame,
+            backend.name(),
+            start.elapsed()
+        ));
+        buffers.insert(backend.name(), buffer);
+    }
+    let text = [("Software", format!("rustlebrot {}", env!("CARGO_PKG_VERSION")))];
+    let mut failed = Vec::new();
+    for (a, b, path) in &pairs {
+        let (first, second) = (&buffers[a.name()], &buffers[b.name()]);
+        let difference = Difference::of(first, second, args.threshold);
+        let heatmap = DynamicImage::ImageRgb8(difference::heatmap(first, second, args.threshold));
+        export::save_png_text(path, &heatmap, &text)?;
+        let passed = difference.share_over() <= args.tolerance;
+        events::say(format!(
+            "{} against {}: {} of {} samples escaped in both, at most {:.3e} and on average \
+             {:.3e} iterations apart, {} in one only; {} ({:.3}%) over {}: {}. Heatmap saved to {}",
+            a.name(),
+            b.name(),
+            difference.escaped,
+            difference.samples,
+            difference.max,
+            difference.mean,
+            difference.disagreeing,
+            difference.over,
+            100.0 * difference.share_over(),
+            args.threshold,
+            if passed { "pass" } else { "fail" },
+            path
+        ));
+        if !passed {
+            failed.push(format!(
+                "{} and {} differ in {:.3}% of the samples",
+                a.name(),
+                b.name(),
+                100.0 * difference.share_over()
+            ));
+        }
+    }
+    if !failed.is_empty() {
+        return Err(RustlebrotError::Argument(format!(
+            "frame {} fails validation: {}, more than the tolerance of {}%",
+            args.frame,
+            failed.join(", "),
+            100.0 * args.tolerance
+        )));
+    }
+    events::say(format!(
+        "Frame {} passes: every pair of {} differs by more than {} iterations in at most {}% \
+         of the samples",
+        args.frame,
+        args.backends.iter().map(|backend| backend.name()).collect::<Vec<_>>().join(", "),
+        args.threshold,
+        100.0 * args.tolerance
+    ));
+    Ok(())
+}
+
+/// The output directories, shard records and manifests of the shards at
+/// `paths`, each given as its directory or its manifest, in shard order.
+///
+/// The shards have to be of one zoom, rendered with the same parameters,
+/// and make up all of it between them with every frame saved. Anything
+/// else fails, naming what doesn't fit.
+fn read_shards(paths: &[String]) -> Result<Vec<(String, ShardRecord, Manifest)>, RustlebrotError> {
+    let mut shards: Vec<(String, ShardRecord, Manifest)> = Vec::new();
+    for path in paths {
+        let (dir, manifest_path) = match path.ends_with(".json") {
+            true => {
+                let dir = Path::new(path).parent().and_then(Path::to_str);
+                (dir.filter(|dir| !dir.is_empty()).unwrap_or(".").to_string(), path.clone())
+            }
+            false => {
+                let dir = path.trim_end_matches('/');
+                (dir.to_string(), format!("{}/manifest.json", dir))
+            }
+        };
+        let manifest = Manifest::read(&manifest_path)?;
+        let Some(record) = manifest.shard.clone() else {
+            return Err(RustlebrotError::Argument(format!(
+                "{} is the manifest of a whole zoom; merge puts together the shards of one \
+                 rendered with --shard-index and --shard-count",
+                manifest_path
+            )));
+        };
+        if let Some((first_dir, _, first)) = shards.first() {
+            let (expected, found) = (first.parameters(), manifest.parameters());
+            let mut names = expected.keys().chain(found.keys());
+            if let Some(name) = names.find(|&name| expected.get(name) != found.get(name)) {
+                let shown = |parameters: &BTreeMap<String, String>| {
+                    parameters.get(name).map_or("nothing".to_string(), String::clone)
+                };
+                return Err(RustlebrotError::Argument(format!(
+                    "{} was rendered with {} {}, but {} with {}; the shards of a zoom have to \
+                     be rendered with the same parameters",
+                    dir,
+                    name,
+                    shown(&found),
+                    first_dir,
+                    shown(&expected)
+                )));
+            }
+        }
+        let index = record.shard.index;
+        let taken = shards.iter().find(|(_, other, _)| other.shard.index == index);
+        if let Some((other, _, _)) = taken {
+            return Err(RustlebrotError::Argument(format!(
+                "{} and {} are both shard {}",
+                other, dir, index
+            )));
+        }
+        shards.push((dir, record, manifest));
+    }
+    shards.sort_by_key(|(_, record, _)| record.shard.index);
+
+    let ShardRecord {
+        shard: Shard { count, .. },
+        zoom_start,
+        zoom_end,
+    } = shards[0].1;
+    let missing: Vec<String> = (0..count)
+        .filter(|&index| shards.iter().all(|(_, record, _)| record.shard.index != index))
+        .map(|index| {
+            let frames = Shard { index, count }.frames(zoom_start..zoom_end);
+            let last = frames.end.saturating_sub(1);
+            format!("shard {}, frames {} to {}", index, frames.start, last)
+        })
+        .collect();
+    if !missing.is_empty() {
+        return Err(RustlebrotError::Argument(format!(
+            "the zoom was cut into {} shards, but these are missing: {}",
+            count,
+            missing.join("; ")
+        )));
+    }
+    for (dir, record, manifest) in &shards {
+        let saved = |frame: &u32| manifest.frames.iter().any(|record| record.frame == *frame);
+        let mut missing = record.shard.frames(zoom_start..zoom_end).filter(|frame| !saved(frame));
+        if let Some(frame) = missing.next() {
+            return Err(RustlebrotError::Argument(format!(
+                "{} is missing {} of the frames of shard {}, from frame {} on; render them with \
+                 --resume before merging",
+                dir,
+                missing.count() + 1,
+                record.shard.index,
+                frame
+            )));
+        }
+    }
+    Ok(shards)
+}
+
+/// Puts the file at `from` at `to` as a hard link, or as a copy where they
+/// can't share one, as across file systems. A file at `to` is replaced.
+fn link_or_copy(from: &str, to: &str) -> Result<(), RustlebrotError> {
+    match std::fs::remove_file(to) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            return Err(RustlebrotError::write(to, e))
+        }
+        _ => {}
+    }
+    if std::fs::hard_link(from, to).is_err() {
+        std::fs::copy(from, to).map_err(|e| RustlebrotError::write(to, e))?;
+    }
+    Ok(())
+}
+
+/// The path of the file `filenames` names for `name` in `dir`.
+fn frame_file(dir: &str, filenames: &FilenameTemplate, name: &FrameName) -> String {
+    format!("{}/{}", dir, filenames.path(name))
+}
+
+/// Creates the directories of the files at `paths` the template puts in
+/// directories of their own.
+fn create_parents<'p>(paths: impl IntoIterator<Item = &'p str>) -> Result<(), RustlebrotError> {
+    for path in paths {
+        if let Some(parent) = Path::new(path).parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| RustlebrotError::write(&parent.to_string_lossy(), e))?;
+        }
+    }
+    Ok(())
+}
+
+/// Fails if the output directory, or the directory of any palette, has
+/// files of any of `frames` already, unless `overwrite` allows replacing
+/// them. This keeps a run from mixing its frames with, or writing over, the
+/// frames of an earlier one.
+fn check_existing(zoom: &Zoom, frames: Range<u32>, overwrite: bool) -> Result<(), RustlebrotError> {
+    if overwrite {
+        return Ok(());
+    }
+    for frame in frames {
+        let ext = zoom.image_format.extension();
+        let images = zoom.palettes.iter().map(|set| zoom.frame_path(Some(set), frame, ext));
+        let outputs = zoom.outputs.iter().skip(1).map(|output| zoom.output_path(output, frame));
+        let others = ["exr", "npy", "json"].map(|ext| zoom.frame_path(None, frame, ext));
+        for path in images.chain(outputs).chain(others) {
+            if Path::new(&path).exists() {
+                return Err(RustlebrotError::Argument(format!(
+                    "{} exists already; pass --overwrite to replace the frames, or write them \
+                     somewhere else with --output-dir or --run-name",
+                    path
+                )));
+            }
+        }
+    }
+    Ok(())
+}
 
-/// Computes the escape time for a point in the Mandelbrot set.
+/// The frames of `frames` that were saved already by an earlier run with
+/// the same parameters, and can be kept by `--resume`.
 ///
-/// `c` is the complex number for the point and `max_iter` is the maximum
-/// number of iterations to compute. Returns the escape time as a floating
-/// point number.
-fn mandelbrot(c: (f64, f64), max_iter: u32) -> f64 {
-    let mut z: (f64, f64) = (0.0, 0.0);
-    for i in 0..max_iter {
-        let (x, y): (f64, f64) = (z.0 * z.0 - z.1 * z.1 + c.0, 2.0 * z.0 * z.1 + c.1);
-        if x * x + y * y > 4.0 {
-            return i as f64;
+/// A frame is kept when its image decodes, along with every other file the
+/// run writes for it. Frames cut short are rendered again. A frame saved
+/// with other parameters fails the run, since the zoom would mix two
+/// renders. The text chunks of the frames don't tell the fractal or mode,
+/// so those are checked against the `previous` manifest.
+fn resumed_frames(
+    zoom: &Zoom,
+    frames: Range<u32>,
+    previous: Option<&Manifest>,
+) -> Result<Vec<u32>, RustlebrotError> {
+    if let Some(previous) = previous {
+        let found = (previous.fractal.as_str(), previous.mode.as_str());
+        let expected = (zoom.fractal.name(), zoom.mode.name());
+        if found != expected {
+            return Err(RustlebrotError::Argument(format!(
+                "the frames in {} are a {} render in {} mode, but this run is a {} render in \
+                 {} mode",
+                zoom.output_dir, found.0, found.1, expected.0, expected.1
+            )));
         }
-        z = (x, y);
     }
-    max_iter as f64
+    let mut resumed = Vec::new();
+    'frames: for frame in frames {
+        let plan = zoom.plan(frame);
+        for set in &zoom.palettes {
+            let path = zoom.frame_path(Some(set), frame, zoom.image_format.extension());
+            if !Path::new(&path).exists() {
+                continue 'frames;
+            }
+            let read = match zoom.image_format {
+                ImageFormat::Png => export::read_png(&path)
+                    .map(|png| (png.width, png.height, png.bit_depth as u8, Some(png.text))),
+                // Only PNGs keep the parameters they were rendered with, so
+                // frames in other formats are kept if they decode at the
+                // size of the run.
+                _ => image::open(&path).map_err(|e| RustlebrotError::format(&path, e)).map(|img| {
+                    let color = img.color();
+                    let bits = 8 * color.bytes_per_pixel() / color.channel_count();
+                    (img.width(), img.height(), bits, None)
+                }),
+            };
+            let (width, height, bits, text) = match read {
+                Ok(read) => read,
+                Err(e) => {
+                    events::say(format!("Rendering frame {} again, {}", frame, e));
+                    continue 'frames;
+                }
+            };
+            let depth = match zoom.colors.bit_depth {
+                BitDepth::Eight => 8,
+                BitDepth::Sixteen => 16,
+            };
+            let (frame_width, frame_height) = zoom.frame_size();
+            if (width, height, bits) != (frame_width, frame_height, depth) {
+                return Err(RustlebrotError::Argument(format!(
+                    "{} is a {}x{} frame with {}-bit channels, but this run renders {}x{} with \
+                     {}-bit",
+                    path, width, height, bits, frame_width, frame_height, depth
+                )));
+            }
+            let Some(text) = text else {
+                continue;
+            };
+            let metadata = Metadata {
+                frame,
+                center: &plan.camera.center,
+                x_range: plan.x_range,
+                y_range: plan.y_range,
+                rotation: plan.rotation,
+                zoom_factor: zoom.zoom_factor,
+                max_iter: plan.camera.max_iter,
+                palette: set.name,
+            };
+            // Frames of another version of the program are kept, as long as
+            // they show the same thing.
+            let shown = metadata.text().into_iter();
+            for (keyword, expected) in shown.filter(|&(keyword, _)| keyword != "Software") {
+                let found = text.iter().find(|(found, _)| found == keyword);
+                let found = found.map_or("nothing", |(_, text)| text.as_str());
+                if found != expected {
+                    return Err(RustlebrotError::Argument(format!(
+                        "{} was rendered with {} {}, but this run has {}; resume with the \
+                         parameters of that run, or render into another --output-dir",
+                        path, keyword, found, expected
+                    )));
+                }
+            }
+        }
+        let mut others = Vec::new();
+        if zoom.export.exr {
+            others.push("exr");
+        }
+        if zoom.dump_iterations {
+            others.extend(["npy", "json"]);
+        }
+        let others = others.iter().map(|ext| zoom.frame_path(None, frame, ext));
+        let outputs = zoom.outputs.iter().skip(1).map(|output| zoom.output_path(output, frame));
+        if others.chain(outputs).all(|path| Path::new(&path).exists()) {
+            resumed.push(frame);
+        }
+    }
+    Ok(resumed)
 }
 
-/// Renders a region of the Mandelbrot set as an image.
-///
-/// This function generates an image of a given region of the Mandelbrot set.
-/// Each pixel in the image corresponds to a point in the complex plane, and
-/// its color is determined by the number of iterations it takes for the
-/// corresponding point to escape the Mandelbrot set, according to the
-/// color_gradient function.
-///
-/// # Arguments
-///
-/// * `width` - The width of the image in pixels.
-/// * `height` - The height of the image in pixels.
-/// * `max_iter` - The maximum number of iterations to determine if a point
-///    is in the Mandelbrot set.
-/// * `x_range` - A tuple representing the range of the x coordinates in the
-///    complex plane to be rendered.
-/// * `y_range` - A tuple representing the range of the y coordinates in the
-///    complex plane to be rendered.
-///
-/// # Returns
-///
-/// * An `ImageBuffer` that represents the rendered image of the region of the
-///   Mandelbrot set.
-///
-/// # Panics
-///
-/// This function will panic if it fails to create an `ImageBuffer` from the
-/// generated pixel data. This can occur if the pixel data is not of the correct
-/// size, i.e., `width` * `height` * 3.
-///
-/// # Examples
+/// The error of encoding the frames at `paths` failing with `error`,
+/// which tells the command to encode them by hand. That reads them by their
+/// image sequence `pattern`, numbered from `start`, if they have one.
+fn encoding_failed(
+    error: RustlebrotError,
+    pattern: Option<String>,
+    paths: &[String],
+    start: u32,
+    stem: &str,
+    bit_depth: BitDepth,
+    video: &video::VideoOptions,
+) -> RustlebrotError {
+    let frames = match &pattern {
+        Some(pattern) => video::FrameFiles::Pattern {
+            pattern,
+            start,
+            count: paths.len(),
+        },
+        None => video::FrameFiles::Paths(paths),
+    };
+    RustlebrotError::Video {
+        source: Box::new(error),
+        command: video::manual_command(&frames, stem, bit_depth, video),
+    }
+}
+
+/// Colors every frame dumped to `args.dir` with `--dump-iterations` again,
+/// overwriting the frames in `format`, and returns their frame numbers and paths in
+/// frame order.
 ///
-/// ```
-/// let width = 800;
-/// let height = 800;
-/// let max_iter = 1000;
-/// let x_range = (-2.0, 1.0);
-/// let y_range = (-1.5, 1.5);
-/// let img = render_mandelbrot(width, height, max_iter, x_range, y_range);
-/// ```
-fn render_mandelbrot(
-    width: u32,
-    height: u32,
-    max_iter: u32,
-    x_range: (f64, f64),
-    y_range: (f64, f64),
-) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
-    let scalex: f64 = (x_range.1 - x_range.0) / width as f64;
-    let scaley: f64 = (y_range.1 - y_range.0) / height as f64;
+/// The headers of all the frames are checked before anything is written, so
+/// a stale or mixed up cache leaves the frames as they were.
+fn recolor_frames(
+    args: &cli::RecolorArgs,
+    manifest: Option<&Manifest>,
+    filenames: &FilenameTemplate,
+    format: ImageFormat,
+) -> Result<Vec<(u32, String)>, RustlebrotError> {
+    let dumps = dumped_frames(args, manifest, filenames, format.extension())?;
+    if dumps.is_empty() {
+        return Err(RustlebrotError::Argument(format!(
+            "no frames dumped with --dump-iterations in {}",
+            args.dir
+        )));
+    }
+
+    let headers = dumps
+        .iter()
+        .map(|dump| export::read_header(&dump.json))
+        .collect::<Result<Vec<Header>, _>>()?;
+    let first = &headers[0];
+    // Frames can be supersampled differently, as long as they make images of
+    // the same size. Their palette_iter only differs with an iteration
+    // schedule, whose frames were colored by their own.
+    let size = |header: &Header| (header.width / header.samples, header.height / header.samples);
+    for (dump, header) in dumps.iter().zip(&headers) {
+        if size(header) != size(first) {
+            let (width, height) = size(header);
+            let (first_width, first_height) = size(first);
+            return Err(RustlebrotError::format(
+                &dump.json,
+                format!(
+                    "a {}x{} frame, but {} is {}x{}; the directory mixes frames of different \
+                     runs",
+                    width, height, dumps[0].json, first_width, first_height
+                ),
+            ));
+        }
+        if let Some(coloring) = args.coloring {
+            if !header.coloring.recolors_as(coloring) {
+                return Err(RustlebrotError::Argument(format!(
+                    "{} holds {} values, which can't be colored as {}",
+                    dump.json,
+                    header.coloring.name(),
+                    coloring.name()
+                )));
+            }
+        }
+        let coloring = args.coloring.unwrap_or(header.coloring);
+        args.colors.check_transfer(coloring).map_err(RustlebrotError::Argument)?;
+    }
+
+    // Frames of stabilized colors are colored by the references they were,
+    // as a histogram of their own would differ.
+    let path = format!("{}/manifest.json", args.dir);
+    let references = match manifest {
+        Some(manifest) => color_references(&path, &manifest.frames)?,
+        None => BTreeMap::new(),
+    };
+    let (colormap, cycle) = palette(&args.colors, &args.colors.palettes()[0]);
+    let colors = ColorOptions {
+        palette_iter: first.palette_iter,
+        histogram_clip: args.colors.histogram_clip,
+        transfer: args.colors.transfer,
+        reference: None,
+        colormap: &colormap,
+        cycle,
+        interior: args.colors.interior,
+        bit_depth: args.colors.bit_depth,
+        dither: args.colors.dither,
+        alpha: Alpha::None,
+        phase: args.colors.phase,
+        lighting: args.colors.lighting,
+        script: None,
+        interior_coloring: None,
+        contours: args.colors.contours,
+        silhouette: args.colors.silhouette,
+        line_art: args.colors.line_art(),
+    };
+    create_parents(dumps.iter().map(|dump| dump.image.as_str()))?;
+    dumps.par_iter().zip(&headers).try_for_each(|(dump, header)| {
+        let recolor = || {
+            let mut buffer = export::read_npy(&dump.npy, header)?;
+            buffer.coloring = args.coloring.unwrap_or(buffer.coloring);
+            let colors = ColorOptions {
+                palette_iter: header.palette_iter,
+                reference: references.get(&header.frame),
+                cycle: cycle.at_frame(args.colors.palette_drift, header.frame),
+                lighting: colors.lighting.map(|lighting| lighting.at_frame(header.frame)),
+                ..colors
+            };
+            let metadata = Metadata {
+                frame: header.frame,
+                center: &header.center,
+                x_range: header.x_range,
+                y_range: header.y_range,
+                rotation: header.rotation,
+                zoom_factor: header.zoom_factor,
+                max_iter: header.max_iter,
+                palette: args.colors.palettes()[0].name(),
+            };
+            export::save_frame(&dump.image, &colorize(&buffer, &colors), format, &metadata)
+        };
+        recolor().map_err(|e| e.in_frame(header.frame))
+    })?;
+    let paths = dumps.into_iter().map(|dump| dump.image);
+    Ok(headers.iter().map(|header| header.frame).zip(paths).collect())
+}
 
-    let mut data = vec![0u8; (width * height * 3) as usize];
+/// The files of a frame dumped with `--dump-iterations`, and the image it
+/// is colored into again.
+struct Dump {
+    json: String,
+    npy: String,
+    image: String,
+}
 
-    data.par_chunks_mut(3).enumerate().for_each(|(i, chunk)| {
-        let x = i as u32 % width;
-        let y = i as u32 / width;
+/// The frames dumped to `args.dir`, in frame order.
+///
+/// Frames named by `--filename-template` are the frames of the `manifest`
+/// of their run that were dumped, and the others are found by their
+/// default names. Their images have the extension `ext`.
+fn dumped_frames(
+    args: &cli::RecolorArgs,
+    manifest: Option<&Manifest>,
+    filenames: &FilenameTemplate,
+    ext: &str,
+) -> Result<Vec<Dump>, RustlebrotError> {
+    let palette = args.colors.palettes()[0].name();
+    let Some(manifest) = manifest.filter(|_| *filenames != FilenameTemplate::default()) else {
+        let entries =
+            std::fs::read_dir(&args.dir).map_err(|e| RustlebrotError::read(&args.dir, e))?;
+        let mut stems: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                let stem = name.strip_suffix(".json")?;
+                stem.starts_with("mandelbrot_set_").then(|| format!("{}/{}", args.dir, stem))
+            })
+            .collect();
+        stems.sort();
+        let dumps = stems.into_iter().map(|stem| Dump {
+            json: format!("{}.json", stem),
+            npy: format!("{}.npy", stem),
+            image: format!("{}.{}", stem, ext),
+        });
+        return Ok(dumps.collect());
+    };
+    let mut records: Vec<&FrameRecord> = manifest.frames.iter().collect();
+    records.sort_by_key(|record| record.frame);
+    let path = |record: &FrameRecord, palette: &str, ext: &str| {
+        let name = FrameName {
+            frame: record.frame,
+            magnification: record.magnification,
+            palette,
+            ext,
+        };
+        frame_file(&args.dir, filenames, &name)
+    };
+    let dumps = records.into_iter().map(|record| Dump {
+        json: path(record, &manifest.palette, "json"),
+        npy: path(record, &manifest.palette, "npy"),
+        image: path(record, palette, ext),
+    });
+    Ok(dumps.filter(|dump| Path::new(&dump.json).exists()).collect())
+}
 
-        let cx = x as f64 * scalex + x_range.0;
-        let cy = y as f64 * scaley + y_range.0;
+/// The format the run of `manifest` saved its colored frames in.
+fn recorded_image_format(manifest: &Manifest) -> Result<ImageFormat, RustlebrotError> {
+    ImageFormat::from_name(&manifest.image_format).ok_or_else(|| {
+        RustlebrotError::Argument(format!("unknown image format '{}'", manifest.image_format))
+    })
+}
 
-        let c = (cx, cy);
-        let iter_ratio = mandelbrot(c, max_iter) / max_iter as f64;
+/// The filename template the run of `manifest` named its files with,
+/// which runs from before templates did by default.
+fn recorded_filenames(manifest: &Manifest) -> Result<FilenameTemplate, RustlebrotError> {
+    match &manifest.settings {
+        Some(settings) => FilenameTemplate::parse(&settings.filename_template)
+            .map_err(RustlebrotError::Argument),
+        None => Ok(FilenameTemplate::default()),
+    }
+}
 
-        let (r, g, b) = color_gradient(iter_ratio);
-        chunk[0] = r;
-        chunk[1] = g;
-        chunk[2] = b;
+/// Runs the `serve` subcommand, which only returns if the server can't be
+/// started.
+#[cfg(feature = "serve")]
+fn serve(args: &[String]) -> Result<(), RustlebrotError> {
+    let args = cli::parse_serve(args).map_err(RustlebrotError::Argument)?;
+    let half_width = args.fractal.default_half_width();
+    let plane = args.ranges.unwrap_or_else(|| {
+        let (x, y) = args.center.clone().unwrap_or(("0".to_string(), "0".to_string()));
+        let (x, y): (f64, f64) = (x.parse().unwrap(), y.parse().unwrap());
+        ((x - half_width, x + half_width), (y - half_width, y + half_width))
     });
+    let root = view::fit(plane.0, plane.1, serve::TILE_SIZE, serve::TILE_SIZE, view::Fit::Contain);
+    let (colormap, cycle) = palette(&args.colors, &args.colors.palettes()[0]);
+    let tiles = serve::TileOptions {
+        fractal: args.fractal,
+        root,
+        max_zoom: args.max_zoom.unwrap_or_else(|| serve::deepest_zoom(root)),
+        auto_iter: args.auto_iter,
+        options: RenderOptions {
+            max_iter: args.max_iter,
+            periodicity: true,
+            subdivision: render::Subdivision::Off,
+            work_units: render::WorkUnits::Auto,
+            reuse: None,
+            refine: None,
+            rotation: Rotation::NONE,
+            window: None,
+            timing: None,
+            attractors: false,
+            bailout: 2.0,
+            coloring: args.coloring,
+            single_precision: false,
+        },
+        colors: ColorOptions {
+            palette_iter: args.max_iter,
+            histogram_clip: args.colors.histogram_clip,
+            transfer: args.colors.transfer,
+            reference: None,
+            colormap: &colormap,
+            cycle,
+            interior: args.colors.interior,
+            bit_depth: args.colors.bit_depth,
+            dither: args.colors.dither,
+            alpha: Alpha::None,
+            phase: args.colors.phase,
+            lighting: args.colors.lighting,
+            script: None,
+            interior_coloring: None,
+            contours: args.colors.contours,
+            silhouette: args.colors.silhouette,
+            line_art: args.colors.line_art(),
+        },
+        cache_tiles: args.cache_tiles,
+        cache_dir: args.cache_dir.as_deref(),
+    };
+    #[cfg(feature = "window")]
+    if let Some((width, height)) = args.window {
+        // The window opens on the view tile zoom level 0 would show.
+        let ((x_min, x_max), (y_min, y_max)) =
+            view::fit(plane.0, plane.1, width, height, view::Fit::Contain);
+        let explore = window::ExploreOptions {
+            fractal: args.fractal,
+            size: (width, height),
+            center: ((x_min + x_max) / 2.0, (y_min + y_max) / 2.0),
+            pixel_size: (x_max - x_min) / width as f64,
+            auto_iter: args.auto_iter,
+            options: tiles.options,
+            colors: tiles.colors,
+            bookmarks: args.bookmarks.as_deref(),
+        };
+        return match args.fractal {
+            FractalKind::Mandelbrot => window::explore(&Mandelbrot, &explore),
+            FractalKind::Tricorn => window::explore(&Tricorn, &explore),
+            FractalKind::Newton
+            | FractalKind::Formula
+            | FractalKind::Julia
+            | FractalKind::Lyapunov => {
+                unreachable!("serve rejects --fractal newton, julia and lyapunov")
+            }
+        };
+    }
+    let address = format!("{}:{}", args.bind, args.port);
+    let listener = std::net::TcpListener::bind(&address)
+        .map_err(|e| RustlebrotError::System(format!("can't listen on {}: {}", address, e)))?;
+    println!(
+        "Serving {} tiles down to zoom level {} at http://{}/",
+        args.fractal.name(),
+        tiles.max_zoom,
+        address
+    );
+    match args.fractal {
+        FractalKind::Mandelbrot => serve::serve(&Mandelbrot, &listener, args.workers, &tiles),
+        FractalKind::Tricorn => serve::serve(&Tricorn, &listener, args.workers, &tiles),
+        FractalKind::Newton
+        | FractalKind::Formula
+        | FractalKind::Julia
+        | FractalKind::Lyapunov => {
+            unreachable!("serve rejects --fractal newton, julia and lyapunov")
+        }
+    }
+}
+
+/// Runs the `still` subcommand, which renders one image in tiles, into a
+/// PNG or a PNG of every tile.
+fn still(args: &[String]) -> Result<(), RustlebrotError> {
+    let start = Instant::now();
+    let args = cli::parse_still(args).map_err(RustlebrotError::Argument)?;
+    let (colormap, cycle) = palette(&args.colors, &args.colors.palettes()[0]);
+    let still = plan_still(&args, &colormap, cycle)?;
+    let path = write_still(&args, &still)?;
+    events::say(format!("Still saved to {} in {:.2?}", path, start.elapsed()));
+    Ok(())
+}
+
+/// Runs the `export-dzi` subcommand, which renders one image into the
+/// tiles of a Deep Zoom Image.
+fn export_dzi(args: &[String]) -> Result<(), RustlebrotError> {
+    let start = Instant::now();
+    let args = cli::parse_export_dzi(args).map_err(RustlebrotError::Argument)?;
+    let (colormap, cycle) = palette(&args.view.colors, &args.view.colors.palettes()[0]);
+    let still = plan_still(&args.view, &colormap, cycle)?;
+    let dzi = Dzi {
+        tile_size: args.tile_size,
+        overlap: args.overlap,
+        format: args.format,
+    };
+    let (out, resume, overwrite) = (args.out.as_str(), args.resume, args.view.overwrite);
+    let built = match args.view.fractal {
+        FractalKind::Mandelbrot => dzi::write(&Mandelbrot, &still, &dzi, out, resume, overwrite),
+        FractalKind::Tricorn => dzi::write(&Tricorn, &still, &dzi, out, resume, overwrite),
+        FractalKind::Newton | FractalKind::Formula | FractalKind::Julia | FractalKind::Lyapunov => {
+            unreachable!("export-dzi rejects --fractal newton, julia and lyapunov")
+        }
+    }?;
+    let kept = match built.kept {
+        0 => String::new(),
+        kept => format!(", keeping {} from before", kept),
+    };
+    events::say(format!(
+        "Deep zoom image saved to {} in {:.2?}, {} tiles written{}",
+        out,
+        start.elapsed(),
+        built.written,
+        kept
+    ));
+    Ok(())
+}
+
+/// Runs the `export-mesh` subcommand, which raises the escape times of one
+/// view into a heightfield and writes it as a mesh, colored like a still of
+/// the view would be.
+fn export_mesh(args: &[String]) -> Result<(), RustlebrotError> {
+    let start = Instant::now();
+    let args = cli::parse_export_mesh(args).map_err(RustlebrotError::Argument)?;
+    let view = &args.view;
+    // The material of a textured OBJ goes next to it.
+    let material = |obj: &str| Path::new(obj).with_extension("mtl").to_string_lossy().into_owned();
+    let objs = args.out.iter().filter(|(_, format)| *format == mesh::Format::Obj);
+    let materials: Vec<String> = match &args.texture {
+        Some(_) => objs.map(|(path, _)| material(path)).collect(),
+        None => Vec::new(),
+    };
+    let paths = args.out.iter().map(|(path, _)| path).chain(&args.texture).chain(&materials);
+    for path in paths {
+        still::check_new(path, view.overwrite)?;
+    }
+    let (colormap, cycle) = palette(&view.colors, &view.colors.palettes()[0]);
+    let still = plan_still(view, &colormap, cycle)?;
+    let buffer = match view.fractal {
+        FractalKind::Mandelbrot => still.compute(&Mandelbrot),
+        FractalKind::Tricorn => still.compute(&Tricorn),
+        FractalKind::Newton | FractalKind::Formula | FractalKind::Julia | FractalKind::Lyapunov => {
+            unreachable!("export-mesh rejects --fractal newton, julia and lyapunov")
+        }
+    };
+    let mut field = Heightfield::of(&buffer, view.colors.transfer, args.plateau);
+    field.smooth(args.smooth);
+    let mesh = Mesh::of(&field, args.z_scale, args.solid_base);
+    let image = render::colorize(&buffer, &still.colors);
+    if let Some(texture) = &args.texture {
+        let text = [("Software", format!("rustlebrot {}", env!("CARGO_PKG_VERSION")))];
+        export::save_png_text(texture, &image, &text)?;
+        events::say(format!("Texture saved to {}", texture));
+    }
+    // Vertices take the color of the pixel they were raised from.
+    let colors: Vec<[f64; 3]> = match &args.texture {
+        Some(_) => Vec::new(),
+        None => {
+            let image = image.to_rgb32f();
+            let (width, height) = (image.width() - 1, image.height() - 1);
+            mesh.uvs
+                .iter()
+                .map(|[u, v]| {
+                    let (x, y) = ((u * width as f64).round(), ((1.0 - v) * height as f64).round());
+                    image.get_pixel(x as u32, y as u32).0.map(|channel| channel as f64)
+                })
+                .collect()
+        }
+    };
+    let mut materials = materials.iter();
+    for (path, format) in &args.out {
+        let bytes = match format {
+            mesh::Format::Stl => mesh.to_stl(),
+            mesh::Format::Obj => match (&args.texture, materials.next()) {
+                (Some(texture), Some(library)) => {
+                    // Relative to the material where it's next to it.
+                    let beside = Path::new(texture).parent() == Path::new(library).parent();
+                    let map = match (beside, Path::new(texture).file_name()) {
+                        (true, Some(name)) => name.to_string_lossy().into_owned(),
+                        _ => std::fs::canonicalize(texture)
+                            .map_err(|e| RustlebrotError::read(texture, e))?
+                            .to_string_lossy()
+                            .into_owned(),
+                    };
+                    let mtl = format!("newmtl fractal\nKd 1 1 1\nmap_Kd {}\n", map);
+                    std::fs::write(library, mtl).map_err(|e| RustlebrotError::write(library, e))?;
+                    let name = Path::new(library).file_name().map(|name| name.to_string_lossy());
+                    let name = name.unwrap_or_default();
+                    mesh.to_obj(None, Some((&name, "fractal"))).into_bytes()
+                }
+                _ => mesh.to_obj(Some(&colors), None).into_bytes(),
+            },
+        };
+        std::fs::write(path, bytes).map_err(|e| RustlebrotError::write(path, e))?;
+    }
+    let outs: Vec<&str> = args.out.iter().map(|(path, _)| path.as_str()).collect();
+    events::say(format!(
+        "Mesh of {} vertices and {} triangles saved to {} in {:.2?}",
+        mesh.vertices.len(),
+        mesh.triangles.len(),
+        outs.join(", "),
+        start.elapsed()
+    ));
+    Ok(())
+}
 
-    ImageBuffer::from_vec(width, height, data).unwrap()
+/// Runs the `export-svg` subcommand, which traces isolines of the escape
+/// times of one view at chosen levels and writes them as SVG paths, in the
+/// colors of the palette at their levels.
+fn export_svg(args: &[String]) -> Result<(), RustlebrotError> {
+    let start = Instant::now();
+    let args = cli::parse_export_svg(args).map_err(RustlebrotError::Argument)?;
+    let view = &args.view;
+    still::check_new(&args.out, view.overwrite)?;
+    let (colormap, cycle) = palette(&view.colors, &view.colors.palettes()[0]);
+    let still = plan_still(view, &colormap, cycle)?;
+    let buffer = match view.fractal {
+        FractalKind::Mandelbrot => still.compute(&Mandelbrot),
+        FractalKind::Tricorn => still.compute(&Tricorn),
+        FractalKind::Newton | FractalKind::Formula | FractalKind::Julia | FractalKind::Lyapunov => {
+            unreachable!("export-svg rejects --fractal newton, julia and lyapunov")
+        }
+    };
+    let levels = match &args.levels {
+        cli::Levels::Given(levels) => levels.clone(),
+        cli::Levels::Count(count, spacing) => match isoline::escaped_range(&buffer) {
+            Some(range) => spacing.levels(range, *count),
+            None => Vec::new(),
+        },
+    };
+    let lines = |field: &isoline::Field, level: f64| -> Vec<isoline::Polyline> {
+        let traced = isoline::trace(field, level).into_iter();
+        let lines = traced.map(|line| line.in_pixels(buffer.samples).simplified(args.tolerance));
+        lines.filter(|line| line.points.len() > 1).collect()
+    };
+    let mut layers = Vec::new();
+    if let Some(color) = args.set_fill {
+        let lines = lines(&isoline::Field::interior(&buffer), 0.5);
+        layers.push(isoline::Layer::Set { color, lines });
+    }
+    // The widths ramp from the lowest level to the highest.
+    let (lowest, highest) = levels.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |range, level| {
+        (range.0.min(*level), range.1.max(*level))
+    });
+    let field = isoline::Field::escape_times(&buffer);
+    let colors = &still.colors;
+    for &level in &levels {
+        let t = match highest > lowest {
+            true => (level - lowest) / (highest - lowest),
+            false => 0.0,
+        };
+        let (low, high) = args.stroke_width;
+        let position = colors.transfer.position(level, colors.palette_iter as f64);
+        let [r, g, b, _] = colors.colormap.at(colors.cycle.parameter(position)).to_rgba8();
+        layers.push(isoline::Layer::Level {
+            level,
+            color: args.stroke_color.unwrap_or((r, g, b)),
+            width: low + t * (high - low),
+            lines: lines(&field, level),
+        });
+    }
+    let svg = isoline::to_svg(view.width, view.height, &layers);
+    std::fs::write(&args.out, svg).map_err(|e| RustlebrotError::write(&args.out, e))?;
+    let (paths, points) = layers.iter().fold((0, 0), |(paths, points), layer| {
+        let lines = match layer {
+            isoline::Layer::Level { lines, .. } | isoline::Layer::Set { lines, .. } => lines,
+        };
+        (paths + lines.len(), points + lines.iter().map(|line| line.points.len()).sum::<usize>())
+    });
+    events::say(format!(
+        "Isolines at {} levels, {} lines of {} points, saved to {} in {:.2?}",
+        levels.len(),
+        paths,
+        points,
+        args.out,
+        start.elapsed()
+    ));
+    Ok(())
 }
 
-/// Maps a number between 0 and 1 to a color gradient.
+/// Runs the `render-batch` subcommand, which renders the stills of a
+/// batch file, several at once as far as `--max-memory` allows.
 ///
-/// `iters_to_escape` is the number to map. Returns an RGB color as a tuple of three bytes.
-fn color_gradient(iters_to_escape: f64) -> (u8, u8, u8) {
-    let g = sinebow();
-    let t = (4.0 * iters_to_escape) % 1.0;
-    let rgba = g.at(t).to_rgba8();
-    (rgba[0], rgba[1], rgba[2])
+/// A still that fails is reported with the others at the end, once the
+/// rest of the batch is rendered.
+fn render_batch(args: &[String]) -> Result<(), RustlebrotError> {
+    let start = Instant::now();
+    let args = cli::parse_batch(args).map_err(RustlebrotError::Argument)?;
+    let entries = batch::read(&args.input)?;
+    let labels: Vec<String> =
+        entries.iter().enumerate().map(|(index, entry)| entry.label(index)).collect();
+    let stills: Vec<Result<cli::StillArgs, String>> = entries
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            let earlier = entries[..index].iter().position(|other| other.output == entry.output);
+            match earlier {
+                Some(earlier) => {
+                    Err(format!("{} is saved to {} already", entry.output, labels[earlier]))
+                }
+                None => entry.still_args(args.max_memory, args.overwrite),
+            }
+        })
+        .collect();
+    // Stills of the same palette share its colormap.
+    let mut palettes: Vec<(PaletteSource, Colormap, Cycle)> = Vec::new();
+    for still in stills.iter().flatten() {
+        let source = &still.colors.palettes()[0];
+        if !palettes.iter().any(|(known, _, _)| known == source) {
+            let (colormap, cycle) = palette(&still.colors, source);
+            palettes.push((source.clone(), colormap, cycle));
+        }
+    }
+    let plans: Vec<Result<(&cli::StillArgs, Still), String>> = stills
+        .iter()
+        .map(|still| {
+            let still = still.as_ref().map_err(String::clone)?;
+            let source = &still.colors.palettes()[0];
+            let (_, colormap, cycle) = palettes
+                .iter()
+                .find(|(known, _, _)| known == source)
+                .expect("every palette is built");
+            let plan = plan_still(still, colormap, *cycle).map_err(|e| e.to_string())?;
+            Ok((still, plan))
+        })
+        .collect();
+
+    // Stills are taken in order, each once the bands of those rendering
+    // leave room for its own, or right away when none are rendering.
+    let memory: Vec<u64> = plans
+        .iter()
+        .map(|plan| plan.as_ref().map_or(0, |(_, still)| still.band_memory()))
+        .collect();
+    let taken = Mutex::new((0, 0u64));
+    let freed = Condvar::new();
+    let results: Mutex<Vec<Option<Result<String, String>>>> =
+        Mutex::new((0..plans.len()).map(|_| None).collect());
+    let workers = plans.len().min(rayon::current_num_threads());
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let index = {
+                    let mut taken = taken.lock().unwrap();
+                    loop {
+                        let (next, used) = *taken;
+                        if next == plans.len() {
+                            return;
+                        }
+                        if used == 0 || used + memory[next] <= args.max_memory {
+                            *taken = (next + 1, used + memory[next]);
+                            break next;
+                        }
+                        taken = freed.wait(taken).unwrap();
+                    }
+                };
+                let result = match &plans[index] {
+                    Ok((still_args, still)) => {
+                        let started = Instant::now();
+                        let written = write_still(still_args, still).map_err(|e| e.to_string());
+                        written.map(|path| {
+                            let elapsed = started.elapsed();
+                            let saved = format!("Saved {} to {} in {:.2?}", labels[index], path, elapsed);
+                            events::say(saved);
+                            path.to_string()
+                        })
+                    }
+                    Err(e) => Err(e.clone()),
+                };
+                results.lock().unwrap()[index] = Some(result);
+                taken.lock().unwrap().1 -= memory[index];
+                freed.notify_all();
+            });
+        }
+    });
+
+    let results = results.into_inner().unwrap();
+    let failed: Vec<String> = results
+        .into_iter()
+        .zip(&labels)
+        .filter_map(|(result, label)| match result.expect("every still is rendered") {
+            Ok(_) => None,
+            Err(e) => Some(format!("{}: {}", label, e)),
+        })
+        .collect();
+    events::say(format!(
+        "Rendered {} of {} stills in {:.2?}",
+        labels.len() - failed.len(),
+        labels.len(),
+        start.elapsed()
+    ));
+    for failure in &failed {
+        events::say(format!("Failed {}", failure));
+    }
+    match failed.len() {
+        0 => Ok(()),
+        count => Err(RustlebrotError::Argument(format!(
+            "{} of {} stills failed",
+            count,
+            labels.len()
+        ))),
+    }
 }
 
+/// The still `args` ask for, colored with `colormap` and `cycle`, their
+/// palette. Fails if its pixels can't be told apart in f64.
+fn plan_still<'a>(
+    args: &'a cli::StillArgs,
+    colormap: &'a Colormap,
+    cycle: Cycle,
+) -> Result<Still<'a>, RustlebrotError> {
+    let half_width = args.fractal.default_half_width() / args.magnification;
+    let (x_range, y_range) = args.ranges.unwrap_or_else(|| {
+        let (x, y) = match &args.center {
+            Some((x, y)) => (x.as_str(), y.as_str()),
+            None => args.fractal.default_center(),
+        };
+        let (x, y): (f64, f64) = (
+            x.parse().expect("centers are validated when read"),
+            y.parse().expect("centers are validated when read"),
+        );
+        ((x - half_width, x + half_width), (y - half_width, y + half_width))
+    });
+    let (width, height) = (args.width, args.height);
+    if let Some(corners) = args.view {
+        report_fit(corners, width, height, args.fit);
+    }
+    let (x_range, y_range) = view::fit(x_range, y_range, width, height, args.fit);
+    let center = ((x_range.0 + x_range.1) / 2.0, (y_range.0 + y_range.1) / 2.0);
+    let (columns, rows) = (width * args.supersample, height * args.supersample);
+    let pixel_size =
+        ((x_range.1 - x_range.0) / columns as f64).min((y_range.1 - y_range.0) / rows as f64);
+    let precision = args.precision.resolve(center, pixel_size);
+    let deep = matches!(precision, Precision::DoubleDouble | Precision::Perturbation);
+    if deep || pixel_size < precision.resolution(center) {
+        return Err(RustlebrotError::Argument(format!(
+            "pixels of a {}x{} still of this view can't be told apart in f64; still renders \
+             in f32 and f64 only, render views this deep as a zoom frame with --precision \
+             dd or perturb",
+            width, height
+        )));
+    }
+    Ok(Still {
+        width,
+        height,
+        samples: args.supersample,
+        tile_size: args.tile_size,
+        band_height: args.band_height,
+        x_range,
+        y_range,
+        options: RenderOptions {
+            max_iter: args.max_iter,
+            periodicity: true,
+            subdivision: render::Subdivision::Off,
+            work_units: render::WorkUnits::Auto,
+            reuse: None,
+            refine: None,
+            rotation: Rotation::degrees(args.rotation),
+            window: None,
+            timing: None,
+            attractors: false,
+            bailout: 2.0,
+            coloring: args.coloring,
+            single_precision: precision == Precision::F32,
+        },
+        colors: ColorOptions {
+            palette_iter: args.max_iter,
+            histogram_clip: args.colors.histogram_clip,
+            transfer: args.colors.transfer,
+            reference: None,
+            colormap,
+            cycle,
+            interior: args.colors.interior,
+            bit_depth: args.colors.bit_depth,
+            dither: args.colors.dither,
+            alpha: Alpha::None,
+            phase: args.colors.phase,
+            lighting: args.colors.lighting,
+            script: None,
+            interior_coloring: None,
+            contours: args.colors.contours,
+            silhouette: args.colors.silhouette,
+            line_art: args.colors.line_art(),
+        },
+    })
+}
 
-fn render_frame(
-    frame: u32,
-    width: u32,
-    height: u32,
-    max_iter: u32,
-    x_range_initial: (f64, f64),
-    y_range_initial: (f64, f64),
-    x_center: f64,
-    y_center: f64,
-    zoom_factor: f64,
-) {
-    let x_range_width: f64 = (x_range_initial.1 - x_range_initial.0) / zoom_factor.powi(frame as i32);
-    let y_range_width: f64 = (y_range_initial.1 - y_range_initial.0) / zoom_factor.powi(frame as i32);
+/// Renders `still` to where `args` ask for, returning the path. A PNG
+/// fails before rendering if a band of it would take more than
+/// `--max-memory`.
+fn write_still<'a>(args: &'a cli::StillArgs, still: &Still) -> Result<&'a str, RustlebrotError> {
+    let overwrite = args.overwrite;
+    match &args.output {
+        cli::StillOutput::Png(path) => {
+            let memory = still.band_memory();
+            if memory > args.max_memory {
+                let mib = |bytes: u64| bytes.div_ceil(1 << 20);
+                return Err(RustlebrotError::Argument(format!(
+                    "rendering a band of {} rows takes about {} MiB, more than the {} MiB of \
+                     --max-memory; lower --band-height or raise --max-memory",
+                    args.band_height.min(args.height),
+                    mib(memory),
+                    mib(args.max_memory),
+                )));
+            }
+            match args.fractal {
+                FractalKind::Mandelbrot => still::write_png(&Mandelbrot, still, path, overwrite),
+                FractalKind::Tricorn => still::write_png(&Tricorn, still, path, overwrite),
+                FractalKind::Newton
+                | FractalKind::Formula
+                | FractalKind::Julia
+                | FractalKind::Lyapunov => {
+                    unreachable!("still rejects --fractal newton, julia and lyapunov")
+                }
+            }?;
+            Ok(path)
+        }
+        cli::StillOutput::Tiles(dir) => {
+            match args.fractal {
+                FractalKind::Mandelbrot => still::write_tiles(&Mandelbrot, still, dir, overwrite),
+                FractalKind::Tricorn => still::write_tiles(&Tricorn, still, dir, overwrite),
+                FractalKind::Newton
+                | FractalKind::Formula
+                | FractalKind::Julia
+                | FractalKind::Lyapunov => {
+                    unreachable!("still rejects --fractal newton, julia and lyapunov")
+                }
+            }?;
+            Ok(dir)
+        }
+    }
+}
 
-    let x_range: (f64, f64) = (
-        x_center - x_range_width / 2.0,
-        x_center + x_range_width / 2.0,
-    );
-    let y_range = (
-        y_center - y_range_width / 2.0,
-        y_center + y_range_width / 2.0,
+/// Builds the colormap and cycling of `source`, one of the palettes chosen
+/// in `colors`.
+fn palette(colors: &ColorArgs, source: &PaletteSource) -> (Colormap, Cycle) {
+    let gradient = match source {
+        PaletteSource::Stops { stops, .. } => palette::custom_gradient(stops, colors.blending),
+        PaletteSource::Map { colors: entries, .. } => {
+            let stops = palette::map_stops(entries, colors.map_interpolate);
+            palette::custom_gradient(&stops, colors.blending)
+        }
+        PaletteSource::Named(palette) => palette.gradient(),
+    };
+    let cycle = Cycle::new(
+        &gradient,
+        colors.palette_cycles,
+        colors.palette_offset,
+        colors.palette_reverse,
     );
+    let colormap = Colormap::with_entries(&gradient, &colors.adjust, colors.palette_resolution);
+    (colormap, cycle)
+}
 
-    let start_time: Instant = Instant::now();
-    let mut img = render_mandelbrot(width, height, max_iter, x_range, y_range);
-
-    invert(&mut img);
+/// Runs the `info` subcommand, printing the render parameters saved in the
+/// text chunks of a frame, or a summary of a render from its manifest.
+fn info(args: &[String]) -> Result<(), RustlebrotError> {
+    let [path] = args else {
+        return Err(RustlebrotError::Argument(format!(
+            "info takes the path of one PNG, manifest or render directory\n{}",
+            cli::USAGE
+        )));
+    };
+    let manifest = match Path::new(path).is_dir() {
+        true => Some(format!("{}/manifest.json", path.trim_end_matches('/'))),
+        false => path.ends_with(".json").then(|| path.clone()),
+    };
+    if let Some(manifest) = manifest {
+        if !Path::new(&manifest).exists() {
+            return Err(RustlebrotError::Argument(format!(
+                "{} isn't the directory of a render, it has no manifest.json",
+                path
+            )));
+        }
+        print!("{}", summary(&Manifest::read(&manifest)?));
+        return Ok(());
+    }
+    let text = export::read_png(path)?.text;
+    if text.is_empty() {
+        println!("{} has no render parameters", path);
+    }
+    for (keyword, text) in text {
+        println!("{}: {}", keyword, text);
+    }
+    Ok(())
+}
 
-    let output_filename = format!("rust_data/mandelbrot_set_{:04}.png", frame);
+/// A summary of the render `manifest` records, a line for each thing.
+fn summary(manifest: &Manifest) -> String {
+    let mut lines = vec![
+        format!("Rendered by: {}", manifest.software),
+        format!("Fractal: {}", manifest.fractal),
+        format!("Mode: {}", manifest.mode),
+        format!("Center: {}, {}", manifest.center.0, manifest.center.1),
+        format!("Size: {}x{}", manifest.width, manifest.height),
+        format!("Zoom factor: {}", manifest.zoom_factor),
+        format!("Direction: {}", manifest.direction),
+        match manifest.palettes.is_empty() {
+            true => format!("Palette: {}", manifest.palette),
+            false => format!("Palettes: {}", manifest.palettes.join(", ")),
+        },
+    ];
+    let frames = manifest.frames.iter().map(|record| record.frame);
+    match (frames.clone().min(), frames.max()) {
+        (Some(first), Some(last)) => {
+            let deepest = manifest.frames.iter().map(|record| record.magnification);
+            let seconds: f64 = manifest.frames.iter().map(|record| record.seconds).sum();
+            let max_iter = manifest.frames.iter().map(|record| record.max_iter).max();
+            lines.push(format!(
+                "Frames: {} rendered, {} to {}",
+                manifest.frames.len(),
+                first,
+                last
+            ));
+            lines.push(format!("Deepest magnification: {:.3e}", deepest.fold(0.0, f64::max)));
+            lines.push(format!("Max iterations: {} to {}", manifest.max_iter, max_iter.unwrap()));
+            lines.push(format!("Render time: {:.2}s", seconds));
+        }
+        _ => lines.push("Frames: none rendered yet".to_string()),
+    }
+    if let Some(stopped) = &manifest.early_stop {
+        lines.push(format!("Stopped early after frame {}", stopped.frame));
+    }
+    let settings = manifest.settings.as_ref().map(Settings::by_name).unwrap_or_default();
+    for (setting, value) in settings {
+        lines.push(format!("{}: {}", setting, value));
+    }
+    lines.iter().map(|line| format!("{}\n", line)).collect()
+}
 
-    let output_path = Path::new(&output_filename);
-    if let Err(e) = img.save(&output_path) {
-        eprintln!("Failed to save image: {}", e);
-        std::process::exit(1);
+/// Runs the `bench` subcommand.
+fn bench(args: &[String]) -> Result<(), RustlebrotError> {
+    let args = cli::parse_bench(args).map_err(RustlebrotError::Argument)?;
+    let debug = cfg!(debug_assertions);
+    if debug && !args.allow_debug {
+        return Err(RustlebrotError::Argument(
+            "this is a debug build, which renders many times slower than a release; build \
+             with --release to benchmark, or pass --allow-debug to run anyway"
+                .to_string(),
+        ));
+    }
+    // On stderr, so it can't be missed for the JSON on stdout.
+    if debug {
+        eprintln!(
+            "Warning: THIS IS A DEBUG BUILD. Its numbers are no measure of a release build, \
+             don't compare them with any."
+        );
+    }
+    throttle::configure(args.threads, false)?;
+    let scenes = bench::SCENES
+        .iter()
+        .filter(|scene| args.scenes.is_empty() || args.scenes.contains(&scene.name));
+    let report = bench::Report {
+        version: env!("CARGO_PKG_VERSION"),
+        threads: rayon::current_num_threads(),
+        backend: bench::backend(),
+        debug,
+        formula: args.formula.as_ref().map(|formula| formula.source().to_string()),
+        kernel: args.formula.as_ref().map(Formula::kernel),
+        scenes: scenes
+            .map(|scene| bench::run(scene, args.repeats, args.formula.as_ref(), args.work_units))
+            .collect(),
+    };
+    match args.json {
+        true => println!(
+            "{}",
+            serde_json::to_string_pretty(&report).expect("reports serialize to JSON")
+        ),
+        false => print!("{}", bench::table(&report)),
     }
+    Ok(())
+}
 
-    let elapsed_time = start_time.elapsed();
+/// Runs the `daemon` subcommand, which renders the jobs clients submit one
+/// after the other.
+#[cfg(feature = "daemon")]
+fn daemon(args: &[String]) -> Result<(), RustlebrotError> {
+    let args = cli::parse_daemon(args).map_err(RustlebrotError::Argument)?;
+    let socket = match &args.endpoint {
+        daemon::Endpoint::Socket(path) => Some(path),
+        daemon::Endpoint::Tcp(_) => None,
+    };
+    for path in socket.into_iter().chain([&args.queue]) {
+        if let Some(dir) = Path::new(path).parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| RustlebrotError::write(&dir.display().to_string(), e))?;
+        }
+    }
+    let queue = daemon::Queue::open(&args.queue, check_job)?;
+    let listener = daemon::Listener::bind(&args.endpoint)?;
+    interrupt::install()?;
     println!(
-        "Frame {} saved in {:.2?} seconds.",
-        frame,
-        elapsed_time.as_secs_f64(),
+        "Listening on {} with {} jobs queued, keeping them in {}",
+        args.endpoint,
+        queue.queued(),
+        args.queue
     );
+    daemon::run(listener, queue)
 }
 
-fn generate_frames(
-    zoom_start: u32,
-    zoom_end: u32,
-    x_range_initial: (f64, f64),
-    y_range_initial: (f64, f64),
-    x_center: f64,
-    y_center: f64,
-    zoom_factor: f64,
-    width: u32,
-    height: u32,
-    max_iter: u32,
-) {
-    (zoom_start..zoom_end).into_par_iter().for_each(|frame| {
-        render_frame(
-            frame,
-            width,
-            height,
-            max_iter,
-            x_range_initial,
-            y_range_initial,
-            x_center,
-            y_center,
-            zoom_factor,
-        );
-    });
+/// Checks that `job` is a zoom that can be rendered, so a job that can't is
+/// turned down when it is submitted rather than failing once it is its
+/// turn.
+#[cfg(feature = "daemon")]
+fn check_job(job: &daemon::JobSpec) -> Result<(), String> {
+    if job.args.iter().any(|arg| arg.starts_with("--progress-format")) {
+        return Err("the daemon follows jobs by their JSON events, so they can't set \
+                    --progress-format"
+            .to_string());
+    }
+    cli::parse_args(&job.args).map(|_| ())
 }
 
+/// Runs the `submit` subcommand, which sends a request to a daemon.
+#[cfg(feature = "daemon")]
+fn submit(args: &[String]) -> Result<(), RustlebrotError> {
+    let args = cli::parse_submit(args).map_err(RustlebrotError::Argument)?;
+    let request = match &args.action {
+        cli::SubmitAction::Job(path) => {
+            let json =
+                std::fs::read_to_string(path).map_err(|e| RustlebrotError::read(path, e))?;
+            let job = serde_json::from_str(&json).map_err(|e| RustlebrotError::format(path, e))?;
+            daemon::Request::Submit { job }
+        }
+        cli::SubmitAction::Status => daemon::Request::Status { id: None },
+        cli::SubmitAction::Cancel(id) => daemon::Request::Cancel { id: *id },
+    };
+    let response = daemon::request(&args.endpoint, &request)?;
+    if args.json {
+        println!("{}", serde_json::to_string(&response).expect("responses serialize to JSON"));
+    }
+    match response {
+        daemon::Response::Error { message } => return Err(RustlebrotError::Argument(message)),
+        _ if args.json => {}
+        daemon::Response::Submitted { id } => println!("Submitted job {}", id),
+        daemon::Response::Cancelled { id } => println!("Cancelled job {}", id),
+        daemon::Response::Status { jobs } => {
+            if jobs.is_empty() {
+                println!("No jobs");
+            }
+            for job in jobs {
+                let name = job.spec.name.unwrap_or_else(|| job.spec.args.join(" "));
+                let frames = match job.frames {
+                    Some(frames) => format!(", {} of {} frames", job.frames_done, frames),
+                    None => String::new(),
+                };
+                println!("{:>4} {:<9} {}{}", job.id, job.state.name(), name, frames);
+                if let Some(error) = &job.error {
+                    println!("     {}", error);
+                }
+                if let Some(last) = job.paths.last() {
+                    println!("     {} files written, the last {}", job.paths.len(), last);
+                }
+            }
+        }
+    }
+    Ok(())
+}
 
 fn main() {
     let args: Vec<String> = env::args().collect();
-    if args.len() != 5 {
-        eprintln!("Usage: mandelbrot <max_iter> <zoom_start> <zoom_end> <zoom_factor>");
-        std::process::exit(1);
+    // Everything that fails ends up here, to be reported once. Ctrl-C said
+    // how far the run got already.
+    if let Err(e) = run(&args[1..]) {
+        if !matches!(e, RustlebrotError::Interrupted) {
+            events::report(&e.to_string());
+        }
+        std::process::exit(e.exit_status());
     }
+}
 
-    let max_iter: u32 = match args[1].parse() {
-        Ok(n) => n,
-        Err(_) => {
-            eprintln!("Error: max_iter should be an integer");
-            std::process::exit(1);
-        }
+/// Runs the subcommand the command line names after the global flags, or a
+/// zoom the old way when it names none.
+fn run(args: &[String]) -> Result<(), RustlebrotError> {
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{}", cli::USAGE);
+        return Ok(());
+    }
+    let (global, args) = cli::parse_global(args).map_err(RustlebrotError::Argument)?;
+    events::set_verbosity(global.verbosity);
+    let default_dir = global.output_dir.as_deref().unwrap_or("rust_data");
+    let rest = args.get(1..).unwrap_or_default();
+    let passed = |command, args| {
+        global.pass_output_dir(command, args).map_err(RustlebrotError::Argument)
     };
+    let command = args.first().map(String::as_str);
+    if let Some(feature) = command.and_then(missing_feature) {
+        let wanted = format!("the {} subcommand", args[0]);
+        return Err(RustlebrotError::missing_feature(feature, wanted));
+    }
+    match command {
+        Some("render") => render_zoom(&passed("render", rest)?),
+        Some("animate-julia") => {
+            let render = cli::animate_julia_args(rest).map_err(RustlebrotError::Argument)?;
+            render_zoom(&passed("animate-julia", &render)?)
+        }
+        Some("pan") => {
+            let render = cli::pan_args(rest).map_err(RustlebrotError::Argument)?;
+            render_zoom(&passed("pan", &render)?)
+        }
+        Some("recolor") => recolor(rest, default_dir),
+        Some("animate-palette") => animate_palette(&passed("animate-palette", rest)?),
+        Some("animate-iter") => animate_iter(&passed("animate-iter", rest)?),
+        Some("assemble") => assemble(rest, default_dir),
+        Some("montage") => montage(rest, default_dir),
+        Some("verify") => verify(rest, default_dir),
+        Some("merge") => merge(&passed("merge", rest)?),
+        #[cfg(feature = "bigfloat")]
+        Some("survey") => survey(&passed("survey", rest)?),
+        Some(
+            command @ ("render-frame" | "find-target" | "find-nucleus" | "orbit" | "explore"
+            | "serve" | "still" | "export-dzi" | "export-mesh" | "export-svg" | "render-batch"
+            | "info" | "bench" | "validate" | "daemon" | "submit"),
+        ) => {
+            global.refuse_output_dir(command).map_err(RustlebrotError::Argument)?;
+            match command {
+                "render-frame" => rerender_frame(rest),
+                #[cfg(feature = "bigfloat")]
+                "find-target" => find_target(rest),
+                #[cfg(feature = "bigfloat")]
+                "find-nucleus" => find_nucleus(rest),
+                "orbit" => orbit(rest),
+                #[cfg(feature = "explore")]
+                "explore" => serve(rest),
+                // `serve` is the subcommand's old name.
+                #[cfg(feature = "serve")]
+                "serve" => serve(rest),
+                "still" => still(rest),
+                "export-dzi" => export_dzi(rest),
+                "export-mesh" => export_mesh(rest),
+                "export-svg" => export_svg(rest),
+                "render-batch" => render_batch(rest),
+                "info" => info(rest),
+                "bench" => bench(rest),
+                "validate" => validate(rest),
+                #[cfg(feature = "daemon")]
+                "daemon" => daemon(rest),
+                #[cfg(feature = "daemon")]
+                "submit" => submit(rest),
+                _ => unreachable!("{} was refused for the feature it needs", command),
+            }
+        }
+        _ if args.iter().any(|arg| arg == "--list-palettes") => {
+            for palette in Palette::ALL {
+                println!("{}", palette.name());
+            }
+            Ok(())
+        }
+        _ if args.iter().any(|arg| arg == "--list-presets") => {
+            for preset in PRESETS {
+                println!(
+                    "{:<18} {}; to {:.0e} at max_iter {}",
+                    preset.name, preset.description, preset.depth, preset.max_iter
+                );
+            }
+            Ok(())
+        }
+        _ => {
+            // On stderr, like the debug build's warning, so it's seen even
+            // with JSON events on stdout.
+            if !args.is_empty() {
+                eprintln!(
+                    "Note: zooms without a subcommand are deprecated; run `rustlebrot render` \
+                     with the same arguments"
+                );
+            }
+            render_zoom(&passed("render", args)?)
+        }
+    }
+}
 
-    let zoom_start: u32 = match args[2].parse() {
-        Ok(n) => n,
-        Err(_) => {
-            eprintln!("Error: zoom_start should be an integer");
-            std::process::exit(1);
+/// The cargo feature the subcommand `command` needs, if this build was
+/// made without it.
+fn missing_feature(command: &str) -> Option<&'static str> {
+    match command {
+        "find-target" | "find-nucleus" | "survey" if !cfg!(feature = "bigfloat") => {
+            Some("bigfloat")
         }
-    };
+        "explore" if !cfg!(feature = "explore") => Some("explore"),
+        "serve" if !cfg!(feature = "serve") => Some("serve"),
+        "daemon" | "submit" if !cfg!(feature = "daemon") => Some("daemon"),
+        _ => None,
+    }
+}
+
+/// Runs the `render` subcommand, which renders the zoom the command line
+/// asks for.
+fn render_zoom(command_line: &[String]) -> Result<(), RustlebrotError> {
+    // A time budget covers the whole run, calibration included.
+    let run_start = Instant::now();
+    let args = cli::parse_args(command_line).map_err(RustlebrotError::Argument)?;
+    for (setting, value) in args.settings().by_name() {
+        events::detail(format!("{}: {}", setting, value));
+    }
+
+    let colormaps: Vec<(Colormap, Cycle)> =
+        args.colors.palettes().iter().map(|source| palette(&args.colors, source)).collect();
+    let mut zoom = zoom_of(&args, &colormaps);
+    let (width, height) = (args.width, args.height);
 
-    let zoom_end: u32 = match args[3].parse() {
-        Ok(n) => n,
-        Err(_) => {
-            eprintln!("Error: zoom_end should be an integer");
-            std::process::exit(1);
+    events::set_format(args.progress_format);
+    if let Some(target) = &args.target {
+        events::say(format!(
+            "{}s at {} fps is {} frames, zooming by {:.6} a frame to reach a magnification of \
+             {:.3e}.",
+            target.seconds,
+            target.fps,
+            target.frames,
+            target.zoom_factor,
+            target.magnification
+        ));
+    }
+    if let Some(quality) = quality_record(&args) {
+        let jpeg = quality.jpeg_quality.map(|jpeg| format!(", JPEG quality {}", jpeg));
+        events::say(format!(
+            "Quality {}: {}x{}, {}x{} {}samples a pixel, max_iter {}, {}-bit{}.",
+            quality.quality,
+            quality.width,
+            quality.height,
+            quality.supersample,
+            quality.supersample,
+            if quality.adaptive { "adaptive " } else { "" },
+            quality.max_iter,
+            quality.bit_depth,
+            jpeg.unwrap_or_default()
+        ));
+    }
+    zoom.term_preview = args.term_preview.and_then(|every| {
+        // Previews are only drawn where they're seen, unless asked for in
+        // a protocol by name.
+        let detected = || Protocol::detect().filter(|_| events::says_to_terminal());
+        let protocol = args.term_protocol.or_else(detected);
+        if protocol.is_none() {
+            events::say("Terminal previews are off, as the terminal can't show images.");
+        }
+        protocol.map(|protocol| TermPreview { protocol, every })
+    });
+    if let Some(address) = args.dashboard {
+        #[cfg(feature = "dashboard")]
+        {
+            zoom.dashboard = Some(dashboard::start(address)?);
+        }
+        #[cfg(not(feature = "dashboard"))]
+        {
+            let wanted = format!("--dashboard {}", address);
+            return Err(RustlebrotError::missing_feature("dashboard", wanted));
+        }
+    }
+    // The calibration renders on the threads the frames will.
+    throttle::configure(args.threads, args.background)?;
+    let calibration = args.time_budget.map(|seconds| {
+        let start = Instant::now();
+        let model = calibrate(&zoom, args.zoom_start..args.zoom_end);
+        if args.fit_supersample {
+            let frames: Vec<u32> = (args.zoom_start..args.zoom_end).collect();
+            let limits = unscaled_limits(&mut zoom, &frames);
+            let left = seconds - run_start.elapsed().as_secs_f64();
+            zoom.supersample = model.supersample(
+                &limits,
+                width as u64 * height as u64 * zoom.sub_frames() as u64,
+                budget::MARGIN * left,
+                MAX_BUDGET_SUPERSAMPLE,
+            );
+        }
+        (model, start.elapsed().as_secs_f64())
+    });
+    let budget_left = |seconds: f64| seconds - run_start.elapsed().as_secs_f64();
+    if args.dry_run {
+        if let (Some(seconds), Some((model, _))) = (args.time_budget, &calibration) {
+            let frames: Vec<u32> = (args.zoom_start..args.zoom_end).collect();
+            fit_budget(&mut zoom, model.clone(), &frames, budget_left(seconds));
+        }
+        print_schedule(args.zoom_start, args.zoom_end, &zoom);
+        let encodes = !args.no_video && (zoom.mode != Mode::Escape || zoom.export.png);
+        let encoder = EncoderKind::resolve(args.encoder).ok().filter(|_| encodes);
+        let frames: Vec<u32> = (args.zoom_start..args.zoom_end).collect();
+        let model = args.time_budget.zip(calibration.as_ref());
+        let model = model.map(|(seconds, (model, _))| (model, budget_left(seconds)));
+        preflight(&args, &mut zoom, &frames, encoder, model)?;
+        return Ok(());
+    }
+    if let Some(arg) = &args.location {
+        if !arg.ignored.is_empty() {
+            events::say(format!(
+                "Ignoring what only Kalles Fraktaler uses of {}: {}",
+                arg.path,
+                arg.ignored.join(", ")
+            ));
+        }
+        if args.zoom_factor > 1.0 {
+            events::say(format!(
+                "The location of {} is at a magnification of {:.3e}, reached at frame {} \
+                 zooming by {}",
+                arg.path,
+                arg.location.magnification,
+                arg.location.frames(args.zoom_factor) - 1,
+                args.zoom_factor
+            ));
+        }
+    }
+    if args.dump_iterations && args.export.png && !args.image_format.lossless() {
+        events::say(format!(
+            "Note: {} frames are lossy and can't be read back as they were rendered; recolor \
+             them from the iterations --dump-iterations saves.",
+            args.image_format.name()
+        ));
+    }
+    if let Some(path) = &args.camera_path {
+        let rows = path.frames();
+        if rows.start < args.zoom_start || rows.end > args.zoom_end {
+            events::say(format!(
+                "Note: ignoring the rows of {} outside frames {} to {}",
+                path.file,
+                args.zoom_start,
+                args.zoom_end - 1
+            ));
+        }
+    }
+    let allow_loss = args.allow_precision_loss;
+    let path = args.camera_path.as_ref();
+    let zoom_end = precision_end(&zoom, args.zoom_start, args.zoom_end, allow_loss, path)?;
+    // Centers worked out from the ranges only have the digits of an f64.
+    let given = match (&args.keyframes, &args.center, args.ranges, &args.camera_path) {
+        (Some(keyframes), _, _, _) => keyframes.iter().map(|keyframe| &keyframe.center).collect(),
+        (None, _, _, Some(path)) => path.rows.iter().map(|row| &row.center).collect(),
+        (None, Some(center), _, None) => vec![center],
+        (None, None, Some(_), None) => vec![],
+        (None, None, None, None) => vec![zoom.path.first_center()],
+    };
+    // The centers along a pan are worked out to the digits they need, as
+    // are those of a zoom closing in on a point.
+    let given = match (&args.pan, &args.zoom_to) {
+        (Some(pan), _) => pan.waypoints.iter().collect(),
+        (None, Some(target)) => vec![target],
+        (None, None) => given,
+    };
+    warn_truncated_centers(&zoom, &given, args.zoom_start..zoom_end);
+    if let Some(corners) = args.view {
+        report_fit(corners, args.width, args.height, args.fit);
+    }
+    // Every shard stops where the zoom does, so together they render the
+    // frames the whole run would.
+    let shard = args.shard.map(|shard| ShardRecord {
+        shard,
+        zoom_start: args.zoom_start,
+        zoom_end,
+    });
+    let (zoom_start, zoom_end) = match args.shard {
+        Some(shard) => {
+            let frames = shard.frames(args.zoom_start..zoom_end);
+            events::say(format!(
+                "Rendering frames {} to {} as shard {} of {}",
+                frames.start,
+                frames.end.saturating_sub(1),
+                shard.index,
+                shard.count
+            ));
+            (frames.start, frames.end)
         }
+        None => (args.zoom_start, zoom_end),
     };
+    let threads = frame_threads(args.frame_parallelism);
+    let memory = frame_memory(&zoom, threads, args.pipe_video) * args.frame_parallelism as u64
+        + image_memory(&zoom) * zoom.palettes.len() as u64 * (SAVE_QUEUE + SAVERS) as u64;
+    if memory > args.max_memory {
+        let mib = |bytes: u64| bytes.div_ceil(1 << 20);
+        return Err(RustlebrotError::Argument(format!(
+            "rendering {} frames at once takes about {} MiB, more than the {} MiB of \
+             --max-memory; render fewer at once with --frame-parallelism or raise --max-memory",
+            args.frame_parallelism,
+            mib(memory),
+            mib(args.max_memory),
+        )));
+    }
 
-    let zoom_factor: f64 = match args[4].parse() {
-        Ok(n) => n,
-        Err(_) => {
-            eprintln!("Error: zoom_factor should be a float");
-            std::process::exit(1);
+    let dir = &args.output_dir;
+    let manifest_path = format!("{}/manifest.json", dir);
+    // The manifest of the run being resumed, if it left one.
+    let previous = match args.resume {
+        true => Manifest::read(&manifest_path).ok(),
+        false => None,
+    };
+    for set in &zoom.palettes {
+        std::fs::create_dir_all(&set.dir).map_err(|e| RustlebrotError::write(&set.dir, e))?;
+    }
+    let resumed = match args.resume {
+        true => resumed_frames(&zoom, zoom_start..zoom_end, previous.as_ref())?,
+        false => {
+            check_existing(&zoom, zoom_start..zoom_end, args.video.overwrite)?;
+            Vec::new()
         }
     };
+    if args.resume {
+        events::say(format!(
+            "Resuming with {} of {} frames saved already",
+            resumed.len(),
+            zoom_end.saturating_sub(zoom_start)
+        ));
+    }
 
-    let (width, height) = (1200, 1200);
+    // Without PNG frames there is nothing to encode. Every palette gets a
+    // video of its own, next to its frames, and so does every size of
+    // --outputs. Shards leave that to merge.
+    let encodes = !args.no_video
+        && (zoom.mode != Mode::Escape || zoom.export.png)
+        && (args.shard.is_none() || args.assemble);
+    let dirs: Vec<String> = match zoom.outputs.is_empty() {
+        true => zoom.palettes.iter().map(|set| set.dir.clone()).collect(),
+        false => {
+            let dir = |output: &Output| format!("{}/{}", zoom.output_dir, output.name());
+            zoom.outputs.iter().map(dir).collect()
+        }
+    };
+    let stems: Vec<String> = dirs.iter().map(|dir| format!("{}/rust_out", dir)).collect();
+    let encoder = match encodes {
+        true => {
+            let encoder = EncoderKind::resolve(args.encoder)?;
+            let outputs = stems.iter().map(|stem| encoder.output(stem, &args.video));
+            Some((encoder, outputs.collect::<Result<Vec<_>, _>>()?))
+        }
+        false => None,
+    };
+    let mut video_options = args.video.clone();
+    if let (Some((encoder, _)), true) = (&encoder, args.alpha != Alpha::None) {
+        let codec = &args.video.codec;
+        match (encoder, video::alpha_pix_fmt(codec)) {
+            (EncoderKind::Ffmpeg, Some(_)) => video_options.alpha = true,
+            _ => events::say(format!(
+                "Note: {} videos have no alpha channel, so only the frames are transparent; \
+                 use --codec prores_ks or vp9 for a video that keeps it.",
+                match encoder {
+                    EncoderKind::Ffmpeg => codec.as_str(),
+                    encoder => encoder.name(),
+                }
+            )),
+        }
+        zoom.video_alpha = video_options.alpha;
+    }
+    // ffmpeg's pixel format can refuse the size of the frames, which is
+    // better found out now than once they're rendered.
+    // The video is of the frames as they're written, which with --roi are
+    // of the region.
+    let (video_width, video_height) = zoom.frame_size();
+    if let Some((encoder, _)) = &encoder {
+        encoder.video_size(video_width, video_height, args.colors.bit_depth, &video_options)?;
+        for output in zoom.outputs.iter().skip(1) {
+            encoder.video_size(output.width, output.height, args.colors.bit_depth, &video_options)?;
+        }
+    }
 
-    // let zoom_point = (-0.75, 0.109); // The point to zoom in on
-    // let zoom_point = (-0.10109636384562, 0.95628651080914);
-    // let zoom_point = (-0.77568377, 0.13646737);
-    let x_center: f64 = -1.74999841099374081749002483162428393452822172335808534616943930976364725846655540417646727085571962736578151132907961927190726789896685696750162524460775546580822744596887978637416593715319388030232414667046419863755743802804780843375;
-    let y_center: f64 = -0.00000000000000165712469295418692325810961981279189026504290127375760405334498110850956047368308707050735960323397389547038231194872482690340369921750514146922400928554011996123112902000856666847088788158433995358406779259404221904755;
+    let frames: Vec<u32> =
+        (zoom_start..zoom_end).filter(|frame| !resumed.contains(frame)).collect();
+    let model = args.time_budget.zip(calibration.as_ref());
+    let model = model.map(|(seconds, (model, _))| (model, budget_left(seconds)));
+    let kind = encoder.as_ref().map(|(encoder, _)| *encoder);
+    if !preflight(&args, &mut zoom, &frames, kind, model)? {
+        events::say("Leaving the run, as asked");
+        return Ok(());
+    }
+    if args.expmap {
+        zoom.expmap = Some(expmap_strips(&zoom, &frames, &args)?);
+    }
+    let time_budget = args.time_budget.zip(calibration).map(|(seconds, (model, calibration))| {
+        fit_budget(&mut zoom, model, &frames, budget_left(seconds));
+        let budget = zoom.time_budget.as_ref().unwrap().lock().unwrap();
+        BudgetRecord {
+            seconds,
+            calibration_seconds: calibration,
+            predicted_seconds: budget.predicted_seconds(),
+            supersample: zoom.supersample,
+            scale: budget.scale(),
+            schedule: budget.schedule(),
+        }
+    });
+    let interior_periods = match args.interior_coloring {
+        Some(coloring) if coloring.shows_period() => interior::legend(),
+        _ => Vec::new(),
+    };
+    let manifest = Manifest {
+        version: MANIFEST_VERSION,
+        software: format!("rustlebrot {}", env!("CARGO_PKG_VERSION")),
+        fractal: args.fractal.name().to_string(),
+        mode: args.mode.name().to_string(),
+        center: zoom.path.first_center().clone(),
+        zoom_factor: args.zoom_factor,
+        max_iter: args.max_iter,
+        width,
+        height,
+        palette: zoom.palettes[0].name.to_string(),
+        palettes: match zoom.palettes.len() {
+            1 => Vec::new(),
+            _ => zoom.palettes.iter().map(|set| set.name.to_string()).collect(),
+        },
+        outputs: zoom.outputs.iter().map(Output::name).collect(),
+        interior_periods: interior_periods.clone(),
+        image_format: args.image_format.name().to_string(),
+        post: args.post.as_ref().map(Chain::to_string),
+        quality: quality_record(&args),
+        time_budget,
+        target: args.target,
+        direction: args.direction.name().to_string(),
+        sequence: sequence_record(&video_options, kind, zoom_start..zoom_end),
+        shutter: args.motion_blur.map(|blur| Shutter {
+            sub_frames: blur.sub_frames,
+            angle: blur.shutter_angle,
+        }),
+        camera_path: args.camera_path.as_ref().map(|path| CameraPathRecord {
+            file: path.file.clone(),
+            crc32: format!("{:08x}", path.crc32),
+        }),
+        view: args.view.map(|corners| {
+            let (fitted_x_range, fitted_y_range) = corners.fitted(width, height, args.fit);
+            ViewRecord {
+                x_range: corners.x_range,
+                y_range: corners.y_range,
+                fit: args.fit.name().to_string(),
+                fitted_x_range,
+                fitted_y_range,
+                center: corners.center(),
+                zoom_to: args.zoom_to.clone().unwrap_or_else(|| zoom.path.first_center().clone()),
+            }
+        }),
+        settings: Some(args.settings()),
+        args: command_line.to_vec(),
+        shard,
+        frames: Vec::new(),
+        early_stop: None,
+        budget_adjustments: Vec::new(),
+    };
+    // The records of the frames kept are carried over from the run that
+    // saved them.
+    let records = previous.map(|previous| previous.frames).unwrap_or_default();
+    let mut manifest = ManifestWriter::create(&manifest_path, &manifest)?;
+    let kept: Vec<&FrameRecord> =
+        records.iter().filter(|record| resumed.contains(&record.frame)).collect();
+    for record in &kept {
+        manifest.append(record)?;
+    }
+    // Stabilized colors carry on from the references of the frames kept.
+    let references = color_references(&manifest_path, kept)?;
+    let stats = StatsWriter::create(&format!("{}/stats.csv", dir), &resumed)?;
+    events::emit(&Event::RunStarted {
+        version: events::EVENTS_VERSION,
+        software: format!("rustlebrot {}", env!("CARGO_PKG_VERSION")),
+        fractal: args.fractal.name(),
+        mode: args.mode.name(),
+        precision: args.precision.name(),
+        coloring: args.coloring.name(),
+        center: zoom.path.first_center(),
+        zoom_factor: args.zoom_factor,
+        max_iter: args.max_iter,
+        width,
+        height,
+        palette: zoom.palettes[0].name,
+        zoom_start,
+        zoom_end,
+        output_dir: dir,
+        resumed: &resumed,
+    });
+    if !interior_periods.is_empty() {
+        let periods: Vec<String> = (1..)
+            .zip(&interior_periods)
+            .map(|(period, color)| format!("{} {}", period, color))
+            .collect();
+        events::say(format!("Interior periods: {}", periods.join(", ")));
+    }
 
-    let x_range_initial: (f64, f64) = (-2.0 + x_center, 2.0 + x_center);
-    let y_range_initial: (f64, f64) = (-2.0 + y_center, 2.0 + y_center);
+    // Frames are only piped with one palette.
+    let video = match encoder.as_ref().filter(|_| args.pipe_video) {
+        Some((encoder, outputs)) => {
+            let output = &outputs[0];
+            let bit_depth = args.colors.bit_depth;
+            let video =
+                encoder.open(output, video_width, video_height, bit_depth, &video_options)?;
+            let fps = encoder.frame_rate(&video_options);
+            let video = video::sequenced(video, output, fps, &video_options)?;
+            events::emit(&Event::VideoStarted {
+                path: output,
+                encoder: encoder.name(),
+            });
+            Some(video)
+        }
+        None => None,
+    };
 
     let program_start_time: Instant = Instant::now();
 
-    generate_frames(
-        zoom_start,
-        zoom_end,
-        x_range_initial,
-        y_range_initial,
-        x_center,
-        y_center,
-        zoom_factor,
-        width,
-        height,
-        max_iter,
-    );
-    
+    interrupt::install()?;
+    let parallelism = args.frame_parallelism;
+    let (times, stopped_early) =
+        generate_frames(frames, &zoom, manifest, stats, video, parallelism, references)?;
+    let rendered: Vec<u32> = times.iter().map(|times| times.frame).collect();
+
     let program_elapsed_time = program_start_time.elapsed();
-    println!(
+    events::say(format!(
         "Avg time per frame: {:.2?} ms.",
         program_elapsed_time.as_millis() as f64 / (zoom_end - zoom_start + 1) as f64,
-    );
+    ));
+
+    // After Ctrl-C or an early stop, the frames up to the first one missing
+    // can still be encoded.
+    let stopped = interrupt::requested();
+    let ended = stopped || stopped_early.is_some();
+    let frames: Vec<u32> = (zoom_start..zoom_end)
+        .take_while(|frame| !ended || resumed.contains(frame) || rendered.contains(frame))
+        .collect();
+    if stopped {
+        events::say(format!(
+            "Stopped with {} of {} frames saved, run again with --resume to render the rest",
+            resumed.len() + rendered.len(),
+            zoom_end.saturating_sub(zoom_start)
+        ));
+    } else if let Some(stop) = &stopped_early {
+        events::say(format!(
+            "Stopped early after frame {}: {} frames in a row were uniform, their escape times \
+             spread by less than {} iterations. {} of {} frames are saved, pass \
+             --no-early-stop to render them all",
+            stop.frame,
+            stop.frames,
+            stop.spread,
+            resumed.len() + rendered.len(),
+            zoom_end.saturating_sub(zoom_start)
+        ));
+    }
+
+    if let Some(path) = args.save_location.as_deref().filter(|_| !stopped) {
+        save_location(&args, &zoom, &frames, path)?;
+    }
+
+    // Piped frames are encoded already.
+    let encoder = encoder.filter(|_| !args.pipe_video && !frames.is_empty());
+    if encoder.is_some() && interrupt::video_skipped() {
+        events::say("Leaving out the video, as asked");
+    }
+    let encoder = encoder.filter(|_| !interrupt::video_skipped());
+    let end = match stopped {
+        true => Err(RustlebrotError::Interrupted),
+        false => Ok(()),
+    };
+    let Some((encoder, outputs)) = encoder else {
+        report_performance(dir, &times, program_start_time.elapsed(), None, parallelism)?;
+        return end;
+    };
+    let encoding = Instant::now();
+    let bit_depth = args.colors.bit_depth;
+    let ext = zoom.image_format.extension();
+    for (index, ((output, stem), dir)) in outputs.iter().zip(&stems).zip(&dirs).enumerate() {
+        let set = &zoom.palettes[if zoom.outputs.is_empty() { index } else { 0 }];
+        let path = |&frame: &u32| match zoom.outputs.get(index) {
+            Some(size) => zoom.output_path(size, frame),
+            None => zoom.frame_path(Some(set), frame, ext),
+        };
+        let paths: Vec<String> = frames.iter().map(path).collect();
+        events::emit(&Event::VideoStarted {
+            path: output,
+            encoder: encoder.name(),
+        });
+        let output = video::encode_frames(&paths, output, bit_depth, encoder, &video_options)
+            .map_err(|e| {
+                let pattern = zoom.filenames.ffmpeg_pattern(set.name, ext);
+                let pattern = pattern.map(|pattern| format!("{}/{}", dir, pattern));
+                encoding_failed(e, pattern, &paths, zoom_start, stem, bit_depth, &video_options)
+            })?;
+        video_saved(&output);
+    }
+    let (wall, video) = (program_start_time.elapsed(), Some(encoding.elapsed()));
+    report_performance(dir, &times, wall, video, parallelism)?;
+    end
+}
+
+/// Writes the performance report of the frames of `times`, rendered in
+/// `wall` time, `parallelism` at a time, and encoded into a video in
+/// `video` after, to `performance.json` in `dir`, and prints it as a
+/// table. Runs that rendered no frames have nothing to report.
+fn report_performance(
+    dir: &str,
+    times: &[FrameTimes],
+    wall: Duration,
+    video: Option<Duration>,
+    parallelism: usize,
+) -> Result<(), RustlebrotError> {
+    if times.is_empty() {
+        return Ok(());
+    }
+    let threads = rayon::current_num_threads();
+    let report = performance::Report::of(times, wall, video, threads, parallelism);
+    let path = format!("{}/performance.json", dir);
+    performance::write(&path, &report)?;
+    events::say(performance::table(&report));
+    events::detail(format!("Performance report saved to {}", path));
+    Ok(())
+}
 
-    Command::new("ffmpeg")
-        .arg("-framerate")
-        .arg("30")
-        .arg("-i")
-        .arg("rust_data/mandelbrot_set_%04d.png")
-        .arg("-c:v")
-        .arg("libx264")
-        .arg("-pix_fmt")
-        .arg("yuv420p")
-        .arg("rust_data/rust_out.mp4")
-        .output()
-        .expect("Failed to execute command");
-
-    println!("Zoom saved to rust_data/rust_out.mp4");
+/// The zoom `args` asks for, colored with `colormaps`, the colormaps of
+/// its palettes in order, as it is before the run fits it to a time budget
+/// or prepares the strips of an exponential map.
+fn zoom_of<'a>(args: &'a cli::Args, colormaps: &'a [(Colormap, Cycle)]) -> Zoom<'a> {
+    let (width, height) = (args.width, args.height);
+
+    let first_row = args.camera_path.as_ref().map(|path| &path.rows[0]);
+    // A pan starts from its first waypoint, as a zoom from its center.
+    let center = args.pan.as_ref().map(|pan| &pan.waypoints[0]).or(args.center.as_ref());
+    let (x_digits, y_digits) = match (center, args.ranges, &args.keyframes, first_row) {
+        (_, _, Some(keyframes), _) => keyframes[0].center.clone(),
+        (_, _, None, Some(row)) => row.center.clone(),
+        (Some(center), _, _, _) => center.clone(),
+        (None, Some((x_range, y_range)), _, _) => (
+            ((x_range.0 + x_range.1) / 2.0).to_string(),
+            ((y_range.0 + y_range.1) / 2.0).to_string(),
+        ),
+        (None, None, None, None) => {
+            let (x, y) = args.fractal.default_center();
+            (x.to_string(), y.to_string())
+        }
+    };
+    let x_center: f64 = x_digits.parse().expect("centers are validated when read");
+    let y_center: f64 = y_digits.parse().expect("centers are validated when read");
+    let half_width = args.fractal.default_half_width();
+
+    // Without ranges, the fractal's default square is fitted to the frame,
+    // which for the default contain fit keeps all of it in view.
+    let (x_range_initial, y_range_initial) = args.ranges.unwrap_or((
+        (-half_width + x_center, half_width + x_center),
+        (-half_width + y_center, half_width + y_center),
+    ));
+    let (x_range_initial, y_range_initial) =
+        view::fit(x_range_initial, y_range_initial, width, height, args.fit);
+    // A time budget can raise the supersampling, which interpolated centers
+    // need the digits for.
+    let supersample = match args.time_budget.is_some() && args.fit_supersample {
+        true => MAX_BUDGET_SUPERSAMPLE,
+        false => args.supersample,
+    };
+    let sample_size = ((x_range_initial.1 - x_range_initial.0) / width as f64)
+        .min((y_range_initial.1 - y_range_initial.0) / height as f64)
+        / supersample as f64;
+    // A pan is held at the one magnification its view width gives, on a
+    // keyframe in every frame.
+    let pan = args.pan.as_ref().map(|pan| {
+        let magnification = (x_range_initial.1 - x_range_initial.0) / pan.view_width;
+        let pixel_size = ((x_range_initial.1 - x_range_initial.0) / magnification / width as f64)
+            .min((y_range_initial.1 - y_range_initial.0) / magnification / height as f64);
+        let frames = args.zoom_start..args.zoom_end;
+        let sample_size = pixel_size / supersample as f64;
+        pan.plan(frames, args.easing, magnification, pixel_size, sample_size)
+    });
+    let path = match (args.keyframes.clone(), &args.camera_path, &pan) {
+        (_, _, Some(pan)) => CameraPath::new(pan.keyframes.clone(), 1.0, sample_size),
+        (Some(keyframes), _, _) => CameraPath::new(keyframes, args.zoom_factor, sample_size),
+        (None, Some(path), None) => {
+            CameraPath::through(path, x_range_initial.1 - x_range_initial.0, sample_size)
+        }
+        (None, None, None) => match &args.zoom_to {
+            Some(target) => CameraPath::toward(
+                (x_digits, y_digits),
+                target.clone(),
+                args.zoom_factor,
+                sample_size,
+            ),
+            None => CameraPath::fixed((x_digits, y_digits), args.zoom_factor, sample_size),
+        },
+    };
+
+    let several = colormaps.len() > 1;
+    let palettes = args.colors.palettes().iter().zip(colormaps).map(|(source, (colormap, cycle))| {
+        PaletteSet {
+            name: source.name(),
+            colormap,
+            cycle: *cycle,
+            dir: match (several, args.outputs.first()) {
+                (true, _) => format!("{}/{}", args.output_dir, source.name()),
+                (false, Some(output)) => format!("{}/{}", args.output_dir, output.name()),
+                (false, None) => args.output_dir.clone(),
+            },
+        }
+    });
+    let (colormap, cycle) = &colormaps[0];
+    Zoom {
+        fractal: args.fractal,
+        newton: args.newton.clone(),
+        formula: args.formula.clone(),
+        lyapunov: args.lyapunov.clone(),
+        c_path: args.c_path.clone(),
+        c_easing: args.c_easing,
+        width,
+        height,
+        roi: args.roi,
+        outputs: args.outputs.clone(),
+        x_range_initial,
+        y_range_initial,
+        path,
+        // The pan eases its own way along the path, between whole pixels.
+        easing: match pan {
+            Some(_) => Easing::Linear,
+            None => args.easing,
+        },
+        pan,
+        frames: args.zoom_start..args.zoom_end,
+        direction: args.direction,
+        initial_rotation: args.initial_rotation,
+        rotation_per_frame: args.rotation_per_frame,
+        zoom_factor: args.zoom_factor,
+        precision: args.precision,
+        series_terms: args.series_terms,
+        mode: args.mode,
+        export: args.export,
+        image_format: args.image_format,
+        video_alpha: false,
+        dump_iterations: args.dump_iterations,
+        frame_stats: args.frame_stats,
+        measure_dimension: args.measure_dimension,
+        debug_channels: args.debug_channels,
+        early_stop: args.early_stop,
+        options: RenderOptions {
+            max_iter: args.max_iter,
+            periodicity: args.periodicity,
+            subdivision: args.subdivision,
+            work_units: args.work_units,
+            reuse: None,
+            refine: None,
+            rotation: Rotation::NONE,
+            window: None,
+            timing: None,
+            attractors: args.interior_coloring.is_some()
+                || args.colors.line_art().is_some_and(|lines| lines.interior),
+            bailout: args.bailout,
+            coloring: args.coloring,
+            single_precision: false,
+        },
+        colors: ColorOptions {
+            palette_iter: args.max_iter,
+            histogram_clip: args.colors.histogram_clip,
+            transfer: args.colors.transfer,
+            reference: None,
+            colormap,
+            cycle: *cycle,
+            interior: args.colors.interior,
+            bit_depth: args.colors.bit_depth,
+            dither: args.colors.dither,
+            alpha: args.alpha,
+            phase: args.colors.phase,
+            lighting: args.colors.lighting,
+            script: args.color_expr.as_ref().map(|arg| Scripted {
+                script: &arg.script,
+                frame: 0,
+                magnification: 1.0,
+            }),
+            interior_coloring: args.interior_coloring,
+            contours: args.colors.contours,
+            silhouette: args.colors.silhouette,
+            line_art: args.colors.line_art(),
+        },
+        buddhabrot: BuddhabrotOptions {
+            samples: args.samples,
+            sampler: args.sampler,
+            seed: args.seed,
+            min_iter: args.min_iter,
+            tone: args.tone,
+            bands: args
+                .bands
+                .unwrap_or([args.max_iter, args.max_iter / 10, args.max_iter / 100]),
+            bit_depth: args.colors.bit_depth,
+        },
+        auto_iter: args.auto_iter,
+        iter_schedule: args.iter_schedule.clone(),
+        time_budget: None,
+        palette_drift: args.colors.palette_drift,
+        stabilize_colors: args.stabilize_colors,
+        palettes: palettes.collect(),
+        preview_every: args.preview_every,
+        preview_progressive: args.preview_progressive.as_deref(),
+        term_preview: None,
+        dashboard: None,
+        post: args.post.clone(),
+        hud: args.hud,
+        julia_inset: args.julia_inset,
+        rays: args.rays.iter().map(|&angle| Mutex::new(Ray::new(angle))).collect(),
+        output_dir: &args.output_dir,
+        filenames: &args.filenames,
+        incremental: args.incremental,
+        motion_blur: args.motion_blur,
+        supersample: args.supersample,
+        flip_y: args.flip_y,
+        adaptive: args.adaptive,
+        orbits: OrbitCache::default(),
+        expmap: None,
+        color_expr: args.color_expr.as_ref().map(|arg| arg.path.as_str()),
+        recorded_limit: None,
+    }
 }
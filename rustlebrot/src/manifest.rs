@@ -0,0 +1,1083 @@
+use crate::buddhabrot::Sampler;
+use crate::camera::{IterSchedule, Keyframe, Pan};
+use crate::coloring::{Coloring, Phase, Transfer};
+use crate::contour::{Contours, Line};
+use crate::dimension::Dimension;
+use crate::error::RustlebrotError;
+use crate::julia::CPath;
+use crate::lighting::Lighting;
+use crate::lineart::LineArt;
+use crate::lyapunov::Lyapunov;
+use crate::palette::Adjust;
+use crate::render::{Adaptive, Alpha, Roi};
+use crate::stats::FrameStats;
+use crate::trap::Trap;
+use crate::video::Sequence;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{Seek, SeekFrom, Write};
+use std::ops::Range;
+
+/// Version of the manifest schema. It changes whenever a field is removed
+/// or changes meaning, so tools reading manifests can tell what they got.
+pub const MANIFEST_VERSION: u32 = 2;
+
+/// Closes the frame array and the manifest object after the last frame.
+const TAIL: &str = "\n  ]\n}\n";
+
+/// The first line of `stats.csv`.
+const STATS_COLUMNS: &str = "frame,max_iter,seconds,min_iterations,max_iterations,\
+                             mean_iterations,std_dev_iterations,interior_fraction,\
+                             escaped_within_10,spread,dimension,dimension_r_squared";
+
+/// The record of a run written to `manifest.json` in the output directory.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    /// The crate name and version that rendered the run.
+    pub software: String,
+    pub fractal: String,
+    pub mode: String,
+    /// The center as given, with every digit.
+    pub center: (String, String),
+    pub zoom_factor: f64,
+    /// The iteration limit of the first frame, before auto-iter raises it.
+    pub max_iter: u32,
+    pub width: u32,
+    pub height: u32,
+    pub palette: String,
+    /// Every palette the frames were colored with, each into a directory of
+    /// its name, when there were several. `palette` is the first of them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub palettes: Vec<String>,
+    /// The sizes of `--outputs` the colored frames were written at, each
+    /// into a directory of its name, the first the one they were rendered
+    /// at.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outputs: Vec<String>,
+    /// The colors of the periods of the cycles in the interior, from period
+    /// 1 on, as `#rrggbb`, when `--interior-coloring` tells them by hue.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub interior_periods: Vec<String>,
+    /// The format of the colored frames, as with `--image-format`. Runs
+    /// from before it saved PNGs.
+    #[serde(default = "png")]
+    pub image_format: String,
+    /// The filters of `--post` the colored frames went through, in order,
+    /// if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post: Option<String>,
+    /// The settings `--quality` gives defaults for as the run had them, if
+    /// it was given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quality: Option<QualityRecord>,
+    /// How the run was fitted into `--time-budget`, if it was.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_budget: Option<BudgetRecord>,
+    /// The length and depth the run was asked for, if its frames were
+    /// derived from them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<ZoomTarget>,
+    /// Which way the camera goes, `in`, `out` or `in-out`, as with
+    /// `--direction`. With `in-out`, the video plays the frames back out
+    /// after the last.
+    #[serde(default = "inward")]
+    pub direction: String,
+    /// The order the video shows the frames in, when `--video-sequence` gave
+    /// one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<SequenceRecord>,
+    /// How the frames were motion blurred, if they were.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shutter: Option<Shutter>,
+    /// The file of `--camera-path` the run followed, if it did, in place
+    /// of a zoom by `zoom_factor`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub camera_path: Option<CameraPathRecord>,
+    /// The corners of `--view` the first frame was given by, and what the
+    /// run made of them, if it was.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub view: Option<ViewRecord>,
+    /// The rest of the parameters that change what the frames look like,
+    /// for `merge` to check the shards of a zoom agree on. Manifests from
+    /// before sharding don't have them.
+    #[serde(
+        default,
+        deserialize_with = "typed_settings",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub settings: Option<Settings>,
+    /// The arguments of `render` the run was started with, which
+    /// `render-frame` renders any of its frames again from. Manifests from
+    /// before it don't have them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    /// The share of the zoom the run rendered, when it was one of several
+    /// shards.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shard: Option<ShardRecord>,
+    /// Every frame rendered so far, in the order they were finished. Frames
+    /// are rendered in parallel, so this isn't necessarily frame order.
+    pub frames: Vec<FrameRecord>,
+    /// Why the run ended before its last frame, if its frames turned
+    /// uniform.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub early_stop: Option<StoppedEarly>,
+    /// The changes to the time budget's scale as the run went, in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub budget_adjustments: Vec<BudgetAdjustment>,
+}
+
+/// One of `count` runs that split the frames of a zoom between them, as
+/// chosen with `--shard-index` and `--shard-count`.
+///
+/// The frames are cut into `count` contiguous blocks, whose sizes differ by
+/// one frame at most, and shard `index` renders block `index`, counting
+/// from 0. A shard's frames make a stretch of the video on their own, but
+/// deeper frames take longer, so the last shards have the most to do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Shard {
+    pub index: u32,
+    pub count: u32,
+}
+
+impl Shard {
+    /// The frames of `frames` this shard renders.
+    pub fn frames(self, frames: Range<u32>) -> Range<u32> {
+        let len = (frames.end.saturating_sub(frames.start)) as u64;
+        let at = |index: u32| frames.start + (len * index as u64 / self.count as u64) as u32;
+        at(self.index)..at(self.index + 1)
+    }
+}
+
+/// The shard a run was, with the frames of the whole zoom it was a shard
+/// of.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ShardRecord {
+    #[serde(flatten)]
+    pub shard: Shard,
+    /// The first frame of the zoom and the one after its last, once it
+    /// stops where precision runs out.
+    pub zoom_start: u32,
+    pub zoom_end: u32,
+}
+
+/// The camera path a run followed, with the checksum of the file, so that
+/// rendering its frames again can tell when the file has changed since.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CameraPathRecord {
+    pub file: String,
+    /// The CRC-32 of the file, as 8 hex digits.
+    pub crc32: String,
+}
+
+/// The view of a run given by its corners with `--view`, as given and as
+/// fitted to the frame by `fit`, and the point the zoom closed in on.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ViewRecord {
+    pub x_range: (f64, f64),
+    pub y_range: (f64, f64),
+    /// As with `--fit`.
+    pub fit: String,
+    /// The ranges of the first frame, once fitted.
+    pub fitted_x_range: (f64, f64),
+    pub fitted_y_range: (f64, f64),
+    pub center: (f64, f64),
+    /// The middle of the view, or the point of `--zoom-to`, which stays
+    /// where it is in the frame all the way in.
+    pub zoom_to: (String, String),
+}
+
+/// The parameters of a run that change what its frames look like, past the
+/// ones the manifest has fields of their own for, as the flags of `render`
+/// give them. Choices are written by the names the flags take them by, and
+/// colors as `[r, g, b]`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Settings {
+    /// The coefficients of the polynomial of a Newton fractal, highest
+    /// power first.
+    pub newton: Option<Vec<f64>>,
+    pub formula: Option<String>,
+    pub formula_log_base: Option<f64>,
+    pub c_path: Option<CPathRecord>,
+    pub c_easing: String,
+    pub lyapunov: Option<LyapunovRecord>,
+    pub precision: String,
+    pub allow_precision_loss: bool,
+    pub series_terms: usize,
+    pub periodicity: bool,
+    /// `off`, `on`, or `show` with `--show-subdivision`.
+    pub subdivision: String,
+    pub supersample: u32,
+    pub adaptive: Option<AdaptiveRecord>,
+    pub coloring: ColoringRecord,
+    pub interior_coloring: Option<String>,
+    /// The source of the script of `--color-expr`.
+    pub color_expr: Option<String>,
+    pub histogram_clip: f64,
+    pub transfer: TransferRecord,
+    pub stabilize_colors: Option<f64>,
+    pub phase: PhaseRecord,
+    pub lighting: Option<LightingRecord>,
+    pub contours: Option<ContoursRecord>,
+    pub silhouette: Option<LineRecord>,
+    pub line_art: Option<LineArtRecord>,
+    pub palette_sources: Vec<PaletteRecord>,
+    pub interior: [u8; 3],
+    pub palette_cycles: f64,
+    pub palette_offset: f64,
+    pub palette_reverse: bool,
+    pub palette_drift: f64,
+    pub adjust: AdjustRecord,
+    pub bit_depth: u8,
+    pub dither: String,
+    /// `linear`, or `srgb` with `--legacy-gamma`.
+    pub blending: String,
+    pub palette_resolution: usize,
+    pub samples: u64,
+    pub sampler: SamplerRecord,
+    pub seed: u64,
+    pub min_iter: u32,
+    pub tone: String,
+    pub bands: Option<[u32; 3]>,
+    pub auto_iter: Option<f64>,
+    pub iter_schedule: Option<IterScheduleRecord>,
+    pub bailout: f64,
+    pub ranges: Option<RangesRecord>,
+    pub zoom_to: Option<(String, String)>,
+    pub keyframes: Option<Vec<KeyframeRecord>>,
+    /// The CRC-32 of the file of `--camera-path`, as 8 hex digits.
+    pub camera_path: Option<String>,
+    pub pan: Option<PanRecord>,
+    pub easing: String,
+    pub motion_blur: Option<Shutter>,
+    pub expmap: bool,
+    pub initial_rotation: f64,
+    pub rotation_per_frame: f64,
+    pub fit: String,
+    pub roi: Option<RoiRecord>,
+    /// The sizes of `--outputs`, as the directories of their frames are
+    /// named.
+    pub outputs: Vec<String>,
+    pub flip_y: bool,
+    /// The files written for every frame, `png` and `exr`.
+    pub export: Vec<String>,
+    pub image_format: String,
+    pub jpeg_quality: Option<u8>,
+    pub alpha: AlphaRecord,
+    pub dump_iterations: bool,
+    /// The channels of `--debug-channels`, `iter`, `time` and `samples`.
+    pub debug_channels: Vec<String>,
+    /// The filters of `--post`, as given.
+    pub post: Option<String>,
+    pub filename_template: String,
+}
+
+impl Settings {
+    /// The settings by name, each as its JSON.
+    pub fn by_name(&self) -> BTreeMap<String, serde_json::Value> {
+        match serde_json::to_value(self).expect("settings serialize to JSON") {
+            serde_json::Value::Object(settings) => settings.into_iter().collect(),
+            _ => unreachable!("settings are an object"),
+        }
+    }
+}
+
+/// The settings of a manifest, or none for one of version 1, which wrote
+/// them as strings that can't be read back.
+fn typed_settings<'de, D>(deserializer: D) -> Result<Option<Settings>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = serde_json::Value::deserialize(deserializer)?;
+    Ok(serde_json::from_value(value).ok())
+}
+
+/// The path the `c` of a Julia zoom takes, as with `--c-path`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "path", rename_all = "kebab-case")]
+pub enum CPathRecord {
+    Circle { center: (f64, f64), radius: f64, turns: f64 },
+    Keyframes { points: Vec<(f64, f64)> },
+}
+
+impl From<&CPath> for CPathRecord {
+    fn from(path: &CPath) -> Self {
+        match path {
+            &CPath::Circle { center, radius, turns } => {
+                CPathRecord::Circle { center, radius, turns }
+            }
+            CPath::Keyframes(points) => CPathRecord::Keyframes { points: points.clone() },
+        }
+    }
+}
+
+/// The sequence and iterations of a Lyapunov fractal.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LyapunovRecord {
+    /// As given with `--sequence`, like `AB`.
+    pub sequence: String,
+    pub warmup: u32,
+    pub samples: u32,
+}
+
+impl From<&Lyapunov> for LyapunovRecord {
+    fn from(lyapunov: &Lyapunov) -> Self {
+        LyapunovRecord {
+            sequence: lyapunov.sequence(),
+            warmup: lyapunov.warmup(),
+            samples: lyapunov.samples(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AdaptiveRecord {
+    pub threshold: f64,
+    pub samples: u32,
+}
+
+impl From<Adaptive> for AdaptiveRecord {
+    fn from(adaptive: Adaptive) -> Self {
+        let Adaptive { threshold, samples } = adaptive;
+        AdaptiveRecord { threshold, samples }
+    }
+}
+
+/// A coloring as chosen with `--coloring`, with what its kind is given.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "coloring", rename_all = "kebab-case")]
+pub enum ColoringRecord {
+    Escape,
+    Smooth,
+    Histogram,
+    Distance,
+    Trap { trap: TrapRecord },
+    Phase,
+    /// Binary decomposition into `2^sectors` sectors.
+    Binary { sectors: u32 },
+    Stripes { density: f64 },
+    /// The script of `color_expr`.
+    Script,
+}
+
+impl From<Coloring> for ColoringRecord {
+    fn from(coloring: Coloring) -> Self {
+        match coloring {
+            Coloring::EscapeTime => ColoringRecord::Escape,
+            Coloring::Smooth => ColoringRecord::Smooth,
+            Coloring::Histogram => ColoringRecord::Histogram,
+            Coloring::Distance => ColoringRecord::Distance,
+            Coloring::Trap(trap) => ColoringRecord::Trap { trap: trap.into() },
+            Coloring::Phase => ColoringRecord::Phase,
+            Coloring::Binary(sectors) => ColoringRecord::Binary { sectors },
+            Coloring::Stripes(density) => ColoringRecord::Stripes { density },
+            Coloring::Script(_) => ColoringRecord::Script,
+        }
+    }
+}
+
+/// The shape orbits are measured against, as with `--trap`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "shape", rename_all = "kebab-case")]
+pub enum TrapRecord {
+    Point { x: f64, y: f64 },
+    Cross { x: f64, y: f64 },
+    Circle { radius: f64 },
+}
+
+impl From<Trap> for TrapRecord {
+    fn from(trap: Trap) -> Self {
+        match trap {
+            Trap::Point(x, y) => TrapRecord::Point { x, y },
+            Trap::Cross(x, y) => TrapRecord::Cross { x, y },
+            Trap::Circle(radius) => TrapRecord::Circle { radius },
+        }
+    }
+}
+
+/// How escape times are spread over the gradient, as with `--transfer`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "transfer", rename_all = "kebab-case")]
+pub enum TransferRecord {
+    Linear,
+    Sqrt,
+    Log,
+    Power { exponent: f64 },
+}
+
+impl From<Transfer> for TransferRecord {
+    fn from(transfer: Transfer) -> Self {
+        match transfer {
+            Transfer::Linear => TransferRecord::Linear,
+            Transfer::Sqrt => TransferRecord::Sqrt,
+            Transfer::Log => TransferRecord::Log,
+            Transfer::Power(exponent) => TransferRecord::Power { exponent },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PhaseRecord {
+    pub weight: f64,
+    pub turns: f64,
+}
+
+impl From<Phase> for PhaseRecord {
+    fn from(phase: Phase) -> Self {
+        let Phase { weight, turns } = phase;
+        PhaseRecord { weight, turns }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LightingRecord {
+    pub angle: f64,
+    pub elevation: f64,
+    pub strength: f64,
+    pub specular: f64,
+    pub spin: f64,
+}
+
+impl From<Lighting> for LightingRecord {
+    fn from(lighting: Lighting) -> Self {
+        let Lighting { angle, elevation, strength, specular, spin } = lighting;
+        LightingRecord { angle, elevation, strength, specular, spin }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LineRecord {
+    pub width: f64,
+    pub color: [u8; 3],
+}
+
+impl From<Line> for LineRecord {
+    fn from(line: Line) -> Self {
+        LineRecord {
+            width: line.width,
+            color: rgb(line.color),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ContoursRecord {
+    pub every: f64,
+    pub line: LineRecord,
+    pub background: Option<[u8; 3]>,
+}
+
+impl From<Contours> for ContoursRecord {
+    fn from(contours: Contours) -> Self {
+        ContoursRecord {
+            every: contours.every,
+            line: contours.line.into(),
+            background: contours.background.map(rgb),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LineArtRecord {
+    pub threshold: f64,
+    pub weight: u32,
+    pub silhouette: bool,
+    pub interior: bool,
+    pub thin: bool,
+    /// `white` or `transparent`, as with `--line-paper`.
+    pub paper: String,
+}
+
+impl From<LineArt> for LineArtRecord {
+    fn from(lines: LineArt) -> Self {
+        LineArtRecord {
+            threshold: lines.threshold,
+            weight: lines.weight,
+            silhouette: lines.silhouette,
+            interior: lines.interior,
+            thin: lines.thin,
+            paper: lines.paper.name().to_string(),
+        }
+    }
+}
+
+/// A palette of a run: a built-in one by name, or the stops or colors it
+/// was read as.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PaletteRecord {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stops: Vec<StopRecord>,
+    /// The colors of a `.map` file, in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub colors: Vec<[u8; 3]>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StopRecord {
+    pub position: f64,
+    pub color: [u8; 3],
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AdjustRecord {
+    pub invert: bool,
+    pub hue_shift: f64,
+    pub saturation: f64,
+    pub gamma: f64,
+}
+
+impl From<Adjust> for AdjustRecord {
+    fn from(adjust: Adjust) -> Self {
+        let Adjust { invert, hue_shift, saturation, gamma } = adjust;
+        AdjustRecord { invert, hue_shift, saturation, gamma }
+    }
+}
+
+/// How the points of a Buddhabrot are drawn, as with `--sampler`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "sampler", rename_all = "kebab-case")]
+pub enum SamplerRecord {
+    Uniform,
+    Metropolis { mutation_scale: f64, burn_in: u64 },
+}
+
+impl From<Sampler> for SamplerRecord {
+    fn from(sampler: Sampler) -> Self {
+        match sampler {
+            Sampler::Uniform => SamplerRecord::Uniform,
+            Sampler::Metropolis(metropolis) => SamplerRecord::Metropolis {
+                mutation_scale: metropolis.mutation,
+                burn_in: metropolis.burn_in,
+            },
+        }
+    }
+}
+
+/// The iteration limits of `--iter-schedule`, as `[frame, max_iter]`
+/// breakpoints, and whether the limit runs straight between them.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IterScheduleRecord {
+    pub points: Vec<(u32, u32)>,
+    pub interpolate: bool,
+}
+
+impl From<&IterSchedule> for IterScheduleRecord {
+    fn from(schedule: &IterSchedule) -> Self {
+        IterScheduleRecord {
+            points: schedule.points().to_vec(),
+            interpolate: schedule.interpolates(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RangesRecord {
+    pub x_range: (f64, f64),
+    pub y_range: (f64, f64),
+}
+
+/// A keyframe of `--keyframes`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct KeyframeRecord {
+    pub frame: u32,
+    /// The center with every digit.
+    pub center: (String, String),
+    pub magnification: f64,
+    pub max_iter: Option<u32>,
+    pub rotation: Option<f64>,
+}
+
+impl From<&Keyframe> for KeyframeRecord {
+    fn from(keyframe: &Keyframe) -> Self {
+        KeyframeRecord {
+            frame: keyframe.frame,
+            center: keyframe.center.clone(),
+            magnification: keyframe.magnification,
+            max_iter: keyframe.max_iter,
+            rotation: keyframe.rotation,
+        }
+    }
+}
+
+/// The path of a pan, through its waypoints with every digit.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PanRecord {
+    pub waypoints: Vec<(String, String)>,
+    pub view_width: f64,
+    pub refresh: u32,
+}
+
+impl From<&Pan> for PanRecord {
+    fn from(pan: &Pan) -> Self {
+        PanRecord {
+            waypoints: pan.waypoints.clone(),
+            view_width: pan.view_width,
+            refresh: pan.refresh,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RoiRecord {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub fill: bool,
+}
+
+impl From<Roi> for RoiRecord {
+    fn from(roi: Roi) -> Self {
+        let Roi { x, y, width, height, fill } = roi;
+        RoiRecord { x, y, width, height, fill }
+    }
+}
+
+/// Which pixels of the frames are transparent, as with `--alpha`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "alpha", rename_all = "kebab-case")]
+pub enum AlphaRecord {
+    None,
+    Interior,
+    Threshold { value: f64 },
+    /// The paper of transparent line art.
+    Lines,
+}
+
+impl From<Alpha> for AlphaRecord {
+    fn from(alpha: Alpha) -> Self {
+        match alpha {
+            Alpha::None => AlphaRecord::None,
+            Alpha::Interior => AlphaRecord::Interior,
+            Alpha::Threshold(value) => AlphaRecord::Threshold { value },
+            Alpha::Lines => AlphaRecord::Lines,
+        }
+    }
+}
+
+/// `color` as `[r, g, b]`.
+pub fn rgb(color: (u8, u8, u8)) -> [u8; 3] {
+    [color.0, color.1, color.2]
+}
+
+/// The shutter of a run with `--motion-blur`, which averaged every frame
+/// over `sub_frames` views taken while it was open, for `angle` degrees of
+/// the 360 a frame lasts, centered on the frame.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Shutter {
+    pub sub_frames: u32,
+    pub angle: f64,
+}
+
+/// What `--quality` came to in a run: the settings it gives defaults for,
+/// as they were in the end, with flags given over them. Qualities can be
+/// retuned, so what one meant stays on record.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct QualityRecord {
+    pub quality: String,
+    pub width: u32,
+    pub height: u32,
+    /// Samples per side of a pixel, at most with `adaptive`.
+    pub supersample: u32,
+    pub adaptive: bool,
+    pub max_iter: u32,
+    /// The quality of the frames, if they're JPEGs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jpeg_quality: Option<u8>,
+    pub bit_depth: u8,
+}
+
+/// The order the video of a run shows its frames in, as `video::Order` has
+/// it for `--video-sequence`. A run that stops short plays the frames it saved
+/// the same way.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SequenceRecord {
+    /// As given with `--video-sequence`, like `loop-hold:2`.
+    pub sequence: String,
+    /// The stretches of frames the video plays, by the numbers of their
+    /// first and last frame, counting down where the first is the later.
+    pub spans: Vec<(u32, u32)>,
+    /// Times the last frame is shown again at the end.
+    pub held: usize,
+}
+
+/// The record of a run that ended early because its frames turned uniform.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StoppedEarly {
+    /// The last of the uniform frames that ended the run. Frames already
+    /// in progress were still saved after it.
+    pub frame: u32,
+    /// Consecutive uniform frames that end a run.
+    pub frames: u32,
+    /// The spread under which a frame counts as uniform, in iterations.
+    pub spread: f64,
+}
+
+/// A zoom asked for as a video of `seconds` at `fps` that ends at
+/// `magnification`, as with `--duration` and `--target-magnification`, and
+/// the frames derived from it.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ZoomTarget {
+    pub magnification: f64,
+    pub seconds: f64,
+    /// The frames a second the video plays at, as with `--fps`, or as the
+    /// delay of `--format gif` has it.
+    pub fps: f64,
+    /// The frames of the zoom, the first at magnification 1.
+    pub frames: u32,
+    /// The factor every frame zooms by, which takes the last frame to
+    /// `magnification`.
+    pub zoom_factor: f64,
+}
+
+/// The plan a run made to fit into its time budget, before its first frame.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BudgetRecord {
+    /// The budget of the whole run, calibration included.
+    pub seconds: f64,
+    pub calibration_seconds: f64,
+    /// What the frames were predicted to take as planned.
+    pub predicted_seconds: f64,
+    /// Samples along each side of a pixel.
+    pub supersample: u32,
+    /// How far every frame's max_iter is scaled from what the command line
+    /// asks for.
+    pub scale: f64,
+    /// The max_iter planned for every frame, as `[frame, max_iter]` pairs.
+    pub schedule: Vec<(u32, u32)>,
+}
+
+/// A change to the scale of the max_iter of a budgeted run's remaining
+/// frames, after they ran faster or slower than predicted.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BudgetAdjustment {
+    /// The frame after which the scale changed.
+    pub frame: u32,
+    /// The time the frames so far took, over what was predicted.
+    pub ratio: f64,
+    pub scale: f64,
+}
+
+impl Manifest {
+    /// Reads the manifest at `path`.
+    pub fn read(path: &str) -> Result<Manifest, RustlebrotError> {
+        let json = std::fs::read_to_string(path).map_err(|e| RustlebrotError::read(path, e))?;
+        serde_json::from_str(&json).map_err(|e| RustlebrotError::format(path, e))
+    }
+
+    /// The order of `--video-sequence` the video of the run was encoded in, if it
+    /// had one.
+    pub fn sequence(&self) -> Option<Sequence> {
+        let record = self.sequence.as_ref()?;
+        Sequence::from_spec(&record.sequence).ok()
+    }
+
+    /// The directory the run in `dir` wrote its frames in `palette` to, or
+    /// with `--outputs`, the frames as they were rendered.
+    pub fn frames_dir(&self, dir: &str, palette: &str) -> String {
+        match (self.outputs.first(), self.palettes.is_empty()) {
+            (Some(output), _) => format!("{}/{}", dir, output),
+            (None, false) => format!("{}/{}", dir, palette),
+            (None, true) => dir.to_string(),
+        }
+    }
+
+    /// The parameters of the zoom the run rendered, by name, as they have
+    /// to agree between its shards: everything but the frames, how the run
+    /// went, and which shard it was.
+    pub fn parameters(&self) -> BTreeMap<String, String> {
+        let mut parameters = BTreeMap::from([
+            ("software".to_string(), self.software.clone()),
+            ("fractal".to_string(), self.fractal.clone()),
+            ("mode".to_string(), self.mode.clone()),
+            ("center".to_string(), format!("{},{}", self.center.0, self.center.1)),
+            ("zoom_factor".to_string(), self.zoom_factor.to_string()),
+            ("max_iter".to_string(), self.max_iter.to_string()),
+            ("width".to_string(), self.width.to_string()),
+            ("height".to_string(), self.height.to_string()),
+            ("palette".to_string(), self.palette.clone()),
+            ("palettes".to_string(), self.palettes.join(",")),
+            ("direction".to_string(), self.direction.clone()),
+        ]);
+        if let Some(record) = &self.shard {
+            parameters.insert("shard_count".to_string(), record.shard.count.to_string());
+            let frames = format!("{}..{}", record.zoom_start, record.zoom_end);
+            parameters.insert("zoom_frames".to_string(), frames);
+        }
+        if let Some(settings) = &self.settings {
+            let settings = settings.by_name().into_iter();
+            parameters.extend(settings.map(|(name, value)| (name, value.to_string())));
+        }
+        parameters
+    }
+}
+
+/// How one frame of a run was rendered.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FrameRecord {
+    pub frame: u32,
+    pub x_range: (f64, f64),
+    pub y_range: (f64, f64),
+    /// The width of a pixel in the complex plane.
+    pub pixel_size: f64,
+    /// The scale of the view relative to the first frame of a plain zoom.
+    /// Manifests from before easing don't have it.
+    #[serde(default)]
+    pub magnification: f64,
+    /// How far the frame is turned, in degrees counterclockwise. Manifests
+    /// from before rotation don't have it.
+    #[serde(default)]
+    pub rotation: f64,
+    /// Which way the camera moves at the frame, `in` or `out`, going by
+    /// the frame numbers.
+    #[serde(default = "inward")]
+    pub direction: String,
+    /// The iteration limit the frame was rendered with.
+    pub max_iter: u32,
+    /// The precision the frame was rendered in, like `f64`. Manifests from
+    /// before it was recorded don't have it.
+    #[serde(default)]
+    pub precision: Option<String>,
+    /// With motion blur, the magnifications of the first and the last of
+    /// the views the frame was averaged from.
+    #[serde(default)]
+    pub shutter_magnifications: Option<(f64, f64)>,
+    /// Wall time of the frame, in seconds.
+    pub seconds: f64,
+    /// How far the escape times of the frame spread, see `--early-stop-spread`,
+    /// when its samples are escape times.
+    #[serde(default)]
+    pub spread: Option<f64>,
+    /// With `--stabilize-colors`, the escape times at evenly spaced
+    /// gradient positions the frame was colored by, see
+    /// `stabilize::Reference`, so it can be colored the same way again.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color_reference: Option<Vec<f64>>,
+    /// The `c` of the Julia set of the frame, in a zoom of `--fractal
+    /// julia`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub julia_c: Option<(f64, f64)>,
+    /// The box-counting dimension of the boundary in the frame, with
+    /// `--measure-dimension`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dimension: Option<DimensionRecord>,
+    /// The center of the frame with every digit, in a pan or a zoom with
+    /// `--zoom-to`, where it moves from frame to frame.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub center: Option<(String, String)>,
+}
+
+/// The box-counting dimension of the boundary in a frame, see
+/// `Dimension`. The estimate and its fit are left out when there were too
+/// few boundary samples for one.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DimensionRecord {
+    pub boundary_samples: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimate: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r_squared: Option<f64>,
+}
+
+impl From<Dimension> for DimensionRecord {
+    fn from(dimension: Dimension) -> Self {
+        DimensionRecord {
+            boundary_samples: dimension.boundary_samples,
+            estimate: dimension.estimate,
+            r_squared: dimension.r_squared,
+        }
+    }
+}
+
+/// The direction of the runs of manifests from before zooming out, which
+/// all zoomed in.
+fn inward() -> String {
+    "in".to_string()
+}
+
+fn png() -> String {
+    "png".to_string()
+}
+
+/// Writes a manifest one frame at a time.
+///
+/// After every frame the file is a complete manifest of the frames finished
+/// so far, so a run that dies partway still leaves a usable record.
+pub struct ManifestWriter {
+    file: File,
+    path: String,
+    frames: usize,
+    /// What follows the frame array, which is rewritten after every frame.
+    tail: String,
+    early_stop: Option<StoppedEarly>,
+    budget_adjustments: Vec<BudgetAdjustment>,
+}
+
+impl ManifestWriter {
+    /// Creates the manifest at `path` with the run parameters of `manifest`
+    /// and none of its frames.
+    pub fn create(path: &str, manifest: &Manifest) -> Result<Self, RustlebrotError> {
+        let empty = Manifest {
+            frames: Vec::new(),
+            early_stop: None,
+            budget_adjustments: Vec::new(),
+            ..manifest.clone()
+        };
+        let json =
+            serde_json::to_string_pretty(&empty).map_err(|e| RustlebrotError::encode(path, e))?;
+        // Leave the frame array open for `append`.
+        let head = json
+            .strip_suffix("[]\n}")
+            .expect("the frame array is the last field of the manifest");
+        let failed = |e| RustlebrotError::write(path, e);
+        let mut file = File::create(path).map_err(failed)?;
+        write!(file, "{}[{}", head, TAIL).map_err(failed)?;
+        Ok(ManifestWriter {
+            file,
+            path: path.to_string(),
+            frames: 0,
+            tail: TAIL.to_string(),
+            early_stop: None,
+            budget_adjustments: Vec::new(),
+        })
+    }
+
+    /// Adds `record` to the end of the frame array. The file isn't buffered,
+    /// so the record is written out before this returns.
+    pub fn append(&mut self, record: &FrameRecord) -> Result<(), RustlebrotError> {
+        let json =
+            serde_json::to_string(record).map_err(|e| RustlebrotError::encode(&self.path, e))?;
+        let separator = if self.frames == 0 { "" } else { "," };
+        self.file
+            .seek(SeekFrom::End(-(self.tail.len() as i64)))
+            .and_then(|_| write!(self.file, "{}\n    {}{}", separator, json, self.tail))
+            .map_err(|e| RustlebrotError::write(&self.path, e))?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Records why the run ended early after the frame array. Frames can
+    /// still be appended after this.
+    pub fn stop_early(&mut self, stop: &StoppedEarly) -> Result<(), RustlebrotError> {
+        self.early_stop = Some(stop.clone());
+        self.write_tail()
+    }
+
+    /// Adds `adjustment` to the changes of the time budget after the frame
+    /// array. Frames can still be appended after this.
+    pub fn adjust_budget(&mut self, adjustment: &BudgetAdjustment) -> Result<(), RustlebrotError> {
+        self.budget_adjustments.push(adjustment.clone());
+        self.write_tail()
+    }
+
+    /// Rewrites what follows the frame array with the fields recorded so
+    /// far.
+    fn write_tail(&mut self) -> Result<(), RustlebrotError> {
+        let encode = |e| RustlebrotError::encode(&self.path, e);
+        let mut tail = "\n  ]".to_string();
+        if let Some(stop) = &self.early_stop {
+            let json = serde_json::to_string(stop).map_err(encode)?;
+            tail += &format!(",\n  \"early_stop\": {}", json);
+        }
+        if !self.budget_adjustments.is_empty() {
+            let json = serde_json::to_string(&self.budget_adjustments).map_err(encode)?;
+            tail += &format!(",\n  \"budget_adjustments\": {}", json);
+        }
+        tail += "\n}\n";
+        self.file
+            .seek(SeekFrom::End(-(self.tail.len() as i64)))
+            .and_then(|_| self.file.write_all(tail.as_bytes()))
+            .map_err(|e| RustlebrotError::write(&self.path, e))?;
+        self.tail = tail;
+        Ok(())
+    }
+}
+
+/// Writes `stats.csv`, the statistics of every frame, one row at a time.
+///
+/// Rows are written in the order frames are finished. Columns a frame has
+/// no value for, such as the escape times of a distance estimate, are
+/// left empty, but for the dimension of a frame with too few boundary
+/// samples to fit, which is `n/a`.
+pub struct StatsWriter {
+    file: File,
+    path: String,
+}
+
+impl StatsWriter {
+    /// Creates the table at `path`, with the rows of the frames `kept` from
+    /// the table a resumed run left there.
+    pub fn create(path: &str, kept: &[u32]) -> Result<Self, RustlebrotError> {
+        let failed = |e| RustlebrotError::write(path, e);
+        let previous = fs::read_to_string(path).unwrap_or_default();
+        let rows = previous.lines().skip(1).filter(|row| {
+            let frame = row.split(',').next().and_then(|frame| frame.parse().ok());
+            frame.is_some_and(|frame| kept.contains(&frame))
+        });
+        let mut file = File::create(path).map_err(failed)?;
+        writeln!(file, "{}", STATS_COLUMNS).map_err(failed)?;
+        for row in rows {
+            writeln!(file, "{}", row).map_err(failed)?;
+        }
+        Ok(StatsWriter {
+            file,
+            path: path.to_string(),
+        })
+    }
+
+    /// Writes the table at `path` from the rows of the `tables` at the
+    /// paths given with the frames to take from each, in frame order.
+    pub fn merge(path: &str, tables: &[(String, Vec<u32>)]) -> Result<(), RustlebrotError> {
+        let mut rows = BTreeMap::new();
+        for (table, frames) in tables {
+            let table = fs::read_to_string(table).map_err(|e| RustlebrotError::read(table, e))?;
+            for row in table.lines().skip(1) {
+                let frame = row.split(',').next().and_then(|frame| frame.parse().ok());
+                if let Some(frame) = frame.filter(|frame: &u32| frames.contains(frame)) {
+                    rows.entry(frame).or_insert_with(|| row.to_string());
+                }
+            }
+        }
+        let mut table = format!("{}\n", STATS_COLUMNS);
+        for row in rows.values() {
+            table += row;
+            table += "\n";
+        }
+        fs::write(path, table).map_err(|e| RustlebrotError::write(path, e))
+    }
+
+    /// Adds the row of the frame of `record`, with its `stats` if it has
+    /// any. The file isn't buffered, so the row is written out before this
+    /// returns.
+    pub fn append(
+        &mut self,
+        record: &FrameRecord,
+        stats: Option<&FrameStats>,
+    ) -> Result<(), RustlebrotError> {
+        let escape = stats.and_then(|stats| stats.escape);
+        let cell = |value: Option<f64>| value.map(|value| value.to_string()).unwrap_or_default();
+        // Measured, but with too few boundary samples for a fit.
+        let measured = |value: Option<Option<f64>>| match value {
+            Some(None) => "n/a".to_string(),
+            value => cell(value.flatten()),
+        };
+        let row = [
+            record.frame.to_string(),
+            record.max_iter.to_string(),
+            record.seconds.to_string(),
+            cell(escape.map(|escape| escape.min)),
+            cell(escape.map(|escape| escape.max)),
+            cell(escape.map(|escape| escape.mean)),
+            cell(escape.map(|escape| escape.std_dev)),
+            cell(stats.map(|stats| stats.interior)),
+            cell(escape.map(|escape| escape.fast)),
+            cell(stats.map(|stats| stats.spread)),
+            measured(record.dimension.map(|dimension| dimension.estimate)),
+            measured(record.dimension.map(|dimension| dimension.r_squared)),
+        ];
+        writeln!(self.file, "{}", row.join(",")).map_err(|e| RustlebrotError::write(&self.path, e))
+    }
+}
@@ -0,0 +1,272 @@
+use crate::coloring::Transfer;
+use crate::contour;
+use crate::render::EscapeBuffer;
+use std::fmt::Write;
+use std::path::Path;
+
+/// What the interior of the set is raised to in a heightfield, as chosen
+/// with `--plateau`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Plateau {
+    /// A flat top at the height of the highest escape time.
+    Top,
+    /// Down to the base, so the set is a hole in the landscape.
+    Base,
+}
+
+impl Plateau {
+    pub fn from_name(name: &str) -> Option<Plateau> {
+        match name {
+            "top" => Some(Plateau::Top),
+            "base" => Some(Plateau::Base),
+            _ => None,
+        }
+    }
+}
+
+/// The file a mesh is written to, from the extension of `--out`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Wavefront OBJ, with the colors of the vertices or the coordinates of
+    /// a texture.
+    Obj,
+    /// Binary STL, which has no colors.
+    Stl,
+}
+
+impl Format {
+    pub fn from_path(path: &str) -> Option<Format> {
+        match Path::new(path).extension()?.to_str()? {
+            "obj" => Some(Format::Obj),
+            "stl" => Some(Format::Stl),
+            _ => None,
+        }
+    }
+}
+
+/// The heights of the pixels of an image, from 0 to 1, a row at a time
+/// from the top.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Heightfield {
+    pub width: u32,
+    pub height: u32,
+    pub heights: Vec<f64>,
+}
+
+impl Heightfield {
+    /// The heights of the pixels of `buffer`: the escape times of their
+    /// samples through `transfer`, from 0 at none to 1 at the iteration
+    /// limit, averaged. Samples in the set are raised to where `plateau`
+    /// puts them.
+    pub fn of(buffer: &EscapeBuffer, transfer: Transfer, plateau: Plateau) -> Heightfield {
+        let samples = buffer.samples.max(1);
+        let (width, height) = (buffer.width / samples, buffer.height / samples);
+        let span = buffer.max_iter as f64;
+        let sample_height = |x: u32, y: u32| {
+            let sample = buffer.values[(y * buffer.width + x) as usize];
+            match contour::level(sample) {
+                Some(value) => transfer.position(value, span).clamp(0.0, 1.0),
+                None if plateau == Plateau::Top => 1.0,
+                None => 0.0,
+            }
+        };
+        let heights = (0..width * height)
+            .map(|index| {
+                let (x, y) = (index % width * samples, index / width * samples);
+                let sum: f64 = (0..samples * samples)
+                    .map(|sample| sample_height(x + sample % samples, y + sample / samples))
+                    .sum();
+                sum / (samples * samples) as f64
+            })
+            .collect();
+        Heightfield {
+            width,
+            height,
+            heights,
+        }
+    }
+
+    /// Blurs the heights `passes` times with a 3x3 box, which takes the
+    /// spikes of the escape times near the boundary down the most. The
+    /// edges are blurred with the pixels they have.
+    pub fn smooth(&mut self, passes: u32) {
+        let (width, height) = (self.width as usize, self.height as usize);
+        for _ in 0..passes {
+            let heights = &self.heights;
+            let at = |x: usize, y: usize| {
+                let (mut sum, mut count) = (0.0, 0);
+                for other_y in y.saturating_sub(1)..(y + 2).min(height) {
+                    for other_x in x.saturating_sub(1)..(x + 2).min(width) {
+                        sum += heights[other_y * width + other_x];
+                        count += 1;
+                    }
+                }
+                sum / count as f64
+            };
+            self.heights =
+                (0..width * height).map(|index| at(index % width, index / width)).collect();
+        }
+    }
+}
+
+/// A triangle mesh, its triangles wound counterclockwise as seen from
+/// outside.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mesh {
+    pub vertices: Vec<[f64; 3]>,
+    /// Where each vertex is in the image it was raised from, from 0 to 1,
+    /// across from the left and up from the bottom.
+    pub uvs: Vec<[f64; 2]>,
+    /// Indices into `vertices`.
+    pub triangles: Vec<[u32; 3]>,
+}
+
+impl Mesh {
+    /// The surface of `field`, a vertex a pixel and two triangles between
+    /// every four, with the longer side of the image 1 long, the top row
+    /// at the back, and the heights scaled by `z_scale`.
+    ///
+    /// With a `base`, the surface is raised by it and closed into a solid
+    /// with walls down to 0 and a bottom, so every edge is shared by two
+    /// triangles, as slicers need.
+    pub fn of(field: &Heightfield, z_scale: f64, base: Option<f64>) -> Mesh {
+        let (width, height) = (field.width, field.height);
+        let unit = 1.0 / (width.max(height).max(2) - 1) as f64;
+        let lift = base.unwrap_or(0.0);
+        let uv = |x: u32, y: u32| {
+            [x as f64 / (width.max(2) - 1) as f64, 1.0 - y as f64 / (height.max(2) - 1) as f64]
+        };
+        let mut mesh = Mesh {
+            vertices: Vec::new(),
+            uvs: Vec::new(),
+            triangles: Vec::new(),
+        };
+        for y in 0..height {
+            for x in 0..width {
+                let z = lift + z_scale * field.heights[(y * width + x) as usize];
+                mesh.vertices.push([x as f64 * unit, (height - 1 - y) as f64 * unit, z]);
+                mesh.uvs.push(uv(x, y));
+            }
+        }
+        let index = |x: u32, y: u32| y * width + x;
+        for y in 0..height.saturating_sub(1) {
+            for x in 0..width.saturating_sub(1) {
+                let (a, b) = (index(x, y), index(x + 1, y));
+                let (c, d) = (index(x, y + 1), index(x + 1, y + 1));
+                mesh.triangles.push([a, c, b]);
+                mesh.triangles.push([b, c, d]);
+            }
+        }
+        if base.is_some() && width > 1 && height > 1 {
+            mesh.close(field);
+        }
+        mesh
+    }
+
+    /// Closes the surface of `field` into a solid: walls from its edges
+    /// down to 0, and a bottom fanned from its middle, which shares every
+    /// vertex of the walls.
+    fn close(&mut self, field: &Heightfield) {
+        let (width, height) = (field.width, field.height);
+        // The edge of the surface counterclockwise from above: down the
+        // left side, right along the front, up the right side and back
+        // along the top.
+        let mut edge: Vec<(u32, u32)> = Vec::new();
+        edge.extend((0..height - 1).map(|y| (0, y)));
+        edge.extend((0..width - 1).map(|x| (x, height - 1)));
+        edge.extend((1..height).rev().map(|y| (width - 1, y)));
+        edge.extend((1..width).rev().map(|x| (x, 0)));
+        let first = self.vertices.len() as u32;
+        for &(x, y) in &edge {
+            let [vx, vy, _] = self.vertices[(y * width + x) as usize];
+            self.vertices.push([vx, vy, 0.0]);
+            self.uvs.push(self.uvs[(y * width + x) as usize]);
+        }
+        let middle = first + edge.len() as u32;
+        let [far_x, _, _] = self.vertices[(width - 1) as usize];
+        let [_, far_y, _] = self.vertices[0];
+        self.vertices.push([far_x / 2.0, far_y / 2.0, 0.0]);
+        self.uvs.push([0.5, 0.5]);
+        for (k, &(x, y)) in edge.iter().enumerate() {
+            let (next_x, next_y) = edge[(k + 1) % edge.len()];
+            let (top, next_top) = (y * width + x, next_y * width + next_x);
+            let (bottom, next_bottom) = (first + k as u32, first + ((k + 1) % edge.len()) as u32);
+            self.triangles.push([bottom, next_bottom, next_top]);
+            self.triangles.push([bottom, next_top, top]);
+            self.triangles.push([middle, next_bottom, bottom]);
+        }
+    }
+
+    /// The mesh as Wavefront OBJ. With `colors`, one for every vertex with
+    /// channels from 0 to 1, they follow its coordinates. With `material`,
+    /// the name of a material library and of the material in it, the
+    /// vertices have texture coordinates from `uvs`.
+    pub fn to_obj(&self, colors: Option<&[[f64; 3]]>, material: Option<(&str, &str)>) -> String {
+        let mut obj = format!(
+            "# rustlebrot {} heightfield, {} vertices, {} triangles\n",
+            env!("CARGO_PKG_VERSION"),
+            self.vertices.len(),
+            self.triangles.len()
+        );
+        if let Some((library, name)) = material {
+            writeln!(obj, "mtllib {}\nusemtl {}", library, name).expect("strings take writes");
+        }
+        for (index, [x, y, z]) in self.vertices.iter().enumerate() {
+            match colors.map(|colors| colors[index]) {
+                Some([r, g, b]) => writeln!(obj, "v {} {} {} {:.4} {:.4} {:.4}", x, y, z, r, g, b),
+                None => writeln!(obj, "v {} {} {}", x, y, z),
+            }
+            .expect("strings take writes");
+        }
+        if material.is_some() {
+            for [u, v] in &self.uvs {
+                writeln!(obj, "vt {} {}", u, v).expect("strings take writes");
+            }
+        }
+        // OBJ counts vertices from 1.
+        for triangle in &self.triangles {
+            let [a, b, c] = triangle.map(|index| index + 1);
+            match material {
+                Some(_) => writeln!(obj, "f {a}/{a} {b}/{b} {c}/{c}"),
+                None => writeln!(obj, "f {} {} {}", a, b, c),
+            }
+            .expect("strings take writes");
+        }
+        obj
+    }
+
+    /// The mesh as binary STL: an 80 byte header, the number of triangles,
+    /// and each triangle as its normal and corners in little-endian 32-bit
+    /// floats, and two unused bytes.
+    pub fn to_stl(&self) -> Vec<u8> {
+        let mut stl = Vec::with_capacity(84 + 50 * self.triangles.len());
+        let mut header = format!("rustlebrot {} heightfield", env!("CARGO_PKG_VERSION"))
+            .into_bytes();
+        header.resize(80, b' ');
+        stl.extend(header);
+        stl.extend((self.triangles.len() as u32).to_le_bytes());
+        for triangle in &self.triangles {
+            let [a, b, c] = triangle.map(|index| self.vertices[index as usize]);
+            let normal = normal(a, b, c);
+            for point in [normal, a, b, c] {
+                for coordinate in point {
+                    stl.extend((coordinate as f32).to_le_bytes());
+                }
+            }
+            stl.extend([0, 0]);
+        }
+        stl
+    }
+}
+
+/// The unit normal of the triangle `a`, `b`, `c`, on the side it's wound
+/// counterclockwise from, or 0 for a triangle with no area.
+fn normal(a: [f64; 3], b: [f64; 3], c: [f64; 3]) -> [f64; 3] {
+    let (u, v) = ([0, 1, 2].map(|i| b[i] - a[i]), [0, 1, 2].map(|i| c[i] - a[i]));
+    let cross = [u[1] * v[2] - u[2] * v[1], u[2] * v[0] - u[0] * v[2], u[0] * v[1] - u[1] * v[0]];
+    let length = cross.iter().map(|c| c * c).sum::<f64>().sqrt();
+    match length > 0.0 {
+        true => cross.map(|c| c / length),
+        false => [0.0; 3],
+    }
+}
@@ -0,0 +1,30 @@
+/// The rendering pipeline, as chosen with `--mode`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// Color every pixel by iterating its own point.
+    Escape,
+    /// Plot the density of escaping orbits from randomly sampled points.
+    Buddhabrot,
+    /// A Buddhabrot per color channel, each plotting a different band of
+    /// escape times.
+    Nebulabrot,
+}
+
+impl Mode {
+    pub fn from_name(name: &str) -> Option<Mode> {
+        match name {
+            "escape" => Some(Mode::Escape),
+            "buddhabrot" => Some(Mode::Buddhabrot),
+            "nebulabrot" => Some(Mode::Nebulabrot),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Mode::Escape => "escape",
+            Mode::Buddhabrot => "buddhabrot",
+            Mode::Nebulabrot => "nebulabrot",
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use crate::error::RustlebrotError;
+use crate::export::{self, ImageFormat};
+use crate::grid::{self, Grid, BACKGROUND};
+use crate::hud::{self, CELL};
+use image::{DynamicImage, GenericImage, Rgb, RgbImage};
+use rayon::prelude::*;
+use std::collections::BTreeMap;
+use std::fs;
+
+/// The most thumbnails a montage shows unless `--every` says otherwise.
+pub const TILES: u32 = 64;
+
+/// Lines of the label under every thumbnail: the frame and its
+/// magnification.
+const LABEL_LINES: u32 = 2;
+
+/// A frame of a run that can go in a montage.
+pub struct Frame {
+    pub frame: u32,
+    /// Where the frame is saved, or would be if it is missing.
+    pub path: String,
+    /// The magnification of the frame, where the run recorded it.
+    pub magnification: Option<f64>,
+}
+
+/// The montage of a run, and the number of the frames it shows that
+/// couldn't be read.
+pub struct Montage {
+    pub image: RgbImage,
+    pub shown: usize,
+    pub missing: usize,
+}
+
+/// The frames saved in `dir`, in order, for a directory without a
+/// manifest. The frames are the images whose names are the same but for a
+/// number at the end, the most of any such names, so other images there
+/// aren't taken for frames; of the frames of one number, the first by name
+/// is.
+pub fn frames_in(dir: &str) -> Result<Vec<Frame>, RustlebrotError> {
+    let entries = fs::read_dir(dir).map_err(|e| RustlebrotError::read(dir, e))?;
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .collect();
+    names.sort();
+    // The frames by the name they'd have without their number.
+    let mut runs: BTreeMap<(String, String), BTreeMap<u32, String>> = BTreeMap::new();
+    for name in names {
+        let Some((stem, ext)) = name.rsplit_once('.') else {
+            continue;
+        };
+        if ImageFormat::from_extension(ext).is_none() {
+            continue;
+        }
+        let prefix = stem.trim_end_matches(|c: char| c.is_ascii_digit());
+        if let Ok(frame) = stem[prefix.len()..].parse() {
+            let run = runs.entry((prefix.to_string(), ext.to_string())).or_default();
+            run.entry(frame).or_insert_with(|| format!("{}/{}", dir, name));
+        }
+    }
+    let frames = runs.into_values().rev().max_by_key(BTreeMap::len).unwrap_or_default();
+    let frames = frames.into_iter().map(|(frame, path)| Frame {
+        frame,
+        path,
+        magnification: None,
+    });
+    Ok(frames.collect())
+}
+
+/// The frame numbers a montage of `frames` shows: every `every`th from the
+/// first to the last, or as many as keep it to `TILES` thumbnails, each
+/// with its frame where there is one of that number.
+pub fn sample(frames: &[Frame], every: Option<u32>) -> Vec<(u32, Option<&Frame>)> {
+    let (Some(first), Some(last)) = (frames.first(), frames.last()) else {
+        return Vec::new();
+    };
+    let span = last.frame - first.frame + 1;
+    let every = every.unwrap_or_else(|| span.div_ceil(TILES)).max(1);
+    let by_number: BTreeMap<u32, &Frame> =
+        frames.iter().map(|frame| (frame.frame, frame)).collect();
+    (first.frame..=last.frame)
+        .step_by(every as usize)
+        .map(|number| (number, by_number.get(&number).copied()))
+        .collect()
+}
+
+/// Lays out the thumbnails of the frames `shown`, `columns` to a row, each
+/// at most `thumbnail` pixels along its longer side and labeled with its
+/// frame and magnification under it. The thumbnails shrink as far as it
+/// takes for the montage to be at most `max_size` pixels across and down.
+///
+/// Frames that aren't there or can't be read leave a gap labeled as
+/// missing, rather than failing the montage.
+pub fn compose(
+    shown: &[(u32, Option<&Frame>)],
+    columns: u32,
+    thumbnail: u32,
+    max_size: u32,
+) -> Result<Montage, RustlebrotError> {
+    // The thumbnails take the shape of the first frame that can be read.
+    let size = shown.iter().find_map(|(_, frame)| image::image_dimensions(&(*frame)?.path).ok());
+    let Some((width, height)) = size else {
+        return Err(RustlebrotError::Argument(
+            "none of the frames of the montage can be read".to_string(),
+        ));
+    };
+    let tile = grid::fit(width, height, thumbnail, thumbnail);
+    let columns = columns.min(shown.len() as u32);
+    let grid = Grid::new(shown.len(), columns, tile, LABEL_LINES * CELL.1, 0)
+        .shrunk_to((max_size, max_size))
+        .ok_or_else(|| {
+            RustlebrotError::Argument(format!(
+                "{} thumbnails don't fit in {} pixels; show fewer with --every or allow a larger \
+                 montage with --max-size",
+                shown.len(),
+                max_size
+            ))
+        })?;
+    // Frames are read and shrunk one at a time on every thread, so only
+    // the thumbnails are held.
+    let thumbnails: Vec<Option<(RgbImage, Option<f64>)>> = shown
+        .par_iter()
+        .map(|(_, frame)| {
+            let frame = (*frame)?;
+            let img = image::open(&frame.path).ok()?.to_rgb8();
+            let magnification = frame.magnification.or_else(|| png_magnification(frame));
+            Some((grid::downscale(&img, grid.tile.0, grid.tile.1), magnification))
+        })
+        .collect();
+
+    let (width, height) = grid.size();
+    let mut montage = DynamicImage::ImageRgb8(RgbImage::from_pixel(width, height, Rgb(BACKGROUND)));
+    let mut missing = 0;
+    for (index, ((frame, _), thumbnail)) in shown.iter().zip(thumbnails).enumerate() {
+        let place = grid.place(index);
+        let mut lines = vec![format!("frame {}", frame)];
+        match thumbnail {
+            Some((thumbnail, magnification)) => {
+                let (left, top) = grid.tile_at(place.0, place.1);
+                montage
+                    .copy_from(&DynamicImage::ImageRgb8(thumbnail), left, top)
+                    .expect("thumbnails fit their place on the montage");
+                if let Some(magnification) = magnification {
+                    lines.push(format!("zoom {}", hud::scientific(magnification)));
+                }
+            }
+            None => {
+                lines.push("missing".to_string());
+                missing += 1;
+            }
+        }
+        hud::label(&mut montage, &grid, place, &lines);
+    }
+    Ok(Montage {
+        image: montage.to_rgb8(),
+        shown: shown.len(),
+        missing,
+    })
+}
+
+/// The magnification of a PNG `frame` of a plain zoom, from the zoom
+/// factor saved in it, for frames found without a manifest.
+fn png_magnification(frame: &Frame) -> Option<f64> {
+    let png = export::read_png(&frame.path).ok()?;
+    let (_, zoom_factor) = png.text.iter().find(|(keyword, _)| keyword == "Zoom Factor")?;
+    let zoom_factor: f64 = zoom_factor.parse().ok()?;
+    Some(zoom_factor.powf(frame.frame as f64))
+}
@@ -0,0 +1,217 @@
+use crate::complex::Complex;
+use std::f64::consts::TAU;
+
+/// The polynomial of `--fractal newton` unless `--poly` says otherwise,
+/// z³ - 1, whose basins are the classic three-armed picture.
+pub const DEFAULT_POLYNOMIAL: [f64; 4] = [1.0, 0.0, 0.0, -1.0];
+
+/// The highest degree `--poly` takes. Every root gets a color of its own,
+/// and past this many they can hardly be told apart.
+pub const MAX_DEGREE: usize = 16;
+
+/// A Newton step shorter than this ends the iteration, which has converged
+/// to a root by then.
+const EPSILON: f64 = 1e-6;
+
+/// How far from the nearest root a converged point can be and still count
+/// as in its basin. Newton's method only closes in on a repeated root a
+/// factor of its multiplicity at a time, so this leaves room for that.
+const MATCH_DISTANCE: f64 = 1e-3;
+
+/// Iterations of Durand-Kerner the roots are searched with.
+const ROOT_ITERATIONS: u32 = 1000;
+
+/// Roots closer together than this are taken for one repeated root.
+const ROOT_MERGE: f64 = 1e-5;
+
+/// A polynomial with real coefficients and its roots, for rendering the
+/// basins of Newton's method with `--fractal newton`.
+///
+/// Every point iterates `z - p(z) / p'(z)` until the step is shorter than
+/// `EPSILON`, and is colored by the root it reached and how fast. This
+/// ends on convergence rather than escape, so it has a kernel of its own
+/// instead of implementing `Fractal`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Newton {
+    /// Coefficients from the highest degree down.
+    coefficients: Vec<f64>,
+    derivative: Vec<f64>,
+    /// The distinct roots, by their angle counterclockwise from the positive
+    /// real axis and then by magnitude, which is the order they get the
+    /// colors of the gradient in.
+    roots: Vec<(f64, f64)>,
+}
+
+impl Default for Newton {
+    fn default() -> Self {
+        Newton::new(&DEFAULT_POLYNOMIAL).expect("the default polynomial is valid")
+    }
+}
+
+impl Newton {
+    /// The polynomial with `coefficients`, from the highest degree down,
+    /// with its roots found numerically.
+    pub fn new(coefficients: &[f64]) -> Result<Newton, String> {
+        if coefficients.iter().any(|c| !c.is_finite()) {
+            return Err("the coefficients of the polynomial should be finite".to_string());
+        }
+        if coefficients.first().is_some_and(|&leading| leading == 0.0) {
+            return Err("the leading coefficient of the polynomial should not be 0".to_string());
+        }
+        let degree = coefficients.len().saturating_sub(1);
+        if !(2..=MAX_DEGREE).contains(&degree) {
+            return Err(format!(
+                "the polynomial should be of degree 2 to {}, got {}",
+                MAX_DEGREE, degree
+            ));
+        }
+        let derivative = coefficients[..degree]
+            .iter()
+            .enumerate()
+            .map(|(i, c)| c * (degree - i) as f64)
+            .collect();
+        let mut newton = Newton {
+            coefficients: coefficients.to_vec(),
+            derivative,
+            roots: Vec::new(),
+        };
+        newton.roots = newton.find_roots();
+        Ok(newton)
+    }
+
+    /// Parses the coefficients given with `--poly`, such as `1,0,0,-1` for
+    /// z³ - 1.
+    pub fn from_spec(spec: &str) -> Result<Newton, String> {
+        let coefficients = spec
+            .split(',')
+            .map(|c| c.trim().parse())
+            .collect::<Result<Vec<f64>, _>>()
+            .map_err(|_| format!("poly takes coefficients like 1,0,0,-1, got '{}'", spec))?;
+        Newton::new(&coefficients)
+    }
+
+    pub fn coefficients(&self) -> &[f64] {
+        &self.coefficients
+    }
+
+    pub fn roots(&self) -> &[(f64, f64)] {
+        &self.roots
+    }
+
+    /// The root Newton's method takes `z` to, by its index in `roots`, and
+    /// the smooth number of iterations it took, or `None` if it doesn't
+    /// converge within `max_iter` iterations.
+    ///
+    /// The iterations are smoothed like escape times: the step shrinks
+    /// quadratically near a simple root, so the fraction of an iteration
+    /// is how far the last step undershot `EPSILON`, in doublings of its
+    /// logarithm.
+    pub fn basin(&self, z: (f64, f64), max_iter: u32) -> Option<(usize, f64)> {
+        let mut z = Complex::from(z);
+        for i in 1..=max_iter {
+            let derivative = evaluate(&self.derivative, z);
+            if derivative.norm_sqr() == 0.0 {
+                return None;
+            }
+            let step = evaluate(&self.coefficients, z) / derivative;
+            z = z - step;
+            if !(z.re.is_finite() && z.im.is_finite()) {
+                return None;
+            }
+            let size = step.norm_sqr();
+            if size < EPSILON * EPSILON {
+                let (root, distance) = self.nearest(z);
+                if distance > MATCH_DISTANCE * MATCH_DISTANCE {
+                    return None;
+                }
+                let fraction = (0.5 * size.ln() / EPSILON.ln()).log2().clamp(0.0, 1.0);
+                return Some((root, i as f64 - fraction));
+            }
+        }
+        None
+    }
+
+    /// The index of the root nearest to `z`, and its squared distance.
+    #[inline]
+    fn nearest(&self, z: Complex<f64>) -> (usize, f64) {
+        self.roots
+            .iter()
+            .map(|&root| (z - root.into()).norm_sqr())
+            .enumerate()
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .expect("polynomials of degree 2 and up have roots")
+    }
+
+    /// Finds the roots with the Durand-Kerner method, which closes in on
+    /// all of them at once, then polishes each with Newton's method.
+    fn find_roots(&self) -> Vec<(f64, f64)> {
+        let leading = self.coefficients[0];
+        let monic: Vec<f64> = self.coefficients.iter().map(|c| c / leading).collect();
+        let degree = monic.len() - 1;
+        // Powers of a number that is neither real nor a root of unity, so
+        // the starting points are spread out and never symmetric.
+        let mut roots: Vec<Complex<f64>> = Vec::with_capacity(degree);
+        let seed = Complex::new(0.4, 0.9);
+        let mut power = Complex::ONE;
+        for _ in 0..degree {
+            roots.push(power);
+            power = power * seed;
+        }
+        for _ in 0..ROOT_ITERATIONS {
+            let mut moved: f64 = 0.0;
+            for k in 0..degree {
+                let mut product = Complex::ONE;
+                for (j, &other) in roots.iter().enumerate() {
+                    if j != k {
+                        product = product * (roots[k] - other);
+                    }
+                }
+                let step = evaluate(&monic, roots[k]) / product;
+                if step.re.is_finite() && step.im.is_finite() {
+                    roots[k] = roots[k] - step;
+                    moved = moved.max(step.norm_sqr());
+                }
+            }
+            if moved < 1e-30 {
+                break;
+            }
+        }
+        for root in &mut roots {
+            for _ in 0..4 {
+                let derivative = evaluate(&self.derivative, *root);
+                if derivative.norm_sqr() == 0.0 {
+                    break;
+                }
+                let polished = *root - evaluate(&self.coefficients, *root) / derivative;
+                if !(polished.re.is_finite() && polished.im.is_finite()) {
+                    break;
+                }
+                *root = polished;
+            }
+            // The roots of real polynomials are real or come in conjugate
+            // pairs, so what is left of an imaginary part that small is
+            // rounding.
+            if root.im.abs() <= 1e-12 * (1.0 + root.re.abs()) {
+                root.im = 0.0;
+            }
+        }
+        let mut distinct: Vec<Complex<f64>> = Vec::with_capacity(degree);
+        for root in roots {
+            if distinct.iter().all(|&other| (root - other).norm_sqr() > ROOT_MERGE * ROOT_MERGE) {
+                distinct.push(root);
+            }
+        }
+        let angle = |root: Complex<f64>| root.im.atan2(root.re).rem_euclid(TAU);
+        distinct.sort_by(|&a, &b| {
+            angle(a).total_cmp(&angle(b)).then(a.norm_sqr().total_cmp(&b.norm_sqr()))
+        });
+        distinct.into_iter().map(Complex::into).collect()
+    }
+}
+
+/// The value of the polynomial with `coefficients`, from the highest degree
+/// down, at `z`, by Horner's method.
+#[inline]
+fn evaluate(coefficients: &[f64], z: Complex<f64>) -> Complex<f64> {
+    coefficients.iter().fold(Complex::ZERO, |value, &c| value * z + Complex::new(c, 0.0))
+}
@@ -0,0 +1,225 @@
+use crate::bigfloat::{self, Big};
+
+/// The longest period `find_period` looks for.
+pub const MAX_PERIOD: u32 = 100_000;
+
+/// Newton steps taken at one precision before giving up on it settling.
+const MAX_STEPS: usize = 64;
+
+/// How far Newton's method may wander from where it started, in radii of
+/// the region it was asked to search, before it's taken to be diverging.
+const MAX_WANDER: f64 = 16.0;
+
+/// Bits below the precision that a Newton step can be and still count as
+/// having settled, for the rounding in the `period` iterations it takes.
+const SETTLE_BITS: f64 = 8.0;
+
+/// The center of a minibrot of the Mandelbrot set, the `c` whose orbit
+/// comes back to 0 after `period` iterations.
+pub struct Nucleus {
+    pub center: (Big, Big),
+    pub period: u32,
+    /// The size of the minibrot relative to the whole set, which is 1 on
+    /// this scale, see `size`.
+    pub size: f64,
+    /// The bits of `center` that are right, enough to zoom far past the
+    /// minibrot.
+    pub bits: usize,
+}
+
+impl Nucleus {
+    /// The magnification at which the minibrot fills the frame as the whole
+    /// set fills the first frame of a zoom.
+    pub fn magnification(&self) -> f64 {
+        1.0 / self.size
+    }
+
+    /// An iteration limit that resolves the minibrot, whose points take
+    /// many times its period to escape.
+    pub fn max_iter(&self) -> u32 {
+        self.period.saturating_mul(256).max(1000)
+    }
+}
+
+/// The lowest period of the nuclei in the disk of `radius` around `center`,
+/// found by following the disk's orbit as a ball until it covers 0, or
+/// `None` if it escapes or `max_period` is reached first.
+///
+/// The ball is an upper bound on where the orbits of every point of the disk
+/// are, so the period found is that of the first nucleus it can contain.
+pub fn find_period(center: &(Big, Big), radius: f64, max_period: u32) -> Option<u32> {
+    let mut z = (Big::ZERO, Big::ZERO);
+    let mut r = 0.0;
+    for period in 1..=max_period {
+        // |(z + e)^2 - z^2| is at most 2|z||e| + |e|^2, and c adds its own.
+        r = r * (2.0 * abs(&z) + r) + radius;
+        z = add(&sqr(&z), center);
+        let distance = abs(&z);
+        if distance < r {
+            return Some(period);
+        }
+        if distance - r > 2.0 || !r.is_finite() {
+            return None;
+        }
+    }
+    None
+}
+
+/// Finds the nucleus of `period` nearest to `near`, by Newton's method on
+/// the `period`th iterate of 0, from the region of `radius` around it.
+///
+/// The steps are taken in the precision of `near` and then in as much as
+/// the size of the minibrot takes, until that is enough. Fails if the
+/// steps leave the region far behind or don't settle.
+pub fn find_nucleus(near: &(Big, Big), radius: f64, period: u32) -> Result<Nucleus, String> {
+    let approx = (near.0.to_f64().value(), near.1.to_f64().value());
+    let mut bits = bigfloat::required_bits(approx, radius).max(near.0.precision());
+    let mut c = near.clone();
+    loop {
+        c = (c.0.with_precision(bits).value(), c.1.with_precision(bits).value());
+        c = newton(&c, near, radius, period, bits)?;
+        // A nucleus of a divisor of the period is a root as well.
+        let period = lowest_period(&c, period, bits);
+        let size = abs_f64(size(&c, period));
+        if !(size > 0.0 && size.is_finite()) {
+            return Err(format!(
+                "the period {} nucleus found has no size; it may not be a minibrot's",
+                period
+            ));
+        }
+        let needed = bigfloat::required_bits(approx, size);
+        if needed <= bits {
+            return Ok(Nucleus {
+                center: c,
+                period,
+                size,
+                bits,
+            });
+        }
+        bits = needed;
+    }
+}
+
+/// Newton's method for the root of the `period`th iterate of 0 from `c`,
+/// in `bits` of precision.
+fn newton(
+    c: &(Big, Big),
+    near: &(Big, Big),
+    radius: f64,
+    period: u32,
+    bits: usize,
+) -> Result<(Big, Big), String> {
+    let scale = abs(c).max(1.0);
+    let settled = scale * (SETTLE_BITS - bits as f64).exp2();
+    let mut c = c.clone();
+    for _ in 0..MAX_STEPS {
+        let mut z = (Big::ZERO, Big::ZERO);
+        let mut dz = (Big::ZERO, Big::ZERO);
+        for _ in 0..period {
+            let twice = mul(&z, &dz);
+            dz = ((twice.0 << 1) + Big::ONE, twice.1 << 1);
+            z = add(&sqr(&z), &c);
+            if abs(&z) > 1e150 {
+                return Err(format!(
+                    "Newton's method was thrown out of the set looking for a nucleus of period \
+                     {}; try another --period or a smaller --radius",
+                    period
+                ));
+            }
+        }
+        if abs(&dz) == 0.0 {
+            return Err(format!(
+                "Newton's method reached a point where the period {} iterate is flat; try \
+                 another --near",
+                period
+            ));
+        }
+        let step = div(&z, &dz);
+        c = (&c.0 - &step.0, &c.1 - &step.1);
+        let wandered = abs(&(&c.0 - &near.0, &c.1 - &near.1));
+        if wandered.is_nan() || wandered > MAX_WANDER * radius {
+            return Err(format!(
+                "Newton's method wandered {:.3e} away looking for a nucleus of period {}, far \
+                 outside --radius {:.3e}; try another --period or a smaller --radius",
+                wandered, period, radius
+            ));
+        }
+        if abs(&step) <= settled {
+            return Ok(c);
+        }
+    }
+    Err(format!(
+        "Newton's method didn't settle on a nucleus of period {} in {} steps; try another \
+         --period or a smaller --radius",
+        period, MAX_STEPS
+    ))
+}
+
+/// The lowest period of the nucleus `c`, found as a root of the iterate of
+/// `period` in `bits` of precision, which is a divisor of it.
+fn lowest_period(c: &(Big, Big), period: u32, bits: usize) -> u32 {
+    let zero = (-(bits as f64) / 2.0).exp2();
+    let mut z = (Big::ZERO, Big::ZERO);
+    for lower in 1..period {
+        z = add(&sqr(&z), c);
+        if period.is_multiple_of(lower) && abs(&z) < zero {
+            return lower;
+        }
+    }
+    period
+}
+
+/// The size of the minibrot whose nucleus of `period` is `c`, a complex
+/// number whose modulus is the size relative to the whole set and whose
+/// argument is how far the minibrot is turned.
+///
+/// It's the estimate of the minibrot as the image of the whole set under
+/// the linear part of the map the `period` iterations make near `c`.
+fn size(c: &(Big, Big), period: u32) -> (f64, f64) {
+    let mut z = (Big::ZERO, Big::ZERO);
+    let mut l = (1.0, 0.0);
+    let mut b = (1.0, 0.0);
+    for _ in 1..period {
+        z = add(&sqr(&z), c);
+        let zf = (z.0.to_f64().value(), z.1.to_f64().value());
+        l = mul_f64((2.0 * zf.0, 2.0 * zf.1), l);
+        b = (b.0 + inv_f64(l).0, b.1 + inv_f64(l).1);
+    }
+    inv_f64(mul_f64(b, mul_f64(l, l)))
+}
+
+fn add(a: &(Big, Big), b: &(Big, Big)) -> (Big, Big) {
+    (&a.0 + &b.0, &a.1 + &b.1)
+}
+
+fn sqr(a: &(Big, Big)) -> (Big, Big) {
+    (a.0.sqr() - a.1.sqr(), (&a.0 * &a.1) << 1)
+}
+
+fn mul(a: &(Big, Big), b: &(Big, Big)) -> (Big, Big) {
+    (&a.0 * &b.0 - &a.1 * &b.1, &a.0 * &b.1 + &a.1 * &b.0)
+}
+
+fn div(a: &(Big, Big), b: &(Big, Big)) -> (Big, Big) {
+    let norm = b.0.sqr() + b.1.sqr();
+    let numerator = mul(a, &(b.0.clone(), -b.1.clone()));
+    (numerator.0 / &norm, numerator.1 / &norm)
+}
+
+/// The modulus of `a`, as far as f64 tells it.
+fn abs(a: &(Big, Big)) -> f64 {
+    a.0.to_f64().value().hypot(a.1.to_f64().value())
+}
+
+fn abs_f64(a: (f64, f64)) -> f64 {
+    a.0.hypot(a.1)
+}
+
+fn mul_f64(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    (a.0 * b.0 - a.1 * b.1, a.0 * b.1 + a.1 * b.0)
+}
+
+fn inv_f64(a: (f64, f64)) -> (f64, f64) {
+    let norm = a.0 * a.0 + a.1 * a.1;
+    (a.0 / norm, -a.1 / norm)
+}
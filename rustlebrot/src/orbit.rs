@@ -0,0 +1,134 @@
+use crate::error::RustlebrotError;
+use rustlebrot::trace::{Trace, Verdict};
+use serde::Serialize;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+/// The first line of an orbit written as CSV.
+const COLUMNS: &str = "iteration,re,im,abs";
+
+/// How the orbit of the `orbit` subcommand is written, from the extension
+/// of `--output`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Json,
+}
+
+impl Format {
+    pub fn from_path(path: &str) -> Option<Format> {
+        match Path::new(path).extension()?.to_str()? {
+            "csv" => Some(Format::Csv),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+}
+
+/// What the orbit was traced with, which the JSON records along with it.
+pub struct Settings<'a> {
+    pub fractal: &'a str,
+    pub precision: &'a str,
+    /// The point and the reference of perturbation as given, with every
+    /// digit.
+    pub point: &'a (String, String),
+    pub reference: Option<&'a (String, String)>,
+    pub max_iter: u32,
+    pub bailout: f64,
+}
+
+/// An orbit as it is written to JSON.
+#[derive(Serialize)]
+struct OrbitRecord<'a> {
+    fractal: &'a str,
+    precision: &'a str,
+    point: &'a (String, String),
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reference: Option<&'a (String, String)>,
+    max_iter: u32,
+    bailout: f64,
+    verdict: VerdictRecord,
+    points: Vec<PointRecord>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum VerdictRecord {
+    Escaped { iteration: u32, smooth: f64 },
+    Bounded { period: Option<u32> },
+}
+
+#[derive(Serialize)]
+struct PointRecord {
+    iteration: usize,
+    re: f64,
+    im: f64,
+    abs: f64,
+}
+
+/// The verdict on the orbit as a line for people to read.
+pub fn verdict(trace: &Trace) -> String {
+    match trace.verdict {
+        Verdict::Escaped { iteration, smooth } => {
+            format!("Escaped at iteration {} with smooth value {}", iteration, smooth)
+        }
+        Verdict::Bounded {
+            period: Some(period),
+        } => format!("Bounded with period {}", period),
+        Verdict::Bounded { period: None } => "Bounded with no period detected".to_string(),
+    }
+}
+
+/// The points of the orbit as CSV, one row a step, with every float
+/// written as the shortest decimal that reads back to it, so orbits of
+/// different precisions can be diffed line by line.
+pub fn to_csv(trace: &Trace) -> String {
+    let mut csv = format!("{}\n", COLUMNS);
+    for (iteration, z) in trace.points.iter().enumerate() {
+        let abs = z.0.hypot(z.1);
+        writeln!(csv, "{},{},{},{}", iteration, z.0, z.1, abs).expect("strings take writes");
+    }
+    csv
+}
+
+pub fn to_json(trace: &Trace, settings: &Settings) -> String {
+    let record = OrbitRecord {
+        fractal: settings.fractal,
+        precision: settings.precision,
+        point: settings.point,
+        reference: settings.reference,
+        max_iter: settings.max_iter,
+        bailout: settings.bailout,
+        verdict: match trace.verdict {
+            Verdict::Escaped { iteration, smooth } => VerdictRecord::Escaped { iteration, smooth },
+            Verdict::Bounded { period } => VerdictRecord::Bounded { period },
+        },
+        points: trace
+            .points
+            .iter()
+            .enumerate()
+            .map(|(iteration, z)| PointRecord {
+                iteration,
+                re: z.0,
+                im: z.1,
+                abs: z.0.hypot(z.1),
+            })
+            .collect(),
+    };
+    serde_json::to_string_pretty(&record).expect("orbits serialize to JSON") + "\n"
+}
+
+/// Writes the orbit to `path` in `format`.
+pub fn write(
+    path: &str,
+    format: Format,
+    trace: &Trace,
+    settings: &Settings,
+) -> Result<(), RustlebrotError> {
+    let text = match format {
+        Format::Csv => to_csv(trace),
+        Format::Json => to_json(trace, settings),
+    };
+    fs::write(path, text).map_err(|e| RustlebrotError::write(path, e))
+}
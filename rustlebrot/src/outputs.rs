@@ -0,0 +1,182 @@
+use crate::render::{from_linear, to_linear};
+use image::imageops::{self, FilterType};
+use image::{ColorType, DynamicImage, Rgba32FImage};
+use rayon::prelude::*;
+use std::cmp::Reverse;
+
+/// One of the sizes of `--outputs` the frames of a run are written at, as
+/// `1920x1080`, or `512x512@center-crop` for the middle of the frame.
+///
+/// The frames are rendered once, at the largest of the outputs, and the
+/// others are made from them by cropping and downscaling, so a run costs
+/// what its largest output does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Output {
+    pub width: u32,
+    pub height: u32,
+    /// Whether the output is the largest region of the frame of its shape,
+    /// about the center, rather than all of it.
+    pub crop: bool,
+}
+
+impl Output {
+    pub fn from_spec(spec: &str) -> Result<Output, String> {
+        let (size, framing) = match spec.split_once('@') {
+            Some((size, framing)) => (size, Some(framing)),
+            None => (spec, None),
+        };
+        let crop = match framing {
+            None => false,
+            Some("center-crop") => true,
+            Some(framing) => {
+                return Err(format!(
+                    "output '{}' should be WxH or WxH@center-crop, not @{}",
+                    spec, framing
+                ))
+            }
+        };
+        let (width, height) = size
+            .split_once('x')
+            .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+            .filter(|&(width, height)| width > 0 && height > 0)
+            .ok_or_else(|| format!("output '{}' should be WxH, like 1920x1080", spec))?;
+        Ok(Output {
+            width,
+            height,
+            crop,
+        })
+    }
+
+    /// The outputs of a comma separated list, the largest first, which the
+    /// frames are rendered at. Fails if any of the others can't be made from
+    /// it without stretching or upscaling.
+    pub fn list_from_spec(spec: &str) -> Result<Vec<Output>, String> {
+        let mut outputs = spec
+            .split(',')
+            .map(|spec| Output::from_spec(spec.trim()))
+            .collect::<Result<Vec<_>, _>>()?;
+        // Stable, so of outputs of the same area the first given is
+        // rendered.
+        outputs.sort_by_key(|output| Reverse(output.width as u64 * output.height as u64));
+        let largest = outputs[0];
+        for (index, output) in outputs.iter().enumerate() {
+            if outputs[..index].iter().any(|other| other.name() == output.name()) {
+                return Err(format!("output {} is given twice", output.name()));
+            }
+            output.check(largest.width, largest.height)?;
+        }
+        Ok(outputs)
+    }
+
+    /// The name of the directory of the output's frames.
+    pub fn name(&self) -> String {
+        match self.crop {
+            true => format!("{}x{}-center-crop", self.width, self.height),
+            false => format!("{}x{}", self.width, self.height),
+        }
+    }
+
+    /// The region of a frame of `width` by `height` pixels the output
+    /// shows, as its left and top edges, width and height.
+    pub fn region(&self, width: u32, height: u32) -> (u32, u32, u32, u32) {
+        if !self.crop {
+            return (0, 0, width, height);
+        }
+        let (wide, tall) = (width as u64 * self.height as u64, self.width as u64 * height as u64);
+        let (crop_width, crop_height) = match wide >= tall {
+            true => (((tall as f64 / self.height as f64).round() as u32).min(width), height),
+            false => (width, ((wide as f64 / self.width as f64).round() as u32).min(height)),
+        };
+        ((width - crop_width) / 2, (height - crop_height) / 2, crop_width, crop_height)
+    }
+
+    /// Checks the output can be made from frames of `width` by `height`:
+    /// that its region of them has its shape, to a pixel, and at least its
+    /// pixels.
+    pub fn check(&self, width: u32, height: u32) -> Result<(), String> {
+        let (_, _, region_width, region_height) = self.region(width, height);
+        let shaped = region_height as f64 * self.width as f64 / self.height as f64;
+        if (shaped - region_width as f64).abs() > 1.0 {
+            return Err(format!(
+                "output {} isn't the shape of the {}x{} frames it's made from; add \
+                 @center-crop to crop them to it",
+                self.name(),
+                width,
+                height
+            ));
+        }
+        if region_width < self.width || region_height < self.height {
+            return Err(format!(
+                "output {} would be upscaled from {}x{} pixels of the {}x{} frames; outputs are \
+                 only ever downscaled",
+                self.name(),
+                region_width,
+                region_height,
+                width,
+                height
+            ));
+        }
+        Ok(())
+    }
+
+    /// The output of the frame `img`, cropped and downscaled by
+    /// `resample`.
+    pub fn of(&self, img: &DynamicImage) -> DynamicImage {
+        let (x, y, width, height) = self.region(img.width(), img.height());
+        match (x, y, width, height) == (0, 0, img.width(), img.height()) {
+            true => resample(img, self.width, self.height),
+            false => resample(&img.crop_imm(x, y, width, height), self.width, self.height),
+        }
+    }
+}
+
+/// `img` scaled to `width` by `height` with a Lanczos filter, in linear
+/// light, so fine detail averages to the brightness it has when seen from
+/// afar rather than darkening. Colors are weighted by their opacity, so
+/// transparent pixels don't bleed into their neighbors. The result has the
+/// channels and bit depth of `img`.
+pub fn resample(img: &DynamicImage, width: u32, height: u32) -> DynamicImage {
+    if (img.width(), img.height()) == (width, height) {
+        return img.clone();
+    }
+    let scaled = imageops::resize(&premultiplied(img), width, height, FilterType::Lanczos3);
+    encoded(scaled, img.color())
+}
+
+/// The pixels of `img` in linear light, their colors multiplied by their
+/// opacity, as images are filtered without darkening or bleeding.
+pub(crate) fn premultiplied(img: &DynamicImage) -> Rgba32FImage {
+    let mut linear = img.to_rgba32f();
+    linear.par_chunks_mut(4).for_each(|pixel| {
+        let alpha = pixel[3];
+        for channel in &mut pixel[..3] {
+            *channel = to_linear(*channel as f64) as f32 * alpha;
+        }
+    });
+    linear
+}
+
+/// The image of `linear`, pixels as `premultiplied` gives them, in sRGB
+/// with the channels and bit depth of `color`.
+pub(crate) fn encoded(mut linear: Rgba32FImage, color: ColorType) -> DynamicImage {
+    linear.par_chunks_mut(4).for_each(|pixel| {
+        // The lobes of Lanczos filters and sharpening overshoot at sharp
+        // edges.
+        let alpha = pixel[3].clamp(0.0, 1.0);
+        for channel in &mut pixel[..3] {
+            let color = match alpha > 0.0 {
+                true => *channel / alpha,
+                false => 0.0,
+            };
+            *channel = from_linear(color.clamp(0.0, 1.0) as f64) as f32;
+        }
+        pixel[3] = alpha;
+    });
+    let linear = DynamicImage::ImageRgba32F(linear);
+    match (color.bytes_per_pixel() > color.channel_count(), color.has_alpha()) {
+        (false, false) => DynamicImage::ImageRgb8(linear.to_rgb8()),
+        (false, true) => DynamicImage::ImageRgba8(linear.to_rgba8()),
+        (true, false) => DynamicImage::ImageRgb16(linear.to_rgb16()),
+        (true, true) => DynamicImage::ImageRgba16(linear.to_rgba16()),
+    }
+}
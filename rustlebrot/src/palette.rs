@@ -0,0 +1,443 @@
+use colorgrad::{BlendMode, Color, CustomGradient, Gradient};
+use image::RgbImage;
+
+/// The colorgrad preset gradients selectable with `--palette`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Palette {
+    Sinebow,
+    Turbo,
+    Viridis,
+    Magma,
+    Inferno,
+    Plasma,
+    Cividis,
+    Cubehelix,
+    Rainbow,
+    Spectral,
+    Warm,
+    Cool,
+}
+
+impl Palette {
+    /// Every palette, in the order `--list-palettes` prints them.
+    pub const ALL: [Palette; 12] = [
+        Palette::Sinebow,
+        Palette::Turbo,
+        Palette::Viridis,
+        Palette::Magma,
+        Palette::Inferno,
+        Palette::Plasma,
+        Palette::Cividis,
+        Palette::Cubehelix,
+        Palette::Rainbow,
+        Palette::Spectral,
+        Palette::Warm,
+        Palette::Cool,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Palette> {
+        Palette::ALL.into_iter().find(|palette| palette.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Palette::Sinebow => "sinebow",
+            Palette::Turbo => "turbo",
+            Palette::Viridis => "viridis",
+            Palette::Magma => "magma",
+            Palette::Inferno => "inferno",
+            Palette::Plasma => "plasma",
+            Palette::Cividis => "cividis",
+            Palette::Cubehelix => "cubehelix",
+            Palette::Rainbow => "rainbow",
+            Palette::Spectral => "spectral",
+            Palette::Warm => "warm",
+            Palette::Cool => "cool",
+        }
+    }
+
+    /// Builds the gradient. This is done once per run and the result shared
+    /// by every pixel, since some presets interpolate between a list of
+    /// colors that would otherwise be parsed again each time.
+    pub fn gradient(self) -> Gradient {
+        match self {
+            Palette::Sinebow => colorgrad::sinebow(),
+            Palette::Turbo => colorgrad::turbo(),
+            Palette::Viridis => colorgrad::viridis(),
+            Palette::Magma => colorgrad::magma(),
+            Palette::Inferno => colorgrad::inferno(),
+            Palette::Plasma => colorgrad::plasma(),
+            Palette::Cividis => colorgrad::cividis(),
+            Palette::Cubehelix => colorgrad::cubehelix_default(),
+            Palette::Rainbow => colorgrad::rainbow(),
+            Palette::Spectral => colorgrad::spectral(),
+            Palette::Warm => colorgrad::warm(),
+            Palette::Cool => colorgrad::cool(),
+        }
+    }
+}
+
+/// Entries of a `Colormap` lookup table, unless `--palette-resolution` says
+/// otherwise. Colors in between are linearly interpolated, which is far
+/// below the precision of 16-bit output. The entries are also close enough
+/// that interpolating their sRGB channels comes out the same as
+/// interpolating in linear light.
+pub const TABLE_SIZE: usize = 4096;
+
+/// The fewest and most entries `--palette-resolution` takes.
+pub const TABLE_SIZES: (usize, usize) = (16, 1 << 20);
+
+/// Adjustments applied to the palette as a whole, as set with `--invert`,
+/// `--hue-shift`, `--saturation` and `--gamma`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Adjust {
+    /// Turns every color into its negative.
+    pub invert: bool,
+    /// Rotates the hue, in degrees.
+    pub hue_shift: f64,
+    /// Multiplies the saturation.
+    pub saturation: f64,
+    /// Brightens the colors for values above 1 and darkens them below.
+    pub gamma: f64,
+}
+
+impl Default for Adjust {
+    fn default() -> Self {
+        Adjust {
+            invert: false,
+            hue_shift: 0.0,
+            saturation: 1.0,
+            gamma: 1.0,
+        }
+    }
+}
+
+impl Adjust {
+    /// Applies the adjustments to one color: hue and saturation first, then
+    /// gamma, then inversion.
+    pub fn apply(&self, color: Color) -> Color {
+        let mut color = color;
+        // Going through HSV isn't exact, so only do it when asked to.
+        if self.hue_shift != 0.0 || self.saturation != 1.0 {
+            let (h, s, v, a) = color.to_hsva();
+            color = Color::from_hsva(h + self.hue_shift, s * self.saturation, v, a);
+        }
+        if self.gamma != 1.0 {
+            let exponent = 1.0 / self.gamma;
+            color = Color::new(
+                color.r.powf(exponent),
+                color.g.powf(exponent),
+                color.b.powf(exponent),
+                color.a,
+            );
+        }
+        if self.invert {
+            color = Color::new(1.0 - color.r, 1.0 - color.g, 1.0 - color.b, color.a);
+        }
+        color
+    }
+}
+
+/// The light the colors between the stops of a gradient are mixed in, as
+/// chosen with `--legacy-gamma`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Blending {
+    /// Mixes linear intensities, as light mixes. The halfway point between
+    /// red and green is a bright yellow.
+    Linear,
+    /// Mixes the sRGB encoded channels, which darkens and muddies the
+    /// colors in between. This is how gradients were mixed before, kept
+    /// to render frames as they used to look.
+    Srgb,
+}
+
+/// A gradient sampled into a lookup table, with the adjustments applied to
+/// every entry once per run instead of to every pixel of every frame.
+pub struct Colormap {
+    table: Vec<Color>,
+}
+
+impl Colormap {
+    pub fn new(gradient: &Gradient, adjust: &Adjust) -> Self {
+        Colormap::with_entries(gradient, adjust, TABLE_SIZE)
+    }
+
+    /// The colormap of `gradient` sampled into `entries` steps, with one
+    /// more entry for the end.
+    pub fn with_entries(gradient: &Gradient, adjust: &Adjust, entries: usize) -> Self {
+        let table = (0..=entries)
+            .map(|i| adjust.apply(gradient.at(i as f64 / entries as f64)))
+            .collect();
+        Colormap { table }
+    }
+
+    /// The steps the gradient is sampled into.
+    pub fn entries(&self) -> usize {
+        self.table.len() - 1
+    }
+
+    /// The color at `t`, between 0 and 1.
+    #[inline]
+    pub fn at(&self, t: f64) -> Color {
+        let entries = self.entries();
+        let x = t.clamp(0.0, 1.0) * entries as f64;
+        let i = (x as usize).min(entries - 1);
+        let f = x - i as f64;
+        let (a, b) = (&self.table[i], &self.table[i + 1]);
+        Color::new(
+            a.r + (b.r - a.r) * f,
+            a.g + (b.g - a.g) * f,
+            a.b + (b.b - a.b) * f,
+            a.a + (b.a - a.a) * f,
+        )
+    }
+}
+
+/// How gradient positions wrap around the gradient, as set with
+/// `--palette-cycles`, `--palette-offset` and `--palette-reverse`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cycle {
+    /// How many times the gradient repeats over the range of positions.
+    pub cycles: f64,
+    /// Shifts every position along the gradient, in cycles.
+    pub offset: f64,
+    pub reverse: bool,
+    /// Whether every other cycle runs back down the gradient. Gradients
+    /// whose ends don't match would otherwise show a seam at every wrap.
+    pub mirror: bool,
+}
+
+impl Cycle {
+    /// Sets up cycling for `gradient`, mirroring it unless it already
+    /// wraps around seamlessly.
+    pub fn new(gradient: &Gradient, cycles: f64, offset: f64, reverse: bool) -> Self {
+        let (start, end) = (gradient.at(0.0).to_rgba8(), gradient.at(1.0).to_rgba8());
+        let seamless = start.iter().zip(end).all(|(a, b)| a.abs_diff(b) <= 2);
+        Cycle {
+            cycles,
+            offset,
+            reverse,
+            mirror: !seamless,
+        }
+    }
+
+    /// The cycling of frame number `frame` of a zoom whose phase advances
+    /// by `drift` cycles every frame.
+    ///
+    /// The phase only depends on the frame number, so a frame rendered on
+    /// its own matches the same frame rendered as part of the whole zoom.
+    pub fn at_frame(&self, drift: f64, frame: u32) -> Cycle {
+        Cycle {
+            offset: self.offset + drift * frame as f64,
+            ..*self
+        }
+    }
+
+    /// The cycling of frame `frame` of a loop of `frames` that goes round
+    /// the palette once, so frame `frames` is colored as frame 0 is and the
+    /// loop has no seam.
+    pub fn looped(&self, frame: u32, frames: u32) -> Cycle {
+        Cycle {
+            offset: self.offset + frame as f64 / frames as f64,
+            ..*self
+        }
+    }
+
+    /// Maps a gradient position, which runs from 0 to 1 over the range of
+    /// values the coloring spreads out, to the parameter the gradient is
+    /// evaluated at.
+    #[inline]
+    pub fn parameter(&self, position: f64) -> f64 {
+        let t = (self.cycles * position + self.offset).rem_euclid(1.0);
+        let t = if self.mirror { 1.0 - (2.0 * t - 1.0).abs() } else { t };
+        if self.reverse {
+            1.0 - t
+        } else {
+            t
+        }
+    }
+}
+
+/// A color stop of a user-defined gradient.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stop {
+    pub color: Color,
+    /// Where on the gradient the color is reached, from 0 to 1.
+    pub position: f64,
+}
+
+/// Parses the stops of `--gradient` or `--gradient-file`, like
+/// `#000428@0,#004e92@0.5,white@1`.
+///
+/// Stops are separated by commas or newlines, and each is a hex or CSS named
+/// color followed by `@` and its position. Blank lines are skipped. The stops
+/// are sorted by position, keeping the given order of stops at the same
+/// position so hard edges can be made by repeating a position.
+pub fn parse_stops(spec: &str) -> Result<Vec<Stop>, String> {
+    let mut stops = Vec::new();
+    let tokens = spec.split([',', '\n']).map(str::trim).filter(|token| !token.is_empty());
+    for (n, token) in tokens.enumerate() {
+        let invalid = |problem: String| format!("gradient stop {} '{}': {}", n + 1, token, problem);
+        let (color, position) = token
+            .rsplit_once('@')
+            .ok_or_else(|| invalid("expected color@position".to_string()))?;
+        let color = Color::from_html(color.trim())
+            .map_err(|_| invalid(format!("unknown color '{}'", color.trim())))?;
+        let position: f64 = position
+            .trim()
+            .parse()
+            .map_err(|_| invalid(format!("position '{}' should be a float", position.trim())))?;
+        if !(0.0..=1.0).contains(&position) {
+            return Err(invalid(format!("position {} should be between 0 and 1", position)));
+        }
+        stops.push(Stop { color, position });
+    }
+    if stops.len() < 2 {
+        return Err(format!("a gradient needs at least two stops, got {}", stops.len()));
+    }
+    stops.sort_by(|a, b| a.position.total_cmp(&b.position));
+    Ok(stops)
+}
+
+/// Builds the gradient through `stops`, which `parse_stops` has validated
+/// and sorted, mixing neighboring stops by `blending`.
+pub fn custom_gradient(stops: &[Stop], blending: Blending) -> Gradient {
+    let colors: Vec<Color> = stops.iter().map(|stop| stop.color.clone()).collect();
+    let positions: Vec<f64> = stops.iter().map(|stop| stop.position).collect();
+    CustomGradient::new()
+        .colors(&colors)
+        .domain(&positions)
+        .mode(match blending {
+            Blending::Linear => BlendMode::LinearRgb,
+            Blending::Srgb => BlendMode::Rgb,
+        })
+        .build()
+        .expect("stops are sorted and there is a position for every color")
+}
+
+/// The stops of a gradient exported as an image strip, as given with
+/// `--palette-image`: the pixels along the middle of its longer side, left
+/// to right or top to bottom, spread evenly from 0 to 1.
+///
+/// Strips narrower than the table of a `Colormap` are interpolated between
+/// their pixels like any other stops. Wider ones are sampled down to one
+/// stop per entry, as the colors in between would never be looked up.
+pub fn strip_stops(strip: &RgbImage) -> Result<Vec<Stop>, String> {
+    let (width, height) = strip.dimensions();
+    let length = width.max(height);
+    let pixel = |i| match width >= height {
+        true => strip.get_pixel(i, height / 2),
+        false => strip.get_pixel(width / 2, i),
+    };
+    if length < 2 {
+        return Err(format!(
+            "a palette image needs at least two pixels along it, got {}x{}",
+            width, height
+        ));
+    }
+    let count = (length as usize).min(TABLE_SIZE);
+    Ok((0..count)
+        .map(|i| {
+            let position = i as f64 / (count - 1) as f64;
+            let [r, g, b] = pixel((position * (length - 1) as f64).round() as u32).0;
+            Stop {
+                color: Color::from_rgba8(r, g, b, 255),
+                position,
+            }
+        })
+        .collect())
+}
+
+/// Entries of a Fractint `.map` palette.
+pub const MAP_ENTRIES: usize = 256;
+
+/// Parses a Fractint `.map` palette, a line of `R G B` from 0 to 255 for
+/// every entry, into its colors, with a warning for everything in it that
+/// had to be fixed up.
+///
+/// Anything after the three numbers of a line is a comment, as are lines
+/// starting with `;` or `#`, and blank lines are skipped. Channels out of
+/// range are clamped and entries past the 256th dropped. A short file is
+/// taken as it is, its entries spread over the whole palette.
+pub fn parse_map(text: &str) -> Result<(Vec<Color>, Vec<String>), String> {
+    let mut colors = Vec::new();
+    let mut clamped = Vec::new();
+    let mut extra = 0;
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with([';', '#']) {
+            continue;
+        }
+        let mut rgb = [0; 3];
+        let mut tokens = line.split_whitespace();
+        for channel in &mut rgb {
+            let value: i64 = tokens.next().and_then(|token| token.parse().ok()).ok_or_else(|| {
+                format!("line {} '{}': expected three numbers R G B", n + 1, line)
+            })?;
+            if !(0..=255).contains(&value) {
+                clamped.push(n + 1);
+            }
+            *channel = value.clamp(0, 255) as u8;
+        }
+        if colors.len() == MAP_ENTRIES {
+            extra += 1;
+            continue;
+        }
+        colors.push(Color::from_rgba8(rgb[0], rgb[1], rgb[2], 255));
+    }
+    if colors.len() < 2 {
+        return Err(format!("a palette map needs at least two entries, got {}", colors.len()));
+    }
+    let mut warnings = Vec::new();
+    if let Some(first) = clamped.first() {
+        warnings.push(format!(
+            "{} channels out of 0 to 255 were clamped, the first on line {}",
+            clamped.len(),
+            first
+        ));
+    }
+    if extra > 0 {
+        warnings.push(format!("{} entries past the {}th were dropped", extra, MAP_ENTRIES));
+    }
+    if colors.len() < MAP_ENTRIES {
+        warnings.push(format!(
+            "only {} of {} entries, spread over the whole palette",
+            colors.len(),
+            MAP_ENTRIES
+        ));
+    }
+    Ok((colors, warnings))
+}
+
+/// The stops of the palette of `colors` read from a `.map` file, each held
+/// over an equal share of the gradient as Fractint steps through them, or
+/// with `interpolate` spread evenly from 0 to 1 and blended between.
+pub fn map_stops(colors: &[Color], interpolate: bool) -> Vec<Stop> {
+    let stop = |color: &Color, position| Stop {
+        color: color.clone(),
+        position,
+    };
+    let count = colors.len();
+    match interpolate {
+        true => {
+            let steps = (count - 1) as f64;
+            colors.iter().enumerate().map(|(i, color)| stop(color, i as f64 / steps)).collect()
+        }
+        // A stop at either end of every entry's share makes hard edges
+        // between them.
+        false => colors
+            .iter()
+            .enumerate()
+            .flat_map(|(i, color)| {
+                [stop(color, i as f64 / count as f64), stop(color, (i + 1) as f64 / count as f64)]
+            })
+            .collect(),
+    }
+}
+
+/// Parses a single hex or CSS named color, as given to `--interior-color`.
+pub fn parse_color(value: &str) -> Result<(u8, u8, u8), String> {
+    let color = Color::from_html(value.trim()).map_err(|_| format!("unknown color '{}'", value))?;
+    let [r, g, b, _] = color.to_rgba8();
+    Ok((r, g, b))
+}
@@ -0,0 +1,239 @@
+use crate::error::RustlebrotError;
+use rustlebrot::render::{EscapeBuffer, Sample};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::time::Duration;
+
+/// A stage of the work on a frame, which `performance.json` breaks the time
+/// of a run down by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    /// Iterating the samples, sub-frames and previews included.
+    Compute,
+    /// Taking more samples where adaptive anti-aliasing asks for them.
+    Refine,
+    /// Coloring the escape buffer in every palette, and resampling it to the
+    /// other `--outputs`.
+    Colorize,
+    /// Encoding the colored frames as images, in memory.
+    Encode,
+    /// Writing the encoded images to disk.
+    Write,
+    /// Handing the frame to the video encoder, or encoding the video once
+    /// the frames are saved.
+    Video,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 6] = [
+        Stage::Compute,
+        Stage::Refine,
+        Stage::Colorize,
+        Stage::Encode,
+        Stage::Write,
+        Stage::Video,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Stage::Compute => "compute",
+            Stage::Refine => "refine",
+            Stage::Colorize => "colorize",
+            Stage::Encode => "encode",
+            Stage::Write => "write",
+            Stage::Video => "video",
+        }
+    }
+}
+
+/// How long each stage of a frame took, with what the frame rendered.
+///
+/// The times are of the frame, from the stages' starts to their ends on the
+/// threads that worked on it, so frames rendered at once with
+/// `--frame-parallelism` don't count each other's work.
+#[derive(Clone, Debug, Default)]
+pub struct FrameTimes {
+    pub frame: u32,
+    stages: [Duration; Stage::ALL.len()],
+    pub pixels: u64,
+    /// See `iterations`.
+    pub iterations: Option<u64>,
+    pub precision: &'static str,
+}
+
+impl FrameTimes {
+    pub fn new(frame: u32, pixels: u64) -> FrameTimes {
+        FrameTimes {
+            frame,
+            pixels,
+            ..FrameTimes::default()
+        }
+    }
+
+    pub fn add(&mut self, stage: Stage, time: Duration) {
+        self.stages[stage as usize] += time;
+    }
+
+    pub fn of(&self, stage: Stage) -> Duration {
+        self.stages[stage as usize]
+    }
+}
+
+/// The iterations the samples of `buffer` took, counting those that never
+/// escaped at the iteration limit, or `None` when its values aren't escape
+/// times.
+pub fn iterations(buffer: &EscapeBuffer) -> Option<u64> {
+    if !buffer.coloring.uses_escape_times() {
+        return None;
+    }
+    buffer
+        .values
+        .par_iter()
+        .map(|sample| match *sample {
+            Sample::Value(time) | Sample::Phase { value: time, .. } => Some(time as u64),
+            Sample::Root { iterations, .. } => Some(iterations as u64),
+            Sample::Interior | Sample::Attractor { .. } | Sample::Boundary => {
+                Some(buffer.max_iter as u64)
+            }
+            Sample::Exponent(_) => None,
+        })
+        .sum()
+}
+
+/// The time a run spent in one stage.
+#[derive(Clone, Debug, Serialize)]
+pub struct StageReport {
+    pub stage: &'static str,
+    pub seconds: f64,
+    /// The mean time of a frame.
+    pub mean: f64,
+    /// The time 95% of the frames took at most, unless some of the stage's
+    /// time wasn't spent on any one frame.
+    pub p95: Option<f64>,
+}
+
+/// The performance report of a run, as `performance.json` has it.
+#[derive(Clone, Debug, Serialize)]
+pub struct Report {
+    pub version: &'static str,
+    /// The frames rendered, leaving out those kept with `--resume`.
+    pub frames: usize,
+    /// The wall time of the run, from the first frame to the video.
+    pub seconds: f64,
+    pub threads: usize,
+    pub frame_parallelism: usize,
+    pub backend: &'static str,
+    /// How many frames were rendered in each precision.
+    pub precisions: BTreeMap<&'static str, usize>,
+    pub stages: Vec<StageReport>,
+    /// Pixels of the frames over the wall time, in millions.
+    pub megapixels_per_second: f64,
+    /// Iterations of the frames over the wall time, when their values are
+    /// escape times.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iterations_per_second: Option<f64>,
+}
+
+impl Report {
+    /// The report of the run that rendered `frames`, which are not empty, in
+    /// `wall` time, on `threads` threads `frame_parallelism` frames at a
+    /// time, and encoded its video in `video` once they were saved.
+    pub fn of(
+        frames: &[FrameTimes],
+        wall: Duration,
+        video: Option<Duration>,
+        threads: usize,
+        frame_parallelism: usize,
+    ) -> Report {
+        let count = frames.len();
+        let stages = Stage::ALL
+            .iter()
+            .map(|&stage| {
+                let mut times: Vec<f64> =
+                    frames.iter().map(|frame| frame.of(stage).as_secs_f64()).collect();
+                times.sort_by(f64::total_cmp);
+                let after = match stage {
+                    Stage::Video => video,
+                    _ => None,
+                };
+                let seconds =
+                    times.iter().sum::<f64>() + after.map_or(0.0, |after| after.as_secs_f64());
+                // The nearest rank.
+                let p95 = times[(count * 95).div_ceil(100).max(1) - 1];
+                StageReport {
+                    stage: stage.name(),
+                    seconds,
+                    mean: seconds / count as f64,
+                    p95: after.is_none().then_some(p95),
+                }
+            })
+            .collect();
+        let mut precisions = BTreeMap::new();
+        for frame in frames {
+            *precisions.entry(frame.precision).or_insert(0) += 1;
+        }
+        let wall = wall.as_secs_f64();
+        let pixels: u64 = frames.iter().map(|frame| frame.pixels).sum();
+        let iterations: Option<u64> = frames.iter().map(|frame| frame.iterations).sum();
+        Report {
+            version: env!("CARGO_PKG_VERSION"),
+            frames: count,
+            seconds: wall,
+            threads,
+            frame_parallelism,
+            backend: crate::bench::backend(),
+            precisions,
+            stages,
+            megapixels_per_second: pixels as f64 / 1e6 / wall,
+            iterations_per_second: iterations.map(|iterations| iterations as f64 / wall),
+        }
+    }
+}
+
+/// The report as a table, a row per stage.
+pub fn table(report: &Report) -> String {
+    let precisions: Vec<String> = report
+        .precisions
+        .iter()
+        .map(|(precision, frames)| format!("{} in {}", frames, precision))
+        .collect();
+    let mut text = format!(
+        "Performance of {} frames in {:.2} seconds, {} threads, {} backend, {}\n",
+        report.frames,
+        report.seconds,
+        report.threads,
+        report.backend,
+        precisions.join(", ")
+    );
+    text.push_str(&format!(
+        "{:<10} {:>10} {:>10} {:>10} {:>6}\n",
+        "stage", "total", "mean", "p95", "share"
+    ));
+    let total: f64 = report.stages.iter().map(|stage| stage.seconds).sum();
+    for stage in &report.stages {
+        let p95 = match stage.p95 {
+            Some(p95) => format!("{:.1}ms", p95 * 1e3),
+            None => "-".to_string(),
+        };
+        text.push_str(&format!(
+            "{:<10} {:>9.2}s {:>8.1}ms {:>10} {:>5.1}%\n",
+            stage.stage,
+            stage.seconds,
+            stage.mean * 1e3,
+            p95,
+            100.0 * stage.seconds / total.max(f64::MIN_POSITIVE),
+        ));
+    }
+    text.push_str(&format!("{:.2} MP/s", report.megapixels_per_second));
+    if let Some(iterations) = report.iterations_per_second {
+        text.push_str(&format!(", {:.3} Giter/s", iterations / 1e9));
+    }
+    text
+}
+
+pub fn write(path: &str, report: &Report) -> Result<(), RustlebrotError> {
+    let json = serde_json::to_string_pretty(report).expect("reports serialize to JSON") + "\n";
+    fs::write(path, json).map_err(|e| RustlebrotError::write(path, e))
+}
@@ -0,0 +1,254 @@
+use crate::bigfloat::Big;
+use crate::complex::Complex;
+use crate::fractal::{derivative_step, distance_bailout, estimate_distance, Escape};
+use crate::series::Series;
+use crate::stripes::StripeAverage;
+use crate::trap::Trap;
+use std::sync::{Arc, Mutex};
+
+/// The orbit of the frame center, computed once in high precision and
+/// rounded to f64 for the per-pixel delta iteration.
+///
+/// `z[0]` is always zero and the orbit stops at the first `z` that escapes
+/// if the center itself escapes, in which case pixels that outlive it rebase
+/// onto the start of the orbit. An `EscapeTimeFractal` has no delta
+/// iteration, and its orbits only hold the center.
+pub struct ReferenceOrbit {
+    pub z: Vec<(f64, f64)>,
+    /// Mantissa bits the orbit was computed with.
+    pub bits: usize,
+    pub max_iter: u32,
+}
+
+impl ReferenceOrbit {
+    /// Iterates `center` in arbitrary precision and records every `z` until
+    /// it escapes past `bailout`.
+    ///
+    /// With `conjugate` set the Tricorn iteration is used.
+    pub fn compute(
+        center: &(Big, Big),
+        bits: usize,
+        max_iter: u32,
+        bailout: f64,
+        conjugate: bool,
+    ) -> Self {
+        let bailout_sqr = Big::try_from(bailout * bailout).expect("bailout is finite");
+        let mut z: (Big, Big) = (Big::ZERO, Big::ZERO);
+        let mut z_sqr: (Big, Big) = (Big::ZERO, Big::ZERO);
+        let mut orbit = Vec::with_capacity(max_iter as usize + 1);
+        orbit.push((0.0, 0.0));
+        for _ in 0..max_iter {
+            let cross = (&z.0 * &z.1) << 1;
+            let x = &z_sqr.0 - &z_sqr.1 + &center.0;
+            let y = if conjugate {
+                &center.1 - cross
+            } else {
+                cross + &center.1
+            };
+            let (x_sqr, y_sqr) = (x.sqr(), y.sqr());
+            orbit.push((x.to_f64().value(), y.to_f64().value()));
+            if &x_sqr + &y_sqr > bailout_sqr {
+                break;
+            }
+            z = (x, y);
+            z_sqr = (x_sqr, y_sqr);
+        }
+        ReferenceOrbit {
+            z: orbit,
+            bits,
+            max_iter,
+        }
+    }
+
+    /// Whether this orbit is precise and long enough to render a frame
+    /// needing `bits` of precision and `max_iter` iterations.
+    fn covers(&self, bits: usize, max_iter: u32) -> bool {
+        self.bits >= bits && self.max_iter >= max_iter
+    }
+}
+
+/// Holds on to the last reference orbit so consecutive frames that share
+/// the zoom center can reuse it.
+///
+/// An orbit is reused as long as it is of the same center, given as decimal
+/// strings, and was computed with at least as many bits as the new frame
+/// needs, so the deepest frame rendered so far seeds all shallower ones.
+#[derive(Default)]
+pub struct OrbitCache {
+    orbit: Mutex<Option<CachedOrbit>>,
+}
+
+/// The orbit an `OrbitCache` holds, with the center it is of.
+struct CachedOrbit {
+    center: (String, String),
+    orbit: Arc<ReferenceOrbit>,
+}
+
+impl OrbitCache {
+    /// Returns a cached orbit that covers the request, or computes a new one.
+    pub fn get_or_compute<C>(
+        &self,
+        center: &(String, String),
+        bits: usize,
+        max_iter: u32,
+        compute: C,
+    ) -> Arc<ReferenceOrbit>
+    where
+        C: FnOnce() -> ReferenceOrbit,
+    {
+        let mut cached = self.orbit.lock().unwrap();
+        if let Some(cached) = cached.as_ref() {
+            if &cached.center == center && cached.orbit.covers(bits, max_iter) {
+                return Arc::clone(&cached.orbit);
+            }
+        }
+        let orbit = Arc::new(compute());
+        *cached = Some(CachedOrbit {
+            center: center.clone(),
+            orbit: Arc::clone(&orbit),
+        });
+        orbit
+    }
+}
+
+/// Computes the escape time of the pixel at offset `dc` from the reference
+/// center, iterating only the difference to the reference orbit.
+///
+/// With `Z` the reference and `d` the delta, the Mandelbrot step becomes
+/// `d' = 2*Z*d + d^2 + dc`; for the Tricorn the first two terms are
+/// conjugated. Whenever the full value `Z + d` gets smaller than `d`, or
+/// the reference runs out, the delta is rebased onto the start of the orbit,
+/// which keeps it small enough that f64 never loses the pixel's detail.
+///
+/// With a `series`, iteration starts from its approximated delta at
+/// `series.skip`, skipping the beginning of the orbit; pass `None` to
+/// iterate from scratch. A `trap` is measured against the full `Z + d`.
+pub fn escape_time<const CONJUGATE: bool>(
+    orbit: &[(f64, f64)],
+    dc: (f64, f64),
+    series: Option<&Series>,
+    max_iter: u32,
+    bailout: f64,
+    trap: Option<&Trap>,
+) -> Escape {
+    let (mut d, start) = match series {
+        Some(series) => (series.delta(dc), series.skip),
+        None => ((0.0, 0.0), 0),
+    };
+    let bailout_sqr = bailout * bailout;
+    let last = orbit.len() - 1;
+    let mut m = start;
+    let mut trap_distance = f64::INFINITY;
+    for i in start as u32..max_iter {
+        let r = orbit[m];
+        let x = 2.0 * (r.0 * d.0 - r.1 * d.1) + d.0 * d.0 - d.1 * d.1;
+        let y = 2.0 * (r.0 * d.1 + r.1 * d.0) + 2.0 * d.0 * d.1;
+        d = if CONJUGATE {
+            (x + dc.0, -y + dc.1)
+        } else {
+            (x + dc.0, y + dc.1)
+        };
+        m += 1;
+
+        let z = (orbit[m].0 + d.0, orbit[m].1 + d.1);
+        let z_norm = z.0 * z.0 + z.1 * z.1;
+        if let Some(trap) = trap {
+            trap_distance = trap_distance.min(trap.distance(z));
+        }
+        if z_norm > bailout_sqr {
+            return Escape::escaped(i, z, bailout, trap_distance);
+        }
+        if m == last || z_norm < d.0 * d.0 + d.1 * d.1 {
+            d = z;
+            m = 0;
+        }
+    }
+    Escape::interior(max_iter, trap_distance)
+}
+
+/// Computes the exterior distance estimate of the pixel at offset `dc`, or
+/// `None` if it doesn't escape.
+///
+/// The delta is iterated exactly like `escape_time`, while the derivative is
+/// carried on the full `Z + d`, which is well within f64 range. Since the
+/// reference stops at the frame's bailout, pixels rebase onto the start of
+/// the orbit on their way out to the larger distance bailout.
+pub fn distance<const CONJUGATE: bool>(
+    orbit: &[(f64, f64)],
+    dc: (f64, f64),
+    max_iter: u32,
+    bailout: f64,
+) -> Option<f64> {
+    let bailout_sqr = distance_bailout(bailout);
+    let last = orbit.len() - 1;
+    let mut d: (f64, f64) = (0.0, 0.0);
+    let mut dz = Complex::ZERO;
+    let mut dz_conj = Complex::ZERO;
+    let mut m = 0;
+    for _ in 0..max_iter {
+        let r = orbit[m];
+        let z = Complex::new(r.0 + d.0, r.1 + d.1);
+        (dz, dz_conj) = derivative_step::<CONJUGATE>(z, dz, dz_conj);
+
+        let x = 2.0 * (r.0 * d.0 - r.1 * d.1) + d.0 * d.0 - d.1 * d.1;
+        let y = 2.0 * (r.0 * d.1 + r.1 * d.0) + 2.0 * d.0 * d.1;
+        d = if CONJUGATE {
+            (x + dc.0, -y + dc.1)
+        } else {
+            (x + dc.0, y + dc.1)
+        };
+        m += 1;
+
+        let z = (orbit[m].0 + d.0, orbit[m].1 + d.1);
+        let z_norm = Complex::from(z).norm_sqr();
+        if z_norm > bailout_sqr {
+            return Some(estimate_distance(z.into(), dz, dz_conj));
+        }
+        if m == last || z_norm < Complex::from(d).norm_sqr() {
+            d = z;
+            m = 0;
+        }
+    }
+    None
+}
+
+/// Computes the stripe average of the pixel at offset `dc`, or `None` if it
+/// doesn't escape, iterating the delta exactly like `escape_time`.
+pub fn stripes<const CONJUGATE: bool>(
+    orbit: &[(f64, f64)],
+    dc: (f64, f64),
+    max_iter: u32,
+    bailout: f64,
+    density: f64,
+) -> Option<f64> {
+    let bailout_sqr = bailout * bailout;
+    let last = orbit.len() - 1;
+    let mut stripes = StripeAverage::new(density);
+    let mut d: (f64, f64) = (0.0, 0.0);
+    let mut m = 0;
+    for i in 0..max_iter {
+        let r = orbit[m];
+        let x = 2.0 * (r.0 * d.0 - r.1 * d.1) + d.0 * d.0 - d.1 * d.1;
+        let y = 2.0 * (r.0 * d.1 + r.1 * d.0) + 2.0 * d.0 * d.1;
+        d = if CONJUGATE {
+            (x + dc.0, -y + dc.1)
+        } else {
+            (x + dc.0, y + dc.1)
+        };
+        m += 1;
+
+        let z = (orbit[m].0 + d.0, orbit[m].1 + d.1);
+        let z_norm = Complex::from(z).norm_sqr();
+        if i > 0 {
+            stripes.add(z);
+        }
+        if z_norm > bailout_sqr {
+            return Some(stripes.escaped(i, z, bailout));
+        }
+        if m == last || z_norm < Complex::from(d).norm_sqr() {
+            d = z;
+            m = 0;
+        }
+    }
+    None
+}
@@ -0,0 +1,219 @@
+use crate::outputs::{encoded, premultiplied};
+use image::{DynamicImage, Rgba32FImage};
+use rayon::prelude::*;
+use std::fmt;
+
+/// The width of the blur `unsharp` takes from a frame to sharpen it, as the
+/// standard deviation of a Gaussian in pixels.
+pub const UNSHARP_SIGMA: f64 = 1.0;
+
+/// A filter of `--post`, with its parameter.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Filter {
+    /// A Gaussian blur, its standard deviation in pixels.
+    GaussianBlur(f64),
+    /// An unsharp mask: the difference from a blur of `UNSHARP_SIGMA`,
+    /// times the amount, added to the frame.
+    Unsharp(f64),
+    /// Darkening toward the corners, with the square of the distance from
+    /// the middle, by the given share at the corners.
+    Vignette(f64),
+    /// Levels stretching the intensities from a black point to a white
+    /// point over the whole range, clipping those outside.
+    Levels { black: f64, white: f64 },
+}
+
+impl Filter {
+    pub fn name(self) -> &'static str {
+        match self {
+            Filter::GaussianBlur(_) => "gaussian-blur",
+            Filter::Unsharp(_) => "unsharp",
+            Filter::Vignette(_) => "vignette",
+            Filter::Levels { .. } => "levels",
+        }
+    }
+
+    /// Parses a filter such as `gaussian-blur:0.6` or `levels:0.02-0.98`.
+    pub fn from_spec(spec: &str) -> Result<Filter, String> {
+        let (name, parameter) = spec.split_once(':').unwrap_or((spec, ""));
+        let (name, parameter) = (name.trim(), parameter.trim());
+        let number = |what: &str, parameter: &str, valid: fn(f64) -> bool, range: &str| {
+            parameter.parse().ok().filter(|&value: &f64| valid(value)).ok_or_else(|| {
+                format!("the {} of {} should be {}, got '{}'", what, name, range, parameter)
+            })
+        };
+        let positive = |value: f64| value > 0.0 && value.is_finite();
+        let unit = |value: f64| (0.0..=1.0).contains(&value);
+        let filter = match name {
+            "gaussian-blur" => {
+                Filter::GaussianBlur(number("sigma", parameter, positive, "positive")?)
+            }
+            "unsharp" => Filter::Unsharp(number("amount", parameter, positive, "positive")?),
+            "vignette" => Filter::Vignette(number("strength", parameter, unit, "from 0 to 1")?),
+            "levels" => {
+                let (black, white) = parameter.split_once('-').ok_or_else(|| {
+                    format!(
+                        "levels takes a black and a white point like 0.02-0.98, got '{}'",
+                        parameter
+                    )
+                })?;
+                let black = number("black point", black.trim(), unit, "from 0 to 1")?;
+                let white = number("white point", white.trim(), unit, "from 0 to 1")?;
+                if black >= white {
+                    return Err(format!(
+                        "the black point of levels should be below the white point, got {}-{}",
+                        black, white
+                    ));
+                }
+                Filter::Levels { black, white }
+            }
+            _ => {
+                return Err(format!(
+                    "unknown post filter '{}', expected gaussian-blur, unsharp, vignette or \
+                     levels",
+                    name
+                ))
+            }
+        };
+        Ok(filter)
+    }
+
+    /// Filters `linear`, pixels as `outputs::premultiplied` gives them.
+    fn apply(self, linear: Rgba32FImage) -> Rgba32FImage {
+        match self {
+            Filter::GaussianBlur(sigma) => blur(&linear, sigma),
+            Filter::Unsharp(amount) => {
+                let mut sharpened = blur(&linear, UNSHARP_SIGMA);
+                let amount = amount as f32;
+                sharpened.par_iter_mut().zip(linear.par_iter()).for_each(|(blurred, &value)| {
+                    *blurred = value + amount * (value - *blurred);
+                });
+                sharpened
+            }
+            Filter::Vignette(strength) => vignette(linear, strength),
+            Filter::Levels { black, white } => {
+                let mut linear = linear;
+                let (black, span) = (black as f32, (white - black) as f32);
+                linear.par_chunks_mut(4).for_each(|pixel| {
+                    let alpha = pixel[3];
+                    if alpha <= 0.0 {
+                        return;
+                    }
+                    for channel in &mut pixel[..3] {
+                        *channel = ((*channel / alpha - black) / span).clamp(0.0, 1.0) * alpha;
+                    }
+                });
+                linear
+            }
+        }
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Filter::GaussianBlur(value) | Filter::Unsharp(value) | Filter::Vignette(value) => {
+                write!(f, "{}:{}", self.name(), value)
+            }
+            Filter::Levels { black, white } => write!(f, "levels:{}-{}", black, white),
+        }
+    }
+}
+
+/// The filters of `--post`, applied to every colored frame in order before
+/// it's saved or piped, such as
+/// `gaussian-blur:0.6,unsharp:1.2,vignette:0.3,levels:0.02-0.98`.
+///
+/// The filters work in linear light with colors weighted by their opacity,
+/// so blurs spread light as a lens does and transparent pixels don't bleed
+/// into their neighbors.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Chain {
+    pub filters: Vec<Filter>,
+}
+
+impl Chain {
+    pub fn from_spec(spec: &str) -> Result<Chain, String> {
+        let filters = spec
+            .split(',')
+            .map(|filter| match filter.trim() {
+                "" => Err(format!("post takes filters like gaussian-blur:0.6, got '{}'", spec)),
+                filter => Filter::from_spec(filter),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Chain { filters })
+    }
+
+    /// `img` through every filter, with its channels and bit depth.
+    pub fn apply(&self, img: &DynamicImage) -> DynamicImage {
+        let linear = self.filters.iter().fold(premultiplied(img), |linear, filter| {
+            filter.apply(linear)
+        });
+        encoded(linear, img.color())
+    }
+}
+
+impl fmt::Display for Chain {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let filters: Vec<String> = self.filters.iter().map(Filter::to_string).collect();
+        write!(f, "{}", filters.join(","))
+    }
+}
+
+/// The weights of a Gaussian of standard deviation `sigma`, out to three
+/// of them either side of the middle, summing to 1.
+fn kernel(sigma: f64) -> Vec<f32> {
+    let radius = (3.0 * sigma).ceil() as i64;
+    let weights: Vec<f64> = (-radius..=radius)
+        .map(|offset| (-(offset * offset) as f64 / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let sum: f64 = weights.iter().sum();
+    weights.iter().map(|weight| (weight / sum) as f32).collect()
+}
+
+/// `linear` blurred by a Gaussian of standard deviation `sigma`, across the
+/// rows and then down the columns, with the pixels at the edges carried on
+/// past them.
+fn blur(linear: &Rgba32FImage, sigma: f64) -> Rgba32FImage {
+    let kernel = kernel(sigma);
+    let radius = kernel.len() / 2;
+    let (width, height) = (linear.width() as usize, linear.height() as usize);
+    let mut across = vec![0.0; width * height * 4];
+    across.par_chunks_mut(width * 4).zip(linear.par_chunks(width * 4)).for_each(|(row, from)| {
+        for x in 0..width {
+            for (k, weight) in kernel.iter().enumerate() {
+                let source = (x + k).saturating_sub(radius).min(width - 1);
+                for channel in 0..4 {
+                    row[x * 4 + channel] += weight * from[source * 4 + channel];
+                }
+            }
+        }
+    });
+    let mut down = vec![0.0; width * height * 4];
+    down.par_chunks_mut(width * 4).enumerate().for_each(|(y, row)| {
+        for (k, weight) in kernel.iter().enumerate() {
+            let source = (y + k).saturating_sub(radius).min(height - 1);
+            let from = &across[source * width * 4..(source + 1) * width * 4];
+            row.iter_mut().zip(from).for_each(|(value, from)| *value += weight * from);
+        }
+    });
+    Rgba32FImage::from_raw(width as u32, height as u32, down).expect("a pixel for every pixel")
+}
+
+/// `linear` darkened by `strength` times the square of the distance from
+/// its middle, which is 1 at the middles of the corner pixels.
+fn vignette(mut linear: Rgba32FImage, strength: f64) -> Rgba32FImage {
+    let width = linear.width() as usize;
+    let middle = ((linear.width() - 1) as f64 / 2.0, (linear.height() - 1) as f64 / 2.0);
+    let reach = middle.0 * middle.0 + middle.1 * middle.1;
+    linear.par_chunks_mut(4).enumerate().for_each(|(index, pixel)| {
+        let (x, y) = ((index % width) as f64 - middle.0, (index / width) as f64 - middle.1);
+        let distance = match reach > 0.0 {
+            true => (x * x + y * y) / reach,
+            false => 0.0,
+        };
+        let factor = (1.0 - strength * distance) as f32;
+        pixel[..3].iter_mut().for_each(|channel| *channel *= factor);
+    });
+    linear
+}
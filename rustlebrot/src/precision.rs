@@ -0,0 +1,119 @@
+use crate::dd;
+
+/// Bits past the last one of the center coordinates a pixel has to span for
+/// auto precision to keep a float type, so a pixel is at least 8 ulps.
+const MARGIN_BITS: i32 = 3;
+
+/// Ulps of the center a pixel has to span before its frame gets a warning
+/// about losing precision.
+pub const WARN_ULPS: f64 = 100.0;
+
+/// The numeric backend used to render a frame, as chosen with `--precision`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Precision {
+    /// Use f32 while the pixel size is well above f32 resolution at the
+    /// center, then f64 until it nears f64 resolution, then double-double
+    /// until it nears that, then perturbation.
+    Auto,
+    /// Plain f32 iteration of every pixel, in twice as many lanes as f64.
+    /// Only escape time colorings have an f32 loop, and only with the
+    /// `simd` feature.
+    F32,
+    /// Plain f64 iteration of every pixel.
+    F64,
+    /// Plain double-double iteration of every pixel, a pair of f64s for
+    /// about 32 digits. Only escape time colorings have a double-double
+    /// loop.
+    DoubleDouble,
+    /// f64 deltas against a high-precision reference orbit.
+    Perturbation,
+    /// Arbitrary-precision iteration of every pixel. Exact but very slow.
+    Big,
+}
+
+impl Precision {
+    pub fn from_name(name: &str) -> Option<Precision> {
+        match name {
+            "auto" => Some(Precision::Auto),
+            "f32" => Some(Precision::F32),
+            "f64" => Some(Precision::F64),
+            "dd" | "double-double" => Some(Precision::DoubleDouble),
+            "perturb" | "perturbation" => Some(Precision::Perturbation),
+            "big" => Some(Precision::Big),
+            _ => None,
+        }
+    }
+
+    /// The cargo feature this precision needs, if this build was made
+    /// without it.
+    pub fn missing_feature(self) -> Option<&'static str> {
+        match self {
+            Precision::Perturbation | Precision::Big if !cfg!(feature = "bigfloat") => {
+                Some("bigfloat")
+            }
+            _ => None,
+        }
+    }
+
+    /// The name used on the command line and in the frame log.
+    pub fn name(self) -> &'static str {
+        match self {
+            Precision::Auto => "auto",
+            Precision::F32 => "f32",
+            Precision::F64 => "f64",
+            Precision::DoubleDouble => "dd",
+            Precision::Perturbation => "perturbation",
+            Precision::Big => "big",
+        }
+    }
+
+    /// The smallest step between points this precision can tell apart
+    /// around `center`, for the precisions `resolve` returns.
+    ///
+    /// Perturbation and arbitrary precision keep the center exact, but the
+    /// offsets of pixels from it are f64, which lose bits once they are
+    /// smaller than the smallest normal f64.
+    pub fn resolution(self, center: (f64, f64)) -> f64 {
+        let magnitude = center.0.abs().max(center.1.abs()).max(f64::MIN_POSITIVE);
+        match self {
+            Precision::F32 => magnitude * f32::EPSILON as f64,
+            Precision::Auto | Precision::F64 => magnitude * f64::EPSILON,
+            Precision::DoubleDouble => magnitude * dd::EPSILON,
+            Precision::Perturbation | Precision::Big => f64::MIN_POSITIVE,
+        }
+    }
+
+    /// Whether pixels `pixel_size` apart around `center` span enough steps
+    /// of `resolution` for this precision to tell them apart, with the
+    /// margin auto precision keeps before it moves on.
+    pub fn resolves(self, center: (f64, f64), pixel_size: f64) -> bool {
+        pixel_size >= 2f64.powi(MARGIN_BITS) * self.resolution(center)
+    }
+
+    /// Picks the backend for a frame with the given pixel size around
+    /// `center`. Never returns `Auto`.
+    ///
+    /// In auto mode this switches over once a pixel spans fewer than a few
+    /// ulps of the center coordinates, which is where frames start to turn
+    /// blocky, from f32 to f64, from f64 to double-double and from
+    /// double-double to perturbation. Without the `bigfloat` feature it
+    /// stays with double-double.
+    pub fn resolve(self, center: (f64, f64), pixel_size: f64) -> Precision {
+        match self {
+            Precision::Auto => {
+                if cfg!(feature = "bigfloat")
+                    && !Precision::DoubleDouble.resolves(center, pixel_size)
+                {
+                    Precision::Perturbation
+                } else if !Precision::F64.resolves(center, pixel_size) {
+                    Precision::DoubleDouble
+                } else if cfg!(feature = "simd") && Precision::F32.resolves(center, pixel_size) {
+                    Precision::F32
+                } else {
+                    Precision::F64
+                }
+            }
+            other => other,
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use crate::budget::{CostModel, MARGIN};
+use std::fmt;
+
+/// Bytes per pixel a frame's files and its share of the video come to,
+/// for the estimate of how much room a run needs. Fractal frames are
+/// noisy where they are detailed and compress poorly, so these are rough
+/// figures on the high side, for frames that are mostly detail.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Footprint {
+    /// Bytes per pixel of the files every frame is saved as.
+    pub frame: f64,
+    /// Bytes per pixel of every frame in the video.
+    pub video: f64,
+}
+
+/// The time a run is predicted to take.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Times {
+    /// The seconds of the first and the last frame, which tell how much
+    /// slower frames get as the zoom deepens.
+    pub first: f64,
+    pub last: f64,
+    /// The seconds of every frame together.
+    pub total: f64,
+}
+
+impl Times {
+    /// Predicts what `frames`, given with their limits, take to render with
+    /// `samples` samples each by the cost `model`.
+    ///
+    /// The model only times the escape loop, so coloring, saving and
+    /// encoding are taken to cost what `budget::MARGIN` leaves for them, as
+    /// time budgets do.
+    pub fn new(model: &CostModel, frames: &[(u32, u32)], samples: u64) -> Times {
+        let seconds = |&(frame, max_iter): &(u32, u32)| {
+            model.seconds(frame, samples, max_iter) / MARGIN
+        };
+        Times {
+            first: frames.first().map_or(0.0, seconds),
+            last: frames.last().map_or(0.0, seconds),
+            total: frames.iter().map(seconds).sum(),
+        }
+    }
+}
+
+/// What a run is predicted to take, worked out before it starts.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Estimate {
+    pub frames: usize,
+    /// The time, where the run can be probed for it.
+    pub times: Option<Times>,
+    /// The bytes of the frames saved and of the video.
+    pub frame_bytes: u64,
+    pub video_bytes: u64,
+}
+
+impl Estimate {
+    /// The estimate of `frames` frames of `pixels` pixels each, whose files
+    /// take the room of `footprint`.
+    pub fn new(frames: usize, times: Option<Times>, pixels: u64, footprint: Footprint) -> Self {
+        let bytes = |per_pixel: f64| (frames as f64 * pixels as f64 * per_pixel) as u64;
+        Estimate {
+            frames,
+            times,
+            frame_bytes: bytes(footprint.frame),
+            video_bytes: bytes(footprint.video),
+        }
+    }
+
+    /// The bytes of every file of the run.
+    pub fn bytes(&self) -> u64 {
+        self.frame_bytes + self.video_bytes
+    }
+}
+
+/// What is known of a run before it starts, for `check`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Run {
+    pub zoom_factor: f64,
+    /// Whether anything but the zoom factor changes from frame to frame,
+    /// such as keyframes, rotation, a Julia path or palette drift.
+    pub animated: bool,
+    /// The bytes the files of the run are estimated to take.
+    pub bytes: u64,
+    /// The bytes free where they are written, if that is known.
+    pub free_bytes: Option<u64>,
+}
+
+/// Something wrong with a run, found before it starts.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Finding {
+    /// The files take more room than is free, so the run would fail
+    /// partway.
+    DiskFull { bytes: u64, free_bytes: u64 },
+    /// The files would leave less than a tenth of what is free now.
+    DiskTight { bytes: u64, free_bytes: u64 },
+    /// Every frame shows the same view.
+    NoZoom,
+}
+
+impl Finding {
+    /// Whether the run can't go ahead, rather than only being worth a
+    /// second look.
+    pub fn is_error(&self) -> bool {
+        matches!(self, Finding::DiskFull { .. })
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Finding::DiskFull { bytes, free_bytes } => write!(
+                f,
+                "the frames and video need about {}, but only {} is free",
+                format_bytes(*bytes),
+                format_bytes(*free_bytes)
+            ),
+            Finding::DiskTight { bytes, free_bytes } => write!(
+                f,
+                "the frames and video need about {} of the {} free, leaving little room",
+                format_bytes(*bytes),
+                format_bytes(*free_bytes)
+            ),
+            Finding::NoZoom => write!(
+                f,
+                "a zoom_factor of 1 keeps the magnification, and nothing else changes from \
+                 frame to frame, so every frame is the same"
+            ),
+        }
+    }
+}
+
+/// What is wrong with `run`, errors first.
+pub fn check(run: &Run) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    if let Some(free_bytes) = run.free_bytes {
+        if run.bytes > free_bytes {
+            findings.push(Finding::DiskFull {
+                bytes: run.bytes,
+                free_bytes,
+            });
+        } else if run.bytes > free_bytes / 10 * 9 {
+            findings.push(Finding::DiskTight {
+                bytes: run.bytes,
+                free_bytes,
+            });
+        }
+    }
+    if run.zoom_factor == 1.0 && !run.animated {
+        findings.push(Finding::NoZoom);
+    }
+    findings
+}
+
+/// `bytes` in the largest binary unit it makes at least one of, as
+/// `12.3 GiB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["bytes", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{} bytes", bytes),
+        _ => format!("{:.1} {}", value, UNITS[unit]),
+    }
+}
@@ -0,0 +1,95 @@
+use crate::fractal::FractalKind;
+
+/// A well-known place in the Mandelbrot set to zoom into, as chosen with
+/// `--preset`.
+///
+/// Presets fill in what the command line would otherwise give: the center,
+/// the iteration limit, and enough frames to reach `depth`. Flags given as
+/// well take precedence.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Preset {
+    pub name: &'static str,
+    /// One line on what the zoom shows, printed by `--list-presets`.
+    pub description: &'static str,
+    /// The center as decimal strings, with enough digits for `depth`.
+    pub center: (&'static str, &'static str),
+    /// An iteration limit that resolves the deepest frame.
+    pub max_iter: u32,
+    /// The magnification the zoom is worth going to, relative to the first
+    /// frame.
+    pub depth: f64,
+}
+
+/// The zoom factor of a preset's frames when the command line gives none.
+pub const ZOOM_FACTOR: f64 = 1.1;
+
+/// Every preset, in the order `--list-presets` prints them. Adding one only
+/// takes an entry here.
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        name: "seahorse-valley",
+        description: "spirals of seahorse tails between the main cardioid and the period 2 bulb",
+        center: (
+            "-0.743643887037158704752191506114774",
+            "0.131825904205311970493132056385139",
+        ),
+        max_iter: 50_000,
+        depth: 1e30,
+    },
+    Preset {
+        name: "elephant-valley",
+        description: "trunks curling off the cusp of the main cardioid, down to a minibrot",
+        center: (
+            "0.2926025441219267163217880747748528155401846427982324433955723",
+            "-0.014812893895656158721693442286686208226255699675801869796920424",
+        ),
+        max_iter: 20_000,
+        depth: 1e8,
+    },
+    Preset {
+        name: "misiurewicz",
+        description: "a point whose orbit ends up in a cycle, the same branching pattern at every \
+                      depth",
+        center: (
+            "-0.775683768009053797469483503934741045728246504615800394794952001400666",
+            "0.1364673682946901247332744096178487651977721115924676964078001954008357",
+        ),
+        max_iter: 4_000,
+        depth: 1e45,
+    },
+    Preset {
+        name: "seahorse-minibrot",
+        description: "the period 39 minibrot in seahorse valley, filling the frame at the end",
+        center: (
+            "-0.74364230165788594611859731943377783587419897727005989670694352",
+            "0.13182651981259472349919301230551915131016916179828424905261128",
+        ),
+        max_iter: 10_000,
+        depth: 1e6,
+    },
+    Preset {
+        name: "default",
+        description: "the antenna near -1.75 the zoom closes in on without a center",
+        center: FractalKind::Mandelbrot.default_center(),
+        max_iter: 50_000,
+        depth: 1e14,
+    },
+];
+
+impl Preset {
+    pub fn from_name(name: &str) -> Option<&'static Preset> {
+        PRESETS.iter().find(|preset| preset.name == name)
+    }
+
+    /// The frames of a zoom by `zoom_factor` from the first frame to one at
+    /// `depth`, both included.
+    pub fn frames(&self, zoom_factor: f64) -> u32 {
+        frames_to(self.depth, zoom_factor)
+    }
+}
+
+/// The frames of a zoom by `zoom_factor` from the first frame to one at
+/// `magnification`, both included.
+pub fn frames_to(magnification: f64, zoom_factor: f64) -> u32 {
+    (magnification.ln() / zoom_factor.ln()).ceil() as u32 + 1
+}
@@ -0,0 +1,234 @@
+use crate::events::{self, Event};
+use crate::render::ROWS_COMPUTED;
+use std::collections::HashSet;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How often the progress line is redrawn on a terminal.
+pub const REDRAW_INTERVAL: Duration = Duration::from_millis(200);
+
+/// How often progress is logged when it isn't shown on a terminal.
+const LOG_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How many of the latest frames the frames per minute are averaged over.
+const RATE_WINDOW: usize = 10;
+
+/// Width of the bar, in characters.
+const BAR_WIDTH: usize = 20;
+
+/// Shows how far a run has got and when it will be done.
+///
+/// On a terminal this is a line at the bottom that is redrawn as frames
+/// progress, with the lines frames print scrolling above it. Otherwise a
+/// plain line is logged every so often, so logs stay readable.
+pub struct Progress {
+    /// The frames to render, to predict what the rest of them cost.
+    frames: Vec<u32>,
+    /// Rows of pixels the compute pass goes through per frame, or `None`
+    /// when frames aren't computed row by row.
+    rows_per_frame: Option<u64>,
+    terminal: bool,
+    start: Instant,
+    /// `ROWS_COMPUTED` when the run started.
+    rows_at_start: u64,
+    state: Mutex<State>,
+}
+
+struct State {
+    started: usize,
+    /// The number and render time in seconds of every finished frame.
+    finished: Vec<(u32, f64)>,
+    /// When the latest frames finished, for the frame rate.
+    recent: Vec<Instant>,
+    last_log: Instant,
+    /// Whether the progress line is on screen and has to be cleared before
+    /// anything else is printed.
+    drawn: bool,
+    /// Set by `finish`, after which nothing is drawn.
+    finished_run: bool,
+}
+
+impl Progress {
+    pub fn new(frames: &[u32], rows_per_frame: Option<u64>) -> Self {
+        let start = Instant::now();
+        Progress {
+            frames: frames.to_vec(),
+            rows_per_frame,
+            terminal: events::says_to_terminal(),
+            start,
+            rows_at_start: ROWS_COMPUTED.load(Ordering::Relaxed),
+            state: Mutex::new(State {
+                started: 0,
+                finished: Vec::new(),
+                recent: Vec::new(),
+                last_log: start,
+                drawn: false,
+                finished_run: false,
+            }),
+        }
+    }
+
+    pub fn frame_started(&self) {
+        self.state.lock().unwrap().started += 1;
+    }
+
+    /// Records that `frame` took `seconds` to render and prints `message`
+    /// about it.
+    pub fn frame_finished(&self, frame: u32, seconds: f64, message: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.finished.push((frame, seconds));
+        state.recent.push(Instant::now());
+        if state.recent.len() > RATE_WINDOW {
+            state.recent.remove(0);
+        }
+        self.clear(&mut state);
+        events::say(message);
+        self.show(&mut state);
+    }
+
+    /// Sends how far the run has got as a `progress` event.
+    pub fn emit(&self) {
+        let state = self.state.lock().unwrap();
+        events::emit(&Event::Progress {
+            done: state.finished.len(),
+            frames: self.frames.len(),
+            elapsed: self.start.elapsed().as_secs_f64(),
+            frames_per_minute: rate(&state),
+            eta: self.eta(&state).map(|eta| eta.as_secs_f64()),
+        });
+    }
+
+    /// Prints `message`, about a frame in progress, above the progress line.
+    pub fn say(&self, message: &str) {
+        let mut state = self.state.lock().unwrap();
+        self.clear(&mut state);
+        events::say(message);
+        self.show(&mut state);
+    }
+
+    /// Redraws the progress line, or logs it when it's time to.
+    pub fn tick(&self) {
+        let mut state = self.state.lock().unwrap();
+        self.show(&mut state);
+    }
+
+    /// Leaves the last progress line on screen, for after the run.
+    pub fn finish(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.finished_run = true;
+        if self.terminal {
+            self.draw(&mut state);
+            events::say("");
+            state.drawn = false;
+        }
+    }
+
+    fn show(&self, state: &mut State) {
+        if state.finished_run {
+            return;
+        }
+        if self.terminal {
+            self.draw(state);
+        } else if state.last_log.elapsed() >= LOG_INTERVAL {
+            state.last_log = Instant::now();
+            events::say(format!("Progress: {}", self.status(state)));
+        }
+    }
+
+    fn draw(&self, state: &mut State) {
+        let done = state.finished.len() as f64 / self.frames.len().max(1) as f64;
+        let filled = (done * BAR_WIDTH as f64) as usize;
+        let bar = format!("{}{}", "#".repeat(filled), "-".repeat(BAR_WIDTH - filled));
+        events::say_partial(&format!("\r\x1b[K[{}] {}", bar, self.status(state)));
+        state.drawn = true;
+    }
+
+    fn clear(&self, state: &mut State) {
+        if state.drawn {
+            events::say_partial("\r\x1b[K");
+            state.drawn = false;
+        }
+    }
+
+    fn status(&self, state: &State) -> String {
+        let mut status = format!(
+            "frame {}/{}, {} elapsed",
+            state.finished.len(),
+            self.frames.len(),
+            format_duration(self.start.elapsed())
+        );
+        let in_progress = state.started - state.finished.len();
+        if let Some(rows_per_frame) = self.rows_per_frame.filter(|_| in_progress > 0) {
+            // Rows of the frames in progress are the rows computed beyond
+            // those of the finished frames.
+            let rows = ROWS_COMPUTED.load(Ordering::Relaxed) - self.rows_at_start;
+            let partial = rows.saturating_sub(state.finished.len() as u64 * rows_per_frame);
+            let total = in_progress as u64 * rows_per_frame;
+            status.push_str(&format!(", rows {}%", (100 * partial / total).min(99)));
+        }
+        if let Some(rate) = rate(state) {
+            status.push_str(&format!(", {:.1} frames/min", rate));
+        }
+        if let Some(eta) = self.eta(state) {
+            status.push_str(&format!(", ETA {}", format_duration(eta)));
+        }
+        status
+    }
+
+    /// The time until the frames left are done.
+    ///
+    /// Frames get slower as the zoom deepens and max_iter grows, so the
+    /// render time is fit as a line over the frame number and the frames
+    /// left are predicted from it, rather than taken to cost as much as
+    /// the average so far. Frames render in parallel, so the prediction is
+    /// divided by how many seconds of frames have been rendered per second.
+    fn eta(&self, state: &State) -> Option<Duration> {
+        let elapsed = self.start.elapsed().as_secs_f64();
+        if state.finished.is_empty() || elapsed <= 0.0 {
+            return None;
+        }
+        let n = state.finished.len() as f64;
+        let mean_frame = state.finished.iter().map(|&(frame, _)| frame as f64).sum::<f64>() / n;
+        let mean_seconds = state.finished.iter().map(|&(_, seconds)| seconds).sum::<f64>() / n;
+        let (mut covariance, mut variance) = (0.0, 0.0);
+        for &(frame, seconds) in &state.finished {
+            covariance += (frame as f64 - mean_frame) * (seconds - mean_seconds);
+            variance += (frame as f64 - mean_frame).powi(2);
+        }
+        let slope = if variance > 0.0 { covariance / variance } else { 0.0 };
+        // No frame is predicted faster than the fastest one so far.
+        let fastest = state.finished.iter().map(|&(_, seconds)| seconds).fold(f64::MAX, f64::min);
+        let predict =
+            |frame: u32| (mean_seconds + slope * (frame as f64 - mean_frame)).max(fastest);
+
+        let finished: HashSet<u32> = state.finished.iter().map(|&(frame, _)| frame).collect();
+        let left: f64 = self
+            .frames
+            .iter()
+            .filter(|frame| !finished.contains(frame))
+            .map(|&frame| predict(frame))
+            .sum();
+        let parallelism = (n * mean_seconds / elapsed).max(f64::MIN_POSITIVE);
+        Some(Duration::from_secs_f64(left / parallelism))
+    }
+}
+
+/// The frames per minute over the latest frames, once two have finished.
+fn rate(state: &State) -> Option<f64> {
+    let [first, .., last] = state.recent[..] else {
+        return None;
+    };
+    let minutes = (last - first).as_secs_f64() / 60.0;
+    (minutes > 0.0).then(|| (state.recent.len() - 1) as f64 / minutes)
+}
+
+/// `duration` to the second, as `1h02m`, `4m05s` or `12s`.
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match (seconds / 3600, seconds / 60 % 60, seconds % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m{:02}s", m, s),
+        (h, m, _) => format!("{}h{:02}m", h, m),
+    }
+}
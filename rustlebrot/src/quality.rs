@@ -0,0 +1,153 @@
+use crate::render::BitDepth;
+
+/// How finely a zoom is rendered, as chosen with `--quality`: the size of
+/// the frames, their anti-aliasing, the iteration limit and the depth and
+/// quality of the files, in one choice.
+///
+/// Qualities only fill in what the command line leaves out, so flags given
+/// as well take precedence, each over the one setting it's for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quality {
+    pub name: &'static str,
+    /// The size of the frames, as a share of the size without `--width`
+    /// and `--height`.
+    pub scale: f64,
+    /// Samples per side of a pixel.
+    pub supersample: u32,
+    /// Whether the samples past the first are only taken where neighboring
+    /// pixels differ.
+    pub adaptive: bool,
+    /// The iteration limit of a run that neither gives one nor zooms into
+    /// a place that does.
+    pub max_iter: u32,
+    /// Whether `max_iter` also caps the limit of a place zoomed into.
+    pub caps_max_iter: bool,
+    /// The quality of JPEG frames.
+    pub jpeg_quality: u8,
+    pub bit_depth: BitDepth,
+}
+
+/// Every quality, from the fastest to the finest.
+pub const QUALITIES: &[Quality] = &[
+    Quality {
+        name: "draft",
+        scale: 0.25,
+        supersample: 1,
+        adaptive: false,
+        max_iter: 500,
+        caps_max_iter: true,
+        jpeg_quality: 75,
+        bit_depth: BitDepth::Eight,
+    },
+    Quality {
+        name: "preview",
+        scale: 0.5,
+        supersample: 1,
+        adaptive: false,
+        max_iter: 2_000,
+        caps_max_iter: true,
+        jpeg_quality: 85,
+        bit_depth: BitDepth::Eight,
+    },
+    Quality {
+        name: "standard",
+        scale: 1.0,
+        supersample: 2,
+        adaptive: false,
+        max_iter: 5_000,
+        caps_max_iter: false,
+        jpeg_quality: 90,
+        bit_depth: BitDepth::Eight,
+    },
+    Quality {
+        name: "high",
+        scale: 1.0,
+        supersample: 3,
+        adaptive: true,
+        max_iter: 20_000,
+        caps_max_iter: false,
+        jpeg_quality: 95,
+        bit_depth: BitDepth::Sixteen,
+    },
+    Quality {
+        name: "insane",
+        scale: 2.0,
+        supersample: 4,
+        adaptive: true,
+        max_iter: 100_000,
+        caps_max_iter: false,
+        jpeg_quality: 100,
+        bit_depth: BitDepth::Sixteen,
+    },
+];
+
+impl Quality {
+    pub fn from_name(name: &str) -> Option<&'static Quality> {
+        QUALITIES.iter().find(|quality| quality.name == name)
+    }
+
+    /// The settings this quality gives, over `built_in`, the ones without
+    /// flags or a quality.
+    pub fn settings(&self, built_in: &Settings) -> Settings {
+        let scaled = |size: Option<u32>| size.map(|size| (size as f64 * self.scale).round() as u32);
+        Settings {
+            width: scaled(built_in.width),
+            height: scaled(built_in.height),
+            supersample: Some(self.supersample),
+            adaptive: Some(self.adaptive),
+            jpeg_quality: Some(self.jpeg_quality),
+            bit_depth: Some(self.bit_depth),
+        }
+    }
+
+    /// The iteration limit of a run that doesn't give one, out of `place`,
+    /// the limit of the preset or location zoomed into, if any.
+    pub fn max_iter(&self, place: Option<u32>) -> u32 {
+        match place {
+            Some(limit) if self.caps_max_iter => limit.min(self.max_iter),
+            Some(limit) => limit,
+            None => self.max_iter,
+        }
+    }
+}
+
+/// Settings a quality gives defaults for, as one layer of defaults and
+/// flags has them, with `None` for those it leaves to the layers under it.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Settings {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub supersample: Option<u32>,
+    pub adaptive: Option<bool>,
+    pub jpeg_quality: Option<u8>,
+    pub bit_depth: Option<BitDepth>,
+}
+
+impl Settings {
+    /// These settings, with those of `under` where they leave some out.
+    ///
+    /// Supersampling and adaptive anti-aliasing go together, so a layer
+    /// that gives either one doesn't take the other from under it: the
+    /// samples of `--supersample 2` aren't made adaptive by a quality.
+    pub fn over(self, under: Settings) -> Settings {
+        let anti_aliasing = match self.supersample.is_some() || self.adaptive.is_some() {
+            true => self,
+            false => under,
+        };
+        Settings {
+            width: self.width.or(under.width),
+            height: self.height.or(under.height),
+            supersample: anti_aliasing.supersample,
+            adaptive: anti_aliasing.adaptive,
+            jpeg_quality: self.jpeg_quality.or(under.jpeg_quality),
+            bit_depth: self.bit_depth.or(under.bit_depth),
+        }
+    }
+}
+
+/// The settings of a run from its layers, the flags given over the defaults
+/// of `quality` over the built-in ones.
+pub fn resolve(flags: Settings, quality: Option<&Quality>, built_in: Settings) -> Settings {
+    let quality = quality.map(|quality| quality.settings(&built_in)).unwrap_or_default();
+    flags.over(quality).over(built_in)
+}
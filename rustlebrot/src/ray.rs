@@ -0,0 +1,445 @@
+use crate::bigfloat::{self, Big};
+use crate::render::Rotation;
+use image::{DynamicImage, GenericImage, Rgba};
+use std::f64::consts::TAU;
+use std::fmt;
+
+/// The radius rays are traced in from, far enough out that they're
+/// straight lines from the origin there.
+const ESCAPE_RADIUS: f64 = 65536.0;
+
+/// Points traced for every iteration a ray descends, each closer to the set
+/// by the same share of potential. Fewer points make Newton's method jump
+/// between rays where they run close together near the set.
+const SHARPNESS: u32 = 8;
+
+/// Newton steps taken for a point of a ray before giving up on it.
+const MAX_STEPS: usize = 64;
+
+/// How many times the step before a Newton step may move a point of a ray
+/// before the point is taken to have landed on another ray.
+const MAX_JUMP: f64 = 16.0;
+
+/// The share of the step between points below which Newton's method is
+/// taken to have settled.
+const SETTLED: f64 = 1.0 / (1u64 << 20) as f64;
+
+/// Steps between points at least this share of their distance from the
+/// origin are taken in f64, which tells them apart with bits to spare for
+/// the steps of Newton's method. Shorter steps are taken in `Big`.
+const F64_STEP: f64 = 1.0 / (1u64 << 30) as f64;
+
+/// Points of a ray traced for every pixel along it where it's drawn, so
+/// that what's left of it past the last point, which lands about as far
+/// again in as the last few steps went, stays within a pixel.
+pub const STEPS_PER_PIXEL: f64 = 16.0;
+
+/// Frame rows per pixel of the width of a ray, as the border of
+/// `--julia-inset` is.
+const ROWS_PER_WIDTH: u32 = 360;
+
+/// An external angle of the Mandelbrot set in turns, kept as a fraction so
+/// doubling it, as the iterations of the rays do, stays exact.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExternalAngle {
+    numerator: u64,
+    /// Below 2^63, so twice the numerator fits.
+    denominator: u64,
+}
+
+impl ExternalAngle {
+    /// The angle of `numerator / denominator` turns, less whole turns.
+    pub fn new(numerator: u64, denominator: u64) -> Result<ExternalAngle, String> {
+        if denominator == 0 || denominator >= 1 << 63 {
+            return Err(format!(
+                "the denominator of an external angle should be from 1 to 2^63, got {}",
+                denominator
+            ));
+        }
+        let numerator = numerator % denominator;
+        let divisor = gcd(numerator, denominator);
+        Ok(ExternalAngle {
+            numerator: numerator / divisor,
+            denominator: denominator / divisor,
+        })
+    }
+
+    /// The angle of `spec`, a fraction of a turn such as `1/3`, or its
+    /// binary expansion such as `0.(01)` or `.0(01)`, where the bits in
+    /// parentheses repeat.
+    pub fn from_spec(spec: &str) -> Result<ExternalAngle, String> {
+        let spec = spec.trim();
+        if let Some((numerator, denominator)) = spec.split_once('/') {
+            let parse = |part: &str| part.trim().parse::<u64>().ok();
+            return match (parse(numerator), parse(denominator)) {
+                (Some(numerator), Some(denominator)) => ExternalAngle::new(numerator, denominator),
+                _ => Err(format!(
+                    "an external angle should be a fraction like 1/3 or binary like 0.(01), \
+                     got '{}'",
+                    spec
+                )),
+            };
+        }
+        let invalid = || {
+            format!(
+                "an external angle should be a fraction like 1/3 or binary like 0.(01), got '{}'",
+                spec
+            )
+        };
+        let bits = spec.strip_prefix('0').unwrap_or(spec).strip_prefix('.').ok_or_else(invalid)?;
+        let (fixed, repeating) = match bits.split_once('(') {
+            Some((fixed, rest)) => (fixed, rest.strip_suffix(')').ok_or_else(invalid)?),
+            None => (bits, ""),
+        };
+        let value = |bits: &str| {
+            bits.chars().try_fold(0u64, |value, bit| match bit {
+                '0' | '1' => Some((value << 1) | (bit == '1') as u64),
+                _ => None,
+            })
+        };
+        let (Some(prefix), Some(period)) = (value(fixed), value(repeating)) else {
+            return Err(invalid());
+        };
+        if fixed.len() + repeating.len() > 62 {
+            return Err(format!(
+                "an external angle can have at most 62 bits before it repeats, got '{}'",
+                spec
+            ));
+        }
+        // 0.a(b) is (a + b / (2^p - 1)) / 2^k for the k bits of a and the
+        // p of b, or a / 2^k where nothing repeats.
+        let cycle = match repeating.is_empty() {
+            true => 1,
+            false => (1u64 << repeating.len()) - 1,
+        };
+        let numerator = match repeating.is_empty() {
+            true => prefix,
+            false => prefix * cycle + period,
+        };
+        ExternalAngle::new(numerator, cycle << fixed.len())
+    }
+
+    /// The angle in turns, as far as f64 tells it.
+    pub fn turns(self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+
+    /// Twice the angle, less whole turns, which is the angle of the ray
+    /// through the square of a point outside the set.
+    pub fn doubled(self) -> ExternalAngle {
+        ExternalAngle::new(self.numerator * 2, self.denominator)
+            .expect("the denominator stays what it was")
+    }
+}
+
+impl fmt::Display for ExternalAngle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.numerator, self.denominator)
+    }
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    match b {
+        0 => a,
+        _ => gcd(b, a % b),
+    }
+}
+
+/// An external ray of the Mandelbrot set, traced in from `ESCAPE_RADIUS`
+/// toward where it lands on the set as far as it's been asked to.
+///
+/// The ray is the points whose `n`th iterate has the argument of the angle
+/// doubled `n - 1` times. Every point is found by Newton's method on the
+/// `n`th iterate from the point before, aiming at a radius a little closer
+/// to the set each time, and the iterations go up by one whenever the
+/// radius has come down to its square root, `SHARPNESS` points later. This
+/// is the method of Kawahira's "An algorithm to draw external rays of the
+/// Mandelbrot set".
+///
+/// The ray ends where Newton's method can't follow it: where it doesn't
+/// settle, or jumps away from the points before, which happens as the ray
+/// runs into the boundary farther than the precision of the points can
+/// tell. Without the `bigfloat` feature that's where f64 runs out.
+pub struct Ray {
+    pub angle: ExternalAngle,
+    /// The points traced so far, from the outside in.
+    points: Vec<(Big, Big)>,
+    /// How far apart the last two points are.
+    step: f64,
+    /// The iterations of the points being traced, the angle their last
+    /// iterate is aimed at, and how many of the points of those iterations
+    /// have been traced.
+    iterations: u32,
+    target: ExternalAngle,
+    traced: u32,
+    ended: bool,
+}
+
+impl Ray {
+    /// The ray of `angle`, traced as far as its first point.
+    pub fn new(angle: ExternalAngle) -> Ray {
+        let (sin, cos) = (TAU * angle.turns()).sin_cos();
+        let start = (ESCAPE_RADIUS * cos, ESCAPE_RADIUS * sin);
+        Ray {
+            angle,
+            points: vec![big(start)],
+            step: ESCAPE_RADIUS,
+            iterations: 1,
+            target: angle,
+            traced: 0,
+            ended: false,
+        }
+    }
+
+    /// The points of the ray traced so far, from the outside in.
+    pub fn points(&self) -> &[(Big, Big)] {
+        &self.points
+    }
+
+    /// Whether the ray ended where it couldn't be followed any closer to
+    /// the set.
+    pub fn ended(&self) -> bool {
+        self.ended
+    }
+
+    /// Traces the ray on until its points are closer together than
+    /// `resolution`, or its iterations reach `max_iter`, or it ends.
+    /// Tracing again for a finer resolution carries on from there.
+    pub fn extend(&mut self, resolution: f64, max_iter: u32) {
+        while !self.ended && self.step >= resolution {
+            if self.traced == SHARPNESS {
+                if self.iterations >= max_iter {
+                    return;
+                }
+                self.iterations += 1;
+                self.target = self.target.doubled();
+                self.traced = 0;
+            }
+            self.trace_point();
+        }
+    }
+
+    /// Traces the next point of the ray, or ends it.
+    fn trace_point(&mut self) {
+        let share = 0.5f64.powf((self.traced as f64 + 0.5) / SHARPNESS as f64);
+        let radius = ESCAPE_RADIUS.powf(share);
+        let (sin, cos) = (TAU * self.target.turns()).sin_cos();
+        let target = (radius * cos, radius * sin);
+        let last = self.points.last().expect("rays start with a point");
+        let approx = (last.0.to_f64().value(), last.1.to_f64().value());
+        let point = match self.step >= approx.0.hypot(approx.1) * F64_STEP {
+            true => newton_f64(approx, target, self.iterations, self.step).map(big),
+            false => {
+                let bits = bigfloat::required_bits(approx, self.step);
+                let last = (
+                    last.0.clone().with_precision(bits).value(),
+                    last.1.clone().with_precision(bits).value(),
+                );
+                // Without the bigfloat feature, there's no more precision
+                // to be had past f64.
+                match last.0.precision() >= bits {
+                    true => newton_big(&last, target, self.iterations, self.step),
+                    false => None,
+                }
+            }
+        };
+        let Some(point) = point else {
+            self.ended = true;
+            return;
+        };
+        let last = self.points.last().expect("rays start with a point");
+        let step = abs(&sub(&point, last));
+        if step.is_nan() || step > MAX_JUMP * self.step {
+            self.ended = true;
+            return;
+        }
+        self.step = step;
+        self.points.push(point);
+        self.traced += 1;
+    }
+}
+
+/// The point near `c` whose `iterations`th iterate is `target`, found by
+/// Newton's method in f64, or `None` if it doesn't settle on one within
+/// `MAX_JUMP` times `step` of `c`.
+fn newton_f64(c: (f64, f64), target: (f64, f64), iterations: u32, step: f64) -> Option<(f64, f64)> {
+    let mut point = c;
+    for _ in 0..MAX_STEPS {
+        let mut z = (0.0, 0.0);
+        let mut dz = (0.0, 0.0);
+        for _ in 0..iterations {
+            dz = (
+                2.0 * (z.0 * dz.0 - z.1 * dz.1) + 1.0,
+                2.0 * (z.0 * dz.1 + z.1 * dz.0),
+            );
+            z = (z.0 * z.0 - z.1 * z.1 + point.0, 2.0 * z.0 * z.1 + point.1);
+        }
+        let (x, y) = (z.0 - target.0, z.1 - target.1);
+        let norm = dz.0 * dz.0 + dz.1 * dz.1;
+        let delta = ((x * dz.0 + y * dz.1) / norm, (y * dz.0 - x * dz.1) / norm);
+        point = (point.0 - delta.0, point.1 - delta.1);
+        let moved = delta.0.hypot(delta.1);
+        let wandered = (point.0 - c.0).hypot(point.1 - c.1);
+        if wandered.is_nan() || wandered > MAX_JUMP * step {
+            return None;
+        }
+        if moved <= SETTLED * step {
+            return Some(point);
+        }
+    }
+    None
+}
+
+/// `newton_f64` in the precision of `c`.
+fn newton_big(c: &(Big, Big), target: (f64, f64), iterations: u32, step: f64) -> Option<(Big, Big)> {
+    let bits = c.0.precision();
+    let target = (bigfloat::from_f64(target.0, bits), bigfloat::from_f64(target.1, bits));
+    let mut point = c.clone();
+    for _ in 0..MAX_STEPS {
+        let mut z = (Big::ZERO, Big::ZERO);
+        let mut dz = (Big::ZERO, Big::ZERO);
+        for _ in 0..iterations {
+            let twice = mul(&z, &dz);
+            dz = ((twice.0 << 1) + Big::ONE, twice.1 << 1);
+            z = add(&sqr(&z), &point);
+        }
+        let delta = div(&sub(&z, &target), &dz);
+        point = sub(&point, &delta);
+        let moved = abs(&delta);
+        let wandered = abs(&sub(&point, c));
+        if wandered.is_nan() || wandered > MAX_JUMP * step {
+            return None;
+        }
+        if moved <= SETTLED * step {
+            return Some(point);
+        }
+    }
+    None
+}
+
+fn big(point: (f64, f64)) -> (Big, Big) {
+    let parts = (Big::try_from(point.0), Big::try_from(point.1));
+    (parts.0.expect("rays are finite"), parts.1.expect("rays are finite"))
+}
+
+fn add(a: &(Big, Big), b: &(Big, Big)) -> (Big, Big) {
+    (&a.0 + &b.0, &a.1 + &b.1)
+}
+
+fn sub(a: &(Big, Big), b: &(Big, Big)) -> (Big, Big) {
+    (&a.0 - &b.0, &a.1 - &b.1)
+}
+
+fn sqr(a: &(Big, Big)) -> (Big, Big) {
+    (a.0.sqr() - a.1.sqr(), (&a.0 * &a.1) << 1)
+}
+
+fn mul(a: &(Big, Big), b: &(Big, Big)) -> (Big, Big) {
+    (&a.0 * &b.0 - &a.1 * &b.1, &a.0 * &b.1 + &a.1 * &b.0)
+}
+
+fn div(a: &(Big, Big), b: &(Big, Big)) -> (Big, Big) {
+    let norm = b.0.sqr() + b.1.sqr();
+    let numerator = mul(a, &(b.0.clone(), -b.1.clone()));
+    (numerator.0 / &norm, numerator.1 / &norm)
+}
+
+/// The modulus of `a`, as far as f64 tells it.
+fn abs(a: &(Big, Big)) -> f64 {
+    a.0.to_f64().value().hypot(a.1.to_f64().value())
+}
+
+/// The part of the plane a frame shows, which rays are drawn on it in.
+pub struct View {
+    pub center: (Big, Big),
+    /// The widths of the ranges across and up the frame. The second is
+    /// negative where row 0 is at the bottom.
+    pub range_widths: (f64, f64),
+    pub rotation: Rotation,
+}
+
+impl View {
+    /// Where `points` are in an image of the view `size` pixels across and
+    /// down, in pixels from its top left corner.
+    pub fn pixels(&self, points: &[(Big, Big)], (width, height): (u32, u32)) -> Vec<(f64, f64)> {
+        let pixel = |point| {
+            let offset = sub(point, &self.center);
+            let offset = (offset.0.to_f64().value(), offset.1.to_f64().value());
+            let (dx, dy) = self.rotation.inverse().apply(offset);
+            (
+                width as f64 / 2.0 + dx / self.range_widths.0 * width as f64,
+                height as f64 / 2.0 - dy / self.range_widths.1 * height as f64,
+            )
+        };
+        points.iter().map(pixel).collect()
+    }
+}
+
+/// Draws a white line through `pixels` on `img`, in pixels from its top
+/// left corner, cut off at its edges.
+pub fn draw(img: &mut DynamicImage, pixels: &[(f64, f64)]) {
+    let size = (img.width(), img.height());
+    let width = (size.1 / ROWS_PER_WIDTH).max(1);
+    // The lines are drawn as squares of the width along them, so they're
+    // cut off as far out as a square can reach in.
+    let reach = width as f64;
+    let bounds = (-reach, -reach, size.0 as f64 + reach, size.1 as f64 + reach);
+    for segment in pixels.windows(2) {
+        // Points too far away for f64 to place leave nothing to draw.
+        let finite = |(x, y): (f64, f64)| x.is_finite() && y.is_finite();
+        if !finite(segment[0]) || !finite(segment[1]) {
+            continue;
+        }
+        let Some((from, to)) = clip(segment[0], segment[1], bounds) else {
+            continue;
+        };
+        let steps = (to.0 - from.0).abs().max((to.1 - from.1).abs()).ceil().max(1.0) as u32;
+        for step in 0..=steps {
+            let t = step as f64 / steps as f64;
+            let (x, y) = (from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t);
+            let (left, top) = ((x - width as f64 / 2.0).round(), (y - width as f64 / 2.0).round());
+            for dy in 0..width {
+                for dx in 0..width {
+                    let (x, y) = (left + dx as f64, top + dy as f64);
+                    if x >= 0.0 && y >= 0.0 && x < size.0 as f64 && y < size.1 as f64 {
+                        img.put_pixel(x as u32, y as u32, Rgba([255; 4]));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The part of the line from `from` to `to` inside `bounds`, as left, top,
+/// right and bottom, by the Liang-Barsky algorithm, or `None` if it's all
+/// outside.
+fn clip(
+    from: (f64, f64),
+    to: (f64, f64),
+    (left, top, right, bottom): (f64, f64, f64, f64),
+) -> Option<((f64, f64), (f64, f64))> {
+    let delta = (to.0 - from.0, to.1 - from.1);
+    let (mut enter, mut leave) = (0.0f64, 1.0f64);
+    let edges = [
+        (-delta.0, from.0 - left),
+        (delta.0, right - from.0),
+        (-delta.1, from.1 - top),
+        (delta.1, bottom - from.1),
+    ];
+    for (p, q) in edges {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else if p < 0.0 {
+            enter = enter.max(q / p);
+        } else {
+            leave = leave.min(q / p);
+        }
+    }
+    if enter > leave {
+        return None;
+    }
+    let at = |t: f64| (from.0 + delta.0 * t, from.1 + delta.1 * t);
+    Some((at(enter), at(leave)))
+}
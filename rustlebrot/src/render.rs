@@ -0,0 +1,2198 @@
+use crate::bigfloat::{self, Big};
+use crate::coloring::{Coloring, Phase, Transfer};
+use crate::contour::{Contours, Line};
+use crate::dd::DoubleDouble;
+use crate::debug::Timing;
+use crate::dither::Dither;
+use crate::expmap::{ExpMap, STRIP_RINGS};
+use crate::fractal::{Attractor, Escape, Fractal};
+use crate::interior::InteriorColoring;
+use crate::histogram::Histogram;
+use crate::lighting::{Lighting, Shade};
+use crate::lineart::LineArt;
+use crate::lyapunov::Lyapunov;
+use crate::newton::Newton;
+use crate::palette::{Colormap, Cycle};
+use crate::perturbation::ReferenceOrbit;
+use crate::script::{Failure, Inputs, Orbit, Output, Script};
+use crate::series::Series;
+use crate::stabilize::Reference;
+use crate::throttle;
+use image::{DynamicImage, GenericImage, ImageBuffer, Primitive};
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+/// Rows of pixels computed so far by the compute functions, in every frame
+/// together, for showing the progress within frames.
+pub static ROWS_COMPUTED: AtomicU64 = AtomicU64::new(0);
+
+/// Fraction of a pixel two orbit points have to come within to be counted
+/// as a cycle. Exterior points close to the boundary can wander slowly
+/// enough to look periodic at coarser thresholds.
+const PERIODICITY_FRACTION: f64 = 1.0 / 65536.0;
+
+/// Distance estimates are spread over the gradient on a log2 scale, with
+/// this many doublings of the distance spanning the full gradient.
+const DISTANCE_OCTAVES: f64 = 16.0;
+
+/// Trap distance spanning the full gradient.
+const TRAP_SCALE: f64 = 4.0;
+
+/// How bright the dark tone of binary decomposition is, relative to the
+/// color of the escape time.
+const BINARY_SHADE: f64 = 0.4;
+
+/// Iterations over which the basins of Newton's method darken by a factor
+/// of e, so points that take long to settle on a root draw the boundaries
+/// between the basins in dark.
+const ROOT_FADE: f64 = 16.0;
+
+/// How far from 0 a Lyapunov exponent takes its color most of the way, by
+/// a factor of e, along its half of the gradient.
+const EXPONENT_SCALE: f64 = 1.0;
+
+/// How far the last sample may move the color of a refined pixel, in any
+/// channel, for the pixel to count as converged: a level of 8-bit output.
+const CONVERGED: f64 = 1.0 / 256.0;
+
+/// Samples a refined pixel gets, its own included, before it can count as
+/// converged.
+const MIN_SAMPLES: usize = 4;
+
+/// Side of the square tiles `compute_subdivided` renders in parallel.
+const TILE: u32 = 64;
+
+/// Rectangles narrower or shorter than this are computed pixel by pixel.
+const MIN_SIDE: u32 = 6;
+
+/// Side of the squares of the checkerboard `fill_around` puts around a
+/// region, in pixels.
+const CHECKER: u32 = 8;
+
+/// The grays of the checkerboard, light and dark, which no palette is
+/// likely to be mistaken for.
+const CHECKER_GRAYS: [u8; 2] = [0x99, 0x66];
+
+/// How far the real axis may be from a row, or from halfway between two,
+/// for rows to be mirrored across it, in pixels. Mirrored rows are off by
+/// as much from where they'd be computed.
+const MIRROR_TOLERANCE: f64 = 1.0 / 64.0;
+
+/// Per-pixel settings of the compute pass, shared by all the render
+/// functions.
+#[derive(Clone, Copy)]
+pub struct RenderOptions<'a> {
+    /// The maximum number of iterations to determine if a point is in the
+    /// set.
+    pub max_iter: u32,
+    /// Whether to stop iterating orbits that are found to be periodic. The
+    /// detection threshold is a small fraction of a pixel, so it shrinks
+    /// along with the view.
+    pub periodicity: bool,
+    /// The radius past which orbits count as escaped.
+    pub bailout: f64,
+    /// What per-pixel value is computed to be mapped onto the gradient.
+    pub coloring: Coloring,
+    /// Whether `compute_escape` iterates escape times in f32 instead of
+    /// f64, see `Precision::F32`.
+    pub single_precision: bool,
+    /// Whether escape times are filled in from the borders of rectangles
+    /// instead of computed for every pixel.
+    pub subdivision: Subdivision,
+    /// How the frame is cut into the units of work of the threads.
+    pub work_units: WorkUnits,
+    /// The previous frame of a zoom, to take the samples of the pixels it
+    /// already covers from.
+    pub reuse: Option<Reuse<'a>>,
+    /// A pass of adaptive anti-aliasing. The buffer computed then only holds
+    /// the samples of the pixels it refines, in order.
+    pub refine: Option<Refine<'a>>,
+    /// How the sampling grid is turned about the center of the view.
+    pub rotation: Rotation,
+    /// The part of the frame that is computed, when it is rendered in
+    /// tiles or only a region of it is. The width and height the render
+    /// functions are given are then those of the part.
+    pub window: Option<Window>,
+    /// Where the time every pixel takes to compute is recorded, for
+    /// `--debug-channels time`.
+    pub timing: Option<&'a Timing>,
+    /// Whether the interior samples of `compute_escape` carry the cycle
+    /// their orbit settles on, for `--interior-coloring`. The cycles take
+    /// a loop of their own, which only runs for points that didn't escape.
+    pub attractors: bool,
+}
+
+/// A part of a larger frame, for rendering the frame in tiles, or only the
+/// region of `--roi`.
+///
+/// A render with a window only computes the pixels of the part, but places
+/// and sizes them as a render of the whole frame would, from the ranges of
+/// the whole frame. Neighboring tiles line up exactly, where ranges worked
+/// out for every tile would be off by their rounding.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Window {
+    /// The size of the whole frame, in pixels.
+    pub frame: (u32, u32),
+    /// The pixel of the frame at the top left of the part.
+    pub origin: (u32, u32),
+}
+
+/// The region of every frame `--roi` computes, in pixels of the frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Roi {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Whether the region is written in place in a frame of full size,
+    /// around it a checkerboard, rather than on its own.
+    pub fill: bool,
+}
+
+impl Roi {
+    /// The window the region is of a frame with `samples` along each side
+    /// of a pixel, which is `width` by `height` pixels.
+    pub fn window(&self, width: u32, height: u32, samples: u32) -> Window {
+        Window {
+            frame: (width * samples, height * samples),
+            origin: (self.x * samples, self.y * samples),
+        }
+    }
+}
+
+/// A turn of the sampling grid about the view center, counterclockwise in
+/// the plane.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rotation {
+    sin: f64,
+    cos: f64,
+}
+
+impl Rotation {
+    /// The axis-aligned grid.
+    pub const NONE: Rotation = Rotation { sin: 0.0, cos: 1.0 };
+
+    pub fn degrees(angle: f64) -> Rotation {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        Rotation { sin, cos }
+    }
+
+    pub fn is_none(self) -> bool {
+        self == Rotation::NONE
+    }
+
+    /// The turn the other way.
+    pub fn inverse(self) -> Rotation {
+        Rotation {
+            sin: -self.sin,
+            cos: self.cos,
+        }
+    }
+
+    /// `offset` turned about the origin. Without a turn, every offset stays
+    /// exactly as it is.
+    #[inline]
+    pub fn apply(self, offset: (f64, f64)) -> (f64, f64) {
+        (
+            offset.0 * self.cos - offset.1 * self.sin,
+            offset.0 * self.sin + offset.1 * self.cos,
+        )
+    }
+}
+
+/// Whether the compute pass fills in rectangles whose border escapes
+/// uniformly, as in the Mariani-Silver algorithm, see `compute_subdivided`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Subdivision {
+    Off,
+    On,
+    /// Same as `On`, with the pixels that were filled in drawn as boundary,
+    /// to see where iterations were saved.
+    Show,
+}
+
+/// How the compute pass cuts a frame into the units of work its threads
+/// take, as chosen with `--work-unit` and `--chunk-size`. A thread computes
+/// the pixels of a unit one after the other, or a batch of them at once
+/// where the fractal iterates several points in lanes.
+///
+/// The units don't change the samples: every pixel is computed the same
+/// way whichever unit it's in. Subdivision fills rectangles in from their
+/// borders, so which pixels it fills depends on where the units are cut,
+/// and it always works in tiles of `TILE` for that reason.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WorkUnits {
+    /// Square tiles sized by the iteration limit, see `WorkUnits::size`.
+    Auto,
+    /// Bands of this many whole rows.
+    Rows(u32),
+    /// Square tiles this many pixels on a side.
+    Tiles(u32),
+}
+
+impl WorkUnits {
+    /// The width and height of the units of a frame `width` pixels wide,
+    /// iterated up to `max_iter` times.
+    ///
+    /// Automatic tiles shrink as the iteration limit grows: the more a
+    /// pixel can cost, the more a tile that hits the interior can hold up
+    /// the last thread, and the less the overhead of handing out another
+    /// unit counts.
+    pub fn size(self, width: u32, max_iter: u32) -> (u32, u32) {
+        match self {
+            WorkUnits::Auto => {
+                let side = match max_iter {
+                    0..=2_000 => 64,
+                    2_001..=20_000 => 32,
+                    _ => 16,
+                };
+                (side, side)
+            }
+            WorkUnits::Rows(rows) => (width.max(1), rows),
+            WorkUnits::Tiles(side) => (side, side),
+        }
+    }
+}
+
+/// Settings of `--incremental`, which renders a zoom by taking what it can
+/// of every frame from the one before.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Incremental {
+    /// How far, in pixels of the previous frame, a pixel may land from one
+    /// of its samples to take its value.
+    pub threshold: f64,
+    /// Every this many frames, the whole frame is computed, so errors don't
+    /// pile up over the frames.
+    pub keyframe_every: u32,
+}
+
+/// How far the view of a frame of a pan moved from the previous one, in
+/// whole samples, so every sample the two share is at the same point.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Shift {
+    /// How many samples the view moved right, and down the rows.
+    pub x: i64,
+    pub y: i64,
+    /// The samples of the previous frame along the edges the view moved
+    /// toward that are computed again, see `camera::Pan::refresh`.
+    pub refresh: u32,
+}
+
+/// A previous frame of a zoom that samples can be taken from.
+#[derive(Clone, Copy)]
+pub struct Reuse<'a> {
+    /// The previous frame, which has the same size in pixels, and the same
+    /// center unless the view was shifted.
+    pub previous: &'a EscapeBuffer,
+    /// The size of the view relative to the previous one.
+    pub scale: f64,
+    /// How the view is turned relative to the previous one.
+    pub rotation: Rotation,
+    /// See `Incremental::threshold`.
+    pub threshold: f64,
+    /// Whether the frame is of the same view as the previous one, as the
+    /// frames of `animate-iter` are, and `scale`, `rotation` and
+    /// `threshold` don't matter. At a limit at least as high, a sample that
+    /// escaped then escapes at the same iteration again, so every one is
+    /// taken whatever its neighbors.
+    pub raised_limit: bool,
+    /// How the view moved from the previous one, if it did along a pan, in
+    /// which case `scale`, `rotation` and `threshold` don't matter. Every
+    /// sample still in view is taken, and only those newly in view and
+    /// the band refreshed are computed.
+    pub shift: Option<Shift>,
+}
+
+impl Reuse<'_> {
+    /// The sample of the previous frame for pixel `(x, y)`, if the pixel
+    /// lands close to one whose neighbors all have the same sample.
+    ///
+    /// Whole escape times have to be the same, so with smooth coloring the
+    /// neighbors have to lie within a band. Interior samples are only taken
+    /// if the previous frame iterated as far as `max_iter`. A shifted view
+    /// lands every pixel on a sample, which is taken as it is.
+    fn sample(&self, x: u32, y: u32, max_iter: u32) -> Option<Sample> {
+        let previous = self.previous;
+        // A sample at the same point escapes at the same iteration at any
+        // limit it escaped within.
+        let same_point = |sample: Sample| match sample {
+            Sample::Value(_) | Sample::Phase { .. } if previous.max_iter <= max_iter => {
+                Some(sample)
+            }
+            Sample::Interior | Sample::Attractor { .. } if previous.max_iter == max_iter => {
+                Some(sample)
+            }
+            _ => None,
+        };
+        if self.raised_limit {
+            return same_point(previous.values[(y * previous.width + x) as usize]);
+        }
+        if let Some(shift) = self.shift {
+            let (from_x, from_y) = (x as i64 + shift.x, y as i64 + shift.y);
+            let band = shift.refresh as i64;
+            // The band is on the side the new samples come in from.
+            let kept = |moved: i64, size: u32| match moved.signum() {
+                1 => 0..size as i64 - band,
+                -1 => band..size as i64,
+                _ => 0..size as i64,
+            };
+            if !kept(shift.x, previous.width).contains(&from_x)
+                || !kept(shift.y, previous.height).contains(&from_y)
+            {
+                return None;
+            }
+            return same_point(previous.values[(from_y * previous.width as i64 + from_x) as usize]);
+        }
+        let (width, height) = (previous.width as f64, previous.height as f64);
+        // Both frames are centered on the same point. Rows run down the
+        // frame, so a counterclockwise turn in the plane is a clockwise one
+        // on the pixels.
+        let (from_x, from_y) = match self.rotation.is_none() {
+            true => (
+                x as f64 * self.scale + width / 2.0 * (1.0 - self.scale),
+                y as f64 * self.scale + height / 2.0 * (1.0 - self.scale),
+            ),
+            false => {
+                let offset = (x as f64 - width / 2.0, y as f64 - height / 2.0);
+                let (dx, dy) = self.rotation.inverse().apply(offset);
+                (width / 2.0 + dx * self.scale, height / 2.0 + dy * self.scale)
+            }
+        };
+        let (near_x, near_y) = (from_x.round(), from_y.round());
+        let inside = (1.0..width - 1.0).contains(&near_x) && (1.0..height - 1.0).contains(&near_y);
+        if !inside || (from_x - near_x).hypot(from_y - near_y) > self.threshold {
+            return None;
+        }
+        let (near_x, near_y) = (near_x as usize, near_y as usize);
+        let at = |x: usize, y: usize| previous.values[y * previous.width as usize + x];
+        let sample = at(near_x, near_y);
+        let band = |sample: Sample| match sample {
+            Sample::Value(value) | Sample::Phase { value, .. } => Some(Some(value.floor())),
+            Sample::Root { .. } | Sample::Exponent(_) => None,
+            Sample::Interior | Sample::Attractor { .. } if previous.max_iter >= max_iter => {
+                Some(None)
+            }
+            Sample::Interior | Sample::Attractor { .. } | Sample::Boundary => None,
+        };
+        let own = band(sample)?;
+        for y in near_y - 1..=near_y + 1 {
+            for x in near_x - 1..=near_x + 1 {
+                if band(at(x, y)) != Some(own) {
+                    return None;
+                }
+            }
+        }
+        Some(sample)
+    }
+}
+
+/// Settings of `--adaptive`, which anti-aliases only the pixels whose color
+/// stands out from a neighbor's, see `refine`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Adaptive {
+    /// How far apart the colors of two neighbors have to be in any channel,
+    /// from 0 to 1, for both to be refined.
+    pub threshold: f64,
+    /// Samples along each side of a refined pixel, at most. Every sample is
+    /// jittered within a cell of its own of that grid.
+    pub samples: u32,
+}
+
+/// One pass of adaptive anti-aliasing, which adds a sample to the pixels
+/// that haven't converged yet.
+#[derive(Clone, Copy)]
+pub struct Refine<'a> {
+    /// Whether each pixel of the frame, row by row, gets a sample.
+    pub pixels: &'a [bool],
+    pub width: u32,
+    /// The number of the pass, from 1 on, which picks the cell of the pixel
+    /// the sample is in.
+    pub pass: u32,
+    /// See `Adaptive::samples`.
+    pub cells: u32,
+}
+
+impl Refine<'_> {
+    fn includes(&self, x: u32, y: u32) -> bool {
+        self.pixels[y as usize * self.width as usize + x as usize]
+    }
+
+    /// Where pixel `(x, y)` is sampled in this pass, in pixels.
+    ///
+    /// Passes step through the cells 5 at a time, which is coprime with
+    /// every number of cells up to 4×4, so the first few spread over the
+    /// pixel. Cell 0 is where the pixel's own sample is.
+    fn position(&self, x: u32, y: u32) -> (f64, f64) {
+        let cell = self.pass * 5 % (self.cells * self.cells);
+        let index = y as u64 * self.width as u64 + x as u64;
+        let mut rng = SmallRng::seed_from_u64(index << 32 | self.pass as u64);
+        let cells = self.cells as f64;
+        (
+            x as f64 + ((cell % self.cells) as f64 + rng.gen::<f64>()) / cells,
+            y as f64 + ((cell / self.cells) as f64 + rng.gen::<f64>()) / cells,
+        )
+    }
+}
+
+/// Settings of the colorize pass, which turns an `EscapeBuffer` into an
+/// image.
+#[derive(Clone, Copy)]
+pub struct ColorOptions<'a> {
+    /// The escape time the gradient is spread over. Keeping this fixed
+    /// while max_iter grows between frames keeps the colors of the pixels
+    /// that escaped in both frames the same.
+    pub palette_iter: u32,
+    /// Percentage of pixels at either end of the escape time distribution
+    /// that histogram coloring clamps to the first and last color.
+    pub histogram_clip: f64,
+    /// How escape times are spread over the gradient up to `palette_iter`,
+    /// for the colorings that take a transfer.
+    pub transfer: Transfer,
+    /// The distribution histogram coloring spreads escape times by in
+    /// place of the buffer's own, as `--stabilize-colors` carries it from
+    /// frame to frame.
+    pub reference: Option<&'a Reference>,
+    /// The colors pixels are given, built once per run from the chosen
+    /// palette and adjustments.
+    pub colormap: &'a Colormap,
+    /// How positions wrap around the gradient.
+    pub cycle: Cycle,
+    /// The color of points that never escape.
+    pub interior: (u8, u8, u8),
+    pub bit_depth: BitDepth,
+    /// The pattern channels are rounded to `bit_depth` by.
+    pub dither: Dither,
+    /// Which pixels are transparent, if any are.
+    pub alpha: Alpha,
+    /// How phase coloring blends in the color of the angle.
+    pub phase: Phase,
+    /// The light that shades the slopes of the values, if any.
+    pub lighting: Option<Lighting>,
+    /// The script buffers computed for script coloring are colored by.
+    pub script: Option<Scripted<'a>>,
+    /// How the interior samples that found their cycle are colored. Without
+    /// it, they take the interior color as the others do.
+    pub interior_coloring: Option<InteriorColoring>,
+    /// The contour lines drawn over the colors, and the background they're
+    /// drawn on in place of the colors if they have one.
+    pub contours: Option<Contours>,
+    /// The line drawn around the outside of the set.
+    pub silhouette: Option<Line>,
+    /// The line art drawn in place of all the coloring above, if the frame
+    /// is drawn as such.
+    pub line_art: Option<LineArt>,
+}
+
+/// A script of `--color-expr` and what it's told about the frame it
+/// colors.
+#[derive(Clone, Copy)]
+pub struct Scripted<'a> {
+    pub script: &'a Script,
+    pub frame: u32,
+    pub magnification: f64,
+}
+
+/// Bits per channel of rendered frames.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BitDepth {
+    Eight,
+    /// Samples the gradient at full precision, which avoids the banding of
+    /// 8-bit output in smooth gradients.
+    Sixteen,
+}
+
+impl BitDepth {
+    pub fn from_name(name: &str) -> Option<BitDepth> {
+        match name {
+            "8" => Some(BitDepth::Eight),
+            "16" => Some(BitDepth::Sixteen),
+            _ => None,
+        }
+    }
+}
+
+/// Which pixels of a frame are transparent, for compositing it over
+/// something else. Frames with any have an alpha channel after the color
+/// channels, which the palette and its adjustments leave alone.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Alpha {
+    /// Opaque frames, without an alpha channel.
+    None,
+    /// The set is transparent.
+    Interior,
+    /// The set is transparent, and so are points that escape before the
+    /// value, fading in over the iteration after it.
+    Threshold(f64),
+    /// Only the lines of line art on transparent paper are opaque.
+    Lines,
+}
+
+impl RenderOptions<'_> {
+    /// Where pixel `(x, y)` is sampled, in pixels from the top left of the
+    /// frame.
+    #[inline]
+    fn position(&self, x: u32, y: u32) -> (f64, f64) {
+        let (x, y) = match self.refine {
+            Some(refine) => refine.position(x, y),
+            None => (x as f64, y as f64),
+        };
+        match self.window {
+            Some(window) => (x + window.origin.0 as f64, y + window.origin.1 as f64),
+            None => (x, y),
+        }
+    }
+
+    /// The size of the frame a render of `width` by `height` pixels is a
+    /// part of, which the ranges are spread over.
+    fn frame_size(&self, width: u32, height: u32) -> (u32, u32) {
+        self.window.map_or((width, height), |window| window.frame)
+    }
+
+    /// Runs `compute` for the pixels `pixels`, recording how long it took
+    /// against them if the time is recorded.
+    #[inline]
+    fn timed<T>(&self, pixels: &[(u32, u32)], compute: impl FnOnce() -> T) -> T {
+        let Some(timing) = self.timing else {
+            return compute();
+        };
+        let start = Instant::now();
+        let computed = compute();
+        timing.record(pixels, start.elapsed());
+        computed
+    }
+
+    /// The sample of a pixel whose escape time was computed as `escape`:
+    /// its smooth escape time with smooth coloring, along with the angle it
+    /// escaped at with phase and binary coloring, or its whole escape time
+    /// otherwise.
+    #[inline]
+    fn escape_sample(&self, escape: &Escape) -> Sample {
+        if escape.iterations >= self.max_iter as f64 {
+            return Sample::Interior;
+        }
+        match self.coloring {
+            Coloring::Smooth => Sample::Value(escape.smooth),
+            Coloring::Phase | Coloring::Binary(_) => Sample::Phase {
+                value: escape.smooth,
+                angle: escape.angle() as f32,
+            },
+            _ => Sample::Value(escape.iterations),
+        }
+    }
+}
+
+impl ColorOptions<'_> {
+    /// Maps the raw value of a sample computed for `coloring` onto the
+    /// gradient. `histogram` is only used with histogram coloring, and
+    /// only without a reference.
+    #[inline]
+    fn position(&self, coloring: Coloring, value: f64, histogram: Option<&Histogram>) -> f64 {
+        if let (Coloring::Histogram, Some(reference)) = (coloring, self.reference) {
+            return reference.position(value);
+        }
+        match (coloring, histogram) {
+            (Coloring::Histogram, Some(histogram)) => histogram.position(value),
+            (Coloring::Distance, _) => (value.log2() + 1.0) / DISTANCE_OCTAVES,
+            (Coloring::Trap(_), _) => value / TRAP_SCALE,
+            (Coloring::Stripes(_), _) => value,
+            _ => self.transfer.position(value, self.palette_iter as f64),
+        }
+    }
+}
+
+/// The raw result of the compute pass for one pixel, before it is mapped
+/// onto the gradient.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Sample {
+    /// The value the coloring mode maps onto the gradient: the escape time,
+    /// the distance estimate in pixels, the trap distance or the stripe
+    /// average.
+    Value(f64),
+    /// The smooth escape time and the angle of `z` as it escaped, in
+    /// radians, for phase and binary coloring. The angle is only a color, so it is
+    /// kept in f32, which keeps samples as small as the others.
+    Phase { value: f64, angle: f32 },
+    /// The root of `roots` Newton's method took the point to, and the
+    /// smooth number of iterations it took, for `--fractal newton`.
+    Root {
+        root: u16,
+        roots: u16,
+        iterations: f64,
+    },
+    /// The Lyapunov exponent of the point, for `--fractal lyapunov`.
+    Exponent(f64),
+    /// The point never escaped, as opposed to escaping on the last
+    /// iteration. Drawn in the interior color.
+    Interior,
+    /// The point never escaped, and its orbit settled on a cycle of
+    /// `period` with `derivative`, see `Attractor`. Drawn as
+    /// `--interior-coloring` says, or else in the interior color. The
+    /// derivative is only a brightness, so it is kept in f32.
+    Attractor { period: u32, derivative: f32 },
+    /// The point is too close to the set to resolve. Drawn as boundary
+    /// black.
+    Boundary,
+}
+
+impl Sample {
+    /// The sample of the point across the real axis from this one, for
+    /// fractals that are symmetric about it. Its orbit is the conjugate of
+    /// this one's, so it escapes at the opposite angle.
+    #[inline]
+    fn mirrored(self) -> Sample {
+        match self {
+            Sample::Phase { value, angle } => Sample::Phase {
+                value,
+                angle: -angle,
+            },
+            sample => sample,
+        }
+    }
+}
+
+/// The samples of a frame, row by row, as the compute pass left them.
+#[derive(Clone)]
+pub struct EscapeBuffer {
+    /// Samples per row. These are pixels unless the frame is supersampled.
+    pub width: u32,
+    pub height: u32,
+    /// Samples along each side of a pixel, which `colorize` averages into
+    /// the pixel. Distance estimates are still in samples.
+    pub samples: u32,
+    /// More samples of the pixels adaptive anti-aliasing refined, by index,
+    /// at jittered positions within them. `colorize` averages them in as
+    /// well. Only buffers with a sample per pixel are refined.
+    pub refined: HashMap<usize, Vec<Sample>>,
+    /// The iteration limit the samples were computed with.
+    pub max_iter: u32,
+    /// The coloring the values were computed for. This can differ from the
+    /// one asked for when a precision can't carry it out.
+    pub coloring: Coloring,
+    pub values: Vec<Sample>,
+    /// With script coloring, the orbit of every sample of `values`, whose
+    /// exterior samples are smooth escape times. Empty otherwise.
+    pub orbits: Vec<Orbit>,
+}
+
+/// Computes the escape buffer of a region of an escape-time fractal.
+///
+/// This is the compute pass of rendering: each sample corresponds to a
+/// point in the complex plane and holds the raw value the coloring mode is
+/// based on, such as the number of iterations it takes for the point to
+/// escape the set. Pass the result to `colorize` for an image.
+///
+/// # Arguments
+///
+/// * `fractal` - The fractal to render. The function is monomorphized per
+///   fractal so the per-pixel loop calls its escape time function directly.
+/// * `width` - The width of the image in pixels.
+/// * `height` - The height of the image in pixels.
+/// * `x_range` - A tuple representing the range of the x coordinates in the
+///   complex plane to be rendered.
+/// * `y_range` - A tuple representing the range of the y coordinates in the
+///   complex plane to be rendered. Row 0 is at `y_range.1` and the last row
+///   towards `y_range.0`, so with the smaller end first the imaginary axis
+///   points up the image, as it is usually drawn. Passing the range the
+///   other way around flips the image vertically. With `options.rotation`,
+///   the grid is turned about the middle of the ranges.
+/// * `options` - The iteration limit, periodicity checking and coloring
+///   mode, see `RenderOptions`.
+///
+/// # Returns
+///
+/// * The escape buffer of the region, for `colorize` or export.
+///
+/// # Examples
+///
+/// ```
+/// # use rustlebrot::coloring::{Coloring, Phase, Transfer};
+/// # use rustlebrot::dither::Dither;
+/// # use rustlebrot::fractal::Mandelbrot;
+/// # use rustlebrot::palette::{Adjust, Colormap, Cycle, Palette};
+/// # use rustlebrot::render::*;
+/// let width = 200;
+/// let height = 200;
+/// let x_range = (-2.0, 1.0);
+/// let y_range = (-1.5, 1.5);
+/// let options = RenderOptions {
+///     max_iter: 1000,
+///     periodicity: true,
+///     bailout: 2.0,
+///     coloring: Coloring::EscapeTime,
+///     single_precision: false,
+///     subdivision: Subdivision::Off,
+///     work_units: WorkUnits::Auto,
+///     reuse: None,
+///     refine: None,
+///     rotation: Rotation::NONE,
+///     window: None,
+///     timing: None,
+///     attractors: false,
+/// };
+/// let buffer = compute_escape(&Mandelbrot, width, height, x_range, y_range, &options);
+///
+/// let gradient = Palette::Sinebow.gradient();
+/// let colormap = Colormap::new(&gradient, &Adjust::default());
+/// let colors = ColorOptions {
+///     palette_iter: 1000,
+///     histogram_clip: 0.0,
+///     transfer: Transfer::Linear,
+///     reference: None,
+///     colormap: &colormap,
+///     cycle: Cycle::new(&gradient, 4.0, 0.0, false),
+///     interior: (0, 0, 0),
+///     bit_depth: BitDepth::Eight,
+///     dither: Dither::None,
+///     alpha: Alpha::None,
+///     phase: Phase::default(),
+///     lighting: None,
+///     script: None,
+///     interior_coloring: None,
+///     contours: None,
+///     silhouette: None,
+///     line_art: None,
+/// };
+/// let img = colorize(&buffer, &colors);
+/// ```
+pub fn compute_escape<F: Fractal>(
+    fractal: &F,
+    width: u32,
+    height: u32,
+    x_range: (f64, f64),
+    y_range: (f64, f64),
+    options: &RenderOptions,
+) -> EscapeBuffer {
+    let (max_iter, bailout) = (options.max_iter, options.bailout);
+    let (frame_width, frame_height) = options.frame_size(width, height);
+    let scalex: f64 = (x_range.1 - x_range.0) / frame_width as f64;
+    let scaley: f64 = (y_range.1 - y_range.0) / frame_height as f64;
+    let pixel_size = scalex.abs().min(scaley.abs());
+    let periodicity = options.periodicity.then_some(pixel_size * PERIODICITY_FRACTION);
+
+    let middle = ((x_range.0 + x_range.1) / 2.0, (y_range.0 + y_range.1) / 2.0);
+    let half = ((x_range.1 - x_range.0) / 2.0, (y_range.1 - y_range.0) / 2.0);
+    let point = |x: u32, y: u32| {
+        let (x, y) = options.position(x, y);
+        if options.rotation.is_none() {
+            return (x * scalex + x_range.0, y_range.1 - y * scaley);
+        }
+        let (dx, dy) = options.rotation.apply((x * scalex - half.0, half.1 - y * scaley));
+        (middle.0 + dx, middle.1 + dy)
+    };
+    if let Coloring::Script(needs) = options.coloring {
+        let (values, orbits) = compute_orbits(width, 0..height, options, |x, y| {
+            let c = point(x, y);
+            let trap = needs.trap.as_ref();
+            let escape = fractal.escape_time(c, max_iter, bailout, periodicity, trap);
+            let distance = match needs.distance {
+                true => fractal.distance(c, max_iter, bailout),
+                false => None,
+            };
+            script_sample(&escape, distance, pixel_size, max_iter)
+        });
+        return EscapeBuffer {
+            width,
+            height,
+            samples: 1,
+            refined: HashMap::new(),
+            max_iter,
+            coloring: options.coloring,
+            values,
+            orbits,
+        };
+    }
+    // Jittered samples don't mirror each other, and neither do the rows of
+    // a turned grid. Tiles are computed in full, so they don't depend on
+    // which rows the rest of the frame has.
+    let symmetric = options.refine.is_none()
+        && options.rotation.is_none()
+        && options.window.is_none()
+        && fractal.symmetric()
+        && match options.coloring {
+            Coloring::Trap(trap) => trap.symmetric(),
+            // The sine of the angle flips its sign in the conjugate orbit.
+            Coloring::Stripes(_) => false,
+            _ => true,
+        };
+    let mirror = symmetric.then(|| Mirror::of(y_range, height)).flatten();
+    let rows = mirror.as_ref().map_or(0..height, |mirror| mirror.computed.clone());
+    let samples = match options.coloring {
+        // Whole rows go to the fractal at once, so a vectorized loop has
+        // enough points to keep its lanes busy.
+        Coloring::EscapeTime
+        | Coloring::Smooth
+        | Coloring::Histogram
+        | Coloring::Phase
+        | Coloring::Binary(_) => {
+            compute_escapes(width, rows, options, |pixels| {
+                let points: Vec<(f64, f64)> = pixels.iter().map(|&(x, y)| point(x, y)).collect();
+                let mut escapes = vec![Escape::interior(max_iter, f64::INFINITY); points.len()];
+                match options.single_precision {
+                    true => fractal.escape_times_f32(
+                        &points,
+                        max_iter,
+                        bailout,
+                        periodicity,
+                        &mut escapes,
+                    ),
+                    false => {
+                        fractal.escape_times(&points, max_iter, bailout, periodicity, &mut escapes)
+                    }
+                }
+                let samples = escapes.iter().map(|escape| options.escape_sample(escape));
+                match options.attractors {
+                    true => samples
+                        .zip(&points)
+                        .map(|(sample, &c)| match sample {
+                            Sample::Interior => attractor_sample(fractal.attractor(c, max_iter)),
+                            sample => sample,
+                        })
+                        .collect(),
+                    false => samples.collect(),
+                }
+            })
+        }
+        Coloring::Distance => compute_samples(width, rows, options, |x, y| {
+            distance_sample(fractal.distance(point(x, y), max_iter, bailout), pixel_size)
+        }),
+        Coloring::Trap(trap) => compute_samples(width, rows, options, |x, y| {
+            let c = point(x, y);
+            let escape = fractal.escape_time(c, max_iter, bailout, periodicity, Some(&trap));
+            Sample::Value(escape.trap)
+        }),
+        Coloring::Stripes(density) => compute_samples(width, rows, options, |x, y| {
+            match fractal.stripes(point(x, y), max_iter, bailout, density) {
+                Some(average) => Sample::Value(average),
+                None => Sample::Interior,
+            }
+        }),
+        Coloring::Script(_) => unreachable!("script samples are computed with their orbits"),
+    };
+    let samples = match mirror {
+        Some(mirror) => mirror.unfold(width, height, samples),
+        None => samples,
+    };
+    EscapeBuffer {
+        width,
+        height,
+        samples: 1,
+        refined: HashMap::new(),
+        max_iter: options.max_iter,
+        coloring: options.coloring,
+        values: samples,
+        orbits: Vec::new(),
+    }
+}
+
+/// How the rows of a frame mirror each other across the real axis, for
+/// fractals that are symmetric about it.
+struct Mirror {
+    /// The rows that are computed, which take in one row of every pair.
+    computed: Range<u32>,
+    /// Every row `y` outside `computed` mirrors row `axis - y`.
+    axis: u32,
+}
+
+impl Mirror {
+    /// How the rows of a frame of `height` rows spanning `y_range` mirror,
+    /// or `None` if the real axis isn't on a row or halfway between two, or
+    /// no row has its mirror image in the frame.
+    ///
+    /// With a view that isn't centered on the axis, only the band of rows
+    /// on both sides of it mirrors, and the rest of the rows on the larger
+    /// side are computed as well.
+    fn of(y_range: (f64, f64), height: u32) -> Option<Mirror> {
+        let scaley = (y_range.1 - y_range.0) / height as f64;
+        // Rows y and axis - y are at opposite imaginary parts.
+        let exact = 2.0 * y_range.1 / scaley;
+        let axis = exact.round();
+        let aligned = (exact - axis).abs() <= MIRROR_TOLERANCE;
+        if !aligned || axis < 1.0 || axis > 2.0 * height as f64 - 3.0 {
+            return None;
+        }
+        let axis = axis as u32;
+        let computed = match axis < height {
+            true => axis.div_ceil(2)..height,
+            false => 0..axis / 2 + 1,
+        };
+        Some(Mirror { computed, axis })
+    }
+
+    /// The samples of the whole frame, from the `samples` of the rows
+    /// computed.
+    fn unfold(&self, width: u32, height: u32, samples: Vec<Sample>) -> Vec<Sample> {
+        let start = self.computed.start;
+        let width = width as usize;
+        let mut frame = Vec::with_capacity(width * height as usize);
+        for y in 0..height {
+            match self.computed.contains(&y) {
+                true => frame.extend_from_slice(&samples[(y - start) as usize * width..][..width]),
+                false => {
+                    let row = &samples[(self.axis - y - start) as usize * width..][..width];
+                    frame.extend(row.iter().map(|sample| sample.mirrored()));
+                }
+            }
+        }
+        let mirrored = height as usize - self.computed.len();
+        ROWS_COMPUTED.fetch_add(mirrored as u64, Ordering::Relaxed);
+        frame
+    }
+}
+
+/// Computes a region like `compute_escape`, but in arbitrary precision.
+///
+/// The view is given as a high-precision `center` plus the width of the
+/// x and y ranges, since at the depths this is used for the range ends
+/// themselves can't be told apart in f64. Each pixel's offset from the center
+/// is still small enough to compute in f64 and is added on exactly. Rows run
+/// from the center plus half of `range_width.1` at the top down, as with
+/// `compute_escape`, and a negative `range_width.1` flips the image. Offsets
+/// are turned by `options.rotation`.
+///
+/// Samples are always whole escape times, whatever `options.coloring` says,
+/// since smooth coloring, distance estimation and traps would have to be
+/// carried out in arbitrary precision as well.
+/// Histogram coloring only needs whole escape times and is kept.
+pub fn compute_escape_big<F: Fractal>(
+    fractal: &F,
+    width: u32,
+    height: u32,
+    center: &(Big, Big),
+    range_width: (f64, f64),
+    bits: usize,
+    options: &RenderOptions,
+) -> EscapeBuffer {
+    let options = RenderOptions {
+        coloring: match options.coloring {
+            Coloring::Histogram => Coloring::Histogram,
+            _ => Coloring::EscapeTime,
+        },
+        ..*options
+    };
+    let max_iter = options.max_iter;
+    let (frame_width, frame_height) = options.frame_size(width, height);
+    let scalex: f64 = range_width.0 / frame_width as f64;
+    let scaley: f64 = range_width.1 / frame_height as f64;
+
+    let sample = |x: u32, y: u32| {
+        let (x, y) = options.position(x, y);
+        let offset = (x * scalex - range_width.0 / 2.0, range_width.1 / 2.0 - y * scaley);
+        let (dx, dy) = options.rotation.apply(offset);
+
+        let c = (
+            &center.0 + bigfloat::from_f64(dx, bits),
+            &center.1 + bigfloat::from_f64(dy, bits),
+        );
+        let iterations = fractal.escape_time_big(&c, max_iter, options.bailout);
+        if iterations >= max_iter as f64 {
+            Sample::Interior
+        } else {
+            Sample::Value(iterations)
+        }
+    };
+    let samples = compute_escapes(width, 0..height, &options, |pixels| {
+        pixels.iter().map(|&(x, y)| sample(x, y)).collect()
+    });
+    EscapeBuffer {
+        width,
+        height,
+        samples: 1,
+        refined: HashMap::new(),
+        max_iter: options.max_iter,
+        coloring: options.coloring,
+        values: samples,
+        orbits: Vec::new(),
+    }
+}
+
+/// Computes a region like `compute_escape_big`, but in double-double, which
+/// resolves pixels down to about 1e-30 of the center at a fraction of the
+/// cost of arbitrary precision.
+///
+/// Only colorings of escape times are carried out in double-double; any
+/// other is computed as smooth coloring. Offsets are turned by
+/// `options.rotation`, and rows aren't mirrored.
+pub fn compute_escape_dd<F: Fractal>(
+    fractal: &F,
+    width: u32,
+    height: u32,
+    center: (DoubleDouble, DoubleDouble),
+    range_width: (f64, f64),
+    options: &RenderOptions,
+) -> EscapeBuffer {
+    let options = RenderOptions {
+        coloring: match options.coloring.uses_escape_times() {
+            true => options.coloring,
+            false => Coloring::Smooth,
+        },
+        ..*options
+    };
+    let (max_iter, bailout) = (options.max_iter, options.bailout);
+    let (frame_width, frame_height) = options.frame_size(width, height);
+    let scalex: f64 = range_width.0 / frame_width as f64;
+    let scaley: f64 = range_width.1 / frame_height as f64;
+    let pixel_size = scalex.abs().min(scaley.abs());
+    let periodicity = options.periodicity.then_some(pixel_size * PERIODICITY_FRACTION);
+
+    let sample = |x: u32, y: u32| {
+        let (x, y) = options.position(x, y);
+        let offset = (x * scalex - range_width.0 / 2.0, range_width.1 / 2.0 - y * scaley);
+        let (dx, dy) = options.rotation.apply(offset);
+        let c = (center.0 + DoubleDouble::from(dx), center.1 + DoubleDouble::from(dy));
+        options.escape_sample(&fractal.escape_time_dd(c, max_iter, bailout, periodicity))
+    };
+    let samples = compute_escapes(width, 0..height, &options, |pixels| {
+        pixels.iter().map(|&(x, y)| sample(x, y)).collect()
+    });
+    EscapeBuffer {
+        width,
+        height,
+        samples: 1,
+        refined: HashMap::new(),
+        max_iter,
+        coloring: options.coloring,
+        values: samples,
+        orbits: Vec::new(),
+    }
+}
+
+/// Computes the basins of Newton's method for `newton` over a region like
+/// `compute_escape`, with every sample the root its point converges to,
+/// or interior if it doesn't within `options.max_iter` iterations.
+///
+/// Only the iteration limit, rotation, window and refinement of `options`
+/// apply. The rows aren't mirrored: conjugate points reach conjugate roots,
+/// which are roots of their own.
+pub fn compute_basins(
+    newton: &Newton,
+    width: u32,
+    height: u32,
+    x_range: (f64, f64),
+    y_range: (f64, f64),
+    options: &RenderOptions,
+) -> EscapeBuffer {
+    let (frame_width, frame_height) = options.frame_size(width, height);
+    let scalex: f64 = (x_range.1 - x_range.0) / frame_width as f64;
+    let scaley: f64 = (y_range.1 - y_range.0) / frame_height as f64;
+    let middle = ((x_range.0 + x_range.1) / 2.0, (y_range.0 + y_range.1) / 2.0);
+    let half = ((x_range.1 - x_range.0) / 2.0, (y_range.1 - y_range.0) / 2.0);
+    let roots = newton.roots().len() as u16;
+    let samples = compute_samples(width, 0..height, options, |x, y| {
+        let (x, y) = options.position(x, y);
+        let (dx, dy) = options.rotation.apply((x * scalex - half.0, half.1 - y * scaley));
+        match newton.basin((middle.0 + dx, middle.1 + dy), options.max_iter) {
+            Some((root, iterations)) => Sample::Root {
+                root: root as u16,
+                roots,
+                iterations,
+            },
+            None => Sample::Interior,
+        }
+    });
+    EscapeBuffer {
+        width,
+        height,
+        samples: 1,
+        refined: HashMap::new(),
+        max_iter: options.max_iter,
+        coloring: options.coloring,
+        values: samples,
+        orbits: Vec::new(),
+    }
+}
+
+/// Computes the Lyapunov exponents of `lyapunov` over a region like
+/// `compute_escape`, with `a` along x and `b` along y, and every sample the
+/// exponent of its point, or interior if its orbit runs off to infinity.
+///
+/// Only the rotation, window and refinement of `options` apply; the
+/// iterations are the warmup and samples of `lyapunov`.
+pub fn compute_lyapunov(
+    lyapunov: &Lyapunov,
+    width: u32,
+    height: u32,
+    x_range: (f64, f64),
+    y_range: (f64, f64),
+    options: &RenderOptions,
+) -> EscapeBuffer {
+    let (frame_width, frame_height) = options.frame_size(width, height);
+    let scalex: f64 = (x_range.1 - x_range.0) / frame_width as f64;
+    let scaley: f64 = (y_range.1 - y_range.0) / frame_height as f64;
+    let middle = ((x_range.0 + x_range.1) / 2.0, (y_range.0 + y_range.1) / 2.0);
+    let half = ((x_range.1 - x_range.0) / 2.0, (y_range.1 - y_range.0) / 2.0);
+    let samples = compute_samples(width, 0..height, options, |x, y| {
+        let (x, y) = options.position(x, y);
+        let (dx, dy) = options.rotation.apply((x * scalex - half.0, half.1 - y * scaley));
+        match lyapunov.exponent(middle.0 + dx, middle.1 + dy) {
+            Some(exponent) => Sample::Exponent(exponent),
+            None => Sample::Interior,
+        }
+    });
+    EscapeBuffer {
+        width,
+        height,
+        samples: 1,
+        refined: HashMap::new(),
+        max_iter: options.max_iter,
+        coloring: options.coloring,
+        values: samples,
+        orbits: Vec::new(),
+    }
+}
+
+/// Computes strip `strip` of the exponential map `map`, a row of samples
+/// around every one of its rings, from the innermost down, for a colorize
+/// pass into the strip's image.
+///
+/// Only the escape time colorings can be computed this way, since distance
+/// estimates are relative to the spacing of the samples, which grows with
+/// the radius. Neighboring samples of a ring are as far apart as the ring
+/// is from the next, which periodicity checking goes by. Samples are always
+/// computed in f64, and `options.rotation` and `options.window` don't
+/// apply.
+pub fn compute_rings<F: Fractal>(
+    fractal: &F,
+    map: &ExpMap,
+    strip: i32,
+    options: &RenderOptions,
+) -> EscapeBuffer {
+    assert!(options.coloring.uses_escape_times(), "rings are computed for escape times");
+    let (max_iter, bailout) = (options.max_iter, options.bailout);
+    let first = strip as i64 * STRIP_RINGS as i64;
+    let samples = compute_rows(0..STRIP_RINGS, |row| {
+        let ring = (first + row as i64) as f64;
+        let spacing = map.radius(ring) * map.step();
+        let periodicity = options.periodicity.then_some(spacing * PERIODICITY_FRACTION);
+        let points: Vec<(f64, f64)> =
+            (0..map.angles).map(|angle| map.point(ring, angle as f64)).collect();
+        let mut escapes = vec![Escape::interior(max_iter, f64::INFINITY); points.len()];
+        fractal.escape_times(&points, max_iter, bailout, periodicity, &mut escapes);
+        escapes.iter().map(|escape| options.escape_sample(escape)).collect()
+    });
+    EscapeBuffer {
+        width: map.angles,
+        height: STRIP_RINGS,
+        samples: 1,
+        refined: HashMap::new(),
+        max_iter,
+        coloring: options.coloring,
+        values: samples,
+        orbits: Vec::new(),
+    }
+}
+
+/// Computes a region like `compute_escape`, using perturbation against a
+/// reference orbit of the view center.
+///
+/// Each pixel only carries its f64 offset from the center, so this keeps
+/// working long after the range ends become indistinguishable in f64, at
+/// close to f64 speed. With a `series`, every pixel starts from the
+/// approximated delta at `series.skip` instead of from the first iteration.
+/// Distance estimation, traps and stripes ignore the series, since they
+/// need every iteration of the orbit. Rows are laid out as with `compute_escape_big`.
+pub fn compute_escape_perturbed<F: Fractal>(
+    fractal: &F,
+    width: u32,
+    height: u32,
+    orbit: &ReferenceOrbit,
+    series: Option<&Series>,
+    range_width: (f64, f64),
+    options: &RenderOptions,
+) -> EscapeBuffer {
+    let (max_iter, bailout) = (options.max_iter, options.bailout);
+    let (frame_width, frame_height) = options.frame_size(width, height);
+    let scalex: f64 = range_width.0 / frame_width as f64;
+    let scaley: f64 = range_width.1 / frame_height as f64;
+    let pixel_size = scalex.abs().min(scaley.abs());
+
+    let delta = |x: u32, y: u32| {
+        let (x, y) = options.position(x, y);
+        let offset = (x * scalex - range_width.0 / 2.0, range_width.1 / 2.0 - y * scaley);
+        options.rotation.apply(offset)
+    };
+    if let Coloring::Script(needs) = options.coloring {
+        let (values, orbits) = compute_orbits(width, 0..height, options, |x, y| {
+            let dc = delta(x, y);
+            let trap = needs.trap.as_ref();
+            let escape =
+                fractal.escape_time_perturbed(&orbit.z, dc, None, max_iter, bailout, trap);
+            let distance = match needs.distance {
+                true => fractal.distance_perturbed(&orbit.z, dc, max_iter, bailout),
+                false => None,
+            };
+            script_sample(&escape, distance, pixel_size, max_iter)
+        });
+        return EscapeBuffer {
+            width,
+            height,
+            samples: 1,
+            refined: HashMap::new(),
+            max_iter,
+            coloring: options.coloring,
+            values,
+            orbits,
+        };
+    }
+    let sample = |x: u32, y: u32| {
+        let dc = delta(x, y);
+        match options.coloring {
+            Coloring::EscapeTime
+            | Coloring::Smooth
+            | Coloring::Histogram
+            | Coloring::Phase
+            | Coloring::Binary(_) => {
+                let escape =
+                    fractal.escape_time_perturbed(&orbit.z, dc, series, max_iter, bailout, None);
+                options.escape_sample(&escape)
+            }
+            Coloring::Distance => {
+                let distance = fractal.distance_perturbed(&orbit.z, dc, max_iter, bailout);
+                distance_sample(distance, pixel_size)
+            }
+            Coloring::Trap(trap) => {
+                let escape = fractal.escape_time_perturbed(
+                    &orbit.z,
+                    dc,
+                    None,
+                    max_iter,
+                    bailout,
+                    Some(&trap),
+                );
+                Sample::Value(escape.trap)
+            }
+            Coloring::Stripes(density) => {
+                match fractal.stripes_perturbed(&orbit.z, dc, max_iter, bailout, density) {
+                    Some(average) => Sample::Value(average),
+                    None => Sample::Interior,
+                }
+            }
+            Coloring::Script(_) => unreachable!("script samples are computed with their orbits"),
+        }
+    };
+    let samples = match options.coloring {
+        Coloring::EscapeTime
+        | Coloring::Smooth
+        | Coloring::Histogram
+        | Coloring::Phase
+        | Coloring::Binary(_) => {
+            compute_escapes(width, 0..height, options, |pixels| {
+                pixels.iter().map(|&(x, y)| sample(x, y)).collect()
+            })
+        }
+        Coloring::Distance | Coloring::Trap(_) | Coloring::Stripes(_) | Coloring::Script(_) => {
+            compute_samples(width, 0..height, options, sample)
+        }
+    };
+    EscapeBuffer {
+        width,
+        height,
+        samples: 1,
+        refined: HashMap::new(),
+        max_iter: options.max_iter,
+        coloring: options.coloring,
+        values: samples,
+        orbits: Vec::new(),
+    }
+}
+
+/// The sample of an interior pixel whose orbit settled on `attractor`, or
+/// plain interior if its cycle wasn't found.
+fn attractor_sample(attractor: Option<Attractor>) -> Sample {
+    match attractor {
+        Some(Attractor { period, derivative }) => Sample::Attractor {
+            period,
+            derivative: derivative as f32,
+        },
+        None => Sample::Interior,
+    }
+}
+
+/// The sample of a pixel with the distance estimate `distance`, in pixels.
+/// Points inside the set or within half a pixel of it are boundary.
+fn distance_sample(distance: Option<f64>, pixel_size: f64) -> Sample {
+    match distance {
+        Some(distance) if distance / pixel_size >= 0.5 => Sample::Value(distance / pixel_size),
+        _ => Sample::Boundary,
+    }
+}
+
+/// The sample of a pixel for script coloring, whose orbit escaped as
+/// `escape`, with its orbit, and the distance estimate `distance` if one
+/// was worked out.
+fn script_sample(
+    escape: &Escape,
+    distance: Option<f64>,
+    pixel_size: f64,
+    max_iter: u32,
+) -> (Sample, Orbit) {
+    if escape.iterations >= max_iter as f64 {
+        return (Sample::Interior, Orbit::default());
+    }
+    let orbit = Orbit {
+        iterations: escape.iterations,
+        z: escape.z,
+        distance: distance.map_or(0.0, |distance| distance / pixel_size),
+        trap: escape.trap,
+    };
+    (Sample::Value(escape.smooth), orbit)
+}
+
+/// The compute pass: runs `sample` for every pixel of `rows` in parallel,
+/// or only those a refinement pass includes, and collects the results row
+/// by row, counting every row in `ROWS_COMPUTED`.
+fn compute_samples<S>(
+    width: u32,
+    rows: Range<u32>,
+    options: &RenderOptions,
+    sample: S,
+) -> Vec<Sample>
+where
+    S: Fn(u32, u32) -> Sample + Sync,
+{
+    let size = options.work_units.size(width, options.max_iter);
+    compute_units(width, rows, size, options, |unit| {
+        unit.pixels.iter().map(|&(x, y)| options.timed(&[(x, y)], || sample(x, y))).collect()
+    })
+}
+
+/// Same as `compute_samples`, with `sample` giving the orbit of every
+/// pixel as well, for script coloring.
+fn compute_orbits<S>(
+    width: u32,
+    rows: Range<u32>,
+    options: &RenderOptions,
+    sample: S,
+) -> (Vec<Sample>, Vec<Orbit>)
+where
+    S: Fn(u32, u32) -> (Sample, Orbit) + Sync,
+{
+    let size = options.work_units.size(width, options.max_iter);
+    let samples = compute_units(width, rows, size, options, |unit| {
+        unit.pixels.iter().map(|&(x, y)| options.timed(&[(x, y)], || sample(x, y))).collect()
+    });
+    samples.into_iter().unzip()
+}
+
+/// The compute pass of escape times, with `batch` computing the samples of
+/// a list of pixels at once. Pixels are sent a unit of work at a time, or
+/// with subdivision, a border or rectangle at a time.
+fn compute_escapes<B>(
+    width: u32,
+    rows: Range<u32>,
+    options: &RenderOptions,
+    batch: B,
+) -> Vec<Sample>
+where
+    B: Fn(&[(u32, u32)]) -> Vec<Sample> + Sync,
+{
+    let batch = |pixels: &[(u32, u32)]| options.timed(pixels, || batch(pixels));
+    // Fractional escape times are never equal along a border, so smooth
+    // coloring and the colorings with angles can only fill in the interior.
+    let fill = |sample: Sample| match options.coloring {
+        Coloring::Smooth | Coloring::Phase | Coloring::Binary(_) => sample == Sample::Interior,
+        _ => true,
+    };
+    let size = options.work_units.size(width, options.max_iter);
+    // The units only hold the pixels the pass refines.
+    if options.refine.is_some() {
+        return compute_units(width, rows, size, options, |unit| batch(&unit.pixels));
+    }
+    // Deep frames can be computed for another coloring than the previous.
+    let reuse = options.reuse.filter(|reuse| reuse.previous.coloring == options.coloring);
+    if let Some(reuse) = reuse {
+        // Only the pixels that can't be taken from the previous frame are
+        // computed, a unit of them at a time.
+        return compute_units(width, rows, size, options, |unit| {
+            let reused: Vec<Option<Sample>> =
+                unit.pixels.iter().map(|&(x, y)| reuse.sample(x, y, options.max_iter)).collect();
+            let missing: Vec<(u32, u32)> = unit
+                .pixels
+                .iter()
+                .zip(&reused)
+                .filter(|(_, sample)| sample.is_none())
+                .map(|(&pixel, _)| pixel)
+                .collect();
+            let mut computed = batch(&missing).into_iter();
+            reused
+                .into_iter()
+                .map(|sample| {
+                    sample.unwrap_or_else(|| computed.next().expect("a sample per pixel"))
+                })
+                .collect()
+        });
+    }
+    match options.subdivision {
+        Subdivision::Off => compute_units(width, rows, size, options, |unit| batch(&unit.pixels)),
+        Subdivision::On => compute_subdivided(width, rows, options, fill, false, batch),
+        Subdivision::Show => compute_subdivided(width, rows, options, fill, true, batch),
+    }
+}
+
+/// The compute pass by rectangle subdivision.
+///
+/// The image is cut into tiles of `TILE` rendered in parallel, whatever
+/// `options.work_units` says, so the pixels filled in don't change with
+/// them. Within a tile, the
+/// border of a rectangle is computed first, and if every pixel on it has
+/// the same sample and `fill` allows it, the inside is filled in with that
+/// sample without iterating it. The Mandelbrot set and its escape time
+/// bands are connected, so a uniform border rarely hides anything within.
+/// Otherwise the rectangle is split into quadrants sharing their middle
+/// lines, down to rectangles small enough to compute pixel by pixel. With
+/// `show`, filled in pixels are made boundary.
+fn compute_subdivided<F, B>(
+    width: u32,
+    rows: Range<u32>,
+    options: &RenderOptions,
+    fill: F,
+    show: bool,
+    batch: B,
+) -> Vec<Sample>
+where
+    F: Fn(Sample) -> bool + Sync,
+    B: Fn(&[(u32, u32)]) -> Vec<Sample> + Sync,
+{
+    let subdivider = Subdivider {
+        fill,
+        show,
+        batch,
+    };
+    compute_units(width, rows, (TILE, TILE), options, |unit| {
+        let mut tile = Tile {
+            origin: unit.origin,
+            width: unit.width,
+            samples: vec![None; unit.pixels.len()],
+        };
+        subdivider.subdivide(&mut tile, (0, 0, unit.width - 1, unit.height - 1));
+        tile.samples.into_iter().map(|sample| sample.expect("every pixel is computed")).collect()
+    })
+}
+
+/// The samples of one tile of a subdivided frame, `None` where they aren't
+/// known yet.
+struct Tile {
+    /// The pixel of the frame at the top left corner.
+    origin: (u32, u32),
+    width: u32,
+    samples: Vec<Option<Sample>>,
+}
+
+impl Tile {
+    fn get(&self, (x, y): (u32, u32)) -> Option<Sample> {
+        self.samples[y as usize * self.width as usize + x as usize]
+    }
+
+    fn set(&mut self, (x, y): (u32, u32), sample: Sample) {
+        self.samples[y as usize * self.width as usize + x as usize] = Some(sample);
+    }
+}
+
+struct Subdivider<F, B> {
+    fill: F,
+    show: bool,
+    batch: B,
+}
+
+impl<F, B> Subdivider<F, B>
+where
+    F: Fn(Sample) -> bool,
+    B: Fn(&[(u32, u32)]) -> Vec<Sample>,
+{
+    /// Computes the pixels of `tile` from `(left, top)` to `(right, bottom)`
+    /// inclusive.
+    fn subdivide(&self, tile: &mut Tile, (left, top, right, bottom): (u32, u32, u32, u32)) {
+        if right - left < MIN_SIDE || bottom - top < MIN_SIDE {
+            let pixels = (top..=bottom).flat_map(|y| (left..=right).map(move |x| (x, y)));
+            self.compute(tile, pixels.collect());
+            return;
+        }
+        let border: Vec<(u32, u32)> = (left..=right)
+            .flat_map(|x| [(x, top), (x, bottom)])
+            .chain((top + 1..bottom).flat_map(|y| [(left, y), (right, y)]))
+            .collect();
+        self.compute(tile, border.clone());
+
+        let first = tile.get((left, top)).expect("the border is computed");
+        if (self.fill)(first) && border.iter().all(|&pixel| tile.get(pixel) == Some(first)) {
+            let sample = if self.show { Sample::Boundary } else { first };
+            for y in top + 1..bottom {
+                for x in left + 1..right {
+                    tile.set((x, y), sample);
+                }
+            }
+            return;
+        }
+        let (middle_x, middle_y) = ((left + right) / 2, (top + bottom) / 2);
+        self.subdivide(tile, (left, top, middle_x, middle_y));
+        self.subdivide(tile, (middle_x, top, right, middle_y));
+        self.subdivide(tile, (left, middle_y, middle_x, bottom));
+        self.subdivide(tile, (middle_x, middle_y, right, bottom));
+    }
+
+    /// Computes the pixels of `tile` among `pixels` that aren't known yet.
+    fn compute(&self, tile: &mut Tile, mut pixels: Vec<(u32, u32)>) {
+        pixels.retain(|&pixel| tile.get(pixel).is_none());
+        let (x0, y0) = tile.origin;
+        let frame: Vec<(u32, u32)> = pixels.iter().map(|&(x, y)| (x0 + x, y0 + y)).collect();
+        for (pixel, sample) in pixels.into_iter().zip((self.batch)(&frame)) {
+            tile.set(pixel, sample);
+        }
+    }
+}
+
+/// A unit of work of the compute pass, see `WorkUnits`.
+struct Unit {
+    /// The pixel of the frame at the top left corner.
+    origin: (u32, u32),
+    width: u32,
+    height: u32,
+    /// The pixels of the unit to compute, row by row: every one of them, or
+    /// those a refinement pass includes.
+    pixels: Vec<(u32, u32)>,
+}
+
+/// Cuts `rows` of a frame `width` pixels wide into units of `size`, the
+/// last across and down cut short, and runs `unit` on them in parallel for
+/// a result for each of their pixels in order. The results are collected
+/// row by row across the frame, counting every band of rows in
+/// `ROWS_COMPUTED` once its units are done.
+fn compute_units<T, U>(
+    width: u32,
+    rows: Range<u32>,
+    (unit_width, unit_height): (u32, u32),
+    options: &RenderOptions,
+    unit: U,
+) -> Vec<T>
+where
+    T: Send,
+    U: Fn(&Unit) -> Vec<T> + Sync,
+{
+    let (unit_width, unit_height) = (unit_width.max(1), unit_height.max(1));
+    (0..(rows.len() as u32).div_ceil(unit_height))
+        .into_par_iter()
+        .flat_map_iter(|band| {
+            let top = rows.start + band * unit_height;
+            let height = unit_height.min(rows.end - top);
+            let units: Vec<(Unit, Vec<T>)> = (0..width.div_ceil(unit_width))
+                .into_par_iter()
+                .map(|column| {
+                    let left = column * unit_width;
+                    let width = unit_width.min(width - left);
+                    let pixels = (top..top + height)
+                        .flat_map(|y| (left..left + width).map(move |x| (x, y)))
+                        .filter(|&(x, y)| options.refine.is_none_or(|refine| refine.includes(x, y)))
+                        .collect();
+                    let work = Unit {
+                        origin: (left, top),
+                        width,
+                        height,
+                        pixels,
+                    };
+                    let results = throttle::paced(|| unit(&work));
+                    (work, results)
+                })
+                .collect();
+            ROWS_COMPUTED.fetch_add(height as u64, Ordering::Relaxed);
+            // Every row of the band runs across all of the units.
+            let mut units: Vec<_> = units
+                .into_iter()
+                .map(|(work, results)| work.pixels.into_iter().zip(results).peekable())
+                .collect();
+            let mut band = Vec::new();
+            for y in top..top + height {
+                for unit in &mut units {
+                    while let Some((_, result)) = unit.next_if(|((_, row), _)| *row == y) {
+                        band.push(result);
+                    }
+                }
+            }
+            band
+        })
+        .collect()
+}
+
+/// Computes the samples of `rows` in parallel with `row` computing those
+/// of a whole row at once, for samples that aren't pixels of a frame.
+fn compute_rows<T, R>(rows: Range<u32>, row: R) -> Vec<T>
+where
+    T: Send,
+    R: Fn(u32) -> Vec<T> + Sync,
+{
+    rows.into_par_iter()
+        .flat_map_iter(|y| {
+            let row = throttle::paced(|| row(y));
+            ROWS_COMPUTED.fetch_add(1, Ordering::Relaxed);
+            row
+        })
+        .collect()
+}
+
+/// The colorize pass: maps the samples of `buffer` onto the gradient
+/// according to the coloring they were computed for, into an image with 8
+/// or 16 bits per channel as `colors.bit_depth` says.
+///
+/// Histogram coloring looks at the whole frame first, building the
+/// distribution of the exterior escape times the positions are taken from.
+/// Since nothing is iterated here, a buffer can be colored again with other
+/// settings for a fraction of the cost of computing it.
+///
+/// A supersampled buffer gives an image with a pixel for every square of
+/// samples, whose color is the mean of theirs. Colors are averaged in
+/// linear light, so a filament half covering a pixel leaves it half as
+/// bright rather than darker. The samples `refine` adds are averaged in
+/// the same way.
+///
+/// Panics if a script of `colors.script` fails on a pixel, which
+/// `colorize_blurred` reports instead.
+pub fn colorize(buffer: &EscapeBuffer, colors: &ColorOptions) -> DynamicImage {
+    match colorize_blurred(&[buffer], colors) {
+        Ok(img) => img,
+        Err(failure) => panic!("the coloring script failed {}", failure),
+    }
+}
+
+/// The image of `roi` placed in a frame of `width` by `height` pixels, with
+/// the pixels around it a checkerboard of grays, opaque whatever the alpha
+/// of the region.
+pub fn fill_around(part: &DynamicImage, roi: &Roi, width: u32, height: u32) -> DynamicImage {
+    let mut frame = DynamicImage::new(width, height, part.color());
+    for (x, y) in (0..height).flat_map(|y| (0..width).map(move |x| (x, y))) {
+        let gray = CHECKER_GRAYS[((x / CHECKER + y / CHECKER) % 2) as usize];
+        frame.put_pixel(x, y, image::Rgba([gray, gray, gray, u8::MAX]));
+    }
+    // Pixels of a `DynamicImage` as such are 8-bit, so 16-bit regions are
+    // put in place as the images they are.
+    let (x, y) = (roi.x as i64, roi.y as i64);
+    match (&mut frame, part) {
+        (DynamicImage::ImageRgb16(frame), DynamicImage::ImageRgb16(part)) => {
+            image::imageops::replace(frame, part, x, y)
+        }
+        (DynamicImage::ImageRgba16(frame), DynamicImage::ImageRgba16(part)) => {
+            image::imageops::replace(frame, part, x, y)
+        }
+        (DynamicImage::ImageLuma16(frame), DynamicImage::ImageLuma16(part)) => {
+            image::imageops::replace(frame, part, x, y)
+        }
+        (frame, part) => image::imageops::replace(frame, part, x, y),
+    }
+    frame
+}
+
+/// Colors the sub-frames of a frame of `--motion-blur` like `colorize`,
+/// into an image whose pixels are the mean of theirs, taken in linear light
+/// as the samples of a pixel are. The buffers have to be of the same size.
+///
+/// Every sub-frame is colored on its own, with the histogram of its own
+/// samples, and the image is only dithered once they're averaged. Fails
+/// with the pixel a script of `colors.script` gave no color for, if any.
+pub fn colorize_blurred(
+    buffers: &[&EscapeBuffer],
+    colors: &ColorOptions,
+) -> Result<DynamicImage, Failure> {
+    let buffer = buffers[0];
+    let (width, height) = (buffer.width / buffer.samples, buffer.height / buffer.samples);
+    Ok(match (colors.bit_depth, colors.alpha) {
+        (BitDepth::Eight, Alpha::None) => {
+            u8::image(width, height, colorize_channels(buffers, colors)?)
+        }
+        (BitDepth::Sixteen, Alpha::None) => {
+            u16::image(width, height, colorize_channels(buffers, colors)?)
+        }
+        (BitDepth::Eight, _) => {
+            u8::rgba_image(width, height, colorize_channels(buffers, colors)?)
+        }
+        (BitDepth::Sixteen, _) => {
+            u16::rgba_image(width, height, colorize_channels(buffers, colors)?)
+        }
+    })
+}
+
+/// Colors every pixel of `buffers`, averaged over them, into interleaved
+/// RGB channels of type `T`, and alpha channels unless `colors.alpha` is
+/// `Alpha::None`.
+fn colorize_channels<T: Channel>(
+    buffers: &[&EscapeBuffer],
+    colors: &ColorOptions,
+) -> Result<Vec<T>, Failure> {
+    let exposures: Vec<Exposure> =
+        buffers.iter().map(|buffer| Exposure::new(buffer, colors)).collect();
+    let buffer = buffers[0];
+    let samples = buffer.samples as usize;
+    let width = buffer.width as usize / samples;
+    let channels = match colors.alpha {
+        Alpha::None => 3,
+        _ => 4,
+    };
+    let mut data = vec![T::from_unit(0.0); buffer.values.len() / (samples * samples) * channels];
+    // A row at a time, as handing out every pixel on its own costs more
+    // than coloring it.
+    data.par_chunks_mut((width * channels).max(1)).enumerate().try_for_each(|(y, row)| {
+        row.chunks_mut(channels).enumerate().try_for_each(|(x, chunk)| {
+            let pixel = y * width + x;
+            let rgba = match exposures.as_slice() {
+                [exposure] => exposure.rgba(pixel),
+                exposures => try_mean(exposures.iter().map(|exposure| exposure.rgba(pixel))),
+            };
+            let [r, g, b, a] = rgba.map_err(|message| Failure {
+                pixel: (x as u32, y as u32),
+                message,
+            })?;
+            let offset = colors.dither.offset(x as u32, y as u32);
+            chunk[..3].copy_from_slice(&[r, g, b].map(|channel| T::dithered(channel, offset)));
+            if channels == 4 {
+                chunk[3] = T::from_unit(a);
+            }
+            Ok(())
+        })
+    })?;
+    Ok(data)
+}
+
+/// A buffer as `colorize` colors it, of a frame or one of its sub-frames.
+struct Exposure<'a> {
+    buffer: &'a EscapeBuffer,
+    shading: Shading<'a>,
+    /// The shade of every sample with lighting.
+    shades: Option<Vec<Shade>>,
+    /// How much of every sample the contour lines cover, with them.
+    contours: Option<(Contours, Vec<f64>)>,
+    /// How much of every sample the line around the set covers, with one.
+    silhouette: Option<(Line, Vec<f64>)>,
+    /// Whether every sample is inked, when the buffer is drawn as line art.
+    line_art: Option<(LineArt, Vec<bool>)>,
+}
+
+impl<'a> Exposure<'a> {
+    fn new(buffer: &'a EscapeBuffer, colors: &'a ColorOptions<'a>) -> Self {
+        Exposure {
+            buffer,
+            shading: Shading::new(buffer, colors),
+            shades: colors.lighting.map(|lighting| lighting.shades(buffer)),
+            contours: colors.contours.map(|contours| (contours, contours.coverage(buffer))),
+            silhouette: colors.silhouette.map(|line| (line, line.silhouette(buffer))),
+            line_art: colors.line_art.map(|line_art| (line_art, line_art.ink(buffer))),
+        }
+    }
+
+    /// The color and opacity of `pixel`, the mean of its samples', or why
+    /// the script gave none for one of them.
+    fn rgba(&self, pixel: usize) -> Result<[f64; 4], String> {
+        let buffer = self.buffer;
+        // The color of `sample` lit by the shade of the sample at `index`,
+        // with the lines over it there, and its opacity. Refined samples
+        // are lit and drawn over as their pixel is.
+        let lit = |index: usize, sample: Sample, color: [f64; 3]| {
+            if let Some((line_art, ink)) = &self.line_art {
+                return line_art.rgba(ink[index]);
+            }
+            let color = match &self.shades {
+                Some(shades) => shades[index].apply(color),
+                None => color,
+            };
+            let color = match &self.contours {
+                Some((contours, coverage)) => contours.apply(sample, coverage[index], color),
+                None => color,
+            };
+            let [r, g, b] = match &self.silhouette {
+                Some((line, coverage)) => line.over(coverage[index], color),
+                None => color,
+            };
+            [r, g, b, self.shading.coverage(sample)]
+        };
+        if let Some(refined) = buffer.refined.get(&pixel) {
+            let own = std::iter::once(buffer.values[pixel]);
+            let samples = own.chain(refined.iter().copied());
+            let colors = samples.map(|sample| lit(pixel, sample, self.shading.rgb(sample)));
+            return Ok(mean(colors));
+        }
+        // The color of the sample at `index`, from its orbit with script
+        // coloring.
+        let color = |index: usize| {
+            let sample = buffer.values[index];
+            let color = match buffer.orbits.get(index) {
+                Some(orbit) => self.shading.scripted(sample, orbit)?,
+                None => self.shading.rgb(sample),
+            };
+            Ok(lit(index, sample, color))
+        };
+        let samples = buffer.samples as usize;
+        if samples == 1 {
+            return color(pixel);
+        }
+        let row = buffer.width as usize;
+        let (x, y) = (pixel % (row / samples) * samples, pixel / (row / samples) * samples);
+        let square = (y..y + samples).flat_map(|y| (x..x + samples).map(move |x| (x, y)));
+        try_mean(square.map(|(x, y)| color(y * row + x)))
+    }
+}
+
+/// The linear intensity of an sRGB channel between 0 and 1.
+#[inline]
+pub fn to_linear(channel: f64) -> f64 {
+    match channel <= 0.04045 {
+        true => channel / 12.92,
+        false => ((channel + 0.055) / 1.055).powf(2.4),
+    }
+}
+
+/// The sRGB channel of a linear intensity between 0 and 1.
+#[inline]
+pub fn from_linear(intensity: f64) -> f64 {
+    match intensity <= 0.0031308 {
+        true => intensity * 12.92,
+        false => 1.055 * intensity.powf(1.0 / 2.4) - 0.055,
+    }
+}
+
+/// The colors `colorize` gives the samples of a buffer.
+struct Shading<'a> {
+    colors: &'a ColorOptions<'a>,
+    coloring: Coloring,
+    /// The script the samples are colored by, if they were computed for
+    /// one, and the iteration limit they were computed with.
+    script: Option<(Scripted<'a>, u32)>,
+    /// With histogram coloring, the distribution of the buffer's own
+    /// samples, unless the colors have a reference.
+    histogram: Option<Histogram>,
+    /// Distances are colored in pixels, to keep their colors at every
+    /// sample count.
+    unit: f64,
+    interior: [f64; 3],
+}
+
+impl<'a> Shading<'a> {
+    fn new(buffer: &EscapeBuffer, colors: &'a ColorOptions<'a>) -> Self {
+        let own = buffer.coloring == Coloring::Histogram && colors.reference.is_none();
+        let histogram = own.then(|| {
+            let exterior = buffer.values.iter().filter_map(|sample| match sample {
+                Sample::Value(value) => Some(*value),
+                _ => None,
+            });
+            Histogram::new(exterior, colors.histogram_clip)
+        });
+        let (r, g, b) = colors.interior;
+        let script = match buffer.coloring {
+            Coloring::Script(_) => colors.script.map(|script| (script, buffer.max_iter)),
+            _ => None,
+        };
+        Shading {
+            colors,
+            coloring: buffer.coloring,
+            script,
+            histogram,
+            unit: match buffer.coloring {
+                Coloring::Distance => buffer.samples as f64,
+                _ => 1.0,
+            },
+            interior: [r, g, b].map(|c| c as f64 / 255.0),
+        }
+    }
+
+    /// The color of `sample`, with channels between 0 and 1.
+    #[inline]
+    fn rgb(&self, sample: Sample) -> [f64; 3] {
+        match sample {
+            Sample::Value(value) => {
+                let histogram = self.histogram.as_ref();
+                let position = self.colors.position(self.coloring, value / self.unit, histogram);
+                let color = self.colors.colormap.at(self.colors.cycle.parameter(position));
+                [color.r, color.g, color.b]
+            }
+            Sample::Phase { value, angle } => {
+                let position = self.colors.position(self.coloring, value, None);
+                let escape = self.colors.colormap.at(self.colors.cycle.parameter(position));
+                let turn = angle as f64 / std::f64::consts::TAU + 0.5;
+                if let Coloring::Binary(k) = self.coloring {
+                    // The sector the angle falls in, from the negative real
+                    // axis counterclockwise, so with one split the upper
+                    // half plane is dark. The angle of exactly π is the
+                    // last sector's.
+                    let sectors = 1u64 << k;
+                    let sector = ((turn * sectors as f64) as u64).min(sectors - 1);
+                    let shade = match (sector + value as u64) % 2 {
+                        0 => 1.0,
+                        _ => BINARY_SHADE,
+                    };
+                    return [escape.r, escape.g, escape.b].map(|channel| channel * shade);
+                }
+                let phase = self.colors.phase;
+                let cycle = Cycle {
+                    cycles: phase.turns,
+                    ..self.colors.cycle
+                };
+                let angle = self.colors.colormap.at(cycle.parameter(turn));
+                let mix = |escape: f64, angle: f64| escape + phase.weight * (angle - escape);
+                [mix(escape.r, angle.r), mix(escape.g, angle.g), mix(escape.b, angle.b)]
+            }
+            Sample::Root {
+                root,
+                roots,
+                iterations,
+            } => {
+                // Every root gets its own stretch of one pass over the
+                // gradient, so none of them share a color.
+                let cycle = Cycle {
+                    cycles: 1.0,
+                    mirror: false,
+                    ..self.colors.cycle
+                };
+                let position = (root as f64 + 0.5) / roots as f64;
+                let color = self.colors.colormap.at(cycle.parameter(position));
+                let shade = (-iterations / ROOT_FADE).exp();
+                [color.r, color.g, color.b].map(|channel| channel * shade)
+            }
+            Sample::Exponent(exponent) => {
+                // Negative exponents take the first half of one pass over
+                // the gradient and positive ones the second, each from its
+                // start at 0 further along the further the exponent is
+                // from it, so order and chaos never share a color.
+                let cycle = Cycle {
+                    cycles: 1.0,
+                    mirror: false,
+                    ..self.colors.cycle
+                };
+                let along = 1.0 - (-exponent.abs() / EXPONENT_SCALE).exp();
+                let position = match exponent < 0.0 {
+                    true => 0.5 * along,
+                    false => 0.5 + 0.5 * along,
+                };
+                let color = self.colors.colormap.at(cycle.parameter(position));
+                [color.r, color.g, color.b]
+            }
+            Sample::Interior => self.interior,
+            Sample::Attractor { period, derivative } => match self.colors.interior_coloring {
+                Some(coloring) => coloring.rgb(period, derivative as f64),
+                None => self.interior,
+            },
+            Sample::Boundary => [0.0; 3],
+        }
+    }
+
+    /// How opaque `sample` is, from 0 to 1.
+    ///
+    /// With distances, the points within a pixel of the set fade out
+    /// towards it, which smooths its edge where the samples would cut it.
+    #[inline]
+    fn coverage(&self, sample: Sample) -> f64 {
+        match (self.colors.alpha, sample) {
+            (Alpha::None, _) => 1.0,
+            (_, Sample::Interior | Sample::Attractor { .. } | Sample::Boundary) => 0.0,
+            (_, Sample::Value(distance)) if self.coloring == Coloring::Distance => {
+                (distance / self.unit).clamp(0.0, 1.0)
+            }
+            (Alpha::Threshold(threshold), Sample::Value(value) | Sample::Phase { value, .. }) => {
+                (value - threshold).clamp(0.0, 1.0)
+            }
+            _ => 1.0,
+        }
+    }
+
+    /// The color of `sample` of a buffer computed for script coloring,
+    /// whose orbit was `orbit`, as the script gives it, or as `rgb` does
+    /// without a script or outside the set.
+    ///
+    /// Gradient positions are clamped to the gradient, which they span
+    /// once, without the palette's cycles.
+    fn scripted(&self, sample: Sample, orbit: &Orbit) -> Result<[f64; 3], String> {
+        let (Sample::Value(smooth), Some((scripted, max_iter))) = (sample, self.script) else {
+            return Ok(self.rgb(sample));
+        };
+        let inputs = Inputs {
+            smooth,
+            max_iter,
+            orbit: *orbit,
+            frame: scripted.frame,
+            magnification: scripted.magnification,
+        };
+        Ok(match scripted.script.run(&inputs)? {
+            Output::Position(position) => {
+                let color = self.colors.colormap.at(position.clamp(0.0, 1.0));
+                [color.r, color.g, color.b]
+            }
+            Output::Rgb(rgb) => rgb.map(|channel| channel.clamp(0.0, 1.0)),
+        })
+    }
+}
+
+/// The mean of `colors` with their opacities, taken in linear light.
+///
+/// Colors count as much as they're opaque, so the edge of a transparent
+/// region doesn't take on its color, and opacities are averaged alone. A
+/// pixel with none opaque keeps the mean of all of them.
+fn mean(colors: impl Iterator<Item = [f64; 4]>) -> [f64; 4] {
+    let (mut sum, mut weighted, mut opacity, mut count) = ([0.0; 3], [0.0; 3], 0.0, 0);
+    for [r, g, b, a] in colors {
+        for ((sum, weighted), channel) in sum.iter_mut().zip(&mut weighted).zip([r, g, b]) {
+            let linear = to_linear(channel);
+            *sum += linear;
+            *weighted += a * linear;
+        }
+        opacity += a;
+        count += 1;
+    }
+    let [r, g, b] = match opacity > 0.0 {
+        true => weighted.map(|sum| from_linear(sum / opacity)),
+        false => sum.map(|sum| from_linear(sum / count as f64)),
+    };
+    [r, g, b, opacity / count as f64]
+}
+
+/// The mean of `colors` like `mean`, or the first error among them.
+fn try_mean(colors: impl Iterator<Item = Result<[f64; 4], String>>) -> Result<[f64; 4], String> {
+    let mut error = None;
+    let mean = mean(colors.map_while(|color| color.map_err(|message| error = Some(message)).ok()));
+    error.map_or(Ok(mean), Err)
+}
+
+/// Adaptive anti-aliasing: adds samples to the pixels of `buffer` whose
+/// color stands out from one of their neighbors', returning the fraction
+/// of the pixels that were refined.
+///
+/// Colors are compared as `colorize` gives them with `colors`, so pixels
+/// that only differ in value within a color aren't refined. The pixels
+/// get a sample in every pass, computed by `compute`, until the last one
+/// moved their color by less than `CONVERGED` or they have as many as
+/// `adaptive.samples` squared.
+pub fn refine<C>(
+    buffer: &mut EscapeBuffer,
+    colors: &ColorOptions,
+    adaptive: &Adaptive,
+    compute: C,
+) -> f64
+where
+    C: Fn(Refine) -> EscapeBuffer,
+{
+    let shading = Shading::new(buffer, colors);
+    let (width, height) = (buffer.width as usize, buffer.height as usize);
+    let rgb: Vec<[f64; 3]> = buffer.values.par_iter().map(|&sample| shading.rgb(sample)).collect();
+    let stands_out = |a: usize, b: usize| {
+        rgb[a].iter().zip(&rgb[b]).any(|(a, b)| (a - b).abs() > adaptive.threshold)
+    };
+    // Every pair of neighbors is compared once, from the pixel above or to
+    // the left, and marks both.
+    let mut pixels = vec![false; width * height];
+    for y in 0..height {
+        for x in 0..width {
+            let index = y * width + x;
+            let below = (y + 1 < height).then_some(index + width);
+            let neighbors = [
+                (x + 1 < width).then_some(index + 1),
+                below.filter(|_| x > 0).map(|below| below - 1),
+                below,
+                below.filter(|_| x + 1 < width).map(|below| below + 1),
+            ];
+            for neighbor in neighbors.into_iter().flatten() {
+                if stands_out(index, neighbor) {
+                    (pixels[index], pixels[neighbor]) = (true, true);
+                }
+            }
+        }
+    }
+    let refined = pixels.iter().filter(|&&refined| refined).count();
+
+    // The sums of the linear colors of the refined pixels.
+    let mut sums: HashMap<usize, [f64; 3]> = HashMap::new();
+    for pass in 1..adaptive.samples * adaptive.samples {
+        if !pixels.contains(&true) {
+            break;
+        }
+        let new = compute(Refine {
+            pixels: &pixels,
+            width: buffer.width,
+            pass,
+            cells: adaptive.samples,
+        });
+        let refining = pixels.iter_mut().enumerate().filter(|(_, refining)| **refining);
+        for ((index, refining), sample) in refining.zip(new.values) {
+            let sum = sums.entry(index).or_insert_with(|| rgb[index].map(to_linear));
+            let added = buffer.refined.entry(index).or_default();
+            added.push(sample);
+            let count = added.len() + 1;
+            let before = sum.map(|sum| from_linear(sum / (count - 1) as f64));
+            for (sum, channel) in sum.iter_mut().zip(shading.rgb(sample)) {
+                *sum += to_linear(channel);
+            }
+            let after = sum.map(|sum| from_linear(sum / count as f64));
+            let moved = before.iter().zip(after).any(|(before, after)| {
+                (before - after).abs() >= CONVERGED
+            });
+            *refining = moved || count < MIN_SAMPLES;
+        }
+    }
+    refined as f64 / (width * height) as f64
+}
+
+/// Maps a number between 0 and 1 to a color of `colormap`.
+///
+/// `t` is the number to map, usually from `Cycle::parameter`. Returns the
+/// RGB channels at the precision of `T`, rounded from the full precision
+/// color.
+pub fn color_gradient<T: Channel>(colormap: &Colormap, t: f64) -> [T; 3] {
+    let color = colormap.at(t);
+    [color.r, color.g, color.b].map(T::from_unit)
+}
+
+/// A channel type frames can be colored into.
+pub trait Channel: Primitive + Send + Sync {
+    /// Converts an intensity between 0 and 1 to the channel's range.
+    fn from_unit(value: f64) -> Self;
+
+    /// Converts an intensity between 0 and 1 to the channel's range, with
+    /// `offset` levels added before rounding.
+    fn dithered(value: f64, offset: f64) -> Self;
+
+    /// Wraps interleaved RGB channels, row by row, into an image.
+    fn image(width: u32, height: u32, data: Vec<Self>) -> DynamicImage;
+
+    /// Wraps interleaved RGBA channels, row by row, into an image.
+    fn rgba_image(width: u32, height: u32, data: Vec<Self>) -> DynamicImage;
+}
+
+impl Channel for u8 {
+    #[inline]
+    fn from_unit(value: f64) -> u8 {
+        (value * 255.0 + 0.5) as u8
+    }
+
+    #[inline]
+    fn dithered(value: f64, offset: f64) -> u8 {
+        (value * 255.0 + 0.5 + offset) as u8
+    }
+
+    fn image(width: u32, height: u32, data: Vec<u8>) -> DynamicImage {
+        let image = ImageBuffer::from_vec(width, height, data);
+        DynamicImage::ImageRgb8(image.expect("three channels for every pixel"))
+    }
+
+    fn rgba_image(width: u32, height: u32, data: Vec<u8>) -> DynamicImage {
+        let image = ImageBuffer::from_vec(width, height, data);
+        DynamicImage::ImageRgba8(image.expect("four channels for every pixel"))
+    }
+}
+
+impl Channel for u16 {
+    #[inline]
+    fn from_unit(value: f64) -> u16 {
+        (value * 65535.0 + 0.5) as u16
+    }
+
+    #[inline]
+    fn dithered(value: f64, offset: f64) -> u16 {
+        (value * 65535.0 + 0.5 + offset) as u16
+    }
+
+    fn image(width: u32, height: u32, data: Vec<u16>) -> DynamicImage {
+        let image = ImageBuffer::from_vec(width, height, data);
+        DynamicImage::ImageRgb16(image.expect("three channels for every pixel"))
+    }
+
+    fn rgba_image(width: u32, height: u32, data: Vec<u16>) -> DynamicImage {
+        let image = ImageBuffer::from_vec(width, height, data);
+        DynamicImage::ImageRgba16(image.expect("four channels for every pixel"))
+    }
+}
@@ -0,0 +1,126 @@
+use crate::complex::Complex;
+
+/// Series approximation of the perturbation deltas near a reference orbit.
+///
+/// Close to the reference every pixel's delta after `n` iterations is a
+/// polynomial in its offset `dc` from the center, so instead of iterating
+/// the first `n` steps per pixel the polynomial is evaluated once and the
+/// delta iteration resumes from there.
+///
+/// Coefficients are stored pre-multiplied by `radius^k` and evaluated at
+/// `dc / radius`, which keeps the high-order terms from overflowing at depth
+/// where the raw coefficients grow like `radius^-k`.
+pub struct Series {
+    /// The iteration the series has been advanced to.
+    pub skip: usize,
+    radius: f64,
+    coefficients: Vec<Complex<f64>>,
+}
+
+/// Relative error tolerated between the series and the iterated delta at
+/// the probe points.
+const PROBE_TOLERANCE: f64 = 1e-6;
+
+/// How small the last term has to stay relative to the linear one for the
+/// series to be considered converged.
+const TRUNCATION_TOLERANCE: f64 = 1e-9;
+
+impl Series {
+    /// Advances the series along `orbit` for as long as it stays valid.
+    ///
+    /// `radius` is the largest offset of any pixel from the center and
+    /// `probes` are offsets (typically the frame corners and edge
+    /// midpoints) whose deltas are iterated directly alongside the series.
+    /// The series stops one step before it disagrees with any probe, before
+    /// the truncation error grows, or before a probe would need rebasing or
+    /// escape past `bailout`.
+    /// Returns `None` when no iteration can be skipped.
+    pub fn compute(
+        orbit: &[(f64, f64)],
+        radius: f64,
+        probes: &[(f64, f64)],
+        terms: usize,
+        max_iter: u32,
+        bailout: f64,
+    ) -> Option<Series> {
+        if terms == 0 || radius <= 0.0 || orbit.len() < 2 {
+            return None;
+        }
+
+        let mut coefficients = vec![Complex::ZERO; terms];
+        let mut deltas = vec![Complex::ZERO; probes.len()];
+        let mut skip = 0;
+        let limit = (orbit.len() - 1).min(max_iter as usize);
+
+        for n in 0..limit {
+            let two_z = Complex::from(orbit[n]).scale(2.0);
+
+            let mut next = vec![Complex::ZERO; terms];
+            for k in 0..terms {
+                let mut term = two_z * coefficients[k];
+                for j in 0..k {
+                    term = term + coefficients[j] * coefficients[k - 1 - j];
+                }
+                if k == 0 {
+                    term.re += radius;
+                }
+                next[k] = term;
+            }
+
+            let mut next_deltas = deltas.clone();
+            for (delta, &dc) in next_deltas.iter_mut().zip(probes) {
+                *delta = two_z * *delta + delta.sqr() + dc.into();
+            }
+
+            let linear = next[0].norm_sqr();
+            let last = next[terms - 1].norm_sqr();
+            let converged = last <= TRUNCATION_TOLERANCE * TRUNCATION_TOLERANCE * linear;
+            if !converged || !linear.is_finite() {
+                break;
+            }
+            let z_next = Complex::from(orbit[n + 1]);
+            let valid = next_deltas.iter().zip(probes).all(|(&delta, &dc)| {
+                let approx = evaluate(&next, Complex::new(dc.0 / radius, dc.1 / radius));
+                let full = z_next + delta;
+                let norm = delta.norm_sqr();
+                (approx - delta).norm_sqr() <= PROBE_TOLERANCE * PROBE_TOLERANCE * norm
+                    && full.norm_sqr() >= norm
+                    && full.norm_sqr() <= bailout * bailout
+            });
+            if !valid {
+                break;
+            }
+
+            coefficients = next;
+            deltas = next_deltas;
+            skip = n + 1;
+        }
+
+        if skip == 0 {
+            return None;
+        }
+        Some(Series {
+            skip,
+            radius,
+            coefficients,
+        })
+    }
+
+    /// Returns the approximate delta after `skip` iterations for the pixel
+    /// at offset `dc`.
+    #[inline]
+    pub fn delta(&self, dc: (f64, f64)) -> (f64, f64) {
+        let u = Complex::new(dc.0 / self.radius, dc.1 / self.radius);
+        evaluate(&self.coefficients, u).into()
+    }
+}
+
+/// Evaluates `sum(c[k] * u^(k+1))` by Horner's rule.
+#[inline]
+fn evaluate(coefficients: &[Complex<f64>], u: Complex<f64>) -> Complex<f64> {
+    let mut acc = Complex::ZERO;
+    for &c in coefficients.iter().rev() {
+        acc = (acc + c) * u;
+    }
+    acc
+}
@@ -0,0 +1,372 @@
+use crate::bookmarks;
+use crate::decimal::Decimal;
+use crate::error::RustlebrotError;
+use crate::export;
+use crate::fractal::{Fractal, FractalKind};
+use crate::location::Location;
+use crate::precision::{Precision, WARN_ULPS};
+use crate::render::{colorize, compute_escape, ColorOptions, RenderOptions};
+use image::{DynamicImage, Rgb, RgbImage};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Pixels along each side of a tile.
+pub const TILE_SIZE: u32 = 256;
+
+/// The page served at `/`, a Leaflet map of the tiles.
+const PAGE: &str = include_str!("serve.html");
+
+/// A tile of the map, by zoom level and by column and row from the top
+/// left, as slippy map clients ask for them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Tile {
+    pub z: u32,
+    pub x: u32,
+    pub y: u32,
+}
+
+impl Tile {
+    /// The tile of a `{z}/{x}/{y}.png` path.
+    fn parse(path: &str) -> Option<Tile> {
+        let mut parts = path.strip_suffix(".png")?.split('/');
+        let mut part = || parts.next()?.parse().ok();
+        let tile = Tile {
+            z: part()?,
+            x: part()?,
+            y: part()?,
+        };
+        parts.next().is_none().then_some(tile)
+    }
+}
+
+/// The x and y ranges of a view.
+type View = ((f64, f64), (f64, f64));
+
+/// How the tiles of the map are rendered and kept.
+pub struct TileOptions<'a> {
+    /// The fractal the tiles are of, which locations saved from the page
+    /// are in.
+    pub fractal: FractalKind,
+    /// The view of the one tile at zoom level 0. Every level has twice as
+    /// many tiles along each side as the one before.
+    pub root: View,
+    /// Deeper zoom levels get the error tile, as do tiles whose pixels f64
+    /// can't tell apart well.
+    pub max_zoom: u32,
+    /// How fast max_iter grows with magnification, as with `--auto-iter`.
+    pub auto_iter: Option<f64>,
+    /// The per-pixel settings, with the iteration limit at zoom level 0.
+    pub options: RenderOptions<'a>,
+    pub colors: ColorOptions<'a>,
+    /// Tiles kept in memory, the least recently used going first.
+    pub cache_tiles: usize,
+    /// Where tiles are also saved and looked up, as `{z}/{x}/{y}.png`.
+    /// Nothing tells tiles of other settings apart, so a directory should
+    /// only be used with one set of them.
+    pub cache_dir: Option<&'a str>,
+}
+
+impl TileOptions<'_> {
+    /// The x and y ranges `tile` shows, or why it can't be rendered.
+    pub fn view(&self, tile: Tile) -> Result<View, String> {
+        if tile.z > self.max_zoom {
+            return Err(format!("zoom level {} is past the deepest, {}", tile.z, self.max_zoom));
+        }
+        let tiles = 2f64.powi(tile.z as i32);
+        if tile.x as f64 >= tiles || tile.y as f64 >= tiles {
+            return Err(format!("zoom level {} has no tile {},{}", tile.z, tile.x, tile.y));
+        }
+        let ((x_min, x_max), (y_min, y_max)) = self.root;
+        let size = ((x_max - x_min) / tiles, (y_max - y_min) / tiles);
+        let (x, y) = (tile.x as f64, tile.y as f64);
+        // Rows count down from the top, where the imaginary part is largest.
+        let x_range = (x_min + x * size.0, x_min + (x + 1.0) * size.0);
+        let y_range = (y_max - (y + 1.0) * size.1, y_max - y * size.1);
+        let center = ((x_range.0 + x_range.1) / 2.0, (y_range.0 + y_range.1) / 2.0);
+        if size.0 / TILE_SIZE as f64 / Precision::F64.resolution(center) < WARN_ULPS {
+            return Err(format!("the pixels of zoom level {} are too small for f64", tile.z));
+        }
+        Ok((x_range, y_range))
+    }
+
+    /// The location of a view of the page, from the query of `/location`:
+    /// the center `x` and `y`, the `width` and `height` of the view in the
+    /// plane, and the zoom level `z` it shows tiles of, for the iteration
+    /// limit.
+    pub fn location(&self, query: &str) -> Result<Location, String> {
+        let mut values: HashMap<&str, &str> = HashMap::new();
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            values.insert(key, value);
+        }
+        let value = |key: &str| values.get(key).copied().ok_or(format!("{} is missing", key));
+        let number = |key: &str| {
+            let value = value(key)?;
+            let parsed = value.parse::<f64>().ok().filter(|v| *v > 0.0 && v.is_finite());
+            parsed.ok_or(format!("{} should be a positive number, got '{}'", key, value))
+        };
+        let center = (Decimal::parse(value("x")?)?, Decimal::parse(value("y")?)?);
+        let extent = number("width")?.min(number("height")?);
+        let z = value("z")?;
+        let z = z.parse().map_err(|_| format!("z should be a zoom level, got '{}'", z))?;
+        Ok(Location {
+            name: None,
+            notes: None,
+            fractal: self.fractal,
+            newton: None,
+            center: (center.0.to_string(), center.1.to_string()),
+            magnification: 2.0 * self.fractal.default_half_width() / extent,
+            max_iter: self.max_iter(z),
+            rotation: 0.0,
+        })
+    }
+
+    /// The iteration limit at zoom level `z`.
+    fn max_iter(&self, z: u32) -> u32 {
+        let base = self.options.max_iter;
+        match self.auto_iter {
+            Some(k) => (base as f64 * (1.0 + k * z as f64 * 2f64.log10())).round() as u32,
+            None => base,
+        }
+    }
+}
+
+/// The deepest zoom level whose pixels span `WARN_ULPS` steps of f64
+/// everywhere in `root`, which is where f64 is coarsest, at the corner
+/// farthest from the origin.
+pub fn deepest_zoom(root: View) -> u32 {
+    let ((x_min, x_max), (y_min, y_max)) = root;
+    let corner = (x_min.abs().max(x_max.abs()), y_min.abs().max(y_max.abs()));
+    let pixel = (x_max - x_min) / TILE_SIZE as f64;
+    (pixel / (WARN_ULPS * Precision::F64.resolution(corner))).log2().floor().max(0.0) as u32
+}
+
+/// The tiles rendered last, as PNGs.
+struct TileCache {
+    capacity: usize,
+    /// Every tile, with when it was last used.
+    tiles: HashMap<Tile, (u64, Arc<Vec<u8>>)>,
+    /// The tiles by when they were last used.
+    used: BTreeMap<u64, Tile>,
+    clock: u64,
+}
+
+impl TileCache {
+    fn new(capacity: usize) -> Self {
+        TileCache {
+            capacity,
+            tiles: HashMap::new(),
+            used: BTreeMap::new(),
+            clock: 0,
+        }
+    }
+
+    fn get(&mut self, tile: Tile) -> Option<Arc<Vec<u8>>> {
+        let (used, png) = self.tiles.get_mut(&tile)?;
+        self.used.remove(used);
+        self.clock += 1;
+        *used = self.clock;
+        self.used.insert(self.clock, tile);
+        Some(png.clone())
+    }
+
+    /// Keeps `png` as `tile`, making room by dropping the least recently
+    /// used tiles.
+    fn insert(&mut self, tile: Tile, png: Arc<Vec<u8>>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if let Some((used, _)) = self.tiles.remove(&tile) {
+            self.used.remove(&used);
+        }
+        while self.tiles.len() >= self.capacity {
+            let (_, oldest) = self.used.pop_first().expect("every tile has a time");
+            self.tiles.remove(&oldest);
+        }
+        self.clock += 1;
+        self.tiles.insert(tile, (self.clock, png));
+        self.used.insert(self.clock, tile);
+    }
+}
+
+struct Server<'a, F> {
+    fractal: &'a F,
+    tiles: &'a TileOptions<'a>,
+    cache: Mutex<TileCache>,
+    /// The page, with the root view and deepest zoom level filled in.
+    page: String,
+    /// What is served in place of tiles that can't be rendered.
+    error_tile: Vec<u8>,
+}
+
+/// Serves the tiles of `fractal` and a page to browse them on `listener`,
+/// handling up to `workers` requests at once, until the process is stopped.
+///
+/// Tiles are `/tiles/{z}/{x}/{y}.png`, the page is at `/`, and the location
+/// file of a view of it at `/location`.
+pub fn serve<F: Fractal>(
+    fractal: &F,
+    listener: &TcpListener,
+    workers: usize,
+    tiles: &TileOptions,
+) -> Result<(), RustlebrotError> {
+    let ((x_min, x_max), (y_min, y_max)) = tiles.root;
+    let page = PAGE
+        .replace("{{max_zoom}}", &tiles.max_zoom.to_string())
+        .replace("{{x_min}}", &format!("{:?}", x_min))
+        .replace("{{x_max}}", &format!("{:?}", x_max))
+        .replace("{{y_min}}", &format!("{:?}", y_min))
+        .replace("{{y_max}}", &format!("{:?}", y_max));
+    let gray = RgbImage::from_pixel(TILE_SIZE, TILE_SIZE, Rgb([48, 48, 48]));
+    let mut error_tile = Vec::new();
+    export::encode_png(&mut error_tile, &DynamicImage::ImageRgb8(gray), &[])
+        .map_err(|e| RustlebrotError::encode("the error tile", e))?;
+    let server = Server {
+        fractal,
+        tiles,
+        cache: Mutex::new(TileCache::new(tiles.cache_tiles)),
+        page,
+        error_tile,
+    };
+    // Every worker takes the next connection once it is done with one, so
+    // at most `workers` tiles are rendered at a time.
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                match listener.accept() {
+                    // Clients drop the tiles they no longer need, so a
+                    // connection closed early is nothing to report.
+                    Ok((stream, _)) => {
+                        let _ = server.handle(stream);
+                    }
+                    Err(e) => eprintln!("Warning: failed to accept a connection: {}", e),
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+impl<F: Fractal> Server<'_, F> {
+    /// Answers the one request on `stream`.
+    fn handle(&self, mut stream: TcpStream) -> io::Result<()> {
+        // Requests are small, so anything longer is cut off.
+        let mut reader = BufReader::new(stream.try_clone()?.take(16 << 10));
+        let mut request = String::new();
+        reader.read_line(&mut request)?;
+        // The headers say nothing the server needs, but are read so the
+        // client isn't cut off sending them.
+        let mut header = String::new();
+        while reader.read_line(&mut header)? > 0 && !header.trim().is_empty() {
+            header.clear();
+        }
+        let mut words = request.split_whitespace();
+        let (method, target) = (words.next().unwrap_or(""), words.next().unwrap_or(""));
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        if method != "GET" {
+            return respond(&mut stream, "405 Method Not Allowed", "text/plain", b"only GET\n");
+        }
+        match path {
+            "/" | "/index.html" => {
+                respond(&mut stream, "200 OK", "text/html; charset=utf-8", self.page.as_bytes())
+            }
+            "/error.png" => respond(&mut stream, "200 OK", "image/png", &self.error_tile),
+            "/location" => match self.tiles.location(query) {
+                Ok(location) => {
+                    let json = bookmarks::to_json(&[location]);
+                    respond(&mut stream, "200 OK", "application/json", json.as_bytes())
+                }
+                Err(e) => {
+                    let message = format!("{}\n", e);
+                    respond(&mut stream, "400 Bad Request", "text/plain", message.as_bytes())
+                }
+            },
+            _ => match path.strip_prefix("/tiles/").and_then(Tile::parse) {
+                Some(tile) => match self.tile(tile) {
+                    Ok(png) => respond(&mut stream, "200 OK", "image/png", &png),
+                    Err(_) => respond(&mut stream, "404 Not Found", "image/png", &self.error_tile),
+                },
+                None => respond(&mut stream, "404 Not Found", "text/plain", b"no such page\n"),
+            },
+        }
+    }
+
+    /// The PNG of `tile`, from the caches if it is in one.
+    fn tile(&self, tile: Tile) -> Result<Arc<Vec<u8>>, String> {
+        if let Some(png) = self.cache.lock().unwrap().get(tile) {
+            return Ok(png);
+        }
+        let path = self.tiles.cache_dir.map(|dir| {
+            format!("{}/{}/{}/{}.png", dir, tile.z, tile.x, tile.y)
+        });
+        let png = match path.as_deref().and_then(|path| fs::read(path).ok()) {
+            Some(png) => png,
+            None => {
+                let png = self.render(tile)?;
+                if let Some(path) = &path {
+                    if let Err(e) = save(path, &png) {
+                        eprintln!("Warning: can't save tile {}: {}", path, e);
+                    }
+                }
+                png
+            }
+        };
+        let png = Arc::new(png);
+        self.cache.lock().unwrap().insert(tile, png.clone());
+        Ok(png)
+    }
+
+    /// Renders `tile` as a PNG.
+    fn render(&self, tile: Tile) -> Result<Vec<u8>, String> {
+        let (x_range, y_range) = self.tiles.view(tile)?;
+        let options = RenderOptions {
+            max_iter: self.tiles.max_iter(tile.z),
+            ..self.tiles.options
+        };
+        let buffer = compute_escape(self.fractal, TILE_SIZE, TILE_SIZE, x_range, y_range, &options);
+        let text = [
+            ("Software", format!("rustlebrot {}", env!("CARGO_PKG_VERSION"))),
+            ("Tile", format!("{}/{}/{}", tile.z, tile.x, tile.y)),
+            ("X Range", format!("{:?},{:?}", x_range.0, x_range.1)),
+            ("Y Range", format!("{:?},{:?}", y_range.0, y_range.1)),
+            ("Max Iter", options.max_iter.to_string()),
+        ];
+        let mut png = Vec::new();
+        export::encode_png(&mut png, &colorize(&buffer, &self.tiles.colors), &text)
+            .map_err(|e| format!("can't encode tile: {}", e))?;
+        Ok(png)
+    }
+}
+
+/// Writes a response with `body` to `stream`.
+fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    stream.flush()
+}
+
+/// Saves `png` at `path`, moving it there once complete so that a tile cut
+/// short is never found.
+fn save(path: &str, png: &[u8]) -> io::Result<()> {
+    if let Some(dir) = std::path::Path::new(path).parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let partial = format!("{}.part", path);
+    fs::write(&partial, png)?;
+    fs::rename(&partial, path)
+}
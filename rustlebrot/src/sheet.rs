@@ -0,0 +1,180 @@
+use crate::hud::{self, CELL};
+use rustlebrot::bigfloat::{self, Big};
+use rustlebrot::error::RustlebrotError;
+use rustlebrot::grid::{Grid, BACKGROUND, GAP};
+use rustlebrot::survey::{Level, SurveyOptions};
+use image::{DynamicImage, GenericImage, Rgb, RgbImage};
+use serde::Serialize;
+use std::fs;
+
+/// Lines of the label under every thumbnail: the real and imaginary parts
+/// of the center of its cell, and its score.
+const LABEL_LINES: u32 = 3;
+
+/// The JSON written next to the contact sheets of a survey.
+#[derive(Serialize)]
+struct SurveyFile<'a> {
+    software: String,
+    fractal: &'a str,
+    grid: (usize, usize),
+    levels: Vec<LevelRecord>,
+}
+
+#[derive(Serialize)]
+struct LevelRecord {
+    level: usize,
+    /// The contact sheet, relative to the JSON.
+    sheet: String,
+    center: (String, String),
+    radius: f64,
+    magnification: f64,
+    max_iter: u32,
+    /// The column and row of the cell the next level is of.
+    best: (usize, usize),
+    cells: Vec<CellRecord>,
+}
+
+#[derive(Serialize)]
+struct CellRecord {
+    column: usize,
+    row: usize,
+    center: (String, String),
+    /// The width and height of the cell in the plane.
+    size: (f64, f64),
+    score: f64,
+}
+
+/// The name of the contact sheet of level `level`, counted from 1.
+fn sheet_name(level: usize) -> String {
+    format!("survey_{}.png", level)
+}
+
+/// Writes the contact sheet of every one of `levels` and `survey.json`
+/// with the cells of all of them into `dir`, returning the paths written.
+pub fn write_survey(
+    levels: &[Level],
+    options: &SurveyOptions,
+    fractal: &str,
+    dir: &str,
+) -> Result<Vec<String>, RustlebrotError> {
+    fs::create_dir_all(dir).map_err(|e| RustlebrotError::write(dir, e))?;
+    let mut paths = Vec::new();
+    let mut records = Vec::new();
+    for (index, level) in levels.iter().enumerate() {
+        let name = sheet_name(index + 1);
+        let path = format!("{}/{}", dir, name);
+        contact_sheet(level, index + 1, options)
+            .save(&path)
+            .map_err(|e| RustlebrotError::encode(&path, e))?;
+        paths.push(path);
+        records.push(level_record(level, index + 1, name, options));
+    }
+    let file = SurveyFile {
+        software: format!("rustlebrot {}", env!("CARGO_PKG_VERSION")),
+        fractal,
+        grid: options.grid,
+        levels: records,
+    };
+    let path = format!("{}/survey.json", dir);
+    let json =
+        serde_json::to_string_pretty(&file).map_err(|e| RustlebrotError::encode(&path, e))?;
+    fs::write(&path, json + "\n").map_err(|e| RustlebrotError::write(&path, e))?;
+    paths.push(path);
+    Ok(paths)
+}
+
+/// `value` with enough significant digits to place it within a tenth of
+/// `pixel`, which `--center` reads back.
+pub fn decimal(value: &Big, pixel: f64) -> String {
+    let magnitude = value.to_f64().value().abs().max(pixel);
+    let digits = (magnitude / pixel).log10().ceil().max(0.0) as usize + 2;
+    bigfloat::to_decimal(value, digits)
+}
+
+/// What `survey.json` records of `level`.
+fn level_record(
+    level: &Level,
+    number: usize,
+    sheet: String,
+    options: &SurveyOptions,
+) -> LevelRecord {
+    let size = level.cell_size(options.grid);
+    let pixel = size.0 / options.thumbnail as f64;
+    let center = |center: &(Big, Big)| (decimal(&center.0, pixel), decimal(&center.1, pixel));
+    let best = &level.cells[level.best];
+    LevelRecord {
+        level: number,
+        sheet,
+        center: center(&level.center),
+        radius: level.radius,
+        magnification: level.magnification,
+        max_iter: level.max_iter,
+        best: (best.column, best.row),
+        cells: level
+            .cells
+            .iter()
+            .map(|cell| CellRecord {
+                column: cell.column,
+                row: cell.row,
+                center: center(&cell.center),
+                size,
+                // A cell of one escape time scores -0.
+                score: cell.score.abs(),
+            })
+            .collect(),
+    }
+}
+
+/// Lays the thumbnails of `level` out as its cells are, each with the
+/// center of its cell and its score written under it, below a line about
+/// the level. The best cell is outlined in white.
+fn contact_sheet(level: &Level, number: usize, options: &SurveyOptions) -> RgbImage {
+    let tile = level.cells[0].thumbnail.dimensions();
+    let columns = options.grid.0 as u32;
+    let grid = Grid::new(level.cells.len(), columns, tile, LABEL_LINES * CELL.1, CELL.1);
+    let (width, height) = grid.size();
+    let mut sheet = DynamicImage::ImageRgb8(RgbImage::from_pixel(width, height, Rgb(BACKGROUND)));
+    let header = format!(
+        "level {}  zoom {}  iter {}",
+        number,
+        hud::scientific(level.magnification),
+        level.max_iter
+    );
+    hud::write(&mut sheet, GAP as i64, GAP as i64, 1, &header);
+
+    // Enough decimals to tell the centers of neighboring cells apart.
+    let (cell_width, _) = level.cell_size(options.grid);
+    let decimals = ((1.0 - cell_width.log10()).ceil() as usize).clamp(2, 15);
+    for (index, cell) in level.cells.iter().enumerate() {
+        let place = (cell.column as u32, cell.row as u32);
+        let (left, top) = grid.tile_at(place.0, place.1);
+        sheet
+            .copy_from(&DynamicImage::ImageRgb8(cell.thumbnail.clone()), left, top)
+            .expect("thumbnails fit their place on the sheet");
+        if index == level.best {
+            outline(&mut sheet, left, top, tile.0, tile.1);
+        }
+        let (re, im) = (cell.center.0.to_f64().value(), cell.center.1.to_f64().value());
+        let lines = [
+            format!("{:.*}", decimals, re),
+            format!("{:+.*}i", decimals, im),
+            format!("score {:.3}", cell.score.abs()),
+        ];
+        hud::label(&mut sheet, &grid, place, &lines);
+    }
+    sheet.to_rgb8()
+}
+
+/// Draws a white border one pixel wide just inside the rectangle of
+/// `width` by `height` pixels at `(left, top)`.
+fn outline(sheet: &mut DynamicImage, left: u32, top: u32, width: u32, height: u32) {
+    let white = image::Rgba([255, 255, 255, 255]);
+    for x in left..left + width {
+        sheet.put_pixel(x, top, white);
+        sheet.put_pixel(x, top + height - 1, white);
+    }
+    for y in top..top + height {
+        sheet.put_pixel(left, y, white);
+        sheet.put_pixel(left + width - 1, y, white);
+    }
+}
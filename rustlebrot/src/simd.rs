@@ -0,0 +1,233 @@
+use crate::fractal::Escape;
+use std::ops::{Add, AddAssign, BitOr, BitOrAssign, Mul, Neg, Sub};
+use wide::{f32x8, f64x4};
+
+/// A vector of floats the escape-time loop runs on, with a point in every
+/// lane.
+pub trait Lanes:
+    Copy
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Neg<Output = Self>
+    + AddAssign
+    + BitOr<Output = Self>
+    + BitOrAssign
+{
+    /// The floats in the lanes.
+    type Float: Copy + Into<f64>;
+    /// The lanes as an array.
+    type Array: Copy + AsRef<[Self::Float]> + AsMut<[Self::Float]>;
+    /// Points iterated at once.
+    const LANES: usize;
+
+    /// `value`, rounded to `Float`.
+    fn float(value: f64) -> Self::Float;
+    fn splat(value: f64) -> Self;
+    fn from_array(array: Self::Array) -> Self;
+    fn to_array(self) -> Self::Array;
+    fn simd_gt(self, other: Self) -> Self;
+    fn simd_lt(self, other: Self) -> Self;
+    fn simd_ge(self, other: Self) -> Self;
+    fn simd_eq(self, other: Self) -> Self;
+    /// The lanes of `if_one` where the mask `self` is set, and of
+    /// `if_zero` elsewhere.
+    fn bitselect(self, if_one: Self, if_zero: Self) -> Self;
+    /// The mask `self`, one bit per lane.
+    fn to_bitmask(self) -> u32;
+}
+
+macro_rules! lanes {
+    ($vector:ty, $float:ty, $lanes:expr) => {
+        impl Lanes for $vector {
+            type Float = $float;
+            type Array = [$float; $lanes];
+            const LANES: usize = $lanes;
+
+            #[inline(always)]
+            fn float(value: f64) -> $float {
+                value as $float
+            }
+            #[inline(always)]
+            fn splat(value: f64) -> Self {
+                <$vector>::splat(value as $float)
+            }
+            #[inline(always)]
+            fn from_array(array: Self::Array) -> Self {
+                <$vector>::new(array)
+            }
+            #[inline(always)]
+            fn to_array(self) -> Self::Array {
+                <$vector>::to_array(self)
+            }
+            #[inline(always)]
+            fn simd_gt(self, other: Self) -> Self {
+                <$vector>::simd_gt(self, other)
+            }
+            #[inline(always)]
+            fn simd_lt(self, other: Self) -> Self {
+                <$vector>::simd_lt(self, other)
+            }
+            #[inline(always)]
+            fn simd_ge(self, other: Self) -> Self {
+                <$vector>::simd_ge(self, other)
+            }
+            #[inline(always)]
+            fn simd_eq(self, other: Self) -> Self {
+                <$vector>::simd_eq(self, other)
+            }
+            #[inline(always)]
+            fn bitselect(self, if_one: Self, if_zero: Self) -> Self {
+                <$vector>::bitselect(self, if_one, if_zero)
+            }
+            #[inline(always)]
+            fn to_bitmask(self) -> u32 {
+                <$vector>::to_bitmask(self)
+            }
+        }
+    };
+}
+
+lanes!(f64x4, f64, 4);
+lanes!(f32x8, f32, 8);
+
+/// The escape-time loop of `fractal::escape_time` without a trap, run on
+/// the lanes of `L` at once.
+///
+/// Every lane carries its own point, iteration count and cycle check, so
+/// lanes retire independently whenever their point escapes, is found
+/// periodic or reaches `max_iter`, and are refilled with the next point of
+/// `points` straight away. Points for which `inside` holds are known not
+/// to escape and never take a lane.
+///
+/// With `f64x4`, each lane does the same floating point operations in the
+/// same order as the scalar loop, so the escapes are identical to it, not
+/// just close. With `f32x8` the points are rounded to f32 and iterated in
+/// it.
+#[inline(always)]
+pub fn escape_times<L: Lanes, const CONJUGATE: bool, const PERIODIC: bool>(
+    points: &[(f64, f64)],
+    max_iter: u32,
+    bailout: f64,
+    eps: f64,
+    inside: impl Fn((f64, f64)) -> bool,
+    escapes: &mut [Escape],
+) {
+    let interior = Escape::interior(max_iter, f64::INFINITY);
+    if max_iter == 0 {
+        escapes.fill(interior);
+        return;
+    }
+    let bailout_sqr = L::splat(bailout * bailout);
+    let eps_sqr = L::splat(eps * eps);
+    let limit = L::splat(max_iter as f64);
+    let two = L::splat(2.0);
+    let one = L::splat(1.0);
+    let zero = L::splat(0.0);
+
+    // The pixel of `escapes` each lane fills, or `None` once the points
+    // have run out.
+    let mut pixel: Vec<Option<usize>> = vec![None; L::LANES];
+    let (mut cx, mut cy) = (zero.to_array(), zero.to_array());
+    let (mut zx, mut zy) = (zero, zero);
+    let (mut saved_x, mut saved_y) = (zero, zero);
+    // Iteration counts are kept as floats, which are exact up to 2^24 even
+    // in f32, so they can be compared with the rest.
+    let mut i = zero;
+    let mut interval = L::splat(8.0);
+    let mut next_save = interval;
+
+    let widen = |value: L::Float| -> f64 { value.into() };
+    let mut next = 0;
+    // Takes the next point that has to be iterated, with its index, filling
+    // in the ones that don't on the way.
+    let refill = |next: &mut usize, escapes: &mut [Escape]| {
+        while let Some(&c) = points.get(*next) {
+            *next += 1;
+            if !inside(c) {
+                return Some((*next - 1, c));
+            }
+            escapes[*next - 1] = interior;
+        }
+        None
+    };
+    for (lane, pixel) in pixel.iter_mut().enumerate() {
+        if let Some((index, c)) = refill(&mut next, escapes) {
+            *pixel = Some(index);
+            (cx.as_mut()[lane], cy.as_mut()[lane]) = (L::float(c.0), L::float(c.1));
+        }
+    }
+    let (mut c_re, mut c_im) = (L::from_array(cx), L::from_array(cy));
+
+    // Lanes without a point are left iterating zero, which never escapes.
+    let mut active = lane_bits(&pixel);
+    while active != 0 {
+        let cross = two * zx * zy;
+        let x = zx * zx - zy * zy + c_re;
+        let y = if CONJUGATE { -cross } else { cross } + c_im;
+        let escaped = (x * x + y * y).simd_gt(bailout_sqr);
+        (zx, zy) = (x, y);
+
+        let mut done = escaped;
+        if PERIODIC {
+            let (dx, dy) = (zx - saved_x, zy - saved_y);
+            done |= (dx * dx + dy * dy).simd_lt(eps_sqr);
+            let save = i.simd_eq(next_save);
+            saved_x = save.bitselect(zx, saved_x);
+            saved_y = save.bitselect(zy, saved_y);
+            interval = save.bitselect(interval * two, interval);
+            next_save = save.bitselect(i + interval, next_save);
+        }
+        let count = i;
+        i += one;
+        done |= i.simd_ge(limit);
+        let mask = done.to_bitmask() & active;
+        if mask == 0 {
+            continue;
+        }
+
+        let (escaped, count) = (escaped.to_bitmask(), count.to_array());
+        let (x, y) = (x.to_array(), y.to_array());
+        let (mut lanes_zx, mut lanes_zy) = (zx.to_array(), zy.to_array());
+        let (mut lanes_saved_x, mut lanes_saved_y) = (saved_x.to_array(), saved_y.to_array());
+        let (mut lanes_i, mut lanes_interval, mut lanes_next_save) =
+            (i.to_array(), interval.to_array(), next_save.to_array());
+        for lane in (0..L::LANES).filter(|lane| mask & (1 << lane) != 0) {
+            if let Some(index) = pixel[lane] {
+                escapes[index] = match escaped & (1 << lane) != 0 {
+                    true => Escape::escaped(
+                        widen(count.as_ref()[lane]) as u32,
+                        (widen(x.as_ref()[lane]), widen(y.as_ref()[lane])),
+                        bailout,
+                        f64::INFINITY,
+                    ),
+                    false => interior,
+                };
+            }
+            let point = refill(&mut next, escapes);
+            pixel[lane] = point.map(|(index, _)| index);
+            let c = point.map_or((0.0, 0.0), |(_, c)| c);
+            (cx.as_mut()[lane], cy.as_mut()[lane]) = (L::float(c.0), L::float(c.1));
+            for lanes in [&mut lanes_zx, &mut lanes_zy, &mut lanes_saved_x, &mut lanes_saved_y] {
+                lanes.as_mut()[lane] = L::float(0.0);
+            }
+            lanes_i.as_mut()[lane] = L::float(0.0);
+            lanes_interval.as_mut()[lane] = L::float(8.0);
+            lanes_next_save.as_mut()[lane] = L::float(8.0);
+        }
+        (zx, zy) = (L::from_array(lanes_zx), L::from_array(lanes_zy));
+        (saved_x, saved_y) = (L::from_array(lanes_saved_x), L::from_array(lanes_saved_y));
+        (i, interval, next_save) = (
+            L::from_array(lanes_i),
+            L::from_array(lanes_interval),
+            L::from_array(lanes_next_save),
+        );
+        (c_re, c_im) = (L::from_array(cx), L::from_array(cy));
+        active = lane_bits(&pixel);
+    }
+}
+
+/// The mask of the lanes that have a pixel, one bit per lane.
+fn lane_bits(pixel: &[Option<usize>]) -> u32 {
+    (0..pixel.len()).filter(|&lane| pixel[lane].is_some()).map(|lane| 1 << lane).sum()
+}
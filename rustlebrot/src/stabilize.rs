@@ -0,0 +1,88 @@
+/// Gradient positions a reference holds the escape time of, evenly spaced
+/// from the first color to the last.
+pub const QUANTILES: usize = 65;
+
+/// The distribution of escape times histogram coloring spreads over the
+/// gradient with `--stabilize-colors`, in place of each frame's own.
+///
+/// A frame's own histogram follows every shift of its escape times, so the
+/// colors of a zoom pump as detail comes and goes. A reference instead
+/// follows them as a moving average, and only slowly: each frame moves it
+/// part of the way towards that frame's distribution, by averaging the
+/// escape times at each gradient position.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reference {
+    /// The escape time at each of `QUANTILES` positions, in order.
+    quantiles: Vec<f64>,
+}
+
+impl Reference {
+    /// The distribution of `values`, the escape times of the exterior
+    /// pixels, with `clip` percent clamped at either end as `Histogram`
+    /// clamps them, or `None` without any.
+    pub fn of<I: Iterator<Item = f64>>(values: I, clip: f64) -> Option<Reference> {
+        let mut sorted: Vec<f64> = values.collect();
+        if sorted.is_empty() {
+            return None;
+        }
+        sorted.sort_unstable_by(f64::total_cmp);
+        let n = sorted.len();
+        let cut = ((n as f64 * clip / 100.0) as usize).min((n - 1) / 2);
+        let span = (n - 1 - 2 * cut) as f64;
+        let quantiles = (0..QUANTILES).map(|index| {
+            let at = cut + (span * index as f64 / (QUANTILES - 1) as f64).round() as usize;
+            sorted[at]
+        });
+        Some(Reference {
+            quantiles: quantiles.collect(),
+        })
+    }
+
+    /// The reference of the escape times `quantiles` returns, as a manifest
+    /// records them.
+    pub fn from_quantiles(quantiles: Vec<f64>) -> Result<Reference, String> {
+        if quantiles.len() != QUANTILES {
+            return Err(format!(
+                "a color reference has {} escape times, got {}",
+                QUANTILES,
+                quantiles.len()
+            ));
+        }
+        if !quantiles.iter().all(|value| value.is_finite())
+            || !quantiles.windows(2).all(|pair| pair[0] <= pair[1])
+        {
+            return Err("the escape times of a color reference go up".to_string());
+        }
+        Ok(Reference { quantiles })
+    }
+
+    /// The escape time at each of `QUANTILES` evenly spaced positions.
+    pub fn quantiles(&self) -> &[f64] {
+        &self.quantiles
+    }
+
+    /// The reference moved `weight` of the way towards `frame`, 1 taking
+    /// `frame` as it is.
+    pub fn follow(&self, frame: &Reference, weight: f64) -> Reference {
+        let quantiles = self.quantiles.iter().zip(&frame.quantiles);
+        Reference {
+            quantiles: quantiles.map(|(old, new)| old + weight * (new - old)).collect(),
+        }
+    }
+
+    /// The gradient position of a pixel that escaped after `value`
+    /// iterations, between those of the escape times around it.
+    pub fn position(&self, value: f64) -> f64 {
+        let quantiles = &self.quantiles;
+        let above = quantiles.partition_point(|&quantile| quantile <= value);
+        if above == 0 {
+            return 0.0;
+        }
+        if above == QUANTILES {
+            return 1.0;
+        }
+        let (low, high) = (quantiles[above - 1], quantiles[above]);
+        let between = (value - low) / (high - low);
+        (above - 1) as f64 / (QUANTILES - 1) as f64 + between / (QUANTILES - 1) as f64
+    }
+}
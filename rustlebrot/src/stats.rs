@@ -0,0 +1,117 @@
+use crate::render::{EscapeBuffer, Sample};
+
+/// Samples escaping within this many iterations count as escaping fast.
+/// A frame where most do is mostly empty plane.
+pub const FAST_ESCAPE: f64 = 10.0;
+
+/// Statistics of the samples of a frame, gathered from its escape buffer
+/// before it is colored.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameStats {
+    /// The escape times of the samples that escaped, if any did.
+    pub escape: Option<EscapeStats>,
+    /// The fraction of the samples that never escaped. When this drops off
+    /// while the frame still looks full of structure, max_iter is too low.
+    pub interior: f64,
+    /// The standard deviation of the escape times of all samples, counting
+    /// the interior at max_iter. Frames of nothing but interior or a single
+    /// band spread by next to nothing.
+    pub spread: f64,
+}
+
+/// The escape times of the samples of a frame that escaped.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EscapeStats {
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub std_dev: f64,
+    /// The fraction of all samples that escaped within `FAST_ESCAPE`
+    /// iterations.
+    pub fast: f64,
+}
+
+impl FrameStats {
+    /// The statistics of the samples of `buffer`, leaving out the ones
+    /// adaptive anti-aliasing added, or `None` when its values aren't
+    /// escape times. Distance estimates and trap distances don't tell the
+    /// interior apart either.
+    pub fn of(buffer: &EscapeBuffer) -> Option<FrameStats> {
+        if !buffer.coloring.uses_escape_times() {
+            return None;
+        }
+        let interior = buffer
+            .values
+            .iter()
+            .filter(|sample| matches!(sample, Sample::Interior | Sample::Attractor { .. }))
+            .count();
+        let times: Vec<f64> = buffer
+            .values
+            .iter()
+            .map(|sample| match *sample {
+                Sample::Value(time)
+                | Sample::Phase { value: time, .. }
+                | Sample::Root { iterations: time, .. }
+                | Sample::Exponent(time) => time,
+                Sample::Interior | Sample::Attractor { .. } | Sample::Boundary => {
+                    buffer.max_iter as f64
+                }
+            })
+            .collect();
+        Some(FrameStats {
+            escape: escape_stats(buffer),
+            interior: interior as f64 / buffer.values.len().max(1) as f64,
+            spread: std_dev(&times),
+        })
+    }
+}
+
+/// Settings of the early stop, which ends a zoom once its frames have been
+/// uniform for a while, as when the center drifted off the boundary.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EarlyStop {
+    /// Consecutive uniform frames after which the zoom ends.
+    pub frames: u32,
+    /// The `FrameStats::spread` under which a frame counts as uniform, in
+    /// iterations.
+    pub spread: f64,
+}
+
+impl EarlyStop {
+    pub fn is_uniform(&self, stats: &FrameStats) -> bool {
+        stats.spread < self.spread
+    }
+}
+
+/// The standard deviation of `values`, around their mean, which stays
+/// accurate where the sum of squares wouldn't.
+fn std_dev(values: &[f64]) -> f64 {
+    let count = values.len().max(1) as f64;
+    let mean = values.iter().sum::<f64>() / count;
+    (values.iter().map(|value| (value - mean) * (value - mean)).sum::<f64>() / count).sqrt()
+}
+
+fn escape_stats(buffer: &EscapeBuffer) -> Option<EscapeStats> {
+    let escaped: Vec<f64> = buffer
+        .values
+        .iter()
+        .filter_map(|sample| match *sample {
+            Sample::Value(time)
+            | Sample::Phase { value: time, .. }
+            | Sample::Root { iterations: time, .. }
+            | Sample::Exponent(time) => Some(time),
+            Sample::Interior | Sample::Attractor { .. } | Sample::Boundary => None,
+        })
+        .collect();
+    if escaped.is_empty() {
+        return None;
+    }
+    let fast = escaped.iter().filter(|&&time| time <= FAST_ESCAPE).count();
+    Some(EscapeStats {
+        min: escaped.iter().copied().fold(f64::INFINITY, f64::min),
+        max: escaped.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        mean: escaped.iter().sum::<f64>() / escaped.len() as f64,
+        std_dev: std_dev(&escaped),
+        fast: fast as f64 / buffer.values.len() as f64,
+    })
+}
@@ -0,0 +1,225 @@
+use crate::error::RustlebrotError;
+use crate::export::{self, PngStream};
+use crate::fractal::Fractal;
+use crate::render::{
+    colorize, compute_escape, BitDepth, ColorOptions, EscapeBuffer, RenderOptions, Sample, Window,
+};
+use image::DynamicImage;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
+/// The version of the tile manifest format.
+const MANIFEST_VERSION: u32 = 1;
+
+/// An image rendered in tiles, as with the `still` subcommand, so it can be
+/// far larger than what fits in memory at once.
+pub struct Still<'a> {
+    pub width: u32,
+    pub height: u32,
+    /// Samples along each side of a pixel, which are computed at the
+    /// positions they'd have in a supersampled render of the whole image.
+    pub samples: u32,
+    /// Pixels along each side of a tile. The tiles of the last column and
+    /// row are cut to fit.
+    pub tile_size: u32,
+    /// Rows of pixels in each row of tiles, which `write_png` holds one of
+    /// at a time. Tiles saved as files are square, with this the tile size.
+    pub band_height: u32,
+    /// The ranges of the whole image, which every tile is placed in.
+    pub x_range: (f64, f64),
+    pub y_range: (f64, f64),
+    pub options: RenderOptions<'a>,
+    pub colors: ColorOptions<'a>,
+}
+
+/// One tile of a still, by column and row from the top left, with the
+/// pixels of the image it covers.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct TileRect {
+    pub column: u32,
+    pub row: u32,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// The manifest written next to the tiles of a still, which says how they
+/// stitch together into the image.
+#[derive(Serialize)]
+struct TileManifest<'a> {
+    version: u32,
+    software: String,
+    width: u32,
+    height: u32,
+    tile_size: u32,
+    x_range: (f64, f64),
+    y_range: (f64, f64),
+    max_iter: u32,
+    tiles: Vec<TileRecord<'a>>,
+}
+
+#[derive(Serialize)]
+struct TileRecord<'a> {
+    /// The tile's PNG, relative to the manifest.
+    path: &'a str,
+    #[serde(flatten)]
+    rect: TileRect,
+}
+
+impl Still<'_> {
+    /// The tiles of every row, from the top, each from left to right.
+    pub fn tile_rows(&self) -> Vec<Vec<TileRect>> {
+        let (size, rows) = (self.tile_size, self.band_height);
+        let rect = |column: u32, row: u32| {
+            let (x, y) = (column * size, row * rows);
+            TileRect {
+                column,
+                row,
+                x,
+                y,
+                width: size.min(self.width - x),
+                height: rows.min(self.height - y),
+            }
+        };
+        let columns = self.width.div_ceil(size);
+        (0..self.height.div_ceil(rows))
+            .map(|row| (0..columns).map(|column| rect(column, row)).collect())
+            .collect()
+    }
+
+    /// About how many bytes writing one PNG takes at most: the colors of a
+    /// band of tiles, and the samples of the tile being rendered.
+    pub fn band_memory(&self) -> u64 {
+        let rows = self.band_height.min(self.height) as u64;
+        let channel = match self.colors.bit_depth {
+            BitDepth::Eight => 1,
+            BitDepth::Sixteen => 2,
+        };
+        let samples = (self.samples * self.samples) as u64;
+        let tile = self.tile_size.min(self.width) as u64 * rows * samples;
+        3 * channel * self.width as u64 * rows + size_of::<Sample>() as u64 * tile
+    }
+
+    /// Renders and colors `tile`. Its pixels are placed from the ranges of
+    /// the whole image, so they're exactly the ones a render of the whole
+    /// image would have there.
+    pub fn render_tile<F: Fractal>(&self, fractal: &F, tile: &TileRect) -> DynamicImage {
+        let samples = self.samples;
+        let options = RenderOptions {
+            window: Some(Window {
+                frame: (self.width * samples, self.height * samples),
+                origin: (tile.x * samples, tile.y * samples),
+            }),
+            ..self.options
+        };
+        let (x_range, y_range) = (self.x_range, self.y_range);
+        let (width, height) = (tile.width * samples, tile.height * samples);
+        let buffer = compute_escape(fractal, width, height, x_range, y_range, &options);
+        colorize(&EscapeBuffer { samples, ..buffer }, &self.colors)
+    }
+
+    /// Renders the whole image at once, uncolored, for coloring it more
+    /// than once.
+    pub fn compute<F: Fractal>(&self, fractal: &F) -> EscapeBuffer {
+        let samples = self.samples;
+        let (width, height) = (self.width * samples, self.height * samples);
+        let (x_range, y_range) = (self.x_range, self.y_range);
+        let buffer = compute_escape(fractal, width, height, x_range, y_range, &self.options);
+        EscapeBuffer { samples, ..buffer }
+    }
+
+    /// The text chunks of the image, or with `tile`, of that tile.
+    fn text(&self, tile: Option<&TileRect>) -> Vec<(&'static str, String)> {
+        let mut text = vec![
+            ("Software", format!("rustlebrot {}", env!("CARGO_PKG_VERSION"))),
+            ("X Range", format!("{:?},{:?}", self.x_range.0, self.x_range.1)),
+            ("Y Range", format!("{:?},{:?}", self.y_range.0, self.y_range.1)),
+            ("Max Iter", self.options.max_iter.to_string()),
+            ("Supersample", self.samples.to_string()),
+        ];
+        if let Some(tile) = tile {
+            text.push(("Tile", format!("{},{}", tile.column, tile.row)));
+        }
+        text
+    }
+}
+
+/// The file name of `tile` in the directory of the tiles.
+fn tile_name(tile: &TileRect) -> String {
+    format!("tile_{:04}_{:04}.png", tile.row, tile.column)
+}
+
+/// Renders `still` into one PNG at `path`, a row of tiles at a time, so
+/// only one row of them is ever held, see `Still::band_memory`.
+pub fn write_png<F: Fractal>(
+    fractal: &F,
+    still: &Still,
+    path: &str,
+    overwrite: bool,
+) -> Result<(), RustlebrotError> {
+    check_new(path, overwrite)?;
+    let depth = still.colors.bit_depth;
+    let mut png = PngStream::create(path, still.width, still.height, depth, &still.text(None))?;
+    let rows = still.tile_rows();
+    for (row, tiles) in rows.iter().enumerate() {
+        let start = Instant::now();
+        let band: Vec<DynamicImage> =
+            tiles.iter().map(|tile| still.render_tile(fractal, tile)).collect();
+        png.write_band(&band)?;
+        println!("Rendered row {} of {} of tiles in {:.2?}", row + 1, rows.len(), start.elapsed());
+    }
+    png.finish()
+}
+
+/// Renders `still` into a PNG of every tile in `dir`, along with
+/// `tiles.json`, which says where each goes in the image.
+pub fn write_tiles<F: Fractal>(
+    fractal: &F,
+    still: &Still,
+    dir: &str,
+    overwrite: bool,
+) -> Result<(), RustlebrotError> {
+    let tiles: Vec<TileRect> = still.tile_rows().into_iter().flatten().collect();
+    let names: Vec<String> = tiles.iter().map(tile_name).collect();
+    let manifest_path = format!("{}/tiles.json", dir);
+    check_new(&manifest_path, overwrite)?;
+    for name in &names {
+        check_new(&format!("{}/{}", dir, name), overwrite)?;
+    }
+    fs::create_dir_all(dir).map_err(|e| RustlebrotError::write(dir, e))?;
+    for (tile, name) in tiles.iter().zip(&names) {
+        let start = Instant::now();
+        let path = format!("{}/{}", dir, name);
+        let img = still.render_tile(fractal, tile);
+        export::save_png_text(&path, &img, &still.text(Some(tile)))?;
+        println!("Tile {} saved in {:.2?}", path, start.elapsed());
+    }
+    let manifest = TileManifest {
+        version: MANIFEST_VERSION,
+        software: format!("rustlebrot {}", env!("CARGO_PKG_VERSION")),
+        width: still.width,
+        height: still.height,
+        tile_size: still.tile_size,
+        x_range: still.x_range,
+        y_range: still.y_range,
+        max_iter: still.options.max_iter,
+        tiles: tiles.iter().zip(&names).map(|(&rect, path)| TileRecord { path, rect }).collect(),
+    };
+    let json = serde_json::to_string_pretty(&manifest)
+        .map_err(|e| RustlebrotError::encode(&manifest_path, e))?;
+    fs::write(&manifest_path, json + "\n").map_err(|e| RustlebrotError::write(&manifest_path, e))
+}
+
+/// Fails if `path` exists already, unless `overwrite` allows replacing it.
+pub fn check_new(path: &str, overwrite: bool) -> Result<(), RustlebrotError> {
+    match !overwrite && Path::new(path).exists() {
+        true => Err(RustlebrotError::Argument(format!(
+            "{} exists already; pass --overwrite to replace it",
+            path
+        ))),
+        false => Ok(()),
+    }
+}
@@ -0,0 +1,248 @@
+use crate::coloring::Coloring;
+use crate::error::RustlebrotError;
+use crate::fractal::Mandelbrot;
+use crate::precision::Precision;
+use crate::render::{self, EscapeBuffer, RenderOptions, Window};
+use futures_core::Stream;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::thread;
+use std::time::Instant;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+
+/// The frames of a `ZoomJob` on their way to its stream.
+type Frames = Sender<Result<Frame, RustlebrotError>>;
+
+/// Rows of a frame computed at once. Between bands the job looks for its
+/// stream having been dropped, so this bounds the work done after that.
+const BAND_HEIGHT: u32 = 32;
+
+/// What the pixels of the frames of a `ZoomJob` are handed over as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    Png,
+    /// JPEG at a quality from 1 to 100.
+    Jpeg(u8),
+    /// RGBA, 8 bits a channel, row by row from the top.
+    Raw,
+}
+
+/// A zoom into the Mandelbrot set in f64, smooth coloring and the default
+/// palette, as `render_region` renders one view.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ZoomOptions {
+    pub width: u32,
+    pub height: u32,
+    pub center: (f64, f64),
+    /// The distance between pixels at frame 0.
+    pub scale: f64,
+    /// How much closer every frame is than the one before.
+    pub zoom_factor: f64,
+    pub frames: u32,
+    pub max_iter: u32,
+    pub encoding: Encoding,
+    /// Frames rendered ahead of the consumer at most, waiting in the
+    /// stream. The job pauses once they're all rendered.
+    pub ahead: usize,
+}
+
+impl Default for ZoomOptions {
+    fn default() -> Self {
+        ZoomOptions {
+            width: 640,
+            height: 480,
+            center: (-0.743643887037151, 0.131825904205330),
+            scale: 4.0 / 640.0,
+            zoom_factor: 1.05,
+            frames: 300,
+            max_iter: 1000,
+            encoding: Encoding::Jpeg(85),
+            ahead: 2,
+        }
+    }
+}
+
+/// A frame of a `ZoomJob`, with where it is and how it was rendered.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    /// The frame's number, counting from 0.
+    pub index: u32,
+    /// The pixels, as the job's `Encoding` says.
+    pub bytes: Vec<u8>,
+    pub magnification: f64,
+    pub x_range: (f64, f64),
+    pub y_range: (f64, f64),
+    pub max_iter: u32,
+    /// Wall time of the frame, in seconds, colored and encoded.
+    pub seconds: f64,
+}
+
+/// How far a `ZoomJob` has got, which can be kept after the job is dropped.
+#[derive(Clone, Debug, Default)]
+pub struct Progress {
+    frames: Arc<AtomicU32>,
+    finished: Arc<AtomicBool>,
+}
+
+impl Progress {
+    /// The frames rendered and handed to the stream so far.
+    pub fn frames(&self) -> u32 {
+        self.frames.load(Ordering::Relaxed)
+    }
+
+    /// Whether the job has stopped, at its last frame, at an error, or
+    /// because its stream was dropped.
+    pub fn finished(&self) -> bool {
+        self.finished.load(Ordering::Acquire)
+    }
+}
+
+/// A zoom rendered on a thread of its own, its frames taken in order as a
+/// stream, from any async runtime.
+///
+/// The frames are computed by the rayon pool in bands of rows and handed
+/// over through a channel of `ahead` frames: a consumer that falls behind
+/// pauses the rendering instead of piling frames up, and dropping the
+/// stream stops it at the end of the band under way. A frame that can't be
+/// rendered is the last item of the stream, as its error.
+pub struct ZoomJob {
+    frames: Receiver<Result<Frame, RustlebrotError>>,
+    progress: Progress,
+}
+
+impl ZoomJob {
+    /// Starts rendering the zoom of `options`. Fails if its frames have no
+    /// pixels.
+    pub fn stream(options: ZoomOptions) -> Result<ZoomJob, RustlebrotError> {
+        RustlebrotError::check_size(options.width, options.height)?;
+        let (sender, frames) = mpsc::channel(options.ahead.max(1));
+        let progress = Progress::default();
+        let job = progress.clone();
+        thread::Builder::new()
+            .name("rustlebrot-zoom".to_string())
+            .spawn(move || {
+                run(&options, &sender, &job.frames);
+                job.finished.store(true, Ordering::Release);
+            })
+            .map_err(|e| RustlebrotError::System(format!("can't start a zoom thread: {}", e)))?;
+        Ok(ZoomJob { frames, progress })
+    }
+
+    /// The next frame, or `None` after the last.
+    pub async fn next(&mut self) -> Option<Result<Frame, RustlebrotError>> {
+        self.frames.recv().await
+    }
+
+    pub fn progress(&self) -> Progress {
+        self.progress.clone()
+    }
+}
+
+impl Stream for ZoomJob {
+    type Item = Result<Frame, RustlebrotError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.frames.poll_recv(cx)
+    }
+}
+
+/// Renders the frames of `options` into `sender` until the last, an error,
+/// or the stream is dropped, counting them in `frames`.
+fn run(options: &ZoomOptions, sender: &Frames, frames: &AtomicU32) {
+    for index in 0..options.frames {
+        let frame = match render_frame(options, index, sender) {
+            Some(frame) => frame,
+            None => return,
+        };
+        let failed = frame.is_err();
+        // Waits while the stream is full, and fails once it's dropped.
+        if sender.blocking_send(frame).is_err() || failed {
+            return;
+        }
+        frames.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Frame `index` of `options`, or `None` if the stream of `sender` was
+/// dropped while it was rendered.
+fn render_frame(
+    options: &ZoomOptions,
+    index: u32,
+    sender: &Frames,
+) -> Option<Result<Frame, RustlebrotError>> {
+    let start = Instant::now();
+    let (width, height) = (options.width, options.height);
+    let magnification = options.zoom_factor.powi(index as i32);
+    let pixel_size = options.scale / magnification;
+    if !Precision::F64.resolves(options.center, pixel_size) {
+        return Some(Err(RustlebrotError::PrecisionExhausted {
+            frame: index,
+            precision: Precision::F64,
+        }));
+    }
+    let half_width = width as f64 * pixel_size / 2.0;
+    let half_height = height as f64 * pixel_size / 2.0;
+    let (x, y) = options.center;
+    let x_range = (x - half_width, x + half_width);
+    let y_range = (y - half_height, y + half_height);
+    let mut values = Vec::with_capacity(width as usize * height as usize);
+    for top in (0..height).step_by(BAND_HEIGHT as usize) {
+        if sender.is_closed() {
+            return None;
+        }
+        let render_options = RenderOptions {
+            window: Some(Window {
+                frame: (width, height),
+                origin: (0, top),
+            }),
+            ..crate::smooth_options(options.max_iter)
+        };
+        let rows = BAND_HEIGHT.min(height - top);
+        let band =
+            render::compute_escape(&Mandelbrot, width, rows, x_range, y_range, &render_options);
+        values.extend(band.values);
+    }
+    let buffer = EscapeBuffer {
+        width,
+        height,
+        samples: 1,
+        refined: HashMap::new(),
+        max_iter: options.max_iter,
+        coloring: Coloring::Smooth,
+        values,
+        orbits: Vec::new(),
+    };
+    let (colormap, cycle) = crate::default_colormap();
+    let colors = crate::default_colors(&colormap, cycle, options.max_iter);
+    let image = render::colorize(&buffer, &colors);
+    let mut bytes = Cursor::new(Vec::new());
+    let encoded = match options.encoding {
+        Encoding::Png => image.write_with_encoder(PngEncoder::new(&mut bytes)),
+        Encoding::Jpeg(quality) => {
+            image.to_rgb8().write_with_encoder(JpegEncoder::new_with_quality(&mut bytes, quality))
+        }
+        Encoding::Raw => {
+            bytes = Cursor::new(image.to_rgba8().into_raw());
+            Ok(())
+        }
+    };
+    // The frame has no file, so it goes by its number.
+    let frame = encoded
+        .map_err(|e| RustlebrotError::encode(&format!("frame {}", index), e))
+        .map(|_| Frame {
+            index,
+            bytes: bytes.into_inner(),
+            magnification,
+            x_range,
+            y_range,
+            max_iter: options.max_iter,
+            seconds: start.elapsed().as_secs_f64(),
+        });
+    Some(frame)
+}
@@ -0,0 +1,59 @@
+use crate::fractal::Escape;
+
+/// The stripe density of `--coloring stripes` unless `--stripe-density`
+/// says otherwise.
+pub const DEFAULT_DENSITY: f64 = 5.0;
+
+/// The running stripe average of an orbit, for stripe average coloring.
+///
+/// Every point of the orbit after `c` itself, whose angle would only draw
+/// rays out of the origin, adds `0.5 + 0.5 * sin(density * arg(z))`, and
+/// the average of those is the value of the pixel. The average jumps by a
+/// whole term from one escape time band to the next, so at escape it is
+/// blended between the averages with and without the last point by the
+/// fraction of an iteration the orbit overshot the bailout by, the same
+/// fraction smooth coloring interpolates with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StripeAverage {
+    density: f64,
+    sum: f64,
+    last: f64,
+    count: u32,
+}
+
+impl StripeAverage {
+    pub fn new(density: f64) -> StripeAverage {
+        StripeAverage {
+            density,
+            sum: 0.0,
+            last: 0.0,
+            count: 0,
+        }
+    }
+
+    /// Adds the point `z` of the orbit.
+    #[inline]
+    pub fn add(&mut self, z: (f64, f64)) {
+        self.last = 0.5 + 0.5 * (self.density * z.1.atan2(z.0)).sin();
+        self.sum += self.last;
+        self.count += 1;
+    }
+
+    /// The stripe average of an orbit that reached `z` past `bailout` on
+    /// iteration `i`, with `z` added already. An orbit that escapes on its
+    /// first step has nothing to average, and is given the middle value.
+    #[inline]
+    pub fn escaped(&self, i: u32, z: (f64, f64), bailout: f64) -> f64 {
+        if self.count == 0 {
+            return 0.5;
+        }
+        let escape = Escape::escaped(i, z, bailout, f64::INFINITY);
+        let fraction = (escape.smooth - escape.iterations).clamp(0.0, 1.0);
+        let average = self.sum / self.count as f64;
+        let before = match self.count {
+            1 => average,
+            count => (self.sum - self.last) / (count - 1) as f64,
+        };
+        before + fraction * (average - before)
+    }
+}
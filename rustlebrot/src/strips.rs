@@ -0,0 +1,178 @@
+use crate::dither::Dither;
+use crate::error::RustlebrotError;
+use crate::events;
+use crate::export;
+use crate::expmap::{ExpMap, Strip, View, STRIP_RINGS};
+use crate::fractal::Fractal;
+use crate::render::{colorize, compute_rings, Alpha, BitDepth, ColorOptions, RenderOptions};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Version of the map file. It changes whenever a field is removed or
+/// changes meaning, and strips saved under another version are rendered
+/// again.
+pub const MAP_VERSION: u32 = 2;
+
+/// What the strips in the `expmap` directory of a run were rendered from,
+/// saved as `map.json` beside them.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct MapFile {
+    version: u32,
+    /// The center of the map with every digit.
+    center: (String, String),
+    angles: u32,
+    /// The settings that change the colors of the strips, by name.
+    settings: BTreeMap<String, serde_json::Value>,
+    /// The strips saved, by index, with the iteration limit of each.
+    strips: BTreeMap<i32, u32>,
+}
+
+/// The strips of the exponential map a zoom is resampled from with
+/// `--expmap`, saved in a directory and read back as frames need them.
+pub struct Strips {
+    pub map: ExpMap,
+    dir: String,
+    /// The strips read already, with when each was last needed, up to
+    /// `capacity` of them.
+    cache: Mutex<BTreeMap<i32, (u64, Arc<Strip>)>>,
+    uses: AtomicU64,
+    capacity: usize,
+}
+
+/// The strip `index` of the map in `dir`.
+fn strip_path(dir: &str, index: i32) -> String {
+    format!("{}/strip_{:+05}.png", dir, index)
+}
+
+impl Strips {
+    /// The strips of `map` around `center` in `dir`, rendering those of
+    /// `budgets` that aren't saved there already with the same iteration
+    /// limit and `settings`. Each is rendered with `max_iter` from
+    /// `budgets` and colored with `colors` at 16 bits, without dithering,
+    /// so that frames are only rounded once.
+    ///
+    /// At most `capacity` strips are kept in memory at once.
+    #[allow(clippy::too_many_arguments)]
+    pub fn prepare<F: Fractal>(
+        fractal: &F,
+        map: ExpMap,
+        center: &(String, String),
+        dir: &str,
+        settings: BTreeMap<String, serde_json::Value>,
+        budgets: &BTreeMap<i32, u32>,
+        options: &RenderOptions,
+        colors: &ColorOptions,
+        capacity: usize,
+    ) -> Result<Strips, RustlebrotError> {
+        fs::create_dir_all(dir).map_err(|e| RustlebrotError::write(dir, e))?;
+        let path = format!("{}/map.json", dir);
+        let mut file = MapFile {
+            version: MAP_VERSION,
+            center: center.clone(),
+            angles: map.angles,
+            settings,
+            strips: BTreeMap::new(),
+        };
+        // A map of anything else is rendered again from scratch.
+        if let Ok(json) = fs::read_to_string(&path) {
+            if let Ok(saved) = serde_json::from_str::<MapFile>(&json) {
+                let key = |file: &MapFile| {
+                    (file.version, file.center.clone(), file.angles, file.settings.clone())
+                };
+                if key(&saved) == key(&file) {
+                    file.strips = saved.strips;
+                }
+            }
+        }
+        let missing: Vec<(i32, u32)> = budgets
+            .iter()
+            .filter(|&(index, max_iter)| file.strips.get(index) != Some(max_iter))
+            .map(|(&index, &max_iter)| (index, max_iter))
+            .collect();
+        if missing.len() < budgets.len() {
+            events::say(format!(
+                "Reusing {} of the {} strips of the map in {}",
+                budgets.len() - missing.len(),
+                budgets.len(),
+                dir
+            ));
+        }
+        let colors = ColorOptions {
+            bit_depth: BitDepth::Sixteen,
+            dither: Dither::None,
+            alpha: Alpha::None,
+            ..*colors
+        };
+        for (index, max_iter) in missing {
+            let start = Instant::now();
+            let options = RenderOptions { max_iter, ..*options };
+            let rings = compute_rings(fractal, &map, index, &options);
+            let strip = strip_path(dir, index);
+            export::save_png_text(&strip, &colorize(&rings, &colors), &[])?;
+            // Saved after every strip, so a run that's stopped keeps them.
+            file.strips.insert(index, max_iter);
+            let json =
+                serde_json::to_string_pretty(&file).map_err(|e| RustlebrotError::encode(&path, e))?;
+            fs::write(&path, json).map_err(|e| RustlebrotError::write(&path, e))?;
+            events::say(format!(
+                "Strip {} rendered at max_iter {} in {:.2?}, radii {:.3e} to {:.3e}",
+                index,
+                max_iter,
+                start.elapsed(),
+                map.radius(index as f64 * STRIP_RINGS as f64),
+                map.radius((index + 1) as f64 * STRIP_RINGS as f64),
+            ));
+        }
+        Ok(Strips {
+            map,
+            dir: dir.to_string(),
+            cache: Mutex::new(BTreeMap::new()),
+            uses: AtomicU64::new(0),
+            capacity: capacity.max(1),
+        })
+    }
+
+    /// The strip `index`, read from its file unless it's in memory, when
+    /// the one needed longest ago makes way for it.
+    fn strip(&self, index: i32) -> Result<Arc<Strip>, RustlebrotError> {
+        let used = self.uses.fetch_add(1, Ordering::Relaxed);
+        if let Some((last, strip)) = self.cache.lock().unwrap().get_mut(&index) {
+            *last = used;
+            return Ok(strip.clone());
+        }
+        let path = strip_path(&self.dir, index);
+        let img = image::open(&path).map_err(|e| RustlebrotError::format(&path, e))?;
+        let strip = Strip::new(index, self.map.angles, &img)
+            .map_err(|e| RustlebrotError::format(&path, e))?;
+        let strip = Arc::new(strip);
+        let mut cache = self.cache.lock().unwrap();
+        while cache.len() >= self.capacity {
+            let oldest = cache.iter().min_by_key(|(_, (last, _))| *last).map(|(&index, _)| index);
+            cache.remove(&oldest.expect("a full cache has strips"));
+        }
+        cache.insert(index, (used, strip.clone()));
+        Ok(strip)
+    }
+
+    /// The frame of `view`, resampled from the strips it takes in and
+    /// rounded to `bit_depth` as `dither` says.
+    pub fn frame(
+        &self,
+        view: &View,
+        bit_depth: BitDepth,
+        dither: Dither,
+    ) -> Result<DynamicImage, RustlebrotError> {
+        let strips: Vec<Arc<Strip>> =
+            self.map.strips(view).map(|index| self.strip(index)).collect::<Result<_, _>>()?;
+        let strips: Vec<&Strip> = strips.iter().map(|strip| strip.as_ref()).collect();
+        Ok(match bit_depth {
+            BitDepth::Eight => self.map.reproject::<u8>(view, &strips, dither),
+            BitDepth::Sixteen => self.map.reproject::<u16>(view, &strips, dither),
+        })
+    }
+}
@@ -0,0 +1,139 @@
+use crate::bigfloat::{self, Big};
+use crate::fractal::Fractal;
+use crate::target;
+use image::RgbImage;
+
+/// Settings for the `survey` subcommand.
+pub struct SurveyOptions {
+    /// Cells across and down the region.
+    pub grid: (usize, usize),
+    /// Levels surveyed, each of the best cell of the one before.
+    pub depth: usize,
+    /// Iteration budget of the first level. It grows with the magnification
+    /// like `--auto-iter`.
+    pub max_iter: u32,
+    /// Width of the thumbnail of every cell, in pixels.
+    pub thumbnail: usize,
+}
+
+/// A cell of a level of a survey.
+pub struct Cell {
+    pub column: usize,
+    pub row: usize,
+    pub center: (Big, Big),
+    /// How interesting the cell looks, as `find-target` scores its cells.
+    pub score: f64,
+    /// The escape times of the cell, colored.
+    pub thumbnail: RgbImage,
+}
+
+/// One level of a survey: the region, split into cells.
+pub struct Level {
+    pub center: (Big, Big),
+    /// Half the width of the region, which is square.
+    pub radius: f64,
+    /// Magnification of the region relative to the first one.
+    pub magnification: f64,
+    pub max_iter: u32,
+    /// The cells, row by row from the top.
+    pub cells: Vec<Cell>,
+    /// The cell with the highest score, which the next level is of.
+    pub best: usize,
+}
+
+impl Level {
+    /// The width and height of a cell in the plane.
+    pub fn cell_size(&self, grid: (usize, usize)) -> (f64, f64) {
+        let side = 2.0 * self.radius;
+        (side / grid.0 as f64, side / grid.1 as f64)
+    }
+}
+
+/// Surveys the square region `radius` around `center`, split into a grid
+/// of cells, and then the best of its cells, and so on for
+/// `options.depth` levels.
+///
+/// Every level is probed in one grid of escape times, by perturbation from
+/// the orbit of its center, with `options.thumbnail` probes across every
+/// cell. Each cell is scored by the interest of its probes and colored
+/// into its thumbnail. The next level is the square around the best cell,
+/// as wide as the cell is across its longer side.
+pub fn survey<F: Fractal>(
+    fractal: &F,
+    center: (Big, Big),
+    radius: f64,
+    options: &SurveyOptions,
+) -> Vec<Level> {
+    let (columns, rows) = options.grid;
+    let mut center = center;
+    let mut radius = radius;
+    let mut levels: Vec<Level> = Vec::new();
+
+    for _ in 0..options.depth {
+        let magnification = levels.first().map_or(1.0, |first| first.radius / radius);
+        let max_iter = (options.max_iter as f64 * (1.0 + magnification.log10().max(0.0))).round();
+        let max_iter = max_iter as u32;
+        let pixel = 2.0 * radius / (columns * options.thumbnail) as f64;
+        // Cells as tall as they are wide in probes when the grid is square.
+        let cell_height = ((2.0 * radius / rows as f64) / pixel).round().max(1.0) as usize;
+        let cell = (options.thumbnail, cell_height);
+        let approx = (center.0.to_f64().value(), center.1.to_f64().value());
+        let bits = bigfloat::required_bits(approx, pixel);
+        center = (
+            center.0.with_precision(bits).value(),
+            center.1.with_precision(bits).value(),
+        );
+        let size = (columns * cell.0, rows * cell.1);
+        let grid = target::probe_grid(fractal, &center, bits, pixel, size, max_iter);
+        let probes = target::color_probes(&grid, size, max_iter);
+
+        // The center of a cell, in probes from the center of the grid.
+        let offset = |start: usize, side: usize, span: usize| {
+            (start as f64 + side as f64 / 2.0 - span as f64 / 2.0) * pixel
+        };
+        let cells: Vec<Cell> = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .map(|(column, row)| {
+                let start = (column * cell.0, row * cell.1);
+                let score = target::interest(&grid, size.0, start, cell, max_iter);
+                let thumbnail = image::imageops::crop_imm(
+                    &probes,
+                    start.0 as u32,
+                    start.1 as u32,
+                    cell.0 as u32,
+                    cell.1 as u32,
+                )
+                .to_image();
+                let (dx, dy) = (offset(start.0, cell.0, size.0), offset(start.1, cell.1, size.1));
+                Cell {
+                    column,
+                    row,
+                    center: (
+                        &center.0 + bigfloat::from_f64(dx, bits),
+                        &center.1 - bigfloat::from_f64(dy, bits),
+                    ),
+                    score,
+                    thumbnail,
+                }
+            })
+            .collect();
+        // The first of equally good cells, so a survey always comes out
+        // the same.
+        let best = (0..cells.len())
+            .reduce(|best, i| if cells[i].score > cells[best].score { i } else { best })
+            .expect("the grid has at least one cell");
+        let level = Level {
+            center: center.clone(),
+            radius,
+            magnification,
+            max_iter,
+            cells,
+            best,
+        };
+        let (width, height) = level.cell_size(options.grid);
+        center = level.cells[best].center.clone();
+        radius = width.max(height) / 2.0;
+        levels.push(level);
+    }
+    levels
+}
@@ -0,0 +1,280 @@
+use crate::bigfloat::{self, Big};
+use crate::fractal::Fractal;
+use crate::palette::{Adjust, Colormap, Cycle, Palette};
+use crate::render::color_gradient;
+use image::{ImageBuffer, Rgb, RgbImage};
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+
+/// Side of the square probe grid rendered at every step.
+const PROBE_SIZE: usize = 64;
+
+/// Side of a candidate sub-region, in probes. Each step zooms in by
+/// `PROBE_SIZE / CELL_SIZE`.
+const CELL_SIZE: usize = 16;
+
+/// Candidates overlap by half their size, so a feature on the border
+/// between two cells still has a candidate centered on it.
+const CELL_STRIDE: usize = CELL_SIZE / 2;
+
+/// Escape times are binned by their log2 for the entropy, so a region
+/// full of slowly growing counts doesn't look busier than it is.
+const BINS_PER_OCTAVE: f64 = 4.0;
+
+/// Settings for the `find-target` subcommand.
+pub struct TargetOptions {
+    /// Magnification to descend to, relative to the starting region.
+    pub depth: f64,
+    /// Iteration budget at the starting region. It grows with the
+    /// magnification like `--auto-iter`.
+    pub max_iter: u32,
+    /// Seeds the jitter that breaks ties between equally good candidates.
+    pub seed: u64,
+}
+
+/// The outcome of a search.
+pub struct Target {
+    pub center: (Big, Big),
+    /// Half the width of the final region.
+    pub half_width: f64,
+    /// Magnification of the final region relative to the starting one.
+    pub magnification: f64,
+    /// An iteration limit that resolves the final region.
+    pub max_iter: u32,
+    /// Every probe grid evaluated, with the chosen cell outlined.
+    pub contact: ImageBuffer<Rgb<u8>, Vec<u8>>,
+}
+
+/// Descends from the region `half_width` around `center` toward its most
+/// interesting part until `options.depth` is reached.
+///
+/// Every step renders a small probe grid by perturbation around the current
+/// center, scores overlapping sub-regions by the entropy of their escape
+/// times, and zooms into the best one. The score is weighted by how evenly
+/// a cell mixes interior and exterior probes, which steers the search onto
+/// the boundary instead of into flat interior or empty exterior, and by how
+/// smoothly escape times change between probes. The search stops early when
+/// every candidate is flat.
+pub fn find_target<F: Fractal>(
+    fractal: &F,
+    center: (Big, Big),
+    half_width: f64,
+    options: &TargetOptions,
+) -> Target {
+    let zoom_per_step = (PROBE_SIZE / CELL_SIZE) as f64;
+    let steps = options.depth.log(zoom_per_step).ceil().max(0.0) as usize;
+    let mut rng = SmallRng::seed_from_u64(options.seed);
+    let mut center = center;
+    let mut half_width = half_width;
+    let mut magnification: f64 = 1.0;
+    let mut max_iter = options.max_iter;
+    let mut grids = Vec::new();
+
+    for _ in 0..steps {
+        max_iter = (options.max_iter as f64 * (1.0 + magnification.log10().max(0.0))).round() as u32;
+        let pixel = 2.0 * half_width / PROBE_SIZE as f64;
+        let approx = (center.0.to_f64().value(), center.1.to_f64().value());
+        let bits = bigfloat::required_bits(approx, pixel / CELL_SIZE as f64);
+        center = (
+            center.0.with_precision(bits).value(),
+            center.1.with_precision(bits).value(),
+        );
+        let grid = probe(fractal, &center, bits, pixel, max_iter);
+
+        let mut best: Option<(f64, (usize, usize))> = None;
+        for cy in (0..=PROBE_SIZE - CELL_SIZE).step_by(CELL_STRIDE) {
+            for cx in (0..=PROBE_SIZE - CELL_SIZE).step_by(CELL_STRIDE) {
+                let score = score(&grid, (cx, cy), max_iter) + rng.gen::<f64>() * 1e-9;
+                if best.is_none_or(|(best, _)| score > best) {
+                    best = Some((score, (cx, cy)));
+                }
+            }
+        }
+        let (score, cell) = best.expect("the probe grid has at least one cell");
+        grids.push((grid, max_iter, cell));
+        if score < 1e-6 {
+            break;
+        }
+
+        let offset = |start: usize| (start as f64 + CELL_SIZE as f64 / 2.0) * pixel - half_width;
+        center = (
+            &center.0 + bigfloat::from_f64(offset(cell.0), bits),
+            &center.1 - bigfloat::from_f64(offset(cell.1), bits),
+        );
+        half_width /= zoom_per_step;
+        magnification *= zoom_per_step;
+    }
+
+    // The deepest grid shows how long orbits near the target take to
+    // escape; leave room for the detail further in.
+    let highest = grids
+        .last()
+        .and_then(|(grid, limit, _)| grid.iter().copied().filter(|&i| i < *limit).max())
+        .unwrap_or(0);
+    Target {
+        center,
+        half_width,
+        magnification,
+        max_iter: max_iter.max(highest * 2),
+        contact: contact_sheet(&grids),
+    }
+}
+
+/// Computes the escape times of a `PROBE_SIZE` square grid of points
+/// `pixel` apart around `center`, row by row from the top, the way frames
+/// are laid out.
+fn probe<F: Fractal>(
+    fractal: &F,
+    center: &(Big, Big),
+    bits: usize,
+    pixel: f64,
+    max_iter: u32,
+) -> Vec<u32> {
+    probe_grid(fractal, center, bits, pixel, (PROBE_SIZE, PROBE_SIZE), max_iter)
+}
+
+/// Computes the escape times of a grid of `size` points `pixel` apart
+/// around `center` like `probe`, by perturbation from the orbit of the
+/// center, in `bits` of precision.
+pub fn probe_grid<F: Fractal>(
+    fractal: &F,
+    center: &(Big, Big),
+    bits: usize,
+    pixel: f64,
+    size: (usize, usize),
+    max_iter: u32,
+) -> Vec<u32> {
+    let orbit = fractal.reference_orbit(center, bits, max_iter, 2.0);
+    let (width, height) = size;
+    let half = (width as f64 / 2.0, height as f64 / 2.0);
+    (0..width * height)
+        .into_par_iter()
+        .map(|i| {
+            let dc = (
+                ((i % width) as f64 + 0.5 - half.0) * pixel,
+                (half.1 - (i / width) as f64 - 0.5) * pixel,
+            );
+            let escape = fractal.escape_time_perturbed(&orbit.z, dc, None, max_iter, 2.0, None);
+            escape.iterations as u32
+        })
+        .collect()
+}
+
+/// Scores the cell whose top left probe is at `start`.
+///
+/// Besides entropy and the interior mix, the score rewards cells where
+/// neighboring probes mostly land in nearby bins. Regions whose escape
+/// times jump around from probe to probe are too fine for the probe grid,
+/// and zooming into them tends to stay noisy for a long way down.
+fn score(grid: &[u32], start: (usize, usize), max_iter: u32) -> f64 {
+    interest(grid, PROBE_SIZE, start, (CELL_SIZE, CELL_SIZE), max_iter)
+}
+
+/// Scores the part of `size` probes of a grid `grid_width` probes wide
+/// whose top left probe is at `start`, as `find_target` scores its cells:
+/// by the entropy of the escape times, how evenly interior and exterior
+/// mix, and how coherent neighboring probes are.
+pub fn interest(
+    grid: &[u32],
+    grid_width: usize,
+    start: (usize, usize),
+    size: (usize, usize),
+    max_iter: u32,
+) -> f64 {
+    let (width, height) = size;
+    // Interior probes share the bin past every escape time.
+    let bin = |x: usize, y: usize| {
+        let iterations = grid[y * grid_width + x];
+        if iterations >= max_iter {
+            0
+        } else {
+            1 + ((iterations as f64 + 1.0).log2() * BINS_PER_OCTAVE) as usize
+        }
+    };
+    let mut bins: Vec<u32> = Vec::new();
+    let mut interior = 0;
+    let mut coherent = 0;
+    for y in start.1..start.1 + height {
+        for x in start.0..start.0 + width {
+            let b = bin(x, y);
+            if b == 0 {
+                interior += 1;
+            }
+            if b >= bins.len() {
+                bins.resize(b + 1, 0);
+            }
+            bins[b] += 1;
+            if x + 1 < start.0 + width && bin(x + 1, y).abs_diff(b) <= 1 {
+                coherent += 1;
+            }
+            if y + 1 < start.1 + height && bin(x, y + 1).abs_diff(b) <= 1 {
+                coherent += 1;
+            }
+        }
+    }
+
+    let total = (width * height) as f64;
+    let entropy: f64 = bins
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / total;
+            -p * p.log2()
+        })
+        .sum();
+    let interior = interior as f64 / total;
+    // Every pair of neighbors across and down. A part a single probe wide
+    // has none one way, and one probe none at all.
+    let pairs = (width - 1) * height + width * (height - 1);
+    let coherence = match pairs {
+        0 => 1.0,
+        pairs => coherent as f64 / pairs as f64,
+    };
+    entropy * (0.1 + 2.0 * interior.min(1.0 - interior)) * coherence * coherence
+}
+
+/// Lays the probe grids out left to right, eight to a row, each colored by
+/// escape time with the chosen cell outlined in white.
+fn contact_sheet(grids: &[(Vec<u32>, u32, (usize, usize))]) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    const COLUMNS: usize = 8;
+    const GAP: usize = 4;
+    let columns = grids.len().clamp(1, COLUMNS);
+    let rows = grids.len().div_ceil(COLUMNS).max(1);
+    let tile = PROBE_SIZE + GAP;
+    let mut img = ImageBuffer::new((columns * tile) as u32, (rows * tile) as u32);
+
+    for (n, (grid, max_iter, cell)) in grids.iter().enumerate() {
+        let origin = ((n % COLUMNS) * tile, (n / COLUMNS) * tile);
+        let probes = color_probes(grid, (PROBE_SIZE, PROBE_SIZE), *max_iter);
+        for y in 0..PROBE_SIZE {
+            for x in 0..PROBE_SIZE {
+                let rgb = probes.get_pixel(x as u32, y as u32).0;
+                let on_cell_x = (cell.0..cell.0 + CELL_SIZE).contains(&x);
+                let on_cell_y = (cell.1..cell.1 + CELL_SIZE).contains(&y);
+                let on_border = (on_cell_x && (y == cell.1 || y == cell.1 + CELL_SIZE - 1))
+                    || (on_cell_y && (x == cell.0 || x == cell.0 + CELL_SIZE - 1));
+                let color = if on_border { [255, 255, 255] } else { rgb };
+                img.put_pixel((origin.0 + x) as u32, (origin.1 + y) as u32, Rgb(color));
+            }
+        }
+    }
+    img
+}
+
+/// The escape times of a probe grid of `size` colored as the contact sheet
+/// shows them, with the interior black.
+pub fn color_probes(grid: &[u32], size: (usize, usize), max_iter: u32) -> RgbImage {
+    let gradient = Palette::Sinebow.gradient();
+    let colormap = Colormap::new(&gradient, &Adjust::default());
+    let cycle = Cycle::new(&gradient, 4.0, 0.0, false);
+    RgbImage::from_fn(size.0 as u32, size.1 as u32, |x, y| {
+        let iterations = grid[y as usize * size.0 + x as usize];
+        Rgb(match iterations >= max_iter {
+            true => [0, 0, 0],
+            false => {
+                color_gradient(&colormap, cycle.parameter(iterations as f64 / max_iter as f64))
+            }
+        })
+    })
+}
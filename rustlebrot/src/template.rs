@@ -0,0 +1,286 @@
+use std::fmt;
+
+/// The frame files of a run unless `--filename-template` says otherwise.
+pub const DEFAULT_TEMPLATE: &str = "mandelbrot_set_{frame:04}.{ext}";
+
+/// The widest zero padding `{frame:N}` takes.
+const MAX_PADDING: usize = 20;
+
+/// Where the files of every frame of a run go, relative to its output
+/// directory, as given with `--filename-template`.
+///
+/// Templates are text with placeholders in braces, `{{` and `}}` standing
+/// for the braces themselves:
+///
+/// * `{frame}` - the frame number, or with `{frame:06}` zero padded to 6
+///   digits.
+/// * `{name}` - the run name, see `bind`.
+/// * `{zoom}` - the magnification of the frame, like `1.500e3`.
+/// * `{palette}` - the name of the palette. Files that aren't colored, like
+///   EXR frames and dumps, go by the first palette of the run.
+/// * `{timestamp}` - when the run started, see `bind`.
+/// * `{ext}` - the extension of the file, since a frame can be saved in
+///   several formats. Every template has to have it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FilenameTemplate {
+    parts: Vec<Part>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Part {
+    Text(String),
+    /// The frame number, zero padded to `padding` digits.
+    Frame { padding: usize },
+    Name,
+    Zoom,
+    Palette,
+    Timestamp,
+    Ext,
+}
+
+/// The frame a file name is made for.
+#[derive(Clone, Copy, Debug)]
+pub struct FrameName<'a> {
+    pub frame: u32,
+    pub magnification: f64,
+    pub palette: &'a str,
+    pub ext: &'a str,
+}
+
+impl Default for FilenameTemplate {
+    fn default() -> Self {
+        FilenameTemplate::parse(DEFAULT_TEMPLATE).expect("the default template is valid")
+    }
+}
+
+impl FilenameTemplate {
+    /// Parses `template`, which has to be a relative path that stays
+    /// within the output directory.
+    pub fn parse(template: &str) -> Result<FilenameTemplate, String> {
+        let invalid = |problem: String| format!("filename template '{}': {}", template, problem);
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let end = rest
+                        .find('}')
+                        .filter(|&end| !rest[..end].contains('{'))
+                        .ok_or_else(|| invalid("a '{' isn't closed; write '{{' for one".into()))?;
+                    let placeholder = parse_placeholder(&rest[..end]).map_err(invalid)?;
+                    chars = rest[end + 1..].chars();
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(placeholder);
+                }
+                '}' => return Err(invalid("a '}' isn't opened; write '}}' for one".into())),
+                _ => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        let parsed = FilenameTemplate { parts };
+        if !parsed.parts.contains(&Part::Ext) {
+            return Err(invalid(
+                "it needs {ext}, as a frame can be saved in several formats".into(),
+            ));
+        }
+        match parsed.sample() {
+            text if text.starts_with(['/', '\\']) => Err(invalid(
+                "it should be relative to the output directory".into(),
+            )),
+            text if text.split(['/', '\\']).any(|part| part == "..") => Err(invalid(
+                "it should stay within the output directory, without '..'".into(),
+            )),
+            _ => Ok(parsed),
+        }
+    }
+
+    /// Whether the frame number is in the name, which it has to be for
+    /// more than one frame not to take the same files.
+    pub fn has_frame(&self) -> bool {
+        self.parts.iter().any(|part| matches!(part, Part::Frame { .. }))
+    }
+
+    /// Whether the start of the run is in the name, which a later run can't
+    /// find the files by.
+    pub fn has_timestamp(&self) -> bool {
+        self.parts.contains(&Part::Timestamp)
+    }
+
+    /// Fills in what is the same for every frame of the run: the run
+    /// `name` and the `timestamp` it started at. The template this returns
+    /// names the files without them, so it can be recorded as it is.
+    pub fn bind(&self, name: &str, timestamp: &str) -> FilenameTemplate {
+        let parts = self.parts.iter().map(|part| match part {
+            Part::Name => Part::Text(name.to_string()),
+            Part::Timestamp => Part::Text(timestamp.to_string()),
+            part => part.clone(),
+        });
+        let mut bound: Vec<Part> = Vec::with_capacity(self.parts.len());
+        for part in parts {
+            match (bound.last_mut(), part) {
+                (Some(Part::Text(text)), Part::Text(more)) => text.push_str(&more),
+                (_, part) => bound.push(part),
+            }
+        }
+        FilenameTemplate { parts: bound }
+    }
+
+    /// The path of the file of `name`, relative to the output directory.
+    /// Placeholders `bind` hasn't filled in are left as they are.
+    pub fn path(&self, name: &FrameName) -> String {
+        let mut path = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => path.push_str(text),
+                Part::Frame { padding } => {
+                    path.push_str(&format!("{:0width$}", name.frame, width = padding))
+                }
+                Part::Zoom => path.push_str(&format!("{:.3e}", name.magnification)),
+                Part::Palette => path.push_str(name.palette),
+                Part::Ext => path.push_str(name.ext),
+                Part::Name => path.push_str("{name}"),
+                Part::Timestamp => path.push_str("{timestamp}"),
+            }
+        }
+        path
+    }
+
+    /// The files of the frames in `palette` with extension `ext`, as an
+    /// ffmpeg image sequence pattern like `mandelbrot_set_%04d.png`, or
+    /// `None` when the names don't differ by the frame number alone, which
+    /// a pattern can't describe.
+    pub fn ffmpeg_pattern(&self, palette: &str, ext: &str) -> Option<String> {
+        let frames = self.parts.iter().filter(|part| matches!(part, Part::Frame { .. }));
+        if frames.count() != 1 || self.parts.contains(&Part::Zoom) {
+            return None;
+        }
+        let mut pattern = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Frame { padding: 0 } => pattern.push_str("%d"),
+                Part::Frame { padding } => pattern.push_str(&format!("%0{}d", padding)),
+                Part::Text(text) => pattern.push_str(&text.replace('%', "%%")),
+                Part::Palette => pattern.push_str(&palette.replace('%', "%%")),
+                Part::Ext => pattern.push_str(ext),
+                Part::Name => pattern.push_str("{name}"),
+                Part::Timestamp => pattern.push_str("{timestamp}"),
+                Part::Zoom => unreachable!("checked above"),
+            }
+        }
+        Some(pattern)
+    }
+
+    /// The template with a letter for every placeholder, which is the
+    /// shape of every path it makes.
+    fn sample(&self) -> String {
+        let texts = self.parts.iter().map(|part| match part {
+            Part::Text(text) => text.as_str(),
+            _ => "x",
+        });
+        texts.collect()
+    }
+}
+
+/// The template as it would be given, with the braces of its text doubled.
+impl fmt::Display for FilenameTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => write!(f, "{}", text.replace('{', "{{").replace('}', "}}"))?,
+                Part::Frame { padding: 0 } => write!(f, "{{frame}}")?,
+                Part::Frame { padding } => write!(f, "{{frame:{:02}}}", padding)?,
+                Part::Name => write!(f, "{{name}}")?,
+                Part::Zoom => write!(f, "{{zoom}}")?,
+                Part::Palette => write!(f, "{{palette}}")?,
+                Part::Timestamp => write!(f, "{{timestamp}}")?,
+                Part::Ext => write!(f, "{{ext}}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses what is between the braces of a placeholder.
+fn parse_placeholder(placeholder: &str) -> Result<Part, String> {
+    let (name, format) = match placeholder.split_once(':') {
+        Some((name, format)) => (name, Some(format)),
+        None => (placeholder, None),
+    };
+    let part = match name {
+        "frame" => {
+            let padding = match format {
+                Some(digits) => digits
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|padding| (1..=MAX_PADDING).contains(padding))
+                    .ok_or_else(|| {
+                        format!(
+                            "{{frame:{}}} should give the digits to pad to, from 1 to {}, \
+                             like {{frame:06}}",
+                            digits, MAX_PADDING
+                        )
+                    })?,
+                None => 0,
+            };
+            return Ok(Part::Frame { padding });
+        }
+        "name" => Part::Name,
+        "zoom" => Part::Zoom,
+        "palette" => Part::Palette,
+        "timestamp" => Part::Timestamp,
+        "ext" => Part::Ext,
+        _ => {
+            return Err(format!(
+                "unknown placeholder {{{}}}; it takes {{frame}}, {{frame:06}}, {{name}}, \
+                 {{zoom}}, {{palette}}, {{timestamp}} and {{ext}}",
+                placeholder
+            ))
+        }
+    };
+    match format {
+        Some(_) => Err(format!("{{{}}} takes no format", name)),
+        None => Ok(part),
+    }
+}
+
+/// The UTC time `seconds` after the Unix epoch as `20240131-235959`, for
+/// `{timestamp}`.
+pub fn timestamp(seconds: u64) -> String {
+    let (days, time) = (seconds / 86_400, seconds % 86_400);
+    // Howard Hinnant's days_from_civil, the other way around, on eras of
+    // 400 years starting on March 1st.
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524
+        - day_of_era / 146_096)
+        / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
@@ -0,0 +1,248 @@
+use crate::grid::{downscale, fit};
+use color_quant::NeuQuant;
+use image::{DynamicImage, Rgb, RgbImage};
+
+/// The longest side of a preview shown as an image, in pixels.
+const PREVIEW_PIXELS: u32 = 256;
+
+/// The widest preview of half blocks, in columns, unless the terminal is
+/// narrower.
+const PREVIEW_COLUMNS: u32 = 64;
+
+/// The most base64 a Kitty graphics command carries, after which the image
+/// goes on in the next.
+const KITTY_CHUNK: usize = 4096;
+
+/// How many pixels NeuQuant learns the palette of a sixel preview from,
+/// one in this many, as for GIFs.
+const NEUQUANT_SAMPLING: i32 = 10;
+
+/// `--term-preview`: every `every`th frame is drawn on the terminal with
+/// `protocol` once it's saved.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TermPreview {
+    pub protocol: Protocol,
+    pub every: u32,
+}
+
+/// How `--term-preview` draws frames on the terminal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Protocol {
+    /// The graphics protocol of Kitty, which WezTerm and Ghostty speak as
+    /// well, with the frame in full color.
+    Kitty,
+    /// DEC sixel graphics, in 256 colors.
+    Sixel,
+    /// Unicode half blocks, two pixels a character in 24-bit color, which
+    /// any terminal with color shows at a coarse resolution.
+    Blocks,
+}
+
+impl Protocol {
+    pub fn from_name(name: &str) -> Option<Protocol> {
+        match name {
+            "kitty" => Some(Protocol::Kitty),
+            "sixel" => Some(Protocol::Sixel),
+            "blocks" => Some(Protocol::Blocks),
+            _ => None,
+        }
+    }
+
+    /// The best protocol the terminal is known to show, going by what it
+    /// says it is in the environment, or `None` on a dumb terminal, which
+    /// can't show any of them.
+    ///
+    /// Asking the terminal itself would mean reading its answer from the
+    /// keyboard, which a run in the background doesn't have.
+    pub fn detect() -> Option<Protocol> {
+        let var = |name: &str| std::env::var(name).unwrap_or_default();
+        let term = var("TERM");
+        if term.is_empty() || term == "dumb" {
+            return None;
+        }
+        let program = var("TERM_PROGRAM");
+        if term.contains("kitty")
+            || std::env::var_os("KITTY_WINDOW_ID").is_some()
+            || matches!(program.as_str(), "WezTerm" | "ghostty")
+        {
+            return Some(Protocol::Kitty);
+        }
+        let sixel_terms = ["mlterm", "foot", "foot-extra", "contour", "yaft-256color"];
+        if term.contains("sixel") || sixel_terms.contains(&term.as_str()) {
+            return Some(Protocol::Sixel);
+        }
+        Some(Protocol::Blocks)
+    }
+
+    /// The text that draws `img` on the terminal, shrunk to a preview no
+    /// wider than the terminal's `columns` if they're known.
+    pub fn encode(self, img: &DynamicImage, columns: Option<u32>) -> String {
+        let img = img.to_rgb8();
+        let (width, height) = img.dimensions();
+        let (width, height) = match self {
+            Protocol::Kitty | Protocol::Sixel => fit(width, height, PREVIEW_PIXELS, PREVIEW_PIXELS),
+            // Half blocks are about as wide as they're high.
+            Protocol::Blocks => {
+                let columns = columns.unwrap_or(PREVIEW_COLUMNS).clamp(1, PREVIEW_COLUMNS);
+                fit(width, height, columns, u32::MAX)
+            }
+        };
+        let preview = downscale(&img, width, height);
+        match self {
+            Protocol::Kitty => kitty(&preview),
+            Protocol::Sixel => sixel(&preview),
+            Protocol::Blocks => blocks(&preview),
+        }
+    }
+}
+
+/// `img` as Kitty graphics commands, its RGB pixels in base64 cut into
+/// chunks. Responses are turned off, as nothing reads them.
+fn kitty(img: &RgbImage) -> String {
+    let data = base64(img.as_raw());
+    let chunks: Vec<&str> = data
+        .as_bytes()
+        .chunks(KITTY_CHUNK)
+        .map(|chunk| std::str::from_utf8(chunk).expect("base64 is ASCII"))
+        .collect();
+    let mut text = String::new();
+    for (index, chunk) in chunks.iter().enumerate() {
+        let more = (index + 1 < chunks.len()) as u8;
+        text.push_str(&match index {
+            0 => format!(
+                "\x1b_Ga=T,f=24,s={},v={},q=2,m={};{}\x1b\\",
+                img.width(),
+                img.height(),
+                more,
+                chunk
+            ),
+            _ => format!("\x1b_Gm={};{}\x1b\\", more, chunk),
+        });
+    }
+    text
+}
+
+/// `img` as sixels, quantized to 256 colors, each band of six rows drawn
+/// once per color in it, with runs of the same sixel compressed.
+fn sixel(img: &RgbImage) -> String {
+    let (width, height) = (img.width() as usize, img.height() as usize);
+    let rgba: Vec<u8> = img.pixels().flat_map(|&Rgb([r, g, b])| [r, g, b, 255]).collect();
+    let quant = NeuQuant::new(NEUQUANT_SAMPLING, 256, &rgba);
+    let indices: Vec<usize> = rgba.chunks_exact(4).map(|pixel| quant.index_of(pixel)).collect();
+    let mut text = format!("\x1bPq\"1;1;{};{}", width, height);
+    for (index, color) in quant.color_map_rgb().chunks_exact(3).enumerate() {
+        let percent = |channel: u8| (channel as u32 * 100 + 127) / 255;
+        let (r, g, b) = (percent(color[0]), percent(color[1]), percent(color[2]));
+        text.push_str(&format!("#{};2;{};{};{}", index, r, g, b));
+    }
+    for band in (0..height).step_by(6) {
+        let rows = band..(band + 6).min(height);
+        let mut colors: Vec<usize> =
+            rows.clone().flat_map(|y| indices[y * width..][..width].iter().copied()).collect();
+        colors.sort_unstable();
+        colors.dedup();
+        for (nth, &color) in colors.iter().enumerate() {
+            if nth > 0 {
+                text.push('$');
+            }
+            let sixels = (0..width).map(|x| {
+                let bits = rows.clone().filter(|&y| indices[y * width + x] == color);
+                let bits = bits.fold(0, |bits, y| bits | 1 << (y - band));
+                (63 + bits) as u8 as char
+            });
+            text.push_str(&format!("#{}", color));
+            push_runs(&mut text, sixels);
+        }
+        text.push('-');
+    }
+    text + "\x1b\\"
+}
+
+/// Adds `sixels` to `text`, with runs of more than three of one written
+/// as a count.
+fn push_runs(text: &mut String, sixels: impl Iterator<Item = char>) {
+    let mut run: Option<(char, usize)> = None;
+    let flush = |text: &mut String, (sixel, count): (char, usize)| match count {
+        1..=3 => text.extend(std::iter::repeat_n(sixel, count)),
+        _ => text.push_str(&format!("!{}{}", count, sixel)),
+    };
+    for sixel in sixels {
+        run = match run {
+            Some((last, count)) if last == sixel => Some((last, count + 1)),
+            Some(last) => {
+                flush(text, last);
+                Some((sixel, 1))
+            }
+            None => Some((sixel, 1)),
+        };
+    }
+    if let Some(last) = run {
+        flush(text, last);
+    }
+}
+
+/// `img` as rows of upper half blocks, the top pixel of each the
+/// foreground and the bottom one the background, with the colors only set
+/// where they change. The last row of an image of odd height is drawn on
+/// the terminal's own background.
+fn blocks(img: &RgbImage) -> String {
+    let mut text = String::new();
+    for y in (0..img.height()).step_by(2) {
+        let (mut foreground, mut background) = (None, None);
+        for x in 0..img.width() {
+            let top = img.get_pixel(x, y).0;
+            if foreground.replace(top) != Some(top) {
+                let [r, g, b] = top;
+                text.push_str(&format!("\x1b[38;2;{};{};{}m", r, g, b));
+            }
+            let bottom = (y + 1 < img.height()).then(|| img.get_pixel(x, y + 1).0);
+            if background.replace(bottom) != Some(bottom) {
+                text.push_str(&match bottom {
+                    Some([r, g, b]) => format!("\x1b[48;2;{};{};{}m", r, g, b),
+                    None => "\x1b[49m".to_string(),
+                });
+            }
+            text.push('▀');
+        }
+        text.push_str("\x1b[0m\n");
+    }
+    text.pop();
+    text
+}
+
+/// `bytes` in standard base64, padded.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut text = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let word = chunk.iter().enumerate().fold(0, |word, (i, &byte)| {
+            word | (byte as u32) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => text.push(ALPHABET[(word >> (18 - 6 * i) & 63) as usize] as char),
+                false => text.push('='),
+            }
+        }
+    }
+    text
+}
+
+/// The width of the terminal, in columns, if stdout or stderr is one and
+/// tells.
+#[cfg(unix)]
+pub fn columns() -> Option<u32> {
+    [libc::STDOUT_FILENO, libc::STDERR_FILENO].into_iter().find_map(|fd| {
+        // SAFETY: winsize is plain integers, and TIOCGWINSZ only writes the
+        // one it is given.
+        let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+        let got = unsafe { libc::ioctl(fd, libc::TIOCGWINSZ, &mut size) };
+        (got == 0 && size.ws_col > 0).then_some(size.ws_col as u32)
+    })
+}
+
+#[cfg(not(unix))]
+pub fn columns() -> Option<u32> {
+    std::env::var("COLUMNS").ok()?.parse().ok()
+}
@@ -0,0 +1,72 @@
+use crate::error::RustlebrotError;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Fraction of the time a render thread works with `--background`. It
+/// sleeps for the rest.
+const DUTY: f64 = 0.75;
+
+/// Longest sleep after a unit of work, so a slow row doesn't stall its
+/// thread for long.
+const MAX_PAUSE: Duration = Duration::from_millis(20);
+
+/// How much nicer a background run is than the processes around it.
+#[cfg(unix)]
+const NICENESS: i32 = 10;
+
+static BACKGROUND: AtomicBool = AtomicBool::new(false);
+
+/// Sets up the render threads: `threads` of them, or with `background` one
+/// fewer than the CPUs at a lower priority, paced by `paced`.
+///
+/// This has to be called before anything runs on the rayon pool, which
+/// it sizes.
+pub fn configure(threads: Option<usize>, background: bool) -> Result<(), RustlebrotError> {
+    BACKGROUND.store(background, Ordering::Relaxed);
+    if background {
+        lower_priority();
+    }
+    let threads = threads.or_else(|| {
+        let cpus = std::thread::available_parallelism().map_or(1, |cpus| cpus.get());
+        background.then(|| cpus.saturating_sub(1).max(1))
+    });
+    let Some(threads) = threads else {
+        return Ok(());
+    };
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build_global()
+        .map_err(|e| {
+            RustlebrotError::System(format!("can't start {} render threads: {}", threads, e))
+        })
+}
+
+/// Runs one unit of the work of a parallel loop, such as a row, and with
+/// `--background` then sleeps in proportion to how long it took.
+///
+/// Every thread paces itself, so the machine stays responsive within a
+/// long frame instead of only between frames.
+pub fn paced<T>(work: impl FnOnce() -> T) -> T {
+    if !BACKGROUND.load(Ordering::Relaxed) {
+        return work();
+    }
+    let start = Instant::now();
+    let result = work();
+    let pause = start.elapsed().mul_f64((1.0 - DUTY) / DUTY);
+    std::thread::sleep(pause.min(MAX_PAUSE));
+    result
+}
+
+/// Lowers the priority of the process, and of the threads it starts from
+/// here on, where the platform allows it.
+#[cfg(unix)]
+fn lower_priority() {
+    // Not being able to is no reason to stop the run.
+    // SAFETY: setpriority only reads its arguments.
+    unsafe {
+        libc::setpriority(libc::PRIO_PROCESS, 0, NICENESS);
+    }
+}
+
+#[cfg(not(unix))]
+fn lower_priority() {}
@@ -0,0 +1,338 @@
+//! The orbit of a single point, iterated in whichever precision is asked
+//! for and recorded step by step, for looking into what a render made of
+//! it.
+
+use crate::bigfloat::{self, Big};
+use crate::complex::{Complex, Scalar};
+use crate::decimal::Decimal;
+use crate::fractal::{self, Escape, Fractal, ATTRACTOR_EPSILON};
+use crate::palette::Palette;
+use crate::perturbation::ReferenceOrbit;
+use crate::precision::Precision;
+use image::{Rgba, RgbaImage};
+use rayon::prelude::*;
+
+/// How close an f32 orbit has to come back to a point it passed to count
+/// as a cycle. Its points are only ever that close in f32 rounding, far
+/// coarser than `ATTRACTOR_EPSILON`.
+const F32_EPSILON: f64 = 16.0 * f32::EPSILON as f64;
+
+/// Share of the extent of the orbit left as a margin around it in a plot.
+const PLOT_MARGIN: f64 = 0.1;
+
+/// The smallest half width of the region a plot shows, so an orbit that
+/// settles at once on a fixed point still has its surroundings in view.
+const PLOT_MIN_HALF_WIDTH: f64 = 0.25;
+
+/// The pixels of a dot of a plot, about the one a point falls in.
+const DOT: [(f64, f64); 9] = [
+    (0.0, 0.0),
+    (-1.0, 0.0),
+    (1.0, 0.0),
+    (0.0, -1.0),
+    (0.0, 1.0),
+    (-2.0, 0.0),
+    (2.0, 0.0),
+    (0.0, -2.0),
+    (0.0, 2.0),
+];
+
+/// The orbit of a point, from `z0 = 0` on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trace {
+    /// Every `z` of the orbit rounded to f64, `points[k]` being `z` after
+    /// `k` steps. It ends with the first point past the bailout, or after
+    /// `max_iter` steps.
+    pub points: Vec<(f64, f64)>,
+    pub verdict: Verdict,
+}
+
+/// What became of the orbit of a point.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Verdict {
+    /// The orbit left the circle of the bailout on step `iteration`, with
+    /// `smooth` the escape time interpolated as smooth coloring has it.
+    Escaped { iteration: u32, smooth: f64 },
+    /// The orbit stayed within the bailout for every step. `period` is the
+    /// length of the cycle it settled on, if it came back close enough to a
+    /// point it passed to tell.
+    Bounded { period: Option<u32> },
+}
+
+/// Iterates `c` for up to `max_iter` steps in `precision`, with `conjugate`
+/// for the Tricorn, and records its orbit until it escapes past `bailout`.
+///
+/// Auto precision iterates in f64, there being no pixels for it to choose
+/// by. Perturbation iterates the delta of `c` against the orbit of
+/// `reference` just as a frame centered on `reference` would, rebasing
+/// when the delta grows past the orbit or the reference escapes, so the
+/// points show any glitch the frame would have.
+pub fn trace(
+    c: &(Decimal, Decimal),
+    reference: &(Decimal, Decimal),
+    precision: Precision,
+    conjugate: bool,
+    max_iter: u32,
+    bailout: f64,
+) -> Trace {
+    let points = match conjugate {
+        true => points::<true>(c, reference, precision, max_iter, bailout),
+        false => points::<false>(c, reference, precision, max_iter, bailout),
+    };
+    let escaped = points.last().filter(|z| z.0 * z.0 + z.1 * z.1 > bailout * bailout);
+    let verdict = match escaped {
+        Some(&z) => {
+            let iteration = points.len() as u32 - 1;
+            // `Escape` counts the steps before the one that escaped.
+            let escape = Escape::escaped(iteration - 1, z, bailout, f64::INFINITY);
+            Verdict::Escaped {
+                iteration,
+                smooth: escape.smooth,
+            }
+        }
+        None => {
+            let eps = match precision {
+                Precision::F32 => F32_EPSILON,
+                _ => ATTRACTOR_EPSILON,
+            };
+            Verdict::Bounded {
+                period: period(&points, eps),
+            }
+        }
+    };
+    Trace { points, verdict }
+}
+
+fn points<const CONJUGATE: bool>(
+    c: &(Decimal, Decimal),
+    reference: &(Decimal, Decimal),
+    precision: Precision,
+    max_iter: u32,
+    bailout: f64,
+) -> Vec<(f64, f64)> {
+    match precision {
+        Precision::F32 => {
+            let c = Complex::new(c.0.to_f64() as f32, c.1.to_f64() as f32);
+            points_in::<f32, CONJUGATE>(c, max_iter, bailout)
+        }
+        Precision::Auto | Precision::F64 => {
+            let c = Complex::new(c.0.to_f64(), c.1.to_f64());
+            points_in::<f64, CONJUGATE>(c, max_iter, bailout)
+        }
+        Precision::DoubleDouble => {
+            let c = Complex::new(c.0.to_double_double(), c.1.to_double_double());
+            points_in::<_, CONJUGATE>(c, max_iter, bailout)
+        }
+        Precision::Perturbation => {
+            let bits = bits_of(&[c, reference]);
+            let (c, reference) = (to_big(c, bits), to_big(reference, bits));
+            let orbit = ReferenceOrbit::compute(&reference, bits, max_iter, bailout, CONJUGATE);
+            let dc = (
+                (&c.0 - &reference.0).to_f64().value(),
+                (&c.1 - &reference.1).to_f64().value(),
+            );
+            perturbed_points::<CONJUGATE>(&orbit.z, dc, max_iter, bailout)
+        }
+        Precision::Big => {
+            let bits = bits_of(&[c]);
+            big_points::<CONJUGATE>(&to_big(c, bits), max_iter, bailout)
+        }
+    }
+}
+
+/// The orbit of `c` iterated in `T`, as the escape-time loop of `fractal`
+/// iterates it.
+fn points_in<T: Scalar, const CONJUGATE: bool>(
+    c: Complex<T>,
+    max_iter: u32,
+    bailout: f64,
+) -> Vec<(f64, f64)> {
+    let bailout_sqr = bailout * bailout;
+    let mut points = vec![(0.0, 0.0)];
+    let mut z = Complex::ZERO;
+    for _ in 0..max_iter {
+        z = fractal::step::<T, CONJUGATE>(z, c);
+        let (x, y) = (z.re.leading(), z.im.leading());
+        points.push((x, y));
+        if x * x + y * y > bailout_sqr {
+            break;
+        }
+    }
+    points
+}
+
+/// The orbit of the point at offset `dc` from the reference of `orbit`,
+/// iterated as `perturbation::escape_time` iterates it.
+fn perturbed_points<const CONJUGATE: bool>(
+    orbit: &[(f64, f64)],
+    dc: (f64, f64),
+    max_iter: u32,
+    bailout: f64,
+) -> Vec<(f64, f64)> {
+    let bailout_sqr = bailout * bailout;
+    let last = orbit.len() - 1;
+    let mut points = vec![(0.0, 0.0)];
+    let mut d: (f64, f64) = (0.0, 0.0);
+    let mut m = 0;
+    for _ in 0..max_iter {
+        let r = orbit[m];
+        let x = 2.0 * (r.0 * d.0 - r.1 * d.1) + d.0 * d.0 - d.1 * d.1;
+        let y = 2.0 * (r.0 * d.1 + r.1 * d.0) + 2.0 * d.0 * d.1;
+        d = if CONJUGATE {
+            (x + dc.0, -y + dc.1)
+        } else {
+            (x + dc.0, y + dc.1)
+        };
+        m += 1;
+
+        let z = (orbit[m].0 + d.0, orbit[m].1 + d.1);
+        let z_norm = z.0 * z.0 + z.1 * z.1;
+        points.push(z);
+        if z_norm > bailout_sqr {
+            break;
+        }
+        if m == last || z_norm < d.0 * d.0 + d.1 * d.1 {
+            d = z;
+            m = 0;
+        }
+    }
+    points
+}
+
+/// The orbit of `c` iterated in its own precision, as
+/// `bigfloat::escape_time` iterates it.
+fn big_points<const CONJUGATE: bool>(
+    c: &(Big, Big),
+    max_iter: u32,
+    bailout: f64,
+) -> Vec<(f64, f64)> {
+    let bailout_sqr = Big::try_from(bailout * bailout).expect("bailout is finite");
+    let mut points = vec![(0.0, 0.0)];
+    let mut z: (Big, Big) = (Big::ZERO, Big::ZERO);
+    let mut z_sqr: (Big, Big) = (Big::ZERO, Big::ZERO);
+    for _ in 0..max_iter {
+        let cross = (&z.0 * &z.1) << 1;
+        let x = &z_sqr.0 - &z_sqr.1 + &c.0;
+        let y = if CONJUGATE { &c.1 - cross } else { cross + &c.1 };
+        let (x_sqr, y_sqr) = (x.sqr(), y.sqr());
+        points.push((x.to_f64().value(), y.to_f64().value()));
+        if &x_sqr + &y_sqr > bailout_sqr {
+            break;
+        }
+        z = (x, y);
+        z_sqr = (x_sqr, y_sqr);
+    }
+    points
+}
+
+/// Bits that keep every digit of the coordinates of `points`, as
+/// find-nucleus keeps those it's given.
+fn bits_of(points: &[&(Decimal, Decimal)]) -> usize {
+    let digits = points
+        .iter()
+        .flat_map(|(x, y)| [x.as_str().len(), y.as_str().len()])
+        .max()
+        .unwrap_or(0);
+    (digits as f64 * std::f64::consts::LOG2_10) as usize + 64
+}
+
+fn to_big(point: &(Decimal, Decimal), bits: usize) -> (Big, Big) {
+    let parse = |decimal: &Decimal| {
+        bigfloat::parse_decimal(decimal.as_str(), bits).expect("decimals parse as numbers")
+    };
+    (parse(&point.0), parse(&point.1))
+}
+
+/// The period of the cycle `points` settle on, found by Brent's method as
+/// the renders find it: a saved point is compared against every later one
+/// and replaced at exponentially growing intervals, so the steps since the
+/// last save when the orbit comes back within `eps` of it are the period.
+fn period(points: &[(f64, f64)], eps: f64) -> Option<u32> {
+    let eps_sqr = eps * eps;
+    let mut saved = points[0];
+    let mut since_saved: u32 = 0;
+    let mut interval: u32 = 8;
+    for &z in &points[1..] {
+        since_saved += 1;
+        let (dx, dy) = (z.0 - saved.0, z.1 - saved.1);
+        if dx * dx + dy * dy < eps_sqr {
+            return Some(since_saved);
+        }
+        if since_saved == interval {
+            saved = z;
+            since_saved = 0;
+            interval *= 2;
+        }
+    }
+    None
+}
+
+/// Scatters the points of `trace` over a faint rendering of `fractal`
+/// around them, of `width` by `height` pixels, with the imaginary axis up.
+///
+/// The region is that of the points within the bailout, with a margin. The
+/// points run from blue to red along the orbit in Turbo, which unlike the
+/// cyclic default palette doesn't come back round, so where the orbit
+/// wanders early and where it ends up tell apart.
+pub fn plot<F: Fractal>(
+    trace: &Trace,
+    fractal: &F,
+    max_iter: u32,
+    bailout: f64,
+    (width, height): (u32, u32),
+) -> RgbaImage {
+    let inside: Vec<(f64, f64)> = trace
+        .points
+        .iter()
+        .copied()
+        .filter(|z| z.0 * z.0 + z.1 * z.1 <= bailout * bailout)
+        .collect();
+    let (mut left, mut right, mut bottom, mut top) = inside.iter().fold(
+        (f64::INFINITY, f64::NEG_INFINITY, f64::INFINITY, f64::NEG_INFINITY),
+        |(left, right, bottom, top), z| {
+            (left.min(z.0), right.max(z.0), bottom.min(z.1), top.max(z.1))
+        },
+    );
+    let center = ((left + right) / 2.0, (bottom + top) / 2.0);
+    let half_width = ((right - left) / 2.0)
+        .max((top - bottom) / 2.0 * width as f64 / height as f64)
+        .max(PLOT_MIN_HALF_WIDTH)
+        * (1.0 + PLOT_MARGIN);
+    let pixel = 2.0 * half_width / width as f64;
+    (left, top) = (center.0 - half_width, center.1 + pixel * height as f64 / 2.0);
+    (right, bottom) = (center.0 + half_width, center.1 - pixel * height as f64 / 2.0);
+    debug_assert!(left < right && bottom < top);
+
+    let mut image = RgbaImage::new(width, height);
+    let eps = Some(ATTRACTOR_EPSILON);
+    image.par_chunks_mut(4 * width as usize).enumerate().for_each(|(row, pixels)| {
+        let y = top - (row as f64 + 0.5) * pixel;
+        for (column, color) in pixels.chunks_mut(4).enumerate() {
+            let x = left + (column as f64 + 0.5) * pixel;
+            let escape = fractal.escape_time((x, y), max_iter, bailout, eps, None);
+            // Dim gray, lighter toward the boundary, and a dark interior.
+            let shade = match escape.iterations >= max_iter as f64 {
+                true => 16,
+                false => {
+                    let depth = escape.smooth.max(0.0).ln_1p() / (max_iter as f64).ln_1p();
+                    40 + (64.0 * depth) as u8
+                }
+            };
+            color.copy_from_slice(&[shade, shade, shade, 255]);
+        }
+    });
+
+    let gradient = Palette::Turbo.gradient();
+    let steps = trace.points.len().saturating_sub(1).max(1) as f64;
+    for (k, z) in trace.points.iter().enumerate() {
+        let (column, row) = ((z.0 - left) / pixel, (top - z.1) / pixel);
+        let color = Rgba(gradient.at(k as f64 / steps).to_rgba8());
+        for (dx, dy) in DOT {
+            let (x, y) = (column.floor() + dx, row.floor() + dy);
+            if x >= 0.0 && y >= 0.0 && x < width as f64 && y < height as f64 {
+                image.put_pixel(x as u32, y as u32, color);
+            }
+        }
+    }
+    image
+}
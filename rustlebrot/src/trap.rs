@@ -0,0 +1,79 @@
+use crate::complex::Complex;
+
+/// A shape the orbit is measured against for orbit trap coloring, as given
+/// with `--trap`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Trap {
+    /// A single point.
+    Point(f64, f64),
+    /// The horizontal and vertical lines through a point.
+    Cross(f64, f64),
+    /// A circle of the given radius around the origin.
+    Circle(f64),
+}
+
+impl Trap {
+    /// Parses a trap such as `point:0,0`, `cross` or `circle:0.25`.
+    ///
+    /// Points and crosses default to the origin and circles to a radius of
+    /// one when no parameters are given. A circle's radius is positive.
+    pub fn from_spec(spec: &str) -> Result<Trap, String> {
+        let (shape, params) = match spec.split_once(':') {
+            Some((shape, params)) => (shape, Some(params)),
+            None => (spec, None),
+        };
+        let numbers = |count: usize, default: &[f64]| -> Result<Vec<f64>, String> {
+            let Some(params) = params else {
+                return Ok(default.to_vec());
+            };
+            let invalid = || format!("invalid {} trap parameters '{}'", shape, params);
+            let numbers = params
+                .split(',')
+                .map(|n| n.trim().parse())
+                .collect::<Result<Vec<f64>, _>>()
+                .map_err(|_| invalid())?;
+            if numbers.len() != count {
+                return Err(invalid());
+            }
+            Ok(numbers)
+        };
+        match shape {
+            "point" => {
+                let p = numbers(2, &[0.0, 0.0])?;
+                Ok(Trap::Point(p[0], p[1]))
+            }
+            "cross" => {
+                let p = numbers(2, &[0.0, 0.0])?;
+                Ok(Trap::Cross(p[0], p[1]))
+            }
+            "circle" => {
+                let radius = numbers(1, &[1.0])?[0];
+                // A circle of no radius is the point trap at the origin, and
+                // no orbit comes near one of a negative radius.
+                if !(radius > 0.0 && radius.is_finite()) {
+                    return Err(format!("circle trap radius should be positive, got {}", radius));
+                }
+                Ok(Trap::Circle(radius))
+            }
+            _ => Err(format!("unknown trap '{}'", shape)),
+        }
+    }
+
+    /// Whether the trap is its own mirror image across the real axis.
+    pub fn symmetric(&self) -> bool {
+        match *self {
+            Trap::Point(_, y) | Trap::Cross(_, y) => y == 0.0,
+            Trap::Circle(_) => true,
+        }
+    }
+
+    /// The distance from `z` to the trap.
+    #[inline]
+    pub fn distance(&self, z: (f64, f64)) -> f64 {
+        match *self {
+            Trap::Point(x, y) => Complex::new(z.0 - x, z.1 - y).norm_sqr().sqrt(),
+            Trap::Cross(x, y) => (z.0 - x).abs().min((z.1 - y).abs()),
+            Trap::Circle(radius) => (Complex::from(z).norm_sqr().sqrt() - radius).abs(),
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use crate::error::RustlebrotError;
+use crate::export::{self, ImageFormat};
+use rayon::prelude::*;
+use std::fs;
+use std::io::ErrorKind;
+
+/// The fewest bytes a frame can be saved in, under which a file is taken
+/// for one that was only started, whatever it holds.
+pub const MIN_BYTES: u64 = 64;
+
+/// The eight bytes every PNG starts with.
+const SIGNATURE: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+
+/// What is wrong with the file of a frame.
+pub enum Fault {
+    /// There is none.
+    Missing,
+    /// It is cut short, corrupt, or of another size than the run's frames.
+    Corrupt(RustlebrotError),
+}
+
+/// Checks the frames at `paths`, saved in `format` at `size`, returning
+/// what is wrong with each of them, if anything, in their order.
+///
+/// A PNG is checked by the checksums of its chunks, which catches a file
+/// cut short or damaged without decompressing it, and frames in other
+/// formats by their headers, unless `full_decode` says to decode every
+/// frame in full as well.
+pub fn check(
+    paths: &[String],
+    format: ImageFormat,
+    size: (u32, u32),
+    full_decode: bool,
+) -> Result<Vec<Option<Fault>>, RustlebrotError> {
+    // The frames are checked on threads of their own, leaving the global
+    // pool to be sized by the run for frames rendered again.
+    let pool = rayon::ThreadPoolBuilder::new().build().map_err(|e| {
+        RustlebrotError::System(format!("can't start threads to check the frames: {}", e))
+    })?;
+    Ok(pool.install(|| {
+        let checked = paths.par_iter().map(|path| check_frame(path, format, size, full_decode));
+        checked.map(Result::err).collect()
+    }))
+}
+
+fn check_frame(
+    path: &str,
+    format: ImageFormat,
+    size: (u32, u32),
+    full_decode: bool,
+) -> Result<(), Fault> {
+    let bytes = match fs::metadata(path) {
+        Ok(metadata) => metadata.len(),
+        Err(e) if e.kind() == ErrorKind::NotFound => return Err(Fault::Missing),
+        Err(e) => return Err(Fault::Corrupt(RustlebrotError::read(path, e))),
+    };
+    if bytes < MIN_BYTES {
+        let message = format!("{} bytes is too few for a frame", bytes);
+        return Err(Fault::Corrupt(RustlebrotError::format(path, message)));
+    }
+    let found = match (format, full_decode) {
+        (ImageFormat::Png, false) => png_size(path),
+        // The decoder lets checksums go, so they're checked all the same.
+        (ImageFormat::Png, true) => png_size(path)
+            .and_then(|_| export::read_png(path))
+            .map(|png| (png.width, png.height)),
+        (_, false) => image::image_dimensions(path).map_err(|e| RustlebrotError::format(path, e)),
+        (_, true) => image::open(path)
+            .map(|img| (img.width(), img.height()))
+            .map_err(|e| RustlebrotError::format(path, e)),
+    };
+    let (width, height) = found.map_err(Fault::Corrupt)?;
+    if (width, height) != size {
+        let message = format!(
+            "the frame is {}x{}, but the run's frames are {}x{}",
+            width, height, size.0, size.1
+        );
+        return Err(Fault::Corrupt(RustlebrotError::format(path, message)));
+    }
+    Ok(())
+}
+
+/// The width and height of the PNG at `path`, once every one of its chunks
+/// is found to match its checksum, from the header on up to the end.
+fn png_size(path: &str) -> Result<(u32, u32), RustlebrotError> {
+    let data = fs::read(path).map_err(|e| RustlebrotError::read(path, e))?;
+    let failed = |message: String| RustlebrotError::format(path, message);
+    let mut rest = data.strip_prefix(&SIGNATURE[..]).ok_or_else(|| failed("not a PNG".into()))?;
+    let word = |bytes: &[u8]| u32::from_be_bytes(bytes.try_into().unwrap());
+    let mut size = None;
+    loop {
+        let Some(length) = rest.get(..4).map(|length| word(length) as usize) else {
+            return Err(failed("cut short before its end".to_string()));
+        };
+        // The checksum covers the kind of the chunk along with its data.
+        let (Some(chunk), Some(crc)) = (rest.get(4..8 + length), rest.get(8 + length..12 + length))
+        else {
+            return Err(failed("cut short in its last chunk".to_string()));
+        };
+        let kind = String::from_utf8_lossy(&chunk[..4]);
+        if crc32fast::hash(chunk) != word(crc) {
+            return Err(failed(format!("the checksum of a {} chunk doesn't match", kind)));
+        }
+        match (&chunk[..4], size) {
+            (b"IHDR", None) if length >= 8 => {
+                size = Some((word(&chunk[4..8]), word(&chunk[8..12])))
+            }
+            (_, None) => return Err(failed("doesn't start with its header".to_string())),
+            (b"IEND", Some(size)) => return Ok(size),
+            _ => {}
+        }
+        rest = &rest[12 + length..];
+    }
+}
@@ -0,0 +1,1328 @@
+use crate::error::RustlebrotError;
+use crate::events;
+use crate::export::ImageFormat;
+use crate::render::BitDepth;
+#[cfg(feature = "video-internal")]
+use color_quant::NeuQuant;
+#[cfg(feature = "video-internal")]
+use image::{codecs::jpeg::JpegEncoder, ColorType};
+use image::DynamicImage;
+#[cfg(feature = "video-internal")]
+use rayon::prelude::*;
+use std::fs::{self, File};
+#[cfg(feature = "video-internal")]
+use std::io::BufWriter;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::thread::JoinHandle;
+
+/// JPEG quality of the frames of the internal encoder.
+#[cfg(feature = "video-internal")]
+const JPEG_QUALITY: u8 = 95;
+
+/// A video encoder that is handed the frames of a zoom one at a time, by
+/// whichever thread writes them.
+pub trait Encoder: Send {
+    /// Adds the next frame, as interleaved RGB rows with 8-bit channels, or
+    /// little-endian 16-bit channels for 16-bit video. Videos with
+    /// `VideoOptions::alpha` take RGBA rows.
+    fn push_frame(&mut self, frame: &[u8]) -> Result<(), RustlebrotError>;
+
+    /// Writes out the rest of the video and returns its path.
+    fn finish(self: Box<Self>) -> Result<String, RustlebrotError>;
+}
+
+/// The encoders selectable with `--encoder`, and the animated image
+/// formats of `--format`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EncoderKind {
+    /// H.264 through an ffmpeg binary found on the path.
+    Ffmpeg,
+    /// Motion JPEG in an AVI file, written without any external program.
+    Internal,
+    /// An animated GIF.
+    Gif(GifOptions),
+    /// An animated PNG.
+    Apng,
+}
+
+/// Settings of the video and how it's encoded, shared by the encoders
+/// where they apply.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VideoOptions {
+    /// Frames per second of videos and APNGs.
+    pub fps: u16,
+    /// The ffmpeg encoder, passed to `-c:v`.
+    pub codec: String,
+    /// The constant rate factor ffmpeg encodes with, or `None` for the
+    /// codec's default.
+    pub crf: Option<u8>,
+    /// Extra arguments for ffmpeg, added just before the output.
+    pub ffmpeg_args: Vec<String>,
+    /// Where to write the video, in place of `rust_out` in the output
+    /// directory.
+    pub output: Option<String>,
+    /// Replace the video if it exists already. When rendering, this also
+    /// allows replacing the frames.
+    pub overwrite: bool,
+    /// Play the frames forward and then backward to the first, without
+    /// the last twice, as `--direction in-out` has them.
+    pub there_and_back: bool,
+    /// The order the video shows the frames in, as `--video-sequence` gives it,
+    /// or `None` for every frame once, unless the run being encoded again
+    /// had another.
+    pub sequence: Option<Sequence>,
+    /// Keep the alpha channel of the frames, which only ffmpeg does and
+    /// only with a codec `alpha_pix_fmt` knows.
+    pub alpha: bool,
+    /// Pad frames of an odd size by a row or column of `background` for
+    /// ffmpeg, whose pixel format may need an even one, as `--pad-to-even`
+    /// asks. The frames saved stay as they are.
+    pub pad_to_even: bool,
+    /// The color of the padding, the interior color of the frames.
+    pub background: (u8, u8, u8),
+}
+
+impl Default for VideoOptions {
+    fn default() -> Self {
+        VideoOptions {
+            fps: 30,
+            codec: "libx264".to_string(),
+            crf: None,
+            ffmpeg_args: Vec::new(),
+            output: None,
+            overwrite: false,
+            there_and_back: false,
+            sequence: None,
+            alpha: false,
+            pad_to_even: false,
+            background: (0, 0, 0),
+        }
+    }
+}
+
+/// Settings for `--format gif`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GifOptions {
+    /// Size of the palette every frame is quantized to, at most 256.
+    pub colors: u16,
+    /// Time every frame is shown, in hundredths of a second.
+    pub delay: u16,
+    /// How many times the animation repeats after playing once, or `None`
+    /// to loop forever.
+    pub repeat: Option<u16>,
+}
+
+/// How the frames are put in order for the video, as `--video-sequence` gives
+/// it, for videos posted to loop.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Sequence {
+    /// Every frame once, in order.
+    #[default]
+    Normal,
+    /// The frames forward and then back, leaving out the last and the first
+    /// on the way back, which the video would otherwise show twice where it
+    /// turns and where it loops.
+    Boomerang,
+    /// The frames in order, then the last held for as many seconds.
+    LoopHold(f64),
+}
+
+impl Sequence {
+    /// Parses `normal`, `boomerang` or `loop-hold:SECONDS`.
+    pub fn from_spec(spec: &str) -> Result<Sequence, String> {
+        if let Some(seconds) = spec.strip_prefix("loop-hold:") {
+            return match seconds.parse::<f64>() {
+                Ok(seconds) if seconds > 0.0 && seconds.is_finite() => {
+                    Ok(Sequence::LoopHold(seconds))
+                }
+                _ => Err(format!("loop-hold:S takes a positive number of seconds, got '{}'", spec)),
+            };
+        }
+        match spec {
+            "normal" => Ok(Sequence::Normal),
+            "boomerang" => Ok(Sequence::Boomerang),
+            _ => Err(format!(
+                "video-sequence should be normal, boomerang or loop-hold:S, got '{}'",
+                spec
+            )),
+        }
+    }
+
+    /// The sequence as `from_spec` parses it.
+    pub fn spec(self) -> String {
+        match self {
+            Sequence::Normal => "normal".to_string(),
+            Sequence::Boomerang => "boomerang".to_string(),
+            Sequence::LoopHold(seconds) => format!("loop-hold:{}", seconds),
+        }
+    }
+
+    /// How many times the last frame is shown again at the end, in a video
+    /// of `fps` frames a second.
+    pub fn held(self, fps: f64) -> usize {
+        match self {
+            Sequence::LoopHold(seconds) => (seconds * fps).round() as usize,
+            Sequence::Normal | Sequence::Boomerang => 0,
+        }
+    }
+}
+
+/// The frames of a video in the order it shows them, by their index among
+/// the frames saved: stretches of them played forward or back, and then the
+/// last one shown again.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Order {
+    /// The first and the last index of every stretch, which counts down
+    /// where the first is the larger.
+    pub spans: Vec<(usize, usize)>,
+    /// Times the last frame is shown again at the end.
+    pub held: usize,
+}
+
+impl Order {
+    /// The order of the video of `count` frames, at `fps` frames a second.
+    ///
+    /// `--direction in-out` plays the frames back to the first, the way the
+    /// camera came, and a boomerang stops short of it; with both, the
+    /// frames go back to the first.
+    pub fn new(count: usize, fps: f64, options: &VideoOptions) -> Order {
+        let sequence = options.sequence.unwrap_or_default();
+        let mut spans = Vec::new();
+        if count > 0 {
+            spans.push((0, count - 1));
+        }
+        let back_to = match (options.there_and_back, sequence) {
+            (true, _) => Some(0),
+            (false, Sequence::Boomerang) => Some(1),
+            (false, _) => None,
+        };
+        if let Some(last) = back_to.filter(|&last| count >= last + 2) {
+            spans.push((count - 2, last));
+        }
+        Order {
+            held: if count > 0 { sequence.held(fps) } else { 0 },
+            spans,
+        }
+    }
+
+    /// The indices of the frames, in the order the video shows them.
+    pub fn frames(&self) -> impl Iterator<Item = usize> + '_ {
+        let spans = self.spans.iter().flat_map(|&(first, last)| {
+            (0..=first.abs_diff(last)).map(move |step| match first <= last {
+                true => first + step,
+                false => first - step,
+            })
+        });
+        let last = self.spans.last().map(|&(_, last)| last);
+        spans.chain(last.into_iter().flat_map(|last| std::iter::repeat_n(last, self.held)))
+    }
+
+    /// How many frames the video shows.
+    pub fn shown(&self) -> usize {
+        let spans: usize = self.spans.iter().map(|&(first, last)| first.abs_diff(last) + 1).sum();
+        spans + self.held
+    }
+}
+
+impl EncoderKind {
+    pub fn from_name(name: &str) -> Option<EncoderKind> {
+        match name {
+            "ffmpeg" => Some(EncoderKind::Ffmpeg),
+            "internal" => Some(EncoderKind::Internal),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            EncoderKind::Ffmpeg => "ffmpeg",
+            EncoderKind::Internal => "internal",
+            EncoderKind::Gif(_) => "gif",
+            EncoderKind::Apng => "apng",
+        }
+    }
+
+    /// Frames per second of the video: `--fps`, but for gifs, which show
+    /// every frame for `--gif-delay` instead.
+    pub fn frame_rate(self, options: &VideoOptions) -> f64 {
+        match self {
+            EncoderKind::Gif(gif) => 100.0 / gif.delay as f64,
+            _ => options.fps as f64,
+        }
+    }
+
+    /// Roughly the bytes per pixel of every frame of the video, on the high
+    /// side, for the estimate of how much room a run needs.
+    pub fn bytes_per_pixel(self) -> f64 {
+        match self {
+            EncoderKind::Ffmpeg => 0.05,
+            EncoderKind::Internal => 1.0,
+            EncoderKind::Gif(_) => 0.8,
+            EncoderKind::Apng => 1.0,
+        }
+    }
+
+    /// Picks `kind`, or when none was asked for, ffmpeg if it can be run and
+    /// the internal encoder otherwise. This is settled before rendering, so a
+    /// missing ffmpeg doesn't end a long render with nothing to show for it,
+    /// and fails if ffmpeg was asked for and can't be run.
+    ///
+    /// Without the `video-internal` feature, ffmpeg is the only encoder, so
+    /// it has to be found, and asking for any other fails.
+    pub fn resolve(kind: Option<EncoderKind>) -> Result<EncoderKind, RustlebrotError> {
+        let internal = EncoderKind::Internal.built();
+        match kind {
+            Some(EncoderKind::Ffmpeg) if !ffmpeg_found() => Err(RustlebrotError::System(format!(
+                "ffmpeg can't be run, install it or put it on the PATH, {}or pass --no-video to \
+                 only save the frames",
+                if internal { "or pass --encoder internal to encode without it, " } else { "" }
+            ))),
+            Some(kind) if !kind.built() => Err(kind.not_built()),
+            Some(kind) => Ok(kind),
+            None if !ffmpeg_found() && internal => {
+                events::say("ffmpeg not found, encoding with the internal MJPEG encoder");
+                Ok(EncoderKind::Internal)
+            }
+            None => EncoderKind::resolve(Some(EncoderKind::Ffmpeg)),
+        }
+    }
+
+    /// Whether this build has the encoder, which for all but ffmpeg takes
+    /// the `video-internal` feature.
+    fn built(self) -> bool {
+        self == EncoderKind::Ffmpeg || cfg!(feature = "video-internal")
+    }
+
+    /// The error of asking for this encoder in a build without it.
+    fn not_built(self) -> RustlebrotError {
+        let wanted = match self {
+            EncoderKind::Ffmpeg | EncoderKind::Internal => "--encoder internal".to_string(),
+            EncoderKind::Gif(_) | EncoderKind::Apng => format!("--format {}", self.name()),
+        };
+        RustlebrotError::missing_feature("video-internal", wanted)
+    }
+
+    /// `stem` with the extension of the container, unless `--video-out`
+    /// gave the path.
+    fn default_output(self, stem: &str, options: &VideoOptions) -> String {
+        let extension = match self {
+            EncoderKind::Ffmpeg if options.codec.starts_with("libvpx") => "webm",
+            EncoderKind::Ffmpeg => "mp4",
+            EncoderKind::Internal => "avi",
+            EncoderKind::Gif(_) => "gif",
+            EncoderKind::Apng => "png",
+        };
+        match &options.output {
+            Some(output) => output.clone(),
+            None => format!("{}.{}", stem, extension),
+        }
+    }
+
+    /// The path the video is written to: the one given with `--video-out`,
+    /// or `stem` with the extension of the container.
+    ///
+    /// Unless `--overwrite` was given, this fails if the video exists
+    /// already. It's called before rendering, so a run can't end by
+    /// clobbering an earlier one or by finding out it can't.
+    pub fn output(self, stem: &str, options: &VideoOptions) -> Result<String, RustlebrotError> {
+        let output = self.default_output(stem, options);
+        if !options.overwrite && Path::new(&output).exists() {
+            return Err(RustlebrotError::Argument(format!(
+                "{} exists already, pass --overwrite to replace it",
+                output
+            )));
+        }
+        Ok(output)
+    }
+
+    /// The size `width` by `height` frames are encoded at: their own, or
+    /// with `pad_to_even` a row or column larger where the pixel format
+    /// ffmpeg encodes in has its color at half the resolution.
+    ///
+    /// Fails on an odd size the pixel format can't take without padding.
+    /// It's called before rendering, along with `resolve`, so that doesn't
+    /// end a long render instead.
+    pub fn video_size(
+        self,
+        width: u32,
+        height: u32,
+        bit_depth: BitDepth,
+        options: &VideoOptions,
+    ) -> Result<(u32, u32), RustlebrotError> {
+        let EncoderKind::Ffmpeg = self else {
+            return Ok((width, height));
+        };
+        let pix_fmt = pix_fmt(options, bit_depth);
+        let (across, down) = chroma_blocks(pix_fmt);
+        let padded = (width.next_multiple_of(across), height.next_multiple_of(down));
+        if padded == (width, height) || options.pad_to_even {
+            return Ok(padded);
+        }
+        let odd = match (padded.0 != width, padded.1 != height) {
+            (true, true) => "width and height",
+            (true, false) => "width",
+            _ => "height",
+        };
+        Err(RustlebrotError::Argument(format!(
+            "the frames are {}x{}, but ffmpeg encodes {} video in {}, which needs an even {}; \
+             pass --pad-to-even to pad the video by a pixel, or choose an even --width and \
+             --height",
+            width, height, options.codec, pix_fmt, odd
+        )))
+    }
+
+    /// Starts encoding `width` by `height` frames into `output`.
+    pub fn open(
+        self,
+        output: &str,
+        width: u32,
+        height: u32,
+        bit_depth: BitDepth,
+        options: &VideoOptions,
+    ) -> Result<Box<dyn Encoder>, RustlebrotError> {
+        Ok(match self {
+            EncoderKind::Ffmpeg => {
+                let size = self.video_size(width, height, bit_depth, options)?;
+                let args = ffmpeg_args(options, size.0, size.1, bit_depth, output);
+                let channels = if options.alpha { 4 } else { 3 };
+                let channel_len = match bit_depth {
+                    BitDepth::Eight => 1,
+                    BitDepth::Sixteen => 2,
+                };
+                let padding = (size != (width, height)).then(|| {
+                    let (r, g, b) = options.background;
+                    // The padding is as transparent as the interior.
+                    let pixel = [r, g, b, 0][..channels]
+                        .iter()
+                        .flat_map(|&c| match bit_depth {
+                            BitDepth::Eight => vec![c],
+                            BitDepth::Sixteen => (c as u16 * 257).to_le_bytes().to_vec(),
+                        })
+                        .collect();
+                    let pixel_len = channels * channel_len;
+                    Padding {
+                        row_len: width as usize * pixel_len,
+                        padded_row_len: size.0 as usize * pixel_len,
+                        padded_rows: size.1 as usize,
+                        pixel,
+                    }
+                });
+                let frame_len = width as usize * height as usize * channels * channel_len;
+                Box::new(FfmpegEncoder::spawn(&args, output, frame_len, padding)?)
+            }
+            #[cfg(feature = "video-internal")]
+            EncoderKind::Internal => {
+                Box::new(MjpegEncoder::create(output, width, height, bit_depth, options.fps)?)
+            }
+            #[cfg(feature = "video-internal")]
+            EncoderKind::Gif(gif) => {
+                Box::new(GifEncoder::create(output, width, height, bit_depth, gif)?)
+            }
+            #[cfg(feature = "video-internal")]
+            EncoderKind::Apng => {
+                Box::new(ApngEncoder::create(output, width, height, bit_depth, options.fps)?)
+            }
+            #[cfg(not(feature = "video-internal"))]
+            EncoderKind::Internal | EncoderKind::Gif(_) | EncoderKind::Apng => {
+                return Err(self.not_built())
+            }
+        })
+    }
+}
+
+/// The channels of `img` as `Encoder::push_frame` takes them, with its
+/// alpha channel only if `alpha`.
+pub fn raw_frame(img: &DynamicImage, alpha: bool) -> Vec<u8> {
+    match img {
+        DynamicImage::ImageRgb8(img) => img.as_raw().clone(),
+        DynamicImage::ImageRgb16(img) => little_endian(img.as_raw()),
+        DynamicImage::ImageRgba8(img) if alpha => img.as_raw().clone(),
+        DynamicImage::ImageRgba16(img) if alpha => little_endian(img.as_raw()),
+        DynamicImage::ImageRgba8(_) => img.to_rgb8().into_raw(),
+        DynamicImage::ImageRgba16(_) => little_endian(img.to_rgb16().as_raw()),
+        _ => unreachable!("frames are rendered as RGB or RGBA"),
+    }
+}
+
+fn little_endian(channels: &[u16]) -> Vec<u8> {
+    channels.iter().flat_map(|c| c.to_le_bytes()).collect()
+}
+
+/// The pixel format ffmpeg encodes frames with an alpha channel into with
+/// `codec`, or `None` if the codec has none.
+pub fn alpha_pix_fmt(codec: &str) -> Option<&'static str> {
+    match codec {
+        "libvpx-vp9" => Some("yuva420p"),
+        "prores_ks" => Some("yuva444p10le"),
+        "qtrle" => Some("argb"),
+        "png" => Some("rgba"),
+        _ => None,
+    }
+}
+
+/// Encodes the frames at `paths` into `output` with `kind`, in the `Order`
+/// of `options`. Frames shown again are read again, but for a hold of the
+/// last, which is read once.
+pub fn encode_frames(
+    paths: &[String],
+    output: &str,
+    bit_depth: BitDepth,
+    kind: EncoderKind,
+    options: &VideoOptions,
+) -> Result<String, RustlebrotError> {
+    let mut encoder: Option<Box<dyn Encoder>> = None;
+    let order = Order::new(paths.len(), kind.frame_rate(options), options);
+    // The index of the frame read last, and its channels.
+    let mut read: Option<(usize, Vec<u8>)> = None;
+    for index in order.frames() {
+        if read.as_ref().is_none_or(|&(last, _)| last != index) {
+            let path = &paths[index];
+            let img = image::open(path).map_err(|e| RustlebrotError::format(path, e))?;
+            let img = match (bit_depth, options.alpha) {
+                (BitDepth::Eight, false) => DynamicImage::ImageRgb8(img.to_rgb8()),
+                (BitDepth::Sixteen, false) => DynamicImage::ImageRgb16(img.to_rgb16()),
+                (BitDepth::Eight, true) => DynamicImage::ImageRgba8(img.to_rgba8()),
+                (BitDepth::Sixteen, true) => DynamicImage::ImageRgba16(img.to_rgba16()),
+            };
+            if encoder.is_none() {
+                let opened = kind.open(output, img.width(), img.height(), bit_depth, options)?;
+                encoder = Some(opened);
+            }
+            read = Some((index, raw_frame(&img, options.alpha)));
+        }
+        let (encoder, (_, frame)) = encoder.as_mut().zip(read.as_ref()).expect("read above");
+        encoder.push_frame(frame)?;
+    }
+    encoder.ok_or_else(|| RustlebrotError::encode(output, "no frames to encode"))?.finish()
+}
+
+/// `encoder`, handed the frames as they're rendered, with those `--video-sequence`
+/// adds after them once they're all in: the hold of the last, or the way
+/// back of a boomerang. For that, the frames are spooled to a file next to
+/// `output` as they come, and read back from it in reverse, so none is
+/// rendered twice and none is kept in memory.
+pub fn sequenced(
+    encoder: Box<dyn Encoder>,
+    output: &str,
+    fps: f64,
+    options: &VideoOptions,
+) -> Result<Box<dyn Encoder>, RustlebrotError> {
+    let sequence = options.sequence.unwrap_or_default();
+    let spool = match sequence {
+        Sequence::Normal => return Ok(encoder),
+        Sequence::Boomerang => {
+            let path = format!("{}.spool", output);
+            let file = File::options()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(&path)
+                .map_err(|e| RustlebrotError::write(&path, e))?;
+            Some((file, path))
+        }
+        Sequence::LoopHold(_) => None,
+    };
+    Ok(Box::new(Sequenced {
+        encoder,
+        held: sequence.held(fps),
+        spool,
+        last: Vec::new(),
+        count: 0,
+    }))
+}
+
+/// The encoder of `sequenced`.
+struct Sequenced {
+    encoder: Box<dyn Encoder>,
+    /// Times the last frame is shown again.
+    held: usize,
+    /// The file the frames are spooled to for the way back, and its path.
+    spool: Option<(File, String)>,
+    /// The last frame handed over, and how many were.
+    last: Vec<u8>,
+    count: usize,
+}
+
+impl Sequenced {
+    /// Hands the encoder the frames spooled to `file`, from the one before
+    /// the last back to the second, as `Order` has a boomerang.
+    fn play_back(&mut self, file: &mut File, path: &str) -> Result<(), RustlebrotError> {
+        let mut frame = vec![0; self.last.len()];
+        for index in (1..self.count.saturating_sub(1)).rev() {
+            file.seek(SeekFrom::Start((index * frame.len()) as u64))
+                .and_then(|_| file.read_exact(&mut frame))
+                .map_err(|e| RustlebrotError::read(path, e))?;
+            self.encoder.push_frame(&frame)?;
+        }
+        Ok(())
+    }
+}
+
+impl Encoder for Sequenced {
+    fn push_frame(&mut self, frame: &[u8]) -> Result<(), RustlebrotError> {
+        self.encoder.push_frame(frame)?;
+        if let Some((file, path)) = &mut self.spool {
+            file.write_all(frame).map_err(|e| RustlebrotError::write(path.as_str(), e))?;
+        }
+        self.last.clear();
+        self.last.extend_from_slice(frame);
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Adds the frames of the sequence, and finishes the video. The spool
+    /// is removed whether they could be added or not.
+    fn finish(mut self: Box<Self>) -> Result<String, RustlebrotError> {
+        if let Some((mut file, path)) = self.spool.take() {
+            let played = self.play_back(&mut file, &path);
+            drop(file);
+            let _ = fs::remove_file(&path);
+            played?;
+        }
+        if self.count > 0 {
+            for _ in 0..self.held {
+                self.encoder.push_frame(&self.last)?;
+            }
+        }
+        self.encoder.finish()
+    }
+}
+
+/// Whether an ffmpeg binary can be run.
+fn ffmpeg_found() -> bool {
+    Command::new("ffmpeg")
+        .arg("-version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok()
+}
+
+/// The arguments ffmpeg is run with to encode raw `width` by `height`
+/// frames from its stdin into `output`.
+pub fn ffmpeg_args(
+    options: &VideoOptions,
+    width: u32,
+    height: u32,
+    bit_depth: BitDepth,
+    output: &str,
+) -> Vec<String> {
+    let pixel_format = match (bit_depth, options.alpha) {
+        (BitDepth::Eight, false) => "rgb24",
+        (BitDepth::Sixteen, false) => "rgb48le",
+        (BitDepth::Eight, true) => "rgba",
+        (BitDepth::Sixteen, true) => "rgba64le",
+    };
+    // `output` has been checked already, and ffmpeg shouldn't ask.
+    let overwrite = if options.overwrite { "-y" } else { "-n" };
+    let mut args: Vec<String> = [overwrite, "-f", "rawvideo", "-pixel_format", pixel_format]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+    args.push("-video_size".to_string());
+    args.push(format!("{}x{}", width, height));
+    args.push("-framerate".to_string());
+    args.push(options.fps.to_string());
+    args.extend(["-i", "-"].map(String::from));
+    args.extend(output_args(options, bit_depth, output));
+    args
+}
+
+/// The frames `manual_command` encodes.
+pub enum FrameFiles<'a> {
+    /// The frames matching an image sequence pattern, numbered from
+    /// `start`.
+    Pattern { pattern: &'a str, start: u32, count: usize },
+    /// Frames whose names no pattern matches, in order, which are piped
+    /// into ffmpeg instead.
+    Paths(&'a [String]),
+}
+
+/// The ffmpeg command that encodes the `frames` with `options`. It's
+/// given when encoding frames that were rendered fine failed, so they can
+/// be encoded by hand instead of rendered again.
+///
+/// With `there_and_back` or a boomerang, ffmpeg plays the frames back in a
+/// filter, which holds all of them in memory, and another holds the last
+/// for `--video-sequence loop-hold`.
+pub fn manual_command(
+    frames: &FrameFiles,
+    stem: &str,
+    bit_depth: BitDepth,
+    options: &VideoOptions,
+) -> String {
+    let output = EncoderKind::Ffmpeg.default_output(stem, options);
+    let mut args: Vec<String> = vec!["ffmpeg".to_string(), "-framerate".to_string()];
+    args.push(options.fps.to_string());
+    let (count, piped) = match frames {
+        FrameFiles::Pattern {
+            pattern,
+            start,
+            count,
+        } => {
+            args.push("-start_number".to_string());
+            args.push(start.to_string());
+            args.extend(["-i", pattern].map(String::from));
+            (*count, None)
+        }
+        FrameFiles::Paths(paths) => {
+            args.extend(["-f", "image2pipe"].map(String::from));
+            // The piped images are decoded as the format of their files.
+            let ext = paths.first().and_then(|path| Path::new(path).extension()?.to_str());
+            if let Some(format) = ext.and_then(ImageFormat::from_extension) {
+                args.extend(["-c:v", format.ffmpeg_codec()].map(String::from));
+            }
+            args.extend(["-i", "-"].map(String::from));
+            let quoted: Vec<String> = paths.iter().map(|path| shell_quote(path)).collect();
+            (paths.len(), Some(format!("cat {} | ", quoted.join(" "))))
+        }
+    };
+    // As `open` pads the frames, to an even size whatever the format.
+    let (r, g, b) = options.background;
+    let pad = format!(",pad=ceil(iw/2)*2:ceil(ih/2)*2:color=0x{:02x}{:02x}{:02x}", r, g, b);
+    let pad = if options.pad_to_even { pad.as_str() } else { "" };
+    let order = Order::new(count, options.fps as f64, options);
+    let tail = match order.held {
+        0 => pad.to_string(),
+        held => format!(",tpad=stop_mode=clone:stop={}{}", held, pad),
+    };
+    match order.spans.get(1) {
+        Some(&(_, last)) => {
+            args.push("-filter_complex".to_string());
+            args.push(format!(
+                "[0:v]trim=end_frame={},setpts=PTS-STARTPTS,split[in][back];\
+                 [back]reverse,trim=start_frame=1:end_frame={},setpts=PTS-STARTPTS[out];\
+                 [in][out]concat{}",
+                count,
+                count - last,
+                tail
+            ));
+        }
+        None if !tail.is_empty() => {
+            args.push("-vf".to_string());
+            args.push(tail[1..].to_string());
+        }
+        None => {}
+    }
+    args.push("-frames:v".to_string());
+    args.push(order.shown().to_string());
+    args.extend(output_args(options, bit_depth, &output));
+    let quoted: Vec<String> = args.iter().map(|arg| shell_quote(arg)).collect();
+    piped.unwrap_or_default() + &quoted.join(" ")
+}
+
+/// `arg` quoted for a POSIX shell, when it needs it.
+fn shell_quote(arg: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "-_./:%=+,".contains(c);
+    if !arg.is_empty() && arg.chars().all(plain) {
+        return arg.to_string();
+    }
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+/// The pixel format ffmpeg encodes the video in, unless `--ffmpeg-arg`
+/// gives another after it.
+fn default_pix_fmt(options: &VideoOptions, bit_depth: BitDepth) -> &'static str {
+    match (bit_depth, options.alpha) {
+        (_, true) => alpha_pix_fmt(&options.codec).unwrap_or("yuva420p"),
+        (BitDepth::Eight, false) => "yuv420p",
+        // Keep some of the extra precision of 16-bit frames in the video.
+        (BitDepth::Sixteen, false) => "yuv420p10le",
+    }
+}
+
+/// The pixel format ffmpeg encodes the video in, the last one the
+/// arguments give.
+fn pix_fmt(options: &VideoOptions, bit_depth: BitDepth) -> &str {
+    options
+        .ffmpeg_args
+        .windows(2)
+        .rev()
+        .find(|pair| pair[0] == "-pix_fmt")
+        .map_or(default_pix_fmt(options, bit_depth), |pair| &pair[1])
+}
+
+/// The multiples of which the width and height of video in `pix_fmt` must
+/// be, 2 along the sides its color is at half the resolution along.
+fn chroma_blocks(pix_fmt: &str) -> (u32, u32) {
+    let subsampled = |prefixes: &[&str]| prefixes.iter().any(|prefix| pix_fmt.starts_with(prefix));
+    if subsampled(&["yuv420", "yuva420", "yuvj420", "nv12", "nv21", "p010", "p016"]) {
+        (2, 2)
+    } else if subsampled(&["yuv422", "yuva422", "yuvj422", "yuyv422", "uyvy422", "nv16"]) {
+        (2, 1)
+    } else {
+        (1, 1)
+    }
+}
+
+/// The arguments that encode the frames ffmpeg reads into `output`.
+fn output_args(options: &VideoOptions, bit_depth: BitDepth, output: &str) -> Vec<String> {
+    let pix_fmt = default_pix_fmt(options, bit_depth);
+    let mut args: Vec<String> =
+        ["-c:v", &options.codec, "-pix_fmt", pix_fmt].map(String::from).into();
+    if let Some(crf) = options.crf {
+        args.push("-crf".to_string());
+        args.push(crf.to_string());
+        // libvpx only keeps to the rate factor without a target bitrate.
+        if options.codec.starts_with("libvpx") {
+            args.extend(["-b:v", "0"].map(String::from));
+        }
+    }
+    args.extend(options.ffmpeg_args.iter().cloned());
+    args.push(output.to_string());
+    args
+}
+
+/// Encodes with an ffmpeg process reading raw frames from its stdin.
+struct FfmpegEncoder {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    /// Collects what ffmpeg prints, to explain why it failed.
+    stderr: Option<JoinHandle<String>>,
+    output: String,
+    frame_len: usize,
+    padding: Option<Padding>,
+}
+
+/// How `FfmpegEncoder` pads frames up to the size of the video.
+struct Padding {
+    /// Bytes of a row of a frame, and of a row of the video.
+    row_len: usize,
+    padded_row_len: usize,
+    padded_rows: usize,
+    /// The bytes of a pixel of padding.
+    pixel: Vec<u8>,
+}
+
+impl Padding {
+    /// `frame`, with every row and then the frame filled out with pixels
+    /// of padding.
+    fn pad(&self, frame: &[u8]) -> Vec<u8> {
+        let padded_row = || self.pixel.iter().copied().cycle();
+        let mut padded = Vec::with_capacity(self.padded_row_len * self.padded_rows);
+        for row in frame.chunks_exact(self.row_len) {
+            padded.extend_from_slice(row);
+            padded.extend(padded_row().take(self.padded_row_len - self.row_len));
+        }
+        let rest = self.padded_row_len * self.padded_rows - padded.len();
+        padded.extend(padded_row().take(rest));
+        padded
+    }
+}
+
+impl FfmpegEncoder {
+    fn spawn(
+        args: &[String],
+        output: &str,
+        frame_len: usize,
+        padding: Option<Padding>,
+    ) -> Result<Self, RustlebrotError> {
+        let mut command = Command::new("ffmpeg");
+        command.args(args).stdin(Stdio::piped()).stdout(Stdio::null()).stderr(Stdio::piped());
+        // Ctrl-C is for us, so ffmpeg finishes the video of the frames sent
+        // to it instead of stopping halfway.
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut command, 0);
+        let mut child = command.spawn().map_err(RustlebrotError::FfmpegSpawn)?;
+        let stdin = child.stdin.take();
+        // Read stderr as it comes, or ffmpeg could block on a full pipe.
+        let stderr = child.stderr.take().map(|mut stderr| {
+            std::thread::spawn(move || {
+                let mut log = String::new();
+                let _ = stderr.read_to_string(&mut log);
+                log
+            })
+        });
+        Ok(FfmpegEncoder {
+            child,
+            stdin,
+            stderr,
+            output: output.to_string(),
+            frame_len,
+            padding,
+        })
+    }
+
+    /// Waits for ffmpeg to exit and describes how it failed, with the last
+    /// lines it printed.
+    fn failure(&mut self) -> RustlebrotError {
+        drop(self.stdin.take());
+        let status = match self.child.wait() {
+            Ok(status) => status.to_string(),
+            Err(e) => e.to_string(),
+        };
+        let log = self.stderr.take().and_then(|log| log.join().ok()).unwrap_or_default();
+        let lines: Vec<&str> = log.lines().collect();
+        let tail = lines[lines.len().saturating_sub(5)..].join("\n");
+        RustlebrotError::FfmpegExit {
+            path: self.output.clone(),
+            message: format!("{}\n{}", status, tail),
+        }
+    }
+}
+
+impl Encoder for FfmpegEncoder {
+    /// Fails as soon as ffmpeg has exited, instead of when the pipe fills up.
+    fn push_frame(&mut self, frame: &[u8]) -> Result<(), RustlebrotError> {
+        assert_eq!(frame.len(), self.frame_len);
+        if let Ok(Some(_)) = self.child.try_wait() {
+            return Err(self.failure());
+        }
+        let padded = self.padding.as_ref().map(|padding| padding.pad(frame));
+        let stdin = self.stdin.as_mut().expect("stdin is open until finish");
+        if stdin.write_all(padded.as_deref().unwrap_or(frame)).is_err() {
+            return Err(self.failure());
+        }
+        Ok(())
+    }
+
+    /// Closes stdin so ffmpeg finishes the video, and waits for it to exit.
+    fn finish(mut self: Box<Self>) -> Result<String, RustlebrotError> {
+        drop(self.stdin.take());
+        match self.child.wait() {
+            Ok(status) if status.success() => Ok(self.output.clone()),
+            _ => Err(self.failure()),
+        }
+    }
+}
+
+/// Size of the headers of the AVI files written by `MjpegEncoder`, up to
+/// the `movi` list the frames go in.
+#[cfg(feature = "video-internal")]
+const AVI_HEADER_LEN: u64 = 224;
+
+/// Writes frames as JPEGs into an AVI file, which every common player can
+/// play back.
+///
+/// Frames are written as they come, and the sizes and frame count in the
+/// headers are filled in by `finish`. AVI sizes are 32-bit, so a video is
+/// limited to 4 GB.
+#[cfg(feature = "video-internal")]
+struct MjpegEncoder {
+    file: BufWriter<File>,
+    output: String,
+    width: u32,
+    height: u32,
+    bit_depth: BitDepth,
+    fps: u32,
+    /// Offset of every frame from the `movi` list type, and its size.
+    index: Vec<(u32, u32)>,
+    /// Bytes written after the `movi` list type.
+    movi_len: u32,
+}
+
+#[cfg(feature = "video-internal")]
+impl MjpegEncoder {
+    fn create(
+        output: &str,
+        width: u32,
+        height: u32,
+        bit_depth: BitDepth,
+        fps: u16,
+    ) -> Result<Self, RustlebrotError> {
+        let file = File::create(output).map_err(|e| RustlebrotError::write(output, e))?;
+        let mut encoder = MjpegEncoder {
+            file: BufWriter::new(file),
+            output: output.to_string(),
+            width,
+            height,
+            bit_depth,
+            fps: fps as u32,
+            index: Vec::new(),
+            movi_len: 0,
+        };
+        encoder.write_headers().map_err(|e| encoder.failed(e))?;
+        Ok(encoder)
+    }
+
+    fn failed(&self, e: std::io::Error) -> RustlebrotError {
+        RustlebrotError::write(&self.output, e)
+    }
+
+    /// Writes the RIFF headers with the sizes and frame count of the frames
+    /// pushed so far, leaving the file at the end of the headers.
+    fn write_headers(&mut self) -> std::io::Result<()> {
+        let frames = self.index.len() as u32;
+        let index_len = 8 + 16 * frames;
+        let largest = self.index.iter().map(|&(_, size)| size).max().unwrap_or(0);
+        let (width, height, fps) = (self.width, self.height, self.fps);
+        let mut header = Vec::with_capacity(AVI_HEADER_LEN as usize);
+        let riff_len = AVI_HEADER_LEN as u32 - 8 + self.movi_len + index_len;
+        header.extend_from_slice(b"RIFF");
+        header.extend_from_slice(&riff_len.to_le_bytes());
+        header.extend_from_slice(b"AVI LIST");
+        header.extend_from_slice(&192u32.to_le_bytes());
+        header.extend_from_slice(b"hdrlavih");
+        let avih = [
+            1_000_000 / fps,
+            largest * fps,
+            0,
+            // AVIF_HASINDEX
+            0x10,
+            frames,
+            0,
+            1,
+            largest,
+            width,
+            height,
+            0,
+            0,
+            0,
+            0,
+        ];
+        header.extend_from_slice(&56u32.to_le_bytes());
+        avih.iter().for_each(|v| header.extend_from_slice(&v.to_le_bytes()));
+        header.extend_from_slice(b"LIST");
+        header.extend_from_slice(&116u32.to_le_bytes());
+        header.extend_from_slice(b"strlstrh");
+        header.extend_from_slice(&56u32.to_le_bytes());
+        header.extend_from_slice(b"vidsMJPG");
+        let strh = [0, 0, 0, 1, fps, 0, frames, largest, u32::MAX, 0];
+        strh.iter().for_each(|v| header.extend_from_slice(&v.to_le_bytes()));
+        [0, 0, width as u16, height as u16]
+            .iter()
+            .for_each(|v: &u16| header.extend_from_slice(&v.to_le_bytes()));
+        header.extend_from_slice(b"strf");
+        header.extend_from_slice(&40u32.to_le_bytes());
+        header.extend_from_slice(&40u32.to_le_bytes());
+        header.extend_from_slice(&width.to_le_bytes());
+        header.extend_from_slice(&height.to_le_bytes());
+        header.extend_from_slice(&1u16.to_le_bytes());
+        header.extend_from_slice(&24u16.to_le_bytes());
+        header.extend_from_slice(b"MJPG");
+        [width * height * 3, 0, 0, 0, 0]
+            .iter()
+            .for_each(|v| header.extend_from_slice(&v.to_le_bytes()));
+        header.extend_from_slice(b"LIST");
+        header.extend_from_slice(&(4 + self.movi_len).to_le_bytes());
+        header.extend_from_slice(b"movi");
+        debug_assert_eq!(header.len() as u64, AVI_HEADER_LEN);
+
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(&header)
+    }
+}
+
+#[cfg(feature = "video-internal")]
+impl Encoder for MjpegEncoder {
+    fn push_frame(&mut self, frame: &[u8]) -> Result<(), RustlebrotError> {
+        let rgb8;
+        let rgb = match self.bit_depth {
+            BitDepth::Eight => frame,
+            // JPEG only takes 8-bit channels, so keep the high byte.
+            BitDepth::Sixteen => {
+                rgb8 = frame.chunks_exact(2).map(|c| c[1]).collect::<Vec<u8>>();
+                &rgb8
+            }
+        };
+        let mut jpeg = Vec::new();
+        JpegEncoder::new_with_quality(&mut jpeg, JPEG_QUALITY)
+            .encode(rgb, self.width, self.height, ColorType::Rgb8)
+            .map_err(|e| RustlebrotError::encode(&self.output, e))?;
+        // Chunks are padded to an even length.
+        if jpeg.len() % 2 == 1 {
+            jpeg.push(0);
+        }
+
+        let size = jpeg.len() as u32;
+        self.index.push((4 + self.movi_len, size));
+        self.file
+            .write_all(b"00dc")
+            .and_then(|_| self.file.write_all(&size.to_le_bytes()))
+            .and_then(|_| self.file.write_all(&jpeg))
+            .map_err(|e| self.failed(e))?;
+        self.movi_len += 8 + size;
+        Ok(())
+    }
+
+    /// Appends the frame index and fills in the headers.
+    fn finish(mut self: Box<Self>) -> Result<String, RustlebrotError> {
+        let mut index = Vec::with_capacity(8 + 16 * self.index.len());
+        index.extend_from_slice(b"idx1");
+        index.extend_from_slice(&(16 * self.index.len() as u32).to_le_bytes());
+        for &(offset, size) in &self.index {
+            index.extend_from_slice(b"00dc");
+            // AVIIF_KEYFRAME
+            index.extend_from_slice(&0x10u32.to_le_bytes());
+            index.extend_from_slice(&offset.to_le_bytes());
+            index.extend_from_slice(&size.to_le_bytes());
+        }
+        self.file
+            .write_all(&index)
+            .and_then(|_| self.write_headers())
+            .and_then(|_| self.file.flush())
+            .map_err(|e| self.failed(e))?;
+        Ok(self.output.clone())
+    }
+}
+
+/// How many pixels NeuQuant learns the palette from, one in this many. 10
+/// is the quality the algorithm recommends, and far faster than looking at
+/// every pixel.
+#[cfg(feature = "video-internal")]
+const NEUQUANT_SAMPLING: i32 = 10;
+
+/// Writes frames into an animated GIF as they come, each quantized to its
+/// own palette with NeuQuant.
+///
+/// A palette per frame follows the colors as they drift through a zoom,
+/// where one palette for the whole sequence would have to be computed before
+/// the first frame could be written.
+#[cfg(feature = "video-internal")]
+struct GifEncoder {
+    encoder: gif::Encoder<BufWriter<File>>,
+    output: String,
+    width: u16,
+    height: u16,
+    bit_depth: BitDepth,
+    options: GifOptions,
+}
+
+#[cfg(feature = "video-internal")]
+impl GifEncoder {
+    fn create(
+        output: &str,
+        width: u32,
+        height: u32,
+        bit_depth: BitDepth,
+        options: GifOptions,
+    ) -> Result<Self, RustlebrotError> {
+        let failed = |e: gif::EncodingError| RustlebrotError::encode(output, e);
+        let size = |side: u32| {
+            u16::try_from(side).map_err(|_| {
+                RustlebrotError::encode(output, format!("GIF frames can't be {} pixels wide", side))
+            })
+        };
+        let (width, height) = (size(width)?, size(height)?);
+        let file = File::create(output).map_err(|e| RustlebrotError::write(output, e))?;
+        let mut encoder =
+            gif::Encoder::new(BufWriter::new(file), width, height, &[]).map_err(failed)?;
+        // A repeat count of 0 would mean forever, so GIFs that play once go
+        // without one.
+        let repeat = match options.repeat {
+            Some(0) => None,
+            Some(count) => Some(gif::Repeat::Finite(count)),
+            None => Some(gif::Repeat::Infinite),
+        };
+        if let Some(repeat) = repeat {
+            encoder.set_repeat(repeat).map_err(failed)?;
+        }
+        Ok(GifEncoder {
+            encoder,
+            output: output.to_string(),
+            width,
+            height,
+            bit_depth,
+            options,
+        })
+    }
+}
+
+#[cfg(feature = "video-internal")]
+impl Encoder for GifEncoder {
+    fn push_frame(&mut self, frame: &[u8]) -> Result<(), RustlebrotError> {
+        // The quantizer takes RGBA. 16-bit frames keep their high bytes.
+        let rgba: Vec<u8> = match self.bit_depth {
+            BitDepth::Eight => {
+                frame.chunks_exact(3).flat_map(|c| [c[0], c[1], c[2], 255]).collect()
+            }
+            BitDepth::Sixteen => {
+                frame.chunks_exact(6).flat_map(|c| [c[1], c[3], c[5], 255]).collect()
+            }
+        };
+        let quant = NeuQuant::new(NEUQUANT_SAMPLING, self.options.colors as usize, &rgba);
+        let pixels: Vec<u8> = rgba.par_chunks_exact(4).map(|c| quant.index_of(c) as u8).collect();
+        let palette = quant.color_map_rgb();
+        let mut frame =
+            gif::Frame::from_palette_pixels(self.width, self.height, pixels, palette, None);
+        frame.delay = self.options.delay;
+        self.encoder
+            .write_frame(&frame)
+            .map_err(|e| RustlebrotError::encode(&self.output, e))
+    }
+
+    /// Writes the GIF trailer.
+    fn finish(self: Box<Self>) -> Result<String, RustlebrotError> {
+        let output = self.output;
+        self.encoder
+            .into_inner()
+            .and_then(|mut file| file.flush())
+            .map_err(|e| RustlebrotError::write(&output, e))?;
+        Ok(output)
+    }
+}
+
+/// Offset of the `acTL` chunk, which follows the signature and `IHDR`.
+#[cfg(feature = "video-internal")]
+const ACTL_OFFSET: u64 = 8 + 25;
+
+/// The `IEND` chunk that closes every PNG.
+#[cfg(feature = "video-internal")]
+const IEND: [u8; 12] = [0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82];
+
+/// Writes frames into an animated PNG as they come, losslessly and at the
+/// bit depth they were rendered at.
+///
+/// After every frame the file ends in `IEND` and its `acTL` counts the
+/// frames written so far, so a run that is interrupted still leaves a valid
+/// animation of the frames it finished.
+#[cfg(feature = "video-internal")]
+struct ApngEncoder {
+    file: BufWriter<File>,
+    output: String,
+    width: u32,
+    height: u32,
+    bit_depth: BitDepth,
+    fps: u16,
+    frames: u32,
+    /// The sequence number of the next `fcTL` or `fdAT` chunk.
+    sequence: u32,
+}
+
+#[cfg(feature = "video-internal")]
+impl ApngEncoder {
+    fn create(
+        output: &str,
+        width: u32,
+        height: u32,
+        bit_depth: BitDepth,
+        fps: u16,
+    ) -> Result<Self, RustlebrotError> {
+        let file = File::create(output).map_err(|e| RustlebrotError::write(output, e))?;
+        let mut encoder = ApngEncoder {
+            file: BufWriter::new(file),
+            output: output.to_string(),
+            width,
+            height,
+            bit_depth,
+            fps,
+            frames: 0,
+            sequence: 0,
+        };
+        let depth = match bit_depth {
+            BitDepth::Eight => 8,
+            BitDepth::Sixteen => 16,
+        };
+        let mut ihdr = Vec::with_capacity(13);
+        ihdr.extend_from_slice(&width.to_be_bytes());
+        ihdr.extend_from_slice(&height.to_be_bytes());
+        // Truecolor, deflate, adaptive filtering, no interlacing.
+        ihdr.extend_from_slice(&[depth, 2, 0, 0, 0]);
+        encoder
+            .file
+            .write_all(b"\x89PNG\r\n\x1a\n")
+            .and_then(|_| encoder.write_chunk(b"IHDR", &ihdr))
+            .and_then(|_| encoder.write_actl())
+            .and_then(|_| encoder.file.write_all(&IEND))
+            .map_err(|e| encoder.failed(e))?;
+        Ok(encoder)
+    }
+
+    fn failed(&self, e: std::io::Error) -> RustlebrotError {
+        RustlebrotError::write(&self.output, e)
+    }
+
+    fn write_chunk(&mut self, kind: &[u8; 4], data: &[u8]) -> std::io::Result<()> {
+        let mut crc = crc32fast::Hasher::new();
+        crc.update(kind);
+        crc.update(data);
+        self.file.write_all(&(data.len() as u32).to_be_bytes())?;
+        self.file.write_all(kind)?;
+        self.file.write_all(data)?;
+        self.file.write_all(&crc.finalize().to_be_bytes())
+    }
+
+    /// Writes the animation control chunk with the frames written so far,
+    /// to play forever.
+    fn write_actl(&mut self) -> std::io::Result<()> {
+        let mut actl = [0; 8];
+        actl[..4].copy_from_slice(&self.frames.to_be_bytes());
+        self.write_chunk(b"acTL", &actl)
+    }
+
+    /// Compresses `frame`, as big-endian channels, the way `save_png`
+    /// compresses frames, and returns its image data.
+    fn compress(&self, frame: &[u8]) -> Result<Vec<u8>, png::EncodingError> {
+        let depth = match self.bit_depth {
+            BitDepth::Eight => png::BitDepth::Eight,
+            BitDepth::Sixteen => png::BitDepth::Sixteen,
+        };
+        let mut encoded = Vec::new();
+        let mut encoder = png::Encoder::new(&mut encoded, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(depth);
+        encoder.set_compression(png::Compression::Default);
+        encoder.set_filter(png::FilterType::Sub);
+        encoder.set_adaptive_filter(png::AdaptiveFilterType::Adaptive);
+        encoder.write_header()?.write_image_data(frame)?;
+
+        // Gather the IDAT chunks of the encoded PNG.
+        let mut data = Vec::new();
+        let mut chunks = &encoded[8..];
+        while chunks.len() >= 12 {
+            let len = u32::from_be_bytes(chunks[..4].try_into().unwrap()) as usize;
+            if &chunks[4..8] == b"IDAT" {
+                data.extend_from_slice(&chunks[8..8 + len]);
+            }
+            chunks = &chunks[12 + len..];
+        }
+        Ok(data)
+    }
+}
+
+#[cfg(feature = "video-internal")]
+impl Encoder for ApngEncoder {
+    fn push_frame(&mut self, frame: &[u8]) -> Result<(), RustlebrotError> {
+        let be;
+        let frame = match self.bit_depth {
+            BitDepth::Eight => frame,
+            BitDepth::Sixteen => {
+                be = frame.chunks_exact(2).flat_map(|c| [c[1], c[0]]).collect::<Vec<u8>>();
+                &be
+            }
+        };
+        let data = self.compress(frame).map_err(|e| RustlebrotError::encode(&self.output, e))?;
+
+        let mut fctl = Vec::with_capacity(26);
+        fctl.extend_from_slice(&self.sequence.to_be_bytes());
+        fctl.extend_from_slice(&self.width.to_be_bytes());
+        fctl.extend_from_slice(&self.height.to_be_bytes());
+        // At the origin, shown for 1/fps seconds, not disposed or blended.
+        fctl.extend_from_slice(&[0; 8]);
+        fctl.extend_from_slice(&1u16.to_be_bytes());
+        fctl.extend_from_slice(&self.fps.to_be_bytes());
+        fctl.extend_from_slice(&[0, 0]);
+        self.sequence += 1;
+        // The first frame is the image data every PNG decoder shows, the
+        // rest is only read by decoders that know APNG.
+        let (kind, data) = if self.frames == 0 {
+            (b"IDAT", data)
+        } else {
+            let mut fdat = Vec::with_capacity(4 + data.len());
+            fdat.extend_from_slice(&self.sequence.to_be_bytes());
+            fdat.extend_from_slice(&data);
+            self.sequence += 1;
+            (b"fdAT", fdat)
+        };
+        self.frames += 1;
+
+        self.file
+            .seek(SeekFrom::End(-(IEND.len() as i64)))
+            .and_then(|_| self.write_chunk(b"fcTL", &fctl))
+            .and_then(|_| self.write_chunk(kind, &data))
+            .and_then(|_| self.file.write_all(&IEND))
+            .and_then(|_| self.file.seek(SeekFrom::Start(ACTL_OFFSET)))
+            .and_then(|_| self.write_actl())
+            .and_then(|_| self.file.flush())
+            .map_err(|e| self.failed(e))
+    }
+
+    /// Every frame is complete when it's pushed, so there is nothing left
+    /// to write.
+    fn finish(self: Box<Self>) -> Result<String, RustlebrotError> {
+        Ok(self.output)
+    }
+}
@@ -0,0 +1,139 @@
+use std::fmt;
+
+/// How the starting view is fitted to a frame of a different shape, as
+/// chosen with `--fit`.
+///
+/// Pixels always cover a square of the plane, so a view whose ranges don't
+/// have the frame's aspect ratio can't be shown exactly; one of its extents
+/// is kept and the other follows from the frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fit {
+    /// Keep the x range and show as much of y as the frame's height gives.
+    Width,
+    /// Keep the y range and show as much of x as the frame's width gives.
+    Height,
+    /// Fill the frame with the view, cropping whichever range sticks out.
+    Cover,
+    /// Show all of both ranges, with more of the plane around the narrower.
+    Contain,
+}
+
+impl Fit {
+    pub fn from_name(name: &str) -> Option<Fit> {
+        match name {
+            "width" => Some(Fit::Width),
+            "height" => Some(Fit::Height),
+            "cover" => Some(Fit::Cover),
+            "contain" => Some(Fit::Contain),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Fit::Width => "width",
+            Fit::Height => "height",
+            Fit::Cover => "cover",
+            Fit::Contain => "contain",
+        }
+    }
+}
+
+/// A view given by its corners, as `--view` takes them, in place of a
+/// center and a magnification.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Corners {
+    pub x_range: (f64, f64),
+    pub y_range: (f64, f64),
+}
+
+impl Corners {
+    /// Parses `x_min,x_max,y_min,y_max`, each range running up and wider
+    /// than nothing.
+    pub fn from_spec(spec: &str) -> Result<Corners, String> {
+        let parts: Vec<f64> = spec
+            .split(',')
+            .map(|part| part.trim().parse().ok().filter(|value: &f64| value.is_finite()))
+            .collect::<Option<_>>()
+            .ok_or_else(|| {
+                format!("view should be four numbers x_min,x_max,y_min,y_max, got '{}'", spec)
+            })?;
+        let [x_min, x_max, y_min, y_max] = parts[..] else {
+            return Err(format!(
+                "view should be four numbers x_min,x_max,y_min,y_max, got {} of them",
+                parts.len()
+            ));
+        };
+        for (axis, min, max) in [("x", x_min, x_max), ("y", y_min, y_max)] {
+            if min == max {
+                return Err(format!(
+                    "the view has no extent along {}, where {}_min and {}_max are both {}",
+                    axis, axis, axis, min
+                ));
+            }
+            if min > max {
+                return Err(format!(
+                    "the view runs backwards along {}, from {} down to {}; the corners go \
+                     x_min,x_max,y_min,y_max",
+                    axis, min, max
+                ));
+            }
+        }
+        Ok(Corners {
+            x_range: (x_min, x_max),
+            y_range: (y_min, y_max),
+        })
+    }
+
+    /// The middle of the view.
+    pub fn center(&self) -> (f64, f64) {
+        ((self.x_range.0 + self.x_range.1) / 2.0, (self.y_range.0 + self.y_range.1) / 2.0)
+    }
+
+    /// The ranges a `width` by `height` frame shows of the view, fitted as
+    /// `fit` says.
+    pub fn fitted(&self, width: u32, height: u32, fit: Fit) -> ((f64, f64), (f64, f64)) {
+        self::fit(self.x_range, self.y_range, width, height, fit)
+    }
+
+    /// Whether the view has the shape of a `width` by `height` frame, so
+    /// every fit shows it as it is, but for rounding.
+    pub fn fits(&self, width: u32, height: u32) -> bool {
+        let x_pixel = (self.x_range.1 - self.x_range.0) / width as f64;
+        let y_pixel = (self.y_range.1 - self.y_range.0) / height as f64;
+        (x_pixel - y_pixel).abs() <= 1e-9 * x_pixel.max(y_pixel)
+    }
+}
+
+impl fmt::Display for Corners {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (x, y) = (self.x_range, self.y_range);
+        write!(f, "{},{},{},{}", x.0, x.1, y.0, y.1)
+    }
+}
+
+/// The ranges of a `width` by `height` frame of square pixels around the
+/// center of `x_range` and `y_range`, with the extent `fit` keeps.
+pub fn fit(
+    x_range: (f64, f64),
+    y_range: (f64, f64),
+    width: u32,
+    height: u32,
+    fit: Fit,
+) -> ((f64, f64), (f64, f64)) {
+    let (x_pixel, y_pixel) = (
+        (x_range.1 - x_range.0) / width as f64,
+        (y_range.1 - y_range.0) / height as f64,
+    );
+    let pixel = match fit {
+        Fit::Width => x_pixel,
+        Fit::Height => y_pixel,
+        Fit::Cover => x_pixel.min(y_pixel),
+        Fit::Contain => x_pixel.max(y_pixel),
+    };
+    let around = |range: (f64, f64), pixels: u32| {
+        let (center, half) = ((range.0 + range.1) / 2.0, pixel * pixels as f64 / 2.0);
+        (center - half, center + half)
+    };
+    (around(x_range, width), around(y_range, height))
+}
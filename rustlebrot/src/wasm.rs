@@ -0,0 +1,20 @@
+//! The library's entry points for JavaScript, through wasm-bindgen.
+
+use wasm_bindgen::prelude::*;
+
+/// `crate::render_region` for JavaScript: the RGBA pixels of the
+/// Mandelbrot set around (`cx`, `cy`), `scale` apart, as a `Uint8Array`
+/// ready for `new ImageData(new Uint8ClampedArray(pixels), width)`. Throws
+/// if the region has no pixels.
+#[wasm_bindgen]
+pub fn render_region(
+    width: u32,
+    height: u32,
+    max_iter: u32,
+    cx: f64,
+    cy: f64,
+    scale: f64,
+) -> Result<Vec<u8>, JsError> {
+    crate::render_region(width, height, max_iter, cx, cy, scale)
+        .map_err(|err| JsError::new(&err.to_string()))
+}
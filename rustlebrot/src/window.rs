@@ -0,0 +1,328 @@
+use crate::bookmarks;
+use crate::error::RustlebrotError;
+use crate::fractal::{Fractal, FractalKind};
+use crate::location::Location;
+use crate::precision::Precision;
+use crate::render::{colorize, compute_escape, ColorOptions, RenderOptions};
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Window, WindowOptions};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+
+/// How many window pixels each pixel of a pass covers along a side. The
+/// coarse passes come back in a moment, so a view follows the mouse, and
+/// the last one fills in every pixel.
+const PASSES: [u32; 3] = [8, 2, 1];
+
+/// How much closer a notch of the scroll wheel zooms.
+const WHEEL_ZOOM: f64 = 1.25;
+
+/// The part of the plane the window shows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct View {
+    center: (f64, f64),
+    /// The distance between pixels of the window.
+    pixel_size: f64,
+}
+
+impl View {
+    /// The point of the plane under window pixel `at`.
+    fn point(&self, at: (f32, f32), size: (u32, u32)) -> (f64, f64) {
+        let dx = at.0 as f64 - size.0 as f64 / 2.0;
+        let dy = at.1 as f64 - size.1 as f64 / 2.0;
+        // Rows count down from the top, where the imaginary part is largest.
+        (self.center.0 + dx * self.pixel_size, self.center.1 - dy * self.pixel_size)
+    }
+
+    /// This view `factor` times closer, keeping the point under `at` where
+    /// it is, or `None` if f64 can't tell its pixels apart.
+    fn zoomed(&self, factor: f64, at: (f32, f32), size: (u32, u32)) -> Option<View> {
+        let fixed = self.point(at, size);
+        let view = View {
+            center: (
+                fixed.0 + (self.center.0 - fixed.0) / factor,
+                fixed.1 + (self.center.1 - fixed.1) / factor,
+            ),
+            pixel_size: self.pixel_size / factor,
+        };
+        Precision::F64.resolves(view.center, view.pixel_size).then_some(view)
+    }
+
+    /// This view with its contents moved `by` window pixels.
+    fn dragged(&self, by: (f32, f32)) -> View {
+        View {
+            center: (
+                self.center.0 - by.0 as f64 * self.pixel_size,
+                self.center.1 + by.1 as f64 * self.pixel_size,
+            ),
+            ..*self
+        }
+    }
+
+    fn ranges(&self, size: (u32, u32)) -> ((f64, f64), (f64, f64)) {
+        let half_width = size.0 as f64 * self.pixel_size / 2.0;
+        let half_height = size.1 as f64 * self.pixel_size / 2.0;
+        let (x, y) = self.center;
+        ((x - half_width, x + half_width), (y - half_height, y + half_height))
+    }
+}
+
+/// How the window shows the fractal.
+pub struct ExploreOptions<'a> {
+    pub fractal: FractalKind,
+    /// The size of the window, in pixels.
+    pub size: (u32, u32),
+    /// The view the window opens on.
+    pub center: (f64, f64),
+    pub pixel_size: f64,
+    /// How fast max_iter grows with magnification, as with `--auto-iter`.
+    pub auto_iter: Option<f64>,
+    /// The per-pixel settings, with the iteration limit of the opening view.
+    pub options: RenderOptions<'a>,
+    pub colors: ColorOptions<'a>,
+    /// The location file `s` adds the view to, besides printing it.
+    pub bookmarks: Option<&'a str>,
+}
+
+impl ExploreOptions<'_> {
+    /// The magnification of `view`, as locations count it.
+    fn magnification(&self, view: &View) -> f64 {
+        let extent = self.size.0.min(self.size.1) as f64 * view.pixel_size;
+        2.0 * self.fractal.default_half_width() / extent
+    }
+
+    /// The iteration limit of `view`.
+    fn max_iter(&self, view: &View) -> u32 {
+        let base = self.options.max_iter;
+        let zoom = view.pixel_size.recip() * self.pixel_size;
+        match self.auto_iter {
+            Some(k) => (base as f64 * (1.0 + k * zoom.log10().max(0.0))).round() as u32,
+            None => base,
+        }
+    }
+
+    /// The location of `view`, its center with every digit f64 has.
+    fn location(&self, view: &View, name: Option<String>) -> Location {
+        Location {
+            name,
+            notes: None,
+            fractal: self.fractal,
+            newton: None,
+            center: (format!("{:?}", view.center.0), format!("{:?}", view.center.1)),
+            magnification: self.magnification(view),
+            max_iter: self.max_iter(view),
+            rotation: 0.0,
+        }
+    }
+}
+
+/// A view the window wants rendered, numbered so that passes of views
+/// since left can be told apart.
+struct Request {
+    id: u64,
+    view: View,
+}
+
+/// A pass over the view of request `id`, in the window's pixels.
+struct Pass {
+    id: u64,
+    pixels: Vec<u32>,
+}
+
+/// Opens a window on `fractal` and lets the view be zoomed with the scroll
+/// wheel and moved by dragging, until the window is closed or Escape is
+/// pressed. `s` prints the location of the view, and adds it to the
+/// bookmarks if there are some.
+///
+/// Views are rendered on a thread of their own, coarsely first, so the
+/// window answers while they are. A view left before it is done is given
+/// up at the end of its pass.
+pub fn explore<F: Fractal + Sync>(
+    fractal: &F,
+    options: &ExploreOptions,
+) -> Result<(), RustlebrotError> {
+    let (width, height) = (options.size.0 as usize, options.size.1 as usize);
+    let mut window = Window::new("rustlebrot", width, height, WindowOptions::default())
+        .map_err(|e| RustlebrotError::System(format!("can't open a window: {}", e)))?;
+    window.set_target_fps(60);
+    println!("Scroll to zoom, drag to move, s to save the view, Escape to close");
+    let (requests, wanted) = mpsc::channel();
+    let (passes, rendered) = mpsc::channel();
+    thread::scope(|scope| {
+        scope.spawn(move || render_views(fractal, options, wanted, passes));
+        // Dropping the requests when the window closes ends the renderer.
+        show(&mut window, options, requests, rendered)
+    })
+}
+
+/// Runs the window until it is closed, asking for the views it shows on
+/// `requests` and drawing the passes of them from `rendered`.
+fn show(
+    window: &mut Window,
+    options: &ExploreOptions,
+    requests: Sender<Request>,
+    rendered: Receiver<Pass>,
+) -> Result<(), RustlebrotError> {
+    let size = options.size;
+    let mut view = View {
+        center: options.center,
+        pixel_size: options.pixel_size,
+    };
+    let mut id = 0;
+    let mut pixels = vec![0; (size.0 * size.1) as usize];
+    let mut dragged_from: Option<(f32, f32)> = None;
+    let mut status = String::new();
+    // The renderer only stops once the requests are dropped, so it can't
+    // have gone while they're sent.
+    let _ = requests.send(Request { id, view });
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        let mut moved = None;
+        let mouse = window.get_mouse_pos(MouseMode::Clamp);
+        if let (Some((_, notches)), Some(at)) = (window.get_scroll_wheel(), mouse) {
+            if notches != 0.0 {
+                let factor = WHEEL_ZOOM.powf(notches.signum() as f64);
+                match view.zoomed(factor, at, size) {
+                    Some(zoomed) => moved = Some(zoomed),
+                    None => status = " (as deep as f64 goes)".to_string(),
+                }
+            }
+        }
+        match (window.get_mouse_down(MouseButton::Left), mouse, dragged_from) {
+            (true, Some(at), Some(from)) => {
+                let by = (at.0 - from.0, at.1 - from.1);
+                if by != (0.0, 0.0) {
+                    moved = Some(moved.unwrap_or(view).dragged(by));
+                    dragged_from = Some(at);
+                }
+            }
+            (true, Some(at), None) => dragged_from = Some(at),
+            _ => dragged_from = None,
+        }
+        if let Some(next) = moved {
+            if next.pixel_size >= view.pixel_size {
+                status.clear();
+            }
+            view = next;
+            id += 1;
+            let _ = requests.send(Request { id, view });
+        }
+        if window.is_key_pressed(Key::S, KeyRepeat::No) {
+            save(options, &view)?;
+        }
+        let mut fresh = false;
+        for pass in rendered.try_iter().filter(|pass| pass.id == id) {
+            pixels = pass.pixels;
+            fresh = true;
+        }
+        window.set_title(&format!(
+            "rustlebrot: {:.3e}x at max_iter {}{}",
+            options.magnification(&view),
+            options.max_iter(&view),
+            status
+        ));
+        let shown = match fresh {
+            true => window.update_with_buffer(&pixels, size.0 as usize, size.1 as usize),
+            false => {
+                window.update();
+                Ok(())
+            }
+        };
+        shown.map_err(|e| RustlebrotError::System(format!("can't draw the window: {}", e)))?;
+    }
+    Ok(())
+}
+
+/// Prints the location of `view`, and adds it to the bookmarks of
+/// `options` under the next free name if it has some.
+fn save(options: &ExploreOptions, view: &View) -> Result<(), RustlebrotError> {
+    let location = options.location(view, None);
+    println!(
+        "center {},{} magnification {:e} max_iter {}",
+        location.center.0, location.center.1, location.magnification, location.max_iter
+    );
+    let Some(path) = options.bookmarks else {
+        return Ok(());
+    };
+    let saved = match Path::new(path).exists() {
+        true => bookmarks::read(path).map_err(|e| RustlebrotError::format(path, e))?.0.len(),
+        false => 0,
+    };
+    let name = format!("explore-{}", saved + 1);
+    let location = Location {
+        name: Some(name.clone()),
+        ..location
+    };
+    bookmarks::save(path, &location)?;
+    println!(
+        "Saved as {} in {}; render it with --location {} --location-name {}",
+        name, path, path, name
+    );
+    Ok(())
+}
+
+/// Renders the views asked for on `wanted` pass by pass into `passes`,
+/// going on to the latest view whenever a newer one is asked for, until
+/// the window drops `wanted`.
+fn render_views<F: Fractal>(
+    fractal: &F,
+    options: &ExploreOptions,
+    wanted: Receiver<Request>,
+    passes: Sender<Pass>,
+) {
+    let Ok(mut request) = wanted.recv() else {
+        return;
+    };
+    'views: loop {
+        for step in PASSES {
+            match wanted.try_recv() {
+                Ok(newer) => {
+                    request = latest(newer, &wanted);
+                    continue 'views;
+                }
+                Err(TryRecvError::Disconnected) => return,
+                Err(TryRecvError::Empty) => {}
+            }
+            let pixels = render_pass(fractal, options, &request.view, step);
+            if passes.send(Pass { id: request.id, pixels }).is_err() {
+                return;
+            }
+        }
+        request = match wanted.recv() {
+            Ok(next) => latest(next, &wanted),
+            Err(_) => return,
+        };
+    }
+}
+
+/// The last of `request` and the requests waiting after it on `wanted`.
+fn latest(request: Request, wanted: &Receiver<Request>) -> Request {
+    wanted.try_iter().last().unwrap_or(request)
+}
+
+/// `view` rendered with pixels `step` window pixels across, each repeated
+/// to cover them, as the window's `0RGB` pixels.
+fn render_pass<F: Fractal>(
+    fractal: &F,
+    options: &ExploreOptions,
+    view: &View,
+    step: u32,
+) -> Vec<u32> {
+    let (width, height) = options.size;
+    let (pass_width, pass_height) = (width.div_ceil(step), height.div_ceil(step));
+    let (x_range, y_range) = view.ranges(options.size);
+    let render_options = RenderOptions {
+        max_iter: options.max_iter(view),
+        ..options.options
+    };
+    let buffer =
+        compute_escape(fractal, pass_width, pass_height, x_range, y_range, &render_options);
+    let image = colorize(&buffer, &options.colors).to_rgb8();
+    let mut pixels = Vec::with_capacity(width as usize * height as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let [r, g, b] = image.get_pixel(x / step, y / step).0;
+            pixels.push(u32::from_be_bytes([0, r, g, b]));
+        }
+    }
+    pixels
+}
@@ -0,0 +1,3907 @@
+//! Runs of the command line program on small frames, checking what it
+//! leaves in the output directory and prints.
+
+use rustlebrot::fractal::{Fractal, Mandelbrot};
+use rustlebrot::palette::{self, Stop};
+use rustlebrot::preset::{self, Preset, PRESETS};
+use serde_json::Value;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::time::Instant;
+
+/// A fresh output directory for the test `name`.
+fn output_dir(name: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("cli").join(name);
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+/// Renders a zoom of 32×32 frames into `dir`, with `args` after the four
+/// positional ones.
+fn zoom(dir: &Path, frames: &str, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+        .args(["render", "100", "0", frames, "1.5", "--width", "32", "--height", "32"])
+        .args(args)
+        .arg("--output-dir")
+        .arg(dir)
+        .output()
+        .unwrap()
+}
+
+fn frame(dir: &Path, frame: u32) -> PathBuf {
+    dir.join(format!("mandelbrot_set_{:04}.png", frame))
+}
+
+/// Everything the run printed, messages and errors.
+fn printed(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned() + &String::from_utf8_lossy(&output.stderr)
+}
+
+#[test]
+fn gif_loops_over_every_frame() {
+    let dir = output_dir("gif");
+    // At 100 iterations the deepest frames are uniform, which would end
+    // the zoom early.
+    let output = zoom(&dir, "100", &["--format", "gif", "--no-early-stop"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    let path = dir.join("rust_out.gif");
+    assert!(fs::metadata(&path).unwrap().len() < 1 << 20);
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = options.read_info(File::open(&path).unwrap()).unwrap();
+    let mut frames = 0;
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        // 30 ms, the delay without --gif-delay.
+        assert_eq!(frame.delay, 3, "frame {}", frames);
+        frames += 1;
+    }
+    assert_eq!(frames, 100);
+    assert_eq!(decoder.repeat(), gif::Repeat::Infinite);
+}
+
+/// The frames of a gif are shown for `--gif-delay`, rounded to the
+/// hundredths of a second gifs count in, in at most `--gif-colors`, and
+/// the animation repeats `--gif-loop` times.
+#[test]
+fn gif_frames_take_their_delay_and_colors() {
+    let dir = output_dir("gif-options");
+    let args = ["--format", "gif", "--gif-delay", "74", "--gif-colors", "16", "--gif-loop", "2"];
+    let output = zoom(&dir, "4", &args);
+    assert!(output.status.success(), "{}", printed(&output));
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::Indexed);
+    let mut decoder = options.read_info(File::open(dir.join("rust_out.gif")).unwrap()).unwrap();
+    let global = decoder.global_palette().map(<[u8]>::to_vec);
+    let mut frames = 0;
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        assert_eq!(frame.delay, 7, "frame {}", frames);
+        let palette = frame.palette.clone().or_else(|| global.clone()).unwrap();
+        let used = frame.buffer.iter().map(|&index| index as usize).max().unwrap();
+        assert!(used < 16 && palette.len() / 3 > used, "frame {} uses color {}", frames, used);
+        frames += 1;
+    }
+    assert_eq!(frames, 4);
+    assert_eq!(decoder.repeat(), gif::Repeat::Finite(2));
+
+    for (flag, value, error) in [
+        ("--gif-delay", "10", "gif-delay should be at least 20 ms, got 10"),
+        ("--gif-colors", "300", "gif-colors should be from 2 to 256, got 300"),
+        ("--gif-loop", "often", "gif-loop should be a count or forever, got 'often'"),
+    ] {
+        let output = zoom(&dir.join("refused"), "2", &["--format", "gif", flag, value]);
+        assert_eq!(output.status.code(), Some(1), "{}", printed(&output));
+        assert!(printed(&output).contains(error), "{}", printed(&output));
+    }
+}
+
+#[test]
+fn apng_frames_are_the_saved_frames() {
+    let dir = output_dir("apng");
+    let output = zoom(&dir, "3", &["--format", "apng"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    let decoder = png::Decoder::new(File::open(dir.join("rust_out.png")).unwrap());
+    let mut reader = decoder.read_info().unwrap();
+    assert_eq!(reader.info().animation_control().unwrap().num_frames, 3);
+    let mut buf = vec![0; reader.output_buffer_size()];
+    for index in 0..3 {
+        let info = reader.next_frame(&mut buf).unwrap();
+        let saved = image::open(frame(&dir, index)).unwrap().to_rgb8();
+        assert_eq!((info.width, info.height), saved.dimensions());
+        assert_eq!(buf[..info.buffer_size()], *saved.as_raw(), "frame {}", index);
+    }
+}
+
+/// The header and values of the `.npy` file at `path`.
+fn read_npy(path: &Path) -> (String, Vec<f64>) {
+    let bytes = fs::read(path).unwrap();
+    assert_eq!(&bytes[..8], b"\x93NUMPY\x01\x00");
+    let length = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+    let header = String::from_utf8(bytes[10..10 + length].to_vec()).unwrap();
+    let values = bytes[10 + length..]
+        .chunks(8)
+        .map(|chunk| f64::from_le_bytes(chunk.try_into().unwrap()))
+        .collect();
+    (header, values)
+}
+
+/// A dumped frame is a row-major float64 array of its height by its width,
+/// with the escape times of the points its sidecar puts at its pixels, and
+/// infinity for the interior.
+#[test]
+fn dumped_iterations_are_npy_arrays_of_the_frame() {
+    let dir = output_dir("npy");
+    let output = zoom(&dir, "1", &["--height", "24", "--dump-iterations", "--no-video"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    let (header, values) = read_npy(&dir.join("mandelbrot_set_0000.npy"));
+    // The header is padded with spaces to a newline that leaves the data
+    // 64-byte aligned.
+    assert_eq!((10 + header.len()) % 64, 0);
+    assert!(header.ends_with(" \n"), "{:?}", header);
+    let dict = "{'descr': '<f8', 'fortran_order': False, 'shape': (24, 32), }";
+    assert_eq!(header.trim_end(), dict);
+    assert_eq!(values.len(), 24 * 32);
+    let sidecar: Value =
+        serde_json::from_str(&fs::read_to_string(dir.join("mandelbrot_set_0000.json")).unwrap())
+            .unwrap();
+    assert_eq!((sidecar["width"].as_u64(), sidecar["height"].as_u64()), (Some(32), Some(24)));
+    assert_eq!(sidecar["flip_y"], false);
+    assert_eq!(sidecar["values"], "escape");
+    let bound = |key: &str| sidecar[key].as_f64().unwrap();
+    let pixel_size = (bound("x_max") - bound("x_min")) / 32.0;
+    assert!((bound("y_max") - bound("y_min") - 24.0 * pixel_size).abs() < 1e-12);
+    for (x, y) in [(0, 0), (5, 3), (16, 12), (20, 12), (31, 23), (9, 17)] {
+        let c = (bound("x_min") + x as f64 * pixel_size, bound("y_max") - y as f64 * pixel_size);
+        let escape = Mandelbrot.escape_time(c, 100, 2.0, None, None);
+        let expected = match escape.iterations >= 100.0 {
+            true => f64::INFINITY,
+            false => escape.iterations,
+        };
+        assert_eq!(values[y * 32 + x], expected, "pixel ({}, {}) at {:?}", x, y, c);
+    }
+    assert!(values.iter().any(|value| value.is_infinite()));
+}
+
+/// A frame in the default settings, computed in f64, is what
+/// `render_mandelbrot` gives for its ranges, as its sidecar records them.
+#[test]
+fn default_frames_are_render_mandelbrot() {
+    let dir = output_dir("render-mandelbrot");
+    let args = ["--height", "24", "--precision", "f64", "--dump-iterations", "--no-video"];
+    let output = zoom(&dir, "1", &args);
+    assert!(output.status.success(), "{}", printed(&output));
+    let sidecar: Value =
+        serde_json::from_str(&fs::read_to_string(dir.join("mandelbrot_set_0000.json")).unwrap())
+            .unwrap();
+    let bound = |key: &str| sidecar[key].as_f64().unwrap();
+    let x_range = (bound("x_min"), bound("x_max"));
+    let y_range = (bound("y_min"), bound("y_max"));
+    let rendered = rustlebrot::render_mandelbrot(32, 24, 100, x_range, y_range);
+    assert!(image::open(frame(&dir, 0)).unwrap().to_rgb8() == rendered);
+}
+
+/// The EXR of a frame reads back, through the exr crate, as the escape
+/// times dumped with it, and the interior as infinity where the frame has
+/// the interior color. With distance coloring it has the distances too.
+#[test]
+fn exr_frames_read_back_as_their_escape_times() {
+    let dir = output_dir("exr");
+    let args = ["--export", "png,exr", "--coloring", "smooth", "--dump-iterations", "--no-video"];
+    let output = zoom(&dir, "2", &args);
+    assert!(output.status.success(), "{}", printed(&output));
+    let path = dir.join("mandelbrot_set_0001.exr");
+    let image = exr::prelude::read_all_flat_layers_from_file(&path).unwrap();
+    assert_eq!(image.layer_data.len(), 1);
+    let layer = &image.layer_data[0];
+    assert_eq!((layer.size.0, layer.size.1), (32, 32));
+    let channel = |name: &str| -> Vec<f32> {
+        let channel = layer.channel_data.list.iter().find(|channel| channel.name == *name);
+        channel.unwrap().sample_data.values_as_f32().collect()
+    };
+    let escape = channel("escape");
+    let (_, dumped) = read_npy(&dir.join("mandelbrot_set_0001.npy"));
+    assert_eq!(escape, dumped.iter().map(|&value| value as f32).collect::<Vec<_>>());
+    let colored = image::open(frame(&dir, 1)).unwrap().to_rgb8();
+    for (&value, pixel) in escape.iter().zip(colored.pixels()) {
+        assert_eq!(value == f32::INFINITY, pixel.0 == [0, 0, 0], "{} {:?}", value, pixel);
+    }
+    assert!(escape.iter().any(|value| value.is_infinite()));
+    assert!(escape.iter().any(|&value| value > 1.0 && value < 100.0));
+
+    let output = zoom(&dir.join("distance"), "1", &["--export", "exr", "--coloring", "distance"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(!frame(&dir.join("distance"), 0).exists());
+    let path = dir.join("distance").join("mandelbrot_set_0000.exr");
+    let image = exr::prelude::read_all_flat_layers_from_file(&path).unwrap();
+    let names: Vec<String> =
+        image.layer_data[0].channel_data.list.iter().map(|c| c.name.to_string()).collect();
+    assert_eq!(names, ["distance", "escape"]);
+}
+
+/// Frames of `--bit-depth 16` are PNGs of 16-bit channels, whose low bytes
+/// carry more than the 8-bit frame does, and which round to it.
+#[test]
+fn sixteen_bit_frames_are_true_sixteen_bit_pngs() {
+    let dir = output_dir("png16");
+    let output = zoom(&dir.join("16"), "1", &["--no-video", "--bit-depth", "16"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    let decoder = png::Decoder::new(File::open(frame(&dir.join("16"), 0)).unwrap());
+    let mut reader = decoder.read_info().unwrap();
+    assert_eq!(reader.info().bit_depth, png::BitDepth::Sixteen);
+    assert_eq!(reader.info().color_type, png::ColorType::Rgb);
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).unwrap();
+    let channels: Vec<u16> = buf[..info.buffer_size()]
+        .chunks(2)
+        .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+        .collect();
+    assert_eq!(channels.len(), 32 * 32 * 3);
+    // An 8-bit value widened to 16 bits repeats its byte.
+    assert!(channels.iter().any(|&c| (c >> 8) != (c & 0xff)));
+
+    let output = zoom(&dir.join("8"), "1", &["--no-video"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    let eight = image::open(frame(&dir.join("8"), 0)).unwrap().to_rgb8();
+    for (&wide, &narrow) in channels.iter().zip(eight.as_raw()) {
+        assert!((wide as f64 / 257.0 - narrow as f64).abs() <= 1.0, "{} {}", wide, narrow);
+    }
+}
+
+#[test]
+fn json_progress_is_an_event_per_line() {
+    let dir = output_dir("json");
+    let output = zoom(&dir, "3", &["--encoder", "internal", "--progress-format", "json"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    let events: Vec<Value> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let names: Vec<&str> = events.iter().map(|event| event["event"].as_str().unwrap()).collect();
+    assert_eq!(names[0], "run_started");
+    assert_eq!(names[10..], ["video_started", "video_completed"]);
+    // Frames are saved while the next ones are computed, so a frame can
+    // start before the one ahead of it completes, but they complete in
+    // order, each followed by the progress.
+    let completed: Vec<usize> = (0..names.len()).filter(|&i| names[i] == "frame_completed").collect();
+    assert_eq!(completed.len(), 3);
+    for (frame, &index) in completed.iter().enumerate() {
+        assert_eq!(events[index]["frame"], frame as u64);
+        assert_eq!(names[index + 1], "progress");
+        assert_eq!(events[index + 1]["done"], frame as u64 + 1);
+        let started = events.iter().position(|event| {
+            event["event"] == "frame_started" && event["frame"] == frame as u64
+        });
+        assert!(started.is_some_and(|started| started < index), "{:?}", names);
+    }
+    assert_eq!(events[completed[2] + 1]["frames"], 3);
+}
+
+#[test]
+fn missing_ffmpeg_fails_before_rendering() {
+    let dir = output_dir("ffmpeg");
+    let empty = output_dir("ffmpeg-path");
+    fs::create_dir_all(&empty).unwrap();
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+            .args(["100", "0", "2", "1.5", "--width", "32", "--height", "32"])
+            .args(args)
+            .arg("--output-dir")
+            .arg(&dir)
+            .env("PATH", &empty)
+            .output()
+            .unwrap()
+    };
+    let output = run(&["--encoder", "ffmpeg"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(printed(&output).contains("ffmpeg can't be run"), "{}", printed(&output));
+    assert!(!frame(&dir, 0).exists());
+
+    let output = run(&["--no-video"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(frame(&dir, 1).exists());
+}
+
+#[test]
+fn resume_renders_only_the_unreadable_frames() {
+    let dir = output_dir("resume");
+    assert!(zoom(&dir, "3", &["--no-video"]).status.success());
+    let saved = fs::read(frame(&dir, 1)).unwrap();
+    fs::write(frame(&dir, 1), &saved[..100]).unwrap();
+    let first = fs::metadata(frame(&dir, 0)).unwrap().modified().unwrap();
+
+    let output = zoom(&dir, "3", &["--no-video", "--resume"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(printed(&output).contains("Rendering frame 1 again"), "{}", printed(&output));
+    assert_eq!(fs::read(frame(&dir, 1)).unwrap(), saved);
+    assert_eq!(fs::metadata(frame(&dir, 0)).unwrap().modified().unwrap(), first);
+
+    for args in [&["--palette", "turbo"][..], &["--mode", "buddhabrot"]] {
+        let output = zoom(&dir, "3", &[&["--no-video", "--resume"], args].concat());
+        assert_eq!(output.status.code(), Some(1));
+        assert!(printed(&output).contains("but this run"), "{}", printed(&output));
+    }
+}
+
+/// Ctrl-C stops the run once the frames in progress are saved, with the
+/// status shells give SIGINT and a manifest of the frames saved, and
+/// `--resume` renders the rest as the run would have.
+#[test]
+#[cfg(unix)]
+fn interrupted_runs_resume_where_they_stopped() {
+    use std::io::{BufRead, BufReader};
+    use std::process::Stdio;
+
+    let dir = output_dir("interrupt");
+    let render = |dir: &Path| {
+        let mut render = Command::new(env!("CARGO_BIN_EXE_rustlebrot"));
+        render
+            .args(["render", "2000", "0", "24", "1.3", "--center", "-0.743643887,0.131825904"])
+            .args(["--width", "48", "--height", "48", "--precision", "f64", "--no-video"])
+            .args(["--no-early-stop", "--output-dir"])
+            .arg(dir);
+        render
+    };
+    let whole = dir.join("whole");
+    let output = render(&whole).output().unwrap();
+    assert!(output.status.success(), "{}", printed(&output));
+
+    let stopped = dir.join("stopped");
+    let mut child = render(&stopped).stdout(Stdio::piped()).stderr(Stdio::null()).spawn().unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut line = String::new();
+    while !line.starts_with("Frame 2 saved") {
+        line.clear();
+        assert!(stdout.read_line(&mut line).unwrap() > 0, "the run ended before frame 2");
+    }
+    let kill = Command::new("kill").args(["-INT", &child.id().to_string()]).status().unwrap();
+    assert!(kill.success());
+    let mut rest = String::new();
+    std::io::Read::read_to_string(&mut stdout, &mut rest).unwrap();
+    assert_eq!(child.wait().unwrap().code(), Some(130), "{}", rest);
+    assert!(rest.contains("run again with --resume to render the rest"), "{}", rest);
+    let saved: Vec<u32> = (0..24).filter(|&n| frame(&stopped, n).exists()).collect();
+    assert!(saved.len() >= 3 && saved.len() < 24, "{:?}", saved);
+    let manifest: Value =
+        serde_json::from_str(&fs::read_to_string(stopped.join("manifest.json")).unwrap()).unwrap();
+    let mut recorded: Vec<u32> = manifest["frames"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|record| record["frame"].as_u64().unwrap() as u32)
+        .collect();
+    recorded.sort();
+    assert_eq!(recorded, saved);
+
+    let output = render(&stopped).arg("--resume").output().unwrap();
+    assert!(output.status.success(), "{}", printed(&output));
+    let decode = |dir: &Path, n| image::open(frame(dir, n)).unwrap().to_rgb8();
+    for n in 0..24 {
+        assert!(decode(&stopped, n) == decode(&whole, n), "frame {}", n);
+    }
+}
+
+/// Fewer threads, or a run in the background, render the same frames.
+#[test]
+fn threads_and_background_dont_change_the_frames() {
+    let dir = output_dir("threads");
+    let args = ["--no-video", "--precision", "f64"];
+    let output = zoom(&dir.join("default"), "3", &args);
+    assert!(output.status.success(), "{}", printed(&output));
+    for (name, extra) in [("one", &["--threads", "1"][..]), ("background", &["--background"])] {
+        let output = zoom(&dir.join(name), "3", &[&args[..], extra].concat());
+        assert!(output.status.success(), "{}", printed(&output));
+        for n in 0..3 {
+            let saved = |run: &str| fs::read(frame(&dir.join(run), n)).unwrap();
+            assert!(saved(name) == saved("default"), "{} frame {}", name, n);
+        }
+    }
+
+    for (threads, error) in [
+        ("0", "threads should be at least 1"),
+        ("two", "threads should be an integer"),
+        ("-1", "threads should be an integer"),
+    ] {
+        let output = zoom(&dir.join("refused"), "1", &["--threads", threads]);
+        assert_eq!(output.status.code(), Some(1));
+        assert!(printed(&output).contains(error), "{}", printed(&output));
+    }
+}
+
+#[test]
+fn an_error_saving_a_frame_stops_the_run() {
+    let dir = output_dir("save-error");
+    // Frame 1 can't be written over the directory in its place.
+    fs::create_dir_all(frame(&dir, 1)).unwrap();
+    let output = zoom(&dir, "8", &["--no-video", "--overwrite"]);
+    assert_eq!(output.status.code(), Some(1), "{}", printed(&output));
+    let printed = printed(&output);
+    assert!(printed.contains("frame 1: failed to write"), "{}", printed);
+    assert!(printed.contains("computed in"), "{}", printed);
+    assert!(printed.contains("encoded as png in"), "{}", printed);
+    let manifest: Value =
+        serde_json::from_str(&fs::read_to_string(dir.join("manifest.json")).unwrap()).unwrap();
+    assert_eq!(manifest["frames"].as_array().unwrap().len(), 1);
+    // Frames rendered while frame 1 was saved are saved too, and no more
+    // are started.
+    assert!(!frame(&dir, 5).exists());
+}
+
+#[test]
+fn output_dir_that_cant_be_created_fails() {
+    let parent = output_dir("not-a-dir");
+    fs::create_dir_all(parent.parent().unwrap()).unwrap();
+    fs::write(&parent, "").unwrap();
+    let dir = parent.join("frames");
+    let output = zoom(&dir, "2", &["--no-video"]);
+    assert_eq!(output.status.code(), Some(1));
+    let expected = format!("failed to write {}", dir.display());
+    assert!(printed(&output).contains(&expected), "{}", printed(&output));
+    fs::remove_file(&parent).unwrap();
+}
+
+#[test]
+fn failed_frame_is_named() {
+    let dir = output_dir("failed-frame");
+    fs::create_dir_all(frame(&dir, 1)).unwrap();
+    let output = zoom(&dir, "3", &["--no-video", "--overwrite"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(printed(&output).contains("Error: frame 1: "), "{}", printed(&output));
+}
+
+#[test]
+fn manifest_records_every_frame() {
+    let dir = output_dir("manifest");
+    assert!(zoom(&dir, "3", &["--no-video"]).status.success());
+    let manifest: Value =
+        serde_json::from_str(&fs::read_to_string(dir.join("manifest.json")).unwrap()).unwrap();
+    assert_eq!(manifest["zoom_factor"], 1.5);
+    assert_eq!((manifest["width"].as_u64(), manifest["height"].as_u64()), (Some(32), Some(32)));
+    let frames = manifest["frames"].as_array().unwrap();
+    assert_eq!(frames.len(), 3);
+    for (index, frame) in frames.iter().enumerate() {
+        assert_eq!(frame["frame"], index as u64);
+        assert_eq!(frame["magnification"], 1.5f64.powi(index as i32));
+    }
+}
+
+/// The settings of a run are written as values, which `info` reads back.
+#[test]
+fn manifest_settings_round_trip() {
+    let dir = output_dir("manifest-settings");
+    let args = [
+        "--no-video",
+        "--gradient",
+        "black@0,#ff4000@0.5,white@1",
+        "--interior-color",
+        "#0a141e",
+        "--trap",
+        "circle:0.25",
+        "--contours",
+        "every=5,color=#ffffff",
+    ];
+    let output = zoom(&dir, "2", &args);
+    assert!(output.status.success(), "{}", printed(&output));
+    let manifest: Value =
+        serde_json::from_str(&fs::read_to_string(dir.join("manifest.json")).unwrap()).unwrap();
+    assert_eq!(manifest["version"], 2);
+    let settings = &manifest["settings"];
+    assert_eq!(settings["interior"], serde_json::json!([10, 20, 30]));
+    let trap = serde_json::json!({"coloring": "trap", "trap": {"shape": "circle", "radius": 0.25}});
+    assert_eq!(settings["coloring"], trap);
+    assert_eq!(settings["contours"]["line"]["color"], serde_json::json!([255, 255, 255]));
+    let stops = &settings["palette_sources"][0]["stops"];
+    assert_eq!(stops[1], serde_json::json!({"position": 0.5, "color": [255, 64, 0]}));
+    assert_eq!(settings["precision"], "auto");
+    assert_eq!(settings["bit_depth"], 8);
+
+    let output = run(&["info", dir.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", printed(&output));
+    let info = printed(&output);
+    assert!(info.contains("interior: [10,20,30]\n"), "{}", info);
+    assert!(info.contains(&format!("coloring: {}\n", trap)), "{}", info);
+    assert!(info.contains("filename_template: "), "{}", info);
+}
+
+#[test]
+fn dry_run_flags_frames_past_f64() {
+    let dir = output_dir("dry-run");
+    let output = Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+        .args(["1000", "0", "20", "10", "--precision", "f64", "--dry-run"])
+        .arg("--output-dir")
+        .arg(&dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(printed(&output).contains("From frame 11 on"), "{}", printed(&output));
+    assert!(printed(&output).contains("Preflight: 20 frames of"), "{}", printed(&output));
+    assert!(printed(&output).contains("Preflight: rendered in f64\n"), "{}", printed(&output));
+    assert!(!dir.exists());
+}
+
+/// A zoom factor that runs out of f64 within a few frames stops the zoom
+/// with a warning where it does, fails up front when it starts there, and
+/// goes on in higher precision with auto precision.
+#[test]
+fn huge_zoom_factors_stop_or_escalate_precision() {
+    let dir = output_dir("huge-zoom-factor");
+    let deep = |name: &str, start: &str, precision: &str| {
+        Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+            .args(["render", "100", start, "10", "1e4", "--width", "16", "--height", "16"])
+            .args(["--precision", precision, "--no-video", "--no-early-stop", "--output-dir"])
+            .arg(dir.join(name))
+            .output()
+            .unwrap()
+    };
+    let output = deep("f64", "0", "f64");
+    assert!(output.status.success(), "{}", printed(&output));
+    let expected = "stopping the zoom at frame 4, where pixels can't be told apart in f64";
+    assert!(printed(&output).contains(expected), "{}", printed(&output));
+    assert!(frame(&dir.join("f64"), 3).exists() && !frame(&dir.join("f64"), 4).exists());
+
+    let output = deep("f64-deep", "5", "f64");
+    assert_eq!(output.status.code(), Some(1), "{}", printed(&output));
+    let expected = "pixels can't be told apart in f64 from frame 5 on";
+    assert!(printed(&output).contains(expected), "{}", printed(&output));
+
+    let output = deep("auto", "0", "auto");
+    assert!(output.status.success(), "{}", printed(&output));
+    let manifest: Value = serde_json::from_str(
+        &fs::read_to_string(dir.join("auto").join("manifest.json")).unwrap(),
+    )
+    .unwrap();
+    let precisions: Vec<&str> = manifest["frames"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|frame| frame["precision"].as_str().unwrap())
+        .collect();
+    assert_eq!(precisions.len(), 10);
+    assert!(precisions[..4].iter().all(|&p| p == "f32" || p == "f64"), "{:?}", precisions);
+    assert_eq!((precisions[4], precisions[9]), ("dd", "perturbation"), "{:?}", precisions);
+}
+
+/// The series approximation skips iterations of a deep frame without
+/// changing it beyond rounding, and `--series-terms 0` turns it off.
+#[test]
+fn series_skips_iterations_of_deep_frames() {
+    let dir = output_dir("series-terms");
+    let center = "-0.743643887037158704752191506114774,0.131825904205311970493132056385139";
+    let deep = |name: &str, terms: &str| {
+        Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+            .args(["render", "3000", "12", "13", "10", "--center", center, "--width", "32"])
+            .args(["--height", "32", "--precision", "perturb", "--series-terms", terms])
+            .args(["--no-video", "--no-early-stop", "--output-dir"])
+            .arg(dir.join(name))
+            .output()
+            .unwrap()
+    };
+    let skipped = |output: &Output| -> u32 {
+        let printed = printed(output);
+        let (_, after) = printed.split_once("skipped ").expect(&printed);
+        after.split(' ').next().unwrap().parse().unwrap()
+    };
+    let output = deep("series", "16");
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(skipped(&output) > 0, "{}", printed(&output));
+    let output = deep("off", "0");
+    assert!(output.status.success(), "{}", printed(&output));
+    assert_eq!(skipped(&output), 0, "{}", printed(&output));
+
+    let decode = |name: &str| image::open(frame(&dir.join(name), 12)).unwrap().to_rgb8();
+    let (series, off) = (decode("series"), decode("off"));
+    let differing = series.pixels().zip(off.pixels()).filter(|(a, b)| a != b).count();
+    // Rounding changes a pixel or two by a level.
+    assert!(differing * 100 <= 32 * 32, "{} pixels differ", differing);
+    for (a, b) in series.as_raw().iter().zip(off.as_raw()) {
+        assert!(a.abs_diff(*b) <= 1, "{} against {}", a, b);
+    }
+
+    let output = deep("one", "1");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(printed(&output).contains("series-terms should be at least 2"), "{}", printed(&output));
+}
+
+#[test]
+fn stats_have_a_row_per_frame() {
+    let dir = output_dir("stats");
+    let output = zoom(&dir, "3", &["--no-video", "--frame-stats"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert_eq!(printed(&output).matches("% interior").count(), 3);
+    let table = fs::read_to_string(dir.join("stats.csv")).unwrap();
+    let rows: Vec<Vec<&str>> = table.lines().map(|row| row.split(',').collect()).collect();
+    assert_eq!(rows[0][0], "frame");
+    assert_eq!(rows.len(), 4);
+    for (index, row) in rows[1..].iter().enumerate() {
+        assert_eq!(row.len(), rows[0].len());
+        assert_eq!(row[0], index.to_string());
+        let interior: f64 = row[7].parse().unwrap();
+        assert!(interior > 0.0 && interior < 1.0, "{}", interior);
+    }
+
+    // Inside the cardioid nothing escapes, which leaves the escape times
+    // empty.
+    let dir = output_dir("stats-interior");
+    let ranges = ["--x-range", "-0.1,0.1", "--y-range", "-0.1,0.1", "--no-video"];
+    assert!(zoom(&dir, "1", &ranges).status.success());
+    let table = fs::read_to_string(dir.join("stats.csv")).unwrap();
+    let row: Vec<&str> = table.lines().nth(1).unwrap().split(',').collect();
+    assert_eq!(row[3..], ["", "", "", "", "1", "", "0", "", ""]);
+
+    // Nor is there any boundary to measure.
+    let dir = output_dir("stats-dimension");
+    let measured = [&ranges[..], &["--measure-dimension"]].concat();
+    assert!(zoom(&dir, "1", &measured).status.success());
+    let table = fs::read_to_string(dir.join("stats.csv")).unwrap();
+    assert!(table.lines().nth(1).unwrap().ends_with(",n/a,n/a"), "{}", table);
+    let manifest: Value =
+        serde_json::from_str(&fs::read_to_string(dir.join("manifest.json")).unwrap()).unwrap();
+    assert_eq!(manifest["frames"][0]["dimension"]["boundary_samples"], 0);
+    assert!(manifest["frames"][0]["dimension"].get("estimate").is_none());
+
+    let dir = output_dir("stats-boundary");
+    let measured = ["--width", "96", "--height", "96", "--no-video", "--measure-dimension"];
+    assert!(zoom(&dir, "1", &measured).status.success());
+    let table = fs::read_to_string(dir.join("stats.csv")).unwrap();
+    let rows: Vec<Vec<&str>> = table.lines().map(|row| row.split(',').collect()).collect();
+    assert_eq!(rows[0][10..], ["dimension", "dimension_r_squared"]);
+    let dimension: f64 = rows[1][10].parse().unwrap();
+    assert!(dimension > 0.8 && dimension < 2.0, "{}", dimension);
+}
+
+#[test]
+fn zoom_into_the_interior_stops_early() {
+    let dir = output_dir("early-stop");
+    let args = ["--center", "-0.1,0", "--early-stop-frames", "5", "--format", "gif"];
+    let output = zoom(&dir, "60", &args);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(printed(&output).contains("Stopped early"), "{}", printed(&output));
+    let manifest: Value =
+        serde_json::from_str(&fs::read_to_string(dir.join("manifest.json")).unwrap()).unwrap();
+    let stop = manifest["early_stop"]["frame"].as_u64().unwrap() as u32;
+    assert_eq!(manifest["early_stop"]["frames"], 5);
+    let frames = manifest["frames"].as_array().unwrap();
+    assert_eq!(frames.len() as u32, stop + 1);
+    for frame in &frames[frames.len() - 5..] {
+        assert!(frame["spread"].as_f64().unwrap() < 0.5);
+    }
+    assert!(frame(&dir, stop).exists() && !frame(&dir, stop + 1).exists());
+    assert!(dir.join("rust_out.gif").exists());
+
+    let dir = output_dir("no-early-stop");
+    let output = zoom(&dir, "60", &["--center", "-0.1,0", "--no-video", "--no-early-stop"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(frame(&dir, 59).exists());
+}
+
+/// Every preset has a center that parses, given to at least two digits
+/// past its depth, and its deepest frame, at its own iteration limit,
+/// still shows more than one color.
+#[test]
+fn every_preset_renders_a_thumbnail() {
+    for preset in PRESETS {
+        let (x, y) = preset.center;
+        assert!(x.parse::<f64>().is_ok() && y.parse::<f64>().is_ok(), "{}", preset.name);
+        let digits = |number: &str| number.trim_start_matches('-').len() - 2;
+        let needed = preset.depth.log10() as usize + 2;
+        assert!(digits(x).min(digits(y)) >= needed, "{}", preset.name);
+
+        let dir = output_dir(&format!("preset-{}", preset.name));
+        let last = preset.frames(preset::ZOOM_FACTOR) - 1;
+        let output = Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+            .arg(preset.max_iter.to_string())
+            .args([last.to_string(), (last + 1).to_string(), preset::ZOOM_FACTOR.to_string()])
+            .args(["--preset", preset.name, "--width", "24", "--height", "24", "--no-video"])
+            .arg("--output-dir")
+            .arg(&dir)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}: {}", preset.name, printed(&output));
+        let image = image::open(frame(&dir, last)).unwrap().to_rgb8();
+        assert!(image.pixels().any(|pixel| pixel != image.get_pixel(0, 0)), "{}", preset.name);
+    }
+}
+
+/// Without the four numbers, a preset zooms from the whole set to its
+/// depth by the default factor.
+#[test]
+fn preset_fills_in_the_zoom() {
+    let output = Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+        .args(["--preset", "seahorse-minibrot", "--dry-run"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", printed(&output));
+    let preset = Preset::from_name("seahorse-minibrot").unwrap();
+    let frames = printed(&output).lines().filter(|line| line.contains(" 10000 ")).count();
+    assert_eq!(frames as u32, preset.frames(preset::ZOOM_FACTOR));
+    assert!(printed(&output).contains("-0.7436423016578859"), "{}", printed(&output));
+}
+
+/// A quality fills in the settings the flags leave out, which the run
+/// prints and records, and caps the limit of a preset when it's a fast one.
+#[test]
+fn quality_fills_in_what_the_flags_leave_out() {
+    let render = |name: &str, args: &[&str]| {
+        let dir = output_dir(name);
+        let output = Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+            .args(["render", "100", "0", "1", "1.5", "--no-video"])
+            .args(args)
+            .arg("--output-dir")
+            .arg(&dir)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", printed(&output));
+        let manifest: Value =
+            serde_json::from_str(&fs::read_to_string(dir.join("manifest.json")).unwrap()).unwrap();
+        (printed(&output), manifest["quality"].clone(), dir)
+    };
+    let (said, quality, dir) = render("quality-high", &["--quality", "high", "--width", "24"]);
+    assert!(said.contains("Quality high: 24x1200, 3x3 adaptive samples"), "{}", said);
+    assert_eq!(quality["width"], 24);
+    assert_eq!(quality["height"], 1200);
+    assert_eq!(quality["adaptive"], true);
+    assert_eq!(quality["max_iter"], 100);
+    assert_eq!(quality["bit_depth"], 16);
+    let image = image::open(frame(&dir, 0)).unwrap();
+    assert_eq!(image.color(), image::ColorType::Rgb16);
+
+    // JPEGs have no 16-bit channels, so the quality leaves them at 8.
+    let args = ["--quality", "high", "--width", "8", "--supersample", "2", "--image-format", "jpg"];
+    let (_, quality, _) = render("quality-jpeg", &args);
+    assert_eq!(quality["supersample"], 2);
+    assert_eq!(quality["adaptive"], false);
+    assert_eq!(quality["jpeg_quality"], 95);
+    assert_eq!(quality["bit_depth"], 8);
+    let (_, quality, _) = render("quality-none", &["--width", "8", "--height", "8"]);
+    assert!(quality.is_null());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+        .args(["--preset", "seahorse-minibrot", "--quality", "draft", "--dry-run"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", printed(&output));
+    let preset = Preset::from_name("seahorse-minibrot").unwrap();
+    let frames = printed(&output).lines().filter(|line| line.contains(" 500 ")).count();
+    assert_eq!(frames as u32, preset.frames(preset::ZOOM_FACTOR));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+        .args(["render", "--quality", "ultra"])
+        .output()
+        .unwrap();
+    assert!(printed(&output).contains("quality should be draft, preview"), "{}", printed(&output));
+}
+
+/// A run with several outputs renders the largest and writes each of them,
+/// from its frames, into its own directory.
+#[test]
+fn outputs_are_written_from_one_render() {
+    let dir = output_dir("outputs");
+    let output = Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+        .args(["render", "100", "0", "1", "1.5", "--no-video"])
+        .args(["--outputs", "32x24,64x48,16x16@center-crop"])
+        .arg("--output-dir")
+        .arg(&dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(printed(&output).contains("resampled to 2 more outputs"), "{}", printed(&output));
+    let sizes = [("64x48", (64, 48)), ("32x24", (32, 24)), ("16x16-center-crop", (16, 16))];
+    for (name, size) in sizes {
+        let image = image::open(frame(&dir.join(name), 0)).unwrap();
+        assert_eq!((image.width(), image.height()), size, "{}", name);
+    }
+    let manifest: Value =
+        serde_json::from_str(&fs::read_to_string(dir.join("manifest.json")).unwrap()).unwrap();
+    assert_eq!(manifest["outputs"], serde_json::json!(["64x48", "32x24", "16x16-center-crop"]));
+    assert_eq!(manifest["width"], 64);
+
+    let fails = |args: &[&str], error: &str| {
+        let output = Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+            .args(["render", "100", "0", "1", "1.5", "--no-video", "--dry-run"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert!(printed(&output).contains(error), "{}", printed(&output));
+    };
+    fails(&["--outputs", "64x48,30x30"], "add @center-crop");
+    fails(&["--outputs", "64x48,50x50@center-crop"], "upscaled");
+    fails(&["--outputs", "64x48", "--width", "20"], "--outputs");
+}
+
+/// A run reports how long each stage of its frames took, frame by frame
+/// however many are rendered at once.
+#[test]
+fn performance_report_breaks_down_the_stages() {
+    let dir = output_dir("performance");
+    let args = ["--no-video", "--supersample", "2", "--adaptive", "--frame-parallelism", "2"];
+    let output = zoom(&dir, "4", &args);
+    assert!(output.status.success(), "{}", printed(&output));
+    let said = printed(&output);
+    assert!(said.contains("Performance of 4 frames in"), "{}", said);
+    assert!(said.contains("4 in f32"), "{}", said);
+    let report: Value =
+        serde_json::from_str(&fs::read_to_string(dir.join("performance.json")).unwrap()).unwrap();
+    assert_eq!(report["frames"], 4);
+    assert_eq!(report["frame_parallelism"], 2);
+    assert_eq!(report["precisions"]["f32"], 4);
+    let stages: Vec<&str> = report["stages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|stage| stage["stage"].as_str().unwrap())
+        .collect();
+    assert_eq!(stages, ["compute", "refine", "colorize", "encode", "write", "video"]);
+    for stage in report["stages"].as_array().unwrap() {
+        let (seconds, mean) = (stage["seconds"].as_f64().unwrap(), stage["mean"].as_f64().unwrap());
+        assert!((mean * 4.0 - seconds).abs() < 1e-9, "{}", stage);
+        assert!(stage["p95"].as_f64().unwrap() <= seconds, "{}", stage);
+    }
+    // A frame's time is its own, which is never longer than the run.
+    let wall = report["seconds"].as_f64().unwrap();
+    assert!(report["stages"][0]["p95"].as_f64().unwrap() <= wall, "{}", report);
+    assert!(report["stages"][1]["seconds"].as_f64().unwrap() > 0.0, "{}", report);
+    assert!(report["megapixels_per_second"].as_f64().unwrap() > 0.0);
+    assert!(report["iterations_per_second"].as_f64().unwrap() > 0.0);
+}
+
+/// The limits an iteration schedule gives are the ones frames are rendered
+/// at, either holding from row to row or running between them.
+#[test]
+fn iter_schedule_sets_every_frame_limit() {
+    let max_iters = |name: &str, schedule: &str| {
+        let dir = output_dir(name);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("schedule.csv");
+        fs::write(&path, schedule).unwrap();
+        let output = zoom(&dir, "5", &["--no-video", "--iter-schedule", path.to_str().unwrap()]);
+        assert!(output.status.success(), "{}", printed(&output));
+        let manifest: Value =
+            serde_json::from_str(&fs::read_to_string(dir.join("manifest.json")).unwrap()).unwrap();
+        let frames = manifest["frames"].as_array().unwrap();
+        frames.iter().map(|frame| frame["max_iter"].as_u64().unwrap()).collect::<Vec<_>>()
+    };
+    let steps = "frame_start,max_iter\n0,50\n2,200 # deeper\n";
+    assert_eq!(max_iters("iter-schedule-steps", steps), [50, 50, 200, 200, 200]);
+    let breakpoints = "frame,max_iter\n\n0,100\n2,300\n4,200\n";
+    assert_eq!(max_iters("iter-schedule-breakpoints", breakpoints), [100, 200, 300, 250, 200]);
+
+    let dir = output_dir("iter-schedule-order");
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("schedule.csv");
+    fs::write(&path, "frame,max_iter\n0,100\n3,200\n3,300\n").unwrap();
+    let output = zoom(&dir, "5", &["--iter-schedule", path.to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(printed(&output).contains("schedule.csv:4:"), "{}", printed(&output));
+}
+
+/// A camera path gives the view of every frame, and the manifest records
+/// the file, which rendering a frame again checks is unchanged.
+#[test]
+fn camera_path_gives_every_frame() {
+    let dir = output_dir("camera-path");
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("path.csv");
+    let rows = "frame,center_x,center_y,width,rotation,max_iter\n\
+                0,-0.75,0,4,,\n\
+                1,-0.745,0.11,0.5,30,\n\
+                2,-0.7453,0.1127,0.01,,300 # deeper\n\
+                3,-0.7453,0.1127,0.001,,\n";
+    fs::write(&path, rows).unwrap();
+    let path_arg = path.to_str().unwrap();
+    let run = dir.join("run");
+    let output = zoom(&run, "3", &["--no-video", "--camera-path", path_arg]);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(printed(&output).contains("ignoring the rows of"), "{}", printed(&output));
+    let manifest: Value =
+        serde_json::from_str(&fs::read_to_string(run.join("manifest.json")).unwrap()).unwrap();
+    assert_eq!(manifest["camera_path"]["file"], path_arg);
+    assert_eq!(manifest["camera_path"]["crc32"].as_str().unwrap().len(), 8);
+    let frames = manifest["frames"].as_array().unwrap();
+    let width = |frame: &Value| {
+        let x_range = frame["x_range"].as_array().unwrap();
+        x_range[1].as_f64().unwrap() - x_range[0].as_f64().unwrap()
+    };
+    for (frame, expected) in frames.iter().zip([4.0, 0.5, 0.01]) {
+        assert!((width(frame) / expected - 1.0).abs() < 1e-9, "{}", frame);
+    }
+    let field = |name: &str| frames.iter().map(|frame| frame[name].clone()).collect::<Vec<_>>();
+    assert_eq!(field("rotation"), [0.0, 30.0, 0.0]);
+    assert_eq!(field("max_iter"), [100, 100, 300]);
+
+    let output = zoom(&dir.join("missing"), "5", &["--camera-path", path_arg]);
+    assert!(!output.status.success());
+    assert!(printed(&output).contains("the row for frame 4 is missing"), "{}", printed(&output));
+    let output = zoom(&dir.join("center"), "3", &["--camera-path", path_arg, "--center", "0,0"]);
+    assert!(printed(&output).contains("can't be used with --center"), "{}", printed(&output));
+
+    let gap = dir.join("gap.csv");
+    fs::write(&gap, "frame,center_x,center_y,width\n0,-0.75,0,4\n2,-0.75,0,2\n").unwrap();
+    let output = zoom(&dir.join("gap"), "3", &["--camera-path", gap.to_str().unwrap()]);
+    assert!(!output.status.success());
+    let expected = "gap.csv:3: the row for frame 2 comes after the one for frame 0";
+    assert!(printed(&output).contains(expected), "{}", printed(&output));
+
+    fs::write(&path, rows.replace(",30,", ",45,")).unwrap();
+    let manifest = run.join("manifest.json");
+    let output = run_frame(&["--manifest", manifest.to_str().unwrap(), "--frame", "1"]);
+    assert!(!output.status.success());
+    assert!(printed(&output).contains("the camera path has changed"), "{}", printed(&output));
+}
+
+/// A budgeted run plans every frame's limit into the manifest, and renders
+/// the first frame at its planned limit.
+#[test]
+fn time_budget_plans_every_frame() {
+    let dir = output_dir("time-budget");
+    let output = zoom(&dir, "5", &["--no-video", "--time-budget", "3s"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(printed(&output).contains("Fitting 5 frames into"), "{}", printed(&output));
+    let manifest: Value =
+        serde_json::from_str(&fs::read_to_string(dir.join("manifest.json")).unwrap()).unwrap();
+    let budget = &manifest["time_budget"];
+    let schedule = budget["schedule"].as_array().unwrap();
+    assert_eq!(schedule.len(), 5);
+    assert!((1..=4).contains(&budget["supersample"].as_u64().unwrap()));
+    assert!(budget["predicted_seconds"].as_f64().unwrap() <= 3.0);
+    let frames = manifest["frames"].as_array().unwrap();
+    assert_eq!(frames[0]["max_iter"], schedule[0][1]);
+
+    let output = zoom(&dir, "5", &["--time-budget", "3s", "--mode", "buddhabrot"]);
+    assert!(!output.status.success());
+    let output = zoom(&dir, "5", &["--time-budget", "soon"]);
+    assert!(printed(&output).contains("like 8h, 90m or 1h30m"), "{}", printed(&output));
+}
+
+/// Every palette gets its own frames and video from one render, and the
+/// frames only differ in their colors.
+#[test]
+fn several_palettes_share_one_render() {
+    let dir = output_dir("palettes");
+    fs::create_dir_all(&dir).unwrap();
+    let gradient = dir.join("ember.txt");
+    fs::write(&gradient, "black@0\n#ff4000@0.5\nwhite@1\n").unwrap();
+    let args = ["--palette", "turbo", "--palette", gradient.to_str().unwrap(), "--format", "gif"];
+    let output = zoom(&dir, "3", &args);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(printed(&output).contains("encoded as png in 2 palettes"), "{}", printed(&output));
+    let decode = |path: &Path| image::open(path).unwrap().to_rgb8();
+    for n in 0..3 {
+        let (turbo, ember) = (frame(&dir.join("turbo"), n), frame(&dir.join("ember"), n));
+        assert_ne!(decode(&turbo), decode(&ember));
+        assert!(!frame(&dir, n).exists());
+    }
+    assert!(dir.join("turbo/rust_out.gif").exists() && dir.join("ember/rust_out.gif").exists());
+    let manifest: Value =
+        serde_json::from_str(&fs::read_to_string(dir.join("manifest.json")).unwrap()).unwrap();
+    assert_eq!(manifest["palette"], "turbo");
+    assert_eq!(manifest["palettes"], serde_json::json!(["turbo", "ember"]));
+    assert_eq!(manifest["frames"].as_array().unwrap().len(), 3);
+
+    let twice = ["--palette", "magma", "--palette", "magma"];
+    let output = zoom(&output_dir("palettes-twice"), "3", &twice);
+    assert!(!output.status.success());
+    assert!(printed(&output).contains("given twice"), "{}", printed(&output));
+}
+
+/// Two palettes color the same pixels as the interior, and only change the
+/// colors of the rest.
+#[test]
+fn palettes_only_change_the_colors() {
+    let dir = output_dir("palette-masks");
+    let render = |palette: &str| {
+        let dir = dir.join(palette);
+        let args = ["--palette", palette, "--interior-color", "#ff00ff", "--no-video"];
+        let output = zoom(&dir, "2", &args);
+        assert!(output.status.success(), "{}", printed(&output));
+        image::open(frame(&dir, 1)).unwrap().to_rgb8()
+    };
+    let (viridis, turbo) = (render("viridis"), render("turbo"));
+    let interior = |img: &image::RgbImage| -> Vec<bool> {
+        img.pixels().map(|pixel| pixel.0 == [255, 0, 255]).collect()
+    };
+    assert_eq!(interior(&viridis), interior(&turbo));
+    let inside = interior(&viridis).iter().filter(|&&inside| inside).count();
+    assert!(0 < inside && inside < 32 * 32, "{} pixels inside", inside);
+    assert_ne!(viridis, turbo);
+}
+
+/// A palette image colors the frames like the gradient it was exported
+/// from, and anything that isn't an image is turned down.
+#[test]
+fn palette_image_colors_like_its_gradient() {
+    let dir = output_dir("palette-image");
+    fs::create_dir_all(&dir).unwrap();
+    let strip = dir.join("ember.png");
+    image::RgbImage::from_fn(2, 1, |x, _| image::Rgb([255 * x as u8, 64 * x as u8, 0]))
+        .save(&strip)
+        .unwrap();
+    let gradient = dir.join("ember.txt");
+    fs::write(&gradient, "black@0\n#ff4000@1\n").unwrap();
+    let runs = [("strip", "--palette-image", &strip), ("stops", "--gradient-file", &gradient)];
+    for (run, flag, path) in runs {
+        let output = zoom(&dir.join(run), "2", &[flag, path.to_str().unwrap()]);
+        assert!(output.status.success(), "{}", printed(&output));
+    }
+    for n in 0..2 {
+        let decode = |run: &str| image::open(frame(&dir.join(run), n)).unwrap().to_rgb8();
+        assert_eq!(decode("strip"), decode("stops"));
+    }
+
+    let output = zoom(&dir.join("text"), "2", &["--palette-image", gradient.to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(printed(&output).contains("can't read palette image"), "{}", printed(&output));
+}
+
+/// Stops written back out as `color@position` parse to the same stops,
+/// sorted by position with ties in the order given.
+#[test]
+fn gradient_stops_round_trip() {
+    let stops = palette::parse_stops("white@1, #000428@0\n\nrebeccapurple@0.5,#f00@0.5").unwrap();
+    let colors: Vec<[u8; 4]> = stops.iter().map(|stop| stop.color.to_rgba8()).collect();
+    let expected = [[0, 4, 40, 255], [102, 51, 153, 255], [255, 0, 0, 255], [255, 255, 255, 255]];
+    assert_eq!(colors, expected);
+    let positions: Vec<f64> = stops.iter().map(|stop| stop.position).collect();
+    assert_eq!(positions, [0.0, 0.5, 0.5, 1.0]);
+    let written = |stops: &[Stop]| {
+        let tokens: Vec<String> = stops
+            .iter()
+            .map(|stop| format!("{}@{}", stop.color.to_hex_string(), stop.position))
+            .collect();
+        tokens.join(",")
+    };
+    assert_eq!(written(&stops), "#000428@0,#663399@0.5,#ff0000@0.5,#ffffff@1");
+    assert_eq!(palette::parse_stops(&written(&stops)).unwrap(), stops);
+}
+
+/// Errors in a gradient name the stop they're in, counted from 1.
+#[test]
+fn gradient_errors_point_at_the_stop() {
+    for (spec, error) in [
+        ("black@0,white", "gradient stop 2 'white': expected color@position"),
+        ("black@0,\nnocolor@1", "gradient stop 2 'nocolor@1': unknown color 'nocolor'"),
+        ("black@0,white@half", "gradient stop 2 'white@half': position 'half' should be a float"),
+        ("black@-0.5,white@1", "gradient stop 1 'black@-0.5': position -0.5 should be between"),
+        ("black@0,white@NaN", "gradient stop 2 'white@NaN': position NaN should be between"),
+        ("black@0", "a gradient needs at least two stops, got 1"),
+        (" , ", "a gradient needs at least two stops, got 0"),
+    ] {
+        let parsed = palette::parse_stops(spec).unwrap_err();
+        assert!(parsed.starts_with(error), "{:?}: {}", spec, parsed);
+    }
+    let dir = output_dir("gradient-errors");
+    let output = zoom(&dir, "1", &["--gradient", "black@0,nocolor@1", "--no-video"]);
+    assert_eq!(output.status.code(), Some(1), "{}", printed(&output));
+    assert!(printed(&output).contains("gradient stop 2 'nocolor@1'"), "{}", printed(&output));
+    fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("stops.txt");
+    fs::write(&file, "black@0\nwhite@2\n").unwrap();
+    let output = zoom(&dir, "1", &["--gradient-file", file.to_str().unwrap(), "--no-video"]);
+    let error = format!("{}: gradient stop 2 'white@2'", file.display());
+    assert!(printed(&output).contains(&error), "{}", printed(&output));
+}
+
+/// A frame colored by a red to blue gradient, not inverted, has only colors
+/// of the gradient outside the set, from both its ends, and the interior
+/// color inside.
+#[test]
+fn gradient_swatch_colors_come_from_its_stops() {
+    let dir = output_dir("gradient-swatch");
+    let args = ["--gradient", "#ff0000@0,#0000ff@1", "--invert", "off", "--no-video"];
+    let output = zoom(&dir, "1", &args);
+    assert!(output.status.success(), "{}", printed(&output));
+    let img = image::open(frame(&dir, 0)).unwrap().to_rgb8();
+    let (mut reddish, mut bluish) = (false, false);
+    for &image::Rgb([r, g, b]) in img.pixels() {
+        if [r, g, b] == [0, 0, 0] {
+            continue;
+        }
+        assert_eq!(g, 0, "{:?} isn't between red and blue", [r, g, b]);
+        reddish |= r > 200 && b < 100;
+        bluish |= b > 200 && r < 100;
+    }
+    assert!(reddish && bluish);
+    assert!(img.pixels().any(|pixel| pixel.0 == [0, 0, 0]));
+}
+
+#[test]
+fn still_in_tiles_matches_the_whole_image() {
+    let dir = output_dir("still");
+    fs::create_dir_all(&dir).unwrap();
+    let still = |tile_size: &str, output: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+            .args(["still", "--width", "64", "--height", "48", "--center", "-0.7,0.3"])
+            .args(["--max-iter", "200", "--tile-size", tile_size])
+            .args(output)
+            .current_dir(&dir)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", printed(&output));
+    };
+    still("64", &["--output", "whole.png"]);
+    still("32", &["--output", "tiled.png"]);
+    assert_eq!(fs::read(dir.join("whole.png")).unwrap(), fs::read(dir.join("tiled.png")).unwrap());
+
+    still("32", &["--tiles", "tiles"]);
+    let whole = image::open(dir.join("whole.png")).unwrap().to_rgb8();
+    let manifest: Value =
+        serde_json::from_str(&fs::read_to_string(dir.join("tiles/tiles.json")).unwrap()).unwrap();
+    let tiles = manifest["tiles"].as_array().unwrap();
+    assert_eq!(tiles.len(), 4);
+    for tile in tiles {
+        let at = |key: &str| tile[key].as_u64().unwrap() as u32;
+        let path = dir.join("tiles").join(tile["path"].as_str().unwrap());
+        let img = image::open(path).unwrap().to_rgb8();
+        let (x, y, width, height) = (at("x"), at("y"), at("width"), at("height"));
+        let crop = image::imageops::crop_imm(&whole, x, y, width, height).to_image();
+        assert_eq!(img, crop, "{}", tile);
+    }
+}
+
+/// The top level of a Deep Zoom Image is the still cut into tiles, each
+/// taking in the overlap from its neighbors, and the level below is it
+/// averaged down in linear light.
+#[test]
+fn dzi_tiles_are_the_still_in_a_pyramid() {
+    let dir = output_dir("dzi");
+    fs::create_dir_all(&dir).unwrap();
+    let view = ["--width", "64", "--height", "48", "--center", "-0.7,0.3", "--max-iter", "200"];
+    let run_in_dir = |args: &[&str]| {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_rustlebrot"));
+        command.args(args).current_dir(&dir).output().unwrap()
+    };
+    let output = run_in_dir(&[&["still"], &view[..], &["--output", "whole.png"]].concat());
+    assert!(output.status.success(), "{}", printed(&output));
+    let dzi = [&["export-dzi"], &view[..], &["--tile-size", "20", "--overlap", "2"]].concat();
+    let dzi = [&dzi[..], &["--format", "png", "--out", "set.dzi"]].concat();
+    let output = run_in_dir(&dzi);
+    assert!(output.status.success(), "{}", printed(&output));
+    let descriptor = fs::read_to_string(dir.join("set.dzi")).unwrap();
+    assert!(descriptor.contains("Format=\"png\" Overlap=\"2\" TileSize=\"20\""), "{}", descriptor);
+    assert!(descriptor.contains("<Size Width=\"64\" Height=\"48\"/>"), "{}", descriptor);
+    assert!(fs::read_to_string(dir.join("set.html")).unwrap().contains("set_files/"));
+
+    // 64x48 halves to 1x1 in 6 levels.
+    let tile = |level: u32, column: u32, row: u32| {
+        dir.join(format!("set_files/{}/{}_{}.png", level, column, row))
+    };
+    let span = |index: u32, size: u32| {
+        let start = (index * 20).saturating_sub(if index > 0 { 2 } else { 0 });
+        (start, ((index + 1) * 20 + 2).min(size) - start)
+    };
+    let whole = image::open(dir.join("whole.png")).unwrap().to_rgb8();
+    for (column, row) in (0..4).flat_map(|column| (0..3).map(move |row| (column, row))) {
+        let ((x, width), (y, height)) = (span(column, 64), span(row, 48));
+        let img = image::open(tile(6, column, row)).unwrap().to_rgb8();
+        let crop = image::imageops::crop_imm(&whole, x, y, width, height).to_image();
+        assert!(img == crop, "tile {}_{} differs", column, row);
+    }
+    let linear = |channel: u8| {
+        let channel = channel as f64 / 255.0;
+        match channel <= 0.04045 {
+            true => channel / 12.92,
+            false => ((channel + 0.055) / 1.055).powf(2.4),
+        }
+    };
+    let below = image::open(tile(5, 0, 0)).unwrap().to_rgb8();
+    assert_eq!(below.dimensions(), (22, 22));
+    for (x, y, pixel) in below.enumerate_pixels() {
+        for channel in 0..3 {
+            let sum: f64 = [(0, 0), (1, 0), (0, 1), (1, 1)]
+                .iter()
+                .map(|(dx, dy)| linear(whole.get_pixel(2 * x + dx, 2 * y + dy)[channel]))
+                .sum();
+            let expected = (linear(pixel[channel]) - sum / 4.0).abs();
+            assert!(expected < 0.01, "pixel {},{} of level 5 isn't the average", x, y);
+        }
+    }
+
+    let saved = [tile(6, 1, 1), tile(3, 0, 0)].map(|path| (fs::read(&path).unwrap(), path));
+    for (_, path) in &saved {
+        fs::remove_file(path).unwrap();
+    }
+    let output = run_in_dir(&dzi);
+    assert!(printed(&output).contains("exists already"), "{}", printed(&output));
+    let output = run_in_dir(&[&dzi[..], &["--resume"]].concat());
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(printed(&output).contains("2 tiles written, keeping"), "{}", printed(&output));
+    for (bytes, path) in &saved {
+        assert!(&fs::read(path).unwrap() == bytes, "{} differs", path.display());
+    }
+    let output = run_in_dir(&[&dzi[..], &["--resume", "--max-iter", "300"]].concat());
+    assert!(printed(&output).contains("other settings"), "{}", printed(&output));
+}
+
+#[test]
+fn export_mesh_writes_obj_and_stl() {
+    let dir = output_dir("mesh");
+    fs::create_dir_all(&dir).unwrap();
+    let run_in_dir = |args: &[&str]| {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_rustlebrot"));
+        command.arg("export-mesh").args(["--width", "8", "--height", "6", "--max-iter", "100"]);
+        command.args(args).current_dir(&dir).output().unwrap()
+    };
+    let output = run_in_dir(&["--solid-base", "0.05", "--out", "set.obj", "--out", "set.stl"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(printed(&output).contains("73 vertices and 142 triangles"), "{}", printed(&output));
+    assert_eq!(fs::read(dir.join("set.stl")).unwrap().len(), 84 + 50 * 142);
+    let obj = fs::read_to_string(dir.join("set.obj")).unwrap();
+    assert_eq!(obj.lines().filter(|line| line.starts_with("v ")).count(), 73);
+    assert_eq!(obj.lines().filter(|line| line.starts_with("f ")).count(), 142);
+
+    let output = run_in_dir(&["--out", "textured.obj", "--texture", "textured.png"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    let obj = fs::read_to_string(dir.join("textured.obj")).unwrap();
+    assert!(obj.contains("mtllib textured.mtl"), "{}", obj);
+    assert_eq!(obj.lines().filter(|line| line.starts_with("vt ")).count(), 48);
+    let material = fs::read_to_string(dir.join("textured.mtl")).unwrap();
+    assert!(material.contains("map_Kd textured.png"), "{}", material);
+    assert_eq!(image::open(dir.join("textured.png")).unwrap().width(), 8);
+
+    let output = run_in_dir(&["--out", "set.ply"]);
+    assert!(printed(&output).contains(".obj or .stl"), "{}", printed(&output));
+    let output = run_in_dir(&["--out", "distance.obj", "--coloring", "distance"]);
+    assert!(!output.status.success(), "{}", printed(&output));
+}
+
+#[test]
+fn export_svg_writes_isolines_grouped_by_level() {
+    let dir = output_dir("svg");
+    fs::create_dir_all(&dir).unwrap();
+    let run_in_dir = |args: &[&str]| {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_rustlebrot"));
+        command.arg("export-svg").args(["--width", "96", "--height", "64", "--max-iter", "200"]);
+        command.args(args).current_dir(&dir).output().unwrap()
+    };
+    let args = ["--levels", "5", "--set-fill", "#102030", "--stroke-width", "0.5-2"];
+    let output = run_in_dir(&[&args[..], &["--supersample", "2", "--out", "set.svg"]].concat());
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(printed(&output).contains("Isolines at 5 levels"), "{}", printed(&output));
+    let svg = fs::read_to_string(dir.join("set.svg")).unwrap();
+    assert!(svg.contains("viewBox=\"0 0 96 64\""), "{}", svg);
+    let groups: Vec<&str> = svg.lines().filter(|line| line.starts_with("<g ")).collect();
+    assert_eq!(groups.len(), 6, "{}", svg);
+    assert!(groups[0].contains("class=\"set\" fill=\"#102030\""), "{}", groups[0]);
+    assert!(groups[1].contains("stroke-width=\"0.5\""), "{}", groups[1]);
+    assert!(groups[5].contains("stroke-width=\"2\""), "{}", groups[5]);
+    // The strokes take the colors of the palette at their levels.
+    let stroke = |group: &str| group.split("stroke=").nth(1).unwrap()[..9].to_string();
+    assert_ne!(stroke(groups[1]), stroke(groups[5]));
+    let set = svg.lines().find(|line| line.contains("evenodd")).unwrap();
+    assert!(set.ends_with("Z\"/>"), "{}", set);
+    // The points are in pixels of the frame, whatever the supersampling.
+    let numbers = svg
+        .lines()
+        .filter_map(|line| line.strip_prefix("<path").and_then(|path| path.split("d=\"").nth(1)))
+        .flat_map(|data| data.split(|c: char| "MLZ \"/>".contains(c)))
+        .filter(|number| !number.is_empty())
+        .map(|number| number.parse::<f64>().unwrap());
+    assert!(numbers.clone().count() > 100);
+    assert!(numbers.clone().all(|number| (0.0..=96.0).contains(&number)));
+
+    let output = run_in_dir(&["--level", "4", "--level", "8.5", "--out", "given.svg"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    let svg = fs::read_to_string(dir.join("given.svg")).unwrap();
+    assert!(svg.contains("data-level=\"4\"") && svg.contains("data-level=\"8.5\""), "{}", svg);
+    assert!(!svg.contains("class=\"set\""), "{}", svg);
+
+    for (args, error) in [
+        (&["--out", "set.png"][..], "should be an .svg file"),
+        (&["--output", "set.svg"], "export-svg writes the isolines to --out"),
+        (&["--level", "4", "--levels", "3"], "can't be used with --levels"),
+        (&["--coloring", "distance"], "--coloring distance doesn't keep"),
+        (&["--levels", "0"], "nothing to draw"),
+        (&["--stroke-width", "2-0"], "stroke-width should be a width"),
+        (&["--fractal", "newton"], "newton"),
+    ] {
+        let output = run_in_dir(&[args, &["--overwrite"]].concat());
+        assert_eq!(output.status.code(), Some(1), "{:?}", args);
+        assert!(printed(&output).contains(error), "{}", printed(&output));
+    }
+}
+
+#[test]
+fn still_streamed_in_bands_matches_the_whole_image() {
+    let dir = output_dir("still-bands");
+    fs::create_dir_all(&dir).unwrap();
+    let still = |band_height: &str, output: &str, args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+            .args(["still", "--width", "64", "--height", "4096", "--max-iter", "100"])
+            .args(["--band-height", band_height, "--output", output])
+            .args(args)
+            .current_dir(&dir)
+            .output()
+            .unwrap()
+    };
+    let output = still("4096", "whole.png", &[]);
+    assert!(output.status.success(), "{}", printed(&output));
+    // Bands of 16 rows fit in far less memory than the whole image does.
+    let output = still("16", "bands.png", &["--max-memory", "1M"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert_eq!(fs::read(dir.join("whole.png")).unwrap(), fs::read(dir.join("bands.png")).unwrap());
+
+    let output = still("4096", "rejected.png", &["--max-memory", "1M"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(printed(&output).contains("more than the 1 MiB"), "{}", printed(&output));
+    assert!(!dir.join("rejected.png").exists());
+}
+
+#[test]
+fn still_renders_one_supersampled_image_of_a_location() {
+    let dir = output_dir("still-location");
+    fs::create_dir_all(&dir).unwrap();
+    let still = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+            .args(["still", "--width", "64", "--height", "48", "--max-iter", "300"])
+            .args(args)
+            .current_dir(&dir)
+            .output()
+            .unwrap()
+    };
+    let output = still(&["--preset", "seahorse-valley", "--magnification", "100"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    let output = Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+        .args(["info", "still.png"])
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    assert!(printed(&output).contains("Supersample: 2"), "{}", printed(&output));
+    assert!(printed(&output).contains("Max Iter: 300"), "{}", printed(&output));
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1, "only the still is written");
+
+    // An earlier still is only replaced with --overwrite, and one sampled
+    // once per pixel comes out otherwise.
+    let output = still(&["--preset", "seahorse-valley", "--magnification", "100"]);
+    assert!(!output.status.success());
+    let args = ["--preset", "seahorse-valley", "--magnification", "100", "--overwrite"];
+    let output = still(&[&args[..], &["--supersample", "1", "--output", "one.png"]].concat());
+    assert!(output.status.success(), "{}", printed(&output));
+    assert_ne!(
+        image::open(dir.join("still.png")).unwrap().to_rgb8(),
+        image::open(dir.join("one.png")).unwrap().to_rgb8()
+    );
+
+    for (args, error) in [
+        (&["--fps", "30"][..], "--fps is an option of a zoom"),
+        (&["--zoom-factor", "1.1"], "--zoom-factor is an option of a zoom"),
+        (&["100", "0", "3", "1.1"], "still takes no positional arguments"),
+        (&["--preset", "seahorse-valley", "--center", "0,0"], "--preset gives the center"),
+        (&["--x-range", "-2,1", "--y-range", "-1,1", "--magnification", "2"], "zooms into"),
+    ] {
+        let output = still(args);
+        assert_eq!(output.status.code(), Some(1), "{:?}", args);
+        assert!(printed(&output).contains(error), "{}", printed(&output));
+    }
+}
+
+#[test]
+fn stabilized_colors_change_less_between_frames() {
+    let dir = output_dir("stabilize");
+    // How far the color of a pixel moves from one frame to the next, on
+    // average over the channels of every pixel and frame.
+    let delta = |dir: &Path| {
+        let frames: Vec<_> =
+            (0..12).map(|n| image::open(frame(dir, n)).unwrap().to_rgb8().into_raw()).collect();
+        let steps = frames.windows(2).map(|pair| {
+            let channels = pair[0].iter().zip(&pair[1]);
+            channels.map(|(a, b)| a.abs_diff(*b) as f64).sum::<f64>() / pair[0].len() as f64
+        });
+        steps.sum::<f64>() / (frames.len() - 1) as f64
+    };
+    let args = ["--coloring", "histogram", "--no-video", "--no-early-stop"];
+    let output = zoom(&dir.join("own"), "12", &args);
+    assert!(output.status.success(), "{}", printed(&output));
+    let stabilized = [&args[..], &["--stabilize-colors", "0.1", "--dump-iterations"]].concat();
+    let output = zoom(&dir.join("stable"), "12", &stabilized);
+    assert!(output.status.success(), "{}", printed(&output));
+    let (own, stable) = (delta(&dir.join("own")), delta(&dir.join("stable")));
+    assert!(stable < 0.8 * own, "{} against {}", stable, own);
+
+    // The references are recorded, so the frames recolor the same.
+    let manifest: Value = serde_json::from_str(
+        &fs::read_to_string(dir.join("stable/manifest.json")).unwrap(),
+    )
+    .unwrap();
+    let references = manifest["frames"].as_array().unwrap().iter();
+    assert!(references.map(|record| &record["color_reference"]).all(Value::is_array));
+    let saved = fs::read(frame(&dir.join("stable"), 7)).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+        .args(["recolor", "--coloring", "histogram", "--no-video"])
+        .arg(dir.join("stable"))
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", printed(&output));
+    assert_eq!(fs::read(frame(&dir.join("stable"), 7)).unwrap(), saved);
+
+    let output = zoom(&dir.join("smooth"), "2", &["--stabilize-colors", "0.1"]);
+    assert!(printed(&output).contains("--coloring histogram"), "{}", printed(&output));
+}
+
+/// Recoloring a cache takes new colors without computing an orbit, much
+/// faster than rendering it did, and gives the frames a render in those
+/// colors would.
+#[test]
+fn recolor_colors_a_cache_faster_than_rendering_it() {
+    let dir = output_dir("recolor");
+    let render = |dir: &Path, args: &[&str]| {
+        let start = Instant::now();
+        let output = Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+            .args(["render", "5000", "10", "13", "2", "--center", "-0.743643887,0.131825904"])
+            .args(["--width", "96", "--height", "96", "--precision", "f64", "--no-video"])
+            .args(["--no-early-stop"])
+            .args(args)
+            .arg("--output-dir")
+            .arg(dir)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", printed(&output));
+        start.elapsed()
+    };
+    let cached = dir.join("cached");
+    let rendering = render(&cached, &["--dump-iterations"]);
+    let start = Instant::now();
+    let output = Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+        .args(["recolor", "--palette", "viridis", "--no-video"])
+        .arg(&cached)
+        .output()
+        .unwrap();
+    let recoloring = start.elapsed();
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(recoloring * 4 < rendering, "{:?} against {:?}", recoloring, rendering);
+
+    let viridis = dir.join("viridis");
+    render(&viridis, &["--palette", "viridis"]);
+    let decode = |dir: &Path, n| image::open(frame(dir, n)).unwrap().to_rgb8();
+    for n in 10..13 {
+        assert!(decode(&cached, n) == decode(&viridis, n), "frame {}", n);
+    }
+}
+
+/// Caches of another version, or whose headers and arrays disagree, are
+/// refused before a frame is written.
+#[test]
+fn recolor_refuses_stale_and_mismatched_caches() {
+    let dir = output_dir("recolor-stale");
+    let output = zoom(&dir.join("run"), "2", &["--dump-iterations", "--no-video"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    let small = ["--width", "16", "--dump-iterations", "--no-video"];
+    let output = zoom(&dir.join("small"), "1", &small);
+    assert!(output.status.success(), "{}", printed(&output));
+    let header = |dir: &Path, n: u32| dir.join(format!("mandelbrot_set_{:04}.json", n));
+    let npy = |dir: &Path, n: u32| dir.join(format!("mandelbrot_set_{:04}.npy", n));
+    let recolor = |dir: &Path| {
+        Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+            .args(["recolor", "--palette", "viridis", "--no-video"])
+            .arg(dir)
+            .output()
+            .unwrap()
+    };
+    let copy = |name: &str| {
+        let copy = dir.join(name);
+        fs::create_dir_all(&copy).unwrap();
+        for n in 0..2 {
+            fs::copy(frame(&dir.join("run"), n), frame(&copy, n)).unwrap();
+            fs::copy(header(&dir.join("run"), n), header(&copy, n)).unwrap();
+            fs::copy(npy(&dir.join("run"), n), npy(&copy, n)).unwrap();
+        }
+        copy
+    };
+    let edit = |path: PathBuf, from: &str, to: &str| {
+        let json = fs::read_to_string(&path).unwrap();
+        assert!(json.contains(from), "{}", json);
+        fs::write(&path, json.replace(from, to)).unwrap();
+    };
+
+    let newer = copy("newer");
+    edit(header(&newer, 1), "\"version\": 3,", "\"version\": 4,");
+    let output = recolor(&newer);
+    assert_eq!(output.status.code(), Some(1));
+    let expected = "a version 4 cache, but this build reads version 3";
+    assert!(printed(&output).contains(expected), "{}", printed(&output));
+    let unversioned = copy("unversioned");
+    edit(header(&unversioned, 0), "\"version\": 3,\n", "");
+    let output = recolor(&unversioned);
+    assert!(printed(&output).contains("a version 0 cache"), "{}", printed(&output));
+
+    let mixed = copy("mixed");
+    fs::copy(header(&dir.join("small"), 0), header(&mixed, 1)).unwrap();
+    fs::copy(npy(&dir.join("small"), 0), npy(&mixed, 1)).unwrap();
+    let output = recolor(&mixed);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(printed(&output).contains("mixes frames of different runs"), "{}", printed(&output));
+
+    let stale = copy("stale");
+    fs::copy(npy(&dir.join("small"), 0), npy(&stale, 1)).unwrap();
+    let output = recolor(&stale);
+    assert_eq!(output.status.code(), Some(1));
+    let expected = "doesn't match its header, which says 32x32; the cache is stale";
+    assert!(printed(&output).contains(expected), "{}", printed(&output));
+
+    // Nothing was colored again.
+    let saved = |dir: &Path, n| fs::read(frame(dir, n)).unwrap();
+    for copy in [&newer, &unversioned, &mixed] {
+        for n in 0..2 {
+            assert!(saved(copy, n) == saved(&dir.join("run"), n), "{}", copy.display());
+        }
+    }
+}
+
+/// Runs `merge` of `shards` into `dir`, with `args` after them.
+fn merge(dir: &Path, shards: &[PathBuf], args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+        .arg("merge")
+        .args(shards)
+        .args(args)
+        .arg("--output-dir")
+        .arg(dir)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn merged_shards_are_the_whole_zoom() {
+    let dir = output_dir("shards");
+    let whole = dir.join("whole");
+    let output = zoom(&whole, "8", &["--no-video"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    let shards: Vec<PathBuf> = (0..3).map(|index| dir.join(format!("shard{}", index))).collect();
+    for (index, shard) in shards.iter().enumerate().rev() {
+        let index = index.to_string();
+        let output = zoom(shard, "8", &["--shard-index", &index, "--shard-count", "3"]);
+        assert!(output.status.success(), "{}", printed(&output));
+        assert!(!shard.join("rust_out.avi").exists() && !shard.join("rust_out.mp4").exists());
+    }
+    // Blocks of 2, 3 and 3 frames.
+    assert!(frame(&shards[0], 1).exists() && !frame(&shards[0], 2).exists());
+    assert!(frame(&shards[2], 5).exists() && !frame(&shards[2], 4).exists());
+
+    let merged = dir.join("merged");
+    let mut given = shards.clone();
+    given[1] = shards[1].join("manifest.json");
+    let output = merge(&merged, &given, &["--encoder", "internal"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    for n in 0..8 {
+        assert_eq!(fs::read(frame(&merged, n)).unwrap(), fs::read(frame(&whole, n)).unwrap());
+    }
+    assert!(merged.join("rust_out.avi").exists());
+    let manifest: Value =
+        serde_json::from_str(&fs::read_to_string(merged.join("manifest.json")).unwrap()).unwrap();
+    assert!(manifest.get("shard").is_none());
+    let frames = manifest["frames"].as_array().unwrap().iter().map(|f| f["frame"].as_u64());
+    assert_eq!(frames.collect::<Vec<_>>(), (0..8).map(Some).collect::<Vec<_>>());
+    let stats = fs::read_to_string(merged.join("stats.csv")).unwrap();
+    assert_eq!(stats.lines().count(), 9);
+}
+
+/// A manifest read back and written out again, as merging a run of one
+/// shard does, is the manifest it was, settings and frames alike, but for
+/// the shard.
+#[test]
+fn manifests_round_trip() {
+    let dir = output_dir("manifest-round-trip");
+    let args = [
+        "--shard-index", "0", "--shard-count", "1", "--no-video", "--gradient",
+        "black@0,#ff4000@0.5,white@1", "--trap", "cross:0.5,-0.25", "--contours", "every=5",
+        "--interior-color", "navy", "--palette-cycles", "2.5",
+    ];
+    let shard = dir.join("shard");
+    let output = zoom(&shard, "3", &args);
+    assert!(output.status.success(), "{}", printed(&output));
+    let merged = dir.join("merged");
+    let output = merge(&merged, std::slice::from_ref(&shard), &["--no-video"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    let read = |dir: &Path| -> Value {
+        serde_json::from_str(&fs::read_to_string(dir.join("manifest.json")).unwrap()).unwrap()
+    };
+    let (mut written, rewritten) = (read(&shard), read(&merged));
+    assert!(written["shard"].is_object());
+    written.as_object_mut().unwrap().remove("shard");
+    assert_eq!(rewritten, written);
+    assert_eq!(rewritten["frames"].as_array().unwrap().len(), 3);
+    assert_eq!(rewritten["settings"]["palette_cycles"], 2.5);
+}
+
+#[test]
+fn merge_rejects_shards_that_dont_fit() {
+    let dir = output_dir("shards-mismatched");
+    let shard = |name: &str, index: &str, args: &[&str]| {
+        let path = dir.join(name);
+        let shard = ["--shard-index", index, "--shard-count", "2"];
+        let output = zoom(&path, "6", &[&shard, args].concat());
+        assert!(output.status.success(), "{}", printed(&output));
+        path
+    };
+    let first = shard("first", "0", &[]);
+    let smooth = shard("smooth", "1", &["--coloring", "smooth"]);
+    let output = merge(&dir.join("merged"), &[first.clone(), smooth], &["--no-video"]);
+    assert_eq!(output.status.code(), Some(1));
+    let expected = r#"coloring {"coloring":"smooth"}"#;
+    assert!(printed(&output).contains(expected), "{}", printed(&output));
+    assert!(!dir.join("merged").exists());
+
+    let output = merge(&dir.join("merged"), &[first], &["--no-video"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(printed(&output).contains("shard 1, frames 3 to 5"), "{}", printed(&output));
+}
+
+#[test]
+fn work_units_leave_the_frames_as_they_are() {
+    let decode = |path: PathBuf| image::open(path).unwrap().to_rgb8();
+    let auto = output_dir("units-auto");
+    assert!(zoom(&auto, "2", &["--no-video"]).status.success());
+    let rows = output_dir("units-rows");
+    let output = zoom(&rows, "2", &["--work-unit", "rows", "--chunk-size", "3", "--no-video"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    for n in 0..2 {
+        assert_eq!(decode(frame(&rows, n)), decode(frame(&auto, n)), "frame {}", n);
+    }
+    let output = zoom(&output_dir("units-subdivided"), "2", &["--subdivide", "--chunk-size", "8"]);
+    assert!(printed(&output).contains("tiles of 64 pixels"), "{}", printed(&output));
+    let output = zoom(&output_dir("units-unknown"), "2", &["--work-unit", "hexagons"]);
+    assert!(printed(&output).contains("unknown work unit"), "{}", printed(&output));
+}
+
+#[test]
+fn zoom_out_shows_the_zoom_in_backwards() {
+    let decode = |path: PathBuf| image::open(path).unwrap().to_rgb8();
+    let inward = output_dir("direction-in");
+    let output = zoom(&inward, "4", &["--no-video"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    let outward = output_dir("direction-out");
+    let output = zoom(&outward, "4", &["--direction", "out", "--no-video"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    for n in 0..4 {
+        assert_eq!(decode(frame(&outward, n)), decode(frame(&inward, 3 - n)), "frame {}", n);
+    }
+    let manifest: Value =
+        serde_json::from_str(&fs::read_to_string(outward.join("manifest.json")).unwrap()).unwrap();
+    assert_eq!(manifest["direction"], "out");
+    assert!(manifest["frames"].as_array().unwrap().iter().all(|f| f["direction"] == "out"));
+
+    // A zoom factor below 1 zooms out as well, from the first view.
+    let shrinking = output_dir("direction-factor");
+    let output = Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+        .args(["100", "0", "3", "0.5", "--width", "32", "--height", "32", "--no-video"])
+        .arg("--output-dir")
+        .arg(&shrinking)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", printed(&output));
+    let manifest: Value =
+        serde_json::from_str(&fs::read_to_string(shrinking.join("manifest.json")).unwrap())
+            .unwrap();
+    let frames = manifest["frames"].as_array().unwrap();
+    let out = |f: &Value| f["direction"] == "out" && f["magnification"].as_f64() <= Some(1.0);
+    assert!(frames.iter().all(out));
+}
+
+#[test]
+fn in_out_plays_the_frames_back() {
+    let dir = output_dir("direction-in-out");
+    let output = zoom(&dir, "3", &["--direction", "in-out", "--format", "apng"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(!frame(&dir, 3).exists());
+    let decoder = png::Decoder::new(File::open(dir.join("rust_out.png")).unwrap());
+    let mut reader = decoder.read_info().unwrap();
+    assert_eq!(reader.info().animation_control().unwrap().num_frames, 5);
+    let mut buf = vec![0; reader.output_buffer_size()];
+    for index in [0, 1, 2, 1, 0] {
+        let info = reader.next_frame(&mut buf).unwrap();
+        let saved = image::open(frame(&dir, index)).unwrap().to_rgb8();
+        assert_eq!(buf[..info.buffer_size()], *saved.as_raw(), "frame {}", index);
+    }
+
+    let piped = ["--direction", "in-out", "--pipe-video"];
+    let output = zoom(&output_dir("direction-piped"), "3", &piped);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(printed(&output).contains("plays the saved frames back"), "{}", printed(&output));
+}
+
+#[test]
+fn sequence_orders_the_frames_of_the_video() {
+    // The frames of the apng in `dir`, and those it shows in order.
+    let shown = |dir: &Path, order: &[u32]| {
+        let decoder = png::Decoder::new(File::open(dir.join("rust_out.png")).unwrap());
+        let mut reader = decoder.read_info().unwrap();
+        let frames = reader.info().animation_control().unwrap().num_frames;
+        assert_eq!(frames as usize, order.len());
+        let mut buf = vec![0; reader.output_buffer_size()];
+        for &index in order {
+            let info = reader.next_frame(&mut buf).unwrap();
+            let saved = image::open(frame(dir, index)).unwrap().to_rgb8();
+            assert_eq!(buf[..info.buffer_size()], *saved.as_raw(), "frame {}", index);
+        }
+    };
+    let sequence = |dir: &Path| {
+        let manifest = fs::read_to_string(dir.join("manifest.json")).unwrap();
+        serde_json::from_str::<Value>(&manifest).unwrap()["sequence"].clone()
+    };
+
+    let boomerang = output_dir("sequence-boomerang");
+    let output = zoom(&boomerang, "4", &["--video-sequence", "boomerang", "--format", "apng"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    shown(&boomerang, &[0, 1, 2, 3, 2, 1]);
+    let record = sequence(&boomerang);
+    assert_eq!(record["sequence"], "boomerang");
+    assert_eq!(record["spans"], serde_json::json!([[0, 3], [2, 1]]));
+    // Piped, the frames on the way back are read back from the spool.
+    let piped = output_dir("sequence-piped");
+    let pipe = ["--pipe-video", "--preview-every", "1", "--format", "apng"];
+    let output = zoom(&piped, "4", &[&pipe[..], &["--video-sequence", "boomerang"]].concat());
+    assert!(output.status.success(), "{}", printed(&output));
+    shown(&piped, &[0, 1, 2, 3, 2, 1]);
+    assert!(!piped.join("rust_out.png.spool").exists());
+
+    let held = output_dir("sequence-loop-hold");
+    let hold = ["--video-sequence", "loop-hold:0.5", "--fps", "4", "--format", "apng"];
+    let output = zoom(&held, "3", &hold);
+    assert!(output.status.success(), "{}", printed(&output));
+    shown(&held, &[0, 1, 2, 2, 2]);
+    assert_eq!(sequence(&held)["held"], 2);
+    // Encoding the run again keeps its order, unless given another. The
+    // frame count of an AVI is the fifth field of its main header.
+    let assembled = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+            .args(["assemble", "--encoder", "internal", "--fps", "4", "--overwrite"])
+            .args(args)
+            .arg(&held)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", printed(&output));
+        let avi = fs::read(held.join("rust_out.avi")).unwrap();
+        u32::from_le_bytes(avi[48..52].try_into().unwrap())
+    };
+    assert_eq!(assembled(&[]), 5);
+    assert_eq!(assembled(&["--video-sequence", "normal"]), 3);
+
+    let output = zoom(&held, "3", &["--video-sequence", "loop-hold:-1"]);
+    assert!(printed(&output).contains("positive number of seconds"), "{}", printed(&output));
+    let output = zoom(&held, "3", &["--video-sequence", "boomerang", "--direction", "in-out"]);
+    assert!(printed(&output).contains("plays the frames back already"), "{}", printed(&output));
+}
+
+#[test]
+fn filename_template_names_the_frames() {
+    let dir = output_dir("filename-template");
+    let template = ["--filename-template", "frames/{frame:03}_{zoom}.{ext}"];
+    let output = zoom(&dir, "3", &template);
+    assert!(output.status.success(), "{}", printed(&output));
+    let frames = ["000_1.000e0", "001_1.500e0", "002_2.250e0"];
+    for name in frames {
+        assert!(dir.join(format!("frames/{}.png", name)).exists(), "{}", name);
+    }
+    assert!(!frame(&dir, 0).exists());
+    assert!(dir.join("rust_out.avi").exists() || dir.join("rust_out.mp4").exists());
+
+    // The recorded template finds the frames again.
+    let output = zoom(&dir, "4", &[&template[..], &["--resume"]].concat());
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(dir.join("frames/003_3.375e0.png").exists());
+    assert!(!printed(&output).contains("Frame 0 saved"), "{}", printed(&output));
+
+    let clash = ["--filename-template", "z.{ext}"];
+    let output = zoom(&output_dir("filename-template-clash"), "3", &clash);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(printed(&output).contains("needs {frame}"), "{}", printed(&output));
+}
+
+#[test]
+fn progressive_previews_leave_the_frames_as_they_were() {
+    let dir = output_dir("preview-progressive");
+    let plain = dir.join("plain");
+    let output = zoom(&plain, "2", &["--no-video", "--supersample", "2"]);
+    assert!(output.status.success(), "{}", printed(&output));
+
+    let previewed = dir.join("previewed");
+    let preview = dir.join("previews/preview.png");
+    let args = ["--no-video", "--supersample", "2", "--progress-format", "json"];
+    let preview_arg = ["--preview-progressive", preview.to_str().unwrap()];
+    let output = zoom(&previewed, "2", &[&args[..], &preview_arg].concat());
+    assert!(output.status.success(), "{}", printed(&output));
+    for index in 0..2 {
+        let (plain, previewed) = (frame(&plain, index), frame(&previewed, index));
+        assert_eq!(fs::read(plain).unwrap(), fs::read(previewed).unwrap(), "frame {}", index);
+    }
+    // The last pass is the frame itself.
+    let last = image::open(&preview).unwrap().to_rgb8();
+    assert_eq!(last, image::open(frame(&previewed, 1)).unwrap().to_rgb8());
+
+    let events: Vec<Value> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .filter(|event: &Value| event["event"] == "frame_previewed")
+        .collect();
+    let passes: Vec<(u64, u64)> = events
+        .iter()
+        .map(|event| (event["frame"].as_u64().unwrap(), event["stride"].as_u64().unwrap()))
+        .collect();
+    assert_eq!(passes, [(0, 8), (0, 4), (0, 2), (0, 1), (1, 8), (1, 4), (1, 2), (1, 1)]);
+
+    let parallel = [&preview_arg[..], &["--frame-parallelism", "2"]].concat();
+    let output = zoom(&dir.join("parallel"), "2", &parallel);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(printed(&output).contains("one frame at a time"), "{}", printed(&output));
+}
+
+#[test]
+fn centers_past_the_precision_are_warned_about() {
+    let dir = output_dir("long-center");
+    let center = "-0.74364388703715870475219150611477,0.13182590420531197049843180199";
+    let output = zoom(&dir, "2", &["--center", center, "--no-video"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    let expected = "Warning: the center is given to 32 significant digits";
+    assert!(printed(&output).contains(expected), "{}", printed(&output));
+    let manifest: Value =
+        serde_json::from_str(&fs::read_to_string(dir.join("manifest.json")).unwrap()).unwrap();
+    assert!(manifest.to_string().contains("-0.74364388703715870475219150611477"));
+
+    let output = zoom(&dir, "2", &["--center", "-0.75,0.1", "--no-video", "--overwrite"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(!printed(&output).contains("significant digits"), "{}", printed(&output));
+
+    let output = zoom(&dir, "2", &["--center", "inf,0", "--no-video", "--overwrite"]);
+    assert_eq!(output.status.code(), Some(1), "{}", printed(&output));
+}
+
+#[test]
+fn manifest_records_the_precision_of_every_frame() {
+    let dir = output_dir("force-precision");
+    let output = zoom(&dir, "3", &["--no-video", "--force-precision", "big"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(printed(&output).contains("Frame 2 saved in"), "{}", printed(&output));
+    let manifest: Value =
+        serde_json::from_str(&fs::read_to_string(dir.join("manifest.json")).unwrap()).unwrap();
+    for frame in manifest["frames"].as_array().unwrap() {
+        assert_eq!(frame["precision"], "big");
+    }
+
+    let output = zoom(&dir, "3", &["--no-video", "--force-precision", "dd", "--overwrite"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    let manifest: Value =
+        serde_json::from_str(&fs::read_to_string(dir.join("manifest.json")).unwrap()).unwrap();
+    for frame in manifest["frames"].as_array().unwrap() {
+        assert_eq!(frame["precision"], "dd");
+    }
+    let args = ["--no-video", "--force-precision", "dd", "--coloring", "distance"];
+    let output = zoom(&dir, "3", &args);
+    assert_eq!(output.status.code(), Some(1), "{}", printed(&output));
+    assert!(printed(&output).contains("--precision dd only works"), "{}", printed(&output));
+
+    let args = ["--no-video", "--force-precision", "big", "--precision", "f64"];
+    let output = zoom(&dir, "3", &args);
+    assert_eq!(output.status.code(), Some(1), "{}", printed(&output));
+    let output = zoom(&dir, "3", &["--no-video", "--force-precision", "auto"]);
+    assert_eq!(output.status.code(), Some(1), "{}", printed(&output));
+}
+
+#[test]
+fn location_files_give_the_center_and_the_frames() {
+    let dir = output_dir("location");
+    fs::create_dir_all(&dir).unwrap();
+    let kfr = dir.join("seahorse.kfr");
+    fs::write(&kfr, "Re: -0.7436438870371587\nIm: 0.1318259042053119\nZoom: 1.2\n\
+                     Iterations: 150\nRotate: 0\n")
+    .unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+        .args(["--width", "32", "--height", "32", "--no-video", "--location"])
+        .arg(&kfr)
+        .arg("--output-dir")
+        .arg(&dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(printed(&output).contains(": Rotate"), "{}", printed(&output));
+    assert!(printed(&output).contains("reached at frame 2 zooming by 1.1"), "{}", printed(&output));
+    assert!(frame(&dir, 2).exists() && !frame(&dir, 3).exists());
+    let manifest: Value =
+        serde_json::from_str(&fs::read_to_string(dir.join("manifest.json")).unwrap()).unwrap();
+    assert_eq!(manifest["center"][0], "-0.7436438870371587");
+    assert_eq!(manifest["max_iter"], 150);
+
+    fs::write(&kfr, "Re: -0.74\nIm 0.13\n").unwrap();
+    let output = zoom(&dir, "2", &["--location", kfr.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(printed(&output).contains("seahorse.kfr: line 2: expected"), "{}", printed(&output));
+}
+
+#[test]
+fn saved_locations_render_again_digit_for_digit() {
+    let dir = output_dir("save-location");
+    let bookmarks = dir.join("bookmarks.loc");
+    let bookmarks = bookmarks.to_str().unwrap();
+    let center = "-0.74364388703715870475219150611477,0.131825904205311970493132056385139";
+    let run = |args: &[&str]| {
+        let output = zoom(&dir, "3", &[&["--no-video", "--overwrite"], args].concat());
+        assert!(output.status.success(), "{}", printed(&output));
+    };
+    run(&["--center", center, "--initial-rotation", "30", "--save-location", bookmarks]);
+    let saved = fs::read_to_string(bookmarks).unwrap();
+    let file: Value = serde_json::from_str(&saved).unwrap();
+    let location = &file["locations"][0];
+    assert_eq!(location["center"][0], "-0.74364388703715870475219150611477");
+    assert_eq!(location["center"][1], "0.131825904205311970493132056385139");
+    assert_eq!(location["magnification"], 2.25);
+    assert_eq!((&location["max_iter"], &location["rotation"]), (&100.into(), &30.0.into()));
+
+    // Rendered from the file, the zoom saves the very same location.
+    let again = dir.join("again.loc");
+    run(&["--location", bookmarks, "--save-location", again.to_str().unwrap()]);
+    assert_eq!(fs::read_to_string(&again).unwrap(), saved);
+    let manifest: Value =
+        serde_json::from_str(&fs::read_to_string(dir.join("manifest.json")).unwrap()).unwrap();
+    assert_eq!(manifest["center"], location["center"]);
+
+    // Named locations go on the list, and are picked by name.
+    run(&["--center", "-0.75,0.1", "--save-location", bookmarks, "--location-name", "neck"]);
+    let file: Value = serde_json::from_str(&fs::read_to_string(bookmarks).unwrap()).unwrap();
+    assert_eq!(file["locations"].as_array().unwrap().len(), 2);
+    run(&["--location", bookmarks, "--location-name", "neck"]);
+    let manifest: Value =
+        serde_json::from_str(&fs::read_to_string(dir.join("manifest.json")).unwrap()).unwrap();
+    assert_eq!(manifest["center"][0], "-0.75");
+    let output = zoom(&dir, "3", &["--no-video", "--overwrite", "--location", bookmarks]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(printed(&output).contains("pick one of ?, neck"), "{}", printed(&output));
+}
+
+#[test]
+fn found_targets_are_saved_as_locations() {
+    let dir = output_dir("find-target");
+    fs::create_dir_all(&dir).unwrap();
+    let bookmarks = dir.join("targets.loc");
+    let output = Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+        .args(["find-target", "--depth", "100", "--location-name", "found", "--contact"])
+        .arg(dir.join("contact.png"))
+        .arg("--save-location")
+        .arg(&bookmarks)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", printed(&output));
+    let file: Value = serde_json::from_str(&fs::read_to_string(&bookmarks).unwrap()).unwrap();
+    let location = &file["locations"][0];
+    assert_eq!(location["name"], "found");
+    let center = location["center"].as_array().unwrap();
+    let (x, y) = (center[0].as_str().unwrap(), center[1].as_str().unwrap());
+    let center = format!("Center: {}, {}", x, y);
+    assert!(printed(&output).contains(&center), "{}", printed(&output));
+    assert!(location["magnification"].as_f64().unwrap() >= 100.0);
+}
+
+#[test]
+fn surveys_zoom_into_their_best_cell() {
+    let dir = output_dir("survey");
+    let args = [
+        "survey", "--center", "-0.75,0.1", "--radius", "0.2", "--grid", "3x2", "--depth", "2",
+        "--thumbnail", "16", "--max-iter", "200", "--output-dir", dir.to_str().unwrap(),
+    ];
+    let output = run(&args);
+    assert!(output.status.success(), "{}", printed(&output));
+    let file: Value =
+        serde_json::from_str(&fs::read_to_string(dir.join("survey.json")).unwrap()).unwrap();
+    let levels = file["levels"].as_array().unwrap();
+    assert_eq!(levels.len(), 2);
+    let cells = levels[0]["cells"].as_array().unwrap();
+    assert_eq!(cells.len(), 6);
+    let (column, row) = (&levels[0]["best"][0], &levels[0]["best"][1]);
+    let best = cells.iter().find(|cell| cell["column"] == *column && cell["row"] == *row);
+    let best = best.unwrap();
+    let highest = cells.iter().map(|cell| cell["score"].as_f64().unwrap()).fold(0.0, f64::max);
+    assert_eq!(best["score"].as_f64().unwrap(), highest);
+    assert_eq!(levels[1]["center"], best["center"]);
+    assert!(levels[1]["max_iter"].as_u64().unwrap() > 200);
+
+    // Three thumbnails 16 wide and two 24 high, each with three lines of
+    // label, under a line of header.
+    for level in 1..=2 {
+        let sheet = image::open(dir.join(format!("survey_{}.png", level))).unwrap();
+        assert_eq!((sheet.width(), sheet.height()), (64, 127));
+    }
+    let last = levels[1]["cells"].as_array().unwrap();
+    let (column, row) = (&levels[1]["best"][0], &levels[1]["best"][1]);
+    let best = last.iter().find(|cell| cell["column"] == *column && cell["row"] == *row);
+    let x = best.unwrap()["center"][0].as_str().unwrap();
+    let best_line = format!("Best cell: {},", x);
+    assert!(printed(&output).contains(&best_line), "{}", printed(&output));
+
+    let output = run(&["survey", "--grid", "0x3"]);
+    assert!(printed(&output).contains("grid should be COLUMNSxROWS"), "{}", printed(&output));
+}
+
+#[test]
+fn found_nuclei_render_as_locations() {
+    let dir = output_dir("find-nucleus");
+    fs::create_dir_all(&dir).unwrap();
+    let output = run(&["find-nucleus", "--near", "-1.754,0", "--radius", "1e-3"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    let file: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let location = &file["locations"][0];
+    assert!(location["notes"].as_str().unwrap().contains("period 3"), "{}", location);
+    let x = location["center"][0].as_str().unwrap();
+    assert!(x.starts_with("-1.7548776662466927"), "{}", x);
+    let magnification = location["magnification"].as_f64().unwrap();
+    assert!((magnification - 52.5).abs() < 0.1, "{}", magnification);
+
+    let path = dir.join("nucleus.loc");
+    fs::write(&path, &output.stdout).unwrap();
+    let output = run(&[
+        "render", "--location", path.to_str().unwrap(), "--width", "32", "--height", "32",
+        "--dry-run", "--output-dir", dir.to_str().unwrap(),
+    ]);
+    assert!(output.status.success(), "{}", printed(&output));
+
+    let output = run(&["find-nucleus", "--near", "-1.754,0"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(printed(&output).contains("find-nucleus needs --radius"), "{}", printed(&output));
+}
+
+#[test]
+fn orbit_agrees_between_precisions() {
+    let dir = output_dir("orbit");
+    fs::create_dir_all(&dir).unwrap();
+    let orbit = |point: &[&str], precision: &str, args: &[&str]| {
+        let precision = ["--precision", precision];
+        let output = run(&[&["orbit", "--point"], point, &precision[..], args].concat());
+        assert!(output.status.success(), "{}", printed(&output));
+        String::from_utf8(output.stdout).unwrap()
+    };
+    // Points of a cycle settle on it, and the rows say when.
+    let rabbit = orbit(&["-0.12", "0.75"], "f64", &["--max-iter", "500"]);
+    let lines: Vec<&str> = rabbit.lines().collect();
+    assert_eq!(lines[0], "iteration,re,im,abs");
+    assert_eq!(lines[2], "1,-0.12,0.75,0.7595393340703298");
+    assert_eq!(lines.len(), 503);
+    assert_eq!(lines[502], "Bounded with period 3");
+
+    // Every precision escapes on the same step, on points that agree
+    // until f32 rounding, and perturbation agrees from a reference away
+    // from the point.
+    let escaping = ["-0.1", "0.65"];
+    let runs = [
+        orbit(&escaping, "f32", &[]),
+        orbit(&escaping, "f64", &[]),
+        orbit(&escaping, "dd", &[]),
+        orbit(&escaping, "big", &[]),
+        orbit(&escaping, "perturb", &["--reference", "-0.1001,0.6501"]),
+    ];
+    let rows = |said: &str| -> Vec<Vec<f64>> {
+        let rows = said.lines().skip(1).take_while(|line| !line.starts_with("Escaped"));
+        rows.map(|row| row.split(',').map(|field| field.parse().unwrap()).collect()).collect()
+    };
+    let f64_rows = rows(&runs[1]);
+    assert_eq!(f64_rows.len(), 76);
+    for (run, tolerance) in runs.iter().zip([1e-2, 0.0, 1e-6, 1e-6, 1e-6]) {
+        assert!(run.contains("Escaped at iteration 75 with smooth value 74.33"), "{}", run);
+        for (row, expected) in rows(run).iter().zip(&f64_rows) {
+            for (value, expected) in row.iter().zip(expected) {
+                assert!((value - expected).abs() <= tolerance * expected.abs().max(1.0));
+            }
+        }
+    }
+
+    let (json, plot) = (dir.join("orbit.json"), dir.join("orbit.png"));
+    let files = ["--output", json.to_str().unwrap(), "--plot", plot.to_str().unwrap()];
+    let size = ["--width", "64", "--height", "48"];
+    let said = orbit(&["-1,0"], "dd", &[&files[..], &size[..]].concat());
+    assert_eq!(said.lines().last(), Some("Bounded with period 2"));
+    let record: Value = serde_json::from_str(&fs::read_to_string(&json).unwrap()).unwrap();
+    assert_eq!(record["verdict"]["period"], 2);
+    assert_eq!(record["points"].as_array().unwrap().len(), 1001);
+    assert_eq!(record["points"][3]["re"], -1.0);
+    assert_eq!(image::image_dimensions(&plot).unwrap(), (64, 48));
+
+    let output = run(&["orbit", "--point", "-1", "0", "--precision", "auto"]);
+    assert!(printed(&output).contains("precision should be f32"), "{}", printed(&output));
+    let output = run(&["orbit", "--point", "-1,0", "--output", json.to_str().unwrap()]);
+    assert!(printed(&output).contains("exists already"), "{}", printed(&output));
+}
+
+/// The mean difference between neighboring pixels of `img`, along both
+/// axes, which blurring lowers.
+fn edges(img: &image::RgbImage) -> f64 {
+    let (width, height) = img.dimensions();
+    let channels = |x, y| img.get_pixel(x, y).0.map(f64::from);
+    let step = |a: [f64; 3], b: [f64; 3]| a.iter().zip(b).map(|(a, b)| (a - b).abs()).sum::<f64>();
+    let mut sum = 0.0;
+    for y in 0..height - 1 {
+        for x in 0..width - 1 {
+            let here = channels(x, y);
+            sum += step(here, channels(x + 1, y)) + step(here, channels(x, y + 1));
+        }
+    }
+    sum / ((width - 1) * (height - 1)) as f64
+}
+
+#[test]
+fn motion_blur_smears_a_fast_zoom() {
+    let fast = |name: &str, args: &[&str]| {
+        let dir = output_dir(name);
+        let output = Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+            .args(["200", "0", "3", "4", "--width", "48", "--height", "48"])
+            .args(["--coloring", "smooth", "--no-video"])
+            .args(args)
+            .arg("--output-dir")
+            .arg(&dir)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", printed(&output));
+        dir
+    };
+    let open = |dir: &Path| image::open(frame(dir, 2)).unwrap().to_rgb8();
+    let sharp = fast("sharp", &[]);
+    let blurred = fast("blurred", &["--motion-blur", "8"]);
+    // A single view is taken at the frame, as without blur.
+    let single = fast("single-view", &["--motion-blur", "1"]);
+    assert_eq!(open(&single), open(&sharp));
+    // The longer the shutter is open, the more the frame is smeared.
+    let open_all_the_time = fast("blurred-360", &["--motion-blur", "8", "--shutter-angle", "360"]);
+    let steps = [&sharp, &blurred, &open_all_the_time].map(|dir| edges(&open(dir)));
+    assert!(steps[1] < 0.9 * steps[0] && steps[2] < steps[1], "{:?}", steps);
+
+    let incremental = fast("blurred-incremental", &["--motion-blur", "8", "--incremental"]);
+    let (full, reused) = (open(&blurred), open(&incremental));
+    let differ = full.pixels().zip(reused.pixels()).filter(|(a, b)| {
+        a.0.iter().zip(b.0).any(|(a, b)| a.abs_diff(b) > 8)
+    });
+    assert!(differ.count() * 20 < 48 * 48);
+
+    let manifest: Value =
+        serde_json::from_str(&fs::read_to_string(blurred.join("manifest.json")).unwrap()).unwrap();
+    assert_eq!(manifest["shutter"]["sub_frames"], 8);
+    assert_eq!(manifest["shutter"]["angle"], 180.0);
+    for record in manifest["frames"].as_array().unwrap() {
+        let magnification = record["magnification"].as_f64().unwrap();
+        let span = &record["shutter_magnifications"];
+        let (first, last) = (span[0].as_f64().unwrap(), span[1].as_f64().unwrap());
+        // Open for half the frame, the shutter spans less than half the
+        // zoom of 4 to the next frame on either side.
+        assert!(magnification / 2.0 < first && first < magnification, "{}", record);
+        assert!(magnification < last && last < magnification * 2.0, "{}", record);
+    }
+    let sharp_manifest = fs::read_to_string(sharp.join("manifest.json")).unwrap();
+    assert!(!sharp_manifest.contains("\"shutter\""));
+}
+
+/// Decodes standard base64.
+fn unbase64(text: &str) -> Vec<u8> {
+    let alphabet = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let digits: Vec<u32> = text
+        .bytes()
+        .filter(|&byte| byte != b'=')
+        .map(|byte| alphabet.iter().position(|&digit| digit == byte).unwrap() as u32)
+        .collect();
+    let mut bytes = Vec::new();
+    for chunk in digits.chunks(4) {
+        let word =
+            chunk.iter().enumerate().fold(0, |word, (i, digit)| word | digit << (18 - 6 * i));
+        bytes.extend(word.to_be_bytes()[1..chunk.len()].iter());
+    }
+    bytes
+}
+
+#[test]
+fn term_previews_draw_the_frames() {
+    let dir = output_dir("term-kitty");
+    let output = zoom(&dir, "3", &["--term-protocol", "kitty", "--no-video"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    // The first frame is always drawn, and at 32×32 it isn't shrunk.
+    let start = "\x1b_Ga=T,f=24,s=32,v=32,q=2,m=0;";
+    let data = &stdout[stdout.find(start).unwrap() + start.len()..];
+    let data = &data[..data.find("\x1b\\").unwrap()];
+    assert_eq!(unbase64(data), image::open(frame(&dir, 0)).unwrap().to_rgb8().into_raw());
+
+    let dir = output_dir("term-sixel");
+    let output = zoom(&dir, "3", &["--term-protocol", "sixel", "--term-preview-every", "2"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(String::from_utf8(output.stdout).unwrap().contains("\x1bPq\"1;1;32;32#0;2;"));
+
+    let dir = output_dir("term-blocks");
+    let output = zoom(&dir, "1", &["--term-protocol", "blocks", "--no-video"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let rows: Vec<&str> = stdout.lines().filter(|line| line.contains('▀')).collect();
+    assert_eq!(rows.len(), 16);
+    assert!(rows.iter().all(|row| row.matches('▀').count() == 32));
+}
+
+#[test]
+fn term_preview_is_off_where_images_cant_be_shown() {
+    for term in ["dumb", "xterm-kitty"] {
+        let dir = output_dir(&format!("term-{}", term));
+        let output = Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+            .args(["100", "0", "2", "1.5", "--width", "32", "--height", "32", "--no-video"])
+            .arg("--term-preview")
+            .arg("--output-dir")
+            .arg(&dir)
+            .env("TERM", term)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", printed(&output));
+        // Output that isn't a terminal isn't drawn on either.
+        let printed = printed(&output);
+        assert!(printed.contains("Terminal previews are off"), "{}", printed);
+        assert!(!printed.contains('\x1b'), "{}", printed);
+        assert!(frame(&dir, 1).exists());
+    }
+}
+
+#[test]
+fn bench_reports_the_pinned_scenes() {
+    let bench = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rustlebrot")).arg("bench").args(args).output().unwrap()
+    };
+    if cfg!(debug_assertions) {
+        let output = bench(&["--scene", "full"]);
+        assert!(!output.status.success());
+        assert!(printed(&output).contains("debug build"), "{}", printed(&output));
+    }
+    let output = bench(&["--scene", "full", "--repeats", "1", "--json", "--allow-debug"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["debug"], cfg!(debug_assertions));
+    assert!(report["threads"].as_u64().unwrap() >= 1);
+    let scenes = report["scenes"].as_array().unwrap();
+    assert_eq!(scenes.len(), 1);
+    assert_eq!(scenes[0]["name"], "full");
+    assert_eq!(scenes[0]["width"], 512);
+    assert_eq!(scenes[0]["max_iter"], 1000);
+    for rate in ["megapixels_per_second", "iterations_per_second"] {
+        assert!(scenes[0][rate].as_f64().unwrap() > 0.0, "{}", report);
+    }
+    assert!(report.get("formula").is_none() && scenes[0].get("formula").is_none());
+    assert_eq!(scenes[0]["work_units"], "64x64");
+
+    let args = ["--scene", "full", "--repeats", "1", "--json", "--allow-debug"];
+    let output = bench(&[&args[..], &["--work-unit", "rows", "--chunk-size", "4"]].concat());
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["scenes"][0]["work_units"], "512x4");
+    let output = bench(&[&args[..], &["--chunk-size", "0"]].concat());
+    assert!(printed(&output).contains("at least 1"), "{}", printed(&output));
+
+    let output = bench(&[&args[..], &["--formula", "z^3 + c"]].concat());
+    assert!(output.status.success(), "{}", printed(&output));
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["formula"], "z^3 + c");
+    assert_eq!(report["kernel"], "z^3 + c, by repeated squaring");
+    let formula = &report["scenes"][0]["formula"];
+    for field in ["iterations_per_second", "nanoseconds_per_iteration", "slowdown"] {
+        assert!(formula[field].as_f64().unwrap() > 0.0, "{}", report);
+    }
+}
+
+/// Sends `args` to the daemon listening in `dir` with `submit`.
+fn submit(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+        .args(["submit", "--socket", "daemon.sock"])
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap()
+}
+
+#[test]
+#[cfg(unix)]
+fn daemon_runs_the_jobs_it_kept_and_was_sent() {
+    let dir = output_dir("daemon");
+    fs::create_dir_all(&dir).unwrap();
+    let args = |frames: &str, output_dir: &str| {
+        serde_json::json!(["100", "0", frames, "1.5", "--width", "32", "--height", "32",
+            "--no-video", "--output-dir", output_dir])
+    };
+    // A restart finds the jobs queued or running when the daemon stopped.
+    let queue = serde_json::json!({
+        "version": 1,
+        "next_id": 3,
+        "jobs": [
+            {"id": 1, "args": args("3", "kept"), "state": "queued"},
+            {"id": 2, "args": args("400", "long"), "state": "running", "frames_done": 7},
+        ],
+    });
+    fs::write(dir.join("queue.json"), queue.to_string()).unwrap();
+    let job = serde_json::json!({"name": "sent", "args": args("2", "sent")});
+    fs::write(dir.join("job.json"), job.to_string()).unwrap();
+
+    let mut daemon = Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+        .args(["daemon", "--socket", "daemon.sock", "--queue", "queue.json"])
+        .current_dir(&dir)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    let status = || {
+        let output = submit(&dir, &["--status", "--json"]);
+        assert!(output.status.success(), "{}", printed(&output));
+        let response: Value = serde_json::from_slice(&output.stdout).unwrap();
+        response["jobs"].as_array().unwrap().clone()
+    };
+    for _ in 0..100 {
+        if dir.join("daemon.sock").exists() {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    // Queued or running by now, it stops either way.
+    let output = submit(&dir, &["--cancel", "2"]);
+    assert!(printed(&output).contains("Cancelled job 2"), "{}", printed(&output));
+    let output = submit(&dir, &["job.json"]);
+    assert!(printed(&output).contains("Submitted job 3"), "{}", printed(&output));
+    let output = submit(&dir, &["--cancel", "9"]);
+    assert!(!output.status.success());
+    assert!(printed(&output).contains("no job 9"), "{}", printed(&output));
+
+    let mut jobs = status();
+    for _ in 0..600 {
+        if jobs.iter().all(|job| !["queued", "running"].contains(&job["state"].as_str().unwrap())) {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        jobs = status();
+    }
+    daemon.kill().unwrap();
+    daemon.wait().unwrap();
+
+    let states: Vec<&str> = jobs.iter().map(|job| job["state"].as_str().unwrap()).collect();
+    assert_eq!(states, ["finished", "cancelled", "finished"], "{:?}", jobs);
+    assert_eq!(jobs[2]["name"], "sent");
+    for (job, frames) in [(&jobs[0], 3), (&jobs[2], 2)] {
+        assert_eq!(job["frames_done"], frames);
+        let paths = job["paths"].as_array().unwrap();
+        assert_eq!(paths.len(), frames);
+        for path in paths {
+            assert!(dir.join(path.as_str().unwrap()).exists(), "{}", path);
+        }
+    }
+    let kept: Value =
+        serde_json::from_str(&fs::read_to_string(dir.join("queue.json")).unwrap()).unwrap();
+    assert_eq!(kept["jobs"][2]["state"], "finished");
+}
+
+#[test]
+fn render_batch_renders_every_still_it_can() {
+    let dir = output_dir("batch");
+    fs::create_dir_all(&dir).unwrap();
+    let batch = serde_json::json!({
+        "version": 1,
+        "stills": [
+            {"name": "seahorse", "center": ["-0.743643887", "0.131825904"],
+             "magnification": 2000, "width": 48, "height": 32, "max_iter": 500,
+             "output": "seahorse.png"},
+            {"x_range": [-2.0, 1.0], "y_range": [-1.0, 1.0], "width": 64, "height": 48,
+             "max_iter": 200, "palette": "magma", "output": "whole.png"},
+            {"name": "bad", "palette": "no-such-palette", "output": "bad.png"},
+            {"center": ["0", "0"], "width": 16, "height": 16, "output": "whole.png"},
+        ],
+    });
+    fs::write(dir.join("batch.json"), batch.to_string()).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+        .args(["render-batch", "--input", "batch.json", "--max-memory", "64M"])
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    let text = printed(&output);
+    assert!(!output.status.success(), "{}", text);
+    assert!(text.contains("Rendered 2 of 4 stills"), "{}", text);
+    assert!(text.contains("still 3 (bad): unknown palette 'no-such-palette'"), "{}", text);
+    assert!(text.contains("still 4: whole.png is saved to still 2 already"), "{}", text);
+    assert!(text.contains("2 of 4 stills failed"), "{}", text);
+    assert_eq!(image::open(dir.join("seahorse.png")).unwrap().width(), 48);
+    assert!(!dir.join("bad.png").exists());
+
+    // A still of a batch is the one `still` renders from the same flags.
+    let output = Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+        .args(["still", "--x-range", "-2,1", "--y-range", "-1,1", "--width", "64"])
+        .args(["--height", "48", "--max-iter", "200", "--palette", "magma"])
+        .args(["--output", "alone.png"])
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", printed(&output));
+    assert_eq!(fs::read(dir.join("whole.png")).unwrap(), fs::read(dir.join("alone.png")).unwrap());
+}
+
+/// A still of a batch can be a location saved to bookmarks, as
+/// `explore --window` saves them, and is then the one `still` renders there.
+#[test]
+fn render_batch_renders_saved_locations() {
+    let dir = output_dir("batch-location");
+    fs::create_dir_all(&dir).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+        .args(["render", "300", "4", "5", "2", "--center", "-0.743643887,0.131825904"])
+        .args(["--width", "16", "--height", "16", "--no-video", "--output-dir", "frames"])
+        .args(["--save-location", "bookmarks.loc", "--location-name", "explore-1"])
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", printed(&output));
+    let batch = serde_json::json!({
+        "version": 1,
+        "stills": [
+            {"location": "bookmarks.loc", "location_name": "explore-1", "width": 48,
+             "height": 32, "output": "saved.png"},
+            {"location": "bookmarks.loc", "location_name": "explore-1", "magnification": 64,
+             "width": 48, "height": 32, "output": "deeper.png"},
+            {"location": "bookmarks.loc", "center": ["0", "0"], "output": "both.png"},
+            {"location": "bookmarks.loc", "location_name": "explore-2", "output": "none.png"},
+        ],
+    });
+    fs::write(dir.join("batch.json"), batch.to_string()).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+        .args(["render-batch", "--input", "batch.json"])
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    let text = printed(&output);
+    assert!(text.contains("Rendered 2 of 4 stills"), "{}", text);
+    assert!(text.contains("still 3: give either a location, a center or ranges"), "{}", text);
+    assert!(text.contains("still 4: ") && text.contains("explore-2"), "{}", text);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+        .args(["still", "--location", "bookmarks.loc", "--location-name", "explore-1"])
+        .args(["--width", "48", "--height", "32", "--output", "alone.png"])
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", printed(&output));
+    assert_eq!(fs::read(dir.join("saved.png")).unwrap(), fs::read(dir.join("alone.png")).unwrap());
+    assert_ne!(fs::read(dir.join("saved.png")).unwrap(), fs::read(dir.join("deeper.png")).unwrap());
+}
+
+#[test]
+fn expmap_frames_look_like_the_rendered_ones() {
+    let dir = output_dir("expmap");
+    let args = ["--center", "-0.743643887,0.131825904", "--coloring", "smooth", "--no-video"];
+    let output = zoom(&dir.join("rendered"), "6", &args);
+    assert!(output.status.success(), "{}", printed(&output));
+    let resampled = [&args[..], &["--expmap"]].concat();
+    let output = zoom(&dir.join("resampled"), "6", &resampled);
+    assert!(output.status.success(), "{}", printed(&output));
+    for n in 0..6 {
+        let open = |name: &str| image::open(frame(&dir.join(name), n)).unwrap().to_rgb8();
+        let (rendered, resampled) = (open("rendered"), open("resampled"));
+        let channels = rendered.as_raw().iter().zip(resampled.as_raw());
+        let difference = channels.map(|(a, b)| a.abs_diff(*b) as f64).sum::<f64>();
+        let difference = difference / rendered.as_raw().len() as f64;
+        // Frames one apart differ by over 20 on average.
+        assert!(difference < 20.0, "frame {}: {}", n, difference);
+    }
+    let map: Value = serde_json::from_str(
+        &fs::read_to_string(dir.join("resampled/expmap/map.json")).unwrap(),
+    )
+    .unwrap();
+    for strip in map["strips"].as_object().unwrap().keys() {
+        let index: i32 = strip.parse().unwrap();
+        assert!(dir.join(format!("resampled/expmap/strip_{:+05}.png", index)).exists());
+    }
+
+    // The strips are kept for the next run, which only resamples them.
+    let again = [&resampled[..], &["--overwrite"]].concat();
+    let output = zoom(&dir.join("resampled"), "6", &again);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(!printed(&output).contains("rendered at"), "{}", printed(&output));
+
+    let output = zoom(&dir.join("refused"), "2", &["--expmap", "--coloring", "histogram"]);
+    assert!(printed(&output).contains("histogram coloring"), "{}", printed(&output));
+}
+
+#[test]
+fn color_scripts_color_the_frames_or_say_where_they_fail() {
+    let dir = output_dir("color_expr");
+    for example in ["electric", "duotone"] {
+        let script = format!("{}/examples/{}.expr", env!("CARGO_MANIFEST_DIR"), example);
+        let out = dir.join(example);
+        let output = zoom(&out, "2", &["--color-expr", &script, "--no-video"]);
+        assert!(output.status.success(), "{}", printed(&output));
+        let img = image::open(frame(&out, 1)).unwrap().to_rgb8();
+        // The electric blue edges and the teal of the duotone.
+        assert!(img.pixels().any(|pixel| pixel[2] > pixel[0].saturating_add(60)), "{}", example);
+    }
+
+    fs::create_dir_all(&dir).unwrap();
+    let broken = dir.join("broken.expr");
+    fs::write(&broken, "glow = exp(-de)\nrgb(glow, glw, 1)\n").unwrap();
+    let output = zoom(&dir.join("broken"), "2", &["--color-expr", broken.to_str().unwrap()]);
+    assert!(!output.status.success());
+    let expected = format!("{}: line 2, column 11: unknown variable 'glw'", broken.display());
+    assert!(printed(&output).contains(&expected), "{}", printed(&output));
+    assert!(!frame(&dir.join("broken"), 0).exists());
+
+    // Fine until the first pixel that escapes in 5 iterations.
+    let failing = dir.join("failing.expr");
+    fs::write(&failing, "sqrt(4 - iter) / 2").unwrap();
+    let output = zoom(&dir.join("failing"), "2", &["--color-expr", failing.to_str().unwrap()]);
+    assert!(!output.status.success());
+    let message = printed(&output);
+    let prefix = format!("frame 0: {}: at pixel (", failing.display());
+    assert!(message.contains(&prefix) && message.contains("NaN"), "{}", message);
+
+    let output = zoom(
+        &dir.join("both"),
+        "2",
+        &["--color-expr", failing.to_str().unwrap(), "--coloring", "smooth"],
+    );
+    assert!(printed(&output).contains("can't be used with --coloring"), "{}", printed(&output));
+}
+
+#[test]
+fn formulas_render_zooms_of_their_own() {
+    let dir = output_dir("formula");
+    let output = zoom(&dir, "2", &["--formula", "abs(z)^2 + c", "--no-video"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    let manifest: Value =
+        serde_json::from_reader(File::open(dir.join("manifest.json")).unwrap()).unwrap();
+    assert_eq!(manifest["fractal"], "formula");
+    // The Burning Ship is neither all inside nor all outside.
+    let img = image::open(frame(&dir, 0)).unwrap().to_rgb8();
+    assert!(img.pixels().any(|pixel| pixel.0 == [0, 0, 0]));
+    assert!(img.pixels().any(|pixel| pixel.0 != [0, 0, 0]));
+
+    for (args, error) in [
+        (&["--formula", "z^2 + sin(q)"][..], "--formula 'z^2 + sin(q)': character 11: unknown"),
+        (&["--formula", "z^2 + c", "--fractal", "tricorn"], "can't be used with --fractal"),
+        (&["--formula", "z^2 + c", "--precision", "perturb"], "only rendered in f64"),
+        (&["--formula", "z^2 + c", "--coloring", "distance"], "no distance estimates"),
+        (&["--formula-log-base", "3"], "--formula-log-base needs --formula"),
+    ] {
+        let output = zoom(&dir.join("refused"), "2", args);
+        assert!(!output.status.success());
+        assert!(printed(&output).contains(error), "{}", printed(&output));
+    }
+}
+
+/// Runs the program with `args`.
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rustlebrot")).args(args).output().unwrap()
+}
+
+#[test]
+fn render_takes_the_frames_as_flags() {
+    let dir = output_dir("render-flags");
+    let dir_arg = dir.to_str().unwrap();
+    let output = run(&[
+        "render", "--max-iter", "100", "--zoom-start", "0", "--zoom-end", "2", "--zoom-factor",
+        "1.5", "--width", "32", "--height", "32", "--no-video", "--output-dir", dir_arg,
+    ]);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(frame(&dir, 1).exists() && !frame(&dir, 2).exists());
+    assert!(!printed(&output).contains("deprecated"), "{}", printed(&output));
+
+    for (args, error) in [
+        (&["--zoom-start", "0", "--zoom-end", "2", "--zoom-factor", "1.5"][..], "--max-iter is missing"),
+        (&["100", "0", "2", "1.5", "--max-iter", "100"], "not both"),
+        (&["100", "5", "2", "1.5"], "zoom_start 5 is after zoom_end 2"),
+        (&["100", "2", "2", "1.5"], "leaves no frames"),
+        (&["100", "0", "2", "1.5", "--output-dir", "--width", "32"], "--output-dir needs a value"),
+    ] {
+        let output = run(&[&["render"], args].concat());
+        assert!(!output.status.success());
+        assert!(printed(&output).contains(error), "{}", printed(&output));
+    }
+}
+
+#[test]
+fn duration_and_target_magnification_give_the_frames() {
+    let dir = output_dir("render-target");
+    let dir_arg = dir.to_str().unwrap();
+    let output = run(&[
+        "render", "--max-iter", "100", "--target-magnification", "1e6", "--duration", "1s",
+        "--fps", "8", "--easing", "ease-in-out", "--no-early-stop", "--width", "16", "--height",
+        "16", "--no-video", "--output-dir", dir_arg,
+    ]);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(printed(&output).contains("1s at 8 fps is 8 frames"), "{}", printed(&output));
+    assert!(frame(&dir, 7).exists() && !frame(&dir, 8).exists());
+    let manifest: Value =
+        serde_json::from_str(&fs::read_to_string(dir.join("manifest.json")).unwrap()).unwrap();
+    assert_eq!(manifest["target"]["magnification"], 1e6);
+    assert_eq!(manifest["target"]["frames"], 8);
+    let last = manifest["frames"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|record| record["frame"] == 7)
+        .unwrap()["magnification"]
+        .as_f64()
+        .unwrap();
+    // Easing moves the frames in between, not the ends.
+    assert!((last / 1e6 - 1.0).abs() < 1e-3, "{}", last);
+
+    for (args, error) in [
+        (&["--duration", "1s", "--max-iter", "100"][..], "each needs the other"),
+        (&["--duration", "1s", "--target-magnification", "1e6"], "--max-iter is missing"),
+        (&["--duration", "1s", "--target-magnification", "1e6", "100", "0", "2", "1.5"], "can't"),
+        (&["--duration", "0.01s", "--target-magnification", "1e6", "--max-iter", "100"], "fewer"),
+    ] {
+        let output = run(&[&["render", "--no-video", "--output-dir", dir_arg], args].concat());
+        assert!(!output.status.success());
+        assert!(printed(&output).contains(error), "{}", printed(&output));
+    }
+}
+
+#[test]
+fn easing_keeps_the_ends_and_moves_the_frames_between() {
+    let dir = output_dir("easing");
+    let base = ["--precision", "f64", "--no-early-stop", "--no-video"];
+    let magnifications = |dir: &Path| {
+        let manifest: Value =
+            serde_json::from_str(&fs::read_to_string(dir.join("manifest.json")).unwrap()).unwrap();
+        let mut frames = manifest["frames"].as_array().unwrap().clone();
+        frames.sort_by_key(|record| record["frame"].as_u64().unwrap());
+        frames.iter().map(|record| record["magnification"].as_f64().unwrap()).collect::<Vec<_>>()
+    };
+    let linear = dir.join("linear");
+    let output = zoom(&linear, "6", &base);
+    assert!(output.status.success(), "{}", printed(&output));
+    let decode = |dir: &Path, n| image::open(frame(dir, n)).unwrap().to_rgb8();
+    let linear_magnification = magnifications(&linear);
+    for easing in ["ease-in", "ease-out", "ease-in-out", "smoothstep"] {
+        let eased = dir.join(easing);
+        let output = zoom(&eased, "6", &[&base[..], &["--easing", easing]].concat());
+        assert!(output.status.success(), "{}", printed(&output));
+        assert!(decode(&eased, 0) == decode(&linear, 0), "{}", easing);
+        assert!(decode(&eased, 5) == decode(&linear, 5), "{}", easing);
+        for n in 1..5 {
+            assert!(decode(&eased, n) != decode(&linear, n), "{} frame {}", easing, n);
+        }
+        let magnification = magnifications(&eased);
+        assert_eq!(magnification.len(), 6);
+        assert_eq!(magnification[0], linear_magnification[0]);
+        assert!((magnification[5] / linear_magnification[5] - 1.0).abs() < 1e-12);
+        // Easing in starts slower than the steady zoom, and out ends slower.
+        let first = magnification[1] / linear_magnification[1];
+        let last = magnification[4] / linear_magnification[4];
+        match easing {
+            "ease-in" => assert!(first < 1.0 && last < 1.0, "{} {}", first, last),
+            "ease-out" => assert!(first > 1.0 && last > 1.0, "{} {}", first, last),
+            _ => assert!(first < 1.0 && last > 1.0, "{} {}", first, last),
+        }
+    }
+}
+
+#[test]
+fn zooms_without_a_subcommand_render_with_a_note() {
+    let dir = output_dir("legacy");
+    let output = run(&["100", "0", "2", "1.5", "--width", "32", "--height", "32", "--no-video",
+        "--output-dir", dir.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(frame(&dir, 1).exists());
+    let note = String::from_utf8_lossy(&output.stderr);
+    assert!(note.contains("deprecated; run `rustlebrot render`"), "{}", note);
+}
+
+/// A Buddhabrot that could plot no orbit is refused before it renders.
+#[test]
+fn buddhabrot_settings_that_plot_nothing_are_refused() {
+    let dir = output_dir("buddhabrot-refused");
+    let args = ["--mode", "buddhabrot", "--samples", "1000", "--min-iter", "99", "--no-video"];
+    let output = zoom(&dir, "1", &args);
+    assert!(output.status.success(), "{}", printed(&output));
+    for (args, error) in [
+        (&["--samples", "0"][..], "samples should be at least 1, or no orbit is plotted"),
+        (&["--min-iter", "100"], "min-iter 100 should be below max_iter 100"),
+        (&["--min-iter", "500"], "min-iter 500 should be below max_iter 100"),
+        (&["--mode", "nebulabrot", "--bands", "300,50,1000", "--min-iter", "50"],
+            "min-iter 50 should be below the lowest of --bands 50"),
+    ] {
+        let args = [&["--mode", "buddhabrot", "--no-video", "--overwrite"][..], args].concat();
+        let output = zoom(&dir, "1", &args);
+        assert_eq!(output.status.code(), Some(1), "{}", printed(&output));
+        assert!(printed(&output).contains(error), "{}", printed(&output));
+        assert!(!printed(&output).contains("Frame 0 saved"), "{}", printed(&output));
+    }
+}
+
+#[test]
+fn bailout_is_at_least_2_and_finite() {
+    let dir = output_dir("bailout");
+    let output = zoom(&dir, "2", &["--bailout", "1e6", "--coloring", "smooth", "--no-video"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    for (bailout, error) in [
+        ("1.5", "bailout should be at least 2, got 1.5"),
+        ("NaN", "bailout should be at least 2, got NaN"),
+        ("inf", "bailout should be finite"),
+    ] {
+        let output = zoom(&dir, "2", &["--bailout", bailout, "--no-video", "--overwrite"]);
+        assert_eq!(output.status.code(), Some(1), "{}", printed(&output));
+        assert!(printed(&output).contains(error), "{}", printed(&output));
+    }
+    let output = run(&["orbit", "--point", "0", "0", "--bailout", "inf"]);
+    assert_eq!(output.status.code(), Some(1), "{}", printed(&output));
+    assert!(printed(&output).contains("bailout should be at least 2"), "{}", printed(&output));
+}
+
+#[test]
+fn help_prints_the_usage() {
+    for args in [&["--help"][..], &["-h"], &["render", "--help"], &["still", "--max-iter", "-h"]] {
+        let output = run(args);
+        assert!(output.status.success(), "{}", printed(&output));
+        let usage = String::from_utf8_lossy(&output.stdout);
+        assert!(usage.starts_with("Usage: rustlebrot "), "{}", usage);
+        assert!(usage.contains("or: rustlebrot still "), "{}", usage);
+        assert!(!usage.contains("mandelbrot render"), "{}", usage);
+    }
+}
+
+#[test]
+fn subcommands_parse_their_flags() {
+    let dir = output_dir("parse");
+    let dir_arg = dir.to_str().unwrap();
+    let output = run(&[
+        "render", "--max-iter", "50", "--zoom-start", "0", "--zoom-end", "1", "--zoom-factor",
+        "2", "--width", "16", "--height", "16", "--palette", "magma", "--no-video",
+        "--output-dir", dir_arg,
+    ]);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(frame(&dir, 0).exists());
+
+    let still = dir.join("still.png");
+    let output = run(&[
+        "still", "--width", "24", "--height", "16", "--center", "-0.5,0", "--magnification", "2",
+        "--max-iter", "50", "--output", still.to_str().unwrap(),
+    ]);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert_eq!(image::image_dimensions(&still).unwrap(), (24, 16));
+
+    let output = run(&["bench", "--scene", "full", "--repeats", "1", "--allow-debug"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(printed(&output).contains("full"), "{}", printed(&output));
+
+    for (args, error) in [
+        (&["render", "--max-iter", "50", "--zoom-start", "3", "--zoom-end", "1",
+            "--zoom-factor", "2"][..], "zoom_start 3 is after zoom_end 1"),
+        (&["render", "50", "0", "1", "2", "--output-dir"], "--output-dir needs a value"),
+        (&["--output-dir"], "--output-dir needs the directory"),
+    ] {
+        let output = run(args);
+        assert_eq!(output.status.code(), Some(1), "{}", printed(&output));
+        assert!(printed(&output).contains(error), "{}", printed(&output));
+    }
+}
+
+#[test]
+fn global_flags_go_before_the_subcommand() {
+    let dir = output_dir("global");
+    let dir_arg = dir.to_str().unwrap();
+    let output = run(&["--quiet", "--output-dir", dir_arg, "render", "100", "0", "2", "1.5",
+        "--width", "32", "--height", "32", "--no-video"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(frame(&dir, 1).exists());
+    assert!(output.stdout.is_empty(), "{}", printed(&output));
+
+    let output = run(&["--verbose", "render", "100", "0", "2", "1.5", "--dry-run"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(printed(&output).contains("supersample: 1"), "{}", printed(&output));
+
+    for (args, error) in [
+        (&["--output-dir"][..], "--output-dir needs the directory to write to"),
+        (&["--output-dir", "--quiet", "render"], "--output-dir needs the directory to write to"),
+        (&["--quiet", "--verbose", "render"], "can't be used together"),
+        (&["--output-dir", dir_arg, "render", "--output-dir", dir_arg], "given both before and after"),
+        (&["--output-dir", dir_arg, "info", "still.png"], "info doesn't write to an output directory"),
+    ] {
+        let output = run(args);
+        assert!(!output.status.success());
+        assert!(printed(&output).contains(error), "{}", printed(&output));
+    }
+}
+
+#[test]
+fn assemble_encodes_the_saved_frames() {
+    let dir = output_dir("assemble");
+    let output = zoom(&dir, "3", &["--no-video"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(!dir.join("rust_out.avi").exists());
+
+    let output = run(&["--output-dir", dir.to_str().unwrap(), "assemble", "--encoder", "internal"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(dir.join("rust_out.avi").exists());
+
+    fs::remove_file(frame(&dir, 1)).unwrap();
+    let output = run(&["assemble", dir.to_str().unwrap(), "--encoder", "internal", "--overwrite"]);
+    assert!(printed(&output).contains("Frame 1 is missing"), "{}", printed(&output));
+    assert!(printed(&output).contains("--allow-gaps"), "{}", printed(&output));
+    let args = ["assemble", dir.to_str().unwrap(), "--encoder", "internal", "--overwrite"];
+    let output = run(&[&args[..], &["--allow-gaps"]].concat());
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(printed(&output).contains("Leaving out 1 missing"), "{}", printed(&output));
+    let output = run(&[&args[..], &["--repair"]].concat());
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(frame(&dir, 1).exists());
+    let output = run(&["assemble", dir.join("nothing").to_str().unwrap()]);
+    assert!(printed(&output).contains("has no manifest.json"), "{}", printed(&output));
+}
+
+#[test]
+fn verify_finds_broken_frames_and_repairs_them() {
+    let dir = output_dir("verify");
+    let output = zoom(&dir, "5", &["--no-video"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    let dir_arg = dir.to_str().unwrap();
+    let output = run(&["verify", dir_arg]);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(printed(&output).contains("All 5 frames"), "{}", printed(&output));
+
+    let saved: Vec<Vec<u8>> = (0..5).map(|n| fs::read(frame(&dir, n)).unwrap()).collect();
+    // Cut short, a byte flipped, of another size, and gone.
+    fs::write(frame(&dir, 1), &saved[1][..saved[1].len() / 2]).unwrap();
+    let mut flipped = saved[2].clone();
+    flipped[saved[2].len() / 2] ^= 0xff;
+    fs::write(frame(&dir, 2), flipped).unwrap();
+    image::RgbImage::new(16, 16).save(frame(&dir, 3)).unwrap();
+    fs::remove_file(frame(&dir, 4)).unwrap();
+    for args in [&[][..], &["--full-decode"]] {
+        let output = run(&[&["verify", dir_arg], args].concat());
+        assert!(!output.status.success());
+        for found in [
+            "Frame 1 is corrupt",
+            "Frame 2 is corrupt",
+            "the frame is 16x16, but the run's frames are 32x32",
+            "Frame 4 is missing",
+            "4 of the 5 frames",
+            "--repair",
+        ] {
+            assert!(printed(&output).contains(found), "{}: {}", found, printed(&output));
+        }
+        assert!(!printed(&output).contains("Frame 0"), "{}", printed(&output));
+    }
+
+    let output = run(&["verify", dir_arg, "--repair"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(printed(&output).contains("Rendering 4 frames again"), "{}", printed(&output));
+    for (n, saved) in saved.iter().enumerate() {
+        assert!(fs::read(frame(&dir, n as u32)).unwrap() == *saved, "frame {} differs", n);
+    }
+
+    let output = run(&["verify", dir_arg, "--palette", "magma"]);
+    assert!(printed(&output).contains("colored with"), "{}", printed(&output));
+    let output = run(&["verify", dir.join("nothing").to_str().unwrap()]);
+    assert!(printed(&output).contains("has no manifest.json"), "{}", printed(&output));
+}
+
+#[test]
+fn montage_shows_every_nth_frame_with_gaps() {
+    let dir = output_dir("montage");
+    let output = zoom(&dir, "7", &["--no-video"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    fs::remove_file(frame(&dir, 3)).unwrap();
+    let dir_arg = dir.to_str().unwrap();
+    let manifest = dir.join("manifest.json");
+    let args = ["--every", "3", "--columns", "2"];
+    let output = run(&[&["montage", "--manifest", manifest.to_str().unwrap()], &args[..]].concat());
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(printed(&output).contains("Montage of 3 frames"), "{}", printed(&output));
+    assert!(printed(&output).contains("1 of them missing"), "{}", printed(&output));
+    // Frames 0 and 3 over 6, 32 pixels a side as they were rendered, each
+    // with two lines of label.
+    let montage = image::open(dir.join("montage.png")).unwrap().to_rgb8();
+    assert_eq!(montage.dimensions(), (76, 112));
+    assert_eq!(montage.get_pixel(56, 20).0, [24, 24, 24]);
+    assert_ne!(montage.get_pixel(20, 20).0, [24, 24, 24]);
+
+    let output = run(&[&["montage", dir_arg], &args[..]].concat());
+    assert!(printed(&output).contains("exists already"), "{}", printed(&output));
+    // Without the manifest the frames are found by their names, and the
+    // montage itself isn't taken for one.
+    fs::remove_file(&manifest).unwrap();
+    let output = run(&[&["montage", dir_arg, "--overwrite"], &args[..]].concat());
+    assert!(output.status.success(), "{}", printed(&output));
+    let again = image::open(dir.join("montage.png")).unwrap().to_rgb8();
+    assert_eq!(again.dimensions(), (76, 112));
+
+    let output = run(&["montage", dir_arg, "--columns", "1", "--max-size", "64", "--overwrite"]);
+    assert!(printed(&output).contains("don't fit in 64 pixels"), "{}", printed(&output));
+    let output = run(&["montage", dir.join("nothing").to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn info_summarizes_a_render() {
+    let dir = output_dir("info");
+    let output = zoom(&dir, "3", &["--no-video"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    for path in [dir.clone(), dir.join("manifest.json")] {
+        let output = run(&["info", path.to_str().unwrap()]);
+        assert!(output.status.success(), "{}", printed(&output));
+        let info = printed(&output);
+        assert!(info.contains("Fractal: mandelbrot\n"), "{}", info);
+        assert!(info.contains("Size: 32x32\n"), "{}", info);
+        assert!(info.contains("Frames: 3 rendered, 0 to 2\n"), "{}", info);
+    }
+    let output = run(&["info", frame(&dir, 0).to_str().unwrap()]);
+    assert!(output.status.success(), "{}", printed(&output));
+}
+
+#[test]
+fn explore_serves_tiles() {
+    use std::io::{BufRead, BufReader};
+
+    let mut explore = Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+        .args(["explore", "--port", "0"])
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut line = String::new();
+    BufReader::new(explore.stdout.take().unwrap()).read_line(&mut line).unwrap();
+    explore.kill().unwrap();
+    explore.wait().unwrap();
+    assert!(line.starts_with("Serving mandelbrot tiles"), "{}", line);
+}
+
+#[test]
+fn explore_window_takes_only_its_own_flags() {
+    let refused = [
+        (&["explore", "--window", "--port", "0"][..], "--port is for serving tiles"),
+        (&["explore", "--bookmarks", "saved.json"][..], "--bookmarks needs --window"),
+        (&["explore", "--window", "--width", "0"][..], "width and height should be at least 1"),
+    ];
+    for (args, error) in refused {
+        let output = run(args);
+        assert!(!output.status.success());
+        // Builds without the window feature refuse the flags outright.
+        let error = match cfg!(feature = "window") {
+            true => error,
+            false => "built without the window feature",
+        };
+        assert!(printed(&output).contains(error), "{:?}: {}", args, printed(&output));
+    }
+}
+
+#[test]
+fn dashboard_streams_the_run_and_stops_it() {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpStream;
+
+    let dir = output_dir("dashboard");
+    // Far more frames than are rendered before the run is stopped.
+    let mut render = Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+        .args(["render", "100", "0", "100000", "1.0001", "--width", "32", "--height", "32"])
+        .args(["--no-video", "--dashboard", "127.0.0.1:0", "--output-dir"])
+        .arg(&dir)
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut stdout = BufReader::new(render.stdout.take().unwrap());
+    let mut line = String::new();
+    while !line.starts_with("Dashboard at http://") {
+        line.clear();
+        assert!(stdout.read_line(&mut line).unwrap() > 0, "the dashboard wasn't served");
+    }
+    let address = line.trim()["Dashboard at http://".len()..].trim_end_matches('/').to_string();
+    // The run blocks once the pipe is full.
+    let drain = std::thread::spawn(move || std::io::copy(&mut stdout, &mut std::io::sink()));
+    let request = |method: &str, path: &str| {
+        let mut stream = TcpStream::connect(&address).unwrap();
+        write!(stream, "{} {} HTTP/1.1\r\nHost: localhost\r\n\r\n", method, path).unwrap();
+        BufReader::new(stream)
+    };
+
+    let mut events = request("GET", "/events");
+    let mut headers = Vec::new();
+    while line != "\r\n" {
+        line.clear();
+        events.read_line(&mut line).unwrap();
+        headers.push(line.clone());
+    }
+    assert_eq!(headers[0], "HTTP/1.1 200 OK\r\n");
+    assert!(headers.contains(&"Content-Type: text/event-stream\r\n".to_string()), "{:?}", headers);
+    // Every message is its name and its JSON, ended by an empty line.
+    let mut next = || {
+        let mut name = None;
+        let mut data = String::new();
+        loop {
+            line.clear();
+            assert!(events.read_line(&mut line).unwrap() > 0, "the stream ended");
+            match line.strip_suffix('\n').unwrap().split_once(": ") {
+                Some(("event", event)) if name.is_none() => name = Some(event.to_string()),
+                Some(("data", json)) => data.push_str(json),
+                None if line == "\n" => break,
+                _ => panic!("not a message: {:?}", line),
+            }
+        }
+        (name.expect("every message is named"), serde_json::from_str::<Value>(&data).unwrap())
+    };
+    let (name, snapshot) = next();
+    assert_eq!(name, "snapshot");
+    for key in ["run", "progress", "times", "frame"] {
+        assert!(snapshot.get(key).is_some(), "{}", snapshot);
+    }
+    let mut seen: Vec<String> = Vec::new();
+    let wanted = ["frame_completed", "progress", "frame"];
+    while !wanted.iter().all(|name| seen.iter().any(|seen| seen == name)) {
+        let (name, event) = next();
+        match name.as_str() {
+            "progress" => assert_eq!(event["frames"], 100000),
+            "frame" => assert!(event["frame"].is_u64(), "{}", event),
+            _ => assert_eq!(event["event"], name.as_str()),
+        }
+        seen.push(name);
+    }
+
+    let mut jpeg = Vec::new();
+    request("GET", "/frame.jpg").read_to_end(&mut jpeg).unwrap();
+    let body = jpeg.windows(4).position(|window| window == b"\r\n\r\n").unwrap() + 4;
+    assert!(jpeg.starts_with(b"HTTP/1.1 200 OK\r\nContent-Type: image/jpeg\r\n"));
+    let thumbnail = image::load_from_memory(&jpeg[body..]).unwrap();
+    assert_eq!((thumbnail.width(), thumbnail.height()), (32, 32));
+
+    let mut stopped = String::new();
+    request("POST", "/stop").read_to_string(&mut stopped).unwrap();
+    assert!(stopped.starts_with("HTTP/1.1 200 OK"), "{}", stopped);
+    assert_eq!(render.wait().unwrap().code(), Some(130));
+    drain.join().unwrap().unwrap();
+    assert!(!frame(&dir, 99999).exists());
+}
+
+fn run_frame(args: &[&str]) -> Output {
+    run(&[&["render-frame"], args].concat())
+}
+
+#[test]
+fn render_frame_renders_a_frame_again_byte_for_byte() {
+    let dir = output_dir("render-frame");
+    for (name, args) in [
+        ("turned", &["--rotation-per-frame", "7", "--easing", "ease-in-out", "--palette-drift", "0.1",
+            "--supersample", "2"][..]),
+        ("stabilized", &["--coloring", "histogram", "--stabilize-colors", "0.5", "--auto-iter"]),
+    ] {
+        let run = dir.join(name);
+        let output = zoom(&run, "5", &[args, &["--no-video"]].concat());
+        assert!(output.status.success(), "{}", printed(&output));
+        let saved = fs::read(frame(&run, 3)).unwrap();
+        fs::remove_file(frame(&run, 3)).unwrap();
+        let manifest = run.join("manifest.json");
+        let output = run_frame(&["--manifest", manifest.to_str().unwrap(), "--frame", "3"]);
+        assert!(output.status.success(), "{}", printed(&output));
+        assert!(fs::read(frame(&run, 3)).unwrap() == saved, "{}: frame 3 differs", name);
+    }
+
+    let manifest = dir.join("turned").join("manifest.json");
+    let manifest = manifest.to_str().unwrap();
+    let larger = dir.join("larger.png");
+    let larger_arg = larger.to_str().unwrap();
+    let args = ["--manifest", manifest, "--frame", "1", "--scale", "2", "--output", larger_arg];
+    let output = run_frame(&args);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert_eq!(image::open(&larger).unwrap().to_rgb8().dimensions(), (64, 64));
+    let output = run_frame(&args);
+    assert!(printed(&output).contains("exists already"), "{}", printed(&output));
+
+    let output = run_frame(&["--manifest", manifest, "--frame", "9"]);
+    assert!(printed(&output).contains("has no frame 9; its run rendered frames 0 to 4"));
+}
+
+#[test]
+fn validate_compares_a_frame_across_backends() {
+    let dir = output_dir("validate");
+    let output = zoom(&dir, "4", &["--no-video", "--center=-0.75,0.1"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    let manifest = dir.join("manifest.json");
+    let heatmaps = dir.join("heatmaps");
+    let validate = |args: &[&str]| {
+        let heatmaps = ["--heatmaps", heatmaps.to_str().unwrap()];
+        run(&[&["validate"], &heatmaps[..], args].concat())
+    };
+    let from_manifest = ["--manifest", manifest.to_str().unwrap(), "--frame", "3"];
+    let output = validate(&[&from_manifest[..], &["--backends", "f64,dd,perturb"]].concat());
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(printed(&output).contains("Frame 3 passes"), "{}", printed(&output));
+    for pair in ["f64_dd", "f64_perturbation", "dd_perturbation"] {
+        let heatmap = image::open(heatmaps.join(format!("difference_{}.png", pair))).unwrap();
+        assert_eq!((heatmap.width(), heatmap.height()), (32, 32));
+    }
+    let output = validate(&[&from_manifest[..], &["--backends", "f64,dd"]].concat());
+    assert!(printed(&output).contains("exists already"), "{}", printed(&output));
+
+    // f32 strays from f64 by more than a thousandth of an iteration.
+    let render = ["100", "0", "4", "1.5", "--width", "32", "--height", "32", "--center=-0.75,0.1"];
+    let args = ["--frame", "3", "--backends", "f32,f64", "--threshold", "0.001"];
+    let output = validate(&[&render[..], &args].concat());
+    assert_eq!(output.status.code(), Some(1), "{}", printed(&output));
+    assert!(printed(&output).contains("frame 3 fails validation"), "{}", printed(&output));
+
+    let deep = ["300", "0", "16", "10", "--width", "32", "--height", "32"];
+    let output = validate(&[&deep[..], &["--frame", "15", "--backends", "f64,dd"]].concat());
+    assert!(printed(&output).contains("too deep to compare in f64"), "{}", printed(&output));
+    let output = validate(&[&from_manifest[..], &["--backends", "auto,dd"]].concat());
+    assert!(printed(&output).contains("auto isn't one"), "{}", printed(&output));
+}
+
+#[test]
+fn frames_are_saved_in_every_image_format() {
+    let dir = output_dir("image-formats");
+    let output = zoom(&dir.join("png"), "1", &["--no-video"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    let png = image::open(frame(&dir.join("png"), 0)).unwrap().to_rgb8();
+    for (format, ext) in [("jpeg", "jpg"), ("webp", "webp"), ("tiff", "tiff"), ("bmp", "bmp")] {
+        let format_dir = dir.join(format);
+        let output = zoom(&format_dir, "1", &["--no-video", "--image-format", format]);
+        assert!(output.status.success(), "{}", printed(&output));
+        let encoded = format!("encoded as {} in", format);
+        assert!(printed(&output).contains(&encoded), "{}", printed(&output));
+        let path = format_dir.join(format!("mandelbrot_set_0000.{}", ext));
+        let img = image::open(&path).unwrap().to_rgb8();
+        assert_eq!(img.dimensions(), (32, 32));
+        match format {
+            // Lossy frames are only close to the PNG.
+            "jpeg" => {
+                let off = img.pixels().zip(png.pixels()).map(|(a, b)| {
+                    a.0.iter().zip(b.0).map(|(&a, b)| (a as i32 - b as i32).abs()).sum::<i32>()
+                });
+                assert!(off.sum::<i32>() / (32 * 32) < 60);
+            }
+            _ => assert_eq!(img, png, "{}", format),
+        }
+    }
+
+    let tiff = dir.join("tiff16");
+    let output = zoom(&tiff, "1", &["--no-video", "--image-format", "tiff", "--bit-depth", "16"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    let img = image::open(tiff.join("mandelbrot_set_0000.tiff")).unwrap();
+    assert!(matches!(img, image::DynamicImage::ImageRgb16(_)));
+    let output = zoom(&dir.join("bmp16"), "1", &["--image-format", "bmp", "--bit-depth", "16"]);
+    assert!(printed(&output).contains("--bit-depth 16 needs --image-format png or tiff"));
+    let lossy = ["--no-video", "--image-format", "jpeg", "--dump-iterations"];
+    let output = zoom(&dir.join("lossy"), "1", &lossy);
+    assert!(printed(&output).contains("Note: jpeg frames are lossy"), "{}", printed(&output));
+}
+
+#[test]
+fn alpha_frames_have_a_transparent_interior() {
+    let dir = output_dir("alpha");
+    let output = zoom(&dir.join("opaque"), "1", &["--no-video"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    let opaque = image::open(frame(&dir.join("opaque"), 0)).unwrap().to_rgb8();
+    let alpha = dir.join("interior");
+    let output = zoom(&alpha, "1", &["--alpha", "interior", "--encoder", "internal"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(printed(&output).contains("Note: internal videos have no alpha channel"));
+    let image::DynamicImage::ImageRgba8(img) = image::open(frame(&alpha, 0)).unwrap() else {
+        panic!("frames with --alpha are RGBA");
+    };
+    assert!(img.pixels().any(|pixel| pixel.0[3] == 0));
+    for (pixel, color) in img.pixels().zip(opaque.pixels()) {
+        if pixel.0[3] == 255 {
+            assert_eq!(pixel.0[..3], color.0);
+        }
+    }
+    let args = ["--alpha", "interior", "--image-format", "jpeg"];
+    let output = zoom(&dir.join("jpeg"), "1", &args);
+    assert!(printed(&output).contains("--alpha needs --image-format png, webp or tiff"));
+    let output = zoom(&dir.join("bad"), "1", &["--alpha", "threshold:-1"]);
+    assert!(printed(&output).contains("threshold:V takes an iteration count"));
+}
+
+#[test]
+fn animate_julia_moves_c_along_its_path() {
+    let dir = output_dir("animate-julia");
+    let julia = |dir: &Path, args: &[&str]| {
+        let dir_arg = dir.to_str().unwrap();
+        let path = "circle:center=-0.1+0.65i,radius=0.05";
+        let common = ["animate-julia", "--c-path", path, "--frames", "4", "--max-iter", "200"];
+        let size = ["--width", "32", "--height", "32", "--no-video", "--output-dir", dir_arg];
+        run(&[&common[..], &size[..], args].concat())
+    };
+    let still = dir.join("still");
+    let output = julia(&still, &["--coloring", "smooth"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    let manifest: Value =
+        serde_json::from_str(&fs::read_to_string(still.join("manifest.json")).unwrap()).unwrap();
+    let frames = manifest["frames"].as_array().unwrap();
+    assert_eq!(frames.len(), 4);
+    let c: Vec<(f64, f64)> = frames
+        .iter()
+        .map(|frame| serde_json::from_value(frame["julia_c"].clone()).unwrap())
+        .collect();
+    let near = |a: (f64, f64), b: (f64, f64)| (a.0 - b.0).hypot(a.1 - b.1) < 1e-12;
+    assert!(near(c[0], (-0.05, 0.65)) && near(c[2], (-0.15, 0.65)), "{:?}", c);
+    assert!(frames.iter().all(|frame| frame["magnification"] == 1.0));
+    let decode = |path: PathBuf| image::open(path).unwrap().to_rgb8();
+    assert_ne!(decode(frame(&still, 0)), decode(frame(&still, 2)));
+
+    // The zoom goes on as it would without the path of c.
+    let zooming = dir.join("zooming");
+    let output = julia(&zooming, &["--zoom-factor", "1.5"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    let manifest: Value =
+        serde_json::from_str(&fs::read_to_string(zooming.join("manifest.json")).unwrap()).unwrap();
+    assert_eq!(manifest["frames"][3]["magnification"], 1.5f64.powi(3));
+    assert_eq!(manifest["frames"][3]["julia_c"], frames[3]["julia_c"]);
+
+    let output = julia(&dir.join("incremental"), &["--incremental"]);
+    assert!(printed(&output).contains("can't be used with --incremental"), "{}", printed(&output));
+    let output = run(&["render", "100", "0", "2", "1", "--fractal", "julia"]);
+    assert!(printed(&output).contains("--fractal julia needs --c-path"), "{}", printed(&output));
+}
+
+#[test]
+fn lyapunov_renders_the_parameter_square() {
+    let dir = output_dir("lyapunov");
+    let lyapunov = |dir: &Path, args: &[&str]| {
+        let dir_arg = dir.to_str().unwrap();
+        let common = ["render", "100", "0", "1", "1", "--fractal", "lyapunov"];
+        let size = ["--width", "32", "--height", "32", "--no-video", "--output-dir", dir_arg];
+        run(&[&common[..], &size[..], args].concat())
+    };
+    let default = dir.join("default");
+    let output = lyapunov(&default, &[]);
+    assert!(output.status.success(), "{}", printed(&output));
+    let manifest: Value =
+        serde_json::from_str(&fs::read_to_string(default.join("manifest.json")).unwrap()).unwrap();
+    assert_eq!(manifest["frames"][0]["x_range"], serde_json::json!([2.0, 4.0]));
+
+    let zircon = dir.join("zircon");
+    let sequence = ["--sequence", "BBBBBBAAAAAA", "--warmup", "50", "--samples", "500"];
+    let output = lyapunov(&zircon, &sequence);
+    assert!(output.status.success(), "{}", printed(&output));
+    let decode = |path: PathBuf| image::open(path).unwrap().to_rgb8();
+    assert_ne!(decode(frame(&default, 0)), decode(frame(&zircon, 0)));
+
+    let output = lyapunov(&dir.join("abc"), &["--sequence", "ABC"]);
+    assert!(printed(&output).contains("of the letters A and B"), "{}", printed(&output));
+    let output = lyapunov(&dir.join("coloring"), &["--coloring", "distance"]);
+    assert!(printed(&output).contains("--coloring doesn't apply"), "{}", printed(&output));
+    let output = run(&["render", "100", "0", "1", "1", "--sequence", "AB"]);
+    let refused = printed(&output);
+    assert!(refused.contains("only available with --fractal lyapunov"), "{}", refused);
+}
+
+#[test]
+#[cfg(unix)]
+fn odd_frames_are_padded_for_ffmpeg_or_refused() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = output_dir("pad-to-even");
+    // A stand-in for ffmpeg, keeping its arguments and the frames it reads.
+    let bin = dir.join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    let ffmpeg = bin.join("ffmpeg");
+    let script = "#!/bin/sh\n[ \"$1\" = -version ] && exit 0\n\
+                  echo \"$@\" > \"$(dirname \"$0\")/args\"\ncat > \"$(dirname \"$0\")/frames\"\n";
+    fs::write(&ffmpeg, script).unwrap();
+    fs::set_permissions(&ffmpeg, fs::Permissions::from_mode(0o755)).unwrap();
+    let path = format!("{}:{}", bin.display(), std::env::var("PATH").unwrap_or_default());
+    let frames = dir.join("frames");
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+            .args(["render", "100", "0", "2", "1.5", "--width", "33", "--height", "32"])
+            .args(["--encoder", "ffmpeg", "--interior-color", "#ff8000"])
+            .args(args)
+            .arg("--output-dir")
+            .arg(&frames)
+            .env("PATH", &path)
+            .output()
+            .unwrap()
+    };
+    let output = run(&[]);
+    assert_eq!(output.status.code(), Some(1));
+    let refused = printed(&output);
+    assert!(refused.contains("33x32") && refused.contains("--pad-to-even"), "{}", refused);
+    assert!(!frame(&frames, 0).exists());
+
+    let output = run(&["--pad-to-even"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    let args = fs::read_to_string(bin.join("args")).unwrap();
+    assert!(args.contains("-video_size 34x32"), "{}", args);
+    let video = fs::read(bin.join("frames")).unwrap();
+    assert_eq!(video.len(), 2 * 34 * 32 * 3);
+    // The padding is the last pixel of every row; the frames stay unpadded.
+    assert_eq!(video[33 * 3..34 * 3], [0xff, 0x80, 0x00]);
+    assert_eq!(image::open(frame(&frames, 0)).unwrap().width(), 33);
+    let saved = image::open(frame(&frames, 1)).unwrap().to_rgb8();
+    assert_eq!(video[34 * 32 * 3..][..33 * 3], saved.as_raw()[..33 * 3]);
+}
+
+#[test]
+#[cfg(unix)]
+fn ffmpeg_is_given_the_arguments_of_the_options() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = output_dir("ffmpeg-args");
+    // A stand-in for ffmpeg that keeps its arguments, one to a line.
+    let bin = dir.join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    let ffmpeg = bin.join("ffmpeg");
+    let script = "#!/bin/sh\n[ \"$1\" = -version ] && exit 0\n\
+                  printf '%s\\n' \"$@\" > \"$(dirname \"$0\")/args\"\ncat > /dev/null\n";
+    fs::write(&ffmpeg, script).unwrap();
+    fs::set_permissions(&ffmpeg, fs::Permissions::from_mode(0o755)).unwrap();
+    let path = format!("{}:{}", bin.display(), std::env::var("PATH").unwrap_or_default());
+    let frames = dir.join("frames");
+    let argv = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+            .args(["render", "100", "0", "2", "1.5", "--width", "32", "--height", "24"])
+            .args(["--encoder", "ffmpeg"])
+            .args(args)
+            .arg("--output-dir")
+            .arg(&frames)
+            .env("PATH", &path)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", printed(&output));
+        let args = fs::read_to_string(bin.join("args")).unwrap();
+        args.lines().map(String::from).collect::<Vec<_>>()
+    };
+    let video = format!("{}/rust_out.mp4", frames.display());
+    let input = "-f rawvideo -pixel_format rgb24 -video_size 32x24";
+    let expected = |args: String| args.split(' ').map(String::from).collect::<Vec<_>>();
+    assert_eq!(
+        argv(&[]),
+        expected(format!("-n {} -framerate 30 -i - -c:v libx264 -pix_fmt yuv420p {}", input, video))
+    );
+
+    let custom = ["--codec", "vp9", "--crf", "30", "--fps", "24", "--overwrite"];
+    let video = format!("{}/rust_out.webm", frames.display());
+    assert_eq!(
+        argv(&[&custom[..], &["--ffmpeg-arg", "-an", "--video-out", &video]].concat()),
+        expected(format!(
+            "-y {} -framerate 24 -i - -c:v libvpx-vp9 -pix_fmt yuv420p -crf 30 -b:v 0 -an {}",
+            input, video
+        ))
+    );
+    let x265 = argv(&["--codec", "x265", "--crf", "18", "--overwrite"]);
+    let crf = x265.iter().position(|arg| arg == "-crf").unwrap();
+    assert_eq!(x265[crf + 1], "18");
+    assert!(!x265.contains(&"-b:v".to_string()), "{:?}", x265);
+    assert!(x265.contains(&"libx265".to_string()), "{:?}", x265);
+}
+
+#[test]
+fn palette_maps_color_like_other_palettes() {
+    let dir = output_dir("palette-map");
+    let maps = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/maps");
+    let map = |name: &str| maps.join(name).to_str().unwrap().to_string();
+    let output = zoom(&dir, "1", &["--palette-map", &map("ragged.map"), "--no-video"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(printed(&output).contains("ragged.map: only 4 of 256"), "{}", printed(&output));
+
+    // A .map given to --palette is read as one, and cycles like the rest.
+    let by_palette = dir.join("by-palette");
+    let output = zoom(&by_palette, "1", &["--palette", &map("fire.map"), "--no-video"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    let reversed = dir.join("reversed");
+    let fire = ["--palette-map", &map("fire.map"), "--palette-reverse", "--no-video"];
+    let output = zoom(&reversed, "1", &fire);
+    assert!(output.status.success(), "{}", printed(&output));
+    let decode = |path: PathBuf| image::open(path).unwrap().to_rgb8();
+    assert_ne!(decode(frame(&by_palette, 0)), decode(frame(&reversed, 0)));
+    let smooth = dir.join("smooth");
+    let output = zoom(&smooth, "1", &[&fire[..], &["--map-interpolate"]].concat());
+    assert!(output.status.success(), "{}", printed(&output));
+    assert_ne!(decode(frame(&reversed, 0)), decode(frame(&smooth, 0)));
+
+    let output = zoom(&dir.join("unused"), "1", &["--map-interpolate", "--no-video"]);
+    let refused = printed(&output);
+    assert!(refused.contains("only applies to the palettes of --palette-map"), "{}", refused);
+}
+
+#[test]
+fn palette_resolution_sets_the_lookup_table() {
+    let dir = output_dir("palette-resolution");
+    let decode = |path: PathBuf| image::open(path).unwrap().to_rgb8();
+    let plain = dir.join("plain");
+    let output = zoom(&plain, "1", &["--no-video"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    let same = dir.join("same");
+    let output = zoom(&same, "1", &["--palette-resolution", "4096", "--no-video"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert_eq!(decode(frame(&plain, 0)), decode(frame(&same, 0)));
+    let coarse = dir.join("coarse");
+    let output = zoom(&coarse, "1", &["--palette-resolution", "16", "--no-video"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert_ne!(decode(frame(&plain, 0)), decode(frame(&coarse, 0)));
+
+    let output = zoom(&dir.join("few"), "1", &["--palette-resolution", "8", "--no-video"]);
+    assert!(printed(&output).contains("palette-resolution should be 16 to"), "{}", printed(&output));
+}
+
+#[test]
+fn hud_writes_the_view_in_a_corner() {
+    let dir = output_dir("hud");
+    let decode = |path: PathBuf| image::open(path).unwrap().to_rgb8();
+    let plain = dir.join("plain");
+    let output = zoom(&plain, "2", &["--width", "96", "--height", "64", "--no-video"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    let hud = dir.join("hud");
+    let args = ["--width", "96", "--height", "64", "--no-video", "--hud", "--hud-scale-bar"];
+    let output = zoom(&hud, "2", &[&args[..], &["--hud-position", "bottom-right"]].concat());
+    assert!(output.status.success(), "{}", printed(&output));
+    let (plain_frame, hud_frame) = (decode(frame(&plain, 1)), decode(frame(&hud, 1)));
+    assert_eq!(plain_frame.get_pixel(0, 0), hud_frame.get_pixel(0, 0));
+    assert_ne!(plain_frame.get_pixel(88, 56), hud_frame.get_pixel(88, 56));
+
+    // The piped video gets the overlay, and the frames saved alongside stay
+    // as they are.
+    let video = dir.join("video");
+    let piped = ["--pipe-video", "--preview-every", "1", "--format", "apng", "--hud-only-video"];
+    let output = zoom(&video, "2", &[&args[..4], &piped[..]].concat());
+    assert!(output.status.success(), "{}", printed(&output));
+    assert_eq!(decode(frame(&video, 1)), plain_frame);
+    let decoder = png::Decoder::new(File::open(video.join("rust_out.png")).unwrap());
+    let mut reader = decoder.read_info().unwrap();
+    let mut buf = vec![0; reader.output_buffer_size()];
+    reader.next_frame(&mut buf).unwrap();
+    let saved = decode(frame(&video, 0));
+    assert_ne!(buf[..saved.as_raw().len()], *saved.as_raw());
+
+    let output = zoom(&dir.join("unpiped"), "2", &["--hud-only-video"]);
+    assert!(printed(&output).contains("it needs --pipe-video"), "{}", printed(&output));
+    let output = zoom(&dir.join("corner"), "2", &["--hud-position", "middle"]);
+    assert!(printed(&output).contains("hud-position should be"), "{}", printed(&output));
+}
+
+#[test]
+fn post_filters_the_saved_frames() {
+    let dir = output_dir("post");
+    let decode = |path: PathBuf| image::open(path).unwrap().to_rgb8();
+    let plain = dir.join("plain");
+    let output = zoom(&plain, "2", &["--no-video"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    let output = zoom(&dir, "2", &["--no-video", "--post", "levels:0-0.5,vignette:1"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    let (plain, img) = (decode(frame(&plain, 1)), decode(frame(&dir, 1)));
+    assert_ne!(plain.get_pixel(0, 0).0, [0, 0, 0]);
+    assert_eq!(img.get_pixel(0, 0).0, [0, 0, 0]);
+    assert_ne!(img, plain);
+    let manifest: Value =
+        serde_json::from_str(&fs::read_to_string(dir.join("manifest.json")).unwrap()).unwrap();
+    assert_eq!(manifest["post"], "levels:0-0.5,vignette:1");
+
+    let output = zoom(&dir.join("unknown"), "2", &["--post", "sharpen:1"]);
+    assert!(printed(&output).contains("unknown post filter 'sharpen'"), "{}", printed(&output));
+    let output = zoom(&dir.join("exr"), "2", &["--post", "vignette:0.3", "--export", "exr"]);
+    assert!(printed(&output).contains("needs png in --export"), "{}", printed(&output));
+}
+
+#[test]
+fn julia_inset_only_covers_its_corner() {
+    let dir = output_dir("julia-inset");
+    let decode = |path: PathBuf| image::open(path).unwrap().to_rgb8();
+    let args = ["--width", "96", "--height", "64", "--no-video"];
+    let plain = dir.join("plain");
+    let output = zoom(&plain, "2", &args);
+    assert!(output.status.success(), "{}", printed(&output));
+    let inset = dir.join("inset");
+    let output = zoom(&inset, "2", &[&args[..], &["--julia-inset", "size=25%"]].concat());
+    assert!(output.status.success(), "{}", printed(&output));
+
+    // A quarter of the width and the frame's shape is 24×16, with a pixel
+    // of border around it four pixels from the bottom right corner.
+    let inside = |x: u32, y: u32| (66..92).contains(&x) && (42..60).contains(&y);
+    for number in 0..2 {
+        let plain_frame = decode(frame(&plain, number));
+        let inset_frame = decode(frame(&inset, number));
+        let mut changed = 0;
+        for (x, y, pixel) in inset_frame.enumerate_pixels() {
+            if !inside(x, y) {
+                assert_eq!(pixel, plain_frame.get_pixel(x, y), "at {}, {}", x, y);
+            } else if pixel != plain_frame.get_pixel(x, y) {
+                changed += 1;
+            }
+        }
+        assert!(changed > 26 * 18 / 2, "{} pixels of the inset changed", changed);
+        assert_eq!(inset_frame.get_pixel(66, 42).0, [255; 3]);
+        assert_eq!(inset_frame.get_pixel(91, 59).0, [255; 3]);
+    }
+
+    let output = zoom(&dir.join("tricorn"), "2", &["--julia-inset", "on", "--fractal", "tricorn"]);
+    let expected = "can't be used with --fractal tricorn";
+    assert!(printed(&output).contains(expected), "{}", printed(&output));
+    let output = zoom(&dir.join("size"), "2", &["--julia-inset", "size=80%"]);
+    assert!(printed(&output).contains("julia-inset size should be"), "{}", printed(&output));
+}
+
+#[test]
+fn rays_are_drawn_along_their_way_in() {
+    let dir = output_dir("rays");
+    let decode = |path: PathBuf| image::open(path).unwrap().to_rgb8();
+    let args = ["--width", "96", "--height", "64", "--no-video"];
+    let plain = dir.join("plain");
+    let output = zoom(&plain, "2", &args);
+    assert!(output.status.success(), "{}", printed(&output));
+    let rayed = dir.join("rayed");
+    let output = zoom(&rayed, "2", &[&args[..], &["--ray", "1/2"]].concat());
+    assert!(output.status.success(), "{}", printed(&output));
+
+    // The ray of 1/2 runs in along the real axis from the left to the tip
+    // of the set, through the middle row.
+    for number in 0..2 {
+        let plain_frame = decode(frame(&plain, number));
+        let rayed_frame = decode(frame(&rayed, number));
+        let mut changed = 0;
+        for (x, y, pixel) in rayed_frame.enumerate_pixels() {
+            if pixel != plain_frame.get_pixel(x, y) {
+                assert!((31..=32).contains(&y), "at {}, {}", x, y);
+                assert_eq!(pixel.0, [255; 3]);
+                changed += 1;
+            }
+        }
+        assert!(changed > 5, "{} pixels of the ray", changed);
+    }
+
+    let output = zoom(&dir.join("tricorn"), "2", &["--ray", "1/3", "--fractal", "tricorn"]);
+    let expected = "can't be used with --fractal tricorn";
+    assert!(printed(&output).contains(expected), "{}", printed(&output));
+    let output = zoom(&dir.join("angle"), "2", &["--ray", "0.(012)"]);
+    assert!(printed(&output).contains("an external angle should be"), "{}", printed(&output));
+}
+
+#[test]
+fn contours_are_drawn_over_any_coloring() {
+    let dir = output_dir("contours");
+    let decode = |path: PathBuf| image::open(path).unwrap().to_rgb8();
+    let args = ["--width", "96", "--height", "64", "--coloring", "smooth", "--no-video"];
+    let plain = dir.join("plain");
+    let output = zoom(&plain, "1", &args);
+    assert!(output.status.success(), "{}", printed(&output));
+    let drawn = dir.join("drawn");
+    let contours = ["--contours", "every=2,color=#ff0000", "--silhouette", "color=#00ff00"];
+    let output = zoom(&drawn, "1", &[&args[..], &contours[..]].concat());
+    assert!(output.status.success(), "{}", printed(&output));
+    let (plain, drawn) = (decode(frame(&plain, 0)), decode(frame(&drawn, 0)));
+    let changed = plain.pixels().zip(drawn.pixels()).filter(|(plain, drawn)| plain != drawn);
+    let (reddened, greened) = changed.fold((0, 0), |(red, green), (plain, drawn)| {
+        (red + (drawn[0] > plain[0]) as u32, green + (drawn[1] > plain[1]) as u32)
+    });
+    assert!(reddened > 50 && greened > 20, "{} red and {} green", reddened, greened);
+
+    // With a background, the lines are drawn alone.
+    let alone = dir.join("alone");
+    let background = ["--contours", "every=5,background=#ffffff"];
+    let output = zoom(&alone, "1", &[&args[..], &background[..]].concat());
+    assert!(output.status.success(), "{}", printed(&output));
+    let alone = decode(frame(&alone, 0));
+    let white = alone.pixels().filter(|pixel| pixel.0 == [255; 3]).count();
+    assert!(white > alone.len() / 3 / 2, "{} white", white);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+        .args(["still", "--contours", "on", "--output"])
+        .arg(dir.join("still.png"))
+        .output()
+        .unwrap();
+    let expected = "still colors in tiles, which --contours would leave seams between";
+    assert!(printed(&output).contains(expected), "{}", printed(&output));
+    let output = zoom(&dir.join("field"), "1", &["--contours", "every=5,height=2"]);
+    assert!(printed(&output).contains("unknown contours field"), "{}", printed(&output));
+}
+
+#[test]
+fn line_art_draws_the_boundary_alone() {
+    let dir = output_dir("lineart");
+    let args = ["--width", "96", "--height", "64", "--coloring", "smooth", "--no-video"];
+    let drawn = dir.join("drawn");
+    let lines = ["--style", "lineart", "--line-silhouette", "--line-weight", "1"];
+    let output = zoom(&drawn, "1", &[&args[..], &lines[..]].concat());
+    assert!(output.status.success(), "{}", printed(&output));
+    let drawn = image::open(frame(&drawn, 0)).unwrap().to_rgb8();
+    assert!(drawn.pixels().all(|pixel| pixel.0 == [0; 3] || pixel.0 == [255; 3]));
+    let inked = drawn.pixels().filter(|pixel| pixel.0 == [0; 3]).count();
+    assert!(inked > 50 && inked < drawn.len() / 3 / 2, "{} inked", inked);
+
+    // On transparent paper, the lines are white and all that's opaque.
+    let clear = dir.join("clear");
+    let paper = ["--line-paper", "transparent"];
+    let output = zoom(&clear, "1", &[&args[..], &lines[..], &paper[..]].concat());
+    assert!(output.status.success(), "{}", printed(&output));
+    let clear = image::open(frame(&clear, 0)).unwrap().to_rgba8();
+    for (drawn, clear) in drawn.pixels().zip(clear.pixels()) {
+        let opaque = (drawn.0 == [0; 3]) as u8 * 255;
+        assert_eq!(clear.0, [255, 255, 255, opaque]);
+    }
+
+    for (flags, expected) in [
+        (&["--line-weight", "2"][..], "--line-weight only applies to --style lineart"),
+        (&["--style", "lineart", "--contours", "every=5"], "can't be used with --contours"),
+        (&["--style", "lineart", "--mode", "buddhabrot"], "only available with --mode escape"),
+        (&["--style", "outline"], "style should be palette or lineart"),
+    ] {
+        let output = zoom(&dir.join("refused"), "1", flags);
+        assert!(printed(&output).contains(expected), "{}", printed(&output));
+    }
+}
+
+/// Runs `animate-palette` into `dir`, with `args` after it.
+fn animate_palette(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+        .arg("animate-palette")
+        .args(args)
+        .arg("--output-dir")
+        .arg(dir)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn animated_palette_colors_one_render_over_and_over() {
+    let dir = output_dir("animate-palette");
+    let decode = |path: PathBuf| image::open(path).unwrap().to_rgb8();
+    let args = ["--frames", "4", "--width", "48", "--height", "32", "--no-video"];
+    let output = animate_palette(&dir.join("view"), &[&args[..], &["--max-iter", "100"]].concat());
+    assert!(output.status.success(), "{}", printed(&output));
+    assert_eq!(printed(&output).matches("Rendered the view once").count(), 1);
+    assert_eq!(printed(&output).matches("colored in").count(), 4, "{}", printed(&output));
+    let frames: Vec<_> = (0..4).map(|n| decode(frame(&dir.join("view"), n))).collect();
+    for (n, pair) in frames.windows(2).enumerate() {
+        assert_ne!(pair[0], pair[1], "frames {} and {}", n, n + 1);
+    }
+    assert!(!frame(&dir.join("view"), 4).exists());
+    let output = animate_palette(&dir.join("view"), &args);
+    assert!(printed(&output).contains("pass --overwrite"), "{}", printed(&output));
+
+    // Frame 0 of the escape times of a dumped frame is that frame as it
+    // was colored.
+    let zoomed = dir.join("zoom");
+    let output = zoom(&zoomed, "1", &["--no-video", "--dump-iterations"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    let dump = zoomed.join("mandelbrot_set_0000.npy");
+    let from = ["--frames", "3", "--no-video", "--from", dump.to_str().unwrap()];
+    let output = animate_palette(&dir.join("from"), &from);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert_eq!(decode(frame(&dir.join("from"), 0)), decode(frame(&zoomed, 0)));
+    assert_ne!(decode(frame(&dir.join("from"), 1)), decode(frame(&zoomed, 0)));
+    let output = animate_palette(&dir.join("sized"), &[&from[..], &["--width", "64"]].concat());
+    assert!(printed(&output).contains("--from colors"), "{}", printed(&output));
+}
+
+/// Runs `animate-iter` into `dir`, with `args` after it.
+fn animate_iter(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+        .arg("animate-iter")
+        .args(args)
+        .arg("--output-dir")
+        .arg(dir)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn animated_limit_takes_what_escaped_from_the_frame_before() {
+    let dir = output_dir("animate-iter");
+    fs::create_dir_all(&dir).unwrap();
+    let decode = |path: PathBuf| image::open(path).unwrap().to_rgb8();
+    let view = ["--width", "48", "--height", "32", "--center=-0.75,0.1"];
+    let args = ["--frames", "4", "--iter-start", "5", "--iter-end", "200", "--curve", "log"];
+    let output = animate_iter(&dir, &[&view[..], &args, &["--no-video"]].concat());
+    assert!(output.status.success(), "{}", printed(&output));
+    for (frame, max_iter) in [(0, 5), (1, 17), (2, 58), (3, 200)] {
+        let line = format!("Frame {} at max_iter {}:", frame, max_iter);
+        assert!(printed(&output).contains(&line), "{}", printed(&output));
+    }
+    assert!(printed(&output).contains("0.0% of the samples taken"), "{}", printed(&output));
+
+    // Taking the samples that escaped leaves the last frame as a still at
+    // its limit would be.
+    let output = Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+        .args(["still", "--max-iter", "200", "--output", "still.png"])
+        .args(view)
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", printed(&output));
+    assert_eq!(decode(frame(&dir, 3)), decode(dir.join("still.png")));
+
+    let output = animate_iter(&dir, &[&args[..], &["--max-iter", "100"]].concat());
+    assert!(printed(&output).contains("--max-iter is an option of still"), "{}", printed(&output));
+    let output = animate_iter(&dir, &["--frames", "4", "--iter-start", "50", "--iter-end", "5"]);
+    assert!(printed(&output).contains("should be above --iter-start"), "{}", printed(&output));
+}
+
+/// Runs `pan` into `dir`, with `args` after it.
+fn pan(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+        .arg("pan")
+        .args(args)
+        .arg("--output-dir")
+        .arg(dir)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn pan_takes_the_frame_before_shifted_by_whole_pixels() {
+    let dir = output_dir("pan");
+    let decode = |path: PathBuf| image::open(path).unwrap().to_rgb8();
+    let path = ["--from=-0.75,0.1", "--via=-0.745,0.11", "--to=-0.74,0.1", "--view-width", "0.05"];
+    let args = ["--frames", "8", "--width", "64", "--height", "48", "--coloring", "smooth"];
+    let args = [&path[..], &args, &["--max-iter", "500", "--no-video"]].concat();
+    for flip in [&[][..], &["--flip-y"]] {
+        let args = [&args[..], flip].concat();
+        let reused = dir.join(format!("reused{}", flip.len()));
+        let output = pan(&reused, &args);
+        assert!(output.status.success(), "{}", printed(&output));
+        let full = dir.join(format!("full{}", flip.len()));
+        let output = pan(&full, &[&args[..], &["--keyframe-every", "1"]].concat());
+        assert!(output.status.success(), "{}", printed(&output));
+        // The samples taken are those a full render computes, but for the
+        // rounding of the centers.
+        for frame_number in 0..8 {
+            let (a, b) = (decode(frame(&reused, frame_number)), decode(frame(&full, frame_number)));
+            let differ = a.pixels().zip(b.pixels()).filter(|(a, b)| {
+                a.0.iter().zip(b.0).any(|(a, b)| a.abs_diff(b) > 8)
+            });
+            assert!(differ.count() * 100 < 64 * 48, "frame {}", frame_number);
+        }
+    }
+
+    let manifest: Value =
+        serde_json::from_str(&fs::read_to_string(dir.join("reused0/manifest.json")).unwrap())
+            .unwrap();
+    let frames = manifest["frames"].as_array().unwrap();
+    let center = |record: &Value| {
+        let part = |index: usize| record["center"][index].as_str().unwrap().parse::<f64>().unwrap();
+        (part(0), part(1))
+    };
+    let pixel_size = frames[0]["pixel_size"].as_f64().unwrap();
+    assert_eq!(center(&frames[0]), (-0.75, 0.1));
+    let last = center(&frames[7]);
+    assert!((last.0 + 0.74).abs() <= pixel_size / 2.0 && (last.1 - 0.1).abs() <= pixel_size / 2.0);
+    for record in frames {
+        assert_eq!(record["magnification"], frames[0]["magnification"]);
+        let (x, y) = center(record);
+        let pixels = ((x + 0.75) / pixel_size, (y - 0.1) / pixel_size);
+        assert!((pixels.0 - pixels.0.round()).abs() < 1e-6, "{}", record);
+        assert!((pixels.1 - pixels.1.round()).abs() < 1e-6, "{}", record);
+    }
+
+    let output = pan(&dir.join("unfinished"), &["--from=-0.75,0.1", "--frames", "4"]);
+    assert!(printed(&output).contains("pan needs --from and --to"), "{}", printed(&output));
+    let centered = [&path[..], &["--frames", "4", "--center=0,0"]].concat();
+    let output = pan(&dir.join("centered"), &centered);
+    assert!(printed(&output).contains("used with --center"), "{}", printed(&output));
+    let output = zoom(&dir.join("widened"), "4", &["--view-width", "0.05"]);
+    assert!(printed(&output).contains("only available with --pan-from"), "{}", printed(&output));
+}
+
+#[test]
+fn view_gives_the_first_frame_by_its_corners() {
+    let dir = output_dir("view");
+    let decode = |path: PathBuf| image::open(path).unwrap().to_rgb8();
+    let still = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rustlebrot"))
+            .args(["still", "--width", "48", "--height", "32", "--max-iter", "200"])
+            .args(args)
+            .current_dir(&dir)
+            .output()
+            .unwrap()
+    };
+    fs::create_dir_all(&dir).unwrap();
+    let output = still(&["--view=-2,1,-1,1", "--output", "view.png"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert!(!printed(&output).contains("isn't shaped like"), "{}", printed(&output));
+    let output = still(&["--x-range=-2,1", "--y-range=-1,1", "--output", "ranges.png"]);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert_eq!(decode(dir.join("view.png")), decode(dir.join("ranges.png")));
+    let output = still(&["--view=-2,1,-1,1", "--height", "48", "--output", "square.png"]);
+    let expected = "isn't shaped like the 48x48 frame, so --fit contain makes it -2,1,-1.5,1.5";
+    assert!(printed(&output).contains(expected), "{}", printed(&output));
+
+    // The zoom closes in on the point of --zoom-to, which stays where it
+    // is in the frame.
+    let args = ["--view=-1.5,0.5,-1,1", "--zoom-to=-0.75,0.1", "--no-video"];
+    let output = zoom(&dir.join("toward"), "4", &args);
+    assert!(output.status.success(), "{}", printed(&output));
+    let ranges = ["--x-range=-1.5,0.5", "--y-range=-1,1", "--no-video"];
+    let output = zoom(&dir.join("ranges"), "1", &ranges);
+    assert!(output.status.success(), "{}", printed(&output));
+    assert_eq!(decode(frame(&dir.join("toward"), 0)), decode(frame(&dir.join("ranges"), 0)));
+    let manifest: Value =
+        serde_json::from_str(&fs::read_to_string(dir.join("toward/manifest.json")).unwrap())
+            .unwrap();
+    assert_eq!(manifest["view"]["x_range"], serde_json::json!([-1.5, 0.5]));
+    assert_eq!(manifest["view"]["fit"], "contain");
+    assert_eq!(manifest["view"]["fitted_y_range"], serde_json::json!([-1.0, 1.0]));
+    assert_eq!(manifest["view"]["center"], serde_json::json!([-0.5, 0.0]));
+    assert_eq!(manifest["view"]["zoom_to"], serde_json::json!(["-0.75", "0.1"]));
+    let frames = manifest["frames"].as_array().unwrap();
+    assert_eq!(frames.len(), 4);
+    for record in frames {
+        let share = |range: &Value, target: f64| {
+            let (min, max) = (range[0].as_f64().unwrap(), range[1].as_f64().unwrap());
+            (target - min) / (max - min)
+        };
+        assert!((share(&record["x_range"], -0.75) - 0.375).abs() < 1e-9, "{}", record);
+        assert!((share(&record["y_range"], 0.1) - 0.55).abs() < 1e-9, "{}", record);
+        assert!(record["center"].is_array(), "{}", record);
+    }
+
+    for (args, error) in [
+        (&["--view=1,-2,-1,1"][..], "runs backwards along x"),
+        (&["--view=-2,1,0,0"], "no extent along y"),
+        (&["--view=-2,1,-1", "--center=0,0"], "got 3 of them"),
+        (&["--view=-2,1,-1,1", "--center=0,0"], "so it can't be used with --center"),
+        (&["--view=-2,1,-1,1", "--x-range=-2,1", "--y-range=-1,1"], "can't be used with --x-range"),
+        (&["--zoom-to=-0.75,0.1"], "so it needs one of them"),
+        (&["--view=-2,1,-1,1", "--zoom-to=2,0"], "outside the view"),
+        (&["--view=-2,1,-1,1", "--zoom-to=0,0", "--expmap"], "can't be used with --expmap"),
+    ] {
+        let output = zoom(&dir.join("refused"), "2", args);
+        assert_eq!(output.status.code(), Some(1), "{:?}", args);
+        assert!(printed(&output).contains(error), "{}", printed(&output));
+    }
+    let output = still(&["--view=-2,1,-1,1", "--magnification", "2"]);
+    let error = "can't be used with --magnification";
+    assert!(printed(&output).contains(error), "{}", printed(&output));
+}
+
+#[test]
+fn roi_renders_the_pixels_a_full_frame_has_there() {
+    let dir = output_dir("roi");
+    let decode = |path: PathBuf| image::open(path).unwrap().to_rgb8();
+    for coloring in ["smooth", "distance"] {
+        let args = ["--width", "96", "--height", "64", "--supersample", "2", "--no-video"];
+        let args = [&args[..], &["--coloring", coloring]].concat();
+        let full = dir.join(coloring).join("full");
+        let output = zoom(&full, "2", &args);
+        assert!(output.status.success(), "{}", printed(&output));
+        let cropped = dir.join(coloring).join("cropped");
+        let output = zoom(&cropped, "2", &[&args[..], &["--roi", "20,12,40,30"]].concat());
+        assert!(output.status.success(), "{}", printed(&output));
+        let filled = dir.join(coloring).join("filled");
+        let roi = ["--roi", "20,12,40,30", "--roi-fill"];
+        let output = zoom(&filled, "2", &[&args[..], &roi[..]].concat());
+        assert!(output.status.success(), "{}", printed(&output));
+
+        let full_frame = decode(frame(&full, 1));
+        let expected = image::imageops::crop_imm(&full_frame, 20, 12, 40, 30).to_image();
+        assert_eq!(decode(frame(&cropped, 1)), expected);
+        let filled_frame = decode(frame(&filled, 1));
+        assert_eq!(filled_frame.dimensions(), (96, 64));
+        assert_eq!(image::imageops::crop_imm(&filled_frame, 20, 12, 40, 30).to_image(), expected);
+        let checker = [filled_frame.get_pixel(0, 0), filled_frame.get_pixel(8, 0)];
+        assert_eq!(checker.map(|pixel| pixel.0), [[0x99; 3], [0x66; 3]]);
+    }
+
+    let output = zoom(&dir.join("outside"), "2", &["--roi", "20,12,40,30"]);
+    assert!(printed(&output).contains("reaches past the 32x32 frame"), "{}", printed(&output));
+    let output = zoom(&dir.join("buddhabrot"), "2", &["--roi", "0,0,8,8", "--mode", "buddhabrot"]);
+    assert!(printed(&output).contains("only available with --mode escape"), "{}", printed(&output));
+    let output = zoom(&dir.join("fill"), "2", &["--roi-fill"]);
+    assert!(printed(&output).contains("only available with --roi"), "{}", printed(&output));
+}
+
+#[test]
+fn debug_channels_line_up_with_the_frame() {
+    let dir = output_dir("debug-channels");
+    let channel = |dir: &Path, name: &str| {
+        let path = dir.join(format!("mandelbrot_set_0000.{}.png", name));
+        image::open(path).unwrap().into_luma16()
+    };
+    let args = ["--width", "48", "--height", "40", "--no-video"];
+    let output = zoom(&dir, "1", &[&args[..], &["--debug-channels", "iter,time"]].concat());
+    assert!(output.status.success(), "{}", printed(&output));
+    let colored = image::open(frame(&dir, 0)).unwrap().to_rgb8();
+    let (iter, time) = (channel(&dir, "iter"), channel(&dir, "time"));
+    assert_eq!(iter.dimensions(), colored.dimensions());
+    assert_eq!(time.dimensions(), colored.dimensions());
+    // The interior is white in the heatmap, and drawn in the interior
+    // color in the frame.
+    for (x, y, pixel) in colored.enumerate_pixels() {
+        assert_eq!(iter.get_pixel(x, y).0[0] == u16::MAX, pixel.0 == [0, 0, 0], "({}, {})", x, y);
+    }
+    assert!(time.pixels().any(|pixel| pixel.0[0] == u16::MAX));
+
+    // A region is placed where it is in the frame, like the frame.
+    let roi = dir.join("roi");
+    let region = ["--roi", "8,4,24,20", "--roi-fill", "--debug-channels", "iter"];
+    let output = zoom(&roi, "1", &[&args[..], &region[..]].concat());
+    assert!(output.status.success(), "{}", printed(&output));
+    let placed = channel(&roi, "iter");
+    assert_eq!(placed.dimensions(), (48, 40));
+    let cropped = |img| image::imageops::crop_imm(img, 8, 4, 24, 20).to_image();
+    assert_eq!(cropped(&placed), cropped(&iter));
+
+    let adaptive = dir.join("adaptive");
+    let refined = ["--adaptive", "--debug-channels", "samples"];
+    let output = zoom(&adaptive, "1", &[&args[..], &refined[..]].concat());
+    assert!(output.status.success(), "{}", printed(&output));
+    let samples = channel(&adaptive, "samples");
+    assert!(samples.pixels().any(|pixel| pixel.0[0] == 0));
+    assert!(samples.pixels().any(|pixel| pixel.0[0] > 0));
+
+    let output = zoom(&dir.join("plain"), "1", &["--debug-channels", "samples"]);
+    assert!(printed(&output).contains("only available with --adaptive"), "{}", printed(&output));
+    let distance = ["--debug-channels", "iter", "--coloring", "distance"];
+    let output = zoom(&dir.join("distance"), "1", &distance);
+    assert!(printed(&output).contains("needs escape times"), "{}", printed(&output));
+    let output = zoom(&dir.join("unknown"), "1", &["--debug-channels", "iter,heat"]);
+    assert!(printed(&output).contains("unknown debug channel 'heat'"), "{}", printed(&output));
+}
@@ -0,0 +1,451 @@
+//! Golden-image tests: small fixed renders compared against the reference
+//! PNGs in `tests/golden`.
+//!
+//! After a change that is meant to alter the output, regenerate the
+//! references with `RUSTLEBROT_BLESS=1 cargo test --test golden` and check
+//! the new images in along with it.
+
+use image::RgbImage;
+use rustlebrot::coloring::{Coloring, Phase, Transfer, ANGLE_BAILOUT};
+use rustlebrot::dither::Dither;
+use rustlebrot::fractal::Mandelbrot;
+use rustlebrot::lighting::Lighting;
+use rustlebrot::lyapunov::Lyapunov;
+use rustlebrot::newton::Newton;
+use rustlebrot::palette::{self, Adjust, Blending, Colormap, Cycle, Palette};
+use rustlebrot::render::{
+    self, Alpha, BitDepth, ColorOptions, EscapeBuffer, RenderOptions, Rotation, Subdivision,
+    WorkUnits,
+};
+use std::path::PathBuf;
+
+/// Pixels along each side of every render.
+const SIZE: u32 = 128;
+
+/// One reference render.
+struct Case {
+    /// The name of the reference, `tests/golden/<name>.png`.
+    name: &'static str,
+    /// The coefficients of the polynomial of a Newton fractal, or `None`
+    /// for the Mandelbrot set.
+    poly: Option<&'static [f64]>,
+    /// The sequence of a Lyapunov fractal, which takes the place of the
+    /// Mandelbrot set like `poly`.
+    sequence: Option<&'static str>,
+    center: (f64, f64),
+    /// The width of the view in the plane.
+    width: f64,
+    max_iter: u32,
+    coloring: Coloring,
+    transfer: Transfer,
+    bailout: f64,
+    lighting: Option<Lighting>,
+    /// A palette map of `tests/maps` to color with in place of the default
+    /// palette, and whether its colors are interpolated.
+    map: Option<(&'static str, bool)>,
+    /// How far a channel can be off before the pixel counts as differing.
+    tolerance: u8,
+    /// How many pixels can differ before the render fails, which leaves
+    /// room for the last bit of floating point differing between platforms
+    /// on the boundary.
+    max_differing: usize,
+}
+
+const DEFAULT_VIEW: Case = Case {
+    name: "default_view",
+    poly: None,
+    sequence: None,
+    center: (0.0, 0.0),
+    width: 4.0,
+    max_iter: 1000,
+    coloring: Coloring::Smooth,
+    transfer: Transfer::Linear,
+    bailout: 2.0,
+    lighting: None,
+    map: None,
+    tolerance: 2,
+    max_differing: 16,
+};
+
+/// Seahorse valley, with the distance estimate drawing the filaments.
+const FILAMENT: Case = Case {
+    name: "filament",
+    poly: None,
+    sequence: None,
+    center: (-0.743643887, 0.131825904),
+    width: 5e-4,
+    max_iter: 2000,
+    coloring: Coloring::Distance,
+    transfer: Transfer::Linear,
+    bailout: 2.0,
+    lighting: None,
+    map: None,
+    tolerance: 2,
+    max_differing: 16,
+};
+
+/// The neck between the cardioid and the period 2 bulb, mostly interior,
+/// where periodicity checking ends most orbits.
+const INTERIOR: Case = Case {
+    name: "interior",
+    poly: None,
+    sequence: None,
+    center: (-0.75, 0.0),
+    width: 0.4,
+    max_iter: 1000,
+    coloring: Coloring::EscapeTime,
+    transfer: Transfer::Linear,
+    bailout: 2.0,
+    lighting: None,
+    map: None,
+    tolerance: 2,
+    max_differing: 16,
+};
+
+/// The default view in phase coloring, where the angles cut every escape
+/// time band into a stripe for either half plane.
+const PHASE: Case = Case {
+    name: "phase",
+    coloring: Coloring::Phase,
+    ..DEFAULT_VIEW
+};
+
+/// The default view in binary decomposition into 8 sectors, at the large
+/// bailout that straightens the field lines.
+const BINARY: Case = Case {
+    name: "binary",
+    coloring: Coloring::Binary(3),
+    bailout: ANGLE_BAILOUT,
+    ..DEFAULT_VIEW
+};
+
+/// Seahorse valley in stripe average coloring, where the stripes follow
+/// the spirals.
+const STRIPES: Case = Case {
+    name: "stripes",
+    coloring: Coloring::Stripes(5.0),
+    bailout: ANGLE_BAILOUT,
+    ..FILAMENT
+};
+
+/// The default view lit from the top right, with a highlight, which shades
+/// the slopes of the smooth escape times up towards the set.
+const LIT: Case = Case {
+    name: "lit",
+    lighting: Some(Lighting {
+        angle: 45.0,
+        elevation: 45.0,
+        strength: 0.6,
+        specular: 0.3,
+        spin: 0.0,
+    }),
+    ..DEFAULT_VIEW
+};
+
+/// The basins of Newton's method for z³ - 1, with the three roots meeting
+/// at every point of the boundary.
+const NEWTON: Case = Case {
+    name: "newton",
+    poly: Some(&[1.0, 0.0, 0.0, -1.0]),
+    max_iter: 100,
+    ..DEFAULT_VIEW
+};
+
+/// The basins of z³ - 2z + 2, where Newton's method falls into the cycle
+/// between 0 and 1 in the black regions instead of reaching a root.
+const NEWTON_CYCLE: Case = Case {
+    name: "newton_cycle",
+    poly: Some(&[1.0, 0.0, -2.0, 2.0]),
+    ..NEWTON
+};
+
+/// Zircon City, the Lyapunov fractal of BBBBBBAAAAAA for a from 2.65 to
+/// 3.25 and b from 3.4 to 4, with the chaotic city above the stable shore.
+const ZIRCON_CITY: Case = Case {
+    name: "zircon_city",
+    sequence: Some("BBBBBBAAAAAA"),
+    center: (2.95, 3.7),
+    width: 0.6,
+    ..DEFAULT_VIEW
+};
+
+/// The default view in escape time bands of the default VGA palette, as
+/// Fractint would step through it.
+const MAP_VGA: Case = Case {
+    name: "map_vga",
+    coloring: Coloring::EscapeTime,
+    map: Some(("vga.map", false)),
+    ..DEFAULT_VIEW
+};
+
+/// The default view in smooth escape times, blended through a fire map
+/// from black to white. The palette spans few iterations, so the bands far
+/// from the set get the brighter colors too.
+const MAP_FIRE: Case = Case {
+    name: "map_fire",
+    max_iter: 40,
+    map: Some(("fire.map", true)),
+    ..DEFAULT_VIEW
+};
+
+/// The default view with its escape times spread by their square root,
+/// which gives the bands far from the set more of the gradient.
+const SQRT: Case = Case {
+    name: "transfer_sqrt",
+    transfer: Transfer::Sqrt,
+    ..DEFAULT_VIEW
+};
+
+/// The default view with its escape times spread by their logarithm, which
+/// spreads the bands far from the set the most.
+const LOG: Case = Case {
+    name: "transfer_log",
+    transfer: Transfer::Log,
+    ..DEFAULT_VIEW
+};
+
+/// The default view with its escape times spread by a power above 1, which
+/// crowds the bands far from the set into the first colors.
+const POWER: Case = Case {
+    name: "transfer_power",
+    transfer: Transfer::Power(2.0),
+    ..DEFAULT_VIEW
+};
+
+const CASES: [&Case; 15] = [
+    &DEFAULT_VIEW,
+    &FILAMENT,
+    &INTERIOR,
+    &PHASE,
+    &BINARY,
+    &STRIPES,
+    &LIT,
+    &NEWTON,
+    &NEWTON_CYCLE,
+    &ZIRCON_CITY,
+    &MAP_VGA,
+    &MAP_FIRE,
+    &SQRT,
+    &LOG,
+    &POWER,
+];
+
+/// Renders `case` in the default colors of the command line program.
+fn render(case: &Case) -> (EscapeBuffer, RgbImage) {
+    let half = case.width / 2.0;
+    let options = RenderOptions {
+        max_iter: case.max_iter,
+        periodicity: true,
+        bailout: case.bailout,
+        coloring: case.coloring,
+        single_precision: false,
+        subdivision: Subdivision::Off,
+        work_units: WorkUnits::Auto,
+        reuse: None,
+        refine: None,
+        rotation: Rotation::NONE,
+        window: None,
+        timing: None,
+        attractors: false,
+    };
+    let (x_range, y_range) = (
+        (case.center.0 - half, case.center.0 + half),
+        (case.center.1 - half, case.center.1 + half),
+    );
+    let buffer = match (case.poly, case.sequence) {
+        (Some(poly), _) => {
+            let newton = Newton::new(poly).unwrap();
+            render::compute_basins(&newton, SIZE, SIZE, x_range, y_range, &options)
+        }
+        (None, Some(sequence)) => {
+            let lyapunov = Lyapunov::new(sequence, 100, 1000).unwrap();
+            render::compute_lyapunov(&lyapunov, SIZE, SIZE, x_range, y_range, &options)
+        }
+        (None, None) => {
+            render::compute_escape(&Mandelbrot, SIZE, SIZE, x_range, y_range, &options)
+        }
+    };
+    let gradient = match case.map {
+        Some((file, interpolate)) => {
+            let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/maps").join(file);
+            let (colors, _) = palette::parse_map(&std::fs::read_to_string(path).unwrap()).unwrap();
+            palette::custom_gradient(&palette::map_stops(&colors, interpolate), Blending::Linear)
+        }
+        None => Palette::Sinebow.gradient(),
+    };
+    // Maps are shown in their own colors.
+    let adjust = Adjust {
+        invert: case.map.is_none(),
+        ..Adjust::default()
+    };
+    let colormap = Colormap::new(&gradient, &adjust);
+    let colors = ColorOptions {
+        palette_iter: case.max_iter,
+        histogram_clip: 0.0,
+        transfer: case.transfer,
+        reference: None,
+        colormap: &colormap,
+        cycle: Cycle::new(&gradient, 4.0, 0.0, false),
+        interior: (0, 0, 0),
+        bit_depth: BitDepth::Eight,
+        dither: Dither::None,
+        alpha: Alpha::None,
+        phase: Phase::default(),
+        lighting: case.lighting,
+        script: None,
+        interior_coloring: None,
+        contours: None,
+        silhouette: None,
+        line_art: None,
+    };
+    let img = render::colorize(&buffer, &colors).to_rgb8();
+    (buffer, img)
+}
+
+/// Compares the render of `case` with its reference, or replaces the
+/// reference in blessed-update mode.
+fn check(case: &Case) {
+    let (_, actual) = render(case);
+    let reference = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{}.png", case.name));
+    if std::env::var_os("RUSTLEBROT_BLESS").is_some_and(|bless| !bless.is_empty()) {
+        actual.save(&reference).unwrap();
+        return;
+    }
+    let expected = image::open(&reference)
+        .unwrap_or_else(|e| {
+            panic!(
+                "can't read {}: {}; run with RUSTLEBROT_BLESS=1 to create it",
+                reference.display(),
+                e
+            )
+        })
+        .to_rgb8();
+    assert_eq!(expected.dimensions(), actual.dimensions(), "size of {}", case.name);
+    let differing = expected
+        .pixels()
+        .zip(actual.pixels())
+        .filter(|(a, b)| a.0.iter().zip(b.0).any(|(&a, b)| a.abs_diff(b) > case.tolerance))
+        .count();
+    if differing > case.max_differing {
+        let saved = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("{}.png", case.name));
+        actual.save(&saved).unwrap();
+        panic!(
+            "{} pixels of {} differ from {} by more than {}, at most {} may; the render is at {}",
+            differing,
+            case.name,
+            reference.display(),
+            case.tolerance,
+            case.max_differing,
+            saved.display()
+        );
+    }
+}
+
+#[test]
+fn default_view() {
+    check(&DEFAULT_VIEW);
+}
+
+#[test]
+fn filament() {
+    check(&FILAMENT);
+}
+
+#[test]
+fn interior() {
+    check(&INTERIOR);
+}
+
+#[test]
+fn phase() {
+    check(&PHASE);
+}
+
+#[test]
+fn binary() {
+    check(&BINARY);
+}
+
+#[test]
+fn stripes() {
+    check(&STRIPES);
+}
+
+#[test]
+fn lit() {
+    check(&LIT);
+}
+
+#[test]
+fn newton() {
+    check(&NEWTON);
+}
+
+#[test]
+fn newton_cycle() {
+    check(&NEWTON_CYCLE);
+}
+
+#[test]
+fn zircon_city() {
+    check(&ZIRCON_CITY);
+}
+
+#[test]
+fn map_vga() {
+    check(&MAP_VGA);
+}
+
+#[test]
+fn map_fire() {
+    check(&MAP_FIRE);
+}
+
+#[test]
+fn transfer_sqrt() {
+    check(&SQRT);
+}
+
+#[test]
+fn transfer_log() {
+    check(&LOG);
+}
+
+#[test]
+fn transfer_power() {
+    check(&POWER);
+}
+
+/// Every pixel is computed on its own, so the number of threads can't
+/// change a single sample.
+#[test]
+fn renders_are_independent_of_thread_count() {
+    for case in CASES {
+        let renders: Vec<_> = [1, 3, 8]
+            .iter()
+            .map(|&threads| {
+                let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+                pool.install(|| render(case))
+            })
+            .collect();
+        for (buffer, img) in &renders[1..] {
+            assert!(buffer.values == renders[0].0.values, "samples of {}", case.name);
+            assert!(*img == renders[0].1, "pixels of {}", case.name);
+        }
+    }
+}
+
+/// `render_region` is the default view's pipeline with the plainer
+/// arguments.
+#[test]
+fn render_region_matches_the_pipeline() {
+    let (_, expected) = render(&DEFAULT_VIEW);
+    let rgba = rustlebrot::render_region(SIZE, SIZE, 1000, 0.0, 0.0, 4.0 / SIZE as f64).unwrap();
+    assert_eq!(rgba.len(), (SIZE * SIZE * 4) as usize);
+    for (rgb, rgba) in expected.pixels().zip(rgba.chunks(4)) {
+        assert_eq!(rgb.0, rgba[..3]);
+        assert_eq!(rgba[3], 255);
+    }
+}
@@ -0,0 +1,2920 @@
+//! Checks of the compute and colorize passes that don't need reference
+//! images.
+
+use rustlebrot::bigfloat;
+use rustlebrot::buddhabrot::{self, BuddhabrotOptions, Metropolis, Sampler, ToneMap};
+use rustlebrot::budget::{self, CostModel, Probe, TimeBudget};
+use rustlebrot::coloring::{Coloring, Phase, Transfer};
+use rustlebrot::dd::{self, DoubleDouble};
+use rustlebrot::decimal::{self, Decimal};
+use rustlebrot::difference::{self, Difference};
+use rustlebrot::dimension::{self, Dimension};
+use rustlebrot::dither::Dither;
+use rustlebrot::error::RustlebrotError;
+use rustlebrot::expmap::{ExpMap, Strip, View};
+use rustlebrot::formula::{Formula, FORMULA_BAILOUT};
+use rustlebrot::julia::{self, CPath, Julia};
+use rustlebrot::complex::Complex;
+use rustlebrot::contour::{Contours, Line};
+use rustlebrot::grid::{self, Grid};
+use rustlebrot::interior::{self, InteriorColoring};
+use rustlebrot::isoline::{self, Field, Layer, Polyline, Spacing};
+use rustlebrot::lineart::{LineArt, Paper};
+use rustlebrot::fractal::{
+    self, Escape, EscapeTimeFractal, Fractal, FractalKind, Mandelbrot, Tricorn,
+};
+use rustlebrot::location::Location;
+use rustlebrot::lyapunov::Lyapunov;
+use rustlebrot::mesh::{self, Heightfield, Mesh, Plateau};
+use rustlebrot::newton::Newton;
+use rustlebrot::nucleus::{self, MAX_PERIOD};
+use rustlebrot::outputs;
+use rustlebrot::palette::{self, Adjust, Blending, Colormap, Cycle, Palette, Stop};
+use rustlebrot::post::{Chain, Filter, UNSHARP_SIGMA};
+use rustlebrot::precision::Precision;
+use rustlebrot::preflight::{self, Estimate, Finding, Footprint, Times};
+use rustlebrot::preset::PRESETS;
+use rustlebrot::quality::{self, Quality, Settings};
+use rustlebrot::ray::{ExternalAngle, Ray};
+use rustlebrot::render::{
+    self, Adaptive, Alpha, BitDepth, ColorOptions, EscapeBuffer, RenderOptions, Reuse, Rotation,
+    Sample, Scripted, Subdivision, Window, WorkUnits,
+};
+use rustlebrot::script::{Inputs, Needs, Orbit, Output, Script};
+use rustlebrot::stabilize::Reference;
+use rustlebrot::template::{self, FilenameTemplate, FrameName};
+use rustlebrot::trap::Trap;
+use rustlebrot::view::{self, Corners, Fit};
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+fn options(max_iter: u32) -> RenderOptions<'static> {
+    RenderOptions {
+        max_iter,
+        periodicity: true,
+        bailout: 2.0,
+        coloring: Coloring::EscapeTime,
+        single_precision: false,
+        subdivision: Subdivision::Off,
+        work_units: WorkUnits::Auto,
+        reuse: None,
+        refine: None,
+        rotation: Rotation::NONE,
+        window: None,
+        timing: None,
+        attractors: false,
+    }
+}
+
+/// Colors a buffer in the default palette, with white as the interior so
+/// that it stands apart from boundary black.
+fn colorize(buffer: &EscapeBuffer) -> image::RgbImage {
+    let gradient = Palette::Sinebow.gradient();
+    let colormap = Colormap::new(&gradient, &Adjust::default());
+    render::colorize(buffer, &colors(&colormap)).to_rgb8()
+}
+
+fn colors(colormap: &Colormap) -> ColorOptions<'_> {
+    ColorOptions {
+        palette_iter: 100,
+        histogram_clip: 0.0,
+        transfer: Transfer::Linear,
+        reference: None,
+        colormap,
+        cycle: Cycle::new(&Palette::Sinebow.gradient(), 1.0, 0.0, false),
+        interior: (255, 255, 255),
+        bit_depth: BitDepth::Eight,
+        dither: Dither::None,
+        alpha: Alpha::None,
+        phase: Phase::default(),
+        lighting: None,
+        script: None,
+        interior_coloring: None,
+        contours: None,
+        silhouette: None,
+        line_art: None,
+    }
+}
+
+fn buffer(width: u32, height: u32, samples: u32, values: Vec<Sample>) -> EscapeBuffer {
+    EscapeBuffer {
+        width,
+        height,
+        samples,
+        refined: HashMap::new(),
+        max_iter: 100,
+        coloring: Coloring::EscapeTime,
+        values,
+        orbits: Vec::new(),
+    }
+}
+
+/// The vectorized loops give exactly what iterating each point on its own
+/// does, with and without periodicity checking.
+#[test]
+fn escape_times_match_escape_time() {
+    fn check<F: Fractal>(fractal: &F) {
+        let points: Vec<(f64, f64)> = (0..64 * 64)
+            .map(|i| (-2.2 + 3.0 * (i % 64) as f64 / 64.0, -1.5 + 3.0 * (i / 64) as f64 / 64.0))
+            .collect();
+        for periodicity in [None, Some(1e-10)] {
+            let mut escapes = vec![Escape::interior(0, 0.0); points.len()];
+            fractal.escape_times(&points, 500, 2.0, periodicity, &mut escapes);
+            for (escape, &c) in escapes.iter().zip(&points) {
+                assert_eq!(*escape, fractal.escape_time(c, 500, 2.0, periodicity, None), "{:?}", c);
+            }
+        }
+    }
+    check(&Mandelbrot);
+    check(&Tricorn);
+}
+
+/// Passing the y range the other way around flips the frame vertically,
+/// also off the real axis. Rows are sampled at their top edge, so the
+/// mirror of a row is one further down.
+#[test]
+fn reversed_y_range_mirrors_the_frame() {
+    let (x_range, y_range) = ((-0.8, -0.2), (0.25, 0.75));
+    let options = options(200);
+    let up = render::compute_escape(&Mandelbrot, 64, 64, x_range, y_range, &options);
+    let down = render::compute_escape(&Mandelbrot, 64, 64, x_range, (0.75, 0.25), &options);
+    let row = |buffer: &EscapeBuffer, y: usize| buffer.values[y * 64..][..64].to_vec();
+    for y in 1..64 {
+        assert_eq!(row(&up, y), row(&down, 64 - y));
+    }
+}
+
+/// A frame computed in four tiles is the frame computed whole, sample for
+/// sample, including tiles that don't split it evenly.
+#[test]
+fn tiles_put_together_make_the_frame() {
+    let (width, height) = (50, 30);
+    let (x_range, y_range) = ((-0.9, -0.3), (0.1, 0.5));
+    for coloring in [Coloring::Smooth, Coloring::Distance] {
+        let options = RenderOptions {
+            coloring,
+            ..options(300)
+        };
+        let whole = render::compute_escape(&Mandelbrot, width, height, x_range, y_range, &options);
+        let mut tiled = vec![Sample::Interior; (width * height) as usize];
+        for (x0, y0, w, h) in [(0, 0, 27, 17), (27, 0, 23, 17), (0, 17, 27, 13), (27, 17, 23, 13)] {
+            let options = RenderOptions {
+                window: Some(Window {
+                    frame: (width, height),
+                    origin: (x0, y0),
+                }),
+                ..options
+            };
+            let tile = render::compute_escape(&Mandelbrot, w, h, x_range, y_range, &options);
+            for (y, row) in tile.values.chunks(w as usize).enumerate() {
+                let start = (y0 as usize + y) * width as usize + x0 as usize;
+                tiled[start..][..w as usize].copy_from_slice(row);
+            }
+        }
+        assert_eq!(whole.values, tiled, "{:?}", coloring);
+    }
+}
+
+/// However the frame is cut into units of work, its samples are the same,
+/// mirrored rows, per-sample colorings and windows included.
+#[test]
+fn work_units_dont_change_the_samples() {
+    let (width, height) = (50, 30);
+    // The real axis is on row 20, so rows 21 to 29 mirror rows 19 to 11.
+    let (x_range, y_range) = ((-2.0, 1.0), (-0.6, 1.2));
+    let units = [WorkUnits::Rows(1), WorkUnits::Rows(7), WorkUnits::Tiles(5), WorkUnits::Tiles(64)];
+    for coloring in [Coloring::Smooth, Coloring::Distance] {
+        for window in [None, Some(Window { frame: (70, 40), origin: (13, 6) })] {
+            let options = RenderOptions {
+                coloring,
+                window,
+                ..options(300)
+            };
+            let auto =
+                render::compute_escape(&Mandelbrot, width, height, x_range, y_range, &options);
+            for work_units in units {
+                let options = RenderOptions {
+                    work_units,
+                    ..options
+                };
+                let buffer =
+                    render::compute_escape(&Mandelbrot, width, height, x_range, y_range, &options);
+                assert!(buffer.values == auto.values, "{:?} {:?}", coloring, work_units);
+            }
+        }
+    }
+}
+
+/// Subdivision fills in rectangles with the sample on their border, which
+/// here is every sample they would have had: the escape time bands where
+/// whole iterations are colored, and only the interior in smooth coloring.
+/// Showing it makes the pixels filled in boundary and leaves the rest.
+#[test]
+fn subdivision_fills_in_the_samples_it_would_compute() {
+    let (width, height) = (160, 120);
+    for (x_range, y_range) in [((-2.2, 0.8), (-1.125, 1.125)), ((-0.8, -0.7), (0.05, 0.125))] {
+        for coloring in [Coloring::EscapeTime, Coloring::Smooth] {
+            let compute = |subdivision| {
+                let options = RenderOptions {
+                    coloring,
+                    subdivision,
+                    ..options(500)
+                };
+                render::compute_escape(&Mandelbrot, width, height, x_range, y_range, &options)
+            };
+            let (off, on, show) =
+                (compute(Subdivision::Off), compute(Subdivision::On), compute(Subdivision::Show));
+            assert!(on.values == off.values, "{:?} {:?}", coloring, x_range);
+            let mut filled = 0;
+            for (shown, computed) in show.values.iter().zip(&off.values) {
+                if *shown == Sample::Boundary {
+                    filled += 1;
+                    assert!(coloring == Coloring::EscapeTime || *computed == Sample::Interior);
+                } else {
+                    assert_eq!(shown, computed);
+                }
+            }
+            assert!(filled > 0, "{:?} {:?} fills in nothing", coloring, x_range);
+        }
+    }
+}
+
+/// The rows mirrored across the real axis escape at the opposite angles,
+/// exactly as when they're computed.
+#[test]
+fn mirrored_rows_escape_at_opposite_angles() {
+    let (x_range, y_range) = ((-2.0, 1.0), (-1.0, 1.0));
+    let options = RenderOptions {
+        coloring: Coloring::Phase,
+        ..options(200)
+    };
+    let mirrored = render::compute_escape(&Mandelbrot, 48, 32, x_range, y_range, &options);
+    // A window of the whole frame is computed in full.
+    let options = RenderOptions {
+        window: Some(Window {
+            frame: (48, 32),
+            origin: (0, 0),
+        }),
+        ..options
+    };
+    let computed = render::compute_escape(&Mandelbrot, 48, 32, x_range, y_range, &options);
+    assert!(mirrored.values.iter().any(|sample| matches!(sample, Sample::Phase { .. })));
+    assert_eq!(mirrored.values, computed.values);
+}
+
+/// A view off the real axis, centered at 0.5i, only mirrors the band of
+/// rows around the axis, and the image is the one computed without
+/// mirroring, pixel for pixel.
+#[test]
+fn off_center_views_mirror_the_band_around_the_axis() {
+    let (x_range, y_range) = ((-1.0, 1.0), (-0.5, 1.5));
+    for coloring in [Coloring::EscapeTime, Coloring::Smooth, Coloring::Distance] {
+        let options = RenderOptions { coloring, ..options(200) };
+        let mirrored = render::compute_escape(&Mandelbrot, 64, 64, x_range, y_range, &options);
+        // A window of the whole frame is computed in full.
+        let window = Some(Window { frame: (64, 64), origin: (0, 0) });
+        let options = RenderOptions { window, ..options };
+        let computed = render::compute_escape(&Mandelbrot, 64, 64, x_range, y_range, &options);
+        assert_eq!(colorize(&mirrored), colorize(&computed), "{:?}", coloring);
+    }
+}
+
+/// Turning `c` and `z` a third of the way around turns the next `conj(z)^2 + c`
+/// with them, so a view of the Tricorn centered on the origin looks the
+/// same turned by 120 degrees, up to the odd pixel rounding moves across
+/// the boundary. The Mandelbrot set doesn't.
+#[test]
+fn tricorn_images_have_three_fold_symmetry() {
+    fn differing<F: Fractal>(fractal: &F) -> f64 {
+        let render = |rotation| {
+            let options = RenderOptions { rotation, ..options(100) };
+            colorize(&render::compute_escape(fractal, 96, 96, (-1.5, 1.5), (-1.5, 1.5), &options))
+        };
+        let (image, turned) = (render(Rotation::NONE), render(Rotation::degrees(120.0)));
+        let differing = image.pixels().zip(turned.pixels()).filter(|(a, b)| a != b).count();
+        differing as f64 / (96 * 96) as f64
+    }
+    let tricorn = differing(&Tricorn);
+    assert!(tricorn < 0.002, "{} of the turned Tricorn differs", tricorn);
+    let mandelbrot = differing(&Mandelbrot);
+    assert!(mandelbrot > 0.1, "only {} of the turned Mandelbrot set differs", mandelbrot);
+}
+
+/// The Mandelbrot and Tricorn iterations written as formulas render the
+/// same samples as the built-in fractals, in every coloring the formulas
+/// carry out.
+#[test]
+fn formulas_render_like_the_builtin_fractals() {
+    struct Quadratic {
+        conjugate: bool,
+    }
+
+    impl EscapeTimeFractal for Quadratic {
+        fn init(&self, _c: (f64, f64)) -> (f64, f64) {
+            (0.0, 0.0)
+        }
+
+        fn step(&self, z: (f64, f64), c: (f64, f64)) -> (f64, f64) {
+            let z = Complex::from(z);
+            let z = if self.conjugate { z.conj() } else { z };
+            (z * z + c.into()).into()
+        }
+    }
+
+    fn check<F: Fractal>(fractal: &F, formula: &Quadratic) {
+        let (x_range, y_range) = ((-2.2, 0.8), (-1.3, 1.1));
+        for coloring in [Coloring::EscapeTime, Coloring::Smooth, Coloring::Stripes(5.0)] {
+            let options = RenderOptions {
+                coloring,
+                ..options(300)
+            };
+            let expected = render::compute_escape(fractal, 48, 40, x_range, y_range, &options);
+            let actual = render::compute_escape(formula, 48, 40, x_range, y_range, &options);
+            assert_eq!(actual.values, expected.values, "{:?}", coloring);
+        }
+    }
+    check(&Mandelbrot, &Quadratic { conjugate: false });
+    check(&Tricorn, &Quadratic { conjugate: true });
+}
+
+/// The Julia set of c = 0 is the unit disk. A path of c goes around its
+/// circle without coming back to the start, or through its keyframes to
+/// the last.
+#[test]
+fn julia_sets_follow_their_c_path() {
+    let julia = Julia { c: (0.0, 0.0) };
+    let buffer = render::compute_escape(&julia, 40, 40, (-2.0, 2.0), (-2.0, 2.0), &options(100));
+    assert_eq!(buffer.values[20 * 40 + 20], Sample::Interior);
+    assert!(matches!(buffer.values[0], Sample::Value(_)));
+
+    let close = |a: (f64, f64), b: (f64, f64)| {
+        (a.0 - b.0).abs() < 1e-12 && (a.1 - b.1).abs() < 1e-12
+    };
+    let circle = CPath::from_spec("circle:center=0+0i,radius=1").unwrap();
+    let at = |path: &CPath, frame| path.at(frame, 4, |u| u);
+    assert!(close(at(&circle, 0), (1.0, 0.0)));
+    assert!(close(at(&circle, 1), (0.0, 1.0)));
+    assert!(close(at(&circle, 3), (0.0, -1.0)));
+    let keyframes = CPath::from_spec("keyframes:-1,i,2").unwrap();
+    let at = |frame| keyframes.at(frame, 5, |u| u);
+    assert_eq!([at(0), at(1), at(2), at(4)], [(-1.0, 0.0), (-0.5, 0.5), (0.0, 1.0), (2.0, 0.0)]);
+    let eased = keyframes.at(1, 5, |u| u * u);
+    assert_eq!(eased, (-0.75, 0.25));
+
+    assert_eq!(julia::parse_complex("-0.1+0.65i"), Ok((-0.1, 0.65)));
+    assert_eq!(julia::parse_complex("0.3i"), Ok((0.0, 0.3)));
+    assert_eq!(julia::parse_complex("-1"), Ok((-1.0, 0.0)));
+    assert_eq!(julia::parse_complex("1e-3-2e-2i"), Ok((0.001, -0.02)));
+    assert_eq!(julia::parse_complex("-i"), Ok((0.0, -1.0)));
+    assert!(julia::parse_complex("1+2j").is_err());
+    assert!(CPath::from_spec("circle:center=0.3i").is_err());
+}
+
+/// Lyapunov exponents are those of the logistic map where both growth rates
+/// are the same, negative where it settles and positive where it's chaotic.
+#[test]
+fn lyapunov_exponents_match_the_logistic_map() {
+    // At r = 2.5 the orbit settles on 0.6, where the map's slope is -0.5.
+    let lyapunov = Lyapunov::new("AB", 100, 1000).unwrap();
+    let settled = lyapunov.exponent(2.5, 2.5).unwrap();
+    assert!((settled - 0.5f64.ln()).abs() < 1e-9, "{}", settled);
+    // At r = 2 it starts on its fixed point, where the slope is 0.
+    assert_eq!(lyapunov.exponent(2.0, 2.0), Some(f64::NEG_INFINITY));
+    assert!(lyapunov.exponent(3.9, 3.9).unwrap() > 0.0);
+    assert_eq!(lyapunov.exponent(3.0, 4.5), None);
+
+    let buffer =
+        render::compute_lyapunov(&lyapunov, 40, 40, (2.0, 4.0), (2.0, 4.0), &options(100));
+    let exponent = |x: usize, y: usize| match buffer.values[y * 40 + x] {
+        Sample::Exponent(exponent) => exponent,
+        sample => panic!("{:?} at {}, {}", sample, x, y),
+    };
+    // Low growth rates are at the bottom left and high ones at the top
+    // right.
+    assert!(exponent(0, 39) < 0.0);
+    assert!(exponent(39, 0) > 0.0);
+
+    assert_eq!(Lyapunov::new("aabab", 0, 1).unwrap().sequence(), "AABAB");
+    assert!(Lyapunov::new("ABC", 100, 1000).is_err());
+    assert!(Lyapunov::new("", 100, 1000).is_err());
+    assert!(Lyapunov::new("AB", 100, 0).is_err());
+}
+
+/// Formulas parsed at runtime render the same samples as the same formulas
+/// compiled in, whether they're specialized to powers of `z` or run as
+/// programs, and smooth escape times are interpolated in the base of their
+/// degree.
+#[test]
+fn runtime_formulas_render_like_compiled_ones() {
+    struct Cubic;
+
+    impl EscapeTimeFractal for Cubic {
+        fn init(&self, _c: (f64, f64)) -> (f64, f64) {
+            (0.0, 0.0)
+        }
+
+        fn step(&self, z: (f64, f64), c: (f64, f64)) -> (f64, f64) {
+            let (z, c) = (Complex::from(z), Complex::from(c));
+            (z * z * z + c * z + c).into()
+        }
+
+        fn bailout(&self) -> f64 {
+            FORMULA_BAILOUT
+        }
+
+        fn symmetric(&self) -> bool {
+            true
+        }
+
+        fn log_base(&self) -> f64 {
+            3.0
+        }
+    }
+
+    fn check<F: Fractal>(fractal: &F, formula: &Formula) {
+        let (x_range, y_range) = ((-2.2, 0.8), (-1.3, 1.1));
+        for coloring in [Coloring::EscapeTime, Coloring::Smooth, Coloring::Stripes(5.0)] {
+            let options = RenderOptions {
+                coloring,
+                ..options(300)
+            };
+            let expected = render::compute_escape(fractal, 48, 40, x_range, y_range, &options);
+            let actual = render::compute_escape(formula, 48, 40, x_range, y_range, &options);
+            assert_eq!(actual.values, expected.values, "{} {:?}", formula.source(), coloring);
+        }
+    }
+    for source in ["z^2 + c", "c + z*z", "z^(4 / 2) + c"] {
+        let formula = Formula::parse(source).unwrap();
+        assert_eq!(formula.kernel(), "z^2 + c, by repeated squaring", "{}", source);
+        check(&Mandelbrot, &formula);
+    }
+    let cubic = Formula::parse("z*z*z + c*z + c").unwrap();
+    assert_eq!(cubic.kernel(), "a program of 6 instructions");
+    assert_eq!((cubic.degree(), cubic.log_base), (Some(3.0), 3.0));
+    check(&Cubic, &cubic);
+
+    for (source, degree, log_base) in [
+        ("z^5 + c", Some(5.0), 5.0),
+        ("z^2.5 + c", Some(2.5), 2.5),
+        ("conj(z)^2 / 3 + c", Some(2.0), 2.0),
+        ("exp(z) + c", None, 2.0),
+        ("z + c", Some(1.0), 2.0),
+    ] {
+        let formula = Formula::parse(source).unwrap();
+        assert_eq!((formula.degree(), formula.log_base), (degree, log_base), "{}", source);
+    }
+}
+
+/// Mistakes in formulas are reported at the character they're at.
+#[test]
+fn formula_errors_say_where_they_are() {
+    for (source, error) in [
+        ("z^2 + sin(q)", "character 11: unknown name 'q'"),
+        ("z^2 + (c", "character 9: expected ')', found the end of the formula"),
+        ("z^2 $ c", "character 5: unexpected '$'"),
+        ("z^2 + * c", "character 7: expected a number, z, c, a function or '('"),
+        ("z c", "character 3: unexpected 'c'"),
+        ("sin z", "character 5: expected '(', found 'z'"),
+        ("  ", "the formula is empty"),
+        ("c + 1", "the formula should use z"),
+    ] {
+        let message = Formula::parse(source).unwrap_err();
+        assert!(message.starts_with(error), "{}: {}", source, message);
+    }
+    let nested = format!("{}z{}", "sin(z) + (".repeat(20), ")".repeat(20));
+    assert!(Formula::parse(&nested).unwrap_err().contains("nested too deeply"));
+}
+
+/// The roots found numerically are the known roots of polynomials of
+/// every degree from 2 to 6, with repeated roots counted once.
+#[test]
+fn newton_finds_the_roots_of_known_polynomials() {
+    let unity = |n: usize| -> Vec<(f64, f64)> {
+        (0..n)
+            .map(|k| {
+                let angle = std::f64::consts::TAU * k as f64 / n as f64;
+                (angle.cos(), angle.sin())
+            })
+            .collect()
+    };
+    let cases: [(&[f64], _); 6] = [
+        (&[1.0, 0.0, 1.0], vec![(0.0, 1.0), (0.0, -1.0)]),
+        (&[1.0, -2.0, -5.0, 6.0], vec![(1.0, 0.0), (-2.0, 0.0), (3.0, 0.0)]),
+        (&[1.0, -1.0, 0.0, 0.0], vec![(0.0, 0.0), (1.0, 0.0)]),
+        (&[2.0, 0.0, 0.0, 0.0, -2.0], unity(4)),
+        (&[1.0, 0.0, 0.0, 0.0, -1.0, 0.0], [vec![(0.0, 0.0)], unity(4)].concat()),
+        (&[1.0, 0.0, 0.0, 0.0, 0.0, 0.0, -1.0], unity(6)),
+    ];
+    for (coefficients, expected) in cases {
+        let newton = Newton::new(coefficients).unwrap();
+        assert_eq!(newton.roots().len(), expected.len(), "roots of {:?}", coefficients);
+        for root in expected {
+            let near = |found: &(f64, f64)| (found.0 - root.0).hypot(found.1 - root.1) < 1e-6;
+            assert!(
+                newton.roots().iter().any(near),
+                "{:?} is a root of {:?}, found {:?}",
+                root,
+                coefficients,
+                newton.roots()
+            );
+        }
+    }
+    assert!(Newton::from_spec("1,0").is_err());
+    assert!(Newton::from_spec("0,1,0,-1").is_err());
+    assert!(Newton::from_spec("1,x,-1").is_err());
+}
+
+/// Newton's method for z² - 1 takes every point to the root on its side of
+/// the imaginary axis, as Cayley showed.
+#[test]
+fn quadratic_basins_are_half_planes() {
+    let newton = Newton::new(&[1.0, 0.0, -1.0]).unwrap();
+    let right = newton.roots().iter().position(|root| root.0 > 0.0).unwrap();
+    for i in 0..40 {
+        for j in 0..40 {
+            let z = (-2.05 + 0.1 * i as f64, -2.05 + 0.1 * j as f64);
+            let (root, _) = newton.basin(z, 100).unwrap();
+            assert_eq!(root == right, z.0 > 0.0, "{:?}", z);
+        }
+    }
+    assert_eq!(newton.basin((0.0, 0.7), 100), None);
+}
+
+/// The basins of z^n - 1 turn into each other with the roots of unity, for
+/// every degree from 2 to 6, apart from points right on the boundaries.
+#[test]
+fn roots_of_unity_basins_turn_with_the_roots() {
+    for n in 2..=6 {
+        let mut coefficients = vec![0.0; n + 1];
+        (coefficients[0], coefficients[n]) = (1.0, -1.0);
+        let newton = Newton::new(&coefficients).unwrap();
+        let angle = std::f64::consts::TAU / n as f64;
+        let turn = |z: (f64, f64)| {
+            (z.0 * angle.cos() - z.1 * angle.sin(), z.0 * angle.sin() + z.1 * angle.cos())
+        };
+        let (mut points, mut differing) = (0, 0);
+        for i in 0..64 {
+            for j in 0..64 {
+                let at = |i: i32| -1.5 + 3.0 * (i as f64 + 0.5) / 64.0;
+                let z = (at(i), at(j));
+                let basin = |z| newton.basin(z, 200).map(|(root, _)| root);
+                points += 1;
+                if basin(turn(z)) != basin(z).map(|root| (root + 1) % n) {
+                    differing += 1;
+                }
+            }
+        }
+        assert!(differing * 100 < points, "{} of {} differ for degree {}", differing, points, n);
+    }
+}
+
+/// A region without pixels is refused up front, with the size asked for.
+#[test]
+fn empty_region_is_an_error() {
+    for (width, height) in [(0, 16), (16, 0)] {
+        let rendered = rustlebrot::render_region(width, height, 100, 0.0, 0.0, 0.01);
+        assert!(
+            matches!(rendered, Err(RustlebrotError::EmptyImage { width: w, height: h })
+                if (w, h) == (width, height)),
+            "{:?}",
+            rendered
+        );
+    }
+}
+
+/// A large bailout escapes the same points as the radius 2 one, a few
+/// iterations later, and the smooth escape times it gives converge: less
+/// the `log2(ln R)` every bailout `R` adds, those of 1e6 and 1e12 agree.
+#[test]
+fn larger_bailouts_escape_the_same_points_later() {
+    let max_iter = 1000;
+    let potential = |escape: &Escape, bailout: f64| escape.smooth - bailout.ln().log2();
+    let mut escaped = 0;
+    for i in 0..64 * 64 {
+        let c = (-2.0 + 2.5 * (i % 64) as f64 / 64.0, -1.25 + 2.5 * (i / 64) as f64 / 64.0);
+        let escape = |bailout| Mandelbrot.escape_time(c, max_iter, bailout, None, None);
+        let (small, large, larger) = (escape(2.0), escape(1e6), escape(1e12));
+        let inside = small.iterations >= max_iter as f64;
+        assert_eq!(inside, large.iterations >= max_iter as f64, "{:?}", c);
+        if inside {
+            continue;
+        }
+        escaped += 1;
+        let later = large.iterations - small.iterations;
+        assert!((0.0..=8.0).contains(&later), "{:?} escapes {} iterations later", c, later);
+        let settled = potential(&larger, 1e12) - potential(&large, 1e6);
+        assert!(settled.abs() < 1e-6, "{:?} moves by {}", c, settled);
+    }
+    assert!(escaped > 1000, "only {} points escaped", escaped);
+}
+
+/// Distance estimation escapes past the larger of `--bailout` and its own
+/// radius, so a small bailout changes nothing and a larger one is kept,
+/// only moving the estimate closer to where it converges.
+#[test]
+fn distance_estimates_keep_a_larger_bailout() {
+    let max_iter = 1000;
+    let mut escaped = 0;
+    for i in 0..32 * 32 {
+        let c = (-2.0 + 2.5 * (i % 32) as f64 / 32.0, -1.25 + 2.5 * (i / 32) as f64 / 32.0);
+        let distance = |bailout| Mandelbrot.distance(c, max_iter, bailout);
+        let (small, own, large) = (distance(2.0), distance(1e3), distance(1e8));
+        assert_eq!(small, own, "{:?}", c);
+        let (Some(small), Some(large)) = (small, large) else {
+            continue;
+        };
+        escaped += 1;
+        assert!(small != large, "{:?} keeps {} at a larger bailout", c, small);
+        assert!((large / small - 1.0).abs() < 1e-2, "{:?}: {} against {}", c, large, small);
+    }
+    assert!(escaped > 500, "only {} points escaped", escaped);
+}
+
+/// Points in the main cardioid and the period 2 bulb take no iterations:
+/// they're found inside in closed form, in f64 and in double-double, right
+/// up to the boundary, and never iterated even with a limit it would take
+/// an age to reach. Points just outside are left to the loop.
+#[test]
+fn cardioid_and_bulb_points_take_no_iterations() {
+    use std::f64::consts::PI;
+
+    // The disk of radius `r` mapped onto the cardioid and onto the bulb,
+    // which it fills for `r` below 1.
+    let cardioid = |r: f64, t: f64| {
+        let (x, y) = (r * t.cos(), r * t.sin());
+        (x / 2.0 - (x * x - y * y) / 4.0, y / 2.0 - x * y / 2.0)
+    };
+    let bulb = |r: f64, t: f64| (-1.0 + r * t.cos() / 4.0, r * t.sin() / 4.0);
+    let inside = |c: (f64, f64)| {
+        let dd = (DoubleDouble::from(c.0), DoubleDouble::from(c.1));
+        let both = (fractal::in_cardioid_or_bulb(c), dd::in_cardioid_or_bulb(dd));
+        assert_eq!(both.0, both.1, "{:?}", c);
+        both.0
+    };
+    for step in 0..64 {
+        let t = 2.0 * PI * step as f64 / 64.0;
+        for c in [cardioid(0.999, t), cardioid(0.5, t), bulb(0.999, t), bulb(0.5, t)] {
+            assert!(inside(c), "{:?} is inside", c);
+        }
+        // Where the two meet, a point just outside one is inside the other,
+        // and the cusp folds the disk back over itself.
+        if (t - PI).abs() > 0.3 && t > 0.3 && t < 2.0 * PI - 0.3 {
+            assert!(!inside(cardioid(1.001, t)), "{:?} is outside", cardioid(1.001, t));
+        }
+        if t > 0.3 && t < 2.0 * PI - 0.3 {
+            assert!(!inside(bulb(1.001, t)), "{:?} is outside", bulb(1.001, t));
+        }
+    }
+    for c in [(0.0, 0.0), (-0.5, 0.3), (0.2, -0.1), (-1.0, 0.0), (-1.1, 0.1)] {
+        let escape = Mandelbrot.escape_time(c, u32::MAX, 2.0, None, None);
+        assert_eq!(escape.iterations, u32::MAX as f64);
+        let dd = (DoubleDouble::from(c.0), DoubleDouble::from(c.1));
+        let escape = Mandelbrot.escape_time_dd(dd, u32::MAX, 2.0, None);
+        assert_eq!(escape.iterations, u32::MAX as f64);
+    }
+}
+
+/// Iterating in f32 is good enough while pixels are far above its
+/// resolution: the escape times only differ in the odd boundary pixel.
+#[test]
+fn single_precision_agrees_with_double_on_shallow_frames() {
+    let half = 2.0 / 1.05f64.powi(155);
+    let center = (-1.7499984109937408, 0.0);
+    let x_range = (center.0 - half, center.0 + half);
+    let y_range = (center.1 - half, center.1 + half);
+    let mut single = options(5000);
+    single.single_precision = true;
+    let f32 = render::compute_escape(&Mandelbrot, 128, 128, x_range, y_range, &single);
+    let f64 = render::compute_escape(&Mandelbrot, 128, 128, x_range, y_range, &options(5000));
+    let differing = f32.values.iter().zip(&f64.values).filter(|(a, b)| a != b).count();
+    assert!(differing * 100 < f64.values.len() * 3, "{} samples differ", differing);
+}
+
+/// The frame right at the f32 boundary, the deepest auto precision iterates
+/// in f32, looks the same in both precisions: its colors only move by the
+/// rounding of the coloring, but for the odd boundary pixel.
+#[test]
+fn f32_boundary_frame_matches_f64() {
+    let center = (-1.7499984109937408, 0.0);
+    let pixel = 8.0 * Precision::F32.resolution(center);
+    assert!(Precision::F32.resolves(center, pixel));
+    assert!(!Precision::F32.resolves(center, pixel * 0.99));
+    let size = 64;
+    let half = pixel * size as f64 / 2.0;
+    let x_range = (center.0 - half, center.0 + half);
+    let y_range = (center.1 - half, center.1 + half);
+    let render = |single_precision: bool| {
+        let options = RenderOptions { single_precision, ..options(5000) };
+        colorize(&render::compute_escape(&Mandelbrot, size, size, x_range, y_range, &options))
+    };
+    let (f32, f64) = (render(true), render(false));
+    let differing = f32
+        .pixels()
+        .zip(f64.pixels())
+        .filter(|(a, b)| a.0.iter().zip(b.0).any(|(&a, b)| a.abs_diff(b) > 1))
+        .count();
+    let pixels = (size * size) as usize;
+    assert!(differing * 100 < pixels * 3, "{} pixels differ", differing);
+}
+
+/// Frames that reuse the samples of the one before, frame after frame of a
+/// zoom, stay close to the frames computed in full. Reused samples only
+/// ever differ where a thin filament escapes between samples that didn't,
+/// so a few pixels in a thousand at most are off, and the frame as a whole
+/// hardly moves.
+#[test]
+fn reused_frames_stay_close_to_full_renders() {
+    let (center, size, zoom) = ((-0.7436, 0.1318), 96, 1.05f64);
+    let options = options(500);
+    let render = |frame: i32, options: &RenderOptions| {
+        let half = 0.02 / zoom.powi(frame);
+        let x_range = (center.0 - half, center.0 + half);
+        let y_range = (center.1 - half, center.1 + half);
+        render::compute_escape(&Mandelbrot, size, size, x_range, y_range, options)
+    };
+    let mut previous = render(0, &options);
+    for frame in 1..=10 {
+        let reuse = Reuse {
+            previous: &previous,
+            scale: 1.0 / zoom,
+            rotation: Rotation::NONE,
+            threshold: 0.5,
+            raised_limit: false,
+            shift: None,
+        };
+        let reused = render(frame, &RenderOptions { reuse: Some(reuse), ..options });
+        let (a, b) = (colorize(&reused), colorize(&render(frame, &options)));
+        let differences: Vec<u8> = a
+            .pixels()
+            .zip(b.pixels())
+            .map(|(a, b)| a.0.iter().zip(b.0).map(|(&a, b)| a.abs_diff(b)).max().unwrap())
+            .collect();
+        let differing = differences.iter().filter(|&&difference| difference > 0).count();
+        let mean = differences.iter().map(|&d| d as f64).sum::<f64>() / differences.len() as f64;
+        assert!(differing * 1000 <= differences.len(), "{} pixels differ", differing);
+        assert!(mean < 0.25, "the colors are {} apart on average", mean);
+        previous = reused;
+    }
+}
+
+/// Stripe averages iterated by perturbation are the ones iterated in f64,
+/// on a frame shallow enough for both to be accurate, but for the few
+/// orbits near the boundary that tell the rounding of either apart.
+#[test]
+fn perturbed_stripes_agree_with_f64() {
+    let (center, max_iter, bailout) = ((-0.7436, 0.1318), 2000, 1000.0);
+    let big = (bigfloat::from_f64(center.0, 128), bigfloat::from_f64(center.1, 128));
+    let orbit = Mandelbrot.reference_orbit(&big, 128, max_iter, bailout);
+    let (mut escaped, mut differing) = (0, 0);
+    for i in 0..32 * 32 {
+        let dc = (1e-3 * ((i % 32) as f64 - 16.0), 1e-3 * ((i / 32) as f64 - 16.0));
+        let c = (center.0 + dc.0, center.1 + dc.1);
+        let direct = Mandelbrot.stripes(c, max_iter, bailout, 5.0);
+        let perturbed = Mandelbrot.stripes_perturbed(&orbit.z, dc, max_iter, bailout, 5.0);
+        escaped += direct.is_some() as usize;
+        differing += match (direct, perturbed) {
+            (Some(a), Some(b)) => (a - b).abs() > 1e-3,
+            (a, b) => a.is_some() != b.is_some(),
+        } as usize;
+    }
+    assert!(escaped > 100, "only {} points escaped", escaped);
+    assert!(differing * 100 < escaped * 3, "{} of {} points differ", differing, escaped);
+}
+
+/// Supersampled pixels average their samples in linear light, so half
+/// black and half white is the lighter sRGB gray, not the middle value.
+#[test]
+fn samples_average_in_linear_light() {
+    let values = vec![Sample::Boundary, Sample::Interior, Sample::Interior, Sample::Boundary];
+    let img = colorize(&buffer(2, 2, 2, values));
+    assert_eq!(img.dimensions(), (1, 1));
+    for channel in img.get_pixel(0, 0).0 {
+        assert!((186..=189).contains(&channel), "{}", channel);
+    }
+}
+
+/// Red and green mix to the bright yellow of half of each in linear light,
+/// whether they're samples of one pixel or stops of a gradient. Mixing the
+/// sRGB channels, as `--legacy-gamma` does for gradients, gives a dark one.
+#[test]
+fn red_and_green_mix_in_linear_light() {
+    let stops = [
+        Stop {
+            color: colorgrad::Color::new(1.0, 0.0, 0.0, 1.0),
+            position: 0.0,
+        },
+        Stop {
+            color: colorgrad::Color::new(0.0, 1.0, 0.0, 1.0),
+            position: 1.0,
+        },
+    ];
+    // Positions 0 and 0.5 of the mirrored gradient are red and green, and
+    // 0.25 is halfway between them.
+    let cycle = Cycle {
+        cycles: 1.0,
+        offset: 0.0,
+        reverse: false,
+        mirror: true,
+    };
+    let (red, green, between) = (Sample::Value(0.0), Sample::Value(50.0), Sample::Value(25.0));
+    let mix = |blending: Blending, samples: u32, values: Vec<Sample>| {
+        let gradient = palette::custom_gradient(&stops, blending);
+        let colormap = Colormap::new(&gradient, &Adjust::default());
+        let colors = ColorOptions {
+            cycle,
+            ..colors(&colormap)
+        };
+        let img = render::colorize(&buffer(samples, samples, samples, values), &colors);
+        img.to_rgb8().get_pixel(0, 0).0
+    };
+    // The sRGB channel of half the intensity.
+    let half = (255.0 * (1.055 * 0.5f64.powf(1.0 / 2.4) - 0.055)).round() as u8;
+    assert_eq!(half, 188);
+
+    for blending in [Blending::Linear, Blending::Srgb] {
+        let averaged = mix(blending, 2, vec![red, green, green, red]);
+        assert_eq!(averaged, [half, half, 0], "{:?}", blending);
+    }
+    assert_eq!(mix(Blending::Linear, 1, vec![between]), [half, half, 0]);
+    assert_eq!(mix(Blending::Srgb, 1, vec![between]), [128, 128, 0]);
+}
+
+/// A palette looped over frames goes round once: the frame after the last
+/// is colored as the first, so the loop has no seam, and halfway through
+/// every value has the color of the one half the gradient on.
+#[test]
+fn looped_palette_comes_back_round() {
+    let gradient = Palette::Sinebow.gradient();
+    let colormap = Colormap::new(&gradient, &Adjust::default());
+    let values = (0..100).map(|i| Sample::Value(i as f64)).collect();
+    let buffer = buffer(100, 1, 1, values);
+    let cycle = Cycle::new(&gradient, 1.0, 0.0, false);
+    let frame = |frame: u32| {
+        let colors = ColorOptions {
+            cycle: cycle.looped(frame, 8),
+            ..colors(&colormap)
+        };
+        render::colorize(&buffer, &colors).to_rgb8()
+    };
+    let first = frame(0);
+    assert_eq!(frame(8), first);
+    assert_ne!(frame(1), first);
+    let half = frame(4);
+    for x in 0..50 {
+        assert_eq!(half.get_pixel(x, 0), first.get_pixel(x + 50, 0), "{}", x);
+    }
+}
+
+/// Gradients whose ends don't match are mirrored, so colors just below and
+/// at every wrap of the cycle, and anywhere else a small step apart, are
+/// close, however the cycles are shifted and turned. Wrapping the same
+/// gradient without the mirror shows the seam.
+#[test]
+fn palette_wraps_without_a_seam() {
+    let stops = palette::parse_stops("red@0,blue@1").unwrap();
+    let gradient = palette::custom_gradient(&stops, Blending::Linear);
+    let colormap = Colormap::new(&gradient, &Adjust::default());
+    let color = |cycle: &Cycle, position: f64| colormap.at(cycle.parameter(position)).to_rgba8();
+    let apart = |a: [u8; 4], b: [u8; 4]| a.iter().zip(b).map(|(a, b)| a.abs_diff(b)).max();
+    let epsilon = 1e-9;
+    for cycles in [1.0, 2.5, 4.0] {
+        for offset in [0.0, 0.3, -0.75] {
+            for reverse in [false, true] {
+                let cycle = Cycle::new(&gradient, cycles, offset, reverse);
+                assert!(cycle.mirror);
+                let wraps = (0..=(cycles + 1.0) as i32).map(|k| (k as f64 - offset) / cycles);
+                for wrap in wraps.filter(|wrap| (0.0..=1.0).contains(wrap)) {
+                    let (below, at) = (color(&cycle, wrap - epsilon), color(&cycle, wrap));
+                    assert!(apart(below, at) <= Some(1), "{} {} {}", cycles, offset, wrap);
+                }
+                let steps = 20_000;
+                for step in 0..steps {
+                    let (a, b) = (step as f64 / steps as f64, (step + 1) as f64 / steps as f64);
+                    assert!(apart(color(&cycle, a), color(&cycle, b)) <= Some(2), "{}", a);
+                }
+            }
+        }
+    }
+    let seamed = Cycle { mirror: false, ..Cycle::new(&gradient, 1.0, 0.0, false) };
+    assert_eq!(color(&seamed, 1.0 - epsilon), [0, 0, 255, 255]);
+    assert_eq!(color(&seamed, 0.0), [255, 0, 0, 255]);
+}
+
+/// On a radial field of values, the value of every sample its distance
+/// from the middle, the contours are the circles of the multiples of their
+/// spacing, anti-aliased by how far each sample is from them. The line
+/// around the set hugs a disc of interior from the outside.
+#[test]
+fn contours_run_along_the_level_sets() {
+    let size = 64;
+    let radius = |index: u32| {
+        let (x, y) = ((index % size) as f64 - 31.5, (index / size) as f64 - 31.5);
+        x.hypot(y)
+    };
+    // How far a sample is from the nearest circle, but that of 0, which
+    // only the middle reaches.
+    let off = |index: u32| {
+        let radius = radius(index);
+        (radius - (radius / 10.0).round().max(1.0) * 10.0).abs()
+    };
+    for width in [1.0, 2.0] {
+        let values = (0..size * size).map(|index| Sample::Value(radius(index))).collect();
+        let spec = format!("every=10,width={}", width);
+        let coverage = Contours::from_spec(&spec).unwrap().coverage(&buffer(size, size, 1, values));
+        // Circles that run off the edge are left out, as the samples across
+        // them are.
+        for index in (0..size * size).filter(|&index| radius(index) < 30.0) {
+            let (coverage, off) = (coverage[index as usize], off(index));
+            let expected = (width / 2.0 + 0.5 - off).clamp(0.0, 1.0);
+            match expected {
+                0.0 => assert_eq!(coverage, 0.0, "{} off at width {}", off, width),
+                _ => assert!(
+                    (coverage - expected).abs() < 0.15,
+                    "{} for {} off at width {}",
+                    coverage,
+                    off,
+                    width
+                ),
+            }
+        }
+    }
+
+    let values = (0..size * size)
+        .map(|index| match radius(index) < 10.0 {
+            true => Sample::Interior,
+            false => Sample::Value(radius(index)),
+        })
+        .collect();
+    let silhouette = Line::silhouette_from_spec("width=1,color=#ff0000").unwrap();
+    assert_eq!(silhouette.color, (255, 0, 0));
+    let coverage = silhouette.silhouette(&buffer(size, size, 1, values));
+    for (index, coverage) in (0..size * size).zip(coverage) {
+        match radius(index) {
+            r if !(10.0..=11.5).contains(&r) => assert_eq!(coverage, 0.0, "at {}", r),
+            r if r < 10.5 => assert!(coverage > 0.9, "{} at {}", coverage, r),
+            _ => {}
+        }
+    }
+
+    for (spec, error) in [
+        ("every=0", "every should be positive"),
+        ("width=-1", "width should be positive"),
+        ("color=#12", "color"),
+        ("spacing=3", "unknown contours field"),
+    ] {
+        let message = Contours::from_spec(spec).unwrap_err();
+        assert!(message.contains(error), "{}", message);
+    }
+}
+
+/// Line art inks the samples either side of a step in the escape field,
+/// and with a silhouette those just outside the set. Thinned, the line is
+/// a sample wide, and a weight widens it by as many pixels either side.
+/// Between the cycles of the interior, one side of the edge is inked.
+#[test]
+fn line_art_inks_the_edges_of_the_field() {
+    let (width, height) = (32, 16);
+    // Values stepping from 1 to 100 between the columns 15 and 16, with a
+    // square of the set to the left of the step.
+    let values: Vec<Sample> = (0..width * height)
+        .map(|index| match (index % width, index / width) {
+            (2..=5, 5..=10) => Sample::Interior,
+            (x, _) if x < 16 => Sample::Value(1.0),
+            _ => Sample::Value(100.0),
+        })
+        .collect();
+    let buffer = buffer(width, height, 1, values);
+    let columns = |lines: LineArt| {
+        let ink = lines.ink(&buffer);
+        let inked = |x: u32, y: u32| ink[(y * width + x) as usize];
+        (0..width).map(|x| (0..height).filter(|&y| inked(x, y)).count()).collect::<Vec<_>>()
+    };
+    let lines = LineArt::default();
+    let step: Vec<usize> = (0..width).map(|x| [0, 16][(15..=16).contains(&x) as usize]).collect();
+    assert_eq!(columns(lines), step);
+
+    let outlined = columns(LineArt {
+        silhouette: true,
+        ..lines
+    });
+    // Above and below the square, and the columns either side of it.
+    assert_eq!(outlined[1..=6], [6, 2, 2, 2, 2, 6]);
+    assert_eq!(outlined[7..], step[7..]);
+
+    let thinned = LineArt {
+        thin: true,
+        ..lines
+    };
+    let ink = thinned.ink(&buffer);
+    for y in 1..height - 1 {
+        let row = &ink[(y * width) as usize..((y + 1) * width) as usize];
+        let inked: Vec<usize> = (0..width as usize).filter(|&x| row[x]).collect();
+        assert!(inked.len() == 1 && (15..=16).contains(&inked[0]), "{:?} in row {}", inked, y);
+    }
+    let widened = columns(LineArt { weight: 2, ..lines });
+    let wide: Vec<usize> = (0..width).map(|x| [0, 16][(13..=18).contains(&x) as usize]).collect();
+    assert_eq!(widened, wide);
+
+    // Cycles of period 1 to the left and 2 to the right, ringed by escaping
+    // points.
+    let values = (0..width * height)
+        .map(|index| match (index % width, index / width) {
+            (0 | 31, _) | (_, 0 | 15) => Sample::Value(10.0),
+            (x, _) => Sample::Attractor {
+                period: 1 + (x >= 16) as u32,
+                derivative: 0.5,
+            },
+        })
+        .collect();
+    let interior = LineArt {
+        interior: true,
+        ..lines
+    };
+    let ink = interior.ink(&EscapeBuffer { values, ..buffer });
+    let inked: Vec<u32> = (0..width * height).filter(|&index| ink[index as usize]).collect();
+    assert_eq!(inked, (1..15).map(|y| y * width + 15).collect::<Vec<_>>());
+
+    assert_eq!(lines.rgba(true), [0.0, 0.0, 0.0, 1.0]);
+    assert_eq!(lines.rgba(false), [1.0; 4]);
+    let transparent = LineArt {
+        paper: Paper::Transparent,
+        ..lines
+    };
+    assert_eq!(transparent.rgba(true), [1.0; 4]);
+    assert_eq!(transparent.rgba(false), [1.0, 1.0, 1.0, 0.0]);
+}
+
+/// `--alpha` makes the set transparent, and with a threshold the points
+/// escaping before it, without changing the colors of the rest, even with
+/// the palette inverted. Pixels partly in the set are as opaque as the
+/// share of their samples outside it, in the color of those.
+#[test]
+fn alpha_leaves_the_colors_alone() {
+    let gradient = Palette::Sinebow.gradient();
+    let colorize = |adjust: &Adjust, alpha: Alpha, samples: u32, values: Vec<Sample>| {
+        let colormap = Colormap::new(&gradient, adjust);
+        let colors = ColorOptions {
+            alpha,
+            ..colors(&colormap)
+        };
+        let width = values.len() as u32 / samples;
+        render::colorize(&buffer(width, samples, samples, values), &colors)
+    };
+    let (outside, threshold) = (Sample::Value(10.0), Alpha::Threshold(5.0));
+    for invert in [false, true] {
+        let adjust = Adjust {
+            invert,
+            ..Adjust::default()
+        };
+        let opaque = colorize(&adjust, Alpha::None, 1, vec![outside]).to_rgb8();
+        let img = colorize(&adjust, Alpha::Interior, 1, vec![Sample::Interior, outside]);
+        let image::DynamicImage::ImageRgba8(img) = img else {
+            panic!("frames with alpha are RGBA");
+        };
+        assert_eq!(img.get_pixel(0, 0).0[3], 0);
+        let [r, g, b, a] = img.get_pixel(1, 0).0;
+        assert_eq!(([r, g, b], a), (opaque.get_pixel(0, 0).0, 255));
+
+        let values = vec![Sample::Interior, outside, outside, Sample::Interior];
+        let img = colorize(&adjust, Alpha::Interior, 2, values).to_rgba8();
+        let [r, g, b, a] = img.get_pixel(0, 0).0;
+        assert_eq!(([r, g, b], a), (opaque.get_pixel(0, 0).0, 128));
+    }
+    let values = vec![Sample::Value(2.0), Sample::Value(5.5), Sample::Value(8.0)];
+    let img = colorize(&Adjust::default(), threshold, 1, values).to_rgba8();
+    let alphas: Vec<u8> = img.pixels().map(|pixel| pixel.0[3]).collect();
+    assert_eq!(alphas, [0, 128, 255]);
+}
+
+/// Adaptive anti-aliasing refines the pixels on either side of an edge and
+/// nothing else, giving every one of them the extra samples.
+#[test]
+fn refine_only_takes_the_edge() {
+    let adaptive = Adaptive {
+        threshold: 0.1,
+        samples: 3,
+    };
+    let gradient = Palette::Sinebow.gradient();
+    let colormap = Colormap::new(&gradient, &Adjust::default());
+    let colors = colors(&colormap);
+    let refine = |values: Vec<Sample>| {
+        let mut buffer = buffer(6, 1, 1, values.clone());
+        let passes = RefCell::new(Vec::new());
+        let fraction = render::refine(&mut buffer, &colors, &adaptive, |refine| {
+            passes.borrow_mut().push(refine.pixels.to_vec());
+            let refined: Vec<Sample> =
+                (0..6).filter(|&x| refine.pixels[x]).map(|x| values[x]).collect();
+            self::buffer(refined.len() as u32, 1, 1, refined)
+        });
+        (fraction, buffer, passes.into_inner())
+    };
+
+    let (fraction, buffer, passes) = refine(vec![Sample::Interior; 6]);
+    assert_eq!(fraction, 0.0);
+    assert!(buffer.refined.is_empty() && passes.is_empty());
+
+    let mut edge = vec![Sample::Interior; 3];
+    edge.extend([Sample::Boundary; 3]);
+    let (fraction, buffer, passes) = refine(edge);
+    assert_eq!(fraction, 2.0 / 6.0);
+    assert_eq!(passes[0], [false, false, true, true, false, false]);
+    let mut refined: Vec<_> = buffer.refined.keys().copied().collect();
+    refined.sort();
+    assert_eq!(refined, [2, 3]);
+}
+
+#[test]
+fn fit_keeps_the_chosen_extent() {
+    let fit = |fit| view::fit((-2.0, 2.0), (-2.0, 2.0), 200, 100, fit);
+    assert_eq!(fit(Fit::Width), ((-2.0, 2.0), (-1.0, 1.0)));
+    assert_eq!(fit(Fit::Height), ((-4.0, 4.0), (-2.0, 2.0)));
+    assert_eq!(fit(Fit::Cover), ((-2.0, 2.0), (-1.0, 1.0)));
+    assert_eq!(fit(Fit::Contain), ((-4.0, 4.0), (-2.0, 2.0)));
+}
+
+/// A wide frame of a square view keeps its pixels square: the main
+/// cardioid, 1 across on the real axis and 3√3/4 high where it's highest,
+/// measures as many pixels as those take, and as many above the axis as
+/// below.
+#[test]
+fn wide_frames_dont_stretch_the_cardioid() {
+    let (width, height) = (64, 16);
+    let (x_range, y_range) = view::fit((-1.0, 0.5), (-0.75, 0.75), width, height, Fit::Contain);
+    let pixel = (y_range.1 - y_range.0) / height as f64;
+    assert_eq!((x_range.1 - x_range.0) / width as f64, pixel);
+    let options = options(500);
+    let buffer = render::compute_escape(&Mandelbrot, width, height, x_range, y_range, &options);
+    let interior = |x: u32, y: u32| buffer.values[(y * width + x) as usize] == Sample::Interior;
+    // Pixels are sampled at their top left corners, so the middle row is on
+    // the axis. Along it, from the cusp with the bulb at -0.75 on, and down
+    // the column through -0.375, where the cardioid is highest.
+    let sample = |x: f64| ((x - x_range.0) / pixel).ceil() as u32;
+    let across = (sample(-0.75)..width).filter(|&x| interior(x, height / 2)).count();
+    let rows: Vec<u32> = (0..height).filter(|&y| interior(sample(-0.375), y)).collect();
+    assert!((across as f64 - 1.0 / pixel).abs() <= 1.0, "{} columns", across);
+    let tall = 3.0 * 3f64.sqrt() / 4.0;
+    assert!((rows.len() as f64 - tall / pixel).abs() <= 1.0, "{} rows", rows.len());
+    assert_eq!(rows[0] + rows[rows.len() - 1], height, "{:?}", rows);
+}
+
+#[test]
+fn corners_read_as_ranges_running_up() {
+    let corners = Corners::from_spec("-2, 1,-1,1").unwrap();
+    assert_eq!(corners.x_range, (-2.0, 1.0));
+    assert_eq!(corners.y_range, (-1.0, 1.0));
+    assert_eq!(corners.center(), (-0.5, 0.0));
+    assert_eq!(Corners::from_spec(&corners.to_string()), Ok(corners));
+
+    let error = |spec| Corners::from_spec(spec).unwrap_err();
+    assert!(error("-2,1,-1").contains("got 3 of them"), "{}", error("-2,1,-1"));
+    assert!(error("-2,1,-1,x").contains("four numbers"), "{}", error("-2,1,-1,x"));
+    assert!(error("-2,1,-1,inf").contains("four numbers"), "{}", error("-2,1,-1,inf"));
+    assert!(error("1,-2,-1,1").contains("backwards along x"), "{}", error("1,-2,-1,1"));
+    assert!(error("-2,1,1,-1").contains("backwards along y"), "{}", error("-2,1,1,-1"));
+    assert!(error("-2,1,0.5,0.5").contains("no extent along y"), "{}", error("-2,1,0.5,0.5"));
+}
+
+/// Corners shaped like the frame show as they are under every fit, and
+/// others are widened or cut about their middle along the side the fit
+/// gives up.
+#[test]
+fn corners_are_fitted_about_their_middle() {
+    let corners = Corners::from_spec("-2,1,-1,1").unwrap();
+    assert!(corners.fits(300, 200));
+    assert!(corners.fits(3000, 2000));
+    for fit in [Fit::Width, Fit::Height, Fit::Cover, Fit::Contain] {
+        assert_eq!(corners.fitted(300, 200, fit), ((-2.0, 1.0), (-1.0, 1.0)));
+    }
+
+    // A square frame: the view is 3 wide and 2 high.
+    assert!(!corners.fits(200, 200));
+    let fitted = |fit| corners.fitted(200, 200, fit);
+    assert_eq!(fitted(Fit::Contain), ((-2.0, 1.0), (-1.5, 1.5)));
+    assert_eq!(fitted(Fit::Width), ((-2.0, 1.0), (-1.5, 1.5)));
+    assert_eq!(fitted(Fit::Cover), ((-1.5, 0.5), (-1.0, 1.0)));
+    assert_eq!(fitted(Fit::Height), ((-1.5, 0.5), (-1.0, 1.0)));
+    for fit in [Fit::Width, Fit::Height, Fit::Cover, Fit::Contain] {
+        let (x_range, y_range) = fitted(fit);
+        let center = ((x_range.0 + x_range.1) / 2.0, (y_range.0 + y_range.1) / 2.0);
+        assert_eq!(center, corners.center());
+        assert!(Corners { x_range, y_range }.fits(200, 200));
+    }
+}
+
+/// Dithering a slow gradient turns the bands 8-bit rounding leaves into
+/// noise: the rounding error of neighboring pixels stops being alike, if
+/// anything alternating, and averages out over small areas instead of over
+/// whole bands.
+/// A gradient written out as an image strip, with an alpha channel that is
+/// ignored, reads back into the same colors, interpolated between the
+/// pixels of the strip.
+#[test]
+fn palette_strips_round_trip() {
+    let gradient = Palette::Viridis.gradient();
+    let strip = image::RgbaImage::from_fn(64, 3, |x, y| {
+        let [r, g, b, _] = gradient.at(x as f64 / 63.0).to_rgba8();
+        image::Rgba([r, g, b, 80 * y as u8])
+    });
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("viridis_strip.png");
+    strip.save(&path).unwrap();
+    let stops = palette::strip_stops(&image::open(&path).unwrap().to_rgb8()).unwrap();
+    assert_eq!(stops.len(), 64);
+    let loaded = palette::custom_gradient(&stops, Blending::Srgb);
+    let (expected, actual) = (
+        Colormap::new(&gradient, &Adjust::default()),
+        Colormap::new(&loaded, &Adjust::default()),
+    );
+    for t in [0.0, 0.1, 0.25, 0.5, 0.77, 0.9, 1.0] {
+        let (a, b) = (expected.at(t).to_rgba8(), actual.at(t).to_rgba8());
+        assert!(a.iter().zip(b).all(|(&a, b)| a.abs_diff(b) <= 2), "{}: {:?} {:?}", t, a, b);
+    }
+    // Strips given as a column read from the top down.
+    let column = image::imageops::rotate90(&image::open(&path).unwrap().to_rgb8());
+    let down = palette::strip_stops(&column).unwrap();
+    assert!(down.iter().zip(&stops).all(|(a, b)| a.color == b.color));
+    let dot = image::RgbImage::new(1, 1);
+    assert!(palette::strip_stops(&dot).unwrap_err().contains("at least two pixels"));
+}
+
+/// A value at the start of the gradient takes the color of its first stop
+/// as adjusted: turned round the hue, desaturated, brightened by gamma and
+/// inverted, one at a time and together.
+#[test]
+fn adjusted_palettes_color_a_value_as_expected() {
+    let cycle = Cycle {
+        cycles: 1.0,
+        offset: 0.0,
+        reverse: false,
+        mirror: false,
+    };
+    let color = |first: &str, adjust: Adjust| {
+        let stops = palette::parse_stops(&format!("{}@0,white@1", first)).unwrap();
+        let gradient = palette::custom_gradient(&stops, Blending::Linear);
+        let colormap = Colormap::new(&gradient, &adjust);
+        let colors = ColorOptions {
+            cycle,
+            ..colors(&colormap)
+        };
+        let img = render::colorize(&buffer(1, 1, 1, vec![Sample::Value(0.0)]), &colors);
+        img.to_rgb8().get_pixel(0, 0).0
+    };
+    let adjust = Adjust::default();
+    assert_eq!(color("red", adjust), [255, 0, 0]);
+    assert_eq!(color("red", Adjust { invert: true, ..adjust }), [0, 255, 255]);
+    assert_eq!(color("red", Adjust { hue_shift: 120.0, ..adjust }), [0, 255, 0]);
+    assert_eq!(color("red", Adjust { hue_shift: -120.0, ..adjust }), [0, 0, 255]);
+    assert_eq!(color("red", Adjust { saturation: 0.5, ..adjust }), [255, 128, 128]);
+    assert_eq!(color("red", Adjust { saturation: 0.0, ..adjust }), [255, 255, 255]);
+    // 0x40 is a quarter of 255, whose square root is a half and whose
+    // square is a sixteenth.
+    assert_eq!(color("#400000", Adjust { gamma: 2.0, ..adjust }), [128, 0, 0]);
+    assert_eq!(color("#400000", Adjust { gamma: 0.5, ..adjust }), [16, 0, 0]);
+    let all = Adjust {
+        invert: true,
+        hue_shift: 240.0,
+        saturation: 1.0,
+        gamma: 2.0,
+    };
+    assert_eq!(color("#400000", all), [255, 255, 127]);
+}
+
+/// The lookup table of every built-in palette colors within one step of
+/// 8-bit output of its gradient, inverted or not.
+#[test]
+fn colormaps_match_their_gradients() {
+    for invert in [false, true] {
+        let adjust = Adjust {
+            invert,
+            ..Adjust::default()
+        };
+        for palette in Palette::ALL {
+            let gradient = palette.gradient();
+            let colormap = Colormap::new(&gradient, &adjust);
+            assert_eq!(colormap.entries(), palette::TABLE_SIZE);
+            for t in (0..=10_000).map(|i| i as f64 / 10_000.0) {
+                let (a, b) = (colormap.at(t).to_rgba8(), adjust.apply(gradient.at(t)).to_rgba8());
+                let off = a.iter().zip(b).map(|(&a, b)| a.abs_diff(b)).max().unwrap();
+                assert!(off <= 1, "{} at {}: {:?} {:?}", palette.name(), t, a, b);
+            }
+        }
+    }
+    // Coarser tables still end on the ends of the gradient.
+    let gradient = Palette::Sinebow.gradient();
+    let coarse = Colormap::with_entries(&gradient, &Adjust::default(), palette::TABLE_SIZES.0);
+    for t in [0.0, 1.0] {
+        assert_eq!(coarse.at(t).to_rgba8(), gradient.at(t).to_rgba8());
+    }
+}
+
+/// A ragged `.map` file is fixed up with a warning for each problem, and
+/// its entries either hold over their share of the gradient or blend.
+#[test]
+fn palette_maps_step_or_blend_their_entries() {
+    let maps = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/maps");
+    let read = |name: &str| std::fs::read_to_string(maps.join(name)).unwrap();
+    let (vga, warnings) = palette::parse_map(&read("vga.map")).unwrap();
+    assert_eq!(vga.len(), palette::MAP_ENTRIES);
+    assert!(warnings.is_empty(), "{:?}", warnings);
+    assert_eq!(vga[1].to_rgba8(), [0, 0, 168, 255]);
+
+    let (ragged, warnings) = palette::parse_map(&read("ragged.map")).unwrap();
+    let rgb: Vec<[u8; 4]> = ragged.iter().map(|color| color.to_rgba8()).collect();
+    let expected = [[0, 0, 40], [20, 60, 255], [0, 140, 255], [255, 220, 120]];
+    assert_eq!(rgb, expected.map(|[r, g, b]| [r, g, b, 255]));
+    assert_eq!(warnings.len(), 2, "{:?}", warnings);
+    assert!(warnings[0].starts_with("2 channels") && warnings[0].ends_with("line 3"));
+    assert!(warnings[1].contains("only 4 of 256"));
+
+    let at = |interpolate: bool, t: f64| {
+        let stops = palette::map_stops(&ragged, interpolate);
+        let gradient = palette::custom_gradient(&stops, Blending::Linear);
+        Colormap::new(&gradient, &Adjust::default()).at(t).to_rgba8()
+    };
+    // Stepping holds the second entry over the second quarter.
+    assert_eq!(at(false, 0.3), rgb[1]);
+    assert_eq!(at(false, 0.45), rgb[1]);
+    assert_eq!(at(false, 1.0), rgb[3]);
+    // Blending puts the entries at thirds, with mixes between.
+    assert_eq!(at(true, 1.0 / 3.0), rgb[1]);
+    assert!(![rgb[1], rgb[2]].contains(&at(true, 0.5)));
+
+    let long = "1 2 3\n".repeat(300);
+    assert!(palette::parse_map(&long).unwrap().1[0].contains("44 entries past"));
+    let invalid = palette::parse_map("0 0 0\n0 zero 0\n").unwrap_err();
+    assert!(invalid.starts_with("line 2"), "{}", invalid);
+    assert!(palette::parse_map("; nothing\n1 2 3\n").unwrap_err().contains("two entries"));
+}
+
+#[test]
+fn dither_whitens_the_rounding_error() {
+    let (width, height) = (256, 64);
+    let gray = |level: f64| colorgrad::Color::new(level, level, level, 1.0);
+    let stops = [
+        Stop {
+            color: gray(0.4),
+            position: 0.0,
+        },
+        Stop {
+            color: gray(0.42),
+            position: 1.0,
+        },
+    ];
+    let gradient = palette::custom_gradient(&stops, Blending::Linear);
+    let colormap = Colormap::new(&gradient, &Adjust::default());
+    let values = (0..width * height).map(|i| Sample::Value((i % width) as f64)).collect();
+    let buffer = buffer(width, height, 1, values);
+    let error = |dither: Dither| -> Vec<f64> {
+        let colors = ColorOptions {
+            palette_iter: width,
+            cycle: Cycle {
+                cycles: 1.0,
+                offset: 0.0,
+                reverse: false,
+                mirror: false,
+            },
+            dither,
+            ..colors(&colormap)
+        };
+        let image = render::colorize(&buffer, &colors).to_rgb8();
+        let ideal = |x: u32| colormap.at(x as f64 / width as f64).r * 255.0;
+        image.enumerate_pixels().map(|(x, _, pixel)| pixel[0] as f64 - ideal(x)).collect()
+    };
+    // The correlation of each error with the one to its right.
+    let correlation = |error: &[f64]| {
+        let mean = error.iter().sum::<f64>() / error.len() as f64;
+        let (mut product, mut square) = (0.0, 0.0);
+        for row in error.chunks(width as usize) {
+            for pair in row.windows(2) {
+                product += (pair[0] - mean) * (pair[1] - mean);
+                square += (pair[0] - mean).powi(2);
+            }
+        }
+        product / square
+    };
+    // The root mean square of the mean error over 16×16 blocks.
+    let blocks = |error: &[f64]| {
+        let mut sums = vec![0.0; (width / 16 * height / 16) as usize];
+        for (i, error) in error.iter().enumerate() {
+            let (x, y) = (i as u32 % width, i as u32 / width);
+            sums[(y / 16 * width / 16 + x / 16) as usize] += error / 256.0;
+        }
+        (sums.iter().map(|mean| mean * mean).sum::<f64>() / sums.len() as f64).sqrt()
+    };
+
+    let banded = error(Dither::None);
+    assert!(correlation(&banded) > 0.8, "{}", correlation(&banded));
+    assert!(blocks(&banded) > 0.15, "{}", blocks(&banded));
+    for dither in [Dither::Ordered, Dither::BlueNoise] {
+        let error = error(dither);
+        assert!(error.iter().all(|error| error.abs() < 1.0), "{:?}", dither);
+        assert!(correlation(&error) < 0.3, "{:?}: {}", dither, correlation(&error));
+        assert!(blocks(&error) < 0.05, "{:?}: {}", dither, blocks(&error));
+    }
+}
+
+/// A time budget scales the limits up to fill it, and back down once the
+/// frames take longer than predicted.
+#[test]
+fn time_budget_keeps_to_the_time_left() {
+    let probe = |frame, max_iter, seconds| Probe {
+        frame,
+        max_iter,
+        samples: 1000,
+        seconds,
+    };
+    let probes = [probe(10, 400, 4.0), probe(0, 100, 1.0), probe(10, 100, 2.0), probe(0, 400, 2.0)];
+    let model = CostModel::new(&probes);
+    assert!((model.seconds(0, 1000, 700) - 3.0).abs() < 1e-9);
+    assert!((model.seconds(5, 2000, 400) - 6.0).abs() < 1e-9);
+
+    let frames: Vec<(u32, u32)> = (0..=10).map(|frame| (frame, 100)).collect();
+    let mut time_budget = TimeBudget::new(model.clone(), frames.clone(), 1000, 60.0);
+    let scale = time_budget.scale();
+    assert!(scale > 1.0);
+    assert!(time_budget.predicted_seconds() <= budget::MARGIN * 60.0);
+    assert!(model.total(&frames, 1000, scale * 1.01) > budget::MARGIN * 60.0);
+
+    let (mut elapsed, mut adjustment) = (0.0, None);
+    for frame in 0..4 {
+        let max_iter = time_budget.max_iter(100);
+        elapsed += 2.0 * model.seconds(frame, 1000, max_iter);
+        adjustment = adjustment.or(time_budget.written(frame, max_iter, elapsed));
+    }
+    let adjustment = adjustment.unwrap();
+    assert!((adjustment.ratio - 2.0).abs() < 1e-9);
+    assert!(adjustment.scale < scale);
+    assert_eq!(time_budget.schedule().len(), 7);
+    assert!(2.0 * time_budget.predicted_seconds() <= budget::MARGIN * (60.0 - elapsed) + 1e-9);
+}
+
+/// The preflight estimate takes the escape loop's time from the probes, and
+/// leaves the rest of every frame's time to what the budget margin does.
+#[test]
+fn preflight_estimates_time_and_room() {
+    let probe = |frame, max_iter, seconds| Probe {
+        frame,
+        max_iter,
+        samples: 1000,
+        seconds,
+    };
+    let probes = [probe(0, 100, 1.0), probe(0, 400, 2.0), probe(10, 100, 2.0), probe(10, 400, 4.0)];
+    let model = CostModel::new(&probes);
+    let frames: Vec<(u32, u32)> = (0..=10).map(|frame| (frame, 100)).collect();
+    let times = Times::new(&model, &frames, 2000);
+    assert!((times.first - 2.0 / budget::MARGIN).abs() < 1e-9, "{:?}", times);
+    assert!((times.last - 4.0 / budget::MARGIN).abs() < 1e-9, "{:?}", times);
+    assert!((times.total - 33.0 / budget::MARGIN).abs() < 1e-9, "{:?}", times);
+    assert_eq!(Times::new(&model, &[], 2000).total, 0.0);
+
+    let footprint = Footprint {
+        frame: 0.9,
+        video: 0.05,
+    };
+    let estimate = Estimate::new(11, Some(times), 1920 * 1080, footprint);
+    assert_eq!(estimate.frame_bytes, 20_528_640);
+    assert_eq!(estimate.video_bytes, 1_140_480);
+    assert_eq!(estimate.bytes(), 21_669_120);
+    assert_eq!(preflight::format_bytes(estimate.bytes()), "20.7 MiB");
+    assert_eq!(preflight::format_bytes(1023), "1023 bytes");
+    assert_eq!(preflight::format_bytes(3 << 40), "3.0 TiB");
+}
+
+#[test]
+fn preflight_finds_runs_that_cant_work() {
+    let run = |zoom_factor, animated, bytes, free_bytes| {
+        preflight::check(&preflight::Run {
+            zoom_factor,
+            animated,
+            bytes,
+            free_bytes,
+        })
+    };
+    assert_eq!(run(1.1, false, 1000, Some(100_000)), vec![]);
+    assert_eq!(run(1.1, false, 1000, None), vec![]);
+    let full = run(1.1, false, 2000, Some(1000));
+    assert_eq!(full, vec![Finding::DiskFull { bytes: 2000, free_bytes: 1000 }]);
+    assert!(full[0].is_error());
+    let tight = run(0.9, false, 950, Some(1000));
+    assert_eq!(tight, vec![Finding::DiskTight { bytes: 950, free_bytes: 1000 }]);
+    assert!(!tight[0].is_error());
+    assert_eq!(run(1.0, false, 1000, None), vec![Finding::NoZoom]);
+    assert!(!Finding::NoZoom.is_error());
+    assert_eq!(run(1.0, true, 1000, None), vec![]);
+    let both = run(1.0, false, 2000, Some(1000));
+    assert_eq!(both.len(), 2);
+    assert!(both[0].is_error() && !both[1].is_error());
+}
+
+/// Tiles go row by row, each with its label under it, and shrink to fit
+/// the largest size asked for.
+#[test]
+fn grids_lay_out_and_shrink_their_tiles() {
+    let gap = grid::GAP;
+    let sheet = Grid::new(6, 3, (16, 24), 27, 9);
+    assert_eq!((sheet.columns, sheet.rows), (3, 2));
+    assert_eq!(sheet.size(), (gap + 3 * (16 + gap), gap + 9 + gap + 2 * (24 + 27 + gap)));
+    assert_eq!(sheet.size(), (64, 127));
+    assert_eq!(sheet.place(4), (1, 1));
+    assert_eq!(sheet.tile_at(1, 1), (24, 72));
+    assert_eq!(sheet.label_at(1, 1), (24, 97));
+
+    let montage = Grid::new(7, 4, (160, 90), 18, 0);
+    assert_eq!(montage.rows, 2);
+    assert_eq!(montage.tile_at(0, 0), (gap, gap));
+    assert_eq!(montage.shrunk_to((4096, 4096)), Some(montage));
+    let shrunk = montage.shrunk_to((400, 4096)).unwrap();
+    assert_eq!(shrunk.tile, (95, 53));
+    assert!(shrunk.size().0 <= 400);
+    let short = montage.shrunk_to((4096, 100)).unwrap();
+    assert!(short.size().1 <= 100, "{:?}", short);
+    assert_eq!(montage.shrunk_to((4096, 40)), None);
+
+    assert_eq!(grid::fit(1920, 1080, 160, 160), (160, 90));
+    assert_eq!(grid::fit(32, 32, 160, 160), (32, 32));
+    assert_eq!(grid::fit(1000, 1, 10, 10), (10, 1));
+}
+
+/// Downscaling averages light rather than sRGB values, so a pattern of
+/// black and white comes out at half the light, brighter than mid gray.
+#[test]
+fn downscaling_averages_in_linear_light() {
+    let checkers = image::RgbImage::from_fn(64, 32, |x, y| match (x + y) % 2 {
+        0 => image::Rgb([255, 255, 255]),
+        _ => image::Rgb([0, 0, 0]),
+    });
+    let small = grid::downscale(&checkers, 8, 4);
+    assert_eq!(small.dimensions(), (8, 4));
+    assert!(small.pixels().all(|pixel| pixel.0 == [188, 188, 188]), "{:?}", small.get_pixel(0, 0));
+    assert_eq!(grid::downscale(&checkers, 64, 32), checkers);
+    let flat = image::RgbImage::from_pixel(30, 20, image::Rgb([40, 120, 200]));
+    assert!(grid::downscale(&flat, 7, 5).pixels().all(|pixel| pixel.0 == [40, 120, 200]));
+}
+
+/// Samples inside in one render and not the other count against a pair
+/// like those whose escape times stray too far, and the heatmap shows each
+/// kind apart.
+#[test]
+fn differences_count_the_samples_past_the_threshold() {
+    let (value, interior) = (Sample::Value, Sample::Interior);
+    let first = buffer(4, 1, 1, vec![value(5.0), value(7.0), interior, interior]);
+    let second = buffer(4, 1, 1, vec![value(5.0), value(7.5), interior, value(99.0)]);
+    let difference = Difference::of(&first, &second, 0.1);
+    assert_eq!((difference.samples, difference.escaped, difference.disagreeing), (4, 2, 1));
+    assert_eq!((difference.max, difference.mean, difference.over), (0.5, 0.25, 2));
+    assert_eq!(difference.share_over(), 0.5);
+    assert_eq!(Difference::of(&first, &second, 1.0).over, 1);
+    let heatmap = difference::heatmap(&first, &second, 0.1);
+    let colors: Vec<[u8; 3]> = heatmap.pixels().map(|pixel| pixel.0).collect();
+    assert_eq!(colors, [[0, 0, 0], [172, 0, 0], [0, 0, 0], [255, 255, 255]]);
+}
+
+/// Box counting gives a line a dimension of 1 and a filled square one of
+/// 2, and leaves a handful of boundary samples unfitted.
+#[test]
+fn box_counting_measures_lines_and_squares() {
+    let size = 256;
+    let line: Vec<bool> = (0..size * size).map(|index| index / size == 100).collect();
+    let dimension = Dimension::measure(&line, size, size);
+    assert_eq!(dimension.boundary_samples, size);
+    assert!((dimension.estimate.unwrap() - 1.0).abs() < 0.02, "{:?}", dimension);
+    assert!(dimension.r_squared.unwrap() > 0.99, "{:?}", dimension);
+    let square = vec![true; size * size];
+    let dimension = Dimension::measure(&square, size, size);
+    assert!((dimension.estimate.unwrap() - 2.0).abs() < 0.02, "{:?}", dimension);
+
+    let sparse: Vec<bool> = (0..size * size).map(|index| index % 1500 == 0).collect();
+    let dimension = Dimension::measure(&sparse, size, size);
+    assert_eq!((dimension.boundary_samples, dimension.estimate), (44, None));
+
+    // The boundary of a half plane of interior is the line of samples
+    // outside it along its edge.
+    let values = (0..64 * 64)
+        .map(|index| match index % 64 < 20 {
+            true => Sample::Interior,
+            false => Sample::Value(10.0),
+        })
+        .collect();
+    let half = buffer(64, 64, 1, values);
+    let boundary = dimension::boundary(&half).unwrap();
+    assert!(boundary.iter().enumerate().all(|(index, &on)| on == (index % 64 == 20)));
+    let dimension = Dimension::of(&half).unwrap();
+    assert!((dimension.estimate.unwrap() - 1.0).abs() < 0.02, "{:?}", dimension);
+    let roots = vec![Sample::Root { root: 0, roots: 3, iterations: 4.0 }; 64 * 64];
+    assert_eq!(Dimension::of(&buffer(64, 64, 1, roots)), None);
+}
+
+/// Heights are the escape times of the pixels' samples through the
+/// transfer, averaged, with the set on top or at the base.
+#[test]
+fn heightfields_raise_escape_times() {
+    let values = vec![
+        Sample::Value(50.0),
+        Sample::Value(50.0),
+        Sample::Interior,
+        Sample::Interior,
+        Sample::Value(0.0),
+        Sample::Value(100.0),
+        Sample::Value(25.0),
+        Sample::Interior,
+    ];
+    let samples = buffer(4, 2, 2, values.clone());
+    let field = Heightfield::of(&samples, Transfer::Linear, Plateau::Top);
+    assert_eq!((field.width, field.height), (2, 1));
+    assert_eq!(field.heights, [0.5, 0.8125]);
+    let field = Heightfield::of(&buffer(8, 1, 1, values), Transfer::Linear, Plateau::Base);
+    assert_eq!(field.heights, [0.5, 0.5, 0.0, 0.0, 0.0, 1.0, 0.25, 0.0]);
+    let mut spike = Heightfield {
+        width: 3,
+        height: 3,
+        heights: vec![0.0, 0.0, 0.0, 0.0, 9.0, 0.0, 0.0, 0.0, 0.0],
+    };
+    spike.smooth(1);
+    assert!(spike.heights.iter().all(|&height| height > 0.0 && height < 9.0));
+    assert_eq!(spike.heights[4], 1.0);
+    assert_eq!(spike.heights[0], 9.0 / 4.0);
+}
+
+/// A field of the distance from a point, `size` samples to the side.
+fn cone(size: usize, center: (f64, f64), outside: Option<f64>) -> Field {
+    let values = (0..size * size)
+        .map(|index| {
+            let (x, y) = ((index % size) as f64, (index / size) as f64);
+            (x - center.0).hypot(y - center.1)
+        })
+        .collect();
+    Field {
+        width: size,
+        height: size,
+        values,
+        outside,
+    }
+}
+
+/// The isoline of a cone is a closed circle around its tip, whose points
+/// are on the circle but for the linear placing along the sides.
+#[test]
+fn isolines_of_a_cone_are_circles() {
+    let lines = isoline::trace(&cone(41, (20.3, 19.6), None), 10.0);
+    assert_eq!(lines.len(), 1);
+    let circle = &lines[0];
+    assert!(circle.closed);
+    for &(x, y) in &circle.points {
+        assert!(((x - 20.3).hypot(y - 19.6) - 10.0).abs() < 0.05, "({}, {})", x, y);
+    }
+    // Every point is where the circle crosses a side, four to a row it
+    // spans but for the ones it touches.
+    assert!(circle.points.len() > 70, "{}", circle.points.len());
+    let area = circle.points.iter().zip(circle.points.iter().cycle().skip(1)).fold(
+        0.0,
+        |area, (a, b)| area + (a.0 * b.1 - b.0 * a.1) / 2.0,
+    );
+    assert!((area.abs() - std::f64::consts::PI * 100.0).abs() < 1.0, "{}", area);
+}
+
+/// A circle that runs past the edges is cut into the arcs inside them, or
+/// closed along the edges with a value taken outside.
+#[test]
+fn isolines_end_at_the_edges_unless_closed_around_them() {
+    let arcs = isoline::trace(&cone(21, (10.0, 10.0), None), 12.0);
+    assert_eq!(arcs.len(), 4);
+    for arc in &arcs {
+        assert!(!arc.closed);
+        let on_edge = |(x, y): (f64, f64)| x == 0.0 || y == 0.0 || x == 20.0 || y == 20.0;
+        assert!(on_edge(arc.points[0]) && on_edge(*arc.points.last().unwrap()), "{:?}", arc);
+    }
+    let closed = isoline::trace(&cone(21, (10.0, 10.0), Some(100.0)), 12.0);
+    assert_eq!(closed.len(), 1);
+    assert!(closed[0].closed);
+    // Along the edges, the line is clamped onto them.
+    let inside = |&(x, y): &(f64, f64)| (0.0..=20.0).contains(&x) && (0.0..=20.0).contains(&y);
+    assert!(closed[0].points.iter().all(inside));
+    assert!(closed[0].points.contains(&(0.0, 10.0)));
+}
+
+/// Through the saddle of `x y`, the isolines at a level either side of it
+/// keep to the quadrants where the product is on that side, the square at
+/// the middle split by the mean of its corners. The field is bilinear, so
+/// the points are exactly on the hyperbolas.
+#[test]
+fn isolines_of_a_saddle_keep_to_their_quadrants() {
+    let values = (0..36)
+        .map(|index| ((index % 6) as f64 - 2.5) * ((index / 6) as f64 - 2.5))
+        .collect();
+    let saddle = Field {
+        width: 6,
+        height: 6,
+        values,
+        outside: None,
+    };
+    for level in [0.1, -0.1] {
+        let lines = isoline::trace(&saddle, level);
+        assert_eq!(lines.len(), 2, "{}", level);
+        let mut quadrants = Vec::new();
+        for line in &lines {
+            assert!(!line.closed);
+            let quadrant = |(x, y): (f64, f64)| (x > 2.5, y > 2.5);
+            quadrants.push(quadrant(line.points[0]));
+            for &point in &line.points {
+                assert_eq!(quadrant(point), quadrant(line.points[0]), "{:?}", line);
+                assert!(((point.0 - 2.5) * (point.1 - 2.5) - level).abs() < 1e-9, "{:?}", point);
+            }
+        }
+        quadrants.sort();
+        match level > 0.0 {
+            true => assert_eq!(quadrants, [(false, false), (true, true)]),
+            false => assert_eq!(quadrants, [(false, true), (true, false)]),
+        }
+    }
+}
+
+#[test]
+fn simplified_lines_keep_the_points_they_need() {
+    let straight: Vec<(f64, f64)> = (0..10).map(|x| (x as f64, 0.5 * x as f64)).collect();
+    assert_eq!(isoline::simplify(&straight, 0.01), [(0.0, 0.0), (9.0, 4.5)]);
+    let wobbly: Vec<(f64, f64)> = (0..10).map(|x| (x as f64, 0.1 * (x % 2) as f64)).collect();
+    assert_eq!(isoline::simplify(&wobbly, 0.2), [(0.0, 0.0), (9.0, 0.1)]);
+    assert_eq!(isoline::simplify(&wobbly, 0.05), wobbly);
+    let spike = [(0.0, 0.0), (1.0, 1.4), (2.0, 3.0), (3.0, 1.6), (4.0, 0.0)];
+    assert_eq!(isoline::simplify(&spike, 0.5), [(0.0, 0.0), (2.0, 3.0), (4.0, 0.0)]);
+
+    // A square with points along its sides comes back to its corners.
+    let mut points = Vec::new();
+    for side in 0..4 {
+        for step in 0..4 {
+            let t = step as f64 / 4.0;
+            points.push([(t, 0.0), (1.0, t), (1.0 - t, 1.0), (0.0, 1.0 - t)][side]);
+        }
+    }
+    let square = Polyline {
+        points,
+        closed: true,
+    };
+    let simplified = square.simplified(0.01);
+    assert!(simplified.closed);
+    assert_eq!(simplified.points, [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]);
+}
+
+#[test]
+fn levels_spread_over_the_escape_times() {
+    assert_eq!(Spacing::Even.levels((10.0, 60.0), 4), [20.0, 30.0, 40.0, 50.0]);
+    let log = Spacing::Log.levels((10.0, 60.0), 4);
+    assert_eq!(log.len(), 4);
+    assert!(log.windows(2).all(|pair| pair[0] < pair[1]));
+    assert!(log[0] > 10.0 && log[3] < 60.0);
+    // The same ratio apart past one below the lowest.
+    let ratios: Vec<f64> = log.windows(2).map(|pair| (pair[1] - 9.0) / (pair[0] - 9.0)).collect();
+    assert!(ratios.iter().all(|ratio| (ratio - ratios[0]).abs() < 1e-9), "{:?}", ratios);
+    assert!(log[1] - log[0] < log[3] - log[2]);
+}
+
+#[test]
+fn isolines_are_written_as_grouped_paths() {
+    let values = vec![Sample::Interior, Sample::Value(3.0), Sample::Value(4.0), Sample::Value(5.0)];
+    let samples = buffer(2, 2, 2, values);
+    assert_eq!(isoline::escaped_range(&samples), Some((3.0, 5.0)));
+    let line = Polyline {
+        points: vec![(0.0, 0.0), (1.0, 0.5)],
+        closed: false,
+    };
+    assert_eq!(line.in_pixels(2).points, [(0.25, 0.25), (0.75, 0.5)]);
+    let set = Polyline {
+        points: vec![(0.0, 0.0), (2.0, 0.0), (2.0, 1.5)],
+        closed: true,
+    };
+    let layers = [
+        Layer::Set {
+            color: (0, 0, 0),
+            lines: vec![set],
+        },
+        Layer::Level {
+            level: 12.5,
+            color: (255, 128, 0),
+            width: 1.5,
+            lines: vec![line],
+        },
+    ];
+    let svg = isoline::to_svg(4, 3, &layers);
+    assert!(svg.contains("width=\"4\" height=\"3\" viewBox=\"0 0 4 3\""), "{}", svg);
+    assert!(svg.contains("<g class=\"set\" fill=\"#000000\" stroke=\"none\">\n\
+         <path fill-rule=\"evenodd\" d=\"M0 0L2 0L2 1.5Z\"/>\n</g>"), "{}", svg);
+    let level = "data-level=\"12.5\" fill=\"none\" stroke=\"#ff8000\" stroke-width=\"1.5\"";
+    assert!(svg.contains(level), "{}", svg);
+    assert!(svg.contains("<path d=\"M0 0L1 0.5\"/>"), "{}", svg);
+    assert!(svg.ends_with("</svg>\n"));
+}
+
+/// A heightfield's mesh has a vertex a pixel at the heights scaled, and
+/// closed into a solid every edge is shared by two triangles, running
+/// either way along it, so it's watertight and faces out.
+#[test]
+fn meshes_of_heightfields_close_into_solids() {
+    let field = Heightfield {
+        width: 4,
+        height: 3,
+        heights: (0..12).map(|index| index as f64 / 11.0).collect(),
+    };
+    let surface = Mesh::of(&field, 0.5, None);
+    assert_eq!(surface.vertices.len(), 12);
+    assert_eq!(surface.triangles.len(), 2 * 3 * 2);
+    // The longer side is 1 long, and the top row at the back.
+    assert_eq!(surface.vertices[0], [0.0, 2.0 / 3.0, 0.0]);
+    assert_eq!(surface.vertices[11], [1.0, 0.0, 0.5]);
+    assert_eq!(surface.uvs[11], [1.0, 0.0]);
+
+    let solid = Mesh::of(&field, 0.5, Some(0.1));
+    // The edge of the surface again at 0, and the middle of the bottom.
+    let edge = 2 * (4 - 1) + 2 * (3 - 1);
+    assert_eq!(solid.vertices.len(), 12 + edge + 1);
+    assert_eq!(solid.triangles.len(), 12 + 3 * edge);
+    assert_eq!(solid.vertices[11], [1.0, 0.0, 0.6]);
+    assert_eq!(solid.vertices[12], [0.0, 2.0 / 3.0, 0.0]);
+    assert_eq!(*solid.vertices.last().unwrap(), [0.5, 1.0 / 3.0, 0.0]);
+    let mut edges = HashMap::new();
+    for [a, b, c] in &solid.triangles {
+        for edge in [(a, b), (b, c), (c, a)] {
+            *edges.entry(edge).or_insert(0) += 1;
+        }
+    }
+    for (&(a, b), &count) in &edges {
+        assert_eq!((count, edges.get(&(b, a))), (1, Some(&1)), "{} {}", a, b);
+    }
+    // The surface faces up, and the bottom down.
+    let z = |triangle: [u32; 3]| {
+        let [a, b, c] = triangle.map(|index| solid.vertices[index as usize]);
+        (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+    };
+    assert!(solid.triangles[..12].iter().all(|&triangle| z(triangle) > 0.0));
+    assert!(solid.triangles[12..].iter().step_by(3).all(|&triangle| z(triangle) == 0.0));
+    assert!(solid.triangles[14..].iter().step_by(3).all(|&triangle| z(triangle) < 0.0));
+
+    let stl = solid.to_stl();
+    assert_eq!(stl.len(), 84 + 50 * solid.triangles.len());
+    assert!(stl.starts_with(b"rustlebrot "));
+    assert_eq!(stl[80..84], (solid.triangles.len() as u32).to_le_bytes());
+    let float = |at: usize| f32::from_le_bytes(stl[at..at + 4].try_into().unwrap());
+    // The first triangle faces up, and starts at the top left corner.
+    assert!(float(92) > 0.0);
+    assert_eq!([float(96), float(100)], [0.0, 2.0 / 3.0]);
+    assert_eq!(float(104), 0.1);
+
+    let obj = surface.to_obj(Some(&[[1.0, 0.5, 0.0]; 12]), None);
+    assert_eq!(obj.lines().filter(|line| line.starts_with("v ")).count(), 12);
+    assert_eq!(obj.lines().filter(|line| line.starts_with("f ")).count(), 12);
+    assert!(obj.contains("\nv 1 0 0.5 1.0000 0.5000 0.0000\n"), "{}", obj);
+    assert!(obj.contains("\nf 1 5 2\n"), "{}", obj);
+    let obj = surface.to_obj(None, Some(("set.mtl", "fractal")));
+    assert!(obj.contains("mtllib set.mtl\nusemtl fractal\n"), "{}", obj);
+    assert!(obj.contains("\nvt 1 0\n") && obj.contains("\nf 1/1 5/5 2/2\n"), "{}", obj);
+    assert_eq!(mesh::Format::from_path("set.stl"), Some(mesh::Format::Stl));
+    assert_eq!(mesh::Format::from_path("set.ply"), None);
+}
+
+/// Outputs are made from the largest by shrinking it whole or its middle,
+/// never by stretching or upscaling it.
+#[test]
+fn outputs_shrink_the_largest_frames() {
+    let list = outputs::Output::list_from_spec("512x512@center-crop,3840x2160,1920x1080").unwrap();
+    let names: Vec<String> = list.iter().map(outputs::Output::name).collect();
+    assert_eq!(names, ["3840x2160", "1920x1080", "512x512-center-crop"]);
+    assert_eq!(list[2].region(3840, 2160), (840, 0, 2160, 2160));
+    assert_eq!(list[1].region(3840, 2160), (0, 0, 3840, 2160));
+    let error = outputs::Output::list_from_spec("1920x1080,1000x1000").unwrap_err();
+    assert!(error.contains("add @center-crop"), "{}", error);
+    let error = outputs::Output::list_from_spec("1920x1080,1200x1200@center-crop").unwrap_err();
+    assert!(error.contains("upscaled from 1080x1080"), "{}", error);
+    let error = outputs::Output::list_from_spec("640x360,640x360").unwrap_err();
+    assert!(error.contains("given twice"), "{}", error);
+    assert!(outputs::Output::from_spec("640x360@letterbox").is_err());
+    assert!(outputs::Output::from_spec("640x0").is_err());
+
+    let flat = image::DynamicImage::ImageRgb16(image::ImageBuffer::from_pixel(
+        60,
+        40,
+        image::Rgb([9000u16, 30000, 50000]),
+    ));
+    let small = outputs::resample(&flat, 30, 20);
+    assert_eq!(small.color(), image::ColorType::Rgb16);
+    assert_eq!((small.width(), small.height()), (30, 20));
+    let small = small.to_rgb16();
+    let near = |pixel: &image::Rgb<u16>| {
+        pixel.0.iter().zip([9000, 30000, 50000]).all(|(&got, want)| (got as i32 - want).abs() <= 2)
+    };
+    assert!(small.pixels().all(near), "{:?}", small.get_pixel(0, 0));
+    let square = outputs::Output::from_spec("10x10@center-crop").unwrap().of(&flat);
+    assert_eq!((square.width(), square.height()), (10, 10));
+}
+
+/// A gray image `width` pixels wide of the linear intensities `levels`, in
+/// 16 bits so the filters can be checked closely.
+fn gray(width: u32, levels: &[f64]) -> image::DynamicImage {
+    let height = levels.len() as u32 / width;
+    image::DynamicImage::ImageRgb16(image::ImageBuffer::from_fn(width, height, |x, y| {
+        let level = render::from_linear(levels[(y * width + x) as usize]);
+        image::Rgb([(level * 65535.0).round() as u16; 3])
+    }))
+}
+
+/// The linear intensities of the red channel of `img`.
+fn intensities(img: &image::DynamicImage) -> Vec<f64> {
+    let img = img.to_rgb16();
+    img.pixels().map(|pixel| render::to_linear(pixel.0[0] as f64 / 65535.0)).collect()
+}
+
+fn assert_near(got: &[f64], want: &[f64]) {
+    let near = got.iter().zip(want).all(|(got, want)| (got - want).abs() < 1e-4);
+    assert!(near && got.len() == want.len(), "got {:?}, expected {:?}", got, want);
+}
+
+/// The chain of `--post` keeps its filters in order, reads back as it was
+/// given, and says what's wrong with a filter it can't take.
+#[test]
+fn post_filters_parse_in_order() {
+    let spec = "gaussian-blur:0.6,unsharp:1.2,vignette:0.3,levels:0.02-0.98";
+    let chain = Chain::from_spec(spec).unwrap();
+    let filters = [
+        Filter::GaussianBlur(0.6),
+        Filter::Unsharp(1.2),
+        Filter::Vignette(0.3),
+        Filter::Levels {
+            black: 0.02,
+            white: 0.98,
+        },
+    ];
+    assert_eq!(chain.filters, filters);
+    assert_eq!(chain.to_string(), spec);
+    for (invalid, problem) in [
+        ("sharpen:1", "unknown post filter 'sharpen'"),
+        ("gaussian-blur", "sigma of gaussian-blur should be positive, got ''"),
+        ("gaussian-blur:0", "should be positive"),
+        ("unsharp:much", "amount of unsharp"),
+        ("vignette:1.5", "from 0 to 1"),
+        ("levels:0.5", "like 0.02-0.98"),
+        ("levels:0.9-0.1", "below the white point"),
+        ("vignette:0.3,,levels:0-1", "post takes filters like"),
+    ] {
+        let error = Chain::from_spec(invalid).unwrap_err();
+        assert!(error.contains(problem), "{}: {}", invalid, error);
+    }
+}
+
+/// A Gaussian blur spreads a point of light over its neighbors by the
+/// weights of the Gaussian, keeping all of it, and leaves an even gray be.
+#[test]
+fn gaussian_blur_spreads_a_point_of_light() {
+    let mut point = vec![0.0; 49];
+    point[24] = 1.0;
+    let blur = Chain::from_spec("gaussian-blur:0.6").unwrap();
+    let blurred = intensities(&blur.apply(&gray(7, &point)));
+    // Out to 3 sigma, two pixels either side.
+    let weights: Vec<f64> = (-2..=2).map(|k: i32| (-(k * k) as f64 / 0.72).exp()).collect();
+    let sum: f64 = weights.iter().sum();
+    let weights: Vec<f64> = weights.iter().map(|weight| weight / sum).collect();
+    let want: Vec<f64> = (0..49)
+        .map(|index| {
+            let (x, y) = (index % 7, index / 7);
+            match (x, y) {
+                (1..=5, 1..=5) => weights[x - 1] * weights[y - 1],
+                _ => 0.0,
+            }
+        })
+        .collect();
+    assert_near(&blurred, &want);
+    assert!((blurred.iter().sum::<f64>() - 1.0).abs() < 1e-3);
+
+    let even = gray(5, &[0.3; 15]);
+    let blur = Chain::from_spec("gaussian-blur:2").unwrap();
+    assert_near(&intensities(&blur.apply(&even)), &[0.3; 15]);
+}
+
+/// An unsharp mask moves every pixel away from a blur of it by the amount,
+/// so a bright point gets brighter and its surroundings darker.
+#[test]
+fn unsharp_pushes_pixels_away_from_their_blur() {
+    let mut point = vec![0.1; 49];
+    point[24] = 0.25;
+    let img = gray(7, &point);
+    let blur = Chain::from_spec(&format!("gaussian-blur:{}", UNSHARP_SIGMA)).unwrap();
+    let blurred = intensities(&blur.apply(&img));
+    let sharpened = intensities(&Chain::from_spec("unsharp:1.2").unwrap().apply(&img));
+    let want: Vec<f64> = point
+        .iter()
+        .zip(&blurred)
+        .map(|(level, blurred)| level + 1.2 * (level - blurred))
+        .collect();
+    assert_near(&sharpened, &want);
+    assert!(sharpened[24] > 0.25 && sharpened[17] < 0.1, "{:?}", sharpened);
+}
+
+/// A vignette leaves the middle of the frame as it was and darkens the
+/// corners by its strength, and the edges between with the square of
+/// their distance.
+#[test]
+fn vignette_darkens_toward_the_corners() {
+    let img = gray(5, &[0.5; 25]);
+    let darkened = intensities(&Chain::from_spec("vignette:0.3").unwrap().apply(&img));
+    assert_near(&[darkened[12], darkened[0], darkened[24], darkened[2]], &[0.5, 0.35, 0.35, 0.425]);
+}
+
+/// Levels stretch the intensities between the black and white points over
+/// the whole range, and leave the opacity of a pixel as it was.
+#[test]
+fn levels_stretch_from_the_black_to_the_white_point() {
+    let levels = Chain::from_spec("levels:0.2-0.8").unwrap();
+    let stretched = intensities(&levels.apply(&gray(4, &[0.1, 0.35, 0.5, 0.9])));
+    assert_near(&stretched, &[0.0, 0.25, 0.5, 1.0]);
+
+    let half = (render::from_linear(0.35) * 65535.0).round() as u16;
+    let pixel = image::Rgba([half, half, half, 32768]);
+    let translucent = image::DynamicImage::ImageRgba16(image::ImageBuffer::from_pixel(1, 1, pixel));
+    let stretched = levels.apply(&translucent).to_rgba16();
+    let pixel = stretched.get_pixel(0, 0).0;
+    assert_near(&[render::to_linear(pixel[0] as f64 / 65535.0)], &[0.25]);
+    assert_eq!(pixel[3], 32768);
+}
+
+#[test]
+fn filename_templates_name_frames_and_patterns() {
+    let name = |frame, ext| FrameName {
+        frame,
+        magnification: 1500.0,
+        palette: "fire",
+        ext,
+    };
+    let default = FilenameTemplate::default();
+    assert_eq!(default.path(&name(7, "png")), "mandelbrot_set_0007.png");
+    assert_eq!(default.ffmpeg_pattern("fire", "png").unwrap(), "mandelbrot_set_%04d.png");
+
+    let nested = FilenameTemplate::parse("frames/{name}_{frame:06}.{ext}").unwrap();
+    let bound = nested.bind("dive", "20240131-235959");
+    assert_eq!(bound.path(&name(12, "exr")), "frames/dive_000012.exr");
+    assert_eq!(bound.ffmpeg_pattern("fire", "png").unwrap(), "frames/dive_%06d.png");
+    assert_eq!(bound.to_string(), "frames/dive_{frame:06}.{ext}");
+
+    let zoomed = FilenameTemplate::parse("{palette}/{frame}_{zoom}%.{ext}").unwrap();
+    assert_eq!(zoomed.path(&name(3, "png")), "fire/3_1.500e3%.png");
+    assert_eq!(zoomed.ffmpeg_pattern("fire", "png"), None);
+    let percent = FilenameTemplate::parse("{{{frame}}}%.{ext}").unwrap();
+    assert_eq!(percent.path(&name(3, "png")), "{3}%.png");
+    assert_eq!(percent.ffmpeg_pattern("fire", "png").unwrap(), "{%d}%%.png");
+    assert_eq!(FilenameTemplate::parse(&percent.to_string()).unwrap(), percent);
+
+    for (invalid, problem) in [
+        ("{frames}.{ext}", "unknown placeholder {frames}"),
+        ("{frame}.png", "needs {ext}"),
+        ("{frame:0}.{ext}", "from 1 to 20"),
+        ("{name:2}{frame}.{ext}", "takes no format"),
+        ("{frame.{ext}", "isn't closed"),
+        ("}{frame}.{ext}", "isn't opened"),
+        ("/tmp/{frame}.{ext}", "relative"),
+        ("{name}/../{frame}.{ext}", "'..'"),
+    ] {
+        let error = FilenameTemplate::parse(invalid).unwrap_err();
+        assert!(error.contains(problem), "{}: {}", invalid, error);
+    }
+    assert!(FilenameTemplate::parse("{name}/{frame}.{ext}").is_ok());
+
+    assert_eq!(template::timestamp(0), "19700101-000000");
+    assert_eq!(template::timestamp(951_782_400 + 86_399), "20000229-235959");
+    assert_eq!(template::timestamp(1_706_745_599), "20240131-235959");
+}
+
+#[test]
+fn decimals_compare_by_value_and_keep_their_digits() {
+    let parse = |text: &str| Decimal::parse(text).unwrap();
+    for text in ["-0.75", "+.5", "1.250e-3", "0", "-0.000123000"] {
+        assert_eq!(parse(text).to_string(), text);
+        assert_eq!(parse(text).as_str(), text);
+    }
+    assert_eq!(parse("0.50"), parse(".5"));
+    assert_eq!(parse("-0"), parse("0"));
+    assert_eq!(parse("1e2"), parse("100.0"));
+    assert_eq!(parse("12.5e-1"), parse("1.25"));
+    let ascending = ["-20", "-1.5", "-1.25", "-0.001", "0", "1e-300", "0.3", "2", "10"];
+    for pair in ascending.windows(2) {
+        assert!(parse(pair[0]) < parse(pair[1]), "{} < {}", pair[0], pair[1]);
+    }
+    assert_eq!(parse("-0.0012300").significant_digits(), 3);
+    assert_eq!(parse("100").significant_digits(), 1);
+    assert_eq!(parse("0.000").significant_digits(), 0);
+    for invalid in ["inf", "nan", "1e400", "", ".", "1_0", "1e", "--1", "0x10"] {
+        assert!(Decimal::parse(invalid).is_err(), "{}", invalid);
+    }
+    assert_eq!(decimal::digits_of_bits(f64::MANTISSA_DIGITS as usize), 15);
+    assert_eq!(decimal::digits_of_bits(f32::MANTISSA_DIGITS as usize), 6);
+    // Every digit of a long center reaches the precision that reads it.
+    let deep = "-1.7499576837060935036022145060706997072711057972625207793024283782028600";
+    let big = bigfloat::parse_decimal(parse(deep).as_str(), 512).unwrap();
+    assert_eq!(big.to_f64().value(), parse(deep).to_f64());
+    let nearby = bigfloat::parse_decimal(&(deep.to_string() + "1"), 512).unwrap();
+    assert!(big != nearby && parse(deep) > parse(&(deep.to_string() + "1")));
+}
+
+/// The frames where auto precision moves on to the next tier look the same
+/// in both, so a zoom has no seam there: the renders of the last frame in
+/// the cheaper tier only differ in the odd boundary pixel. Orbits that stay
+/// near the boundary for thousands of iterations tell the rounding of any
+/// two tiers apart, so this is away from the densest filaments.
+#[test]
+fn auto_precision_hands_over_without_seams() {
+    let (center, size, max_iter) = ((-1.7499984109937408, 0.0), 48, 5000);
+    // The smallest pixels the tier of `pixel` is kept for, walking down
+    // from it, with the tier of the ones below.
+    let handoff = |mut pixel: f64| {
+        let tier = Precision::Auto.resolve(center, pixel);
+        while Precision::Auto.resolve(center, pixel * 0.99) == tier {
+            pixel *= 0.99;
+        }
+        (pixel, tier, Precision::Auto.resolve(center, pixel * 0.99))
+    };
+    let render = |precision: Precision, pixel: f64| {
+        let width = pixel * size as f64;
+        let mut options = options(max_iter);
+        options.coloring = Coloring::Smooth;
+        options.single_precision = precision == Precision::F32;
+        let buffer = match precision {
+            Precision::DoubleDouble => {
+                let center = (DoubleDouble::from(center.0), DoubleDouble::from(center.1));
+                let range = (width, width);
+                render::compute_escape_dd(&Mandelbrot, size, size, center, range, &options)
+            }
+            Precision::Perturbation => {
+                let big = (bigfloat::from_f64(center.0, 128), bigfloat::from_f64(center.1, 128));
+                let orbit = Mandelbrot.reference_orbit(&big, 128, max_iter, options.bailout);
+                let range = (width, width);
+                render::compute_escape_perturbed(
+                    &Mandelbrot, size, size, &orbit, None, range, &options,
+                )
+            }
+            _ => {
+                let x_range = (center.0 - width / 2.0, center.0 + width / 2.0);
+                let y_range = (center.1 - width / 2.0, center.1 + width / 2.0);
+                render::compute_escape(&Mandelbrot, size, size, x_range, y_range, &options)
+            }
+        };
+        colorize(&buffer)
+    };
+    let mut handoffs = vec![handoff(1e-10)];
+    assert_eq!((handoffs[0].1, handoffs[0].2), (Precision::F64, Precision::DoubleDouble));
+    if cfg!(feature = "simd") {
+        handoffs.push(handoff(1e-3));
+        assert_eq!((handoffs[1].1, handoffs[1].2), (Precision::F32, Precision::F64));
+    }
+    if cfg!(feature = "bigfloat") {
+        let deepest = handoff(1e-20);
+        assert_eq!((deepest.1, deepest.2), (Precision::DoubleDouble, Precision::Perturbation));
+        handoffs.push(deepest);
+    }
+    for (pixel, cheaper, next) in handoffs {
+        let (a, b) = (render(cheaper, pixel), render(next, pixel));
+        let differing = a
+            .pixels()
+            .zip(b.pixels())
+            .filter(|(a, b)| a.0.iter().zip(b.0).any(|(&a, b)| a.abs_diff(b) > 8))
+            .count();
+        let pixels = (size * size) as usize;
+        assert!(differing * 100 < pixels * 3, "{} pixels differ at {:?}", differing, cheaper);
+    }
+}
+
+/// Frame 60 of the default zoom, doubling every frame, is far past what
+/// f64 resolves: every pixel rounds to the same point, so the frame is one
+/// flat block, where iterating against an arbitrary-precision reference
+/// draws its detail. Auto precision has left f64 for a tier that resolves
+/// the frame by then.
+#[test]
+fn frame_60_needs_more_than_f64() {
+    let (x, y) = FractalKind::Mandelbrot.default_center();
+    let center = (x.parse::<f64>().unwrap(), y.parse::<f64>().unwrap());
+    // The orbits around the minibrot at the center take tens of thousands
+    // of iterations to escape this deep.
+    let (size, max_iter) = (24, 100_000);
+    let width = 2.0 * FractalKind::Mandelbrot.default_half_width() / 2f64.powi(60);
+    let pixel = width / size as f64;
+    assert!(!Precision::F64.resolves(center, pixel));
+    assert!(Precision::Perturbation.resolves(center, pixel));
+    let auto = Precision::Auto.resolve(center, pixel);
+    assert!(auto != Precision::F64 && auto.resolves(center, pixel), "{:?}", auto);
+
+    let options = options(max_iter);
+    let bits = bigfloat::required_bits(center, pixel);
+    let big_center = (
+        bigfloat::parse_decimal(x, bits).unwrap(),
+        bigfloat::parse_decimal(y, bits).unwrap(),
+    );
+    let orbit = Mandelbrot.reference_orbit(&big_center, bits, max_iter, options.bailout);
+    let range = (width, width);
+    let big =
+        render::compute_escape_perturbed(&Mandelbrot, size, size, &orbit, None, range, &options);
+    let x_range = (center.0 - width / 2.0, center.0 + width / 2.0);
+    let y_range = (center.1 - width / 2.0, center.1 + width / 2.0);
+    // However long it iterates, f64 iterates the one point.
+    let short = RenderOptions { max_iter: 1000, ..options };
+    let f64 = render::compute_escape(&Mandelbrot, size, size, x_range, y_range, &short);
+    let colors = |buffer: &EscapeBuffer| {
+        colorize(buffer).pixels().map(|pixel| pixel.0).collect::<HashSet<_>>().len()
+    };
+    assert_eq!(colors(&f64), 1);
+    assert!(colors(&big) > 10, "arbitrary precision drew {} colors", colors(&big));
+}
+
+/// A step of the kernels in `Complex` is bit for bit the formula they
+/// used to write out by hand, in f64, f32 and double-double, so rewriting
+/// them didn't move a pixel.
+#[test]
+fn complex_steps_match_the_expanded_formula() {
+    let mut rng = SmallRng::seed_from_u64(11);
+    for _ in 0..1000 {
+        let (x, y) = (rng.gen_range(-2.0..2.0), rng.gen_range(-2.0..2.0));
+        let c: (f64, f64) = (rng.gen_range(-2.0..2.0), rng.gen_range(-2.0..2.0));
+        let z = Complex::new(x, y);
+        let expanded = (x * x - y * y + c.0, 2.0 * x * y + c.1);
+        assert_eq!(fractal::step::<f64, false>(z, c.into()), expanded.into());
+        let expanded = (x * x - y * y + c.0, -(2.0 * x * y) + c.1);
+        assert_eq!(fractal::step::<f64, true>(z, c.into()), expanded.into());
+        assert_eq!(z.sqr(), z * z);
+
+        let (x, y) = (x as f32, y as f32);
+        let z = Complex::new(x, y);
+        assert_eq!(z.sqr(), Complex::new(x * x - y * y, 2.0 * x * y));
+
+        let (x, y) = (DoubleDouble::from(x as f64 / 3.0), DoubleDouble::from(y as f64 / 7.0));
+        let z = Complex::new(x, y);
+        assert_eq!(z.sqr(), Complex::new(x.sqr() - y.sqr(), x * y * 2.0));
+    }
+}
+
+/// Conjugates, norms, quotients and powers keep the identities that
+/// kernels lean on.
+#[test]
+fn complex_arithmetic_keeps_its_identities() {
+    let mut rng = SmallRng::seed_from_u64(12);
+    let mut random = || Complex::<f64>::new(rng.gen_range(-2.0..2.0), rng.gen_range(-2.0..2.0));
+    for _ in 0..1000 {
+        let (a, b) = (random(), random());
+        assert_eq!((a * b).conj(), a.conj() * b.conj());
+        assert_eq!(a * b, b * a);
+        assert_eq!(-a + a, Complex::ZERO);
+        let product = a * a.conj();
+        assert!((product.re - a.norm_sqr()).abs() <= 1e-15 * a.norm_sqr());
+        assert_eq!(product.im, 0.0);
+        let quotient = a * b / b;
+        assert!((quotient - a).norm_sqr().sqrt() <= 1e-14 * a.norm_sqr().sqrt());
+        assert_eq!(Complex::ONE / Complex::new(0.0, 1.0), Complex::new(0.0, -1.0));
+        assert_eq!(Complex::from(<(f64, f64)>::from(a)), a);
+
+        assert_eq!(a.powi(0), Complex::ONE);
+        assert_eq!(a.powi(1), a);
+        assert_eq!(a.powi(2), a.sqr());
+        let mut repeated = Complex::ONE;
+        for n in 1..=9 {
+            repeated = repeated * a;
+            let error = (a.powi(n) - repeated).norm_sqr().sqrt();
+            assert!(error <= 1e-14 * repeated.norm_sqr().sqrt(), "{:?}^{}", a, n);
+        }
+    }
+}
+
+/// `value` in arbitrary precision, exactly.
+fn dd_to_big(value: DoubleDouble) -> bigfloat::Big {
+    bigfloat::from_f64(value.hi, 256) + bigfloat::from_f64(value.lo, 256)
+}
+
+/// Double-double sums, differences, products and quotients are within a
+/// few units of 2^-104 of the exact results worked out in arbitrary
+/// precision, relative to the operands for sums and differences, which
+/// cancel, and to the result otherwise.
+#[test]
+fn double_double_arithmetic_is_exact_to_104_bits() {
+    let mut rng = SmallRng::seed_from_u64(7);
+    let mut random = || {
+        let hi = rng.gen_range(-4.0..4.0) * 2f64.powi(rng.gen_range(-40..40));
+        // The low part fills the bits past the last of `hi`.
+        let lo = hi * f64::EPSILON * rng.gen_range(-0.5..0.5);
+        let (hi, lo) = (hi + lo, lo - ((hi + lo) - hi));
+        DoubleDouble { hi, lo }
+    };
+    let mut cases = vec![
+        (DoubleDouble::ONE, DoubleDouble::from(1e-30)),
+        (DoubleDouble::from(0.1), DoubleDouble::from(-0.1)),
+        (DoubleDouble::from(1.0 / 3.0), DoubleDouble::from(3.0)),
+    ];
+    cases.extend((0..500).map(|_| (random(), random())));
+    let relative = |got: DoubleDouble, exact: &bigfloat::Big, scale: f64| {
+        (dd_to_big(got) - exact).to_f64().value().abs() / scale
+    };
+    let tolerance = 4.0 * dd::EPSILON;
+    for (a, b) in cases {
+        let (big_a, big_b) = (dd_to_big(a), dd_to_big(b));
+        let operands = a.hi.abs() + b.hi.abs();
+        let sum = relative(a + b, &(&big_a + &big_b), operands);
+        let difference = relative(a - b, &(&big_a - &big_b), operands);
+        let product = relative(a * b, &(&big_a * &big_b), (a.hi * b.hi).abs());
+        let square = relative(a.sqr(), &big_a.sqr(), a.hi * a.hi);
+        let quotient = relative(a / b, &(&big_a / &big_b), (a.hi / b.hi).abs());
+        for (name, error) in [
+            ("sum", sum),
+            ("difference", difference),
+            ("product", product),
+            ("square", square),
+            ("quotient", quotient),
+        ] {
+            assert!(error < tolerance, "{} of {:?} and {:?} is off by {:e}", name, a, b, error);
+        }
+    }
+}
+
+/// Decimals read in double-double keep the 31 or so digits of 2^-104,
+/// however far the decimal point is from them.
+#[test]
+fn decimals_read_in_double_double_keep_31_digits() {
+    for text in [
+        "-1.7499576837060935036022145060706997072711057972625207793024283782028600",
+        "0.00177171732282826343",
+        "3.14159265358979323846264338327950288e-20",
+        "-271828182845904523536028747135266249775.7",
+        "0.1",
+        "0",
+    ] {
+        let read = Decimal::parse(text).unwrap().to_double_double();
+        let exact = bigfloat::parse_decimal(text, 256).unwrap();
+        let error = (dd_to_big(read) - &exact).to_f64().value().abs();
+        let scale = exact.to_f64().value().abs();
+        assert!(error <= 4.0 * dd::EPSILON * scale, "{} is read off by {:e}", text, error);
+    }
+}
+
+/// Escape times iterated in double-double are those iterated in arbitrary
+/// precision at a depth f64 can't tell the points apart at, for both the
+/// Mandelbrot set and the Tricorn, but for the odd orbit that stays near
+/// the boundary long enough to tell the rounding of the two apart.
+#[test]
+fn double_double_escape_times_match_arbitrary_precision() {
+    let center = ("-0.743643887037158704752191506114774", "0.131825904205311970493132056385139");
+    let (max_iter, spacing) = (3000, 1e-24);
+    let decimal = |text: &str| Decimal::parse(text).unwrap().to_double_double();
+    let center_dd = (decimal(center.0), decimal(center.1));
+    let big = |text: &str| bigfloat::parse_decimal(text, 256).unwrap();
+    let center_big = (big(center.0), big(center.1));
+    let (mut escaped, mut differing) = (0, 0);
+    for i in 0..16 * 16 {
+        let offset = (spacing * ((i % 16) as f64 - 8.0), spacing * ((i / 16) as f64 - 8.0));
+        let c_dd = (
+            center_dd.0 + DoubleDouble::from(offset.0),
+            center_dd.1 + DoubleDouble::from(offset.1),
+        );
+        let c_big = (
+            &center_big.0 + bigfloat::from_f64(offset.0, 256),
+            &center_big.1 + bigfloat::from_f64(offset.1, 256),
+        );
+        for (dd, big) in [
+            (
+                Mandelbrot.escape_time_dd(c_dd, max_iter, 2.0, None),
+                Mandelbrot.escape_time_big(&c_big, max_iter, 2.0),
+            ),
+            (
+                Tricorn.escape_time_dd(c_dd, max_iter, 2.0, None),
+                Tricorn.escape_time_big(&c_big, max_iter, 2.0),
+            ),
+        ] {
+            escaped += (big < max_iter as f64) as usize;
+            differing += (dd.iterations != big) as usize;
+        }
+    }
+    assert!(escaped > 100, "only {} points escaped", escaped);
+    assert!(differing * 100 < escaped * 3, "{} of {} points differ", differing, escaped);
+}
+
+#[test]
+fn kalles_fraktaler_locations_convert_to_frames() {
+    let kfr = "Re: -1.76890230170008519204\r\nIm: 0.00177171732282826343\r\n\
+               Zoom: 1.5E10\r\nIterations: 20000\r\nColorMethod: 7\r\n\r\nSmooth: 1\r\n";
+    let (location, ignored) = Location::parse_kfr(kfr).unwrap();
+    let center = ("-1.76890230170008519204", "0.00177171732282826343");
+    assert_eq!((location.center.0.as_str(), location.center.1.as_str()), center);
+    assert_eq!((location.fractal, location.max_iter), (FractalKind::Mandelbrot, 20000));
+    assert_eq!(ignored, ["ColorMethod", "Smooth"]);
+
+    // At zoom 1 both show 4 across the height, for the Mandelbrot set.
+    assert_eq!(location.magnification, 1.5e10);
+    // 1.1^246 is 1.5e10 and a bit, 1.1^245 a bit short of it.
+    assert_eq!(location.frames(1.1), 247);
+    assert_eq!(location.frames(10.0), 12);
+    let shallow = Location { magnification: 0.5, ..location.clone() };
+    assert_eq!(shallow.frames(1.1), 1);
+
+    for (kfr, error) in [
+        ("Re: 0\nIm: 0\nZoom 1e3\nIterations: 100", "line 3: expected 'Key: value'"),
+        ("Re: 0\nIm: 1,5\nZoom: 1\nIterations: 100", "line 2: '1,5' isn't a decimal"),
+        ("Re: 0\nIm: 0\nZoom: 1e400\nIterations: 100", "line 3: Zoom should be"),
+        ("Re: 0\nIm: 0\nZoom: 1\nIterations: -5", "line 4: Iterations should be"),
+        ("Re: 0\nRe: 1\nIm: 0\nZoom: 1\nIterations: 5", "line 2: Re is given twice"),
+        ("Re: 0\nIm: 0\nIterations: 5", "there is no Zoom: line"),
+    ] {
+        let parsed = Location::parse_kfr(kfr);
+        assert!(parsed.as_ref().is_err_and(|e| e.starts_with(error)), "{:?}", parsed);
+    }
+}
+
+/// Flags win over a quality, which wins over the built-in defaults, one
+/// setting at a time, except that the two of anti-aliasing go together.
+#[test]
+fn qualities_give_way_to_flags() {
+    let built_in = Settings {
+        width: Some(1200),
+        height: Some(800),
+        jpeg_quality: Some(90),
+        bit_depth: Some(BitDepth::Eight),
+        ..Settings::default()
+    };
+    assert_eq!(quality::resolve(Settings::default(), None, built_in), built_in);
+
+    let draft = Quality::from_name("draft").unwrap();
+    let high = Quality::from_name("high").unwrap();
+    let drafted = quality::resolve(Settings::default(), Some(draft), built_in);
+    assert_eq!((drafted.width, drafted.height), (Some(300), Some(200)));
+    assert_eq!((drafted.supersample, drafted.adaptive), (Some(1), Some(false)));
+
+    let flags = Settings {
+        height: Some(50),
+        bit_depth: Some(BitDepth::Eight),
+        ..Settings::default()
+    };
+    let resolved = quality::resolve(flags, Some(high), built_in);
+    assert_eq!((resolved.width, resolved.height), (Some(1200), Some(50)));
+    assert_eq!((resolved.supersample, resolved.adaptive), (Some(3), Some(true)));
+    assert_eq!((resolved.jpeg_quality, resolved.bit_depth), (Some(95), Some(BitDepth::Eight)));
+
+    // Samples of their own aren't made adaptive, and adaptive samples
+    // take their number from the command line alone.
+    let supersampled = Settings { supersample: Some(2), ..Settings::default() };
+    let resolved = quality::resolve(supersampled, Some(high), built_in);
+    assert_eq!((resolved.supersample, resolved.adaptive), (Some(2), None));
+    let adaptive = Settings { adaptive: Some(true), ..Settings::default() };
+    let resolved = quality::resolve(adaptive, Some(high), built_in);
+    assert_eq!((resolved.supersample, resolved.adaptive), (None, Some(true)));
+
+    // Limits of places are capped by the fast qualities alone.
+    assert_eq!((draft.max_iter(None), draft.max_iter(Some(50_000))), (500, 500));
+    assert_eq!(draft.max_iter(Some(100)), 100);
+    assert_eq!((high.max_iter(None), high.max_iter(Some(50_000))), (20_000, 50_000));
+}
+
+/// A reference of a frame's own escape times spreads them as its histogram
+/// does, and following another moves each of its escape times partway.
+#[test]
+fn color_references_follow_the_frames() {
+    let values: Vec<f64> = (0..1000).map(|n| (n as f64 / 10.0).powi(2)).collect();
+    let mut buffer = buffer(100, 10, 1, values.iter().map(|&v| Sample::Value(v)).collect());
+    buffer.coloring = Coloring::Histogram;
+    let gradient = Palette::Sinebow.gradient();
+    let colormap = Colormap::new(&gradient, &Adjust::default());
+    let own = render::colorize(&buffer, &colors(&colormap)).to_rgb8();
+    let reference = Reference::of(values.iter().copied(), 0.0).unwrap();
+    let colors = ColorOptions {
+        reference: Some(&reference),
+        ..colors(&colormap)
+    };
+    let referenced = render::colorize(&buffer, &colors).to_rgb8();
+    let channels = own.as_raw().iter().zip(referenced.as_raw());
+    assert!(channels.map(|(a, b)| a.abs_diff(*b)).max().unwrap() <= 8);
+
+    let doubled = Reference::of(values.iter().map(|v| 2.0 * v), 0.0).unwrap();
+    let halfway = reference.follow(&doubled, 0.5);
+    for (index, &quantile) in halfway.quantiles().iter().enumerate() {
+        assert_eq!(quantile, 1.5 * reference.quantiles()[index]);
+    }
+    assert_eq!(reference.follow(&doubled, 1.0), doubled);
+    assert_eq!(Reference::from_quantiles(halfway.quantiles().to_vec()), Ok(halfway));
+    assert!(Reference::from_quantiles(vec![1.0, 0.0]).is_err());
+}
+
+/// Every transfer takes an escape time of 0 to the first color and the
+/// palette's escape time to the last, keeping the order in between.
+#[test]
+fn transfers_keep_the_ends_and_the_order() {
+    let transfers = ["linear", "sqrt", "log", "power:0.4", "power:2.5"];
+    for spec in transfers {
+        let transfer = Transfer::from_spec(spec).unwrap();
+        let span = 1000.0;
+        assert_eq!(transfer.position(0.0, span), 0.0, "{}", spec);
+        assert!((transfer.position(span, span) - 1.0).abs() < 1e-12, "{}", spec);
+        let positions: Vec<f64> =
+            (0..=4000).map(|n| transfer.position(n as f64 / 2.0, span)).collect();
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]), "{}", spec);
+    }
+    assert!(Transfer::Sqrt.position(10.0, 1000.0) > Transfer::Linear.position(10.0, 1000.0));
+    assert!(Transfer::Log.position(10.0, 1000.0) > Transfer::Sqrt.position(10.0, 1000.0));
+    for spec in ["cube", "power:", "power:0", "power:-1", "power:inf"] {
+        assert!(Transfer::from_spec(spec).is_err(), "{}", spec);
+    }
+}
+
+/// A frame resampled from the exponential map around its center looks like
+/// the frame rendered directly, turned or not.
+#[test]
+fn expmap_frames_match_rendered_frames() {
+    let (size, width) = (96, 0.5);
+    let center = (-0.743643887, 0.131825904);
+    let pixel = width / size as f64;
+    let gradient = Palette::Sinebow.gradient();
+    let colormap = Colormap::new(&gradient, &Adjust::default());
+    let colors = ColorOptions {
+        palette_iter: 500,
+        ..colors(&colormap)
+    };
+    let map = ExpMap::for_frames(center, size, size);
+    for degrees in [0.0, 30.0] {
+        let options = RenderOptions {
+            coloring: Coloring::Smooth,
+            rotation: Rotation::degrees(degrees),
+            ..options(500)
+        };
+        let x_range = (center.0 - width / 2.0, center.0 + width / 2.0);
+        let y_range = (center.1 - width / 2.0, center.1 + width / 2.0);
+        let buffer = render::compute_escape(&Mandelbrot, size, size, x_range, y_range, &options);
+        let rendered = render::colorize(&buffer, &colors).to_rgb8();
+
+        let view = View {
+            width: size,
+            height: size,
+            pixel_size: (pixel, pixel),
+            rotation: Rotation::degrees(degrees),
+        };
+        let strips: Vec<Strip> = map
+            .strips(&view)
+            .map(|index| {
+                let rings = render::compute_rings(&Mandelbrot, &map, index, &options);
+                let colors = ColorOptions {
+                    bit_depth: BitDepth::Sixteen,
+                    ..colors
+                };
+                Strip::new(index, map.angles, &render::colorize(&rings, &colors)).unwrap()
+            })
+            .collect();
+        let strips: Vec<&Strip> = strips.iter().collect();
+        let resampled = map.reproject::<u8>(&view, &strips, Dither::None).to_rgb8();
+        let channels = rendered.as_raw().iter().zip(resampled.as_raw());
+        let difference = channels.map(|(a, b)| a.abs_diff(*b) as f64).sum::<f64>();
+        let difference = difference / rendered.as_raw().len() as f64;
+        // The two sample the frame differently, so they differ where it
+        // has detail finer than a pixel, but mostly agree.
+        assert!(difference < 6.0, "{} degrees: {}", degrees, difference);
+    }
+}
+
+/// Every trap shape colors the same frame by how close the orbits of its
+/// pixels come to it, the interior included, and each one differently.
+#[test]
+fn each_trap_shape_colors_the_frame_by_its_distance() {
+    let (x_range, y_range) = ((-2.2, 0.8), (-1.0, 1.0));
+    let traps = ["point:0,0", "cross", "circle:0.25", "point:-0.5,0.5"];
+    let mut images = Vec::new();
+    for spec in traps {
+        let trap = Trap::from_spec(spec).unwrap();
+        let options = RenderOptions {
+            coloring: Coloring::Trap(trap),
+            periodicity: false,
+            ..options(200)
+        };
+        let buffer = render::compute_escape(&Mandelbrot, 48, 32, x_range, y_range, &options);
+        for (index, &sample) in buffer.values.iter().enumerate() {
+            let (x, y) = ((index % 48) as f64, (index / 48) as f64);
+            let c = (-2.2 + x * 3.0 / 48.0, 1.0 - y * 2.0 / 32.0);
+            let escape = Mandelbrot.escape_time(c, 200, 2.0, None, Some(&trap));
+            let Sample::Value(distance) = sample else {
+                panic!("{}: pixel {} is {:?}", spec, index, sample);
+            };
+            assert!((distance - escape.trap).abs() < 1e-9, "{}: pixel {}", spec, index);
+        }
+        images.push(colorize(&buffer));
+    }
+    for (a, b) in (0..traps.len()).flat_map(|a| (a + 1..traps.len()).map(move |b| (a, b))) {
+        assert_ne!(images[a], images[b], "{} and {}", traps[a], traps[b]);
+    }
+    assert_eq!(Trap::from_spec("circle"), Ok(Trap::Circle(1.0)));
+    assert_eq!(Trap::from_spec("cross:1,-2"), Ok(Trap::Cross(1.0, -2.0)));
+    for (spec, error) in [
+        ("circle:0", "circle trap radius should be positive, got 0"),
+        ("circle:-0.5", "circle trap radius should be positive, got -0.5"),
+        ("circle:inf", "circle trap radius should be positive, got inf"),
+        ("point:1", "invalid point trap parameters '1'"),
+        ("star", "unknown trap 'star'"),
+    ] {
+        assert_eq!(Trap::from_spec(spec), Err(error.to_string()));
+    }
+}
+
+/// Scripts compute what they say, and the ones that can't be compiled say
+/// where they went wrong.
+#[test]
+fn color_scripts_compile_and_run() {
+    let inputs = Inputs {
+        smooth: 12.5,
+        max_iter: 100,
+        orbit: Orbit {
+            iterations: 12.0,
+            z: (3.0, -4.0),
+            distance: 2.0,
+            trap: 0.25,
+        },
+        frame: 7,
+        magnification: 1e3,
+    };
+    let run = |source: &str| Script::compile(source).unwrap().run(&inputs).unwrap();
+    assert_eq!(run("smooth / max_iter"), Output::Position(0.125));
+    assert_eq!(run("# a comment\nr = hypot(zre, zim)  # 5\nr / 10"), Output::Position(0.5));
+    assert_eq!(run("a = 2; b = -a ^ 2; b + 5"), Output::Position(1.0));
+    assert_eq!(run("2 ^ 3 ^ 2 / 1024"), Output::Position(0.5));
+    assert_eq!(run("if(de > 1, trap, 1 - trap)"), Output::Position(0.25));
+    assert_eq!(run("clamp(log10(magnification) / 4, 0, 1) + iter - 12"), Output::Position(0.75));
+    assert_eq!(run("rgb(frame / 7, 0.5, mix(0, 1, 0.25))"), Output::Rgb([1.0, 0.5, 0.25]));
+    let error = |source: &str| Script::compile(source).unwrap_err();
+    assert_eq!(error("x = 1\ny + 1"), "line 2, column 1: unknown variable 'y'");
+    assert_eq!(error("smooth = 1\nsmooth"), "line 1, column 1: 'smooth' is an input, not a variable");
+    assert_eq!(error("x = 1"), "line 1, column 6: the script gives no color");
+    assert!(error("sin(smooth, 2)").starts_with("line 1, column "), "{}", error("sin(smooth, 2)"));
+    assert!(error("1 +").starts_with("line 1, column 4: "), "{}", error("1 +"));
+    let failed = Script::compile("ln(smooth - 20)").unwrap().run(&inputs).unwrap_err();
+    assert!(failed.contains("NaN"), "{}", failed);
+    let needs = |source: &str| Script::compile(source).unwrap().needs(Trap::Circle(0.5));
+    assert_eq!(needs("smooth / 10"), Needs { distance: false, trap: None });
+    assert_eq!(needs("de + trap"), Needs { distance: true, trap: Some(Trap::Circle(0.5)) });
+}
+
+/// The orbits kept for a script are those the built-in colorings color by,
+/// and a script that fails on a pixel says which.
+#[test]
+fn color_scripts_read_the_orbits_of_their_pixels() {
+    let compute = |coloring: Coloring| {
+        let options = RenderOptions {
+            coloring,
+            ..options(200)
+        };
+        render::compute_escape(&Mandelbrot, 48, 32, (-2.2, 0.8), (-1.0, 1.0), &options)
+    };
+    let trap = Trap::Point(0.0, 0.0);
+    let scripted = compute(Coloring::Script(Needs {
+        distance: true,
+        trap: Some(trap),
+    }));
+    let smooth = compute(Coloring::Smooth);
+    let distance = compute(Coloring::Distance);
+    let trapped = compute(Coloring::Trap(trap));
+    let mut exterior = 0;
+    for (index, (&sample, orbit)) in scripted.values.iter().zip(&scripted.orbits).enumerate() {
+        let Sample::Value(value) = sample else {
+            assert_eq!(sample, Sample::Interior);
+            continue;
+        };
+        exterior += 1;
+        let value_of = |buffer: &EscapeBuffer| match buffer.values[index] {
+            Sample::Value(value) => value,
+            _ => 0.0,
+        };
+        assert!((value - value_of(&smooth)).abs() < 1e-9, "pixel {}", index);
+        assert!((orbit.trap - value_of(&trapped)).abs() < 1e-9, "pixel {}", index);
+        // Distances under half a pixel are drawn as the boundary.
+        if let Sample::Value(estimate) = distance.values[index] {
+            assert!((orbit.distance - estimate).abs() < 1e-9, "pixel {}", index);
+        }
+        assert!(orbit.z.0.hypot(orbit.z.1) > 2.0);
+    }
+    assert!(exterior > 48 * 32 / 2);
+
+    let gradient = Palette::Sinebow.gradient();
+    let colormap = Colormap::new(&gradient, &Adjust::default());
+    let script = Script::compile("rgb(zre > 0, 0, 1)").unwrap();
+    let colors = ColorOptions {
+        script: Some(Scripted {
+            script: &script,
+            frame: 0,
+            magnification: 1.0,
+        }),
+        ..colors(&colormap)
+    };
+    let img = render::colorize(&scripted, &colors).to_rgb8();
+    for (pixel, (&sample, orbit)) in img.pixels().zip(scripted.values.iter().zip(&scripted.orbits)) {
+        let expected = match sample {
+            Sample::Interior => [255, 255, 255],
+            _ if orbit.z.0 > 0.0 => [255, 0, 255],
+            _ => [0, 0, 255],
+        };
+        assert_eq!(pixel.0, expected);
+    }
+    // The first pixel from the top left that escapes in 3 iterations.
+    let failing = Script::compile("1 / (iter - 3)").unwrap();
+    let first = scripted.orbits.iter().position(|orbit| orbit.iterations == 3.0).unwrap();
+    let colors = ColorOptions {
+        script: Some(Scripted {
+            script: &failing,
+            frame: 0,
+            magnification: 1.0,
+        }),
+        ..colors
+    };
+    let failure = render::colorize_blurred(&[&scripted], &colors).unwrap_err();
+    assert_eq!(failure.pixel, ((first % 48) as u32, (first / 48) as u32));
+    assert!(failure.to_string().starts_with(&format!("at pixel ({}, {}): ", first % 48, first / 48)));
+}
+
+#[test]
+fn nucleus_of_the_seahorse_minibrot_is_the_presets() {
+    let near = (bigfloat::from_f64(-0.7436423, 128), bigfloat::from_f64(0.1318265, 128));
+    let period = nucleus::find_period(&near, 1e-6, MAX_PERIOD).unwrap();
+    assert_eq!(period, 39);
+    let found = nucleus::find_nucleus(&near, 1e-6, period).unwrap();
+    assert_eq!(found.period, 39);
+    assert!((found.size - 2.166e-6).abs() < 1e-9, "{}", found.size);
+    let preset = PRESETS.iter().find(|preset| preset.name == "seahorse-minibrot").unwrap();
+    let x = bigfloat::parse_decimal(preset.center.0, 256).unwrap();
+    let y = bigfloat::parse_decimal(preset.center.1, 256).unwrap();
+    let off = |a: &bigfloat::Big, b: &bigfloat::Big| (a - b).to_f64().value().abs();
+    assert!(off(&found.center.0, &x) < 1e-25 && off(&found.center.1, &y) < 1e-25);
+}
+
+#[test]
+fn nucleus_of_a_multiple_of_the_period_is_the_lowest() {
+    let near = (bigfloat::from_f64(-1.754, 128), bigfloat::from_f64(0.0, 128));
+    assert_eq!(nucleus::find_period(&near, 1e-3, MAX_PERIOD), Some(3));
+    let found = nucleus::find_nucleus(&near, 1e-3, 39).unwrap();
+    assert_eq!(found.period, 3);
+    assert!((found.size - 1.904e-2).abs() < 1e-5, "{}", found.size);
+    let x = bigfloat::to_decimal(&found.center.0, 20);
+    assert!(x.starts_with("-1.75487766624669276"), "{}", x);
+}
+
+#[test]
+fn nucleus_search_gives_up_far_from_any() {
+    let outside = (bigfloat::from_f64(2.0, 128), bigfloat::from_f64(2.0, 128));
+    assert_eq!(nucleus::find_period(&outside, 0.1, MAX_PERIOD), None);
+    // The period 7 nuclei are all far from the airplane's.
+    let near = (bigfloat::from_f64(-1.754877666, 128), bigfloat::from_f64(0.0, 128));
+    let err = nucleus::find_nucleus(&near, 1e-9, 7).err().unwrap();
+    assert!(err.contains("wandered"), "{}", err);
+}
+
+/// The Buddhabrot of the 48×48 view 1000 times narrower than the whole set
+/// around (-0.75, 0.1), in 200,000 samples drawn by `sampler`.
+fn buddhabrot_close_up(sampler: Sampler, seed: u64) -> Vec<u8> {
+    // A thousandth of the width of the whole Buddhabrot, by the cardioid.
+    let (x, y, half) = (-0.112, 0.889, 1.5 / 1000.0);
+    let buddhabrot = BuddhabrotOptions {
+        samples: 100_000,
+        sampler,
+        seed,
+        min_iter: 200,
+        tone: ToneMap::Sqrt,
+        bands: [2000; 3],
+        bit_depth: BitDepth::Eight,
+    };
+    let img = buddhabrot::render_buddhabrot(
+        &Mandelbrot,
+        48,
+        48,
+        (x - half, x + half),
+        (y - half, y + half),
+        &options(2000),
+        &buddhabrot,
+    );
+    img.to_luma8().into_raw()
+}
+
+#[test]
+fn metropolis_sampling_shows_close_ups_uniform_sampling_misses() {
+    let lit = |pixels: &[u8]| pixels.iter().filter(|&&pixel| pixel > 0).count();
+    let uniform = buddhabrot_close_up(Sampler::Uniform, 0);
+    let metropolis = buddhabrot_close_up(Sampler::Metropolis(Metropolis::default()), 0);
+    assert!(lit(&uniform) < 48 * 48 / 100, "{} pixels lit", lit(&uniform));
+    assert!(lit(&metropolis) > 48 * 48 / 2, "{} pixels lit", lit(&metropolis));
+
+    // Chains are seeded by their index and the seed, not by the threads
+    // they run on.
+    let again = buddhabrot_close_up(Sampler::Metropolis(Metropolis::default()), 0);
+    assert_eq!(metropolis, again);
+    let reseeded = buddhabrot_close_up(Sampler::Metropolis(Metropolis::default()), 1);
+    assert_ne!(metropolis, reseeded);
+}
+
+/// Interior points carry the period of the bulb they're in and a
+/// derivative that vanishes at its nucleus, but only when asked to.
+#[test]
+fn interior_samples_find_the_cycle_of_their_bulb() {
+    // A column of pixels through the nuclei of the period 1, 2 and 3
+    // components, the last the upper rabbit bulb, and a point outside.
+    let nuclei = [(0.0, 0.0), (-1.0, 0.0), (-0.122_561_166_876_654, 0.744_861_766_619_744)];
+    let attractors = RenderOptions {
+        attractors: true,
+        ..options(1000)
+    };
+    let sample = |c: (f64, f64), options: &RenderOptions| {
+        let x_range = (c.0 - 1e-9, c.0 + 1e-9);
+        render::compute_escape(&Mandelbrot, 1, 1, x_range, (c.1 - 1e-9, c.1 + 1e-9), options)
+            .values[0]
+    };
+    for (period, &c) in (1..).zip(&nuclei) {
+        assert_eq!(sample(c, &options(1000)), Sample::Interior);
+        match sample(c, &attractors) {
+            Sample::Attractor {
+                period: found,
+                derivative,
+            } => {
+                assert_eq!(found, period, "at {:?}", c);
+                assert!(derivative < 1e-3, "{} at {:?}", derivative, c);
+            }
+            other => panic!("{:?} at {:?}", other, c),
+        }
+    }
+    // Towards the cusp of the cardioid the fixed point at 0.47 barely
+    // attracts.
+    match sample((0.2491, 0.0), &attractors) {
+        Sample::Attractor { period: 1, derivative } => {
+            assert!((derivative - 0.94).abs() < 1e-3, "{}", derivative)
+        }
+        other => panic!("{:?}", other),
+    }
+    assert!(matches!(sample((0.5, 0.0), &attractors), Sample::Value(_)));
+
+    let gradient = Palette::Sinebow.gradient();
+    let colormap = Colormap::new(&gradient, &Adjust::default());
+    let samples = vec![
+        Sample::Attractor {
+            period: 2,
+            derivative: 0.5,
+        },
+        Sample::Interior,
+    ];
+    let colored = |coloring| {
+        let colors = ColorOptions {
+            interior_coloring: coloring,
+            ..colors(&colormap)
+        };
+        let img = render::colorize(&buffer(2, 1, 1, samples.clone()), &colors).to_rgb8();
+        [img.get_pixel(0, 0).0, img.get_pixel(1, 0).0]
+    };
+    let byte = |channel: f64| (channel * 255.0).round() as u8;
+    let hue = interior::period_color(2).map(byte);
+    assert_eq!(colored(Some(InteriorColoring::Period)), [hue, [255; 3]]);
+    assert_eq!(colored(Some(InteriorColoring::Derivative))[0], [byte(0.5); 3]);
+    assert_eq!(interior::legend()[1], format!("#{:02x}{:02x}{:02x}", hue[0], hue[1], hue[2]));
+    // Without an interior coloring, cycles are drawn as the interior.
+    assert_eq!(colored(None), [[255; 3]; 2]);
+}
+
+#[test]
+fn external_angles_read_as_fractions_or_binary() {
+    let angle = |spec: &str| ExternalAngle::from_spec(spec).unwrap();
+    assert_eq!(angle("1/3"), angle("0.(01)"));
+    assert_eq!(angle("2/6"), angle(".(01)"));
+    assert_eq!(angle("1/6"), angle(".0(01)"));
+    assert_eq!(angle("2/7"), angle("0.(010)"));
+    assert_eq!(angle("3/8"), angle("0.011"));
+    assert_eq!(angle("4/3"), angle("1/3"));
+    assert_eq!(angle("1/6").doubled(), angle("1/3"));
+    assert_eq!(angle("2/3").doubled(), angle("1/3"));
+    assert_eq!(angle("0.(010)").to_string(), "2/7");
+    for bad in ["1/0", "a/3", "0.(2)", "0.(01", "01", ""] {
+        assert!(ExternalAngle::from_spec(bad).is_err(), "{}", bad);
+    }
+}
+
+#[test]
+fn external_rays_land_where_their_angles_say() {
+    let landing = |spec: &str, resolution: f64, max_iter: u32| {
+        let mut ray = Ray::new(ExternalAngle::from_spec(spec).unwrap());
+        ray.extend(resolution, max_iter);
+        assert!(!ray.ended(), "{}", spec);
+        let last = ray.points().last().unwrap();
+        (last.0.to_f64().value(), last.1.to_f64().value())
+    };
+    let near = |a: (f64, f64), b: (f64, f64), within: f64| (a.0 - b.0).hypot(a.1 - b.1) < within;
+    // The ray of 1/6 lands on i, where the orbit of 0 falls on a cycle
+    // after a step, and the rays of 1/2 and 0 on the tips of the real
+    // axis.
+    let i = landing("1/6", 1e-8, 10_000);
+    assert!(near(i, (0.0, 1.0), 1e-6), "{:?}", i);
+    let tip = landing("1/2", 1e-8, 10_000);
+    assert!(near(tip, (-2.0, 0.0), 1e-6), "{:?}", tip);
+    // The rays of 1/3 and 1/7 land on the roots of the bulbs of period 2
+    // and 3, slowly, as the roots are parabolic.
+    let root = landing("1/3", 1e-5, 10_000);
+    assert!(near(root, (-0.75, 0.0), 0.03), "{:?}", root);
+    let root = landing("1/7", 1e-5, 10_000);
+    assert!(near(root, (-0.125, 0.649_519), 0.03), "{:?}", root);
+    // Past what f64 tells apart, the points are traced in Big, where
+    // there's one.
+    let mut ray = Ray::new(ExternalAngle::new(1, 6).unwrap());
+    ray.extend(1e-25, 10_000);
+    let last = ray.points().last().unwrap();
+    let miss = (last.0.to_f64().value(), (&last.1 - bigfloat::Big::ONE).to_f64().value());
+    match cfg!(feature = "bigfloat") {
+        true => assert!(miss.0.hypot(miss.1) < 1e-23, "{:?}", miss),
+        false => assert!(ray.ended()),
+    }
+    let mut ray = Ray::new(ExternalAngle::new(0, 1).unwrap());
+    ray.extend(1e-3, 100);
+    assert!(ray.points().iter().all(|point| point.1.to_f64().value() == 0.0));
+    assert!(ray.points().iter().all(|point| point.0.to_f64().value() > 0.25));
+}
@@ -0,0 +1,123 @@
+//! Checks of the frames a `ZoomJob` streams, and of how it pauses and
+//! stops with its consumer.
+
+use rustlebrot::error::RustlebrotError;
+use rustlebrot::stream::{Encoding, ZoomJob, ZoomOptions};
+use std::time::{Duration, Instant};
+
+fn small(frames: u32, encoding: Encoding) -> ZoomOptions {
+    ZoomOptions {
+        width: 48,
+        height: 40,
+        scale: 3.0 / 48.0,
+        zoom_factor: 1.5,
+        frames,
+        max_iter: 100,
+        encoding,
+        ..ZoomOptions::default()
+    }
+}
+
+#[tokio::test]
+async fn frames_arrive_in_order_as_render_region_renders_them() {
+    let options = small(4, Encoding::Raw);
+    let mut job = ZoomJob::stream(options).unwrap();
+    let mut frames = Vec::new();
+    while let Some(frame) = job.next().await {
+        frames.push(frame.unwrap());
+    }
+    assert_eq!(frames.iter().map(|f| f.index).collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+    for frame in &frames {
+        assert_eq!(frame.bytes.len(), 48 * 40 * 4);
+        assert_eq!(frame.max_iter, 100);
+    }
+    assert_eq!(frames[2].magnification, 2.25);
+    let (x, y) = options.center;
+    let expected = rustlebrot::render_region(48, 40, 100, x, y, options.scale).unwrap();
+    assert_eq!(frames[0].bytes, expected);
+    // A band at a time, every frame is the whole view.
+    let later = rustlebrot::render_region(48, 40, 100, x, y, options.scale / 1.5).unwrap();
+    assert_eq!(frames[1].bytes, later);
+    assert!(frames[3].x_range.1 - frames[3].x_range.0 < frames[0].x_range.1 - frames[0].x_range.0);
+    assert!(job.progress().finished());
+    assert_eq!(job.progress().frames(), 4);
+}
+
+#[tokio::test]
+async fn frames_are_encoded_as_asked() {
+    let mut job = ZoomJob::stream(small(1, Encoding::Png)).unwrap();
+    let png = job.next().await.unwrap().unwrap();
+    let image = image::load_from_memory(&png.bytes).unwrap();
+    assert_eq!((image.width(), image.height()), (48, 40));
+    assert!(job.next().await.is_none());
+
+    let mut job = ZoomJob::stream(small(1, Encoding::Jpeg(90))).unwrap();
+    let jpeg = job.next().await.unwrap().unwrap();
+    assert_eq!(&jpeg.bytes[..2], &[0xff, 0xd8]);
+    let image = image::load_from_memory(&jpeg.bytes).unwrap();
+    assert_eq!((image.width(), image.height()), (48, 40));
+}
+
+#[tokio::test]
+async fn a_slow_consumer_pauses_the_job() {
+    let options = ZoomOptions { ahead: 2, ..small(20, Encoding::Raw) };
+    let mut job = ZoomJob::stream(options).unwrap();
+    let progress = job.progress();
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    // The frames waiting in the stream, and the one waiting to go in.
+    assert!(progress.frames() <= 3, "{} frames ran ahead", progress.frames());
+    assert!(!progress.finished());
+    let mut read = 0;
+    while let Some(frame) = job.next().await {
+        frame.unwrap();
+        read += 1;
+    }
+    assert_eq!(read, 20);
+    assert_eq!(progress.frames(), 20);
+}
+
+#[tokio::test]
+async fn dropping_the_job_stops_it() {
+    let options = ZoomOptions {
+        width: 400,
+        height: 400,
+        scale: 0.5 / 400.0,
+        center: (-0.1, 0.0),
+        zoom_factor: 1.01,
+        frames: 1000,
+        max_iter: 20000,
+        encoding: Encoding::Raw,
+        ahead: 1,
+    };
+    let mut job = ZoomJob::stream(options).unwrap();
+    let progress = job.progress();
+    job.next().await.unwrap().unwrap();
+    drop(job);
+    let dropped = Instant::now();
+    while !progress.finished() {
+        assert!(dropped.elapsed() < Duration::from_secs(10), "the job kept rendering");
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert!(progress.frames() < 1000);
+}
+
+#[tokio::test]
+async fn a_zoom_past_f64_ends_at_an_error() {
+    let options = ZoomOptions { zoom_factor: 1e4, ..small(10, Encoding::Raw) };
+    let mut job = ZoomJob::stream(options).unwrap();
+    let mut items = Vec::new();
+    while let Some(item) = job.next().await {
+        items.push(item);
+    }
+    let last = items.pop().unwrap();
+    assert!(matches!(last, Err(RustlebrotError::PrecisionExhausted { frame, .. })
+        if frame == items.len() as u32));
+    assert!(!items.is_empty());
+    assert!(items.iter().all(|item| item.is_ok()));
+}
+
+#[test]
+fn a_zoom_without_pixels_fails() {
+    let options = ZoomOptions { width: 0, ..ZoomOptions::default() };
+    assert!(ZoomJob::stream(options).is_err());
+}